@@ -0,0 +1,50 @@
+% tight integer loops, which should run without allocating a number.
+
+sum_to(N, Sum) :- sum_to(N, 0, Sum).
+
+sum_to(0, Sum, Sum) :- !.
+sum_to(N, Sum0, Sum) :-
+    Sum1 is Sum0 + N,
+    N1 is N - 1,
+    sum_to(N1, Sum1, Sum).
+
+fib(0, 0) :- !.
+fib(1, 1) :- !.
+fib(N, F) :-
+    N1 is N - 1,
+    N2 is N - 2,
+    fib(N1, F1),
+    fib(N2, F2),
+    F is F1 + F2.
+
+% the total stopping time of the Collatz sequences of 1 to N.
+collatz_steps(N, Steps) :- collatz_steps(N, 0, Steps).
+
+collatz_steps(0, Steps, Steps) :- !.
+collatz_steps(N, Steps0, Steps) :-
+    collatz(N, 0, S),
+    Steps1 is Steps0 + S,
+    N1 is N - 1,
+    collatz_steps(N1, Steps1, Steps).
+
+collatz(1, S, S) :- !.
+collatz(N, S0, S) :-
+    (   N mod 2 =:= 0 ->
+        N1 is N >> 1
+    ;   N1 is 3 * N + 1
+    ),
+    S1 is S0 + 1,
+    collatz(N1, S1, S).
+
+% big integers whose differences are small integers again.
+big_diffs(N, Sum) :-
+    Big is 2^70,
+    big_diffs(N, Big, 0, Sum).
+
+big_diffs(0, _, Sum, Sum) :- !.
+big_diffs(N, Big, Sum0, Sum) :-
+    B is Big + N,
+    D is B - Big,
+    Sum1 is Sum0 + D,
+    N1 is N - 1,
+    big_diffs(N1, Big, Sum1, Sum).
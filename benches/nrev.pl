@@ -0,0 +1,17 @@
+:- use_module(library(between)).
+:- use_module(library(lists)).
+
+app([], L, L).
+app([H|T], L, [H|R]) :- app(T, L, R).
+
+nrev([], []).
+nrev([H|T], R) :- nrev(T, RT), app(RT, [H], R).
+
+run_nrev(Times, Head) :-
+    numlist(1, 30, L),
+    (   between(1, Times, _),
+        nrev(L, _),
+        false
+    ;   true
+    ),
+    nrev(L, [Head|_]).
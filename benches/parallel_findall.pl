@@ -0,0 +1,3 @@
+% the workers of parallel_findall/3 consult the file of the module
+% calling it, which a module loaded from a string doesn't have.
+:- use_module('benches/queens').
@@ -0,0 +1,35 @@
+:- module(queens, [queens_count/2, queens_parallel_count/2]).
+
+:- use_module(library(between)).
+:- use_module(library(concurrency)).
+:- use_module(library(lists)).
+
+% the placements of N queens are searched for independently for every
+% column of the queen in the first row, which parallel_findall/3 turns
+% into as many tasks.
+
+queens_count(N, Count) :-
+    findall(Qs, (between(1, N, Q), queens(N, Q, Qs)), Qss),
+    length(Qss, Count).
+
+queens_parallel_count(N, Count) :-
+    parallel_findall(Qs, (between(1, N, Q), queens(N, Q, Qs)), Qss),
+    length(Qss, Count).
+
+queens(N, Q, [Q|Qs]) :-
+    numlist(1, N, Cs),
+    select(Q, Cs, Cs1),
+    place(Cs1, [Q], Qs).
+
+place([], _, []).
+place(Cs, Placed, [Q|Qs]) :-
+    select(Q, Cs, Cs1),
+    safe(Placed, Q, 1),
+    place(Cs1, [Q|Placed], Qs).
+
+safe([], _, _).
+safe([P|Ps], Q, D) :-
+    Q =\= P + D,
+    Q =\= P - D,
+    D1 is D + 1,
+    safe(Ps, Q, D1).
@@ -19,6 +19,55 @@ pub fn prolog_benches() -> BTreeMap<&'static str, PrologBenchmark> {
             Strategy::Reuse,
             btreemap! { "Head" => Term::integer(1) },
         ),
+        (
+            "nrev",
+            "benches/nrev.pl",
+            "run_nrev(10000, Head).",
+            Strategy::Reuse,
+            btreemap! { "Head" => Term::integer(30) },
+        ),
+        (
+            "arith_sum",
+            "benches/arith.pl",
+            "sum_to(1000000, Sum).",
+            Strategy::Reuse,
+            btreemap! { "Sum" => Term::integer(500000500000_i64) },
+        ),
+        (
+            "arith_fib",
+            "benches/arith.pl",
+            "fib(21, F).",
+            Strategy::Reuse,
+            btreemap! { "F" => Term::integer(10946) },
+        ),
+        (
+            "arith_collatz",
+            "benches/arith.pl",
+            "collatz_steps(10000, Steps).",
+            Strategy::Reuse,
+            btreemap! { "Steps" => Term::integer(849666) },
+        ),
+        (
+            "arith_big_diffs",
+            "benches/arith.pl",
+            "big_diffs(100000, Sum).",
+            Strategy::Reuse,
+            btreemap! { "Sum" => Term::integer(5000050000_i64) },
+        ),
+        (
+            "queens_findall",
+            "benches/parallel_findall.pl",
+            "queens_count(8, Count).",
+            Strategy::Reuse,
+            btreemap! { "Count" => Term::integer(92) },
+        ),
+        (
+            "queens_parallel_findall",
+            "benches/parallel_findall.pl",
+            "queens_parallel_count(8, Count).",
+            Strategy::Reuse,
+            btreemap! { "Count" => Term::integer(92) },
+        ),
         (
             "csv_codename",
             "benches/csv.pl",
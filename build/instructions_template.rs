@@ -19,8 +19,8 @@
  */
 
 use std::any::*;
-use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
 
 struct ArithmeticTerm;
 struct Atom;
@@ -174,14 +174,42 @@ enum ReplCodePtr {
     LoadContextStream,
     #[strum_discriminants(strum(props(Arity = "0", Name = "$pop_load_context")))]
     PopLoadContext,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$report_diagnostic")))]
+    ReportDiagnostic,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$style_warnings")))]
+    StyleWarnings,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$pop_load_state_payload")))]
     PopLoadStatePayload,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$push_load_state_payload")))]
     PushLoadStatePayload,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$push_load_context")))]
     PushLoadContext,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$set_load_context_line")))]
+    SetLoadContextLine,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$use_module")))]
     UseModule,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$reexport")))]
+    Reexport,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$freeze_module_interface")))]
+    FreezeModuleInterface,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$module_interface_frozen")))]
+    ModuleInterfaceFrozen,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$loaded_module")))]
+    LoadedModule,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$module_exports")))]
+    ModuleExports,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$module_location")))]
+    ModuleLocation,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$loaded_files")))]
+    LoadedFiles,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$embedded_library")))]
+    EmbeddedLibrary,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$library_paths")))]
+    LibraryPaths,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$file_modified")))]
+    FileModified,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$loaded_file_dynamic_predicates")))]
+    LoadedFileDynamicPredicates,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$built_in_property")))]
     BuiltInProperty,
     #[strum_discriminants(strum(props(Arity = "4", Name = "$meta_predicate_property")))]
@@ -204,6 +232,8 @@ enum ReplCodePtr {
     IsConsistentWithTermQueue,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$flush_term_queue")))]
     FlushTermQueue,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$complete_compilations")))]
+    CompleteCompilations,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$remove_module_exports")))]
     RemoveModuleExports,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$add_non_counted_backtracking")))]
@@ -219,8 +249,14 @@ enum SystemClauseType {
     AtomChars,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$atom_codes")))]
     AtomCodes,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$atom_concat")))]
+    AtomConcat,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$atom_length")))]
     AtomLength,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$sub_atom")))]
+    SubAtom,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$report_crash")))]
+    ReportCrash,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$bind_from_register")))]
     BindFromRegister,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$call_continuation")))]
@@ -277,6 +313,18 @@ enum SystemClauseType {
     PathCanonical,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$file_time")))]
     FileTime,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$file_type")))]
+    FileType,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$delete_directory_tree")))]
+    DeleteDirectoryTree,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$wildcard_match")))]
+    WildcardMatch,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$fswatch_create")))]
+    FswatchCreate,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$fswatch_wait")))]
+    FswatchWait,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$fswatch_remove")))]
+    FswatchRemove,
     #[strum_discriminants(strum(props(Arity = "arity", Name = "$module_call")))]
     DynamicModuleResolution(usize),
     #[strum_discriminants(strum(props(Arity = "arity", Name = "$prepare_call_clause")))]
@@ -311,7 +359,7 @@ enum SystemClauseType {
     GetBValue,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$get_cont_chunk")))]
     GetContinuationChunk,
-    #[strum_discriminants(strum(props(Arity = "4", Name = "$get_next_op_db_ref")))]
+    #[strum_discriminants(strum(props(Arity = "5", Name = "$get_next_op_db_ref")))]
     GetNextOpDBRef,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$lookup_db_ref")))]
     LookupDBRef,
@@ -331,8 +379,154 @@ enum SystemClauseType {
     InstallSCCCleaner,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$install_inference_counter")))]
     InstallInferenceCounter,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$install_time_limit")))]
+    InstallTimeLimit,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$inference_count")))]
     InferenceCount,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$engine_create")))]
+    EngineCreate,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$engine_destroy")))]
+    EngineDestroy,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$engine_fetch")))]
+    EngineFetch,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$engine_next")))]
+    EngineNext,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$engine_post")))]
+    EnginePost,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$engine_self")))]
+    EngineSelf,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$engine_status")))]
+    EngineStatus,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$engine_yield")))]
+    EngineYield,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$message_queue_create")))]
+    MessageQueueCreate,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$message_queue_destroy")))]
+    MessageQueueDestroy,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$message_queue_next")))]
+    MessageQueueNext,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$message_queue_remove")))]
+    MessageQueueRemove,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$message_queue_send")))]
+    MessageQueueSend,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$message_queue_size")))]
+    MessageQueueSize,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$message_queue_stream")))]
+    MessageQueueStream,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$message_queue_wait")))]
+    MessageQueueWait,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$mutex_create")))]
+    MutexCreate,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$mutex_destroy")))]
+    MutexDestroy,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$mutex_holder")))]
+    MutexHolder,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$mutex_lock")))]
+    MutexLock,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$mutex_trylock")))]
+    MutexTrylock,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$mutex_unlock")))]
+    MutexUnlock,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$condition_create")))]
+    ConditionCreate,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$condition_destroy")))]
+    ConditionDestroy,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$condition_signal")))]
+    ConditionSignal,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$condition_broadcast")))]
+    ConditionBroadcast,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$condition_wait")))]
+    ConditionWait,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$rate_limiter_create")))]
+    RateLimiterCreate,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$rate_limiter_acquire")))]
+    RateLimiterAcquire,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$circuit_breaker_create")))]
+    CircuitBreakerCreate,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$circuit_breaker_enter")))]
+    CircuitBreakerEnter,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$circuit_breaker_exit")))]
+    CircuitBreakerExit,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$circuit_breaker_state")))]
+    CircuitBreakerState,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$resilience_object_destroy")))]
+    ResilienceObjectDestroy,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$regex_matches")))]
+    RegexMatches,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$regex_split")))]
+    RegexSplit,
+    #[strum_discriminants(strum(props(Arity = "5", Name = "$regex_replace")))]
+    RegexReplace,
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$json_parse")))]
+    JsonParse,
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$json_generate")))]
+    JsonGenerate,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$json_next_token")))]
+    JsonNextToken,
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$yaml_parse")))]
+    YamlParse,
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$yaml_generate")))]
+    YamlGenerate,
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$toml_parse")))]
+    TomlParse,
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$toml_generate")))]
+    TomlGenerate,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$csv_read_row")))]
+    CsvReadRow,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$csv_row_chars")))]
+    CsvRowChars,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$array_read")))]
+    ArrayRead,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$thread_create")))]
+    ThreadCreate,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$thread_create_background")))]
+    ThreadCreateBackground,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$thread_cancel")))]
+    ThreadCancel,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$background_threads")))]
+    BackgroundThreads,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$cpu_count")))]
+    CpuCount,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$parse_template")))]
+    ParseTemplate,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$fd_domains_intersection")))]
+    FdDomainsIntersection,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$fd_domains_union")))]
+    FdDomainsUnion,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$fd_domain_subtract")))]
+    FdDomainSubtract,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$interval_number")))]
+    IntervalNumber,
+    #[strum_discriminants(strum(props(Arity = "7", Name = "$interval_op")))]
+    IntervalOp,
+    #[strum_discriminants(strum(props(Arity = "5", Name = "$btree_find_node")))]
+    BtreeFindNode,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$group_pairs")))]
+    GroupPairs,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$thread_goal")))]
+    ThreadGoal,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$thread_status")))]
+    ThreadStatus,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$thread_join")))]
+    ThreadJoin,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$thread_self")))]
+    ThreadSelf,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$thread_set_status")))]
+    ThreadSetStatus,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$machine_create")))]
+    MachineCreate,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$machine_destroy")))]
+    MachineDestroy,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$machine_status")))]
+    MachineStatus,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$machine_consult")))]
+    MachineConsult,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$machine_query")))]
+    MachineQuery,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$machine_next")))]
+    MachineNext,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$machine_close")))]
+    MachineClose,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$lh_length")))]
     LiftedHeapLength,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$load_library_as_stream")))]
@@ -347,10 +541,12 @@ enum SystemClauseType {
     NumberToChars,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$number_to_codes")))]
     NumberToCodes,
-    #[strum_discriminants(strum(props(Arity = "3", Name = "$op")))]
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$op")))]
     OpDeclaration,
     #[strum_discriminants(strum(props(Arity = "7", Name = "$open")))]
     Open,
+    #[strum_discriminants(strum(props(Arity = "7", Name = "$open_resource")))]
+    OpenResource,
     #[strum_discriminants(strum(props(Arity = "5", Name = "$set_stream_options")))]
     SetStreamOptions,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$next_stream")))]
@@ -375,7 +571,9 @@ enum SystemClauseType {
     PutCode,
     #[strum_discriminants(strum(props(Arity = "5", Name = "$read_query_term")))]
     ReadQueryTerm,
-    #[strum_discriminants(strum(props(Arity = "5", Name = "$read_term")))]
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$history")))]
+    History,
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$read_term")))]
     ReadTerm,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$redo_attr_var_binding")))]
     RedoAttrVarBinding,
@@ -383,6 +581,8 @@ enum SystemClauseType {
     RemoveCallPolicyCheck,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$remove_inference_counter")))]
     RemoveInferenceCounter,
+    #[strum_discriminants(strum(props(Arity = "0", Name = "$remove_time_limit")))]
+    RemoveTimeLimit,
     #[strum_discriminants(strum(props(Arity = "0", Name = "$reset_cont_marker")))]
     ResetContinuationMarker,
     #[strum_discriminants(strum(props(Arity = "0", Name = "$restore_cut_policy")))]
@@ -419,6 +619,20 @@ enum SystemClauseType {
     GetDoubleQuotes,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$get_unknown")))]
     GetUnknown,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$get_module_unknown")))]
+    GetModuleUnknown,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$get_error_locations")))]
+    GetErrorLocations,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$get_prefer_rationals")))]
+    GetPreferRationals,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$get_float_flag")))]
+    GetFloatFlag,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$get_rational_flag")))]
+    GetRationalFlag,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$get_integer_rounding_function")))]
+    GetIntegerRoundingFunction,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$get_style_check")))]
+    GetStyleCheck,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$install_new_block")))]
     InstallNewBlock,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$random_integer")))]
@@ -427,11 +641,23 @@ enum SystemClauseType {
     Maybe,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$current_time")))]
     CurrentTime,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$wall_time")))]
+    WallTime,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$monotonic_time")))]
+    MonotonicTime,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$stamp_date_time")))]
+    StampDateTime,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$date_time_stamp")))]
+    DateTimeStamp,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$format_date_time")))]
+    FormatDateTime,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$parse_date_time")))]
+    ParseDateTime,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$quoted_token")))]
     QuotedToken,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$read_from_chars")))]
     ReadFromChars,
-    #[strum_discriminants(strum(props(Arity = "5", Name = "$read_term_from_chars")))]
+    #[strum_discriminants(strum(props(Arity = "6", Name = "$read_term_from_chars")))]
     ReadTermFromChars,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$reset_block")))]
     ResetBlock,
@@ -453,8 +679,26 @@ enum SystemClauseType {
     SetDoubleQuotes,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$set_unknown")))]
     SetUnknown,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$set_module_unknown")))]
+    SetModuleUnknown,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$set_error_locations")))]
+    SetErrorLocations,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$set_prefer_rationals")))]
+    SetPreferRationals,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$set_float_flag")))]
+    SetFloatFlag,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$set_rational_flag")))]
+    SetRationalFlag,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$set_integer_rounding_function")))]
+    SetIntegerRoundingFunction,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$set_style_check")))]
+    SetStyleCheck,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$set_seed")))]
     SetSeed,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$set_shuffle_seed")))]
+    SetShuffleSeed,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$shuffle_unspecified")))]
+    ShuffleUnspecified,
     #[strum_discriminants(strum(props(Arity = "4", Name = "$skip_max_list")))]
     SkipMaxList,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$sleep")))]
@@ -467,18 +711,30 @@ enum SystemClauseType {
     SocketServerAccept,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$socket_server_close")))]
     SocketServerClose,
-    #[strum_discriminants(strum(props(Arity = "4", Name = "$tls_accept_client")))]
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$udp_socket_open")))]
+    UdpSocketOpen,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$udp_send")))]
+    UdpSend,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$udp_receive")))]
+    UdpReceive,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$udp_socket_close")))]
+    UdpSocketClose,
+    #[strum_discriminants(strum(props(Arity = "5", Name = "$tls_accept_client")))]
     TLSAcceptClient,
-    #[strum_discriminants(strum(props(Arity = "3", Name = "$tls_client_connect")))]
+    #[strum_discriminants(strum(props(Arity = "7", Name = "$tls_client_connect")))]
     TLSClientConnect,
     #[strum_discriminants(strum(props(Arity = "0", Name = "$succeed")))]
     Succeed,
+    #[strum_discriminants(strum(props(Arity = "5", Name = "$term_hash")))]
+    TermHash,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$term_attributed_variables")))]
     TermAttributedVariables,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$term_variables")))]
     TermVariables,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$term_variables_under_max_depth")))]
     TermVariablesUnderMaxDepth,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$term_variant")))]
+    TermVariant,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$truncate_lh_to")))]
     TruncateLiftedHeapTo,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$unify_with_occurs_check")))]
@@ -491,9 +747,9 @@ enum SystemClauseType {
     WAMInstructions,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$inlined_instructions")))]
     InlinedInstructions,
-    #[strum_discriminants(strum(props(Arity = "8", Name = "$write_term")))]
+    #[strum_discriminants(strum(props(Arity = "9", Name = "$write_term")))]
     WriteTerm,
-    #[strum_discriminants(strum(props(Arity = "8", Name = "$write_term_to_chars")))]
+    #[strum_discriminants(strum(props(Arity = "9", Name = "$write_term_to_chars")))]
     WriteTermToChars,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$scryer_prolog_version")))]
     ScryerPrologVersion,
@@ -569,16 +825,58 @@ enum SystemClauseType {
     PopCount,
     #[strum_discriminants(strum(props(Arity = "1", Name = "$cpu_now")))]
     CpuNow,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$memory_usage")))]
+    MemoryUsage,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$choice_point_count")))]
+    ChoicePointCount,
+    #[strum_discriminants(strum(props(Arity = "0", Name = "$profiler_start")))]
+    ProfilerStart,
+    #[strum_discriminants(strum(props(Arity = "0", Name = "$profiler_stop")))]
+    ProfilerStop,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$profile_data")))]
+    ProfileData,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$xref")))]
+    Xref,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$module_imports")))]
+    ModuleImports,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$visible_predicates")))]
+    VisiblePredicates,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$module_graph")))]
+    ModuleGraph,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$trace_mode")))]
+    TraceMode,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$trace_spy")))]
+    TraceSpy,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$trace_call_at")))]
+    TraceCallAt,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$trace_event")))]
+    TraceEvent,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$signal_action")))]
+    SignalAction,
+    #[strum_discriminants(strum(props(Arity = "1", Name = "$pending_signals")))]
+    PendingSignals,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$det_length_rundown")))]
     DeterministicLengthRundown,
-    #[strum_discriminants(strum(props(Arity = "7", Name = "$http_open")))]
+    #[strum_discriminants(strum(props(Arity = "11", Name = "$http_open")))]
     HttpOpen,
     #[strum_discriminants(strum(props(Arity = "5", Name = "$http_listen")))]
     HttpListen,
     #[strum_discriminants(strum(props(Arity = "7", Name = "$http_accept")))]
     HttpAccept,
-    #[strum_discriminants(strum(props(Arity = "4", Name = "$http_answer")))]
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$http_lookup_listener")))]
+    HttpLookupListener,
+    #[strum_discriminants(strum(props(Arity = "5", Name = "$http_answer")))]
     HttpAnswer,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$http_websocket")))]
+    HttpWebSocket,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$ws_open")))]
+    WsOpen,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$ws_send")))]
+    WsSend,
+    #[strum_discriminants(strum(props(Arity = "2", Name = "$ws_receive")))]
+    WsReceive,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$ws_close")))]
+    WsClose,
     #[strum_discriminants(strum(props(Arity = "2", Name = "$load_foreign_lib")))]
     LoadForeignLib,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$foreign_call")))]
@@ -601,6 +899,10 @@ enum SystemClauseType {
     GetClauseP,
     #[strum_discriminants(strum(props(Arity = "6", Name = "$invoke_clause_at_p")))]
     InvokeClauseAtP,
+    #[strum_discriminants(strum(props(Arity = "3", Name = "$clause_candidates")))]
+    ClauseCandidates,
+    #[strum_discriminants(strum(props(Arity = "4", Name = "$asserted_clause_loc")))]
+    AssertedClauseLoc,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$get_from_attr_list")))]
     GetFromAttributedVarList,
     #[strum_discriminants(strum(props(Arity = "3", Name = "$put_to_attr_list")))]
@@ -633,8 +935,11 @@ enum InstructionTemplate {
     GetConstant(Level, HeapCellValue, RegType),
     #[strum_discriminants(strum(props(Arity = "2", Name = "get_list")))]
     GetList(Level, RegType),
+    // get_list followed by unify_variable of the head and the tail.
+    #[strum_discriminants(strum(props(Arity = "4", Name = "get_list_variables")))]
+    GetListVariables(Level, RegType, RegType, RegType),
     #[strum_discriminants(strum(props(Arity = "4", Name = "get_partial_string")))]
-    GetPartialString(Level, Rc<String>, RegType),
+    GetPartialString(Level, Arc<String>, RegType),
     #[strum_discriminants(strum(props(Arity = "3", Name = "get_structure")))]
     GetStructure(Level, Atom, usize, RegType),
     #[strum_discriminants(strum(props(Arity = "2", Name = "get_variable")))]
@@ -657,7 +962,7 @@ enum InstructionTemplate {
     #[strum_discriminants(strum(props(Arity = "2", Name = "put_list")))]
     PutList(Level, RegType),
     #[strum_discriminants(strum(props(Arity = "4", Name = "put_partial_string")))]
-    PutPartialString(Level, Rc<String>, RegType),
+    PutPartialString(Level, Arc<String>, RegType),
     #[strum_discriminants(strum(props(Arity = "3", Name = "put_structure")))]
     PutStructure(Atom, usize, RegType),
     #[strum_discriminants(strum(props(Arity = "2", Name = "put_unsafe_value")))]
@@ -761,6 +1066,16 @@ enum InstructionTemplate {
     ASin(ArithmeticTerm, usize),
     #[strum_discriminants(strum(props(Arity = "1", Name = "atan")))]
     ATan(ArithmeticTerm, usize),
+    #[strum_discriminants(strum(props(Arity = "1", Name = "erf")))]
+    Erf(ArithmeticTerm, usize),
+    #[strum_discriminants(strum(props(Arity = "1", Name = "erfc")))]
+    Erfc(ArithmeticTerm, usize),
+    #[strum_discriminants(strum(props(Arity = "1", Name = "lgamma")))]
+    LGamma(ArithmeticTerm, usize),
+    #[strum_discriminants(strum(props(Arity = "1", Name = "expm1")))]
+    ExpM1(ArithmeticTerm, usize),
+    #[strum_discriminants(strum(props(Arity = "1", Name = "log1p")))]
+    Log1P(ArithmeticTerm, usize),
     #[strum_discriminants(strum(props(Arity = "1", Name = "atan2")))]
     ATan2(ArithmeticTerm, ArithmeticTerm, usize),
     #[strum_discriminants(strum(props(Arity = "1", Name = "sqrt")))]
@@ -898,7 +1213,7 @@ fn generate_instruction_preface() -> TokenStream {
         use indexmap::IndexMap;
 
         use std::collections::VecDeque;
-        use std::rc::Rc;
+        use std::sync::Arc;
 
         fn reg_type_into_functor(r: RegType) -> MachineStub {
             match r {
@@ -1012,6 +1327,15 @@ pub enum IndexingInstruction {
             ),
             SwitchOnConstant(IndexMap<HeapCellValue, IndexingCodePtr, FxBuildHasher>),
             SwitchOnStructure(IndexMap<(Atom, usize), IndexingCodePtr, FxBuildHasher>),
+            // Like SwitchOnTerm, on the argument of the given index of the
+            // structure last switched on.
+            SwitchOnSubterm(
+                usize,
+                IndexingCodePtr,
+                IndexingCodePtr,
+                IndexingCodePtr,
+                IndexingCodePtr,
+            ),
         }
 
         #[derive(Debug, Clone, Copy)]
@@ -1058,6 +1382,18 @@ pub fn to_functor(&self) -> MachineStub {
                             ]
                         )
                     }
+                    &IndexingInstruction::SwitchOnSubterm(arg, vars, constants, lists, structures) => {
+                        functor!(
+                            atom!("switch_on_subterm"),
+                            [
+                                fixnum(arg),
+                                indexing_code_ptr(vars),
+                                indexing_code_ptr(constants),
+                                indexing_code_ptr(lists),
+                                indexing_code_ptr(structures)
+                            ]
+                        )
+                    }
                     IndexingInstruction::SwitchOnConstant(constants) => {
                         variadic_functor(
                             atom!("switch_on_constants"),
@@ -1143,6 +1479,7 @@ pub fn registers(&self) -> Vec<RegType> {
                 match *self {
                     Instruction::GetConstant(_, _, r) => vec![r],
                     Instruction::GetList(_, r) => vec![r],
+                    Instruction::GetListVariables(_, r, h, t) => vec![r, h, t],
                     Instruction::GetPartialString(_, _, r) => vec![r],
                     Instruction::GetStructure(_, _, _, r) => vec![r],
                     Instruction::GetVariable(r, t) => vec![r, temp_v!(t)],
@@ -1187,6 +1524,7 @@ pub fn is_head_instr(&self) -> bool {
                     Instruction::Deallocate |
                     Instruction::GetConstant(..) |
                     Instruction::GetList(..) |
+                    Instruction::GetListVariables(..) |
                     Instruction::GetPartialString(..) |
                     Instruction::GetStructure(..) |
                     Instruction::GetValue(..) |
@@ -1440,6 +1778,21 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ATan(ref at, t) => {
                         arith_instr_unary_functor(atom!("atan"), arena, at, t)
                     }
+                    &Instruction::Erf(ref at, t) => {
+                        arith_instr_unary_functor(atom!("erf"), arena, at, t)
+                    }
+                    &Instruction::Erfc(ref at, t) => {
+                        arith_instr_unary_functor(atom!("erfc"), arena, at, t)
+                    }
+                    &Instruction::LGamma(ref at, t) => {
+                        arith_instr_unary_functor(atom!("lgamma"), arena, at, t)
+                    }
+                    &Instruction::ExpM1(ref at, t) => {
+                        arith_instr_unary_functor(atom!("expm1"), arena, at, t)
+                    }
+                    &Instruction::Log1P(ref at, t) => {
+                        arith_instr_unary_functor(atom!("log1p"), arena, at, t)
+                    }
                     &Instruction::Sqrt(ref at, t) => {
                         arith_instr_unary_functor(atom!("sqrt"), arena, at, t)
                     }
@@ -1658,7 +2011,10 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     //
                     &Instruction::CallAtomChars |
                     &Instruction::CallAtomCodes |
+                    &Instruction::CallAtomConcat |
                     &Instruction::CallAtomLength |
+                    &Instruction::CallSubAtom |
+                    &Instruction::CallReportCrash |
                     &Instruction::CallBindFromRegister |
                     &Instruction::CallContinuation |
                     &Instruction::CallCharCode |
@@ -1687,12 +2043,20 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallDeleteDirectory |
                     &Instruction::CallPathCanonical |
                     &Instruction::CallFileTime |
+                    &Instruction::CallFileType |
+                    &Instruction::CallDeleteDirectoryTree |
+                    &Instruction::CallWildcardMatch |
+                    &Instruction::CallFswatchCreate |
+                    &Instruction::CallFswatchWait |
+                    &Instruction::CallFswatchRemove |
                     &Instruction::CallDynamicModuleResolution(..) |
                     &Instruction::CallPrepareCallClause(..) |
                     &Instruction::CallCompileInlineOrExpandedGoal |
                     &Instruction::CallIsExpandedOrInlined |
                     &Instruction::CallGetClauseP |
                     &Instruction::CallInvokeClauseAtP |
+                    &Instruction::CallClauseCandidates |
+                    &Instruction::CallAssertedClauseLoc |
                     &Instruction::CallGetFromAttributedVarList |
                     &Instruction::CallPutToAttributedVarList |
                     &Instruction::CallDeleteFromAttributedVarList |
@@ -1726,6 +2090,79 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallHeadIsDynamic |
                     &Instruction::CallInstallSCCCleaner |
                     &Instruction::CallInstallInferenceCounter |
+                    &Instruction::CallInstallTimeLimit |
+                    &Instruction::CallThreadCreate |
+                    &Instruction::CallThreadCreateBackground |
+                    &Instruction::CallThreadCancel |
+                    &Instruction::CallBackgroundThreads |
+                    &Instruction::CallCpuCount |
+                    &Instruction::CallParseTemplate |
+                    &Instruction::CallFdDomainsIntersection |
+                    &Instruction::CallFdDomainsUnion |
+                    &Instruction::CallFdDomainSubtract |
+                    &Instruction::CallIntervalNumber |
+                    &Instruction::CallIntervalOp |
+                    &Instruction::CallBtreeFindNode |
+                    &Instruction::CallGroupPairs |
+                    &Instruction::CallThreadGoal |
+                    &Instruction::CallThreadStatus |
+                    &Instruction::CallThreadJoin |
+                    &Instruction::CallThreadSelf |
+                    &Instruction::CallThreadSetStatus |
+                    &Instruction::CallMachineCreate |
+                    &Instruction::CallMachineDestroy |
+                    &Instruction::CallMachineStatus |
+                    &Instruction::CallMachineConsult |
+                    &Instruction::CallMachineQuery |
+                    &Instruction::CallMachineNext |
+                    &Instruction::CallMachineClose |
+                    &Instruction::CallEngineCreate |
+                    &Instruction::CallEngineDestroy |
+                    &Instruction::CallEngineFetch |
+                    &Instruction::CallEngineNext |
+                    &Instruction::CallEnginePost |
+                    &Instruction::CallEngineSelf |
+                    &Instruction::CallEngineStatus |
+                    &Instruction::CallEngineYield |
+                    &Instruction::CallMessageQueueCreate |
+                    &Instruction::CallMessageQueueDestroy |
+                    &Instruction::CallMessageQueueNext |
+                    &Instruction::CallMessageQueueRemove |
+                    &Instruction::CallMessageQueueSend |
+                    &Instruction::CallMessageQueueSize |
+                    &Instruction::CallMessageQueueStream |
+                    &Instruction::CallMessageQueueWait |
+                    &Instruction::CallMutexCreate |
+                    &Instruction::CallMutexDestroy |
+                    &Instruction::CallMutexHolder |
+                    &Instruction::CallMutexLock |
+                    &Instruction::CallMutexTrylock |
+                    &Instruction::CallMutexUnlock |
+                    &Instruction::CallConditionCreate |
+                    &Instruction::CallConditionDestroy |
+                    &Instruction::CallConditionSignal |
+                    &Instruction::CallConditionBroadcast |
+                    &Instruction::CallConditionWait |
+                    &Instruction::CallRateLimiterCreate |
+                    &Instruction::CallRateLimiterAcquire |
+                    &Instruction::CallCircuitBreakerCreate |
+                    &Instruction::CallCircuitBreakerEnter |
+                    &Instruction::CallCircuitBreakerExit |
+                    &Instruction::CallCircuitBreakerState |
+                    &Instruction::CallResilienceObjectDestroy |
+                    &Instruction::CallRegexMatches |
+                    &Instruction::CallRegexSplit |
+                    &Instruction::CallRegexReplace |
+                    &Instruction::CallJsonParse |
+                    &Instruction::CallJsonGenerate |
+                    &Instruction::CallJsonNextToken |
+                    &Instruction::CallYamlParse |
+                    &Instruction::CallYamlGenerate |
+                    &Instruction::CallTomlParse |
+                    &Instruction::CallTomlGenerate |
+                    &Instruction::CallCsvReadRow |
+                    &Instruction::CallCsvRowChars |
+                    &Instruction::CallArrayRead |
                     &Instruction::CallInferenceCount |
                     &Instruction::CallLiftedHeapLength |
                     &Instruction::CallLoadLibraryAsStream |
@@ -1736,6 +2173,7 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallNumberToCodes |
                     &Instruction::CallOpDeclaration |
                     &Instruction::CallOpen |
+                    &Instruction::CallOpenResource |
                     &Instruction::CallSetStreamOptions |
                     &Instruction::CallNextStream |
                     &Instruction::CallPartialStringTail |
@@ -1748,10 +2186,12 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallPutChars |
                     &Instruction::CallPutCode |
                     &Instruction::CallReadQueryTerm |
+                    &Instruction::CallHistory |
                     &Instruction::CallReadTerm |
                     &Instruction::CallRedoAttrVarBinding |
                     &Instruction::CallRemoveCallPolicyCheck |
                     &Instruction::CallRemoveInferenceCounter |
+                    &Instruction::CallRemoveTimeLimit |
                     &Instruction::CallResetContinuationMarker |
                     &Instruction::CallRestoreCutPolicy |
                     &Instruction::CallSetCutPoint(..) |
@@ -1770,15 +2210,43 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallGetCutPoint |
                     &Instruction::CallGetDoubleQuotes |
                     &Instruction::CallGetUnknown |
+                    &Instruction::CallGetModuleUnknown |
+                    &Instruction::CallGetErrorLocations |
+                    &Instruction::CallGetPreferRationals |
+                    &Instruction::CallGetFloatFlag |
+                    &Instruction::CallGetRationalFlag |
+                    &Instruction::CallGetIntegerRoundingFunction |
+                    &Instruction::CallGetStyleCheck |
                     &Instruction::CallInstallNewBlock |
                     &Instruction::CallRandomInteger |
                     &Instruction::CallMaybe |
                     &Instruction::CallCpuNow |
+                    &Instruction::CallMemoryUsage |
+                    &Instruction::CallChoicePointCount |
+                    &Instruction::CallProfilerStart |
+                    &Instruction::CallProfilerStop |
+                    &Instruction::CallProfileData |
+                    &Instruction::CallXref |
+                    &Instruction::CallModuleImports |
+                    &Instruction::CallVisiblePredicates |
+                    &Instruction::CallModuleGraph |
+                    &Instruction::CallTraceMode |
+                    &Instruction::CallTraceSpy |
+                    &Instruction::CallTraceCallAt |
+                    &Instruction::CallTraceEvent |
+                    &Instruction::CallSignalAction |
+                    &Instruction::CallPendingSignals |
                     &Instruction::CallDeterministicLengthRundown |
                     &Instruction::CallHttpOpen |
                     &Instruction::CallHttpListen |
                     &Instruction::CallHttpAccept |
+                    &Instruction::CallHttpLookupListener |
                     &Instruction::CallHttpAnswer |
+                    &Instruction::CallHttpWebSocket |
+                    &Instruction::CallWsOpen |
+                    &Instruction::CallWsSend |
+                    &Instruction::CallWsReceive |
+                    &Instruction::CallWsClose |
                     &Instruction::CallLoadForeignLib |
                     &Instruction::CallForeignCall |
                     &Instruction::CallDefineForeignStruct |
@@ -1786,6 +2254,12 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallPredicateDefined |
                     &Instruction::CallStripModule |
                     &Instruction::CallCurrentTime |
+                    &Instruction::CallWallTime |
+                    &Instruction::CallMonotonicTime |
+                    &Instruction::CallStampDateTime |
+                    &Instruction::CallDateTimeStamp |
+                    &Instruction::CallFormatDateTime |
+                    &Instruction::CallParseDateTime |
                     &Instruction::CallQuotedToken |
                     &Instruction::CallReadFromChars |
                     &Instruction::CallReadTermFromChars |
@@ -1799,19 +2273,34 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallSetCutPointByDefault(..) |
                     &Instruction::CallSetDoubleQuotes |
                     &Instruction::CallSetUnknown |
+                    &Instruction::CallSetModuleUnknown |
+                    &Instruction::CallSetErrorLocations |
+                    &Instruction::CallSetPreferRationals |
+                    &Instruction::CallSetFloatFlag |
+                    &Instruction::CallSetRationalFlag |
+                    &Instruction::CallSetIntegerRoundingFunction |
+                    &Instruction::CallSetStyleCheck |
                     &Instruction::CallSetSeed |
+                    &Instruction::CallSetShuffleSeed |
+                    &Instruction::CallShuffleUnspecified |
                     &Instruction::CallSkipMaxList |
                     &Instruction::CallSleep |
                     &Instruction::CallSocketClientOpen |
                     &Instruction::CallSocketServerOpen |
                     &Instruction::CallSocketServerAccept |
                     &Instruction::CallSocketServerClose |
+                    &Instruction::CallUdpSocketOpen |
+                    &Instruction::CallUdpSend |
+                    &Instruction::CallUdpReceive |
+                    &Instruction::CallUdpSocketClose |
                     &Instruction::CallTLSAcceptClient |
                     &Instruction::CallTLSClientConnect |
                     &Instruction::CallSucceed |
+                    &Instruction::CallTermHash |
                     &Instruction::CallTermAttributedVariables |
                     &Instruction::CallTermVariables |
                     &Instruction::CallTermVariablesUnderMaxDepth |
+                    &Instruction::CallTermVariant |
                     &Instruction::CallTruncateLiftedHeapTo |
                     &Instruction::CallUnifyWithOccursCheck |
                     &Instruction::CallUnwindEnvironments |
@@ -1866,10 +2355,24 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallLoadContextModule |
                     &Instruction::CallLoadContextStream |
                     &Instruction::CallPopLoadContext |
+                    &Instruction::CallReportDiagnostic |
+                    &Instruction::CallStyleWarnings |
                     &Instruction::CallPopLoadStatePayload |
                     &Instruction::CallPushLoadContext |
+                    &Instruction::CallSetLoadContextLine |
                     &Instruction::CallPushLoadStatePayload |
                     &Instruction::CallUseModule |
+                    &Instruction::CallReexport |
+                    &Instruction::CallFreezeModuleInterface |
+                    &Instruction::CallModuleInterfaceFrozen |
+                    &Instruction::CallLoadedModule |
+                    &Instruction::CallModuleExports |
+                    &Instruction::CallModuleLocation |
+                    &Instruction::CallLoadedFiles |
+                    &Instruction::CallEmbeddedLibrary |
+                    &Instruction::CallLibraryPaths |
+                    &Instruction::CallFileModified |
+                    &Instruction::CallLoadedFileDynamicPredicates |
                     &Instruction::CallBuiltInProperty |
                     &Instruction::CallMetaPredicateProperty |
                     &Instruction::CallMultifileProperty |
@@ -1881,6 +2384,7 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::CallRetract |
                     &Instruction::CallIsConsistentWithTermQueue |
                     &Instruction::CallFlushTermQueue |
+                    &Instruction::CallCompleteCompilations |
                     &Instruction::CallRemoveModuleExports |
                     &Instruction::CallAddNonCountedBacktracking |
                     &Instruction::CallPopCount |
@@ -1901,7 +2405,10 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     //
                     &Instruction::ExecuteAtomChars |
                     &Instruction::ExecuteAtomCodes |
+                    &Instruction::ExecuteAtomConcat |
                     &Instruction::ExecuteAtomLength |
+                    &Instruction::ExecuteSubAtom |
+                    &Instruction::ExecuteReportCrash |
                     &Instruction::ExecuteBindFromRegister |
                     &Instruction::ExecuteContinuation |
                     &Instruction::ExecuteCharCode |
@@ -1930,12 +2437,20 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecuteDeleteDirectory |
                     &Instruction::ExecutePathCanonical |
                     &Instruction::ExecuteFileTime |
+                    &Instruction::ExecuteFileType |
+                    &Instruction::ExecuteDeleteDirectoryTree |
+                    &Instruction::ExecuteWildcardMatch |
+                    &Instruction::ExecuteFswatchCreate |
+                    &Instruction::ExecuteFswatchWait |
+                    &Instruction::ExecuteFswatchRemove |
                     &Instruction::ExecuteDynamicModuleResolution(..) |
                     &Instruction::ExecutePrepareCallClause(..) |
                     &Instruction::ExecuteCompileInlineOrExpandedGoal |
                     &Instruction::ExecuteIsExpandedOrInlined |
                     &Instruction::ExecuteGetClauseP |
                     &Instruction::ExecuteInvokeClauseAtP |
+                    &Instruction::ExecuteClauseCandidates |
+                    &Instruction::ExecuteAssertedClauseLoc |
                     &Instruction::ExecuteGetFromAttributedVarList |
                     &Instruction::ExecutePutToAttributedVarList |
                     &Instruction::ExecuteDeleteFromAttributedVarList |
@@ -1969,6 +2484,79 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecuteHeadIsDynamic |
                     &Instruction::ExecuteInstallSCCCleaner |
                     &Instruction::ExecuteInstallInferenceCounter |
+                    &Instruction::ExecuteInstallTimeLimit |
+                    &Instruction::ExecuteThreadCreate |
+                    &Instruction::ExecuteThreadCreateBackground |
+                    &Instruction::ExecuteThreadCancel |
+                    &Instruction::ExecuteBackgroundThreads |
+                    &Instruction::ExecuteCpuCount |
+                    &Instruction::ExecuteParseTemplate |
+                    &Instruction::ExecuteFdDomainsIntersection |
+                    &Instruction::ExecuteFdDomainsUnion |
+                    &Instruction::ExecuteFdDomainSubtract |
+                    &Instruction::ExecuteIntervalNumber |
+                    &Instruction::ExecuteIntervalOp |
+                    &Instruction::ExecuteBtreeFindNode |
+                    &Instruction::ExecuteGroupPairs |
+                    &Instruction::ExecuteThreadGoal |
+                    &Instruction::ExecuteThreadStatus |
+                    &Instruction::ExecuteThreadJoin |
+                    &Instruction::ExecuteThreadSelf |
+                    &Instruction::ExecuteThreadSetStatus |
+                    &Instruction::ExecuteMachineCreate |
+                    &Instruction::ExecuteMachineDestroy |
+                    &Instruction::ExecuteMachineStatus |
+                    &Instruction::ExecuteMachineConsult |
+                    &Instruction::ExecuteMachineQuery |
+                    &Instruction::ExecuteMachineNext |
+                    &Instruction::ExecuteMachineClose |
+                    &Instruction::ExecuteEngineCreate |
+                    &Instruction::ExecuteEngineDestroy |
+                    &Instruction::ExecuteEngineFetch |
+                    &Instruction::ExecuteEngineNext |
+                    &Instruction::ExecuteEnginePost |
+                    &Instruction::ExecuteEngineSelf |
+                    &Instruction::ExecuteEngineStatus |
+                    &Instruction::ExecuteEngineYield |
+                    &Instruction::ExecuteMessageQueueCreate |
+                    &Instruction::ExecuteMessageQueueDestroy |
+                    &Instruction::ExecuteMessageQueueNext |
+                    &Instruction::ExecuteMessageQueueRemove |
+                    &Instruction::ExecuteMessageQueueSend |
+                    &Instruction::ExecuteMessageQueueSize |
+                    &Instruction::ExecuteMessageQueueStream |
+                    &Instruction::ExecuteMessageQueueWait |
+                    &Instruction::ExecuteMutexCreate |
+                    &Instruction::ExecuteMutexDestroy |
+                    &Instruction::ExecuteMutexHolder |
+                    &Instruction::ExecuteMutexLock |
+                    &Instruction::ExecuteMutexTrylock |
+                    &Instruction::ExecuteMutexUnlock |
+                    &Instruction::ExecuteConditionCreate |
+                    &Instruction::ExecuteConditionDestroy |
+                    &Instruction::ExecuteConditionSignal |
+                    &Instruction::ExecuteConditionBroadcast |
+                    &Instruction::ExecuteConditionWait |
+                    &Instruction::ExecuteRateLimiterCreate |
+                    &Instruction::ExecuteRateLimiterAcquire |
+                    &Instruction::ExecuteCircuitBreakerCreate |
+                    &Instruction::ExecuteCircuitBreakerEnter |
+                    &Instruction::ExecuteCircuitBreakerExit |
+                    &Instruction::ExecuteCircuitBreakerState |
+                    &Instruction::ExecuteResilienceObjectDestroy |
+                    &Instruction::ExecuteRegexMatches |
+                    &Instruction::ExecuteRegexSplit |
+                    &Instruction::ExecuteRegexReplace |
+                    &Instruction::ExecuteJsonParse |
+                    &Instruction::ExecuteJsonGenerate |
+                    &Instruction::ExecuteJsonNextToken |
+                    &Instruction::ExecuteYamlParse |
+                    &Instruction::ExecuteYamlGenerate |
+                    &Instruction::ExecuteTomlParse |
+                    &Instruction::ExecuteTomlGenerate |
+                    &Instruction::ExecuteCsvReadRow |
+                    &Instruction::ExecuteCsvRowChars |
+                    &Instruction::ExecuteArrayRead |
                     &Instruction::ExecuteInferenceCount |
                     &Instruction::ExecuteLiftedHeapLength |
                     &Instruction::ExecuteLoadLibraryAsStream |
@@ -1979,6 +2567,7 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecuteNumberToCodes |
                     &Instruction::ExecuteOpDeclaration |
                     &Instruction::ExecuteOpen |
+                    &Instruction::ExecuteOpenResource |
                     &Instruction::ExecuteSetStreamOptions |
                     &Instruction::ExecuteNextStream |
                     &Instruction::ExecutePartialStringTail |
@@ -1991,10 +2580,12 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecutePutChars |
                     &Instruction::ExecutePutCode |
                     &Instruction::ExecuteReadQueryTerm |
+                    &Instruction::ExecuteHistory |
                     &Instruction::ExecuteReadTerm |
                     &Instruction::ExecuteRedoAttrVarBinding |
                     &Instruction::ExecuteRemoveCallPolicyCheck |
                     &Instruction::ExecuteRemoveInferenceCounter |
+                    &Instruction::ExecuteRemoveTimeLimit |
                     &Instruction::ExecuteResetContinuationMarker |
                     &Instruction::ExecuteRestoreCutPolicy |
                     &Instruction::ExecuteSetCutPoint(_) |
@@ -2013,15 +2604,43 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecuteGetCutPoint |
                     &Instruction::ExecuteGetDoubleQuotes |
                     &Instruction::ExecuteGetUnknown |
+                    &Instruction::ExecuteGetModuleUnknown |
+                    &Instruction::ExecuteGetErrorLocations |
+                    &Instruction::ExecuteGetPreferRationals |
+                    &Instruction::ExecuteGetFloatFlag |
+                    &Instruction::ExecuteGetRationalFlag |
+                    &Instruction::ExecuteGetIntegerRoundingFunction |
+                    &Instruction::ExecuteGetStyleCheck |
                     &Instruction::ExecuteInstallNewBlock |
                     &Instruction::ExecuteRandomInteger |
                     &Instruction::ExecuteMaybe |
                     &Instruction::ExecuteCpuNow |
+                    &Instruction::ExecuteMemoryUsage |
+                    &Instruction::ExecuteChoicePointCount |
+                    &Instruction::ExecuteProfilerStart |
+                    &Instruction::ExecuteProfilerStop |
+                    &Instruction::ExecuteProfileData |
+                    &Instruction::ExecuteXref |
+                    &Instruction::ExecuteModuleImports |
+                    &Instruction::ExecuteVisiblePredicates |
+                    &Instruction::ExecuteModuleGraph |
+                    &Instruction::ExecuteTraceMode |
+                    &Instruction::ExecuteTraceSpy |
+                    &Instruction::ExecuteTraceCallAt |
+                    &Instruction::ExecuteTraceEvent |
+                    &Instruction::ExecuteSignalAction |
+                    &Instruction::ExecutePendingSignals |
                     &Instruction::ExecuteDeterministicLengthRundown |
                     &Instruction::ExecuteHttpOpen |
                     &Instruction::ExecuteHttpListen |
                     &Instruction::ExecuteHttpAccept |
+                    &Instruction::ExecuteHttpLookupListener |
                     &Instruction::ExecuteHttpAnswer |
+                    &Instruction::ExecuteHttpWebSocket |
+                    &Instruction::ExecuteWsOpen |
+                    &Instruction::ExecuteWsSend |
+                    &Instruction::ExecuteWsReceive |
+                    &Instruction::ExecuteWsClose |
                     &Instruction::ExecuteLoadForeignLib |
                     &Instruction::ExecuteForeignCall |
                     &Instruction::ExecuteDefineForeignStruct |
@@ -2029,6 +2648,12 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecutePredicateDefined |
                     &Instruction::ExecuteStripModule |
                     &Instruction::ExecuteCurrentTime |
+                    &Instruction::ExecuteWallTime |
+                    &Instruction::ExecuteMonotonicTime |
+                    &Instruction::ExecuteStampDateTime |
+                    &Instruction::ExecuteDateTimeStamp |
+                    &Instruction::ExecuteFormatDateTime |
+                    &Instruction::ExecuteParseDateTime |
                     &Instruction::ExecuteQuotedToken |
                     &Instruction::ExecuteReadFromChars |
                     &Instruction::ExecuteReadTermFromChars |
@@ -2042,19 +2667,34 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecuteSetCutPointByDefault(_) |
                     &Instruction::ExecuteSetDoubleQuotes |
                     &Instruction::ExecuteSetUnknown |
+                    &Instruction::ExecuteSetModuleUnknown |
+                    &Instruction::ExecuteSetErrorLocations |
+                    &Instruction::ExecuteSetPreferRationals |
+                    &Instruction::ExecuteSetFloatFlag |
+                    &Instruction::ExecuteSetRationalFlag |
+                    &Instruction::ExecuteSetIntegerRoundingFunction |
+                    &Instruction::ExecuteSetStyleCheck |
                     &Instruction::ExecuteSetSeed |
+                    &Instruction::ExecuteSetShuffleSeed |
+                    &Instruction::ExecuteShuffleUnspecified |
                     &Instruction::ExecuteSkipMaxList |
                     &Instruction::ExecuteSleep |
                     &Instruction::ExecuteSocketClientOpen |
                     &Instruction::ExecuteSocketServerOpen |
                     &Instruction::ExecuteSocketServerAccept |
                     &Instruction::ExecuteSocketServerClose |
+                    &Instruction::ExecuteUdpSocketOpen |
+                    &Instruction::ExecuteUdpSend |
+                    &Instruction::ExecuteUdpReceive |
+                    &Instruction::ExecuteUdpSocketClose |
                     &Instruction::ExecuteTLSAcceptClient |
                     &Instruction::ExecuteTLSClientConnect |
                     &Instruction::ExecuteSucceed |
+                    &Instruction::ExecuteTermHash |
                     &Instruction::ExecuteTermAttributedVariables |
                     &Instruction::ExecuteTermVariables |
                     &Instruction::ExecuteTermVariablesUnderMaxDepth |
+                    &Instruction::ExecuteTermVariant |
                     &Instruction::ExecuteTruncateLiftedHeapTo |
                     &Instruction::ExecuteUnifyWithOccursCheck |
                     &Instruction::ExecuteUnwindEnvironments |
@@ -2109,10 +2749,24 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecuteLoadContextModule |
                     &Instruction::ExecuteLoadContextStream |
                     &Instruction::ExecutePopLoadContext |
+                    &Instruction::ExecuteReportDiagnostic |
+                    &Instruction::ExecuteStyleWarnings |
                     &Instruction::ExecutePopLoadStatePayload |
                     &Instruction::ExecutePushLoadContext |
+                    &Instruction::ExecuteSetLoadContextLine |
                     &Instruction::ExecutePushLoadStatePayload |
                     &Instruction::ExecuteUseModule |
+                    &Instruction::ExecuteReexport |
+                    &Instruction::ExecuteFreezeModuleInterface |
+                    &Instruction::ExecuteModuleInterfaceFrozen |
+                    &Instruction::ExecuteLoadedModule |
+                    &Instruction::ExecuteModuleExports |
+                    &Instruction::ExecuteModuleLocation |
+                    &Instruction::ExecuteLoadedFiles |
+                    &Instruction::ExecuteEmbeddedLibrary |
+                    &Instruction::ExecuteLibraryPaths |
+                    &Instruction::ExecuteFileModified |
+                    &Instruction::ExecuteLoadedFileDynamicPredicates |
                     &Instruction::ExecuteBuiltInProperty |
                     &Instruction::ExecuteMetaPredicateProperty |
                     &Instruction::ExecuteMultifileProperty |
@@ -2124,6 +2778,7 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
                     &Instruction::ExecuteRetract |
                     &Instruction::ExecuteIsConsistentWithTermQueue |
                     &Instruction::ExecuteFlushTermQueue |
+                    &Instruction::ExecuteCompleteCompilations |
                     &Instruction::ExecuteRemoveModuleExports |
                     &Instruction::ExecuteAddNonCountedBacktracking |
                     &Instruction::ExecutePopCount |
@@ -2168,6 +2823,17 @@ fn to_functor(&self, arena: &mut Arena) -> MachineStub {
 
                         functor!(atom!("get_list"), [functor(lvl_stub), functor(rt_stub)])
                     }
+                    &Instruction::GetListVariables(lvl, r, h, t) => {
+                        let lvl_stub = lvl.into_functor();
+                        let rt_stub = reg_type_into_functor(r);
+                        let h_stub = reg_type_into_functor(h);
+                        let t_stub = reg_type_into_functor(t);
+
+                        functor!(atom!("get_list_variables"), [functor(lvl_stub),
+                                                               functor(rt_stub),
+                                                               functor(h_stub),
+                                                               functor(t_stub)])
+                    }
                     &Instruction::GetPartialString(lvl, ref s, r) => {
                         let lvl_stub = lvl.into_functor();
                         let rt_stub = reg_type_into_functor(r);
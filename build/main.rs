@@ -4,8 +4,9 @@
 use instructions_template::generate_instructions_rs;
 use static_string_indexing::index_static_strings;
 
+use std::collections::BTreeSet;
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
@@ -42,6 +43,90 @@ fn find_prolog_files(path_prefix: &str, current_dir: &Path) -> Vec<(String, Path
     libraries
 }
 
+// removes the block comments and the lines commented out with % from
+// a Prolog source, which mention libraries in examples.
+fn strip_comments(source: &str) -> String {
+    let mut code = String::new();
+    let mut rest = source;
+
+    while let Some(start) = rest.find("/*") {
+        code.push_str(&rest[..start]);
+
+        rest = match rest[start..].find("*/") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+
+    code.push_str(rest);
+
+    code.lines()
+        .filter(|line| !line.trim_start().starts_with('%'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// the names of the libraries a Prolog file refers to with
+// library(Name), e.g. in use_module/1 directives.
+fn referenced_libraries(source: &str) -> Vec<String> {
+    strip_comments(source)
+        .split("library(")
+        .skip(1)
+        .filter_map(|rest| {
+            let name = rest.split(')').next()?.trim().trim_matches('\'');
+            Some(name.to_string())
+        })
+        .collect()
+}
+
+// the libraries that are loaded while the machine is built, together
+// with those named by the comma-separated SCRYER_LIBRARIES variable and
+// the libraries they depend on.
+fn needed_libraries(libraries: &[(String, PathBuf)]) -> BTreeSet<String> {
+    let mut needed = BTreeSet::from([
+        "builtins".to_string(),
+        "ops_and_meta_predicates".to_string(),
+    ]);
+
+    let mut pending: Vec<String> = [
+        "src/loader.pl",
+        "src/toplevel.pl",
+        "src/machine/attributed_variables.pl",
+        "src/machine/project_attributes.pl",
+    ]
+    .iter()
+    .flat_map(|path| referenced_libraries(&fs::read_to_string(path).unwrap()))
+    .chain(needed.iter().cloned())
+    .collect();
+
+    if let Ok(extra) = env::var("SCRYER_LIBRARIES") {
+        pending.extend(
+            extra
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from),
+        );
+    }
+
+    while let Some(name) = pending.pop() {
+        let Some((_, path)) = libraries.iter().find(|(lib_name, _)| *lib_name == name) else {
+            // e.g. '$atts', which is not a file.
+            continue;
+        };
+
+        needed.insert(name);
+
+        for dep in referenced_libraries(&fs::read_to_string(path).unwrap()) {
+            if !needed.contains(&dep) {
+                pending.push(dep);
+            }
+        }
+    }
+
+    needed
+}
+
 fn main() {
     let has_rustfmt = Command::new("rustfmt")
         .arg("--version")
@@ -59,7 +144,12 @@ fn main() {
     let mut libraries = File::create(dest_path).unwrap();
     let lib_path = Path::new("src").join("lib");
 
-    let constants = find_prolog_files("", &lib_path);
+    let mut constants = find_prolog_files("", &lib_path);
+
+    if env::var_os("CARGO_FEATURE_MINIMAL_LIBRARIES").is_some() {
+        let needed = needed_libraries(&constants);
+        constants.retain(|(name, _)| needed.contains(name));
+    }
 
     let out_dir = std::env::var("OUT_DIR").unwrap();
 
@@ -101,6 +191,7 @@ fn main() {
     }
 
     println!("cargo:rerun-if-changed=src/");
+    println!("cargo:rerun-if-env-changed=SCRYER_LIBRARIES");
 }
 
 fn format_generated_file(path: &Path) {
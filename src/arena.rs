@@ -1,7 +1,7 @@
 #![allow(clippy::new_without_default)] // annotating structs annotated with #[bitfield] doesn't work
 
 #[cfg(feature = "http")]
-use crate::http::{HttpListener, HttpResponse};
+use crate::http::{HttpListener, HttpResponse, WebSocket};
 use crate::machine::loader::LiveLoadState;
 use crate::machine::streams::*;
 use crate::offset_table::*;
@@ -11,6 +11,7 @@
 use crate::parser::dashu::{Integer, Rational};
 use ordered_float::OrderedFloat;
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
@@ -18,7 +19,7 @@
 use std::io::PipeWriter;
 use std::mem;
 use std::mem::ManuallyDrop;
-use std::net::TcpListener;
+use std::net::{TcpListener, UdpSocket};
 use std::ops::{Deref, DerefMut};
 use std::process::Child;
 use std::ptr;
@@ -78,6 +79,9 @@ pub enum ArenaHeaderTag {
     Dropped = 0b1000100,
     PipeReader = 0b1001001,
     ChildProcess = 0b1001010,
+    WebSocket = 0b1001011,
+    UdpSocket = 0b1001100,
+    MessageQueueStream = 0b1001101,
 }
 
 #[bitfield]
@@ -375,6 +379,20 @@ fn tag() -> ArenaHeaderTag {
     }
 }
 
+impl AllocateInArena<UdpSocket> for UdpSocket {
+    fn arena_allocate(self, arena: &mut Arena) -> TypedArenaPtr<UdpSocket> {
+        UdpSocket::alloc(arena, ManuallyDrop::new(self))
+    }
+}
+
+impl ArenaAllocated for UdpSocket {
+    type Payload = ManuallyDrop<Self>;
+    #[inline]
+    fn tag() -> ArenaHeaderTag {
+        ArenaHeaderTag::UdpSocket
+    }
+}
+
 #[cfg(feature = "http")]
 impl ArenaAllocated for HttpListener {
     type Payload = Self;
@@ -406,6 +424,22 @@ fn arena_allocate(self, arena: &mut Arena) -> TypedArenaPtr<Child> {
     }
 }
 
+#[cfg(feature = "http")]
+impl ArenaAllocated for WebSocket {
+    type Payload = ManuallyDrop<Self>;
+    #[inline]
+    fn tag() -> ArenaHeaderTag {
+        ArenaHeaderTag::WebSocket
+    }
+}
+
+#[cfg(feature = "http")]
+impl AllocateInArena<WebSocket> for WebSocket {
+    fn arena_allocate(self, arena: &mut Arena) -> TypedArenaPtr<WebSocket> {
+        WebSocket::alloc(arena, ManuallyDrop::new(self))
+    }
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct AllocSlab {
@@ -477,6 +511,9 @@ pub struct Arena {
     base: Option<UntypedArenaSlab>,
     pub f64_tbl: F64Table,
     pub code_index_tbl: CodeIndexTable,
+    // the offsets of the floats clauses are indexed on, by the bits
+    // of their values.
+    f64_index_keys: HashMap<u64, F64Offset>,
 }
 
 unsafe impl Send for Arena {}
@@ -490,6 +527,33 @@ pub fn new() -> Self {
             base: None,
             f64_tbl: F64Table::new(),
             code_index_tbl: CodeIndexTable::new(),
+            f64_index_keys: HashMap::new(),
+        }
+    }
+
+    /// The offset of a float unifying with the one at offset, the same
+    /// for all such floats, so that clauses are indexed on the values
+    /// of their floats rather than on where they are stored. All NaNs
+    /// have the same offset, while -0.0 and 0.0 don't.
+    pub fn f64_index_key(&mut self, offset: F64Offset) -> F64Offset {
+        let bits = self.f64_index_bits(offset);
+        *self.f64_index_keys.entry(bits).or_insert(offset)
+    }
+
+    /// The offset [`Arena::f64_index_key`] gave a float unifying with
+    /// the one at offset, if any.
+    pub fn find_f64_index_key(&self, offset: F64Offset) -> Option<F64Offset> {
+        let bits = self.f64_index_bits(offset);
+        self.f64_index_keys.get(&bits).copied()
+    }
+
+    fn f64_index_bits(&self, offset: F64Offset) -> u64 {
+        let value = self.f64_tbl.get_entry(offset);
+
+        if value.is_nan() {
+            f64::NAN.to_bits()
+        } else {
+            value.to_bits()
         }
     }
 }
@@ -544,6 +608,9 @@ macro_rules! drop_typed_slab_in_place {
         ArenaHeaderTag::InputChannelStream => {
             drop_typed_slab_in_place!(InputChannelStream, value);
         }
+        ArenaHeaderTag::MessageQueueStream => {
+            drop_typed_slab_in_place!(MessageQueueStream, value);
+        }
         ArenaHeaderTag::LiveLoadState | ArenaHeaderTag::InactiveLoadState => {
             drop_typed_slab_in_place!(LiveLoadState, value);
         }
@@ -551,6 +618,9 @@ macro_rules! drop_typed_slab_in_place {
         ArenaHeaderTag::TcpListener => {
             drop_typed_slab_in_place!(TcpListener, value);
         }
+        ArenaHeaderTag::UdpSocket => {
+            drop_typed_slab_in_place!(UdpSocket, value);
+        }
         ArenaHeaderTag::HttpListener => {
             #[cfg(feature = "http")]
             drop_typed_slab_in_place!(HttpListener, value);
@@ -574,6 +644,10 @@ macro_rules! drop_typed_slab_in_place {
         ArenaHeaderTag::ChildProcess => {
             drop_typed_slab_in_place!(Child, value);
         }
+        ArenaHeaderTag::WebSocket => {
+            #[cfg(feature = "http")]
+            drop_typed_slab_in_place!(WebSocket, value);
+        }
         ArenaHeaderTag::NullStream => {
             unreachable!("NullStream is never arena allocated!");
         }
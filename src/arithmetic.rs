@@ -138,7 +138,7 @@ fn next(&mut self) -> Option<Self::Item> {
 #[derive(Debug)]
 pub(crate) struct ArithmeticEvaluator<'a> {
     marker: &'a mut DebrayAllocator,
-    f64_tbl: &'a F64Table,
+    f64_tbl: &'a dyn F64Entries,
     interm: Vec<ArithmeticTerm>,
     interm_c: usize,
 }
@@ -158,7 +158,7 @@ fn iter(self) -> Result<Self::Iter, ArithmeticError> {
 }
 
 fn push_literal(
-    f64_tbl: &F64Table,
+    f64_tbl: &dyn F64Entries,
     interm: &mut Vec<ArithmeticTerm>,
     c: &Literal,
 ) -> Result<(), ArithmeticError> {
@@ -166,7 +166,7 @@ fn push_literal(
         Literal::Fixnum(n) => interm.push(ArithmeticTerm::Number(Number::Fixnum(*n))),
         Literal::Integer(n) => interm.push(ArithmeticTerm::Number(Number::Integer(*n))),
         &Literal::F64Offset(offset) => {
-            let n = f64_tbl.get_entry(offset);
+            let n = f64_tbl.f64_entry(offset);
             interm.push(ArithmeticTerm::Number(Number::Float(n)));
         }
         Literal::Rational(n) => interm.push(ArithmeticTerm::Number(Number::Rational(*n))),
@@ -179,6 +179,12 @@ fn push_literal(
         Literal::Atom(name) if name == &atom!("epsilon") => interm.push(ArithmeticTerm::Number(
             Number::Float(OrderedFloat(f64::EPSILON)),
         )),
+        Literal::Atom(name) if name == &atom!("inf") || name == &atom!("infinite") => interm.push(
+            ArithmeticTerm::Number(Number::Float(OrderedFloat(f64::INFINITY))),
+        ),
+        Literal::Atom(name) if name == &atom!("nan") => interm.push(ArithmeticTerm::Number(
+            Number::Float(OrderedFloat(f64::NAN)),
+        )),
         _ => return Err(ArithmeticError::NonEvaluableFunctor(*c, 0)),
     }
 
@@ -188,7 +194,7 @@ fn push_literal(
 impl<'a> ArithmeticEvaluator<'a> {
     pub(crate) fn new(
         marker: &'a mut DebrayAllocator,
-        f64_tbl: &'a F64Table,
+        f64_tbl: &'a dyn F64Entries,
         target_int: usize,
     ) -> Self {
         ArithmeticEvaluator {
@@ -218,6 +224,11 @@ fn get_unary_instr(
             atom!("acos") => Ok(Instruction::ACos(a1, t)),
             atom!("asin") => Ok(Instruction::ASin(a1, t)),
             atom!("atan") => Ok(Instruction::ATan(a1, t)),
+            atom!("erf") => Ok(Instruction::Erf(a1, t)),
+            atom!("erfc") => Ok(Instruction::Erfc(a1, t)),
+            atom!("lgamma") => Ok(Instruction::LGamma(a1, t)),
+            atom!("expm1") => Ok(Instruction::ExpM1(a1, t)),
+            atom!("log1p") => Ok(Instruction::Log1P(a1, t)),
             atom!("float") => Ok(Instruction::Float(a1, t)),
             atom!("truncate") => Ok(Instruction::Truncate(a1, t)),
             atom!("round") => Ok(Instruction::Round(a1, t)),
@@ -432,21 +443,62 @@ pub(crate) fn result_f(n: &Number) -> Result<f64, EvalError> {
     classify_float(rnd_f(n))
 }
 
+thread_local! {
+    // the float flags of the machine running on this thread, which the
+    // float functions of this module aren't passed. published by the
+    // machine as it starts running and as the flags are set.
+    static FLOAT_FLAGS: Cell<FloatFlags> = const {
+        Cell::new(FloatFlags {
+            overflow_to_infinity: false,
+            zero_div_to_infinity: false,
+            undefined_to_nan: false,
+        })
+    };
+}
+
+#[inline]
+pub(crate) fn publish_float_flags(flags: FloatFlags) {
+    FLOAT_FLAGS.with(|cell| cell.set(flags));
+}
+
+#[inline]
+fn float_flags() -> FloatFlags {
+    FLOAT_FLAGS.with(|cell| cell.get())
+}
+
 fn classify_float(f: f64) -> Result<f64, EvalError> {
     match f.classify() {
         FpCategory::Normal | FpCategory::Zero => Ok(f),
         FpCategory::Infinite => {
-            if OrderedFloat(f) == OrderedFloat(f64::MAX) {
+            if float_flags().overflow_to_infinity {
                 Ok(f)
             } else {
                 Err(EvalError::FloatOverflow)
             }
         }
-        FpCategory::Nan => Err(EvalError::Undefined),
+        FpCategory::Nan => {
+            if float_flags().undefined_to_nan {
+                Ok(f)
+            } else {
+                Err(EvalError::Undefined)
+            }
+        }
         _ => Ok(f),
     }
 }
 
+// the standard order of floats, which unlike that of OrderedFloat puts
+// -0.0 before 0.0 and NaN before all other floats, all NaNs being
+// identical.
+pub(crate) fn float_cmp(f1: f64, f2: f64) -> Ordering {
+    match (f1.is_nan(), f2.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => f1.total_cmp(&f2),
+    }
+}
+
 #[inline]
 pub(crate) fn float_fn_to_f(n: i64) -> Result<f64, EvalError> {
     classify_float(n as f64)
@@ -475,10 +527,17 @@ pub(crate) fn mul_f(f1: f64, f2: f64) -> Result<OrderedFloat<f64>, EvalError> {
 #[inline]
 fn div_f(f1: f64, f2: f64) -> Result<OrderedFloat<f64>, EvalError> {
     if FpCategory::Zero == f2.classify() {
-        Err(EvalError::ZeroDivisor)
-    } else {
-        Ok(OrderedFloat(classify_float(f1 / f2)?))
+        if !float_flags().zero_div_to_infinity {
+            return Err(EvalError::ZeroDivisor);
+        }
+
+        // 0.0 / 0.0 is NaN, which float_undefined governs.
+        if f1 != 0.0 && !f1.is_nan() {
+            return Ok(OrderedFloat(f1 / f2));
+        }
     }
+
+    Ok(OrderedFloat(classify_float(f1 / f2)?))
 }
 
 impl Div<Number> for Number {
@@ -624,6 +683,21 @@ fn eq(&self, rhs: &usize) -> bool {
     }
 }
 
+impl Number {
+    // the order of arithmetic comparison, in which NaN is unordered with
+    // every number, itself included, and -0.0 equals 0.0.
+    #[inline]
+    pub(crate) fn arith_cmp(&self, rhs: &Number) -> Option<Ordering> {
+        let is_nan = |n: &Number| matches!(n, Number::Float(f) if f.is_nan());
+
+        if is_nan(self) || is_nan(rhs) {
+            None
+        } else {
+            Some(self.cmp(rhs))
+        }
+    }
+}
+
 impl PartialOrd for Number {
     fn partial_cmp(&self, rhs: &Number) -> Option<Ordering> {
         Some(self.cmp(rhs))
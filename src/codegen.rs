@@ -6,17 +6,18 @@
 use crate::indexing::*;
 use crate::instructions::*;
 use crate::iterators::*;
-use crate::offset_table::F64Table;
+use crate::offset_table::F64Entries;
 use crate::parser::ast::*;
 use crate::targets::*;
 use crate::types::*;
 use crate::variable_records::*;
 
 use crate::machine::disjuncts::*;
+use crate::machine::index_cache::*;
 use crate::machine::machine_errors::*;
 
 use fxhash::FxBuildHasher;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 
 use std::cell::Cell;
 use std::collections::VecDeque;
@@ -273,8 +274,14 @@ pub(crate) fn default_call_policy(&self) -> CallPolicy {
 pub(crate) struct CodeGenerator<'f64_tbl> {
     marker: DebrayAllocator,
     settings: CodeGenSettings,
-    f64_tbl: &'f64_tbl F64Table,
+    f64_tbl: &'f64_tbl dyn F64Entries,
     pub(crate) skeleton: PredicateSkeleton,
+    // the offsets at which the code of each clause of the compiled
+    // predicate starts, in clause order.
+    pub(crate) clause_starts: Vec<usize>,
+    // the indexing code cached for the predicate, if it's static and
+    // the index cache is enabled.
+    pub(crate) index_cache: Option<PredicateIndices>,
 }
 
 impl DebrayAllocator {
@@ -355,13 +362,45 @@ fn structure_cell(term: &Term) -> Option<&Cell<RegType>> {
     }
 }
 
+// replaces each get_list followed by unify_variable instructions for
+// the head and the tail of the list with a single get_list_variables
+// instruction, saving two dispatches per list matched in a head.
+fn fuse_list_variables(code: CodeDeque) -> Code {
+    let mut fused = Vec::with_capacity(code.len());
+    let mut iter = code.into_iter().peekable();
+
+    while let Some(instr) = iter.next() {
+        if let Instruction::GetList(lvl, r) = instr {
+            if let Some(&Instruction::UnifyVariable(h)) = iter.peek() {
+                iter.next();
+
+                if let Some(&Instruction::UnifyVariable(t)) = iter.peek() {
+                    iter.next();
+                    fused.push(Instruction::GetListVariables(lvl, r, h, t));
+                } else {
+                    fused.push(instr);
+                    fused.push(Instruction::UnifyVariable(h));
+                }
+
+                continue;
+            }
+        }
+
+        fused.push(instr);
+    }
+
+    fused
+}
+
 impl<'f64_tbl> CodeGenerator<'f64_tbl> {
-    pub(crate) fn new(f64_tbl: &'f64_tbl F64Table, settings: CodeGenSettings) -> Self {
+    pub(crate) fn new(f64_tbl: &'f64_tbl dyn F64Entries, settings: CodeGenSettings) -> Self {
         CodeGenerator {
             marker: DebrayAllocator::new(),
             settings,
             f64_tbl,
             skeleton: PredicateSkeleton::new(),
+            clause_starts: vec![],
+            index_cache: None,
         }
     }
 
@@ -449,7 +488,13 @@ fn compile_target<'a, Target, Iter>(&mut self, iter: Iter, context: GenContext)
                         if let Some(subterm @ Term::Literal(_, Literal::CodeIndexOffset(_))) =
                             terms.last()
                         {
-                            self.subterm_to_instr::<Target>(subterm, context, &mut target);
+                            // the code index of an expanded goal is
+                            // only kept by the structures built in the
+                            // body. head structures match without it.
+                            if !matches!(context, GenContext::Head) {
+                                self.subterm_to_instr::<Target>(subterm, context, &mut target);
+                            }
+
                             0..terms.len() - 1
                         } else {
                             0..terms.len()
@@ -1063,7 +1108,7 @@ pub(crate) fn compile_rule(
         }
 
         self.marker.reset_free_list();
-        code.extend(fact);
+        code.extend(fuse_list_variables(fact));
 
         self.compile_seq(clauses, &mut code)?;
 
@@ -1088,7 +1133,7 @@ pub(crate) fn compile_fact(
                 return Err(CompilationError::ExceededMaxArity);
             }
 
-            code.extend(compiled_fact);
+            code.extend(fuse_list_variables(compiled_fact));
         }
 
         code.push(instr!("proceed"));
@@ -1119,7 +1164,7 @@ fn split_predicate(clauses: &[PredicateClause]) -> Vec<ClauseSpan> {
         'outer: for (right, clause) in clauses.iter().enumerate() {
             if let Some(args) = clause.args() {
                 for (instantiated_arg_index, arg) in args.iter().enumerate() {
-                    if !matches!(arg, Term::Var(..) | Term::AnonVar) {
+                    if is_indexable_arg(arg) {
                         if optimal_index != instantiated_arg_index {
                             if left >= right {
                                 optimal_index = instantiated_arg_index;
@@ -1181,8 +1226,17 @@ fn compile_pred_subseq<I: Indexer>(
             CodeOffsets::new(I::new(), optimal_index + 1, self.settings.non_counted_bt);
 
         let mut skip_stub_try_me_else = false;
+        let mut clause_offsets = vec![];
+        let mut clause_starts = vec![];
+        let mut clause_subkeys = IndexMap::with_hasher(FxBuildHasher::default());
         let clauses_len = clauses.len();
 
+        // the clauses of a run whose index may be cached are indexed
+        // once it's known the cached index doesn't match them.
+        let cache_index = !self.settings.is_extensible
+            && clauses_len >= MIN_CLAUSES_INDEX_CACHED
+            && self.index_cache.is_some();
+
         for (i, clause) in clauses.iter_mut().enumerate() {
             self.marker.reset();
 
@@ -1228,23 +1282,72 @@ fn compile_pred_subseq<I: Indexer>(
             if let Some(arg) = arg {
                 let index = code.len();
 
-                if clauses_len > 1 || self.settings.is_extensible {
+                if cache_index {
+                    clause_index_info.opt_arg_index_key = opt_arg_index_key(optimal_index + 1, arg);
+                    clause_offsets.push((i, index + 1));
+                } else if clauses_len > 1 || self.settings.is_extensible {
                     code_offsets.index_term(arg, index, &mut clause_index_info);
+                    clause_offsets.push((i, index + 1));
+
+                    if self.settings.is_dynamic() && clause_index_info.opt_arg_index_key.is_some() {
+                        if let Some(args) = clause.args() {
+                            let subkeys = dynamic_subkeys(optimal_index + 1, args);
+
+                            clause_subkeys.insert(index + 1, subkeys.clone());
+                            clause_index_info.subkeys = Some(Box::new(subkeys));
+                        }
+                    }
                 }
             }
 
+            if self.settings.is_dynamic() {
+                clause_index_info.first_arg_key = clause
+                    .args()
+                    .and_then(|args| args.first())
+                    .and_then(subindex_key);
+            }
+
             self.skeleton.clauses.push_back(clause_index_info);
+            clause_starts.push(code.len());
             code.extend(clause_code.into_iter());
         }
 
         let index_code = if clauses_len > 1 || self.settings.is_extensible {
-            code_offsets.compute_indices(skip_stub_try_me_else)
+            // the clauses of extensible predicates are indexed
+            // incrementally, on the indexed argument, and those of
+            // dynamic predicates further as they grow.
+            if self.settings.is_extensible {
+                let mut index_code = code_offsets.compute_indices(skip_stub_try_me_else, None);
+
+                if !clause_subkeys.is_empty() {
+                    let lookup = DynamicSubkeysLookup::Compiled(&clause_subkeys);
+                    subindex_dynamic_choices(&mut index_code, &lookup);
+                }
+
+                index_code
+            } else if cache_index {
+                self.cached_index_code(clauses, optimal_index, &clause_offsets)
+            } else {
+                let clause_args: IndexMap<usize, &[Term], FxBuildHasher> = clause_offsets
+                    .into_iter()
+                    .filter_map(|(i, offset)| Some((offset, clauses[i].args()?)))
+                    .collect();
+
+                let subindexer = SubIndexer::new(
+                    &clause_args,
+                    optimal_index + 1,
+                    self.settings.non_counted_bt,
+                );
+
+                code_offsets.compute_indices(skip_stub_try_me_else, Some(&subindexer))
+            }
         } else {
             vec![]
         };
 
         if !index_code.is_empty() {
             code.push_front(Instruction::IndexingCode(index_code));
+            clause_starts.iter_mut().for_each(|start| *start += 1);
         } else if clauses.len() == 1 && self.settings.is_extensible {
             // the condition is the value of skip_stub_try_me_else, which is
             // true if the predicate is not dynamic. This operation must apply
@@ -1252,11 +1355,69 @@ fn compile_pred_subseq<I: Indexer>(
 
             // remove the TryMeElse(0).
             code.pop_front();
+            clause_starts.iter_mut().for_each(|start| *start -= 1);
         }
 
+        self.clause_starts.extend(clause_starts);
+
         Ok(Vec::from(code))
     }
 
+    // the indexing code of a run of the clauses of a static predicate,
+    // taken from the index cache if it was computed for the same
+    // clauses, or computed and added to it.
+    fn cached_index_code(
+        &mut self,
+        clauses: &[PredicateClause],
+        optimal_index: usize,
+        clause_offsets: &[(usize, usize)],
+    ) -> Vec<IndexingLine> {
+        let fingerprint = run_fingerprint(
+            optimal_index,
+            self.settings.non_counted_bt,
+            clause_offsets
+                .iter()
+                .filter_map(|&(i, offset)| Some((clauses[i].args()?, offset))),
+        );
+
+        let Some(index_cache) = self.index_cache.as_mut() else {
+            unreachable!("runs are indexed from the cache only if there is one")
+        };
+
+        if let Some(index_code) = index_cache.lookup(fingerprint) {
+            index_cache.record(fingerprint, &index_code, true);
+            return index_code;
+        }
+
+        let mut code_offsets = CodeOffsets::new(
+            StaticCodeIndices::new(),
+            optimal_index + 1,
+            self.settings.non_counted_bt,
+        );
+
+        let mut clause_index_info = ClauseIndexInfo::new(0);
+        let mut clause_args: IndexMap<usize, &[Term], FxBuildHasher> =
+            IndexMap::with_hasher(FxBuildHasher::default());
+
+        for &(i, offset) in clause_offsets {
+            if let Some(args) = clauses[i].args() {
+                code_offsets.index_term(&args[optimal_index], offset - 1, &mut clause_index_info);
+                clause_args.insert(offset, args);
+            }
+        }
+
+        let subindexer = SubIndexer::new(
+            &clause_args,
+            optimal_index + 1,
+            self.settings.non_counted_bt,
+        );
+
+        let index_code = code_offsets.compute_indices(false, Some(&subindexer));
+        index_cache.record(fingerprint, &index_code, false);
+
+        index_code
+    }
+
     pub(crate) fn compile_predicate(
         &mut self,
         mut clauses: Vec<PredicateClause>,
@@ -1273,6 +1434,7 @@ pub(crate) fn compile_predicate(
         } in split_pred
         {
             let skel_lower_bound = self.skeleton.clauses.len();
+            let starts_lower_bound = self.clause_starts.len();
             let code_segment = if self.settings.is_dynamic() {
                 self.compile_pred_subseq::<DynamicCodeIndices>(
                     &mut clauses[left..right],
@@ -1299,6 +1461,10 @@ pub(crate) fn compile_predicate(
                 code.push(self.settings.try_me_else(0));
             }
 
+            for start in &mut self.clause_starts[starts_lower_bound..] {
+                *start += code.len();
+            }
+
             if self.settings.is_extensible {
                 let segment_is_indexed = code_segment[0].to_indexing_line().is_some();
 
@@ -0,0 +1,758 @@
+//! A [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/)
+//! server, through which editors such as VS Code debug Prolog programs.
+//!
+//! `scryer-prolog --dap` runs the server on stdin and stdout. The
+//! `launch` request names the `program` to consult and the `goal` to
+//! run, `main` by default, and stops at the first port of the goal if
+//! `stopOnEntry` is true.
+//!
+//! Breakpoints are function breakpoints naming predicate indicators,
+//! such as `app/3`, or just predicate names, and stop at the call
+//! ports of the predicates. Stepping goes from port to port of the
+//! tracer of `library(trace)`: step in goes to the next port, step
+//! over to the next port of the goal or of the goals calling it, and
+//! step out to the exit or fail port of the goal calling it. The stack
+//! holds the goals being run, each as it was at its last port, and
+//! their arguments are shown as variables.
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use serde_json::{json, Value};
+
+use crate::{
+    LeafAnswer, MachineBuilder, OutputStreamConfig, StreamConfig, Term, TraceAction, TraceEvent,
+    TracePort,
+};
+
+const THREAD_ID: u64 = 1;
+
+/// Runs a Debug Adapter Protocol server, reading requests from `input`
+/// and writing responses and events to `output`, until the client
+/// disconnects or `input` ends.
+pub fn run_dap_server(
+    input: impl BufRead + 'static,
+    output: impl Write + 'static,
+) -> io::Result<()> {
+    let session = Rc::new(RefCell::new(Session::new(
+        Box::new(input),
+        Box::new(output),
+    )));
+
+    let Some(launch) = session.borrow_mut().configure()? else {
+        return Ok(());
+    };
+
+    run(&session, launch)?;
+
+    let mut session = session.borrow_mut();
+
+    while !session.disconnected {
+        let Some(request) = session.read_request()? else {
+            break;
+        };
+
+        match command(&request) {
+            "disconnect" | "terminate" => {
+                session.respond(&request, json!({}))?;
+                break;
+            }
+            _ => session.handle(&request)?,
+        }
+    }
+
+    Ok(())
+}
+
+struct Launch {
+    program: String,
+    goal: String,
+    stop_on_entry: bool,
+}
+
+impl Launch {
+    fn from_arguments(arguments: &Value) -> Option<Self> {
+        Some(Launch {
+            program: arguments["program"].as_str()?.to_owned(),
+            goal: arguments["goal"].as_str().unwrap_or("main").to_owned(),
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+// a function breakpoint, stopping at the call ports of the predicate
+// name/arity, or of all predicates called name if arity is None.
+struct Breakpoint {
+    name: String,
+    arity: Option<usize>,
+}
+
+impl Breakpoint {
+    fn parse(indicator: &str) -> Self {
+        let indicator = indicator.trim();
+
+        match indicator.rsplit_once('/') {
+            Some((name, arity)) if arity.trim().parse::<usize>().is_ok() => Breakpoint {
+                name: name.trim().to_owned(),
+                arity: arity.trim().parse().ok(),
+            },
+            _ => Breakpoint {
+                name: indicator.to_owned(),
+                arity: None,
+            },
+        }
+    }
+
+    fn matches(&self, goal: &Term) -> bool {
+        let (name, arity) = match goal {
+            Term::Atom(name) => (name, 0),
+            Term::Compound(name, args) => (name, args.len()),
+            _ => return false,
+        };
+
+        *name == self.name && self.arity.is_none_or(|a| a == arity)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Resume {
+    // stop at the first port, the entry of the goal.
+    Entry,
+    // stop at breakpoints only.
+    Continue,
+    // stop at the next port.
+    StepIn,
+    // stop at the next port at this depth or above.
+    StepOver(usize),
+    // stop at the next exit or fail port at this depth or above.
+    StepOut(usize),
+    // abort the goal at the next port.
+    Abort,
+}
+
+// a goal being run, as it was at its last port.
+struct Frame {
+    depth: usize,
+    port: TracePort,
+    goal: Term,
+}
+
+struct Session {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    seq: u64,
+    breakpoints: Vec<Breakpoint>,
+    resume: Resume,
+    // the goals being run, innermost last.
+    frames: Vec<Frame>,
+    // the terms whose arguments are shown as variables, by their
+    // variables reference less one. cleared whenever the goal resumes.
+    references: Vec<Term>,
+    disconnected: bool,
+}
+
+impl Session {
+    fn new(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Session {
+            input,
+            output,
+            seq: 0,
+            breakpoints: vec![],
+            resume: Resume::Continue,
+            frames: vec![],
+            references: vec![],
+            disconnected: false,
+        }
+    }
+
+    // the next request, or None once the input ends.
+    fn read_request(&mut self) -> io::Result<Option<Value>> {
+        read_message(&mut self.input)
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }))
+    }
+
+    fn respond_error(&mut self, request: &Value, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+
+    // answers the requests up to the launch of the goal, giving the
+    // launch arguments, or None if the client disconnects first.
+    fn configure(&mut self) -> io::Result<Option<Launch>> {
+        let mut launch = None;
+        let mut is_configured = false;
+
+        while let Some(request) = self.read_request()? {
+            match command(&request) {
+                "initialize" => {
+                    self.respond(
+                        &request,
+                        json!({
+                            "supportsConfigurationDoneRequest": true,
+                            "supportsFunctionBreakpoints": true,
+                            "supportsTerminateRequest": true,
+                        }),
+                    )?;
+                    self.event("initialized", json!({}))?;
+                }
+                "launch" => match Launch::from_arguments(&request["arguments"]) {
+                    Some(arguments) => {
+                        self.respond(&request, json!({}))?;
+                        launch = Some(arguments);
+                    }
+                    None => {
+                        self.respond_error(&request, "the program to debug is missing")?;
+                    }
+                },
+                "configurationDone" => {
+                    self.respond(&request, json!({}))?;
+                    is_configured = true;
+                }
+                "disconnect" | "terminate" => {
+                    self.respond(&request, json!({}))?;
+                    return Ok(None);
+                }
+                _ => self.handle(&request)?,
+            }
+
+            if is_configured && launch.is_some() {
+                return Ok(launch);
+            }
+        }
+
+        Ok(None)
+    }
+
+    // answers the requests that don't depend on whether the goal runs.
+    fn handle(&mut self, request: &Value) -> io::Result<()> {
+        let arguments = &request["arguments"];
+
+        match command(request) {
+            "threads" => self.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+            ),
+            "setFunctionBreakpoints" => {
+                self.breakpoints = arguments["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|breakpoint| breakpoint["name"].as_str())
+                    .map(Breakpoint::parse)
+                    .collect();
+
+                let breakpoints: Vec<_> = self
+                    .breakpoints
+                    .iter()
+                    .map(|_| json!({ "verified": true }))
+                    .collect();
+
+                self.respond(request, json!({ "breakpoints": breakpoints }))
+            }
+            "setBreakpoints" => {
+                let breakpoints: Vec<_> = arguments["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|_| {
+                        json!({
+                            "verified": false,
+                            "message": "only function breakpoints are supported",
+                        })
+                    })
+                    .collect();
+
+                self.respond(request, json!({ "breakpoints": breakpoints }))
+            }
+            "setExceptionBreakpoints" => self.respond(request, json!({})),
+            "stackTrace" => {
+                let frames: Vec<_> = self
+                    .frames
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .map(|(idx, frame)| {
+                        let name = if idx == 0 {
+                            format!("{}: {}", port_name(frame.port), frame.goal)
+                        } else {
+                            frame.goal.to_string()
+                        };
+
+                        json!({ "id": frame.depth, "name": name, "line": 0, "column": 0 })
+                    })
+                    .collect();
+
+                self.respond(
+                    request,
+                    json!({ "stackFrames": frames, "totalFrames": frames.len() }),
+                )
+            }
+            "scopes" => {
+                let depth = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+
+                let goal = match self.frames.iter().find(|frame| frame.depth == depth) {
+                    Some(frame) => frame.goal.clone(),
+                    None => return self.respond_error(request, "no such frame"),
+                };
+
+                let reference = self.reference(goal);
+
+                self.respond(
+                    request,
+                    json!({
+                        "scopes": [{
+                            "name": "Arguments",
+                            "variablesReference": reference,
+                            "expensive": false,
+                        }],
+                    }),
+                )
+            }
+            "variables" => {
+                let reference = arguments["variablesReference"].as_u64().unwrap_or(0) as usize;
+
+                let args: Vec<(String, Term)> = match reference
+                    .checked_sub(1)
+                    .and_then(|idx| self.references.get(idx))
+                {
+                    Some(Term::Compound(_, args)) => args
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, arg)| ((idx + 1).to_string(), arg.clone()))
+                        .collect(),
+                    Some(Term::List(items)) => items
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, item)| (format!("[{idx}]"), item.clone()))
+                        .collect(),
+                    _ => vec![],
+                };
+
+                let variables: Vec<_> = args
+                    .into_iter()
+                    .map(|(name, arg)| {
+                        let value = arg.to_string();
+
+                        json!({
+                            "name": name,
+                            "value": value,
+                            "variablesReference": self.reference(arg),
+                        })
+                    })
+                    .collect();
+
+                self.respond(request, json!({ "variables": variables }))
+            }
+            _ => self.respond_error(request, "unsupported request"),
+        }
+    }
+
+    // the variables reference of a term, 0 if it has no arguments.
+    fn reference(&mut self, term: Term) -> usize {
+        match term {
+            Term::Compound(..) => {}
+            Term::List(ref items) if !items.is_empty() => {}
+            _ => return 0,
+        }
+
+        self.references.push(term);
+        self.references.len()
+    }
+
+    // called at each port of the tracer.
+    fn port(&mut self, event: &TraceEvent) -> TraceAction {
+        self.frames.retain(|frame| frame.depth < event.depth);
+        self.frames.push(Frame {
+            depth: event.depth,
+            port: event.port,
+            goal: event.goal.clone(),
+        });
+
+        let is_breakpoint = event.port == TracePort::Call
+            && self
+                .breakpoints
+                .iter()
+                .any(|breakpoint| breakpoint.matches(&event.goal));
+
+        let reason = match self.resume {
+            Resume::Abort => return TraceAction::Abort,
+            Resume::Entry => "entry",
+            Resume::StepIn => "step",
+            Resume::StepOver(depth) if event.depth <= depth => "step",
+            Resume::StepOut(depth)
+                if event.depth <= depth
+                    && matches!(event.port, TracePort::Exit | TracePort::Fail) =>
+            {
+                "step"
+            }
+            _ if is_breakpoint => "breakpoint",
+            _ => return TraceAction::Creep,
+        };
+
+        match self.stop(event, reason) {
+            Ok(action) => action,
+            Err(_) => {
+                self.disconnected = true;
+                TraceAction::Abort
+            }
+        }
+    }
+
+    // answers the requests while stopped at the port of event, until
+    // the goal resumes.
+    fn stop(&mut self, event: &TraceEvent, reason: &str) -> io::Result<TraceAction> {
+        self.references.clear();
+
+        self.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": format!("{}: {}", port_name(event.port), event.goal),
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )?;
+
+        while let Some(request) = self.read_request()? {
+            let resume = match command(&request) {
+                "continue" => Resume::Continue,
+                "next" => Resume::StepOver(event.depth),
+                "stepIn" => Resume::StepIn,
+                "stepOut" => match event.port {
+                    TracePort::Call | TracePort::Redo => Resume::StepOut(event.depth),
+                    TracePort::Exit | TracePort::Fail => Resume::StepOut(event.depth - 1),
+                },
+                "terminate" => Resume::Abort,
+                "disconnect" => {
+                    self.disconnected = true;
+                    Resume::Abort
+                }
+                _ => {
+                    self.handle(&request)?;
+                    continue;
+                }
+            };
+
+            let body = match resume {
+                Resume::Continue => json!({ "allThreadsContinued": true }),
+                _ => json!({}),
+            };
+
+            self.respond(&request, body)?;
+            self.resume = resume;
+
+            return Ok(match resume {
+                Resume::Abort => TraceAction::Abort,
+                _ => TraceAction::Creep,
+            });
+        }
+
+        self.disconnected = true;
+        Ok(TraceAction::Abort)
+    }
+}
+
+// consults the program and runs the goal in a machine writing its
+// output to the client.
+fn run(session: &Rc<RefCell<Session>>, launch: Launch) -> io::Result<()> {
+    let streams = StreamConfig::in_memory()
+        .with_user_output(output_stream(session, "stdout"))
+        .with_user_error(output_stream(session, "stderr"));
+
+    let hook_session = session.clone();
+
+    let mut machine = MachineBuilder::new()
+        .with_streams(streams)
+        .with_trace_hook(move |event| hook_session.borrow_mut().port(event))
+        .build();
+
+    session.borrow_mut().resume = if launch.stop_on_entry {
+        Resume::Entry
+    } else {
+        Resume::Continue
+    };
+
+    let consulted = machine
+        .run_query(format!("consult({}).", Term::atom(launch.program.as_str())))
+        .next();
+
+    let answer = match consulted {
+        Some(Ok(LeafAnswer::True)) => machine
+            .run_query(format!(
+                "use_module(library(trace)), trace, ({}).",
+                launch.goal
+            ))
+            .next(),
+        _ => consulted,
+    };
+
+    machine
+        .run_query("notrace, flush_output, flush_output(user_error).")
+        .next();
+
+    let mut session = session.borrow_mut();
+
+    if session.disconnected {
+        return Ok(());
+    }
+
+    session.event(
+        "output",
+        json!({ "category": "console", "output": answer_text(answer) + "\n" }),
+    )?;
+    session.event("terminated", json!({}))?;
+    session.event("exited", json!({ "exitCode": 0 }))
+}
+
+// a stream sending what's written to it to the client, as output
+// events of category.
+fn output_stream(session: &Rc<RefCell<Session>>, category: &'static str) -> OutputStreamConfig {
+    let session = session.clone();
+
+    OutputStreamConfig::callback(Box::new(move |buffer| {
+        let output = String::from_utf8_lossy(buffer.get_ref()).into_owned();
+
+        buffer.get_mut().clear();
+        buffer.set_position(0);
+
+        if !output.is_empty() {
+            let _ = session
+                .borrow_mut()
+                .event("output", json!({ "category": category, "output": output }));
+        }
+    }))
+}
+
+// the next message of input framed by a Content-Length header, as
+// in the Debug Adapter and Language Server Protocols, or None once
+// input ends.
+pub(crate) fn read_message(input: &mut dyn BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+
+        if let Some(length) = line.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        } else if line.is_empty() {
+            if let Some(content_length) = content_length {
+                let mut content = vec![0; content_length];
+                input.read_exact(&mut content)?;
+
+                return serde_json::from_slice(&content)
+                    .map(Some)
+                    .map_err(io::Error::other);
+            }
+        }
+    }
+}
+
+pub(crate) fn write_message(output: &mut dyn Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+fn command(request: &Value) -> &str {
+    request["command"].as_str().unwrap_or("")
+}
+
+fn port_name(port: TracePort) -> &'static str {
+    match port {
+        TracePort::Call => "Call",
+        TracePort::Exit => "Exit",
+        TracePort::Redo => "Redo",
+        TracePort::Fail => "Fail",
+    }
+}
+
+fn answer_text(answer: Option<Result<LeafAnswer, Term>>) -> String {
+    match answer {
+        Some(Ok(LeafAnswer::True)) => "true.".to_owned(),
+        Some(Ok(LeafAnswer::False)) | None => "false.".to_owned(),
+        Some(Ok(LeafAnswer::Exception(error))) | Some(Err(error)) => {
+            format!("error: {error}.")
+        }
+        Some(Ok(LeafAnswer::LeafAnswer { bindings, .. })) => {
+            let bindings: Vec<_> = bindings
+                .iter()
+                .map(|(var, term)| format!("{var} = {term}"))
+                .collect();
+
+            bindings.join(", ") + "."
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn requests(requests: &[Value]) -> Cursor<Vec<u8>> {
+        let mut input = vec![];
+
+        for (seq, request) in requests.iter().enumerate() {
+            let mut request = request.clone();
+
+            request["seq"] = json!(seq + 1);
+            request["type"] = json!("request");
+
+            let content = request.to_string();
+            write!(
+                input,
+                "Content-Length: {}\r\n\r\n{}",
+                content.len(),
+                content
+            )
+            .unwrap();
+        }
+
+        Cursor::new(input)
+    }
+
+    fn messages(mut output: &[u8]) -> Vec<Value> {
+        let mut messages = vec![];
+
+        while !output.is_empty() {
+            let mut header = String::new();
+            output.read_line(&mut header).unwrap();
+
+            let length: usize = header["Content-Length:".len()..].trim().parse().unwrap();
+            output = &output[2..];
+
+            messages.push(serde_json::from_slice(&output[..length]).unwrap());
+            output = &output[length..];
+        }
+
+        messages
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn breakpoints_and_stepping() {
+        let input = requests(&[
+            json!({ "command": "initialize", "arguments": {} }),
+            json!({
+                "command": "launch",
+                "arguments": { "program": "src/tests/trace.pl", "goal": "p(X)" },
+            }),
+            json!({
+                "command": "setFunctionBreakpoints",
+                "arguments": { "breakpoints": [{ "name": "r/1" }] },
+            }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": 1 } }),
+            json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+            json!({ "command": "scopes", "arguments": { "frameId": 2 } }),
+            json!({ "command": "variables", "arguments": { "variablesReference": 1 } }),
+            json!({ "command": "stepOut", "arguments": { "threadId": 1 } }),
+            json!({ "command": "next", "arguments": { "threadId": 1 } }),
+            json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+            json!({ "command": "disconnect" }),
+        ]);
+
+        let output = Rc::new(RefCell::new(vec![]));
+
+        run_dap_server(input, SharedOutput(output.clone())).unwrap();
+
+        let messages = messages(&output.borrow());
+
+        let stops: Vec<_> = messages
+            .iter()
+            .filter(|message| message["event"] == "stopped")
+            .map(|message| message["body"]["description"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            stops,
+            ["Call: r(1)", "Call: r(2)", "Exit: r(2)", "Exit: p(2)"]
+        );
+
+        let body = |command: &str| {
+            messages
+                .iter()
+                .find(|message| message["command"] == command)
+                .map(|message| message["body"].clone())
+                .unwrap()
+        };
+
+        assert_eq!(
+            body("stackTrace")["stackFrames"],
+            json!([
+                { "id": 2, "name": "Call: r(1)", "line": 0, "column": 0 },
+                { "id": 1, "name": "p(_A)", "line": 0, "column": 0 },
+            ])
+        );
+        assert_eq!(
+            body("variables")["variables"],
+            json!([{ "name": "1", "value": "2", "variablesReference": 0 }])
+        );
+
+        let output: Vec<_> = messages
+            .iter()
+            .filter(|message| message["event"] == "output")
+            .map(|message| message["body"]["output"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(output, ["X = 2.\n"]);
+        assert!(messages
+            .iter()
+            .any(|message| message["event"] == "terminated"));
+    }
+}
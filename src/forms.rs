@@ -2,8 +2,11 @@
 use crate::atom_table::*;
 use crate::functor_macro::*;
 use crate::instructions::*;
+use crate::machine::code_locations::ClauseLocation;
 use crate::machine::disjuncts::VarData;
-use crate::machine::loader::PredicateQueue;
+use crate::machine::dynamic_clause_store::DynamicClauseStore;
+use crate::machine::index_cache::SourceStamp;
+use crate::machine::loader::{CompilationTarget, PredicateQueue};
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::offset_table::OffsetTable;
@@ -43,10 +46,14 @@ pub(crate) fn is_append(self) -> bool {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VarComparison {
     Indistinct,
     Distinct,
+    // variables are equal if they pair up one-to-one across the
+    // compared terms, as in variant checking. a mismatch yields some
+    // non-Equal ordering but not a standard order.
+    Variant,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -556,6 +563,22 @@ pub(crate) fn fetch_op_spec(name: Atom, arity: usize, op_dir: &OpDir) -> Option<
 
 pub(crate) type ModuleDir = IndexMap<Atom, Module, FxBuildHasher>;
 
+// a file loaded from the file system, reloaded by make/0 once it
+// changes.
+#[derive(Debug)]
+pub(crate) struct LoadedFile {
+    pub(crate) stamp: SourceStamp,
+    // the predicates compiled or declared dynamic by the load of the
+    // file.
+    pub(crate) predicates: IndexSet<(CompilationTarget, PredicateKey), FxBuildHasher>,
+}
+
+pub(crate) type LoadedFiles = IndexMap<Atom, LoadedFile, FxBuildHasher>;
+
+// the modules imported by each module, or by user, in the order they
+// were first imported.
+pub(crate) type ModuleImports = IndexMap<Atom, IndexSet<Atom>, FxBuildHasher>;
+
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub enum ModuleExport {
     OpDecl(OpDecl),
@@ -577,6 +600,19 @@ pub struct Module {
     pub(crate) extensible_predicates: ExtensiblePredicates,
     pub(crate) local_extensible_predicates: LocalExtensiblePredicates,
     pub(crate) listing_src: ListingSource,
+    // the exports of module_decl re-exported from the modules the
+    // module imports them from, by reexport/1,2.
+    pub(crate) reexports: IndexSet<ModuleExport>,
+    // the file and line of the module declaration, if it was consulted
+    // from a file.
+    pub(crate) location: Option<ClauseLocation>,
+    // the predicates exported by the module when it froze its
+    // interface, beyond which its exports can't grow.
+    pub(crate) frozen_exports: Option<IndexSet<PredicateKey>>,
+    // the unknown flag of the module, set by set_prolog_flag(M:unknown, V).
+    // calls to undefined predicates from modules without one are handled
+    // as the flag of the machine says.
+    pub(crate) unknown: Option<Unknown>,
 }
 
 // Module's and related types are defined in forms.
@@ -592,6 +628,10 @@ pub(crate) fn new(module_decl: ModuleDecl, listing_src: ListingSource) -> Self {
                 FxBuildHasher::default(),
             ),
             listing_src,
+            reexports: IndexSet::new(),
+            location: None,
+            frozen_exports: None,
+            unknown: None,
         }
     }
 
@@ -606,6 +646,10 @@ pub(crate) fn new_in_situ(module_decl: ModuleDecl) -> Self {
                 FxBuildHasher::default(),
             ),
             listing_src: ListingSource::DynamicallyGenerated,
+            reexports: IndexSet::new(),
+            location: None,
+            frozen_exports: None,
+            unknown: None,
         }
     }
 }
@@ -639,10 +683,18 @@ pub trait ArenaFrom<T> {
     fn arena_from(value: T, arena: &mut Arena) -> Self;
 }
 
+// integers in the range of fixnums are always fixnums, so that the
+// results of big integer arithmetic that fall back into that range
+// are no longer allocated.
 impl ArenaFrom<Integer> for Number {
     #[inline]
     fn arena_from(value: Integer, arena: &mut Arena) -> Number {
-        Number::Integer(arena_alloc!(value, arena))
+        match i64::try_from(&value) {
+            Ok(n) => Fixnum::build_with_checked(n)
+                .map(Number::Fixnum)
+                .unwrap_or_else(|_| Number::Integer(arena_alloc!(value, arena))),
+            Err(_) => Number::Integer(arena_alloc!(value, arena)),
+        }
     }
 }
 
@@ -875,10 +927,36 @@ fn add_assign(&mut self, n: usize) {
     }
 }
 
+// the key of an argument of a dynamic clause, by which the clauses
+// sharing the key of their indexed argument are told apart.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum SubindexKey {
+    Constant(HeapCellValue, Option<HeapCellValue>), // constant, alternative
+    List,
+    Structure(Atom, usize),
+}
+
+// the keys of a dynamic clause below the key of its indexed argument,
+// None where the clause has a variable.
+#[derive(Debug, Clone)]
+pub(crate) struct DynamicSubkeys {
+    // the keys of the arguments of the indexed term, if it's a
+    // structure other than a list.
+    pub(crate) subterms: Box<[Option<SubindexKey>]>,
+    // the keys of the arguments of the clause head.
+    pub(crate) args: Box<[Option<SubindexKey>]>,
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct ClauseIndexInfo {
     pub(crate) clause_start: usize,
     pub(crate) opt_arg_index_key: OptArgIndexKey,
+    // the subkeys of an indexed dynamic clause, until they're moved to
+    // the skeleton of its predicate.
+    pub(crate) subkeys: Option<Box<DynamicSubkeys>>,
+    // the key of the first argument of a dynamic clause, None if it's
+    // a variable.
+    pub(crate) first_arg_key: Option<SubindexKey>,
 }
 
 impl ClauseIndexInfo {
@@ -887,6 +965,8 @@ pub(crate) fn new(clause_start: usize) -> Self {
         Self {
             clause_start,
             opt_arg_index_key: OptArgIndexKey::None,
+            subkeys: None,
+            first_arg_key: None,
             // index_locs: vec![],
         }
     }
@@ -976,6 +1056,12 @@ pub(crate) fn add_retracted_dynamic_clause_info(&mut self, clause_info: ClauseIn
 pub(crate) struct PredicateSkeleton {
     pub(crate) core: LocalPredicateSkeleton,
     pub(crate) clauses: VecDeque<ClauseIndexInfo>,
+    // the subkeys of the living indexed clauses of a dynamic
+    // predicate, by location of their inner choice instructions.
+    pub(crate) clause_subkeys: IndexMap<usize, DynamicSubkeys, FxBuildHasher>,
+    // the clauses of a dynamic predicate by the keys of their first
+    // arguments.
+    pub(crate) clause_store: DynamicClauseStore,
 }
 
 impl PredicateSkeleton {
@@ -984,6 +1070,8 @@ pub(crate) fn new() -> Self {
         PredicateSkeleton {
             core: LocalPredicateSkeleton::new(),
             clauses: VecDeque::new(),
+            clause_subkeys: IndexMap::with_hasher(FxBuildHasher::default()),
+            clause_store: DynamicClauseStore::default(),
         }
     }
 
@@ -998,6 +1086,18 @@ pub(crate) fn predicate_info(&self) -> PredicateInfo {
         }
     }
 
+    // rebuilds the clause store of a dynamic predicate from its
+    // clauses.
+    pub(crate) fn rebuild_clause_store(&mut self) {
+        self.clause_store.rebuild(
+            self.core
+                .clause_clause_locs
+                .iter()
+                .zip(&self.clauses)
+                .map(|(clause_clause_loc, clause)| (*clause_clause_loc, clause.first_arg_key)),
+        );
+    }
+
     pub(crate) fn target_pos_of_clause_clause_loc(
         &mut self,
         clause_clause_loc: usize,
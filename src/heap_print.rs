@@ -448,9 +448,13 @@ macro_rules! print_char {
     };
 }
 
-pub fn fmt_float(mut fl: f64) -> String {
-    if OrderedFloat(fl) == -0f64 {
-        fl = 0f64;
+pub fn fmt_float(fl: f64) -> String {
+    // infinities and NaN are written as SWI-Prolog writes them, in a
+    // syntax the reader reads back.
+    if fl.is_nan() {
+        return "1.5NaN".to_string();
+    } else if fl.is_infinite() {
+        return if fl > 0f64 { "1.0Inf" } else { "-1.0Inf" }.to_string();
     }
 
     let mut buffer = ryu::Buffer::new();
@@ -1807,6 +1811,11 @@ fn handle_heap_term(
                         }
                    }
                    _ => {
+                       if c.get_tag() == ArenaHeaderTag::WebSocket {
+                           self.print_impromptu_atom(atom!("$websocket"));
+                       } else if c.get_tag() == ArenaHeaderTag::UdpSocket {
+                           self.print_impromptu_atom(atom!("$udp_socket"));
+                       }
                    }
                );
             }
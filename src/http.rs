@@ -1,10 +1,50 @@
+use crate::atom_table::Atom;
+
 use bytes::{buf::Reader, Bytes};
-use std::sync::{Arc, Condvar, Mutex};
+use futures::{Future, SinkExt, StreamExt};
+use indexmap::IndexMap;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Condvar, LazyLock, Mutex, PoisonError};
+use tokio::net::TcpStream;
+use tokio::runtime::Handle;
+use tokio::task;
+use tokio_tungstenite::tungstenite::{
+    self,
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 
+use warp::filters::ws::Ws;
 use warp::http;
 
+// the request queues of the servers started by http_listen/3, by
+// address, from which the machines of their workers accept requests.
+static HTTP_LISTENERS: LazyLock<Mutex<IndexMap<Atom, HttpIncoming>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+pub type HttpIncoming = Arc<Mutex<Receiver<HttpRequest>>>;
+
 pub struct HttpListener {
-    pub incoming: std::sync::mpsc::Receiver<HttpRequest>,
+    pub incoming: HttpIncoming,
+}
+
+impl HttpListener {
+    pub(crate) fn register(address: Atom, incoming: Receiver<HttpRequest>) -> Self {
+        let incoming = Arc::new(Mutex::new(incoming));
+
+        HTTP_LISTENERS
+            .lock()
+            .unwrap()
+            .insert(address, incoming.clone());
+
+        HttpListener { incoming }
+    }
+
+    pub(crate) fn lookup(address: Atom) -> Option<Self> {
+        let incoming = HTTP_LISTENERS.lock().unwrap().get(&address).cloned()?;
+        Some(HttpListener { incoming })
+    }
 }
 
 pub struct HttpRequest {
@@ -14,6 +54,16 @@ pub struct HttpRequest {
 
 pub type HttpResponse = Arc<(Mutex<bool>, Mutex<Option<warp::reply::Response>>, Condvar)>;
 
+// hands reply to the server, which waits for it to answer the request.
+pub(crate) fn answer(response: &HttpResponse, reply: warp::reply::Response) {
+    let (ready, response, cvar) = &**response;
+
+    let mut ready = ready.lock().unwrap_or_else(PoisonError::into_inner);
+    *response.lock().unwrap_or_else(PoisonError::into_inner) = Some(reply);
+    *ready = true;
+    cvar.notify_one();
+}
+
 pub struct HttpRequestData {
     pub method: http::Method,
     pub headers: http::HeaderMap,
@@ -21,3 +71,154 @@ pub struct HttpRequestData {
     pub query: String,
     pub body: Reader<Bytes>,
 }
+
+// the WebSocket handshakes of the requests waiting for an answer, by
+// the address of their responses, until their handlers accept them
+// with '$http_websocket'/2.
+static WEBSOCKET_UPGRADES: LazyLock<Mutex<IndexMap<usize, Ws>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+fn response_key(response: &HttpResponse) -> usize {
+    Arc::as_ptr(response) as *const () as usize
+}
+
+pub(crate) fn offer_websocket_upgrade(response: &HttpResponse, ws: Ws) {
+    WEBSOCKET_UPGRADES
+        .lock()
+        .unwrap()
+        .insert(response_key(response), ws);
+}
+
+pub(crate) fn take_websocket_upgrade(response: &HttpResponse) -> Option<Ws> {
+    WEBSOCKET_UPGRADES
+        .lock()
+        .unwrap()
+        .swap_remove(&response_key(response))
+}
+
+pub enum WebSocket {
+    Server(warp::ws::WebSocket),
+    Client(WebSocketStream<MaybeTlsStream<TcpStream>>),
+}
+
+#[derive(Debug)]
+pub enum WebSocketMessage {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(u16, String),
+}
+
+impl From<WebSocketMessage> for warp::ws::Message {
+    fn from(message: WebSocketMessage) -> Self {
+        match message {
+            WebSocketMessage::Text(text) => warp::ws::Message::text(text),
+            WebSocketMessage::Binary(bytes) => warp::ws::Message::binary(bytes),
+            WebSocketMessage::Ping(bytes) => warp::ws::Message::ping(bytes),
+            WebSocketMessage::Pong(bytes) => warp::ws::Message::pong(bytes),
+            WebSocketMessage::Close(code, reason) => warp::ws::Message::close_with(code, reason),
+        }
+    }
+}
+
+impl From<WebSocketMessage> for Message {
+    fn from(message: WebSocketMessage) -> Self {
+        match message {
+            WebSocketMessage::Text(text) => Message::Text(text),
+            WebSocketMessage::Binary(bytes) => Message::Binary(bytes),
+            WebSocketMessage::Ping(bytes) => Message::Ping(bytes),
+            WebSocketMessage::Pong(bytes) => Message::Pong(bytes),
+            WebSocketMessage::Close(code, reason) => Message::Close(Some(CloseFrame {
+                code: CloseCode::from(code),
+                reason: reason.into(),
+            })),
+        }
+    }
+}
+
+impl WebSocketMessage {
+    fn from_server(message: warp::ws::Message) -> Self {
+        if let Some((code, reason)) = message.close_frame() {
+            WebSocketMessage::Close(code, reason.to_string())
+        } else if message.is_close() {
+            WebSocketMessage::Close(NO_STATUS_RECEIVED, String::new())
+        } else if message.is_ping() {
+            WebSocketMessage::Ping(message.into_bytes())
+        } else if message.is_pong() {
+            WebSocketMessage::Pong(message.into_bytes())
+        } else if let Ok(text) = message.to_str() {
+            WebSocketMessage::Text(text.to_string())
+        } else {
+            WebSocketMessage::Binary(message.into_bytes())
+        }
+    }
+
+    // None for the raw frames that are only produced when writing.
+    fn from_client(message: Message) -> Option<Self> {
+        Some(match message {
+            Message::Text(text) => WebSocketMessage::Text(text),
+            Message::Binary(bytes) => WebSocketMessage::Binary(bytes),
+            Message::Ping(bytes) => WebSocketMessage::Ping(bytes),
+            Message::Pong(bytes) => WebSocketMessage::Pong(bytes),
+            Message::Close(frame) => match frame {
+                Some(frame) => {
+                    WebSocketMessage::Close(frame.code.into(), frame.reason.into_owned())
+                }
+                None => WebSocketMessage::Close(NO_STATUS_RECEIVED, String::new()),
+            },
+            Message::Frame(_) => return None,
+        })
+    }
+}
+
+// the status code of a close frame without one (RFC 6455, 7.4.1).
+const NO_STATUS_RECEIVED: u16 = 1005;
+
+impl WebSocket {
+    pub(crate) fn connect(
+        request: tungstenite::handshake::client::Request,
+    ) -> Result<Self, String> {
+        block_on(connect_async(request))
+            .map(|(stream, _response)| WebSocket::Client(stream))
+            .map_err(|err| err.to_string())
+    }
+
+    pub(crate) fn send(&mut self, message: WebSocketMessage) -> Result<(), String> {
+        match self {
+            WebSocket::Server(socket) => {
+                block_on(socket.send(message.into())).map_err(|err| err.to_string())
+            }
+            WebSocket::Client(stream) => {
+                block_on(stream.send(message.into())).map_err(|err| err.to_string())
+            }
+        }
+    }
+
+    // the next message, or None once the connection is closed. pings
+    // are answered as they are received.
+    pub(crate) fn receive(&mut self) -> Result<Option<WebSocketMessage>, String> {
+        loop {
+            let message = match self {
+                WebSocket::Server(socket) => match block_on(socket.next()) {
+                    Some(Ok(message)) => Some(WebSocketMessage::from_server(message)),
+                    Some(Err(err)) => return Err(err.to_string()),
+                    None => return Ok(None),
+                },
+                WebSocket::Client(stream) => match block_on(stream.next()) {
+                    Some(Ok(message)) => WebSocketMessage::from_client(message),
+                    Some(Err(tungstenite::Error::ConnectionClosed)) | None => return Ok(None),
+                    Some(Err(err)) => return Err(err.to_string()),
+                },
+            };
+
+            if message.is_some() {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    task::block_in_place(|| Handle::current().block_on(future))
+}
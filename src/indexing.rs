@@ -6,7 +6,7 @@
 use crate::types::HeapCellValue;
 
 use fxhash::FxBuildHasher;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
 use std::collections::VecDeque;
 use std::hash::Hash;
@@ -72,6 +72,7 @@ struct IndexingCodeMergingPtr<'a> {
     offset: usize,
     append_or_prepend: AppendOrPrepend,
     is_dynamic: bool,
+    lookup: Option<&'a DynamicSubkeysLookup<'a>>,
 }
 
 impl<'a> IndexingCodeMergingPtr<'a> {
@@ -81,6 +82,7 @@ fn new(
         retracted_dynamic_clauses: &'a Option<Vec<ClauseIndexInfo>>,
         indexing_code: &'a mut Vec<IndexingLine>,
         append_or_prepend: AppendOrPrepend,
+        lookup: Option<&'a DynamicSubkeysLookup<'a>>,
     ) -> Self {
         let is_dynamic = match &indexing_code[0] {
             IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(_, v, ..)) => match v {
@@ -98,6 +100,7 @@ fn new(
             offset: 0,
             append_or_prepend,
             is_dynamic,
+            lookup,
         }
     }
 
@@ -234,6 +237,57 @@ fn extend_indexed_choice(&mut self, index: usize) {
         }
     }
 
+    // adds the clause at index to the choice of key pointed to by the
+    // switch at switch_loc, at self.offset, and to the sub-index of the
+    // choice of a dynamic predicate if it's indexed further.
+    fn extend_key_choice(&mut self, switch_loc: usize, key: ChoiceKey, index: usize) {
+        if !self.is_dynamic {
+            self.extend_indexed_choice(index);
+            return;
+        }
+
+        let Some((switch, all_loc)) = dynamic_subindex_at(self.indexing_code, self.offset) else {
+            self.extend_indexed_choice(index);
+
+            if let Some(lookup) = self.lookup {
+                resubindex_dynamic_choice(self.indexing_code, switch_loc, key, self.offset, lookup);
+            }
+
+            return;
+        };
+
+        let subindex_loc = self.offset;
+
+        self.offset = all_loc;
+        self.extend_indexed_choice(index);
+
+        let subkey = match self.lookup.map(|lookup| lookup.clause_subkeys(index)) {
+            Some(ClauseSubkeys::Known(subkeys)) => subkeys.key(switch),
+            _ => None,
+        };
+
+        match (subkey, self.lookup) {
+            (Some(subkey), Some(lookup)) => {
+                for subkey in ChoiceKey::of_subindex_key(subkey) {
+                    index_dynamic_subkey(
+                        self.indexing_code,
+                        subindex_loc,
+                        subkey,
+                        index,
+                        self.append_or_prepend,
+                    );
+                }
+
+                resubindex_dynamic_choice(self.indexing_code, switch_loc, key, all_loc, lookup);
+            }
+            _ => {
+                // the sub-index can't tell the clause apart from the
+                // others, so calls try all of them.
+                drop_dynamic_subindex(self.indexing_code, switch_loc, key, subindex_loc);
+            }
+        }
+    }
+
     fn index_overlapping_constant(
         &mut self,
         orig_constant: HeapCellValue,
@@ -298,8 +352,14 @@ fn index_overlapping_constant(
                             );
                         }
                         Some(IndexingCodePtr::Internal(o)) => {
+                            let switch_loc = self.offset;
+
                             self.offset += o;
-                            self.extend_indexed_choice(index);
+                            self.extend_key_choice(
+                                switch_loc,
+                                ChoiceKey::Constant(overlapping_constant),
+                                index,
+                            );
                         }
                     }
 
@@ -360,8 +420,14 @@ fn index_constant(&mut self, constant: HeapCellValue, index: usize) {
                             self.add_static_indexed_choice_for_constant(o, constant, index);
                         }
                         Some(IndexingCodePtr::Internal(o)) => {
+                            let switch_loc = self.offset;
+
                             self.offset += o;
-                            self.extend_indexed_choice(index);
+                            self.extend_key_choice(
+                                switch_loc,
+                                ChoiceKey::Constant(constant),
+                                index,
+                            );
                         }
                     }
 
@@ -533,8 +599,10 @@ fn index_structure(&mut self, key: PredicateKey, index: usize) {
                             self.add_static_indexed_choice_for_structure(o, key, index);
                         }
                         Some(IndexingCodePtr::Internal(o)) => {
+                            let switch_loc = self.offset;
+
                             self.offset += o;
-                            self.extend_indexed_choice(index);
+                            self.extend_key_choice(switch_loc, ChoiceKey::Structure(key), index);
                         }
                     }
 
@@ -600,8 +668,10 @@ fn index_list(&mut self, index: usize) {
                             .push(IndexingLine::IndexedChoice(third_level_index));
                     }
                     IndexingCodePtr::Internal(o) => {
+                        let switch_loc = self.offset;
+
                         self.offset += o;
-                        self.extend_indexed_choice(index);
+                        self.extend_key_choice(switch_loc, ChoiceKey::List, index);
                     }
                 }
             }
@@ -618,6 +688,7 @@ pub(crate) fn merge_clause_index(
     retracted_clauses: &Option<Vec<ClauseIndexInfo>>,
     new_clause_loc: usize, // the absolute location of the new clause in the code vector.
     append_or_prepend: AppendOrPrepend,
+    lookup: Option<&DynamicSubkeysLookup>,
 ) {
     let opt_arg_index_key = match append_or_prepend {
         AppendOrPrepend::Append => skeleton.last_mut().unwrap().opt_arg_index_key.take(),
@@ -629,6 +700,7 @@ pub(crate) fn merge_clause_index(
         retracted_clauses,
         target_indexing_code,
         append_or_prepend,
+        lookup,
     );
 
     match &opt_arg_index_key {
@@ -786,6 +858,19 @@ pub(crate) fn remove_constant_indices(
 
                     break;
                 }
+                IndexingLine::Indexing(
+                    IndexingInstruction::SwitchOnTerm(..)
+                    | IndexingInstruction::SwitchOnSubterm(..),
+                ) => {
+                    // the sub-index of a dynamic predicate is dropped
+                    // for the choice of all its clauses.
+                    index = drop_dynamic_subindex(
+                        indexing_code,
+                        constants_index,
+                        ChoiceKey::Constant(constant),
+                        index,
+                    );
+                }
                 _ => {
                     unreachable!()
                 }
@@ -921,6 +1006,16 @@ pub(crate) fn remove_structure_index(
 
                 break;
             }
+            IndexingLine::Indexing(
+                IndexingInstruction::SwitchOnTerm(..) | IndexingInstruction::SwitchOnSubterm(..),
+            ) => {
+                index = drop_dynamic_subindex(
+                    indexing_code,
+                    structures_index,
+                    ChoiceKey::Structure((name, arity)),
+                    index,
+                );
+            }
             _ => {
                 unreachable!()
             }
@@ -973,6 +1068,10 @@ pub(crate) fn remove_list_index(indexing_code: &mut [IndexingLine], offset: usiz
         }
     }
 
+    if dynamic_subindex_at(indexing_code, index).is_some() {
+        index = drop_dynamic_subindex(indexing_code, 0, ChoiceKey::List, index);
+    }
+
     match &mut indexing_code[index] {
         IndexingLine::IndexedChoice(ref mut indexed_choice_instrs) => {
             StaticCodeIndices::remove_instruction_with_offset(indexed_choice_instrs, offset);
@@ -1097,6 +1196,29 @@ fn uncap_choice_seq_with_try(prelude: &mut [IndexedChoiceInstruction]) {
     }
 }
 
+// the key of a clause indexed on optimal_arg, its argument of index
+// optimal_index.
+pub(crate) fn opt_arg_index_key(optimal_index: usize, optimal_arg: &Term) -> OptArgIndexKey {
+    match optimal_arg {
+        &Term::Clause(_, atom!("."), ref terms) if terms.len() == 2 => {
+            OptArgIndexKey::List(optimal_index, 0)
+        }
+        &Term::Cons(..) | &Term::PartialString(..) | &Term::CompleteString(..) => {
+            OptArgIndexKey::List(optimal_index, 0)
+        }
+        &Term::Clause(_, name, ref terms) => {
+            OptArgIndexKey::Structure(optimal_index, 0, name, terms.len())
+        }
+        &Term::Literal(_, constant) => OptArgIndexKey::Literal(
+            optimal_index,
+            0,
+            constant,
+            constant_key_alternatives(constant),
+        ),
+        _ => OptArgIndexKey::None,
+    }
+}
+
 pub(crate) fn constant_key_alternatives(constant: Literal) -> Option<Literal> {
     let n = match &constant {
         Literal::Rational(n) if n.denominator().is_one() => n.numerator(),
@@ -1143,17 +1265,20 @@ fn compute_index(
     fn second_level_index<IndexKey: Eq + Hash>(
         indices: IndexMap<IndexKey, VecDeque<Self::ThirdLevelIndex>, FxBuildHasher>,
         prelude: &mut VecDeque<IndexingLine>,
+        subindexer: Option<&SubIndexer>,
     ) -> IndexMap<IndexKey, IndexingCodePtr, FxBuildHasher>;
 
     fn switch_on<IndexKey: Eq + Hash>(
         instr_fn: impl FnMut(IndexMap<IndexKey, IndexingCodePtr, FxBuildHasher>) -> IndexingInstruction,
         index: &mut IndexMap<IndexKey, VecDeque<Self::ThirdLevelIndex>, FxBuildHasher>,
         prelude: &mut VecDeque<IndexingLine>,
+        subindexer: Option<&SubIndexer>,
     ) -> IndexingCodePtr;
 
     fn switch_on_list(
         lists: &mut VecDeque<Self::ThirdLevelIndex>,
         prelude: &mut VecDeque<IndexingLine>,
+        subindexer: Option<&SubIndexer>,
     ) -> IndexingCodePtr;
 
     fn remove_instruction_with_offset(code: &mut VecDeque<Self::ThirdLevelIndex>, offset: usize);
@@ -1209,6 +1334,7 @@ fn compute_index(
     fn second_level_index<IndexKey: Eq + Hash>(
         indices: IndexMap<IndexKey, VecDeque<IndexedChoiceInstruction>, FxBuildHasher>,
         prelude: &mut VecDeque<IndexingLine>,
+        subindexer: Option<&SubIndexer>,
     ) -> IndexMap<IndexKey, IndexingCodePtr, FxBuildHasher> {
         let mut index_locs = IndexMap::with_hasher(FxBuildHasher::default());
 
@@ -1216,7 +1342,7 @@ fn second_level_index<IndexKey: Eq + Hash>(
             if code.len() > 1 {
                 index_locs.insert(key, IndexingCodePtr::Internal(prelude.len() + 1));
                 cap_choice_seq_with_trust(code.make_contiguous());
-                prelude.push_back(IndexingLine::from(code));
+                push_choice_seq(code, prelude, subindexer);
             } else if let Some(i) = code.front() {
                 index_locs.insert(key, IndexingCodePtr::External(i.offset()));
             }
@@ -1231,9 +1357,10 @@ fn switch_on<IndexKey: Eq + Hash>(
         ) -> IndexingInstruction,
         index: &mut IndexMap<IndexKey, VecDeque<IndexedChoiceInstruction>, FxBuildHasher>,
         prelude: &mut VecDeque<IndexingLine>,
+        subindexer: Option<&SubIndexer>,
     ) -> IndexingCodePtr {
         let index = mem::replace(index, IndexMap::with_hasher(FxBuildHasher::default()));
-        let index = Self::second_level_index(index, prelude);
+        let index = Self::second_level_index(index, prelude, subindexer);
 
         if index.len() > 1 {
             let instr = instr_fn(index);
@@ -1252,11 +1379,12 @@ fn switch_on<IndexKey: Eq + Hash>(
     fn switch_on_list(
         lists: &mut VecDeque<IndexedChoiceInstruction>,
         prelude: &mut VecDeque<IndexingLine>,
+        subindexer: Option<&SubIndexer>,
     ) -> IndexingCodePtr {
         if lists.len() > 1 {
             cap_choice_seq_with_trust(lists.make_contiguous());
             let lists = std::mem::take(lists);
-            prelude.push_back(IndexingLine::from(lists));
+            push_choice_seq(lists, prelude, subindexer);
 
             IndexingCodePtr::Internal(1)
         } else {
@@ -1322,6 +1450,7 @@ fn compute_index(_: bool, index: usize, _: bool) -> usize {
     fn second_level_index<IndexKey: Eq + Hash>(
         indices: IndexMap<IndexKey, VecDeque<usize>, FxBuildHasher>,
         prelude: &mut VecDeque<IndexingLine>,
+        _: Option<&SubIndexer>,
     ) -> IndexMap<IndexKey, IndexingCodePtr, FxBuildHasher> {
         let mut index_locs = IndexMap::with_hasher(FxBuildHasher::default());
 
@@ -1345,11 +1474,14 @@ fn switch_on<IndexKey: Eq + Hash>(
         ) -> IndexingInstruction,
         index: &mut IndexMap<IndexKey, VecDeque<usize>, FxBuildHasher>,
         prelude: &mut VecDeque<IndexingLine>,
+        _: Option<&SubIndexer>,
     ) -> IndexingCodePtr {
         let index = mem::replace(index, IndexMap::with_hasher(FxBuildHasher::default()));
-        let index = Self::second_level_index(index, prelude);
+        let index = Self::second_level_index(index, prelude, None);
 
-        if index.len() > 1 {
+        // a single key is switched on too, as the choice of its
+        // clauses may be indexed further.
+        if !index.is_empty() {
             let instr = instr_fn(index);
             prelude.push_front(IndexingLine::from(instr));
 
@@ -1366,6 +1498,7 @@ fn switch_on<IndexKey: Eq + Hash>(
     fn switch_on_list(
         lists: &mut VecDeque<usize>,
         prelude: &mut VecDeque<IndexingLine>,
+        _: Option<&SubIndexer>,
     ) -> IndexingCodePtr {
         if lists.len() > 1 {
             let lists = std::mem::take(lists);
@@ -1419,7 +1552,7 @@ fn index_list(&mut self, index: usize) {
         self.indices.lists().push_back(index);
     }
 
-    fn index_constant(&mut self, constant: Literal, index: usize) -> Option<Literal> {
+    fn index_constant(&mut self, constant: Literal, index: usize) {
         let overlapping_constant_opt = constant_key_alternatives(constant);
         let code = self
             .indices
@@ -1442,8 +1575,6 @@ fn index_constant(&mut self, constant: Literal, index: usize) -> Option<Literal>
 
             code.push_back(index);
         }
-
-        overlapping_constant_opt
     }
 
     fn index_structure(&mut self, name: Atom, arity: usize, index: usize) -> usize {
@@ -1466,26 +1597,20 @@ pub(crate) fn index_term(
         index: usize,
         clause_index_info: &mut ClauseIndexInfo,
     ) {
+        clause_index_info.opt_arg_index_key = opt_arg_index_key(self.optimal_index, optimal_arg);
+
         match optimal_arg {
             &Term::Clause(_, atom!("."), ref terms) if terms.len() == 2 => {
-                clause_index_info.opt_arg_index_key = OptArgIndexKey::List(self.optimal_index, 0);
                 self.index_list(index);
             }
             &Term::Cons(..) | &Term::PartialString(..) | &Term::CompleteString(..) => {
-                clause_index_info.opt_arg_index_key = OptArgIndexKey::List(self.optimal_index, 0);
                 self.index_list(index);
             }
             &Term::Clause(_, name, ref terms) => {
-                clause_index_info.opt_arg_index_key =
-                    OptArgIndexKey::Structure(self.optimal_index, 0, name, terms.len());
-
                 self.index_structure(name, terms.len(), index);
             }
             &Term::Literal(_, constant) => {
-                let overlapping_constants = self.index_constant(constant, index);
-
-                clause_index_info.opt_arg_index_key =
-                    OptArgIndexKey::Literal(self.optimal_index, 0, constant, overlapping_constants);
+                self.index_constant(constant, index);
             }
             _ => {}
         }
@@ -1499,17 +1624,46 @@ pub(crate) fn no_indices(&mut self) -> bool {
         no_constants && no_structures && no_lists
     }
 
-    pub(crate) fn compute_indices(mut self, skip_stub_try_me_else: bool) -> Vec<IndexingLine> {
+    pub(crate) fn compute_indices(
+        mut self,
+        skip_stub_try_me_else: bool,
+        subindexer: Option<&SubIndexer>,
+    ) -> Vec<IndexingLine> {
         if self.no_indices() {
             return vec![];
         }
 
+        let (mut prelude, con_loc, lst_loc, str_loc) = self.index_prelude(subindexer);
+        let var_offset = 1 + skip_stub_try_me_else as usize;
+
+        prelude.push_front(IndexingLine::from(IndexingInstruction::SwitchOnTerm(
+            self.optimal_index,
+            I::var_offset_wrapper(var_offset),
+            con_loc,
+            lst_loc,
+            str_loc,
+        )));
+
+        prelude.into_iter().collect()
+    }
+
+    // the lines following the switch on the type of the indexed term,
+    // and the constant, list and structure pointers of that switch.
+    fn index_prelude(
+        &mut self,
+        subindexer: Option<&SubIndexer>,
+    ) -> (
+        VecDeque<IndexingLine>,
+        IndexingCodePtr,
+        IndexingCodePtr,
+        IndexingCodePtr,
+    ) {
         let mut prelude = VecDeque::new();
 
         let mut emitted_switch_on_structure = false;
         let mut emitted_switch_on_constant = false;
 
-        let mut lst_loc = I::switch_on_list(self.indices.lists(), &mut prelude);
+        let mut lst_loc = I::switch_on_list(self.indices.lists(), &mut prelude, subindexer);
 
         let mut str_loc = I::switch_on(
             |index| {
@@ -1518,6 +1672,7 @@ pub(crate) fn compute_indices(mut self, skip_stub_try_me_else: bool) -> Vec<Inde
             },
             self.indices.structures(),
             &mut prelude,
+            subindexer,
         );
 
         let con_loc = I::switch_on(
@@ -1527,6 +1682,7 @@ pub(crate) fn compute_indices(mut self, skip_stub_try_me_else: bool) -> Vec<Inde
             },
             self.indices.constants(),
             &mut prelude,
+            subindexer,
         );
 
         if let IndexingCodePtr::Internal(ref mut i) = &mut str_loc {
@@ -1538,16 +1694,840 @@ pub(crate) fn compute_indices(mut self, skip_stub_try_me_else: bool) -> Vec<Inde
             *i += emitted_switch_on_structure as usize; // str_loc.is_internal() as usize;
         }
 
-        let var_offset = 1 + skip_stub_try_me_else as usize;
+        (prelude, con_loc, lst_loc, str_loc)
+    }
+}
 
-        prelude.push_front(IndexingLine::from(IndexingInstruction::SwitchOnTerm(
-            self.optimal_index,
-            I::var_offset_wrapper(var_offset),
-            con_loc,
-            lst_loc,
-            str_loc,
-        )));
+impl CodeOffsets<StaticCodeIndices> {
+    // true if some key of the index selects fewer than len clauses.
+    fn separates(&self, len: usize) -> bool {
+        let lists_len = self.indices.lists.len();
+
+        (lists_len > 0 && lists_len < len)
+            || self
+                .indices
+                .constants
+                .values()
+                .chain(self.indices.structures.values())
+                .any(|code| code.len() < len)
+    }
+}
 
-        prelude.into_iter().collect()
+// the greatest number of switches nested in a switch of the indexing
+// code of a static predicate.
+const MAX_SUBINDEX_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+enum SubindexSwitch {
+    // switch on an argument of the indexed term.
+    Subterm(usize),
+    // switch on another argument of the clause heads.
+    Arg(usize),
+}
+
+/// Indexes the clauses of a static predicate sharing a key further,
+/// on an argument of their indexed terms or on another argument of
+/// their heads, so that calls to large fact tables don't try every
+/// clause matching their first instantiated argument.
+#[derive(Debug)]
+pub(crate) struct SubIndexer<'a> {
+    // the head arguments of the clauses, by offset of the clause.
+    clause_args: &'a IndexMap<usize, &'a [Term], FxBuildHasher>,
+    // the terms of the clauses selected by the enclosing switch.
+    indexed_terms: IndexMap<usize, &'a Term, FxBuildHasher>,
+    indexed_args: Vec<usize>,
+    non_counted_bt: bool,
+    depth: usize,
+}
+
+impl<'a> SubIndexer<'a> {
+    pub(crate) fn new(
+        clause_args: &'a IndexMap<usize, &'a [Term], FxBuildHasher>,
+        arg: usize,
+        non_counted_bt: bool,
+    ) -> Self {
+        let indexed_terms = clause_args
+            .iter()
+            .filter_map(|(offset, args)| Some((*offset, args.get(arg - 1)?)))
+            .collect();
+
+        Self {
+            clause_args,
+            indexed_terms,
+            indexed_args: vec![arg],
+            non_counted_bt,
+            depth: 0,
+        }
+    }
+
+    // the switches selecting among the clauses of code, or None if no
+    // argument tells them apart. unbound arguments are sent to the
+    // line following the switches, which must be code itself.
+    fn subindex(
+        &self,
+        code: &VecDeque<IndexedChoiceInstruction>,
+    ) -> Option<VecDeque<IndexingLine>> {
+        if self.depth == MAX_SUBINDEX_DEPTH {
+            return None;
+        }
+
+        let offsets: Vec<usize> = code.iter().map(IndexedChoiceInstruction::offset).collect();
+
+        if let Some(Term::Clause(_, name, args)) = self.indexed_terms.get(offsets.first()?) {
+            if *name != atom!(".") || args.len() != 2 {
+                for subterm in 1..=args.len() {
+                    let terms = offsets
+                        .iter()
+                        .map(|offset| match self.indexed_terms.get(offset) {
+                            Some(Term::Clause(_, _, args)) => {
+                                Some((*offset, args.get(subterm - 1)?))
+                            }
+                            _ => None,
+                        });
+
+                    if let Some(lines) =
+                        self.switch_on(code, terms.collect(), SubindexSwitch::Subterm(subterm))
+                    {
+                        return Some(lines);
+                    }
+                }
+            }
+        }
+
+        let arity = self
+            .clause_args
+            .get(&offsets[0])
+            .map_or(0, |args| args.len());
+
+        for arg in 1..=arity {
+            if self.indexed_args.contains(&arg) {
+                continue;
+            }
+
+            let terms = offsets.iter().map(|offset| {
+                let args = self.clause_args.get(offset)?;
+                Some((*offset, args.get(arg - 1)?))
+            });
+
+            if let Some(lines) = self.switch_on(code, terms.collect(), SubindexSwitch::Arg(arg)) {
+                return Some(lines);
+            }
+        }
+
+        None
+    }
+
+    fn switch_on(
+        &self,
+        code: &VecDeque<IndexedChoiceInstruction>,
+        terms: Option<Vec<(usize, &'a Term)>>,
+        switch: SubindexSwitch,
+    ) -> Option<VecDeque<IndexingLine>> {
+        let terms = terms?;
+
+        if !terms.iter().all(|(_, term)| is_indexable(term)) {
+            return None;
+        }
+
+        let (SubindexSwitch::Subterm(arg) | SubindexSwitch::Arg(arg)) = switch;
+
+        let mut code_offsets = CodeOffsets::new(StaticCodeIndices::new(), arg, self.non_counted_bt);
+        let mut clause_index_info = ClauseIndexInfo::new(0);
+
+        for &(offset, term) in &terms {
+            code_offsets.index_term(term, offset - 1, &mut clause_index_info);
+        }
+
+        // a switch that doesn't tell the clauses apart is kept only
+        // if the switches nested in it do, e.g. on the arguments of
+        // the indexed terms.
+        let separates = code_offsets.separates(code.len());
+
+        if !separates && code_offsets.indices.structures.len() != 1 {
+            return None;
+        }
+
+        let mut indexed_args = self.indexed_args.clone();
+
+        if let SubindexSwitch::Arg(arg) = switch {
+            indexed_args.push(arg);
+        }
+
+        let subindexer = SubIndexer {
+            clause_args: self.clause_args,
+            indexed_terms: terms.into_iter().collect(),
+            indexed_args,
+            non_counted_bt: self.non_counted_bt,
+            depth: self.depth + 1,
+        };
+
+        let (mut lines, c, l, s) = code_offsets.index_prelude(Some(&subindexer));
+
+        if !separates && lines.len() <= 1 {
+            return None;
+        }
+
+        let v = IndexingCodePtr::Internal(lines.len() + 1);
+
+        lines.push_front(IndexingLine::from(match switch {
+            SubindexSwitch::Subterm(arg) => IndexingInstruction::SwitchOnSubterm(arg, v, c, l, s),
+            SubindexSwitch::Arg(arg) => IndexingInstruction::SwitchOnTerm(arg, v, c, l, s),
+        }));
+
+        Some(lines)
+    }
+}
+
+// the least number of clauses sharing the key of the indexed argument
+// of a dynamic predicate for which they're indexed further.
+const MIN_DYNAMIC_SUBINDEXED_CLAUSES: usize = 8;
+
+// the key of an argument of a dynamic clause, or None if it's a
+// variable.
+pub(crate) fn subindex_key(term: &Term) -> Option<SubindexKey> {
+    match term {
+        &Term::Clause(_, atom!("."), ref terms) if terms.len() == 2 => Some(SubindexKey::List),
+        &Term::Cons(..) | &Term::PartialString(..) | &Term::CompleteString(..) => {
+            Some(SubindexKey::List)
+        }
+        &Term::Clause(_, name, ref terms) if !terms.is_empty() => {
+            Some(SubindexKey::Structure(name, terms.len()))
+        }
+        &Term::Literal(_, constant) => Some(SubindexKey::Constant(
+            HeapCellValue::from(constant),
+            constant_key_alternatives(constant).map(HeapCellValue::from),
+        )),
+        Term::Clause(..) | Term::AnonVar | Term::Var(..) => None,
+    }
+}
+
+/// The subkeys of a dynamic clause with head arguments args, indexed
+/// on its argument of index optimal_index.
+pub(crate) fn dynamic_subkeys(optimal_index: usize, args: &[Term]) -> DynamicSubkeys {
+    let subterms = match args.get(optimal_index - 1) {
+        Some(Term::Clause(_, name, terms)) if *name != atom!(".") || terms.len() != 2 => {
+            terms.iter().map(subindex_key).collect()
+        }
+        _ => Box::default(),
+    };
+
+    DynamicSubkeys {
+        subterms,
+        args: args.iter().map(subindex_key).collect(),
     }
 }
+
+impl DynamicSubkeys {
+    fn key(&self, switch: SubindexSwitch) -> Option<SubindexKey> {
+        match switch {
+            SubindexSwitch::Subterm(arg) => self.subterms.get(arg - 1).copied().flatten(),
+            SubindexSwitch::Arg(arg) => self.args.get(arg - 1).copied().flatten(),
+        }
+    }
+}
+
+/// Finds the subkeys of the clauses of a dynamic predicate by their
+/// offsets in its indexing code.
+pub(crate) enum DynamicSubkeysLookup<'a> {
+    // the subkeys of clauses being compiled, all of them living.
+    Compiled(&'a IndexMap<usize, DynamicSubkeys, FxBuildHasher>),
+    // the subkeys of the clauses of the indexing code at index_loc, by
+    // location of their inner choice instructions.
+    Installed {
+        code: &'a [Instruction],
+        index_loc: usize,
+        clause_subkeys: &'a IndexMap<usize, DynamicSubkeys, FxBuildHasher>,
+    },
+}
+
+enum ClauseSubkeys<'a> {
+    Retracted,
+    Unknown,
+    Known(&'a DynamicSubkeys),
+}
+
+impl<'a> DynamicSubkeysLookup<'a> {
+    fn clause_subkeys(&self, offset: usize) -> ClauseSubkeys<'a> {
+        let (clause_subkeys, clause_loc) = match *self {
+            DynamicSubkeysLookup::Compiled(clause_subkeys) => (clause_subkeys, offset),
+            DynamicSubkeysLookup::Installed {
+                code,
+                index_loc,
+                clause_subkeys,
+            } => {
+                let clause_loc = index_loc + offset - 1;
+
+                // the clause being prepended isn't in code yet.
+                if let Some(Instruction::DynamicInternalElse(_, Death::Finite(_), _)) =
+                    code.get(clause_loc)
+                {
+                    return ClauseSubkeys::Retracted;
+                }
+
+                (clause_subkeys, clause_loc)
+            }
+        };
+
+        clause_subkeys
+            .get(&clause_loc)
+            .map_or(ClauseSubkeys::Unknown, ClauseSubkeys::Known)
+    }
+}
+
+// the key of a choice of the clauses of a dynamic predicate, by which
+// a switch points to it.
+#[derive(Debug, Clone, Copy)]
+enum ChoiceKey {
+    Constant(HeapCellValue),
+    List,
+    Structure(PredicateKey),
+}
+
+impl ChoiceKey {
+    fn of_subindex_key(key: SubindexKey) -> impl Iterator<Item = ChoiceKey> {
+        let (key, alternative) = match key {
+            SubindexKey::Constant(constant, alternative) => (
+                ChoiceKey::Constant(constant),
+                alternative.map(ChoiceKey::Constant),
+            ),
+            SubindexKey::List => (ChoiceKey::List, None),
+            SubindexKey::Structure(name, arity) => (ChoiceKey::Structure((name, arity)), None),
+        };
+
+        once(key).chain(alternative)
+    }
+
+    fn of_index_key(opt_arg_index_key: &OptArgIndexKey) -> impl Iterator<Item = ChoiceKey> {
+        let (key, alternative) = match *opt_arg_index_key {
+            OptArgIndexKey::Literal(_, _, constant, alternative) => (
+                Some(ChoiceKey::Constant(HeapCellValue::from(constant))),
+                alternative.map(|constant| ChoiceKey::Constant(HeapCellValue::from(constant))),
+            ),
+            OptArgIndexKey::Structure(_, _, name, arity) => {
+                (Some(ChoiceKey::Structure((name, arity))), None)
+            }
+            OptArgIndexKey::List(..) => (Some(ChoiceKey::List), None),
+            OptArgIndexKey::None => (None, None),
+        };
+
+        key.into_iter().chain(alternative)
+    }
+}
+
+// the pointer of the switch at switch_loc to the clauses of key.
+fn choice_ptr(
+    indexing_code: &[IndexingLine],
+    switch_loc: usize,
+    key: ChoiceKey,
+) -> IndexingCodePtr {
+    match (&indexing_code[switch_loc], key) {
+        (
+            IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(constants)),
+            ChoiceKey::Constant(constant),
+        ) => constants
+            .get(&constant)
+            .copied()
+            .unwrap_or(IndexingCodePtr::Fail),
+        (
+            IndexingLine::Indexing(IndexingInstruction::SwitchOnStructure(structures)),
+            ChoiceKey::Structure(key),
+        ) => structures
+            .get(&key)
+            .copied()
+            .unwrap_or(IndexingCodePtr::Fail),
+        (
+            &IndexingLine::Indexing(
+                IndexingInstruction::SwitchOnTerm(_, _, c, l, s)
+                | IndexingInstruction::SwitchOnSubterm(_, _, c, l, s),
+            ),
+            key,
+        ) => match key {
+            ChoiceKey::Constant(_) => c,
+            ChoiceKey::List => l,
+            ChoiceKey::Structure(_) => s,
+        },
+        _ => unreachable!(),
+    }
+}
+
+// points the switch at switch_loc to ptr for the clauses of key.
+fn set_choice_ptr(
+    indexing_code: &mut [IndexingLine],
+    switch_loc: usize,
+    key: ChoiceKey,
+    ptr: IndexingCodePtr,
+) {
+    match (&mut indexing_code[switch_loc], key) {
+        (
+            IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(constants)),
+            ChoiceKey::Constant(constant),
+        ) => {
+            constants.insert(constant, ptr);
+        }
+        (
+            IndexingLine::Indexing(IndexingInstruction::SwitchOnStructure(structures)),
+            ChoiceKey::Structure(key),
+        ) => {
+            structures.insert(key, ptr);
+        }
+        (
+            IndexingLine::Indexing(
+                IndexingInstruction::SwitchOnTerm(_, _, c, l, s)
+                | IndexingInstruction::SwitchOnSubterm(_, _, c, l, s),
+            ),
+            key,
+        ) => match key {
+            ChoiceKey::Constant(_) => *c = ptr,
+            ChoiceKey::List => *l = ptr,
+            ChoiceKey::Structure(_) => *s = ptr,
+        },
+        _ => unreachable!(),
+    }
+}
+
+// the location of the choice of the clauses of key selected by the
+// switch at switch_loc and the switch on keys it may point to.
+fn choice_loc(indexing_code: &[IndexingLine], switch_loc: usize, key: ChoiceKey) -> Option<usize> {
+    let IndexingCodePtr::Internal(o) = choice_ptr(indexing_code, switch_loc, key) else {
+        return None;
+    };
+
+    let loc = switch_loc + o;
+
+    match &indexing_code[loc] {
+        IndexingLine::Indexing(
+            IndexingInstruction::SwitchOnConstant(_) | IndexingInstruction::SwitchOnStructure(_),
+        ) => match choice_ptr(indexing_code, loc, key) {
+            IndexingCodePtr::Internal(o) => Some(loc + o),
+            _ => None,
+        },
+        _ => Some(loc),
+    }
+}
+
+// the switch of the sub-index at loc and the location of the choice of
+// all its clauses, if there's a sub-index at loc.
+fn dynamic_subindex_at(
+    indexing_code: &[IndexingLine],
+    loc: usize,
+) -> Option<(SubindexSwitch, usize)> {
+    match indexing_code[loc] {
+        IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(
+            arg,
+            IndexingCodePtr::Internal(v),
+            ..,
+        )) if loc > 0 => Some((SubindexSwitch::Arg(arg), loc + v)),
+        IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(
+            arg,
+            IndexingCodePtr::Internal(v),
+            ..,
+        )) => Some((SubindexSwitch::Subterm(arg), loc + v)),
+        _ => None,
+    }
+}
+
+// adds the clause at offset to the choice at choice_loc.
+fn push_dynamic_choice(
+    indexing_code: &mut [IndexingLine],
+    choice_loc: usize,
+    offset: usize,
+    append_or_prepend: AppendOrPrepend,
+) {
+    match &mut indexing_code[choice_loc] {
+        IndexingLine::DynamicIndexedChoice(offsets) if append_or_prepend.is_append() => {
+            offsets.push_back(offset);
+        }
+        IndexingLine::DynamicIndexedChoice(offsets) => {
+            offsets.push_front(offset);
+        }
+        _ => unreachable!(),
+    }
+}
+
+// the argument switch telling apart the most of clauses, none of which
+// has a variable in it, if it tells some of them apart.
+fn dynamic_subindex_switch(
+    clauses: &[(usize, &DynamicSubkeys)],
+    indexed_arg: usize,
+) -> Option<SubindexSwitch> {
+    let (_, first_subkeys) = clauses.first()?;
+
+    let subterms = (1..=first_subkeys.subterms.len()).map(SubindexSwitch::Subterm);
+    let args = (1..=first_subkeys.args.len())
+        .filter(|arg| *arg != indexed_arg)
+        .map(SubindexSwitch::Arg);
+
+    let mut best_switch = None;
+    let mut best_keys_len = 1;
+
+    for switch in subterms.chain(args) {
+        let keys: Option<IndexSet<SubindexKey, FxBuildHasher>> = clauses
+            .iter()
+            .map(|(_, subkeys)| subkeys.key(switch))
+            .collect();
+
+        if let Some(keys) = keys {
+            if keys.len() > best_keys_len {
+                best_switch = Some(switch);
+                best_keys_len = keys.len();
+            }
+        }
+    }
+
+    best_switch
+}
+
+// pushes a switch on the keys of choices followed by the choices,
+// returning the pointer to it from the first of lines.
+fn push_dynamic_switch<Key: Eq + Hash>(
+    lines: &mut Vec<IndexingLine>,
+    choices: IndexMap<Key, VecDeque<usize>, FxBuildHasher>,
+    instr_fn: impl FnOnce(IndexMap<Key, IndexingCodePtr, FxBuildHasher>) -> IndexingInstruction,
+) -> IndexingCodePtr {
+    if choices.is_empty() {
+        return IndexingCodePtr::Fail;
+    }
+
+    let switch_loc = lines.len();
+    let mut ptrs = IndexMap::with_hasher(FxBuildHasher::default());
+
+    lines.push(IndexingLine::DynamicIndexedChoice(VecDeque::new()));
+
+    for (key, choice) in choices {
+        ptrs.insert(key, IndexingCodePtr::Internal(lines.len() - switch_loc));
+        lines.push(IndexingLine::DynamicIndexedChoice(choice));
+    }
+
+    lines[switch_loc] = IndexingLine::from(instr_fn(ptrs));
+    IndexingCodePtr::Internal(switch_loc)
+}
+
+// the lines of a sub-index telling apart clauses by switch: the
+// switch, the switches on the keys it selects and their choices, and
+// last, the choice of all the clauses, to which unbound arguments are
+// sent.
+fn dynamic_subindex_lines(
+    clauses: &[(usize, &DynamicSubkeys)],
+    switch: SubindexSwitch,
+) -> Vec<IndexingLine> {
+    let mut indices = DynamicCodeIndices::new();
+
+    for &(offset, subkeys) in clauses {
+        for key in subkeys
+            .key(switch)
+            .into_iter()
+            .flat_map(ChoiceKey::of_subindex_key)
+        {
+            match key {
+                ChoiceKey::Constant(constant) => {
+                    indices
+                        .constants
+                        .entry(constant)
+                        .or_default()
+                        .push_back(offset);
+                }
+                ChoiceKey::List => {
+                    indices.lists.push_back(offset);
+                }
+                ChoiceKey::Structure(key) => {
+                    indices.structures.entry(key).or_default().push_back(offset);
+                }
+            }
+        }
+    }
+
+    let mut lines = vec![IndexingLine::DynamicIndexedChoice(VecDeque::new())];
+
+    let c = push_dynamic_switch(
+        &mut lines,
+        indices.constants,
+        IndexingInstruction::SwitchOnConstant,
+    );
+    let s = push_dynamic_switch(
+        &mut lines,
+        indices.structures,
+        IndexingInstruction::SwitchOnStructure,
+    );
+
+    let l = if indices.lists.is_empty() {
+        IndexingCodePtr::Fail
+    } else {
+        lines.push(IndexingLine::DynamicIndexedChoice(indices.lists));
+        IndexingCodePtr::Internal(lines.len() - 1)
+    };
+
+    let v = IndexingCodePtr::Internal(lines.len());
+    lines.push(IndexingLine::DynamicIndexedChoice(
+        clauses.iter().map(|(offset, _)| *offset).collect(),
+    ));
+
+    lines[0] = IndexingLine::from(match switch {
+        SubindexSwitch::Subterm(arg) => IndexingInstruction::SwitchOnSubterm(arg, v, c, l, s),
+        SubindexSwitch::Arg(arg) => IndexingInstruction::SwitchOnTerm(arg, v, c, l, s),
+    });
+
+    lines
+}
+
+// indexes the living clauses of the choice at choice_loc further if
+// they're enough and an argument tells them apart, pointing the switch
+// at switch_loc to the new sub-index for key. the replaced lines are
+// left in place for the choice points iterating them.
+fn subindex_dynamic_choice(
+    indexing_code: &mut Vec<IndexingLine>,
+    switch_loc: usize,
+    key: ChoiceKey,
+    choice_loc: usize,
+    lookup: &DynamicSubkeysLookup,
+) {
+    let IndexingLine::DynamicIndexedChoice(offsets) = &indexing_code[choice_loc] else {
+        return;
+    };
+
+    let mut clauses = Vec::with_capacity(offsets.len());
+
+    for &offset in offsets {
+        match lookup.clause_subkeys(offset) {
+            ClauseSubkeys::Retracted => {}
+            ClauseSubkeys::Unknown => return,
+            ClauseSubkeys::Known(subkeys) => clauses.push((offset, subkeys)),
+        }
+    }
+
+    if clauses.len() < MIN_DYNAMIC_SUBINDEXED_CLAUSES {
+        return;
+    }
+
+    let indexed_arg = match indexing_code[0] {
+        IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(arg, ..)) => arg,
+        _ => unreachable!(),
+    };
+
+    if let Some(switch) = dynamic_subindex_switch(&clauses, indexed_arg) {
+        let subindex_loc = indexing_code.len();
+
+        indexing_code.extend(dynamic_subindex_lines(&clauses, switch));
+        set_choice_ptr(
+            indexing_code,
+            switch_loc,
+            key,
+            IndexingCodePtr::Internal(subindex_loc - switch_loc),
+        );
+    }
+}
+
+// indexes the choice at choice_loc of the clauses of key further as
+// its clauses double in number.
+fn resubindex_dynamic_choice(
+    indexing_code: &mut Vec<IndexingLine>,
+    switch_loc: usize,
+    key: ChoiceKey,
+    choice_loc: usize,
+    lookup: &DynamicSubkeysLookup,
+) {
+    match &indexing_code[choice_loc] {
+        IndexingLine::DynamicIndexedChoice(offsets)
+            if offsets.len() >= MIN_DYNAMIC_SUBINDEXED_CLAUSES
+                && offsets.len().is_power_of_two() =>
+        {
+            subindex_dynamic_choice(indexing_code, switch_loc, key, choice_loc, lookup);
+        }
+        _ => {}
+    }
+}
+
+// adds the clause at offset to the choice of key in the sub-index at
+// subindex_loc, adding the switch and the choice if they're missing.
+fn index_dynamic_subkey(
+    indexing_code: &mut Vec<IndexingLine>,
+    subindex_loc: usize,
+    key: ChoiceKey,
+    offset: usize,
+    append_or_prepend: AppendOrPrepend,
+) {
+    let switch_loc = match (choice_ptr(indexing_code, subindex_loc, key), key) {
+        (_, ChoiceKey::List) => subindex_loc,
+        (IndexingCodePtr::Internal(o), _) => subindex_loc + o,
+        (_, ChoiceKey::Constant(_)) | (_, ChoiceKey::Structure(_)) => {
+            indexing_code.push(IndexingLine::from(match key {
+                ChoiceKey::Constant(_) => IndexingInstruction::SwitchOnConstant(
+                    IndexMap::with_hasher(FxBuildHasher::default()),
+                ),
+                _ => IndexingInstruction::SwitchOnStructure(IndexMap::with_hasher(
+                    FxBuildHasher::default(),
+                )),
+            }));
+
+            let switch_loc = indexing_code.len() - 1;
+
+            set_choice_ptr(
+                indexing_code,
+                subindex_loc,
+                key,
+                IndexingCodePtr::Internal(switch_loc - subindex_loc),
+            );
+
+            switch_loc
+        }
+    };
+
+    match choice_ptr(indexing_code, switch_loc, key) {
+        IndexingCodePtr::Internal(o) => {
+            push_dynamic_choice(indexing_code, switch_loc + o, offset, append_or_prepend);
+        }
+        _ => {
+            indexing_code.push(IndexingLine::DynamicIndexedChoice(VecDeque::from([offset])));
+
+            let choice_loc = indexing_code.len() - 1;
+
+            set_choice_ptr(
+                indexing_code,
+                switch_loc,
+                key,
+                IndexingCodePtr::Internal(choice_loc - switch_loc),
+            );
+        }
+    }
+}
+
+// drops the sub-index at subindex_loc, pointing the switch at
+// switch_loc to the choice of all its clauses for key. returns the
+// location of that choice.
+fn drop_dynamic_subindex(
+    indexing_code: &mut [IndexingLine],
+    switch_loc: usize,
+    key: ChoiceKey,
+    subindex_loc: usize,
+) -> usize {
+    let (_, all_loc) = dynamic_subindex_at(indexing_code, subindex_loc).unwrap();
+
+    set_choice_ptr(
+        indexing_code,
+        switch_loc,
+        key,
+        IndexingCodePtr::Internal(all_loc - switch_loc),
+    );
+
+    all_loc
+}
+
+/// Indexes the clauses of a dynamic predicate sharing a key further,
+/// on an argument of their indexed terms or another argument of their
+/// heads, if there are enough of them.
+pub(crate) fn subindex_dynamic_choices(
+    indexing_code: &mut Vec<IndexingLine>,
+    lookup: &DynamicSubkeysLookup,
+) {
+    let (c, l, s) = match indexing_code.first() {
+        Some(&IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(_, _, c, l, s))) => {
+            (c, l, s)
+        }
+        _ => return,
+    };
+
+    let mut choices = vec![];
+
+    if let IndexingCodePtr::Internal(o) = l {
+        choices.push((0, ChoiceKey::List, o));
+    }
+
+    for ptr in [c, s] {
+        let IndexingCodePtr::Internal(switch_loc) = ptr else {
+            continue;
+        };
+
+        let keys: Vec<(ChoiceKey, IndexingCodePtr)> = match &indexing_code[switch_loc] {
+            IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(constants)) => constants
+                .iter()
+                .map(|(constant, ptr)| (ChoiceKey::Constant(*constant), *ptr))
+                .collect(),
+            IndexingLine::Indexing(IndexingInstruction::SwitchOnStructure(structures)) => {
+                structures
+                    .iter()
+                    .map(|(key, ptr)| (ChoiceKey::Structure(*key), *ptr))
+                    .collect()
+            }
+            _ => continue,
+        };
+
+        for (key, ptr) in keys {
+            if let IndexingCodePtr::Internal(o) = ptr {
+                choices.push((switch_loc, key, switch_loc + o));
+            }
+        }
+    }
+
+    for (switch_loc, key, choice_loc) in choices {
+        subindex_dynamic_choice(indexing_code, switch_loc, key, choice_loc, lookup);
+    }
+}
+
+/// Removes the retracted clause at offset from the choices of the
+/// sub-indices it belongs to, except those iterated by choice points,
+/// which skip it as retracted. The choices of all the clauses of the
+/// sub-indices are left to skip it too, like the choices of the keys
+/// of the indexed argument, until the sub-index is rebuilt.
+pub(crate) fn remove_dynamic_subindexed_clause(
+    indexing_code: &mut [IndexingLine],
+    opt_arg_index_key: &OptArgIndexKey,
+    offset: usize,
+    subkeys: &DynamicSubkeys,
+    is_iterated: impl Fn(usize) -> bool,
+) {
+    for key in ChoiceKey::of_index_key(opt_arg_index_key) {
+        let Some(subindex_loc) = choice_loc(indexing_code, 0, key) else {
+            continue;
+        };
+
+        let Some((switch, _)) = dynamic_subindex_at(indexing_code, subindex_loc) else {
+            continue;
+        };
+
+        for subkey in subkeys
+            .key(switch)
+            .into_iter()
+            .flat_map(ChoiceKey::of_subindex_key)
+        {
+            match choice_loc(indexing_code, subindex_loc, subkey) {
+                Some(loc) if !is_iterated(loc) => {
+                    if let IndexingLine::DynamicIndexedChoice(offsets) = &mut indexing_code[loc] {
+                        DynamicCodeIndices::remove_instruction_with_offset(offsets, offset);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_indexable(term: &Term) -> bool {
+    match term {
+        Term::Clause(_, _, terms) => !terms.is_empty(),
+        Term::Cons(..) | Term::Literal(..) | Term::PartialString(..) | Term::CompleteString(..) => {
+            true
+        }
+        Term::AnonVar | Term::Var(..) => false,
+    }
+}
+
+// whether clauses can be told apart by their argument arg. floats
+// can be, as the preprocessor gives equal floats of clause heads the
+// same offset (see Arena::f64_index_key), the key of their value in the
+// switches on constants.
+pub(crate) fn is_indexable_arg(arg: &Term) -> bool {
+    !matches!(arg, Term::Var(..) | Term::AnonVar)
+}
+
+// pushes the choice sequence of a key to prelude, preceded by the
+// switches of subindexer telling its clauses apart.
+fn push_choice_seq(
+    code: VecDeque<IndexedChoiceInstruction>,
+    prelude: &mut VecDeque<IndexingLine>,
+    subindexer: Option<&SubIndexer>,
+) {
+    if let Some(lines) = subindexer.and_then(|subindexer| subindexer.subindex(&code)) {
+        prelude.extend(lines);
+    }
+
+    prelude.push_back(IndexingLine::from(code));
+}
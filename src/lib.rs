@@ -19,6 +19,8 @@
 mod allocator;
 mod arithmetic;
 pub(crate) mod codegen;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dap;
 mod debray_allocator;
 #[cfg(feature = "ffi")]
 mod ffi;
@@ -34,6 +36,8 @@ pub(crate) mod instructions {
     include!(concat!(env!("OUT_DIR"), "/instructions.rs"));
 }
 mod iterators;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lsp;
 pub(crate) mod machine;
 mod raw_block;
 pub(crate) mod read;
@@ -58,7 +62,9 @@ pub fn run_binary() -> std::process::ExitCode {
 
     #[cfg(feature = "repl")]
     ctrlc::set_handler(move || {
-        INTERRUPT.store(true, std::sync::atomic::Ordering::Relaxed);
+        if machine::signals::interrupts() {
+            INTERRUPT.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
     })
     .unwrap();
 
@@ -74,10 +80,108 @@ pub fn run_binary() -> std::process::ExitCode {
         .build()
         .unwrap();
 
-    runtime.block_on(async move {
-        let mut wam = MachineBuilder::default()
-            .with_streams(StreamConfig::stdio())
-            .build();
+    let exit_code = runtime.block_on(async move {
+        // the options of scryer-prolog, the arguments following `--`
+        // being those of the program.
+        let options: Vec<String> = std::env::args().take_while(|arg| arg != "--").collect();
+        let has_option = |option: &str| options.iter().any(|arg| arg == option);
+
+        if has_option("--dap") {
+            return match dap::run_dap_server(std::io::stdin().lock(), std::io::stdout()) {
+                Ok(()) => std::process::ExitCode::SUCCESS,
+                Err(_) => std::process::ExitCode::FAILURE,
+            };
+        }
+
+        if has_option("--lsp") {
+            return match lsp::run_lsp_server(std::io::stdin().lock(), std::io::stdout()) {
+                Ok(()) => std::process::ExitCode::SUCCESS,
+                Err(_) => std::process::ExitCode::FAILURE,
+            };
+        }
+
+        let mut builder = match MachineBuilder::from_env() {
+            Ok(builder) => builder.with_streams(StreamConfig::stdio()),
+            Err(error) => {
+                eprintln!("{error}");
+                return std::process::ExitCode::FAILURE;
+            }
+        };
+
+        if has_option("--progress") {
+            builder = builder.with_load_progress_hook(print_load_progress);
+        }
+
+        if has_option("--index-cache") {
+            builder = builder.with_index_cache(true);
+        }
+
+        let mut args = options.iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "--library-path" {
+                if let Some(library_path) = args.next() {
+                    builder = builder.with_library_path(library_path);
+                }
+            } else if arg == "--crash-report" {
+                if let Some(crash_report) = args.next() {
+                    builder = builder.with_crash_report(crash_report);
+                }
+            }
+        }
+
+        let mut wam = builder.build();
         wam.run_module_predicate(atom!("$toplevel"), (atom!("$repl"), 0))
-    })
+    });
+
+    // the background threads started by spawn_initialization/1 run
+    // on the blocking pool of the runtime, which would otherwise be
+    // waited for.
+    runtime.shutdown_background();
+    exit_code
+}
+
+// the progress meter of --progress, written to stderr. on a terminal,
+// each report overwrites the last until the load is finished.
+#[cfg(not(target_arch = "wasm32"))]
+fn print_load_progress(progress: &LoadProgress) {
+    use std::io::{IsTerminal, Write};
+
+    let mut stderr = std::io::stderr().lock();
+    let is_terminal = stderr.is_terminal();
+    let elapsed = progress.elapsed.as_secs_f64();
+
+    let mut print = || -> std::io::Result<()> {
+        if is_terminal && progress.stage != LoadStage::Loaded {
+            write!(stderr, "\r\x1b[K")?;
+        }
+
+        match progress.stage {
+            LoadStage::Started | LoadStage::Clauses => {
+                write!(
+                    stderr,
+                    "% loading {}: {} clauses, {} files, {elapsed:.2}s",
+                    progress.file.as_deref().unwrap_or("stream"),
+                    progress.clauses,
+                    progress.files,
+                )?;
+
+                if !is_terminal {
+                    writeln!(stderr)?;
+                }
+            }
+            LoadStage::Loaded => {}
+            LoadStage::Finished => {
+                writeln!(
+                    stderr,
+                    "% loaded {} clauses from {} files in {elapsed:.2}s",
+                    progress.clauses, progress.files,
+                )?;
+            }
+        }
+
+        stderr.flush()
+    };
+
+    let _ = print();
 }
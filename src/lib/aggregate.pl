@@ -0,0 +1,201 @@
+/** Aggregating the solutions of goals.
+
+    `aggregate_all/3` folds all solutions of a goal into a single
+    result, such as their count or sum, like `findall/3`, and
+    `aggregate/3` folds them into a result per binding of the free
+    variables of the goal, like `bagof/3`. The kind of result is given
+    by an aggregation specification, which is one of:
+
+      - `count`, the number of solutions.
+      - `sum(Expr)`, the sum of the values of the arithmetic
+        expression Expr.
+      - `max(Expr)` and `min(Expr)`, the greatest and least values of
+        Expr.
+      - `max(Expr, Witness)` and `min(Expr, Witness)`, whose results
+        are `max(Max, Witness)` and `min(Min, Witness)`, with Witness
+        the term of the first solution with that value.
+      - `bag(Template)` and `set(Template)`, the list of the instances
+        of Template, as of `findall/3` and `setof/3`.
+      - a compound term of these, whose result is the same compound
+        term of their results, such as `r(count, max(X))`.
+
+    Example:
+
+    ```
+    sale(apples, 3).
+    sale(pears, 2).
+    sale(apples, 5).
+
+    ?- aggregate_all(sum(N), sale(_, N), Total).
+       Total = 10.
+    ?- aggregate(r(count, sum(N)), sale(Fruit, N), Result).
+       Fruit = apples, Result = r(2, 8)
+    ;  Fruit = pears, Result = r(1, 2).
+    ```
+
+    `aggregate/3` groups the solutions of ground bindings with a hash
+    table of the machine, in a single pass over the solutions, instead
+    of sorting all of them.
+*/
+
+:- module(aggregate, [aggregate/3,
+                      aggregate_all/3,
+                      aggregate_all/4]).
+
+:- use_module(library(error)).
+:- use_module(library(lists)).
+:- use_module(library(pairs)).
+
+:- meta_predicate(aggregate(?, 0, ?)).
+:- meta_predicate(aggregate_all(?, 0, ?)).
+:- meta_predicate(aggregate_all(?, ?, 0, ?)).
+
+%% aggregate_all(+Spec, :Goal, -Result)
+%
+%  Result is the aggregation Spec of all solutions of Goal. With no
+%  solutions, the count is 0, the sum 0 and the bag and set `[]`,
+%  while the maxima and minima fail.
+
+aggregate_all(Spec, Goal, Result) :-
+        spec_template(Spec, Template, aggregate_all/3),
+        findall(Template, Goal, Templates),
+        aggregate_templates(Spec, Templates, Result).
+
+%% aggregate_all(+Spec, ?Discriminator, :Goal, -Result)
+%
+%  Like `aggregate_all/3`, but solutions of Goal count only once for
+%  each instance of Discriminator and the template of Spec. For
+%  example, `aggregate_all(count, X, member(X, Xs), N)` counts the
+%  distinct elements of Xs.
+
+aggregate_all(Spec, Discriminator, Goal, Result) :-
+        spec_template(Spec, Template, aggregate_all/4),
+        findall(Discriminator-Template, Goal, Pairs0),
+        sort(Pairs0, Pairs),
+        pairs_values(Pairs, Templates),
+        aggregate_templates(Spec, Templates, Result).
+
+%% aggregate(+Spec, :Goal, -Result)
+%
+%  Result is the aggregation Spec of the solutions of Goal for each
+%  binding of the variables of Goal that don't occur in Spec, in the
+%  standard order of the bindings. As with `bagof/3`, `Var^Goal`
+%  aggregates over all bindings of Var, and Goal fails if it has no
+%  solutions.
+
+aggregate(Spec, Goal, Result) :-
+        spec_template(Spec, Template, aggregate/3),
+        term_variables(Template, TemplateVars),
+        term_variables(Goal, GoalVars),
+        term_variables(TemplateVars+GoalVars, TGVs),
+        append(TemplateVars, Witnesses0, TGVs),
+        builtins:findall_with_existential(Template, Goal, Pairs, Witnesses0, Witnesses),
+        Pairs = [_|_],
+        (   Witnesses == [] ->
+            pairs_values(Pairs, Templates)
+        ;   solution_groups(Pairs, Groups),
+            group_member(Groups, Witnesses, Templates)
+        ),
+        aggregate_templates(Spec, Templates, Result).
+
+% Groups are the pairs Witnesses-Templates of the pairs of the
+% solutions, in the standard order of the witnesses. Variant witnesses
+% fall into the same group, as in bagof/3, which only non-ground ones
+% need sorting for.
+solution_groups(Pairs, Groups) :-
+        (   '$group_pairs'(Pairs, Groups0) ->
+            keysort(Groups0, Groups)
+        ;   keysort(Pairs, Pairs1),
+            builtins:group_by_variants(Pairs1, Groups)
+        ).
+
+group_member([W-Ts|Groups], Witnesses, Templates) :-
+        (   Groups == [] ->
+            Witnesses-Templates = W-Ts
+        ;   (   Witnesses-Templates = W-Ts
+            ;   group_member(Groups, Witnesses, Templates)
+            )
+        ).
+
+%% Specifications.
+
+spec_template(Spec, Template, Context) :-
+        (   var(Spec) ->
+            instantiation_error(Context)
+        ;   simple_spec_template(Spec, Template0) ->
+            Template = Template0
+        ;   compound(Spec),
+            Spec =.. [_|Specs],
+            maplist(simple_spec_template, Specs, Template) ->
+            true
+        ;   domain_error(aggregate_spec, Spec, Context)
+        ).
+
+simple_spec_template(Spec, _) :-
+        var(Spec), !, fail.
+simple_spec_template(count, []).
+simple_spec_template(sum(Expr), Expr).
+simple_spec_template(max(Expr), Expr).
+simple_spec_template(min(Expr), Expr).
+simple_spec_template(max(Expr, Witness), Expr-Witness).
+simple_spec_template(min(Expr, Witness), Expr-Witness).
+simple_spec_template(bag(Template), Template).
+simple_spec_template(set(Template), Template).
+
+aggregate_templates(Spec, Templates, Result) :-
+        (   simple_spec_template(Spec, _) ->
+            aggregate_simple(Spec, Templates, Result)
+        ;   Spec =.. [F|Specs],
+            same_length(Specs, Columns),
+            templates_columns(Templates, Columns),
+            maplist(aggregate_simple, Specs, Columns, Results),
+            Result =.. [F|Results]
+        ).
+
+templates_columns([], Columns) :-
+        maplist(=([]), Columns).
+templates_columns([Template|Templates], Columns) :-
+        maplist(column_cons, Template, Columns, Columns1),
+        templates_columns(Templates, Columns1).
+
+column_cons(X, [X|Xs], Xs).
+
+aggregate_simple(count, Templates, Count) :-
+        length(Templates, Count).
+aggregate_simple(sum(_), Exprs, Sum) :-
+        foldl(sum_, Exprs, 0, Sum).
+aggregate_simple(max(_), [Expr|Exprs], Max) :-
+        Max0 is Expr,
+        foldl(max_, Exprs, Max0, Max).
+aggregate_simple(min(_), [Expr|Exprs], Min) :-
+        Min0 is Expr,
+        foldl(min_, Exprs, Min0, Min).
+aggregate_simple(max(_, _), [Expr-Witness0|Pairs], max(Max, Witness)) :-
+        Max0 is Expr,
+        foldl(max_witness_, Pairs, Max0-Witness0, Max-Witness).
+aggregate_simple(min(_, _), [Expr-Witness0|Pairs], min(Min, Witness)) :-
+        Min0 is Expr,
+        foldl(min_witness_, Pairs, Min0-Witness0, Min-Witness).
+aggregate_simple(bag(_), Templates, Templates).
+aggregate_simple(set(_), Templates, Set) :-
+        sort(Templates, Set).
+
+sum_(Expr, S0, S) :- S is S0 + Expr.
+
+max_(Expr, M0, M) :- M is max(M0, Expr).
+
+min_(Expr, M0, M) :- M is min(M0, Expr).
+
+max_witness_(Expr-W, M0-W0, M-W1) :-
+        V is Expr,
+        (   V > M0 ->
+            M-W1 = V-W
+        ;   M-W1 = M0-W0
+        ).
+
+min_witness_(Expr-W, M0-W0, M-W1) :-
+        V is Expr,
+        (   V < M0 ->
+            M-W1 = V-W
+        ;   M-W1 = M0-W0
+        ).
@@ -3,13 +3,22 @@
 These predicates are additions to standard the arithmetic functions provided by `is/2`.
 */
 
-:- module(arithmetic, [expmod/4, lcm/3, lsb/2, msb/2, number_to_rational/2,
+:- module(arithmetic, [arithmetic_expression_value/2, arithmetic_function/1,
+                       expmod/4, lcm/3, lsb/2, msb/2, number_to_rational/2,
                        number_to_rational/3, popcount/2,
-                       rational_numerator_denominator/3]).
+                       rational_numerator_denominator/3, rational_round/3,
+                       rational_round/4]).
 
 :- use_module(library(charsio), [write_term_to_chars/3]).
+:- use_module(library(dcgs)).
 :- use_module(library(error)).
-:- use_module(library(lists), [append/3, member/2]).
+:- use_module(library(lists), [append/3, foldl/4, length/2, maplist/2,
+                               maplist/3, member/2, reverse/2]).
+
+:- meta_predicate(arithmetic_function(:)).
+:- meta_predicate(arithmetic_expression_value(:, ?)).
+
+:- dynamic(evaluable/3).
 
 
 %% expmod(+Base, +Expo, +Mod, -R).
@@ -154,6 +163,82 @@ rational_numerator_denominator(R, N, D) :-
     number_chars(N, Ns),
     number_chars(D, Ds).
 
+%% rational_round(+Number, +Digits, -Rounded).
+%
+% True iff Rounded is Number rounded to Digits decimal places, as an exact integer or
+% rational, in the rounding mode of the `rational_rounding` flag. Ties are rounded to the
+% even neighbour by default, so that repeated roundings don't drift as in financial
+% computations. A negative Digits rounds to tens, hundreds and so on. Together with the
+% `prefer_rationals` flag this keeps computations exact up to a chosen precision.
+%
+% ```
+% ?- X is 2 rdiv 3, rational_round(X, 2, R).
+%    X = 2 rdiv 3, R = 67 rdiv 100.
+% ?- rational_round(0.125, 2, R).
+%    R = 3 rdiv 25.
+% ```
+rational_round(Number, Digits, Rounded) :-
+    current_prolog_flag(rational_rounding, Mode),
+    rational_round_(Number, Digits, Mode, Rounded, rational_round/3).
+
+%% rational_round(+Number, +Digits, +Mode, -Rounded).
+%
+% Like `rational_round/3`, rounding in Mode, one of the values of the `rational_rounding`
+% flag: `half_even`, `half_away_from_zero`, `toward_zero`, `away_from_zero`, `down`
+% (toward negative infinity) and `up` (toward positive infinity).
+%
+% ```
+% ?- rational_round(2.5, 0, half_away_from_zero, R).
+%    R = 3.
+% ?- rational_round(-1 rdiv 3, 2, down, R).
+%    R = -17 rdiv 50.
+% ```
+rational_round(Number, Digits, Mode, Rounded) :-
+    rational_round_(Number, Digits, Mode, Rounded, rational_round/4).
+
+rational_round_(Number, Digits, Mode, Rounded, PI) :-
+    (   var(Number) -> instantiation_error(PI)
+    ;   \+ number(Number) -> type_error(number, Number, PI)
+    ;   must_be(integer, Digits),
+        must_be(atom, Mode)
+    ),
+    (   rounding_mode(Mode) -> true
+    ;   domain_error(rational_rounding, Mode, PI)
+    ),
+    (   Digits >= 0 -> Scale is 10 ^ Digits rdiv 1
+    ;   Scale is 1 rdiv 10 ^ (-Digits)
+    ),
+    Scaled is Number rdiv 1 * Scale,
+    round_scaled(Mode, Scaled, N),
+    Rounded is N rdiv Scale.
+
+rounding_mode(half_even).
+rounding_mode(half_away_from_zero).
+rounding_mode(toward_zero).
+rounding_mode(away_from_zero).
+rounding_mode(down).
+rounding_mode(up).
+
+round_scaled(half_even, Scaled, N) :-
+    Floor is floor(Scaled),
+    Fraction is Scaled - Floor,
+    Half is 1 rdiv 2,
+    (   Fraction > Half -> N is Floor + 1
+    ;   Fraction < Half -> N = Floor
+    ;   Floor mod 2 =:= 0 -> N = Floor
+    ;   N is Floor + 1
+    ).
+round_scaled(half_away_from_zero, Scaled, N) :-
+    N is sign(Scaled) * floor(abs(Scaled) + 1 rdiv 2).
+round_scaled(toward_zero, Scaled, N) :-
+    N is truncate(Scaled).
+round_scaled(away_from_zero, Scaled, N) :-
+    N is sign(Scaled) * ceiling(abs(Scaled)).
+round_scaled(down, Scaled, N) :-
+    N is floor(Scaled).
+round_scaled(up, Scaled, N) :-
+    N is ceiling(Scaled).
+
 %% popcount(+Number, -Bits1).
 %
 % True iff given an integer Number, Bits1 is the amount of 1 bits the binary representation
@@ -161,3 +246,134 @@ rational_numerator_denominator(R, N, D) :-
 popcount(X, N) :-
     must_be(integer, X),
     '$popcount'(X, N).
+
+%% arithmetic_function(+PI).
+%
+% Declares Name/Arity, of the current module, an evaluable functor, so that
+% `is/2` and the arithmetic comparisons evaluate `Name(X1, ..., Xn)` by
+% evaluating X1, ..., Xn to V1, ..., Vn and calling `Name(V1, ..., Vn, V)`,
+% where V is the value. Functors declared in `user` are evaluable in all
+% modules. Goals are rewritten as they are loaded, so the declaration,
+% usually a directive, must precede the clauses using the functor.
+% Expressions built at run time are evaluated by
+% `arithmetic_expression_value/2`. Functors already evaluable by `is/2`
+% can't be declared.
+%
+% ```
+% :- arithmetic_function(mid/2).
+%
+% mid(X, Y, Z) :- Z is (X + Y) / 2.
+%
+% ?- X is 2 * mid(1, 2).
+%    X = 3.0.
+% ```
+arithmetic_function(M:PI) :-
+    (   var(PI) -> instantiation_error(arithmetic_function/1)
+    ;   PI = Name/Arity -> true
+    ;   type_error(predicate_indicator, PI, arithmetic_function/1)
+    ),
+    must_be(atom, Name),
+    must_be(integer, Arity),
+    (   Arity < 0 ->
+        domain_error(not_less_than_zero, Arity, arithmetic_function/1)
+    ;   evaluable(M, Name, Arity) -> true
+    ;   builtin_evaluable(Name, Arity) ->
+        throw(error(permission_error(modify, evaluable, PI), arithmetic_function/1))
+    ;   assertz(evaluable(M, Name, Arity))
+    ).
+
+builtin_evaluable(Name, Arity) :-
+    length(Args, Arity),
+    maplist(=(1), Args),
+    Expr =.. [Name|Args],
+    catch((_ is Expr, E = none), E, true),
+    E \= error(type_error(evaluable, _), _).
+
+%% arithmetic_expression_value(:Expr, -Value).
+%
+% Value is the value of the arithmetic expression Expr, which may contain
+% the functors declared by `arithmetic_function/1` in the calling module or
+% `user`. Like `is/2`, for expressions only known at run time.
+arithmetic_expression_value(M:Expr, Value) :-
+    phrase(expression(Expr, M, Expr1), Goals),
+    maplist(call, Goals),
+    Value is Expr1.
+
+expression(E0, M, E) -->
+    (   { var(E0) } -> { E = E0 }
+    ;   { number(E0) } -> { E = E0 }
+    ;   { functor(E0, Name, Arity),
+          evaluable_module(M, Name, Arity, FM) } ->
+        { E0 =.. [Name|Args0] },
+        expressions(Args0, M, Args),
+        { append(Args, [E], Args1),
+          G =.. [Name|Args1] },
+        [FM:G]
+    ;   { compound(E0) } ->
+        { E0 =.. [Name|Args0] },
+        subexpressions(Args0, M, Args),
+        { E =.. [Name|Args] }
+    ;   { E = E0 }
+    ).
+
+% arguments of declared functors are evaluated before the call.
+expressions([], _, []) --> [].
+expressions([E0|Es0], M, [V|Vs]) -->
+    expression(E0, M, E),
+    [V is E],
+    expressions(Es0, M, Vs).
+
+subexpressions([], _, []) --> [].
+subexpressions([E0|Es0], M, [E|Es]) -->
+    expression(E0, M, E),
+    subexpressions(Es0, M, Es).
+
+evaluable_module(M, Name, Arity, FM) :-
+    (   evaluable(M, Name, Arity) -> FM = M
+    ;   evaluable(user, Name, Arity) -> FM = user
+    ).
+
+arithmetic_goal(X is Y, [Y], [E], X is E).
+arithmetic_goal(X =:= Y, [X, Y], [E, F], E =:= F).
+arithmetic_goal(X =\= Y, [X, Y], [E, F], E =\= F).
+arithmetic_goal(X < Y, [X, Y], [E, F], E < F).
+arithmetic_goal(X > Y, [X, Y], [E, F], E > F).
+arithmetic_goal(X =< Y, [X, Y], [E, F], E =< F).
+arithmetic_goal(X >= Y, [X, Y], [E, F], E >= F).
+
+expand_arithmetic_goal(Goal0, Goal) :-
+    evaluable(_, _, _),
+    !,
+    arithmetic_goal(Goal0, Es0, Es, Goal1),
+    (   prolog_load_context(module, M) -> true
+    ;   M = user
+    ),
+    phrase(subexpressions(Es0, M, Es), Goals),
+    Goals = [_|_],
+    reverse(Goals, Goals1),
+    foldl(conjunction, Goals1, Goal1, Goal).
+
+conjunction(G, Goal0, (G, Goal0)).
+
+user:term_expansion(Term, []) :-
+    nonvar(Term),
+    Term = (:- arithmetic_function(PI)),
+    (   prolog_load_context(module, M) -> true
+    ;   M = user
+    ),
+    arithmetic:arithmetic_function(M:PI).
+
+user:goal_expansion(X is Y, Goal) :-
+    arithmetic:expand_arithmetic_goal(X is Y, Goal).
+user:goal_expansion(X =:= Y, Goal) :-
+    arithmetic:expand_arithmetic_goal(X =:= Y, Goal).
+user:goal_expansion(X =\= Y, Goal) :-
+    arithmetic:expand_arithmetic_goal(X =\= Y, Goal).
+user:goal_expansion(X < Y, Goal) :-
+    arithmetic:expand_arithmetic_goal(X < Y, Goal).
+user:goal_expansion(X > Y, Goal) :-
+    arithmetic:expand_arithmetic_goal(X > Y, Goal).
+user:goal_expansion(X =< Y, Goal) :-
+    arithmetic:expand_arithmetic_goal(X =< Y, Goal).
+user:goal_expansion(X >= Y, Goal) :-
+    arithmetic:expand_arithmetic_goal(X >= Y, Goal).
@@ -0,0 +1,70 @@
+/** Readers of numeric array files.
+
+`npy_read/2` reads the `.npy` files of NumPy, as written by
+`numpy.save`, and `mm_read/2` the Matrix Market exchange format, as
+written by `scipy.io.mmwrite`, so that arrays computed in Python can
+be analysed in Prolog without converting them to CSV.
+
+Arrays are terms `array(Shape, Elements)`, where Shape is the list of
+the lengths of the dimensions of the array and Elements are the nested
+lists of its elements, as `tolist()` of NumPy yields them:
+
+```
+>>> numpy.save("a.npy", numpy.arange(6).reshape(2, 3))
+
+?- npy_read("a.npy", A).
+   A = array([2,3],[[0,1,2],[3,4,5]]).
+```
+
+An array of no dimensions is `array([], Element)`. Elements are
+integers, floats, `true` and `false` for booleans and `complex(Re, Im)`
+for complex numbers. Arrays of Fortran order are read in the same
+order as the others.
+
+Sparse matrices are terms `sparse([Rows, Columns], Entries)`, where
+Entries is the list of the elements that aren't zero, ordered by rows
+and then columns, as `entry(I, J, Value)` with indices from 1:
+
+```
+?- mm_read("m.mtx", M).
+   M = sparse([3,3],[entry(1,1,2.0),entry(2,3,-1.0)]).
+```
+*/
+
+:- module(array_formats, [npy_read/2,
+                          mm_read/2]).
+
+:- use_module(library(iso_ext)).
+
+%% npy_read(+File, -Array).
+%
+% Array is the array of the `.npy` file File. The elements of the
+% file must be booleans, integers, floats or complex numbers, of
+% versions 1 to 3 of the format. Throws `syntax_error(npy)` if File
+% isn't such a file.
+npy_read(File, Array) :-
+    array_read(File, npy, Array, npy_read/2).
+
+%% mm_read(+File, -Matrix).
+%
+% Matrix is the matrix of the Matrix Market file File, an array of two
+% dimensions if the file is of the `array` format and a sparse matrix
+% if it is of the `coordinate` format. The elements of the `pattern`
+% field are 1. The matrices that are symmetric, skew-symmetric or
+% hermitian have all their elements, not only those of their lower
+% triangles as in the file. Throws `syntax_error(matrix_market)` if
+% File isn't such a file.
+mm_read(File, Matrix) :-
+    array_read(File, matrix_market, Matrix, mm_read/2).
+
+array_read(File, Format, Array, Context) :-
+    setup_call_cleanup(open(File, read, Stream, [type(binary)]),
+                       stream_array(Stream, Format, Array0, Context),
+                       close(Stream)),
+    Array = Array0.
+
+stream_array(Stream, Format, Array, Context) :-
+    (   '$array_read'(Stream, Format, Array) ->
+        true
+    ;   throw(error(syntax_error(Format), Context))
+    ).
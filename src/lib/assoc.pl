@@ -178,24 +178,10 @@ get_assoc(Key, Assoc, Val) :-
     must_be(assoc, Assoc),
     get_assoc_(Key, Assoc, Val).
 
-/*
-:- if(current_predicate('$btree_find_node'/5)).
 get_assoc_(Key, Tree, Val) :-
     Tree \== t,
     '$btree_find_node'(Key, Tree, 0x010405, Node, =),
     arg(2, Node, Val).
-:- else.
-*/
-get_assoc_(Key, t(K,V,_,L,R), Val) :-
-    compare(Rel, Key, K),
-    get_assoc(Rel, Key, V, L, R, Val).
-
-get_assoc(=, _, Val, _, _, Val).
-get_assoc(<, Key, _, Tree, _, Val) :-
-    get_assoc(Key, Tree, Val).
-get_assoc(>, Key, _, _, Tree, Val) :-
-    get_assoc(Key, Tree, Val).
-% :- endif.
 
 
 %% get_assoc(+Key, +Assoc0, ?Val0, ?Assoc, ?Val) is semidet.
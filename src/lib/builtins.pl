@@ -128,8 +128,11 @@ call(_, _, _, _, _, _, _, _, _).
 %  * `max_arity`: The max arity a predicate can have in Prolog. On Scryer is set to 255. Read only.
 %  * `bounded`: `true` if integer arithmethic is bounded between some min/max values. On Scryer is always set
 %    to `false` since it supports unbounded integer arithmethic. Read only.
-%  * `integer_rounding_function`: Describes the rounding donde by `//` and `rem` functions. On Scryer is
-%    always set to `toward_zero`. Read only
+%  * `integer_rounding_function`: Describes the rounding done by the `//` and `rem` functions. Possible
+%    values are `toward_zero` (the default, `-7 // 2` is `-3` and `-7 rem 2` is `-1`) and `down` (`-7 // 2`
+%    is `-4` and `-7 rem 2` is `1`, as with `div` and `mod`), which ports code written for systems whose
+%    integer division rounds down. `X rem Y` is always `X - (X // Y) * Y`, and `div` and `mod` always
+%    round down. The flag applies to all arithmetic, including that of libraries. Read and write.
 %  * `double_quotes`: Determines how double quoted strings are read by Prolog. Scryer uses `chars` by default
 %    which is a list of one-character atoms. Other values are codes (list of integers representing characters),
 %    and atom which creates a whole atom for the string value. Read and write.
@@ -140,21 +143,88 @@ call(_, _, _, _, _, _, _, _, _).
 %  * `occurs_check`: Returns if the occurs check is enabled. The occurs check prevents the creation cyclic terms.
 %    Historically the Prolog unification algorithm didn't do that check so changing the value modifies how Prolog
 %    operates in the low-level. Possible values are `false`  (default), `true` (unification has this check
-%    enabled) and `error` which throws an exception when a cylic term is created. Read and write.
+%    enabled) and `error` which throws an exception when a cylic term is created. The flag applies to every
+%    unification of the machine: head unification, `(=)/2` and the arguments that built-in predicates unify
+%    with their results. Read and write.
 %  * `unknown`: How undefined predicates are handled when called. Possible values are `error` (the default, an error is thrown),
 %    `fail` (the call silently fails) and `warn` (the call fails and a warning about the undefined predicate is printed).
+%    The flag `Module:unknown` of a module overrides it for the calls made by the clauses of Module, e.g.
+%    `set_prolog_flag(ported:unknown, fail)` lets the code of module `ported` call predicates it's missing
+%    while other modules remain strict. Modules without their own value, and `user`, follow the `unknown` flag.
 %  * `answer_write_options`: Additional write options used by the top level for writing answers.
+%  * `answer_variable_names`: How the top level names the variables of answers that aren't variables
+%    of the query, which keep their names. Possible values are `fabricated` (the default, `_A`, `_B`,
+%    ...), `letters` (`A`, `B`, ..., as `numbervars/3` and `write_term/2` name them) and `internal`
+%    (`_` followed by a number, as `write/1` names them). Names of variables of the query are skipped.
+%  * `answer_duplicates`: Which answers the top level shows when enumerating the answers of a query.
+%    Possible values are `all` (the default, every answer), `consecutive` (answers that are variants of
+%    the previous answer are skipped) and `variant` (answers that are variants of any earlier answer of
+%    the query are skipped). The query goes on to its next answer in place of a skipped one, and its last
+%    answer, if skipped, is shown as `false`, the query having no further distinct answers.
+%  * `floundering`: How negations `\+ G` with a goal G that isn't ground when called are handled, which
+%    makes their failure depend on the order of goals. Possible values are `false` (the default, they aren't
+%    checked), `warning` (a warning naming G is printed) and `error` (an instantiation error is thrown). The
+%    negations in clause bodies are only checked if the flag isn't `false` when the clauses are loaded.
+%  * `error_locations`: If `true`, the context `Context` of an error `error(E, Context)` raised while
+%    running a clause consulted from a file becomes `location(Context, File, Line)`, where `File` and
+%    `Line` locate the innermost such clause with an activation, which a clause calling a goal last may
+%    not have. Possible values are `false` (the default) and `true`.
+%  * `prefer_rationals`: If `true`, arithmetic on integers and rationals evaluates to integers and
+%    rationals instead of floats. `(/)/2` and `(**)/2` with an integer exponent are exact, e.g. `1/3`
+%    evaluates to `1 rdiv 3` and `2**(-2)` to `1 rdiv 4`. `(**)/2` with a rational exponent, `sqrt/1`,
+%    `exp/1`, `expm1/1`, `log/1`, `log1p/1`, `sin/1`, `cos/1`, `tan/1`, `asin/1`, `acos/1`, `atan/1`
+%    and `atan2/2` evaluate to rationals of the significant digits of the `rational_precision` flag,
+%    rounded as the `rational_rounding` flag says, e.g. `sqrt(2)` to
+%    `707106781186547524400844362104849 rdiv 500000000000000000000000000000000`. Float arguments,
+%    `erf/1`, `erfc/1`, `lgamma/1` and the constants `pi` and `e` still evaluate to floats;
+%    `rational_round/3` of `library(arithmetic)` rounds results to a given number of decimal places.
+%    Possible values are `false` (the default) and `true`.
+%  * `rational_precision`: The significant digits of the rationals that elementary functions evaluate
+%    to under the `prefer_rationals` flag. Possible values are positive integers, 34 by default.
+%  * `rational_rounding`: How the rationals that elementary functions evaluate to under the
+%    `prefer_rationals` flag are rounded to the `rational_precision` flag's digits, and how
+%    `rational_round/3` rounds. Possible values are `half_even` (the default, ties are rounded to the
+%    even neighbour), `half_away_from_zero`, `toward_zero`, `away_from_zero`, `down` (toward negative
+%    infinity) and `up` (toward positive infinity).
+%  * `float_overflow`, `float_zero_div` and `float_undefined`: What float arithmetic gives for
+%    results too large for floats, for divisions of floats by zero and for undefined results
+%    such as that of `0.0/0.0`. Possible values are `error` (the default of each, an evaluation
+%    error is thrown, as ISO requires), and `infinity`, `infinity` and `nan` respectively,
+%    giving `1.0Inf`, `-1.0Inf` or `1.5NaN`, which are also the values of the evaluables `inf`
+%    and `nan`. Infinities and NaN are written and read in that syntax. They aren't finite, so
+%    arithmetic on them throws under the default values. As IEEE 754 requires, NaN isn't equal
+%    to any number, itself included, in arithmetic comparison, and `-0.0 =:= 0.0`. In the
+%    standard order of terms NaN precedes the other floats, all NaNs are identical, and `-0.0`
+%    precedes and isn't identical to `0.0`.
+%  * `term_hash_algorithm`: The hash function of `term_hash/2` and `term_hash/4` of `library(terms)`.
+%    Possible values are `fxhash` (the default, fast but not collision resistant), `sha3_256` and
+%    `blake2b512`.
+%  * `shuffle_seed`: If a non-negative integer, the answers of built-in predicates that enumerate
+%    them in an order the standard leaves unspecified, such as `current_op/3` and `current_predicate/1`,
+%    are shuffled by a pseudo-random generator seeded with it, to find code that accidentally relies
+%    on that order. The same seed shuffles the same answers alike, so a run can be repeated. Possible
+%    values are `false` (the default, answers aren't shuffled) and integers below 2^64.
 %
 current_prolog_flag(Flag, Value) :- Flag == max_arity, !, Value = 255.
 current_prolog_flag(max_arity, 255).
 current_prolog_flag(Flag, Value) :- Flag == bounded, !, Value = false.
 current_prolog_flag(bounded, false).
-current_prolog_flag(Flag, Value) :- Flag == integer_rounding_function, !, Value == toward_zero.
-current_prolog_flag(integer_rounding_function, toward_zero).
+current_prolog_flag(Flag, Value) :-
+    Flag == integer_rounding_function,
+    !,
+    '$get_integer_rounding_function'(Value).
+current_prolog_flag(integer_rounding_function, Value) :-
+    '$get_integer_rounding_function'(Value).
 current_prolog_flag(Flag, Value) :- Flag == double_quotes, !, '$get_double_quotes'(Value).
 current_prolog_flag(double_quotes, Value) :- '$get_double_quotes'(Value).
 current_prolog_flag(Flag, Value) :- Flag == unknown, !, '$get_unknown'(Value).
 current_prolog_flag(unknown, Value) :- '$get_unknown'(Value).
+current_prolog_flag(Flag, Value) :-
+    nonvar(Flag),
+    Flag = Module:unknown,
+    !,
+    module_flag_module(Module, current_prolog_flag/2),
+    '$get_module_unknown'(Module, Value).
 current_prolog_flag(Flag, _) :- Flag == max_integer, !, '$fail'.
 current_prolog_flag(Flag, _) :- Flag == min_integer, !, '$fail'.
 current_prolog_flag(Flag, OccursCheckEnabled) :-
@@ -169,6 +239,64 @@ current_prolog_flag(Flag, Value) :-
     answer_write_options(Value).
 current_prolog_flag(answer_write_options, Value) :-
     answer_write_options(Value).
+current_prolog_flag(Flag, Value) :-
+    Flag == answer_variable_names,
+    !,
+    answer_variable_names(Value).
+current_prolog_flag(answer_variable_names, Value) :-
+    answer_variable_names(Value).
+current_prolog_flag(Flag, Value) :-
+    Flag == answer_duplicates,
+    !,
+    answer_duplicates(Value).
+current_prolog_flag(answer_duplicates, Value) :-
+    answer_duplicates(Value).
+current_prolog_flag(Flag, Value) :-
+    Flag == floundering,
+    !,
+    floundering(Value).
+current_prolog_flag(floundering, Value) :-
+    floundering(Value).
+current_prolog_flag(Flag, Value) :-
+    Flag == error_locations,
+    !,
+    '$get_error_locations'(Value).
+current_prolog_flag(error_locations, Value) :-
+    '$get_error_locations'(Value).
+current_prolog_flag(Flag, Value) :-
+    Flag == prefer_rationals,
+    !,
+    '$get_prefer_rationals'(Value).
+current_prolog_flag(prefer_rationals, Value) :-
+    '$get_prefer_rationals'(Value).
+current_prolog_flag(Flag, Value) :-
+    atom(Flag),
+    rational_flag(Flag),
+    !,
+    '$get_rational_flag'(Flag, Value).
+current_prolog_flag(Flag, Value) :-
+    rational_flag(Flag),
+    '$get_rational_flag'(Flag, Value).
+current_prolog_flag(Flag, Value) :-
+    atom(Flag),
+    float_flag(Flag, _),
+    !,
+    '$get_float_flag'(Flag, Value).
+current_prolog_flag(Flag, Value) :-
+    float_flag(Flag, _),
+    '$get_float_flag'(Flag, Value).
+current_prolog_flag(Flag, Value) :-
+    Flag == term_hash_algorithm,
+    !,
+    term_hash_algorithm(Value).
+current_prolog_flag(term_hash_algorithm, Value) :-
+    term_hash_algorithm(Value).
+current_prolog_flag(Flag, Value) :-
+    Flag == shuffle_seed,
+    !,
+    shuffle_seed(Value).
+current_prolog_flag(shuffle_seed, Value) :-
+    shuffle_seed(Value).
 current_prolog_flag(Flag, _) :-
     atom(Flag),
     throw(error(domain_error(prolog_flag, Flag), current_prolog_flag/2)). % 8.17.2.3 b
@@ -181,6 +309,39 @@ answer_write_options(Value) :-
     ;   Value = []
     ).
 
+answer_variable_names(Value) :-
+    (   iso_ext:bb_get('$answer_variable_names', Value) -> true
+    ;   Value = fabricated
+    ).
+
+answer_duplicates(Value) :-
+    (   iso_ext:bb_get('$answer_duplicates', Value) -> true
+    ;   Value = all
+    ).
+
+floundering(Value) :-
+    (   '$fetch_global_var'('$floundering', Value0) -> Value = Value0
+    ;   Value = false
+    ).
+
+term_hash_algorithm(Value) :-
+    (   '$fetch_global_var'('$term_hash_algorithm', Value0) -> Value = Value0
+    ;   Value = fxhash
+    ).
+
+shuffle_seed(Value) :-
+    (   '$fetch_global_var'('$shuffle_seed', Value0) -> Value = Value0
+    ;   Value = false
+    ).
+
+rational_flag(rational_precision).
+rational_flag(rational_rounding).
+
+% the float flags, and their values other than error.
+float_flag(float_overflow, infinity).
+float_flag(float_zero_div, infinity).
+float_flag(float_undefined, nan).
+
 %% set_prolog_flag(Flag, Value).
 %
 % Sets the internal value of the flag. To see the list of flags supported by Scryer Prolog,
@@ -198,10 +359,12 @@ set_prolog_flag(max_integer, Value) :-
 set_prolog_flag(min_integer, Value) :- integer(Value), !, '$fail'. % 7.11.1.3
 set_prolog_flag(min_integer, Value) :-
     throw(error(domain_error(flag_value, min_integer + Value), set_prolog_flag/2)). % 8.17.1.3 e
-set_prolog_flag(integer_rounding_function, down) :- !. % 7.11.1.4
+set_prolog_flag(integer_rounding_function, toward_zero) :-
+    !, '$set_integer_rounding_function'(toward_zero). % 7.11.1.4
+set_prolog_flag(integer_rounding_function, down) :-
+    !, '$set_integer_rounding_function'(down).
 set_prolog_flag(integer_rounding_function, Value) :-
-    throw(error(domain_error(flag_value, integer_rounding_function + Value),
-                set_prolog_flag/2)). % 8.17.1.3 e
+    flag_domain_error(integer_rounding_function, Value).
 set_prolog_flag(double_quotes, chars) :-
     !, '$set_double_quotes'(chars). % 7.11.2.5, list of one-char atoms.
 set_prolog_flag(double_quotes, atom) :-
@@ -214,6 +377,13 @@ set_prolog_flag(unknown, warning) :-
     !, '$set_unknown'(warning).
 set_prolog_flag(unknown, fail) :-
     !, '$set_unknown'(fail).
+set_prolog_flag(Module:unknown, Value) :-
+    !,
+    module_flag_module(Module, set_prolog_flag/2),
+    (   lists:member(Value, [error, warning, fail]) ->
+        '$set_module_unknown'(Module, Value)
+    ;   flag_domain_error(unknown, Value)
+    ).
 set_prolog_flag(occurs_check, true) :-
     !, '$set_sto_as_unify'.
 set_prolog_flag(occurs_check, false) :-
@@ -230,12 +400,89 @@ set_prolog_flag(answer_write_options, Options) :-
           error(type_error(_,_), _),               % ... to catch type and domain errors.
           flag_domain_error(answer_write_options, Options)),
     iso_ext:bb_put('$answer_write_options', Options).
+set_prolog_flag(answer_variable_names, Value) :-
+    !,
+    (   lists:member(Value, [fabricated, letters, internal]) ->
+        iso_ext:bb_put('$answer_variable_names', Value)
+    ;   flag_domain_error(answer_variable_names, Value)
+    ).
+set_prolog_flag(answer_duplicates, Value) :-
+    !,
+    (   lists:member(Value, [all, consecutive, variant]) ->
+        iso_ext:bb_put('$answer_duplicates', Value)
+    ;   flag_domain_error(answer_duplicates, Value)
+    ).
+set_prolog_flag(floundering, Value) :-
+    !,
+    (   lists:member(Value, [false, warning, error]) ->
+        '$store_global_var'('$floundering', Value)
+    ;   flag_domain_error(floundering, Value)
+    ).
+set_prolog_flag(error_locations, true) :-
+    !, '$set_error_locations'(true).
+set_prolog_flag(error_locations, false) :-
+    !, '$set_error_locations'(false).
+set_prolog_flag(error_locations, Value) :-
+    flag_domain_error(error_locations, Value).
+set_prolog_flag(prefer_rationals, true) :-
+    !, '$set_prefer_rationals'(true).
+set_prolog_flag(prefer_rationals, false) :-
+    !, '$set_prefer_rationals'(false).
+set_prolog_flag(prefer_rationals, Value) :-
+    flag_domain_error(prefer_rationals, Value).
+set_prolog_flag(rational_precision, Value) :-
+    !,
+    (   integer(Value),
+        Value > 0,
+        '$set_rational_flag'(rational_precision, Value) ->
+        true
+    ;   flag_domain_error(rational_precision, Value)
+    ).
+set_prolog_flag(rational_rounding, Value) :-
+    !,
+    (   lists:member(Value, [half_even, half_away_from_zero, toward_zero,
+                             away_from_zero, down, up]) ->
+        '$set_rational_flag'(rational_rounding, Value)
+    ;   flag_domain_error(rational_rounding, Value)
+    ).
+set_prolog_flag(Flag, Value) :-
+    float_flag(Flag, NonError),
+    !,
+    (   lists:member(Value, [error, NonError]) ->
+        '$set_float_flag'(Flag, Value)
+    ;   flag_domain_error(Flag, Value)
+    ).
+set_prolog_flag(term_hash_algorithm, Value) :-
+    !,
+    (   lists:member(Value, [fxhash, sha3_256, blake2b512]) ->
+        '$store_global_var'('$term_hash_algorithm', Value)
+    ;   flag_domain_error(term_hash_algorithm, Value)
+    ).
+set_prolog_flag(shuffle_seed, Value) :-
+    !,
+    (   (   Value == false
+        ;   integer(Value),
+            Value >= 0,
+            Value < 2^64
+        ) ->
+        '$set_shuffle_seed'(Value),
+        '$store_global_var'('$shuffle_seed', Value)
+    ;   flag_domain_error(shuffle_seed, Value)
+    ).
 set_prolog_flag(Flag, _) :-
     atom(Flag),
     throw(error(domain_error(prolog_flag, Flag), set_prolog_flag/2)). % 8.17.1.3 d
 set_prolog_flag(Flag, _) :-
     throw(error(type_error(atom, Flag), set_prolog_flag/2)). % 8.17.1.3 c
 
+module_flag_module(Module, PI) :-
+    (   var(Module) ->
+        throw(error(instantiation_error, PI))
+    ;   atom(Module) ->
+        true
+    ;   throw(error(type_error(atom, Module), PI))
+    ).
+
 flag_domain_error(Flag, Value) :-
     % domain error via 8.17.1.3 e: Value is inappropriate for Flag
     throw(error(domain_error(flag_value, Flag + Value), set_prolog_flag/2)).
@@ -253,9 +500,41 @@ fail :- '$fail'.
 %% \+(Goal)
 %
 % True iff Goal fails
-\+ G :- call(G), !, '$fail'.
+\+ G :- audit_negation(G), call(G), !, '$fail'.
 \+ _.
 
+% checks that the goal of a negation is ground, as set by the
+% floundering flag. the loader calls it before the negations in clause
+% bodies if the flag is set.
+audit_negation(G) :-
+    (   ground(G) ->
+        true
+    ;   floundering(Value),
+        (   Value == error ->
+            throw(error(instantiation_error, (\+)/1))
+        ;   Value == warning ->
+            term_variables(G, Vars),
+            negation_variable_names(Vars, 0, VarNames),
+            write('% Warning: \\+ called on a non-ground goal: '),
+            write_term(G, [quoted(true), variable_names(VarNames)]),
+            nl
+        ;   true
+        )
+    ).
+
+negation_variable_names([], _, []).
+negation_variable_names([Var|Vars], N, [Name=Var|VarNames]) :-
+    Code is 0'A + N mod 26,
+    char_code(Char, Code),
+    (   N < 26 ->
+        Name = Char
+    ;   Suffix is N // 26,
+        number_chars(Suffix, SuffixChars),
+        atom_chars(Name, [Char|SuffixChars])
+    ),
+    N1 is N + 1,
+    negation_variable_names(Vars, N1, VarNames).
+
 %% \=(?X, ?Y)
 %
 % True iff X and Y can't be unified
@@ -580,8 +859,8 @@ parse_options_list(Options, Selector, DefaultPairs, OptionValues, Stub) :-
 
 
 parse_write_options(Options, OptionValues, Stub) :-
-    DefaultOptions = [double_quotes-false, ignore_ops-false, max_depth-0, numbervars-false,
-                      quoted-false, variable_names-[]],
+    DefaultOptions = [double_quotes-false, ignore_ops-false, max_depth-0, module-user,
+                      numbervars-false, quoted-false, variable_names-[]],
     parse_options_list(Options, builtins:parse_write_options_, DefaultOptions, OptionValues, Stub).
 
 
@@ -599,6 +878,13 @@ parse_write_options_(ignore_ops(IgnoreOps), ignore_ops-IgnoreOps) :-
        !
     ;  throw(error(domain_error(write_option, ignore_ops(IgnoreOps)), _))
     ).
+parse_write_options_(module(Module), module-Module) :-
+    (  var(Module) ->
+       throw(error(instantiation_error, _))
+    ;  atom(Module),
+       !
+    ;  throw(error(domain_error(write_option, module(Module)), _))
+    ).
 parse_write_options_(quoted(Quoted), quoted-Quoted) :-
     (  var(Quoted) ->
        throw(error(instantiation_error, _))
@@ -671,9 +957,10 @@ write_term(Term, Options) :-
 %  * `quoted(+Boolean)` if true, strings and atoms that need quotes to be valid Prolog syntax, are quoted. Default is false.
 %  * `variable_names(+List)` assign names to variables in term. List should be a list of terms of format `Name=Var`.
 %  * `double_quotes(+Boolean)` if true, strings are printed in double quotes rather than with list notation. Default is false.
+%  * `module(+Module)` writes operators as declared in Module, whose own operators shadow those of `user`. Default is `user`.
 write_term(Stream, Term, Options) :-
-    parse_write_options(Options, [DoubleQuotes, IgnoreOps, MaxDepth, NumberVars, Quoted, VNNames], write_term/3),
-    '$write_term'(Stream, Term, IgnoreOps, NumberVars, Quoted, VNNames, MaxDepth, DoubleQuotes).
+    parse_write_options(Options, [DoubleQuotes, IgnoreOps, MaxDepth, Module, NumberVars, Quoted, VNNames], write_term/3),
+    '$write_term'(Stream, Term, IgnoreOps, NumberVars, Quoted, VNNames, MaxDepth, DoubleQuotes, Module).
 
 
 %% write(+Term).
@@ -681,26 +968,26 @@ write_term(Stream, Term, Options) :-
 % Write Term to the current output stream using a syntax similar to Prolog
 write(Term) :-
     current_output(Stream),
-    '$write_term'(Stream, Term, false, true, false, [], 0, false).
+    '$write_term'(Stream, Term, false, true, false, [], 0, false, user).
 
 %% write(+Stream, +Term).
 %
 % Write Term to the stream Stream using a syntax similar to Prolog
 write(Stream, Term) :-
-    '$write_term'(Stream, Term, false, true, false, [], 0, false).
+    '$write_term'(Stream, Term, false, true, false, [], 0, false, user).
 
 %% write_canonical(+Term).
 %
 % Write Term to the current output stream using canonical Prolog syntax. Can be read back as Prolog terms.
 write_canonical(Term) :-
     current_output(Stream),
-    '$write_term'(Stream, Term, true, false, true, [], 0, false).
+    '$write_term'(Stream, Term, true, false, true, [], 0, false, user).
 
 %% write_canonical(+Stream, +Term).
 %
 % Write Term to the stream Stream using canonical Prolog syntax. Can be read back as Prolog terms.
 write_canonical(Stream, Term) :-
-    '$write_term'(Stream, Term, true, false, true, [], 0, false).
+    '$write_term'(Stream, Term, true, false, true, [], 0, false, user).
 
 %% writeq(+Term).
 %
@@ -708,14 +995,14 @@ write_canonical(Stream, Term) :-
 % quoted according to Prolog syntax.
 writeq(Term) :-
     current_output(Stream),
-    '$write_term'(Stream, Term, false, true, true, [], 0, false).
+    '$write_term'(Stream, Term, false, true, true, [], 0, false, user).
 
 %% writeq(+Stream, +Term).
 %
 % Write Term to the stream Stream using a syntax similar to `write/1` but quoting the atoms that need to be
 % quoted according to Prolog syntax.
 writeq(Stream, Term) :-
-    '$write_term'(Stream, Term, false, true, true, [], 0, false).
+    '$write_term'(Stream, Term, false, true, true, [], 0, false, user).
 
 select_rightmost_options([Option-Value | OptionPairs], OptionValues) :-
     (  pairs:same_key(Option, OptionPairs, OtherValues, _),
@@ -728,11 +1015,18 @@ select_rightmost_options([Option-Value | OptionPairs], OptionValues) :-
 select_rightmost_options([], []).
 
 
-parse_read_term_options(Options, OptionValues, Stub) :-
-    DefaultOptions = [singletons-_, variables-_, variable_names-_],
+parse_read_term_options(Options, Module, OptionValues, Stub) :-
+    DefaultOptions = [module-Module, singletons-_, variables-_, variable_names-_],
     parse_options_list(Options, builtins:parse_read_term_options_, DefaultOptions, OptionValues, Stub).
 
 
+parse_read_term_options_(module(Module), module-Module) :-
+    (  var(Module) ->
+       throw(error(instantiation_error, _))
+    ;  atom(Module),
+       !
+    ;  throw(error(domain_error(read_option, module(Module)), _))
+    ).
 parse_read_term_options_(singletons(Vars), singletons-Vars) :- !.
 parse_read_term_options_(variables(Vars), variables-Vars) :- !.
 parse_read_term_options_(variable_names(Vars), variable_names-Vars) :- !.
@@ -746,13 +1040,20 @@ parse_read_term_options_(E,_) :-
 %  * `variables(-Vars)` unifies Vars with a list of variables in the term. Similar to do `term_variables/2` with the new term.
 %  * `variable_names(-Vars)` unifies Vars with a list `Name=Var` with Name describing the variable name and Var the variable itself that appears in Term.
 %  * `singletons` similar to `variable_names` but only reports variables occurring only once in Term.
-read_term(Stream, Term, Options) :-
-    parse_read_term_options(Options, [Singletons, VariableNames, Variables], read_term/3),
-    '$read_term'(Stream, Term, Singletons, Variables, VariableNames).
+%  * `module(+Module)` reads operators as declared in Module, whose own operators shadow those of `user`.
+%    Defaults to the module calling `read_term/3`.
+:- meta_predicate(read_term(?, ?, :)).
+
+read_term(Stream, Term, Options0) :-
+    loader:strip_module(Options0, Module0, Options),
+    parse_read_term_options(Options, Module0, [Module, Singletons, VariableNames, Variables], read_term/3),
+    '$read_term'(Stream, Term, Singletons, Variables, VariableNames, Module).
 
 %% read_term(-Term, +Options).
 %
 % Read Term from the current input stream. It supports several options described in more detail in `read_term/3`.
+:- meta_predicate(read_term(?, :)).
+
 read_term(Term, Options) :-
     current_input(Stream),
     read_term(Stream, Term, Options).
@@ -763,14 +1064,14 @@ read_term(Term, Options) :-
 % to read input from a file or the user. Use other predicates like `phrase_from_file/2` for that.
 read(Term) :-
     current_input(Stream),
-    read_term(Stream, Term, []).
+    read_term(Stream, Term, user:[]).
     % read(Stream, Term).
 
 %% read(+Stream, -Term).
 %
 % Same as read_term/3 with all default options.
 read(Stream, Term) :-
-    read_term(Stream, Term, []).
+    read_term(Stream, Term, user:[]).
 
 % ensures List is either a variable or a list.
 can_be_list(List, _)  :-
@@ -959,33 +1260,10 @@ set_difference(Xs, [], Xs).
 % variant/2 checks whether X is a variant of Y per the definition in
 % 7.1.6.1 of the ISO standard.
 
-:- non_counted_backtracking variant/4.
-
-variant(X,Y,VPs,VPs0) :-
-    (  var(X) ->
-       var(Y),
-       VPs = [X-Y|VPs0]
-    ;  var(Y) ->
-       false
-    ;  X =.. [FX | XArgs],
-       Y =.. [FX | YArgs],
-       lists:foldl('$call'(builtins:variant), XArgs, YArgs, VPs, VPs0)
-    ).
-
 :- non_counted_backtracking variant/2.
 
-singleton([_]).
-
 variant(X, Y) :-
-    variant(X,Y, VPs, []),
-    keysort(VPs, SVPs),
-    pairs:group_pairs_by_key(SVPs, SVPKs),
-    pairs:pairs_values(SVPKs, Vals),
-    lists:maplist('$call'(builtins:term_variables), Vals, Vs),
-    lists:maplist('$call'(builtins:singleton), Vs),
-    term_variables(Vs, YVars),
-    lists:length(SVPKs, N),
-    lists:length(YVars, N).
+    '$term_variant'(X, Y).
 
 
 :- non_counted_backtracking group_by_variant/4.
@@ -1180,9 +1458,11 @@ asserta(Clause0) :-
 
 asserta_(Module, (Head :- Body)) :-
     !,
-    '$asserta'(Module, Head, Body).
+    '$asserta'(Module, Head, Body),
+    record_provenance(Module, Head, first).
 asserta_(Module, Fact) :-
-    '$asserta'(Module, Fact, true).
+    '$asserta'(Module, Fact, true),
+    record_provenance(Module, Fact, first).
 
 :- meta_predicate(assertz(:)).
 
@@ -1196,9 +1476,29 @@ assertz(Clause0) :-
 
 assertz_(Module, (Head :- Body)) :-
     !,
-    '$assertz'(Module, Head, Body).
+    '$assertz'(Module, Head, Body),
+    record_provenance(Module, Head, last).
 assertz_(Module, Fact) :-
-    '$assertz'(Module, Fact, true).
+    '$assertz'(Module, Fact, true),
+    record_provenance(Module, Fact, last).
+
+% records the provenance of the clause of Head just asserted, if it
+% was asserted under provenance_call/2 of library(provenance).
+record_provenance(Module, Head, Which) :-
+    (  iso_ext:bb_get('$provenance_context', context(Tag, Goal)),
+       '$asserted_clause_loc'(Module, Head, Which, L) ->
+       provenance:record_provenance(L, Tag, Goal)
+    ;  true
+    ).
+
+% forgets the provenance of the retracted clause at L, if
+% library(provenance) was ever used to record provenance.
+forget_provenance(L, Module) :-
+    (  Module \== provenance,
+       iso_ext:bb_get('$provenance_context', _) ->
+       provenance:forget_provenance(L)
+    ;  true
+    ).
 
 
 :- meta_predicate(retract(:)).
@@ -1220,7 +1520,8 @@ retract(Clause0) :-
 retract_clauses([L-P | Ps], Head, Body, Name, Arity, Module) :-
     '$invoke_clause_at_p'(Head, Body, L, P, N, Module),
     (  integer(N) ->
-       '$retract_clause'(Name, Arity, N, Module)
+       '$retract_clause'(Name, Arity, N, Module),
+       forget_provenance(L, Module)
     ;  true % the clause at index N has already been retracted in this
             % case but unify (Head :- Body) anyway.
     ),
@@ -1241,8 +1542,23 @@ call_retract_helper(Head, Body, P, Module) :-
     '$get_clause_p'(Head, P, Module),
     true.
 
+% the candidate clauses of Head whose clause-clauses unify with
+% '$clause'(Head, Body).
+matching_clauses([], _, _, _, []).
+matching_clauses([L-P | Cs], Head, Body, Module, Ps) :-
+    (  \+ \+ '$invoke_clause_at_p'(Head, Body, L, P, _, Module) ->
+       Ps = [L-P | Ps0]
+    ;  Ps = Ps0
+    ),
+    matching_clauses(Cs, Head, Body, Module, Ps0).
+
 call_retract(Head, Body, Name, Arity, Module) :-
-    findall(P, builtins:call_retract_helper(Head, Body, P, Module), Ps),
+    (  '$clause_candidates'(Head, Module, Cs) ->
+       % the index of the predicate rules out some of its clauses,
+       % so only the others are tried.
+       matching_clauses(Cs, Head, Body, Module, Ps)
+    ;  findall(P, builtins:call_retract_helper(Head, Body, P, Module), Ps)
+    ),
     retract_clauses(Ps, Head, Body, Name, Arity, Module).
 
 retract_clause(Head, Body) :-
@@ -1365,7 +1681,8 @@ abolish(Pred) :-
 % It can be used to check for existence of a predicate or to enumerate all loaded predicates
 current_predicate(Pred) :-
     (  var(Pred) ->
-       '$get_db_refs'(_, _, _, PIs),
+       '$get_db_refs'(_, _, _, PIs0),
+       '$shuffle_unspecified'(PIs0, PIs),
        lists:member(Pred, PIs)
     ;  loader:strip_module(Pred, Module, UnqualifiedPred),
        UnqualifiedPred = Name/Arity ->
@@ -1377,7 +1694,8 @@ current_predicate(Pred) :-
        ;  nonvar(Name),
           nonvar(Arity) ->
           '$lookup_db_ref'(Module, Name, Arity)
-       ;  '$get_db_refs'(Module, Name, Arity, PIs),
+       ;  '$get_db_refs'(Module, Name, Arity, PIs0),
+          '$shuffle_unspecified'(PIs0, PIs),
           lists:member(UnqualifiedPred, PIs)
        )
     ;  throw(error(type_error(predicate_indicator, Pred), current_predicate/1))
@@ -1394,11 +1712,19 @@ can_be_op_specifier(Spec) :- op_specifier(Spec).
 %
 % True iff there's an operator defined with name Op, with spec Spec and priority Priority.
 % Can be used to find all operators currently defined.
-current_op(Priority, Spec, Op) :-
+%
+% The operators are those in effect in the calling module, or in
+% Module if Op is qualified as `Module:Op`: the operators declared in
+% or imported into the module shadow those of `user`.
+:- meta_predicate(current_op(?, ?, :)).
+
+current_op(Priority, Spec, Op0) :-
+    loader:strip_module(Op0, Module, Op),
     (  can_be_op_priority(Priority),
        can_be_op_specifier(Spec),
        error:can_be(atom, Op) ->
-       '$get_next_op_db_ref'(Priority, Spec, Op, ListOfOps),
+       '$get_next_op_db_ref'(Priority, Spec, Op, ListOfOps0, Module),
+       '$shuffle_unspecified'(ListOfOps0, ListOfOps),
        lists:member(op(Priority, Spec, Op), ListOfOps)
     ).
 
@@ -1443,8 +1769,8 @@ valid_op(Op) :-
     ).
 
 
-op_(Priority, OpSpec, Op) :-
-    '$op'(Priority, OpSpec, Op).
+op_(Priority, OpSpec, Module, Op) :-
+    '$op'(Priority, OpSpec, Op, Module).
 
 
 %% op(Priority, Spec, Op)
@@ -1453,24 +1779,38 @@ op_(Priority, OpSpec, Op) :-
 % The priority is an integer between 0 (null) and 1200.
 % Spec can be: `xf`, `yf`, `xfx`, `xfy`, `yfx`, `fy` and `fx` where f indicates the position of the
 % operator and x and y the arguments.
-op(Priority, OpSpec, Op) :-
+%
+% The operator is declared globally, as by a directive of a `user`
+% file, unless Op is qualified as `Module:Op`: it is then declared in
+% that module only, where it shadows any operator of the same name and
+% class of `user`, as by a directive of the file of the module.
+op(Priority, OpSpec, Op0) :-
+    '$strip_module'(Op0, MQ, Op),
+    (  MQ = specified(Module) ->
+       true
+    ;  Module = user
+    ),
     (  var(Priority) ->
        throw(error(instantiation_error, op/3)) % 8.14.3.3 a)
     ;  var(OpSpec)   ->
        throw(error(instantiation_error, op/3)) % 8.14.3.3 b)
     ;  var(Op)       ->
        throw(error(instantiation_error, op/3)) % 8.14.3.3 c)
+    ;  var(Module)   ->
+       throw(error(instantiation_error, op/3))
+    ;  \+ atom(Module) ->
+       throw(error(type_error(atom, Module), op/3))
     ;  Op == '|'     ->
        (  op_priority(Priority),
           op_specifier(OpSpec),
           lists:member(OpSpec, [xfx, xfy, yfx]),
           ( Priority >= 1001 ; Priority == 0 )
-       -> '$op'(Priority, OpSpec, Op)
+       -> '$op'(Priority, OpSpec, Op, Module)
        ;  throw(error(permission_error(create, operator, (|)), op/3))) % www.complang.tuwien.ac.at/ulrich/iso-prolog/conformity_testing#72
     ;  valid_op(Op), op_priority(Priority), op_specifier(OpSpec) ->
-       '$op'(Priority, OpSpec, Op)
+       '$op'(Priority, OpSpec, Op, Module)
     ;  list_of_op_atoms(Op), op_priority(Priority), op_specifier(OpSpec) ->
-       lists:maplist(builtins:op_(Priority, OpSpec), Op),
+       lists:maplist(builtins:op_(Priority, OpSpec, Module), Op),
        !
     ;  throw(error(type_error(list, Op), op/3)) % 8.14.3.3 f)
     ).
@@ -1520,7 +1860,8 @@ atom_length(Atom, Length) :-
 %% atom_chars(?Atom, ?Chars).
 %
 % Relates an atom with a string in chars representation. It can be used to convert
-% between atoms and strings. Examples:
+% between atoms and strings. The chars of an atom are a packed string,
+% taking a byte per char rather than a cons cell. Examples:
 %
 % ```
 % ?- atom_chars(marseille, X).
@@ -1614,10 +1955,7 @@ atom_concat(Atom_1, Atom_2, Atom_12) :-
           lists:append(Atom_1_Chars, Atom_2_Chars, Atom_12_Chars),
           atom_chars(Atom_2, Atom_2_Chars)
        )
-    ;  atom_chars(Atom_1, Atom_1_Chars),
-       atom_chars(Atom_2, Atom_2_Chars),
-       lists:append(Atom_1_Chars, Atom_2_Chars, Atom_12_Chars),
-       atom_chars(Atom_12, Atom_12_Chars)
+    ;  '$atom_concat'(Atom_1, Atom_2, Atom_12)
     ).
 
 %% sub_atom(+Atom, ?Before, ?Length, ?After, ?SubAtom).
@@ -1647,6 +1985,21 @@ sub_atom(Atom, Before, Length, After, Sub_atom) :-
        throw(error(domain_error(not_less_than_zero, Length), sub_atom/5))
     ;  integer(After), After < 0 ->
        throw(error(domain_error(not_less_than_zero, After), sub_atom/5))
+    ;  integer(Before), integer(Length) ->
+       '$sub_atom'(Atom, Before, Length, Sub_atom0),
+       '$atom_length'(Atom, AtomLength),
+       After is AtomLength - Before - Length,
+       Sub_atom = Sub_atom0
+    ;  integer(Length), integer(After) ->
+       '$atom_length'(Atom, AtomLength),
+       Before is AtomLength - Length - After,
+       Before >= 0,
+       '$sub_atom'(Atom, Before, Length, Sub_atom)
+    ;  integer(Before), integer(After) ->
+       '$atom_length'(Atom, AtomLength),
+       Length is AtomLength - Before - After,
+       Length >= 0,
+       '$sub_atom'(Atom, Before, Length, Sub_atom)
     ;  atom_chars(Atom, AtomChars),
        lists:append(BeforeChars, LengthAndAfterChars, AtomChars),
        lists:append(LengthChars, AfterChars, LengthAndAfterChars),
@@ -1762,8 +2115,9 @@ codes_or_vars([C|Cs], PI) :-
 
 %% number_chars(?N, ?Chars).
 %
-% Relates a number and its representation as list of chars (string).
-% Throws an error if Chars is not the representation of a number.
+% Relates a number and its representation as list of chars (string),
+% which is packed as in `atom_chars/2`. Throws an error if Chars is not
+% the representation of a number.
 % Examples:
 %
 % ```
@@ -1945,6 +2299,10 @@ open(SourceSink, Mode, Stream) :-
 %  * `type(+Type)`: Type can be `text` or `binary`. Defines the type of the stream, if it's optimized for plain text
 %    or just binary
 %
+% File can also be `resource(Name)`, naming a resource file registered
+% by the application embedding the system with
+% `MachineBuilder::with_resource`. Resources are only opened for reading.
+%
 % Example:
 %
 % ```
@@ -1965,6 +2323,11 @@ open(SourceSink, Mode, Stream, StreamOptions) :-
        (   SourceSink = stream(S0) ->
            '$set_stream_options'(S0, Alias, EOFAction, Reposition, Type),
            Stream = S0
+       ;   SourceSink = resource(Name) ->
+           (  var(Name) ->
+              throw(error(instantiation_error, open/4))
+           ;  '$open_resource'(SourceSink, Mode, Stream, Alias, EOFAction, Reposition, Type)
+           )
        ;   (
                 atom(SourceSink) ->
                 atom_chars(SourceSink, SourceSinkString)
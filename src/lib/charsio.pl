@@ -212,16 +212,21 @@ read_from_chars(Chars, Term) :-
 %% read_term_from_chars(+Chars, ?Term, +Options).
 %
 % Like `read_from_chars`, except the reader is configured according to
-% `Options` which are those of `read_term`.
+% `Options` which are those of `read_term`. Chars may be a packed string
+% such as those of `atom_chars/2`, and the strings read as chars are
+% packed as well.
 %
 % ```
 % ?- read_term_from_chars("f(X,y).", T, [variable_names(['X'=X])]).
 %    T = f(X,y).
 % ```
-read_term_from_chars(Chars, Term, Options) :-
+:- meta_predicate(read_term_from_chars(?, ?, :)).
+
+read_term_from_chars(Chars, Term, Options0) :-
     must_be(chars, Chars),
-    builtins:parse_read_term_options(Options, [Singletons, VariableNames, Variables], read_term_from_chars/3),
-    '$read_term_from_chars'(Chars, Term0, Singletons, Variables, VariableNames),
+    loader:strip_module(Options0, Module0, Options),
+    builtins:parse_read_term_options(Options, Module0, [Module, Singletons, VariableNames, Variables], read_term_from_chars/3),
+    '$read_term_from_chars'(Chars, Term0, Singletons, Variables, VariableNames, Module),
     Term = Term0.
 
 %% write_term_to_chars(+Term, +Options, -Chars).
@@ -241,7 +246,7 @@ write_term_to_chars(_, Options, _) :-
     var(Options), instantiation_error(write_term_to_chars/3).
 write_term_to_chars(Term, Options, Chars) :-
     builtins:parse_write_options(Options,
-                                 [DoubleQuotes, IgnoreOps, MaxDepth, NumberVars, Quoted, VNNames],
+                                 [DoubleQuotes, IgnoreOps, MaxDepth, Module, NumberVars, Quoted, VNNames],
                                  write_term_to_chars/3),
     (  nonvar(Chars)  ->
        throw(error(uninstantiation_error(Chars), write_term_to_chars/3))
@@ -250,7 +255,7 @@ write_term_to_chars(Term, Options, Chars) :-
     ),
     term_variables(Term, Vars),
     extend_var_list(Vars, VNNames, NewVarNames, numbervars),
-    '$write_term_to_chars'(Chars, Term, IgnoreOps, NumberVars, Quoted, NewVarNames, MaxDepth, DoubleQuotes).
+    '$write_term_to_chars'(Chars, Term, IgnoreOps, NumberVars, Quoted, NewVarNames, MaxDepth, DoubleQuotes, Module).
 
 % Encodes Ch character to list of Bytes.
 char_utf8bytes(Ch, Bytes) :-
@@ -1049,7 +1049,7 @@ del_attrs(Var) :-
    Compute F(NA, NB).
 
    We use a DCG to thread through an implicit argument G0, an
-   association table F(IDA,IDB) -> Node, used for memoization.
+   association table key(F,IDA,IDB) -> Node, used for memoization.
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
 apply(F, NA, NB, Node) :-
@@ -1059,7 +1059,7 @@ apply(F, NA, NB, Node) :-
 apply(F, NA, NB, Node) -->
         (   { integer(NA), integer(NB) } -> { once(bool_op(F, NA, NB, Node)) }
         ;   { apply_shortcut(F, NA, NB, Node) } -> []
-        ;   { node_id(NA, IDA), node_id(NB, IDB), Key =.. [F,IDA,IDB] },
+        ;   { node_id(NA, IDA), node_id(NB, IDB), Key = key(F,IDA,IDB) },
             (   state(G0), { get_assoc(Key, G0, Node) } -> []
             ;   apply_(F, NA, NB, Node),
                 state(G0, G),
@@ -1736,10 +1736,6 @@ del_clpb(Var) :-
         del_attr(Var, clpb_hash),
         del_attr(Var, clpb_atom).
 
-b_setval(K, T) :- bb_b_put(K, T).
-nb_setval(K, T) :- bb_put(K, T).
-b_getval(K, T) :- bb_get(K, T).
-
 /* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
    Fuse formulas that share the same variables into single conjunctions.
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
@@ -0,0 +1,1140 @@
+/** Linear constraints over the rationals.
+
+Constraints are posted with `{}/1`, as conjunctions of linear
+equations and inequalities between arithmetic expressions:
+
+```
+?- {X + Y = 10, X - Y = 4}.
+   X = 7, Y = 3.
+?- {2*X + Y =< 16, X + 2*Y =< 11, X >= 0, Y >= 0}, sup(X + Y, Sup).
+   Sup = 9, clpq:{2*X+Y=<16}, clpq:{X+2*Y=<11}, clpq:{X>=0}, clpq:{Y>=0}.
+```
+
+Expressions are built from variables, integers and rationals (as of
+`rdiv/2`) with `(+)/2`, `(-)/2`, `(-)/1`, `(*)/2`, `(/)/2` and `(rdiv)/2`, where
+at least one factor of each product and the divisor of each division
+must be known. Floats are converted to rationals with
+`number_to_rational/2`. Constraints that aren't linear yet, such as
+`{X*Y = 6}`, are delayed until enough of their variables are known.
+The relations are `=`, `=:=`, `<`, `=<`, `>` and `>=`.
+
+Each posted constraint is checked against all the constraints it
+shares variables with, exactly: equations are solved by Gaussian
+elimination, and the satisfiability of the remaining inequalities,
+including strict ones, is decided with the simplex algorithm.
+Variables that the constraints confine to a single value are bound to
+it, as are variables that are equal to each other unified.
+
+The constraints can be queried with `inf/2` and `sup/2`, which are
+solved as linear programs over the constraints of the expression's
+variables, `minimize/1` and `maximize/1`, `entailed/1`, and
+`bb_inf/3`, which finds the infimum of an expression among the
+solutions in which some variables are integers by branch and bound.
+
+This library follows the interface of the CLP(Q) library of SICStus
+and SWI-Prolog, so that classic programs written for it run as they
+are. For example, the monthly payment MP of a mortgage of principal P
+over T months with the monthly interest rate I and the balance B:
+
+```
+mortgage(P, T, _, B, _) :-
+    { T = 0, B = P }.
+mortgage(P, T, I, B, MP) :-
+    { T >= 1, P1 = P*(1 + I) - MP, T1 = T - 1 },
+    mortgage(P1, T1, I, B, MP).
+
+?- mortgage(1000, 2, 1 rdiv 10, 0, MP).
+   MP = 12100 rdiv 21
+;  false.
+```
+*/
+
+:- module(clpq, [{}/1,
+                 entailed/1,
+                 inf/2,
+                 sup/2,
+                 minimize/1,
+                 maximize/1,
+                 bb_inf/3]).
+
+:- use_module(library(arithmetic), [number_to_rational/2]).
+:- use_module(library(atts)).
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(lists)).
+:- use_module(library(pairs)).
+:- use_module(library(when)).
+
+% The constraints of a variable are kept in the attribute
+% clpq(store(Cs, W)), which all the variables connected by constraints
+% share. Each element of Cs is c(Op, l(Constant, Terms)), the
+% constraint `Constant + Terms Op 0` with Op one of =, =< and <, and
+% Terms a list of Coefficient*Var with nonzero rational coefficients.
+% W is a solution of the inequalities of Cs, as pairs Var-Value, in
+% which all of them hold strictly, so that new constraints it also
+% satisfies need no simplex to check.
+:- attribute clpq/1.
+
+:- meta_predicate(include(1, ?, ?)).
+
+include(_, [], []).
+include(Goal, [L|Ls0], Ls) :-
+    (   call(Goal, L) ->
+        Ls = [L|Rest]
+    ;   Ls = Rest
+    ),
+    include(Goal, Ls0, Rest).
+
+:- meta_predicate(partition(1, ?, ?, ?)).
+
+partition(_, [], [], []).
+partition(Goal, [L|Ls0], Is, Es) :-
+    (   call(Goal, L) ->
+        Is = [L|Is1],
+        Es = Es1
+    ;   Is = Is1,
+        Es = [L|Es1]
+    ),
+    partition(Goal, Ls0, Is1, Es1).
+
+%% {+Constraints}
+%
+% Posts the conjunction of linear constraints Constraints.
+{Constraints} :-
+    phrase(constraints(Constraints), Parts),
+    partition_delayed(Parts, Cs, Delayed),
+    post(Cs),
+    maplist(call, Delayed).
+
+constraints(C) -->
+    (   { var(C) } ->
+        { instantiation_error({}/1) }
+    ;   { C = (A, B) } ->
+        constraints(A),
+        constraints(B)
+    ;   { constraint_op(C, Op, L, R) } ->
+        constraint(Op, L, R, C)
+    ;   { domain_error(clpq_constraint, C, {}/1) }
+    ).
+
+constraint_op(L = R, =, L, R).
+constraint_op(L =:= R, =, L, R).
+constraint_op(L =< R, =<, L, R).
+constraint_op(L >= R, =<, R, L).
+constraint_op(L < R, <, L, R).
+constraint_op(L > R, <, R, L).
+
+constraint(Op, L, R, C) -->
+    { catch(( linear(L, LinL),
+              linear(R, LinR),
+              lin_sub(LinL, LinR, Lin),
+              Delay = no
+            ),
+            clpq_nonlinear,
+            Delay = yes) },
+    (   { Delay == no } ->
+        [c(Op, Lin)]
+    ;   { term_variables(C, Vs),
+          foldl(bound_condition, Vs, false, Cond) },
+        [delayed(when(Cond, clpq:{C}))]
+    ).
+
+bound_condition(V, Cond0, Cond) :-
+    (   Cond0 == false ->
+        Cond = nonvar(V)
+    ;   Cond = (Cond0 ; nonvar(V))
+    ).
+
+partition_delayed([], [], []).
+partition_delayed([P|Ps], Cs, Ds) :-
+    (   P = delayed(D) ->
+        Ds = [D|Ds1],
+        partition_delayed(Ps, Cs, Ds1)
+    ;   Cs = [P|Cs1],
+        partition_delayed(Ps, Cs1, Ds)
+    ).
+
+% linear(+Expr, -Lin): Lin is the linear expression l(Constant, Terms)
+% of Expr. Throws clpq_nonlinear if Expr isn't linear, so that the
+% constraint is delayed until more of its variables are known.
+linear(E, Lin) :-
+    (   var(E) ->
+        Lin = l(0, [1*E])
+    ;   number(E) ->
+        rational_number(E, Q),
+        Lin = l(Q, [])
+    ;   linear_(E, Lin0) ->
+        Lin = Lin0
+    ;   ground(E) ->
+        (   callable(E) ->
+            functor(E, Name, Arity),
+            catch(V is E, error(type_error(evaluable, _), _),
+                  type_error(evaluable, Name/Arity, {}/1)),
+            rational_number(V, Q),
+            Lin = l(Q, [])
+        ;   type_error(evaluable, E, {}/1)
+        )
+    ;   throw(clpq_nonlinear)
+    ).
+
+linear_(A + B, Lin) :-
+    linear(A, LinA),
+    linear(B, LinB),
+    lin_add(LinA, LinB, Lin).
+linear_(A - B, Lin) :-
+    linear(A, LinA),
+    linear(B, LinB),
+    lin_sub(LinA, LinB, Lin).
+linear_(- A, Lin) :-
+    linear(A, LinA),
+    lin_scale(LinA, -1, Lin).
+linear_(+ A, Lin) :-
+    linear(A, Lin).
+linear_(A * B, Lin) :-
+    linear(A, LinA),
+    linear(B, LinB),
+    (   LinA = l(K, []) ->
+        lin_scale(LinB, K, Lin)
+    ;   LinB = l(K, []) ->
+        lin_scale(LinA, K, Lin)
+    ;   throw(clpq_nonlinear)
+    ).
+linear_(A / B, Lin) :-
+    linear_division(A, B, Lin).
+linear_(A rdiv B, Lin) :-
+    linear_division(A, B, Lin).
+
+linear_division(A, B, Lin) :-
+    linear(B, LinB),
+    (   LinB = l(K, []) ->
+        (   K =:= 0 ->
+            throw(error(evaluation_error(zero_divisor), {}/1))
+        ;   linear(A, LinA),
+            Inverse is 1 rdiv K,
+            lin_scale(LinA, Inverse, Lin)
+        )
+    ;   throw(clpq_nonlinear)
+    ).
+
+rational_number(N, Q) :-
+    (   float(N) ->
+        number_to_rational(N, Q)
+    ;   Q = N
+    ).
+
+%% Linear expressions.
+
+lin_add(l(C1, Ts1), l(C2, Ts2), Lin) :-
+    C is C1 + C2,
+    append(Ts1, Ts2, Ts),
+    lin_normalize(l(C, Ts), Lin).
+
+lin_sub(Lin1, Lin2, Lin) :-
+    lin_scale(Lin2, -1, Lin3),
+    lin_add(Lin1, Lin3, Lin).
+
+lin_scale(l(C0, Ts0), K, l(C, Ts)) :-
+    (   K =:= 0 ->
+        C = 0,
+        Ts = []
+    ;   C is C0 * K,
+        maplist(term_scale(K), Ts0, Ts)
+    ).
+
+term_scale(K, A0*X, A*X) :-
+    A is A0 * K.
+
+% lin_normalize(+Lin0, -Lin): Lin is Lin0 with the terms of variables
+% that have been bound since folded into the constant, the terms of
+% each variable merged into the first one, and zero terms removed.
+lin_normalize(l(C0, Ts0), l(C, Ts)) :-
+    foldl(add_term, Ts0, C0-[], C-Ts1),
+    reverse(Ts1, Ts2),
+    include(nonzero_term, Ts2, Ts).
+
+add_term(A*X, C0-Ts0, C-Ts) :-
+    (   var(X) ->
+        C = C0,
+        (   add_coefficient(Ts0, X, A, Ts1) ->
+            Ts = Ts1
+        ;   Ts = [A*X|Ts0]
+        )
+    ;   number(X) ->
+        rational_number(X, Q),
+        C is C0 + A * Q,
+        Ts = Ts0
+    ;   type_error(number, X, {}/1)
+    ).
+
+% add_coefficient(+Ts0, +X, +A, -Ts): Ts is Ts0 with A added to the
+% coefficient of X, which Ts0 has a term of.
+add_coefficient([T|Ts0], X, A, Ts) :-
+    T = B*Y,
+    (   Y == X ->
+        AB is A + B,
+        Ts = [AB*X|Ts0]
+    ;   Ts = [T|Ts1],
+        add_coefficient(Ts0, X, A, Ts1)
+    ).
+
+% select_term(+Ts0, +X, -A, -Ts): Ts0 has the term A*X, and Ts are its
+% other terms.
+select_term([T|Ts0], X, A, Ts) :-
+    T = B*Y,
+    (   Y == X ->
+        A = B,
+        Ts = Ts0
+    ;   Ts = [T|Ts1],
+        select_term(Ts0, X, A, Ts1)
+    ).
+
+nonzero_term(A*_) :-
+    A =\= 0.
+
+lin_variables(l(_, Ts), Vs) :-
+    maplist(term_variable, Ts, Vs).
+
+term_variable(_*X, X).
+
+% substitute(+X-LinX, +Lin0, -Lin): Lin is Lin0 with X replaced by LinX.
+substitute(X-LinX, Lin0, Lin) :-
+    Lin0 = l(C0, Ts0),
+    (   select_term(Ts0, X, A, Ts) ->
+        lin_scale(LinX, A, LinA),
+        lin_add(l(C0, Ts), LinA, Lin)
+    ;   Lin = Lin0
+    ).
+
+%% Posting and solving constraints.
+
+% posts the constraints Cs0 in the form of the attribute, together with
+% the constraints of all the variables they are connected with.
+post(Cs0) :-
+    term_variables(Cs0, Vs),
+    post(Vs, store(Cs0, [])).
+
+post(Vs, Store0) :-
+    foldl(variable_store, Vs, [Store0], Stores),
+    maplist(store_constraints, Stores, Css),
+    append(Css, Cs1),
+    maplist(store_witness, Stores, Ws0),
+    append(Ws0, W0),
+    term_variables(Cs1, Ws),
+    maplist(remove_store, Ws),
+    solve(Cs1, W0, Cs, W),
+    term_variables(Cs, Us),
+    maplist(put_store(store(Cs, W)), Us).
+
+variable_store(V, Stores0, Stores) :-
+    (   get_atts(V, +clpq(Store)),
+        \+ ( member(S, Stores0), S == Store ) ->
+        Stores = [Store|Stores0]
+    ;   Stores = Stores0
+    ).
+
+store_constraints(store(Cs, _), Cs).
+
+store_witness(store(_, W), W).
+
+remove_store(V) :-
+    put_atts(V, -clpq(_)).
+
+put_store(Store, V) :-
+    put_atts(V, +clpq(Store)).
+
+verify_attributes(Var, Value, Goals) :-
+    (   get_atts(Var, +clpq(Store)) ->
+        (   var(Value) ->
+            true
+        ;   number(Value) ->
+            true
+        ;   type_error(number, Value, {}/1)
+        ),
+        Goals = [clpq:reconsider(Store)]
+    ;   Goals = []
+    ).
+
+reconsider(Store) :-
+    store_constraints(Store, Cs),
+    term_variables(Cs, Vs),
+    post(Vs, Store).
+
+% solve(+Cs0, +W0, -Cs, -W): Cs are the constraints Cs0, with equations
+% solved for one of their variables each, which the other constraints
+% don't contain. Fails if Cs0 are unsatisfiable. Variables that
+% equations equate to constants or to other variables are bound to
+% them, and inequalities that only hold as equations are made
+% equations, before solving what remains again. W is a solution of the
+% inequalities of Cs in which all of them hold strictly, found from the
+% previous solution W0 if possible, and by the simplex algorithm
+% otherwise.
+solve(Cs0, W0, Cs, W) :-
+    maplist(normalize_constraint, Cs0, Cs1),
+    partition(is_equation, Cs1, Eqs, Ineqs0),
+    foldl(eliminate, Eqs, [], Solved),
+    maplist(substitute_solved(Solved), Ineqs0, Ineqs1),
+    partition(ground_constraint, Ineqs1, Grounds, Ineqs2),
+    maplist(holds, Grounds),
+    maplist(solved_constraint, Solved, SolvedCs),
+    solved_bindings(Solved, Bindings),
+    (   Bindings = [_|_] ->
+        maplist(bind, Bindings),
+        append(SolvedCs, Ineqs2, Cs2),
+        solve(Cs2, W0, Cs, W)
+    ;   witness(Ineqs2, W0, W1) ->
+        append(SolvedCs, Ineqs2, Cs),
+        W = W1
+    ;   interior(Ineqs2, Interior),
+        (   Interior = true(W) ->
+            append(SolvedCs, Ineqs2, Cs)
+        ;   Interior = boundary(Vs, Values),
+            partition(implicit_equality(Ineqs2, Vs, Values), Ineqs2, Implicit, Ineqs),
+            Implicit = [_|_],
+            maplist(equation, Implicit, ImplicitEqs),
+            append([SolvedCs, ImplicitEqs, Ineqs], Cs2),
+            solve(Cs2, W0, Cs, W)
+        )
+    ).
+
+normalize_constraint(c(Op, Lin0), c(Op, Lin)) :-
+    lin_normalize(Lin0, Lin).
+
+is_equation(c(=, _)).
+
+% eliminate(+Eq, +Solved0, -Solved): Solved are the solved equations
+% Solved0 and Eq, as pairs X-Lin for X = Lin.
+eliminate(c(=, Lin0), Solved0, Solved) :-
+    foldl(substitute, Solved0, Lin0, Lin),
+    (   Lin = l(C, []) ->
+        C =:= 0,
+        Solved = Solved0
+    ;   Lin = l(C, [A*X|Ts]),
+        K is -1 rdiv A,
+        lin_scale(l(C, Ts), K, LinX),
+        maplist(substitute_pair(X-LinX), Solved0, Solved1),
+        Solved = [X-LinX|Solved1]
+    ).
+
+substitute_pair(S, Y-Lin0, Y-Lin) :-
+    substitute(S, Lin0, Lin).
+
+substitute_solved(Solved, c(Op, Lin0), c(Op, Lin)) :-
+    foldl(substitute, Solved, Lin0, Lin).
+
+ground_constraint(c(_, l(_, []))).
+
+holds(c(Op, l(C, []))) :-
+    holds(Op, C).
+
+holds(=<, C) :- C =< 0.
+holds(<, C) :- C < 0.
+
+solved_constraint(X-l(C, Ts), c(=, l(C1, [1*X|Ts1]))) :-
+    lin_scale(l(C, Ts), -1, l(C1, Ts1)).
+
+% the bindings of solved equations to constants and other variables.
+solved_bindings(Solved, Bindings) :-
+    foldl(solved_binding, Solved, [], Bindings).
+
+solved_binding(X-Lin, Bs0, Bs) :-
+    (   Lin = l(C, []) ->
+        Bs = [X-C|Bs0]
+    ;   Lin = l(0, [1*Y]) ->
+        Bs = [X-Y|Bs0]
+    ;   Bs = Bs0
+    ).
+
+bind(X-V) :-
+    X = V.
+
+% Lin =< 0 is an implicit equality of the inequalities Ineqs if Lin is
+% 0 in all of their solutions, i.e. if the supremum of -Lin is 0. Only
+% the inequalities that are tight at the solution Values of the
+% variables Vs can be.
+implicit_equality(Ineqs, Vs, Values, c(=<, Lin)) :-
+    Lin = l(C, Ts),
+    foldl(term_value(Vs, Values), Ts, C, Value),
+    Value =:= 0,
+    lin_scale(Lin, -1, NegLin),
+    optimum(Ineqs, NegLin, optimal(Max)),
+    Max =:= 0.
+
+equation(c(_, Lin), c(=, Lin)).
+
+% witness(+Ineqs, +W0, -W): W is a solution of the inequalities Ineqs
+% in which all of them hold strictly, as pairs X-Value, if one is found
+% by keeping the values W0 of their variables. The other variables
+% take a value within the bounds of the inequalities in which they are
+% the only variable without one, in turn.
+witness(Ineqs, W0, W) :-
+    term_variables(Ineqs, Vs),
+    partition(witnessed(W0), Vs, Known, Unknown),
+    maplist(witness_pair(W0), Known, W1),
+    foldl(witness_value(Ineqs), Unknown, W1, W),
+    maplist(strictly_holds(W), Ineqs).
+
+witnessed(W, X) :-
+    witness_pair(W, X, _).
+
+witness_pair(W, X, X-V) :-
+    member(Y-V, W),
+    Y == X,
+    !.
+
+witness_value(Ineqs, X, W, [X-V|W]) :-
+    foldl(witness_bound(X, W), Ineqs, none-none, Lo-Hi),
+    (   Lo == none ->
+        (   Hi == none ->
+            V = 0
+        ;   V is Hi - 1
+        )
+    ;   Hi == none ->
+        V is Lo + 1
+    ;   Lo < Hi,
+        V is (Lo + Hi) rdiv 2
+    ).
+
+% A*X + S op 0, so that X < -S/A if A is positive and X > -S/A if it
+% is negative, for the strict solutions.
+witness_bound(X, W, c(_, l(C, Ts)), Lo0-Hi0, Lo-Hi) :-
+    (   foldl(bound_term(X, W), Ts, 0-C, A-S),
+        A =\= 0 ->
+        B is -S rdiv A,
+        (   A > 0 ->
+            Lo = Lo0,
+            (   Hi0 == none ->
+                Hi = B
+            ;   Hi is min(Hi0, B)
+            )
+        ;   Hi = Hi0,
+            (   Lo0 == none ->
+                Lo = B
+            ;   Lo is max(Lo0, B)
+            )
+        )
+    ;   Lo = Lo0,
+        Hi = Hi0
+    ).
+
+bound_term(X, W, B*Y, A0-S0, A-S) :-
+    (   Y == X ->
+        A = B,
+        S = S0
+    ;   witness_pair(W, Y, _-V) ->
+        A = A0,
+        S is S0 + B * V
+    ).
+
+strictly_holds(W, c(_, l(C, Ts))) :-
+    foldl(witness_term(W), Ts, C, Value),
+    Value < 0.
+
+witness_term(W, A*X, V0, V) :-
+    witness_pair(W, X, _-XV),
+    V is V0 + A * XV.
+
+%% The simplex algorithm.
+
+% The inequalities are turned into a linear program over nonnegative
+% columns, with each variable X the difference of the two columns
+% X+ and X-.
+
+% interior(+Ineqs, -Interior): Interior is true(W) if the inequalities
+% Ineqs have a solution W in which all of them hold strictly, and
+% boundary(Vs, Values) with a solution Values of their variables Vs
+% if they only have solutions in which some Lin =< 0 or Lin < 0 of
+% them is 0. Fails if they have no solutions even then.
+%
+% This is found by maximizing D, no greater than 1, subject to
+% Lin + D =< 0 for all the inequalities, with D a column of its own.
+% If D can't be positive, either some non-strict inequalities are
+% implicit equalities, or the strict inequalities can't hold.
+interior(Ineqs, Interior) :-
+    (   Ineqs == [] ->
+        Interior = true([])
+    ;   term_variables(Ineqs, Vs),
+        maplist(inequality_row(Vs, interior), Ineqs, Rows0),
+        length(Vs, N),
+        Width is 2 * N,
+        zeros(Width, Zeros),
+        append(Zeros, [1], DRow),
+        append(Zeros, [1], Objective),
+        lp_max([DRow-1|Rows0], Objective, optimal(D, Ys)),
+        length(Ys0, Width),
+        append(Ys0, [_], Ys),
+        column_values(Ys0, Values),
+        (   D > 0 ->
+            pairs_keys_values(W, Vs, Values),
+            Interior = true(W)
+        ;   Interior = boundary(Vs, Values)
+        )
+    ).
+
+inequality_row(Vs, Closure, c(_, l(C, Ts)), Coeffs-Rhs) :-
+    maplist(column_pair(Ts), Vs, Pairs),
+    append(Pairs, Coeffs0),
+    (   Closure == interior ->
+        append(Coeffs0, [1], Coeffs)
+    ;   Coeffs = Coeffs0
+    ),
+    Rhs is -C.
+
+column_pair(Ts, V, [A, NA]) :-
+    (   member(A0*W, Ts),
+        W == V ->
+        A = A0
+    ;   A = 0
+    ),
+    NA is -A.
+
+% optimum(+Ineqs, +Lin, -Result): Result is optimal(Max) with Max the
+% supremum of Lin subject to the inequalities Ineqs with their strict
+% inequalities relaxed, or unbounded.
+optimum(Ineqs, Lin, Result) :-
+    optimum(Ineqs, Lin, Result, _, _).
+
+optimum(Ineqs, l(C, Ts), Result, Vs, Values) :-
+    lin_variables(l(C, Ts), LinVs),
+    term_variables(Ineqs-LinVs, Vs),
+    maplist(inequality_row(Vs, closed), Ineqs, Rows),
+    maplist(column_pair(Ts), Vs, Pairs),
+    append(Pairs, Objective),
+    lp_max(Rows, Objective, Result0),
+    (   Result0 = optimal(Value, Ys) ->
+        Max is Value + C,
+        Result = optimal(Max),
+        column_values(Ys, Values)
+    ;   Result = Result0
+    ).
+
+column_values([], []).
+column_values([P, N|Ys], [V|Vs]) :-
+    V is P - N,
+    column_values(Ys, Vs).
+
+zeros(N, Zeros) :-
+    length(Zeros, N),
+    maplist(=(0), Zeros).
+
+% lp_max(+Rows, +Objective, -Result): Result is optimal(Max, Ys) with
+% Max the maximum of Objective*Ys at Ys subject to Coeffs*Ys =< Rhs for
+% every Coeffs-Rhs of Rows and Ys >= 0, unbounded or infeasible.
+%
+% The tableau t(Rows, D, V) holds a row r(Basic, Coeffs, Rhs) for each
+% row of the program, and the objective V + D*Ys in terms of the
+% nonbasic columns. Columns enter and leave the basis by Bland's rule,
+% so that the algorithm terminates. If a right-hand side is negative,
+% an auxiliary column T is subtracted from every row, and -T is
+% maximized first to find a feasible basis.
+lp_max(Rows0, Objective, Result) :-
+    length(Objective, N),
+    length(Rows0, M),
+    (   member(_-Rhs, Rows0),
+        Rhs < 0 ->
+        Aux = [-1]
+    ;   Aux = []
+    ),
+    foldl(tableau_row(N, M, Aux), Rows0, Rows1, 0, _),
+    (   Aux == [] ->
+        Rows = Rows1,
+        Feasible = true
+    ;   K is N + M,
+        zeros(K, Zeros),
+        append(Zeros, [-1], D0),
+        min_rhs_row(Rows1, R),
+        pivot(t(Rows1, D0, 0), R, K, T0),
+        simplex(T0, Result1),
+        (   Result1 = optimal(t(Rows2, _, V)),
+            V =:= 0 ->
+            drive_out(Rows2, K, Rows3),
+            maplist(drop_last_column, Rows3, Rows),
+            Feasible = true
+        ;   Feasible = false
+        )
+    ),
+    (   Feasible == true ->
+        zeros(M, Slacks),
+        append(Objective, Slacks, D1),
+        foldl(price_out, Rows, D1-0, D-V1),
+        simplex(t(Rows, D, V1), Result2),
+        (   Result2 = optimal(t(FinalRows, _, Max)) ->
+            column_indices(N, Columns),
+            maplist(column_value(FinalRows), Columns, Ys),
+            Result = optimal(Max, Ys)
+        ;   Result = Result2
+        )
+    ;   Result = infeasible
+    ).
+
+tableau_row(N, M, Aux, Coeffs0-Rhs, r(Basic, Coeffs, Rhs), I0, I) :-
+    Basic is N + I0,
+    Zeros is M - I0 - 1,
+    zeros(I0, Before),
+    zeros(Zeros, After),
+    append([Coeffs0, Before, [1|After], Aux], Coeffs),
+    I is I0 + 1.
+
+min_rhs_row(Rows, R) :-
+    foldl(min_rhs, Rows, 0-none-0, _-R-_).
+
+min_rhs(r(_, _, Rhs), I-R0-Min0, I1-R-Min) :-
+    I1 is I + 1,
+    (   Rhs < Min0 ->
+        R = I,
+        Min = Rhs
+    ;   R = R0,
+        Min = Min0
+    ).
+
+% drive_out(+Rows0, +K, -Rows): Rows are Rows0 without the auxiliary
+% column K in the basis, pivoting it out of its row, or removing the
+% row if it is zero otherwise.
+drive_out(Rows0, K, Rows) :-
+    (   nth0(R, Rows0, r(K, Coeffs, _)) ->
+        (   nth0(J, Coeffs, A),
+            J =\= K,
+            A =\= 0 ->
+            pivot(t(Rows0, [], 0), R, J, t(Rows, _, _))
+        ;   exclude_row(Rows0, K, Rows)
+        )
+    ;   Rows = Rows0
+    ).
+
+exclude_row([Row|Rows0], K, Rows) :-
+    (   Row = r(K, _, _) ->
+        Rows = Rows0
+    ;   Rows = [Row|Rows1],
+        exclude_row(Rows0, K, Rows1)
+    ).
+
+drop_last_column(r(B, Coeffs0, Rhs), r(B, Coeffs, Rhs)) :-
+    but_last(Coeffs0, Coeffs).
+
+but_last([X|Xs], Ys) :-
+    but_last_(Xs, X, Ys).
+
+but_last_([], _, []).
+but_last_([X|Xs], Y, [Y|Ys]) :-
+    but_last_(Xs, X, Ys).
+
+price_out(r(Basic, Coeffs, Rhs), D0-V0, D-V) :-
+    nth0(Basic, D0, DB),
+    (   DB =:= 0 ->
+        D = D0,
+        V = V0
+    ;   maplist(times_minus(DB), Coeffs, D0, D),
+        V is V0 + DB * Rhs
+    ).
+
+times_minus(F, P, X0, X) :-
+    X is X0 - F * P.
+
+simplex(T0, Result) :-
+    T0 = t(Rows, D, _),
+    (   nth0(J, D, DJ),
+        DJ > 0 ->
+        (   leaving_row(Rows, J, R) ->
+            pivot(T0, R, J, T1),
+            simplex(T1, Result)
+        ;   Result = unbounded
+        )
+    ;   Result = optimal(T0)
+    ).
+
+leaving_row(Rows, J, R) :-
+    foldl(ratio_row(J), Rows, 0-none, _-Best),
+    Best = R-_-_.
+
+ratio_row(J, r(Basic, Coeffs, Rhs), I-Best0, I1-Best) :-
+    I1 is I + 1,
+    nth0(J, Coeffs, A),
+    (   A > 0 ->
+        Ratio is Rhs rdiv A,
+        (   Best0 == none ->
+            Best = I-Ratio-Basic
+        ;   Best0 = _-Ratio0-Basic0,
+            (   Ratio < Ratio0
+            ;   Ratio =:= Ratio0,
+                Basic < Basic0
+            ) ->
+            Best = I-Ratio-Basic
+        ;   Best = Best0
+        )
+    ;   Best = Best0
+    ).
+
+pivot(t(Rows0, D0, V0), RI, J, t(Rows, D, V)) :-
+    nth0(RI, Rows0, r(_, Coeffs0, Rhs0)),
+    nth0(J, Coeffs0, A),
+    maplist(divide_by(A), Coeffs0, Coeffs),
+    Rhs is Rhs0 rdiv A,
+    Pivot = r(J, Coeffs, Rhs),
+    foldl(eliminate_row(Pivot, RI), Rows0, Rows, 0, _),
+    (   D0 == [] ->
+        D = [],
+        V = V0
+    ;   nth0(J, D0, DJ),
+        maplist(times_minus(DJ), Coeffs, D0, D),
+        V is V0 + DJ * Rhs
+    ).
+
+divide_by(A, X0, X) :-
+    X is X0 rdiv A.
+
+eliminate_row(Pivot, RI, Row0, Row, I0, I) :-
+    I is I0 + 1,
+    (   I0 =:= RI ->
+        Row = Pivot
+    ;   Pivot = r(J, Coeffs, Rhs),
+        Row0 = r(Basic, Coeffs0, Rhs0),
+        nth0(J, Coeffs0, F),
+        (   F =:= 0 ->
+            Row = Row0
+        ;   maplist(times_minus(F), Coeffs, Coeffs0, Coeffs1),
+            Rhs1 is Rhs0 - F * Rhs,
+            Row = r(Basic, Coeffs1, Rhs1)
+        )
+    ).
+
+column_value(Rows, J, Y) :-
+    (   member(r(J, _, Rhs), Rows) ->
+        Y = Rhs
+    ;   Y = 0
+    ).
+
+column_indices(N, Js) :-
+    length(Js, N),
+    foldl(column_index, Js, 0, _).
+
+column_index(J, J, J1) :-
+    J1 is J + 1.
+
+%% Querying constraints.
+
+% the solved equations and the inequalities of the constraints of the
+% variables of Lin0, and Lin0 in terms of the variables of the
+% inequalities and of variables without constraints.
+reduced(Lin0, Ineqs, Lin, Solved) :-
+    lin_normalize(Lin0, Lin1),
+    lin_variables(Lin1, Vs),
+    foldl(variable_store, Vs, [], Stores),
+    maplist(store_constraints, Stores, Css),
+    append(Css, Cs),
+    partition(is_equation, Cs, Eqs, Ineqs),
+    foldl(eliminate, Eqs, [], Solved),
+    foldl(substitute, Solved, Lin1, Lin).
+
+%% sup(+Expr, -Sup)
+%
+% Sup is the supremum of the linear expression Expr subject to the
+% posted constraints. Fails if Expr is unbounded from above.
+sup(Expr, Sup) :-
+    expression_linear(Expr, sup/2, Lin0),
+    reduced(Lin0, Ineqs, Lin, _),
+    (   Lin = l(C, []) ->
+        Sup = C
+    ;   optimum(Ineqs, Lin, optimal(Sup))
+    ).
+
+%% inf(+Expr, -Inf)
+%
+% Inf is the infimum of the linear expression Expr subject to the
+% posted constraints. Fails if Expr is unbounded from below.
+inf(Expr, Inf) :-
+    sup(-Expr, Sup),
+    Inf is -Sup.
+
+%% maximize(+Expr)
+%
+% Posts that Expr equals its supremum.
+maximize(Expr) :-
+    sup(Expr, Sup),
+    { Expr = Sup }.
+
+%% minimize(+Expr)
+%
+% Posts that Expr equals its infimum.
+minimize(Expr) :-
+    inf(Expr, Inf),
+    { Expr = Inf }.
+
+expression_linear(Expr, Context, Lin) :-
+    catch(linear(Expr, Lin),
+          clpq_nonlinear,
+          type_error(linear_expression, Expr, Context)).
+
+%% entailed(+Constraint)
+%
+% True if the posted constraints imply the linear constraint
+% Constraint, which may also be a disequation `L =\= R`.
+entailed(C) :-
+    (   var(C) ->
+        instantiation_error(entailed/1)
+    ;   C = (A, B) ->
+        entailed(A),
+        entailed(B)
+    ;   C = (L =\= R) ->
+        \+ { L = R }
+    ;   constraint_op(C, Op, L, R) ->
+        entailed_(Op, L, R)
+    ;   domain_error(clpq_constraint, C, entailed/1)
+    ).
+
+entailed_(=, L, R) :-
+    \+ { L < R },
+    \+ { L > R }.
+entailed_(=<, L, R) :-
+    \+ { L > R }.
+entailed_(<, L, R) :-
+    \+ { L >= R }.
+
+%% bb_inf(+Ints, +Expr, -Inf)
+%
+% Inf is the infimum of the linear expression Expr subject to the
+% posted constraints and to the variables of the list Ints being
+% integers, found by branch and bound on the infima of Expr. Fails if
+% there are no such solutions or Expr is unbounded from below.
+bb_inf(Ints, Expr, Inf) :-
+    must_be(list, Ints),
+    expression_linear(Expr, bb_inf/3, _),
+    bb_search(Ints, Expr, none, Inf0),
+    Inf0 \== none,
+    Inf = Inf0.
+
+bb_search(Ints, Expr, Best0, Best) :-
+    (   inf_values(Expr, Ints, Inf, Values) ->
+        (   Best0 \== none,
+            Inf >= Best0 ->
+            Best = Best0
+        ;   nth0(I, Values, Value),
+            \+ integer(Value) ->
+            nth0(I, Ints, X),
+            Floor is floor(Value),
+            Ceiling is Floor + 1,
+            bb_branch({ X =< Floor }, Ints, Expr, Best0, Best1),
+            bb_branch({ X >= Ceiling }, Ints, Expr, Best1, Best)
+        ;   Best = Inf
+        )
+    ;   Best = Best0
+    ).
+
+bb_branch(Constraint, Ints, Expr, Best0, Best) :-
+    (   findall(B, (Constraint, bb_search(Ints, Expr, Best0, B)), [Best1]) ->
+        Best = Best1
+    ;   Best = Best0
+    ).
+
+% the infimum Inf of Expr subject to the posted constraints, and
+% values of the variables Xs at a solution where Expr is Inf.
+inf_values(Expr, Xs, Inf, Values) :-
+    linear(-Expr, Lin0),
+    reduced(Lin0, Ineqs, Lin, Solved),
+    optimum(Ineqs, Lin, optimal(Sup), Vs, VsValues),
+    Inf is -Sup,
+    maplist(variable_value(Solved, Vs, VsValues), Xs, Values).
+
+variable_value(Solved, Vs, VsValues, X, Value) :-
+    (   var(X) ->
+        (   member(Y-Lin, Solved),
+            Y == X ->
+            Lin = l(C, Ts),
+            foldl(term_value(Vs, VsValues), Ts, C, Value)
+        ;   nth0(I, Vs, Y),
+            Y == X ->
+            nth0(I, VsValues, Value)
+        ;   Value = 0
+        )
+    ;   Value = X
+    ).
+
+term_value(Vs, VsValues, A*X, V0, V) :-
+    (   nth0(I, Vs, Y),
+        Y == X ->
+        nth0(I, VsValues, XV),
+        V is V0 + A * XV
+    ;   V = V0
+    ).
+
+%% Residual goals.
+
+% The constraints are projected onto the variables of the query, so
+% that auxiliary variables, such as those of the recursive calls of a
+% predicate, don't show in its answers. Their solved equations are
+% dropped, their occurrences in other equations eliminated with
+% these, and then in inequalities by Fourier-Motzkin elimination.
+project_attributes(QueryVars, AttrVars) :-
+    foldl(variable_store, AttrVars, [], Stores),
+    maplist(project_store(QueryVars), Stores).
+
+project_store(QueryVars, store(Cs0, _)) :-
+    term_variables(Cs0, Vs),
+    maplist(remove_store, Vs),
+    partition(is_equation, Cs0, Eqs, Ineqs0),
+    foldl(eliminate, Eqs, [], Solved0),
+    project_solved(Solved0, QueryVars, Solved, Ineqs0, Ineqs1),
+    term_variables(Ineqs1, IneqVs),
+    exclude_variables(IneqVs, QueryVars, Auxiliary),
+    foldl(fourier_motzkin, Auxiliary, Ineqs1, Ineqs),
+    maplist(solved_constraint, Solved, SolvedCs),
+    append(SolvedCs, Ineqs, Cs),
+    term_variables(Cs, Us),
+    maplist(put_store(store(Cs, [])), Us).
+
+project_solved(Solved0, QueryVars, Solved, Ineqs0, Ineqs) :-
+    (   select(X-_, Solved0, Solved1),
+        \+ variable_member(X, QueryVars) ->
+        project_solved(Solved1, QueryVars, Solved, Ineqs0, Ineqs)
+    ;   select(X-Lin, Solved0, Solved1),
+        Lin = l(C, Ts0),
+        select(A*Y, Ts0, Ts),
+        \+ variable_member(Y, QueryVars) ->
+        % X = C + A*Y + Ts, so that Y = (X - C - Ts) / A.
+        K is -1 rdiv A,
+        lin_scale(l(C, [-1*X|Ts]), K, LinY),
+        maplist(substitute_pair(Y-LinY), Solved1, Solved2),
+        maplist(substitute_solved([Y-LinY]), Ineqs0, Ineqs1),
+        project_solved(Solved2, QueryVars, Solved, Ineqs1, Ineqs)
+    ;   Solved = Solved0,
+        Ineqs = Ineqs0
+    ).
+
+variable_member(X, Vs) :-
+    member(V, Vs),
+    V == X,
+    !.
+
+exclude_variables([], _, []).
+exclude_variables([V|Vs], Ws, Us) :-
+    (   variable_member(V, Ws) ->
+        Us = Us1
+    ;   Us = [V|Us1]
+    ),
+    exclude_variables(Vs, Ws, Us1).
+
+% fourier_motzkin(+Y, +Ineqs0, -Ineqs): Ineqs are the inequalities
+% that hold for the values of the other variables of Ineqs0 for which
+% there is a value of Y satisfying Ineqs0, i.e. those of Ineqs0
+% without Y, and the sums of each pair of its inequalities with
+% opposite signs of Y, scaled so that Y cancels.
+fourier_motzkin(Y, Ineqs0, Ineqs) :-
+    foldl(y_sign(Y), Ineqs0, []-[]-[], Pos-Neg-Zero),
+    findall(Ineq,
+            ( member(A-P, Pos),
+              member(B-N, Neg),
+              combined(A-P, B-N, Ineq) ),
+            Combined),
+    reverse(Zero, Ineqs1),
+    append(Ineqs1, Combined, Ineqs).
+
+y_sign(Y, Ineq, Pos0-Neg0-Zero0, Pos-Neg-Zero) :-
+    Ineq = c(_, l(_, Ts)),
+    (   member(A*X, Ts),
+        X == Y ->
+        (   A > 0 ->
+            Pos = [A-Ineq|Pos0],
+            Neg = Neg0
+        ;   Pos = Pos0,
+            Neg = [A-Ineq|Neg0]
+        ),
+        Zero = Zero0
+    ;   Pos = Pos0,
+        Neg = Neg0,
+        Zero = [Ineq|Zero0]
+    ).
+
+combined(A-c(Op1, Lin1), B-c(Op2, Lin2), c(Op, Lin)) :-
+    NB is -B,
+    lin_scale(Lin1, NB, Lin3),
+    lin_scale(Lin2, A, Lin4),
+    lin_add(Lin3, Lin4, Lin),
+    Lin = l(_, [_|_]),
+    (   ( Op1 == (<) ; Op2 == (<) ) ->
+        Op = (<)
+    ;   Op = (=<)
+    ).
+
+attribute_goals(X) -->
+    { get_atts(X, +clpq(store(Cs, _))),
+      include(first_variable(X), Cs, Cs1),
+      maplist(constraint_goal, Cs1, Goals),
+      put_atts(X, -clpq(_)) },
+    goals(Goals).
+
+goals([]) --> [].
+goals([G|Gs]) --> [clpq:{G}], goals(Gs).
+
+first_variable(X, c(_, l(_, [_*Y|_]))) :-
+    Y == X.
+
+constraint_goal(c(Op, Lin), Goal) :-
+    (   Op == (=) ->
+        equation_goal(Lin, Goal)
+    ;   inequality_goal(Op, Lin, Goal)
+    ).
+
+% the equation solved for its first variable.
+equation_goal(l(C, [A*X|Ts]), X = Rhs) :-
+    K is -1 rdiv A,
+    lin_scale(l(C, Ts), K, Lin),
+    lin_expression(Lin, Rhs).
+
+% the inequality with the terms of positive coefficients on the left
+% and the others and the constant on the right, or only on the right
+% with the relation flipped if there are no positive coefficients.
+inequality_goal(Op0, l(C, Ts), Goal) :-
+    partition(positive_term, Ts, Pos, Neg0),
+    maplist(term_scale(-1), Neg0, Neg),
+    NC is -C,
+    (   Pos == [] ->
+        lin_expression(l(0, Neg), Lhs),
+        Rhs = C,
+        flipped(Op0, Op)
+    ;   lin_expression(l(0, Pos), Lhs),
+        lin_expression(l(NC, Neg), Rhs),
+        Op = Op0
+    ),
+    Goal =.. [Op, Lhs, Rhs].
+
+positive_term(A*_) :-
+    A > 0.
+
+flipped(=<, >=).
+flipped(<, >).
+
+% the expression of the linear expression, written with its constant
+% last, unless it is positive and its first coefficient negative.
+lin_expression(l(C, Ts), E) :-
+    (   Ts == [] ->
+        E = C
+    ;   Ts = [A*_|_],
+        A < 0,
+        C > 0 ->
+        foldl(add_term_expression, Ts, C, E)
+    ;   Ts = [T|Ts1],
+        term_expression(T, E0),
+        foldl(add_term_expression, Ts1, E0, E1),
+        (   C > 0 ->
+            E = E1 + C
+        ;   C < 0 ->
+            NC is -C,
+            E = E1 - NC
+        ;   E = E1
+        )
+    ).
+
+term_expression(A*X, E) :-
+    (   A =:= 1 ->
+        E = X
+    ;   A =:= -1 ->
+        E = -X
+    ;   E = A*X
+    ).
+
+add_term_expression(A*X, E0, E) :-
+    (   A < 0 ->
+        B is -A,
+        term_expression(B*X, T),
+        E = E0 - T
+    ;   term_expression(A*X, T),
+        E = E0 + T
+    ).
@@ -1458,7 +1458,10 @@ domain_remove_smaller_than(split(S,Left0,Right0), V, D) :-
    Remove a whole domain from another domain. (Set difference.)
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
-domain_subtract(Dom0, Sub, Dom) :- domain_subtract(Dom0, Dom0, Sub, Dom).
+domain_subtract(Dom0, Sub, Dom) :-
+        (   '$fd_domain_subtract'(Dom0, Sub, Dom1) -> Dom = Dom1
+        ;   domain_subtract(Dom0, Dom0, Sub, Dom)
+        ).
 
 domain_subtract(empty, _, _, empty).
 domain_subtract(from_to(From0,To0), Dom, Sub, D) :-
@@ -1518,11 +1521,20 @@ domain_intervals(from_to(From,To))      --> [From-To].
    To compute the intersection of two domains D1 and D2, we choose D1
    as the reference domain. For each interval of D1, we compute how
    far and to which values D2 lets us extend it.
+
+   The intersection, union and difference of domains whose bounds are
+   small integers are computed by the machine, on the lists of their
+   intervals, in time linear in the number of intervals. Its results
+   are the argument domains themselves if they are unchanged. Domains
+   with larger bounds are left to the definitions here.
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
 domains_intersection(D1, D2, Intersection) :-
-        domains_intersection_(D1, D2, Intersection),
-        Intersection \== empty.
+        (   '$fd_domains_intersection'(D1, D2, Intersection0) -> true
+        ;   domains_intersection_(D1, D2, Intersection0)
+        ),
+        Intersection0 \== empty,
+        Intersection = Intersection0.
 
 domains_intersection_(empty, _, empty).
 domains_intersection_(from_to(L0,U0), D2, Dom) :-
@@ -1557,11 +1569,13 @@ narrow(split(S, Left0, Right0), From0, To0, Dom) :-
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
 domains_union(D1, D2, Union) :-
-        domain_intervals(D1, Is1),
-        domain_intervals(D2, Is2),
-        append(Is1, Is2, IsU0),
-        merge_intervals(IsU0, IsU1),
-        intervals_to_domain(IsU1, Union).
+        (   '$fd_domains_union'(D1, D2, Union0) -> Union = Union0
+        ;   domain_intervals(D1, Is1),
+            domain_intervals(D2, Is2),
+            append(Is1, Is2, IsU0),
+            merge_intervals(IsU0, IsU1),
+            intervals_to_domain(IsU1, Union)
+        ).
 
 /* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
    Shift the domain by an offset.
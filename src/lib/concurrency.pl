@@ -0,0 +1,663 @@
+/** Timeouts, retries and races for goals.
+
+This library provides small wrappers that give goals a bounded
+amount of time (`call_with_time_limit/2`), retry goals that raise
+transient exceptions (`retry_with_backoff/3`), let several goals race
+for the first answer (`first_of/2`) and map goals over lists as
+independent tasks (`concurrent_maplist/2`, `concurrent_maplist/3`).
+
+An exceeded time limit is expressed through the exception mechanism,
+so `setup_call_cleanup/3` and `catch/3` inside the goal behave as they
+do for any other exception. A user interrupt (Ctrl-C) is never
+swallowed by these predicates.
+
+The tasks of `concurrent_maplist/2` and the competitors of
+`first_of/2` run as engines of the current machine (see
+`library(engines)`), which take turns of about a thousand instructions
+each: a task that doesn't terminate doesn't keep the others from
+completing, and a task is resumed where its previous turn stopped.
+Each task works on a copy of its goal, and results are only unified
+with the caller's terms once every task has completed. A cancelled
+task is destroyed with its engine, without running the cleanup goals
+it set up. Goals passed to these predicates should be free of side
+effects.
+
+Mutexes (`mutex_create/1`, `with_mutex/2`, ...) and condition
+variables (`condition_create/1`, `condition_wait/2`, ...) are
+implemented natively and, like message queues, shared by every thread
+of the process (see `library(threads)`), which name them by atoms. A
+mutex is held by a thread. Mutexes are recursive: the holder of a
+mutex may lock it again, and it is released once it has been unlocked
+as often as it was locked. A thread blocked on a mutex or a condition
+variable can still be interrupted or cancelled.
+
+Updates of dynamic predicates follow the logical update view: a goal
+calling a dynamic predicate sees the clauses that existed when the
+call was made, regardless of clauses asserted or retracted while it
+runs. Each `assertz/1`, `asserta/1` and `retract/1` is atomic, but a
+sequence of them is not: the tasks of `concurrent_maplist/2` and
+`first_of/2` share the database of the calling machine and may see
+each other's updates half done. Mutexes don't order these tasks, as
+they all run on the calling thread, which may lock a mutex it holds.
+
+The dynamic database isn't shared between threads: the threads of
+`library(threads)` have machines, and so databases, of their own, and
+a clause asserted by one thread is never seen by another. Mutexes and
+condition variables coordinate threads on what they do share, such as
+message queues, files and external services, and state meant for
+several threads is kept by one of them and sent to the others as
+messages.
+*/
+
+:- module(concurrency, [call_with_time_limit/2,
+                        concurrent_maplist/2,
+                        condition_broadcast/1,
+                        condition_create/1,
+                        condition_destroy/1,
+                        condition_signal/1,
+                        condition_wait/2,
+                        condition_wait/3,
+                        concurrent_maplist/3,
+                        first_of/2,
+                        mutex_create/1,
+                        mutex_destroy/1,
+                        mutex_lock/1,
+                        mutex_property/2,
+                        mutex_trylock/1,
+                        mutex_unlock/1,
+                        parallel_findall/3,
+                        parallel_findall/4,
+                        retry_with_backoff/3,
+                        with_mutex/2]).
+
+:- use_module(library(engines), [engine_create/3, engine_destroy/1]).
+:- use_module(library(error)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists), [append/2, append/3, length/2, maplist/2, maplist/3, maplist/4, member/2]).
+:- use_module(library(charsio), [write_term_to_chars/3]).
+:- use_module(library(pairs), [pairs_keys_values/3]).
+:- use_module(library(threads), [message_queue_create/1,
+                                 message_queue_destroy/1,
+                                 thread_cancel/1,
+                                 thread_get_message/2,
+                                 thread_join/2,
+                                 thread_self/1,
+                                 thread_send_message/2]).
+:- use_module(library(time), [sleep/1]).
+
+:- meta_predicate(call_with_time_limit(?, 0)).
+
+%% call_with_time_limit(+Time, :Goal).
+%
+% Calls Goal as `once/1`, but throws the exception
+% `time_limit_exceeded` if Goal has not completed after Time
+% seconds (an integer or a float). Calls may be nested; each limit
+% only applies to its own goal.
+%
+% ```
+% ?- call_with_time_limit(0.5, (repeat, false)).
+%    throw(time_limit_exceeded).
+% ```
+call_with_time_limit(Time, Goal) :-
+    must_be_time(Time, call_with_time_limit/2),
+    setup_call_cleanup(install_time_limit(Time),
+                       once(Goal),
+                       remove_time_limit).
+
+install_time_limit(Time) :-
+    '$install_time_limit'(Time).
+
+remove_time_limit :-
+    '$remove_time_limit'.
+
+:- meta_predicate(retry_with_backoff(0, ?, ?)).
+
+%% retry_with_backoff(:Goal, +Retries, +Delay).
+%
+% Calls Goal as `once/1`. If Goal throws an exception, waits Delay
+% seconds and tries again, doubling the delay after each attempt.
+% After Retries unsuccessful retries, the last exception is
+% rethrown. Failure of Goal is not retried, and neither are user
+% interrupts.
+%
+% ```
+% ?- retry_with_backoff(call_with_time_limit(2, fetch(Data)), 3, 0.1).
+% ```
+retry_with_backoff(Goal, Retries, Delay) :-
+    must_be(integer, Retries),
+    (   Retries < 0 ->
+        domain_error(not_less_than_zero, Retries, retry_with_backoff/3)
+    ;   true
+    ),
+    must_be_time(Delay, retry_with_backoff/3),
+    retry_with_backoff_(Retries, Delay, Goal).
+
+retry_with_backoff_(Retries, Delay, Goal) :-
+    catch(once(Goal), E, true),
+    (   var(E) ->
+        true
+    ;   Retries > 0,
+        \+ interrupt_exception(E) ->
+        sleep(Delay),
+        Retries1 is Retries - 1,
+        Delay1 is Delay * 2,
+        retry_with_backoff_(Retries1, Delay1, Goal)
+    ;   throw(E)
+    ).
+
+interrupt_exception(error('$interrupt_thrown', _)).
+
+:- meta_predicate(first_of(:, ?)).
+
+%% first_of(:Goals, -Winner).
+%
+% Races the goals of the list Goals against each other. Winner is
+% unified with the instance of the first goal to find an answer, and
+% the remaining goals are cancelled. Fails if every goal fails. If a
+% goal throws an exception before a winner is found, the race is
+% cancelled and the exception is rethrown.
+%
+% The goals take turns, so that the goal needing the fewest
+% instructions for its first answer wins (up to a turn, ties going to
+% the earlier goal), independently of the wall clock. Each turn
+% resumes a goal where its previous turn stopped.
+%
+% ```
+% ?- first_of([(length(_, N), N > 1000), X = a], W).
+%    X = a, W = (a=a).
+% ```
+first_of(M:Goals, Winner) :-
+    must_be(list, Goals),
+    setup_call_cleanup(maplist(goal_engine(M), Goals, Engines),
+                       (   pairs_keys_values(Contestants, Engines, Goals),
+                           race(Contestants, Winner)
+                       ),
+                       maplist(engine_destroy, Engines)).
+
+goal_engine(M, G, E) :-
+    engine_create(G, M:G, E).
+
+race([C|Cs], Winner) :-
+    race_round([C|Cs], Pending, Outcome),
+    (   Outcome = winner(Winner0) ->
+        Winner = Winner0
+    ;   race(Pending, Winner)
+    ).
+
+race_round([], [], pending).
+race_round([E-G|Cs], Pending, Outcome) :-
+    engine_turn(E, Kind, T),
+    (   Kind == the ->
+        G = T,
+        Outcome = winner(G)
+    ;   Kind == pending ->
+        Pending = [E-G|Pending0],
+        race_round(Cs, Pending0, Outcome)
+    ;   Kind == exception ->
+        throw(T)
+    ;   race_round(Cs, Pending, Outcome)
+    ).
+
+:- meta_predicate(concurrent_maplist(1, ?)).
+
+%% concurrent_maplist(:Goal, ?Ls).
+%
+% Like `maplist/2`, but each call of Goal is an independent task: it
+% is called as `once/1` on a copy of Goal and its element, and the
+% instantiated copies are unified with Ls once all tasks have
+% succeeded. If a task fails or throws an exception, the remaining
+% tasks are cancelled.
+concurrent_maplist(G_1, Ls) :-
+    must_be(list, Ls),
+    setup_call_cleanup(maplist(task_engine(G_1), Ls, Engines),
+                       run_tasks(Engines, Results),
+                       maplist(engine_destroy, Engines)),
+    Ls = Results.
+
+:- meta_predicate(concurrent_maplist(2, ?, ?)).
+
+%% concurrent_maplist(:Goal, ?Ls0, ?Ls).
+%
+% Like `concurrent_maplist/2`, for goals relating two lists of equal
+% length. At least one of the lists must be a proper list.
+concurrent_maplist(G_2, Ls0, Ls) :-
+    (   nonvar(Ls0) ->
+        must_be(list, Ls0)
+    ;   must_be(list, Ls)
+    ),
+    setup_call_cleanup(maplist(task_engine(G_2), Ls0, Ls, Engines),
+                       run_tasks(Engines, Pairs),
+                       maplist(engine_destroy, Engines)),
+    pairs_keys_values(Pairs, Ls0, Ls).
+
+task_engine(G_1, L, E) :-
+    engine_create(L, call(G_1, L), E).
+
+task_engine(G_2, L0, L, E) :-
+    engine_create(L0-L, call(G_2, L0, L), E).
+
+% Results are the first answers of Engines, which take turns until
+% each of them found one.
+run_tasks(Engines, Results) :-
+    length(Engines, N),
+    length(Results, N),
+    pairs_keys_values(Tasks, Engines, Results),
+    run_tasks_(Tasks).
+
+run_tasks_([]).
+run_tasks_([T|Ts]) :-
+    run_turns([T|Ts], Pending),
+    run_tasks_(Pending).
+
+run_turns([], []).
+run_turns([E-R|Ts], Pending) :-
+    engine_turn(E, Kind, T),
+    (   Kind == the ->
+        R = T,
+        run_turns(Ts, Pending)
+    ;   Kind == pending ->
+        Pending = [E-R|Pending0],
+        run_turns(Ts, Pending0)
+    ;   Kind == exception ->
+        throw(T)
+    ).
+
+% runs engine E for a turn of 4 interrupt polls of the dispatch loop,
+% about a thousand instructions. Kind is pending if E found no answer
+% in its turn.
+engine_turn(E, Kind, T) :-
+    '$engine_next'(E, 4, Kind, T).
+
+:- meta_predicate(parallel_findall(?, :, ?)).
+
+%% parallel_findall(+Template, :Goal, -Ts).
+%
+% Like `parallel_findall/4` without options.
+parallel_findall(Template, Goal, Ts) :-
+    parallel_findall(Template, Goal, Ts, []).
+
+:- meta_predicate(parallel_findall(?, :, ?, ?)).
+
+%% parallel_findall(+Template, :Goal, -Ts, +Options).
+%
+% Like `findall/3`, with the search split into tasks that worker
+% threads run in parallel. If Goal is a conjunction
+% `(Generator, Test)`, every solution of Generator becomes a task that
+% collects the solutions of Test. Ts lists the instances of Template
+% in the order `findall/3` would. Otherwise, Goal is a single task.
+% Options is a list of:
+%
+%  - `workers(N)`: run the tasks in at most N worker threads. The
+%    default is the number of CPUs.
+%
+% The tasks are divided evenly among the workers, each of which is a
+% thread of `library(threads)` with a machine of its own. A worker
+% first consults the file of the module Goal is called in, without
+% running its initialization goals, so that Test may call the
+% predicates of that file. The clauses of the dynamic predicates of
+% that module and of `user`, including those asserted or retracted
+% since the file was loaded, and the values of the Prolog flags are
+% then copied to the worker. Tasks are run by the calling thread if
+% there is a single worker, a single task, or no such file, as for
+% goals of the `user` module.
+%
+% **Warning:** any other state of the calling machine is not shared
+% with the workers. Test sees the static predicates of other modules
+% as loaded from their files, dynamic predicates of other modules
+% without the changes made to them since, and no global variables of
+% `nb_setval/2`. Tasks and their solutions cross threads in written
+% form, which doesn't preserve attributes of variables.
+%
+% Only goals without side effects should be used: Test must not
+% depend on the order in which tasks are run, nor on the bindings
+% made by other tasks.
+%
+% ```
+% ?- parallel_findall(X-Y, (member(X, [1,2]), member(Y, [a,b])), Ps).
+%    Ps = [1-a,1-b,2-a,2-b].
+% ```
+parallel_findall(Template, M:Goal, Ts, Options) :-
+    must_be(list, Options),
+    (   member(workers(Workers), Options) ->
+        must_be(integer, Workers),
+        (   Workers < 1 ->
+            domain_error(positive_integer, Workers, parallel_findall/4)
+        ;   true
+        )
+    ;   '$cpu_count'(Workers)
+    ),
+    (   nonvar(Goal),
+        Goal = (Generator, Test) ->
+        findall(Template-(M:Test), M:Generator, Tasks),
+        length(Tasks, N),
+        (   Workers > 1,
+            N > 1,
+            catch(module_property(M, file(File)), _, false) ->
+            Chunks is min(Workers, N),
+            shared_state([M, user], State),
+            run_workers(File, State, Tasks, Chunks, Tss)
+        ;   maplist(task_solutions, Tasks, Tss)
+        ),
+        append(Tss, Ts)
+    ;   findall(Template, M:Goal, Ts)
+    ).
+
+task_solutions(Template-Test, Ts) :-
+    findall(Template, Test, Ts).
+
+% State holds the values of the Prolog flags and the clauses of the
+% dynamic predicates of Modules, which the workers restore.
+shared_state(Modules, state(Flags, Preds)) :-
+    findall(Flag-Value, current_prolog_flag(Flag, Value), Flags),
+    findall(Module:Head-Clauses,
+            ( member(Module, Modules),
+              current_predicate(Module:Name/Arity),
+              functor(Head, Name, Arity),
+              predicate_property(Module:Head, dynamic),
+              findall((Head :- Body), clause(Module:Head, Body), Clauses)
+            ),
+            Preds).
+
+restore_state(state(Flags, Preds)) :-
+    maplist(restore_flag, Flags),
+    maplist(restore_predicate, Preds).
+
+% flags that can't be set, such as bounded, are skipped.
+restore_flag(Flag-Value) :-
+    catch(set_prolog_flag(Flag, Value), _, true).
+
+restore_predicate(Module:Head-Clauses) :-
+    retractall(Module:Head),
+    maplist(assert_clause(Module), Clauses).
+
+assert_clause(Module, Clause) :-
+    assertz(Module:Clause).
+
+% Tss are the solutions of Tasks, which are divided among Chunks
+% worker threads consulting File and restoring State.
+run_workers(File, State, Tasks, Chunks, Tss) :-
+    chunks(Tasks, Chunks, TaskChunks),
+    setup_call_cleanup(message_queue_create(Queue),
+                       setup_call_cleanup(start_workers(TaskChunks, 0, File, State, Queue, Ids),
+                                          collect_results(Ids, 0, Queue, ChunkTss),
+                                          maplist(stop_worker, Ids)),
+                       message_queue_destroy(Queue)),
+    append(ChunkTss, Tss).
+
+chunks(Ls, N, Chunks) :-
+    length(Ls, Len),
+    Size is (Len + N - 1) // N,
+    chunks_(Ls, Size, Chunks).
+
+chunks_([], _, []).
+chunks_([L|Ls], Size, [Chunk|Chunks]) :-
+    take(Size, [L|Ls], Chunk, Rest),
+    chunks_(Rest, Size, Chunks).
+
+take(0, Ls, [], Ls) :-
+    !.
+take(_, [], [], []).
+take(N, [L|Ls], [L|Chunk], Rest) :-
+    N1 is N - 1,
+    take(N1, Ls, Chunk, Rest).
+
+start_workers([], _, _, _, _, []).
+start_workers([Tasks|TaskChunks], I, File, State, Queue, [Id|Ids]) :-
+    % library(concurrency) is loaded by the goal of the worker, which
+    % is read before.
+    write_term_to_chars(( use_module(library(concurrency)),
+                          concurrency:'$parallel_findall_worker'(File, State, Queue, I, Tasks)
+                        ),
+                        [quoted(true), ignore_ops(true), max_depth(0)],
+                        Chars),
+    (   '$thread_create'(Chars, Id) ->
+        true
+    ;   throw(error(resource_error(threads), parallel_findall/4))
+    ),
+    I1 is I + 1,
+    start_workers(TaskChunks, I1, File, State, Queue, Ids).
+
+collect_results([], _, _, []).
+collect_results([_|Ids], I, Queue, [Tss|ChunkTss]) :-
+    thread_get_message(Queue, I-Result),
+    (   Result = the(Tss) ->
+        true
+    ;   Result = exception(E),
+        throw(E)
+    ),
+    I1 is I + 1,
+    collect_results(Ids, I1, Queue, ChunkTss).
+
+stop_worker(Id) :-
+    thread_cancel(Id),
+    thread_join(Id, _).
+
+'$parallel_findall_worker'(File, State, Queue, I, Tasks) :-
+    catch(( loader:consult_without_initialization(File),
+            restore_state(State),
+            maplist(task_solutions, Tasks, Tss),
+            Result = the(Tss)
+          ),
+          E,
+          Result = exception(E)),
+    thread_send_message(Queue, I-Result).
+
+%% mutex_create(?Mutex).
+%
+% Creates a mutex. If Mutex is an atom, the mutex is named by it,
+% otherwise Mutex is unified with the name of a new anonymous mutex.
+% Throws a permission error if a mutex named Mutex already exists.
+mutex_create(Mutex) :-
+    (   var(Mutex) ->
+        '$mutex_create'(Mutex)
+    ;   must_be(atom, Mutex),
+        (   '$mutex_create'(Mutex) ->
+            true
+        ;   throw(error(permission_error(create, mutex, Mutex), mutex_create/1))
+        )
+    ).
+
+%% mutex_destroy(+Mutex).
+%
+% Destroys Mutex. Throws a permission error if Mutex is locked.
+mutex_destroy(Mutex) :-
+    existing_mutex(Mutex, mutex_destroy/1),
+    (   '$mutex_destroy'(Mutex) ->
+        true
+    ;   throw(error(permission_error(destroy, mutex, Mutex), mutex_destroy/1))
+    ).
+
+%% mutex_lock(+Mutex).
+%
+% Locks Mutex, waiting until no other thread holds it. An atom that
+% doesn't name a mutex yet creates it.
+mutex_lock(Mutex) :-
+    (   atom(Mutex),
+        \+ '$mutex_holder'(Mutex, _, _) ->
+        % another thread may create the mutex first.
+        catch(mutex_create(Mutex), error(permission_error(_, _, _), _), true)
+    ;   true
+    ),
+    existing_mutex(Mutex, mutex_lock/1),
+    lock(Mutex).
+
+lock(Mutex) :-
+    (   '$mutex_lock'(Mutex) ->
+        true
+    ;   % the wait was interrupted, which is raised by this call, or
+        % the mutex was destroyed.
+        existing_mutex(Mutex, mutex_lock/1),
+        lock(Mutex)
+    ).
+
+%% mutex_trylock(+Mutex).
+%
+% Like `mutex_lock/1`, but fails instead of waiting if another thread
+% holds Mutex.
+mutex_trylock(Mutex) :-
+    existing_mutex(Mutex, mutex_trylock/1),
+    '$mutex_trylock'(Mutex).
+
+%% mutex_unlock(+Mutex).
+%
+% Unlocks Mutex. Throws a permission error if the calling thread
+% doesn't hold Mutex.
+mutex_unlock(Mutex) :-
+    existing_mutex(Mutex, mutex_unlock/1),
+    (   '$mutex_unlock'(Mutex) ->
+        true
+    ;   throw(error(permission_error(unlock, mutex, Mutex), mutex_unlock/1))
+    ).
+
+%% mutex_property(+Mutex, ?Property).
+%
+% Property is a property of Mutex. The only property is `status(S)`,
+% where S is `unlocked` or `locked(Thread, Count)` if Thread locked
+% Mutex Count times.
+mutex_property(Mutex, status(S)) :-
+    existing_mutex(Mutex, mutex_property/2),
+    '$mutex_holder'(Mutex, Thread, Count),
+    (   Count =:= 0 ->
+        S = unlocked
+    ;   S = locked(Thread, Count)
+    ).
+
+:- meta_predicate(with_mutex(?, 0)).
+
+%% with_mutex(+Mutex, :Goal).
+%
+% Calls Goal as `once/1` while holding Mutex. The mutex is unlocked
+% when Goal succeeds, fails or throws an exception.
+%
+% ```
+% ?- with_mutex(counter, (retract(count(N0)), N is N0+1, assertz(count(N)))).
+% ```
+with_mutex(Mutex, Goal) :-
+    setup_call_cleanup(mutex_lock(Mutex),
+                       once(Goal),
+                       mutex_unlock(Mutex)).
+
+existing_mutex(Mutex, PI) :-
+    (   var(Mutex) ->
+        instantiation_error(PI)
+    ;   atom(Mutex) ->
+        (   '$mutex_holder'(Mutex, _, _) ->
+            true
+        ;   throw(error(existence_error(mutex, Mutex), PI))
+        )
+    ;   type_error(mutex, Mutex, PI)
+    ).
+
+%% condition_create(?Condition).
+%
+% Creates a condition variable. If Condition is an atom, the condition
+% variable is named by it, otherwise Condition is unified with the name
+% of a new anonymous condition variable. Throws a permission error if
+% a condition variable named Condition already exists.
+condition_create(Condition) :-
+    (   var(Condition) ->
+        '$condition_create'(Condition)
+    ;   must_be(atom, Condition),
+        (   '$condition_create'(Condition) ->
+            true
+        ;   throw(error(permission_error(create, condition, Condition),
+                        condition_create/1))
+        )
+    ).
+
+%% condition_destroy(+Condition).
+%
+% Destroys Condition. Threads waiting on it keep waiting until their
+% timeout runs out.
+condition_destroy(Condition) :-
+    must_be_condition(Condition, condition_destroy/1),
+    (   '$condition_destroy'(Condition) ->
+        true
+    ;   throw(error(existence_error(condition, Condition), condition_destroy/1))
+    ).
+
+%% condition_wait(+Condition, +Mutex).
+%
+% Like `condition_wait/3` without options.
+condition_wait(Condition, Mutex) :-
+    condition_wait(Condition, Mutex, []).
+
+%% condition_wait(+Condition, +Mutex, +Options).
+%
+% Unlocks Mutex, which the calling thread must hold, and waits until
+% Condition is signalled by `condition_signal/1` or
+% `condition_broadcast/1`. Mutex is locked again, as often as it was
+% before, when the wait ends. Options is a list of:
+%
+%  - `timeout(T)`: fail if Condition isn't signalled within T seconds.
+%    The default is `infinite`.
+%
+% The wait may also end without a signal, so that the condition waited
+% for must be checked again:
+%
+% ```
+% await_ready :-
+%     with_mutex(m, await_ready_).
+%
+% await_ready_ :-
+%     (   ready ->
+%         true
+%     ;   condition_wait(c, m),
+%         await_ready_
+%     ).
+% ```
+condition_wait(Condition, Mutex, Options) :-
+    must_be_condition(Condition, condition_wait/3),
+    existing_mutex(Mutex, condition_wait/3),
+    must_be(list, Options),
+    (   member(timeout(Timeout), Options) ->
+        (   Timeout == infinite ->
+            true
+        ;   must_be_time(Timeout, condition_wait/3)
+        )
+    ;   Timeout = infinite
+    ),
+    thread_self(Self),
+    (   '$mutex_holder'(Mutex, Holder, _),
+        Holder == Self ->
+        (   '$condition_wait'(Condition, Mutex, Timeout, Outcome) ->
+            Outcome == signalled
+        ;   throw(error(existence_error(condition, Condition), condition_wait/3))
+        )
+    ;   throw(error(permission_error(unlock, mutex, Mutex), condition_wait/3))
+    ).
+
+%% condition_signal(+Condition).
+%
+% Wakes one of the threads waiting on Condition, if any.
+condition_signal(Condition) :-
+    must_be_condition(Condition, condition_signal/1),
+    (   '$condition_signal'(Condition) ->
+        true
+    ;   throw(error(existence_error(condition, Condition), condition_signal/1))
+    ).
+
+%% condition_broadcast(+Condition).
+%
+% Wakes every thread waiting on Condition.
+condition_broadcast(Condition) :-
+    must_be_condition(Condition, condition_broadcast/1),
+    (   '$condition_broadcast'(Condition) ->
+        true
+    ;   throw(error(existence_error(condition, Condition), condition_broadcast/1))
+    ).
+
+must_be_condition(Condition, PI) :-
+    (   var(Condition) ->
+        instantiation_error(PI)
+    ;   atom(Condition) ->
+        true
+    ;   type_error(condition, Condition, PI)
+    ).
+
+must_be_time(Time, PI) :-
+    builtins:must_be_number(Time, PI),
+    (   Time < 0 ->
+        domain_error(not_less_than_zero, Time, PI)
+    ;   true
+    ).
@@ -1,16 +1,49 @@
+/** Delimited continuations.
+
+`reset/3` delimits a goal, and `shift/1` called within that goal
+captures the rest of the computation up to the nearest enclosing
+`reset/3` as a continuation. The continuation is a term that can be
+called like any other goal, any number of times. Effect handlers,
+iterators and tabling (`library(tabling)`) are built on these two
+predicates.
+
+```
+?- reset((shift(a), write(b)), Ball, cont(Goal)), write(Ball), call(Goal).
+ab   Ball = a, Goal = ... .
+```
+*/
+
 :- module(cont, [reset/3, shift/1]).
 
 :- meta_predicate(reset(0, ?, ?)).
 
+%% reset(:Goal, ?Ball, -Cont).
+%
+% Calls Goal. If Goal calls `shift(Ball1)`, the execution of Goal is
+% suspended, Ball is unified with Ball1 and Cont is unified with
+% `cont(Continuation)`, where calling Continuation resumes Goal
+% after the call to `shift/1`. If Goal completes without calling
+% `shift/1`, Cont is unified with `none`. Goal is called
+% nondeterministically: on backtracking, Goal is re-entered and may
+% shift again.
 reset(Goal, Ball, Cont) :-
     call(Goal),
     '$reset_cont_marker',
     '$bind_from_register'(Cont, 3),
     '$bind_from_register'(Ball, 4).
 
+%% shift(+Ball).
+%
+% Captures the continuation up to the nearest enclosing `reset/3`
+% and returns control to it, unifying its Ball argument with Ball.
+% Throws `existence_error(reset, Ball)` if there is no enclosing
+% `reset/3`.
 shift(Ball) :-
     '$nextEP'(first, E, P),
-    get_chunks(E, P, L),
+    (  get_chunks(E, P, L) ->
+       true
+    ;  throw(error(existence_error(reset, Ball), shift/1))
+    ),
     (  L == [] ->
        Cont = cont(true)
     ;  Cont = cont(cont:call_continuation(L))
@@ -0,0 +1,54 @@
+/** Coroutining: goals that are suspended until variables are bound.
+
+This library collects the coroutining predicates of Scryer Prolog:
+
+- `freeze/2` delays a goal until a variable is instantiated,
+- `when/2` delays a goal until a condition holds,
+- `dif/2` constrains two terms to remain different,
+- `frozen/2` inspects the goals delayed on the variables of a term.
+
+All of them are implemented with attributed variables
+(`library(atts)`): a suspended goal is stored in an attribute of each
+variable it waits on, and is woken by the engine as soon as such a
+variable is bound. Woken goals run before the next goal of the
+clause that caused the binding.
+
+```
+?- freeze(X, write(woken)), X = a.
+woken   X = a.
+?- when(ground(X-Y), Z is X + Y), X = 1, Y = 2.
+   X = 1, Y = 2, Z = 3.
+```
+*/
+
+:- module(coroutining, [dif/2,
+                        freeze/2,
+                        frozen/2,
+                        when/2]).
+
+:- use_module(library(dif)).
+:- use_module(library(freeze)).
+:- use_module(library(iso_ext), [copy_term/3]).
+:- use_module(library(when)).
+
+%% frozen(@Term, -Goal).
+%
+% Goal is the conjunction of all goals suspended on the variables of
+% Term, or `true` if there are none. The goals remain suspended.
+%
+% ```
+% ?- freeze(X, write(x)), frozen(X, G).
+%    G = freeze:freeze(X,write(x)), freeze(X,write(x)).
+% ```
+frozen(Term, Goal) :-
+    copy_term(Term, Copy, Goals),
+    Copy = Term,
+    goals_conjunction(Goals, Goal).
+
+goals_conjunction([], true).
+goals_conjunction([G|Gs], Goal) :-
+    (   Gs == [] ->
+        Goal = G
+    ;   Goal = (G, Goal1),
+        goals_conjunction(Gs, Goal1)
+    ).
@@ -16,6 +16,7 @@
           [hex_bytes/2,                  % ?Hex, ?Bytes
            crypto_n_random_bytes/2,      % +N, -Bytes
            crypto_data_hash/3,           % +Data, -Hash, +Options
+           crypto_constant_time_equal/2, % +As, +Bs
            crypto_data_hkdf/4,           % +Data, +Length, -Bytes, +Options
            crypto_password_hash/2,       % +Password, ?Hash
            crypto_password_hash/3,       % +Password, -Hash, +Options
@@ -251,28 +252,57 @@ crypto_data_hash(Data0, Hash, Options0) :-
         ),
         (   member(HMAC, Options0), nonvar(HMAC), HMAC = hmac(Ks) ->
             must_be_bytes(Ks, crypto_data_hash/3),
-            hmac_algorithm(A),
+            (   hmac_algorithm(A) -> true
+            ;   domain_error(hmac_algorithm, A, crypto_data_hash/3)
+            ),
             '$crypto_hmac'(Data, Encoding, Ks, HashBytes, A),
             (   var(Hash) ->
                 hex_bytes(Hash, HashBytes)
             ;   must_be(chars, Hash),
                 hex_bytes(HashMAC, HashBytes),
-                chars_equal_constant_time(Hash, HashMAC)
+                crypto_constant_time_equal(Hash, HashMAC)
             )
         ;   '$crypto_data_hash'(Data, Encoding, HashBytes, A),
             hex_bytes(Hash, HashBytes)
         ).
 
-chars_equal_constant_time(As, Bs) :-
-        maplist(chars_xor, As, Bs, Xs),
+%% crypto_constant_time_equal(+As, +Bs)
+%
+%  True iff As and Bs, which are lists of characters or lists of
+%  bytes, are equal. The comparison of lists of the same length takes
+%  the same time independent of whether and where they differ, so
+%  that comparing a secret such as an HMAC or an access token with a
+%  given value reveals nothing about the secret except its length.
+%
+%  Example:
+%
+% ```
+%  ?- crypto_constant_time_equal("secret", "secret").
+%     true.
+%  ?- crypto_constant_time_equal("secret", "Secret").
+%     false.
+% ```
+
+crypto_constant_time_equal(As, Bs) :-
+        must_be(list, As),
+        must_be(list, Bs),
+        length(As, N),
+        length(Bs, N),
+        maplist(element_xor, As, Bs, Xs),
         sum_list(Xs, Sum),
         Sum =:= 0.
 
-chars_xor(A, B, Xor) :-
-        char_code(A, CA),
-        char_code(B, CB),
+element_xor(A, B, Xor) :-
+        element_code(A, CA),
+        element_code(B, CB),
         Xor is xor(CA,CB).
 
+element_code(E, C) :-
+        (   integer(E) ->
+            C = E
+        ;   char_code(E, C)
+        ).
+
 hmac_algorithm(sha256).
 hmac_algorithm(sha384).
 hmac_algorithm(sha512).
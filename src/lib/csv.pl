@@ -61,17 +61,51 @@ With some options
     ),
     [with_header(false), line_separator('\r\n'), token_separator(';'), null_value('\\N')]).
 ```
+
+## Reading and writing rows
+
+`csv_read_file/3`, `csv_read_stream/3` and `csv_write_file/3` read and
+write rows, lists of fields, with the CSV parser of the system, which
+is much faster than `parse_csv//1` and quotes fields as RFC 4180 does.
+`csv_read_stream/3` reads the rows of a stream one at a time, so that
+files of any size can be processed.
+
+Options of reading:
+
+- `token_separator(',')`, an ASCII character
+- `convert(true)`, whether fields written as numbers are read as numbers
+
+Options of writing:
+
+- `token_separator(',')`
+- `line_separator('\n')`
+
+### Examples
+
+```
+?- csv_write_file('./test.csv', [["name","size"],["a, b",12]], []).
+?- csv_read_file('./test.csv', Rows, []).
+   Rows = [["name","size"],["a, b",12]].
+?- open('./test.csv', read, S), csv_read_stream(S, Row, [convert(false)]).
+   S = '$stream'(...), Row = ["name","size"]
+;  S = '$stream'(...), Row = ["a, b","12"]
+;  false.
+```
 */
 
 :- module(csv, [
   parse_csv//1,
   parse_csv//2,
   write_csv/2,
-  write_csv/3
+  write_csv/3,
+  csv_read_file/3,
+  csv_read_stream/3,
+  csv_write_file/3
 ]).
 
 :- use_module(library(format)).
 :- use_module(library(dcgs)).
+:- use_module(library(error)).
 :- use_module(library(iso_ext)).
 :- use_module(library(lists)).
 
@@ -249,3 +283,132 @@ parse_csv(frame(Header, Rows), Opt) -->
   rows(Rows, Opt0).
 parse_csv(R) -->
   parse_csv(R, []).
+
+
+%% -- rows --
+
+
+csv_option(Context, Names, Option) :-
+  ( var(Option) ->
+    instantiation_error(Context)
+  ; functor(Option, Name, 1),
+    memberchk(Name, Names),
+    csv_option_(Option) ->
+    true
+  ; domain_error(csv_option, Option, Context)).
+
+
+csv_option_(token_separator(Tk_Sep)) :-
+  atom(Tk_Sep),
+  atom_length(Tk_Sep, 1),
+  char_code(Tk_Sep, Code),
+  Code < 128,
+  \+ memberchk(Tk_Sep, ['"', '\n', '\r']).
+csv_option_(convert(Convert)) :-
+  memberchk(Convert, [true, false]).
+csv_option_(line_separator(Line_Sep)) :-
+  atom(Line_Sep).
+
+
+csv_options(Opt, Names, Defaults, Opt0, Context) :-
+  must_be(list, Opt),
+  maplist(csv_option(Context, Names), Opt),
+  option_extends(Opt, Defaults, Opt0).
+
+
+csv_read_options(Opt, Tk_Code, Convert, Context) :-
+  csv_options(Opt, [token_separator, convert], [
+    token_separator(','),
+    convert(true)
+  ], Opt0, Context),
+  option(token_separator(Tk_Sep), Opt0),
+  char_code(Tk_Sep, Tk_Code),
+  option(convert(Convert), Opt0).
+
+
+csv_read_row(Stream, Tk_Code, Convert, Row) :-
+  ( '$csv_read_row'(Stream, Tk_Code, Convert, Row0) ->
+    Row = Row0
+  ; throw(error(syntax_error(csv), csv_read_stream/3))).
+
+
+csv_read_rows(Stream, Tk_Code, Convert, Rows) :-
+  csv_read_row(Stream, Tk_Code, Convert, Row),
+  ( Row == end_of_file ->
+    Rows = []
+  ; Rows = [Row | Rows0],
+    csv_read_rows(Stream, Tk_Code, Convert, Rows0)).
+
+
+%% csv_read_file(+File, -Rows, +Options).
+%
+% Rows is the list of the rows of the CSV file File, each the list of
+% its fields. Fields are lists of characters, or numbers if they are
+% written as numbers and the option `convert(true)` is given, the
+% default. Empty lines are skipped.
+csv_read_file(File_Name, Rows, Opt) :-
+  csv_read_options(Opt, Tk_Code, Convert, csv_read_file/3),
+  setup_call_cleanup(
+    open(File_Name, read, In),
+    csv_read_rows(In, Tk_Code, Convert, Rows0),
+    close(In)),
+  Rows = Rows0.
+
+
+%% csv_read_stream(+Stream, -Row, +Options).
+%
+% Row is the next row of the CSV text of Stream, read as by
+% `csv_read_file/3`. The rows after it are read one at a time on
+% backtracking, and Stream is left after the last row read. Fails at
+% the end of Stream.
+csv_read_stream(Stream, Row, Opt) :-
+  csv_read_options(Opt, Tk_Code, Convert, csv_read_stream/3),
+  csv_read_stream_(Stream, Tk_Code, Convert, Row).
+
+
+csv_read_stream_(Stream, Tk_Code, Convert, Row) :-
+  csv_read_row(Stream, Tk_Code, Convert, Row0),
+  Row0 \== end_of_file,
+  ( Row = Row0
+  ; csv_read_stream_(Stream, Tk_Code, Convert, Row)).
+
+
+csv_field(Field) :-
+  ( number(Field) -> true
+  ; atom(Field) -> true
+  ; catch(must_be(chars, Field), error(_, _), false)).
+
+
+csv_row(Row) :-
+  must_be(list, Row),
+  ( member(Field, Row),
+    \+ csv_field(Field) ->
+    domain_error(csv_field, Field, csv_write_file/3)
+  ; true).
+
+
+csv_write_row(Out, Tk_Code, Line_Sep, Row) :-
+  '$csv_row_chars'(Row, Tk_Code, Chars),
+  format(Out, "~s~w", [Chars, Line_Sep]).
+
+
+%% csv_write_file(+File, +Rows, +Options).
+%
+% Writes Rows, lists of fields, to the CSV file File, each followed by
+% the line separator. Fields are numbers, atoms or lists of characters,
+% and are quoted if they contain the token separator, quotes or line
+% breaks.
+csv_write_file(File_Name, Rows, Opt) :-
+  csv_options(Opt, [token_separator, line_separator], [
+    token_separator(','),
+    line_separator('\n')
+  ], Opt0, csv_write_file/3),
+  option(token_separator(Tk_Sep), Opt0),
+  char_code(Tk_Sep, Tk_Code),
+  option(line_separator(Line_Sep), Opt0),
+  must_be(list, Rows),
+  maplist(csv_row, Rows),
+  setup_call_cleanup(
+    open(File_Name, write, Out),
+    maplist(csv_write_row(Out, Tk_Code, Line_Sep), Rows),
+    close(Out)).
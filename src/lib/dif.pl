@@ -7,6 +7,7 @@ arguments are different terms.
 
 :- use_module(library(atts)).
 :- use_module(library(dcgs)).
+:- use_module(library(iso_ext), [copy_term_nat/2]).
 :- use_module(library(lists), [append/3, maplist/3]).
 
 :- attribute dif/1.
@@ -87,7 +88,10 @@ verify_attributes(Var, Value, Goals) :-
 % ```
 dif(X, Y) :-
     X \== Y,
-    (   X \= Y -> true
+    % test unifiability on an attribute-free copy, so that the test
+    % doesn't wake goals suspended on X and Y by other coroutines.
+    copy_term_nat(X-Y, X1-Y1),
+    (   X1 \= Y1 -> true
     ;   term_variables(dif(X,Y), Vars),
         dif_set_variables(Vars, X, Y)
     ).
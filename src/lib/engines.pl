@@ -0,0 +1,167 @@
+/** Engines.
+
+An engine is a goal whose answers are computed on demand. It is
+created with `engine_create/3`, suspended until `engine_next/2` asks
+it for its next answer, and suspended again after each answer until
+the next request or until it is destroyed by `engine_destroy/1`.
+
+```
+?- engine_create(X, member(X, [a,b]), E),
+   engine_next(E, A1),
+   engine_next(E, A2),
+   \+ engine_next(E, _),
+   engine_destroy(E).
+   E = '$engine_0', A1 = a, A2 = b.
+```
+
+Engines run in the machine that created them, which switches to the
+stacks of an engine while it computes an answer, so that the goal of
+an engine sees the same database and modules as its creator. An
+engine is suspended with its choice points intact, so computing the
+next answer doesn't repeat the work of the previous ones.
+
+Answers and posted terms are copied between engines, like with
+`copy_term/2`: their variables are fresh, and attributes of variables
+are not preserved.
+*/
+
+:- module(engines, [engine_create/3,
+                    engine_create/4,
+                    engine_destroy/1,
+                    engine_fetch/1,
+                    engine_next/2,
+                    engine_next_reified/2,
+                    engine_post/2,
+                    engine_post/3,
+                    engine_self/1,
+                    engine_yield/1]).
+
+:- use_module(library(error)).
+:- use_module(library(lists), [member/2]).
+
+:- meta_predicate(engine_create(?, 0, ?)).
+
+%% engine_create(+Template, :Goal, -Engine).
+%
+% Like `engine_create/4` without options.
+engine_create(Template, Goal, Engine) :-
+    engine_create(Template, Goal, Engine, []).
+
+:- meta_predicate(engine_create(?, 0, ?, ?)).
+
+%% engine_create(+Template, :Goal, -Engine, +Options).
+%
+% Creates an engine whose answers are the instances of Template for
+% the solutions of Goal. Goal isn't called before the first request
+% for an answer. Options is a list of:
+%
+%  - `alias(Alias)`: name the engine Alias, which must not name
+%    another engine.
+engine_create(Template, Goal, Engine, Options) :-
+    must_be(var, Engine),
+    must_be(list, Options),
+    (   member(alias(Alias), Options) ->
+        must_be(atom, Alias)
+    ;   true
+    ),
+    (   '$engine_create'(Template-Goal, Alias, Engine) ->
+        true
+    ;   throw(error(permission_error(create, engine, Alias), engine_create/4))
+    ).
+
+%% engine_next(+Engine, -Term).
+%
+% Asks Engine for its next answer and unifies it with Term. Fails if
+% Engine has no more answers, and rethrows the exceptions thrown by the
+% goal of Engine. Engine has no more answers after an exception.
+engine_next(Engine, Term) :-
+    must_be_engine(Engine, engine_next/2),
+    '$engine_next'(Engine, infinite, Kind, Term0),
+    (   Kind == the ->
+        Term = Term0
+    ;   Kind == exception ->
+        throw(Term0)
+    ).
+
+%% engine_next_reified(+Engine, -Answer).
+%
+% Like `engine_next/2`, but Answer is `the(Term)` for an answer Term,
+% `no` if there are no more answers, and `throw(E)` for an exception
+% E. It never fails nor throws the exceptions of Engine.
+engine_next_reified(Engine, Answer) :-
+    catch(( engine_next(Engine, Term) ->
+            Answer = the(Term)
+          ; Answer = no
+          ),
+          E,
+          Answer = throw(E)).
+
+%% engine_post(+Engine, +Term).
+%
+% Posts a copy of Term to Engine, where it can be fetched with
+% `engine_fetch/1`.
+engine_post(Engine, Term) :-
+    must_be_engine(Engine, engine_post/2),
+    '$engine_post'(Engine, Term).
+
+%% engine_post(+Engine, +Term, -Answer).
+%
+% Posts Term to Engine and asks it for its next answer, like
+% `engine_post/2` followed by `engine_next/2`.
+engine_post(Engine, Term, Answer) :-
+    engine_post(Engine, Term),
+    engine_next(Engine, Answer).
+
+%% engine_destroy(+Engine).
+%
+% Destroys Engine, discarding the choice points of its goal. An
+% engine can't destroy itself or an engine it is computing an answer
+% for.
+engine_destroy(Engine) :-
+    must_be_engine(Engine, engine_destroy/1),
+    '$engine_destroy'(Engine).
+
+%% engine_self(-Engine).
+%
+% Engine is the engine calling this predicate. Fails if it is not
+% called by the goal of an engine.
+engine_self(Engine) :-
+    '$engine_self'(Engine).
+
+%% engine_fetch(-Term).
+%
+% Removes the oldest term posted to the calling engine and unifies it
+% with Term. Throws an existence error if no term was posted.
+engine_fetch(Term) :-
+    (   '$engine_self'(_) ->
+        (   '$engine_fetch'(Term0) ->
+            Term = Term0
+        ;   throw(error(existence_error(term, delivery), engine_fetch/1))
+        )
+    ;   throw(error(existence_error(engine, self), engine_fetch/1))
+    ).
+
+%% engine_yield(+Term).
+%
+% Makes Term the next answer of the calling engine without leaving
+% its goal, which resumes at the next request for an answer.
+engine_yield(Term) :-
+    (   '$engine_self'(_) ->
+        '$engine_yield'(Term)
+    ;   throw(error(existence_error(engine, self), engine_yield/1))
+    ).
+
+% throws the errors of the predicates resuming or destroying Engine,
+% which must exist and must not be running.
+must_be_engine(Engine, PI) :-
+    (   var(Engine) ->
+        instantiation_error(PI)
+    ;   \+ atom(Engine) ->
+        type_error(engine, Engine, PI)
+    ;   '$engine_status'(Engine, Status) ->
+        (   Status == running ->
+            throw(error(permission_error(access, engine, Engine), PI))
+        ;   true
+        )
+    ;   throw(error(existence_error(engine, Engine), PI))
+    ).
@@ -73,6 +73,7 @@ _lists of characters_. This is an ideal representation:
 		          rename_file/2,
 		          file_copy/2,
 		          delete_directory/1,
+                  delete_directory/2,
                   make_directory/1,
                   make_directory_path/1,
                   working_directory/2,
@@ -80,7 +81,11 @@ _lists of characters_. This is an ideal representation:
                   path_segments/2,
                   file_modification_time/2,
                   file_creation_time/2,
-                  file_access_time/2]).
+                  file_access_time/2,
+                  file_property/2,
+                  wildcard_match/2,
+                  file_glob/2,
+                  directory_walk/2]).
 
 :- use_module(library(error)).
 :- use_module(library(lists)).
@@ -171,6 +176,31 @@ delete_directory(Directory) :-
         must_be(chars, Directory),
         '$delete_directory'(Directory).
 
+%% delete_directory(+Directory, +Options).
+%
+% Like `delete_directory/1`, with a list of options:
+%
+%  * `recursive(true)`: the files and directories in Directory are
+%    deleted with it. With `recursive(false)`, the default, Directory
+%    must be empty.
+
+delete_directory(Directory, Options) :-
+        directory_must_exist(Directory, delete_directory/2),
+        must_be(list, Options),
+        maplist(delete_directory_option, Options),
+        (   member(recursive(true), Options) ->
+            '$delete_directory_tree'(Directory)
+        ;   '$delete_directory'(Directory)
+        ).
+
+delete_directory_option(Option) :-
+        (   var(Option) ->
+            instantiation_error(delete_directory/2)
+        ;   Option = recursive(Bool) ->
+            must_be(boolean, Bool)
+        ;   domain_error(delete_directory_option, Option, delete_directory/2)
+        ).
+
 file_must_exist(File, Context) :-
         (   file_exists(File) -> true
         ;   throw(error(existence_error(file, File), Context))
@@ -250,6 +280,144 @@ file_time_(File, Which, T) :-
         read_from_chars(T0, T).
 
 
+%% file_property(+File, ?Property).
+%
+% Property is a property of File, a file or directory that must
+% exist. On backtracking, all properties are enumerated:
+%
+%  * `type(Type)`: Type is `file`, `directory`, `symlink` or `other`.
+%    Symbolic links are not followed.
+%  * `size(Size)`: Size is the size of File in bytes.
+%  * `modification_time(T)`: T is the modification time of File, a
+%    time stamp as in `file_modification_time/2`.
+
+file_property(File, Property) :-
+        must_be(chars, File),
+        (   '$file_type'(File, Type) -> true
+        ;   throw(error(existence_error(file, File), file_property/2))
+        ),
+        (   var(Property) -> true
+        ;   memberchk(Property, [type(_), size(_), modification_time(_)]) -> true
+        ;   domain_error(file_property, Property, file_property/2)
+        ),
+        file_property_(Property, File, Type).
+
+file_property_(type(Type), _, Type).
+file_property_(size(Size), File, _) :-
+        '$file_size'(File, Size).
+file_property_(modification_time(T), File, _) :-
+        '$file_time'(File, modification, T0),
+        read_from_chars(T0, T).
+
+%% wildcard_match(+Pattern, +Name).
+%
+% True iff Name matches the glob pattern Pattern, where `*` matches
+% any sequence of characters, `?` matches any single character, and
+% `[...]` matches any character of a class such as `[abc]`, `[a-z]`
+% or its complement `[!a-z]`. All other characters match themselves.
+%
+% ```
+% ?- wildcard_match("*.pl", "files.pl").
+%    true.
+% ```
+
+wildcard_match(Pattern, Name) :-
+        must_be(chars, Pattern),
+        must_be(chars, Name),
+        '$wildcard_match'(Pattern, Name).
+
+%% file_glob(+Pattern, -Paths).
+%
+% Paths is the sorted list of paths of the files and directories that
+% exist and match Pattern. Each segment of Pattern is matched against
+% the names in a directory with `wildcard_match/2`, except `**`, which
+% matches any number of nested directories. Names that start with `.`
+% are only matched by segments that start with `.`.
+%
+% ```
+% ?- file_glob("src/**/*.pl", Paths).
+% ```
+
+file_glob(Pattern, Paths) :-
+        must_be(chars, Pattern),
+        can_be(list, Paths),
+        path_segments(Pattern, Segments),
+        findall(Path, glob_path(Segments, [], Path), Paths0),
+        sort(Paths0, Paths).
+
+% Prefix are the segments matched so far, in reverse order.
+glob_path([], Prefix, Path) :-
+        reverse(Prefix, Ps),
+        path_segments(Path, Ps),
+        '$file_type'(Path, _).
+glob_path([Segment|Segments], Prefix, Path) :-
+        (   Segment == "**" ->
+            (   glob_path(Segments, Prefix, Path)
+            ;   glob_name(Prefix, "*", Name, Dir),
+                '$file_type'(Dir, directory),
+                glob_path([Segment|Segments], [Name|Prefix], Path)
+            )
+        ;   wildcard_free(Segment) ->
+            glob_path(Segments, [Segment|Prefix], Path)
+        ;   glob_name(Prefix, Segment, Name, _),
+            glob_path(Segments, [Name|Prefix], Path)
+        ).
+
+% Name in the directory of Prefix matches Pattern, and Path is its path.
+glob_name(Prefix, Pattern, Name, Path) :-
+        reverse(Prefix, Ps),
+        (   Ps == [] ->
+            Dir = "."
+        ;   Ps == [[]] ->
+            path_segments(Dir, [[], []])
+        ;   path_segments(Dir, Ps)
+        ),
+        directory_exists(Dir),
+        directory_files(Dir, Names),
+        member(Name, Names),
+        (   Name = ['.'|_] ->
+            Pattern = ['.'|_]
+        ;   true
+        ),
+        '$wildcard_match'(Pattern, Name),
+        append(Ps, [Name], Ps1),
+        path_segments(Path, Ps1).
+
+wildcard_free(Segment) :-
+        \+ ( member(C, Segment), memberchk(C, "*?[") ).
+
+%% directory_walk(+Directory, -Path).
+%
+% Path is the path of a file or directory anywhere below Directory,
+% which must exist. On backtracking, the entries of each directory
+% are enumerated in the standard order of their names, each directory
+% just before its contents. Symbolic links to directories are not
+% entered.
+%
+% ```
+% ?- findall(Path, directory_walk("src", Path), Paths).
+% ```
+
+directory_walk(Directory, Path) :-
+        directory_must_exist(Directory, directory_walk/2),
+        path_segments(Directory, Ds0),
+        (   append(Ds, [[]], Ds0), Ds \== [] ->
+            true
+        ;   Ds = Ds0
+        ),
+        directory_walk_(Directory, Ds, Path).
+
+directory_walk_(Directory, Ds, Path) :-
+        directory_files(Directory, Names0),
+        sort(Names0, Names),
+        member(Name, Names),
+        append(Ds, [Name], Ps),
+        path_segments(Path0, Ps),
+        (   Path = Path0
+        ;   '$file_type'(Path0, directory),
+            directory_walk_(Path0, Ps, Path)
+        ).
+
 %% path_segments(?Ps, ?Segments).
 %
 % True iff Segments are the segments of Ps.
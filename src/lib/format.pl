@@ -574,15 +574,21 @@ portray_clause(Stream, Term) :-
         flush_output(Stream).
 
 portray_clause_(Term) -->
-        { unique_variable_names(numbervars, Term, VNs) },
-        portray_(Term, VNs), ".\n".
+        portray_clause_(Term, user).
 
-literal(Lit, VNs) -->
-        { write_term_to_chars(Lit, [quoted(true),variable_names(VNs),double_quotes(true)], Ls) },
+% portrays Term with the operators of Module.
+portray_clause_(Term, Module) -->
+        { unique_variable_names(numbervars, Term, VNs),
+          Opts = [quoted(true),variable_names(VNs),double_quotes(true),module(Module)] },
+        portray_(Term, Opts), ".\n".
+
+literal(Lit, Opts) -->
+        { write_term_to_chars(Lit, Opts, Ls) },
         (   { nonvar(Lit),
               \+ number(Lit),
               functor(Lit, F, A),
-              current_op(Pri, _, F),
+              memberchk(module(Module), Opts),
+              current_op(Pri, _, Module:F),
               (   A =:= 0
               ;   Pri >= 1000
               ) } ->
@@ -590,47 +596,47 @@ literal(Lit, VNs) -->
         ;   seq(Ls)
         ).
 
-literal_(Lit, VNs) -->
-        { phrase(literal(Lit, VNs), Ls) },
+literal_(Lit, Opts) -->
+        { phrase(literal(Lit, Opts), Ls) },
         seq(Ls),
         (   { phrase((...,[Last]), Ls), char_type(Last, graphic_token) } ->
             " "
         ;   ""
         ).
 
-portray_(Var, VNs) --> { var(Var) }, !, literal(Var, VNs).
-portray_((Head :- Body), VNs) --> !,
-        literal(Head, VNs), " :-\n",
-        body_(Body, 0, 3, VNs).
-portray_((Head --> Body), VNs) --> !,
-        literal(Head, VNs), " -->\n",
-        body_(Body, 0, 3, VNs).
-portray_(Any, VNs) --> literal_(Any, VNs).
+portray_(Var, Opts) --> { var(Var) }, !, literal(Var, Opts).
+portray_((Head :- Body), Opts) --> !,
+        literal(Head, Opts), " :-\n",
+        body_(Body, 0, 3, Opts).
+portray_((Head --> Body), Opts) --> !,
+        literal(Head, Opts), " -->\n",
+        body_(Body, 0, 3, Opts).
+portray_(Any, Opts) --> literal_(Any, Opts).
 
 
-body_(Var, C, I, VNs) --> { var(Var) }, !,
+body_(Var, C, I, Opts) --> { var(Var) }, !,
         indent_to(C, I),
-        literal(Var, VNs).
-body_((A,B), C, I, VNs) --> !,
-        body_(A, C, I, VNs), ",\n",
-        body_(B, 0, I, VNs).
-body_(Body, C, I, VNs) -->
+        literal(Var, Opts).
+body_((A,B), C, I, Opts) --> !,
+        body_(A, C, I, Opts), ",\n",
+        body_(B, 0, I, Opts).
+body_(Body, C, I, Opts) -->
         { body_if_then_else(Body, If, Then, Else) },
         !,
         indent_to(C, I),
         "(  ",
         { C1 is I + 3 },
-        body_(If, C1, C1, VNs), " ->\n",
-        body_(Then, 0, C1, VNs), "\n",
-        else_branch(Else, I, VNs).
-body_((A;B), C, I, VNs) --> !,
+        body_(If, C1, C1, Opts), " ->\n",
+        body_(Then, 0, C1, Opts), "\n",
+        else_branch(Else, I, Opts).
+body_((A;B), C, I, Opts) --> !,
         indent_to(C, I),
         "(  ",
         { C1 is I + 3 },
-        body_(A, C1, C1, VNs), "\n",
-        else_branch(B, I, VNs).
-body_(Goal, C, I, VNs) -->
-        indent_to(C, I), literal_(Goal, VNs).
+        body_(A, C1, C1, Opts), "\n",
+        else_branch(B, I, Opts).
+body_(Goal, C, I, Opts) -->
+        indent_to(C, I), literal_(Goal, Opts).
 
 
 % True iff Body has the shape ( If -> Then ; Else ).
@@ -640,18 +646,18 @@ body_if_then_else(Body, If, Then, Else) :-
         nonvar(A),
         A = (If -> Then).
 
-else_branch(Else, I, VNs) -->
+else_branch(Else, I, Opts) -->
         indent_to(0, I),
         ";  ",
         { C is I + 3 },
         (   { body_if_then_else(Else, If, Then, NextElse) } ->
-            body_(If, C, C, VNs), " ->\n",
-            body_(Then, 0, C, VNs), "\n",
-            else_branch(NextElse, I, VNs)
+            body_(If, C, C, Opts), " ->\n",
+            body_(Then, 0, C, Opts), "\n",
+            else_branch(NextElse, I, Opts)
         ;   { nonvar(Else), Else = ( A ; B ) } ->
-            body_(A, C, C, VNs), "\n",
-            else_branch(B, I, VNs)
-        ;   body_(Else, C, C, VNs), "\n",
+            body_(A, C, C, Opts), "\n",
+            else_branch(B, I, Opts)
+        ;   body_(Else, C, C, Opts), "\n",
             indent_to(0, I),
             ")"
         ).
@@ -699,21 +705,40 @@ a :-
 
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
-listing(PI) :-
+%% listing(+PI)
+%
+%  Portrays the clauses of the predicate indicated by PI, which may be
+%  qualified by the module defining it, with the operators of that
+%  module.
+
+:- meta_predicate(listing(:)).
+
+listing(PI0) :-
+        loader:strip_module(PI0, Module0, PI),
         nonvar(PI),
-        (   PI = Name/Arity0 ->
+        (   PI = Name0/Arity0 ->
             Arity = Arity0
-        ;   PI = Name//Arity0 ->
+        ;   PI = Name0//Arity0 ->
             Arity is Arity0 + 2
         ;   type_error(predicate_indicator, PI, listing/1)
         ),
+        (   nonvar(Name0), Name0 = Module:Name ->
+            true
+        ;   Module = Module0,
+            Name = Name0
+        ),
         functor(Head, Name, Arity),
-        \+ \+ clause(Head, _), % only true if there is at least one clause
-        (   clause(Head, Body),
+        (   Module == user ->
+            QualifiedHead = Head
+        ;   QualifiedHead = Module:Head
+        ),
+        \+ \+ clause(QualifiedHead, _), % only true if there is at least one clause
+        (   clause(QualifiedHead, Body),
             (   Body == true ->
-                portray_clause(Head)
-            ;   portray_clause((Head :- Body))
+                Clause = Head
+            ;   Clause = (Head :- Body)
             ),
+            phrase_to_stream(portray_clause_(Clause, Module), user_output),
             false
         ;   true
         ).
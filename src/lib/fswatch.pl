@@ -0,0 +1,176 @@
+/** Watching files and directories for changes.
+
+`watch_path/2` starts watching a file, or a directory with all files
+and directories below it, and `watch_event/2` blocks until one of them
+changes and yields the event:
+
+```
+?- watch_path("src", W),
+   watch_event(W, Event).
+   W = watch(fswatch_1), Event = modified("src/lib/files.pl").
+```
+
+Events are `created(Path)`, `modified(Path)` and `deleted(Path)`, for
+the paths of the files and directories below a watched directory, or
+the path of a watched file. Directories are only created and
+deleted, never modified: the changes of their contents are events of
+their own.
+
+Changes are reported by the file system notifications of the OS
+(inotify, FSEvents, kqueue or ReadDirectoryChangesW), or found by
+polling with the `interval(Seconds)` option of `watch_path/3`, e.g. on
+network file systems that send no notifications. Polling reads the
+contents of every watched file at each poll, so it's best kept to
+small trees. The events that follow each
+other within 50 milliseconds are taken as one batch of changes, in
+which repeated changes and the modifications of created or deleted
+paths are left out.
+
+Tools that reload programs on changes wait for events in a loop:
+
+```
+reload_on_change(W, File) :-
+    watch_event(W, _),
+    consult(File),
+    reload_on_change(W, File).
+```
+*/
+
+:- module(fswatch, [watch_path/2,
+                    watch_path/3,
+                    watch_event/2,
+                    watch_event/3,
+                    unwatch/1]).
+
+:- use_module(library(error)).
+:- use_module(library(files)).
+:- use_module(library(iso_ext), [bb_get/2, bb_put/2]).
+:- use_module(library(lists), [maplist/2, maplist/4, member/2]).
+
+%% watch_path(+Path, -Watch).
+%
+% Same as `watch_path(Path, Watch, [])`.
+watch_path(Path, Watch) :-
+    watch_path(Path, Watch, []).
+
+%% watch_path(+Path, -Watch, +Options).
+%
+% Watch is a new watch of the file or directory Path, which must
+% exist. Options are:
+%
+%  * `interval(Seconds)`: the paths are polled every Seconds instead
+%    of being watched with the file system notifications of the OS.
+watch_path(Path, watch(Key), Options) :-
+    must_be(chars, Path),
+    must_be(list, Options),
+    maplist(watch_path_option, Options),
+    (   member(interval(Interval), Options) ->
+        true
+    ;   Interval = native
+    ),
+    (   (   file_exists(Path)
+        ;   directory_exists(Path)
+        ) ->
+        true
+    ;   existence_error(file, Path, watch_path/3)
+    ),
+    '$fswatch_create'(Path, Interval, Key),
+    bb_put(Key, watch([])).
+
+watch_path_option(O) :-
+    (   var(O) ->
+        instantiation_error(watch_path/3)
+    ;   O = interval(Interval) ->
+        must_be_number(Interval, watch_path/3),
+        (   Interval > 0 ->
+            true
+        ;   domain_error(positive_number, Interval, watch_path/3)
+        )
+    ;   domain_error(watch_path_option, O, watch_path/3)
+    ).
+
+%% watch_event(+Watch, -Event).
+%
+% Same as `watch_event(Watch, Event, [])`.
+watch_event(Watch, Event) :-
+    watch_event(Watch, Event, []).
+
+%% watch_event(+Watch, -Event, +Options).
+%
+% Event is the next change of the paths of Watch, blocking until
+% there is one. The changes of one batch are yielded one by one in
+% the standard order of their paths. Options are:
+%
+%  * `timeout(Seconds)`: Event is `timeout` if there is no change
+%    within Seconds. The default is `infinite`.
+watch_event(Watch, Event, Options) :-
+    watch_state(Watch, watch_event/3, Key, State),
+    must_be(list, Options),
+    maplist(watch_event_option, Options),
+    (   member(timeout(Timeout), Options) ->
+        true
+    ;   Timeout = infinite
+    ),
+    next_event(State, Key, Timeout, Event0),
+    Event = Event0.
+
+watch_event_option(O) :-
+    (   var(O) ->
+        instantiation_error(watch_event/3)
+    ;   O = timeout(Timeout) ->
+        (   Timeout == infinite ->
+            true
+        ;   must_be_number(Timeout, watch_event/3),
+            (   Timeout >= 0 ->
+                true
+            ;   domain_error(not_less_than_zero, Timeout, watch_event/3)
+            )
+        )
+    ;   domain_error(watch_event_option, O, watch_event/3)
+    ).
+
+next_event(watch(Pending), Key, Timeout, Event) :-
+    (   Pending = [Event|Pending1] ->
+        bb_put(Key, watch(Pending1))
+    ;   '$fswatch_wait'(Key, Timeout, Kinds, Paths),
+        maplist(event, Kinds, Paths, Events),
+        (   Events = [Event|Pending1] ->
+            bb_put(Key, watch(Pending1))
+        ;   Timeout == infinite ->
+            next_event(watch([]), Key, Timeout, Event)
+        ;   Event = timeout
+        )
+    ).
+
+event(Kind, Path, Event) :-
+    Event =.. [Kind, Path].
+
+%% unwatch(+Watch).
+%
+% Stops watching the paths of Watch, which can no longer be used.
+unwatch(Watch) :-
+    watch_state(Watch, unwatch/1, Key, _),
+    '$fswatch_remove'(Key),
+    bb_put(Key, unwatched).
+
+watch_state(Watch, Context, Key, State) :-
+    (   var(Watch) ->
+        instantiation_error(Context)
+    ;   Watch = watch(Key),
+        atom(Key),
+        catch(bb_get(Key, State), _, false),
+        State = watch(_) ->
+        true
+    ;   existence_error(watch, Watch, Context)
+    ).
+
+must_be_number(X, Context) :-
+    (   var(X) ->
+        instantiation_error(Context)
+    ;   number(X) ->
+        true
+    ;   type_error(number, X, Context)
+    ).
+
+existence_error(Type, Culprit, Context) :-
+    throw(error(existence_error(Type, Culprit), Context)).
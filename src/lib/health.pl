@@ -0,0 +1,192 @@
+/** Health checks for services built on Scryer Prolog.
+
+Orchestration systems probe services for whether they are _ready_ to
+take requests, e.g. whether their database is reachable. This library
+keeps the readiness checks of a program, named goals that succeed if
+the part of the service they check is ready, and reports them together
+with the memory used by the machine.
+
+```
+?- add_readiness_check(db, db_connected),
+   health_report(Report).
+   Report = health(pass, [check('memory:heap',pass,bytes(...)), ...,
+                          check('readiness:db',pass,none)]).
+```
+
+`health_json/1` writes reports in the format of the IETF draft "Health
+Check Response Format for HTTP APIs", as served with the media type
+`application/health+json`. The Rust API reports the same through
+`Machine::health`, and whether the machine is stuck in a query through
+`Machine::health_monitor`.
+*/
+
+:- module(health, [add_readiness_check/2,
+                   remove_readiness_check/1,
+                   readiness/2,
+                   set_memory_limit/1,
+                   health_report/1,
+                   health_json/1]).
+
+:- use_module(library(charsio), [write_term_to_chars/3]).
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(iso_ext), [bb_get/2, bb_put/2]).
+:- use_module(library(lists), [append/3, member/2]).
+:- use_module(library(serialization/json)).
+
+% readiness_check(Name, Goal): Goal checks whether Name is ready.
+:- dynamic(readiness_check/2).
+
+:- meta_predicate(add_readiness_check(?, 0)).
+
+%% add_readiness_check(+Name, :Goal).
+%
+% Adds the readiness check Name, which passes if Goal succeeds. The
+% check replaces any earlier check named Name.
+add_readiness_check(Name, Goal0) :-
+    must_be(atom, Name),
+    loader:strip_module(Goal0, Module, Goal),
+    retractall(readiness_check(Name, _)),
+    assertz(readiness_check(Name, Module:Goal)).
+
+%% remove_readiness_check(+Name).
+%
+% Removes the readiness check Name, if there is one.
+remove_readiness_check(Name) :-
+    must_be(atom, Name),
+    retractall(readiness_check(Name, _)).
+
+%% readiness(-Status, -Checks).
+%
+% Runs the readiness checks in the order they were added, each as
+% `once/1`. Checks is a list of pairs `Name-Result`, where Result is
+% `pass` if the goal of check Name succeeded, `fail` if it failed and
+% `error(E)` if it threw E. Status is `pass` if all checks passed, and
+% `fail` otherwise.
+readiness(Status, Checks) :-
+    findall(Name-Result,
+            (   readiness_check(Name, Goal),
+                readiness_result(Goal, Result)
+            ),
+            Checks),
+    (   member(_-Result, Checks),
+        Result \== pass ->
+        Status = fail
+    ;   Status = pass
+    ).
+
+readiness_result(Goal, Result) :-
+    (   catch(Goal, E, true) ->
+        (   var(E) ->
+            Result = pass
+        ;   Result = error(E)
+        )
+    ;   Result = fail
+    ).
+
+%% set_memory_limit(+Limit).
+%
+% Reports memory pressure, the `warn` status, once the heap, stack and
+% trail use more than Limit bytes together. Limit is `none`, the
+% default, or a positive integer.
+set_memory_limit(Limit) :-
+    (   Limit == none ->
+        true
+    ;   must_be(integer, Limit),
+        (   Limit > 0 ->
+            true
+        ;   domain_error(positive_integer, Limit, set_memory_limit/1)
+        )
+    ),
+    bb_put('$health_memory_limit', Limit).
+
+%% health_report(-Report).
+%
+% Report is `health(Status, Checks)`, where Checks are terms
+% `check(Name, Status, Details)`:
+%
+%  * `check('memory:heap', S, bytes(N))`, and likewise `'memory:stack'`
+%    and `'memory:trail'`, the bytes used by the heap, the stack and
+%    the trail. S is `warn` if they exceed the limit of
+%    `set_memory_limit/1` together, and `pass` otherwise.
+%  * `check('readiness:Name', S, Details)` for each readiness check,
+%    where S is `pass` or `fail` and Details is `none`, or `error(E)`
+%    if its goal threw E.
+%
+% Status is `fail` if a readiness check failed, `warn` if the memory
+% limit is exceeded, and `pass` otherwise.
+health_report(health(Status, Checks)) :-
+    '$memory_usage'(Heap, Stack, Trail),
+    (   bb_get('$health_memory_limit', Limit),
+        integer(Limit),
+        Heap + Stack + Trail > Limit ->
+        MemoryStatus = warn
+    ;   MemoryStatus = pass
+    ),
+    MemoryChecks = [check('memory:heap', MemoryStatus, bytes(Heap)),
+                    check('memory:stack', MemoryStatus, bytes(Stack)),
+                    check('memory:trail', MemoryStatus, bytes(Trail))],
+    readiness(ReadinessStatus, Readiness),
+    findall(check(CheckName, CheckStatus, Details),
+            (   member(Name-Result, Readiness),
+                atom_concat('readiness:', Name, CheckName),
+                readiness_check_status(Result, CheckStatus, Details)
+            ),
+            ReadinessChecks),
+    append(MemoryChecks, ReadinessChecks, Checks),
+    (   ReadinessStatus == fail ->
+        Status = fail
+    ;   Status = MemoryStatus
+    ).
+
+readiness_check_status(pass, pass, none).
+readiness_check_status(fail, fail, none).
+readiness_check_status(error(E), fail, error(E)).
+
+%% health_json(-Chars).
+%
+% Chars is the report of `health_report/1` as JSON, in the health check
+% response format, e.g.
+%
+% ```
+% {"status":"pass","checks":{"memory:heap":[{"status":"pass",
+%  "observedValue":52416,"observedUnit":"bytes"}],...}}
+% ```
+%
+% The `output` of a readiness check whose goal threw an exception is
+% the exception, written as by `writeq/1`.
+health_json(Chars) :-
+    health_report(health(Status, Checks)),
+    findall(string(NameChars)-list([pairs(Pairs)]),
+            (   member(check(Name, CheckStatus, Details), Checks),
+                atom_chars(Name, NameChars),
+                check_pairs(CheckStatus, Details, Pairs)
+            ),
+            CheckPairs),
+    atom_chars(Status, StatusChars),
+    phrase(json_chars(pairs([string("status")-string(StatusChars),
+                             string("checks")-pairs(CheckPairs)])),
+           Chars).
+
+check_pairs(Status, Details, [string("status")-string(StatusChars)|Pairs]) :-
+    atom_chars(Status, StatusChars),
+    details_pairs(Details, Pairs).
+
+details_pairs(none, []).
+details_pairs(bytes(N), [string("observedValue")-number(N),
+                         string("observedUnit")-string("bytes")]).
+details_pairs(error(E), [string("output")-string(Output)]) :-
+    error_output(E, Output).
+
+error_output(E, Output) :-
+    write_term_to_chars(E, [quoted(true)], Output).
+
+% the check of a report of health_report/1 with the exception of a
+% readiness check written to output(Chars), as Machine::health reports
+% it.
+check_output(check(Name, Status, Details0), check(Name, Status, Details)) :-
+    (   Details0 = error(E) ->
+        error_output(E, Output),
+        Details = output(Output)
+    ;   Details = Details0
+    ).
@@ -0,0 +1,267 @@
+/** HTML generation.
+
+HTML documents are written as Prolog terms and rendered to strings,
+escaping the text and attribute values they contain:
+
+```
+?- html_chars(div([class=note], [h1("Fish & Chips"), p(["Only ", b(5), " left"])]), Cs).
+   Cs = "<div class=\"note\"><h1>Fish &amp; Chips</h1><p>Only <b>5</b> left</p></div>".
+```
+
+An HTML term is one of:
+
+  - a list of HTML terms, rendered one after the other.
+  - a string (list of characters), an atom or a number, rendered as
+    text in which `&`, `<` and `>` are escaped.
+  - `\Raw`, with Raw a string or an atom rendered unescaped, e.g. for
+    the contents of `script` elements or markup rendered elsewhere.
+  - `Tag(Content)`, an element without attributes, or `Tag(Attributes)`
+    if Tag is a void element such as `br`, `img` or `input`.
+  - `Tag(Attributes, Content)`, an element with attributes.
+  - `element(Tag, Attributes, Content)`, as `library(sgml)` parses
+    documents to, so that parsed documents can be rendered again.
+
+Attributes are lists of `Name=Value` or `Name(Value)`, where values
+are strings, atoms or numbers escaped as needed, and of atoms `Name`
+for attributes without values, e.g. `input([type=checkbox, checked])`.
+
+The handlers of `library(http/http_server)` can answer with HTML terms
+by giving the body `html(HTML)`.
+*/
+
+:- module(html_write, [html//1,
+                       html_chars/2,
+                       html_chars/3]).
+
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(lists)).
+
+%% html(+HTML)//
+%
+% Describes the string of HTML, rendered compactly.
+html(HTML) -->
+    { html_nodes(HTML, Nodes, html//1) },
+    nodes(Nodes, compact, 0).
+
+%% html_chars(+HTML, -Chars).
+%
+% Same as `html_chars(HTML, Chars, [])`.
+html_chars(HTML, Chars) :-
+    html_chars(HTML, Chars, []).
+
+%% html_chars(+HTML, -Chars, +Options).
+%
+% Chars is the string of HTML. Options are:
+%
+%  * `pretty(Bool)`: if `true`, elements that only contain block
+%    elements, such as `div`, `ul` and `li`, are written with each of
+%    their children on a line of its own, indented by two spaces for
+%    each level. Text, inline elements such as `a` and `b`, and the
+%    contents of `pre`, `textarea`, `script` and `style` elements, are
+%    written as they are, so that no whitespace is added to the text
+%    of the document. The default is `false`.
+%  * `doctype(Bool)`: if `true`, the string starts with the line
+%    `<!DOCTYPE html>`. The default is `false`.
+html_chars(HTML, Chars, Options) :-
+    must_be(list, Options),
+    option(pretty, Options, Pretty),
+    option(doctype, Options, Doctype),
+    html_nodes(HTML, Nodes, html_chars/3),
+    (   Pretty == true -> Layout = pretty
+    ;   Layout = compact
+    ),
+    phrase((doctype(Doctype), nodes(Nodes, Layout, 0)), Chars).
+
+option(Name, Options, Value) :-
+    Option =.. [Name, Value0],
+    (   member(Option0, Options), var(Option0) ->
+        instantiation_error(html_chars/3)
+    ;   memberchk(Option, Options) ->
+        (   memberchk(Value0, [true, false]) ->
+            Value = Value0
+        ;   domain_error(html_option, Option, html_chars/3)
+        )
+    ;   Value = false
+    ).
+
+doctype(true) --> "<!DOCTYPE html>\n".
+doctype(false) --> [].
+
+% html_nodes(HTML, Nodes, Context): Nodes are the text(Cs), raw(Cs) and
+% element(Tag, Attributes, Nodes) terms HTML describes, with the
+% attributes as Name-Value pairs of atoms and strings.
+html_nodes(HTML, Nodes, Context) :-
+    html_nodes(HTML, Nodes, [], Context).
+
+html_nodes(HTML, Nodes0, Nodes, Context) :-
+    (   var(HTML) ->
+        instantiation_error(Context)
+    ;   HTML == [] ->
+        Nodes0 = Nodes
+    ;   chars(HTML) ->
+        Nodes0 = [text(HTML)|Nodes]
+    ;   HTML = [_|_] ->
+        list_nodes(HTML, Nodes0, Nodes, Context)
+    ;   HTML = \Raw ->
+        text_chars(Raw, Cs, Context),
+        Nodes0 = [raw(Cs)|Nodes]
+    ;   atomic(HTML) ->
+        text_chars(HTML, Cs, Context),
+        Nodes0 = [text(Cs)|Nodes]
+    ;   HTML = element(Tag, Attributes, Content) ->
+        element_node(Tag, Attributes, Content, Node, Context),
+        Nodes0 = [Node|Nodes]
+    ;   HTML =.. [Tag, Arg], void_element(Tag) ->
+        element_node(Tag, Arg, [], Node, Context),
+        Nodes0 = [Node|Nodes]
+    ;   HTML =.. [Tag, Content] ->
+        element_node(Tag, [], Content, Node, Context),
+        Nodes0 = [Node|Nodes]
+    ;   HTML =.. [Tag, Attributes, Content] ->
+        element_node(Tag, Attributes, Content, Node, Context),
+        Nodes0 = [Node|Nodes]
+    ;   type_error(html, HTML, Context)
+    ).
+
+list_nodes(HTML, Nodes0, Nodes, Context) :-
+    (   var(HTML) ->
+        instantiation_error(Context)
+    ;   HTML == [] ->
+        Nodes0 = Nodes
+    ;   HTML = [H|Hs] ->
+        html_nodes(H, Nodes0, Nodes1, Context),
+        list_nodes(Hs, Nodes1, Nodes, Context)
+    ;   type_error(list, HTML, Context)
+    ).
+
+element_node(Tag, Attributes0, Content, element(Tag, Attributes, Nodes), Context) :-
+    (   atom(Tag) -> true
+    ;   type_error(atom, Tag, Context)
+    ),
+    must_be(list, Attributes0),
+    (   chars(Attributes0) ->
+        type_error(html_attributes, Attributes0, Context)
+    ;   true
+    ),
+    maplist(attribute_pair(Context), Attributes0, Attributes),
+    (   void_element(Tag), Content \== [] ->
+        type_error(void_element_content, Content, Context)
+    ;   html_nodes(Content, Nodes, Context)
+    ).
+
+attribute_pair(Context, Attribute, Name-Value) :-
+    (   var(Attribute) ->
+        instantiation_error(Context)
+    ;   Attribute = (Name = Value0) ->
+        attribute_value(Name, Value0, Value, Context)
+    ;   atom(Attribute) ->
+        Name = Attribute,
+        Value = none
+    ;   Attribute =.. [Name, Value0] ->
+        attribute_value(Name, Value0, Value, Context)
+    ;   type_error(html_attribute, Attribute, Context)
+    ).
+
+attribute_value(Name, Value0, some(Cs), Context) :-
+    (   atom(Name) -> true
+    ;   type_error(html_attribute, Name=Value0, Context)
+    ),
+    text_chars(Value0, Cs, Context).
+
+text_chars(Text, Cs, Context) :-
+    (   var(Text) ->
+        instantiation_error(Context)
+    ;   Text == [] ->
+        Cs = []
+    ;   chars(Text) ->
+        Cs = Text
+    ;   atom(Text) ->
+        atom_chars(Text, Cs)
+    ;   number(Text) ->
+        number_chars(Text, Cs)
+    ;   type_error(text, Text, Context)
+    ).
+
+chars(Term) :-
+    '$skip_max_list'(_, _, Term, Tail),
+    Tail == [],
+    Term = [_|_],
+    \+ ( member(C, Term), \+ ( atom(C), atom_length(C, 1) ) ).
+
+void_element(Tag) :-
+    memberchk(Tag, [area, base, br, col, embed, hr, img, input, link,
+                    meta, source, track, wbr]).
+
+block_node(element(Tag, _, _)) :-
+    \+ inline_element(Tag).
+
+inline_element(Tag) :-
+    memberchk(Tag, [a, abbr, b, bdi, bdo, br, button, cite, code, data,
+                    dfn, em, i, img, input, kbd, label, mark, q, s, samp,
+                    select, small, span, strong, sub, sup, textarea, time,
+                    u, var, wbr]).
+
+% elements whose contents are never reindented.
+verbatim_element(Tag) :-
+    memberchk(Tag, [pre, textarea, script, style]).
+
+nodes([], _, _) --> [].
+nodes([Node|Nodes], Layout, Depth) -->
+    node(Node, Layout, Depth),
+    nodes(Nodes, Layout, Depth).
+
+node(text(Cs), _, _) --> escaped(Cs, text).
+node(raw(Cs), _, _) --> seq(Cs).
+node(element(Tag, Attributes, Nodes), Layout, Depth) -->
+    "<", atom_chars_(Tag), attributes(Attributes), ">",
+    (   { void_element(Tag) } ->
+        []
+    ;   (   { Layout == pretty, Nodes = [_|_], \+ verbatim_element(Tag),
+              \+ ( member(Node, Nodes), \+ block_node(Node) ) } ->
+            { Depth1 is Depth + 1 },
+            children(Nodes, Depth1),
+            newline(Depth)
+        ;   nodes(Nodes, compact, Depth)
+        ),
+        "</", atom_chars_(Tag), ">"
+    ).
+
+children([], _) --> [].
+children([Node|Nodes], Depth) -->
+    newline(Depth),
+    node(Node, pretty, Depth),
+    children(Nodes, Depth).
+
+newline(Depth) -->
+    "\n",
+    { N is 2 * Depth, length(Indent, N), maplist(=(' '), Indent) },
+    seq(Indent).
+
+attributes([]) --> [].
+attributes([Name-Value|Attributes]) -->
+    " ", atom_chars_(Name),
+    (   { Value = some(Cs) } ->
+        "=\"", escaped(Cs, quoted), "\""
+    ;   []
+    ),
+    attributes(Attributes).
+
+atom_chars_(Atom) -->
+    { atom_chars(Atom, Cs) },
+    seq(Cs).
+
+escaped([], _) --> [].
+escaped([C|Cs], Where) -->
+    { entity(Where, C, Es) },
+    seq(Es),
+    escaped(Cs, Where).
+
+entity(Where, C, Es) :-
+    (   C == (&) -> Es = "&amp;"
+    ;   C == (<) -> Es = "&lt;"
+    ;   C == (>) -> Es = "&gt;"
+    ;   Where == quoted, C == '"' -> Es = "&quot;"
+    ;   Where == quoted, C == '\'' -> Es = "&#39;"
+    ;   Es = [C]
+    ).
@@ -7,6 +7,9 @@
 
 This library contains the predicate `http_open/3` which allows you to perform HTTP(S) calls.
 Useful for making API calls, or parsing websites. It uses Hyper underneath.
+
+The body of the reply is read from the network as the stream is read,
+so large replies needn't fit in memory.
 */
 
 :- module(http_open, [http_open/3]).
@@ -27,6 +30,14 @@ Useful for making API calls, or parsing websites. It uses Hyper underneath.
 %   * `request_headers(+RequestHeaders)`: Headers to be used in the request
 %   * `headers(-ListHeaders)`: Unifies with a list with all headers returned in the response
 %   * `status_code(-Code)`: Unifies with the status code of the request (200, 201, 404, ...)
+%   * `max_redirect(+Max)`: Follows at most Max redirects, 10 by default. With 0, redirects are
+%     not followed, and the status code and headers are those of the redirect. Fails if there are
+%     more redirects.
+%   * `final_url(-URL)`: Unifies with the address of the reply, after redirects
+%   * `timeout(+Seconds)`: Fails if there is no reply within Seconds. Reading the body from
+%     Stream throws an error if no part of it arrives within Seconds. `infinite` by default.
+%   * `cert_verify(+Boolean)`: If `false`, the certificate of an HTTPS server is not verified,
+%     as is needed for servers with self-signed certificates. `true` by default.
 %
 % Example:
 %
@@ -42,7 +53,12 @@ http_open(Address, Response, Options) :-
     ( member(status_code(Code), OptionValues) -> true; true),
     ( member(headers(Headers), OptionValues) -> true; true),
     ( member(size(Size), OptionValues) -> member('content-length'(Size), Headers); true),
-    '$http_open'(Address, Response, Method, Code, Data, Headers, RequestHeaders).
+    ( member(max_redirect(MaxRedirect), OptionValues) -> true; MaxRedirect = 10),
+    ( member(final_url(FinalURL), OptionValues) -> true; true),
+    ( member(timeout(Timeout), OptionValues) -> true; Timeout = infinite),
+    ( member(cert_verify(CertVerify), OptionValues) -> true; CertVerify = true),
+    '$http_open'(Address, Response, Method, Code, Data, Headers, RequestHeaders,
+                 Timeout, MaxRedirect, CertVerify, FinalURL).
 
 parse_http_options(Options, OptionValues) :-
     maplist(parse_http_options_, Options, OptionValues).
@@ -71,3 +87,27 @@ parse_http_options_(request_headers(Headers), request_headers(Headers)) :-
 parse_http_options_(size(Size), size(Size)).
 parse_http_options_(status_code(Code), status_code(Code)).
 parse_http_options_(headers(Headers), headers(Headers)).
+
+parse_http_options_(max_redirect(Max), max_redirect(Max)) :-
+    (  var(Max) ->
+       throw(error(instantiation_error, http_open/3))
+    ;  integer(Max), Max >= 0 -> true
+    ;  throw(error(domain_error(http_option, max_redirect(Max)), _))
+    ).
+
+parse_http_options_(final_url(URL), final_url(URL)).
+
+parse_http_options_(timeout(Timeout), timeout(Timeout)) :-
+    (  var(Timeout) ->
+       throw(error(instantiation_error, http_open/3))
+    ;  Timeout == infinite -> true
+    ;  number(Timeout), Timeout > 0 -> true
+    ;  throw(error(domain_error(http_option, timeout(Timeout)), _))
+    ).
+
+parse_http_options_(cert_verify(CertVerify), cert_verify(CertVerify)) :-
+    (  var(CertVerify) ->
+       throw(error(instantiation_error, http_open/3))
+    ;  member(CertVerify, [true, false]) -> true
+    ;  throw(error(domain_error(http_option, cert_verify(CertVerify)), _))
+    ).
@@ -33,6 +33,13 @@ run:-
   ]).
 ```
 
+Handlers can also be registered with `http_handler/3` and removed with
+`http_delete_handler/2`, before or while the server runs. The registry
+of handlers is shared by every thread of the process. The server
+looks up the handlers of the registry for every request, and calls
+the handler predicates by name, so that registrations and reloaded
+predicates (e.g. with `make/0`) take effect from the next request on.
+
 Every handler predicate will have at least 2-arity, with Request and Response.
 Although you can work directly with `http_request` and `http_response` terms, it is
 recommeded to use the helper predicates, which are easier to understand and cleaner:
@@ -43,30 +50,103 @@ recommeded to use the helper predicates, which are easier to understand and clea
    - `http_body(Response/Request, binary(Body))`
    - `http_body(Request, form(Form))`
    - `http_body(Response, file(Filename))`
+   - `http_body(Response, html(HTML))`
    - `http_redirect(Response, Url)`
    - `http_query(Request, QueryName, QueryValue)`
+   - `http_parameters(Request, Parameters)`
+   - `http_cookies(Request, Cookies)`
 
 Some things that are still missing:
 
    - Read forms in multipart format
    - Session handling via cookies
-   - HTML Templating (but you can use [Teruel](https://github.com/aarroyoc/teruel/), [Marquete](https://github.com/aarroyoc/marquete/) or [Djota](https://github.com/aarroyoc/djota) for that)
+
+Requests are handled one at a time unless the server is started with
+the option `workers(N)` of `http_listen/3`, so that a slow handler
+doesn't keep the others waiting. Requests that arrive while 1024 are
+waiting for a handler are answered with 503 Service Unavailable.
+
+**Warning:** every worker but the calling thread has a machine of its
+own (see `library(threads)`), which consults the files consulted
+before the server is started. Workers don't share their databases:
+the clauses a handler asserts or retracts are only seen by the
+requests handled by the same worker, and predicates loaded after the
+server is started only by the calling thread. Keep the state shared
+by the requests of a server with several workers outside the
+database, for example in message queues of `library(threads)`.
+
+A body of the form `stream(Goal)` is written by `call(Goal, Stream)`
+and sent in chunks as Stream is flushed (e.g. by `format/3`), so that
+long or endless responses start before they are complete:
+
+```
+count_handler(Request, Response) :-
+  http_body(Response, stream(count_to(10))).
+
+count_to(N, Stream) :-
+  between(1, N, I),
+  format(Stream, "~d~n", [I]),
+  sleep(1),
+  I == N.
+```
+
+A body of the form `websocket(Goal)` upgrades the connection to a
+WebSocket, over which `call(Goal, WebSocket)` exchanges messages with
+the predicates of `library(websocket)`:
+
+```
+echo_handler(Request, Response) :-
+  http_body(Response, websocket(echo)).
+
+echo(WebSocket) :-
+  ws_receive(WebSocket, Message),
+  (   Message = text(_) ->
+      ws_send(WebSocket, Message),
+      echo(WebSocket)
+  ;   true
+  ).
+```
+
+Pages can be rendered from templates with `library(templates)`, or
+written as terms of `library(html_write)`:
+
+```
+page_handler(Request, Response) :-
+  http_body(Response, html([h1("Hello"), p([class=intro], "Welcome to Scryer Prolog!")])).
+```
+
+Parameters of the query or of a form are converted and checked with
+`http_parameters/2`. A request whose parameters are missing or invalid
+is answered with 400 Bad Request, without further checks in the handler:
+
+```
+age_handler(Request, Response) :-
+  http_parameters(Request, [name(Name, []), age(Age, [integer, between(0, 130)])]),
+  phrase(format_("~s is ~d years old", [Name, Age]), Text),
+  http_body(Response, text(Text)).
+```
 */
 
 
 :- module(http_server, [
 	      http_listen/2,
 	      http_listen/3,
+	      http_handler/3,
+	      http_delete_handler/2,
+	      http_current_handler/3,
 	      http_headers/2,
 	      http_status_code/2,
 	      http_body/2,
 	      http_redirect/2,
 	      http_query/3,
+	      http_parameters/2,
+	      http_cookies/2,
 	      http_basic_auth/4
 ]).
 
 :- meta_predicate(http_listen(?, :)).
 :- meta_predicate(http_listen(?, :, ?)).
+:- meta_predicate(http_handler(?, ?, :)).
 
 :- meta_predicate(http_basic_auth(:, :, ?, ?)).
 
@@ -74,10 +154,18 @@ Some things that are still missing:
 :- use_module(library(crypto)).
 :- use_module(library(error)).
 :- use_module(library(format)).
+:- use_module(library(html_write), [html_chars/2]).
 :- use_module(library(iso_ext)).
 :- use_module(library(lists)).
+:- use_module(library(message_queues)).
 :- use_module(library(pio)).
 :- use_module(library(time)).
+:- use_module(library(websocket), [ws_close/3]).
+
+% the registered handlers, as handler(Method, Path, Module:Goal), as
+% of the generation of the registry.
+:- dynamic(handler/3).
+:- dynamic(handlers_generation/1).
 
 %% http_listen(+Port, +Handlers).
 %
@@ -93,12 +181,17 @@ http_listen(Port, Module:Handlers0) :-
 % Listens for HTTP connections on port Port. Each handler on the list Handlers should be of the form: `HttpVerb(PathUnification, Predicate)`.
 % For example: `get(user/User, get_info(User))` will match an HTTP request that is a GET, the path unifies with /user/User (where User is a variable)
 % and it will call `get_info` with three arguments: an `http_request` term, an `http_response` term and User.
+% The handlers are registered as with `http_handler/3`, in addition to those registered before.
 %
 % The following options are supported:
 %
 % - `tls_key(+Key)` - a TLS key for HTTPS (string)
 % - `tls_cert(+Cert)` - a TLS cert for HTTPS (string)
 % - `content_length_limit(+Limit)` - maximum length (in bytes) for the incoming bodies. By default, 32KB.
+% - `workers(+N)` - the number of requests handled concurrently. By default, 1. Every worker but the
+%   calling thread is a background thread (see `library(threads)`) with a machine of its own, which
+%   consults the files consulted before the server is started. The workers share the registry of
+%   handlers, but not their databases (see the warning above).
 %
 % In order to have a HTTPS server (instead of plain HTTP), both `tls_key` and `tls_cert` options must be provided.
 http_listen(Port, Module:Handlers0, Options) :-
@@ -112,18 +205,141 @@ module_qualification(M, H0, H) :-
     H0 =.. [Method, Path, Goal],
     H =.. [Method, Path, M:Goal].
 
+%% http_handler(+Method, +Path, :Handler).
+%
+% Registers Handler for the requests of method Method (in lowercase, e.g. `get`) whose path unifies with
+% Path, like the handler `Method(Path, Handler)` of `http_listen/3`. A handler registered before for the
+% same Method and Path (up to variable renaming) is replaced. The running servers of every thread use
+% the handler from their next request on.
+http_handler(Method, Path, Module:Handler) :-
+    must_be(atom, Method),
+    (   callable(Handler) ->
+        true
+    ;   type_error(callable, Handler, http_handler/3)
+    ),
+    update_handlers(replace_handler(handler(Method, Path, Module:Handler))).
+
+%% http_delete_handler(+Method, +Path).
+%
+% Removes the handler registered for Method and Path (up to variable renaming), if any.
+http_delete_handler(Method, Path) :-
+    must_be(atom, Method),
+    update_handlers(delete_handler(Method, Path)).
+
+replace_handler(handler(Method, Path, Handler), Handlers0, Handlers) :-
+    delete_handler(Method, Path, Handlers0, Handlers1),
+    append(Handlers1, [handler(Method, Path, Handler)], Handlers).
+
+delete_handler(_, _, [], []).
+delete_handler(Method, Path, [H|Hs0], Hs) :-
+    (   H = handler(Method, Path0, _),
+        subsumes_term(Path0, Path),
+        subsumes_term(Path, Path0) ->
+        Hs = Hs1
+    ;   Hs = [H|Hs1]
+    ),
+    delete_handler(Method, Path, Hs0, Hs1).
+
+%% http_current_handler(?Method, ?Path, ?Handler).
+%
+% Handler is registered for the requests of method Method whose path unifies with Path. Handler is
+% module-qualified.
+http_current_handler(Method, Path, Handler) :-
+    sync_handlers,
+    handler(Method, Path, Handler).
+
+% the registry of handlers is the single message handlers(Generation,
+% Handlers) of the queue '$http_handlers', which is shared by the
+% machines of every thread. Taking the message locks the registry
+% until it is sent back, which the cleanup of the update does however
+% the update ends.
+update_handlers(Update) :-
+    catch(( message_queue_create('$http_handlers'),
+            thread_send_message('$http_handlers', handlers(0, []))
+          ),
+          error(permission_error(_, _, _), _),
+          true),
+    setup_call_cleanup(thread_get_message('$http_handlers', handlers(G0, Handlers0)),
+                       once(( call(Update, Handlers0, Handlers),
+                              G is G0 + 1
+                            )),
+                       return_handlers(G0-Handlers0, G-Handlers)),
+    sync_handlers.
+
+% sends the registry back to its queue, updated if the update
+% succeeded.
+return_handlers(G0-Handlers0, G-Handlers) :-
+    (   integer(G) ->
+        thread_send_message('$http_handlers', handlers(G, Handlers))
+    ;   thread_send_message('$http_handlers', handlers(G0, Handlers0))
+    ).
+
+% updates the handlers of the machine to those of the registry, unless
+% the registry is being updated.
+sync_handlers :-
+    (   catch(thread_peek_message('$http_handlers', handlers(G, Handlers)),
+              error(existence_error(_, _), _),
+              false),
+        \+ handlers_generation(G) ->
+        retractall(handler(_, _, _)),
+        maplist(assertz, Handlers),
+        retractall(handlers_generation(_)),
+        assertz(handlers_generation(G))
+    ;   true
+    ).
+
 http_listen_(Port, Handlers, Options) :-
-    parse_options(Options, TLSKey, TLSCert, ContentLengthLimit),
+    maplist(register_handler, Handlers),
+    parse_options(Options, TLSKey, TLSCert, ContentLengthLimit, Workers),
     phrase(format_("0.0.0.0:~d", [Port]), Addr),
     '$http_listen'(Addr, HttpListener, TLSKey, TLSCert, ContentLengthLimit),!,
     format("Listening at ~s\n", [Addr]),
-    http_loop(HttpListener, Handlers).
+    start_workers(Workers, Addr),
+    http_loop(HttpListener).
+
+% starts the workers besides the calling thread, which accept the
+% requests of the server at Addr.
+start_workers(Workers, Addr) :-
+    (   Workers > 1 ->
+        '$loaded_files'(Files),
+        N is Workers - 1,
+        length(Ids, N),
+        maplist(start_worker(Files, Addr), Ids)
+    ;   true
+    ).
+
+start_worker(Files, Addr, Id) :-
+    write_term_to_chars((use_module(library(http/http_server)),
+                         http_server:http_worker(Files, Addr)),
+                        [quoted(true), max_depth(0)],
+                        Chars),
+    (   '$thread_create_background'(Chars, Id) ->
+        true
+    ;   throw(error(resource_error(threads), http_listen/3))
+    ).
+
+http_worker(Files, Addr) :-
+    % the initialization goals of the files, which may start the
+    % server, are not run again.
+    maplist(loader:consult_without_initialization, Files),
+    '$http_lookup_listener'(Addr, HttpListener),
+    http_loop(HttpListener).
+
+register_handler(H) :-
+    H =.. [Method, Path, Handler],
+    http_handler(Method, Path, Handler).
 
-parse_options(Options, TLSKey, TLSCert, ContentLengthLimit) :-
+parse_options(Options, TLSKey, TLSCert, ContentLengthLimit, Workers) :-
     member_option_default(tls_key, Options, "", TLSKey),
     member_option_default(tls_cert, Options, "", TLSCert),
     member_option_default(content_length_limit, Options, 32768, ContentLengthLimit),
-    must_be(integer, ContentLengthLimit).
+    must_be(integer, ContentLengthLimit),
+    member_option_default(workers, Options, 1, Workers),
+    must_be(integer, Workers),
+    (   Workers < 1 ->
+        domain_error(not_less_than_one, Workers, http_listen/3)
+    ;   true
+    ).
 
 member_option_default(Key, List, _Default, Value) :-
     X =.. [Key, Value],
@@ -133,35 +349,42 @@ member_option_default(Key, List, Default, Default) :-
     \+ member(X, List).
 	
 
-http_loop(HttpListener, Handlers) :-
+http_loop(HttpListener) :-
     '$http_accept'(HttpListener, RequestMethod, RequestPath, RequestHeaders, RequestQuery, RequestStream, ResponseHandle),
     current_time(Time),
     phrase(format_time("%Y-%m-%d (%H:%M:%S)", Time), TimeString),
     format("~s ~w ~s\n", [TimeString, RequestMethod, RequestPath]),
     maplist(map_header_kv, RequestHeaders, RequestHeadersKV),
     phrase(parse_queries(RequestQueries), RequestQuery),
+    % the handlers are looked up for each request, so that changes
+    % to the registry apply to the running server.
+    sync_handlers,
+    findall(H, (handler(Method, Pattern, Goal), H =.. [Method, Pattern, Goal]), Handlers),
     (
 	match_handler(Handlers, RequestMethod, RequestPath, Handler) ->
 	(
 	    HttpRequest = http_request(RequestHeadersKV, stream(RequestStream), RequestQueries),
 	    HttpResponse = http_response(_, _, _),
-	    (call(Handler, HttpRequest, HttpResponse) ->
-		 send_response(ResponseHandle, HttpResponse)
+	    (catch(call(Handler, HttpRequest, HttpResponse),
+		   error(Error, http_parameter(Name)),
+		   bad_request_response(Error, Name, HttpResponse)) ->
+		 handler_response(Handler, HttpResponse, HttpResponse1),
+		 send_response(ResponseHandle, HttpResponse1)
 	    ;    (
-		'$http_answer'(ResponseHandle, 500, [], ResponseStream),
+		'$http_answer'(ResponseHandle, 500, [], false, ResponseStream),
 		call_cleanup(format(ResponseStream, "Internal Server Error", []), close(ResponseStream)))
 	    )
 	)
     ; (
-	'$http_answer'(ResponseHandle, 404, [], ResponseStream),
+	'$http_answer'(ResponseHandle, 404, [], false, ResponseStream),
 	call_cleanup(format(ResponseStream, "Not Found", []), close(ResponseStream)))
     ),
-    http_loop(HttpListener, Handlers).
+    http_loop(HttpListener).
 
 send_response(ResponseHandle, http_response(StatusCode0, text(ResponseText), ResponseHeaders0)) :-
     default(StatusCode0, 200, StatusCode),
     maplist(map_header_kv_2, ResponseHeaders, ResponseHeaders0),
-    '$http_answer'(ResponseHandle, StatusCode, ResponseHeaders, ResponseStream0),
+    '$http_answer'(ResponseHandle, StatusCode, ResponseHeaders, false, ResponseStream0),
     open(stream(ResponseStream0), write, ResponseStream, [type(text)]),
     catch(
 	call_cleanup(format(ResponseStream, "~s", [ResponseText]),close(ResponseStream)),
@@ -169,20 +392,46 @@ send_response(ResponseHandle, http_response(StatusCode0, text(ResponseText), Res
 	true
     ).
 
+send_response(ResponseHandle, http_response(StatusCode, html(HTML), ResponseHeaders0)) :-
+    html_chars(HTML, ResponseText),
+    default(ResponseHeaders0, [], ResponseHeaders1),
+    (   member("content-type"-_, ResponseHeaders1) ->
+        ResponseHeaders = ResponseHeaders1
+    ;   ResponseHeaders = ["content-type"-"text/html; charset=utf-8"|ResponseHeaders1]
+    ),
+    send_response(ResponseHandle, http_response(StatusCode, text(ResponseText), ResponseHeaders)).
+
 send_response(ResponseHandle, http_response(StatusCode0, bytes(ResponseBytes), ResponseHeaders0)) :-
     default(StatusCode0, 200, StatusCode),
     maplist(map_header_kv_2, ResponseHeaders, ResponseHeaders0),
-    '$http_answer'(ResponseHandle, StatusCode, ResponseHeaders, ResponseStream),
+    '$http_answer'(ResponseHandle, StatusCode, ResponseHeaders, false, ResponseStream),
     catch(
         call_cleanup(format(ResponseStream, "~s", [ResponseBytes]),close(ResponseStream)),
 	error(existence_error(stream, _), _),
 	true
     ).
 
+send_response(ResponseHandle, http_response(StatusCode0, stream(Goal), ResponseHeaders0)) :-
+    default(StatusCode0, 200, StatusCode),
+    maplist(map_header_kv_2, ResponseHeaders, ResponseHeaders0),
+    '$http_answer'(ResponseHandle, StatusCode, ResponseHeaders, true, ResponseStream0),
+    open(stream(ResponseStream0), write, ResponseStream, [type(text)]),
+    catch(
+	call_cleanup(call(Goal, ResponseStream), close(ResponseStream)),
+	error(existence_error(stream, _), _),
+	true
+    ).
+
+send_response(ResponseHandle, http_response(_, websocket(Goal), _)) :-
+    (   '$http_websocket'(ResponseHandle, WebSocket) ->
+        call_cleanup(call(Goal, WebSocket), ws_close(WebSocket, 1000, ""))
+    ;   send_response(ResponseHandle, http_response(400, text("Bad Request: expected a WebSocket handshake"), []))
+    ).
+
 send_response(ResponseHandle, http_response(StatusCode0, file(Filename), ResponseHeaders0)) :-
     default(StatusCode0, 200, StatusCode),
     maplist(map_header_kv_2, ResponseHeaders, ResponseHeaders0),
-    '$http_answer'(ResponseHandle, StatusCode, ResponseHeaders, ResponseStream),
+    '$http_answer'(ResponseHandle, StatusCode, ResponseHeaders, false, ResponseStream),
     catch(
 	call_cleanup(
 	    setup_call_cleanup(
@@ -200,6 +449,17 @@ send_response(ResponseHandle, http_response(StatusCode0, file(Filename), Respons
     ).
 
 
+% the goal of a stream(Goal) or websocket(Goal) body is called in the
+% module of the handler.
+handler_response(Handler, http_response(StatusCode, Body0, Headers), http_response(StatusCode, Body, Headers)) :-
+    loader:strip_module(Handler, Module, _),
+    (   nonvar(Body0),
+        Body0 =.. [Kind, Goal],
+        member(Kind, [stream, websocket]) ->
+        Body =.. [Kind, Module:Goal]
+    ;   Body = Body0
+    ).
+
 default(Var, Default, Out) :-
     (var(Var) -> Out = Default
     ;   Var = Out  
@@ -277,6 +537,13 @@ http_headers(http_response(_, _, Headers), Headers).
 %  * `text(Bytes)` for both requests and responses, interprets the body as text
 %  * `form(Form)` only for requests, interprets the body as an `application/x-www-form-urlencoded` form.
 %  * `file(File)` only for responses, interprets the body as the content of a file (useful to send static files).
+%  * `html(HTML)` only for responses, renders the HTML term HTML of `library(html_write)`, with the
+%    content type `text/html` unless the response has a `content-type` header.
+%  * `stream(Goal)` only for responses, calls `call(Goal, Stream)` after sending the status code and
+%    headers, and sends what Goal writes to the text stream Stream in chunks as it is flushed.
+%  * `websocket(Goal)` only for responses to WebSocket handshakes, accepts the handshake and calls
+%    `call(Goal, WebSocket)` with the WebSocket of `library(websocket)`, which is closed once Goal
+%    exits. Requests that aren't handshakes are answered with 400 Bad Request.
 http_body(http_request(_, stream(StreamBody), _), bytes(BytesBody)) :- get_n_chars(StreamBody, _, BytesBody).
 http_body(http_request(_, stream(StreamBody), _), text(TextBody)) :- get_n_chars(StreamBody, _, TextBody).
 http_body(http_request(Headers, stream(StreamBody), _), form(FormBody)) :- 
@@ -301,6 +568,177 @@ http_redirect(http_response(307, text("Moved Temporarily"), ["Location"-Uri]), U
 % True iff there's a query in request Request with key Key and value Value.
 http_query(http_request(_, _, Queries), Key, Value) :- member(Key-Value, Queries).
 
+%% http_cookies(+Request, -Cookies).
+%
+% Cookies is the list of the cookies sent with request Request, as pairs Name-Value of strings, in the
+% order of their `cookie` headers.
+http_cookies(http_request(Headers, _, _), Cookies) :-
+    findall(Cookie,
+            (   member("cookie"-Chars, Headers),
+                once(phrase(cookies(Cookies0), Chars)),
+                member(Cookie, Cookies0)
+            ),
+            Cookies).
+
+cookies([Name-Value|Cookies]) -->
+    spaces,
+    string_without("=;", Name),
+    "=",
+    string_without(";", Value),
+    (   ";" ->
+        cookies(Cookies)
+    ;   { Cookies = [] }
+    ).
+
+spaces --> " ", spaces.
+spaces --> [].
+
+%% http_parameters(+Request, ?Parameters).
+%
+% Unifies the values of the parameters of Parameters with the parameters of the query of request
+% Request and, if its content type is `application/x-www-form-urlencoded`, of the form of its body.
+% Since the body is read, the handler can't read it again. Each element of Parameters is of the form
+% `Name(Value, Options)`, where the value of the parameter Name is converted and checked
+% according to the list Options:
+%
+%  * `integer`, `float`, `number`, `atom`, `boolean` or `string` converts the value to the type.
+%    Booleans are written as `true`/`false`, `yes`/`no`, `on`/`off` or `1`/`0`. Values are strings
+%    by default, numbers if the options have `between/2` and atoms if they have `oneof/1`.
+%  * `between(Low, High)` checks that the value is a number between Low and High.
+%  * `oneof(Values)` checks that the value is a member of Values.
+%  * `default(Default)` unifies Value with Default if the parameter is missing.
+%  * `optional(true)` leaves Value unbound if the parameter is missing.
+%  * `list` unifies Value with the list of the values of all the parameters named Name (as from
+%    checkboxes), each converted and checked according to the other options.
+%
+% A missing parameter throws `error(existence_error(http_parameter, Name), http_parameter(Name))`,
+% and a parameter that can't be converted or fails a check throws a type or domain error of the same
+% context. The server answers the requests whose handlers throw these errors with 400 Bad Request.
+http_parameters(http_request(Headers, Body, Queries), Parameters) :-
+    must_be(list, Parameters),
+    (   member("content-type"-ContentType, Headers),
+        append("application/x-www-form-urlencoded", _, ContentType),
+        Body = stream(StreamBody) ->
+        get_n_chars(StreamBody, _, TextBody),
+        phrase(parse_queries(Form), TextBody),
+        append(Queries, Form, Pairs)
+    ;   Pairs = Queries
+    ),
+    maplist(parameter_value(Pairs), Parameters).
+
+parameter_value(Pairs, Parameter) :-
+    (   callable(Parameter),
+        Parameter =.. [Name, Value, Options] ->
+        true
+    ;   domain_error(http_parameter, Parameter, http_parameters/2)
+    ),
+    must_be(list, Options),
+    maplist(parameter_option, Options),
+    atom_chars(Name, Key),
+    findall(Chars, member(Key-Chars, Pairs), Values),
+    (   memberchk(list, Options) ->
+        maplist(parameter_convert(Name, Options), Values, Value)
+    ;   Values = [Chars|_] ->
+        parameter_convert(Name, Options, Chars, Value)
+    ;   memberchk(default(Default), Options) ->
+        Value = Default
+    ;   memberchk(optional(true), Options) ->
+        true
+    ;   throw(error(existence_error(http_parameter, Name), http_parameter(Name)))
+    ).
+
+parameter_option(Option) :-
+    (   var(Option) ->
+        instantiation_error(http_parameters/2)
+    ;   parameter_type(Option) ->
+        true
+    ;   member(Option, [between(_, _), oneof(_), default(_), optional(true), list]) ->
+        true
+    ;   domain_error(http_parameter_option, Option, http_parameters/2)
+    ).
+
+parameter_type(integer).
+parameter_type(float).
+parameter_type(number).
+parameter_type(atom).
+parameter_type(boolean).
+parameter_type(string).
+
+parameter_convert(Name, Options, Chars, Value) :-
+    (   member(Type, Options),
+        parameter_type(Type) ->
+        true
+    ;   memberchk(between(_, _), Options) ->
+        Type = number
+    ;   memberchk(oneof(_), Options) ->
+        Type = atom
+    ;   Type = string
+    ),
+    (   convert_parameter(Type, Chars, Value) ->
+        true
+    ;   throw(error(type_error(Type, Chars), http_parameter(Name)))
+    ),
+    maplist(check_parameter(Name, Value), Options).
+
+convert_parameter(string, Chars, Chars).
+convert_parameter(atom, Chars, Atom) :-
+    atom_chars(Atom, Chars).
+convert_parameter(integer, Chars, N) :-
+    parameter_number(Chars, N),
+    integer(N).
+convert_parameter(float, Chars, F) :-
+    parameter_number(Chars, N),
+    F is float(N).
+convert_parameter(number, Chars, N) :-
+    parameter_number(Chars, N).
+convert_parameter(boolean, Chars, Boolean) :-
+    atom_chars(Atom, Chars),
+    parameter_boolean(Atom, Boolean).
+
+parameter_number(Chars, N) :-
+    Chars = [C|_],
+    \+ char_type(C, whitespace),
+    catch(number_chars(N, Chars), error(syntax_error(_), _), false).
+
+parameter_boolean(true, true).
+parameter_boolean(yes, true).
+parameter_boolean(on, true).
+parameter_boolean('1', true).
+parameter_boolean(false, false).
+parameter_boolean(no, false).
+parameter_boolean(off, false).
+parameter_boolean('0', false).
+
+check_parameter(Name, Value, between(Low, High)) :-
+    !,
+    (   number(Value),
+        Low =< Value,
+        Value =< High ->
+        true
+    ;   throw(error(domain_error(between(Low, High), Value), http_parameter(Name)))
+    ).
+check_parameter(Name, Value, oneof(Values)) :-
+    !,
+    (   memberchk(Value, Values) ->
+        true
+    ;   throw(error(domain_error(oneof(Values), Value), http_parameter(Name)))
+    ).
+check_parameter(_, _, _).
+
+% the 400 response to a request whose parameter Name is missing or
+% invalid, as thrown by http_parameters/2.
+bad_request_response(Error, Name, http_response(400, text(Text), [])) :-
+    phrase(bad_request_message(Error, Name), Text).
+
+bad_request_message(existence_error(_, _), Name) -->
+    format_("Bad Request: missing parameter ~a", [Name]).
+bad_request_message(type_error(Type, _), Name) -->
+    format_("Bad Request: parameter ~a must be of type ~a", [Name, Type]).
+bad_request_message(domain_error(between(Low, High), _), Name) -->
+    format_("Bad Request: parameter ~a must be between ~w and ~w", [Name, Low, High]).
+bad_request_message(domain_error(oneof(Values), _), Name) -->
+    format_("Bad Request: parameter ~a must be one of ~w", [Name, Values]).
+
 parse_queries([Key-Value|Queries]) -->
     string_without("=", Key0),
     "=",
@@ -0,0 +1,114 @@
+/** Interval arithmetic.
+
+`is_interval/2` evaluates an arithmetic expression over closed
+intervals of floats, yielding an interval `Lo..Hi` that is guaranteed
+to enclose the exact real value of the expression. The bounds of each
+operation are rounded outwards, so that rounding errors widen the
+interval instead of going unnoticed:
+
+```
+?- is_interval(I, 0.1 + 0.2).
+   I = 0.3..0.30000000000000004.
+?- is_interval(I, 1/3).
+   I = 0.3333333333333333..0.33333333333333337.
+?- is_interval(I, (1..2) * (-1..3)).
+   I = -2.0..6.0.
+```
+
+Numbers are the least intervals of floats enclosing them, so that
+integers and rationals that floats can't represent exactly are
+enclosed by the floats around them. `A..B` is the interval from the
+lower bound of A to the upper bound of B.
+
+The evaluables are `+`, `-`, `*`, `/`, `min`, `max`, `abs`, `sqrt`,
+`exp`, `log`, `sin`, `cos`, `tan`, `atan`, `pi`, `e`, `X^N` for
+integers N, and `X**Y`, which is `X^Y` if Y is an integer and
+`exp(Y*log(X))` otherwise.
+
++, -, *, / and `sqrt` yield the tightest enclosures. The elementary
+functions of the platform aren't correctly rounded, and their bounds
+are widened by two units in the last place. Operations that aren't
+defined on all reals of their operands, such as division by an
+interval containing zero, throw `evaluation_error(undefined)`.
+*/
+
+:- module(intervals, [is_interval/2,
+                      op(450, xfx, ..)]).
+
+:- use_module(library(error)).
+
+%% is_interval(-Interval, +Expr).
+%
+% Interval is `Lo..Hi`, the interval of floats enclosing the value of
+% the arithmetic expression Expr.
+is_interval(Interval, Expr) :-
+    interval(Expr, Lo, Hi),
+    Interval = Lo..Hi.
+
+interval(Expr, _, _) :-
+    var(Expr),
+    !,
+    instantiation_error(is_interval/2).
+interval(N, Lo, Hi) :-
+    number(N),
+    !,
+    '$interval_number'(N, Lo, Hi).
+interval(A..B, Lo, Hi) :-
+    !,
+    interval(A, Lo, _),
+    interval(B, _, Hi),
+    (   Lo =< Hi ->
+        true
+    ;   domain_error(interval, A..B, is_interval/2)
+    ).
+% the nearest floats below and above pi and e.
+interval(pi, 3.141592653589793, 3.1415926535897936) :-
+    !.
+interval(e, 2.718281828459045, 2.7182818284590455) :-
+    !.
+interval(+X, Lo, Hi) :-
+    !,
+    interval(X, Lo, Hi).
+interval(X^N, Lo, Hi) :-
+    !,
+    must_be(integer, N),
+    interval(X, Lo1, Hi1),
+    '$interval_op'(^, Lo1, Hi1, N, N, Lo, Hi).
+interval(X**Y, Lo, Hi) :-
+    integer(Y),
+    !,
+    interval(X^Y, Lo, Hi).
+interval(Expr, Lo, Hi) :-
+    functor(Expr, Name, Arity),
+    (   Arity =:= 1,
+        unary_evaluable(Name) ->
+        arg(1, Expr, X),
+        interval(X, Lo1, Hi1),
+        '$interval_op'(Name, Lo1, Hi1, [], [], Lo, Hi)
+    ;   Arity =:= 2,
+        binary_evaluable(Name) ->
+        arg(1, Expr, X),
+        arg(2, Expr, Y),
+        interval(X, Lo1, Hi1),
+        interval(Y, Lo2, Hi2),
+        '$interval_op'(Name, Lo1, Hi1, Lo2, Hi2, Lo, Hi)
+    ;   type_error(evaluable, Name/Arity, is_interval/2)
+    ).
+
+unary_evaluable(-).
+unary_evaluable(abs).
+unary_evaluable(sqrt).
+unary_evaluable(exp).
+unary_evaluable(log).
+unary_evaluable(sin).
+unary_evaluable(cos).
+unary_evaluable(tan).
+unary_evaluable(atan).
+
+binary_evaluable(+).
+binary_evaluable(-).
+binary_evaluable(*).
+binary_evaluable(/).
+binary_evaluable(min).
+binary_evaluable(max).
+binary_evaluable(**).
@@ -4,13 +4,17 @@ Predicates available here are similar to the ones defined in builtin.pl,
 but they're not part of the ISO Prolog standard at the moment.
 */
 
-:- module(iso_ext, [bb_b_put/2,
+:- module(iso_ext, [b_getval/2,
+                    b_setval/2,
+                    bb_b_put/2,
                     bb_get/2,
                     bb_put/2,
                     call_cleanup/2,
                     call_with_inference_limit/3,
                     call_residue_vars/2,
                     forall/2,
+                    nb_getval/2,
+                    nb_setval/2,
                     partial_string/1,
                     partial_string/3,
                     partial_string_tail/2,
@@ -116,6 +120,57 @@ bb_get(Key, Value) :-
     ).
 
 
+%% b_setval(+Key, +Value).
+%
+% Sets the global variable Key to Value, like `bb_b_put/2`: the
+% assignment is undone on backtracking. Value is not copied, so its
+% variables remain shared with the caller. Compatible with SWI-Prolog.
+%
+% ```
+% ?- b_setval(v, 1), (b_setval(v, 2), false ; b_getval(v, X)).
+%    X = 1.
+% ```
+b_setval(Key, Value) :-
+    global_variable_key(Key, b_setval/2),
+    '$store_backtrackable_global_var'(Key, Value).
+
+%% b_getval(+Key, -Value).
+%
+% Value is the value of the global variable Key. Throws
+% `existence_error(variable, Key)` if Key has no value.
+b_getval(Key, Value) :-
+    global_variable_key(Key, b_getval/2),
+    (  '$fetch_global_var'(Key, Value0) ->
+       Value = Value0
+    ;  throw(error(existence_error(variable, Key), b_getval/2))
+    ).
+
+%% nb_setval(+Key, +Value).
+%
+% Sets the global variable Key to a copy of Value, like `bb_put/2`:
+% the assignment survives backtracking. Compatible with SWI-Prolog.
+nb_setval(Key, Value) :-
+    global_variable_key(Key, nb_setval/2),
+    '$store_global_var'(Key, Value).
+
+%% nb_getval(+Key, -Value).
+%
+% Like `b_getval/2`.
+nb_getval(Key, Value) :-
+    global_variable_key(Key, nb_getval/2),
+    (  '$fetch_global_var'(Key, Value0) ->
+       Value = Value0
+    ;  throw(error(existence_error(variable, Key), nb_getval/2))
+    ).
+
+global_variable_key(Key, PI) :-
+    (  atom(Key) ->
+       true
+    ;  var(Key) ->
+       instantiation_error(PI)
+    ;  type_error(atom, Key, PI)
+    ).
+
 %% succ(?I, ?S).
 %
 % True iff S is the successor of the non-negative integer I.
@@ -0,0 +1,117 @@
+/** Machines queried from Prolog.
+
+A machine created with `machine_create/1` is a separate Prolog machine
+in the same thread. It has a database of its own, to which programs
+are added with `machine_consult/2`, and is queried with
+`remote_call/3`, whose answers are computed on demand: the machine
+runs until it finds an answer and stays suspended until the caller
+backtracks for the next one.
+
+```
+?- machine_create(M),
+   machine_consult(M, "p(1). p(2). p(3)."),
+   findall(X, remote_call(M, X, p(X)), Xs),
+   machine_destroy(M).
+   M = '$machine_0', Xs = [1,2,3].
+```
+
+Goals are called in the `user` module of the machine. Goals and
+answers are copied between the machines as text, so the variables of
+an answer are fresh, and neither attributes of variables nor residual
+goals are preserved. A machine runs one query at a time.
+*/
+
+:- module(machines, [machine_consult/2,
+                     machine_create/1,
+                     machine_destroy/1,
+                     remote_call/2,
+                     remote_call/3]).
+
+:- use_module(library(charsio), [write_term_to_chars/3]).
+:- use_module(library(error)).
+:- use_module(library(iso_ext), [setup_call_cleanup/3]).
+:- use_module(library(threads)).
+
+%% machine_create(-Machine).
+%
+% Creates a new machine, with only the libraries loaded at startup.
+machine_create(Machine) :-
+    must_be(var, Machine),
+    '$machine_create'(Machine).
+
+%% machine_destroy(+Machine).
+%
+% Destroys Machine, abandoning its running query if any.
+machine_destroy(Machine) :-
+    must_be(atom, Machine),
+    (   '$machine_destroy'(Machine) ->
+        true
+    ;   throw(error(existence_error(machine, Machine), machine_destroy/1))
+    ).
+
+%% machine_consult(+Machine, +Program).
+%
+% Consults the string Program into the `user` module of Machine.
+% Throws a permission error if Machine is running a query.
+machine_consult(Machine, Program) :-
+    must_be(atom, Machine),
+    must_be(chars, Program),
+    idle_machine(Machine, consult, machine_consult/2),
+    '$machine_consult'(Machine, Program).
+
+:- meta_predicate(remote_call(?, 0)).
+
+%% remote_call(+Machine, :Goal).
+%
+% Like `remote_call(Machine, Goal, Goal)`.
+remote_call(Machine, Goal) :-
+    remote_call(Machine, Goal, Goal).
+
+:- meta_predicate(remote_call(?, ?, 0)).
+
+%% remote_call(+Machine, ?Template, :Goal).
+%
+% Calls Goal on Machine and unifies Template with its instance in
+% each answer of Goal, which are computed one at a time on
+% backtracking. Exceptions thrown by Goal are rethrown. Throws a
+% permission error if Machine is already running a query, for example
+% if it's called again while the answers of another call are pending.
+remote_call(Machine, Template, Goal) :-
+    must_be(atom, Machine),
+    idle_machine(Machine, query, remote_call/3),
+    threads:unqualified_goal(Goal, Goal1),
+    % operators of this machine may not be those of Machine.
+    write_term_to_chars((Goal1, Answer = Template),
+                        [quoted(true), ignore_ops(true), max_depth(0),
+                         variable_names(['Answer'=Answer])],
+                        Chars),
+    setup_call_cleanup(machine_query(Machine, Chars, Id),
+                       remote_answer(Machine, Id, Template),
+                       machine_close(Machine, Id)).
+
+idle_machine(Machine, Action, Context) :-
+    (   '$machine_status'(Machine, Status) ->
+        (   Status == idle ->
+            true
+        ;   throw(error(permission_error(Action, machine, Machine), Context))
+        )
+    ;   throw(error(existence_error(machine, Machine), Context))
+    ).
+
+machine_query(Machine, Chars, Id) :-
+    '$machine_query'(Machine, Chars, Id).
+
+machine_close(Machine, Id) :-
+    '$machine_close'(Machine, Id).
+
+remote_answer(Machine, Id, Template) :-
+    '$machine_next'(Machine, Id, Answer),
+    (   Answer = last(Template0) ->
+        Template = Template0
+    ;   Answer = exception(E) ->
+        throw(E)
+    ;   Answer = answer(Template0),
+        (   Template = Template0
+        ;   remote_answer(Machine, Id, Template)
+        )
+    ).
@@ -0,0 +1,194 @@
+/** Message queues.
+
+A message queue holds a sequence of terms. Queues are shared by all
+Prolog machines of a process and are safe to use from several OS
+threads at once: senders never block, and receivers block until a
+matching message arrives or a timeout runs out.
+
+Messages are copied when they are sent, like with `assertz/1`:
+variables of a received message are fresh, and attributes of
+variables are not preserved.
+
+```
+?- message_queue_create(Q),
+   thread_send_message(Q, hello(world)),
+   thread_get_message(Q, hello(X)).
+   Q = '$message_queue_0', X = world.
+```
+
+A queue can also be read as a stream of terms with
+`message_queue_stream/2`, so that `read/2` removes its messages in
+the order they were sent:
+
+```
+?- message_queue_create(Q),
+   thread_send_message(Q, hello(world)),
+   message_queue_stream(Q, S),
+   read(S, T).
+   Q = '$message_queue_0', S = '$stream'(...), T = hello(world).
+```
+*/
+
+:- module(message_queues, [message_queue_create/1,
+                           message_queue_destroy/1,
+                           message_queue_size/2,
+                           message_queue_stream/2,
+                           thread_get_message/2,
+                           thread_get_message/3,
+                           thread_peek_message/2,
+                           thread_send_message/2]).
+
+:- use_module(library(charsio), [read_term_from_chars/3,
+                                 write_term_to_chars/3]).
+:- use_module(library(error)).
+:- use_module(library(lists), [append/3, member/2]).
+
+%% message_queue_create(?Queue).
+%
+% Creates a message queue. If Queue is an atom, it is used as the
+% name of the queue, otherwise Queue is unified with the name of a new
+% anonymous queue. Throws a permission error if a queue named Queue
+% already exists.
+message_queue_create(Queue) :-
+    (   var(Queue) ->
+        '$message_queue_create'(Queue)
+    ;   must_be(atom, Queue),
+        (   '$message_queue_create'(Queue) ->
+            true
+        ;   throw(error(permission_error(create, message_queue, Queue),
+                        message_queue_create/1))
+        )
+    ).
+
+%% message_queue_destroy(+Queue).
+%
+% Destroys Queue and the messages it holds.
+message_queue_destroy(Queue) :-
+    must_be_queue(Queue, message_queue_destroy/1),
+    (   '$message_queue_destroy'(Queue) ->
+        true
+    ;   existence_error(Queue, message_queue_destroy/1)
+    ).
+
+%% message_queue_size(+Queue, -Size).
+%
+% Size is the number of messages in Queue.
+message_queue_size(Queue, Size) :-
+    must_be_queue(Queue, message_queue_size/2),
+    '$message_queue_size'(Queue, Size).
+
+%% message_queue_stream(+Queue, -Stream).
+%
+% Stream is a text input stream reading the messages of Queue, each
+% message followed by ` .` and a newline so that it can be read as a
+% term. Reading removes messages in the order they were sent, and
+% blocks until a message arrives. Stream is at its end once Queue is
+% destroyed and holds no more messages.
+message_queue_stream(Queue, Stream) :-
+    must_be_queue(Queue, message_queue_stream/2),
+    (   var(Stream) ->
+        true
+    ;   throw(error(uninstantiation_error(Stream), message_queue_stream/2))
+    ),
+    (   '$message_queue_stream'(Queue, Stream) ->
+        true
+    ;   existence_error(Queue, message_queue_stream/2)
+    ).
+
+%% thread_send_message(+Queue, +Term).
+%
+% Appends a copy of Term to Queue.
+thread_send_message(Queue, Term) :-
+    must_be_queue(Queue, thread_send_message/2),
+    write_term_to_chars(Term, [quoted(true), max_depth(0)], Chars),
+    (   '$message_queue_send'(Queue, Chars) ->
+        true
+    ;   existence_error(Queue, thread_send_message/2)
+    ).
+
+%% thread_get_message(+Queue, ?Term).
+%
+% Removes the oldest message of Queue that unifies with Term, and
+% unifies it with Term. Blocks until such a message arrives.
+thread_get_message(Queue, Term) :-
+    thread_get_message(Queue, Term, []).
+
+%% thread_get_message(+Queue, ?Term, +Options).
+%
+% Like `thread_get_message/2`. Options is a list of:
+%
+%  - `timeout(T)`: fail if no matching message arrives within T
+%    seconds. The default is `infinite`.
+%
+% Throws an existence error if Queue is destroyed while waiting.
+thread_get_message(Queue, Term, Options) :-
+    must_be_queue(Queue, thread_get_message/3),
+    must_be(list, Options),
+    (   member(timeout(Timeout), Options) ->
+        must_be_timeout(Timeout)
+    ;   Timeout = infinite
+    ),
+    get_message(Queue, -1, Timeout, Term).
+
+get_message(Queue, After, Timeout, Term) :-
+    next_message(Queue, After, Term, Outcome),
+    (   Outcome = found(Id) ->
+        (   '$message_queue_remove'(Queue, Id) ->
+            true
+        ;   % another receiver took the message first.
+            get_message(Queue, Id, Timeout, Term)
+        )
+    ;   Outcome = none(Last),
+        (   '$message_queue_wait'(Queue, Last, Timeout, Timeout1) ->
+            get_message(Queue, Last, Timeout1, Term)
+        ;   % the wait timed out, unless the queue was destroyed.
+            must_be_queue(Queue, thread_get_message/3),
+            fail
+        )
+    ).
+
+%% thread_peek_message(+Queue, ?Term).
+%
+% True if Queue holds a message that unifies with Term. The message is
+% unified with Term but not removed, and the predicate never blocks.
+thread_peek_message(Queue, Term) :-
+    must_be_queue(Queue, thread_peek_message/2),
+    next_message(Queue, -1, Term, found(_)).
+
+% Outcome is found(Id) for the oldest message after message After
+% that unifies with Term, and none(Last) if there is no such message,
+% Last being the id of the newest message seen.
+next_message(Queue, After, Term, Outcome) :-
+    (   '$message_queue_next'(Queue, After, Id, Chars) ->
+        append(Chars, " .", Chars1),
+        read_term_from_chars(Chars1, Term0, []),
+        (   Term0 = Term ->
+            Outcome = found(Id)
+        ;   next_message(Queue, Id, Term, Outcome)
+        )
+    ;   Outcome = none(After)
+    ).
+
+must_be_queue(Queue, PI) :-
+    (   var(Queue) ->
+        instantiation_error(PI)
+    ;   atom(Queue) ->
+        (   '$message_queue_size'(Queue, _) ->
+            true
+        ;   existence_error(Queue, PI)
+        )
+    ;   type_error(message_queue, Queue, PI)
+    ).
+
+must_be_timeout(Timeout) :-
+    (   Timeout == infinite ->
+        true
+    ;   builtins:must_be_number(Timeout, thread_get_message/3),
+        (   Timeout < 0 ->
+            domain_error(not_less_than_zero, Timeout, thread_get_message/3)
+        ;   true
+        )
+    ).
+
+existence_error(Queue, PI) :-
+    throw(error(existence_error(message_queue, Queue), PI)).
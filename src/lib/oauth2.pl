@@ -0,0 +1,511 @@
+/** OAuth 2.0 and OpenID Connect clients.
+
+This library implements the client side of the authorization code
+flow (RFC 6749, section 4.1) with PKCE (RFC 7636), of the client
+credentials flow (section 4.4) and of refreshing access tokens
+(section 6), over `http_open/3`, and verifies the ID tokens of
+OpenID Connect with `library(jwt)`.
+
+A client is described by a list of:
+
+  - `client_id(ID)`, required.
+  - `client_secret(Secret)`, for confidential clients, which are
+    authenticated to the token endpoint with HTTP Basic authentication,
+    or with the parameters of the request body if the list has
+    `client_authentication(post)`.
+  - `authorization_endpoint(URL)` and `token_endpoint(URL)`.
+  - `redirect_uri(URI)`, for the authorization code flow.
+  - `scope(Scope)`, the space-separated scopes to request.
+  - `issuer(Issuer)` and `jwks_uri(URL)`, for OpenID Connect.
+
+All these values are strings. `oidc_client/3` finds the endpoints of
+an OpenID provider by discovery.
+
+In the authorization code flow, the user is sent to the URL of
+`oauth2_authorization_url/4` with a random state and the challenge of
+a PKCE code verifier, both kept until the user returns to the redirect
+URI with a code and the same state, which `oauth2_exchange_code/4`
+exchanges for a token:
+
+```
+?- oauth2_pkce(Verifier, Challenge),
+   oauth2_random_state(State),
+   oauth2_authorization_url(Client, State, [code_challenge(Challenge)], URL).
+   % ... and once the user is redirected back with Code:
+?- oauth2_exchange_code(Client, Code, [code_verifier(Verifier)], Token).
+```
+
+Tokens are the JSON objects of the token responses, as of
+`library(serialization/json)`, with an additional member `expires_at`,
+the Unix time at which the access token expires, if the response
+tells. Errors of the authorization server are thrown as
+`error(oauth2_error(Error, Description), Context)`, with Error the
+string of its error code, or its HTTP status code if the response
+doesn't have one.
+*/
+
+:- module(oauth2, [oauth2_pkce/2,
+                   oauth2_random_state/1,
+                   oauth2_authorization_url/4,
+                   oauth2_exchange_code/4,
+                   oauth2_client_credentials/3,
+                   oauth2_refresh_token/4,
+                   oauth2_fresh_token/4,
+                   oauth2_token_expired/2,
+                   oauth2_bearer_header/2,
+                   oidc_client/3,
+                   oidc_jwks/2,
+                   oidc_verify_id_token/5]).
+
+:- use_module(library(charsio)).
+:- use_module(library(crypto)).
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(http/http_open)).
+:- use_module(library(jwt)).
+:- use_module(library(lists)).
+:- use_module(library(serialization/json)).
+:- use_module(library(time)).
+
+%% oauth2_pkce(-Verifier, -Challenge).
+%
+% Verifier is a new random PKCE code verifier, and Challenge its
+% `S256` code challenge, the Base64url encoding of its SHA-256 hash.
+% If Verifier is given, Challenge is its challenge.
+oauth2_pkce(Verifier, Challenge) :-
+    (   var(Verifier) ->
+        random_base64url(32, Verifier)
+    ;   must_be(chars, Verifier)
+    ),
+    crypto_data_hash(Verifier, Hex, [algorithm(sha256)]),
+    hex_bytes(Hex, Bytes),
+    bytes_base64url(Bytes, Challenge).
+
+%% oauth2_random_state(-State).
+%
+% State is a new random string, for the `state` parameter that binds
+% the authorization request to the redirect it results in.
+oauth2_random_state(State) :-
+    random_base64url(16, State).
+
+random_base64url(N, Cs) :-
+    crypto_n_random_bytes(N, Bytes),
+    bytes_base64url(Bytes, Cs).
+
+bytes_base64url(Bytes, Cs) :-
+    maplist(char_code, Octets, Bytes),
+    chars_base64(Octets, Cs, [padding(false), charset(url)]).
+
+%% oauth2_authorization_url(+Client, +State, +Options, -URL).
+%
+% URL is the authorization endpoint of Client with the parameters of
+% an authorization code request with the state State. Options are:
+%
+%  * `code_challenge(Challenge)`: the `S256` challenge of a PKCE code
+%    verifier, as of `oauth2_pkce/2`.
+%  * `nonce(Nonce)`: the nonce of OpenID Connect, which the ID token
+%    will contain.
+%  * `scope(Scope)`: the scopes to request instead of those of Client.
+%  * `param(Name, Value)`: any other parameter, such as `prompt`.
+oauth2_authorization_url(Client, State, Options, URL) :-
+    must_be(chars, State),
+    must_be(list, Options),
+    client_value(Client, authorization_endpoint, Endpoint, oauth2_authorization_url/4),
+    client_value(Client, client_id, ID, oauth2_authorization_url/4),
+    client_value(Client, redirect_uri, RedirectURI, oauth2_authorization_url/4),
+    foldl(authorization_option, Options, Params, []),
+    (   member(scope(_), Options) ->
+        ScopeParams = []
+    ;   member(scope(Scope), Client) ->
+        ScopeParams = ["scope"-Scope]
+    ;   ScopeParams = []
+    ),
+    append([["response_type"-"code", "client_id"-ID, "redirect_uri"-RedirectURI],
+            ScopeParams,
+            ["state"-State],
+            Params], AllParams),
+    phrase(form(AllParams), Query),
+    (   member('?', Endpoint) ->
+        Separator = "&"
+    ;   Separator = "?"
+    ),
+    append([Endpoint, Separator, Query], URL).
+
+authorization_option(O) -->
+    (   { var(O) } ->
+        { instantiation_error(oauth2_authorization_url/4) }
+    ;   { O = code_challenge(Challenge) } ->
+        ["code_challenge"-Challenge, "code_challenge_method"-"S256"]
+    ;   { O = nonce(Nonce) } ->
+        ["nonce"-Nonce]
+    ;   { O = scope(Scope) } ->
+        ["scope"-Scope]
+    ;   { O = param(Name, Value) } ->
+        [Name-Value]
+    ;   { domain_error(oauth2_option, O, oauth2_authorization_url/4) }
+    ).
+
+%% oauth2_exchange_code(+Client, +Code, +Options, -Token).
+%
+% Token is the token that the token endpoint of Client issues for the
+% authorization code Code. Options are:
+%
+%  * `code_verifier(Verifier)`: the PKCE code verifier whose challenge
+%    the authorization request had.
+oauth2_exchange_code(Client, Code, Options, Token) :-
+    must_be(chars, Code),
+    must_be(list, Options),
+    maplist(exchange_option, Options),
+    client_value(Client, redirect_uri, RedirectURI, oauth2_exchange_code/4),
+    (   member(code_verifier(Verifier), Options) ->
+        VerifierParams = ["code_verifier"-Verifier]
+    ;   VerifierParams = []
+    ),
+    append(["grant_type"-"authorization_code", "code"-Code, "redirect_uri"-RedirectURI],
+           VerifierParams, Params),
+    token_request(Client, Params, Token, oauth2_exchange_code/4).
+
+exchange_option(O) :-
+    (   var(O) ->
+        instantiation_error(oauth2_exchange_code/4)
+    ;   O = code_verifier(Verifier) ->
+        must_be(chars, Verifier)
+    ;   domain_error(oauth2_option, O, oauth2_exchange_code/4)
+    ).
+
+%% oauth2_client_credentials(+Client, +Options, -Token).
+%
+% Token is a token that the token endpoint of Client issues to Client
+% itself. Options are:
+%
+%  * `scope(Scope)`: the scopes to request instead of those of Client.
+oauth2_client_credentials(Client, Options, Token) :-
+    must_be(list, Options),
+    maplist(scope_option(oauth2_client_credentials/3), Options),
+    scope_params(Client, Options, ScopeParams),
+    token_request(Client, ["grant_type"-"client_credentials"|ScopeParams], Token,
+                  oauth2_client_credentials/3).
+
+%% oauth2_refresh_token(+Client, +Token0, +Options, -Token).
+%
+% Token is the token that the token endpoint of Client issues for the
+% refresh token of Token0. If the response has no refresh token, the
+% refresh token of Token0 is kept in Token. Options are:
+%
+%  * `scope(Scope)`: the scopes to request, no more than those of
+%    Token0.
+oauth2_refresh_token(Client, Token0, Options, Token) :-
+    must_be(list, Options),
+    maplist(scope_option(oauth2_refresh_token/4), Options),
+    (   token_member(Token0, "refresh_token", string(RefreshToken)) ->
+        true
+    ;   domain_error(oauth2_refresh_token, Token0, oauth2_refresh_token/4)
+    ),
+    (   member(scope(Scope), Options) ->
+        ScopeParams = ["scope"-Scope]
+    ;   ScopeParams = []
+    ),
+    token_request(Client, ["grant_type"-"refresh_token", "refresh_token"-RefreshToken|ScopeParams],
+                  Token1, oauth2_refresh_token/4),
+    (   token_member(Token1, "refresh_token", _) ->
+        Token = Token1
+    ;   Token1 = pairs(Pairs),
+        append(Pairs, [string("refresh_token")-string(RefreshToken)], Pairs1),
+        Token = pairs(Pairs1)
+    ).
+
+scope_option(Context, O) :-
+    (   var(O) ->
+        instantiation_error(Context)
+    ;   O = scope(Scope) ->
+        must_be(chars, Scope)
+    ;   domain_error(oauth2_option, O, Context)
+    ).
+
+scope_params(Client, Options, ScopeParams) :-
+    (   member(scope(Scope), Options) ->
+        ScopeParams = ["scope"-Scope]
+    ;   member(scope(Scope), Client) ->
+        ScopeParams = ["scope"-Scope]
+    ;   ScopeParams = []
+    ).
+
+%% oauth2_fresh_token(+Client, +Token0, +Leeway, -Token).
+%
+% Token is Token0 if its access token doesn't expire within Leeway
+% seconds, and else the token that refreshing it yields.
+oauth2_fresh_token(Client, Token0, Leeway, Token) :-
+    (   oauth2_token_expired(Token0, Leeway) ->
+        oauth2_refresh_token(Client, Token0, [], Token)
+    ;   Token = Token0
+    ).
+
+%% oauth2_token_expired(+Token, +Leeway).
+%
+% True if the access token of Token expires within Leeway seconds.
+% Tokens without an expiry time never expire.
+oauth2_token_expired(Token, Leeway) :-
+    must_be(integer, Leeway),
+    token_member(Token, "expires_at", number(ExpiresAt)),
+    unix_time(Now),
+    Now + Leeway >= ExpiresAt.
+
+%% oauth2_bearer_header(+Token, -Header).
+%
+% Header is the `authorization` header of requests with the access
+% token of Token, for the `request_headers/1` option of `http_open/3`.
+oauth2_bearer_header(Token, authorization(Value)) :-
+    (   token_member(Token, "access_token", string(AccessToken)) ->
+        append("Bearer ", AccessToken, Value)
+    ;   domain_error(oauth2_token, Token, oauth2_bearer_header/2)
+    ).
+
+token_member(pairs(Pairs), Name, Value) :-
+    memberchk(string(Name)-Value, Pairs).
+
+%% Token requests.
+
+% POSTs the form Params to the token endpoint of Client, authenticating
+% Client if it has a secret.
+token_request(Client, Params0, Token, Context) :-
+    client_value(Client, token_endpoint, Endpoint, Context),
+    client_value(Client, client_id, ID, Context),
+    (   member(client_secret(Secret), Client) ->
+        (   member(client_authentication(post), Client) ->
+            append(Params0, ["client_id"-ID, "client_secret"-Secret], Params),
+            AuthHeaders = []
+        ;   basic_authorization(ID, Secret, Authorization),
+            Params = Params0,
+            AuthHeaders = [authorization(Authorization)]
+        )
+    ;   append(Params0, ["client_id"-ID], Params),
+        AuthHeaders = []
+    ),
+    phrase(form(Params), Body),
+    Headers = ['content-type'("application/x-www-form-urlencoded"),
+               accept("application/json"),
+               'user-agent'("Scryer Prolog")|AuthHeaders],
+    http_json(Endpoint, [method(post), data(Body), request_headers(Headers)], Status, JSON),
+    token_response(Status, JSON, Token, Context).
+
+% RFC 6749, section 2.3.1: the ID and the secret are form-encoded
+% before they are joined.
+basic_authorization(ID, Secret, Authorization) :-
+    phrase(form_value(ID), EncodedID),
+    phrase(form_value(Secret), EncodedSecret),
+    append([EncodedID, ":", EncodedSecret], Credentials),
+    chars_utf8bytes(Credentials, Bytes),
+    maplist(char_code, Octets, Bytes),
+    chars_base64(Octets, Base64, []),
+    append("Basic ", Base64, Authorization).
+
+token_response(Status, JSON, Token, Context) :-
+    (   Status >= 200,
+        Status < 300,
+        JSON = pairs(Pairs),
+        memberchk(string("access_token")-_, Pairs) ->
+        (   memberchk(string("expires_in")-number(ExpiresIn), Pairs) ->
+            unix_time(Now),
+            ExpiresAt is Now + ExpiresIn,
+            append(Pairs, [string("expires_at")-number(ExpiresAt)], Pairs1),
+            Token = pairs(Pairs1)
+        ;   Token = JSON
+        )
+    ;   oauth2_error(Status, JSON, Context)
+    ).
+
+oauth2_error(Status, JSON, Context) :-
+    (   JSON = pairs(Pairs),
+        memberchk(string("error")-string(Error), Pairs) ->
+        (   memberchk(string("error_description")-string(Description), Pairs) ->
+            true
+        ;   Description = []
+        )
+    ;   Error = Status,
+        Description = []
+    ),
+    throw(error(oauth2_error(Error, Description), Context)).
+
+% Status is the status code of the response to the request of URL
+% with the options Options of http_open/3, and JSON its body, or null
+% if it isn't JSON.
+http_json(URL, Options, Status, JSON) :-
+    http_open(URL, Stream, [status_code(Status)|Options]),
+    get_n_chars(Stream, _, Cs),
+    close(Stream),
+    (   phrase(json_chars(JSON0), Cs) ->
+        JSON = JSON0
+    ;   JSON = null
+    ).
+
+unix_time(Time) :-
+    current_time(Stamp),
+    memberchk(s=Cs, Stamp),
+    number_chars(Time, Cs).
+
+client_value(Client, Name, Value, Context) :-
+    must_be(list, Client),
+    Option =.. [Name, Value],
+    (   memberchk(Option, Client) ->
+        must_be(chars, Value)
+    ;   existence_error(oauth2_client_option, Name, Context)
+    ).
+
+existence_error(Type, Culprit, Context) :-
+    throw(error(existence_error(Type, Culprit), Context)).
+
+%% Form encoding.
+
+% the application/x-www-form-urlencoded encoding of Name-Value pairs.
+form([]) --> [].
+form([Name-Value|Pairs]) -->
+    form_value(Name),
+    "=",
+    form_value(Value),
+    (   { Pairs == [] } ->
+        []
+    ;   "&",
+        form(Pairs)
+    ).
+
+% percent-encodes the UTF-8 bytes of all characters but the unreserved
+% ones of RFC 3986.
+form_value(Cs) -->
+    { chars_utf8bytes(Cs, Bytes) },
+    form_bytes(Bytes).
+
+form_bytes([]) --> [].
+form_bytes([B|Bs]) -->
+    { char_code(C, B) },
+    (   { unreserved(C) } ->
+        [C]
+    ;   { High is B >> 4,
+          Low is B /\ 0xf,
+          hex_digit(High, H),
+          hex_digit(Low, L) },
+        ['%', H, L]
+    ),
+    form_bytes(Bs).
+
+unreserved(C) :-
+    (   char_type(C, alnum),
+        C @< '\x80\' ->
+        true
+    ;   member(C, "-._~")
+    ).
+
+hex_digit(D, C) :-
+    nth0(D, "0123456789ABCDEF", C).
+
+%% OpenID Connect.
+
+%% oidc_client(+Issuer, +Client0, -Client).
+%
+% Client is the client Client0 of the OpenID provider Issuer, with the
+% endpoints, issuer and JWKS URL of the provider's configuration, as
+% of OpenID Connect Discovery, unless Client0 has them already.
+oidc_client(Issuer, Client0, Client) :-
+    must_be(chars, Issuer),
+    must_be(list, Client0),
+    (   append(Base, "/", Issuer) ->
+        true
+    ;   Base = Issuer
+    ),
+    append(Base, "/.well-known/openid-configuration", URL),
+    http_json(URL, [request_headers([accept("application/json"), 'user-agent'("Scryer Prolog")])],
+              Status, JSON),
+    (   Status =:= 200,
+        JSON = pairs(Pairs) ->
+        true
+    ;   oauth2_error(Status, JSON, oidc_client/3)
+    ),
+    foldl(provider_option(Pairs),
+          [issuer, authorization_endpoint, token_endpoint, jwks_uri],
+          Client0, Client).
+
+provider_option(Pairs, Name, Client0, Client) :-
+    Option =.. [Name, _],
+    (   memberchk(Option, Client0) ->
+        Client = Client0
+    ;   atom_chars(Name, Key),
+        memberchk(string(Key)-string(Value), Pairs) ->
+        Option =.. [Name, Value],
+        append(Client0, [Option], Client)
+    ;   Client = Client0
+    ).
+
+%% oidc_jwks(+Client, -JWKS).
+%
+% JWKS is the JSON Web Key Set at the `jwks_uri` of Client, the public
+% keys of the provider.
+oidc_jwks(Client, JWKS) :-
+    client_value(Client, jwks_uri, URL, oidc_jwks/2),
+    http_json(URL, [request_headers([accept("application/json"), 'user-agent'("Scryer Prolog")])],
+              Status, JWKS0),
+    (   Status =:= 200,
+        JWKS0 = pairs(_) ->
+        JWKS = JWKS0
+    ;   oauth2_error(Status, JWKS0, oidc_jwks/2)
+    ).
+
+%% oidc_verify_id_token(+IDToken, +Client, +JWKS, +Options, -Claims).
+%
+% Claims are the claims of the ID token IDToken, a string, signed with
+% a key of JWKS, the JSON Web Key Set of the provider as of
+% `oidc_jwks/2`, for Client by the issuer of Client. The keys can be
+% RSA keys for RS256 and Ed25519 keys for EdDSA, and the key is
+% chosen by the `kid` of the token. Fails if the token is not valid,
+% as of `jwt_verify/4`. Options are:
+%
+%  * `nonce(Nonce)`: the token must have the nonce of the
+%    authorization request.
+%  * `time(Time)` and `leeway(Seconds)`, as of `jwt_verify/4`.
+oidc_verify_id_token(IDToken, Client, JWKS, Options, Claims) :-
+    must_be(chars, IDToken),
+    must_be(list, Options),
+    client_value(Client, client_id, ID, oidc_verify_id_token/5),
+    client_value(Client, issuer, Issuer, oidc_verify_id_token/5),
+    jwt_decode(IDToken, pairs(Header), _),
+    (   JWKS = pairs(JWKSPairs),
+        memberchk(string("keys")-list(Keys), JWKSPairs) ->
+        true
+    ;   domain_error(jwks, JWKS, oidc_verify_id_token/5)
+    ),
+    (   memberchk(string("kid")-string(Kid), Header) ->
+        once(( member(pairs(KeyPairs), Keys),
+               memberchk(string("kid")-string(Kid), KeyPairs)
+            ))
+    ;   Keys = [pairs(KeyPairs)]
+    ),
+    jwk_key(KeyPairs, Key),
+    findall(O, ( member(O, Options), O \= nonce(_) ), JWTOptions0),
+    jwt_verify(IDToken, Key, Claims, [audience(ID), issuer(Issuer)|JWTOptions0]),
+    (   member(nonce(Nonce), Options) ->
+        Claims = pairs(ClaimPairs),
+        memberchk(string("nonce")-string(Nonce), ClaimPairs)
+    ;   true
+    ).
+
+% the key of library(jwt) of a JSON Web Key (RFC 7517, RFC 8037).
+jwk_key(Pairs, Key) :-
+    memberchk(string("kty")-string(Kty), Pairs),
+    (   Kty == "RSA" ->
+        memberchk(string("n")-string(N64), Pairs),
+        memberchk(string("e")-string(E64), Pairs),
+        base64url_integer(N64, N),
+        base64url_integer(E64, E),
+        Key = rs256(rsa_public_key(N, E))
+    ;   Kty == "OKP",
+        memberchk(string("crv")-string("Ed25519"), Pairs),
+        memberchk(string("x")-string(X64), Pairs),
+        chars_base64(PublicKey, X64, [padding(false), charset(url)]),
+        Key = eddsa(PublicKey)
+    ).
+
+base64url_integer(Base64, I) :-
+    chars_base64(Octets, Base64, [padding(false), charset(url)]),
+    foldl(octet_integer, Octets, 0, I).
+
+octet_integer(C, I0, I) :-
+    char_code(C, B),
+    I is I0 * 256 + B.
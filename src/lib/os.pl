@@ -14,8 +14,27 @@
 
 /** Predicates for reasoning about the operating system (OS) environment.
 
-This includes predicates about environment variables, calls to shell and
-finding out the PID of the running system.
+This includes predicates about environment variables, calls to shell,
+finding out the PID of the running system and handling signals.
+
+Long-running services shut down gracefully by mapping the signals
+that stop them to goals:
+
+```
+serve :-
+    on_signal(term, stop),
+    on_signal(int, stop),
+    repeat,
+    serve_request,
+    false.
+
+stop :-
+    close_connections,
+    halt.
+```
+
+`sleep/1` of `library(time)` is exported too. It takes fractions of
+seconds, and returns early when a signal mapped to a goal arrives.
 */
 
 :- module(os, [getenv/2,
@@ -24,6 +43,7 @@ finding out the PID of the running system.
                shell/1,
                shell/2,
                pid/1,
+               on_signal/2,
 	       raw_argv/1,
 	       argv/1]).
 
@@ -32,6 +52,12 @@ finding out the PID of the running system.
 :- use_module(library(lists)).
 :- use_module(library(si)).
 
+:- reexport(library(time), [sleep/1]).
+
+:- meta_predicate(on_signal(+, 0)).
+
+:- dynamic(signal_goal/2).
+
 %% getenv(+Key, -Value).
 %
 % True iff Value contains the value of the environment variable Key.
@@ -90,6 +116,60 @@ pid(PID) :-
         can_be(integer, PID),
         '$pid'(PID).
 
+%% on_signal(+Signal, +Action).
+%
+% Handles Signal, which is `int` (SIGINT, as sent by Ctrl-C) or `term`
+% (SIGTERM, as sent by `kill` and service managers), by Action:
+%
+%  * `default`: as the system does by default. SIGINT interrupts the
+%    running goal in the toplevel, and SIGTERM terminates the system.
+%  * `ignore`: the signal is ignored.
+%  * a goal: the goal is called once when the signal arrives, before
+%    the next call of a predicate. The interrupted goal then goes on,
+%    whether the goal succeeds or fails, and exceptions of the goal
+%    are thrown from the call. Builtins that wait, such as those
+%    reading input, call it once they return, except for `sleep/1`,
+%    which returns early.
+%
+% Signals are only supported on Unix. Elsewhere, a permission error
+% is thrown.
+on_signal(Signal, Action) :-
+        must_be(atom, Signal),
+        (   memberchk(Signal, [int, term]) -> true
+        ;   domain_error(signal, Signal, on_signal/2)
+        ),
+        loader:strip_module(Action, _, Action0),
+        (   Action0 == default -> Kind = default
+        ;   Action0 == ignore -> Kind = ignore
+        ;   callable(Action0) -> Kind = goal
+        ;   var(Action0) -> instantiation_error(on_signal/2)
+        ;   type_error(callable, Action0, on_signal/2)
+        ),
+        retractall(signal_goal(Signal, _)),
+        (   Kind == goal -> assertz(signal_goal(Signal, Action))
+        ;   true
+        ),
+        (   '$signal_action'(Signal, Kind) -> true
+        ;   permission_error(modify, signal, Signal, on_signal/2)
+        ).
+
+% called by the machine in place of the goal at P, whose call is
+% interrupted by signals mapped to goals.
+'$signal_goal'(Goal, P, Dyn) :-
+        '$pending_signals'(Signals),
+        maplist(call_signal_goal, Signals),
+        '$trace_call_at'(Goal, P, Dyn).
+
+call_signal_goal(Signal) :-
+        (   signal_goal(Signal, Goal),
+            call(Goal) ->
+            true
+        ;   true
+        ).
+
+permission_error(Action, Type, Culprit, Context) :-
+        throw(error(permission_error(Action, Type, Culprit), Context)).
+
 /* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
    For now, we only support a restricted subset of variable names.
 
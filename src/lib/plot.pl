@@ -0,0 +1,256 @@
+/** Charts rendered as SVG.
+
+`chart_svg/3` renders a line chart, a bar chart or a scatter plot of
+pairs `X-Y` as the string of an SVG image, which browsers show as it
+is, without any other program:
+
+```
+?- chart_svg(line([0-0,1-1,2-4,3-9]), Cs, [title("Squares")]),
+   phrase_to_file(seq(Cs), "squares.svg").
+```
+
+Paired lists of Xs and Ys are made pairs with `pairs_keys_values/3`
+of `library(pairs)`. A chart is one of:
+
+  - `line(Pairs)`, the points X-Y joined by lines in the order of
+    Pairs, where X and Y are numbers.
+  - `scatter(Pairs)`, the points X-Y, where X and Y are numbers.
+  - `bar(Pairs)`, a bar of height Y for each pair, in the order of
+    Pairs, labelled with X, an atom, a number or a string.
+
+The axes are labelled with the least and greatest values of the
+chart. The bars of bar charts rise from their horizontal axis, at 0.
+
+The handlers of `library(http/http_server)` can answer with charts by
+giving the body `text(Cs)` and the header
+`"content-type"-"image/svg+xml"`.
+*/
+
+:- module(plot, [chart_svg//2,
+                 chart_svg/3]).
+
+:- use_module(library(between)).
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(format)).
+:- use_module(library(html_write)).
+:- use_module(library(lists)).
+:- use_module(library(pairs)).
+
+%% chart_svg(+Chart, +Options)//
+%
+% Describes the string of the SVG image of Chart, with the options of
+% `chart_svg/3`.
+chart_svg(Chart, Options) -->
+    { chart_term(Chart, Options, SVG) },
+    html(SVG).
+
+%% chart_svg(+Chart, -Chars, +Options).
+%
+% Chars is the string of the SVG image of Chart. Options are:
+%
+%  * `width(W)` and `height(H)`: the size of the image in pixels, 640
+%    and 400 by default.
+%  * `title(Chars)`: the title written above the chart.
+%  * `color(Chars)`: the color of the lines, points and bars, a color
+%    of CSS such as `"#d62728"`. `"steelblue"` by default.
+chart_svg(Chart, Chars, Options) :-
+    chart_term(Chart, Options, SVG),
+    html_chars(SVG, Chars).
+
+chart_term(Chart, Options, svg([xmlns="http://www.w3.org/2000/svg",
+                                width=W, height=H,
+                                viewBox=ViewBox,
+                                'font-family'="sans-serif",
+                                'font-size'=12],
+                               [rect([width="100%", height="100%", fill=white], []),
+                                Title,
+                                Axes,
+                                Marks])) :-
+    chart(Chart, Kind, Pairs),
+    chart_options(Options, W, H, TitleChars, Color),
+    phrase(format_("0 0 ~d ~d", [W, H]), ViewBox),
+    frame(Kind, Pairs, W, H, Frame),
+    title(TitleChars, W, Title),
+    axes(Kind, Frame, Axes),
+    marks(Kind, Pairs, Frame, Color, Marks).
+
+chart(Chart, Kind, Pairs) :-
+    (   var(Chart) ->
+        instantiation_error(chart_svg/3)
+    ;   Chart =.. [Kind, Pairs],
+        memberchk(Kind, [line, scatter, bar]) ->
+        must_be(list, Pairs),
+        (   Pairs == [] ->
+            domain_error(non_empty_list, Pairs, chart_svg/3)
+        ;   maplist(point(Kind), Pairs)
+        )
+    ;   domain_error(chart, Chart, chart_svg/3)
+    ).
+
+point(Kind, Pair) :-
+    (   var(Pair) ->
+        instantiation_error(chart_svg/3)
+    ;   Pair = X-Y ->
+        (   Kind == bar ->
+            label_chars(X, _)
+        ;   coordinate(X)
+        ),
+        coordinate(Y)
+    ;   type_error(pair, Pair, chart_svg/3)
+    ).
+
+coordinate(X) :-
+    (   var(X) ->
+        instantiation_error(chart_svg/3)
+    ;   number(X) ->
+        true
+    ;   type_error(number, X, chart_svg/3)
+    ).
+
+label_chars(X, Cs) :-
+    (   var(X) ->
+        instantiation_error(chart_svg/3)
+    ;   atom(X) ->
+        atom_chars(X, Cs)
+    ;   number(X) ->
+        number_chars(X, Cs)
+    ;   must_be(chars, X),
+        Cs = X
+    ).
+
+chart_options(Options, W, H, Title, Color) :-
+    must_be(list, Options),
+    option(width(W), Options, 640),
+    option(height(H), Options, 400),
+    maplist(size, [W, H]),
+    option(title(Title), Options, []),
+    must_be(chars, Title),
+    option(color(Color), Options, "steelblue"),
+    must_be(chars, Color).
+
+option(Option, Options, Default) :-
+    (   memberchk(Option, Options) ->
+        true
+    ;   arg(1, Option, Default)
+    ).
+
+% the least size that leaves room for the margins.
+size(N) :-
+    must_be(integer, N),
+    (   N >= 160 ->
+        true
+    ;   domain_error(chart_size, N, chart_svg/3)
+    ).
+
+% frame(Left, Top, Right, Bottom, X0, X1, Y0, Y1): the plotting area
+% in pixels, and the values at its edges. Bar charts have a slot of
+% width 1 for each bar.
+frame(Kind, Pairs, W, H, frame(60, 40, Right, Bottom, X0, X1, Y0, Y1)) :-
+    Right is W - 30,
+    Bottom is H - 40,
+    pairs_keys_values(Pairs, Xs, Ys),
+    (   Kind == bar ->
+        length(Pairs, X1),
+        X0 = 0,
+        list_min([0|Ys], Y0),
+        list_max([0|Ys], Y10)
+    ;   list_min(Xs, X0),
+        list_max(Xs, X10),
+        widened(X0, X10, X1),
+        list_min(Ys, Y0),
+        list_max(Ys, Y10)
+    ),
+    widened(Y0, Y10, Y1).
+
+% a range of a single value is widened to a range of 1, so that there
+% is no division by zero.
+widened(Min, Max0, Max) :-
+    (   Max0 =:= Min ->
+        Max is Min + 1
+    ;   Max = Max0
+    ).
+
+px(frame(Left, _, Right, _, X0, X1, _, _), X, Px) :-
+    Px0 is Left + (X - X0) * (Right - Left) / (X1 - X0),
+    rounded(Px0, Px).
+
+py(frame(_, Top, _, Bottom, _, _, Y0, Y1), Y, Py) :-
+    Py0 is Bottom - (Y - Y0) * (Bottom - Top) / (Y1 - Y0),
+    rounded(Py0, Py).
+
+% coordinates and labels are written to two decimals.
+rounded(X, Y) :-
+    (   integer(X) ->
+        Y = X
+    ;   Y is round(X * 100) / 100
+    ).
+
+title([], _, []).
+title([C|Cs], W, text([x=X, y=24, 'text-anchor'=middle, 'font-size'=16], [C|Cs])) :-
+    X is W // 2.
+
+axes(Kind, Frame, [line([x1=Left, y1=AxisY, x2=Right, y2=AxisY, stroke=black], []),
+                   line([x1=Left, y1=Top, x2=Left, y2=Bottom, stroke=black], []),
+                   text([x=LabelX, y=BottomY, 'text-anchor'=end], Y0Label),
+                   text([x=LabelX, y=TopY, 'text-anchor'=end], Y1Label)
+                   | XLabels]) :-
+    Frame = frame(Left, Top, Right, Bottom, X0, X1, Y0, Y1),
+    LabelX is Left - 6,
+    BottomY is Bottom + 4,
+    TopY is Top + 4,
+    rounded(Y0, Y0Label),
+    rounded(Y1, Y1Label),
+    (   Kind == bar ->
+        py(Frame, 0, AxisY),
+        XLabels = []
+    ;   AxisY = Bottom,
+        LabelY is Bottom + 18,
+        rounded(X0, X0Label),
+        rounded(X1, X1Label),
+        XLabels = [text([x=Left, y=LabelY, 'text-anchor'=middle], X0Label),
+                   text([x=Right, y=LabelY, 'text-anchor'=middle], X1Label)]
+    ).
+
+marks(line, Pairs, Frame, Color, polyline([fill=none, stroke=Color,
+                                           'stroke-width'=2,
+                                           points=Points], [])) :-
+    phrase(points(Pairs, Frame), Points).
+marks(scatter, Pairs, Frame, Color, Circles) :-
+    maplist(circle(Frame, Color), Pairs, Circles).
+marks(bar, Pairs, Frame, Color, Bars) :-
+    length(Pairs, N),
+    findall(I, between(1, N, I), Is),
+    maplist(bar(Frame, Color), Is, Pairs, Bars).
+
+points([], _) --> [].
+points([X-Y|Pairs], Frame) -->
+    { px(Frame, X, Px),
+      py(Frame, Y, Py) },
+    format_("~w,~w", [Px, Py]),
+    (   { Pairs == [] } ->
+        []
+    ;   " "
+    ),
+    points(Pairs, Frame).
+
+circle(Frame, Color, X-Y, circle([cx=Px, cy=Py, r=4, fill=Color], [])) :-
+    px(Frame, X, Px),
+    py(Frame, Y, Py).
+
+% the I-th bar fills the middle 80% of its slot, from 0 to its height.
+bar(Frame, Color, I, X-Y, [rect([x=Bx, y=By, width=Bw, height=Bh, fill=Color], []),
+                          text([x=Cx, y=LabelY, 'text-anchor'=middle], Label)]) :-
+    Frame = frame(_, _, _, Bottom, _, _, _, _),
+    px(Frame, I - 0.9, Bx),
+    px(Frame, I - 0.1, Bx1),
+    px(Frame, I - 0.5, Cx),
+    Bw0 is Bx1 - Bx,
+    rounded(Bw0, Bw),
+    py(Frame, 0, Py0),
+    py(Frame, Y, PyY),
+    By is min(Py0, PyY),
+    Bh0 is abs(Py0 - PyY),
+    rounded(Bh0, Bh),
+    LabelY is Bottom + 18,
+    label_chars(X, Label).
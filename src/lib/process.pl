@@ -4,13 +4,18 @@
     process_release/1, 
     process_wait/2, 
     process_wait/3, 
-    process_kill/1
+    process_kill/1,
+    process_term_server/3,
+    term_server_call/3,
+    term_server_stop/1,
+    serve_terms/1
 ]).
 
 :- use_module(library(error)).
 :- use_module(library(iso_ext)).
-:- use_module(library(lists), [member/2, maplist/2, maplist/3, append/2]).
+:- use_module(library(lists), [member/2, maplist/2, maplist/3, append/2, append/3, length/2]).
 :- use_module(library(reif), [tfilter/3, memberd_t/3]).
+:- use_module(library(charsio), [read_term_from_chars/3, write_term_to_chars/3, chars_utf8bytes/2]).
 
 
 %% process_create(+Exe, +Args:list, +Options).
@@ -36,6 +41,18 @@
 %  * `file(+Path)`  attach the strea to the file at `Path`
 %  * `null`         discards writes and behaves as eof for read. Equivalent to using `file(/dev/null)`
 %  * `pipe(-Steam)` create a new pipe and assigne one end to the created process and the other end to `Stream`
+%  * `stream(+Stream)` attach the stream to an existing pipe `Stream`, the reading end of a pipe for `stdin` and the writing end for `stdout` and `stderr`
+%
+% With `stream/1` the output of one process is piped into another, as in the shell pipeline `ls | wc -l`.
+% `Stream` should be closed after the second process is created, so that it sees end of file when the first one exits:
+%
+% ```
+% ?- process_create("ls", [], [stdout(pipe(Out)), process(P1)]),
+%    process_create("wc", ["-l"], [stdin(stream(Out)), stdout(pipe(Count)), process(P2)]),
+%    close(Out),
+%    get_line_to_chars(Count, Line, []),
+%    process_wait(P1, _), process_wait(P2, _).
+% ```
 %
 % Specifying an option multiple times is an error, when an option is not specified the following defaults apply:
 %
@@ -145,6 +162,145 @@ process_release_(Process) :-
     '$process_release'(Process).
 
 
+%% process_term_server(+Exe, +Args, -Server).
+%
+% Create a process as with `process_create/3` that serves terms over its stdin and stdout,
+% and let `Server` be its handle for `term_server_call/3` and `term_server_stop/1`.
+%
+% Terms are exchanged in frames, each of which consists of the length of its payload in bytes,
+% written in decimal and followed by a newline, and the payload, the term written in canonical
+% form and followed by `.` and a newline. Variables are not shared between a request and its reply.
+% The stderr of the server is inherited.
+%
+% Prolog programs serve terms with `serve_terms/1`. If `server.pl` loads `library(process)`
+% and defines `answer(square(X), Y) :- Y is X*X`, then:
+%
+% ```
+% ?- process_term_server("scryer-prolog", ["-g", "serve_terms(answer), halt", "server.pl"], S),
+%    term_server_call(S, square(3), Reply),
+%    term_server_stop(S).
+% ```
+%
+% binds `Reply` to 9.
+%
+process_term_server(Exe, Args, Server) :- call_with_error_context(process_term_server_(Exe, Args, Server), predicate-process_term_server/3).
+
+process_term_server_(Exe, Args, term_server(Process, In, Out)) :-
+    process_create(Exe, Args, [stdin(pipe(In)), stdout(pipe(Out)), process(Process)]).
+
+%% term_server_call(+Server, +Request, -Reply).
+%
+% Send the term `Request` to the server `Server` created by `process_term_server/3`, and
+% unify `Reply` with the term that it sends back.
+%
+% It's an error if the server exits before it replies.
+%
+term_server_call(Server, Request, Reply) :- call_with_error_context(term_server_call_(Server, Request, Reply), predicate-term_server_call/3).
+
+term_server_call_(Server, Request, Reply) :-
+    valid_term_server(Server),
+    Server = term_server(_, In, Out),
+    write_term_frame(In, Request),
+    (   read_term_frame(Out, Reply0) ->
+        Reply = Reply0
+    ;   throw(error(existence_error(term_server, Server), []))
+    ).
+
+%% term_server_stop(+Server).
+%
+% Close the stdin of the server `Server`, which ends `serve_terms/1`, wait for it to exit and
+% release its process handle.
+%
+term_server_stop(Server) :- call_with_error_context(term_server_stop_(Server), predicate-term_server_stop/1).
+
+term_server_stop_(Server) :-
+    valid_term_server(Server),
+    Server = term_server(Process, In, Out),
+    close(In),
+    process_release(Process),
+    close(Out).
+
+%% serve_terms(:Handler).
+%
+% Serve terms over the current input and output in the frames of `process_term_server/3`:
+% for each `Request` read, `call(Handler, Request, Reply)` is called once and `Reply` is sent
+% back. The loop ends at the end of the input, and with the failure or exception of `Handler`.
+%
+:- meta_predicate(serve_terms(2)).
+
+serve_terms(Handler) :-
+    current_input(In),
+    current_output(Out),
+    serve_terms(Handler, In, Out).
+
+serve_terms(Handler, In, Out) :-
+    (   read_term_frame(In, Request) ->
+        once(call(Handler, Request, Reply)),
+        write_term_frame(Out, Reply),
+        serve_terms(Handler, In, Out)
+    ;   true
+    ).
+
+valid_term_server(Server) :-
+    (   var(Server) -> instantiation_error([])
+    ;   Server = term_server(_, _, _) -> true
+    ;   type_error(term_server, Server, [])
+    ).
+
+write_term_frame(Stream, Term) :-
+    write_term_to_chars(Term, [quoted(true), ignore_ops(true)], Cs0),
+    append(Cs0, ".\n", Cs),
+    chars_utf8bytes(Cs, Bytes),
+    length(Bytes, Length),
+    write(Stream, Length),
+    nl(Stream),
+    maplist(put_char(Stream), Cs),
+    flush_output(Stream).
+
+% fails at the end of Stream.
+read_term_frame(Stream, Term) :-
+    get_char(Stream, C),
+    C \== end_of_file,
+    frame_length(C, Stream, Ds),
+    (   Ds = [_|_],
+        maplist(decimal_digit, Ds) ->
+        number_chars(Length, Ds)
+    ;   throw(error(syntax_error(invalid_term_frame), []))
+    ),
+    frame_payload(Length, Stream, Cs),
+    read_term_from_chars(Cs, Term, []).
+
+frame_length(C, Stream, Ds) :-
+    (   C == end_of_file -> Ds = []
+    ;   C == '\n' -> Ds = []
+    ;   Ds = [C|Ds1],
+        get_char(Stream, C1),
+        frame_length(C1, Stream, Ds1)
+    ).
+
+decimal_digit(C) :- C @>= '0', C @=< '9'.
+
+% Cs are the characters of the next Length bytes of Stream, in UTF-8.
+frame_payload(Length, Stream, Cs) :-
+    (   Length =< 0 -> Cs = []
+    ;   get_char(Stream, C),
+        (   C == end_of_file -> throw(error(syntax_error(incomplete_term_frame), []))
+        ;   char_code(C, Code),
+            utf8_length(Code, N),
+            Length1 is Length - N,
+            Cs = [C|Cs1],
+            frame_payload(Length1, Stream, Cs1)
+        )
+    ).
+
+utf8_length(Code, N) :-
+    (   Code < 0x80 -> N = 1
+    ;   Code < 0x800 -> N = 2
+    ;   Code < 0x10000 -> N = 3
+    ;   N = 4
+    ).
+
+
 must_be_known_options(Valid, Options, Domain) :- call_with_error_context(must_be_known_options_(Valid, [], Options, Domain),predicate-must_be_known_options/3).
 
 must_be_known_options_(_, _,  [], _).
@@ -194,6 +350,7 @@ valid_stdio_(std).
 valid_stdio_(null).
 valid_stdio_(pipe(Stream)) :- must_be(var, Stream).
 valid_stdio_(file(Path)) :- must_be(chars, Path).
+valid_stdio_(stream(Stream)) :- ( var(Stream) -> instantiation_error([]) ; true ).
 
 valid_env(env(E)) :- 
     must_be(list, E),
@@ -0,0 +1,88 @@
+/** Profiling of goals.
+
+`profile/1` runs a goal and records, for each predicate, how often it
+was called, how often it was retried on backtracking, and the time
+spent in it. Calls and redos are counted exactly by the machine. Time
+is charged every few thousand instructions: the _exclusive_ time to
+the predicate running at that moment, and the _inclusive_ time to
+every predicate that is running or waiting for a goal it called to
+return.
+
+```
+?- profile(nrev(...)), show_profile([top(5)]).
+```
+
+The same data is available as a list of terms with
+`statistics(profile, Rows)` from `library(time)`.
+*/
+
+:- module(profile, [profile/1,
+                    show_profile/1]).
+
+:- use_module(library(error)).
+:- use_module(library(format)).
+:- use_module(library(iso_ext), [call_cleanup/2]).
+:- use_module(library(lists), [member/2, reverse/2]).
+:- use_module(library(pairs)).
+
+:- meta_predicate(profile(0)).
+
+%% profile(Goal).
+%
+% Runs Goal like `once/1`, profiling the predicates it calls. The
+% profile replaces that of the previous call of `profile/1`. When
+% called during another profile, Goal is only run.
+profile(Goal) :-
+    (   '$profiler_start' ->
+        call_cleanup(once(Goal), profiler_stop)
+    ;   once(Goal)
+    ).
+
+profiler_stop :-
+    '$profiler_stop'.
+
+%% show_profile(+Options).
+%
+% Prints the predicates of the last profile, in decreasing order.
+% Options are:
+%
+%   - `top(N)`: print at most N predicates, 20 by default.
+%   - `order(Key)`: order the predicates by `calls`, `redos`,
+%     `inclusive` time or `exclusive` time, the default.
+show_profile(Options) :-
+    must_be(list, Options),
+    (   member(top(Top), Options) ->
+        must_be(integer, Top)
+    ;   Top = 20
+    ),
+    (   member(order(Order), Options) ->
+        must_be(atom, Order)
+    ;   Order = exclusive
+    ),
+    (   order_arg(Order, Arg) ->
+        true
+    ;   domain_error(profile_order, Order, show_profile/1)
+    ),
+    '$profile_data'(Rows0),
+    map_list_to_pairs(arg(Arg), Rows0, Pairs0),
+    keysort(Pairs0, Pairs1),
+    reverse(Pairs1, Pairs),
+    pairs_values(Pairs, Rows),
+    format("~s~t~40|~t~s~10+~t~s~10+~t~s~12+~t~s~12+~n",
+           ["Predicate", "Calls", "Redos", "Incl. ms", "Excl. ms"]),
+    show_rows(Rows, Top).
+
+order_arg(calls, 2).
+order_arg(redos, 3).
+order_arg(inclusive, 4).
+order_arg(exclusive, 5).
+
+show_rows([], _).
+show_rows([profile(PI, Calls, Redos, Inclusive, Exclusive)|Rows], Top) :-
+    (   Top > 0 ->
+        format("~q~t~40|~t~d~10+~t~d~10+~t~2f~12+~t~2f~12+~n",
+               [PI, Calls, Redos, Inclusive, Exclusive]),
+        Top1 is Top - 1,
+        show_rows(Rows, Top1)
+    ;   true
+    ).
@@ -0,0 +1,80 @@
+/** Proof trees of successful derivations.
+
+`prove/2` calls a goal and gives, for each of its answers, the tree of
+the goals called in its derivation:
+
+```
+?- prove(grandparent(ann, Z), Proof).
+   Z = cid, Proof = proof(grandparent(ann,cid),[proof(parent(ann,bob),[]),proof(parent(bob,cid),[])]).
+```
+
+A node `proof(Goal, Subproofs)` has the instance of Goal in the answer
+and the proofs of the goals called by the clause that proved it, in
+their order. Goals are run compiled, as they are without `prove/2`:
+the machine records the calls into or out of user code, so that the
+calls of library predicates are leaves, and the builtins the compiler
+inlines, such as `(is)/2` or `(=)/2`, have no nodes.
+*/
+
+:- module(proof_trees, [prove/2]).
+
+:- use_module(library(iso_ext), [b_getval/2, b_setval/2]).
+:- use_module(library(lists), [reverse/2]).
+:- use_module(library(threads)).
+
+:- meta_predicate(prove(0, ?)).
+
+%% prove(:Goal, -Proof).
+%
+% Calls Goal, and unifies Proof with the proof tree of each answer.
+% The proof of a control construct such as a conjunction is a node
+% whose subproofs are those of its goals. The tracer of
+% `library(trace)` doesn't show the ports of Goal.
+prove(Goal0, Proof) :-
+    threads:unqualified_goal(Goal0, Goal),
+    (   catch(b_getval('$proof', Proofs0), _, false) ->
+        true
+    ;   Proofs0 = []
+    ),
+    b_setval('$proof', []),
+    '$trace_mode'(Mode, prove),
+    proved_call(Goal0, Mode),
+    b_getval('$proof', Proofs1),
+    b_setval('$proof', Proofs0),
+    reverse(Proofs1, Proofs),
+    (   Proofs = [proof(Goal1, _)],
+        Goal1 == Goal ->
+        [Proof] = Proofs
+    ;   Proof = proof(Goal, Proofs)
+    ).
+
+% calls Goal with the tracer recording it, restoring Mode when Goal is
+% left. a goal that exits deterministically leaves no choice point.
+proved_call(Goal, Mode) :-
+    '$get_cp'(B0),
+    (   choice_point(B1),
+        catch(Goal, E, ('$trace_mode'(_, Mode), throw(E))),
+        choice_point(B2),
+        (   B1 == B2 ->
+            '$set_cp'(B0),
+            '$trace_mode'(_, Mode)
+        ;   '$trace_mode'(_, Mode)
+        ;   '$trace_mode'(_, prove),
+            false
+        )
+    ;   '$trace_mode'(_, Mode),
+        false
+    ).
+
+choice_point(B) :-
+    '$get_cp'(B).
+
+% called by the machine in place of a recorded goal, whose code is at
+% P.
+'$prove_goal'(Goal, P, Dyn) :-
+    b_getval('$proof', Proofs0),
+    b_setval('$proof', []),
+    '$trace_call_at'(Goal, P, Dyn),
+    b_getval('$proof', Subproofs0),
+    reverse(Subproofs0, Subproofs),
+    b_setval('$proof', [proof(Goal, Subproofs)|Proofs0]).
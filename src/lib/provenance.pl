@@ -0,0 +1,84 @@
+/** Provenance of asserted clauses.
+
+`provenance_call/2` runs a goal and records, for each clause it
+asserts with `asserta/1` or `assertz/1`, the goal, a user tag and the
+time of the assertion. `clause_property/2` retrieves them, so that an
+application can explain which rule asserted a fact it concludes.
+
+```
+?- provenance_call(rule(r1), assertz(likes(ann, tea))),
+   clause_property(likes(ann, X), tag(T)).
+   X = tea, T = rule(r1).
+```
+
+Nothing is recorded outside of `provenance_call/2`, and asserting
+clauses costs the same as without this library until it's first used.
+*/
+
+:- module(provenance, [clause_property/2,
+                       provenance_call/2]).
+
+:- use_module(library(error)).
+:- use_module(library(iso_ext), [bb_get/2, bb_put/2, call_cleanup/2]).
+:- use_module(library(time), [current_time/1]).
+
+% provenance(L, Goal, Time, Tag): the clause whose '$clause'/2 clause
+% is at L was asserted by Goal at Time under Tag.
+:- dynamic(provenance/4).
+
+:- meta_predicate(provenance_call(?, 0)).
+
+%% provenance_call(+Tag, :Goal).
+%
+% Runs Goal like `once/1`, recording the provenance of the clauses it
+% asserts: their properties `source(Goal)`, `tag(Tag)` and
+% `timestamp(T)`, where T is a time stamp as obtained by
+% `current_time/1` of `library(time)`. Inner calls of
+% `provenance_call/2` take precedence over outer ones.
+provenance_call(Tag, Goal) :-
+    (   bb_get('$provenance_context', Context0) ->
+        true
+    ;   Context0 = none
+    ),
+    bb_put('$provenance_context', context(Tag, Goal)),
+    call_cleanup(once(Goal), bb_put('$provenance_context', Context0)).
+
+:- meta_predicate(clause_property(:, ?)).
+
+%% clause_property(:Clause, ?Property).
+%
+% Property is a recorded property of a clause of a dynamic predicate
+% unifying with Clause, which is either `Head :- Body` or a head, whose
+% clauses are considered regardless of their body. Properties are
+% `source(Goal)`, `tag(Tag)` and `timestamp(T)`, as recorded by
+% `provenance_call/2`. Clauses asserted otherwise have no properties.
+clause_property(Clause0, Property) :-
+    loader:strip_module(Clause0, Module, Clause),
+    (   Clause = (Head :- Body) ->
+        true
+    ;   Head = Clause
+    ),
+    (   var(Head) ->
+        instantiation_error(clause_property/2)
+    ;   callable(Head) ->
+        true
+    ;   type_error(callable, Head, clause_property/2)
+    ),
+    '$head_is_dynamic'(Module, Head),
+    builtins:call_retract_helper(Head, Body, P, Module),
+    P = L-_,
+    provenance(L, Goal, Time, Tag),
+    provenance_property(Goal, Time, Tag, Property).
+
+provenance_property(Goal, _, _, source(Goal)).
+provenance_property(_, _, Tag, tag(Tag)).
+provenance_property(_, Time, _, timestamp(Time)).
+
+record_provenance(L, Tag, Goal) :-
+    current_time(Time),
+    % asserted directly, since assertz/1 would record the provenance
+    % of this clause as well.
+    '$assertz'(provenance, provenance(L, Goal, Time, Tag), true).
+
+forget_provenance(L) :-
+    retractall(provenance(L, _, _, _)).
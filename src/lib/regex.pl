@@ -0,0 +1,88 @@
+/** Regular expressions.
+
+Matching, splitting and replacing strings by regular expressions, as
+done by scripts processing logs and other text line by line. The
+expressions are those of the Rust `regex` crate, described at
+https://docs.rs/regex, and are matched in linear time. Patterns are
+compiled once and kept, so the same pattern can be matched against
+many strings cheaply.
+
+Patterns, strings and replacements are lists of characters, and the
+strings produced are partial strings.
+
+```
+?- re_match("(\\w+)=(\\d+)", "a=1, b=22", Match).
+   Match = ["a=1","a","1"]
+;  Match = ["b=22","b","22"]
+;  false.
+?- re_split(",\\s*", "a, b,c", inf, Parts).
+   Parts = ["a","b","c"].
+?- re_replace("(\\w+)@(\\w+)", "$2 at $1", "me@home", inf, Result).
+   Result = "home at me".
+```
+*/
+
+:- module(regex, [re_match/3,
+                  re_split/4,
+                  re_replace/5]).
+
+:- use_module(library(error)).
+:- use_module(library(lists), [member/2]).
+
+%% re_match(+Pattern, +String, -Match).
+%
+% Match is the list of the text of a match of Pattern in String,
+% followed by the texts of the groups of Pattern. Groups that didn't
+% take part in the match are `none`. Each non-overlapping match is
+% found in turn on backtracking, from the start of String. Fails if
+% Pattern doesn't match String.
+re_match(Pattern, String, Match) :-
+    must_be(chars, Pattern),
+    must_be(chars, String),
+    (   '$regex_matches'(Pattern, String, Matches) ->
+        member(Match, Matches)
+    ;   domain_error(regex, Pattern, re_match/3)
+    ).
+
+%% re_split(+Pattern, +String, +Limit, -Parts).
+%
+% Parts is the list of the parts of String separated by matches of
+% Pattern. Limit is `inf` or a positive integer, the most parts to
+% split String into, the last part being the rest of String.
+re_split(Pattern, String, Limit0, Parts) :-
+    must_be(chars, Pattern),
+    must_be(chars, String),
+    limit(Limit0, Limit, re_split/4),
+    (   '$regex_split'(Pattern, String, Limit, Parts0) ->
+        Parts = Parts0
+    ;   domain_error(regex, Pattern, re_split/4)
+    ).
+
+%% re_replace(+Pattern, +Replacement, +String, +Limit, -Result).
+%
+% Result is String with its matches of Pattern replaced by
+% Replacement. Limit is `inf` to replace all matches, or a positive
+% integer, the number of matches replaced from the start of String.
+% In Replacement, `$N` and `${N}` stand for the text of the Nth group
+% of the match, where `$0` is the whole match, `${Name}` for that of a
+% group named by `(?<Name>...)`, and `$$` for `$`.
+re_replace(Pattern, Replacement, String, Limit0, Result) :-
+    must_be(chars, Pattern),
+    must_be(chars, Replacement),
+    must_be(chars, String),
+    limit(Limit0, Limit, re_replace/5),
+    (   '$regex_replace'(Pattern, Replacement, String, Limit, Result0) ->
+        Result = Result0
+    ;   domain_error(regex, Pattern, re_replace/5)
+    ).
+
+% the limit of the builtins, where 0 stands for no limit.
+limit(Limit0, Limit, Context) :-
+    (   Limit0 == inf ->
+        Limit = 0
+    ;   must_be(integer, Limit0),
+        (   Limit0 > 0 ->
+            Limit = Limit0
+        ;   domain_error(positive_integer, Limit0, Context)
+        )
+    ).
@@ -0,0 +1,249 @@
+/** Rate limiters and circuit breakers.
+
+Services that call other systems, over HTTP or sockets, protect them
+and themselves by limiting the rate of their calls, and by ceasing to
+call systems that keep failing until they had time to recover.
+
+A _rate limiter_ is a token bucket: it holds up to a capacity of
+tokens and gains tokens at a constant rate, and each call takes a
+token, waiting until there is one.
+
+A _circuit breaker_ starts closed, letting all calls through. After a
+number of consecutive failed calls it opens, rejecting all calls
+with a resource error until a reset timeout runs out. It then lets a
+single trial call through: if it succeeds, the breaker closes again,
+and else it reopens for another reset timeout.
+
+Rate limiters and circuit breakers are named by atoms, and shared by
+all Prolog machines and threads of a process, like message queues.
+
+```
+?- rate_limiter_create(L, [rate(10), capacity(20)]),
+   circuit_breaker_create(B, [failure_threshold(3), reset_timeout(60)]),
+   with_rate_limit(L, with_circuit_breaker(B, http_open(URL, S, []))).
+```
+*/
+
+:- module(resilience, [rate_limiter_create/2,
+                       rate_limiter_destroy/1,
+                       rate_limiter_acquire/1,
+                       rate_limiter_acquire/2,
+                       rate_limiter_try_acquire/1,
+                       rate_limiter_try_acquire/2,
+                       with_rate_limit/2,
+                       circuit_breaker_create/2,
+                       circuit_breaker_destroy/1,
+                       circuit_breaker_state/2,
+                       with_circuit_breaker/2]).
+
+:- use_module(library(error)).
+:- use_module(library(lists), [maplist/2, member/2]).
+:- use_module(library(time), [sleep/1]).
+
+:- meta_predicate(with_rate_limit(?, 0)).
+:- meta_predicate(with_circuit_breaker(?, 0)).
+
+%% rate_limiter_create(?Limiter, +Options).
+%
+% Creates a rate limiter. If Limiter is an atom, it is used as the
+% name of the limiter, otherwise Limiter is unified with the name of a
+% new anonymous limiter. Options are:
+%
+%  * `rate(Rate)`: the number of tokens gained per second, required.
+%  * `capacity(Capacity)`: the most tokens the limiter holds, the
+%    largest burst of calls it lets through at once. The default is
+%    Rate, and at least 1.
+%
+% The limiter starts with Capacity tokens.
+rate_limiter_create(Limiter, Options) :-
+    must_be(list, Options),
+    maplist(rate_limiter_option, Options),
+    (   member(rate(Rate0), Options) ->
+        Rate is float(Rate0)
+    ;   domain_error(rate_limiter_options, Options, rate_limiter_create/2)
+    ),
+    (   member(capacity(Capacity0), Options) ->
+        Capacity is float(Capacity0)
+    ;   Capacity is max(1.0, Rate)
+    ),
+    (   var(Limiter) ->
+        '$rate_limiter_create'(Limiter, Capacity, Rate)
+    ;   must_be(atom, Limiter),
+        (   '$rate_limiter_create'(Limiter, Capacity, Rate) ->
+            true
+        ;   throw(error(permission_error(create, rate_limiter, Limiter),
+                        rate_limiter_create/2))
+        )
+    ).
+
+rate_limiter_option(O) :-
+    (   var(O) ->
+        instantiation_error(rate_limiter_create/2)
+    ;   O = rate(Rate) ->
+        must_be_positive_number(Rate, rate_limiter_create/2)
+    ;   O = capacity(Capacity) ->
+        must_be_positive_number(Capacity, rate_limiter_create/2)
+    ;   domain_error(rate_limiter_option, O, rate_limiter_create/2)
+    ).
+
+%% rate_limiter_destroy(+Limiter).
+%
+% Destroys the rate limiter Limiter.
+rate_limiter_destroy(Limiter) :-
+    must_be(atom, Limiter),
+    (   '$resilience_object_destroy'(Limiter, rate_limiter) ->
+        true
+    ;   existence_error(rate_limiter, Limiter, rate_limiter_destroy/1)
+    ).
+
+%% rate_limiter_acquire(+Limiter).
+%
+% Same as `rate_limiter_acquire(Limiter, 1)`.
+rate_limiter_acquire(Limiter) :-
+    rate_limiter_acquire(Limiter, 1).
+
+%% rate_limiter_acquire(+Limiter, +N).
+%
+% Takes N tokens of Limiter, sleeping until it holds them. Throws a
+% domain error if N exceeds the capacity of Limiter.
+rate_limiter_acquire(Limiter, N) :-
+    acquire(Limiter, N, Wait, rate_limiter_acquire/2),
+    (   Wait =:= 0 ->
+        true
+    ;   sleep(Wait),
+        rate_limiter_acquire(Limiter, N)
+    ).
+
+%% rate_limiter_try_acquire(+Limiter).
+%
+% Same as `rate_limiter_try_acquire(Limiter, 1)`.
+rate_limiter_try_acquire(Limiter) :-
+    rate_limiter_try_acquire(Limiter, 1).
+
+%% rate_limiter_try_acquire(+Limiter, +N).
+%
+% Takes N tokens of Limiter if it holds them, and fails otherwise.
+rate_limiter_try_acquire(Limiter, N) :-
+    acquire(Limiter, N, Wait, rate_limiter_try_acquire/2),
+    Wait =:= 0.
+
+acquire(Limiter, N, Wait, Context) :-
+    must_be(atom, Limiter),
+    must_be_positive_number(N, Context),
+    N1 is float(N),
+    (   '$rate_limiter_acquire'(Limiter, N1, Wait0) ->
+        (   Wait0 == never ->
+            domain_error(rate_limiter_capacity, N, Context)
+        ;   Wait = Wait0
+        )
+    ;   existence_error(rate_limiter, Limiter, Context)
+    ).
+
+%% with_rate_limit(+Limiter, :Goal).
+%
+% Calls Goal once a token of Limiter is taken.
+with_rate_limit(Limiter, Goal) :-
+    rate_limiter_acquire(Limiter),
+    call(Goal).
+
+%% circuit_breaker_create(?Breaker, +Options).
+%
+% Creates a circuit breaker, named as with `rate_limiter_create/2`.
+% Options are:
+%
+%  * `failure_threshold(N)`: the number of consecutive failed calls
+%    that open the breaker, by default 5.
+%  * `reset_timeout(Seconds)`: how long the breaker stays open, by
+%    default 30.
+circuit_breaker_create(Breaker, Options) :-
+    must_be(list, Options),
+    maplist(circuit_breaker_option, Options),
+    (   member(failure_threshold(Threshold), Options) ->
+        true
+    ;   Threshold = 5
+    ),
+    (   member(reset_timeout(Timeout0), Options) ->
+        Timeout is float(Timeout0)
+    ;   Timeout = 30.0
+    ),
+    (   var(Breaker) ->
+        '$circuit_breaker_create'(Breaker, Threshold, Timeout)
+    ;   must_be(atom, Breaker),
+        (   '$circuit_breaker_create'(Breaker, Threshold, Timeout) ->
+            true
+        ;   throw(error(permission_error(create, circuit_breaker, Breaker),
+                        circuit_breaker_create/2))
+        )
+    ).
+
+circuit_breaker_option(O) :-
+    (   var(O) ->
+        instantiation_error(circuit_breaker_create/2)
+    ;   O = failure_threshold(N) ->
+        must_be(integer, N),
+        (   N > 0 ->
+            true
+        ;   domain_error(positive_integer, N, circuit_breaker_create/2)
+        )
+    ;   O = reset_timeout(Seconds) ->
+        must_be_positive_number(Seconds, circuit_breaker_create/2)
+    ;   domain_error(circuit_breaker_option, O, circuit_breaker_create/2)
+    ).
+
+%% circuit_breaker_destroy(+Breaker).
+%
+% Destroys the circuit breaker Breaker.
+circuit_breaker_destroy(Breaker) :-
+    must_be(atom, Breaker),
+    (   '$resilience_object_destroy'(Breaker, circuit_breaker) ->
+        true
+    ;   existence_error(circuit_breaker, Breaker, circuit_breaker_destroy/1)
+    ).
+
+%% circuit_breaker_state(+Breaker, -State).
+%
+% State is the state of Breaker: `closed`, `open` or `half_open`, the
+% latter once the reset timeout of the open breaker has run out.
+circuit_breaker_state(Breaker, State) :-
+    must_be(atom, Breaker),
+    (   '$circuit_breaker_state'(Breaker, State0) ->
+        State = State0
+    ;   existence_error(circuit_breaker, Breaker, circuit_breaker_state/2)
+    ).
+
+%% with_circuit_breaker(+Breaker, :Goal).
+%
+% Calls Goal once, as `once/1`, if Breaker lets the call through, and
+% throws `error(resource_error(circuit_breaker(Breaker)), Context)`
+% otherwise. The call fails if Goal fails or throws an exception,
+% which is rethrown.
+with_circuit_breaker(Breaker, Goal) :-
+    must_be(atom, Breaker),
+    (   '$circuit_breaker_enter'(Breaker, Permit) ->
+        true
+    ;   existence_error(circuit_breaker, Breaker, with_circuit_breaker/2)
+    ),
+    (   Permit == rejected ->
+        throw(error(resource_error(circuit_breaker(Breaker)), with_circuit_breaker/2))
+    ;   catch(Goal, E, true) ->
+        (   var(E) ->
+            '$circuit_breaker_exit'(Breaker, Permit, success)
+        ;   '$circuit_breaker_exit'(Breaker, Permit, failure),
+            throw(E)
+        )
+    ;   '$circuit_breaker_exit'(Breaker, Permit, failure),
+        false
+    ).
+
+must_be_positive_number(X, Context) :-
+    (   var(X) ->
+        instantiation_error(Context)
+    ;   \+ number(X) ->
+        type_error(number, X, Context)
+    ;   X > 0 ->
+        true
+    ;   domain_error(positive_number, X, Context)
+    ).
+
+existence_error(Type, Culprit, Context) :-
+    throw(error(existence_error(Type, Culprit), Context)).
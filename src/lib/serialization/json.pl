@@ -4,6 +4,8 @@
 
    `json_chars//1` can be used with [`phrase_from_file/2`](src/lib/pio.pl)
    or [`phrase/2`](src/lib/dcgs.pl) to parse and generate [JSON](https://www.json.org/json-en.html).
+   `json_chars/2` reads and writes the same terms much faster, and
+   `json_events_foldl/4` reads documents too large to hold in memory.
 
    BSD 3-Clause License
 
@@ -37,13 +39,19 @@
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
 :- module(json, [
-                 json_chars//1
+                 json_chars//1,
+                 json_chars/2,
+                 json_chars_with_options/3,
+                 json_events_foldl/4
                 ]).
 
 :- use_module(library(dcgs)).
 :- use_module(library(dif)).
+:- use_module(library(error)).
 :- use_module(library(lists)).
 
+:- meta_predicate(json_events_foldl(3, +, ?, ?)).
+
 /*  The DCGs are written to match the McKeeman form presented on the right side of https://www.json.org/json-en.html
     as closely as possible. Note that the names in the McKeeman form conflict with the pictures on the site. */
 json_chars(Internal) --> json_element(Internal).
@@ -271,3 +279,187 @@ json_ws -->
             json_ws_greedy
         ;   json_ws_lazy
         ).
+
+%% json_chars(?Term, ?Chars).
+%
+% Term is the JSON value whose text is Chars, in the representation of
+% `json_chars//1`: objects are `pairs(Pairs)` with pairs
+% `string(Key)-Value` in the order of the text, arrays `list(Values)`,
+% strings `string(Chars)`, numbers `number(N)`, `true` and `false` are
+% `boolean(true)` and `boolean(false)`, and `null` is `null`. Numbers
+% with a fraction or a negative exponent are floats, and other numbers
+% integers.
+%
+% If Chars is a list of characters, it is read as JSON text. Otherwise,
+% Chars is the text of Term, written without whitespace. Both are done
+% by the system in one step, which is much faster than `json_chars//1`
+% and leaves no choice points. Throws `syntax_error(json)` if Chars
+% isn't JSON text.
+%
+% ```
+% ?- json_chars(T, "{\"a\": [1, 2.5, null]}").
+%    T = pairs([string("a")-list([number(1),number(2.5),null])]).
+% ?- json_chars(pairs([string("a")-boolean(true)]), Cs).
+%    Cs = "{\"a\":true}".
+% ```
+json_chars(Term, Chars) :-
+    json_chars_(Term, Chars, [], json_chars/2).
+
+%% json_chars_with_options(?Term, ?Chars, +Options).
+%
+% Like `json_chars/2`, with the representation of Term changed by
+% Options:
+%
+%  * `null(Null)`: `null` is Null, by default `null`.
+%  * `true(True)`, `false(False)`: `true` and `false` are True and
+%    False, by default `boolean(true)` and `boolean(false)`.
+%  * `keys(Keys)`: the keys of objects are read as `string(Chars)` if
+%    Keys is `string`, the default, as atoms if it is `atom` and as
+%    lists of characters if it is `chars`. Keys of each of these forms
+%    are written.
+%
+% ```
+% ?- json_chars_with_options(T, "{\"a\": true}", [keys(atom), true(yes)]).
+%    T = pairs([a-yes]).
+% ```
+json_chars_with_options(Term, Chars, Options) :-
+    json_chars_(Term, Chars, Options, json_chars_with_options/3).
+
+json_chars_(Term, Chars, Options, Context) :-
+    must_be(list, Options),
+    maplist(json_option(Context), Options),
+    json_option_value(null(Null), Options, null),
+    json_option_value(true(True), Options, boolean(true)),
+    json_option_value(false(False), Options, boolean(false)),
+    json_option_value(keys(Keys), Options, string),
+    (   json_text(Chars) ->
+        (   '$json_parse'(Chars, Null, True, False, Keys, Term0) ->
+            Term = Term0
+        ;   throw(error(syntax_error(json), Context))
+        )
+    ;   '$json_generate'(Term, Null, True, False, Keys, Chars0) ->
+        Chars = Chars0
+    ;   ground(Term) ->
+        domain_error(json_term, Term, Context)
+    ;   instantiation_error(Context)
+    ).
+
+json_text(Chars) :-
+    catch(must_be(chars, Chars), error(_, _), false).
+
+json_option(Context, Option) :-
+    (   var(Option) ->
+        instantiation_error(Context)
+    ;   json_option_(Option) ->
+        true
+    ;   domain_error(json_option, Option, Context)
+    ).
+
+json_option_(null(_)).
+json_option_(true(_)).
+json_option_(false(_)).
+json_option_(keys(Keys)) :-
+    memberchk(Keys, [string, atom, chars]).
+
+json_option_value(Option, Options, Default) :-
+    (   memberchk(Option, Options) ->
+        true
+    ;   arg(1, Option, Default)
+    ).
+
+%% json_events_foldl(:Goal, +Stream, ?V0, ?V).
+%
+% Reads the next JSON value of Stream token by token, calling
+% `call(Goal, Event, V0, V1)` for each of its events in turn as
+% `foldl/4` does for the elements of a list. The value is never held in
+% memory as a whole, so documents of any size can be processed. The
+% events are `begin_object`, `key(Chars)`, `end_object`, `begin_array`,
+% `end_array`, and `string(Chars)`, `number(N)`, `boolean(true)`,
+% `boolean(false)` and `null` for the other values, as in `json_chars/2`.
+%
+% Stream is left after the value, so that values following each other,
+% e.g. one per line, are read by repeated calls. If only whitespace is
+% left in Stream, Goal is called with the single event `end_of_file`.
+% Throws `syntax_error(json)` if the text of Stream isn't JSON.
+%
+% ```
+% count_event(_, N0, N) :- N is N0 + 1.
+%
+% ?- open("big.json", read, S), json_events_foldl(count_event, S, 0, N).
+% ```
+json_events_foldl(Goal, Stream, V0, V) :-
+    json_token(Stream, Token),
+    (   Token == end_of_file ->
+        call(Goal, end_of_file, V0, V)
+    ;   json_value_events(Token, Stream, Goal, V0, V)
+    ).
+
+json_token(Stream, Token) :-
+    (   '$json_next_token'(Stream, Token0) ->
+        Token = Token0
+    ;   json_syntax_error
+    ).
+
+json_syntax_error :-
+    throw(error(syntax_error(json), json_events_foldl/4)).
+
+json_value_events(begin_object, Stream, Goal, V0, V) :-
+    !,
+    call(Goal, begin_object, V0, V1),
+    json_token(Stream, Token),
+    (   Token == end_object ->
+        call(Goal, end_object, V1, V)
+    ;   json_member_events(Token, Stream, Goal, V1, V)
+    ).
+json_value_events(begin_array, Stream, Goal, V0, V) :-
+    !,
+    call(Goal, begin_array, V0, V1),
+    json_token(Stream, Token),
+    (   Token == end_array ->
+        call(Goal, end_array, V1, V)
+    ;   json_element_events(Token, Stream, Goal, V1, V)
+    ).
+json_value_events(Token, _, Goal, V0, V) :-
+    json_scalar_event(Token, Event),
+    !,
+    call(Goal, Event, V0, V).
+json_value_events(_, _, _, _, _) :-
+    json_syntax_error.
+
+json_scalar_event(string(Chars), string(Chars)).
+json_scalar_event(number(N), number(N)).
+json_scalar_event(true, boolean(true)).
+json_scalar_event(false, boolean(false)).
+json_scalar_event(null, null).
+
+json_member_events(string(Key), Stream, Goal, V0, V) :-
+    !,
+    call(Goal, key(Key), V0, V1),
+    json_token(Stream, NameSeparator),
+    (   NameSeparator == name_separator ->
+        true
+    ;   json_syntax_error
+    ),
+    json_token(Stream, Token),
+    json_value_events(Token, Stream, Goal, V1, V2),
+    json_token(Stream, Separator),
+    (   Separator == value_separator ->
+        json_token(Stream, Next),
+        json_member_events(Next, Stream, Goal, V2, V)
+    ;   Separator == end_object ->
+        call(Goal, end_object, V2, V)
+    ;   json_syntax_error
+    ).
+json_member_events(_, _, _, _, _) :-
+    json_syntax_error.
+
+json_element_events(Token, Stream, Goal, V0, V) :-
+    json_value_events(Token, Stream, Goal, V0, V1),
+    json_token(Stream, Separator),
+    (   Separator == value_separator ->
+        json_token(Stream, Next),
+        json_element_events(Next, Stream, Goal, V1, V)
+    ;   Separator == end_array ->
+        call(Goal, end_array, V1, V)
+    ;   json_syntax_error
+    ).
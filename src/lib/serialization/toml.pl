@@ -0,0 +1,87 @@
+/** TOML documents.
+
+Reading and writing [TOML](https://toml.io) documents, such as the
+configuration files of many programs. Documents are represented by
+the terms `json_chars/2` of [`library(serialization/json)`](src/lib/serialization/json.pl)
+uses for JSON values: a document and its tables are `pairs(Pairs)` in
+the order of the text, arrays `list(Values)`, strings `string(Chars)`,
+integers and floats `number(N)`, and booleans `boolean(true)` and
+`boolean(false)`. Dates and times are read as strings of their text.
+
+Documents are read and written by the system in one step.
+
+```
+?- toml_chars(T, "title = \"app\"\n[server]\nport = 80\n").
+   T = pairs([string("title")-string("app"),string("server")-pairs([string("port")-number(80)])]).
+?- toml_chars(pairs([string("port")-number(80)]), Cs).
+   Cs = "port = 80\n".
+```
+*/
+
+:- module(toml, [toml_chars/2,
+                 toml_chars_with_options/3]).
+
+:- use_module(library(error)).
+:- use_module(library(lists)).
+
+%% toml_chars(?Term, ?Chars).
+%
+% Term is the value of the TOML document Chars. If Chars is a list of
+% characters, it is read as a TOML document. Otherwise, Chars is the
+% document of Term, which must be `pairs(Pairs)`, as TOML has no other
+% documents, and mustn't have a null, which TOML lacks. Throws
+% `syntax_error(toml)` if Chars isn't a TOML document, or has a float
+% that isn't finite.
+toml_chars(Term, Chars) :-
+    toml_chars_(Term, Chars, [], toml_chars/2).
+
+%% toml_chars_with_options(?Term, ?Chars, +Options).
+%
+% Like `toml_chars/2`, with the representation of Term changed by the
+% options of `json_chars_with_options/3`: `true(True)`, `false(False)`
+% and `keys(Keys)`. `null(Null)` is accepted too, though no document
+% has Null.
+toml_chars_with_options(Term, Chars, Options) :-
+    toml_chars_(Term, Chars, Options, toml_chars_with_options/3).
+
+toml_chars_(Term, Chars, Options, Context) :-
+    must_be(list, Options),
+    maplist(toml_option(Context), Options),
+    toml_option_value(null(Null), Options, null),
+    toml_option_value(true(True), Options, boolean(true)),
+    toml_option_value(false(False), Options, boolean(false)),
+    toml_option_value(keys(Keys), Options, string),
+    (   toml_text(Chars) ->
+        (   '$toml_parse'(Chars, Null, True, False, Keys, Term0) ->
+            Term = Term0
+        ;   throw(error(syntax_error(toml), Context))
+        )
+    ;   '$toml_generate'(Term, Null, True, False, Keys, Chars0) ->
+        Chars = Chars0
+    ;   ground(Term) ->
+        domain_error(toml_term, Term, Context)
+    ;   instantiation_error(Context)
+    ).
+
+toml_text(Chars) :-
+    catch(must_be(chars, Chars), error(_, _), false).
+
+toml_option(Context, Option) :-
+    (   var(Option) ->
+        instantiation_error(Context)
+    ;   toml_option_(Option) ->
+        true
+    ;   domain_error(toml_option, Option, Context)
+    ).
+
+toml_option_(null(_)).
+toml_option_(true(_)).
+toml_option_(false(_)).
+toml_option_(keys(Keys)) :-
+    memberchk(Keys, [string, atom, chars]).
+
+toml_option_value(Option, Options, Default) :-
+    (   memberchk(Option, Options) ->
+        true
+    ;   arg(1, Option, Default)
+    ).
@@ -0,0 +1,88 @@
+/** YAML documents.
+
+Reading and writing [YAML](https://yaml.org) documents, such as the
+configuration files of many programs. Documents are represented by
+the terms `json_chars/2` of [`library(serialization/json)`](src/lib/serialization/json.pl)
+uses for JSON values, so that the same code can process both: mappings
+are `pairs(Pairs)`, sequences `list(Values)`, strings `string(Chars)`,
+numbers `number(N)`, booleans `boolean(true)` and `boolean(false)`,
+and null is `null`.
+
+Documents are read and written by the system in one step. Keys of
+mappings that are numbers, booleans or null are read as their text,
+and so are the scalars with tags of the application, such as
+`!port 80`.
+
+```
+?- yaml_chars(T, "name: app\nports: [80, 443]\n").
+   T = pairs([string("name")-string("app"),string("ports")-list([number(80),number(443)])]).
+?- yaml_chars(pairs([string("debug")-boolean(false)]), Cs).
+   Cs = "debug: false\n".
+```
+*/
+
+:- module(yaml, [yaml_chars/2,
+                 yaml_chars_with_options/3]).
+
+:- use_module(library(error)).
+:- use_module(library(lists)).
+
+%% yaml_chars(?Term, ?Chars).
+%
+% Term is the value of the YAML document Chars. If Chars is a list of
+% characters, it is read as a YAML document. Otherwise, Chars is the
+% document of Term. Throws `syntax_error(yaml)` if Chars isn't a YAML
+% document, or has a value without a term, as the infinite numbers and
+% the mappings with sequences as keys do.
+yaml_chars(Term, Chars) :-
+    yaml_chars_(Term, Chars, [], yaml_chars/2).
+
+%% yaml_chars_with_options(?Term, ?Chars, +Options).
+%
+% Like `yaml_chars/2`, with the representation of Term changed by the
+% options of `json_chars_with_options/3`: `null(Null)`, `true(True)`,
+% `false(False)` and `keys(Keys)`.
+yaml_chars_with_options(Term, Chars, Options) :-
+    yaml_chars_(Term, Chars, Options, yaml_chars_with_options/3).
+
+yaml_chars_(Term, Chars, Options, Context) :-
+    must_be(list, Options),
+    maplist(yaml_option(Context), Options),
+    yaml_option_value(null(Null), Options, null),
+    yaml_option_value(true(True), Options, boolean(true)),
+    yaml_option_value(false(False), Options, boolean(false)),
+    yaml_option_value(keys(Keys), Options, string),
+    (   yaml_text(Chars) ->
+        (   '$yaml_parse'(Chars, Null, True, False, Keys, Term0) ->
+            Term = Term0
+        ;   throw(error(syntax_error(yaml), Context))
+        )
+    ;   '$yaml_generate'(Term, Null, True, False, Keys, Chars0) ->
+        Chars = Chars0
+    ;   ground(Term) ->
+        domain_error(yaml_term, Term, Context)
+    ;   instantiation_error(Context)
+    ).
+
+yaml_text(Chars) :-
+    catch(must_be(chars, Chars), error(_, _), false).
+
+yaml_option(Context, Option) :-
+    (   var(Option) ->
+        instantiation_error(Context)
+    ;   yaml_option_(Option) ->
+        true
+    ;   domain_error(yaml_option, Option, Context)
+    ).
+
+yaml_option_(null(_)).
+yaml_option_(true(_)).
+yaml_option_(false(_)).
+yaml_option_(keys(Keys)) :-
+    memberchk(Keys, [string, atom, chars]).
+
+yaml_option_value(Option, Options, Default) :-
+    (   memberchk(Option, Options) ->
+        true
+    ;   arg(1, Option, Default)
+    ).
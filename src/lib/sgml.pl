@@ -24,6 +24,8 @@ represented as a list of elements where each is of the form:
 
   * a list of characters, representing text
 
+  * `comment(Cs)`, where Cs is the text of a comment
+
   * `element(Name, Attrs, Children)`
 
     - `Name`, an atom, is the name of the tag
@@ -33,8 +35,20 @@ represented as a list of elements where each is of the form:
 
     - `Children` is a list of elements as specified here.
 
-Currently, Options are ignored. In the future, more options may be
-provided to control parsing.
+Options is a list of:
+
+  - `space(Space)`, where Space is `preserve` (the default), keeping
+    all text, or `remove`, removing the text that consists only of
+    whitespace, such as the indentation between elements.
+  - `fragment(Boolean)`, for `load_html/3` only. If `true`, the
+    document is parsed as a fragment of HTML, such as the content
+    of a `body` element, and Es are its elements rather than those
+    of a complete document. The default is `false`.
+
+HTML is parsed as browsers do, tolerating malformed documents. XML
+documents must be well-formed, otherwise `syntax_error(xml)` is
+thrown. Es of an XML document is a list of its root element, with
+processing instructions omitted.
 
 Example:
 
@@ -60,7 +74,18 @@ For example, to fetch the title of the document above, we can use:
       xpath(Es, //title(text), T).
 ```
 
-Yielding `T = "Hello!"`.
+Yielding `T = "Hello!"`. The list of nodes loaded is a DOM of its own,
+whose elements are its roots, so paths starting at the root such as
+`/html/head/title(text)` select from it as well.
+
+Fragments of HTML and indented XML are parsed with:
+
+```
+   ?- load_html("<li>a</li><li>b</li>", Es, [fragment(true)]).
+      Es = [element(li,[],["a"]),element(li,[],["b"])].
+   ?- load_xml("<a>\n  <b/>\n</a>", Es, [space(remove)]).
+      Es = [element(a,[],[element(b,[],[])])].
+```
 
 Use `http_open/3` from `library(http/http_open)` to read answers from
 web servers via streams.
@@ -74,14 +99,15 @@ web servers via streams.
 :- use_module(library(dcgs)).
 :- use_module(library(pio)).
 :- use_module(library(charsio)).
+:- use_module(library(lists)).
 
 load_html(Source, Es, Options) :-
         must_be_source(Source, load_html/3),
-        must_be(list, Options),
+        must_be_options(Options, html, load_html/3),
         load_structure_(Source, Es, Options, html).
 load_xml(Source, Es, Options) :-
         must_be_source(Source, load_xml/3),
-        must_be(list, Options),
+        must_be_options(Options, xml, load_xml/3),
         load_structure_(Source, Es, Options, xml).
 
 must_be_source(Source, Context) :-
@@ -95,15 +121,64 @@ is_sgml_source(stream(_)).
 is_sgml_source([]).
 is_sgml_source([C|Cs]) :- must_be(chars, [C|Cs]).
 
+must_be_options(Options, What, Context) :-
+        must_be(list, Options),
+        maplist(must_be_option(What, Context), Options).
+
+must_be_option(What, Context, Option) :-
+        (   var(Option) -> instantiation_error(Context)
+        ;   sgml_option(What, Option) -> true
+        ;   domain_error(sgml_option, Option, Context)
+        ).
+
+sgml_option(_, space(Space)) :-
+        atom(Space),
+        memberchk(Space, [preserve,remove]).
+sgml_option(html, fragment(Boolean)) :-
+        atom(Boolean),
+        memberchk(Boolean, [true,false]).
+
+option(Option, Options, Default) :-
+        (   memberchk(Option, Options) -> true
+        ;   arg(1, Option, Default)
+        ).
+
 load_structure_([], [], _, _).
-load_structure_([C|Cs], [E|Es], Options, What) :-
-        load_(What, [C|Cs], [E|Es], Options).
-load_structure_(file(Fs), [E|Es], Options, What) :-
+load_structure_([C|Cs], Es, Options, What) :-
+        load_(What, [C|Cs], Es, Options).
+load_structure_(file(Fs), Es, Options, What) :-
         once(phrase_from_file(seq(Cs), Fs)),
-        load_(What, Cs, [E|Es], Options).
-load_structure_(stream(Stream), [E|Es], Options, What) :-
+        load_(What, Cs, Es, Options).
+load_structure_(stream(Stream), Es, Options, What) :-
         get_n_chars(Stream, _, Cs),
-        load_(What, Cs, [E|Es], Options).
+        load_(What, Cs, Es, Options).
+
+load_(What, Cs, Es, Options) :-
+        parse_(What, Cs, Es0, Options),
+        option(space(Space), Options, preserve),
+        space(Space, Es0, Es).
+
+parse_(html, Cs, Es, Options) :-
+        option(fragment(Fragment), Options, false),
+        '$load_html'(Cs, Es, Fragment).
+parse_(xml, Cs, [E], _) :-
+        (   '$load_xml'(Cs, E0, []) -> E = E0
+        ;   throw(error(syntax_error(xml), load_xml/3))
+        ).
 
-load_(html, Cs, E, Options) :- '$load_html'(Cs, E, Options).
-load_(xml, Cs, E, Options) :- '$load_xml'(Cs, E, Options).
+space(preserve, Es, Es).
+space(remove, Es0, Es) :-
+        remove_space(Es0, Es).
+
+remove_space([], []).
+remove_space([E0|Es0], Es) :-
+        (   E0 = element(Name, Attrs, Children0) ->
+            remove_space(Children0, Children),
+            Es = [element(Name, Attrs, Children)|Es1]
+        ;   E0 = [_|_], maplist(space_char, E0) ->
+            Es = Es1
+        ;   Es = [E0|Es1]
+        ),
+        remove_space(Es0, Es1).
+
+space_char(C) :- char_type(C, whitespace).
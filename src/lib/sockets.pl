@@ -3,14 +3,22 @@ Predicates for handling network sockets, both as a server and as a client.
 As a server, you should open a socket an call `socket_server_accept/4` to get a stream for each connection.
 As a client, you should just open a socket and you will receive a stream.
 In both cases, with a stream, you can use the usual predicates to read and write to the stream.
+UDP sockets have no connections and no streams: open one with `udp_socket_open/2` and send and receive
+datagrams, lists of bytes, with `udp_send/3` and `udp_receive/3`.
 */
 :- module(sockets, [socket_client_open/3,
                     socket_server_open/2,
                     socket_server_accept/4,
                     socket_server_close/1,
+                    udp_socket_open/2,
+                    udp_send/3,
+                    udp_receive/3,
+                    udp_socket_close/1,
                     current_hostname/1]).
 
+:- use_module(library(between)).
 :- use_module(library(error)).
+:- use_module(library(lists)).
 
 %% socket_client_open(+Addr, -Stream, +Options).
 %
@@ -87,6 +95,64 @@ socket_server_accept(ServerSocket, Client, Stream, Options) :-
 socket_server_close(ServerSocket) :-
     '$socket_server_close'(ServerSocket).
 
+%% udp_socket_open(+Addr, -Socket).
+%
+% Open a UDP Socket bound to Addr, which is as in `socket_server_open/2`. If the port of Addr is a
+% variable, it is unified with the port chosen by the operating system.
+udp_socket_open(Addr, Socket) :-
+    must_be(var, Socket),
+    (  ( integer(Addr) ; var(Addr) ) ->
+       '$udp_socket_open'([], Addr, Socket)
+    ;
+       Addr = Address:Port,
+       must_be(atom, Address),
+       can_be(integer, Port),
+       '$udp_socket_open'(Address, Port, Socket)
+    ).
+
+%% udp_send(+Socket, +Bytes, +Addr).
+%
+% Send the list of bytes Bytes as a datagram from Socket to Addr, which must satisfy
+% `Addr = Address:Port`. Datagrams may be lost on their way, and nothing tells if they are.
+udp_send(Socket, Bytes, Addr) :-
+    must_be(list, Bytes),
+    maplist(udp_byte, Bytes),
+    (  ( var(Addr) ; Addr = Address:Port, ( var(Address) ; var(Port) ) ) ->
+       throw(error(instantiation_error, udp_send/3))
+    ;
+       true
+    ),
+    (  Addr = Address:Port,
+       atom(Address),
+       integer(Port),
+       between(0, 65535, Port) ->
+       true
+    ;
+       throw(error(type_error(socket_address, Addr), udp_send/3))
+    ),
+    '$udp_send'(Socket, Bytes, Address, Port).
+
+udp_byte(B) :-
+    must_be(integer, B),
+    (  between(0, 255, B) ->
+       true
+    ;
+       throw(error(type_error(byte, B), udp_send/3))
+    ).
+
+%% udp_receive(+Socket, -Bytes, -Addr).
+%
+% Wait for the next datagram received by Socket, and unify Bytes with its bytes and Addr with
+% `Address:Port` of its sender, to which an answer can be sent with `udp_send/3`.
+udp_receive(Socket, Bytes, Addr) :-
+    '$udp_receive'(Socket, Bytes, Addr).
+
+%% udp_socket_close(+Socket).
+%
+% Close the UDP Socket.
+udp_socket_close(Socket) :-
+    '$udp_socket_close'(Socket).
+
 %% current_hostname(-HostName).
 %
 % Returns the current hostname of the computer in which Scryer Prolog is executing right now
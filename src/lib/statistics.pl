@@ -0,0 +1,146 @@
+/** Descriptive statistics of lists of numbers.
+
+```
+?- mean([1,2,3,4], M).
+   M = 2.5.
+?- median([3,1,4,1,5], M).
+   M = 3.
+?- stddev([2,4,4,4,5,5,7,9], S).
+   S = 2.138089935299395.
+?- quantiles([0.25,0.5,0.75], [1,2,3,4,5,6,7,8], Qs).
+   Qs = [2.75,4.5,6.25].
+```
+
+The variance and standard deviation are those of a sample, dividing
+by N - 1 for N numbers. They are computed in a single pass by
+Welford's method, which avoids the loss of precision of subtracting
+large sums of squares. Quantiles interpolate linearly between the
+closest ranks, as the default methods of R and NumPy do.
+
+All predicates throw a domain error for empty lists.
+*/
+
+:- module(statistics, [mean/2,
+                       median/2,
+                       variance/2,
+                       stddev/2,
+                       quantile/3,
+                       quantiles/3]).
+
+:- use_module(library(error)).
+:- use_module(library(lists)).
+:- use_module(library(pairs)).
+
+%% mean(+Xs, -Mean).
+%
+% Mean is the arithmetic mean of the numbers Xs.
+mean(Xs, Mean) :-
+    numbers(Xs, N, mean/2),
+    sum_list(Xs, Sum),
+    Mean is Sum / N.
+
+%% median(+Xs, -Median).
+%
+% Median is the middle number of Xs in order, or the mean of the two
+% middle numbers if Xs has an even length.
+median(Xs, Median) :-
+    numbers(Xs, N, median/2),
+    sort_numbers(Xs, Sorted),
+    I is (N - 1) // 2,
+    nth0_tail(I, Sorted, [X0|Rest]),
+    (   N mod 2 =:= 1 ->
+        Median = X0
+    ;   Rest = [X1|_],
+        Median is (X0 + X1) / 2
+    ).
+
+%% variance(+Xs, -Variance).
+%
+% Variance is the sample variance of the numbers Xs, of which there
+% must be at least two.
+variance(Xs, Variance) :-
+    numbers(Xs, N, variance/2),
+    (   N < 2 ->
+        throw(error(evaluation_error(undefined), variance/2))
+    ;   foldl(welford, Xs, 0-0.0-0.0, _-_-M2),
+        Variance is M2 / (N - 1)
+    ).
+
+welford(X, K0-Mean0-M20, K-Mean-M2) :-
+    K is K0 + 1,
+    Delta is X - Mean0,
+    Mean is Mean0 + Delta / K,
+    M2 is M20 + Delta * (X - Mean).
+
+%% stddev(+Xs, -StdDev).
+%
+% StdDev is the sample standard deviation of the numbers Xs.
+stddev(Xs, StdDev) :-
+    variance(Xs, Variance),
+    StdDev is sqrt(Variance).
+
+%% quantile(+P, +Xs, -Q).
+%
+% Q is the P-quantile of the numbers Xs, where P is a number between
+% 0 and 1.
+quantile(P, Xs, Q) :-
+    quantiles([P], Xs, [Q]).
+
+%% quantiles(+Ps, +Xs, -Qs).
+%
+% Qs are the quantiles of the numbers Xs for each probability in Ps,
+% sorting Xs once.
+quantiles(Ps, Xs, Qs) :-
+    must_be(list, Ps),
+    maplist(probability, Ps),
+    numbers(Xs, N, quantiles/3),
+    sort_numbers(Xs, Sorted),
+    maplist(quantile_(N, Sorted), Ps, Qs).
+
+probability(P) :-
+    (   var(P) ->
+        instantiation_error(quantiles/3)
+    ;   \+ number(P) ->
+        type_error(number, P, quantiles/3)
+    ;   P >= 0,
+        P =< 1 ->
+        true
+    ;   domain_error(probability, P, quantiles/3)
+    ).
+
+quantile_(N, Sorted, P, Q) :-
+    H is (N - 1) * P,
+    I is floor(H),
+    nth0_tail(I, Sorted, [X0|Rest]),
+    (   H =:= I ->
+        Q = X0
+    ;   Rest = [X1|_],
+        Q is X0 + (H - I) * (X1 - X0)
+    ).
+
+nth0_tail(I, Xs, Tail) :-
+    length(Prefix, I),
+    append(Prefix, Tail, Xs).
+
+% sorts Xs without removing duplicates.
+sort_numbers(Xs, Sorted) :-
+    pairs_keys(Pairs, Xs),
+    keysort(Pairs, SortedPairs),
+    pairs_keys(SortedPairs, Sorted).
+
+numbers(Xs, N, PI) :-
+    must_be(list, Xs),
+    maplist(number_(PI), Xs),
+    length(Xs, N),
+    (   N =:= 0 ->
+        domain_error(non_empty_list, Xs, PI)
+    ;   true
+    ).
+
+number_(PI, X) :-
+    (   var(X) ->
+        instantiation_error(PI)
+    ;   number(X) ->
+        true
+    ;   type_error(number, X, PI)
+    ).
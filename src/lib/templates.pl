@@ -0,0 +1,253 @@
+/** Templates.
+
+Mustache-like templates, rendered to strings from Prolog data. A
+template is a string with tags between double braces:
+
+  - `{{name}}` is replaced by the value of `name`, escaped for HTML.
+  - `{{{name}}}` and `{{& name}}` are replaced by the value unescaped.
+  - `{{#name}}...{{/name}}` is a section, rendered once for each element
+    of a list, once if the value is any other value than `false` or
+    `[]`, and not at all otherwise.
+  - `{{^name}}...{{/name}}` is an inverted section, rendered only if
+    `name` has no value, or the value is `false` or `[]`.
+  - `{{> name}}` is replaced by the partial template `name`.
+  - `{{! comment}}` is a comment.
+
+The data of a template is a list of pairs `Key-Value` or `Key=Value`,
+where the keys are atoms. Values are strings, atoms, numbers, lists
+or again lists of pairs, whose keys can then be named in sections and
+with dotted names like `{{author.name}}`. Names are looked up in the
+elements of the enclosing sections first, from the innermost, and
+then in the data; `{{.}}` is the element of the innermost section.
+Names without a value are replaced by nothing. A list of pairs is one
+element, so a section over it is rendered once; a list of characters
+is a string.
+
+```
+?- render_template("<h1>{{title}}</h1>\n{{#items}}<li>{{.}}</li>\n{{/items}}",
+                   [title="Fish & Chips", items=["cod","haddock"]], Cs).
+   Cs = "<h1>Fish &amp; Chips</h1>\n<li>cod</li>\n<li>haddock</li>\n".
+```
+
+Templates are parsed in Rust, and `compile_template/2` parses a
+template once for rendering it many times. The handlers of
+`library(http/http_server)` can answer with rendered pages:
+
+```
+:- use_module(library(http/http_server)).
+:- use_module(library(templates)).
+
+hello_handler(Name, _Request, Response) :-
+    render_template("<p>Hello, {{name}}!</p>", [name=Name], Html),
+    http_headers(Response, ["content-type"-"text/html; charset=utf-8"]),
+    http_body(Response, text(Html)).
+```
+*/
+
+:- module(templates, [compile_template/2,
+                      render_template/3,
+                      render_template/4]).
+
+:- use_module(library(charsio), [write_term_to_chars/3]).
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(lists)).
+
+%% compile_template(+Template, -Compiled).
+%
+% Compiled is the string Template parsed, for rendering with
+% `render_template/3,4`. A template whose tags aren't closed, or whose
+% sections aren't balanced, raises a syntax error.
+compile_template(Template, template(Tree)) :-
+    template_tree(Template, Tree, compile_template/2).
+
+%% render_template(+Template, +Data, -Chars).
+%
+% Same as `render_template(Template, Data, Chars, [])`.
+render_template(Template, Data, Chars) :-
+    render_template(Template, Data, Chars, []).
+
+%% render_template(+Template, +Data, -Chars, +Options).
+%
+% Chars is the string Template, or a template compiled by
+% `compile_template/2`, rendered with the values of Data. Options are:
+%
+%  * `escape(Mode)`: how the values of `{{name}}` tags are escaped,
+%    `html` (the default) replacing `&`, `<`, `>`, `"` and `'` by
+%    entities, or `none`.
+%  * `partials(Partials)`: the partial templates, a list of pairs
+%    `Name-Template` of atoms and templates as Template. Partials that
+%    aren't given are replaced by nothing.
+render_template(Template, Data, Chars, Options) :-
+    template_tree(Template, Tree, render_template/4),
+    must_be(list, Data),
+    render_options(Options, Opts),
+    phrase(nodes(Tree, [Data], Opts), Chars).
+
+template_tree(Template, Tree, Context) :-
+    (   var(Template) ->
+        instantiation_error(Context)
+    ;   Template = template(Tree0) ->
+        Tree = Tree0
+    ;   '$parse_template'(Template, Tokens) ->
+        (   Tokens = error(Reason) ->
+            throw(error(syntax_error(Reason), Context))
+        ;   nest(Tokens, Tree, [])
+        )
+    ;   type_error(chars, Template, Context)
+    ).
+
+% nest(Tokens0, Nodes, Tokens): Nodes are the tokens up to the end of
+% the innermost section, with the tokens of their sections nested.
+nest([], [], []).
+nest([end|Tokens], [], Tokens) :- !.
+nest([section(Path)|Tokens0], [section(Path, Body)|Nodes], Tokens) :-
+    !,
+    nest(Tokens0, Body, Tokens1),
+    nest(Tokens1, Nodes, Tokens).
+nest([inverted(Path)|Tokens0], [inverted(Path, Body)|Nodes], Tokens) :-
+    !,
+    nest(Tokens0, Body, Tokens1),
+    nest(Tokens1, Nodes, Tokens).
+nest([Token|Tokens0], [Token|Nodes], Tokens) :-
+    nest(Tokens0, Nodes, Tokens).
+
+render_options(Options, opts(Escape, Partials)) :-
+    must_be(list, Options),
+    (   member(Option, Options), var(Option) ->
+        instantiation_error(render_template/4)
+    ;   true
+    ),
+    (   member(escape(Escape0), Options) ->
+        (   memberchk(Escape0, [html, none]) ->
+            Escape = Escape0
+        ;   domain_error(template_option, escape(Escape0), render_template/4)
+        )
+    ;   Escape = html
+    ),
+    (   member(partials(Partials0), Options) ->
+        must_be(list, Partials0),
+        Partials = Partials0
+    ;   Partials = []
+    ).
+
+nodes([], _, _) --> [].
+nodes([Node|Nodes], Contexts, Opts) -->
+    node(Node, Contexts, Opts),
+    nodes(Nodes, Contexts, Opts).
+
+node(text(Text), _, _) --> seq(Text).
+node(var(Path, Mode), Contexts, opts(Escape, _)) -->
+    (   { lookup(Path, Contexts, Value) } ->
+        { value_chars(Value, Cs) },
+        (   { Mode == html, Escape == html } ->
+            html_escaped(Cs)
+        ;   seq(Cs)
+        )
+    ;   []
+    ).
+node(section(Path, Body), Contexts, Opts) -->
+    (   { lookup(Path, Contexts, Value) } ->
+        section(Value, Body, Contexts, Opts)
+    ;   []
+    ).
+node(inverted(Path, Body), Contexts, Opts) -->
+    (   { lookup(Path, Contexts, Value), \+ falsy(Value) } ->
+        []
+    ;   nodes(Body, Contexts, Opts)
+    ).
+node(partial(Name), Contexts, Opts) -->
+    { Opts = opts(_, Partials) },
+    (   { member(Name0-Partial, Partials), Name0 == Name } ->
+        { template_tree(Partial, Tree, render_template/4) },
+        nodes(Tree, Contexts, Opts)
+    ;   []
+    ).
+
+section(Value, Body, Contexts, Opts) -->
+    (   { falsy(Value) } ->
+        []
+    ;   { Value == true } ->
+        nodes(Body, Contexts, Opts)
+    ;   { chars(Value) ; pairs(Value) ; \+ proper_list(Value) } ->
+        nodes(Body, [Value|Contexts], Opts)
+    ;   elements(Value, Body, Contexts, Opts)
+    ).
+
+elements([], _, _, _) --> [].
+elements([Value|Values], Body, Contexts, Opts) -->
+    nodes(Body, [Value|Contexts], Opts),
+    elements(Values, Body, Contexts, Opts).
+
+falsy(Value) :-
+    (   Value == false -> true
+    ;   Value == []
+    ).
+
+% lookup(Path, Contexts, Value): the first name of Path is looked up in
+% the innermost context having it, the other names in its value.
+lookup([], [Value|_], Value).
+lookup([Name|Names], Contexts, Value) :-
+    member(Context, Contexts),
+    context_value(Context, Name, Value0),
+    !,
+    path_value(Names, Value0, Value).
+
+path_value([], Value, Value).
+path_value([Name|Names], Value0, Value) :-
+    context_value(Value0, Name, Value1),
+    path_value(Names, Value1, Value).
+
+context_value(Context, Name, Value) :-
+    pairs(Context),
+    member(Pair, Context),
+    pair_key_value(Pair, Name, Value),
+    !.
+
+pair_key_value(Key-Value, Key, Value).
+pair_key_value(Key=Value, Key, Value).
+
+pairs(Term) :-
+    Term = [_|_],
+    proper_list(Term),
+    \+ ( member(Pair, Term),
+         \+ ( nonvar(Pair), pair_key_value(Pair, Key, _), atom(Key) )
+       ).
+
+chars(Term) :-
+    Term = [_|_],
+    proper_list(Term),
+    \+ ( member(C, Term), \+ ( atom(C), atom_length(C, 1) ) ).
+
+value_chars(Value, Cs) :-
+    (   var(Value) ->
+        instantiation_error(render_template/4)
+    ;   chars(Value) ->
+        Cs = Value
+    ;   Value == [] ->
+        Cs = []
+    ;   atom(Value) ->
+        atom_chars(Value, Cs)
+    ;   number(Value) ->
+        number_chars(Value, Cs)
+    ;   write_term_to_chars(Value, [], Cs)
+    ).
+
+proper_list(Term) :-
+    '$skip_max_list'(_, _, Term, Tail),
+    Tail == [].
+
+html_escaped([]) --> [].
+html_escaped([C|Cs]) -->
+    { html_entity(C, Es) },
+    seq(Es),
+    html_escaped(Cs).
+
+html_entity(C, Es) :-
+    (   C == (&) -> Es = "&amp;"
+    ;   C == (<) -> Es = "&lt;"
+    ;   C == (>) -> Es = "&gt;"
+    ;   C == '"' -> Es = "&quot;"
+    ;   C == '\'' -> Es = "&#39;"
+    ;   Es = [C]
+    ).
@@ -1,19 +1,160 @@
-:- module(terms, [numbervars/3]).
+:- module(terms, [numbervars/3,
+                  numbervars/4,
+                  term_variant/2,
+                  term_hash/2,
+                  term_hash/4]).
 
 :- use_module(library(error)).
+:- use_module(library(lists), [memberchk/2]).
 
+%% numbervars(?Term, +N0, ?N).
+%
+% Binds the variables of Term, in the order of their first occurrence,
+% to `'$VAR'(N0)`, `'$VAR'(N0+1)`, ..., `'$VAR'(N-1)`. `write_term/2`
+% with `numbervars(true)` writes these terms as the letters `A`, `B`,
+% ..., `Z`, `A1`, ..., which is how answers name variables if the
+% flag `answer_variable_names` is `letters`.
+%
+% ```
+% ?- T = f(X, Y, X), numbervars(T, 0, N), write_term(T, [numbervars(true)]), nl.
+% f(A,B,A)
+%    T = f('$VAR'(0),'$VAR'(1),'$VAR'(0)), X = '$VAR'(0), Y = '$VAR'(1), N = 2.
+% ```
 numbervars(Term, N0, N) :-
-   catch(internal_numbervars(Term, N0, N),
-	     error(E,Ctx),
-	     ( ( var(Ctx) -> Ctx = numbervars/3 ; true ), throw(error(E,Ctx) ) ) ).
-
-internal_numbervars(Term, N0, N) :-
-   must_be(integer, N0),
-   can_be(integer, N),
-   term_variables(Term, Vars),
-   numberlist(Vars, N0, N).
-
-numberlist([], N, N).
-numberlist(['$VAR'(N0)|Vars], N0, N) :-
+   numbervars_(Term, N0, N, [], numbervars/3).
+
+%% numbervars(?Term, +N0, ?N, +Options).
+%
+% Like numbervars/3, with a list of options:
+%
+%  * `functor_name(F)`: bind the variables to `F(N0)`, ... instead of
+%    `'$VAR'(N0)`, ...
+%  * `attvar(Action)`: how attributed variables are handled. `bind`
+%    (the default) binds them like other variables, which runs their
+%    attribute hooks. `skip` leaves them unbound and `error` throws a
+%    type error if there are any.
+numbervars(Term, N0, N, Options) :-
+   numbervars_(Term, N0, N, Options, numbervars/4).
+
+numbervars_(Term, N0, N, Options, PI) :-
+   (  var(N0) -> instantiation_error(PI)
+   ;  integer(N0) -> true
+   ;  type_error(integer, N0, PI)
+   ),
+   (  var(N) -> true
+   ;  integer(N) -> true
+   ;  type_error(integer, N, PI)
+   ),
+   numbervars_options(Options, '$VAR', F, bind, AttVar, PI),
+   term_variables(Term, Vars0),
+   (  AttVar == bind ->
+      Vars = Vars0
+   ;  '$term_attributed_variables'(Term, AttVars),
+      (  AttVars == [] ->
+         Vars = Vars0
+      ;  AttVar == error ->
+         type_error(free_of_attvar, Term, PI)
+      ;  exclude_variables(Vars0, AttVars, Vars)
+      )
+   ),
+   numberlist(Vars, F, N0, N).
+
+numbervars_options(Options, _, _, _, _, PI) :-
+   var(Options),
+   instantiation_error(PI).
+numbervars_options([], F, F, AttVar, AttVar, _) :-
+   !.
+numbervars_options([Option|Options], F0, F, AttVar0, AttVar, PI) :-
+   !,
+   (  var(Option) ->
+      instantiation_error(PI)
+   ;  Option = functor_name(F1), atom(F1) ->
+      numbervars_options(Options, F1, F, AttVar0, AttVar, PI)
+   ;  Option = attvar(AttVar1), memberchk(AttVar1, [bind, skip, error]) ->
+      numbervars_options(Options, F0, F, AttVar1, AttVar, PI)
+   ;  domain_error(numbervars_option, Option, PI)
+   ).
+numbervars_options(Options, _, _, _, _, PI) :-
+   type_error(list, Options, PI).
+
+exclude_variables([], _, []).
+exclude_variables([V|Vs0], Excluded, Vs) :-
+   (  variable_memberchk(V, Excluded) ->
+      Vs = Vs1
+   ;  Vs = [V|Vs1]
+   ),
+   exclude_variables(Vs0, Excluded, Vs1).
+
+variable_memberchk(V, [W|Ws]) :-
+   (  V == W -> true
+   ;  variable_memberchk(V, Ws)
+   ).
+
+numberlist([], _, N, N).
+numberlist([V|Vs], F, N0, N) :-
+   functor(V, F, 1),
+   arg(1, V, N0),
    N1 is N0+1,
-   numberlist(Vars, N1, N).
+   numberlist(Vs, F, N1, N).
+
+%% term_variant(?X, ?Y).
+%
+% True iff X and Y are variants of each other: they are equal up to
+% a one-to-one renaming of their variables. No variable is bound.
+%
+% ```
+% ?- term_variant(f(X, Y, X), f(A, B, A)).
+%    true.
+% ?- term_variant(f(X, Y), f(A, A)).
+%    false.
+% ```
+term_variant(X, Y) :-
+   '$term_variant'(X, Y).
+
+%% term_hash(?Term, -Hash).
+%
+% Hash is an integer between 0 and 2^32-1 hashing Term if Term is
+% ground, and is left unbound otherwise. Terms equal in the standard
+% order of terms hash alike, and the hash of a term is the same
+% across sessions and platforms.
+%
+% The flag `term_hash_algorithm` selects how terms are hashed: with
+% `fxhash`, the default, hashing is fast but collisions are easy to
+% construct, while `sha3_256` and `blake2b512` hash terms with the
+% first 64 bits of these digests, for hash tables keyed by terms
+% from untrusted sources.
+%
+% ```
+% ?- term_hash(f("ab", [1.0]), H0), term_hash(f([a,b], [1.0]), H).
+%    H0 = H, ... .
+% ?- term_hash(f(_), H).
+%    true.
+% ```
+term_hash(Term, Hash) :-
+   term_hash(Term, -1, 4294967296, Hash).
+
+%% term_hash(?Term, +Depth, +Range, -Hash).
+%
+% Like term_hash/2, but only inspects Term down to Depth, where Term
+% itself is at depth 1 and -1 inspects the whole term. Hash is left
+% unbound only if a variable occurs within Depth. Hash is an integer
+% between 0 and Range-1.
+%
+% ```
+% ?- term_hash(f(a, g(_)), 2, 100, H).
+%    H = ... .
+% ```
+term_hash(Term, Depth, Range, Hash) :-
+   must_be(integer, Depth),
+   (  Depth >= -1 -> true
+   ;  domain_error(term_hash_depth, Depth, term_hash/4)
+   ),
+   must_be(integer, Range),
+   (  Range > 0 -> true
+   ;  domain_error(positive_integer, Range, term_hash/4)
+   ),
+   current_prolog_flag(term_hash_algorithm, Algorithm),
+   (  '$term_hash'(Term, Depth, Range, Algorithm, Hash0) ->
+      Hash = Hash0
+   ;  type_error(acyclic_term, Term, term_hash/4)
+   ).
@@ -0,0 +1,253 @@
+/** Threads.
+
+`thread_create/3` runs a goal in a new OS thread. Each thread has a
+Prolog machine of its own: threads don't share the database, global
+variables or streams other than `user_output` and `user_error`. The
+goal of a new thread is a copy of the goal passed to
+`thread_create/3`, called in the `user` module of the new machine. It
+may only call library predicates and predicates it loads itself, for
+example with `consult/1`. In particular, clauses asserted or
+retracted by a thread only change the dynamic predicates of its own
+machine; threads exchange terms through message queues instead, and
+coordinate on other shared resources with the mutexes of
+`library(concurrency)`.
+
+A loaded program can start goals that keep running in the background
+while the toplevel remains interactive, for example an HTTP server or
+a timer, with the directive `:- spawn_initialization(Goal).` Like
+`initialization/1`, it runs once the file is loaded, but Goal is
+called in a background thread whose machine first loads the same file
+(without running its initialization goals). Under the tokio runtime
+of Scryer Prolog, background threads run on its pool of blocking
+threads. They are listed by `background_thread/1`, awaited with
+`thread_join/2` and stopped with `thread_cancel/1`.
+
+Threads communicate through message queues (`library(message_queues)`,
+whose predicates are exported by this library as well). Every thread
+has a queue named by its thread identifier, so that
+`thread_send_message(Id, Term)` sends Term to thread Id, which
+receives it with `thread_get_message/1`. The thread that started
+Scryer Prolog is called `main`.
+
+```
+?- thread_create((thread_get_message(X), Y is X * 2,
+                  thread_send_message(main, Y)), Id),
+   thread_send_message(Id, 21),
+   thread_get_message(Y),
+   thread_join(Id, Status).
+   Id = '$thread_0', Y = 42, Status = true.
+```
+*/
+
+:- module(threads, [message_queue_create/1,
+                    message_queue_destroy/1,
+                    message_queue_size/2,
+                    message_queue_stream/2,
+                    background_thread/1,
+                    spawn_initialization/1,
+                    spawn_initialization/2,
+                    thread_cancel/1,
+                    thread_create/2,
+                    thread_create/3,
+                    thread_get_message/1,
+                    thread_get_message/2,
+                    thread_get_message/3,
+                    thread_join/2,
+                    thread_peek_message/1,
+                    thread_peek_message/2,
+                    thread_property/2,
+                    thread_self/1,
+                    thread_send_message/2]).
+
+:- use_module(library(charsio), [read_term_from_chars/3,
+                                 write_term_to_chars/3]).
+:- use_module(library(error)).
+:- use_module(library(lists), [append/3, maplist/3, member/2]).
+:- use_module(library(message_queues)).
+
+:- initialization(create_main_queue).
+
+create_main_queue :-
+    catch(message_queue_create(main), error(permission_error(_, _, _), _), true).
+
+:- meta_predicate(thread_create(0, ?)).
+
+%% thread_create(:Goal, -Id).
+%
+% Like `thread_create/3` without options.
+thread_create(Goal, Id) :-
+    thread_create(Goal, Id, []).
+
+:- meta_predicate(thread_create(0, ?, ?)).
+
+%% thread_create(:Goal, -Id, +Options).
+%
+% Starts a new thread that calls Goal as `once/1`, and unifies Id with
+% its identifier. Options is a list of:
+%
+%  - `alias(Alias)`: use the atom Alias as the identifier of the
+%    thread. Throws a permission error if Alias is already the name of
+%    a thread or a message queue.
+thread_create(Goal, Id, Options) :-
+    must_be(var, Id),
+    must_be(list, Options),
+    (   member(alias(Alias), Options) ->
+        must_be(atom, Alias),
+        Id = Alias
+    ;   true
+    ),
+    % the new machine has no module of the caller's, so the goal is
+    % called in its user module.
+    unqualified_goal(Goal, Goal1),
+    write_term_to_chars(Goal1, [quoted(true), max_depth(0)], Chars),
+    (   '$thread_create'(Chars, Id) ->
+        true
+    ;   throw(error(permission_error(create, thread, Id), thread_create/3))
+    ).
+
+% removes the module qualifications added by goal expansion, which
+% qualifies each goal of a control construct.
+unqualified_goal(Goal0, Goal) :-
+    loader:strip_module(Goal0, _, Goal1),
+    (   var(Goal1) ->
+        Goal = Goal1
+    ;   control_construct(Goal1, Goal, Args0, Args) ->
+        maplist(unqualified_goal, Args0, Args)
+    ;   Goal = Goal1
+    ).
+
+control_construct((G1, G2), (H1, H2), [G1, G2], [H1, H2]).
+control_construct((G1 ; G2), (H1 ; H2), [G1, G2], [H1, H2]).
+control_construct((G1 -> G2), (H1 -> H2), [G1, G2], [H1, H2]).
+control_construct(\+ G, \+ H, [G], [H]).
+
+%% thread_join(+Id, -Status).
+%
+% Waits for thread Id to finish and unifies Status with `true`,
+% `false`, `exception(E)` or `cancelled`, according to the outcome of
+% its goal. The message queue of the thread is destroyed.
+thread_join(Id, Status) :-
+    must_be(atom, Id),
+    (   '$thread_join'(Id, Chars) ->
+        read_term_from_chars(Chars, Status0, []),
+        Status = Status0
+    ;   throw(error(existence_error(thread, Id), thread_join/2))
+    ).
+
+%% thread_cancel(+Id).
+%
+% Asks thread Id to stop. The thread throws `'$thread_cancelled'`
+% from its next inference, or as soon as it is woken while waiting
+% for a message or an HTTP request, and then has the status
+% `cancelled`, unless its goal catches the exception. Use
+% `thread_join/2` to wait until it stopped.
+thread_cancel(Id) :-
+    must_be(atom, Id),
+    (   '$thread_cancel'(Id) ->
+        true
+    ;   throw(error(existence_error(thread, Id), thread_cancel/1))
+    ).
+
+:- meta_predicate(spawn_initialization(0)).
+
+%% spawn_initialization(:Goal).
+%
+% Like `spawn_initialization/2`, discarding the identifier. As a
+% directive, Goal is started once the file is loaded.
+spawn_initialization(Goal) :-
+    spawn_initialization(Goal, _).
+
+:- meta_predicate(spawn_initialization(0, ?)).
+
+%% spawn_initialization(:Goal, -Id).
+%
+% Calls Goal as `once/1` in a new background thread with the
+% identifier Id. If called while a file is loaded, the machine of the
+% thread loads the same file first, so that Goal may call its
+% predicates.
+spawn_initialization(Goal, Id) :-
+    must_be(var, Id),
+    (   prolog_load_context(source, File) ->
+        true
+    ;   File = []
+    ),
+    write_term_to_chars(threads:'$background_main'(File, Goal),
+                        [quoted(true), max_depth(0)],
+                        Chars),
+    (   '$thread_create_background'(Chars, Id) ->
+        true
+    ;   throw(error(resource_error(threads), spawn_initialization/2))
+    ).
+
+'$background_main'(File, Goal) :-
+    (   File == [] ->
+        true
+    ;   loader:consult_without_initialization(File)
+    ),
+    call(Goal).
+
+%% background_thread(?Id).
+%
+% Id is a thread started by `spawn_initialization/1` that wasn't
+% joined yet.
+background_thread(Id) :-
+    '$background_threads'(Ids),
+    member(Id, Ids).
+
+%% thread_self(-Id).
+%
+% Id is the identifier of the calling thread.
+thread_self(Id) :-
+    '$thread_self'(Id).
+
+%% thread_property(+Id, ?Property).
+%
+% Property is a property of the thread Id, which must not have been
+% joined yet. The only property is `status(S)`, where S is `running`
+% or `finished`.
+thread_property(Id, status(S)) :-
+    must_be(atom, Id),
+    (   '$thread_status'(Id, S0) ->
+        S = S0
+    ;   throw(error(existence_error(thread, Id), thread_property/2))
+    ).
+
+%% thread_get_message(?Term).
+%
+% Like `thread_get_message/2` for the queue of the calling thread.
+thread_get_message(Term) :-
+    thread_self(Id),
+    thread_get_message(Id, Term).
+
+%% thread_peek_message(?Term).
+%
+% Like `thread_peek_message/2` for the queue of the calling thread.
+thread_peek_message(Term) :-
+    thread_self(Id),
+    thread_peek_message(Id, Term).
+
+% the entry point of new threads.
+'$thread_main' :-
+    catch(( thread_goal(Goal),
+            (   call(user:Goal) ->
+                Status = true
+            ;   Status = false
+            )
+          ),
+          E,
+          (   E == '$thread_cancelled' ->
+              Status = cancelled
+          ;   Status = exception(E)
+          )),
+    write_term_to_chars(Status, [quoted(true), max_depth(0)], StatusChars0),
+    append(StatusChars0, " .", StatusChars),
+    '$thread_set_status'(StatusChars),
+    flush_output(user_output),
+    flush_output(user_error).
+
+% the goal is fetched inside the catch/3 of '$thread_main', from
+% which on the thread may be cancelled.
+thread_goal(Goal) :-
+    '$thread_goal'(Chars0),
+    append(Chars0, " .", Chars),
+    read_term_from_chars(Chars, Goal, []).
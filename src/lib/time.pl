@@ -4,6 +4,25 @@
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
 /** This library provides predicates for reasoning about time.
+
+Points in time are _time stamps_, the number of seconds since
+1970-01-01 00:00:00 UTC, or `date_time/7` terms:
+
+```
+date_time(Year, Month, Day, Hour, Minute, Second, Offset)
+```
+
+where Second is an integer, or a float with the fraction of the
+second, and Offset is the offset of the time zone in seconds east of
+UTC. `stamp_date_time/3` and `date_time_stamp/2` convert between them,
+and `format_time/3` and `parse_time/3` convert them to and from strings
+with the format specifiers of `strftime`:
+
+```
+?- parse_time("2024-03-01 12:30:05", "%Y-%m-%d %H:%M:%S", DT),
+   date_time_stamp(DT, Stamp).
+   DT = date_time(2024,3,1,12,30,5,0), Stamp = 1709296205.
+```
 */
 
 :- module(time, [max_sleep_time/1,
@@ -11,6 +30,12 @@
                  time/1,
                  current_time/1,
                  format_time//2,
+                 get_time/1,
+                 monotonic_time/1,
+                 stamp_date_time/3,
+                 date_time_stamp/2,
+                 format_time/3,
+                 parse_time/3,
                  statistics/2
                 ]).
 
@@ -57,6 +82,7 @@ current_time(T) :-
 % |  `%a` |  abbreviated weekday name, always 3 letters            |
 % |  `%A` |  full weekday name                                     |
 % |  `%j` |  day of the year (001-366), zero-padded to 3 digits    |
+% |  `%s` |  seconds since 1970-01-01 00:00:00 UTC (Unix time)    |
 % |  `%%` |  the literal `%`                                       |
 %
 % Example:
@@ -76,6 +102,142 @@ format_time(['%',Spec|Fs], T) --> !,
         format_time(Fs, T).
 format_time([F|Fs], T) --> [F], format_time(Fs, T).
 
+%% get_time(-Stamp)
+%
+%  Stamp is the current time of the system as a time stamp, a float
+%  of seconds since 1970-01-01 00:00:00 UTC.
+
+get_time(Stamp) :-
+        '$wall_time'(Stamp).
+
+%% monotonic_time(-T)
+%
+%  T is a float of seconds since an unspecified moment, by a clock
+%  that never goes back, even when the time of the system is set.
+%  Differences of such times measure elapsed time:
+%
+% ```
+%    ?- monotonic_time(T0), sleep(0.5), monotonic_time(T), D is T - T0.
+%       T0 = 0.0, T = 0.500101, D = 0.500101.
+% ```
+
+monotonic_time(T) :-
+        '$monotonic_time'(T).
+
+%% stamp_date_time(+Stamp, -DateTime, +TimeZone)
+%
+%  DateTime is the `date_time/7` term of the time stamp Stamp in
+%  TimeZone, which is `local` for the time zone of the system, `utc`,
+%  or an offset in seconds east of UTC. Example:
+%
+% ```
+%    ?- stamp_date_time(0, DT, utc).
+%       DT = date_time(1970,1,1,0,0,0,0).
+% ```
+
+stamp_date_time(Stamp, DateTime, TimeZone) :-
+        must_be_number(Stamp, stamp_date_time/3),
+        must_be_time_zone(TimeZone, Zone),
+        (   '$stamp_date_time'(Stamp, Zone, Fields) ->
+            fields_date_time(Fields, DateTime)
+        ;   domain_error(time_stamp, Stamp, stamp_date_time/3)
+        ).
+
+must_be_time_zone(TimeZone, Zone) :-
+        (   var(TimeZone) ->
+            instantiation_error(stamp_date_time/3)
+        ;   TimeZone == local ->
+            Zone = local
+        ;   TimeZone == utc ->
+            Zone = 0
+        ;   integer(TimeZone) ->
+            Zone = TimeZone
+        ;   domain_error(time_zone, TimeZone, stamp_date_time/3)
+        ).
+
+fields_date_time([Y,Mo,D,H,Mi,S0,Ns,Off], date_time(Y,Mo,D,H,Mi,S,Off)) :-
+        (   Ns =:= 0 ->
+            S = S0
+        ;   S is S0 + Ns / 1000000000
+        ).
+
+%% date_time_stamp(+DateTime, -Stamp)
+%
+%  Stamp is the time stamp of the `date_time/7` term DateTime. It is
+%  an integer if DateTime has no fraction of a second.
+
+date_time_stamp(DateTime, Stamp) :-
+        date_time_fields(DateTime, date_time_stamp/2, Fields),
+        (   '$date_time_stamp'(Fields, Stamp0) ->
+            Stamp = Stamp0
+        ;   domain_error(date_time, DateTime, date_time_stamp/2)
+        ).
+
+date_time_fields(DateTime, Context, [Y,Mo,D,H,Mi,S,Ns,Off]) :-
+        (   var(DateTime) ->
+            instantiation_error(Context)
+        ;   DateTime = date_time(Y,Mo,D,H,Mi,S0,Off) ->
+            maplist(must_be(integer), [Y,Mo,D,H,Mi,Off]),
+            must_be_number(S0, Context),
+            S is floor(S0),
+            Ns is min(round((S0 - S) * 1000000000), 999999999)
+        ;   type_error(date_time, DateTime, Context)
+        ).
+
+must_be_number(X, Context) :-
+        (   var(X) ->
+            instantiation_error(Context)
+        ;   number(X) ->
+            true
+        ;   type_error(number, X, Context)
+        ).
+
+%% format_time(+Format, +Time, -Chars)
+%
+%  Chars is the string of Time formatted by Format, with the format
+%  specifiers of `strftime`, such as `%Y-%m-%dT%H:%M:%S%:z`. Time is
+%  a `date_time/7` term, which is formatted in its own time zone, or a
+%  time stamp, which is formatted in the time zone of the system.
+%  Example:
+%
+% ```
+%    ?- format_time("%a, %d %b %Y %H:%M", date_time(2024,3,1,12,30,5,0), Cs).
+%       Cs = "Fri, 01 Mar 2024 12:30".
+% ```
+
+format_time(Format, Time, Chars) :-
+        must_be(chars, Format),
+        (   number(Time) ->
+            stamp_date_time(Time, DateTime, local)
+        ;   DateTime = Time
+        ),
+        date_time_fields(DateTime, format_time/3, Fields),
+        (   '$format_date_time'(Format, Fields, Chars0) ->
+            Chars = Chars0
+        ;   '$format_date_time'("", Fields, _) ->
+            domain_error(time_format, Format, format_time/3)
+        ;   domain_error(date_time, DateTime, format_time/3)
+        ).
+
+%% parse_time(+Chars, +Format, -DateTime)
+%
+%  DateTime is the `date_time/7` term of the date and time Chars
+%  describes in Format, with the format specifiers of `strftime`. The
+%  time is in UTC if Chars has no offset, and at midnight if it has
+%  only a date. Fails if Chars doesn't match Format or has no date.
+%  Example:
+%
+% ```
+%    ?- parse_time("2024-03-01T12:30:05+01:00", "%Y-%m-%dT%H:%M:%S%:z", DT).
+%       DT = date_time(2024,3,1,12,30,5,3600).
+% ```
+
+parse_time(Chars, Format, DateTime) :-
+        must_be(chars, Chars),
+        must_be(chars, Format),
+        '$parse_date_time'(Chars, Format, Fields),
+        fields_date_time(Fields, DateTime).
+
 %% max_sleep_time(T)
 %
 %  The maximum admissible time span for `sleep/1`.
@@ -97,16 +259,23 @@ sleep(T) :-
     ).
 
 
-%% statistics(?Keyword, ?List)
+%% statistics(?Keyword, ?Value)
+%
+%  Preliminary support for statistics/2, yielding timing and profiling
+%  information. The supported keywords are:
 %
-%  Preliminary support for statistics/2, yielding timing information.
-%  The only supported `Keyword` is `runtime`. The first element of
-%  `List` is the CPU time in milliseconds, the second element is
-%  currently not supported.
+%   - `runtime`: `Value` is a list whose first element is the CPU time
+%     in milliseconds. The second element is currently not supported.
+%   - `profile`: `Value` is a list of terms
+%     `profile(Module:Name/Arity, Calls, Redos, Inclusive, Exclusive)`
+%     for the predicates called or retried during the last
+%     `profile/1` of `library(profile)`, with times in milliseconds.
 
 statistics(runtime, [T,unsupported]) :-
         '$cpu_now'(T0),
         T is T0*1000.
+statistics(profile, Rows) :-
+        '$profile_data'(Rows).
 
 :- meta_predicate(time(0)).
 
@@ -28,6 +28,30 @@
    tls_client_negotiate(Context, S0, S)
 
    S will be an encrypted and authenticated stream with the server.
+   S0 can be any stream that is both readable and writable, such as
+   the stream of a plain text connection that is upgraded to TLS, as
+   with the STARTTLS commands of SMTP and IMAP.
+
+   The options of tls_client_context/2 are:
+
+      hostname(Chars)
+         the name of the server, which its certificate must match.
+
+      cacerts(Certs)
+         Certs is a list of strings, each a PEM certificate of an
+         authority trusted in addition to those trusted by the system,
+         for example to connect to servers with self-signed
+         certificates.
+
+      certificate(Cert) and key(Key)
+         the PEM certificate chain of the client and its PKCS #8
+         private key, as strings, for servers that authenticate
+         clients by their certificates.
+
+      verify(Bool)
+         if false, the certificate of the server is not verified at
+         all. This is insecure, and only useful for testing. true by
+         default.
 
    The advantage of separating the creation of the client context from
    negotiating a connection is that the context can be created only once,
@@ -36,15 +60,36 @@
    every connection, using the specified hostname.
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
-tls_client_context(tls_context(Host), Options) :-
+tls_client_context(tls_context(Host,CACerts,Cert,Key,Verify), Options) :-
         must_be(list, Options),
         (   member(hostname(Host), Options) ->
             must_be(chars, Host)
         ;   Host = ""
+        ),
+        (   member(cacerts(CACerts), Options) ->
+            must_be(list, CACerts),
+            maplist(must_be(chars), CACerts)
+        ;   CACerts = []
+        ),
+        optional_identity(Options, Cert, Key, tls_client_context/2),
+        (   member(verify(Verify), Options) ->
+            must_be(boolean, Verify)
+        ;   Verify = true
         ).
 
-tls_client_negotiate(tls_context(Host), S0, S) :-
-        '$tls_client_connect'(Host, S0, S).
+tls_client_negotiate(tls_context(Host,CACerts,Cert,Key,Verify), S0, S) :-
+        '$tls_client_connect'(Host, CACerts, Cert, Key, Verify, S0, S).
+
+optional_identity(Options, Cert, Key, Context) :-
+        (   member(certificate(Cert), Options) ->
+            must_be(chars, Cert),
+            (   member(key(Key), Options) ->
+                must_be(chars, Key)
+            ;   domain_error(contains_key, Options, Context)
+            )
+        ;   Cert = [],
+            Key = []
+        ).
 
 /* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
    TLS Servers
@@ -55,7 +100,8 @@ tls_client_negotiate(tls_context(Host), S0, S) :-
    tls_server_context(Context, [pkcs12(Chars)])
 
    where Chars is a list of characters with the contents of a
-   DER-formatted PKCS #12 archive. The option password(Ps) can be used
+   DER-formatted PKCS #12 archive. The option pcks12(Chars) is
+   accepted as well, for compatibility. The option password(Ps) can be used
    to specify the password Ps (also a string) for decrypting the key.
    On some versions of OSX, and potentially also on other platforms,
    empty passwords are not supported.
@@ -78,6 +124,14 @@ tls_client_negotiate(tls_context(Host), S0, S) :-
 
    phrase_from_file(seq(Chars), "identity.pfx", [type(binary)])
 
+   Instead of a PKCS #12 archive, the options certificate(Cert) and
+   key(Key) can give the PEM certificate chain of the server and its
+   PKCS #8 private key, as strings, for example as read from the
+   "cert.pem" and "key.pem" files of Let's Encrypt or created with:
+
+   $ openssl req -x509 -newkey rsa:2048 -nodes -subj /CN=localhost \
+                 -keyout key.pem -out cert.pem
+
    The obtained context should be treated as an opaque Prolog term.
 
    Using the context and an existing stream S0 (for example, the
@@ -95,16 +149,23 @@ tls_client_negotiate(tls_context(Host), S0, S) :-
    is created for every connection, using the specified parameters.
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
-tls_server_context(tls_context(Cert,Password), Options) :-
-        (   member(pcks12(Cert), Options) ->
-            must_be(chars, Cert)
-        ;   domain_error(contains_pcks12, Options, tls_server_context/2)
-        ),
-        (   member(password(Password), Options) ->
-            must_be(chars, Password)
-        ;   Password = ""
+tls_server_context(tls_context(Kind,Cert,Secret), Options) :-
+        must_be(list, Options),
+        (   (   member(pkcs12(Cert), Options)
+            ;   member(pcks12(Cert), Options)
+            ) ->
+            must_be(chars, Cert),
+            Kind = pkcs12,
+            (   member(password(Secret), Options) ->
+                must_be(chars, Secret)
+            ;   Secret = ""
+            )
+        ;   optional_identity(Options, Cert, Secret, tls_server_context/2),
+            Cert \== [] ->
+            Kind = pem
+        ;   domain_error(contains_pkcs12_or_certificate, Options, tls_server_context/2)
         ).
 
-tls_server_negotiate(tls_context(Cert,Password), S0, S) :-
-        '$tls_accept_client'(Cert, Password, S0, S).
+tls_server_negotiate(tls_context(Kind,Cert,Secret), S0, S) :-
+        '$tls_accept_client'(Kind, Cert, Secret, S0, S).
 
@@ -0,0 +1,412 @@
+/** A port tracer.
+
+`trace/0` switches on the tracer, which shows the ports of the calls
+into or out of user code, stopping at each to ask how to go on:
+
+```
+?- trace, app([a], [b], Xs).
+   Call: (1) app([a],[b],_12) ? creep
+   Call: (2) app([],[b],_25) ? creep
+   Exit: (2) app([],[b],[b]) ? creep
+   Exit: (1) app([a],[b],[a,b]) ? creep
+   Xs = [a,b].
+```
+
+A goal is entered at its `Call` port and left at its `Exit` port when
+it succeeds. Backtracking re-enters it at its `Redo` port, and it's
+left at its `Fail` port when it has no more answers. At a port, the
+tracer reads a single character:
+
+  - `c`, space or newline: creep to the next port.
+  - `s`: skip to the exit or fail port of the goal.
+  - `l`: leap to the next port of a spy point.
+  - `f`: fail the goal.
+  - `a`: abort the query.
+  - `n`: switch off the tracer and run the rest of the query.
+  - `h`: list these commands.
+
+`spy/1` places a spy point on a predicate, from which the tracer, if
+switched off, leaps to the ports of the predicate. `leash/1` chooses
+the ports at which the tracer stops; it only shows the others.
+
+The ports are written to `user_error`, unless the machine is embedded
+with a trace hook, which is called at each port instead. Calls within libraries aren't
+traced, and neither are the builtins the compiler inlines, such as
+`(is)/2`, `(=)/2` or `var/1`.
+
+`trace_feed/2` switches on the tracer without stopping, writing a line
+of JSON with the state of the machine at each port instead, for
+teaching tools that show how a query runs:
+
+```
+?- trace_feed(user_output, []), app([a], [b], Xs).
+{"step":1,"port":"call","depth":1,"goal":"app([a],[b],_1)","registers":["[a]","[b]","_1"],"heap":487120,"stack":2192,"trail":74704,"choicepoints":8}
+...
+```
+
+`machine_state/1` gives the state of the machine on demand, in the
+same form.
+*/
+
+:- module(trace, [leash/1,
+                  machine_state/1,
+                  nospy/1,
+                  notrace/0,
+                  spy/1,
+                  trace/0,
+                  trace_feed/2]).
+
+:- use_module(library(charsio), [get_single_char/1, write_term_to_chars/3]).
+:- use_module(library(error)).
+:- use_module(library(format), [format/3]).
+:- use_module(library(iso_ext), [b_getval/2, b_setval/2, bb_get/2, bb_put/2]).
+:- use_module(library(lists), [foldl/5, maplist/3, member/2]).
+:- use_module(library(serialization/json), [json_chars_with_options/3]).
+
+:- dynamic(leashed/1).
+:- dynamic(spied/2).
+:- dynamic(feed/2).
+
+leashed(call).
+leashed(exit).
+leashed(redo).
+leashed(fail).
+
+%% trace.
+%
+% Switches on the tracer, which creeps from the next call.
+trace :-
+    retractall(feed(_, _)),
+    '$trace_mode'(_, creep).
+
+%% notrace.
+%
+% Switches off the tracer. It still leaps to spy points if there are
+% any.
+notrace :-
+    retractall(feed(_, _)),
+    (   spied(_, _) ->
+        '$trace_mode'(_, leap)
+    ;   '$trace_mode'(_, off)
+    ).
+
+%% spy(+PI).
+%
+% Places a spy point on the predicate indicator PI, of the form
+% Name/Arity, and leaps to it if the tracer is off.
+spy(PI) :-
+    predicate_indicator(PI, spy/1, Name, Arity),
+    '$trace_spy'(Name, Arity, true),
+    (   spied(Name, Arity) ->
+        true
+    ;   assertz(spied(Name, Arity))
+    ),
+    (   '$trace_mode'(off, off) ->
+        '$trace_mode'(_, leap)
+    ;   true
+    ).
+
+%% nospy(+PI).
+%
+% Removes the spy point on the predicate indicator PI, if any.
+nospy(PI) :-
+    predicate_indicator(PI, nospy/1, Name, Arity),
+    '$trace_spy'(Name, Arity, false),
+    retractall(spied(Name, Arity)).
+
+%% leash(+Ports).
+%
+% Stops the tracer at Ports only, which is a list of `call`, `exit`,
+% `redo` and `fail`, or one of `all` and `none`.
+leash(Ports0) :-
+    (   Ports0 == all ->
+        Ports = [call, exit, redo, fail]
+    ;   Ports0 == none ->
+        Ports = []
+    ;   must_be(list, Ports0),
+        Ports = Ports0,
+        (   member(Port, Ports),
+            \+ port_name(Port, _) ->
+            domain_error(port, Port, leash/1)
+        ;   true
+        )
+    ),
+    retractall(leashed(_)),
+    (   member(Port, Ports),
+        assertz(leashed(Port)),
+        false
+    ;   true
+    ).
+
+%% trace_feed(+Stream, +Options).
+%
+% Switches on the tracer, which creeps from the next call without
+% stopping, writing a line to Stream at each port with the JSON object
+% of the port and the state of the machine. Options are:
+%
+%  * `every(N)`: writes a line at every N-th port only, 1 by default.
+%
+% The members of the object are:
+%
+%  * `"step"`: the number of the port, from 1 for the first port
+%    after `trace_feed/2`.
+%  * `"port"`: `"call"`, `"exit"`, `"redo"` or `"fail"`.
+%  * `"depth"`: the depth of the goal, 1 for the goals of the query.
+%  * `"goal"`: the goal, written as the tracer writes it, with its
+%    variables named `_1`, `_2`, ... in the order they appear.
+%  * `"registers"`: the arguments of the goal, which are in the
+%    argument registers of the machine when the goal is called,
+%    written likewise.
+%  * the members of `machine_state/1`.
+%
+% `trace/0` and `notrace/0` switch off the feed.
+trace_feed(Stream, Options) :-
+    must_be(list, Options),
+    (   member(O, Options),
+        \+ ( nonvar(O), O = every(_) ) ->
+        domain_error(trace_feed_option, O, trace_feed/2)
+    ;   true
+    ),
+    (   member(every(Every), Options) ->
+        must_be(integer, Every),
+        (   Every > 0 ->
+            true
+        ;   domain_error(positive_integer, Every, trace_feed/2)
+        )
+    ;   Every = 1
+    ),
+    stream_property(Stream, output),
+    retractall(feed(_, _)),
+    assertz(feed(Stream, Every)),
+    bb_put('$trace_feed_step', 0),
+    '$trace_mode'(_, creep).
+
+%% machine_state(-Chars).
+%
+% Chars is the JSON object of the state of the machine, with the
+% members:
+%
+%  * `"heap"`, `"stack"` and `"trail"`: the bytes in use by the heap,
+%    the stack and the trail.
+%  * `"choicepoints"`: the number of choice points on the stack,
+%    including those the tracer leaves for the ports of the goals it
+%    traces.
+machine_state(Chars) :-
+    state_pairs(Pairs),
+    json_chars_with_options(pairs(Pairs), Chars, [keys(atom)]).
+
+state_pairs([heap-number(Heap),
+             stack-number(Stack),
+             trail-number(Trail),
+             choicepoints-number(B)]) :-
+    '$memory_usage'(Heap, Stack, Trail),
+    '$choice_point_count'(B).
+
+predicate_indicator(PI0, Context, Name, Arity) :-
+    loader:strip_module(PI0, _, PI),
+    (   var(PI) ->
+        instantiation_error(Context)
+    ;   PI = Name/Arity ->
+        must_be(atom, Name),
+        must_be(integer, Arity)
+    ;   type_error(predicate_indicator, PI, Context)
+    ).
+
+% called by the machine in place of a traced goal, whose code is at
+% P. a goal that exits deterministically leaves no choice point.
+'$trace_goal'(Goal, P, Dyn) :-
+    '$get_cp'(B0),
+    depth(D0),
+    D is D0 + 1,
+    (   port(call, D, Goal, Action)
+    ;   port(fail, D, Goal, _),
+        false
+    ),
+    b_setval('$trace_depth', D),
+    choice_point(B1),
+    run_goal(Action, Goal, P, Dyn),
+    choice_point(B2),
+    b_setval('$trace_depth', D0),
+    (   B1 == B2 ->
+        port(exit, D, Goal, Action1),
+        Action1 \== fail,
+        '$set_cp'(B0)
+    ;   port(exit, D, Goal, Action1),
+        Action1 \== fail
+    ;   port(redo, D, Goal, _),
+        false
+    ).
+
+% the latest choice point, unlike '$get_cp'/1 in a clause body.
+choice_point(B) :-
+    '$get_cp'(B).
+
+depth(D) :-
+    catch(b_getval('$trace_depth', D), _, D = 0).
+
+run_goal(fail, _, _, _) :-
+    !,
+    false.
+run_goal(skip, Goal, P, Dyn) :-
+    !,
+    '$trace_mode'(Mode, skip),
+    '$get_cp'(B0),
+    (   choice_point(B1),
+        catch(call_at(Goal, P, Dyn), E, ('$trace_mode'(_, Mode), throw(E))),
+        choice_point(B2),
+        (   B1 == B2 ->
+            '$set_cp'(B0),
+            '$trace_mode'(_, Mode)
+        ;   '$trace_mode'(_, Mode)
+        ;   '$trace_mode'(_, skip),
+            false
+        )
+    ;   '$trace_mode'(_, Mode),
+        false
+    ).
+run_goal(_, Goal, P, Dyn) :-
+    '$trace_call_at'(Goal, P, Dyn).
+
+call_at(Goal, P, Dyn) :-
+    '$trace_call_at'(Goal, P, Dyn).
+
+port(Port, D, Goal, Action) :-
+    '$trace_mode'(Mode, Mode),
+    (   shows_port(Mode, Goal) ->
+        (   feed(Stream, Every) ->
+            feed_port(Stream, Every, Port, D, Goal),
+            Action = creep
+        ;   '$trace_event'(Port, D, Goal, Action0) ->
+            port_action(Action0, Port, Action)
+        ;   show_port(Port, D, Goal, Action)
+        )
+    ;   Action = creep
+    ).
+
+% the hook of an embedding machine, if any, is called in place of
+% show_port/4.
+show_port(Port, D, Goal, Action) :-
+    port_name(Port, Name),
+    write(user_error, '   '),
+    write(user_error, Name),
+    write(user_error, ': ('),
+    write(user_error, D),
+    write(user_error, ') '),
+    write_term(user_error, Goal, [quoted(true), max_depth(10)]),
+    (   leashed(Port) ->
+        write(user_error, ' ? '),
+        read_action(Action0),
+        port_action(Action0, Port, Action)
+    ;   nl(user_error),
+        Action = creep
+    ).
+
+% writes the line of the port to the stream of trace_feed/2. the
+% state is taken first, and the heap used to write the line is
+% reclaimed by backtracking, so that the feed doesn't show its own use
+% of the heap.
+feed_port(Stream, Every, Port, D, Goal) :-
+    bb_get('$trace_feed_step', Step0),
+    Step is Step0 + 1,
+    bb_put('$trace_feed_step', Step),
+    (   Step mod Every =:= 0,
+        state_pairs(Pairs),
+        Goal =.. [_|Args],
+        term_variables(Goal, Vs),
+        foldl(variable_name, Vs, VNs, 1, _),
+        term_json(VNs, Goal, GoalJSON),
+        maplist(term_json(VNs), Args, Registers),
+        atom_chars(Port, PortChars),
+        json_chars_with_options(pairs([step-number(Step),
+                                       port-string(PortChars),
+                                       depth-number(D),
+                                       goal-GoalJSON,
+                                       registers-list(Registers)
+                                       | Pairs]),
+                                Chars,
+                                [keys(atom)]),
+        format(Stream, "~s~n", [Chars]),
+        flush_output(Stream),
+        false
+    ;   true
+    ).
+
+variable_name(V, Name=V, N0, N) :-
+    number_chars(N0, Cs),
+    atom_chars(Name, ['_'|Cs]),
+    N is N0 + 1.
+
+term_json(VNs, Term, string(Chars)) :-
+    write_term_to_chars(Term, [quoted(true), max_depth(10), variable_names(VNs)], Chars).
+
+shows_port(creep, _).
+shows_port(leap, Goal) :-
+    functor(Goal, Name, Arity),
+    spied(Name, Arity).
+
+port_name(call, 'Call').
+port_name(exit, 'Exit').
+port_name(redo, 'Redo').
+port_name(fail, 'Fail').
+
+read_action(Action) :-
+    flush_output(user_error),
+    get_single_char(C),
+    (   command(C, Action0) ->
+        write(user_error, Action0),
+        nl(user_error),
+        (   Action0 == help ->
+            write_help,
+            write(user_error, '   ? '),
+            read_action(Action)
+        ;   Action = Action0
+        )
+    ;   write(user_error, 'unknown command, h for help'),
+        nl(user_error),
+        write(user_error, '   ? '),
+        read_action(Action)
+    ).
+
+command(c, creep).
+command(' ', creep).
+command('\n', creep).
+command('\r', creep).
+command(s, skip).
+command(l, leap).
+command(f, fail).
+command(a, abort).
+command(end_of_file, abort).
+command(n, nodebug).
+command(h, help).
+command(?, help).
+
+write_help :-
+    (   member(Line, ["c, space, newline: creep",
+                      "s: skip",
+                      "l: leap",
+                      "f: fail",
+                      "a: abort",
+                      "n: nodebug",
+                      "h: help"]),
+        write(user_error, '     '),
+        atom_chars(A, Line),
+        write(user_error, A),
+        nl(user_error),
+        false
+    ;   true
+    ).
+
+port_action(creep, _, creep) :-
+    '$trace_mode'(_, creep).
+port_action(skip, Port, Action) :-
+    (   Port == call ->
+        Action = skip
+    ;   port_action(creep, Port, Action)
+    ).
+port_action(leap, _, creep) :-
+    '$trace_mode'(_, leap).
+port_action(fail, _, fail).
+port_action(abort, _, _) :-
+    throw('$aborted').
+port_action(nodebug, _, creep) :-
+    '$trace_mode'(_, off).
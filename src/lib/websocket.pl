@@ -0,0 +1,162 @@
+/** WebSocket connections.
+
+A WebSocket is a connection over which both ends send messages
+whenever they like. A client opens one with `ws_open/3`:
+
+```
+?- ws_open("ws://localhost:8080/echo", WebSocket, []),
+   ws_send(WebSocket, text("hello")),
+   ws_receive(WebSocket, Message),
+   ws_close(WebSocket, 1000, "bye").
+   WebSocket = ..., Message = text("hello").
+```
+
+A server of `library(http/http_server)` accepts one when a handler
+answers with the body `websocket(Goal)`. The handshake is answered
+and `call(Goal, WebSocket)` is called, after which the WebSocket is
+closed:
+
+```
+echo_handler(_Request, Response) :-
+    http_body(Response, websocket(echo)).
+
+echo(WebSocket) :-
+    ws_receive(WebSocket, Message),
+    (   Message = text(_) ->
+        ws_send(WebSocket, Message),
+        echo(WebSocket)
+    ;   true
+    ).
+
+?- http_listen(8080, [get(echo, echo_handler)]).
+```
+
+Messages are the terms:
+
+  * `text(Chars)`, a text message, where Chars is a string.
+  * `binary(Bytes)`, a binary message, where Bytes is a list of bytes.
+  * `ping(Bytes)` and `pong(Bytes)`, to check that the connection is
+    alive. Pings are answered with pongs as they are received.
+  * `close(Code, Reason)`, received when the other end closes the
+    connection, where Code is a status code of RFC 6455 (1000 for a
+    normal closure) and Reason is a string.
+
+Both ends are free to send `term(Term)`, which is sent as the text of
+Term as written by `writeq/1`, and read back by `ws_receive/3`.
+*/
+
+:- module(websocket, [ws_open/3,
+                      ws_send/2,
+                      ws_receive/2,
+                      ws_receive/3,
+                      ws_close/3]).
+
+:- use_module(library(between)).
+:- use_module(library(charsio)).
+:- use_module(library(error)).
+:- use_module(library(lists)).
+
+%% ws_open(+URL, -WebSocket, +Options).
+%
+% Opens WebSocket to URL, a string with the scheme `ws` or `wss`.
+% Throws an existence error if the connection can't be established.
+% The options are:
+%
+%   * `request_headers(+Headers)`: the headers of the handshake, as
+%     `Name(Value)` terms where Value is a string.
+ws_open(URL, WebSocket, Options) :-
+    must_be(chars, URL),
+    must_be(list, Options),
+    (   member(request_headers(Headers), Options) ->
+        must_be(list, Headers),
+        maplist(request_header, Headers)
+    ;   Headers = []
+    ),
+    '$ws_open'(URL, Headers, WebSocket).
+
+request_header(Header) :-
+    (   var(Header) ->
+        instantiation_error(ws_open/3)
+    ;   Header =.. [_, Value] ->
+        must_be(chars, Value)
+    ;   domain_error(http_header, Header, ws_open/3)
+    ).
+
+%% ws_send(+WebSocket, +Message).
+%
+% Sends Message, which is `text(Chars)`, `binary(Bytes)`,
+% `ping(Bytes)`, `pong(Bytes)` or `term(Term)`. Throws an existence
+% error if the connection is closed.
+ws_send(WebSocket, Message) :-
+    (   var(Message) ->
+        instantiation_error(ws_send/2)
+    ;   Message = text(Chars) ->
+        must_be(chars, Chars),
+        chars_utf8bytes(Chars, Bytes),
+        '$ws_send'(WebSocket, text, Bytes)
+    ;   Message = term(Term) ->
+        write_term_to_chars(Term, [quoted(true)], Chars),
+        chars_utf8bytes(Chars, Bytes),
+        '$ws_send'(WebSocket, text, Bytes)
+    ;   Message =.. [Kind, Bytes],
+        member(Kind, [binary, ping, pong]) ->
+        must_be(list, Bytes),
+        maplist(byte, Bytes),
+        '$ws_send'(WebSocket, Kind, Bytes)
+    ;   domain_error(websocket_message, Message, ws_send/2)
+    ).
+
+byte(B) :-
+    must_be(integer, B),
+    (   0 =< B,
+        B =< 255 ->
+        true
+    ;   type_error(byte, B, ws_send/2)
+    ).
+
+%% ws_receive(+WebSocket, -Message).
+%
+% Equivalent to `ws_receive(WebSocket, Message, [])`.
+ws_receive(WebSocket, Message) :-
+    ws_receive(WebSocket, Message, []).
+
+%% ws_receive(+WebSocket, -Message, +Options).
+%
+% Message is the next message received, waiting for it if none has
+% arrived yet, or `end_of_file` once the connection is closed. The
+% options are:
+%
+%   * `format(+Format)`: if Format is `term`, text messages are read as
+%     terms and received as `term(Term)`. `text` by default.
+ws_receive(WebSocket, Message, Options) :-
+    must_be(list, Options),
+    (   member(format(Format), Options) ->
+        must_be(atom, Format),
+        (   member(Format, [text, term]) ->
+            true
+        ;   domain_error(websocket_format, Format, ws_receive/3)
+        )
+    ;   Format = text
+    ),
+    '$ws_receive'(WebSocket, Message0),
+    (   Format == term,
+        Message0 = text(Chars) ->
+        append(Chars, " .", Chars1),
+        read_term_from_chars(Chars1, Term, []),
+        Message = term(Term)
+    ;   Message = Message0
+    ).
+
+%% ws_close(+WebSocket, +Code, +Reason).
+%
+% Closes WebSocket with the status code Code, usually 1000 for a normal
+% closure, and the string Reason. Closing a closed WebSocket succeeds.
+ws_close(WebSocket, Code, Reason) :-
+    must_be(integer, Code),
+    (   between(1000, 4999, Code) ->
+        true
+    ;   domain_error(websocket_status_code, Code, ws_close/3)
+    ),
+    must_be(chars, Reason),
+    chars_utf8bytes(Reason, Bytes),
+    '$ws_close'(WebSocket, Code, Bytes).
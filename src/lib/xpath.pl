@@ -128,7 +128,9 @@ xpath_chk(DOM, Spec, Content) :-
 
 %% xpath(+DOM, +Spec, ?Content) is nondet.
 %
-% Match an element in a DOM structure.   The syntax is inspired by
+% Match an element in a DOM structure, which is an element or a list
+% of nodes such as the documents of `load_html/3` and `load_xml/3`,
+% whose elements are the roots of the DOM. The syntax is inspired by
 % XPath, using () rather than  []   to  select  inside an element.
 % First we can construct paths using / and //:
 %
@@ -319,9 +321,10 @@ in_dom(//Spec, DOM, Value) :-
     element_spec(Spec, Name, Modifiers),
     sub_dom(I, Len, Name, E, DOM),
     modifiers(Modifiers, I, Len, E, Value).
-in_dom(/Spec, E, Value) :-
+in_dom(/Spec, DOM, Value) :-
     !,
     element_spec(Spec, Name, Modifiers),
+    root_element(DOM, E),
     (   Name == self
     ->  true
     ;   element_name(E, Name)
@@ -335,13 +338,29 @@ in_dom(A//B, DOM, Value) :-
     !,
     in_dom(A, DOM, Value0),
     in_dom(//B, Value0, Value).
-in_dom(Spec, element(_, _, Content), Value) :-
+in_dom(Spec, DOM, Value) :-
     element_spec(Spec, Name, Modifiers),
+    dom_content(DOM, Content),
     count_named_elements(Content, Name, CLen),
     CLen > 0,
     nth_element(N, Name, E, Content),
     modifiers(Modifiers, N, CLen, E, Value).
 
+% a DOM is an element, or a list of nodes as loaded by load_html/3
+% and load_xml/3, whose elements are its roots.
+root_element(element(Name, Attributes, Content), E) :-
+    !,
+    E = element(Name, Attributes, Content).
+root_element(DOM, E) :-
+    list_si(DOM),
+    member(E, DOM),
+    E = element(_, _, _).
+
+dom_content(element(_, _, Content), Content) :-
+    !.
+dom_content(DOM, DOM) :-
+    list_si(DOM).
+
 element_spec(Var, _, _) :-
     var(Var),
     !,
@@ -0,0 +1,71 @@
+/** Cross-referencing of loaded predicates.
+
+These predicates relate the predicates loaded so far, as read from
+their compiled code. A predicate calls another if one of its clauses
+calls it by name or passes it as a goal to a meta-predicate such as
+`findall/3`. Goals built while running, and called with `call/N`, are
+not seen.
+
+Predicates are written `Module:Name/Arity`, the module of predicates
+loaded outside of a module being `user`. `xref_module_graph/2` writes
+the graph of the modules importing each other, for documentation and
+architecture checks.
+
+```
+?- [user].
+p(Xs) :- lists:append(Xs, [], _), q.
+
+?- xref_calls(user:p/1, Callee).
+   Callee = lists:append/3
+;  Callee = user:q/0.
+?- xref_undefined(Caller, Callee).
+   Caller = user:p/1, Callee = user:q/0
+;  ... .
+```
+*/
+
+:- module(xref, [xref_calls/2,
+                 xref_module_graph/2,
+                 xref_undefined/2,
+                 xref_unused_export/1]).
+
+:- use_module(library(error), [domain_error/3, must_be/2]).
+:- use_module(library(lists), [member/2, memberchk/2]).
+
+%% xref_calls(?Caller, ?Callee).
+%
+% Caller calls Callee, defined or not.
+xref_calls(Caller, Callee) :-
+    '$xref'(Calls, _),
+    member(call(Caller, Callee, _), Calls).
+
+%% xref_undefined(?Caller, ?Callee).
+%
+% Caller calls Callee, which is defined by neither clauses nor a
+% `dynamic/1` declaration.
+xref_undefined(Caller, Callee) :-
+    '$xref'(Calls, _),
+    member(call(Caller, Callee, false), Calls).
+
+%% xref_unused_export(?Predicate).
+%
+% Predicate is exported by its module, but no predicate of another
+% module calls it.
+xref_unused_export(Predicate) :-
+    '$xref'(_, UnusedExports),
+    member(Predicate, UnusedExports).
+
+%% xref_module_graph(+Format, -Graph).
+%
+% Graph is the string of the import graph of the loaded modules, as
+% seen by `module_imports/2`, written in Format: `dot`, the DOT
+% language of Graphviz, with an edge from each module to each module
+% it imports, or `json`, an object of `modules`, the list of module
+% names, and `imports`, the list of imports as objects of `importer`
+% and `imported`.
+xref_module_graph(Format, Graph) :-
+    must_be(atom, Format),
+    (  memberchk(Format, [dot, json]) ->
+       '$module_graph'(Format, Graph)
+    ;  domain_error(module_graph_format, Format, xref_module_graph/2)
+    ).
@@ -3,11 +3,16 @@
                    expand_term/2,
                    file_load/2,
                    load/1,
+                   make/0,
+                   module_imports/2,
+                   module_property/2,
                    predicate_property/2,
                    prolog_load_context/2,
                    strip_module/3,
+                   style_check/1,
                    use_module/1,
                    use_module/2,
+                   visible_predicate/2,
                    current_module/1
                   ]).
 
@@ -42,6 +47,21 @@ write_error(Error) :-
     nl,
     '$fail'.
 
+% the file being loaded, left unbound outside of a load.
+load_context_file(File) :-
+    (  prolog_load_context(file, File0) ->
+       File = File0
+    ;  true
+    ).
+
+% reports a diagnostic of Severity about Error at Line, left unbound
+% if unknown, to the machine if it collects them, as it does while
+% loading through its Rust API. fails otherwise, leaving the caller to
+% print it.
+report_diagnostic(Severity, Error, Line) :-
+    load_context_file(File),
+    '$report_diagnostic'(Severity, Error, File, Line).
+
 expand_term(Term, ExpandedTerm) :-
     (  '$predicate_defined'(user, term_expansion, 2),
        catch(user:term_expansion(Term, ExpandedTerm0),
@@ -101,7 +121,11 @@ run_initialization_goals(Module) :-
     (  predicate_property(Module:'$initialization_goals'(_), dynamic) ->
        findall(Module:Goal, builtins:retract(Module:'$initialization_goals'(Goal)), Goals),
        abolish(Module:'$initialization_goals'/1),
-       maplist(loader:success_or_warning, Goals)
+       (  '$fetch_global_var'('$skip_initialization', true) ->
+          % see consult_without_initialization/1.
+          true
+       ;  maplist(loader:success_or_warning, Goals)
+       )
     ;  true
     ).
 
@@ -110,6 +134,8 @@ run_initialization_goals(Module) :-
 success_or_warning(Goal) :-
     (   call(Goal) ->
         true
+    ;   report_diagnostic(warning, initialization_failed(Goal), _) ->
+        true
     ;   %% initialization goals can fail without thwarting the load.
         write('% Warning: initialization/1 failed for: '),
         writeq(Goal),
@@ -134,12 +160,20 @@ file_load_init(Stream, Evacuable) :-
     run_initialization_goals.
 
 file_load_cleanup(Evacuable, Error) :-
+    % the predicates compiled before the error are kept.
+    catch('$complete_compilations'(Evacuable), _, true),
     load_context(Module),
     abolish(Module:'$initialization_goals'/1),
+    load_context_file(File),
     unload_evacuable(Evacuable),
     (  clause('$toplevel':started, _) ->
        % let the toplevel call loader:write_error/1
        throw(Error)
+    ;  Error == file_load_error ->
+       % the error was reported by the load that threw it.
+       throw(file_load_error)
+    ;  '$report_diagnostic'(error, Error, File, _) ->
+       throw(file_load_error)
     ;  '$print_message_and_fail'(Error)
     ;  throw(file_load_error)
     ).
@@ -174,6 +208,20 @@ print_comma_separated_list([VN=_, VNEq | VNEqs]) :-
     write(', '),
     print_comma_separated_list([VNEq | VNEqs]).
 
+% writes where a warning was raised, the line or the file being
+% omitted if unknown.
+print_warning_location(Line) :-
+    (  integer(Line) ->
+       write(' at line '),
+       write(Line)
+    ;  true
+    ),
+    (  prolog_load_context(file, File) ->
+       write(' of '),
+       write(File)
+    ;  true
+    ).
+
 
 filter_anonymous_vars([], []).
 filter_anonymous_vars([VN=V | VNEqs0], VNEqs) :-
@@ -183,21 +231,50 @@ filter_anonymous_vars([VN=V | VNEqs0], VNEqs) :-
        filter_anonymous_vars(VNEqs0, VNEqs1)
     ).
 
+% fails if the machine doesn't collect diagnostics.
+report_singletons([], _).
+report_singletons([VN=_ | VNEqs], LinesRead) :-
+    report_diagnostic(warning, singleton_variable(VN), LinesRead),
+    report_singletons(VNEqs, LinesRead).
+
 warn_about_singletons([], _).
 warn_about_singletons([Singleton|Singletons], LinesRead) :-
-    (  filter_anonymous_vars([Singleton|Singletons], VarEqs),
+    (  '$get_style_check'(singleton, true),
+       filter_anonymous_vars([Singleton|Singletons], VarEqs),
        VarEqs \== [] ->
-       write('% Warning: singleton variables '),
-       print_comma_separated_list(VarEqs),
-       write(' at line '),
-       write(LinesRead),
-       write(' of '),
-       prolog_load_context(file, File),
-       write(File),
-       nl
+       (  report_singletons(VarEqs, LinesRead) ->
+          true
+       ;  write('% Warning: singleton variables '),
+          print_comma_separated_list(VarEqs),
+          print_warning_location(LinesRead),
+          nl
+       )
     ;  true
     ).
 
+% reports the warnings of the style checks raised by compiling the
+% clauses loaded so far.
+warn_about_style(Evacuable) :-
+    '$style_warnings'(Evacuable, Warnings),
+    maplist(loader:report_style_warning, Warnings).
+
+report_style_warning(Warning-Line) :-
+    (  report_diagnostic(warning, Warning, Line) ->
+       true
+    ;  write('% Warning: '),
+       print_style_warning(Warning),
+       print_warning_location(Line),
+       nl
+    ).
+
+print_style_warning(discontiguous_predicate(PI)) :-
+    write('overwriting '),
+    write(PI),
+    write(' because the clauses are discontiguous').
+print_style_warning(redefined_predicate(PI)) :-
+    write('redefining '),
+    write(PI).
+
 
 load_loop(Stream, Evacuable) :-
     (  '$devour_whitespace'(Stream) ->
@@ -206,14 +283,46 @@ load_loop(Stream, Evacuable) :-
     ;  Term = end_of_file
     ),
     (  Term == end_of_file ->
-       close(Stream),
-       '$conclude_load'(Evacuable)
-    ;  var(Term) ->
+       conclude_stream_load(Stream, Evacuable)
+    ;  load_term(Term, Singletons, LinesRead, Evacuable),
+       load_loop(Stream, Evacuable)
+    ).
+
+load_term(Term, Singletons, LinesRead, Evacuable) :-
+    (  var(Term) ->
        instantiation_error(load/1)
     ;  LineNum is LinesRead + 1,
+       '$set_load_context_line'(LineNum),
        warn_about_singletons(Singletons, LineNum),
        compile_term(Term, Evacuable),
-       load_loop(Stream, Evacuable)
+       warn_about_style(Evacuable)
+    ).
+
+conclude_stream_load(Stream, Evacuable) :-
+    close(Stream),
+    '$conclude_load'(Evacuable),
+    warn_about_style(Evacuable).
+
+% the text of a feed arrives in pieces, each ending with a complete
+% clause. once the clauses received are compiled, Status is pending
+% and backtracking resumes the load when more text is received. it is
+% done once the stream is closed by the sender.
+feed_loop(Stream, Evacuable, Status) :-
+    (  '$devour_whitespace'(Stream) ->
+       stream_property(Stream, position(position_and_lines_read(_, LinesRead))),
+       read_term(Stream, Term, [singletons(Singletons)]),
+       (  Term == end_of_file ->
+          conclude_stream_load(Stream, Evacuable),
+          Status = done
+       ;  load_term(Term, Singletons, LinesRead, Evacuable),
+          feed_loop(Stream, Evacuable, Status)
+       )
+    ;  stream_property(Stream, end_of_stream(at)) ->
+       conclude_stream_load(Stream, Evacuable),
+       Status = done
+    ;  (  Status = pending
+       ;  feed_loop(Stream, Evacuable, Status)
+       )
     ).
 
 
@@ -285,11 +394,14 @@ module_expanded_head_variables(Head, HeadVars) :-
 
 
 print_goal_expansion_warning(Pred) :-
-    nl,
-    write('% Warning: clause body goal expansion failed because '),
-    writeq(Pred),
-    write(' is not callable.'),
-    nl.
+    (  report_diagnostic(warning, goal_expansion_failed(Pred), _) ->
+       true
+    ;  nl,
+       write('% Warning: clause body goal expansion failed because '),
+       writeq(Pred),
+       write(' is not callable.'),
+       nl
+    ).
 
 expand_term_goals(Terms0, Terms) :-
     (  Terms0 = (Head1 :- Body0) ->
@@ -348,7 +460,8 @@ compile_dispatch_or_clause(OrigTerm, Term, Evacuable) :-
 compile_dispatch((:- Declaration), Evacuable) :-
     (  var(Declaration) ->
        instantiation_error(load/1)
-    ;  compile_declaration(Declaration, Evacuable)
+    ;  '$complete_compilations'(Evacuable),
+       compile_declaration(Declaration, Evacuable)
     ).
 compile_dispatch(term_expansion(Term, Terms), Evacuable) :-
     '$add_term_expansion_clause'(term_expansion(Term, Terms), Evacuable).
@@ -443,6 +556,15 @@ compile_declaration(use_module(Module, Exports), Evacuable) :-
        remove_module(Module, Evacuable)
     ;  use_module(Module, Exports, Evacuable)
     ).
+compile_declaration(reexport(Module), Evacuable) :-
+    reexport(Module, [], Evacuable).
+compile_declaration(reexport(Module, Exports), Evacuable) :-
+    (  Exports == [] ->
+       remove_module(Module, Evacuable)
+    ;  reexport(Module, Exports, Evacuable)
+    ).
+compile_declaration(freeze_module_interface, Evacuable) :-
+    '$freeze_module_interface'(Evacuable).
 compile_declaration(module(Module, Exports), Evacuable) :-
     (  atom(Module) ->
        '$declare_module'(Module, Exports, Evacuable)
@@ -457,8 +579,14 @@ compile_declaration(discontiguous(PIs), Evacuable) :-
 compile_declaration(initialization(Goal), Evacuable) :-
     prolog_load_context(module, Module),
     assertz(Module:'$initialization_goals'(Goal)).
+compile_declaration(spawn_initialization(Goal), Evacuable) :-
+    compile_declaration(use_module(library(threads), [spawn_initialization/1]), Evacuable),
+    prolog_load_context(module, Module),
+    assertz(Module:'$initialization_goals'(spawn_initialization(Goal))).
 compile_declaration(set_prolog_flag(Flag, Value), _) :-
     set_prolog_flag(Flag, Value).
+compile_declaration(style_check(Spec), _) :-
+    style_check(Spec).
 compile_declaration(non_counted_backtracking(Name/Arity), Evacuable) :-
     must_be(atom, Name),
     must_be(integer, Arity),
@@ -540,10 +668,49 @@ prolog_load_context(term_position, TermPosition) :-
 
 
 consult(Item) :-
-    (  atom(Item) -> use_module(Item)
+    (  atom(Item) ->
+       (  load_context_path(Item, Dir),
+          '$directory_exists'(Dir) ->
+          consult_directory(Dir)
+       ;  use_module(Item)
+       )
+    ;  compound(Item),
+       functor(Item, _, 1) ->
+       use_module(Item)
     ;  type_error(atom, Item, consult/1)
     ).
 
+%% consult_without_initialization(+Item)
+%
+% Consults Item as consult/1 does, without running the initialization
+% goals of the files it loads. The machine of a thread loads the
+% program of the machine that started it this way, so as not to start
+% the program a second time.
+consult_without_initialization(Item) :-
+    '$store_global_var'('$skip_initialization', true),
+    catch(( consult(Item) -> Result = true ; Result = false ),
+          E,
+          Result = exception(E)),
+    '$store_global_var'('$skip_initialization', false),
+    (  Result = exception(E) ->
+       throw(E)
+    ;  Result == true
+    ).
+
+% consults the files of Dir named with a .pl extension, in the standard
+% order of their names.
+consult_directory(Dir) :-
+    '$directory_files'(Dir, Names),
+    findall(File,
+            (  lists:member(NameChars, Names),
+               atom_chars(Name, NameChars),
+               atom_concat(_, '.pl', Name),
+               lists:foldl(builtins:atom_concat, ['/', Dir], Name, File)
+            ),
+            Files0),
+    sort(Files0, Files),
+    maplist(loader:consult, Files).
+
 
 use_module(Module) :-
     '$push_load_state_payload'(Evacuable),
@@ -558,6 +725,44 @@ use_module(Module, Exports) :-
     ;  use_module(Module, Exports, Evacuable)
     ).
 
+% reloads the files changed since they were loaded, in the order they
+% were first loaded. the clauses of their dynamic predicates are kept
+% as they were before the reload.
+make :-
+    '$loaded_files'(Files),
+    maplist(loader:remake_file, Files).
+
+remake_file(File) :-
+    (  '$file_modified'(File) ->
+       '$loaded_file_dynamic_predicates'(File, PIs),
+       maplist(loader:dynamic_clauses, PIs, Clauses),
+       (  consult(File) ->
+          true
+       ;  true
+       ),
+       maplist(loader:restore_dynamic_clauses, PIs, Clauses)
+    ;  true
+    ).
+
+dynamic_clauses(Module:Name/Arity, Clauses) :-
+    functor(Head, Name, Arity),
+    (  Module == user ->
+       % clause/2 looks up the predicates of user unqualified.
+       findall(Head-Body, clause(Head, Body), Clauses)
+    ;  findall(Head-Body, clause(Module:Head, Body), Clauses)
+    ).
+
+restore_dynamic_clauses(Module:Name/Arity, Clauses) :-
+    functor(Head, Name, Arity),
+    (  predicate_property(Module:Head, dynamic) ->
+       retractall(Module:Head),
+       maplist(loader:assertz_dynamic_clause(Module), Clauses)
+    ;  true
+    ).
+
+assertz_dynamic_clause(Module, Head-Body) :-
+    assertz(Module:(Head :- Body)).
+
 current_module(Module) :-
     (  var(Module) ->
        instantiation_error(current_module/1)
@@ -566,6 +771,102 @@ current_module(Module) :-
     ;  '$module_exists'(Module)
     ).
 
+% Property is a property of the loaded module Module: exports(PIs), the
+% predicates it exports, interface(Interface), frozen if it declared
+% :- freeze_module_interface and open otherwise, and file(File) and
+% line(Line), where it's declared if it was consulted from a file.
+module_property(Module, Property) :-
+    (  var(Module) ->
+       instantiation_error(module_property/2)
+    ;  \+ atom(Module) ->
+       type_error(atom, Module, module_property/2)
+    ;  nonvar(Property),
+       \+ (  functor(Property, Name, 1),
+             memberchk(Name, [exports, interface, file, line])
+          ) ->
+       domain_error(module_property, Property, module_property/2)
+    ;  '$module_exists'(Module),
+       module_property_(Property, Module)
+    ).
+
+module_property_(exports(PIs), Module) :-
+    '$module_exports'(Module, PIs).
+module_property_(interface(Interface), Module) :-
+    (  '$module_interface_frozen'(Module) ->
+       Interface = frozen
+    ;  Interface = open
+    ).
+module_property_(file(File), Module) :-
+    '$module_location'(Module, File, _).
+module_property_(line(Line), Module) :-
+    '$module_location'(Module, _, Line).
+
+% Module imports the module Imported by use_module/1,2 or
+% reexport/1,2, Module being user for the imports of files consulted
+% outside of a module.
+module_imports(Module, Imported) :-
+    (  nonvar(Module), \+ atom(Module) ->
+       type_error(atom, Module, module_imports/2)
+    ;  nonvar(Imported), \+ atom(Imported) ->
+       type_error(atom, Imported, module_imports/2)
+    ;  '$module_imports'(Imports),
+       (  ground(Module-Imported) ->
+          memberchk(Module-Imported, Imports)
+       ;  member(Module-Imported, Imports)
+       )
+    ).
+
+% Name/Arity is callable without qualification from Module: defined
+% or declared dynamic in it, imported into it, or built in.
+visible_predicate(Module, PI) :-
+    (  var(Module) ->
+       instantiation_error(visible_predicate/2)
+    ;  \+ atom(Module) ->
+       type_error(atom, Module, visible_predicate/2)
+    ;  nonvar(PI), PI \= _/_ ->
+       type_error(predicate_indicator, PI, visible_predicate/2)
+    ;  '$visible_predicates'(Module, PIs),
+       (  ground(PI) ->
+          memberchk(PI, PIs)
+       ;  member(PI, PIs)
+       )
+    ).
+
+% enables the style check Check of loaded clauses with +Check,
+% disables it with -Check, and succeeds with ?(Check) if it's
+% enabled. the checks, enabled by default, warn of
+%
+% * singleton: variables occurring once in a clause, unless named
+%   with a leading underscore.
+% * discontiguous: clauses of a predicate not declared discontiguous
+%   separated by those of others, which replace its earlier clauses.
+% * redefined: predicates loaded before replaced by a later load.
+style_check(Spec) :-
+    (  var(Spec) ->
+       instantiation_error(style_check/1)
+    ;  Spec = +Check ->
+       must_be_style_check(Check),
+       '$set_style_check'(Check, true)
+    ;  Spec = -Check ->
+       must_be_style_check(Check),
+       '$set_style_check'(Check, false)
+    ;  Spec = ?(Check) ->
+       (  var(Check) ->
+          true
+       ;  must_be_style_check(Check)
+       ),
+       member(Check, [singleton, discontiguous, redefined]),
+       '$get_style_check'(Check, true)
+    ;  domain_error(style_check, Spec, style_check/1)
+    ).
+
+must_be_style_check(Check) :-
+    must_be(atom, Check),
+    (  memberchk(Check, [singleton, discontiguous, redefined]) ->
+       true
+    ;  domain_error(style_name, Check, style_check/1)
+    ).
+
 
 %% If use_module is invoked in an existing load context, use its
 %% directory. Otherwise, use the relative path of Path.
@@ -607,35 +908,134 @@ open_file(Path, Stream) :-
 
 
 use_module(Module, Exports, Evacuable) :-
+    use_module(Module, Exports, _, Evacuable).
+
+% loads Module and imports Exports from it into the load of Evacuable,
+% or all of its exports if Exports is [], unifying ModuleName with the
+% name of the module it declares, if any.
+use_module(Module, Exports, ModuleName, Evacuable) :-
     (  var(Module) ->
        instantiation_error(load/1)
     ;  Module = library(Library) ->
        (  path_atom(Library, LibraryPath) ->
           (  '$load_compiled_library'(LibraryPath, Exports, Evacuable) ->
-             true
+             ModuleName = LibraryPath
+          ;  \+ '$embedded_library'(LibraryPath),
+             search_path_file(library, LibraryPath, Path) ->
+             load_file_path(Path, Exports, ModuleName, Evacuable)
           ;  '$load_library_as_stream'(LibraryPath, Stream, Path),
              file_load(Stream, Path, Subevacuable),
+             loaded_module(Subevacuable, ModuleName),
              '$use_module'(Evacuable, Subevacuable, Exports)
           )
        ;  var(Library) ->
           instantiation_error(load/1)
        ;  type_error(atom, Library, load/1)
        )
+    ;  compound(Module),
+       functor(Module, Alias, 1),
+       Alias \== (/) ->
+       arg(1, Module, Spec),
+       path_atom(Spec, SpecPath),
+       (  search_path_file(Alias, SpecPath, Path) ->
+          load_file_path(Path, Exports, ModuleName, Evacuable)
+       ;  throw(error(existence_error(source_sink, Module), load/1))
+       )
     ;  (  path_atom(Module, ModulePath) ->
           load_context_path(ModulePath, Path),
-          open_file(Path, Stream),
-          stream_property(Stream, file_name(PathFileName)),
-          file_load(Stream, PathFileName, Subevacuable),
-          '$use_module'(Evacuable, Subevacuable, Exports)
+          load_file_path(Path, Exports, ModuleName, Evacuable)
        ;  type_error(atom, Module, load/1)
        )
     ).
 
+load_file_path(Path, Exports, ModuleName, Evacuable) :-
+    open_file(Path, Stream),
+    stream_property(Stream, file_name(PathFileName)),
+    file_load(Stream, PathFileName, Subevacuable),
+    loaded_module(Subevacuable, ModuleName),
+    '$use_module'(Evacuable, Subevacuable, Exports).
+
+% File is the first file named Path, or Path with .pl appended, in the
+% directories of Alias.
+search_path_file(Alias, Path, File) :-
+    search_path_directory(Alias, Dir),
+    foldl(builtins:atom_concat, ['/', Dir], Path, File0),
+    (  '$file_exists'(File0) ->
+       File = File0
+    ;  atom_concat(File0, '.pl', File),
+       '$file_exists'(File)
+    ),
+    !.
+
+% Dir is a directory of Alias, by the clauses of user:file_search_path/2,
+% where a directory can be given as an Alias0(Path) spec relative to the
+% directories of Alias0. those of library are followed by the clauses
+% of user:library_directory/1 and the library paths of the machine.
+search_path_directory(Alias, Dir) :-
+    (  \+ '$no_such_predicate'(user, file_search_path(_, _)),
+       user:file_search_path(Alias, Dir0),
+       (  atom(Dir0) ->
+          Dir = Dir0
+       ;  compound(Dir0),
+          functor(Dir0, Alias0, 1),
+          arg(1, Dir0, Spec),
+          path_atom(Spec, SpecPath),
+          search_path_directory(Alias0, Dir1),
+          foldl(builtins:atom_concat, ['/', Dir1], SpecPath, Dir)
+       )
+    ;  Alias == library,
+       (  \+ '$no_such_predicate'(user, library_directory(_)),
+          user:library_directory(Dir)
+       ;  '$library_paths'(Dirs),
+          member(Dir, Dirs)
+       )
+    ).
+
+loaded_module(Subevacuable, ModuleName) :-
+    (  '$loaded_module'(Subevacuable, ModuleName0) ->
+       ModuleName = ModuleName0
+    ;  true
+    ).
+
+% loads Module as use_module/3 does, adding the exports imported from
+% it to those of the module being loaded.
+reexport(Module, Exports, Evacuable) :-
+    use_module(Module, Exports, ModuleName, Evacuable),
+    (  atom(ModuleName) ->
+       '$reexport'(ModuleName, Exports, Evacuable)
+    ;  true
+    ).
+
 consult_stream(Stream, PathFileName) :-
    '$push_load_state_payload'(Evacuable),
     file_load(Stream, PathFileName, Subevacuable),
     '$use_module'(Evacuable, Subevacuable, _).
 
+% consults the module PathFileName from the stream aliased Alias as
+% Machine::consult_module_feed sends its text, leaving Status pending
+% between the pieces of text as feed_loop/3 does.
+consult_feed(Alias, PathFileName, Status) :-
+    stream_property(Stream, alias(Alias)),
+    !,
+    '$push_load_state_payload'(Evacuable),
+    create_file_load_context(Stream, PathFileName, Subevacuable),
+    '$add_in_situ_filename_module'(Subevacuable),
+    catch(loader:feed_load_init(Stream, Subevacuable, Status),
+          E,
+          loader:file_load_cleanup(Subevacuable, E)),
+    (  Status == done ->
+       '$pop_load_context',
+       '$use_module'(Evacuable, Subevacuable, _)
+    ;  true
+    ).
+
+feed_load_init(Stream, Evacuable, Status) :-
+    feed_loop(Stream, Evacuable, Status),
+    (  Status == done ->
+       run_initialization_goals
+    ;  true
+    ).
+
 :- non_counted_backtracking check_predicate_property/5.
 
 check_predicate_property(meta_predicate, Module, Name, Arity, MetaPredicateTerm) :-
@@ -934,7 +1334,10 @@ expand_goal_cases((Goals0 ; Goals1), Module, ExpandedGoals, HeadVars, TGs) :-
     ExpandedGoals = (ExpandedGoals0 ; ExpandedGoals1).
 expand_goal_cases((\+ Goals0), Module, ExpandedGoals, HeadVars, TGs) :-
     expand_goal(Goals0, Module, Goals1, HeadVars, TGs),
-    ExpandedGoals = (\+ Goals1).
+    (  builtins:floundering(false) ->
+       ExpandedGoals = (\+ Goals1)
+    ;  ExpandedGoals = (builtins:audit_negation(Goals0), \+ Goals1)
+    ).
 expand_goal_cases((Module:Goals0), _, ExpandedGoals, HeadVars, TGs) :-
     expand_goal(Goals0, Module, Goals1, HeadVars, TGs),
     ExpandedGoals = (Module:Goals1).
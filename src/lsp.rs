@@ -0,0 +1,559 @@
+//! A [Language Server Protocol](https://microsoft.github.io/language-server-protocol/)
+//! server, through which editors check and navigate Prolog programs.
+//!
+//! `scryer-prolog --lsp` runs the server on stdin and stdout. It reads
+//! the open documents with the parser of the machine and reports their
+//! syntax errors, singleton variables and calls of undefined
+//! predicates. A predicate is defined if the document or the files it
+//! loads, transitively, have clauses for it or declare it, or if it is
+//! exported by a library the document uses or callable without loading
+//! anything. Go to definition finds the clauses of the predicate named
+//! at the cursor in the same files and in the other open documents,
+//! and completion offers the names of the predicates defined there.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use indexmap::{IndexMap, IndexSet};
+use serde_json::{json, Value};
+
+use crate::dap::{read_message, write_message};
+use crate::machine::source_analysis::{
+    PredicateSite, Severity, SourceAnalysis, SourceAnalyzer, SourcePos, SourceTokenKind,
+};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Runs a Language Server Protocol server, reading requests and
+/// notifications from `input` and writing responses and notifications
+/// to `output`, until the client sends `exit` or `input` ends.
+pub fn run_lsp_server(
+    mut input: impl BufRead + 'static,
+    output: impl Write + 'static,
+) -> io::Result<()> {
+    let mut server = Server {
+        output: Box::new(output),
+        analyzer: SourceAnalyzer::new(),
+        documents: IndexMap::new(),
+    };
+
+    while let Some(message) = read_message(&mut input)? {
+        if message["method"] == "exit" {
+            break;
+        }
+
+        server.handle(&message)?;
+    }
+
+    Ok(())
+}
+
+struct Server {
+    output: Box<dyn Write>,
+    analyzer: SourceAnalyzer,
+    // the analyses of the open documents by URI.
+    documents: IndexMap<String, SourceAnalysis>,
+}
+
+impl Server {
+    fn respond(&mut self, request: &Value, result: Value) -> io::Result<()> {
+        write_message(
+            &mut self.output,
+            &json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+        )
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        write_message(
+            &mut self.output,
+            &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+        )
+    }
+
+    fn handle(&mut self, message: &Value) -> io::Result<()> {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+
+        match message["method"].as_str().unwrap_or("") {
+            "initialize" => self.respond(
+                message,
+                json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "definitionProvider": true,
+                        "completionProvider": {},
+                    },
+                    "serverInfo": { "name": "scryer-prolog" },
+                }),
+            ),
+            "shutdown" => self.respond(message, Value::Null),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.open(uri, text)
+            }
+            "textDocument/didChange" => {
+                // the whole text is sent with each change.
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                    .unwrap_or("");
+
+                self.open(uri, text)
+            }
+            "textDocument/didClose" => {
+                self.documents.shift_remove(uri);
+                self.notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )
+            }
+            "textDocument/definition" => {
+                let locations = self.definitions(uri, &params["position"]);
+                self.respond(message, json!(locations))
+            }
+            "textDocument/completion" => {
+                let items = self.completions(uri);
+                self.respond(message, json!(items))
+            }
+            method if message.get("id").is_some() => write_message(
+                &mut self.output,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": {
+                        "code": METHOD_NOT_FOUND,
+                        "message": format!("unsupported method {method}"),
+                    },
+                }),
+            ),
+            // other notifications are ignored.
+            _ => Ok(()),
+        }
+    }
+
+    fn open(&mut self, uri: &str, text: &str) -> io::Result<()> {
+        let analysis = self.analyzer.analyze(text);
+        self.documents.insert(uri.to_owned(), analysis);
+
+        let diagnostics = self.diagnostics(uri);
+
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    // the analyses of the document at uri and of the files it loads,
+    // transitively, by URI.
+    fn loaded_analyses(&mut self, uri: &str) -> Vec<(String, SourceAnalysis)> {
+        let mut analyses = vec![];
+        let mut visited = IndexSet::new();
+        let mut pending = VecDeque::from([uri.to_owned()]);
+
+        while let Some(uri) = pending.pop_front() {
+            if !visited.insert(uri.clone()) {
+                continue;
+            }
+
+            let analysis = match self.documents.get(&uri) {
+                Some(analysis) => analysis.clone(),
+                None => match uri_path(&uri).and_then(|path| std::fs::read_to_string(path).ok()) {
+                    Some(text) => self.analyzer.analyze(&text),
+                    None => continue,
+                },
+            };
+
+            if let Some(dir) = uri_path(&uri).and_then(|path| path.parent().map(Path::to_path_buf))
+            {
+                pending.extend(
+                    analysis
+                        .loaded_files
+                        .iter()
+                        .filter_map(|file| resolve_file(&dir, file))
+                        .map(|path| path_uri(&path)),
+                );
+            }
+
+            analyses.push((uri, analysis));
+        }
+
+        analyses
+    }
+
+    fn diagnostics(&mut self, uri: &str) -> Vec<Value> {
+        let analyses = self.loaded_analyses(uri);
+
+        let Some((_, analysis)) = analyses.first() else {
+            return vec![];
+        };
+
+        let defined: IndexSet<(String, usize)> = analyses
+            .iter()
+            .flat_map(|(_, analysis)| {
+                let definitions = analysis
+                    .definitions
+                    .iter()
+                    .map(|site| (site.name.clone(), site.arity));
+
+                let declared = analysis
+                    .declared
+                    .iter()
+                    .chain(&analysis.imported)
+                    .map(|(name, arity)| (name.as_str().to_string(), *arity));
+
+                definitions.chain(declared).collect::<Vec<_>>()
+            })
+            .collect();
+
+        let mut diagnostics: Vec<_> = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let severity = match diagnostic.severity {
+                    Severity::Error => 1,
+                    Severity::Warning => 2,
+                };
+
+                json!({
+                    "range": range(diagnostic.start, diagnostic.end),
+                    "severity": severity,
+                    "source": "scryer-prolog",
+                    "message": diagnostic.message,
+                })
+            })
+            .collect();
+
+        for goal in &analysis.goals {
+            if defined.contains(&(goal.name.clone(), goal.arity))
+                || self.analyzer.is_visible(&goal.name, goal.arity)
+            {
+                continue;
+            }
+
+            diagnostics.push(json!({
+                "range": range(goal.start, goal.end),
+                "severity": 2,
+                "source": "scryer-prolog",
+                "message": format!("undefined procedure {}/{}", goal.name, goal.arity),
+            }));
+        }
+
+        diagnostics
+    }
+
+    fn definitions(&mut self, uri: &str, position: &Value) -> Vec<Value> {
+        let Some((name, arity)) = self.documents.get(uri).and_then(|analysis| {
+            let pos = SourcePos {
+                line: position["line"].as_u64()? as usize,
+                col: position["character"].as_u64()? as usize,
+            };
+
+            predicate_at(analysis, pos)
+        }) else {
+            return vec![];
+        };
+
+        let mut analyses = self.loaded_analyses(uri);
+
+        for (other_uri, analysis) in &self.documents {
+            if analyses.iter().all(|(uri, _)| uri != other_uri) {
+                analyses.push((other_uri.clone(), analysis.clone()));
+            }
+        }
+
+        let sites = |matches: &dyn Fn(&PredicateSite) -> bool| -> Vec<Value> {
+            analyses
+                .iter()
+                .flat_map(|(uri, analysis)| {
+                    analysis
+                        .definitions
+                        .iter()
+                        .filter(|site| matches(site))
+                        .map(
+                            move |site| json!({ "uri": uri, "range": range(site.start, site.end) }),
+                        )
+                })
+                .collect()
+        };
+
+        // the predicate may be a nonterminal, or be named with
+        // another arity than that at the cursor, as in a predicate
+        // indicator.
+        let locations =
+            sites(&|site| site.name == name && (site.arity == arity || site.arity == arity + 2));
+
+        if locations.is_empty() {
+            sites(&|site| site.name == name)
+        } else {
+            locations
+        }
+    }
+
+    fn completions(&mut self, uri: &str) -> Vec<Value> {
+        let mut predicates = IndexSet::new();
+
+        for (_, analysis) in self.loaded_analyses(uri) {
+            predicates.extend(
+                analysis
+                    .definitions
+                    .iter()
+                    .map(|site| (site.name.clone(), site.arity)),
+            );
+            predicates.extend(
+                analysis
+                    .imported
+                    .iter()
+                    .map(|(name, arity)| (name.as_str().to_string(), *arity)),
+            );
+        }
+
+        predicates.extend(self.analyzer.visible_predicates());
+
+        predicates
+            .into_iter()
+            .map(|(name, arity)| {
+                json!({
+                    "label": name,
+                    "kind": 3,
+                    "detail": format!("{name}/{arity}"),
+                })
+            })
+            .collect()
+    }
+}
+
+// the name and arity of the atom at pos, counting the arguments that
+// follow it.
+fn predicate_at(analysis: &SourceAnalysis, pos: SourcePos) -> Option<(String, usize)> {
+    let tokens = &analysis.tokens;
+    let idx = tokens
+        .iter()
+        .position(|token| token.start <= pos && pos <= token.end)?;
+
+    let SourceTokenKind::Atom(name) = &tokens[idx].kind else {
+        return None;
+    };
+
+    if tokens.get(idx + 1)?.kind != SourceTokenKind::FunctorOpen {
+        return Some((name.clone(), 0));
+    }
+
+    let mut depth = 0;
+    let mut arity = 1;
+
+    for token in &tokens[idx + 1..] {
+        match token.kind {
+            SourceTokenKind::FunctorOpen | SourceTokenKind::Open => depth += 1,
+            SourceTokenKind::Close if depth == 1 => break,
+            SourceTokenKind::Close => depth -= 1,
+            SourceTokenKind::Comma if depth == 1 => arity += 1,
+            SourceTokenKind::End => break,
+            _ => {}
+        }
+    }
+
+    Some((name.clone(), arity))
+}
+
+fn range(start: SourcePos, end: SourcePos) -> Value {
+    json!({
+        "start": { "line": start.line, "character": start.col },
+        "end": { "line": end.line, "character": end.col },
+    })
+}
+
+// the file a directive names relative to dir, with or without its .pl
+// extension.
+fn resolve_file(dir: &Path, file: &str) -> Option<PathBuf> {
+    let path = dir.join(file);
+    let with_extension = PathBuf::from(format!("{}.pl", path.display()));
+
+    [path, with_extension]
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut idx = 0;
+
+    while idx < path.len() {
+        let escaped = (path[idx] == b'%')
+            .then(|| std::str::from_utf8(path.get(idx + 1..idx + 3)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(path[idx]);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+fn path_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn messages(mut output: &[u8]) -> Vec<Value> {
+        let mut messages = vec![];
+
+        while let Some(message) = read_message(&mut output).unwrap() {
+            messages.push(message);
+        }
+
+        messages
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn diagnostics_definitions_and_completions() {
+        let dir = std::env::temp_dir().join(format!("scryer_lsp_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("helper.pl"), "helper(Xs) :- Xs = [_|_].\n").unwrap();
+
+        let uri = path_uri(&dir.join("main.pl"));
+        let text = concat!(
+            ":- use_module(library(lists)).\n",
+            ":- ensure_loaded(helper).\n",
+            "\n",
+            "main :- append([1], [2], Xs), helper(Xs), missing(Xs), length(Xs, N).\n",
+            "bad :- ( .\n",
+            "greeting --> [hello], name.\n",
+            "name --> [world].\n",
+        );
+
+        let mut input = vec![];
+
+        for message in [
+            json!({ "id": 1, "method": "initialize", "params": {} }),
+            json!({
+                "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": uri, "text": text } },
+            }),
+            json!({
+                "id": 2,
+                "method": "textDocument/definition",
+                "params": {
+                    "textDocument": { "uri": uri },
+                    "position": { "line": 3, "character": 32 },
+                },
+            }),
+            json!({
+                "id": 3,
+                "method": "textDocument/definition",
+                "params": {
+                    "textDocument": { "uri": uri },
+                    "position": { "line": 5, "character": 22 },
+                },
+            }),
+            json!({
+                "id": 4,
+                "method": "textDocument/completion",
+                "params": { "textDocument": { "uri": uri } },
+            }),
+            json!({ "id": 5, "method": "shutdown" }),
+            json!({ "method": "exit" }),
+        ] {
+            write_message(&mut input, &message).unwrap();
+        }
+
+        let output = Rc::new(RefCell::new(vec![]));
+
+        run_lsp_server(Cursor::new(input), SharedOutput(output.clone())).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let messages = messages(&output.borrow());
+
+        let diagnostics: Vec<_> = messages[1]["params"]["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic["range"]["start"]["line"].as_u64().unwrap(),
+                    diagnostic["message"].as_str().unwrap(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            diagnostics,
+            [
+                (3, "singleton variable N"),
+                (4, "syntax error: incomplete_reduction"),
+                (3, "undefined procedure missing/1"),
+            ]
+        );
+
+        let helper_uri = path_uri(&dir.join("helper.pl"));
+
+        assert_eq!(
+            messages[2]["result"],
+            json!([{
+                "uri": helper_uri,
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 6 },
+                },
+            }])
+        );
+        assert_eq!(
+            messages[3]["result"][0]["range"]["start"],
+            json!({ "line": 6, "character": 0 })
+        );
+
+        let completions: Vec<_> = messages[4]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["detail"].as_str().unwrap())
+            .collect();
+
+        for detail in ["main/0", "greeting/2", "helper/1", "append/3", "length/2"] {
+            assert!(completions.contains(&detail), "{detail} is not completed");
+        }
+
+        assert_eq!(messages[5]["result"], Value::Null);
+    }
+}
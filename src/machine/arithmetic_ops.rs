@@ -11,6 +11,7 @@
 use crate::heap_iter::*;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_state::*;
+use crate::machine::rational_arithmetic;
 use crate::offset_table::*;
 use crate::parser::ast::*;
 use crate::parser::dashu::{Integer, Rational};
@@ -218,6 +219,11 @@ pub(crate) fn abs(n: Number, arena: &mut Arena) -> Number {
 
 #[inline]
 pub(crate) fn sub(lhs: Number, rhs: Number, arena: &mut Arena) -> Result<Number, EvalError> {
+    if let (Number::Fixnum(n1), Number::Fixnum(n2)) = (lhs, rhs) {
+        // the difference of two fixnums always fits in an i64.
+        return Ok(fixnum!(Number, n1.get_num() - n2.get_num(), arena));
+    }
+
     let neg_result = neg(rhs, arena);
     add(lhs, neg_result, arena)
 }
@@ -280,7 +286,8 @@ pub(crate) fn mul(lhs: Number, rhs: Number, arena: &mut Arena) -> Result<Number,
 pub(crate) fn div(n1: Number, n2: Number) -> Result<Number, MachineStubGen> {
     let stub_gen = || functor_stub(atom!("/"), 2);
 
-    if n2.is_zero() {
+    // divisions involving a float are left to the float_zero_div flag.
+    if n2.is_zero() && !matches!(n1, Number::Float(_)) && !matches!(n2, Number::Float(_)) {
         Err(zero_divisor_eval_error(stub_gen))
     } else {
         try_numeric_result!(n1 / n2, stub_gen)
@@ -566,6 +573,145 @@ pub(crate) fn rdiv(
     }
 }
 
+// `/` under the prefer_rationals flag: the quotient of integers and
+// rationals is exact, floats are divided as by div.
+pub(crate) fn exact_div(
+    n1: Number,
+    n2: Number,
+    arena: &mut Arena,
+) -> Result<Number, MachineStubGen> {
+    if matches!(n1, Number::Float(_)) || matches!(n2, Number::Float(_)) {
+        return div(n1, n2);
+    }
+
+    let stub_gen = || functor_stub(atom!("/"), 2);
+
+    if n2.is_zero() {
+        return Err(zero_divisor_eval_error(stub_gen));
+    }
+
+    let r1 = rational_from_number(n1, stub_gen, arena)?;
+    let r2 = rational_from_number(n2, stub_gen, arena)?;
+
+    Ok(Number::arena_from(Rational::from(&*r1 / &*r2), arena))
+}
+
+// `**` under the prefer_rationals flag: an integer or rational raised to a
+// small integer power is exact, to another integer or rational power it's
+// rounded as by exact_elementary, and any other power is computed as by
+// pow.
+pub(crate) fn exact_pow(
+    n1: Number,
+    n2: Number,
+    culprit: Atom,
+    flags: RationalFlags,
+    arena: &mut Arena,
+) -> Result<Number, MachineStubGen> {
+    let stub_gen = move || functor_stub(culprit, 2);
+
+    let exponent = match (n1, n2) {
+        (Number::Float(_), _) | (_, Number::Float(_)) => return pow(n1, n2, culprit),
+        (_, Number::Fixnum(n2)) if !(n1.is_zero() && n2.get_num() < 0) => n2.get_num(),
+        (_, Number::Fixnum(_)) | (_, Number::Integer(_)) => return pow(n1, n2, culprit),
+        (_, Number::Rational(r2)) => {
+            let r1 = rational_from_number(n1, stub_gen, arena)?;
+
+            return match rational_arithmetic::pow(&r1, &r2, flags) {
+                Some(result) => Ok(Number::arena_from(result, arena)),
+                None => Err(undefined_eval_error(stub_gen)),
+            };
+        }
+    };
+
+    let r1 = rational_from_number(n1, stub_gen, arena)?;
+    let result = r1.pow(exponent.unsigned_abs() as usize);
+
+    Ok(Number::arena_from(
+        if exponent < 0 {
+            Rational::ONE / result
+        } else {
+            result
+        },
+        arena,
+    ))
+}
+
+// the elementary functions under the prefer_rationals flag: of integers
+// and rationals they evaluate to rationals of the significant digits of
+// the rational_precision flag, rounded as the rational_rounding flag says,
+// and of floats to floats as without the flag.
+pub(crate) fn exact_elementary(
+    name: Atom,
+    n1: Number,
+    flags: RationalFlags,
+    arena: &mut Arena,
+) -> Result<Number, MachineStubGen> {
+    let x = match n1 {
+        Number::Float(_) => {
+            let float_fn = match name {
+                atom!("sqrt") => sqrt,
+                atom!("exp") => exp,
+                atom!("expm1") => expm1,
+                atom!("log") => log,
+                atom!("log1p") => log1p,
+                atom!("sin") => sin,
+                atom!("cos") => cos,
+                atom!("tan") => tan,
+                atom!("asin") => asin,
+                atom!("acos") => acos,
+                atom!("atan") => atan,
+                _ => unreachable!(),
+            };
+
+            return float_fn(n1).map(|f| Number::Float(OrderedFloat(f)));
+        }
+        Number::Fixnum(n) => Rational::from(n.get_num()),
+        Number::Integer(n) => Rational::from((*n).clone()),
+        Number::Rational(r) => (*r).clone(),
+    };
+
+    let result = match name {
+        atom!("sqrt") => rational_arithmetic::sqrt(&x, flags),
+        atom!("exp") => Some(rational_arithmetic::exp(&x, flags)),
+        atom!("expm1") => Some(rational_arithmetic::expm1(&x, flags)),
+        atom!("log") => rational_arithmetic::log(&x, flags),
+        atom!("log1p") => rational_arithmetic::log1p(&x, flags),
+        atom!("sin") => Some(rational_arithmetic::sin(&x, flags)),
+        atom!("cos") => Some(rational_arithmetic::cos(&x, flags)),
+        atom!("tan") => Some(rational_arithmetic::tan(&x, flags)),
+        atom!("asin") => rational_arithmetic::asin(&x, flags),
+        atom!("acos") => rational_arithmetic::acos(&x, flags),
+        atom!("atan") => Some(rational_arithmetic::atan(&x, flags)),
+        _ => unreachable!(),
+    };
+
+    match result {
+        Some(result) => Ok(Number::arena_from(result, arena)),
+        None => Err(undefined_eval_error(|| functor_stub(atom!("is"), 2))),
+    }
+}
+
+// atan2 under the prefer_rationals flag, as by exact_elementary.
+pub(crate) fn exact_atan2(
+    n1: Number,
+    n2: Number,
+    flags: RationalFlags,
+    arena: &mut Arena,
+) -> Result<Number, MachineStubGen> {
+    if matches!(n1, Number::Float(_)) || matches!(n2, Number::Float(_)) {
+        return atan2(n1, n2).map(|f| Number::Float(OrderedFloat(f)));
+    }
+
+    let stub_gen = || functor_stub(atom!("is"), 2);
+    let r1 = rational_from_number(n1, stub_gen, arena)?;
+    let r2 = rational_from_number(n2, stub_gen, arena)?;
+
+    match rational_arithmetic::atan2(&r1, &r2, flags) {
+        Some(result) => Ok(Number::arena_from(result, arena)),
+        None => Err(undefined_eval_error(stub_gen)),
+    }
+}
+
 pub(crate) fn idiv(n1: Number, n2: Number, arena: &mut Arena) -> Result<Number, MachineStubGen> {
     let stub_gen = || {
         let idiv_atom = atom!("//");
@@ -613,6 +759,28 @@ pub(crate) fn idiv(n1: Number, n2: Number, arena: &mut Arena) -> Result<Number,
     }
 }
 
+// `//` under the integer_rounding_function flag set to down: the
+// quotient of integers is rounded toward negative infinity, as by div.
+pub(crate) fn idiv_down(
+    n1: Number,
+    n2: Number,
+    arena: &mut Arena,
+) -> Result<Number, MachineStubGen> {
+    let stub_gen = || functor_stub(atom!("//"), 2);
+
+    let quotient = idiv(n1, n2, arena)?;
+    let remainder = remainder(n1, n2, arena)?;
+
+    if !remainder.is_zero() && remainder.is_negative() != n2.is_negative() {
+        try_numeric_result!(
+            sub(quotient, Number::Fixnum(Fixnum::build_with(1)), arena),
+            stub_gen
+        )
+    } else {
+        Ok(quotient)
+    }
+}
+
 pub(crate) fn int_floor_div(
     n1: Number,
     n2: Number,
@@ -623,12 +791,44 @@ pub(crate) fn int_floor_div(
         functor_stub(div_atom, 2)
     };
 
+    if let (Number::Fixnum(n1), Number::Fixnum(n2)) = (n1, n2) {
+        let n2 = n2.get_num();
+
+        if n2 == 0 {
+            return Err(zero_divisor_eval_error(stub_gen));
+        }
+
+        return Ok(fixnum!(
+            Number,
+            DivFloor::div_floor(n1.get_num(), n2),
+            arena
+        ));
+    }
+
     let modulus = modulus(n1, n2, arena)?;
     let n1 = try_numeric_result!(sub(n1, modulus, arena), stub_gen)?;
 
     idiv(n1, n2, arena)
 }
 
+// `rem` under the integer_rounding_function flag set to down: X - (X //
+// Y) * Y with `//` rounded down, which is X mod Y.
+pub(crate) fn remainder_down(
+    x: Number,
+    y: Number,
+    arena: &mut Arena,
+) -> Result<Number, MachineStubGen> {
+    let stub_gen = || functor_stub(atom!("rem"), 2);
+
+    let remainder = remainder(x, y, arena)?;
+
+    if !remainder.is_zero() && remainder.is_negative() != y.is_negative() {
+        try_numeric_result!(add(remainder, y, arena), stub_gen)
+    } else {
+        Ok(remainder)
+    }
+}
+
 pub(crate) fn shr(lhs: Number, rhs: Number, arena: &mut Arena) -> Result<Number, MachineStubGen> {
     let stub_gen = || {
         let shr_atom = atom!(">>");
@@ -1031,18 +1231,20 @@ pub(crate) fn atan(n1: Number) -> Result<f64, MachineStubGen> {
 }
 
 #[inline]
-pub(crate) fn float_fractional_part(n1: Number) -> Result<f64, MachineStubGen> {
-    unary_float_fn_template(n1, |f| f.fract())
+pub(crate) fn erf(n1: Number) -> Result<f64, MachineStubGen> {
+    unary_float_fn_template(n1, libm::erf)
 }
 
 #[inline]
-pub(crate) fn float_integer_part(n1: Number) -> Result<f64, MachineStubGen> {
-    unary_float_fn_template(n1, |f| f.trunc())
+pub(crate) fn erfc(n1: Number) -> Result<f64, MachineStubGen> {
+    unary_float_fn_template(n1, libm::erfc)
 }
 
-#[inline]
-pub(crate) fn sqrt(n1: Number) -> Result<f64, MachineStubGen> {
-    if n1.is_negative() {
+pub(crate) fn lgamma(n1: Number) -> Result<f64, MachineStubGen> {
+    let f = float(n1)?;
+
+    // the poles of the gamma function.
+    if f <= 0.0 && f.fract() == 0.0 {
         let stub_gen = || {
             let is_atom = atom!("is");
             functor_stub(is_atom, 2)
@@ -1051,6 +1253,34 @@ pub(crate) fn sqrt(n1: Number) -> Result<f64, MachineStubGen> {
         return Err(undefined_eval_error(stub_gen));
     }
 
+    unary_float_fn_template(n1, libm::lgamma)
+}
+
+#[inline]
+pub(crate) fn expm1(n1: Number) -> Result<f64, MachineStubGen> {
+    unary_float_fn_template(n1, libm::expm1)
+}
+
+#[inline]
+pub(crate) fn log1p(n1: Number) -> Result<f64, MachineStubGen> {
+    unary_float_fn_template(n1, libm::log1p)
+}
+
+#[inline]
+pub(crate) fn float_fractional_part(n1: Number) -> Result<f64, MachineStubGen> {
+    unary_float_fn_template(n1, |f| f.fract())
+}
+
+#[inline]
+pub(crate) fn float_integer_part(n1: Number) -> Result<f64, MachineStubGen> {
+    // the integer part of a negative fraction is 0.0, not -0.0.
+    unary_float_fn_template(n1, |f| f.trunc() + 0.0)
+}
+
+#[inline]
+pub(crate) fn sqrt(n1: Number) -> Result<f64, MachineStubGen> {
+    // the square roots of negative numbers are NaN, undefined unless
+    // the float_undefined flag is nan.
     unary_float_fn_template(n1, |f| f.sqrt())
 }
 
@@ -1213,9 +1443,25 @@ pub(crate) fn arith_eval_by_metacall(
                                 iter,
                                 try_numeric_result!(mul(a1, a2, &mut self.arena), stub_gen)
                             )),
+                            atom!("/") if self.flags.prefer_rationals => self.interms.push(
+                                drop_iter_on_err!(self, iter, exact_div(a1, a2, &mut self.arena))
+                            ),
                             atom!("/") => self.interms.push(
                                 drop_iter_on_err!(self, iter, div(a1, a2))
                             ),
+                            atom!("**") if self.flags.prefer_rationals => self.interms.push(
+                                drop_iter_on_err!(
+                                    self,
+                                    iter,
+                                    exact_pow(
+                                        a1,
+                                        a2,
+                                        atom!("is"),
+                                        self.flags.rational_flags,
+                                        &mut self.arena
+                                    )
+                                )
+                            ),
                             atom!("**") => self.interms.push(
                                 drop_iter_on_err!(self, iter, pow(a1, a2, atom!("is")))
                             ),
@@ -1248,6 +1494,9 @@ pub(crate) fn arith_eval_by_metacall(
 
                                 self.interms.push(Number::Rational(result));
                             }
+                            atom!("//") if self.flags.integer_rounding_function == IntegerRoundingFunction::Down => self.interms.push(
+                                drop_iter_on_err!(self, iter, idiv_down(a1, a2, &mut self.arena))
+                            ),
                             atom!("//") => self.interms.push(
                                 drop_iter_on_err!(self, iter, idiv(a1, a2, &mut self.arena))
                             ),
@@ -1272,9 +1521,23 @@ pub(crate) fn arith_eval_by_metacall(
                             atom!("mod") => self.interms.push(
                                 drop_iter_on_err!(self, iter, modulus(a1, a2, &mut self.arena))
                             ),
+                            atom!("rem") if self.flags.integer_rounding_function == IntegerRoundingFunction::Down => self.interms.push(
+                                drop_iter_on_err!(
+                                    self,
+                                    iter,
+                                    remainder_down(a1, a2, &mut self.arena)
+                                )
+                            ),
                             atom!("rem") => self.interms.push(
                                 drop_iter_on_err!(self, iter, remainder(a1, a2, &mut self.arena))
                             ),
+                            atom!("atan2") if self.flags.prefer_rationals => self.interms.push(
+                                drop_iter_on_err!(
+                                    self,
+                                    iter,
+                                    exact_atan2(a1, a2, self.flags.rational_flags, &mut self.arena)
+                                )
+                            ),
                             atom!("atan2") => self.interms.push(Number::Float(OrderedFloat(
                                 drop_iter_on_err!(self, iter, atan2(a1, a2))
                             ))),
@@ -1299,6 +1562,16 @@ pub(crate) fn arith_eval_by_metacall(
                         match name {
                             atom!("-") => self.interms.push(neg(a1, &mut self.arena)),
                             atom!("+") => self.interms.push(a1),
+                            atom!("sqrt") | atom!("exp") | atom!("expm1") | atom!("log") |
+                            atom!("log1p") | atom!("sin") | atom!("cos") | atom!("tan") |
+                            atom!("asin") | atom!("acos") | atom!("atan")
+                                if self.flags.prefer_rationals => self.interms.push(
+                                drop_iter_on_err!(
+                                    self,
+                                    iter,
+                                    exact_elementary(name, a1, self.flags.rational_flags, &mut self.arena)
+                                )
+                            ),
                             atom!("cos") => self.interms.push(Number::Float(OrderedFloat(
                                 drop_iter_on_err!(self, iter, cos(a1))
                             ))),
@@ -1332,6 +1605,21 @@ pub(crate) fn arith_eval_by_metacall(
                             atom!("atan") => self.interms.push(Number::Float(OrderedFloat(
                                 drop_iter_on_err!(self, iter, atan(a1))
                             ))),
+                            atom!("erf") => self.interms.push(Number::Float(OrderedFloat(
+                                drop_iter_on_err!(self, iter, erf(a1))
+                            ))),
+                            atom!("erfc") => self.interms.push(Number::Float(OrderedFloat(
+                                drop_iter_on_err!(self, iter, erfc(a1))
+                            ))),
+                            atom!("lgamma") => self.interms.push(Number::Float(OrderedFloat(
+                                drop_iter_on_err!(self, iter, lgamma(a1))
+                            ))),
+                            atom!("expm1") => self.interms.push(Number::Float(OrderedFloat(
+                                drop_iter_on_err!(self, iter, expm1(a1))
+                            ))),
+                            atom!("log1p") => self.interms.push(Number::Float(OrderedFloat(
+                                drop_iter_on_err!(self, iter, log1p(a1))
+                            ))),
                             atom!("abs") => self.interms.push(abs(a1, &mut self.arena)),
                             atom!("float") => self.interms.push(Number::Float(OrderedFloat(
                                 drop_iter_on_err!(self, iter, float(a1))
@@ -1373,6 +1661,14 @@ pub(crate) fn arith_eval_by_metacall(
                                 self.interms.push(Number::Float(OrderedFloat(f64::EPSILON)));
                                 continue;
                             }
+                            atom!("inf") | atom!("infinite") => {
+                                self.interms.push(Number::Float(OrderedFloat(f64::INFINITY)));
+                                continue;
+                            }
+                            atom!("nan") => {
+                                self.interms.push(Number::Float(OrderedFloat(f64::NAN)));
+                                continue;
+                            }
                             _ => {
                             }
                         }
@@ -1477,4 +1773,92 @@ fn arith_eval_by_metacall_tests() {
             Ok(Number::Fixnum(Fixnum::build_with(-1)))
         );
     }
+
+    #[test]
+    fn small_integer_results_are_fixnums() {
+        let mut arena = Arena::new();
+
+        let big = Number::arena_from(Integer::from(1u64 << 62), &mut arena);
+        assert!(matches!(big, Number::Integer(_)));
+
+        let five = Number::Fixnum(Fixnum::build_with(5));
+        let sum = add(big, five, &mut arena).unwrap();
+
+        assert!(matches!(sum, Number::Integer(_)));
+        assert_eq!(sub(sum, big, &mut arena).unwrap(), five);
+        assert_eq!(
+            sub(big, big, &mut arena).unwrap(),
+            Number::Fixnum(Fixnum::build_with(0))
+        );
+
+        assert_eq!(
+            int_floor_div(
+                Number::Fixnum(Fixnum::build_with(-7)),
+                Number::Fixnum(Fixnum::build_with(2)),
+                &mut arena
+            )
+            .ok(),
+            Some(Number::Fixnum(Fixnum::build_with(-4)))
+        );
+    }
+
+    #[test]
+    fn rounding_down_division() {
+        let mut arena = Arena::new();
+
+        // around 0 and the bounds of fixnums, i64 and beyond.
+        let mut values = vec![0i128, 1, 2, 3, 7];
+
+        for bound in [1 << 55, i64::MAX as i128, 1 << 64, 1 << 100] {
+            values.extend([bound - 1, bound, bound + 1]);
+        }
+
+        let values: Vec<i128> = values.into_iter().flat_map(|n| [n, -n]).collect();
+
+        for &x in &values {
+            for &y in values.iter().filter(|&&y| y != 0) {
+                let n1 = Number::arena_from(Integer::from(x), &mut arena);
+                let n2 = Number::arena_from(Integer::from(y), &mut arena);
+
+                let quotient =
+                    Number::arena_from(Integer::from(DivFloor::div_floor(x, y)), &mut arena);
+                let remainder =
+                    Number::arena_from(Integer::from(RemFloor::rem_floor(x, y)), &mut arena);
+
+                assert_eq!(
+                    idiv_down(n1, n2, &mut arena).ok(),
+                    Some(quotient),
+                    "{x} // {y}"
+                );
+                assert_eq!(
+                    remainder_down(n1, n2, &mut arena).ok(),
+                    Some(remainder),
+                    "{x} rem {y}"
+                );
+
+                // rounding down, // and rem agree with div and mod.
+                assert_eq!(int_floor_div(n1, n2, &mut arena).ok(), Some(quotient));
+                assert_eq!(modulus(n1, n2, &mut arena).ok(), Some(remainder));
+
+                let quotient = Number::arena_from(Integer::from(x / y), &mut arena);
+                let remainder = Number::arena_from(Integer::from(x % y), &mut arena);
+
+                assert_eq!(idiv(n1, n2, &mut arena).ok(), Some(quotient), "{x} // {y}");
+                assert_eq!(
+                    super::remainder(n1, n2, &mut arena).ok(),
+                    Some(remainder),
+                    "{x} rem {y}"
+                );
+            }
+        }
+
+        let zero = Number::Fixnum(Fixnum::build_with(0));
+        let one = Number::Fixnum(Fixnum::build_with(1));
+        let float = Number::Float(OrderedFloat(1.0));
+
+        assert!(idiv_down(one, zero, &mut arena).is_err());
+        assert!(remainder_down(one, zero, &mut arena).is_err());
+        assert!(idiv_down(float, one, &mut arena).is_err());
+        assert!(remainder_down(one, float, &mut arena).is_err());
+    }
 }
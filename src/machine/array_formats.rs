@@ -0,0 +1,600 @@
+use crate::parser::dashu::Integer;
+
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ArrayElement {
+    Integer(Integer),
+    Float(f64),
+    Complex(f64, f64),
+    Boolean(bool),
+}
+
+impl ArrayElement {
+    fn neg(&self) -> Self {
+        match self {
+            ArrayElement::Integer(n) => ArrayElement::Integer(-n),
+            ArrayElement::Float(f) => ArrayElement::Float(-f),
+            ArrayElement::Complex(re, im) => ArrayElement::Complex(-re, -im),
+            ArrayElement::Boolean(b) => ArrayElement::Boolean(*b),
+        }
+    }
+
+    fn conj(&self) -> Self {
+        match self {
+            ArrayElement::Complex(re, im) => ArrayElement::Complex(*re, -im),
+            element => element.clone(),
+        }
+    }
+}
+
+// an array of the given shape, whose elements are in row-major order.
+// the array of shape [] has a single element.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Array {
+    pub(crate) shape: Vec<usize>,
+    pub(crate) elements: Vec<ArrayElement>,
+}
+
+// the entries of a sparse matrix, (row, column, value) with indices
+// from 1, in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SparseMatrix {
+    pub(crate) rows: usize,
+    pub(crate) columns: usize,
+    pub(crate) entries: Vec<(usize, usize, ArrayElement)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum MatrixMarket {
+    Array(Array),
+    Coordinate(SparseMatrix),
+}
+
+// the values of the Python literals of the headers of .npy files.
+#[derive(Debug, Clone, PartialEq)]
+enum PyValue {
+    Str(String),
+    Bool(bool),
+    Int(usize),
+    Tuple(Vec<PyValue>),
+    Dict(Vec<(String, PyValue)>),
+}
+
+struct PyParser<'a> {
+    input: &'a str,
+}
+
+impl PyParser<'_> {
+    fn skip_space(&mut self) {
+        self.input = self.input.trim_start();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+
+        match self.input.strip_prefix(token) {
+            Some(rest) => {
+                self.input = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // the items of a sequence ended by close, whose last item may be
+    // followed by a comma.
+    fn items<T>(
+        &mut self,
+        close: &str,
+        mut item: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        let mut items = vec![];
+
+        loop {
+            if self.eat(close) {
+                return Some(items);
+            }
+
+            items.push(item(self)?);
+
+            if !self.eat(",") {
+                return self.eat(close).then_some(items);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<PyValue> {
+        self.skip_space();
+
+        if self.eat("{") {
+            self.items("}", |parser| {
+                let PyValue::Str(key) = parser.value()? else {
+                    return None;
+                };
+
+                parser.eat(":").then_some(())?;
+                Some((key, parser.value()?))
+            })
+            .map(PyValue::Dict)
+        } else if self.eat("(") {
+            self.items(")", Self::value).map(PyValue::Tuple)
+        } else if self.eat("True") {
+            Some(PyValue::Bool(true))
+        } else if self.eat("False") {
+            Some(PyValue::Bool(false))
+        } else if let Some(rest) = self.input.strip_prefix(['\'', '"']) {
+            let quote = self.input.chars().next()?;
+            let end = rest.find(quote)?;
+
+            self.input = &rest[end + 1..];
+            Some(PyValue::Str(rest[..end].to_string()))
+        } else {
+            let end = self
+                .input
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(self.input.len());
+            let n = self.input[..end].parse().ok()?;
+
+            self.input = &self.input[end..];
+            Some(PyValue::Int(n))
+        }
+    }
+}
+
+// the number of elements of an array of shape.
+fn shape_len(shape: &[usize]) -> Option<usize> {
+    shape.iter().try_fold(1usize, |len, &n| len.checked_mul(n))
+}
+
+// reads the elements of the NumPy type descr, e.g. <f8, from data.
+fn npy_elements(descr: &str, data: &[u8], len: usize) -> Option<Vec<ArrayElement>> {
+    let mut chars = descr.chars();
+    let little_endian = match chars.next()? {
+        '<' => true,
+        '>' => false,
+        '|' | '=' => cfg!(target_endian = "little"),
+        _ => return None,
+    };
+    let kind = chars.next()?;
+    let size: usize = chars.as_str().parse().ok()?;
+
+    if data.len() < len.checked_mul(size)? {
+        return None;
+    }
+
+    macro_rules! element {
+        ($bytes:expr, $t:ty) => {{
+            let bytes = $bytes.try_into().unwrap();
+
+            if little_endian {
+                <$t>::from_le_bytes(bytes)
+            } else {
+                <$t>::from_be_bytes(bytes)
+            }
+        }};
+    }
+
+    let element = |bytes: &[u8]| match (kind, size) {
+        ('b', 1) => Some(ArrayElement::Boolean(bytes[0] != 0)),
+        ('i', 1) => Some(ArrayElement::Integer(Integer::from(bytes[0] as i8))),
+        ('i', 2) => Some(ArrayElement::Integer(Integer::from(element!(bytes, i16)))),
+        ('i', 4) => Some(ArrayElement::Integer(Integer::from(element!(bytes, i32)))),
+        ('i', 8) => Some(ArrayElement::Integer(Integer::from(element!(bytes, i64)))),
+        ('u', 1) => Some(ArrayElement::Integer(Integer::from(bytes[0]))),
+        ('u', 2) => Some(ArrayElement::Integer(Integer::from(element!(bytes, u16)))),
+        ('u', 4) => Some(ArrayElement::Integer(Integer::from(element!(bytes, u32)))),
+        ('u', 8) => Some(ArrayElement::Integer(Integer::from(element!(bytes, u64)))),
+        ('f', 4) => Some(ArrayElement::Float(element!(bytes, f32) as f64)),
+        ('f', 8) => Some(ArrayElement::Float(element!(bytes, f64))),
+        ('c', 8) => Some(ArrayElement::Complex(
+            element!(&bytes[..4], f32) as f64,
+            element!(&bytes[4..], f32) as f64,
+        )),
+        ('c', 16) => Some(ArrayElement::Complex(
+            element!(&bytes[..8], f64),
+            element!(&bytes[8..], f64),
+        )),
+        _ => None,
+    };
+
+    data.chunks_exact(size).take(len).map(element).collect()
+}
+
+// reads the array of the bytes of a .npy file, of versions 1 to 3,
+// whose elements are booleans, integers, floats or complex numbers.
+// None if bytes aren't such a file.
+pub(crate) fn read_npy(bytes: &[u8]) -> Option<Array> {
+    if !bytes.starts_with(b"\x93NUMPY") {
+        return None;
+    }
+
+    // the major version is followed by the minor version.
+    let (header_len, header_start): (usize, usize) = match *bytes.get(6)? {
+        1 => (
+            u16::from_le_bytes(bytes.get(8..10)?.try_into().ok()?) as usize,
+            10,
+        ),
+        2 | 3 => (
+            u32::from_le_bytes(bytes.get(8..12)?.try_into().ok()?) as usize,
+            12,
+        ),
+        _ => return None,
+    };
+
+    let data_start = header_start.checked_add(header_len)?;
+    let header = std::str::from_utf8(bytes.get(header_start..data_start)?).ok()?;
+
+    let PyValue::Dict(header) = (PyParser { input: header }).value()? else {
+        return None;
+    };
+
+    let field = |key: &str| {
+        header
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    };
+
+    let Some(PyValue::Str(descr)) = field("descr") else {
+        return None;
+    };
+    let Some(PyValue::Bool(fortran_order)) = field("fortran_order") else {
+        return None;
+    };
+    let Some(PyValue::Tuple(shape)) = field("shape") else {
+        return None;
+    };
+
+    let shape = shape
+        .iter()
+        .map(|n| match n {
+            PyValue::Int(n) => Some(*n),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let len = shape_len(&shape)?;
+    let elements = npy_elements(descr, &bytes[data_start..], len)?;
+
+    let elements = if *fortran_order && shape.len() > 1 {
+        column_to_row_major(&shape, elements)
+    } else {
+        elements
+    };
+
+    Some(Array { shape, elements })
+}
+
+// the elements of an array of shape in column-major order, in
+// row-major order.
+fn column_to_row_major(shape: &[usize], elements: Vec<ArrayElement>) -> Vec<ArrayElement> {
+    let mut strides = Vec::with_capacity(shape.len());
+    let mut stride = 1;
+
+    for &n in shape {
+        strides.push(stride);
+        stride *= n;
+    }
+
+    let mut index = vec![0; shape.len()];
+    let mut row_major = Vec::with_capacity(elements.len());
+
+    for _ in 0..elements.len() {
+        let offset: usize = index.iter().zip(&strides).map(|(i, s)| i * s).sum();
+        row_major.push(elements[offset].clone());
+
+        for (i, &n) in index.iter_mut().zip(shape).rev() {
+            *i += 1;
+
+            if *i < n {
+                break;
+            }
+
+            *i = 0;
+        }
+    }
+
+    row_major
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MmField {
+    Real,
+    Integer,
+    Complex,
+    Pattern,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MmSymmetry {
+    General,
+    Symmetric,
+    SkewSymmetric,
+    Hermitian,
+}
+
+fn mm_element<'a>(
+    field: MmField,
+    words: &mut impl Iterator<Item = &'a str>,
+) -> Option<ArrayElement> {
+    let mut float = || words.next().and_then(|word| f64::from_str(word).ok());
+
+    match field {
+        MmField::Real => float().map(ArrayElement::Float),
+        MmField::Integer => Integer::from_str(words.next()?)
+            .ok()
+            .map(ArrayElement::Integer),
+        MmField::Complex => Some(ArrayElement::Complex(float()?, float()?)),
+        MmField::Pattern => Some(ArrayElement::Integer(Integer::from(1))),
+    }
+}
+
+// the element at (j, i) of a matrix of symmetry whose element at (i, j)
+// is element.
+fn mirror(symmetry: MmSymmetry, element: &ArrayElement) -> ArrayElement {
+    match symmetry {
+        MmSymmetry::SkewSymmetric => element.neg(),
+        MmSymmetry::Hermitian => element.conj(),
+        MmSymmetry::General | MmSymmetry::Symmetric => element.clone(),
+    }
+}
+
+// reads the text of a file of the Matrix Market exchange format, a
+// dense matrix of the array format or a sparse matrix of the
+// coordinate format. the elements of symmetric, skew-symmetric and
+// hermitian matrices are expanded from those of their lower triangles.
+// None if text isn't such a file.
+pub(crate) fn read_matrix_market(text: &str) -> Option<MatrixMarket> {
+    let mut lines = text.lines();
+    let banner = lines.next()?.to_ascii_lowercase();
+    let mut banner = banner.split_whitespace();
+
+    if banner.next()? != "%%matrixmarket" || banner.next()? != "matrix" {
+        return None;
+    }
+
+    let coordinate = match banner.next()? {
+        "coordinate" => true,
+        "array" => false,
+        _ => return None,
+    };
+
+    let field = match banner.next()? {
+        "real" | "double" => MmField::Real,
+        "integer" => MmField::Integer,
+        "complex" => MmField::Complex,
+        "pattern" if coordinate => MmField::Pattern,
+        _ => return None,
+    };
+
+    let symmetry = match banner.next()? {
+        "general" => MmSymmetry::General,
+        "symmetric" => MmSymmetry::Symmetric,
+        "skew-symmetric" => MmSymmetry::SkewSymmetric,
+        "hermitian" if field == MmField::Complex => MmSymmetry::Hermitian,
+        _ => return None,
+    };
+
+    let mut words = lines
+        .filter(|line| !line.starts_with('%'))
+        .flat_map(str::split_whitespace);
+
+    let mut size = || words.next().and_then(|word| usize::from_str(word).ok());
+    let rows = size()?;
+    let columns = size()?;
+
+    if symmetry != MmSymmetry::General && rows != columns {
+        return None;
+    }
+
+    if coordinate {
+        let nonzeros = size()?;
+        let mut entries = Vec::with_capacity(nonzeros);
+
+        for _ in 0..nonzeros {
+            let i = words.next().and_then(|word| usize::from_str(word).ok())?;
+            let j = words.next().and_then(|word| usize::from_str(word).ok())?;
+
+            if !(1..=rows).contains(&i) || !(1..=columns).contains(&j) {
+                return None;
+            }
+
+            let element = mm_element(field, &mut words)?;
+
+            if i != j && symmetry != MmSymmetry::General {
+                entries.push((j, i, mirror(symmetry, &element)));
+            }
+
+            entries.push((i, j, element));
+        }
+
+        entries.sort_by_key(|&(i, j, _)| (i, j));
+
+        return (words.next().is_none()).then_some(MatrixMarket::Coordinate(SparseMatrix {
+            rows,
+            columns,
+            entries,
+        }));
+    }
+
+    let len = rows.checked_mul(columns)?;
+    let mut elements: Vec<Option<ArrayElement>> = vec![None; len];
+
+    // the elements are listed by columns, only from the diagonal down
+    // if the matrix isn't general, and without the diagonal if it is
+    // skew-symmetric.
+    for j in 0..columns {
+        let first_row = match symmetry {
+            MmSymmetry::General => 0,
+            MmSymmetry::SkewSymmetric => j + 1,
+            MmSymmetry::Symmetric | MmSymmetry::Hermitian => j,
+        };
+
+        for i in first_row..rows {
+            let element = mm_element(field, &mut words)?;
+
+            if i != j && symmetry != MmSymmetry::General {
+                elements[j * columns + i] = Some(mirror(symmetry, &element));
+            }
+
+            elements[i * columns + j] = Some(element);
+        }
+    }
+
+    if words.next().is_some() {
+        return None;
+    }
+
+    let zero = match field {
+        MmField::Integer => ArrayElement::Integer(Integer::from(0)),
+        MmField::Complex => ArrayElement::Complex(0.0, 0.0),
+        MmField::Real | MmField::Pattern => ArrayElement::Float(0.0),
+    };
+
+    let elements = elements
+        .into_iter()
+        .map(|element| element.unwrap_or_else(|| zero.clone()))
+        .collect();
+
+    Some(MatrixMarket::Array(Array {
+        shape: vec![rows, columns],
+        elements,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn npy(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn floats(elements: &[f64]) -> Vec<ArrayElement> {
+        elements.iter().map(|&f| ArrayElement::Float(f)).collect()
+    }
+
+    fn integers(elements: &[i32]) -> Vec<ArrayElement> {
+        elements
+            .iter()
+            .map(|&n| ArrayElement::Integer(Integer::from(n)))
+            .collect()
+    }
+
+    #[test]
+    fn reads_npy() {
+        let data: Vec<u8> = [1.0f64, 2.0, 3.0, 4.0, 5.0, 6.0]
+            .iter()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+
+        let header = "{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }";
+        assert_eq!(
+            read_npy(&npy(header, &data)),
+            Some(Array {
+                shape: vec![2, 3],
+                elements: floats(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            })
+        );
+
+        let header = "{'descr': '<f8', 'fortran_order': True, 'shape': (2, 3), }";
+        assert_eq!(
+            read_npy(&npy(header, &data)).unwrap().elements,
+            floats(&[1.0, 3.0, 5.0, 2.0, 4.0, 6.0])
+        );
+
+        let header = "{'descr': '>i2', 'fortran_order': False, 'shape': (), }";
+        assert_eq!(
+            read_npy(&npy(header, &[0xff, 0xfe])),
+            Some(Array {
+                shape: vec![],
+                elements: integers(&[-2]),
+            })
+        );
+
+        let header = "{'descr': '|b1', 'fortran_order': False, 'shape': (2,), }";
+        assert_eq!(
+            read_npy(&npy(header, &[1, 0])).unwrap().elements,
+            [ArrayElement::Boolean(true), ArrayElement::Boolean(false)]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_npy() {
+        let header = "{'descr': '<f8', 'fortran_order': False, 'shape': (2,), }";
+        assert_eq!(read_npy(&npy(header, &[0; 15])), None);
+
+        let header = "{'descr': [('a', '<f8')], 'fortran_order': False, 'shape': (1,), }";
+        assert_eq!(read_npy(&npy(header, &[0; 8])), None);
+        assert_eq!(read_npy(b"NUMPY"), None);
+    }
+
+    #[test]
+    fn reads_matrix_market() {
+        let text = "%%MatrixMarket matrix array real general\n% comment\n2 2\n1\n2\n3\n4\n";
+        assert_eq!(
+            read_matrix_market(text),
+            Some(MatrixMarket::Array(Array {
+                shape: vec![2, 2],
+                elements: floats(&[1.0, 3.0, 2.0, 4.0]),
+            }))
+        );
+
+        let text = "%%MatrixMarket matrix array integer skew-symmetric\n3 3\n1 2 3\n";
+        assert_eq!(
+            read_matrix_market(text),
+            Some(MatrixMarket::Array(Array {
+                shape: vec![3, 3],
+                elements: integers(&[0, -1, -2, 1, 0, -3, 2, 3, 0]),
+            }))
+        );
+
+        let text = "%%MatrixMarket matrix coordinate pattern symmetric\n3 3 2\n2 1\n3 3\n";
+        assert_eq!(
+            read_matrix_market(text),
+            Some(MatrixMarket::Coordinate(SparseMatrix {
+                rows: 3,
+                columns: 3,
+                entries: vec![
+                    (1, 2, ArrayElement::Integer(Integer::from(1))),
+                    (2, 1, ArrayElement::Integer(Integer::from(1))),
+                    (3, 3, ArrayElement::Integer(Integer::from(1))),
+                ],
+            }))
+        );
+
+        let text = "%%MatrixMarket matrix coordinate complex hermitian\n2 2 1\n2 1 1.5 2\n";
+        let Some(MatrixMarket::Coordinate(matrix)) = read_matrix_market(text) else {
+            panic!();
+        };
+        assert_eq!(
+            matrix.entries,
+            [
+                (1, 2, ArrayElement::Complex(1.5, -2.0)),
+                (2, 1, ArrayElement::Complex(1.5, 2.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_matrix_market() {
+        assert_eq!(read_matrix_market("2 2\n1 2 3 4\n"), None);
+        assert_eq!(
+            read_matrix_market("%%MatrixMarket matrix coordinate real general\n2 2 1\n3 1 1.0\n"),
+            None
+        );
+        assert_eq!(
+            read_matrix_market("%%MatrixMarket matrix array real general\n2 2\n1 2 3\n"),
+            None
+        );
+        assert_eq!(
+            read_matrix_market("%%MatrixMarket matrix array real general\n1 1\n1 2\n"),
+            None
+        );
+    }
+}
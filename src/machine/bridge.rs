@@ -0,0 +1,137 @@
+use crate::atom_table::*;
+use crate::machine::config::*;
+use crate::machine::lib_machine::*;
+use crate::machine::Machine;
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static BRIDGED_MACHINE_COUNT: AtomicUsize = AtomicUsize::new(0);
+static BRIDGED_QUERY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) enum BridgedAnswer {
+    Answer(Term),
+    // the answer after which the query has no choice points left.
+    Last(Term),
+    Exception(Term),
+}
+
+// a machine queried by another in the same thread, see
+// library(machines). Terms cross between the machines through the
+// Term type of the library interface.
+pub(crate) enum BridgedMachine {
+    Idle(Box<Machine>),
+    Querying(usize, Box<Machine>, Box<QueryCursor>),
+    // only while changing states.
+    Taken,
+}
+
+impl fmt::Debug for BridgedMachine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BridgedMachine::Idle(_) => write!(f, "BridgedMachine::Idle"),
+            BridgedMachine::Querying(query_id, ..) => {
+                write!(f, "BridgedMachine::Querying({query_id})")
+            }
+            BridgedMachine::Taken => write!(f, "BridgedMachine::Taken"),
+        }
+    }
+}
+
+impl BridgedMachine {
+    pub(crate) fn new(atom_tbl: &AtomTable) -> (Atom, Self) {
+        let count = BRIDGED_MACHINE_COUNT.fetch_add(1, Ordering::Relaxed);
+        let name = AtomTable::build_with(atom_tbl, &format!("$machine_{count}"));
+
+        let streams = StreamConfig::in_memory()
+            .with_user_output(OutputStreamConfig::stdout())
+            .with_user_error(OutputStreamConfig::stderr());
+
+        let machine = MachineBuilder::new().with_streams(streams).build();
+
+        (name, BridgedMachine::Idle(Box::new(machine)))
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        matches!(self, BridgedMachine::Idle(_))
+    }
+
+    pub(crate) fn consult(&mut self, program: String) -> bool {
+        match self {
+            BridgedMachine::Idle(machine) => {
+                machine.consult_module_string("user", program);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // starts a query if the machine is idle, returning its id.
+    pub(crate) fn start_query(&mut self, query: String) -> Option<usize> {
+        match std::mem::replace(self, BridgedMachine::Taken) {
+            BridgedMachine::Idle(mut machine) => {
+                let query_id = BRIDGED_QUERY_COUNT.fetch_add(1, Ordering::Relaxed);
+                let cursor = machine.start_query(query, QueryOptions::default());
+
+                *self = BridgedMachine::Querying(query_id, machine, Box::new(cursor));
+                Some(query_id)
+            }
+            state => {
+                *self = state;
+                None
+            }
+        }
+    }
+
+    // the next answer of the query with the given id: the binding of
+    // its variable Answer, or the exception it threw. the query is
+    // closed after its last answer. None if the query has no more
+    // answers or isn't running.
+    pub(crate) fn next_answer(&mut self, query_id: usize) -> Option<BridgedAnswer> {
+        let answer = match self {
+            BridgedMachine::Querying(id, machine, cursor) if *id == query_id => {
+                cursor.next_answer(machine)
+            }
+            _ => return None,
+        };
+
+        let term = match answer {
+            Some(Ok(LeafAnswer::True)) => Term::Var("_".into()),
+            Some(Ok(LeafAnswer::LeafAnswer { mut bindings, .. })) => bindings
+                .remove("Answer")
+                .unwrap_or_else(|| Term::Var("_".into())),
+            Some(Ok(LeafAnswer::Exception(term)) | Err(term)) => {
+                self.close_query(query_id);
+                return Some(BridgedAnswer::Exception(term));
+            }
+            Some(Ok(LeafAnswer::False)) | None => {
+                self.close_query(query_id);
+                return None;
+            }
+        };
+
+        let is_last = matches!(
+            self,
+            BridgedMachine::Querying(_, machine, cursor) if cursor.is_exhausted(machine)
+        );
+
+        if is_last {
+            self.close_query(query_id);
+            Some(BridgedAnswer::Last(term))
+        } else {
+            Some(BridgedAnswer::Answer(term))
+        }
+    }
+
+    pub(crate) fn close_query(&mut self, query_id: usize) {
+        match std::mem::replace(self, BridgedMachine::Taken) {
+            BridgedMachine::Querying(id, mut machine, _) if id == query_id => {
+                machine.trust_me();
+                *self = BridgedMachine::Idle(machine);
+            }
+            state => {
+                *self = state;
+            }
+        }
+    }
+}
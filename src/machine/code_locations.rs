@@ -0,0 +1,78 @@
+use crate::atom_table::*;
+use crate::machine::stack::*;
+
+use std::collections::BTreeMap;
+
+// the file and line at which a clause consulted from a file starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ClauseLocation {
+    pub(crate) file: Atom,
+    pub(crate) line: usize,
+}
+
+// the debug-info table of the code: the locations of the clauses
+// consulted from files, keyed by the code offsets at which their code
+// starts. the offset at which the code of a run of located clauses
+// ends maps to None, so that code compiled after it, from queries,
+// asserts and libraries, isn't located.
+#[derive(Debug, Default)]
+pub(crate) struct CodeLocations {
+    starts: BTreeMap<usize, Option<ClauseLocation>>,
+}
+
+impl CodeLocations {
+    // records the code of clauses starting at the given offsets and
+    // ending at end. the code only grows, so the end of the previous
+    // run is overwritten by the start of the next if they meet.
+    pub(crate) fn record(
+        &mut self,
+        starts: impl IntoIterator<Item = (usize, ClauseLocation)>,
+        end: usize,
+    ) {
+        let mut is_located = false;
+
+        for (start, location) in starts {
+            self.starts.insert(start, Some(location));
+            is_located = true;
+        }
+
+        if is_located {
+            self.starts.insert(end, None);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, p: usize) -> Option<ClauseLocation> {
+        self.starts
+            .range(..=p)
+            .next_back()
+            .and_then(|(_, location)| *location)
+    }
+
+    // the location of the innermost clause with a location among that
+    // running at p and those with an activation in the continuation
+    // chain.
+    pub(crate) fn innermost(
+        &self,
+        p: usize,
+        cp: usize,
+        mut e: usize,
+        stack: &Stack,
+    ) -> Option<ClauseLocation> {
+        if let Some(location) = self.get(p).or_else(|| self.get(cp)) {
+            return Some(location);
+        }
+
+        while e != 0 {
+            let frame = stack.index_and_frame(e);
+
+            if let Some(location) = self.get(frame.prelude.cp) {
+                return Some(location);
+            }
+
+            e = frame.prelude.e;
+        }
+
+        None
+    }
+}
@@ -1,8 +1,12 @@
 use crate::atom_table::*;
 use crate::codegen::*;
 use crate::forms::*;
-use crate::indexing::{merge_clause_index, remove_index};
+use crate::indexing::{
+    merge_clause_index, remove_dynamic_subindexed_clause, remove_index, DynamicSubkeysLookup,
+};
 use crate::instructions::*;
+use crate::machine::compile_workers::*;
+use crate::machine::index_cache::*;
 use crate::machine::load_state::*;
 use crate::machine::loader::*;
 use crate::machine::machine_errors::*;
@@ -11,11 +15,30 @@
 use crate::machine::*;
 use crate::parser::ast::*;
 
+use fxhash::FxBuildHasher;
+use indexmap::IndexMap;
+
 use std::cell::Cell;
 use std::collections::VecDeque;
 use std::mem;
 use std::ops::Range;
 
+// the fewest clauses of a static predicate compiled on the compile
+// workers, below which passing them to a worker costs more than it
+// saves.
+const MIN_CLAUSES_COMPILED_ON_WORKERS: usize = 8;
+
+// a static predicate whose code is being generated by a compile worker.
+pub(crate) struct PendingCompilation {
+    key: PredicateKey,
+    code_idx: CodeIndex,
+    // the clause locations and line of the predicate, its clauses
+    // having been passed to the worker.
+    predicates: PredicateQueue,
+    settings: CodeGenSettings,
+    codegen: PendingCodegen,
+}
+
 struct StandaloneCompileResult {
     clause_code: Code,
     standalone_skeleton: PredicateSkeleton,
@@ -130,6 +153,7 @@ fn merge_indices(
                 retracted_dynamic_clauses,
                 clause_loc,
                 AppendOrPrepend::Append,
+                None,
             );
 
             retraction_info.push_record(RetractionRecord::AddedIndex(
@@ -142,6 +166,53 @@ fn merge_indices(
     }
 }
 
+// merges the index of the clause at new_clause_loc into the indexing
+// code at index_loc, indexing the clauses of a dynamic predicate
+// further by their subkeys.
+fn merge_clause_index_at(
+    code: &mut Code,
+    index_loc: usize,
+    clauses: &mut [ClauseIndexInfo],
+    retracted_dynamic_clauses: &Option<Vec<ClauseIndexInfo>>,
+    clause_subkeys: &IndexMap<usize, DynamicSubkeys, FxBuildHasher>,
+    new_clause_loc: usize,
+    append_or_prepend: AppendOrPrepend,
+) {
+    let mut indexing_code = mem::take(code[index_loc].to_indexing_line_mut().unwrap());
+
+    let lookup = DynamicSubkeysLookup::Installed {
+        code: &code[..],
+        index_loc,
+        clause_subkeys,
+    };
+
+    merge_clause_index(
+        &mut indexing_code,
+        clauses,
+        retracted_dynamic_clauses,
+        new_clause_loc,
+        append_or_prepend,
+        Some(&lookup),
+    );
+
+    *code[index_loc].to_indexing_line_mut().unwrap() = indexing_code;
+}
+
+// moves the subkeys of the clause of skeleton at clause_index to the
+// skeleton, by the location of its inner choice instruction.
+fn install_dynamic_subkeys(code: &Code, skeleton: &mut PredicateSkeleton, clause_index: usize) {
+    let clause_index_info = &mut skeleton.clauses[clause_index];
+
+    if let Some(index_loc) = clause_index_info.opt_arg_index_key.switch_on_term_loc() {
+        if let Some(subkeys) = clause_index_info.subkeys.take() {
+            let clause_loc =
+                find_inner_choice_instr(code, clause_index_info.clause_start, index_loc);
+
+            skeleton.clause_subkeys.insert(clause_loc, *subkeys);
+        }
+    }
+}
+
 fn find_outer_choice_instr(code: &Code, mut index: usize) -> usize {
     loop {
         match &code[index] {
@@ -353,6 +424,7 @@ fn delete_from_skeleton(
     }
 
     if skeleton.core.is_dynamic {
+        skeleton.clause_store.remove(clause_clause_loc);
         skeleton
             .core
             .add_retracted_dynamic_clause_info(clause_index_info);
@@ -884,12 +956,16 @@ fn prepend_compiled_clause(
                 // N_2 | RevJmpBy (RetryMeElse(M_1) or TryMeElse(0) at index_loc + 1)
                 // N_3 | RevJmpBy (TryMeElse(N_1) at index_loc - 1 or TrustMe if N_1 == 0)
 
-                let target_indexing_line = code[index_loc].to_indexing_line_mut().unwrap();
+                if let Some(subkeys) = skeleton.clauses[0].subkeys.take() {
+                    skeleton.clause_subkeys.insert(clause_loc + 2, *subkeys);
+                }
 
-                merge_clause_index(
-                    target_indexing_line,
+                merge_clause_index_at(
+                    code,
+                    index_loc,
                     skeleton.clauses.make_contiguous(),
                     &skeleton.core.retracted_dynamic_clauses,
+                    &skeleton.clause_subkeys,
                     clause_loc + 2, // == skeleton.clauses[0].clause_start
                     AppendOrPrepend::Prepend,
                 );
@@ -1022,6 +1098,8 @@ fn prepend_compiled_clause(
         }
     };
 
+    install_dynamic_subkeys(code, skeleton, 0);
+
     if skeleton.core.is_dynamic {
         IndexPtr::dynamic_index(clause_loc)
     } else {
@@ -1074,12 +1152,16 @@ fn append_compiled_clause(
                 skeleton.clauses[target_pos].clause_start,
             ));
 
-            let target_indexing_line = code[index_loc].to_indexing_line_mut().unwrap();
+            if let Some(subkeys) = skeleton.clauses[target_pos].subkeys.take() {
+                skeleton.clause_subkeys.insert(clause_loc, *subkeys);
+            }
 
-            merge_clause_index(
-                target_indexing_line,
+            merge_clause_index_at(
+                code,
+                index_loc,
                 &mut skeleton.clauses.make_contiguous()[lower_bound..],
                 &skeleton.core.retracted_dynamic_clauses,
+                &skeleton.clause_subkeys,
                 clause_loc,
                 AppendOrPrepend::Append,
             );
@@ -1129,6 +1211,8 @@ fn append_compiled_clause(
                 if !skeleton.core.is_dynamic {
                     set_switch_var_offset(code, index_loc, 2, retraction_info);
                 }
+
+                install_dynamic_subkeys(code, skeleton, target_pos);
             }
 
             match skeleton.clauses[lower_bound]
@@ -1182,27 +1266,35 @@ fn mergeable_indexed_subsequences(
     false
 }
 
-fn print_overwrite_warning(
-    compilation_target: &CompilationTarget,
+// the style warning of compiling key over code_ptr, if the compiled
+// clauses replace those already there.
+fn overwrite_warning(
+    compilation_target: CompilationTarget,
     code_ptr: IndexPtr,
     key: PredicateKey,
     is_dynamic: bool,
-) {
+    is_compiled_by_load: bool,
+    style_check: StyleCheck,
+) -> Option<StyleWarning> {
     if let CompilationTarget::Module(atom!("builtins") | atom!("loader")) = compilation_target {
-        return;
+        return None;
     }
 
     match code_ptr.tag() {
-        IndexPtrTag::DynamicUndefined | IndexPtrTag::Undefined => return,
-        _ if is_dynamic => return,
+        IndexPtrTag::DynamicUndefined | IndexPtrTag::Undefined => return None,
+        _ if is_dynamic => return None,
         _ => {}
     }
 
-    println!(
-        "% Warning: overwriting {}/{} because the clauses are discontiguous",
-        key.0.as_str(),
-        key.1
-    );
+    if is_compiled_by_load {
+        style_check
+            .discontiguous
+            .then_some(StyleWarning::Discontiguous(compilation_target, key))
+    } else {
+        style_check
+            .redefined
+            .then_some(StyleWarning::Redefined(compilation_target, key))
+    }
 }
 
 impl<'a, LS: LoadState<'a>> Loader<'a, LS> {
@@ -1244,6 +1336,21 @@ fn compile_standalone_clause(
         })
     }
 
+    fn preprocess_predicate(
+        &mut self,
+        predicates: &mut PredicateQueue,
+        settings: CodeGenSettings,
+    ) -> Result<Vec<PredicateClause>, SessionError> {
+        let mut clauses = vec![];
+        let mut preprocessor = Preprocessor::new(settings);
+
+        for term in predicates.predicates.drain(0..) {
+            clauses.push(preprocessor.try_term_to_tl(self, term)?);
+        }
+
+        Ok(clauses)
+    }
+
     fn compile(
         &mut self,
         key: PredicateKey,
@@ -1254,25 +1361,180 @@ fn compile(
 
         LS::err_on_builtin_overwrite(self, key)?;
 
-        let code_len = self.wam_prelude.code.len();
-        let mut code_ptr = code_len;
+        let clauses = self.preprocess_predicate(&mut predicates, settings)?;
+        let index_cache = self.take_cached_indices(key, predicates.compilation_target, settings);
+        let f64_tbl = &LS::machine_st(&mut self.payload).arena.f64_tbl;
 
-        let mut clauses = vec![];
-        let mut preprocessor = Preprocessor::new(settings);
+        let mut cg = CodeGenerator::new(f64_tbl, settings);
+        cg.index_cache = index_cache;
 
-        for term in predicates.predicates.drain(0..) {
-            clauses.push(preprocessor.try_term_to_tl(self, term)?);
+        let code = cg.compile_predicate(clauses)?;
+
+        let compiled = CompiledPredicate {
+            code,
+            skeleton: cg.skeleton,
+            clause_starts: cg.clause_starts,
+            index_cache: cg.index_cache,
+        };
+
+        self.install_compiled_predicate(key, code_idx, predicates, settings, compiled);
+        Ok(code_idx)
+    }
+
+    // compiles the static predicate key on a compile worker, leaving
+    // its code to be installed by complete_compilations.
+    fn compile_on_worker(
+        &mut self,
+        key: PredicateKey,
+        mut predicates: PredicateQueue,
+        settings: CodeGenSettings,
+    ) -> Result<(), SessionError> {
+        let code_idx = self.get_or_insert_code_index(key, predicates.compilation_target);
+
+        LS::err_on_builtin_overwrite(self, key)?;
+
+        let clauses = self.preprocess_predicate(&mut predicates, settings)?;
+        let index_cache = self.take_cached_indices(key, predicates.compilation_target, settings);
+        let machine_st = LS::machine_st(&mut self.payload);
+
+        let codegen = match &machine_st.compile_workers {
+            Some(compile_workers) => {
+                compile_workers.submit(clauses, settings, &machine_st.arena.f64_tbl, index_cache)
+            }
+            None => unreachable!("predicates are compiled on workers only if there are some"),
+        };
+
+        self.payload.pending_compilations.push(PendingCompilation {
+            key,
+            code_idx,
+            predicates,
+            settings,
+            codegen,
+        });
+
+        Ok(())
+    }
+
+    // the indexing code cached for the static predicate key of the file
+    // being loaded, if the index cache is enabled.
+    fn take_cached_indices(
+        &mut self,
+        key: PredicateKey,
+        compilation_target: CompilationTarget,
+        settings: CodeGenSettings,
+    ) -> Option<PredicateIndices> {
+        if settings.is_extensible || LS::machine_st(&mut self.payload).index_cache.is_none() {
+            return None;
         }
 
-        let f64_tbl = &LS::machine_st(&mut self.payload).arena.f64_tbl;
+        let source = self.wam_prelude.load_contexts.last()?.path.clone();
 
-        let mut cg = CodeGenerator::new(f64_tbl, settings);
-        let mut code = cg.compile_predicate(clauses)?;
+        if !source.is_file() {
+            return None;
+        }
+
+        let machine_st = LS::machine_st(&mut self.payload);
+        let index_cache = machine_st.index_cache.as_mut()?;
+
+        Some(index_cache.take_indices(
+            &machine_st.atom_tbl,
+            &source,
+            compilation_target.module_name(),
+            key,
+        ))
+    }
+
+    // writes the index file of the file being loaded, once its
+    // predicates are compiled.
+    pub(super) fn save_index_cache(&mut self) {
+        let Some(load_context) = self.wam_prelude.load_contexts.last() else {
+            return;
+        };
+
+        if let Some(index_cache) = LS::machine_st(&mut self.payload).index_cache.as_mut() {
+            index_cache.save(&load_context.path);
+        }
+    }
+
+    // installs the code of the predicates compiled on workers, in the
+    // order they were submitted.
+    pub(super) fn complete_compilations(&mut self) -> Result<(), SessionError> {
+        if self.payload.pending_compilations.is_empty() {
+            return Ok(());
+        }
+
+        let pending_compilations = mem::take(&mut self.payload.pending_compilations);
+        let mut result = Ok(());
+
+        // every compilation is awaited, even after an error, so that
+        // no worker holds the f64 table once they're done.
+        for pending in pending_compilations {
+            match (pending.codegen.wait(), &result) {
+                (Ok(compiled), Ok(())) => {
+                    self.install_compiled_predicate(
+                        pending.key,
+                        pending.code_idx,
+                        pending.predicates,
+                        pending.settings,
+                        compiled,
+                    );
+
+                    self.add_to_listing_src_module(pending.key, pending.code_idx);
+                }
+                (Err(err), Ok(())) => {
+                    result = Err(SessionError::from(err));
+                }
+                (_, Err(_)) => {}
+            }
+        }
+
+        result
+    }
+
+    fn install_compiled_predicate(
+        &mut self,
+        key: PredicateKey,
+        code_idx: CodeIndex,
+        mut predicates: PredicateQueue,
+        settings: CodeGenSettings,
+        mut compiled: CompiledPredicate,
+    ) {
+        let code_len = self.wam_prelude.code.len();
+        let mut code_ptr = code_len;
+        let mut code = compiled.code;
+
+        let clause_locations: Vec<_> = compiled
+            .clause_starts
+            .iter()
+            .zip(predicates.locations.drain(0..))
+            .filter_map(|(start, location)| Some((code_len + start, location?)))
+            .collect();
+
+        if let Some(indices) = compiled.index_cache.take() {
+            if let Some(index_cache) = LS::machine_st(&mut self.payload).index_cache.as_mut() {
+                index_cache.store_indices(indices);
+            }
+        }
 
         if settings.is_extensible {
             let mut clause_clause_locs = VecDeque::new();
 
-            for clause_index_info in cg.skeleton.clauses.iter_mut() {
+            for clause_index_info in compiled.skeleton.clauses.iter_mut() {
+                if let Some(index_loc) = clause_index_info.opt_arg_index_key.switch_on_term_loc() {
+                    if let Some(subkeys) = clause_index_info.subkeys.take() {
+                        let clause_loc = find_inner_choice_instr(
+                            &code,
+                            clause_index_info.clause_start,
+                            index_loc,
+                        );
+
+                        compiled
+                            .skeleton
+                            .clause_subkeys
+                            .insert(code_len + clause_loc, *subkeys);
+                    }
+                }
+
                 clause_index_info.clause_start += code_len;
                 clause_index_info.opt_arg_index_key += code_len;
 
@@ -1291,7 +1553,20 @@ fn compile(
                 Some(skeleton) => {
                     let skeleton_clause_len = skeleton.clauses.len();
 
-                    skeleton.clauses.extend(cg.skeleton.clauses);
+                    if skeleton.core.is_dynamic {
+                        for (clause_index_info, clause_loc) in
+                            compiled.skeleton.clauses.iter().zip(&clause_clause_locs)
+                        {
+                            skeleton
+                                .clause_store
+                                .push_back(*clause_loc, clause_index_info.first_arg_key);
+                        }
+                    }
+
+                    skeleton.clauses.extend(compiled.skeleton.clauses);
+                    skeleton
+                        .clause_subkeys
+                        .extend(compiled.skeleton.clause_subkeys);
                     skeleton
                         .core
                         .clause_clause_locs
@@ -1306,14 +1581,19 @@ fn compile(
                     );
                 }
                 None => {
-                    cg.skeleton
+                    compiled
+                        .skeleton
                         .core
                         .clause_clause_locs
                         .extend(&clause_clause_locs.make_contiguous()[0..]);
 
-                    let mut skeleton = cg.skeleton;
+                    let mut skeleton = compiled.skeleton;
                     skeleton.core.is_dynamic = settings.is_dynamic();
 
+                    if skeleton.core.is_dynamic {
+                        skeleton.rebuild_clause_store();
+                    }
+
                     self.add_extensible_predicate(key, skeleton, predicates.compilation_target);
                 }
             };
@@ -1330,12 +1610,21 @@ fn compile(
             .code_index_tbl
             .get_entry(code_idx.into());
 
-        print_overwrite_warning(
-            &predicates.compilation_target,
+        let is_compiled_by_load = !self
+            .payload
+            .compiled_predicates
+            .insert((predicates.compilation_target, key));
+
+        if let Some(warning) = overwrite_warning(
+            predicates.compilation_target,
             index_ptr,
             key,
             settings.is_dynamic(),
-        );
+            is_compiled_by_load,
+            LS::machine_st(&mut self.payload).flags.style_check,
+        ) {
+            self.payload.style_warnings.push((warning, predicates.line));
+        }
 
         let index_ptr = if settings.is_dynamic() {
             IndexPtr::dynamic_index(code_ptr)
@@ -1351,8 +1640,11 @@ fn compile(
             index_ptr,
         );
 
+        LS::machine_st(&mut self.payload)
+            .code_locations
+            .record(clause_locations, code_len + code.len());
+
         self.wam_prelude.code.extend(code);
-        Ok(code_idx)
     }
 
     fn extend_local_predicate_skeleton(
@@ -1503,7 +1795,9 @@ pub(super) fn incremental_compile_clause(
                     non_counted_bt,
                 };
 
-                let mut predicate_queue = predicate_queue![clause];
+                let mut predicate_queue = predicate_queue![];
+
+                predicate_queue.push(clause, None);
                 predicate_queue.compilation_target = compilation_target;
 
                 return self.compile(key, predicate_queue, settings);
@@ -1532,6 +1826,12 @@ pub(super) fn incremental_compile_clause(
             AppendOrPrepend::Append => {
                 let clause_index_info = standalone_skeleton.clauses.pop_back().unwrap();
 
+                if skeleton.core.is_dynamic {
+                    skeleton
+                        .clause_store
+                        .push_back(code_len, clause_index_info.first_arg_key);
+                }
+
                 skeleton.clauses.push_back(clause_index_info);
                 skeleton.core.clause_clause_locs.push_back(code_len);
 
@@ -1570,6 +1870,13 @@ pub(super) fn incremental_compile_clause(
             }
             AppendOrPrepend::Prepend => {
                 let clause_index_info = standalone_skeleton.clauses.pop_back().unwrap();
+
+                if skeleton.core.is_dynamic {
+                    skeleton
+                        .clause_store
+                        .push_front(code_len, clause_index_info.first_arg_key);
+                }
+
                 skeleton.clauses.push_front(clause_index_info);
 
                 skeleton.core.clause_clause_locs.push_front(code_len);
@@ -1643,6 +1950,32 @@ pub(super) fn retract_dynamic_clause(&mut self, key: PredicateKey, target_pos: u
             _ => unreachable!(),
         }
 
+        if let Some(index_loc) = skeleton.clauses[target_pos]
+            .opt_arg_index_key
+            .switch_on_term_loc()
+        {
+            if let Some(subkeys) = skeleton.clause_subkeys.swap_remove(&clause_loc) {
+                let iterated_lines =
+                    LS::machine_st(&mut self.payload).iterated_indexing_lines(index_loc);
+
+                let indexing_code = self.wam_prelude.code[index_loc]
+                    .to_indexing_line_mut()
+                    .unwrap();
+
+                remove_dynamic_subindexed_clause(
+                    indexing_code,
+                    &skeleton.clauses[target_pos].opt_arg_index_key,
+                    clause_loc - index_loc + 1,
+                    &subkeys,
+                    |line| {
+                        iterated_lines
+                            .as_ref()
+                            .is_none_or(|lines| lines.contains(&(line as u32)))
+                    },
+                );
+            }
+        }
+
         delete_from_skeleton(
             self.payload.compilation_target,
             key,
@@ -2056,20 +2389,26 @@ pub(super) fn compile_clause_clauses<ClauseIter: Iterator<Item = (Term, Term)>>(
             .get_predicate_skeleton_mut(&clause_clause_compilation_target, &(atom!("$clause"), 2))
         {
             Some(skeleton) if append_or_prepend.is_append() => {
+                let mut old_locs = vec![];
+
                 for _ in 0..num_clause_predicates {
-                    skeleton.core.clause_clause_locs.pop_back();
+                    old_locs.extend(skeleton.core.clause_clause_locs.pop_back());
                 }
 
-                for loc in locs_vec {
+                for (loc, old_loc) in locs_vec.into_iter().zip(old_locs.into_iter().rev()) {
+                    skeleton.clause_store.relocate(old_loc, loc);
                     skeleton.core.clause_clause_locs.push_back(loc);
                 }
             }
             Some(skeleton) => {
+                let mut old_locs = vec![];
+
                 for _ in 0..num_clause_predicates {
-                    skeleton.core.clause_clause_locs.pop_front();
+                    old_locs.extend(skeleton.core.clause_clause_locs.pop_front());
                 }
 
-                for loc in locs_vec.into_iter().rev() {
+                for (loc, old_loc) in locs_vec.into_iter().zip(old_locs).rev() {
+                    skeleton.clause_store.relocate(old_loc, loc);
                     skeleton.core.clause_clause_locs.push_front(loc);
                 }
             }
@@ -2104,6 +2443,29 @@ pub(super) fn compile_clause_clauses<ClauseIter: Iterator<Item = (Term, Term)>>(
         Ok(())
     }
 
+    // adds the code of key at code_idx to the module of the file being
+    // loaded, if the file has no module declaration.
+    fn add_to_listing_src_module(&mut self, key: PredicateKey, code_idx: CodeIndex) {
+        if let Some(filename) = self.listing_src_file_name() {
+            if let Some(ref mut module) = self.wam_prelude.indices.modules.get_mut(&filename) {
+                let index_ptr = LS::machine_st(&mut self.payload)
+                    .arena
+                    .code_index_tbl
+                    .get_entry(code_idx.into());
+
+                let code_idx = *module.code_dir.entry(key).or_insert(code_idx);
+
+                set_code_index::<LS>(
+                    &mut self.payload,
+                    &CompilationTarget::Module(filename),
+                    key,
+                    code_idx,
+                    index_ptr,
+                );
+            }
+        }
+    }
+
     pub(super) fn compile_and_submit(&mut self) -> Result<(), SessionError> {
         let key = self
             .payload
@@ -2115,6 +2477,16 @@ pub(super) fn compile_and_submit(&mut self) -> Result<(), SessionError> {
             })
             .ok_or(SessionError::NamelessEntry)?;
 
+        // the clauses of a predicate still compiling on a worker are
+        // discontiguous, and are compiled over its installed code.
+        let compilation_target = self.payload.predicates.compilation_target;
+
+        if self.payload.pending_compilations.iter().any(|pending| {
+            pending.key == key && pending.predicates.compilation_target == compilation_target
+        }) {
+            self.complete_compilations()?;
+        }
+
         let listing_src_file_name = self.listing_src_file_name();
 
         // payload_compilation_target describes the compilation context,
@@ -2162,7 +2534,9 @@ pub(super) fn compile_and_submit(&mut self) -> Result<(), SessionError> {
         if predicate_info.compile_incrementally() {
             let predicates = self.payload.predicates.take();
 
-            for term in predicates.predicates {
+            for (term, location) in predicates.predicates.into_iter().zip(predicates.locations) {
+                let code_len = self.wam_prelude.code.len();
+
                 self.incremental_compile_clause(
                     key,
                     term,
@@ -2170,6 +2544,12 @@ pub(super) fn compile_and_submit(&mut self) -> Result<(), SessionError> {
                     non_counted_bt,
                     AppendOrPrepend::Append,
                 )?;
+
+                let code_end = self.wam_prelude.code.len();
+
+                LS::machine_st(&mut self.payload)
+                    .code_locations
+                    .record(location.map(|location| (code_len, location)), code_end);
             }
         } else {
             if is_cross_module_clause && !local_predicate_info.is_extensible {
@@ -2225,25 +2605,16 @@ pub(super) fn compile_and_submit(&mut self) -> Result<(), SessionError> {
             };
 
             let predicates = self.payload.predicates.take();
-            let offset = self.compile(key, predicates, settings)?;
-
-            if let Some(filename) = self.listing_src_file_name() {
-                if let Some(ref mut module) = self.wam_prelude.indices.modules.get_mut(&filename) {
-                    let index_ptr = LS::machine_st(&mut self.payload)
-                        .arena
-                        .code_index_tbl
-                        .get_entry(offset.into());
 
-                    let offset = *module.code_dir.entry(key).or_insert(offset);
+            let compile_on_worker = !settings.is_extensible
+                && predicates.len() >= MIN_CLAUSES_COMPILED_ON_WORKERS
+                && LS::machine_st(&mut self.payload).compile_workers.is_some();
 
-                    set_code_index::<LS>(
-                        &mut self.payload,
-                        &CompilationTarget::Module(filename),
-                        key,
-                        offset,
-                        index_ptr,
-                    );
-                }
+            if compile_on_worker {
+                self.compile_on_worker(key, predicates, settings)?;
+            } else {
+                let offset = self.compile(key, predicates, settings)?;
+                self.add_to_listing_src_module(key, offset);
             }
         }
 
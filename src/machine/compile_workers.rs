@@ -0,0 +1,216 @@
+use crate::codegen::*;
+use crate::forms::*;
+use crate::instructions::*;
+use crate::iterators::*;
+use crate::machine::index_cache::*;
+use crate::machine::machine_errors::*;
+use crate::offset_table::*;
+use crate::parser::ast::*;
+
+use ordered_float::OrderedFloat;
+
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, SendError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+// the code generated for the clauses of a predicate by a worker.
+pub(crate) struct CompiledPredicate {
+    pub(crate) code: Code,
+    pub(crate) skeleton: PredicateSkeleton,
+    pub(crate) clause_starts: Vec<usize>,
+    pub(crate) index_cache: Option<PredicateIndices>,
+}
+
+type CodegenResult = Result<CompiledPredicate, CompilationError>;
+
+// the floats of the clauses of a job, copied from the f64 table of the
+// machine, which isn't shared with the workers.
+#[derive(Debug)]
+struct JobFloats(HashMap<usize, OrderedFloat<f64>>);
+
+impl JobFloats {
+    fn new(clauses: &[PredicateClause], f64_tbl: &F64Table) -> Self {
+        let mut floats = HashMap::new();
+
+        let mut copy_floats = |term: &Term| {
+            for term_ref in post_order_iter(term) {
+                if let TermRef::Literal(_, _, &Literal::F64Offset(offset)) = term_ref {
+                    floats.insert(usize::from(offset), f64_tbl.get_entry(offset));
+                }
+            }
+        };
+
+        for clause in clauses {
+            match clause {
+                PredicateClause::Fact(fact, _) => copy_floats(&fact.head),
+                PredicateClause::Rule(rule, _) => {
+                    rule.head.1.iter().for_each(&mut copy_floats);
+
+                    for item in ClauseIterator::new(&rule.clauses) {
+                        if let ClauseItem::Chunk { terms } = item {
+                            for term in terms {
+                                if let QueryTerm::Clause(_, _, args, _) = term {
+                                    args.iter().for_each(&mut copy_floats);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        JobFloats(floats)
+    }
+}
+
+impl F64Entries for JobFloats {
+    #[inline]
+    fn f64_entry(&self, offset: F64Offset) -> OrderedFloat<f64> {
+        self.0[&usize::from(offset)]
+    }
+}
+
+// the clauses of a job, or the code generated for them, whose big
+// integers and rationals are pointers into the arena of the machine
+// submitting the job.
+struct ArenaLiterals<T>(T);
+
+// SAFETY: the arena pointers of the literals are all that keeps clauses
+// and their code from being Send. big integers and rationals aren't
+// changed once allocated and their slabs are freed only with the
+// arena, which MachineState drops after joining its workers. the
+// pointers are never sent anywhere else, since ArenaLiterals is built
+// and taken apart only in this module.
+unsafe impl Send for ArenaLiterals<Vec<PredicateClause>> {}
+unsafe impl Send for ArenaLiterals<CodegenResult> {}
+
+struct CompileJob {
+    clauses: ArenaLiterals<Vec<PredicateClause>>,
+    settings: CodeGenSettings,
+    floats: JobFloats,
+    index_cache: Option<PredicateIndices>,
+    result: Sender<ArenaLiterals<CodegenResult>>,
+}
+
+impl CompileJob {
+    fn run(self) {
+        let CompileJob {
+            clauses: ArenaLiterals(clauses),
+            settings,
+            floats,
+            index_cache,
+            result,
+        } = self;
+
+        let mut cg = CodeGenerator::new(&floats, settings);
+        cg.index_cache = index_cache;
+
+        let compiled = cg.compile_predicate(clauses).map(|code| CompiledPredicate {
+            code,
+            skeleton: cg.skeleton,
+            clause_starts: cg.clause_starts,
+            index_cache: cg.index_cache,
+        });
+
+        // the load waiting on the job may have been dropped.
+        let _ = result.send(ArenaLiterals(compiled));
+    }
+}
+
+// the code generated for a predicate on a worker, awaited by the load
+// that submitted it.
+pub(crate) struct PendingCodegen(Receiver<ArenaLiterals<CodegenResult>>);
+
+impl PendingCodegen {
+    pub(crate) fn wait(self) -> CodegenResult {
+        match self.0.recv() {
+            Ok(ArenaLiterals(result)) => result,
+            Err(_) => panic!("a compilation worker panicked"),
+        }
+    }
+}
+
+// the threads generating the code of the static predicates of loads,
+// set up by MachineBuilder::with_parallel_compilation. the loader
+// reads and preprocesses the clauses on the thread of the machine.
+#[derive(Debug)]
+pub(crate) struct CompileWorkers {
+    jobs: Option<Sender<CompileJob>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl CompileWorkers {
+    pub(crate) fn new() -> Self {
+        let num_workers = std::thread::available_parallelism().map_or(1, usize::from);
+        let (jobs, receiver) = channel::<CompileJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..num_workers)
+            .map(|n| {
+                let receiver = receiver.clone();
+
+                std::thread::Builder::new()
+                    .name(format!("compile_worker_{n}"))
+                    .spawn(move || loop {
+                        let job = match receiver.lock() {
+                            Ok(receiver) => receiver.recv(),
+                            Err(_) => return,
+                        };
+
+                        match job {
+                            Ok(job) => job.run(),
+                            Err(_) => return,
+                        }
+                    })
+                    .expect("compilation workers should spawn")
+            })
+            .collect();
+
+        Self {
+            jobs: Some(jobs),
+            workers,
+        }
+    }
+
+    // generates the code of clauses on a worker. the floats of clauses
+    // are copied from f64_tbl.
+    pub(crate) fn submit(
+        &self,
+        clauses: Vec<PredicateClause>,
+        settings: CodeGenSettings,
+        f64_tbl: &F64Table,
+        index_cache: Option<PredicateIndices>,
+    ) -> PendingCodegen {
+        let (result, receiver) = channel();
+
+        let job = CompileJob {
+            floats: JobFloats::new(&clauses, f64_tbl),
+            clauses: ArenaLiterals(clauses),
+            settings,
+            index_cache,
+            result,
+        };
+
+        if let Some(jobs) = &self.jobs {
+            if let Err(SendError(job)) = jobs.send(job) {
+                // the workers are gone, so the job is run here.
+                job.run();
+            }
+        }
+
+        PendingCodegen(receiver)
+    }
+}
+
+impl Drop for CompileWorkers {
+    fn drop(&mut self) {
+        // closing the channel stops the workers once the jobs sent are
+        // done.
+        self.jobs.take();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
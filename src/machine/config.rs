@@ -1,14 +1,21 @@
 use std::borrow::Cow;
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
 
+use indexmap::IndexMap;
 use rand::{rngs::StdRng, SeedableRng};
 
-use crate::Machine;
+use crate::machine::compile_workers::CompileWorkers;
+use crate::machine::crash_report::CrashReporter;
+use crate::machine::index_cache::IndexCache;
+use crate::parser::ast::Unknown;
+use crate::{LoadProgress, Machine, QueryOptions, Term, TraceAction, TraceEvent};
 
 use super::{
     bootstrapping_compile, current_dir, import_builtin_impls, libraries, load_module, Arena, Atom,
-    Callback, CompilationTarget, IndexStore, ListingSource, MachineArgs, MachineState, Stream,
+    Callback, CompilationTarget, IndexStore, ListingSource, LoadProgressHook, LoadProgressMeter,
+    MachineArgs, MachineState, Stream, TraceHook,
 };
 
 #[derive(Default)]
@@ -114,17 +121,19 @@ pub fn stdin() -> Self {
     pub fn channel() -> (UserInput, Self) {
         let (sender, receiver) = channel();
         (
-            UserInput { inner: sender },
+            UserInput::new(sender),
             Self {
                 inner: InputStreamConfigInner::Channel(receiver),
             },
         )
     }
 
-    fn into_stream(self, arena: &mut Arena, add_history: bool) -> Stream {
+    // output is the user output stream, which the toplevel commands of
+    // the history of stdin write to.
+    fn into_stream(self, arena: &mut Arena, add_history: bool, output: Stream) -> Stream {
         match self.inner {
             InputStreamConfigInner::String(s) => Stream::from_owned_string(s, arena),
-            InputStreamConfigInner::Stdin => Stream::stdin(arena, add_history),
+            InputStreamConfigInner::Stdin => Stream::stdin(arena, add_history, output),
             InputStreamConfigInner::Channel(channel) => Stream::input_channel(channel, arena),
         }
     }
@@ -198,20 +207,29 @@ pub fn with_user_error(self, user_error: OutputStreamConfig) -> Self {
     }
 
     fn into_streams(self, arena: &mut Arena, add_history: bool) -> (Stream, Stream, Stream) {
+        let user_output = self.user_output.into_stream(arena);
+
         (
-            self.user_input.into_stream(arena, add_history),
-            self.user_output.into_stream(arena),
+            self.user_input.into_stream(arena, add_history, user_output),
+            user_output,
             self.user_error.into_stream(arena),
         )
     }
 }
 
-/// A handler for the stdin of the [`Machine`](crate::Machine).
+/// A handler writing to an input stream of the [`Machine`](crate::Machine),
+/// such as its stdin.
 #[derive(Debug)]
 pub struct UserInput {
     inner: Sender<Vec<u8>>,
 }
 
+impl UserInput {
+    pub(crate) fn new(inner: Sender<Vec<u8>>) -> Self {
+        Self { inner }
+    }
+}
+
 impl Write for UserInput {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.inner
@@ -229,6 +247,187 @@ fn flush(&mut self) -> std::io::Result<()> {
 pub struct MachineBuilder {
     pub(crate) streams: StreamConfig,
     pub(crate) toplevel: Cow<'static, str>,
+    pub(crate) trace_hook: Option<TraceHook>,
+    pub(crate) load_progress_hook: Option<LoadProgressHook>,
+    pub(crate) parallel_compilation: bool,
+    pub(crate) index_cache: bool,
+    pub(crate) unknown: UnknownProcedure,
+    pub(crate) library_paths: Vec<PathBuf>,
+    pub(crate) libraries: Vec<(String, String)>,
+    pub(crate) resources: Vec<(String, Cow<'static, [u8]>)>,
+    pub(crate) flags: Vec<(String, Term)>,
+    pub(crate) query_options: QueryOptions,
+    pub(crate) crash_report: Option<PathBuf>,
+}
+
+/// The configuration a [`Machine`](crate::Machine) runs with, as
+/// returned by [`Machine::config`](crate::Machine::config).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineConfig {
+    /// The current `unknown` flag of the machine.
+    pub unknown: UnknownProcedure,
+    /// The directories searched for libraries, as added by
+    /// [`MachineBuilder::with_library_path`].
+    pub library_paths: Vec<PathBuf>,
+    /// Whether code is generated on worker threads, as by
+    /// [`MachineBuilder::with_parallel_compilation`].
+    pub parallel_compilation: bool,
+    /// Whether indices are cached in index files, as by
+    /// [`MachineBuilder::with_index_cache`].
+    pub index_cache: bool,
+    /// The options of the queries run by
+    /// [`Machine::run_query`](crate::Machine::run_query).
+    pub query_options: QueryOptions,
+    /// The flags set by [`MachineBuilder::with_flag`], with their
+    /// current values.
+    pub flags: Vec<(String, Term)>,
+    /// The file crashes are reported to, as by
+    /// [`MachineBuilder::with_crash_report`].
+    pub crash_report: Option<PathBuf>,
+}
+
+/// A `SCRYER_*` environment variable whose value
+/// [`MachineBuilder::from_env`] can't make sense of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvVarError {
+    /// The name of the variable.
+    pub variable: String,
+    /// The value of the variable.
+    pub value: String,
+}
+
+impl std::fmt::Display for EnvVarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid value {:?} of {}", self.value, self.variable)
+    }
+}
+
+impl std::error::Error for EnvVarError {}
+
+/// A Prolog flag [`MachineBuilder::with_flag`] can't set to a value,
+/// because the flag doesn't exist, is read-only or doesn't take the
+/// value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlagError {
+    /// The name of the flag.
+    pub name: String,
+    /// The value of the flag.
+    pub value: Term,
+}
+
+impl std::fmt::Display for FlagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the flag {} can't be set to {}", self.name, self.value)
+    }
+}
+
+impl std::error::Error for FlagError {}
+
+// whether set_prolog_flag/2 of library(builtins) sets the flag name
+// to value.
+fn is_flag_value(name: &str, value: &Term) -> bool {
+    fn is_atom_in(value: &Term, atoms: &[&str]) -> bool {
+        matches!(value, Term::Atom(atom) if atoms.contains(&atom.as_str()))
+    }
+
+    fn is_list(value: &Term) -> Option<&[Term]> {
+        match value {
+            Term::List(items) => Some(items),
+            Term::Atom(atom) if atom == "[]" => Some(&[]),
+            _ => None,
+        }
+    }
+
+    fn is_write_option(option: &Term) -> bool {
+        let Term::Compound(name, args) = option else {
+            return false;
+        };
+
+        match (name.as_str(), &args[..]) {
+            ("double_quotes" | "ignore_ops" | "numbervars" | "quoted", [value]) => {
+                is_atom_in(value, &["true", "false"])
+            }
+            ("module", [Term::Atom(_)]) => true,
+            ("max_depth", [Term::Integer(depth)]) => *depth >= 0.into(),
+            ("variable_names", [names]) => is_list(names).is_some_and(|names| {
+                names.iter().all(|name| {
+                    matches!(name, Term::Compound(eq, args)
+                             if eq == "=" && matches!(args[..], [Term::Atom(_), _]))
+                })
+            }),
+            _ => false,
+        }
+    }
+
+    match name {
+        "bounded" => is_atom_in(value, &["false"]),
+        "integer_rounding_function" => is_atom_in(value, &["toward_zero", "down"]),
+        "double_quotes" => is_atom_in(value, &["chars", "atom", "codes"]),
+        "unknown" => is_atom_in(value, &["error", "warning", "fail"]),
+        "occurs_check" => is_atom_in(value, &["true", "false", "error"]),
+        "answer_write_options" => {
+            is_list(value).is_some_and(|options| options.iter().all(is_write_option))
+        }
+        "answer_variable_names" => is_atom_in(value, &["fabricated", "letters", "internal"]),
+        "answer_duplicates" => is_atom_in(value, &["all", "consecutive", "variant"]),
+        "floundering" => is_atom_in(value, &["false", "warning", "error"]),
+        "error_locations" | "prefer_rationals" => is_atom_in(value, &["true", "false"]),
+        "float_overflow" | "float_zero_div" => is_atom_in(value, &["error", "infinity"]),
+        "float_undefined" => is_atom_in(value, &["error", "nan"]),
+        "rational_precision" => {
+            matches!(value, Term::Integer(precision) if u32::try_from(precision).is_ok_and(|p| p > 0))
+        }
+        "rational_rounding" => is_atom_in(
+            value,
+            &[
+                "half_even",
+                "half_away_from_zero",
+                "toward_zero",
+                "away_from_zero",
+                "down",
+                "up",
+            ],
+        ),
+        "term_hash_algorithm" => is_atom_in(value, &["fxhash", "sha3_256", "blake2b512"]),
+        "shuffle_seed" => {
+            is_atom_in(value, &["false"])
+                || matches!(value, Term::Integer(seed) if u64::try_from(seed).is_ok())
+        }
+        _ => false,
+    }
+}
+
+/// How calls to undefined predicates are handled, as by the `unknown`
+/// Prolog flag.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownProcedure {
+    /// An existence error is thrown.
+    #[default]
+    Error,
+    /// The call fails.
+    Fail,
+    /// The call fails after a warning is printed.
+    Warn,
+}
+
+impl From<UnknownProcedure> for Unknown {
+    fn from(unknown: UnknownProcedure) -> Self {
+        match unknown {
+            UnknownProcedure::Error => Unknown::Error,
+            UnknownProcedure::Fail => Unknown::Fail,
+            UnknownProcedure::Warn => Unknown::Warn,
+        }
+    }
+}
+
+impl From<Unknown> for UnknownProcedure {
+    fn from(unknown: Unknown) -> Self {
+        match unknown {
+            Unknown::Error => UnknownProcedure::Error,
+            Unknown::Fail => UnknownProcedure::Fail,
+            Unknown::Warn => UnknownProcedure::Warn,
+        }
+    }
 }
 
 impl Default for MachineBuilder {
@@ -237,6 +436,17 @@ fn default() -> Self {
         MachineBuilder {
             streams: Default::default(),
             toplevel: default_toplevel().into(),
+            trace_hook: None,
+            load_progress_hook: None,
+            parallel_compilation: false,
+            index_cache: false,
+            unknown: UnknownProcedure::Error,
+            library_paths: vec![],
+            libraries: vec![],
+            resources: vec![],
+            flags: vec![],
+            query_options: QueryOptions::default(),
+            crash_report: None,
         }
     }
 }
@@ -247,6 +457,139 @@ pub fn new() -> Self {
         Default::default()
     }
 
+    /// Creates a default configuration changed by the `SCRYER_*`
+    /// environment variables that are set:
+    ///
+    /// * `SCRYER_LIBRARY_PATH`: directories searched for libraries, as
+    ///   by [`MachineBuilder::with_library_path`], separated as in `PATH`.
+    /// * `SCRYER_UNKNOWN`: the `unknown` flag, `error`, `fail` or
+    ///   `warning`, as by [`MachineBuilder::with_unknown`].
+    /// * `SCRYER_PARALLEL_COMPILATION` and `SCRYER_INDEX_CACHE`: `true`
+    ///   or `false`, as by [`MachineBuilder::with_parallel_compilation`]
+    ///   and [`MachineBuilder::with_index_cache`].
+    /// * `SCRYER_STEP_LIMIT` and `SCRYER_HEAP_LIMIT`: the step and heap
+    ///   limits of the answers of [`Machine::run_query`](crate::Machine::run_query),
+    ///   as in [`QueryOptions`].
+    /// * `SCRYER_FLAGS`: Prolog flags as comma-separated `name=value`
+    ///   pairs, e.g. `double_quotes=codes,occurs_check=error`, with
+    ///   integer or atom values, as by [`MachineBuilder::with_flag`].
+    /// * `SCRYER_CRASH_REPORT`: the file crashes are reported to, as by
+    ///   [`MachineBuilder::with_crash_report`].
+    ///
+    /// An error names the first variable with a value that isn't one of
+    /// those above, including `SCRYER_FLAGS` with a flag that doesn't
+    /// exist or can't be set to its value.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_env() -> Result<Self, EnvVarError> {
+        Self::from_env_vars(|variable| std::env::var(variable).ok())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_env_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, EnvVarError> {
+        fn parse<T>(
+            variable: &str,
+            value: String,
+            parse: impl FnOnce(&str) -> Option<T>,
+        ) -> Result<T, EnvVarError> {
+            parse(value.trim()).ok_or_else(|| EnvVarError {
+                variable: variable.to_string(),
+                value,
+            })
+        }
+
+        fn parse_bool(value: &str) -> Option<bool> {
+            match value {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            }
+        }
+
+        let mut builder = Self::new();
+
+        if let Some(value) = var("SCRYER_LIBRARY_PATH") {
+            for library_path in std::env::split_paths(&value) {
+                if !library_path.as_os_str().is_empty() {
+                    builder = builder.with_library_path(library_path);
+                }
+            }
+        }
+
+        if let Some(value) = var("SCRYER_UNKNOWN") {
+            builder = builder.with_unknown(parse("SCRYER_UNKNOWN", value, |value| match value {
+                "error" => Some(UnknownProcedure::Error),
+                "fail" => Some(UnknownProcedure::Fail),
+                "warning" => Some(UnknownProcedure::Warn),
+                _ => None,
+            })?);
+        }
+
+        if let Some(value) = var("SCRYER_PARALLEL_COMPILATION") {
+            builder = builder.with_parallel_compilation(parse(
+                "SCRYER_PARALLEL_COMPILATION",
+                value,
+                parse_bool,
+            )?);
+        }
+
+        if let Some(value) = var("SCRYER_INDEX_CACHE") {
+            builder = builder.with_index_cache(parse("SCRYER_INDEX_CACHE", value, parse_bool)?);
+        }
+
+        if let Some(value) = var("SCRYER_STEP_LIMIT") {
+            builder.query_options.step_limit = Some(parse("SCRYER_STEP_LIMIT", value, |value| {
+                value.parse().ok()
+            })?);
+        }
+
+        if let Some(value) = var("SCRYER_HEAP_LIMIT") {
+            builder.query_options.heap_limit = Some(parse("SCRYER_HEAP_LIMIT", value, |value| {
+                value.parse().ok()
+            })?);
+        }
+
+        if let Some(value) = var("SCRYER_FLAGS") {
+            let flags = parse("SCRYER_FLAGS", value.clone(), |value| {
+                value
+                    .split(',')
+                    .filter(|flag| !flag.trim().is_empty())
+                    .map(|flag| {
+                        let (name, value) = flag.split_once('=')?;
+                        let (name, value) = (name.trim(), value.trim());
+
+                        if name.is_empty() || value.is_empty() {
+                            return None;
+                        }
+
+                        let value = match value.parse::<i64>() {
+                            Ok(n) => Term::integer(n),
+                            Err(_) => Term::atom(value),
+                        };
+
+                        Some((name.to_string(), value))
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })?;
+
+            for (name, flag_value) in flags {
+                builder = builder
+                    .with_flag(name, flag_value)
+                    .map_err(|_| EnvVarError {
+                        variable: "SCRYER_FLAGS".to_string(),
+                        value: value.clone(),
+                    })?;
+            }
+        }
+
+        if let Some(value) = var("SCRYER_CRASH_REPORT") {
+            if !value.is_empty() {
+                builder = builder.with_crash_report(value);
+            }
+        }
+
+        Ok(builder)
+    }
+
     /// Uses the given `crate::StreamConfig` in this configuration.
     pub fn with_streams(mut self, streams: StreamConfig) -> Self {
         self.streams = streams;
@@ -259,6 +602,149 @@ pub fn with_toplevel(mut self, toplevel: impl Into<Cow<'static, str>>) -> Self {
         self
     }
 
+    /// Calls the given hook at the ports the tracer of `library(trace)`
+    /// shows, in place of writing them to `user_error` and prompting.
+    ///
+    /// The hook decides how the tracer goes on from each port. The
+    /// tracer is switched on by running `use_module(library(trace)), trace`
+    /// or by placing a spy point with `spy/1`.
+    pub fn with_trace_hook(
+        mut self,
+        hook: impl FnMut(&TraceEvent) -> TraceAction + 'static,
+    ) -> Self {
+        self.trace_hook = Some(TraceHook(Box::new(hook)));
+        self
+    }
+
+    /// Calls the given hook as files are loaded, with the files started,
+    /// the clauses loaded and the time elapsed since the outermost load
+    /// started.
+    ///
+    /// The hook is called as each file starts and ends loading, and
+    /// after every thousand clauses, telling a slow load from a stuck
+    /// one. The libraries loaded while building the machine aren't
+    /// reported.
+    pub fn with_load_progress_hook(mut self, hook: impl FnMut(&LoadProgress) + 'static) -> Self {
+        self.load_progress_hook = Some(LoadProgressHook(Box::new(hook)));
+        self
+    }
+
+    /// Generates the code of the static predicates of loaded files on
+    /// worker threads, one per core, while the loader goes on reading
+    /// the clauses that follow them.
+    ///
+    /// This cuts the load times of large programs, e.g. generated fact
+    /// bases. The code of the predicates is installed in the order they
+    /// were read before each directive, term or goal expansion clause,
+    /// and at the end of each file. Off by default.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_parallel_compilation(mut self, parallel_compilation: bool) -> Self {
+        self.parallel_compilation = parallel_compilation;
+        self
+    }
+
+    /// Keeps the indices of the large static predicates of loaded files
+    /// in an index file beside each file, e.g. `facts.pl.idx` for
+    /// `facts.pl`, so that loading the file again reads the indexing
+    /// code of its fact tables rather than computing it.
+    ///
+    /// An index file is written once its file is loaded, and is
+    /// ignored if the file changed since. Off by default.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_index_cache(mut self, index_cache: bool) -> Self {
+        self.index_cache = index_cache;
+        self
+    }
+
+    /// Adds a directory to those searched for `library(Path)` once the
+    /// libraries of the system don't have it, after the directories of
+    /// `user:library_directory/1` and `user:file_search_path(library, Dir)`.
+    ///
+    /// The directories are searched in the order they were added.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_library_path(mut self, library_path: impl Into<PathBuf>) -> Self {
+        self.library_paths.push(library_path.into());
+        self
+    }
+
+    /// Registers the library `name` with the given source, loaded by
+    /// `use_module(library(name))` without touching the filesystem, as
+    /// the libraries of the system are.
+    ///
+    /// The name may have several components, as in `myapp/rules`. The
+    /// libraries of the system take precedence over those registered,
+    /// which take precedence over the directories searched for
+    /// libraries.
+    pub fn with_library(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.libraries.push((name.into(), source.into()));
+        self
+    }
+
+    /// Registers the resource file `name` with the given contents,
+    /// opened for reading by `open(resource(name), read, Stream)`.
+    ///
+    /// With the contents given by `include_bytes!`, the data files of an
+    /// application are embedded in its executable, and don't depend on
+    /// paths that may not exist where the application is run.
+    pub fn with_resource(
+        mut self,
+        name: impl Into<String>,
+        contents: impl Into<Cow<'static, [u8]>>,
+    ) -> Self {
+        self.resources.push((name.into(), contents.into()));
+        self
+    }
+
+    /// Sets the `unknown` flag the machine starts with, i.e. how calls to
+    /// undefined predicates are handled by the modules that don't set
+    /// their own `Module:unknown` flag.
+    pub fn with_unknown(mut self, unknown: UnknownProcedure) -> Self {
+        self.unknown = unknown;
+        self
+    }
+
+    /// Sets the Prolog flag `name` to `value` once the machine is built,
+    /// as [`Machine::set_flag`](crate::Machine::set_flag) does. The
+    /// flags are set in the order they were given, after the `unknown`
+    /// flag of [`MachineBuilder::with_unknown`].
+    ///
+    /// Returns an error if `set_prolog_flag/2` wouldn't set the flag to
+    /// the value.
+    pub fn with_flag(mut self, name: impl Into<String>, value: Term) -> Result<Self, FlagError> {
+        let name = name.into();
+
+        if !is_flag_value(&name, &value) {
+            return Err(FlagError { name, value });
+        }
+
+        self.flags.push((name, value));
+        Ok(self)
+    }
+
+    /// Uses the given options for the queries run by
+    /// [`Machine::run_query`](crate::Machine::run_query).
+    pub fn with_query_options(mut self, query_options: QueryOptions) -> Self {
+        self.query_options = query_options;
+        self
+    }
+
+    /// Reports crashes of the machine to the file at `path`, which is
+    /// overwritten. If a query panics, or the toplevel is brought down
+    /// by an uncaught error, a summary of the machine is written there
+    /// before it goes down: the cause, the last query, the predicates
+    /// of the goal stack, the flags, the memory in use and the loaded
+    /// modules.
+    ///
+    /// Panics are reported from a panic hook, which is installed the
+    /// first time a machine reporting crashes is built and which then
+    /// calls the hook installed before it.
+    ///
+    /// Crashes aren't reported on `wasm32`, which has no file system.
+    pub fn with_crash_report(mut self, path: impl Into<PathBuf>) -> Self {
+        self.crash_report = Some(path.into());
+        self
+    }
+
     /// Builds the [`Machine`](crate::Machine) from this configuration.
     pub fn build(self) -> Machine {
         let args = MachineArgs::new();
@@ -279,6 +765,17 @@ pub fn build(self) -> Machine {
             #[cfg(feature = "ffi")]
             foreign_function_table: Default::default(),
             rng: StdRng::from_entropy(),
+            shuffle_rng: None,
+            bridged_machines: IndexMap::new(),
+            engines: IndexMap::new(),
+            running_engines: vec![],
+            boot_code_end: 0,
+            trace_hook: self.trace_hook,
+            diagnostics: None,
+            load_progress: None,
+            query_options: self.query_options,
+            configured_flags: vec![],
+            crash_reporter: None,
         };
 
         let mut lib_path = current_dir();
@@ -355,6 +852,39 @@ pub fn build(self) -> Machine {
         wam.load_top_level(self.toplevel);
         wam.configure_streams();
 
+        wam.boot_code_end = wam.code.len();
+        wam.load_progress = self.load_progress_hook.map(LoadProgressMeter::new);
+
+        if self.parallel_compilation {
+            wam.machine_st.compile_workers = Some(CompileWorkers::new());
+        }
+
+        if self.index_cache {
+            wam.machine_st.index_cache = Some(IndexCache::default());
+        }
+
+        wam.machine_st.flags.unknown = self.unknown.into();
+        wam.crash_reporter = self.crash_report.map(CrashReporter::new);
+        wam.machine_st.library_paths = self.library_paths;
+
+        for (name, source) in self.libraries {
+            wam.add_library(name, source);
+        }
+
+        for (name, contents) in self.resources {
+            wam.add_resource(name, contents);
+        }
+
+        for (name, value) in self.flags {
+            let result = wam.set_flag(&name, value);
+            debug_assert!(
+                result.is_ok(),
+                "with_flag should only accept the values of {name}"
+            );
+
+            wam.configured_flags.push(name);
+        }
+
         wam
     }
 }
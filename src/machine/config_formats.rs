@@ -0,0 +1,271 @@
+use crate::machine::json::*;
+use crate::parser::dashu::Integer;
+
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+// YAML and TOML documents are read into and written from the values
+// of JSON text, so that library(serialization/yaml) and
+// library(serialization/toml) represent them by the terms of
+// library(serialization/json).
+
+// the value of the YAML document text. None if text isn't one YAML
+// document, or has a value without a JSON term, as .inf and mappings
+// with sequences as keys do.
+pub(crate) fn read_yaml(text: &str) -> Option<JsonValue> {
+    let mut documents = YamlLoader::load_from_str(text).ok()?.into_iter();
+
+    match (documents.next(), documents.next()) {
+        (Some(document), None) => yaml_to_json(document, 0),
+        (None, _) => Some(JsonValue::Null),
+        _ => None,
+    }
+}
+
+fn yaml_to_json(value: Yaml, depth: usize) -> Option<JsonValue> {
+    if depth == MAX_DEPTH {
+        return None;
+    }
+
+    Some(match value {
+        Yaml::Null => JsonValue::Null,
+        Yaml::Boolean(b) => JsonValue::Boolean(b),
+        Yaml::Integer(n) => JsonValue::Number(JsonNumber::Integer(Integer::from(n))),
+        // integers outside the 64-bit signed integers are read as
+        // reals.
+        Yaml::Real(text) => match text.parse::<u64>() {
+            Ok(n) => JsonValue::Number(JsonNumber::Integer(Integer::from(n))),
+            Err(_) => JsonValue::Number(JsonNumber::Float(
+                Yaml::Real(text).as_f64().filter(|n| n.is_finite())?,
+            )),
+        },
+        Yaml::String(string) => JsonValue::String(string),
+        Yaml::Array(values) => JsonValue::Array(
+            values
+                .into_iter()
+                .map(|value| yaml_to_json(value, depth + 1))
+                .collect::<Option<_>>()?,
+        ),
+        Yaml::Hash(mapping) => {
+            let mut members = Vec::with_capacity(mapping.len());
+
+            for (key, value) in mapping {
+                // keys that are scalars are read as their text, as in
+                // the {1: a, true: b} of {"1": "a", "true": "b"}.
+                let key = match key {
+                    Yaml::String(key) | Yaml::Real(key) => key,
+                    Yaml::Integer(n) => n.to_string(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    Yaml::Null => "null".to_string(),
+                    _ => return None,
+                };
+
+                members.push((key, yaml_to_json(value, depth + 1)?));
+            }
+
+            JsonValue::Object(members)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => return None,
+    })
+}
+
+// the YAML document of value. None if value has an integer outside
+// the 64-bit integers of YAML.
+pub(crate) fn write_yaml(value: &JsonValue) -> Option<String> {
+    let mut text = String::new();
+    YamlEmitter::new(&mut text)
+        .dump(&json_to_yaml(value)?)
+        .ok()?;
+
+    // the emitter starts the document with a --- line, and doesn't
+    // end its last line.
+    let mut text = text.strip_prefix("---\n").unwrap_or(&text).to_string();
+    text.push('\n');
+
+    Some(text)
+}
+
+fn json_to_yaml(value: &JsonValue) -> Option<Yaml> {
+    Some(match value {
+        JsonValue::Object(members) => Yaml::Hash(
+            members
+                .iter()
+                .map(|(key, value)| Some((Yaml::String(key.clone()), json_to_yaml(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        JsonValue::Array(values) => {
+            Yaml::Array(values.iter().map(json_to_yaml).collect::<Option<_>>()?)
+        }
+        JsonValue::String(string) => Yaml::String(string.clone()),
+        JsonValue::Number(JsonNumber::Integer(n)) => match i64::try_from(n) {
+            Ok(n) => Yaml::Integer(n),
+            Err(_) => Yaml::Real(u64::try_from(n).ok()?.to_string()),
+        },
+        JsonValue::Number(JsonNumber::Float(n)) => Yaml::Real(format!("{n:?}")),
+        JsonValue::Boolean(b) => Yaml::Boolean(*b),
+        JsonValue::Null => Yaml::Null,
+    })
+}
+
+// the value of the TOML document text, an object. dates and times are
+// read as strings of their text. None if text isn't a TOML document,
+// or has a float that isn't finite.
+pub(crate) fn read_toml(text: &str) -> Option<JsonValue> {
+    toml_table_to_json(text.parse().ok()?)
+}
+
+fn toml_table_to_json(table: toml::Table) -> Option<JsonValue> {
+    Some(JsonValue::Object(
+        table
+            .into_iter()
+            .map(|(key, value)| Some((key, toml_to_json(value)?)))
+            .collect::<Option<_>>()?,
+    ))
+}
+
+fn toml_to_json(value: toml::Value) -> Option<JsonValue> {
+    Some(match value {
+        toml::Value::String(string) => JsonValue::String(string),
+        toml::Value::Integer(n) => JsonValue::Number(JsonNumber::Integer(Integer::from(n))),
+        toml::Value::Float(n) if n.is_finite() => JsonValue::Number(JsonNumber::Float(n)),
+        toml::Value::Float(_) => return None,
+        toml::Value::Boolean(b) => JsonValue::Boolean(b),
+        toml::Value::Datetime(datetime) => JsonValue::String(datetime.to_string()),
+        toml::Value::Array(values) => JsonValue::Array(
+            values
+                .into_iter()
+                .map(toml_to_json)
+                .collect::<Option<_>>()?,
+        ),
+        toml::Value::Table(table) => toml_table_to_json(table)?,
+    })
+}
+
+// the TOML document of value. None if value isn't an object, or has
+// a null, which TOML lacks, or an integer outside the 64-bit integers
+// of TOML.
+pub(crate) fn write_toml(value: &JsonValue) -> Option<String> {
+    match json_to_toml(value)? {
+        toml::Value::Table(table) => toml::to_string(&table).ok(),
+        _ => None,
+    }
+}
+
+fn json_to_toml(value: &JsonValue) -> Option<toml::Value> {
+    Some(match value {
+        JsonValue::Object(members) => toml::Value::Table(
+            members
+                .iter()
+                .map(|(key, value)| Some((key.clone(), json_to_toml(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+        JsonValue::Array(values) => {
+            toml::Value::Array(values.iter().map(json_to_toml).collect::<Option<_>>()?)
+        }
+        JsonValue::String(string) => toml::Value::String(string.clone()),
+        JsonValue::Number(JsonNumber::Integer(n)) => toml::Value::Integer(i64::try_from(n).ok()?),
+        JsonValue::Number(JsonNumber::Float(n)) => toml::Value::Float(*n),
+        JsonValue::Boolean(b) => toml::Value::Boolean(*b),
+        JsonValue::Null => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integer(n: i64) -> JsonValue {
+        JsonValue::Number(JsonNumber::Integer(Integer::from(n)))
+    }
+
+    fn string(s: &str) -> JsonValue {
+        JsonValue::String(s.to_string())
+    }
+
+    #[test]
+    fn reads_yaml() {
+        assert_eq!(
+            read_yaml("name: app\nports: [80, 443]\nratio: 0.5\ndebug: false\nextra: ~\n1: one\n"),
+            Some(JsonValue::Object(vec![
+                ("name".to_string(), string("app")),
+                (
+                    "ports".to_string(),
+                    JsonValue::Array(vec![integer(80), integer(443)])
+                ),
+                (
+                    "ratio".to_string(),
+                    JsonValue::Number(JsonNumber::Float(0.5))
+                ),
+                ("debug".to_string(), JsonValue::Boolean(false)),
+                ("extra".to_string(), JsonValue::Null),
+                ("1".to_string(), string("one")),
+            ]))
+        );
+        assert_eq!(read_yaml("!custom 3"), Some(string("3")));
+        assert_eq!(read_yaml("!!int 3"), Some(integer(3)));
+        assert_eq!(read_yaml("a: .inf"), None);
+        assert_eq!(read_yaml("? [a]\n: b\n"), None);
+        assert_eq!(read_yaml("a: [b"), None);
+    }
+
+    #[test]
+    fn writes_yaml() {
+        let value = JsonValue::Object(vec![
+            (
+                "a".to_string(),
+                JsonValue::Array(vec![integer(1), string("x")]),
+            ),
+            ("b".to_string(), JsonValue::Null),
+        ]);
+
+        assert_eq!(
+            write_yaml(&value).as_deref(),
+            Some("a:\n  - 1\n  - x\nb: ~\n")
+        );
+        assert_eq!(
+            write_yaml(&JsonValue::Number(JsonNumber::Integer(
+                Integer::from(u64::MAX) + Integer::from(1)
+            ))),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_toml() {
+        assert_eq!(
+            read_toml("title = \"app\"\n[server]\nport = 80\nstarted = 1979-05-27T07:32:00Z\n"),
+            Some(JsonValue::Object(vec![
+                ("title".to_string(), string("app")),
+                (
+                    "server".to_string(),
+                    JsonValue::Object(vec![
+                        ("port".to_string(), integer(80)),
+                        ("started".to_string(), string("1979-05-27T07:32:00Z")),
+                    ])
+                ),
+            ]))
+        );
+        assert_eq!(read_toml("a = nan"), None);
+        assert_eq!(read_toml("a = "), None);
+    }
+
+    #[test]
+    fn writes_toml() {
+        let value = JsonValue::Object(vec![
+            ("title".to_string(), string("app")),
+            (
+                "server".to_string(),
+                JsonValue::Object(vec![("port".to_string(), integer(80))]),
+            ),
+        ]);
+
+        assert_eq!(
+            write_toml(&value).as_deref(),
+            Some("title = \"app\"\n\n[server]\nport = 80\n")
+        );
+        assert_eq!(write_toml(&JsonValue::Array(vec![])), None);
+        assert_eq!(
+            write_toml(&JsonValue::Object(vec![("a".to_string(), JsonValue::Null)])),
+            None
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::heap_print::*;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::machine::libraries;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::machine::profiler::*;
+use crate::machine::Machine;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::parser::ast::*;
+use crate::types::*;
+
+use std::any::Any;
+use std::cell::RefCell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fmt::Write as _;
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Once;
+
+// the innermost activations of the goal stack listed in a report.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_REPORTED_FRAMES: usize = 64;
+
+thread_local! {
+    // the message and location of the last panic of the thread, as
+    // recorded by the panic hook.
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+// where a machine reports a crash, set by
+// MachineBuilder::with_crash_report, and the last query it was given.
+#[derive(Debug)]
+pub(crate) struct CrashReporter {
+    path: PathBuf,
+    query: Option<String>,
+    // set once a report is written, so that the dispatch loops a
+    // panic unwinds through don't report it again.
+    #[cfg(not(target_arch = "wasm32"))]
+    reported: bool,
+}
+
+impl CrashReporter {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        // the payload of a panic carries its message but not its
+        // location, which only the panic hook is told.
+        #[cfg(not(target_arch = "wasm32"))]
+        INSTALL_PANIC_HOOK.call_once(|| {
+            let default_hook = std::panic::take_hook();
+
+            std::panic::set_hook(Box::new(move |info| {
+                LAST_PANIC.with(|last_panic| *last_panic.borrow_mut() = Some(info.to_string()));
+                default_hook(info);
+            }));
+        });
+
+        CrashReporter {
+            path,
+            query: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            reported: false,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+pub(crate) fn panic_cause(payload: &(dyn Any + Send)) -> String {
+    if let Some(cause) = LAST_PANIC.with(|last_panic| last_panic.borrow_mut().take()) {
+        return cause;
+    }
+
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panicked: {message}")
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panicked: {message}")
+    } else {
+        "panicked".to_string()
+    }
+}
+
+impl Machine {
+    // writes term as by writeq/1.
+    pub(crate) fn term_to_string(&mut self, term: HeapCellValue) -> String {
+        self.machine_st.heap[0] = term;
+
+        let mut printer = HCPrinter::new(
+            &mut self.machine_st.heap,
+            &mut self.machine_st.stack,
+            &self.machine_st.arena,
+            &self.indices.op_dir,
+            PrinterOutputter::new(),
+            0,
+        );
+
+        printer.quoted = true;
+        printer.print().result()
+    }
+
+    // records the query about to run, if crashes are reported.
+    pub(crate) fn record_crash_query(&mut self, query: impl FnOnce(&mut Self) -> String) {
+        if self.crash_reporter.is_some() {
+            let query = query(self);
+
+            if let Some(crash_reporter) = &mut self.crash_reporter {
+                crash_reporter.query = Some(query);
+            }
+        }
+    }
+
+    // writes a report of the crash with the given cause, if crashes
+    // are reported and the crash isn't reported yet. a report that
+    // can't be written is noted on stderr, as the machine is about to
+    // go down regardless.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn report_crash(&mut self, cause: &str) {
+        let Some(crash_reporter) = &mut self.crash_reporter else {
+            return;
+        };
+
+        if crash_reporter.reported {
+            return;
+        }
+
+        crash_reporter.reported = true;
+
+        let path = crash_reporter.path.clone();
+        let report = self.crash_report(cause);
+
+        match std::fs::write(&path, report) {
+            Ok(()) => eprintln!("% crash report written to {}", path.display()),
+            Err(err) => eprintln!("% couldn't write crash report to {}: {err}", path.display()),
+        }
+    }
+
+    // there is no file system to write a report to, and panics abort
+    // without unwinding through the dispatch loop, so the report and
+    // the formatting of the machine it does are left out.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn report_crash(&mut self, _cause: &str) {}
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn crash_report(&self, cause: &str) -> String {
+        let mut report = String::new();
+
+        let _ = writeln!(report, "Scryer Prolog crash report");
+        let _ = writeln!(
+            report,
+            "version: {}",
+            git_version::git_version!(cargo_prefix = "cargo:", fallback = "unknown")
+        );
+        let _ = writeln!(report, "cause: {}", cause.trim_end());
+
+        let query = self
+            .crash_reporter
+            .as_ref()
+            .and_then(|crash_reporter| crash_reporter.query.as_deref());
+
+        let _ = writeln!(report, "query: {}", query.unwrap_or("none"));
+
+        let _ = writeln!(report, "\ngoal stack:");
+        self.write_goal_stack(&mut report);
+
+        let flags = &self.machine_st.flags;
+        let _ = writeln!(report, "\nflags:");
+        let double_quotes = match flags.double_quotes {
+            DoubleQuotes::Atom => "atom",
+            DoubleQuotes::Chars => "chars",
+            DoubleQuotes::Codes => "codes",
+        };
+
+        let unknown = match flags.unknown {
+            Unknown::Error => "error",
+            Unknown::Fail => "fail",
+            Unknown::Warn => "warning",
+        };
+
+        let _ = writeln!(report, "  double_quotes: {double_quotes}");
+        let _ = writeln!(report, "  unknown: {unknown}");
+        let _ = writeln!(report, "  error_locations: {}", flags.error_locations);
+        let _ = writeln!(report, "  prefer_rationals: {}", flags.prefer_rationals);
+        let _ = writeln!(report, "  rational_flags: {:?}", flags.rational_flags);
+        let _ = writeln!(
+            report,
+            "  integer_rounding_function: {}",
+            match flags.integer_rounding_function {
+                IntegerRoundingFunction::TowardZero => "toward_zero",
+                IntegerRoundingFunction::Down => "down",
+            }
+        );
+        let _ = writeln!(report, "  query_options: {:?}", self.query_options);
+
+        let _ = writeln!(report, "\nmemory:");
+        let _ = writeln!(report, "  heap: {} bytes", self.machine_st.heap.byte_len());
+        let _ = writeln!(report, "  stack: {} bytes", self.machine_st.stack.top());
+        let _ = writeln!(
+            report,
+            "  trail: {} bytes",
+            self.machine_st.trail.len() * std::mem::size_of::<TrailEntry>()
+        );
+
+        let _ = writeln!(report, "\nmodules:");
+
+        // the system libraries are those of the version reported above.
+        for (module_name, module) in self.indices.modules.iter() {
+            let source = match module.location {
+                Some(location) => location.file.as_str().to_string(),
+                None if libraries::contains(&module_name.as_str()) => "library".to_string(),
+                None => "no file".to_string(),
+            };
+
+            let _ = writeln!(report, "  {}: {source}", module_name.as_str());
+        }
+
+        report
+    }
+
+    // lists the predicate running at p and those with activations in
+    // the continuation chain, innermost first, with the locations of
+    // clauses consulted from files.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_goal_stack(&self, report: &mut String) {
+        let mut predicates = Profiler::new(self.code.len());
+        self.add_profiled_predicates(&mut predicates);
+
+        let stack = &self.machine_st.stack;
+        let mut e = self.machine_st.e;
+        let mut ps = vec![self.machine_st.p, self.machine_st.cp];
+
+        // the stack of a crashed machine may be inconsistent, so the
+        // walk stops at the first frame beyond its top.
+        while e != 0 && e < stack.top() && ps.len() <= MAX_REPORTED_FRAMES {
+            let frame = stack.index_and_frame(e);
+
+            ps.push(frame.prelude.cp);
+            e = frame.prelude.e;
+        }
+
+        let mut frames = 0;
+
+        for p in ps {
+            let Some(entry) = predicates.predicate_at(p) else {
+                continue;
+            };
+
+            if frames == MAX_REPORTED_FRAMES {
+                let _ = writeln!(report, "  ...");
+                break;
+            }
+
+            frames += 1;
+
+            let _ = write!(
+                report,
+                "  {}:{}/{}",
+                entry.module_name.as_str(),
+                entry.name.as_str(),
+                entry.arity
+            );
+
+            match self.machine_st.code_locations.get(p) {
+                Some(location) => {
+                    let _ = writeln!(report, " at {}:{}", location.file.as_str(), location.line);
+                }
+                None => {
+                    let _ = writeln!(report);
+                }
+            }
+        }
+
+        if frames == 0 {
+            let _ = writeln!(report, "  none");
+        }
+    }
+}
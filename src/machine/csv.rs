@@ -0,0 +1,208 @@
+use crate::parser::char_reader::*;
+use crate::parser::dashu::Integer;
+
+use csv_core::{ReadRecordResult, ReaderBuilder, WriterBuilder};
+
+use std::io;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CsvNumber {
+    Integer(Integer),
+    Float(f64),
+}
+
+// reads the next row of the CSV text of chars, with fields separated
+// by delimiter, leaving chars after the row. rows are ended by \n,
+// \r or \r\n, and empty lines are skipped. Ok(None) at the end of
+// chars.
+pub(crate) fn read_csv_row<R: CharRead>(
+    chars: &mut R,
+    delimiter: u8,
+) -> io::Result<Option<Vec<String>>> {
+    let mut reader = ReaderBuilder::new().delimiter(delimiter).build();
+
+    let mut output = vec![0; 256];
+    let mut ends = vec![0; 16];
+    let (mut output_len, mut ends_len) = (0, 0);
+
+    loop {
+        let c = chars.peek_char().transpose()?;
+
+        let mut buf = [0; 4];
+        let mut input: &[u8] = match c {
+            Some(c) => c.encode_utf8(&mut buf).as_bytes(),
+            None => &[],
+        };
+
+        loop {
+            let (result, nin, nout, nend) =
+                reader.read_record(input, &mut output[output_len..], &mut ends[ends_len..]);
+
+            input = &input[nin..];
+            output_len += nout;
+            ends_len += nend;
+
+            match result {
+                ReadRecordResult::InputEmpty if c.is_some() => break,
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => return Ok(None),
+                ReadRecordResult::OutputFull => output.resize(2 * output.len(), 0),
+                ReadRecordResult::OutputEndsFull => ends.resize(2 * ends.len(), 0),
+                ReadRecordResult::Record => {
+                    if let Some(c) = c.filter(|_| input.is_empty()) {
+                        chars.consume(c.len_utf8());
+                    }
+
+                    let mut start = 0;
+                    let mut fields = Vec::with_capacity(ends_len);
+
+                    for &end in &ends[..ends_len] {
+                        fields.push(String::from_utf8_lossy(&output[start..end]).into_owned());
+                        start = end;
+                    }
+
+                    return Ok(Some(fields));
+                }
+            }
+        }
+
+        if let Some(c) = c {
+            chars.consume(c.len_utf8());
+        }
+    }
+}
+
+// the number written as field, if it is written as Prolog writes
+// integers or floats, as in -12 and 3.5e2.
+pub(crate) fn csv_number(field: &str) -> Option<CsvNumber> {
+    let digits = field.strip_prefix('-').unwrap_or(field);
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    if is_digits(digits) {
+        return Integer::from_str(field).ok().map(CsvNumber::Integer);
+    }
+
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(e) => (&digits[..e], Some(&digits[e + 1..])),
+        None => (digits, None),
+    };
+
+    let (whole, fraction) = mantissa.split_once('.')?;
+
+    if !is_digits(whole) || !is_digits(fraction) {
+        return None;
+    }
+
+    if let Some(exponent) = exponent {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+
+        if !is_digits(exponent) {
+            return None;
+        }
+    }
+
+    let float: f64 = field.parse().ok()?;
+    float.is_finite().then_some(CsvNumber::Float(float))
+}
+
+// writes fields as a row of CSV text separated by delimiter, without
+// a line separator. fields with the delimiter, quotes or line breaks
+// are quoted.
+pub(crate) fn write_csv_row(csv: &mut String, fields: &[String], delimiter: u8) {
+    // a row of one empty field is quoted, as an empty line is no row.
+    if let [field] = fields {
+        if field.is_empty() {
+            csv.push_str("\"\"");
+            return;
+        }
+    }
+
+    let writer = WriterBuilder::new().delimiter(delimiter).build();
+
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            csv.push(char::from(delimiter));
+        }
+
+        if writer.should_quote(field.as_bytes()) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(field);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_rows(text: &str, delimiter: u8) -> Vec<Vec<String>> {
+        let mut chars = CharReader::new(text.as_bytes());
+        let mut rows = vec![];
+
+        while let Some(row) = read_csv_row(&mut chars, delimiter).unwrap() {
+            rows.push(row);
+        }
+
+        rows
+    }
+
+    #[test]
+    fn reads_rows() {
+        assert_eq!(
+            read_rows("a,b\r\n\"x,\"\"y\"\"\",\n\nlast;1", b','),
+            [
+                vec!["a".to_string(), "b".to_string()],
+                vec!["x,\"y\"".to_string(), String::new()],
+                vec!["last;1".to_string()],
+            ]
+        );
+        assert_eq!(
+            read_rows("é;\"multi\nline\"\n", b';'),
+            [vec!["é".to_string(), "multi\nline".to_string()]]
+        );
+        assert_eq!(read_rows("", b','), Vec::<Vec<String>>::new());
+    }
+
+    #[test]
+    fn reads_rows_one_at_a_time() {
+        let mut chars = CharReader::new("a,b\nc\n".as_bytes());
+
+        assert_eq!(
+            read_csv_row(&mut chars, b',').unwrap(),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(chars.peek_char().unwrap().unwrap(), 'c');
+    }
+
+    #[test]
+    fn converts_numbers() {
+        assert_eq!(
+            csv_number("-12"),
+            Some(CsvNumber::Integer(Integer::from(-12)))
+        );
+        assert_eq!(csv_number("3.5e2"), Some(CsvNumber::Float(350.0)));
+        assert_eq!(csv_number("1.0E-1"), Some(CsvNumber::Float(0.1)));
+        assert_eq!(csv_number("1e2"), None);
+        assert_eq!(csv_number("1."), None);
+        assert_eq!(csv_number(".5"), None);
+        assert_eq!(csv_number("inf"), None);
+        assert_eq!(csv_number(""), None);
+    }
+
+    #[test]
+    fn writes_rows() {
+        let mut csv = String::new();
+        let fields = ["a", "b,c", "say \"hi\"", "", "x\ny"].map(String::from);
+
+        write_csv_row(&mut csv, &fields, b',');
+        assert_eq!(csv, "a,\"b,c\",\"say \"\"hi\"\"\",,\"x\ny\"");
+
+        csv.clear();
+        write_csv_row(&mut csv, &[String::new()], b',');
+        assert_eq!(csv, "\"\"");
+        assert_eq!(read_rows(&csv, b','), [vec![String::new()]]);
+    }
+}
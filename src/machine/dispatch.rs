@@ -1,4 +1,5 @@
 use crate::arena::*;
+use crate::arithmetic::publish_float_flags;
 use crate::atom_table::*;
 use crate::functor_macro::*;
 use crate::instructions::*;
@@ -77,6 +78,68 @@ macro_rules! push_cell {
     }};
 }
 
+macro_rules! get_list {
+    ($machine_st:expr, $reg:expr) => {{
+        let deref_v = $machine_st.deref($machine_st[$reg]);
+        let store_v = $machine_st.store(deref_v);
+
+        read_heap_cell!(store_v,
+            (HeapCellValueTag::PStrLoc, h) => {
+                $machine_st.s = HeapPtr::PStr(h);
+                $machine_st.s_offset = 0;
+                $machine_st.mode = MachineMode::Read;
+            }
+            (HeapCellValueTag::Str, s) => {
+                let (name, arity) = cell_as_atom_cell!($machine_st.heap[s])
+                    .get_name_and_arity();
+
+                if name == atom!(".") && arity == 2 {
+                    $machine_st.s = HeapPtr::HeapCell(s+1);
+                    $machine_st.s_offset = 0;
+                    $machine_st.mode = MachineMode::Read;
+                } else {
+                    $machine_st.backtrack();
+                    continue;
+                }
+            }
+            (HeapCellValueTag::Lis, l) => {
+                $machine_st.s = HeapPtr::HeapCell(l);
+                $machine_st.s_offset = 0;
+                $machine_st.mode = MachineMode::Read;
+            }
+            (HeapCellValueTag::AttrVar | HeapCellValueTag::Var | HeapCellValueTag::StackVar) => {
+                let h = $machine_st.heap.cell_len();
+
+                push_cell!($machine_st, list_loc_as_cell!(h+1));
+                $machine_st.bind(store_v.as_var().unwrap(), heap_loc_as_cell!(h));
+
+                $machine_st.mode = MachineMode::Write;
+            }
+            _ => {
+                $machine_st.backtrack();
+                continue;
+            }
+        );
+    }};
+}
+
+macro_rules! unify_variable {
+    ($machine_st:expr, $reg:expr) => {{
+        match $machine_st.mode {
+            MachineMode::Read => {
+                let (value, s_offset_incr) = $machine_st.read_s();
+                $machine_st[$reg] = value;
+                $machine_st.s_offset += s_offset_incr;
+            }
+            MachineMode::Write => {
+                let h = $machine_st.heap.cell_len();
+                push_cell!($machine_st, heap_loc_as_cell!(h));
+                $machine_st[$reg] = heap_loc_as_cell!(h);
+            }
+        }
+    }};
+}
+
 static INSTRUCTIONS_PER_INTERRUPT_POLL: usize = 256;
 
 impl MachineState {
@@ -215,6 +278,21 @@ fn is(&mut self, r: RegType, at: ArithmeticTerm) -> CallResult {
         Ok(())
     }
 
+    // the key of addr in the switches on constants, or None if no
+    // clause is indexed on it. floats are keyed by the offset of an
+    // equal float of a clause head (see Arena::f64_index_key).
+    #[inline(always)]
+    pub(crate) fn switch_on_constant_key(&self, addr: HeapCellValue) -> Option<HeapCellValue> {
+        read_heap_cell!(addr,
+            (HeapCellValueTag::F64Offset, offset) => {
+                self.arena.find_f64_index_key(offset).map(HeapCellValue::from)
+            }
+            _ => {
+                Some(addr)
+            }
+        )
+    }
+
     #[inline(always)]
     pub(crate) fn select_switch_on_term_index(
         &self,
@@ -399,18 +477,15 @@ fn dynamic_external_of_clause_is_valid(machine: &mut Machine, p: usize) -> bool
         let indexing_lines = self.code[self.machine_st.p].to_indexing_line_mut().unwrap();
 
         let mut index = 0;
-        let addr = match &indexing_lines[0] {
-            &IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(arg, ..)) => self
-                .machine_st
-                .store(self.machine_st.deref(self.machine_st.registers[arg])),
-            _ => {
-                unreachable!()
-            }
-        };
+        let mut addr = empty_list_as_cell!();
 
         loop {
             match &indexing_lines[index] {
-                &IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(_, v, c, l, s)) => {
+                &IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(arg, v, c, l, s)) => {
+                    addr = self
+                        .machine_st
+                        .store(self.machine_st.deref(self.machine_st.registers[arg]));
+
                     let offset = self
                         .machine_st
                         .select_switch_on_term_index(addr, v, c, l, s);
@@ -443,10 +518,43 @@ fn dynamic_external_of_clause_is_valid(machine: &mut Machine, p: usize) -> bool
                         }
                     }
                 }
-                IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(hm)) => {
-                    // let lit = self.machine_st.constant_to_literal(addr);
+                &IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(arg, v, c, l, s)) => {
+                    let offset = read_heap_cell!(addr,
+                        (HeapCellValueTag::Str, st) => {
+                            addr = self
+                                .machine_st
+                                .store(self.machine_st.deref(self.machine_st.heap[st + arg]));
+
+                            self.machine_st.select_switch_on_term_index(addr, v, c, l, s)
+                        }
+                        _ => {
+                            v
+                        }
+                    );
 
-                    let offset = match hm.get(&addr) {
+                    match offset {
+                        IndexingCodePtr::Fail => {
+                            self.machine_st.fail = true;
+                            break;
+                        }
+                        IndexingCodePtr::DynamicExternal(_) => {
+                            unreachable!()
+                        }
+                        IndexingCodePtr::External(o) => {
+                            self.machine_st.p += o;
+                            break;
+                        }
+                        IndexingCodePtr::Internal(o) => {
+                            index += o;
+                        }
+                    }
+                }
+                IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(hm)) => {
+                    let offset = match self
+                        .machine_st
+                        .switch_on_constant_key(addr)
+                        .and_then(|key| hm.get(&key))
+                    {
                         Some(offset) => *offset,
                         _ => IndexingCodePtr::Fail,
                     };
@@ -525,8 +633,30 @@ fn dynamic_external_of_clause_is_valid(machine: &mut Machine, p: usize) -> bool
         }
     }
 
-    #[inline(always)]
+    // runs the dispatch loop, reporting a panic escaping it as a crash
+    // if crashes are reported.
     pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
+        if self.crash_reporter.is_none() {
+            return self.run_dispatch_loop();
+        }
+
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.run_dispatch_loop())) {
+            Ok(exit_code) => exit_code,
+            Err(payload) => {
+                self.report_crash(&crash_report::panic_cause(&*payload));
+                std::panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn run_dispatch_loop(&mut self) -> std::process::ExitCode {
+        if let Some(heartbeat) = &self.machine_st.heartbeat {
+            heartbeat.beat();
+        }
+
+        publish_float_flags(self.machine_st.flags.float_flags);
+
         'outer: loop {
             for _ in 0..INSTRUCTIONS_PER_INTERRUPT_POLL {
                 match &self.code[self.machine_st.p] {
@@ -707,8 +837,20 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(a2));
 
-                        self.machine_st.interms[t - 1] =
-                            try_or_throw_gen!(&mut self.machine_st, pow(n1, n2, atom!("**")));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_pow(
+                                    n1,
+                                    n2,
+                                    atom!("**"),
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            try_or_throw_gen!(&mut self.machine_st, pow(n1, n2, atom!("**")))
+                        };
 
                         self.machine_st.p += 1;
                     }
@@ -746,10 +888,20 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(a2));
 
-                        self.machine_st.interms[t - 1] = try_or_throw_gen!(
-                            &mut self.machine_st,
-                            idiv(n1, n2, &mut self.machine_st.arena)
-                        );
+                        let rounds_down = self.machine_st.flags.integer_rounding_function
+                            == IntegerRoundingFunction::Down;
+
+                        self.machine_st.interms[t - 1] = if rounds_down {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                idiv_down(n1, n2, &mut self.machine_st.arena)
+                            )
+                        } else {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                idiv(n1, n2, &mut self.machine_st.arena)
+                            )
+                        };
 
                         self.machine_st.p += 1;
                     }
@@ -785,8 +937,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(a2));
 
-                        self.machine_st.interms[t - 1] =
-                            try_or_throw_gen!(&mut self.machine_st, div(n1, n2));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_div(n1, n2, &mut self.machine_st.arena)
+                            )
+                        } else {
+                            try_or_throw_gen!(&mut self.machine_st, div(n1, n2))
+                        };
 
                         self.machine_st.p += 1;
                     }
@@ -860,101 +1018,312 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(a2));
 
-                        self.machine_st.interms[t - 1] = try_or_throw_gen!(
-                            &mut self.machine_st,
-                            remainder(n1, n2, &mut self.machine_st.arena)
-                        );
+                        let rounds_down = self.machine_st.flags.integer_rounding_function
+                            == IntegerRoundingFunction::Down;
+
+                        self.machine_st.interms[t - 1] = if rounds_down {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                remainder_down(n1, n2, &mut self.machine_st.arena)
+                            )
+                        } else {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                remainder(n1, n2, &mut self.machine_st.arena)
+                            )
+                        };
 
                         self.machine_st.p += 1;
                     }
                     &Instruction::Cos(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
-                        self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, cos(n1)),
-                        ));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("cos"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                cos(n1)
+                            )))
+                        };
 
                         self.machine_st.p += 1;
                     }
                     &Instruction::Sin(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
-                        self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, sin(n1)),
-                        ));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("sin"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                sin(n1)
+                            )))
+                        };
 
                         self.machine_st.p += 1;
                     }
                     &Instruction::Tan(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
-                        self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, tan(n1)),
-                        ));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("tan"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                tan(n1)
+                            )))
+                        };
 
                         self.machine_st.p += 1;
                     }
                     &Instruction::Sqrt(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
-                        self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, sqrt(n1)),
-                        ));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("sqrt"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                sqrt(n1)
+                            )))
+                        };
 
                         self.machine_st.p += 1;
                     }
                     &Instruction::Log(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
-                        self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, log(n1)),
-                        ));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("log"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                log(n1)
+                            )))
+                        };
 
                         self.machine_st.p += 1;
                     }
                     &Instruction::Exp(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
-                        self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, exp(n1)),
-                        ));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("exp"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exp(n1)
+                            )))
+                        };
 
                         self.machine_st.p += 1;
                     }
                     &Instruction::ACos(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("acos"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                acos(n1)
+                            )))
+                        };
+
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::ASin(ref a1, t) => {
+                        let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
+
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("asin"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                asin(n1)
+                            )))
+                        };
+
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::ATan(ref a1, t) => {
+                        let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
+
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("atan"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                atan(n1)
+                            )))
+                        };
+
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::Erf(ref a1, t) => {
+                        let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
+
                         self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, acos(n1)),
+                            try_or_throw_gen!(&mut self.machine_st, erf(n1)),
                         ));
 
                         self.machine_st.p += 1;
                     }
-                    &Instruction::ASin(ref a1, t) => {
+                    &Instruction::Erfc(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
                         self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, asin(n1)),
+                            try_or_throw_gen!(&mut self.machine_st, erfc(n1)),
                         ));
 
                         self.machine_st.p += 1;
                     }
-                    &Instruction::ATan(ref a1, t) => {
+                    &Instruction::LGamma(ref a1, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
 
                         self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, atan(n1)),
+                            try_or_throw_gen!(&mut self.machine_st, lgamma(n1)),
                         ));
 
                         self.machine_st.p += 1;
                     }
+                    &Instruction::ExpM1(ref a1, t) => {
+                        let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
+
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("expm1"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                expm1(n1)
+                            )))
+                        };
+
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::Log1P(ref a1, t) => {
+                        let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
+
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_elementary(
+                                    atom!("log1p"),
+                                    n1,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                log1p(n1)
+                            )))
+                        };
+
+                        self.machine_st.p += 1;
+                    }
                     &Instruction::ATan2(ref a1, ref a2, t) => {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(a1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(a2));
 
-                        self.machine_st.interms[t - 1] = Number::Float(OrderedFloat(
-                            try_or_throw_gen!(&mut self.machine_st, atan2(n1, n2)),
-                        ));
+                        self.machine_st.interms[t - 1] = if self.machine_st.flags.prefer_rationals {
+                            try_or_throw_gen!(
+                                &mut self.machine_st,
+                                exact_atan2(
+                                    n1,
+                                    n2,
+                                    self.machine_st.flags.rational_flags,
+                                    &mut self.machine_st.arena
+                                )
+                            )
+                        } else {
+                            Number::Float(OrderedFloat(try_or_throw_gen!(
+                                &mut self.machine_st,
+                                atan2(n1, n2)
+                            )))
+                        };
 
                         self.machine_st.p += 1;
                     }
@@ -1093,6 +1462,12 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                                 }
                             }
                             None => {
+                                if let FirstOrNext::Next = self.machine_st.dynamic_mode {
+                                    // the choice point was taken for clauses retracted since,
+                                    // so it is dropped rather than retried forever.
+                                    self.trust_me();
+                                }
+
                                 self.machine_st.fail = true;
                             }
                         }
@@ -1172,6 +1547,12 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                                 }
                             }
                             None => {
+                                if let FirstOrNext::Next = self.machine_st.dynamic_mode {
+                                    // the choice point was taken for clauses retracted since,
+                                    // so it is dropped rather than retried forever.
+                                    self.trust_me();
+                                }
+
                                 self.machine_st.fail = true;
                             }
                         }
@@ -1981,8 +2362,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less | Ordering::Equal) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p += 1;
                             }
@@ -1995,8 +2376,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less | Ordering::Equal) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p = self.machine_st.cp;
                             }
@@ -2009,8 +2390,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p += 1;
                             }
@@ -2023,8 +2404,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p = self.machine_st.cp;
                             }
@@ -2037,8 +2418,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 self.machine_st.backtrack();
                             }
                             _ => {
@@ -2051,8 +2432,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 self.machine_st.backtrack();
                             }
                             _ => {
@@ -2065,8 +2446,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater | Ordering::Equal) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p += 1;
                             }
@@ -2079,8 +2460,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater | Ordering::Equal) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p = self.machine_st.cp;
                             }
@@ -2093,8 +2474,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p += 1;
                             }
@@ -2107,8 +2488,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p = self.machine_st.cp;
                             }
@@ -2121,8 +2502,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p += 1;
                             }
@@ -2135,8 +2516,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less) => {
                                 increment_call_count!(self.machine_st);
                                 self.machine_st.p = self.machine_st.cp;
                             }
@@ -2149,8 +2530,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less | Ordering::Equal) => {
                                 self.machine_st.p += 1;
                             }
                             _ => {
@@ -2162,8 +2543,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less | Ordering::Equal) => {
                                 self.machine_st.p = self.machine_st.cp;
                             }
                             _ => {
@@ -2175,8 +2556,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 self.machine_st.backtrack();
                             }
                             _ => {
@@ -2188,8 +2569,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 self.machine_st.backtrack();
                             }
                             _ => {
@@ -2201,8 +2582,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 self.machine_st.p += 1;
                             }
                             _ => {
@@ -2214,8 +2595,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Equal) => {
                                 self.machine_st.p = self.machine_st.cp;
                             }
                             _ => {
@@ -2227,8 +2608,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater | Ordering::Equal) => {
                                 self.machine_st.p += 1;
                             }
                             _ => {
@@ -2240,8 +2621,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater | Ordering::Equal => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater | Ordering::Equal) => {
                                 self.machine_st.p = self.machine_st.cp;
                             }
                             _ => {
@@ -2253,8 +2634,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater) => {
                                 self.machine_st.p += 1;
                             }
                             _ => {
@@ -2266,8 +2647,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Greater => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Greater) => {
                                 self.machine_st.p = self.machine_st.cp;
                             }
                             _ => {
@@ -2279,8 +2660,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less) => {
                                 self.machine_st.p += 1;
                             }
                             _ => {
@@ -2292,8 +2673,8 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         let n1 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_1));
                         let n2 = try_or_throw!(self.machine_st, self.machine_st.get_number(at_2));
 
-                        match n1.cmp(&n2) {
-                            Ordering::Less => {
+                        match n1.arith_cmp(&n2) {
+                            Some(Ordering::Less) => {
                                 self.machine_st.p = self.machine_st.cp;
                             }
                             _ => {
@@ -2701,7 +3082,7 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                     &Instruction::CallNamed(arity, name, idx) => {
                         let idx = self.machine_st.arena.code_index_tbl.get_entry(idx.into());
 
-                        try_or_throw!(self.machine_st, self.try_call(name, arity, idx));
+                        try_or_throw!(self.machine_st, self.try_call(None, name, arity, idx));
 
                         if self.machine_st.fail {
                             self.machine_st.backtrack();
@@ -2712,7 +3093,7 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                     &Instruction::ExecuteNamed(arity, name, idx) => {
                         let idx = self.machine_st.arena.code_index_tbl.get_entry(idx.into());
 
-                        try_or_throw!(self.machine_st, self.try_execute(name, arity, idx));
+                        try_or_throw!(self.machine_st, self.try_execute(None, name, arity, idx));
 
                         if self.machine_st.fail {
                             self.machine_st.backtrack();
@@ -2723,7 +3104,7 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                     &Instruction::DefaultCallNamed(arity, name, idx) => {
                         let idx = self.machine_st.arena.code_index_tbl.get_entry(idx.into());
 
-                        try_or_throw!(self.machine_st, self.try_call(name, arity, idx));
+                        try_or_throw!(self.machine_st, self.try_call(None, name, arity, idx));
 
                         if self.machine_st.fail {
                             self.machine_st.backtrack();
@@ -2732,7 +3113,7 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                     &Instruction::DefaultExecuteNamed(arity, name, idx) => {
                         let idx = self.machine_st.arena.code_index_tbl.get_entry(idx.into());
 
-                        try_or_throw!(self.machine_st, self.try_execute(name, arity, idx));
+                        try_or_throw!(self.machine_st, self.try_execute(None, name, arity, idx));
 
                         if self.machine_st.fail {
                             self.machine_st.backtrack();
@@ -2753,47 +3134,13 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
                     &Instruction::GetList(_, reg) => {
-                        let deref_v = self.machine_st.deref(self.machine_st[reg]);
-                        let store_v = self.machine_st.store(deref_v);
-
-                        read_heap_cell!(store_v,
-                            (HeapCellValueTag::PStrLoc, h) => {
-                                self.machine_st.s = HeapPtr::PStr(h);
-                                self.machine_st.s_offset = 0;
-                                self.machine_st.mode = MachineMode::Read;
-                            }
-                            (HeapCellValueTag::Str, s) => {
-                                let (name, arity) = cell_as_atom_cell!(self.machine_st.heap[s])
-                                    .get_name_and_arity();
-
-                                if name == atom!(".") && arity == 2 {
-                                    self.machine_st.s = HeapPtr::HeapCell(s+1);
-                                    self.machine_st.s_offset = 0;
-                                    self.machine_st.mode = MachineMode::Read;
-                                } else {
-                                    self.machine_st.backtrack();
-                                    continue;
-                                }
-                            }
-                            (HeapCellValueTag::Lis, l) => {
-                                self.machine_st.s = HeapPtr::HeapCell(l);
-                                self.machine_st.s_offset = 0;
-                                self.machine_st.mode = MachineMode::Read;
-                            }
-                            (HeapCellValueTag::AttrVar | HeapCellValueTag::Var | HeapCellValueTag::StackVar) => {
-                                let h = self.machine_st.heap.cell_len();
-
-                                push_cell!(self.machine_st, list_loc_as_cell!(h+1));
-                                self.machine_st.bind(store_v.as_var().unwrap(), heap_loc_as_cell!(h));
-
-                                self.machine_st.mode = MachineMode::Write;
-                            }
-                            _ => {
-                                self.machine_st.backtrack();
-                                continue;
-                            }
-                        );
-
+                        get_list!(self.machine_st, reg);
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::GetListVariables(_, reg, h, t) => {
+                        get_list!(self.machine_st, reg);
+                        unify_variable!(self.machine_st, h);
+                        unify_variable!(self.machine_st, t);
                         self.machine_st.p += 1;
                     }
                     &Instruction::GetPartialString(_, ref string, reg) => {
@@ -3055,19 +3402,7 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.machine_st.p += 1;
                     }
                     &Instruction::UnifyVariable(reg) => {
-                        match self.machine_st.mode {
-                            MachineMode::Read => {
-                                let (value, s_offset_incr) = self.machine_st.read_s();
-                                self.machine_st[reg] = value;
-                                self.machine_st.s_offset += s_offset_incr;
-                            }
-                            MachineMode::Write => {
-                                let h = self.machine_st.heap.cell_len();
-                                push_cell!(self.machine_st, heap_loc_as_cell!(h));
-                                self.machine_st[reg] = heap_loc_as_cell!(h);
-                            }
-                        }
-
+                        unify_variable!(self.machine_st, reg);
                         self.machine_st.p += 1;
                     }
                     &Instruction::UnifyValue(reg) => {
@@ -3254,6 +3589,12 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                                         }
                                     }
                                     None => {
+                                        if let FirstOrNext::Next = self.machine_st.dynamic_mode {
+                                            // the choice point was taken for clauses retracted since,
+                                            // so it is dropped rather than retried forever.
+                                            self.trust_me();
+                                        }
+
                                         self.machine_st.fail = true;
                                     }
                                 }
@@ -3411,6 +3752,30 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                             self.machine_st.p = self.machine_st.cp;
                         }
                     }
+                    &Instruction::CallAtomConcat => {
+                        self.atom_concat();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteAtomConcat => {
+                        self.atom_concat();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallSubAtom => {
+                        self.sub_atom();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSubAtom => {
+                        self.sub_atom();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallReportCrash => {
+                        self.report_toplevel_crash();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteReportCrash => {
+                        self.report_toplevel_crash();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallAtomLength => {
                         self.atom_length();
                         step_or_fail!(self, self.machine_st.p += 1);
@@ -3641,10 +4006,58 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.file_time();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallDynamicModuleResolution(arity) => {
-                        let (module_name, key) = try_or_throw!(
-                            self.machine_st,
-                            self.dynamic_module_resolution(arity - 2)
+                    &Instruction::CallFileType => {
+                        self.file_type();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFileType => {
+                        self.file_type();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallDeleteDirectoryTree => {
+                        try_or_throw!(self.machine_st, self.delete_directory_tree());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteDeleteDirectoryTree => {
+                        try_or_throw!(self.machine_st, self.delete_directory_tree());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFswatchCreate => {
+                        try_or_throw!(self.machine_st, self.fswatch_create());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFswatchCreate => {
+                        try_or_throw!(self.machine_st, self.fswatch_create());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFswatchWait => {
+                        try_or_throw!(self.machine_st, self.fswatch_wait());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFswatchWait => {
+                        try_or_throw!(self.machine_st, self.fswatch_wait());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFswatchRemove => {
+                        self.fswatch_remove();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFswatchRemove => {
+                        self.fswatch_remove();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallWildcardMatch => {
+                        self.wildcard_match();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteWildcardMatch => {
+                        self.wildcard_match();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallDynamicModuleResolution(arity) => {
+                        let (module_name, key) = try_or_throw!(
+                            self.machine_st,
+                            self.dynamic_module_resolution(arity - 2)
                         );
 
                         try_or_throw!(self.machine_st, self.call_clause(module_name, key));
@@ -3810,6 +4223,10 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
                     &Instruction::CallHalt | &Instruction::ExecuteHalt => {
+                        if let Some(heartbeat) = &self.machine_st.heartbeat {
+                            heartbeat.stop();
+                        }
+
                         return self.halt();
                     }
                     &Instruction::CallGetLiftedHeapFromOffset => {
@@ -3860,6 +4277,596 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.install_inference_counter());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallEngineCreate => {
+                        self.engine_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEngineCreate => {
+                        self.engine_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEngineDestroy => {
+                        self.engine_destroy();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEngineDestroy => {
+                        self.engine_destroy();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEngineFetch => {
+                        self.engine_fetch();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEngineFetch => {
+                        self.engine_fetch();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEngineNext => {
+                        if let Err(exit_code) = self.engine_next() {
+                            return exit_code;
+                        }
+
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEngineNext => {
+                        if let Err(exit_code) = self.engine_next() {
+                            return exit_code;
+                        }
+
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEnginePost => {
+                        self.engine_post();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEnginePost => {
+                        self.engine_post();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEngineSelf => {
+                        self.engine_self();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEngineSelf => {
+                        self.engine_self();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEngineStatus => {
+                        self.engine_status();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEngineStatus => {
+                        self.engine_status();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEngineYield => {
+                        self.engine_yield(self.machine_st.p + 1);
+                        step_or_fail!(self, self.machine_st.p = BREAK_FROM_DISPATCH_LOOP_LOC);
+                    }
+                    &Instruction::ExecuteEngineYield => {
+                        self.engine_yield(self.machine_st.cp);
+                        step_or_fail!(self, self.machine_st.p = BREAK_FROM_DISPATCH_LOOP_LOC);
+                    }
+                    &Instruction::CallMessageQueueCreate => {
+                        self.message_queue_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueCreate => {
+                        self.message_queue_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMessageQueueDestroy => {
+                        self.message_queue_destroy();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueDestroy => {
+                        self.message_queue_destroy();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMessageQueueNext => {
+                        self.message_queue_next();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueNext => {
+                        self.message_queue_next();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMessageQueueRemove => {
+                        self.message_queue_remove();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueRemove => {
+                        self.message_queue_remove();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMessageQueueSend => {
+                        self.message_queue_send();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueSend => {
+                        self.message_queue_send();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMessageQueueSize => {
+                        self.message_queue_size();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueSize => {
+                        self.message_queue_size();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMessageQueueStream => {
+                        try_or_throw!(self.machine_st, self.message_queue_stream());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueStream => {
+                        try_or_throw!(self.machine_st, self.message_queue_stream());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMessageQueueWait => {
+                        self.message_queue_wait();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMessageQueueWait => {
+                        self.message_queue_wait();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMutexCreate => {
+                        self.mutex_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMutexCreate => {
+                        self.mutex_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMutexDestroy => {
+                        self.mutex_destroy();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMutexDestroy => {
+                        self.mutex_destroy();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMutexHolder => {
+                        self.mutex_holder();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMutexHolder => {
+                        self.mutex_holder();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMutexLock => {
+                        self.mutex_lock();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMutexLock => {
+                        self.mutex_lock();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMutexTrylock => {
+                        self.mutex_trylock();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMutexTrylock => {
+                        self.mutex_trylock();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMutexUnlock => {
+                        self.mutex_unlock();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMutexUnlock => {
+                        self.mutex_unlock();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallConditionCreate => {
+                        self.condition_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteConditionCreate => {
+                        self.condition_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallConditionDestroy => {
+                        self.condition_destroy();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteConditionDestroy => {
+                        self.condition_destroy();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallConditionSignal => {
+                        self.condition_signal();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteConditionSignal => {
+                        self.condition_signal();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallConditionBroadcast => {
+                        self.condition_broadcast();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteConditionBroadcast => {
+                        self.condition_broadcast();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallConditionWait => {
+                        self.condition_wait();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteConditionWait => {
+                        self.condition_wait();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallRateLimiterCreate => {
+                        self.rate_limiter_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteRateLimiterCreate => {
+                        self.rate_limiter_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallRateLimiterAcquire => {
+                        self.rate_limiter_acquire();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteRateLimiterAcquire => {
+                        self.rate_limiter_acquire();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCircuitBreakerCreate => {
+                        self.circuit_breaker_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCircuitBreakerCreate => {
+                        self.circuit_breaker_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCircuitBreakerEnter => {
+                        self.circuit_breaker_enter();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCircuitBreakerEnter => {
+                        self.circuit_breaker_enter();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCircuitBreakerExit => {
+                        self.circuit_breaker_exit();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCircuitBreakerExit => {
+                        self.circuit_breaker_exit();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCircuitBreakerState => {
+                        self.circuit_breaker_state();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCircuitBreakerState => {
+                        self.circuit_breaker_state();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallResilienceObjectDestroy => {
+                        self.resilience_object_destroy();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteResilienceObjectDestroy => {
+                        self.resilience_object_destroy();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallRegexMatches => {
+                        self.regex_matches();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteRegexMatches => {
+                        self.regex_matches();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallRegexSplit => {
+                        self.regex_split();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteRegexSplit => {
+                        self.regex_split();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallRegexReplace => {
+                        self.regex_replace();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteRegexReplace => {
+                        self.regex_replace();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallJsonParse => {
+                        self.json_parse();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteJsonParse => {
+                        self.json_parse();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallJsonGenerate => {
+                        self.json_generate();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteJsonGenerate => {
+                        self.json_generate();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallJsonNextToken => {
+                        try_or_throw!(self.machine_st, self.json_next_token());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteJsonNextToken => {
+                        try_or_throw!(self.machine_st, self.json_next_token());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallYamlParse => {
+                        self.yaml_parse();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteYamlParse => {
+                        self.yaml_parse();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallYamlGenerate => {
+                        self.yaml_generate();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteYamlGenerate => {
+                        self.yaml_generate();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallTomlParse => {
+                        self.toml_parse();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteTomlParse => {
+                        self.toml_parse();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallTomlGenerate => {
+                        self.toml_generate();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteTomlGenerate => {
+                        self.toml_generate();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCsvReadRow => {
+                        try_or_throw!(self.machine_st, self.csv_read_row());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCsvReadRow => {
+                        try_or_throw!(self.machine_st, self.csv_read_row());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCsvRowChars => {
+                        self.csv_row_chars();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCsvRowChars => {
+                        self.csv_row_chars();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallArrayRead => {
+                        try_or_throw!(self.machine_st, self.array_read());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteArrayRead => {
+                        try_or_throw!(self.machine_st, self.array_read());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadCreate => {
+                        self.thread_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadCreate => {
+                        self.thread_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadCreateBackground => {
+                        self.thread_create_background();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadCreateBackground => {
+                        self.thread_create_background();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadCancel => {
+                        self.thread_cancel();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadCancel => {
+                        self.thread_cancel();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallParseTemplate => {
+                        self.parse_template();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteParseTemplate => {
+                        self.parse_template();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFdDomainsIntersection => {
+                        self.fd_domains_intersection();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFdDomainsIntersection => {
+                        self.fd_domains_intersection();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFdDomainsUnion => {
+                        self.fd_domains_union();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFdDomainsUnion => {
+                        self.fd_domains_union();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFdDomainSubtract => {
+                        self.fd_domain_subtract();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFdDomainSubtract => {
+                        self.fd_domain_subtract();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallIntervalNumber => {
+                        try_or_throw!(self.machine_st, self.interval_number());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteIntervalNumber => {
+                        try_or_throw!(self.machine_st, self.interval_number());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallIntervalOp => {
+                        try_or_throw!(self.machine_st, self.interval_op());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteIntervalOp => {
+                        try_or_throw!(self.machine_st, self.interval_op());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallBtreeFindNode => {
+                        self.btree_find_node();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteBtreeFindNode => {
+                        self.btree_find_node();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGroupPairs => {
+                        try_or_throw!(self.machine_st, self.group_pairs());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGroupPairs => {
+                        try_or_throw!(self.machine_st, self.group_pairs());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallBackgroundThreads => {
+                        self.background_threads();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteBackgroundThreads => {
+                        self.background_threads();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCpuCount => {
+                        self.cpu_count();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCpuCount => {
+                        self.cpu_count();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadGoal => {
+                        self.thread_goal();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadGoal => {
+                        self.thread_goal();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadStatus => {
+                        self.thread_status();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadStatus => {
+                        self.thread_status();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadJoin => {
+                        self.thread_join();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadJoin => {
+                        self.thread_join();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadSelf => {
+                        self.thread_self();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadSelf => {
+                        self.thread_self();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallThreadSetStatus => {
+                        self.thread_set_status();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteThreadSetStatus => {
+                        self.thread_set_status();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMachineCreate => {
+                        self.machine_create();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMachineCreate => {
+                        self.machine_create();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMachineDestroy => {
+                        self.machine_destroy();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMachineDestroy => {
+                        self.machine_destroy();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMachineStatus => {
+                        self.machine_status();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMachineStatus => {
+                        self.machine_status();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMachineConsult => {
+                        self.machine_consult();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMachineConsult => {
+                        self.machine_consult();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMachineQuery => {
+                        self.machine_query();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMachineQuery => {
+                        self.machine_query();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMachineNext => {
+                        try_or_throw!(self.machine_st, self.machine_next());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMachineNext => {
+                        try_or_throw!(self.machine_st, self.machine_next());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMachineClose => {
+                        self.machine_close();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMachineClose => {
+                        self.machine_close();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallInstallTimeLimit => {
+                        self.install_time_limit();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteInstallTimeLimit => {
+                        self.install_time_limit();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallInferenceCount => {
                         let global_count = self.machine_st.cwil.global_count.clone();
                         self.inference_count(self.machine_st.registers[1], global_count);
@@ -3942,6 +4949,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.open());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallOpenResource => {
+                        try_or_throw!(self.machine_st, self.open_resource());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteOpenResource => {
+                        try_or_throw!(self.machine_st, self.open_resource());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallSetStreamOptions => {
                         try_or_throw!(self.machine_st, self.set_stream_options());
                         self.machine_st.p += 1;
@@ -4038,6 +5053,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.read_query_term());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallHistory => {
+                        self.history();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteHistory => {
+                        self.history();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallReadTerm => {
                         try_or_throw!(self.machine_st, self.read_term());
                         step_or_fail!(self, self.machine_st.p += 1);
@@ -4070,6 +5093,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.remove_inference_counter();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallRemoveTimeLimit => {
+                        self.machine_st.time_limits.pop();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteRemoveTimeLimit => {
+                        self.machine_st.time_limits.pop();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallResetContinuationMarker => {
                         self.reset_continuation_marker();
                         self.machine_st.p += 1;
@@ -4171,86 +5202,260 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                     &Instruction::CallFail | &Instruction::ExecuteFail => {
                         self.machine_st.backtrack();
                     }
-                    &Instruction::CallGetBall => {
-                        self.get_ball();
+                    &Instruction::CallGetBall => {
+                        self.get_ball();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetBall => {
+                        self.get_ball();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetCurrentBlock => {
+                        self.get_current_block();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetCurrentBlock => {
+                        self.get_current_block();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetCurrentSCCBlock => {
+                        self.get_current_scc_block();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetCurrentSCCBlock => {
+                        self.get_current_scc_block();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetCutPoint => {
+                        self.get_cut_point();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetCutPoint => {
+                        self.get_cut_point();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetDoubleQuotes => {
+                        self.get_double_quotes();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetDoubleQuotes => {
+                        self.get_double_quotes();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetUnknown => {
+                        self.get_unknown();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetUnknown => {
+                        self.get_unknown();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetModuleUnknown => {
+                        try_or_throw!(self.machine_st, self.get_module_unknown());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetModuleUnknown => {
+                        try_or_throw!(self.machine_st, self.get_module_unknown());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetErrorLocations => {
+                        self.get_error_locations();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetErrorLocations => {
+                        self.get_error_locations();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetPreferRationals => {
+                        self.get_prefer_rationals();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetPreferRationals => {
+                        self.get_prefer_rationals();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetFloatFlag => {
+                        self.get_float_flag();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetFloatFlag => {
+                        self.get_float_flag();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetRationalFlag => {
+                        self.get_rational_flag();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetRationalFlag => {
+                        self.get_rational_flag();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetIntegerRoundingFunction => {
+                        self.get_integer_rounding_function();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetIntegerRoundingFunction => {
+                        self.get_integer_rounding_function();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallGetStyleCheck => {
+                        self.get_style_check();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteGetStyleCheck => {
+                        self.get_style_check();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallInstallNewBlock => {
+                        self.machine_st
+                            .install_new_block(self.machine_st.registers[1]);
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteInstallNewBlock => {
+                        self.machine_st
+                            .install_new_block(self.machine_st.registers[1]);
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallRandomInteger => {
+                        self.random_integer();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteRandomInteger => {
+                        self.random_integer();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMaybe => {
+                        self.maybe();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMaybe => {
+                        self.maybe();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallCpuNow => {
+                        self.cpu_now();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteCpuNow => {
+                        self.cpu_now();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMemoryUsage => {
+                        self.memory_usage();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMemoryUsage => {
+                        self.memory_usage();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallChoicePointCount => {
+                        self.choice_point_count();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteChoicePointCount => {
+                        self.choice_point_count();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallProfilerStart => {
+                        self.profiler_start();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteProfilerStart => {
+                        self.profiler_start();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallProfilerStop => {
+                        self.profiler_stop();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteProfilerStop => {
+                        self.profiler_stop();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallProfileData => {
+                        try_or_throw!(self.machine_st, self.profile_data());
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteGetBall => {
-                        self.get_ball();
+                    &Instruction::ExecuteProfileData => {
+                        try_or_throw!(self.machine_st, self.profile_data());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallGetCurrentBlock => {
-                        self.get_current_block();
+                    &Instruction::CallXref => {
+                        try_or_throw!(self.machine_st, self.xref());
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteGetCurrentBlock => {
-                        self.get_current_block();
+                    &Instruction::ExecuteXref => {
+                        try_or_throw!(self.machine_st, self.xref());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallGetCurrentSCCBlock => {
-                        self.get_current_scc_block();
+                    &Instruction::CallModuleImports => {
+                        try_or_throw!(self.machine_st, self.module_imports());
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteGetCurrentSCCBlock => {
-                        self.get_current_scc_block();
+                    &Instruction::ExecuteModuleImports => {
+                        try_or_throw!(self.machine_st, self.module_imports());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallGetCutPoint => {
-                        self.get_cut_point();
+                    &Instruction::CallVisiblePredicates => {
+                        try_or_throw!(self.machine_st, self.visible_predicates());
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteGetCutPoint => {
-                        self.get_cut_point();
+                    &Instruction::ExecuteVisiblePredicates => {
+                        try_or_throw!(self.machine_st, self.visible_predicates());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallGetDoubleQuotes => {
-                        self.get_double_quotes();
+                    &Instruction::CallModuleGraph => {
+                        try_or_throw!(self.machine_st, self.write_module_graph());
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteGetDoubleQuotes => {
-                        self.get_double_quotes();
+                    &Instruction::ExecuteModuleGraph => {
+                        try_or_throw!(self.machine_st, self.write_module_graph());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallGetUnknown => {
-                        self.get_unknown();
+                    &Instruction::CallTraceMode => {
+                        self.trace_mode();
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteGetUnknown => {
-                        self.get_unknown();
+                    &Instruction::ExecuteTraceMode => {
+                        self.trace_mode();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallInstallNewBlock => {
-                        self.machine_st
-                            .install_new_block(self.machine_st.registers[1]);
+                    &Instruction::CallTraceSpy => {
+                        self.trace_spy();
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteInstallNewBlock => {
-                        self.machine_st
-                            .install_new_block(self.machine_st.registers[1]);
+                    &Instruction::ExecuteTraceSpy => {
+                        self.trace_spy();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallRandomInteger => {
-                        self.random_integer();
+                    &Instruction::CallTraceCallAt => {
+                        self.trace_call_at(false);
+                    }
+                    &Instruction::ExecuteTraceCallAt => {
+                        self.trace_call_at(true);
+                    }
+                    &Instruction::CallTraceEvent => {
+                        self.trace_event();
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteRandomInteger => {
-                        self.random_integer();
+                    &Instruction::ExecuteTraceEvent => {
+                        self.trace_event();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallMaybe => {
-                        self.maybe();
+                    &Instruction::CallSignalAction => {
+                        self.signal_action();
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteMaybe => {
-                        self.maybe();
+                    &Instruction::ExecuteSignalAction => {
+                        self.signal_action();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
-                    &Instruction::CallCpuNow => {
-                        self.cpu_now();
+                    &Instruction::CallPendingSignals => {
+                        try_or_throw!(self.machine_st, self.pending_signals());
                         step_or_fail!(self, self.machine_st.p += 1);
                     }
-                    &Instruction::ExecuteCpuNow => {
-                        self.cpu_now();
+                    &Instruction::ExecutePendingSignals => {
+                        try_or_throw!(self.machine_st, self.pending_signals());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
                     &Instruction::CallDeterministicLengthRundown => {
@@ -4291,6 +5496,16 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.http_accept());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallHttpLookupListener => {
+                        #[cfg(feature = "http")]
+                        self.http_lookup_listener();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteHttpLookupListener => {
+                        #[cfg(feature = "http")]
+                        self.http_lookup_listener();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallHttpAnswer => {
                         #[cfg(feature = "http")]
                         try_or_throw!(self.machine_st, self.http_answer());
@@ -4301,6 +5516,56 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.http_answer());
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallHttpWebSocket => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.http_websocket());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteHttpWebSocket => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.http_websocket());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallWsOpen => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_open());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteWsOpen => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_open());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallWsSend => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_send());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteWsSend => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_send());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallWsReceive => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_receive());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteWsReceive => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_receive());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallWsClose => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_close());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteWsClose => {
+                        #[cfg(feature = "http")]
+                        try_or_throw!(self.machine_st, self.ws_close());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallLoadForeignLib => {
                         #[cfg(feature = "ffi")]
                         try_or_throw!(self.machine_st, self.load_foreign_lib());
@@ -4355,6 +5620,54 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.current_time();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallWallTime => {
+                        self.wall_time();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteWallTime => {
+                        self.wall_time();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallMonotonicTime => {
+                        self.monotonic_time();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteMonotonicTime => {
+                        self.monotonic_time();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallStampDateTime => {
+                        self.stamp_date_time();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteStampDateTime => {
+                        self.stamp_date_time();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallDateTimeStamp => {
+                        self.date_time_stamp();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteDateTimeStamp => {
+                        self.date_time_stamp();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFormatDateTime => {
+                        self.format_date_time();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFormatDateTime => {
+                        self.format_date_time();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallParseDateTime => {
+                        self.parse_date_time();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteParseDateTime => {
+                        self.parse_date_time();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallQuotedToken => {
                         self.quoted_token();
                         step_or_fail!(self, self.machine_st.p += 1);
@@ -4455,6 +5768,62 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.set_unknown();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallSetModuleUnknown => {
+                        try_or_throw!(self.machine_st, self.set_module_unknown());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetModuleUnknown => {
+                        try_or_throw!(self.machine_st, self.set_module_unknown());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallSetErrorLocations => {
+                        self.set_error_locations();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetErrorLocations => {
+                        self.set_error_locations();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallSetPreferRationals => {
+                        self.set_prefer_rationals();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetPreferRationals => {
+                        self.set_prefer_rationals();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallSetFloatFlag => {
+                        self.set_float_flag();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetFloatFlag => {
+                        self.set_float_flag();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallSetRationalFlag => {
+                        self.set_rational_flag();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetRationalFlag => {
+                        self.set_rational_flag();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallSetIntegerRoundingFunction => {
+                        self.set_integer_rounding_function();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetIntegerRoundingFunction => {
+                        self.set_integer_rounding_function();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallSetStyleCheck => {
+                        self.set_style_check();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetStyleCheck => {
+                        self.set_style_check();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallSetSeed => {
                         self.set_seed();
                         step_or_fail!(self, self.machine_st.p += 1);
@@ -4463,6 +5832,22 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.set_seed();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallSetShuffleSeed => {
+                        self.set_shuffle_seed();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteSetShuffleSeed => {
+                        self.set_shuffle_seed();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallShuffleUnspecified => {
+                        try_or_throw!(self.machine_st, self.shuffle_unspecified());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteShuffleUnspecified => {
+                        try_or_throw!(self.machine_st, self.shuffle_unspecified());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallSkipMaxList => {
                         try_or_throw!(self.machine_st, self.machine_st.skip_max_list());
                         step_or_fail!(self, self.machine_st.p += 1);
@@ -4511,6 +5896,38 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.socket_server_close());
                         self.machine_st.p = self.machine_st.cp;
                     }
+                    &Instruction::CallUdpSocketOpen => {
+                        try_or_throw!(self.machine_st, self.udp_socket_open());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteUdpSocketOpen => {
+                        try_or_throw!(self.machine_st, self.udp_socket_open());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallUdpSend => {
+                        try_or_throw!(self.machine_st, self.udp_send());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteUdpSend => {
+                        try_or_throw!(self.machine_st, self.udp_send());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallUdpReceive => {
+                        try_or_throw!(self.machine_st, self.udp_receive());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteUdpReceive => {
+                        try_or_throw!(self.machine_st, self.udp_receive());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallUdpSocketClose => {
+                        try_or_throw!(self.machine_st, self.udp_socket_close());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteUdpSocketClose => {
+                        try_or_throw!(self.machine_st, self.udp_socket_close());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallTLSAcceptClient => {
                         #[cfg(feature = "tls")]
                         try_or_throw!(self.machine_st, self.tls_accept_client());
@@ -4537,6 +5954,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                     &Instruction::ExecuteSucceed => {
                         self.machine_st.p = self.machine_st.cp;
                     }
+                    &Instruction::CallTermHash => {
+                        self.term_hash();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteTermHash => {
+                        self.term_hash();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallTermAttributedVariables => {
                         self.term_attributed_variables();
                         step_or_fail!(self, self.machine_st.p += 1);
@@ -4561,6 +5986,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.term_variables_under_max_depth();
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallTermVariant => {
+                        self.term_variant();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteTermVariant => {
+                        self.term_variant();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallTruncateLiftedHeapTo => {
                         self.truncate_lifted_heap_to();
                         self.machine_st.p += 1;
@@ -5043,6 +6476,22 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         self.pop_load_context();
                         self.machine_st.p = self.machine_st.cp;
                     }
+                    &Instruction::CallReportDiagnostic => {
+                        self.report_diagnostic();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteReportDiagnostic => {
+                        self.report_diagnostic();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallStyleWarnings => {
+                        try_or_throw!(self.machine_st, self.style_warnings());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteStyleWarnings => {
+                        try_or_throw!(self.machine_st, self.style_warnings());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallPopLoadStatePayload => {
                         self.pop_load_state_payload();
                         self.machine_st.p += 1;
@@ -5059,6 +6508,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.push_load_context());
                         self.machine_st.p = self.machine_st.cp;
                     }
+                    &Instruction::CallSetLoadContextLine => {
+                        self.set_load_context_line();
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::ExecuteSetLoadContextLine => {
+                        self.set_load_context_line();
+                        self.machine_st.p = self.machine_st.cp;
+                    }
                     &Instruction::CallPushLoadStatePayload => {
                         self.push_load_state_payload();
                         step_or_fail!(self, self.machine_st.p += 1);
@@ -5075,6 +6532,94 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.use_module());
                         self.machine_st.p = self.machine_st.cp;
                     }
+                    &Instruction::CallReexport => {
+                        try_or_throw!(self.machine_st, self.reexport());
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::ExecuteReexport => {
+                        try_or_throw!(self.machine_st, self.reexport());
+                        self.machine_st.p = self.machine_st.cp;
+                    }
+                    &Instruction::CallFreezeModuleInterface => {
+                        try_or_throw!(self.machine_st, self.freeze_module_interface());
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::ExecuteFreezeModuleInterface => {
+                        try_or_throw!(self.machine_st, self.freeze_module_interface());
+                        self.machine_st.p = self.machine_st.cp;
+                    }
+                    &Instruction::CallModuleInterfaceFrozen => {
+                        self.module_interface_frozen();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteModuleInterfaceFrozen => {
+                        self.module_interface_frozen();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallLoadedModule => {
+                        self.loaded_module();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteLoadedModule => {
+                        self.loaded_module();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallModuleExports => {
+                        try_or_throw!(self.machine_st, self.module_exports());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteModuleExports => {
+                        try_or_throw!(self.machine_st, self.module_exports());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallModuleLocation => {
+                        self.module_location();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteModuleLocation => {
+                        self.module_location();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallLoadedFiles => {
+                        try_or_throw!(self.machine_st, self.loaded_files());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteLoadedFiles => {
+                        try_or_throw!(self.machine_st, self.loaded_files());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallEmbeddedLibrary => {
+                        self.embedded_library();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteEmbeddedLibrary => {
+                        self.embedded_library();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallLibraryPaths => {
+                        try_or_throw!(self.machine_st, self.library_paths());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteLibraryPaths => {
+                        try_or_throw!(self.machine_st, self.library_paths());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallFileModified => {
+                        self.file_modified();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteFileModified => {
+                        self.file_modified();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallLoadedFileDynamicPredicates => {
+                        try_or_throw!(self.machine_st, self.loaded_file_dynamic_predicates());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteLoadedFileDynamicPredicates => {
+                        try_or_throw!(self.machine_st, self.loaded_file_dynamic_predicates());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallBuiltInProperty => {
                         let key = self.machine_st.read_predicate_key(
                             self.machine_st.registers[1],
@@ -5185,6 +6730,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         try_or_throw!(self.machine_st, self.flush_term_queue());
                         self.machine_st.p = self.machine_st.cp;
                     }
+                    &Instruction::CallCompleteCompilations => {
+                        try_or_throw!(self.machine_st, self.complete_compilations());
+                        self.machine_st.p += 1;
+                    }
+                    &Instruction::ExecuteCompleteCompilations => {
+                        try_or_throw!(self.machine_st, self.complete_compilations());
+                        self.machine_st.p = self.machine_st.cp;
+                    }
                     &Instruction::CallRemoveModuleExports => {
                         try_or_throw!(self.machine_st, self.remove_module_exports());
                         self.machine_st.p += 1;
@@ -5242,8 +6795,9 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
                     &Instruction::CallFastCallN(arity) => {
-                        let call_at_index =
-                            |wam: &mut Machine, name, arity, ptr| wam.try_call(name, arity, ptr);
+                        let call_at_index = |wam: &mut Machine, module_name, name, arity, ptr| {
+                            wam.try_call(Some(module_name), name, arity, ptr)
+                        };
 
                         try_or_throw!(self.machine_st, self.fast_call(arity, call_at_index));
 
@@ -5252,8 +6806,9 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                         }
                     }
                     &Instruction::ExecuteFastCallN(arity) => {
-                        let call_at_index =
-                            |wam: &mut Machine, name, arity, ptr| wam.try_execute(name, arity, ptr);
+                        let call_at_index = |wam: &mut Machine, module_name, name, arity, ptr| {
+                            wam.try_execute(Some(module_name), name, arity, ptr)
+                        };
 
                         try_or_throw!(self.machine_st, self.fast_call(arity, call_at_index));
 
@@ -5305,6 +6860,22 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
 
                         step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
                     }
+                    &Instruction::CallClauseCandidates => {
+                        try_or_throw!(self.machine_st, self.clause_candidates());
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteClauseCandidates => {
+                        try_or_throw!(self.machine_st, self.clause_candidates());
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
+                    &Instruction::CallAssertedClauseLoc => {
+                        self.asserted_clause_loc();
+                        step_or_fail!(self, self.machine_st.p += 1);
+                    }
+                    &Instruction::ExecuteAssertedClauseLoc => {
+                        self.asserted_clause_loc();
+                        step_or_fail!(self, self.machine_st.p = self.machine_st.cp);
+                    }
                     &Instruction::CallInvokeClauseAtP => {
                         let key_cell = self.machine_st.registers[1];
                         let key = self.machine_st.name_and_arity_from_heap(key_cell).unwrap();
@@ -5456,6 +7027,46 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                 }
             }
 
+            if let Some(err) = self
+                .machine_st
+                .query_budget_exceeded(INSTRUCTIONS_PER_INTERRUPT_POLL as u64)
+            {
+                self.machine_st.throw_query_budget_exception(err);
+                self.machine_st.backtrack();
+                continue;
+            }
+
+            if self.machine_st.query_cancelled() {
+                self.machine_st.throw_query_cancelled_exception();
+                self.machine_st.backtrack();
+                continue;
+            }
+
+            if let Some(heartbeat) = &self.machine_st.heartbeat {
+                heartbeat.beat();
+            }
+
+            if let Some(profiler) = self.machine_st.profiler.as_deref_mut() {
+                profiler.sample(
+                    self.machine_st.p,
+                    self.machine_st.cp,
+                    self.machine_st.e,
+                    &self.machine_st.stack,
+                );
+            }
+
+            if self.machine_st.time_limit_expired() {
+                self.machine_st.throw_time_limit_exception();
+                self.machine_st.backtrack();
+                continue;
+            }
+
+            if threads::take_cancellation() {
+                self.machine_st.throw_thread_cancelled_exception();
+                self.machine_st.backtrack();
+                continue;
+            }
+
             let interrupted = INTERRUPT.load(std::sync::atomic::Ordering::Relaxed);
 
             match INTERRUPT.compare_exchange(
@@ -5489,6 +7100,14 @@ pub(super) fn dispatch_loop(&mut self) -> std::process::ExitCode {
                 }
                 Err(_) => unreachable!(),
             }
+
+            if self.engine_slice_exhausted() {
+                break 'outer;
+            }
+        }
+
+        if let Some(heartbeat) = &self.machine_st.heartbeat {
+            heartbeat.stop();
         }
 
         std::process::ExitCode::SUCCESS
@@ -0,0 +1,268 @@
+use crate::atom_table::*;
+use crate::forms::SubindexKey;
+use crate::types::*;
+
+use fxhash::FxBuildHasher;
+use indexmap::IndexMap;
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+
+// the key of the first argument of a dynamic clause, or of a head
+// looking for the clauses it may unify with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum FirstArgKey {
+    Constant(HeapCellValue),
+    List,
+    Structure(Atom, usize),
+}
+
+impl FirstArgKey {
+    // the keys of a clause with first argument key. a constant with
+    // an alternative, like a big integer that fits a fixnum, has two.
+    fn of_subindex_key(key: SubindexKey) -> impl Iterator<Item = FirstArgKey> {
+        let (key, alternative) = match key {
+            SubindexKey::Constant(constant, alternative) => (
+                FirstArgKey::Constant(constant),
+                alternative.map(FirstArgKey::Constant),
+            ),
+            SubindexKey::List => (FirstArgKey::List, None),
+            SubindexKey::Structure(name, arity) => (FirstArgKey::Structure(name, arity), None),
+        };
+
+        std::iter::once(key).chain(alternative)
+    }
+}
+
+// the clauses of a dynamic predicate hashed by the keys of their
+// first arguments. clauses are referred to by the locations of their
+// code, which are theirs for as long as they live, and ordered by
+// numbers growing from the front to the back of the predicate.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DynamicClauseStore {
+    // the clauses of each key by their order. None keys the clauses
+    // whose first arguments are variables.
+    buckets: IndexMap<Option<FirstArgKey>, BTreeMap<isize, usize>, FxBuildHasher>,
+    // the order and the first argument key of each clause.
+    clauses: IndexMap<usize, (isize, Option<SubindexKey>), FxBuildHasher>,
+    front: isize,
+    back: isize,
+}
+
+impl DynamicClauseStore {
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.clauses.len()
+    }
+
+    pub(crate) fn push_back(&mut self, clause_loc: usize, key: Option<SubindexKey>) {
+        let order = self.back;
+        self.back += 1;
+
+        if self.clauses.is_empty() {
+            self.front = order;
+        }
+
+        self.insert(clause_loc, order, key);
+    }
+
+    pub(crate) fn push_front(&mut self, clause_loc: usize, key: Option<SubindexKey>) {
+        if self.clauses.is_empty() {
+            self.push_back(clause_loc, key);
+            return;
+        }
+
+        self.front -= 1;
+        self.insert(clause_loc, self.front, key);
+    }
+
+    fn insert(&mut self, clause_loc: usize, order: isize, key: Option<SubindexKey>) {
+        match key {
+            Some(key) => {
+                for key in FirstArgKey::of_subindex_key(key) {
+                    self.buckets
+                        .entry(Some(key))
+                        .or_default()
+                        .insert(order, clause_loc);
+                }
+            }
+            None => {
+                self.buckets
+                    .entry(None)
+                    .or_default()
+                    .insert(order, clause_loc);
+            }
+        }
+
+        self.clauses.insert(clause_loc, (order, key));
+    }
+
+    // removes the clause at clause_loc without looking at the others.
+    pub(crate) fn remove(&mut self, clause_loc: usize) {
+        let Some((order, key)) = self.clauses.swap_remove(&clause_loc) else {
+            return;
+        };
+
+        let mut remove_from = |key: Option<FirstArgKey>| {
+            if let Some(bucket) = self.buckets.get_mut(&key) {
+                bucket.remove(&order);
+
+                if bucket.is_empty() {
+                    self.buckets.swap_remove(&key);
+                }
+            }
+        };
+
+        match key {
+            Some(key) => FirstArgKey::of_subindex_key(key).for_each(|key| remove_from(Some(key))),
+            None => remove_from(None),
+        }
+    }
+
+    // moves the clause at clause_loc to new_clause_loc, keeping its
+    // place in the predicate.
+    pub(crate) fn relocate(&mut self, clause_loc: usize, new_clause_loc: usize) {
+        let Some((order, key)) = self.clauses.swap_remove(&clause_loc) else {
+            return;
+        };
+
+        let mut relocate_in = |key: Option<FirstArgKey>| {
+            if let Some(bucket) = self.buckets.get_mut(&key) {
+                bucket.insert(order, new_clause_loc);
+            }
+        };
+
+        match key {
+            Some(key) => FirstArgKey::of_subindex_key(key).for_each(|key| relocate_in(Some(key))),
+            None => relocate_in(None),
+        }
+
+        self.clauses.insert(new_clause_loc, (order, key));
+    }
+
+    // rebuilds the store from the clauses of its predicate, in order,
+    // after they were restored in the middle of the predicate.
+    pub(crate) fn rebuild(&mut self, clauses: impl Iterator<Item = (usize, Option<SubindexKey>)>) {
+        *self = Self::default();
+
+        for (clause_loc, key) in clauses {
+            self.push_back(clause_loc, key);
+        }
+    }
+
+    // the clauses whose first arguments may unify with a first
+    // argument of key, in order.
+    pub(crate) fn candidates(&self, key: FirstArgKey) -> impl Iterator<Item = usize> + '_ {
+        MergedBuckets {
+            keyed: self
+                .buckets
+                .get(&Some(key))
+                .map(|bucket| bucket.iter().peekable()),
+            unkeyed: self
+                .buckets
+                .get(&None)
+                .map(|bucket| bucket.iter().peekable()),
+        }
+    }
+}
+
+type BucketIter<'a> = Peekable<std::collections::btree_map::Iter<'a, isize, usize>>;
+
+struct MergedBuckets<'a> {
+    keyed: Option<BucketIter<'a>>,
+    unkeyed: Option<BucketIter<'a>>,
+}
+
+impl Iterator for MergedBuckets<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let keyed_order = self
+            .keyed
+            .as_mut()
+            .and_then(|iter| iter.peek())
+            .map(|(o, _)| **o);
+        let unkeyed_order = self
+            .unkeyed
+            .as_mut()
+            .and_then(|iter| iter.peek())
+            .map(|(o, _)| **o);
+
+        let iter = match (keyed_order, unkeyed_order) {
+            (Some(k), Some(u)) if u < k => self.unkeyed.as_mut(),
+            (Some(_), _) => self.keyed.as_mut(),
+            (None, Some(_)) => self.unkeyed.as_mut(),
+            (None, None) => return None,
+        };
+
+        iter.and_then(|iter| iter.next())
+            .map(|(_, clause_loc)| *clause_loc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::Fixnum;
+
+    fn atom_key(name: Atom) -> SubindexKey {
+        SubindexKey::Constant(atom_as_cell!(name), None)
+    }
+
+    #[test]
+    fn candidates_keep_clause_order() {
+        let mut store = DynamicClauseStore::default();
+
+        store.push_back(10, Some(atom_key(atom!("a"))));
+        store.push_back(20, None);
+        store.push_front(30, Some(atom_key(atom!("a"))));
+        store.push_back(40, Some(atom_key(atom!("b"))));
+        store.push_front(50, None);
+        store.push_back(60, Some(atom_key(atom!("a"))));
+
+        let a = FirstArgKey::Constant(atom_as_cell!(atom!("a")));
+        let b = FirstArgKey::Constant(atom_as_cell!(atom!("b")));
+        let c = FirstArgKey::Constant(atom_as_cell!(atom!("c")));
+
+        assert_eq!(
+            store.candidates(a).collect::<Vec<_>>(),
+            [50, 30, 10, 20, 60]
+        );
+        assert_eq!(store.candidates(b).collect::<Vec<_>>(), [50, 20, 40]);
+        assert_eq!(store.candidates(c).collect::<Vec<_>>(), [50, 20]);
+
+        store.remove(20);
+        store.remove(30);
+
+        assert_eq!(store.candidates(a).collect::<Vec<_>>(), [50, 10, 60]);
+        assert_eq!(store.len(), 4);
+
+        store.relocate(10, 70);
+
+        assert_eq!(store.candidates(a).collect::<Vec<_>>(), [50, 70, 60]);
+        assert_eq!(store.len(), 4);
+    }
+
+    #[test]
+    fn alternative_keys_find_the_clause() {
+        let mut store = DynamicClauseStore::default();
+        let one = fixnum_as_cell!(Fixnum::build_with(1));
+
+        store.push_back(
+            10,
+            Some(SubindexKey::Constant(atom_as_cell!(atom!("x")), Some(one))),
+        );
+
+        assert_eq!(
+            store
+                .candidates(FirstArgKey::Constant(one))
+                .collect::<Vec<_>>(),
+            [10]
+        );
+
+        store.remove(10);
+
+        assert_eq!(store.candidates(FirstArgKey::Constant(one)).count(), 0);
+        assert!(store.buckets.is_empty());
+    }
+}
@@ -0,0 +1,478 @@
+use crate::atom_table::*;
+use crate::instructions::*;
+use crate::machine::attributed_variables::*;
+use crate::machine::copier::*;
+use crate::machine::heap::*;
+use crate::machine::machine_indices::*;
+use crate::machine::machine_state::*;
+use crate::machine::stack::*;
+use crate::machine::{Machine, BREAK_FROM_DISPATCH_LOOP_LOC, LIB_QUERY_SUCCESS, MAX_ARITY};
+use crate::types::*;
+
+use indexmap::IndexMap;
+
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use web_time::Instant;
+
+static ANONYMOUS_ENGINE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// the registers, stacks and heap of a computation. An engine owns
+// one, which is swapped with that of the machine while the engine
+// runs, so that the goal of the engine is suspended between answers
+// with its choice points intact.
+#[derive(Debug)]
+pub(crate) struct ExecutionState {
+    pdl: Vec<HeapCellValue>,
+    s: HeapPtr,
+    s_offset: usize,
+    p: usize,
+    oip: u32,
+    iip: u32,
+    b: usize,
+    b0: usize,
+    e: usize,
+    num_of_args: usize,
+    cp: usize,
+    attr_var_init: AttrVarInitializer,
+    fail: bool,
+    heap: Heap,
+    mode: MachineMode,
+    stack: Stack,
+    registers: Registers,
+    trail: Vec<TrailEntry>,
+    tr: usize,
+    hb: usize,
+    block: usize,
+    scc_block: usize,
+    ball: Ball,
+    ball_stack: Vec<Ball>,
+    lifted_heap: Heap,
+    cont_pts: Vec<(HeapCellValue, usize, usize)>,
+    // the budget and the cancellation of a query of the library
+    // interface unwind its own stack, so neither reaches into an
+    // engine.
+    query_budget: Option<QueryBudget>,
+    running_query: Option<Arc<ActiveQuery>>,
+    // the heap locations of the global variables, which b_setval/2
+    // and nb_getval/2 set in the heap of the computation. Their
+    // non-backtrackable values are shared by every computation.
+    global_var_locs: IndexMap<Atom, HeapCellValue>,
+    // the inference limits of a computation refer to its own choice
+    // points, but the inferences of every computation are counted
+    // together.
+    cwil: CWIL,
+    // the time limits of a suspended engine. Those of the computation
+    // that resumes it still apply while it runs, and are kept below
+    // its own, of which there are own_time_limits.
+    time_limits: Vec<Option<Instant>>,
+    own_time_limits: usize,
+}
+
+impl ExecutionState {
+    fn new(verify_attrs_loc: usize) -> Result<Self, usize> {
+        let mut heap = Heap::with_cell_capacity(1024)?;
+
+        // as in MachineState::new, the cell at index 0 is reserved for
+        // use by the runtime.
+        heap.push_cell(empty_list_as_cell!())?;
+        heap.store_resource_error();
+
+        Ok(ExecutionState {
+            pdl: vec![],
+            s: HeapPtr::default(),
+            s_offset: 0,
+            p: 0,
+            oip: 0,
+            iip: 0,
+            b: 0,
+            b0: 0,
+            e: 0,
+            num_of_args: 0,
+            cp: 0,
+            attr_var_init: AttrVarInitializer::new(verify_attrs_loc),
+            fail: false,
+            heap,
+            mode: MachineMode::Write,
+            stack: Stack::new(),
+            registers: [heap_loc_as_cell!(0); MAX_ARITY + 1],
+            trail: vec![],
+            tr: 0,
+            hb: 0,
+            block: 0,
+            scc_block: 0,
+            ball: Ball::new(),
+            ball_stack: vec![],
+            lifted_heap: Heap::new(),
+            cont_pts: vec![],
+            query_budget: None,
+            running_query: None,
+            global_var_locs: IndexMap::new(),
+            cwil: CWIL::new(),
+            time_limits: vec![],
+            own_time_limits: 0,
+        })
+    }
+
+    // swaps the state of the engine in for that of the computation
+    // resuming it.
+    fn resume(&mut self, machine_st: &mut MachineState, global_variables: &mut GlobalVarDir) {
+        self.swap(machine_st, global_variables);
+
+        let own_time_limits = mem::take(&mut self.time_limits);
+
+        self.own_time_limits = own_time_limits.len();
+        machine_st.time_limits.extend(own_time_limits);
+    }
+
+    // swaps the state of the computation that resumed the engine back
+    // in. The time limits it had that expired while the engine ran
+    // stay disarmed.
+    fn suspend(&mut self, machine_st: &mut MachineState, global_variables: &mut GlobalVarDir) {
+        let outer_len = machine_st.time_limits.len() - self.own_time_limits;
+
+        self.time_limits = machine_st.time_limits.split_off(outer_len);
+        self.swap(machine_st, global_variables);
+    }
+
+    fn swap(&mut self, machine_st: &mut MachineState, global_variables: &mut GlobalVarDir) {
+        mem::swap(&mut self.pdl, &mut machine_st.pdl);
+        mem::swap(&mut self.s, &mut machine_st.s);
+        mem::swap(&mut self.s_offset, &mut machine_st.s_offset);
+        mem::swap(&mut self.p, &mut machine_st.p);
+        mem::swap(&mut self.oip, &mut machine_st.oip);
+        mem::swap(&mut self.iip, &mut machine_st.iip);
+        mem::swap(&mut self.b, &mut machine_st.b);
+        mem::swap(&mut self.b0, &mut machine_st.b0);
+        mem::swap(&mut self.e, &mut machine_st.e);
+        mem::swap(&mut self.num_of_args, &mut machine_st.num_of_args);
+        mem::swap(&mut self.cp, &mut machine_st.cp);
+        mem::swap(&mut self.attr_var_init, &mut machine_st.attr_var_init);
+        mem::swap(&mut self.fail, &mut machine_st.fail);
+        mem::swap(&mut self.heap, &mut machine_st.heap);
+        mem::swap(&mut self.mode, &mut machine_st.mode);
+        mem::swap(&mut self.stack, &mut machine_st.stack);
+        mem::swap(&mut self.registers, &mut machine_st.registers);
+        mem::swap(&mut self.trail, &mut machine_st.trail);
+        mem::swap(&mut self.tr, &mut machine_st.tr);
+        mem::swap(&mut self.hb, &mut machine_st.hb);
+        mem::swap(&mut self.block, &mut machine_st.block);
+        mem::swap(&mut self.scc_block, &mut machine_st.scc_block);
+        mem::swap(&mut self.ball, &mut machine_st.ball);
+        mem::swap(&mut self.ball_stack, &mut machine_st.ball_stack);
+        mem::swap(&mut self.lifted_heap, &mut machine_st.lifted_heap);
+        mem::swap(&mut self.cont_pts, &mut machine_st.cont_pts);
+        mem::swap(&mut self.query_budget, &mut machine_st.query_budget);
+        mem::swap(&mut self.running_query, &mut machine_st.running_query);
+        mem::swap(&mut self.cwil, &mut machine_st.cwil);
+        mem::swap(
+            &mut self.cwil.global_count,
+            &mut machine_st.cwil.global_count,
+        );
+
+        let mut global_var_locs = IndexMap::new();
+
+        for (key, (_, loc)) in global_variables.iter_mut() {
+            if let Some(value) = mem::replace(loc, self.global_var_locs.swap_remove(key)) {
+                global_var_locs.insert(*key, value);
+            }
+        }
+
+        // a global variable first set by b_setval/2 in the other
+        // computation.
+        for (key, value) in self.global_var_locs.drain(..) {
+            global_variables.insert(key, (Ball::new(), Some(value)));
+        }
+
+        self.global_var_locs = global_var_locs;
+    }
+
+    // forgets the heap location of the global variable key, whose
+    // value nb_setval/2 replaced.
+    fn forget_global_var(&mut self, key: Atom) {
+        self.global_var_locs.swap_remove(&key);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum EngineStatus {
+    // not run yet. The ball holds Template-Goal.
+    Created(Ball),
+    // stopped at a solution of its goal, and resumed by backtracking
+    // into it.
+    Answered,
+    // stopped by engine_yield/1, and resumed at the given code
+    // location.
+    Yielded(usize),
+    // stopped because its time slice ran out, and resumed at the
+    // given code location.
+    Preempted(usize),
+    Running,
+    Finished,
+}
+
+pub(crate) enum EngineAnswer {
+    The(Ball),
+    Exception(Ball),
+    No,
+    // the time slice of the engine ran out before it found an answer.
+    Pending,
+}
+
+// a goal whose answers are computed on demand by the machine that
+// created it, see library(engines).
+#[derive(Debug)]
+pub(crate) struct PrologEngine {
+    // the state of the engine while it is suspended, and that of the
+    // computation that resumed it while it runs. It is dropped once
+    // the engine is finished.
+    state: Option<ExecutionState>,
+    pub(crate) status: EngineStatus,
+    // the location of the template in the heap of the engine, once it
+    // runs.
+    template: HeapCellValue,
+    // the answer passed to engine_yield/1.
+    pub(crate) yielded: Option<Ball>,
+    // the terms posted by engine_post/2 and not yet fetched.
+    pub(crate) posted: VecDeque<Ball>,
+    // the interrupt polls of the dispatch loop left to the engine
+    // before it is preempted, if it runs with a time slice.
+    slice: Option<usize>,
+    preempted: bool,
+}
+
+impl PrologEngine {
+    pub(crate) fn new(goal: Ball, verify_attrs_loc: usize) -> Result<Self, usize> {
+        Ok(PrologEngine {
+            state: Some(ExecutionState::new(verify_attrs_loc)?),
+            status: EngineStatus::Created(goal),
+            template: empty_list_as_cell!(),
+            yielded: None,
+            posted: VecDeque::new(),
+            slice: None,
+            preempted: false,
+        })
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        matches!(self.status, EngineStatus::Running)
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        matches!(self.status, EngineStatus::Finished)
+    }
+
+    pub(crate) fn forget_global_var(&mut self, key: Atom) {
+        if let Some(state) = self.state.as_mut() {
+            state.forget_global_var(key);
+        }
+    }
+}
+
+// copies the term at addr to a ball, from which it can be copied to
+// the heap of another execution state.
+pub(crate) fn copy_to_ball(
+    machine_st: &mut MachineState,
+    addr: HeapCellValue,
+) -> Result<Ball, usize> {
+    let mut ball = Ball::new();
+
+    ball.boundary = machine_st.heap.cell_len();
+    ball.pstr_boundary = copy_term(
+        CopyBallTerm::new(
+            &mut machine_st.attr_var_init.attr_var_queue,
+            &mut machine_st.stack,
+            &mut machine_st.heap,
+            &mut ball.stub,
+        ),
+        addr,
+        AttrVarPolicy::StripAttributes,
+    )?;
+
+    Ok(ball)
+}
+
+// names an engine created without an alias.
+pub(crate) fn anonymous_engine_name(
+    engines: &IndexMap<Atom, PrologEngine>,
+    atom_tbl: &AtomTable,
+) -> Atom {
+    loop {
+        let count = ANONYMOUS_ENGINE_COUNT.fetch_add(1, Ordering::Relaxed);
+        let name = AtomTable::build_with(atom_tbl, &format!("$engine_{count}"));
+
+        if !engines.contains_key(&name) {
+            return name;
+        }
+    }
+}
+
+impl Machine {
+    // runs the engine named name to its next answer, or until it was
+    // given slice interrupt polls (which are INSTRUCTIONS_PER_INTERRUPT_POLL
+    // instructions apart) to find it. The engine must neither be
+    // running nor finished. Returns the exit code if the goal of the
+    // engine halted the machine.
+    pub(crate) fn run_engine(
+        &mut self,
+        name: Atom,
+        slice: Option<usize>,
+    ) -> Result<EngineAnswer, std::process::ExitCode> {
+        let engine = self.engines.get_mut(&name).unwrap();
+        let status = mem::replace(&mut engine.status, EngineStatus::Running);
+
+        engine.slice = slice;
+        engine.preempted = false;
+
+        engine
+            .state
+            .as_mut()
+            .unwrap()
+            .resume(&mut self.machine_st, &mut self.indices.global_variables);
+        self.running_engines.push(name);
+
+        match status {
+            EngineStatus::Created(goal) => {
+                self.allocate_stub_choice_point();
+
+                let h = match goal.copy_and_align_to(&mut self.machine_st.heap) {
+                    Ok(h) => h,
+                    Err(err_loc) => return Ok(self.stop_engine(name, |_| Err(err_loc))),
+                };
+
+                // the ball holds Template-Goal.
+                let s = self.machine_st.heap[h].get_value() as usize;
+                let engine = self.engines.get_mut(&name).unwrap();
+
+                engine.template = heap_loc_as_cell!(s + 1);
+                self.machine_st.registers[1] = heap_loc_as_cell!(s + 2);
+
+                self.machine_st.cp = LIB_QUERY_SUCCESS;
+                self.machine_st.p = self.call_index_p();
+            }
+            EngineStatus::Answered => {
+                self.machine_st.backtrack();
+            }
+            EngineStatus::Yielded(p) | EngineStatus::Preempted(p) => {
+                self.machine_st.p = p;
+            }
+            EngineStatus::Running | EngineStatus::Finished => {
+                unreachable!()
+            }
+        }
+
+        let exit_code = self.dispatch_loop();
+
+        // a preempted engine stopped before the instruction at p, which
+        // may be a call to halt/0.
+        if self.engines[&name].preempted {
+            return Ok(self.stop_engine(name, |_| Ok(EngineAnswer::Pending)));
+        }
+
+        if matches!(
+            self.code[self.machine_st.p],
+            Instruction::CallHalt | Instruction::ExecuteHalt
+        ) {
+            self.stop_engine(name, |_| Ok(EngineAnswer::No));
+            return Err(exit_code);
+        }
+
+        let engine = self.engines.get_mut(&name).unwrap();
+
+        // engine_yield/1 stores the yielded term in the engine, and
+        // sets its status to where the goal resumes.
+        Ok(if let Some(yielded) = engine.yielded.take() {
+            self.stop_engine(name, |_| Ok(EngineAnswer::The(yielded)))
+        } else if !self.machine_st.ball.stub.is_empty() {
+            let ball = mem::replace(&mut self.machine_st.ball, Ball::new());
+            self.stop_engine(name, |_| Ok(EngineAnswer::Exception(ball)))
+        } else if self.machine_st.p == LIB_QUERY_SUCCESS {
+            let template = engine.template;
+            self.stop_engine(name, |machine_st| {
+                copy_to_ball(machine_st, template).map(EngineAnswer::The)
+            })
+        } else {
+            debug_assert_eq!(self.machine_st.p, BREAK_FROM_DISPATCH_LOOP_LOC);
+            self.stop_engine(name, |_| Ok(EngineAnswer::No))
+        })
+    }
+
+    // suspends the engine running under the name name, which is
+    // finished unless answer is a solution of its goal or a yielded
+    // term. The engine is finished by a resource error if the answer
+    // can't be copied.
+    fn stop_engine(
+        &mut self,
+        name: Atom,
+        answer: impl FnOnce(&mut MachineState) -> Result<EngineAnswer, usize>,
+    ) -> EngineAnswer {
+        let answer = answer(&mut self.machine_st).unwrap_or_else(|err_loc| {
+            copy_to_ball(&mut self.machine_st, str_loc_as_cell!(err_loc))
+                .map_or(EngineAnswer::No, EngineAnswer::Exception)
+        });
+
+        let p = self.machine_st.p;
+        let engine = self.engines.get_mut(&name).unwrap();
+
+        engine
+            .state
+            .as_mut()
+            .unwrap()
+            .suspend(&mut self.machine_st, &mut self.indices.global_variables);
+        self.running_engines.pop();
+
+        engine.status = match (&answer, &engine.status) {
+            (EngineAnswer::The(_), _) if p == LIB_QUERY_SUCCESS => EngineStatus::Answered,
+            (EngineAnswer::The(_), EngineStatus::Yielded(p)) => EngineStatus::Yielded(*p),
+            (EngineAnswer::Pending, _) => EngineStatus::Preempted(p),
+            _ => EngineStatus::Finished,
+        };
+
+        if engine.is_finished() {
+            engine.state = None;
+        }
+
+        answer
+    }
+
+    // charges the innermost running engine for an interrupt poll of
+    // the dispatch loop, returning true if its time slice ran out.
+    // Only engines nest dispatch loops inside instructions, so the
+    // engine is that of the current dispatch loop.
+    pub(crate) fn engine_slice_exhausted(&mut self) -> bool {
+        let Some(name) = self.running_engines.last() else {
+            return false;
+        };
+
+        let engine = self.engines.get_mut(name).unwrap();
+
+        match engine.slice.as_mut() {
+            Some(0) => {
+                engine.preempted = true;
+                true
+            }
+            Some(slice) => {
+                *slice -= 1;
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn call_index_p(&self) -> usize {
+        self.indices
+            .code_dir
+            .get(&(atom!("call"), 1))
+            .map(|offset| {
+                self.machine_st
+                    .arena
+                    .code_index_tbl
+                    .get_entry((*offset).into())
+                    .p() as usize
+            })
+            .expect("couldn't get code index")
+    }
+}
@@ -0,0 +1,280 @@
+// the domains of library(clpz) as sorted lists of disjoint intervals,
+// on which the domain operations that visit every interval of their
+// domains are done.
+//
+// clpz represents a domain as a tree of split(Hole, Left, Right),
+// from_to(From, To) and empty nodes, whose bounds are inf, sup or
+// n(Integer). domains whose bounds aren't all fixnums aren't read,
+// leaving them to the Prolog definitions of the operations.
+
+use crate::atom_table::*;
+use crate::functor_macro::*;
+use crate::machine::heap::*;
+use crate::machine::machine_state::*;
+use crate::parser::ast::*;
+use crate::types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Bound {
+    Inf,
+    N(i64),
+    Sup,
+}
+
+impl Bound {
+    fn succ(self) -> Bound {
+        match self {
+            Bound::N(n) => Bound::N(n + 1),
+            b => b,
+        }
+    }
+
+    fn pred(self) -> Bound {
+        match self {
+            Bound::N(n) => Bound::N(n - 1),
+            b => b,
+        }
+    }
+
+    fn is_fixnum(self) -> bool {
+        match self {
+            Bound::N(n) => Fixnum::build_with_checked(n).is_ok(),
+            _ => true,
+        }
+    }
+}
+
+pub(crate) type Intervals = Vec<(Bound, Bound)>;
+
+fn read_bound(machine_st: &MachineState, cell: HeapCellValue) -> Option<Bound> {
+    read_heap_cell!(machine_st.store(machine_st.deref(cell)),
+        (HeapCellValueTag::Atom, (name, arity)) => {
+            match (name, arity) {
+                (atom!("inf"), 0) => Some(Bound::Inf),
+                (atom!("sup"), 0) => Some(Bound::Sup),
+                _ => None,
+            }
+        }
+        (HeapCellValueTag::Str, s) => {
+            let (name, arity) = cell_as_atom_cell!(machine_st.heap[s]).get_name_and_arity();
+
+            if name != atom!("n") || arity != 1 {
+                return None;
+            }
+
+            read_heap_cell!(machine_st.store(machine_st.deref(machine_st.heap[s + 1])),
+                (HeapCellValueTag::Fixnum, n) => {
+                    Some(Bound::N(n.get_num()))
+                }
+                _ => {
+                    None
+                }
+            )
+        }
+        _ => {
+            None
+        }
+    )
+}
+
+// the intervals of the domain at cell, from the least, or None if
+// cell isn't a domain of fixnum bounds. the tree is walked with an
+// explicit stack, since removing values one at a time makes deep
+// trees.
+pub(crate) fn read_domain(machine_st: &MachineState, cell: HeapCellValue) -> Option<Intervals> {
+    let mut intervals = vec![];
+    let mut nodes = vec![cell];
+
+    while let Some(node) = nodes.pop() {
+        read_heap_cell!(machine_st.store(machine_st.deref(node)),
+            (HeapCellValueTag::Atom, (name, arity)) => {
+                if name != atom!("empty") || arity != 0 {
+                    return None;
+                }
+            }
+            (HeapCellValueTag::Str, s) => {
+                match cell_as_atom_cell!(machine_st.heap[s]).get_name_and_arity() {
+                    (atom!("from_to"), 2) => {
+                        let from = read_bound(machine_st, machine_st.heap[s + 1])?;
+                        let to = read_bound(machine_st, machine_st.heap[s + 2])?;
+
+                        if from > to || intervals.last().is_some_and(|&(_, last)| last >= from) {
+                            return None;
+                        }
+
+                        intervals.push((from, to));
+                    }
+                    (atom!("split"), 3) => {
+                        nodes.push(machine_st.heap[s + 3]);
+                        nodes.push(machine_st.heap[s + 2]);
+                    }
+                    _ => {
+                        return None;
+                    }
+                }
+            }
+            _ => {
+                return None;
+            }
+        );
+    }
+
+    Some(intervals)
+}
+
+fn bound_cell(heap: &mut Heap, bound: Bound) -> Result<HeapCellValue, usize> {
+    match bound {
+        Bound::Inf => Ok(atom_as_cell!(atom!("inf"))),
+        Bound::Sup => Ok(atom_as_cell!(atom!("sup"))),
+        Bound::N(n) => Heap::functor_writer(functor!(atom!("n"), [fixnum(n)]))(heap),
+    }
+}
+
+// writes intervals to the heap as the tree intervals_to_domain/2 of
+// library(clpz) makes of them, split in the middle at the hole before
+// the first interval of the right half. the bounds must be fixnums.
+pub(crate) fn domain_cell(
+    heap: &mut Heap,
+    intervals: &[(Bound, Bound)],
+) -> Result<HeapCellValue, usize> {
+    match intervals {
+        [] => Ok(atom_as_cell!(atom!("empty"))),
+        &[(from, to)] => {
+            let from = bound_cell(heap, from)?;
+            let to = bound_cell(heap, to)?;
+
+            Heap::functor_writer(functor!(atom!("from_to"), [cell(from), cell(to)]))(heap)
+        }
+        _ => {
+            let (front, tail) = intervals.split_at(intervals.len() / 2);
+
+            let Bound::N(start) = tail[0].0 else {
+                unreachable!("only the first interval of a domain starts at inf")
+            };
+
+            let hole = start - 1;
+            let left = domain_cell(heap, front)?;
+            let right = domain_cell(heap, tail)?;
+
+            Heap::functor_writer(functor!(
+                atom!("split"),
+                [fixnum(hole), cell(left), cell(right)]
+            ))(heap)
+        }
+    }
+}
+
+// true if the bounds of intervals are fixnums, so that domain_cell
+// can write them.
+pub(crate) fn fixnum_bounds(intervals: &[(Bound, Bound)]) -> bool {
+    intervals
+        .iter()
+        .all(|&(from, to)| from.is_fixnum() && to.is_fixnum())
+}
+
+pub(crate) fn intersection(d1: &[(Bound, Bound)], d2: &[(Bound, Bound)]) -> Intervals {
+    let mut result = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < d1.len() && j < d2.len() {
+        let from = std::cmp::max(d1[i].0, d2[j].0);
+        let to = std::cmp::min(d1[i].1, d2[j].1);
+
+        if from <= to {
+            result.push((from, to));
+        }
+
+        if d1[i].1 < d2[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    result
+}
+
+pub(crate) fn union(d1: &[(Bound, Bound)], d2: &[(Bound, Bound)]) -> Intervals {
+    let mut intervals: Intervals = d1.iter().chain(d2.iter()).cloned().collect();
+    intervals.sort_unstable();
+
+    let mut result: Intervals = Vec::with_capacity(intervals.len());
+
+    for (from, to) in intervals {
+        match result.last_mut() {
+            Some((_, last)) if from <= last.succ() => {
+                *last = std::cmp::max(*last, to);
+            }
+            _ => result.push((from, to)),
+        }
+    }
+
+    result
+}
+
+pub(crate) fn subtract(d1: &[(Bound, Bound)], d2: &[(Bound, Bound)]) -> Intervals {
+    let mut result = vec![];
+    let mut j = 0;
+
+    for &(from, to) in d1 {
+        while j < d2.len() && d2[j].1 < from {
+            j += 1;
+        }
+
+        let mut rest = Some(from);
+
+        for &(sub_from, sub_to) in d2[j..].iter().take_while(|&&(sub_from, _)| sub_from <= to) {
+            let Some(from) = rest else {
+                break;
+            };
+
+            if sub_from > from {
+                result.push((from, sub_from.pred()));
+            }
+
+            rest = if sub_to >= to {
+                None
+            } else {
+                Some(sub_to.succ())
+            };
+        }
+
+        if let Some(from) = rest {
+            result.push((from, to));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Bound::*;
+
+    #[test]
+    fn domain_operations() {
+        let d1 = vec![(Inf, N(3)), (N(5), N(10)), (N(20), Sup)];
+        let d2 = vec![(N(2), N(6)), (N(9), N(21))];
+
+        assert_eq!(
+            intersection(&d1, &d2),
+            vec![(N(2), N(3)), (N(5), N(6)), (N(9), N(10)), (N(20), N(21))]
+        );
+
+        assert_eq!(union(&d1, &d2), vec![(Inf, Sup)]);
+
+        assert_eq!(
+            subtract(&d1, &d2),
+            vec![(Inf, N(1)), (N(7), N(8)), (N(22), Sup)]
+        );
+
+        assert_eq!(subtract(&[(Inf, Sup)], &[(Inf, N(0))]), vec![(N(1), Sup)]);
+        assert_eq!(subtract(&[(N(1), N(5))], &[(N(1), N(5))]), vec![]);
+        assert_eq!(
+            union(&[(N(1), N(2))], &[(N(3), N(4)), (N(6), N(6))]),
+            vec![(N(1), N(4)), (N(6), N(6))]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::atom_table::*;
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use indexmap::IndexMap;
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Config, Event, PollWatcher, RecursiveMode, Watcher};
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+// the watches of library(fswatch). the watchers send the events of
+// the OS to their channels from threads of their own, so watches are
+// shared by every machine of the process like message queues.
+static WATCHES: LazyLock<Mutex<IndexMap<Atom, Arc<PathWatch>>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+static WATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// blocked waiters wake up at least this often to check for
+// interrupts.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// a change is often reported as several events, e.g. the truncation
+// and the writes of a rewritten file, so the events that follow each
+// other within this time are collected into one batch.
+const SETTLE_INTERVAL: Duration = Duration::from_millis(50);
+
+// the longest a batch is collected while events keep coming.
+const MAX_BATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PathChange {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl PathChange {
+    pub(crate) fn as_atom(self) -> Atom {
+        match self {
+            PathChange::Created => atom!("created"),
+            PathChange::Modified => atom!("modified"),
+            PathChange::Deleted => atom!("deleted"),
+        }
+    }
+}
+
+pub(crate) enum WatchOutcome {
+    Changed(Vec<(PathChange, String)>),
+    TimedOut,
+    Interrupted,
+}
+
+pub(crate) struct PathWatch {
+    // the watch lasts as long as its watcher.
+    _watcher: Mutex<Box<dyn Watcher + Send>>,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl PathWatch {
+    // watches path with the file system notifications of the OS, or by
+    // polling it every poll_interval if given. returns the name of the
+    // new watch.
+    pub(crate) fn create(
+        atom_tbl: &AtomTable,
+        path: &Path,
+        poll_interval: Option<Duration>,
+    ) -> notify::Result<Atom> {
+        let (sender, events) = crossbeam_channel::unbounded();
+        let handler = move |event| {
+            let _ = sender.send(event);
+        };
+
+        let mut watcher: Box<dyn Watcher + Send> = match poll_interval {
+            // modification times are compared by the second, so the
+            // contents are compared too.
+            Some(interval) => Box::new(PollWatcher::new(
+                handler,
+                Config::default()
+                    .with_poll_interval(interval)
+                    .with_compare_contents(true),
+            )?),
+            None => Box::new(notify::recommended_watcher(handler)?),
+        };
+
+        watcher.watch(path, RecursiveMode::Recursive)?;
+
+        let count = WATCH_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+        let name = AtomTable::build_with(atom_tbl, &format!("fswatch_{count}"));
+
+        let watch = PathWatch {
+            _watcher: Mutex::new(watcher),
+            events,
+        };
+
+        WATCHES.lock().unwrap().insert(name, Arc::new(watch));
+        Ok(name)
+    }
+
+    pub(crate) fn lookup(name: Atom) -> Option<Arc<PathWatch>> {
+        WATCHES.lock().unwrap().get(&name).cloned()
+    }
+
+    // stops the watch, returning false if there was none named name.
+    pub(crate) fn remove(name: Atom) -> bool {
+        WATCHES.lock().unwrap().shift_remove(&name).is_some()
+    }
+
+    // waits for the next batch of changes, in the standard order of
+    // their paths.
+    pub(crate) fn wait(
+        &self,
+        timeout: Option<Duration>,
+        interrupted: impl Fn() -> bool,
+    ) -> WatchOutcome {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut changes = vec![];
+
+        // the events of changes that are left out, e.g. the access
+        // of a file, don't end the wait.
+        while changes.is_empty() {
+            if interrupted() {
+                return WatchOutcome::Interrupted;
+            }
+
+            let slice = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+
+                    if deadline <= now {
+                        return WatchOutcome::TimedOut;
+                    }
+
+                    (deadline - now).min(INTERRUPT_POLL_INTERVAL)
+                }
+                None => INTERRUPT_POLL_INTERVAL,
+            };
+
+            match self.events.recv_timeout(slice) {
+                Ok(event) => {
+                    let batch_deadline = Instant::now() + MAX_BATCH_INTERVAL;
+                    push_changes(&mut changes, event);
+
+                    while Instant::now() < batch_deadline {
+                        match self.events.recv_timeout(SETTLE_INTERVAL) {
+                            Ok(event) => push_changes(&mut changes, event),
+                            Err(_) => break,
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return WatchOutcome::TimedOut,
+            }
+        }
+
+        WatchOutcome::Changed(settle_changes(changes))
+    }
+}
+
+// pushes the changes of event. errors of the watcher, e.g. of paths
+// that vanish while they're scanned, are left out.
+fn push_changes(changes: &mut Vec<(PathChange, PathBuf)>, event: notify::Result<Event>) {
+    let Ok(event) = event else {
+        return;
+    };
+
+    let existence = |path: &Path| {
+        if path.exists() {
+            PathChange::Created
+        } else {
+            PathChange::Deleted
+        }
+    };
+
+    for path in event.paths {
+        let change = match event.kind {
+            EventKind::Create(_) => PathChange::Created,
+            EventKind::Remove(_) => PathChange::Deleted,
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => PathChange::Deleted,
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => PathChange::Created,
+            // renames are also reported by their From and To events.
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => continue,
+            EventKind::Modify(ModifyKind::Name(_)) => existence(&path),
+            // the changes of the contents of directories are events of
+            // their own.
+            EventKind::Modify(_) if path.is_dir() => continue,
+            EventKind::Modify(_) => PathChange::Modified,
+            EventKind::Any if path.exists() => PathChange::Modified,
+            EventKind::Any => PathChange::Deleted,
+            EventKind::Access(_) | EventKind::Other => continue,
+        };
+
+        changes.push((change, path));
+    }
+}
+
+// sorts changes by their paths, keeping the order of the changes of
+// each path, and leaves out the changes that are implied by others:
+// repeated changes, and the modifications of paths created or deleted
+// in the same batch.
+fn settle_changes(changes: Vec<(PathChange, PathBuf)>) -> Vec<(PathChange, String)> {
+    let mut changes: Vec<_> = changes
+        .into_iter()
+        .map(|(change, path)| (change, path.to_string_lossy().into_owned()))
+        .collect();
+
+    changes.sort_by(|(_, p1), (_, p2)| p1.cmp(p2));
+
+    let mut settled: Vec<(PathChange, String)> = Vec::with_capacity(changes.len());
+
+    for (change, path) in changes {
+        if let Some((last, last_path)) = settled.last() {
+            if *last_path == path {
+                match (*last, change) {
+                    (last, change) if last == change => continue,
+                    (PathChange::Created, PathChange::Modified) => continue,
+                    (PathChange::Modified, PathChange::Deleted) => {
+                        settled.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        settled.push((change, path));
+    }
+
+    settled
+}
@@ -0,0 +1,599 @@
+use crate::atom_table::*;
+use crate::forms::*;
+use crate::instructions::*;
+use crate::parser::ast::*;
+use crate::types::*;
+
+use fxhash::{FxBuildHasher, FxHasher};
+use indexmap::IndexMap;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// the fewest clauses of a run of clauses sharing an indexed argument
+// whose indexing code is cached, below which indexing them costs less
+// than keeping their index.
+pub(crate) const MIN_CLAUSES_INDEX_CACHED: usize = 64;
+
+const INDEX_FILE_MAGIC: &[u8; 8] = b"SCRYIDX1";
+
+// the indexing code of a run of the clauses of a static predicate
+// sharing an indexed argument.
+#[derive(Debug, Clone)]
+pub(crate) struct CachedIndex {
+    // a hash of the indexed argument, the head arguments of the
+    // clauses and the offsets of their code, which the clauses of a
+    // run must agree with for its indexing code to be reused.
+    fingerprint: u64,
+    lines: Vec<IndexingLine>,
+}
+
+// the indexing code cached for a static predicate, passed to the code
+// generator compiling it.
+#[derive(Debug)]
+pub(crate) struct PredicateIndices {
+    source: PathBuf,
+    key: CachedPredicateKey,
+    cached: Vec<CachedIndex>,
+    compiled: Vec<CachedIndex>,
+    // true if the indexing code of some run had to be computed.
+    missed: bool,
+}
+
+impl PredicateIndices {
+    pub(crate) fn lookup(&self, fingerprint: u64) -> Option<Vec<IndexingLine>> {
+        self.cached
+            .iter()
+            .find(|index| index.fingerprint == fingerprint)
+            .map(|index| index.lines.clone())
+    }
+
+    pub(crate) fn record(&mut self, fingerprint: u64, lines: &[IndexingLine], hit: bool) {
+        if !is_cacheable(lines) {
+            return;
+        }
+
+        self.missed |= !hit;
+        self.compiled.push(CachedIndex {
+            fingerprint,
+            lines: lines.to_vec(),
+        });
+    }
+}
+
+// the size and modification time of a source file, which must match
+// those recorded in its index file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SourceStamp {
+    len: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl SourceStamp {
+    pub(crate) fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+        Some(SourceStamp {
+            len: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+type CachedPredicateKey = (Atom, Atom, usize);
+
+// the indexing code of the static predicates of a source file, read
+// from the index file beside it, and that of the predicates compiled
+// from it since, written back once the file is loaded.
+#[derive(Debug)]
+struct FileIndexCache {
+    stamp: Option<SourceStamp>,
+    cached: IndexMap<CachedPredicateKey, Vec<CachedIndex>, FxBuildHasher>,
+    compiled: IndexMap<CachedPredicateKey, Vec<CachedIndex>, FxBuildHasher>,
+    missed: bool,
+}
+
+impl FileIndexCache {
+    fn open(atom_tbl: &AtomTable, source: &Path) -> Self {
+        let stamp = SourceStamp::of(source);
+
+        // an index file left by a different version of the source is
+        // ignored, and overwritten once the source is loaded.
+        let cached = fs::read(index_file_path(source))
+            .ok()
+            .and_then(|bytes| IndexFileReader::new(&bytes, atom_tbl).read(stamp?))
+            .unwrap_or_default();
+
+        FileIndexCache {
+            stamp,
+            cached,
+            compiled: IndexMap::with_hasher(FxBuildHasher::default()),
+            missed: false,
+        }
+    }
+}
+
+/// The first-argument indices of the large static predicates of
+/// loaded files, kept in an index file beside each file, e.g.
+/// `facts.pl.idx` for `facts.pl`, so that loading the file again
+/// doesn't index its clauses anew.
+///
+/// The index of a run of clauses is reused only if the file is
+/// unchanged, and the heads and code offsets of its clauses are those
+/// it was computed from. Runs indexed on floats or big numbers aren't
+/// cached.
+#[derive(Debug, Default)]
+pub(crate) struct IndexCache {
+    files: IndexMap<PathBuf, FileIndexCache, FxBuildHasher>,
+}
+
+impl IndexCache {
+    pub(crate) fn take_indices(
+        &mut self,
+        atom_tbl: &AtomTable,
+        source: &Path,
+        module_name: Atom,
+        (name, arity): PredicateKey,
+    ) -> PredicateIndices {
+        let file = self
+            .files
+            .entry(source.to_path_buf())
+            .or_insert_with(|| FileIndexCache::open(atom_tbl, source));
+
+        let key = (module_name, name, arity);
+
+        PredicateIndices {
+            source: source.to_path_buf(),
+            key,
+            cached: file.cached.swap_remove(&key).unwrap_or_default(),
+            compiled: vec![],
+            missed: false,
+        }
+    }
+
+    pub(crate) fn store_indices(&mut self, indices: PredicateIndices) {
+        if let Some(file) = self.files.get_mut(&indices.source) {
+            file.missed |= indices.missed;
+            file.compiled.insert(indices.key, indices.compiled);
+        }
+    }
+
+    // writes the index file of source if the indices of its predicates
+    // changed since it was read. the cache is only an optimization,
+    // so failing to write it isn't an error.
+    pub(crate) fn save(&mut self, source: &Path) {
+        let Some(file) = self.files.swap_remove(source) else {
+            return;
+        };
+
+        // predicates left in cached weren't compiled by this load.
+        if !file.missed && file.cached.is_empty() {
+            return;
+        }
+
+        let Some(stamp) = file.stamp else {
+            return;
+        };
+
+        let mut writer = IndexFileWriter::default();
+        writer.write(stamp, &file.compiled);
+
+        let _ = fs::write(index_file_path(source), writer.bytes);
+    }
+}
+
+// true if lines switch on no constants but atoms and fixnums, the
+// offsets of other constants differing between runs.
+fn is_cacheable(lines: &[IndexingLine]) -> bool {
+    lines.iter().all(|line| match line {
+        IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(constants)) => {
+            constants.keys().all(|&constant| {
+                matches!(
+                    Literal::try_from(constant),
+                    Ok(Literal::Atom(_) | Literal::Fixnum(_))
+                )
+            })
+        }
+        IndexingLine::DynamicIndexedChoice(_) => false,
+        _ => true,
+    })
+}
+
+fn index_file_path(source: &Path) -> PathBuf {
+    let mut path = source.as_os_str().to_owned();
+    path.push(".idx");
+    PathBuf::from(path)
+}
+
+// the fingerprint of a run of clauses indexed on the argument of index
+// optimal_index, the code of each clause with an indexed argument
+// starting at the offset paired with it.
+pub(crate) fn run_fingerprint<'a>(
+    optimal_index: usize,
+    non_counted_bt: bool,
+    clauses: impl Iterator<Item = (&'a [Term], usize)>,
+) -> u64 {
+    let mut hasher = FxHasher::default();
+
+    optimal_index.hash(&mut hasher);
+    non_counted_bt.hash(&mut hasher);
+
+    for (args, offset) in clauses {
+        offset.hash(&mut hasher);
+        args.len().hash(&mut hasher);
+
+        for arg in args {
+            hash_term(arg, &mut hasher);
+        }
+    }
+
+    hasher.finish()
+}
+
+// hashes term by the names of its atoms rather than their indices,
+// which differ between runs. numbers other than fixnums are hashed by
+// kind only, as runs indexed on them aren't cached.
+fn hash_term(term: &Term, hasher: &mut FxHasher) {
+    match term {
+        Term::AnonVar | Term::Var(..) => 0u8.hash(hasher),
+        Term::Clause(_, name, terms) => {
+            1u8.hash(hasher);
+            name.as_str().hash(hasher);
+            terms.len().hash(hasher);
+
+            for term in terms {
+                hash_term(term, hasher);
+            }
+        }
+        Term::Cons(_, head, tail) => {
+            2u8.hash(hasher);
+            hash_term(head, hasher);
+            hash_term(tail, hasher);
+        }
+        Term::Literal(_, Literal::Atom(name)) => {
+            3u8.hash(hasher);
+            name.as_str().hash(hasher);
+        }
+        Term::Literal(_, Literal::Fixnum(n)) => {
+            4u8.hash(hasher);
+            n.get_num().hash(hasher);
+        }
+        Term::Literal(..) => 5u8.hash(hasher),
+        Term::PartialString(_, string, tail) => {
+            6u8.hash(hasher);
+            string.hash(hasher);
+            hash_term(tail, hasher);
+        }
+        Term::CompleteString(_, string) => {
+            7u8.hash(hasher);
+            string.hash(hasher);
+        }
+    }
+}
+
+#[derive(Default)]
+struct IndexFileWriter {
+    bytes: Vec<u8>,
+}
+
+impl IndexFileWriter {
+    fn write_u64(&mut self, n: u64) {
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_usize(s.len());
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    fn write(
+        &mut self,
+        stamp: SourceStamp,
+        predicates: &IndexMap<CachedPredicateKey, Vec<CachedIndex>, FxBuildHasher>,
+    ) {
+        self.bytes.extend_from_slice(INDEX_FILE_MAGIC);
+        self.write_u64(stamp.len);
+        self.write_u64(stamp.modified_secs);
+        self.write_u64(stamp.modified_nanos as u64);
+
+        let mut entries = IndexFileWriter::default();
+        let mut num_entries = 0;
+
+        for (&(module_name, name, arity), indices) in predicates {
+            for index in indices {
+                let mut entry = IndexFileWriter::default();
+
+                entry.write_str(&module_name.as_str());
+                entry.write_str(&name.as_str());
+                entry.write_usize(arity);
+                entry.write_u64(index.fingerprint);
+
+                if entry.write_lines(&index.lines).is_some() {
+                    entries.bytes.extend(entry.bytes);
+                    num_entries += 1;
+                }
+            }
+        }
+
+        self.write_usize(num_entries);
+        self.bytes.extend(entries.bytes);
+    }
+
+    // writes lines, returning None if they switch on a constant that
+    // can't be written.
+    fn write_lines(&mut self, lines: &[IndexingLine]) -> Option<()> {
+        self.write_usize(lines.len());
+
+        for line in lines {
+            match line {
+                IndexingLine::Indexing(IndexingInstruction::SwitchOnTerm(arg, v, c, l, s)) => {
+                    self.bytes.push(0);
+                    self.write_switch(*arg, [*v, *c, *l, *s]);
+                }
+                IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(arg, v, c, l, s)) => {
+                    self.bytes.push(1);
+                    self.write_switch(*arg, [*v, *c, *l, *s]);
+                }
+                IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(constants)) => {
+                    self.bytes.push(2);
+                    self.write_usize(constants.len());
+
+                    for (&constant, &ptr) in constants {
+                        match Literal::try_from(constant) {
+                            Ok(Literal::Atom(name)) => {
+                                self.bytes.push(0);
+                                self.write_str(&name.as_str());
+                            }
+                            Ok(Literal::Fixnum(n)) => {
+                                self.bytes.push(1);
+                                self.write_u64(n.get_num() as u64);
+                            }
+                            _ => return None,
+                        }
+
+                        self.write_ptr(ptr);
+                    }
+                }
+                IndexingLine::Indexing(IndexingInstruction::SwitchOnStructure(structures)) => {
+                    self.bytes.push(3);
+                    self.write_usize(structures.len());
+
+                    for (&(name, arity), &ptr) in structures {
+                        self.write_str(&name.as_str());
+                        self.write_usize(arity);
+                        self.write_ptr(ptr);
+                    }
+                }
+                IndexingLine::IndexedChoice(choices) => {
+                    self.bytes.push(4);
+                    self.write_usize(choices.len());
+
+                    for choice in choices {
+                        let tag = match choice {
+                            IndexedChoiceInstruction::Try(_) => 0,
+                            IndexedChoiceInstruction::Retry(_) => 1,
+                            IndexedChoiceInstruction::Trust(_) => 2,
+                            IndexedChoiceInstruction::DefaultRetry(_) => 3,
+                            IndexedChoiceInstruction::DefaultTrust(_) => 4,
+                        };
+
+                        self.bytes.push(tag);
+                        self.write_usize(choice.offset());
+                    }
+                }
+                IndexingLine::DynamicIndexedChoice(_) => return None,
+            }
+        }
+
+        Some(())
+    }
+
+    fn write_switch(&mut self, arg: usize, ptrs: [IndexingCodePtr; 4]) {
+        self.write_usize(arg);
+
+        for ptr in ptrs {
+            self.write_ptr(ptr);
+        }
+    }
+
+    fn write_ptr(&mut self, ptr: IndexingCodePtr) {
+        let (tag, offset) = match ptr {
+            IndexingCodePtr::External(offset) => (0, offset),
+            IndexingCodePtr::DynamicExternal(offset) => (1, offset),
+            IndexingCodePtr::Fail => (2, 0),
+            IndexingCodePtr::Internal(offset) => (3, offset),
+        };
+
+        self.bytes.push(tag);
+        self.write_usize(offset);
+    }
+}
+
+struct IndexFileReader<'a> {
+    bytes: &'a [u8],
+    atom_tbl: &'a AtomTable,
+}
+
+impl<'a> IndexFileReader<'a> {
+    fn new(bytes: &'a [u8], atom_tbl: &'a AtomTable) -> Self {
+        Self { bytes, atom_tbl }
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Some(bytes)
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        self.read_bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn read_u64(&mut self) -> Option<u64> {
+        let bytes = self.read_bytes(8)?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    fn read_usize(&mut self) -> Option<usize> {
+        usize::try_from(self.read_u64()?).ok()
+    }
+
+    fn read_atom(&mut self) -> Option<Atom> {
+        let len = self.read_usize()?;
+        let string = std::str::from_utf8(self.read_bytes(len)?).ok()?;
+
+        Some(AtomTable::build_with(self.atom_tbl, string))
+    }
+
+    // the indices of the file, or None if it's malformed or the source
+    // it was written for isn't stamp.
+    fn read(
+        mut self,
+        stamp: SourceStamp,
+    ) -> Option<IndexMap<CachedPredicateKey, Vec<CachedIndex>, FxBuildHasher>> {
+        if self.read_bytes(INDEX_FILE_MAGIC.len())? != INDEX_FILE_MAGIC {
+            return None;
+        }
+
+        let file_stamp = SourceStamp {
+            len: self.read_u64()?,
+            modified_secs: self.read_u64()?,
+            modified_nanos: u32::try_from(self.read_u64()?).ok()?,
+        };
+
+        if file_stamp != stamp {
+            return None;
+        }
+
+        let mut predicates: IndexMap<_, Vec<_>, _> =
+            IndexMap::with_hasher(FxBuildHasher::default());
+
+        for _ in 0..self.read_usize()? {
+            let module_name = self.read_atom()?;
+            let name = self.read_atom()?;
+            let arity = self.read_usize()?;
+            let fingerprint = self.read_u64()?;
+            let lines = self.read_lines()?;
+
+            predicates
+                .entry((module_name, name, arity))
+                .or_default()
+                .push(CachedIndex { fingerprint, lines });
+        }
+
+        self.bytes.is_empty().then_some(predicates)
+    }
+
+    fn read_lines(&mut self) -> Option<Vec<IndexingLine>> {
+        let len = self.read_usize()?;
+        let mut lines = Vec::with_capacity(len.min(self.bytes.len()));
+
+        for _ in 0..len {
+            let line = match self.read_u8()? {
+                0 => {
+                    let (arg, [v, c, l, s]) = self.read_switch()?;
+                    IndexingLine::from(IndexingInstruction::SwitchOnTerm(arg, v, c, l, s))
+                }
+                1 => {
+                    let (arg, [v, c, l, s]) = self.read_switch()?;
+                    IndexingLine::from(IndexingInstruction::SwitchOnSubterm(arg, v, c, l, s))
+                }
+                2 => {
+                    let mut constants = IndexMap::with_hasher(FxBuildHasher::default());
+
+                    for _ in 0..self.read_usize()? {
+                        let constant = match self.read_u8()? {
+                            0 => atom_as_cell!(self.read_atom()?),
+                            1 => fixnum_as_cell!(Fixnum::build_with_checked(
+                                self.read_u64()? as i64
+                            )
+                            .ok()?),
+                            _ => return None,
+                        };
+
+                        constants.insert(constant, self.read_ptr()?);
+                    }
+
+                    IndexingLine::from(IndexingInstruction::SwitchOnConstant(constants))
+                }
+                3 => {
+                    let mut structures = IndexMap::with_hasher(FxBuildHasher::default());
+
+                    for _ in 0..self.read_usize()? {
+                        let name = self.read_atom()?;
+                        let arity = self.read_usize()?;
+
+                        structures.insert((name, arity), self.read_ptr()?);
+                    }
+
+                    IndexingLine::from(IndexingInstruction::SwitchOnStructure(structures))
+                }
+                4 => {
+                    let len = self.read_usize()?;
+                    let mut choices = VecDeque::with_capacity(len.min(self.bytes.len()));
+
+                    for _ in 0..len {
+                        let tag = self.read_u8()?;
+                        let offset = self.read_usize()?;
+
+                        choices.push_back(match tag {
+                            0 => IndexedChoiceInstruction::Try(offset),
+                            1 => IndexedChoiceInstruction::Retry(offset),
+                            2 => IndexedChoiceInstruction::Trust(offset),
+                            3 => IndexedChoiceInstruction::DefaultRetry(offset),
+                            4 => IndexedChoiceInstruction::DefaultTrust(offset),
+                            _ => return None,
+                        });
+                    }
+
+                    IndexingLine::from(choices)
+                }
+                _ => return None,
+            };
+
+            lines.push(line);
+        }
+
+        Some(lines)
+    }
+
+    fn read_switch(&mut self) -> Option<(usize, [IndexingCodePtr; 4])> {
+        let arg = self.read_usize()?;
+        let ptrs = [
+            self.read_ptr()?,
+            self.read_ptr()?,
+            self.read_ptr()?,
+            self.read_ptr()?,
+        ];
+
+        Some((arg, ptrs))
+    }
+
+    fn read_ptr(&mut self) -> Option<IndexingCodePtr> {
+        let tag = self.read_u8()?;
+        let offset = self.read_usize()?;
+
+        match tag {
+            0 => Some(IndexingCodePtr::External(offset)),
+            1 => Some(IndexingCodePtr::DynamicExternal(offset)),
+            2 => Some(IndexingCodePtr::Fail),
+            3 => Some(IndexingCodePtr::Internal(offset)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,448 @@
+// the intervals of library(intervals), closed intervals of floats
+// whose bounds are rounded outwards, so that every operation yields
+// an interval enclosing the exact results of the operation on the
+// reals of its operands.
+//
+// the rounding errors of +, -, *, / and sqrt are found exactly by the
+// error-free transformations TwoSum and FMA, rounding their bounds to
+// the neighbouring float only if the result isn't exact. the
+// elementary functions of the platform aren't correctly rounded, and
+// their bounds are widened by ELEMENTARY_ULPS units in the last place.
+
+use crate::forms::Number;
+use crate::machine::machine_errors::*;
+
+use dashu::base::{Approximation, BitTest, Signed};
+
+use std::f64::consts::PI;
+
+const ELEMENTARY_ULPS: usize = 2;
+
+// below this magnitude, the residuals of products and quotients may
+// be rounded, and their results are widened to both sides.
+const TINY: f64 = f64::MIN_POSITIVE * (1u64 << 53) as f64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Interval {
+    pub(crate) lo: f64,
+    pub(crate) hi: f64,
+}
+
+fn next_down_by(mut f: f64, ulps: usize) -> f64 {
+    for _ in 0..ulps {
+        f = f.next_down();
+    }
+
+    f
+}
+
+fn next_up_by(mut f: f64, ulps: usize) -> f64 {
+    for _ in 0..ulps {
+        f = f.next_up();
+    }
+
+    f
+}
+
+// the bounds of the exact result of an operation rounded to r, where
+// err has the sign of the exact result less r.
+fn enclose(r: f64, err: f64) -> (f64, f64) {
+    if err > 0.0 {
+        (r, r.next_up())
+    } else if err < 0.0 {
+        (r.next_down(), r)
+    } else {
+        (r, r)
+    }
+}
+
+fn is_tiny(f: f64) -> bool {
+    f != 0.0 && f.abs() < TINY
+}
+
+fn add_bounds(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    let err = (a - (s - bb)) + (b - bb);
+
+    enclose(s, err)
+}
+
+fn sub_bounds(a: f64, b: f64) -> (f64, f64) {
+    add_bounds(a, -b)
+}
+
+fn mul_bounds(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+
+    if is_tiny(p) {
+        (p.next_down(), p.next_up())
+    } else {
+        enclose(p, a.mul_add(b, -p))
+    }
+}
+
+fn div_bounds(a: f64, b: f64) -> (f64, f64) {
+    let q = a / b;
+
+    if is_tiny(q) || is_tiny(a) {
+        (q.next_down(), q.next_up())
+    } else {
+        // a / b = q - r / b, where r = q * b - a is exact.
+        let r = q.mul_add(b, -a);
+        enclose(q, -r * b.signum())
+    }
+}
+
+fn sqrt_bounds(a: f64) -> (f64, f64) {
+    let s = a.sqrt();
+
+    if is_tiny(a) {
+        (s.next_down().max(0.0), s.next_up())
+    } else {
+        enclose(s, -s.mul_add(s, -a))
+    }
+}
+
+fn elementary_bounds(r: f64) -> (f64, f64) {
+    (
+        next_down_by(r, ELEMENTARY_ULPS),
+        next_up_by(r, ELEMENTARY_ULPS),
+    )
+}
+
+// the bounds of x^n for x >= 0, by repeated squaring, keeping the
+// bounds of the lower and upper products apart.
+fn pow_bounds(x: f64, mut n: u64) -> (f64, f64) {
+    let (mut base_lo, mut base_hi) = (x, x);
+    let (mut lo, mut hi) = (1.0, 1.0);
+
+    loop {
+        if n & 1 == 1 {
+            lo = mul_bounds(lo, base_lo).0.max(0.0);
+            hi = mul_bounds(hi, base_hi).1;
+        }
+
+        n >>= 1;
+
+        if n == 0 {
+            return (lo, hi);
+        }
+
+        base_lo = mul_bounds(base_lo, base_lo).0.max(0.0);
+        base_hi = mul_bounds(base_hi, base_hi).1;
+    }
+}
+
+// the bounds of the multiples of PI, less offset, that may lie in
+// [lo, hi].
+fn pi_multiples(lo: f64, hi: f64, offset: f64) -> (f64, f64) {
+    let a = div_bounds(lo, PI).0 - offset;
+    let b = div_bounds(hi, PI).1 - offset;
+
+    (next_down_by(a, 4), next_up_by(b, 4))
+}
+
+impl Interval {
+    pub(crate) fn new(lo: f64, hi: f64) -> Result<Interval, EvalError> {
+        if lo.is_nan() || hi.is_nan() {
+            Err(EvalError::Undefined)
+        } else if lo.is_infinite() || hi.is_infinite() {
+            Err(EvalError::FloatOverflow)
+        } else {
+            // turn negative zeros to zeros.
+            Ok(Interval {
+                lo: lo + 0.0,
+                hi: hi + 0.0,
+            })
+        }
+    }
+
+    pub(crate) fn from_number(n: &Number) -> Result<Interval, EvalError> {
+        let approximation = match n {
+            &Number::Fixnum(n) => {
+                let f = n.get_num() as f64;
+
+                if f as i128 == n.get_num() as i128 {
+                    Approximation::Exact(f)
+                } else {
+                    Approximation::Inexact(f, ())
+                }
+            }
+            Number::Integer(n) => match n.to_f64() {
+                Approximation::Exact(f) => Approximation::Exact(f),
+                Approximation::Inexact(f, _) => Approximation::Inexact(f, ()),
+            },
+            Number::Rational(r) => match r.to_f64() {
+                Approximation::Exact(f) => Approximation::Exact(f),
+                Approximation::Inexact(f, _) => Approximation::Inexact(f, ()),
+            },
+            &Number::Float(f) => Approximation::Exact(f.into_inner()),
+        };
+
+        match approximation {
+            Approximation::Exact(f) => Interval::new(f, f),
+            Approximation::Inexact(f, ()) => Interval::new(f.next_down(), f.next_up()),
+        }
+    }
+
+    fn contains_zero(&self) -> bool {
+        self.lo <= 0.0 && 0.0 <= self.hi
+    }
+
+    pub(crate) fn neg(self) -> Result<Interval, EvalError> {
+        Interval::new(-self.hi, -self.lo)
+    }
+
+    pub(crate) fn abs(self) -> Result<Interval, EvalError> {
+        if self.lo >= 0.0 {
+            Ok(self)
+        } else if self.hi <= 0.0 {
+            self.neg()
+        } else {
+            Interval::new(0.0, self.hi.max(-self.lo))
+        }
+    }
+
+    pub(crate) fn add(self, other: Interval) -> Result<Interval, EvalError> {
+        Interval::new(
+            add_bounds(self.lo, other.lo).0,
+            add_bounds(self.hi, other.hi).1,
+        )
+    }
+
+    pub(crate) fn sub(self, other: Interval) -> Result<Interval, EvalError> {
+        Interval::new(
+            sub_bounds(self.lo, other.hi).0,
+            sub_bounds(self.hi, other.lo).1,
+        )
+    }
+
+    fn corners(
+        self,
+        other: Interval,
+        bounds: impl Fn(f64, f64) -> (f64, f64),
+    ) -> Result<Interval, EvalError> {
+        let corners = [
+            bounds(self.lo, other.lo),
+            bounds(self.lo, other.hi),
+            bounds(self.hi, other.lo),
+            bounds(self.hi, other.hi),
+        ];
+
+        let lo = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+        let hi = corners
+            .iter()
+            .map(|c| c.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        Interval::new(lo, hi)
+    }
+
+    pub(crate) fn mul(self, other: Interval) -> Result<Interval, EvalError> {
+        self.corners(other, mul_bounds)
+    }
+
+    pub(crate) fn div(self, other: Interval) -> Result<Interval, EvalError> {
+        if other.lo == 0.0 && other.hi == 0.0 {
+            Err(EvalError::ZeroDivisor)
+        } else if other.contains_zero() {
+            Err(EvalError::Undefined)
+        } else {
+            self.corners(other, div_bounds)
+        }
+    }
+
+    pub(crate) fn min(self, other: Interval) -> Result<Interval, EvalError> {
+        Interval::new(self.lo.min(other.lo), self.hi.min(other.hi))
+    }
+
+    pub(crate) fn max(self, other: Interval) -> Result<Interval, EvalError> {
+        Interval::new(self.lo.max(other.lo), self.hi.max(other.hi))
+    }
+
+    pub(crate) fn sqrt(self) -> Result<Interval, EvalError> {
+        if self.lo < 0.0 {
+            Err(EvalError::Undefined)
+        } else {
+            Interval::new(sqrt_bounds(self.lo).0, sqrt_bounds(self.hi).1)
+        }
+    }
+
+    pub(crate) fn exp(self) -> Result<Interval, EvalError> {
+        Interval::new(
+            elementary_bounds(self.lo.exp()).0.max(0.0),
+            elementary_bounds(self.hi.exp()).1,
+        )
+    }
+
+    pub(crate) fn log(self) -> Result<Interval, EvalError> {
+        if self.lo <= 0.0 {
+            Err(EvalError::Undefined)
+        } else {
+            Interval::new(
+                elementary_bounds(self.lo.ln()).0,
+                elementary_bounds(self.hi.ln()).1,
+            )
+        }
+    }
+
+    pub(crate) fn atan(self) -> Result<Interval, EvalError> {
+        Interval::new(
+            elementary_bounds(self.lo.atan()).0,
+            elementary_bounds(self.hi.atan()).1,
+        )
+    }
+
+    // the extremes of cos lie at the multiples kPI, those of sin at
+    // (k + 1/2)PI, and are maxima for even k.
+    fn periodic(self, f: impl Fn(f64) -> f64, offset: f64) -> Result<Interval, EvalError> {
+        let (a, b) = pi_multiples(self.lo, self.hi, offset);
+
+        if b - a >= 2.0 {
+            return Interval::new(-1.0, 1.0);
+        }
+
+        let (lo1, hi1) = elementary_bounds(f(self.lo));
+        let (lo2, hi2) = elementary_bounds(f(self.hi));
+
+        let mut lo = lo1.min(lo2);
+        let mut hi = hi1.max(hi2);
+
+        let mut k = a.ceil();
+
+        while k <= b {
+            if k % 2.0 == 0.0 {
+                hi = 1.0;
+            } else {
+                lo = -1.0;
+            }
+
+            k += 1.0;
+        }
+
+        Interval::new(lo.max(-1.0), hi.min(1.0))
+    }
+
+    pub(crate) fn sin(self) -> Result<Interval, EvalError> {
+        self.periodic(f64::sin, 0.5)
+    }
+
+    pub(crate) fn cos(self) -> Result<Interval, EvalError> {
+        self.periodic(f64::cos, 0.0)
+    }
+
+    pub(crate) fn tan(self) -> Result<Interval, EvalError> {
+        let (a, b) = pi_multiples(self.lo, self.hi, 0.5);
+
+        if a.ceil() <= b {
+            // the interval may contain a pole.
+            Err(EvalError::Undefined)
+        } else {
+            Interval::new(
+                elementary_bounds(self.lo.tan()).0,
+                elementary_bounds(self.hi.tan()).1,
+            )
+        }
+    }
+
+    pub(crate) fn powi(self, n: i64) -> Result<Interval, EvalError> {
+        let m = n.unsigned_abs();
+
+        let power = if m == 0 {
+            Interval::new(1.0, 1.0)?
+        } else if m % 2 == 1 {
+            let lo = if self.lo < 0.0 {
+                -pow_bounds(-self.lo, m).1
+            } else {
+                pow_bounds(self.lo, m).0
+            };
+
+            let hi = if self.hi < 0.0 {
+                -pow_bounds(-self.hi, m).0
+            } else {
+                pow_bounds(self.hi, m).1
+            };
+
+            Interval::new(lo, hi)?
+        } else if self.lo >= 0.0 {
+            Interval::new(pow_bounds(self.lo, m).0, pow_bounds(self.hi, m).1)?
+        } else if self.hi <= 0.0 {
+            Interval::new(pow_bounds(-self.hi, m).0, pow_bounds(-self.lo, m).1)?
+        } else {
+            Interval::new(0.0, pow_bounds(self.hi.max(-self.lo), m).1)?
+        };
+
+        if n < 0 {
+            Interval::new(1.0, 1.0)?.div(power)
+        } else {
+            Ok(power)
+        }
+    }
+
+    // the exponent of an integer power, saturated to the i64 of the
+    // same parity.
+    pub(crate) fn integer_exponent(n: &Number) -> Option<i64> {
+        match n {
+            &Number::Fixnum(n) => Some(n.get_num()),
+            Number::Integer(n) => Some(match i64::try_from(&**n) {
+                Ok(n) => n,
+                Err(_) if n.is_positive() => i64::MAX - !n.bit(0) as i64,
+                Err(_) => i64::MIN + n.bit(0) as i64,
+            }),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn pow(self, other: Interval) -> Result<Interval, EvalError> {
+        if self.lo <= 0.0 {
+            Err(EvalError::Undefined)
+        } else {
+            other.mul(self.log()?)?.exp()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(lo: f64, hi: f64) -> Interval {
+        Interval::new(lo, hi).unwrap()
+    }
+
+    #[test]
+    fn interval_operations() {
+        let tenth = interval(0.1, 0.1);
+        let sum = tenth.add(tenth).unwrap().add(tenth).unwrap();
+
+        assert!(sum.lo <= 0.30000000000000004 && 0.30000000000000004 <= sum.hi);
+        assert_eq!(sum.hi, sum.lo.next_up());
+
+        assert_eq!(
+            interval(1.0, 2.0).add(interval(1.0, 1.0)).unwrap(),
+            interval(2.0, 3.0)
+        );
+        assert_eq!(
+            interval(-1.0, 2.0).mul(interval(-3.0, 1.0)).unwrap(),
+            interval(-6.0, 3.0)
+        );
+        assert_eq!(interval(-2.0, 3.0).powi(2).unwrap(), interval(0.0, 9.0));
+        assert_eq!(interval(-2.0, 3.0).powi(3).unwrap(), interval(-8.0, 27.0));
+        assert_eq!(interval(4.0, 9.0).sqrt().unwrap(), interval(2.0, 3.0));
+
+        let third = interval(1.0, 1.0).div(interval(3.0, 3.0)).unwrap();
+        assert_eq!(third.hi, third.lo.next_up());
+
+        let root = interval(2.0, 2.0).sqrt().unwrap();
+        assert!(root.mul(root).unwrap().lo <= 2.0);
+        assert!(root.mul(root).unwrap().hi >= 2.0);
+
+        assert_eq!(interval(0.0, 7.0).cos().unwrap(), interval(-1.0, 1.0));
+        assert_eq!(interval(1.0, 2.0).sin().unwrap().hi, 1.0);
+        assert!(interval(1.0, 2.0).tan().is_err());
+
+        assert!(interval(1.0, 1.0).div(interval(-1.0, 1.0)).is_err());
+        assert!(interval(-1.0, 1.0).sqrt().is_err());
+    }
+}
@@ -0,0 +1,499 @@
+use crate::heap_print::fmt_float;
+use crate::parser::char_reader::*;
+use crate::parser::dashu::Integer;
+
+use std::str::FromStr;
+
+// the deepest nesting of arrays and objects read into a value, which
+// bounds the recursion of the reader and of the writers of its terms.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+// the largest exponent of an integer written with one, as in 15e2.
+// larger exponents are read as floats rather than building huge
+// integers.
+const MAX_INTEGER_EXPONENT: u32 = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonToken {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    NameSeparator,
+    ValueSeparator,
+    String(String),
+    Number(JsonNumber),
+    True,
+    False,
+    Null,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum JsonValue {
+    Object(Vec<(String, JsonValue)>),
+    Array(Vec<JsonValue>),
+    String(String),
+    Number(JsonNumber),
+    Boolean(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonNumber {
+    Integer(Integer),
+    Float(f64),
+}
+
+// the text read isn't JSON, or the input failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct JsonSyntaxError;
+
+// reads the tokens and values of JSON text from chars, as they're
+// needed, so that a document needn't be read whole.
+pub(crate) struct JsonReader<R> {
+    chars: R,
+}
+
+impl<R: CharRead> JsonReader<R> {
+    pub(crate) fn new(chars: R) -> Self {
+        JsonReader { chars }
+    }
+
+    fn peek(&mut self) -> Result<Option<char>, JsonSyntaxError> {
+        match self.chars.peek_char() {
+            Some(Ok(c)) => Ok(Some(c)),
+            Some(Err(_)) => Err(JsonSyntaxError),
+            None => Ok(None),
+        }
+    }
+
+    fn next(&mut self) -> Result<char, JsonSyntaxError> {
+        match self.peek()? {
+            Some(c) => {
+                self.chars.consume(c.len_utf8());
+                Ok(c)
+            }
+            None => Err(JsonSyntaxError),
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), JsonSyntaxError> {
+        while let Some(c @ (' ' | '\t' | '\n' | '\r')) = self.peek()? {
+            self.chars.consume(c.len_utf8());
+        }
+
+        Ok(())
+    }
+
+    // true if only whitespace is left to read.
+    pub(crate) fn at_end(&mut self) -> Result<bool, JsonSyntaxError> {
+        self.skip_whitespace()?;
+        Ok(self.peek()?.is_none())
+    }
+
+    // the next token, or None at the end of the text.
+    pub(crate) fn next_token(&mut self) -> Result<Option<JsonToken>, JsonSyntaxError> {
+        self.skip_whitespace()?;
+
+        let Some(c) = self.peek()? else {
+            return Ok(None);
+        };
+
+        let token = match c {
+            '{' => JsonToken::BeginObject,
+            '}' => JsonToken::EndObject,
+            '[' => JsonToken::BeginArray,
+            ']' => JsonToken::EndArray,
+            ':' => JsonToken::NameSeparator,
+            ',' => JsonToken::ValueSeparator,
+            '"' => return self.read_string().map(|s| Some(JsonToken::String(s))),
+            '-' | '0'..='9' => return self.read_number().map(|n| Some(JsonToken::Number(n))),
+            't' => return self.read_literal("true", JsonToken::True).map(Some),
+            'f' => return self.read_literal("false", JsonToken::False).map(Some),
+            'n' => return self.read_literal("null", JsonToken::Null).map(Some),
+            _ => return Err(JsonSyntaxError),
+        };
+
+        self.chars.consume(c.len_utf8());
+        Ok(Some(token))
+    }
+
+    fn read_literal(
+        &mut self,
+        literal: &str,
+        token: JsonToken,
+    ) -> Result<JsonToken, JsonSyntaxError> {
+        for expected in literal.chars() {
+            if self.next()? != expected {
+                return Err(JsonSyntaxError);
+            }
+        }
+
+        Ok(token)
+    }
+
+    fn read_hex_escape(&mut self) -> Result<u32, JsonSyntaxError> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = self.next()?.to_digit(16).ok_or(JsonSyntaxError)?;
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn read_string(&mut self) -> Result<String, JsonSyntaxError> {
+        let mut string = String::new();
+
+        self.next()?;
+
+        loop {
+            match self.next()? {
+                '"' => return Ok(string),
+                '\\' => {
+                    let c = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let mut code = self.read_hex_escape()?;
+
+                            // characters beyond the basic multilingual
+                            // plane are escaped as surrogate pairs.
+                            if (0xd800..0xdc00).contains(&code) {
+                                if self.next()? != '\\' || self.next()? != 'u' {
+                                    return Err(JsonSyntaxError);
+                                }
+
+                                let low = self.read_hex_escape()?;
+
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(JsonSyntaxError);
+                                }
+
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+
+                            char::from_u32(code).ok_or(JsonSyntaxError)?
+                        }
+                        _ => return Err(JsonSyntaxError),
+                    };
+
+                    string.push(c);
+                }
+                c if c < ' ' => return Err(JsonSyntaxError),
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn read_digits(&mut self, number: &mut String) -> Result<(), JsonSyntaxError> {
+        let start = number.len();
+
+        while let Some(c @ '0'..='9') = self.peek()? {
+            self.chars.consume(1);
+            number.push(c);
+        }
+
+        if number.len() == start {
+            Err(JsonSyntaxError)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_number(&mut self) -> Result<JsonNumber, JsonSyntaxError> {
+        let mut number = String::new();
+
+        if self.peek()? == Some('-') {
+            self.chars.consume(1);
+            number.push('-');
+        }
+
+        if self.peek()? == Some('0') {
+            self.chars.consume(1);
+            number.push('0');
+        } else {
+            self.read_digits(&mut number)?;
+        }
+
+        if self.peek()? == Some('.') {
+            self.chars.consume(1);
+            number.push('.');
+            self.read_digits(&mut number)?;
+        }
+
+        if let Some(c @ ('e' | 'E')) = self.peek()? {
+            self.chars.consume(1);
+            number.push(c);
+
+            if let Some(c @ ('+' | '-')) = self.peek()? {
+                self.chars.consume(1);
+                number.push(c);
+            }
+
+            self.read_digits(&mut number)?;
+        }
+
+        json_number(&number).ok_or(JsonSyntaxError)
+    }
+
+    // reads the next value, which must follow.
+    pub(crate) fn read_value(&mut self) -> Result<JsonValue, JsonSyntaxError> {
+        let token = self.next_token()?.ok_or(JsonSyntaxError)?;
+        self.read_value_from(token, 0)
+    }
+
+    fn read_value_from(
+        &mut self,
+        token: JsonToken,
+        depth: usize,
+    ) -> Result<JsonValue, JsonSyntaxError> {
+        if depth == MAX_DEPTH {
+            return Err(JsonSyntaxError);
+        }
+
+        let value = match token {
+            JsonToken::BeginObject => {
+                let mut members = vec![];
+                let mut token = self.next_token()?.ok_or(JsonSyntaxError)?;
+
+                if token != JsonToken::EndObject {
+                    loop {
+                        let JsonToken::String(name) = token else {
+                            return Err(JsonSyntaxError);
+                        };
+
+                        if self.next_token()? != Some(JsonToken::NameSeparator) {
+                            return Err(JsonSyntaxError);
+                        }
+
+                        let value_token = self.next_token()?.ok_or(JsonSyntaxError)?;
+                        members.push((name, self.read_value_from(value_token, depth + 1)?));
+
+                        match self.next_token()? {
+                            Some(JsonToken::ValueSeparator) => {}
+                            Some(JsonToken::EndObject) => break,
+                            _ => return Err(JsonSyntaxError),
+                        }
+
+                        token = self.next_token()?.ok_or(JsonSyntaxError)?;
+                    }
+                }
+
+                JsonValue::Object(members)
+            }
+            JsonToken::BeginArray => {
+                let mut elements = vec![];
+                let mut token = self.next_token()?.ok_or(JsonSyntaxError)?;
+
+                if token != JsonToken::EndArray {
+                    loop {
+                        elements.push(self.read_value_from(token, depth + 1)?);
+
+                        match self.next_token()? {
+                            Some(JsonToken::ValueSeparator) => {}
+                            Some(JsonToken::EndArray) => break,
+                            _ => return Err(JsonSyntaxError),
+                        }
+
+                        token = self.next_token()?.ok_or(JsonSyntaxError)?;
+                    }
+                }
+
+                JsonValue::Array(elements)
+            }
+            JsonToken::String(string) => JsonValue::String(string),
+            JsonToken::Number(number) => JsonValue::Number(number),
+            JsonToken::True => JsonValue::Boolean(true),
+            JsonToken::False => JsonValue::Boolean(false),
+            JsonToken::Null => JsonValue::Null,
+            _ => return Err(JsonSyntaxError),
+        };
+
+        Ok(value)
+    }
+}
+
+// the value of the number written as number. as by json_chars//1 of
+// library(serialization/json), numbers without a fraction or a
+// negative exponent are integers, and other numbers are floats. None
+// if number is too large for a float.
+fn json_number(number: &str) -> Option<JsonNumber> {
+    let (mantissa, exponent) = match number.find(['e', 'E']) {
+        Some(e) => (&number[..e], number[e + 1..].parse::<i64>().ok()),
+        None => (number, Some(0)),
+    };
+
+    if !mantissa.contains('.') {
+        if let Some(exponent) = exponent.and_then(|exponent| u32::try_from(exponent).ok()) {
+            if exponent <= MAX_INTEGER_EXPONENT {
+                if let Ok(mantissa) = Integer::from_str(mantissa) {
+                    return Some(JsonNumber::Integer(
+                        mantissa * Integer::from(10).pow(exponent as usize),
+                    ));
+                }
+            }
+        }
+    }
+
+    let float: f64 = number.parse().ok()?;
+    float.is_finite().then_some(JsonNumber::Float(float))
+}
+
+// writes string as a JSON string, escaping the characters that must
+// be escaped.
+pub(crate) fn write_json_string(json: &mut String, string: &str) {
+    json.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '\u{8}' => json.push_str("\\b"),
+            '\u{c}' => json.push_str("\\f"),
+            c if c < ' ' => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
+
+// writes the JSON text of value, without whitespace.
+pub(crate) fn write_json_value(json: &mut String, value: &JsonValue) {
+    match value {
+        JsonValue::Object(members) => {
+            json.push('{');
+
+            for (i, (key, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+
+                write_json_string(json, key);
+                json.push(':');
+                write_json_value(json, value);
+            }
+
+            json.push('}');
+        }
+        JsonValue::Array(values) => {
+            json.push('[');
+
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+
+                write_json_value(json, value);
+            }
+
+            json.push(']');
+        }
+        JsonValue::String(string) => write_json_string(json, string),
+        JsonValue::Number(JsonNumber::Integer(n)) => json.push_str(&n.to_string()),
+        JsonValue::Number(JsonNumber::Float(n)) => json.push_str(&fmt_float(*n)),
+        JsonValue::Boolean(true) => json.push_str("true"),
+        JsonValue::Boolean(false) => json.push_str("false"),
+        JsonValue::Null => json.push_str("null"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> Result<JsonValue, JsonSyntaxError> {
+        let mut reader = JsonReader::new(CharReader::new(text.as_bytes()));
+        let value = reader.read_value()?;
+
+        if reader.at_end()? {
+            Ok(value)
+        } else {
+            Err(JsonSyntaxError)
+        }
+    }
+
+    #[test]
+    fn reads_values() {
+        assert_eq!(
+            read(r#" {"a": [1, -2.5e3, true], "b\u00e9\ud83d\ude00": null} "#),
+            Ok(JsonValue::Object(vec![
+                (
+                    "a".to_string(),
+                    JsonValue::Array(vec![
+                        JsonValue::Number(JsonNumber::Integer(Integer::from(1))),
+                        JsonValue::Number(JsonNumber::Float(-2500.0)),
+                        JsonValue::Boolean(true),
+                    ])
+                ),
+                ("bé😀".to_string(), JsonValue::Null),
+            ]))
+        );
+
+        assert_eq!(read("[]"), Ok(JsonValue::Array(vec![])));
+        assert_eq!(read("{}"), Ok(JsonValue::Object(vec![])));
+    }
+
+    #[test]
+    fn rejects_invalid_text() {
+        for text in [
+            "",
+            "[1,]",
+            "{\"a\" 1}",
+            "01",
+            "1.",
+            "-",
+            "\"\n\"",
+            "tru",
+            "[1] 2",
+            "\"\\ud800\"",
+            "1.0e999",
+        ] {
+            assert_eq!(read(text), Err(JsonSyntaxError), "{text:?}");
+        }
+
+        assert_eq!(read(&"[".repeat(MAX_DEPTH + 1)), Err(JsonSyntaxError));
+    }
+
+    #[test]
+    fn converts_numbers() {
+        assert_eq!(
+            json_number("0"),
+            Some(JsonNumber::Integer(Integer::from(0)))
+        );
+        assert_eq!(
+            json_number("-15e2"),
+            Some(JsonNumber::Integer(Integer::from(-1500)))
+        );
+        assert_eq!(json_number("15e-1"), Some(JsonNumber::Float(1.5)));
+        assert_eq!(json_number("2.0"), Some(JsonNumber::Float(2.0)));
+        assert_eq!(json_number("1e99999"), None);
+        assert_eq!(json_number("1.0e999"), None);
+    }
+
+    #[test]
+    fn writes_strings() {
+        let mut json = String::new();
+        write_json_string(&mut json, "a\"\\\n\u{1}é");
+        assert_eq!(json, r#""a\"\\\n\u0001é""#);
+    }
+
+    #[test]
+    fn writes_values() {
+        let text = r#"{"a":[1,2.5,"x"],"b":{},"c":[true,false,null]}"#;
+        let mut json = String::new();
+        write_json_value(&mut json, &read(text).unwrap());
+        assert_eq!(json, text);
+    }
+}
@@ -1,11 +1,27 @@
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
-use std::rc::Rc;
-
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::SystemTime;
+
+use crate::arithmetic::float_cmp;
 use crate::atom_table;
+use crate::forms::{ArenaFrom, MetaSpec, ModuleExport, PredicateKey, VarComparison};
 use crate::heap_iter::{stackful_post_order_iter, NonListElider};
-use crate::machine::machine_indices::VarKey;
+use crate::heap_print::fmt_float;
+use crate::machine::config::{MachineConfig, UserInput};
+use crate::machine::heap::sized_iter_to_heap_list;
+use crate::machine::loader::CompilationTarget;
+use crate::machine::machine_indices::{CodeIndex, VarKey};
+use crate::machine::machine_state::{ActiveQuery, Heartbeat, QueryBudget};
 use crate::machine::mock_wam::CompositeOpDir;
+use crate::machine::threads;
+use crate::machine::xref::XrefPredicate;
 use crate::machine::{
     ArenaHeaderTag, Fixnum, Number, BREAK_FROM_DISPATCH_LOOP_LOC, LIB_QUERY_SUCCESS,
 };
@@ -17,8 +33,12 @@
 
 use dashu::{Integer, Rational};
 use indexmap::IndexMap;
+use ordered_float::OrderedFloat;
 
-use super::{streams::Stream, Atom, AtomCell, HeapCellValue, HeapCellValueTag, Machine};
+use super::{
+    streams::{Callback, Stream},
+    Atom, AtomCell, HeapCellValue, HeapCellValueTag, Machine,
+};
 
 #[cfg(test)]
 mod tests;
@@ -52,15 +72,688 @@ pub fn from_bindings<S: Into<String>>(bindings: impl IntoIterator<Item = (S, Ter
     }
 }
 
-/// Represents a Prolog term.
+/// Options for running a query with [`Machine::run_query_with_options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Captures the text written to `user_output` and `user_error`
+    /// while producing each leaf answer, see [`QueryState::captured_output`].
+    pub capture_output: bool,
+    /// The number of instructions the machine may execute while
+    /// producing each leaf answer.
+    ///
+    /// The count is checked every 256 instructions, so a query may
+    /// overrun it by that much. Once exceeded, the answer is
+    /// `Err(error(resource_error(steps), call/1))`. The error can't be
+    /// caught by `catch/3` and cleanup goals aren't run.
+    pub step_limit: Option<u64>,
+    /// The number of bytes the heap may grow by while producing each
+    /// leaf answer.
+    ///
+    /// Checked alongside [`QueryOptions::step_limit`]. Once exceeded,
+    /// the answer is `Err(error(resource_error(heap), call/1))`.
+    pub heap_limit: Option<usize>,
+    /// How leaf answers name the variables that aren't variables of
+    /// the query, which keep their names.
+    pub variable_names: AnswerVariableNames,
+}
+
+/// How the variables of a leaf answer that aren't variables of the
+/// query are named, as with the `answer_variable_names` flag of the
+/// toplevel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnswerVariableNames {
+    /// `_A`, `_B`, ..., `_Z`, `_A1`, ..., skipping the names of the
+    /// variables of the query.
+    #[default]
+    Fabricated,
+    /// `A`, `B`, ..., `Z`, `A1`, ..., as `numbervars/3` and
+    /// `write_term/2` name variables, skipping the names of the
+    /// variables of the query.
+    Letters,
+    /// `_` followed by the heap address of the variable, as `write/1`
+    /// names it.
+    Internal,
+}
+
+/// The text written to `user_output` and `user_error` while producing a
+/// leaf answer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapturedOutput {
+    /// The text written to `user_output`.
+    pub user_output: String,
+    /// The text written to `user_error`.
+    pub user_error: String,
+}
+
+/// The severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The load was abandoned.
+    Error,
+    /// The load went on.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// An error or warning reported while loading a program, as returned
+/// by [`Machine::consult_module_string`] and
+/// [`Machine::load_module_string`].
+///
+/// Its [`Display`](fmt::Display) implementation renders it on one line,
+/// as in `user:3: error: syntax_error: incomplete_reduction`.
+#[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
+pub struct Diagnostic {
+    /// The severity of the diagnostic.
+    pub severity: Severity,
+    /// The file being loaded, if any.
+    pub file: Option<String>,
+    /// The line of the diagnostic, from 1, if known.
+    pub line: Option<usize>,
+    /// The column of the diagnostic, from 1, if known.
+    pub column: Option<usize>,
+    /// The kind of the diagnostic, the name of the formal term of an
+    /// error such as `syntax_error` or `existence_error`, or of a
+    /// warning: `singleton_variable`, `discontiguous_predicate`,
+    /// `redefined_predicate`, `initialization_failed` or
+    /// `goal_expansion_failed`.
+    pub kind: String,
+    /// The culprit of the diagnostic, the last argument of its formal
+    /// term: the predicate indicator of a permission error, the reason
+    /// of a syntax error, the names of singleton variables, the
+    /// predicate indicator of a discontiguous or redefined predicate,
+    /// and so on.
+    pub culprit: Option<Term>,
+}
+
+impl Diagnostic {
+    /// Creates the diagnostic of an error term, such as the exception
+    /// of a query or of a load. The line is that of the context of a
+    /// syntax error, as in `error(syntax_error(S), read_term/3:Line)`,
+    /// and the file and line those of a context located by the
+    /// `error_locations` flag, as in
+    /// `error(type_error(T, C), location(atom_length/2, File, Line))`.
+    pub fn from_error(error: &Term) -> Self {
+        Self::from_term(Severity::Error, error)
+    }
+
+    pub(crate) fn from_term(severity: Severity, term: &Term) -> Self {
+        let (formal, file, line) = match term {
+            Term::Compound(name, args) if name == "error" && args.len() == 2 => {
+                let (file, line) = match &args[1] {
+                    Term::Compound(name, args) if name == ":" && args.len() == 2 => {
+                        (None, usize_arg(&args[1]))
+                    }
+                    Term::Compound(name, args) if name == "location" && args.len() == 3 => {
+                        let file = match &args[1] {
+                            Term::Atom(file) => Some(file.clone()),
+                            _ => None,
+                        };
+
+                        (file, usize_arg(&args[2]))
+                    }
+                    _ => (None, None),
+                };
+
+                (&args[0], file, line)
+            }
+            _ => (term, None, None),
+        };
+
+        let (kind, culprit) = match formal {
+            Term::Compound(name, args) => (name.clone(), args.last().cloned()),
+            Term::Atom(name) => (name.clone(), None),
+            _ => (formal.to_string(), None),
+        };
+
+        Diagnostic {
+            severity,
+            file,
+            line,
+            column: None,
+            kind,
+            culprit,
+        }
+    }
+}
+
+fn usize_arg(arg: &Term) -> Option<usize> {
+    match arg {
+        Term::Integer(n) => usize::try_from(n).ok(),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+        }
+
+        if let Some(line) = self.line {
+            write!(f, "{line}:")?;
+
+            if let Some(column) = self.column {
+                write!(f, "{column}:")?;
+            }
+        }
+
+        if self.file.is_some() || self.line.is_some() {
+            f.write_str(" ")?;
+        }
+
+        write!(f, "{}: {}", self.severity, self.kind)?;
+
+        if let Some(culprit) = &self.culprit {
+            write!(f, ": {culprit}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A predicate of a module, displayed as `module:name/arity`.
+///
+/// The module of a predicate loaded outside of a module is `user`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PredicateIndicator {
+    /// The module of the predicate.
+    pub module: String,
+    /// The name of the predicate.
+    pub name: String,
+    /// The arity of the predicate.
+    pub arity: usize,
+}
+
+impl PredicateIndicator {
+    fn from_xref((module, (name, arity)): XrefPredicate) -> Self {
+        PredicateIndicator {
+            module: module.as_str().to_string(),
+            name: name.as_str().to_string(),
+            arity,
+        }
+    }
+}
+
+impl fmt::Display for PredicateIndicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}/{}", self.module, self.name, self.arity)
+    }
+}
+
+/// The properties of a loaded predicate, as returned by
+/// [`Machine::predicates`] and reported by `predicate_property/2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredicateInfo {
+    /// The predicate.
+    pub indicator: PredicateIndicator,
+    /// Whether the predicate is declared `dynamic/1`.
+    pub is_dynamic: bool,
+    /// Whether the predicate is declared `multifile/1`.
+    pub is_multifile: bool,
+    /// Whether the predicate is declared `discontiguous/1`.
+    pub is_discontiguous: bool,
+    /// Whether the predicate is built in.
+    pub is_built_in: bool,
+    /// Whether the predicate is exported by its module.
+    pub is_exported: bool,
+    /// The `meta_predicate/1` declaration of the predicate, as
+    /// `meta_predicate(Head)`.
+    pub meta_predicate: Option<Term>,
+}
+
+/// A call of a loaded predicate to another, as returned by
+/// [`Machine::predicate_calls`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PredicateCall {
+    /// The calling predicate.
+    pub caller: PredicateIndicator,
+    /// The called predicate.
+    pub callee: PredicateIndicator,
+    /// Whether the called predicate is defined, by clauses or by a
+    /// `dynamic/1` declaration.
+    pub is_defined: bool,
+}
+
+/// An import of a module by another, or by `user`, made by
+/// `use_module/1,2` or `reexport/1,2`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModuleImport {
+    /// The importing module.
+    pub importer: String,
+    /// The imported module.
+    pub imported: String,
+}
+
+/// The import graph of the loaded modules, as returned by
+/// [`Machine::module_graph`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleGraph {
+    /// The loaded modules, `user` first and the others in the order
+    /// they were loaded.
+    pub modules: Vec<String>,
+    /// The imports between the modules, each once, grouped by
+    /// importing module.
+    pub imports: Vec<ModuleImport>,
+}
+
+impl ModuleGraph {
+    /// The graph in the DOT language of Graphviz, with an edge from
+    /// each module to each module it imports.
+    pub fn to_dot(&self) -> String {
+        fn quoted(name: &str) -> String {
+            format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        let mut dot = String::from("digraph modules {\n");
+
+        for module in &self.modules {
+            dot += &format!("    {};\n", quoted(module));
+        }
+
+        for import in &self.imports {
+            dot += &format!(
+                "    {} -> {};\n",
+                quoted(&import.importer),
+                quoted(&import.imported)
+            );
+        }
+
+        dot.push('}');
+        dot
+    }
+
+    /// The graph as a JSON object of `modules`, the list of module
+    /// names, and `imports`, the list of imports as objects of
+    /// `importer` and `imported`.
+    pub fn to_json(&self) -> String {
+        let imports: Vec<_> = self
+            .imports
+            .iter()
+            .map(|import| {
+                serde_json::json!({
+                    "importer": import.importer,
+                    "imported": import.imported,
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "modules": self.modules,
+            "imports": imports,
+        })
+        .to_string()
+    }
+}
+
+/// The status of a health check or report, as in the health check
+/// response format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// Healthy.
+    Pass,
+    /// Healthy, with concerns, e.g. memory pressure.
+    Warn,
+    /// Unhealthy.
+    Fail,
+}
+
+impl HealthStatus {
+    fn from_term(term: &Term) -> Option<Self> {
+        match term {
+            Term::Atom(status) if status == "pass" => Some(HealthStatus::Pass),
+            Term::Atom(status) if status == "warn" => Some(HealthStatus::Warn),
+            Term::Atom(status) if status == "fail" => Some(HealthStatus::Fail),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            HealthStatus::Pass => "pass",
+            HealthStatus::Warn => "warn",
+            HealthStatus::Fail => "fail",
+        }
+    }
+}
+
+/// A check of a [`HealthReport`], as `library(health)` reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    /// The name of the check, e.g. `memory:heap` or `readiness:db`.
+    pub name: String,
+    /// The status of the check.
+    pub status: HealthStatus,
+    /// The bytes used, for the `memory:` checks.
+    pub observed_bytes: Option<u64>,
+    /// The exception thrown by the goal of a readiness check.
+    pub output: Option<String>,
+}
+
+/// The health of a machine, returned by [`Machine::health`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    /// `Fail` if a readiness check failed, `Warn` if the memory limit
+    /// of `library(health)` is exceeded, and `Pass` otherwise.
+    pub status: HealthStatus,
+    /// The memory checks, then the readiness checks in the order they
+    /// were added.
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    // the report of health_report/1, with the exceptions of readiness
+    // checks written by health:check_output/2.
+    fn from_term(term: &Term) -> Option<Self> {
+        let Term::Compound(name, args) = term else {
+            return None;
+        };
+
+        let [status, Term::List(checks)] = args.as_slice() else {
+            return None;
+        };
+
+        if name != "health" {
+            return None;
+        }
+
+        let checks = checks
+            .iter()
+            .map(|check| {
+                let Term::Compound(name, args) = check else {
+                    return None;
+                };
+
+                let [Term::Atom(check_name), status, details] = args.as_slice() else {
+                    return None;
+                };
+
+                if name != "check" {
+                    return None;
+                }
+
+                let mut check = HealthCheck {
+                    name: check_name.clone(),
+                    status: HealthStatus::from_term(status)?,
+                    observed_bytes: None,
+                    output: None,
+                };
+
+                if let Term::Compound(name, args) = details {
+                    match (name.as_str(), args.as_slice()) {
+                        ("bytes", [Term::Integer(bytes)]) => {
+                            check.observed_bytes = u64::try_from(bytes).ok();
+                        }
+                        ("output", [Term::String(output)]) => {
+                            check.output = Some(output.clone());
+                        }
+                        _ => {}
+                    }
+                }
+
+                Some(check)
+            })
+            .collect::<Option<_>>()?;
+
+        Some(HealthReport {
+            status: HealthStatus::from_term(status)?,
+            checks,
+        })
+    }
+
+    /// The report as JSON in the health check response format, as
+    /// served with the media type `application/health+json`, and as
+    /// `health_json/1` of `library(health)` writes it.
+    pub fn to_json(&self) -> String {
+        let checks: serde_json::Map<_, _> = self
+            .checks
+            .iter()
+            .map(|check| {
+                let mut object = serde_json::json!({ "status": check.status.as_str() });
+
+                if let Some(bytes) = check.observed_bytes {
+                    object["observedValue"] = bytes.into();
+                    object["observedUnit"] = "bytes".into();
+                }
+
+                if let Some(output) = &check.output {
+                    object["output"] = output.as_str().into();
+                }
+
+                (check.name.clone(), serde_json::json!([object]))
+            })
+            .collect();
+
+        serde_json::json!({
+            "status": self.status.as_str(),
+            "checks": checks,
+        })
+        .to_string()
+    }
+}
+
+/// Whether a machine is running a query, as seen by a
+/// [`HealthMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    /// The machine isn't running a query.
+    Idle,
+    /// The machine is running a query.
+    Running {
+        /// The time since the machine last polled for interrupts.
+        since_heartbeat: Duration,
+    },
+}
+
+impl Liveness {
+    /// Whether the machine is idle or polled for interrupts within
+    /// `max_stall`.
+    ///
+    /// The machine polls every 256 instructions, except while a
+    /// built-in predicate blocks, e.g. `sleep/1` or reading a stream,
+    /// which `max_stall` should allow for.
+    pub fn is_responsive(self, max_stall: Duration) -> bool {
+        match self {
+            Liveness::Idle => true,
+            Liveness::Running { since_heartbeat } => since_heartbeat <= max_stall,
+        }
+    }
+}
+
+/// Watches whether a [`Machine`] is responsive, from any thread, while
+/// the machine itself runs queries. Returned by
+/// [`Machine::health_monitor`].
+#[derive(Debug, Clone)]
+pub struct HealthMonitor {
+    heartbeat: Arc<Heartbeat>,
+}
+
+impl HealthMonitor {
+    /// Whether the machine is running a query and when it last polled
+    /// for interrupts.
+    pub fn liveness(&self) -> Liveness {
+        match self.heartbeat.since_last_poll() {
+            Some(since_heartbeat) => Liveness::Running { since_heartbeat },
+            None => Liveness::Idle,
+        }
+    }
+}
+
+/// A query started by a [`Machine`] and not yet finished, as returned
+/// by [`Machine::active_queries`] and [`QueryState::handle`].
+///
+/// Handles can be sent to other threads, to watch or cancel the query
+/// while the machine computes its answers.
+#[derive(Debug, Clone)]
+pub struct QueryHandle {
+    query: Arc<ActiveQuery>,
+}
+
+impl QueryHandle {
+    /// The number identifying the query among those of its machine.
+    pub fn id(&self) -> u64 {
+        self.query.id
+    }
+
+    /// The text of the query.
+    pub fn query(&self) -> &str {
+        &self.query.query
+    }
+
+    /// The time the query was started.
+    pub fn started_at(&self) -> SystemTime {
+        self.query.started_at
+    }
+
+    /// The time since the query was started.
+    pub fn elapsed(&self) -> Duration {
+        self.query.start.elapsed()
+    }
+
+    /// The number of inferences the query has made.
+    ///
+    /// The count is updated every 256 instructions while the machine
+    /// computes an answer, and once it has computed it.
+    pub fn inferences(&self) -> u64 {
+        self.query.inferences()
+    }
+
+    /// Cancels the query.
+    ///
+    /// The answer the machine is computing, or computes next, is
+    /// `Err(error(query_cancelled, call/1))`, after which the query
+    /// has no more answers. As with [`QueryOptions::step_limit`], the
+    /// error can't be caught by `catch/3` and cleanup goals aren't run.
+    pub fn cancel(&self) {
+        self.query.cancel();
+    }
+
+    /// Whether the query was cancelled by [`QueryHandle::cancel`].
+    pub fn is_cancelled(&self) -> bool {
+        self.query.is_cancelled()
+    }
+
+    /// Whether the query has no more answers, or was dropped.
+    pub fn is_finished(&self) -> bool {
+        self.query.is_finished()
+    }
+}
+
+/// A port of a goal traced by `library(trace)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TracePort {
+    /// The goal is called.
+    Call,
+    /// The goal succeeds.
+    Exit,
+    /// The goal is re-entered on backtracking.
+    Redo,
+    /// The goal has no more answers.
+    Fail,
+}
+
+/// A port reached by the tracer, passed to the hook registered with
+/// [`MachineBuilder::with_trace_hook`](crate::MachineBuilder::with_trace_hook).
 #[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    /// The port reached.
+    pub port: TracePort,
+    /// The depth of the goal, 1 for the goals called by the query.
+    pub depth: usize,
+    /// The goal, as instantiated at the port.
+    pub goal: Term,
+}
+
+/// How the tracer goes on from a port, as returned by a trace hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceAction {
+    /// Go on to the next port.
+    Creep,
+    /// Go on to the exit or fail port of the goal, when at its call
+    /// port. Elsewhere, this is the same as [`TraceAction::Creep`].
+    Skip,
+    /// Go on to the next port of a spy point.
+    Leap,
+    /// Fail the goal.
+    Fail,
+    /// Abort the query.
+    Abort,
+}
+
+// the hook of MachineBuilder::with_trace_hook.
+pub(crate) struct TraceHook(pub(crate) Box<dyn FnMut(&TraceEvent) -> TraceAction>);
+
+impl std::fmt::Debug for TraceHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TraceHook").field(&"<callback>").finish()
+    }
+}
+
+/// The stage of a load reported to the hook registered with
+/// [`MachineBuilder::with_load_progress_hook`](crate::MachineBuilder::with_load_progress_hook).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadStage {
+    /// A file, or another stream, starts loading.
+    Started,
+    /// Another thousand clauses are loaded.
+    Clauses,
+    /// A file, or another stream, is loaded, or its load failed.
+    Loaded,
+    /// The outermost load and the loads it started are done.
+    Finished,
+}
+
+/// The progress of a load, passed to the hook registered with
+/// [`MachineBuilder::with_load_progress_hook`](crate::MachineBuilder::with_load_progress_hook).
+///
+/// The counts and the elapsed time are those of the outermost load,
+/// including the files it loads in turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadProgress {
+    /// The stage reached.
+    pub stage: LoadStage,
+    /// The path of the file being loaded, or `None` if the stream
+    /// isn't read from a file.
+    pub file: Option<String>,
+    /// The files started so far.
+    pub files: usize,
+    /// The clauses loaded so far.
+    pub clauses: usize,
+    /// The time since the outermost load started.
+    pub elapsed: Duration,
+}
+
+// the hook of MachineBuilder::with_load_progress_hook.
+pub(crate) struct LoadProgressHook(pub(crate) Box<dyn FnMut(&LoadProgress)>);
+
+impl std::fmt::Debug for LoadProgressHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LoadProgressHook")
+            .field(&"<callback>")
+            .finish()
+    }
+}
+
+/// Represents a Prolog term.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
 pub enum Term {
     /// An arbitrary precision integer.
     Integer(Integer),
     /// An arbitrary precision rational.
     Rational(Rational),
-    /// A float.
+    /// A float, which may be infinite or NaN.
+    ///
+    /// Floats are equal as in the standard order of terms: all NaNs
+    /// are equal, and `-0.0` and `0.0` differ.
     Float(f64),
     /// A Prolog atom.
     Atom(String),
@@ -150,22 +843,181 @@ pub fn try_disjunction(value: impl IntoIterator<Item = Term>) -> Option<Self> {
     }
 }
 
-/// This is an auxiliary function to turn a count into names of anonymous variables like _A, _B,
-/// _AB, etc...
-fn count_to_letter_code(mut count: usize) -> String {
-    let mut letters = Vec::new();
+impl PartialEq for Term {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Term::Integer(n1), Term::Integer(n2)) => n1 == n2,
+            (Term::Rational(r1), Term::Rational(r2)) => r1 == r2,
+            (Term::Float(f1), Term::Float(f2)) => float_cmp(*f1, *f2) == Ordering::Equal,
+            (Term::Atom(a1), Term::Atom(a2)) => a1 == a2,
+            (Term::String(s1), Term::String(s2)) => s1 == s2,
+            (Term::List(l1), Term::List(l2)) => l1 == l2,
+            (Term::Compound(n1, args1), Term::Compound(n2, args2)) => n1 == n2 && args1 == args2,
+            (Term::Var(v1), Term::Var(v2)) => v1 == v2,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Term {}
+
+impl Term {
+    /// Feeds this term into `state` consistently with the standard order
+    /// of terms: terms that [`Machine::compare_terms`] finds equal hash
+    /// alike, whatever their representation.
+    ///
+    /// A [`Term::String`] hashes as the list of its characters, and a
+    /// [`Term::Rational`] with a denominator of 1 as the integer it is.
+    pub fn hash_standard_order<H: Hasher>(&self, state: &mut H) {
+        // the order categories of the terms, so that terms of distinct
+        // categories are unlikely to collide.
+        const VAR: u8 = 0;
+        const FLOAT: u8 = 1;
+        const NUMBER: u8 = 2;
+        const ATOM: u8 = 3;
+        const COMPOUND: u8 = 4;
+
+        fn hash_atom<H: Hasher>(name: &str, state: &mut H) {
+            ATOM.hash(state);
+            name.hash(state);
+        }
+
+        fn hash_cons<H: Hasher>(state: &mut H) {
+            COMPOUND.hash(state);
+            2usize.hash(state);
+            ".".hash(state);
+        }
+
+        match self {
+            Term::Var(name) => {
+                VAR.hash(state);
+                name.hash(state);
+            }
+            Term::Float(f) => {
+                FLOAT.hash(state);
+                OrderedFloat(*f).hash(state);
+            }
+            Term::Integer(n) => {
+                NUMBER.hash(state);
+                n.hash(state);
+            }
+            Term::Rational(r) if r.denominator().is_one() => {
+                NUMBER.hash(state);
+                r.numerator().hash(state);
+            }
+            Term::Rational(r) => {
+                NUMBER.hash(state);
+                r.hash(state);
+            }
+            Term::Atom(name) => hash_atom(name, state),
+            Term::String(s) => {
+                let mut buf = [0; 4];
+
+                for c in s.chars() {
+                    hash_cons(state);
+                    hash_atom(c.encode_utf8(&mut buf), state);
+                }
+
+                hash_atom("[]", state);
+            }
+            Term::List(elems) => {
+                for elem in elems {
+                    hash_cons(state);
+                    elem.hash_standard_order(state);
+                }
+
+                hash_atom("[]", state);
+            }
+            Term::Compound(name, args) if name == "." && args.len() == 2 => {
+                hash_cons(state);
+                args[0].hash_standard_order(state);
+                args[1].hash_standard_order(state);
+            }
+            Term::Compound(name, args) => {
+                COMPOUND.hash(state);
+                args.len().hash(state);
+                name.hash(state);
+
+                for arg in args {
+                    arg.hash_standard_order(state);
+                }
+            }
+        }
+    }
+}
+
+/// Writes the term in canonical syntax, apart from lists, which are
+/// written in list notation.
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_args(f: &mut fmt::Formatter<'_>, args: &[Term]) -> fmt::Result {
+            for (idx, arg) in args.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(",")?;
+                }
+
+                write!(f, "{arg}")?;
+            }
+
+            Ok(())
+        }
+
+        match self {
+            Term::Integer(n) => write!(f, "{n}"),
+            Term::Rational(r) => write!(f, "{} rdiv {}", r.numerator(), r.denominator()),
+            Term::Float(x) => f.write_str(&fmt_float(*x)),
+            Term::Atom(atom) => write_quoted_atom(f, atom),
+            Term::String(string) => write!(f, "{string:?}"),
+            Term::List(items) => {
+                f.write_str("[")?;
+                write_args(f, items)?;
+                f.write_str("]")
+            }
+            Term::Compound(name, args) => {
+                write_quoted_atom(f, name)?;
+                f.write_str("(")?;
+                write_args(f, args)?;
+                f.write_str(")")
+            }
+            Term::Var(var) => f.write_str(var),
+        }
+    }
+}
+
+fn write_quoted_atom(f: &mut fmt::Formatter<'_>, atom: &str) -> fmt::Result {
+    let is_letter_atom = atom.starts_with(|c: char| c.is_ascii_lowercase())
+        && atom.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let is_symbol_atom = !atom.is_empty() && atom.chars().all(|c| "#$&*+-./:<=>?@^~\\".contains(c));
+
+    if is_letter_atom || is_symbol_atom || matches!(atom, "[]" | "!" | ";" | "{}") {
+        return f.write_str(atom);
+    }
 
-    loop {
-        let letter_idx = (count % 26) as u32;
-        letters.push(char::from_u32('A' as u32 + letter_idx).unwrap());
-        count /= 26;
+    f.write_str("'")?;
 
-        if count == 0 {
-            break;
+    for c in atom.chars() {
+        match c {
+            '\'' => f.write_str("\\'")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            _ => write!(f, "{c}")?,
         }
     }
 
-    letters.into_iter().chain("_".chars()).rev().collect()
+    f.write_str("'")
+}
+
+// the name of the count-th variable named by answers, as
+// charsio:fabricate_var_name/3 names them for the toplevel: A, B, ...,
+// Z, A1, B1, ... after prefix.
+fn fabricate_var_name(prefix: &str, count: usize) -> String {
+    let letter = char::from(b'A' + (count % 26) as u8);
+
+    match count / 26 {
+        0 => format!("{prefix}{letter}"),
+        n => format!("{prefix}{letter}{n}"),
+    }
 }
 
 impl Term {
@@ -173,6 +1025,22 @@ pub(crate) fn from_heapcell(
         machine: &mut Machine,
         heap_cell: HeapCellValue,
         var_names: &mut IndexMap<HeapCellValue, VarPtr>,
+    ) -> Self {
+        Self::from_heapcell_named(
+            machine,
+            heap_cell,
+            var_names,
+            AnswerVariableNames::default(),
+        )
+    }
+
+    // var_names names the variables of the term, to which those named
+    // as set by naming are added.
+    pub(crate) fn from_heapcell_named(
+        machine: &mut Machine,
+        heap_cell: HeapCellValue,
+        var_names: &mut IndexMap<HeapCellValue, VarPtr>,
+        naming: AnswerVariableNames,
     ) -> Self {
         // Adapted from MachineState::read_term_from_heap
         let mut term_stack = vec![];
@@ -249,10 +1117,25 @@ pub(crate) fn from_heapcell(
                     let var = var_names.get(&addr).map(|x| x.borrow().clone());
                     match var {
                         Some(Var::Named(name)) => term_stack.push(Term::Var(name.as_ref().to_owned())),
+                        _ if naming == AnswerVariableNames::Internal => {
+                            // as heap_print names unnamed variables.
+                            let name = if addr.is_stack_var() {
+                                format!("_s_{}", addr.get_value())
+                            } else {
+                                format!("_{}", addr.get_value())
+                            };
+
+                            term_stack.push(Term::Var(name));
+                        }
                         _ => {
+                            let prefix = match naming {
+                                AnswerVariableNames::Letters => "",
+                                _ => "_",
+                            };
+
                             let anon_name = loop {
                                 // Generate a name for the anonymous variable
-                                let anon_name = Rc::new(count_to_letter_code(anon_count));
+                                let anon_name = Arc::new(fabricate_var_name(prefix, anon_count));
 
                                 // Find if this name is already being used
                                 var_names.sort_by(|_, a, _, b| {
@@ -384,15 +1267,116 @@ pub(crate) fn from_heapcell(
         debug_assert_eq!(term_stack.len(), 1);
         term_stack.pop().unwrap()
     }
-}
+
+    // writes the term to the heap of machine, the inverse of
+    // from_heapcell. variables of the same name are shared through
+    // var_cells.
+    pub(crate) fn to_heapcell(
+        &self,
+        machine: &mut Machine,
+        var_cells: &mut IndexMap<String, HeapCellValue>,
+    ) -> Result<HeapCellValue, usize> {
+        let machine_st = &mut machine.machine_st;
+
+        Ok(match self {
+            Term::Integer(n) => {
+                let n = match i64::try_from(n) {
+                    Ok(n) => Number::arena_from(n, &mut machine_st.arena),
+                    Err(_) => Number::arena_from(n.clone(), &mut machine_st.arena),
+                };
+
+                HeapCellValue::arena_from(n, &mut machine_st.arena)
+            }
+            Term::Rational(r) => {
+                let r = Number::arena_from(r.clone(), &mut machine_st.arena);
+                HeapCellValue::arena_from(r, &mut machine_st.arena)
+            }
+            Term::Float(f) => HeapCellValue::from(float_alloc!(*f, machine_st.arena)),
+            Term::Atom(name) => {
+                atom_as_cell!(atom_table::AtomTable::build_with(
+                    &machine_st.atom_tbl,
+                    name
+                ))
+            }
+            Term::String(s) => machine_st.heap.allocate_cstr(s)?,
+            Term::List(elems) => {
+                let cells = elems
+                    .iter()
+                    .map(|elem| elem.to_heapcell(machine, var_cells))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                sized_iter_to_heap_list(
+                    &mut machine.machine_st.heap,
+                    cells.len(),
+                    cells.into_iter(),
+                )?
+            }
+            Term::Compound(name, args) => {
+                let cells = args
+                    .iter()
+                    .map(|arg| arg.to_heapcell(machine, var_cells))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let heap = &mut machine.machine_st.heap;
+                let h = heap.cell_len();
+
+                if name == "." && cells.len() == 2 {
+                    heap.push_cell(cells[0])?;
+                    heap.push_cell(cells[1])?;
+
+                    list_loc_as_cell!(h)
+                } else {
+                    let name =
+                        atom_table::AtomTable::build_with(&machine.machine_st.atom_tbl, name);
+
+                    heap.push_cell(atom_as_cell!(name, cells.len()))?;
+
+                    for cell in cells {
+                        heap.push_cell(cell)?;
+                    }
+
+                    str_loc_as_cell!(h)
+                }
+            }
+            Term::Var(name) => match var_cells.get(name) {
+                Some(&cell) => cell,
+                None => {
+                    let h = machine_st.heap.cell_len();
+                    let cell = heap_loc_as_cell!(h);
+
+                    machine_st.heap.push_cell(cell)?;
+                    var_cells.insert(name.clone(), cell);
+
+                    cell
+                }
+            },
+        })
+    }
+}
 
 /// An iterator though the leaf answers of a query.
 pub struct QueryState<'a> {
     machine: &'a mut Machine,
-    term: TermWriteResult,
-    stub_b: usize,
-    var_names: IndexMap<HeapCellValue, VarPtr>,
-    called: bool,
+    cursor: QueryCursor,
+}
+
+impl QueryState<'_> {
+    /// The output captured while producing the leaf answer last returned
+    /// by the iterator.
+    ///
+    /// This is `None` if the query wasn't run with
+    /// [`QueryOptions::capture_output`] or no answer was returned yet.
+    pub fn captured_output(&self) -> Option<&CapturedOutput> {
+        self.cursor.captured_output.as_ref()
+    }
+
+    /// A handle watching or cancelling the query, as listed by
+    /// [`Machine::active_queries`].
+    pub fn handle(&self) -> QueryHandle {
+        QueryHandle {
+            query: self.cursor.query.clone(),
+        }
+    }
 }
 
 impl Drop for QueryState<'_> {
@@ -407,16 +1391,83 @@ impl Iterator for QueryState<'_> {
     type Item = Result<LeafAnswer, Term>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let var_names = &mut self.var_names;
-        let term_write_result = &self.term;
-        let machine = &mut self.machine;
+        self.cursor.next_answer(self.machine)
+    }
+}
+
+// the state of a running query apart from its machine, so that it can
+// be kept next to the machine rather than borrow it.
+pub(crate) struct QueryCursor {
+    term: TermWriteResult,
+    stub_b: usize,
+    var_names: IndexMap<HeapCellValue, VarPtr>,
+    called: bool,
+    capture_output: bool,
+    captured_output: Option<CapturedOutput>,
+    step_limit: Option<u64>,
+    heap_limit: Option<usize>,
+    variable_names: AnswerVariableNames,
+    query: Arc<ActiveQuery>,
+}
+
+impl Drop for QueryCursor {
+    fn drop(&mut self) {
+        self.query.finish();
+    }
+}
 
+impl QueryCursor {
+    // true once the query has no more answers to produce.
+    pub(crate) fn is_exhausted(&self, machine: &Machine) -> bool {
+        self.called && machine.machine_st.b <= self.stub_b
+    }
+
+    pub(crate) fn next_answer(
+        &mut self,
+        machine: &mut Machine,
+    ) -> Option<Result<LeafAnswer, Term>> {
         // No more choicepoints, end iteration
-        if self.called && machine.machine_st.b <= self.stub_b {
+        if self.is_exhausted(machine) {
+            self.query.finish();
             return None;
         }
 
-        machine.dispatch_loop();
+        let var_names = &mut self.var_names;
+        let term_write_result = &self.term;
+
+        if self.step_limit.is_some() || self.heap_limit.is_some() {
+            let heap_len = machine.machine_st.heap.cell_len();
+
+            machine.machine_st.query_budget = Some(QueryBudget {
+                stub_b: self.stub_b,
+                steps_left: self.step_limit,
+                max_heap_len: self
+                    .heap_limit
+                    .map(|bytes| heap_len + bytes / size_of::<HeapCellValue>()),
+            });
+        }
+
+        machine.machine_st.running_query = Some(self.query.clone());
+
+        // a query cancelled between answers is unwound before it's
+        // resumed.
+        if self.query.is_cancelled() {
+            machine.machine_st.throw_query_cancelled_exception();
+            machine.machine_st.backtrack();
+        }
+
+        if self.capture_output {
+            let capture = machine.redirect_user_streams();
+            machine.dispatch_loop();
+            self.captured_output = Some(machine.restore_user_streams(capture));
+        } else {
+            machine.dispatch_loop();
+        }
+
+        machine.machine_st.query_budget = None;
+        machine.machine_st.running_query = None;
+        self.query
+            .update_inferences(&machine.machine_st.cwil.global_count);
 
         self.called = true;
 
@@ -440,8 +1491,14 @@ fn next(&mut self) -> Option<Self::Item> {
                 )));
             }
 
-            let exception_term =
-                Term::from_heapcell(machine, machine.machine_st.heap[h], &mut var_names.clone());
+            let exception_term = Term::from_heapcell_named(
+                machine,
+                machine.machine_st.heap[h],
+                &mut var_names.clone(),
+                self.variable_names,
+            );
+
+            machine.machine_st.ball.reset();
 
             if let Term::Compound(functor, args) = &exception_term {
                 if functor == "error" && args.len() == 2 {
@@ -456,7 +1513,7 @@ fn next(&mut self) -> Option<Self::Item> {
 
         if machine.machine_st.p == LIB_QUERY_SUCCESS {
             if term_write_result.var_dict.is_empty() {
-                self.machine.machine_st.backtrack();
+                machine.machine_st.backtrack();
                 return Some(Ok(LeafAnswer::True));
             }
         } else if machine.machine_st.p == BREAK_FROM_DISPATCH_LOOP_LOC {
@@ -467,6 +1524,10 @@ fn next(&mut self) -> Option<Self::Item> {
 
         let var_dict = &term_write_result.var_dict;
 
+        // shared by the bindings, so that a variable is named alike in
+        // each.
+        let mut answer_var_names = var_names.clone();
+
         for (var_key, term_to_be_printed) in var_dict.iter() {
             let mut var_name = var_key.to_string();
             if var_name.starts_with('_') {
@@ -479,8 +1540,12 @@ fn next(&mut self) -> Option<Self::Item> {
                 }
             }
 
-            let mut term =
-                Term::from_heapcell(machine, *term_to_be_printed, &mut var_names.clone());
+            let mut term = Term::from_heapcell_named(
+                machine,
+                *term_to_be_printed,
+                &mut answer_var_names,
+                self.variable_names,
+            );
 
             if let Term::Var(ref term_str) = term {
                 if *term_str == var_name {
@@ -511,29 +1576,737 @@ fn next(&mut self) -> Option<Self::Item> {
         // through them for further solutions. if
         // self.machine_st.b == stub_b we've backtracked to the stub
         // choice point, so we should break.
-        self.machine.machine_st.backtrack();
+        machine.machine_st.backtrack();
 
         Some(Ok(LeafAnswer::LeafAnswer { bindings }))
     }
 }
 
+/// A consult of a module whose source text arrives in pieces, as
+/// returned by [`Machine::consult_module_feed`].
+///
+/// Each clause is compiled as soon as its text is complete, so the
+/// source is never held whole. A feed dropped before it's finished is
+/// finished then, discarding its diagnostics.
+pub struct ModuleFeed<'a> {
+    machine: &'a mut Machine,
+    cursor: QueryCursor,
+    sender: Option<Sender<Vec<u8>>>,
+    splitter: ClauseSplitter,
+    outer_diagnostics: Option<Vec<Diagnostic>>,
+    concluded: bool,
+}
+
+impl ModuleFeed<'_> {
+    /// Compiles the clauses completed by `text`, the next piece of the
+    /// source. The text of a clause not yet ended is kept until the rest
+    /// of it arrives.
+    pub fn push(&mut self, text: impl AsRef<[u8]>) {
+        if let Some(clauses) = self.splitter.push(text.as_ref()) {
+            self.send(clauses);
+        }
+    }
+
+    /// Compiles the rest of the source and concludes the consult,
+    /// returning the errors and warnings of the load.
+    pub fn finish(mut self) -> Vec<Diagnostic> {
+        self.conclude()
+    }
+
+    fn send(&mut self, text: Vec<u8>) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        if sender.send(text).is_ok() {
+            self.resume();
+        }
+    }
+
+    // runs the load until it has compiled the text sent to it. an error
+    // ends the load, which then reported the error as a diagnostic.
+    fn resume(&mut self) {
+        match self.cursor.next_answer(self.machine) {
+            Some(Ok(LeafAnswer::LeafAnswer { bindings }))
+                if bindings.get("Status") == Some(&Term::atom("pending")) => {}
+            Some(Err(error)) => {
+                if let Some(diagnostics) = &mut self.machine.diagnostics {
+                    diagnostics.push(Diagnostic::from_error(&error));
+                }
+
+                self.sender = None;
+            }
+            _ => {
+                self.sender = None;
+            }
+        }
+    }
+
+    fn conclude(&mut self) -> Vec<Diagnostic> {
+        self.concluded = true;
+
+        let rest = self.splitter.finish();
+
+        // the channel is closed after the rest of the source is sent,
+        // ending the stream of the load.
+        if let Some(sender) = self.sender.take() {
+            if sender.send(rest).is_ok() {
+                drop(sender);
+                self.resume();
+            }
+        }
+
+        self.machine.trust_me();
+
+        let outer_diagnostics = self.outer_diagnostics.take();
+        std::mem::replace(&mut self.machine.diagnostics, outer_diagnostics).unwrap_or_default()
+    }
+}
+
+impl Drop for ModuleFeed<'_> {
+    fn drop(&mut self) {
+        if !self.concluded {
+            self.conclude();
+        }
+    }
+}
+
+// where the clauses of source text received in pieces end. only the
+// text of complete clauses is passed on to the reader, as a clause cut
+// short by the end of the text received so far would be read as a
+// syntax error.
+//
+// a full stop is recognized as the layout or % following a . that is
+// neither quoted, commented nor part of a symbol atom like =.. when
+// in doubt, the text is held back for a later full stop.
+#[derive(Default)]
+struct ClauseSplitter {
+    text: Vec<u8>,
+    scanned: usize,
+    state: ScanState,
+    // the last two characters scanned outside of quotes and comments.
+    prev: [u8; 2],
+}
+
+#[derive(Clone, Copy, Default)]
+enum ScanState {
+    #[default]
+    Code,
+    Dot,
+    Quoted(u8),
+    Escape(u8),
+    LineComment,
+    BlockComment {
+        star: bool,
+    },
+    // after 0'
+    CharCode,
+    // after 0'\
+    CharCodeEscape,
+    // after 0'', which 0''' extends
+    CharCodeQuote,
+}
+
+fn is_symbol_char(c: u8) -> bool {
+    b"+-*/\\^<>=~:.?@#&$".contains(&c)
+}
+
+fn is_alphanumeric_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+impl ClauseSplitter {
+    // appends text, returning the text of the clauses it completes.
+    fn push(&mut self, text: &[u8]) -> Option<Vec<u8>> {
+        self.text.extend_from_slice(text);
+
+        let mut end = None;
+
+        for i in self.scanned..self.text.len() {
+            let c = self.text[i];
+
+            self.state = match self.state {
+                ScanState::Dot => {
+                    if c.is_ascii_whitespace() || c == b'%' {
+                        end = Some(i);
+                    }
+
+                    self.scan_code(c)
+                }
+                ScanState::Code => self.scan_code(c),
+                ScanState::Quoted(q) if c == b'\\' => ScanState::Escape(q),
+                ScanState::Quoted(q) if c == q => self.after(q),
+                ScanState::Quoted(q) | ScanState::Escape(q) => ScanState::Quoted(q),
+                ScanState::LineComment if c == b'\n' => self.after(b'\n'),
+                ScanState::LineComment => ScanState::LineComment,
+                ScanState::BlockComment { star: true } if c == b'/' => self.after(b' '),
+                ScanState::BlockComment { .. } => ScanState::BlockComment { star: c == b'*' },
+                ScanState::CharCode if c == b'\\' => ScanState::CharCodeEscape,
+                ScanState::CharCode if c == b'\'' => ScanState::CharCodeQuote,
+                ScanState::CharCode | ScanState::CharCodeEscape => self.after(b'0'),
+                ScanState::CharCodeQuote => {
+                    self.prev = [b'0', b'0'];
+
+                    if c == b'\'' {
+                        ScanState::Code
+                    } else {
+                        self.scan_code(c)
+                    }
+                }
+            };
+        }
+
+        self.scanned = self.text.len();
+
+        let end = end?;
+        let rest = self.text.split_off(end);
+
+        self.scanned -= end;
+
+        Some(std::mem::replace(&mut self.text, rest))
+    }
+
+    // the text left over once the source ends.
+    fn finish(&mut self) -> Vec<u8> {
+        self.scanned = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    fn after(&mut self, c: u8) -> ScanState {
+        self.prev = [self.prev[1], c];
+        ScanState::Code
+    }
+
+    fn scan_code(&mut self, c: u8) -> ScanState {
+        let [prev2, prev] = self.prev;
+        self.prev = [prev, c];
+
+        match c {
+            b'.' if !is_symbol_char(prev) => ScanState::Dot,
+            b'%' => ScanState::LineComment,
+            b'*' if prev == b'/' => ScanState::BlockComment { star: false },
+            b'\'' if prev == b'0' && !is_alphanumeric_char(prev2) => ScanState::CharCode,
+            b'\'' | b'"' | b'`' => ScanState::Quoted(c),
+            _ => ScanState::Code,
+        }
+    }
+}
+
 impl Machine {
-    /// Loads a module into the [`Machine`] from a string.
-    pub fn load_module_string(&mut self, module_name: &str, program: impl Into<String>) {
-        let stream = Stream::from_owned_string(program.into(), &mut self.machine_st.arena);
-        self.load_file(module_name, stream);
-    }
-
-    /// Consults a module into the [`Machine`] from a string.
-    pub fn consult_module_string(&mut self, module_name: &str, program: impl Into<String>) {
-        let stream = Stream::from_owned_string(program.into(), &mut self.machine_st.arena);
-        self.machine_st.registers[1] = stream_as_cell!(stream);
-        self.machine_st.registers[2] = atom_as_cell!(&atom_table::AtomTable::build_with(
-            &self.machine_st.atom_tbl,
-            module_name
-        ));
+    /// Registers the library `name` with the given source, as
+    /// [`MachineBuilder::with_library`](crate::MachineBuilder::with_library)
+    /// does, replacing the source of a library registered by the same
+    /// name.
+    ///
+    /// A library already loaded keeps the code of its former source.
+    pub fn add_library(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.machine_st
+            .registered_libraries
+            .insert(name.into(), source.into());
+    }
+
+    /// Registers the resource file `name` with the given contents, as
+    /// [`MachineBuilder::with_resource`](crate::MachineBuilder::with_resource)
+    /// does, replacing the contents of a resource registered by the same
+    /// name.
+    ///
+    /// Streams already opened on the resource keep its former contents.
+    pub fn add_resource(
+        &mut self,
+        name: impl Into<String>,
+        contents: impl Into<Cow<'static, [u8]>>,
+    ) {
+        self.machine_st
+            .registered_resources
+            .insert(name.into(), contents.into());
+    }
 
-        self.run_module_predicate(atom!("loader"), (atom!("consult_stream"), 2));
+    /// Loads a module into the [`Machine`] from a string, returning the
+    /// errors and warnings of the load.
+    pub fn load_module_string(
+        &mut self,
+        module_name: &str,
+        program: impl Into<String>,
+    ) -> Vec<Diagnostic> {
+        self.collect_diagnostics(|machine| {
+            let stream = Stream::from_owned_string(program.into(), &mut machine.machine_st.arena);
+            machine.load_file(module_name, stream);
+        })
+    }
+
+    /// Consults a module into the [`Machine`] from a string, returning
+    /// the errors and warnings of the load.
+    pub fn consult_module_string(
+        &mut self,
+        module_name: &str,
+        program: impl Into<String>,
+    ) -> Vec<Diagnostic> {
+        self.collect_diagnostics(|machine| {
+            let stream = Stream::from_owned_string(program.into(), &mut machine.machine_st.arena);
+            machine.machine_st.registers[1] = stream_as_cell!(stream);
+            machine.machine_st.registers[2] = atom_as_cell!(&atom_table::AtomTable::build_with(
+                &machine.machine_st.atom_tbl,
+                module_name
+            ));
+
+            machine.run_module_predicate(atom!("loader"), (atom!("consult_stream"), 2));
+        })
+    }
+
+    /// Consults a module into the [`Machine`] from source text that
+    /// arrives in pieces, such as the chunks of a download.
+    ///
+    /// The pieces are passed to [`ModuleFeed::push`], and
+    /// [`ModuleFeed::finish`] returns the errors and warnings of the
+    /// load, as [`consult_module_string`](Self::consult_module_string)
+    /// does.
+    pub fn consult_module_feed(&mut self, module_name: &str) -> ModuleFeed<'_> {
+        let outer_diagnostics = self.diagnostics.replace(vec![]);
+        let (sender, receiver) = channel();
+        let stream = Stream::input_channel(receiver, &mut self.machine_st.arena);
+
+        self.indices.set_stream(atom!("$module_feed"), stream);
+
+        let query = format!(
+            "loader:consult_feed('$module_feed', {}, Status).",
+            Term::atom(module_name)
+        );
+        let cursor = self.start_query(query, QueryOptions::default());
+
+        let mut feed = ModuleFeed {
+            machine: self,
+            cursor,
+            sender: Some(sender),
+            splitter: ClauseSplitter::default(),
+            outer_diagnostics,
+            concluded: false,
+        };
+
+        feed.resume();
+        feed
+    }
+
+    // runs load, collecting the diagnostics the loader reports through
+    // '$report_diagnostic'/4 instead of printing them.
+    fn collect_diagnostics(&mut self, load: impl FnOnce(&mut Self)) -> Vec<Diagnostic> {
+        let outer_diagnostics = self.diagnostics.replace(vec![]);
+        load(self);
+
+        // the error ending a load is reported as a diagnostic, not left
+        // for the next query to find.
+        self.machine_st.ball.reset();
+
+        std::mem::replace(&mut self.diagnostics, outer_diagnostics).unwrap_or_default()
+    }
+
+    /// Opens an input stream with the alias `alias`, from which Prolog
+    /// reads what is written to the returned [`UserInput`]. The stream
+    /// that had the alias before loses it, but stays open.
+    ///
+    /// A read that finds no input pending waits for more to be written,
+    /// from another thread, until the query is interrupted. The stream
+    /// only ends once the [`UserInput`] is dropped, after the input
+    /// written to it. On wasm32, where reads can't wait, such a read
+    /// throws `resource_error(pending_input)` instead, and the stream
+    /// can be read again once more input is written.
+    pub fn open_input_stream(&mut self, alias: &str) -> UserInput {
+        let (sender, receiver) = channel();
+        let stream = Stream::awaited_input_channel(receiver, &mut self.machine_st.arena);
+        let alias = atom_table::AtomTable::build_with(&self.machine_st.atom_tbl, alias);
+
+        self.indices.set_stream(alias, stream);
+        UserInput::new(sender)
+    }
+
+    /// Opens an output stream with the alias `alias`, calling `callback`
+    /// with what Prolog writes to it whenever the stream is flushed, as
+    /// [`OutputStreamConfig::callback`](crate::OutputStreamConfig::callback)
+    /// does. The stream that had the alias before loses it, but stays
+    /// open.
+    pub fn open_output_stream(&mut self, alias: &str, callback: Callback) {
+        let stream = Stream::from_callback(callback, &mut self.machine_st.arena);
+        let alias = atom_table::AtomTable::build_with(&self.machine_st.atom_tbl, alias);
+
+        self.indices.set_stream(alias, stream);
+    }
+
+    /// Compares two terms in the standard order of terms, as
+    /// `compare/3` does.
+    ///
+    /// Variables of the same name are the same variable. Distinct
+    /// variables are ordered by their first occurrence, in `a` and then
+    /// in `b`.
+    pub fn compare_terms(&mut self, a: &Term, b: &Term) -> Ordering {
+        let h = self.machine_st.heap.cell_len();
+        let mut var_cells = IndexMap::new();
+
+        let a = a
+            .to_heapcell(self, &mut var_cells)
+            .expect("couldn't write term to heap");
+        let b = b
+            .to_heapcell(self, &mut var_cells)
+            .expect("couldn't write term to heap");
+
+        let ordering = compare_term_test!(self.machine_st, a, b).unwrap_or(Ordering::Equal);
+
+        self.machine_st.heap.truncate(h);
+        ordering
+    }
+
+    /// The calls between the predicates loaded so far, as read from
+    /// their compiled code, each call once per caller.
+    ///
+    /// A predicate calls another if one of its clauses calls it by name
+    /// or passes it as a goal to a meta-predicate such as `findall/3`.
+    /// Goals built while running, and called with `call/N`, are not
+    /// seen. This is the data of `library(xref)`.
+    pub fn predicate_calls(&self) -> Vec<PredicateCall> {
+        self.cross_reference()
+            .calls
+            .into_iter()
+            .map(|(caller, callee, is_defined)| PredicateCall {
+                caller: PredicateIndicator::from_xref(caller),
+                callee: PredicateIndicator::from_xref(callee),
+                is_defined,
+            })
+            .collect()
+    }
+
+    /// The calls of [`Machine::predicate_calls`] to undefined
+    /// predicates.
+    pub fn undefined_calls(&self) -> Vec<PredicateCall> {
+        self.predicate_calls()
+            .into_iter()
+            .filter(|call| !call.is_defined)
+            .collect()
+    }
+
+    /// The predicates exported by their modules but called by no
+    /// predicate of another module, as found by
+    /// [`Machine::predicate_calls`].
+    pub fn unused_exports(&self) -> Vec<PredicateIndicator> {
+        self.cross_reference()
+            .unused_exports
+            .into_iter()
+            .map(PredicateIndicator::from_xref)
+            .collect()
+    }
+
+    /// The import graph of the loaded modules: which modules, or
+    /// `user`, import which by `use_module/1,2` and `reexport/1,2`.
+    ///
+    /// Reloading a module replaces its imports by those of the
+    /// reload.
+    pub fn module_graph(&self) -> ModuleGraph {
+        let modules = std::iter::once("user".to_string())
+            .chain(
+                self.indices
+                    .modules
+                    .keys()
+                    .map(|module_name| module_name.as_str().to_string()),
+            )
+            .collect();
+
+        let imports = self
+            .indices
+            .module_imports
+            .iter()
+            .flat_map(|(importer, imported_modules)| {
+                imported_modules.iter().map(|imported| ModuleImport {
+                    importer: importer.as_str().to_string(),
+                    imported: imported.as_str().to_string(),
+                })
+            })
+            .collect();
+
+        ModuleGraph { modules, imports }
+    }
+
+    /// The predicates defined in a module, or outside of any module if
+    /// `module` is `user`, in the order they were first defined.
+    ///
+    /// Predicates imported into the module are left out, as are those
+    /// only called and the internal ones, whose names start with `$`.
+    pub fn predicates(&self, module: &str) -> Vec<PredicateInfo> {
+        let module_name = atom_table::AtomTable::build_with(&self.machine_st.atom_tbl, module);
+
+        let (code_dir, exports, compilation_target) = match module_name {
+            atom!("user") => (&self.indices.code_dir, &[][..], CompilationTarget::User),
+            _ => match self.indices.modules.get(&module_name) {
+                Some(module) => (
+                    &module.code_dir,
+                    &module.module_decl.exports[..],
+                    CompilationTarget::Module(module_name),
+                ),
+                None => return vec![],
+            },
+        };
+
+        let is_exported_by = |exports: &[ModuleExport], key: PredicateKey| {
+            exports
+                .iter()
+                .any(|export| matches!(export, ModuleExport::PredicateKey(k) if *k == key))
+        };
+
+        let code_index_tbl = &self.machine_st.arena.code_index_tbl;
+
+        // a predicate is imported if another module exports it, not
+        // having re-exported it, its code index there leading to the
+        // same code.
+        let is_imported = |key: PredicateKey, idx: CodeIndex| {
+            let code = code_index_tbl.get_entry(idx.into()).local();
+
+            self.indices
+                .modules
+                .iter()
+                .filter(|(&name, _)| name != module_name)
+                .any(|(_, other)| {
+                    is_exported_by(&other.module_decl.exports, key)
+                        && !other.reexports.contains(&ModuleExport::PredicateKey(key))
+                        && other.code_dir.get(&key).is_some_and(|&other_idx| {
+                            usize::from(CodeIndexOffset::from(other_idx))
+                                == usize::from(CodeIndexOffset::from(idx))
+                                || code.is_some()
+                                    && code_index_tbl.get_entry(other_idx.into()).local() == code
+                        })
+                })
+        };
+
+        code_dir
+            .iter()
+            .filter(|(&key, &idx)| {
+                let skeleton = self
+                    .indices
+                    .get_predicate_skeleton(&compilation_target, &key);
+                let is_defined = !code_index_tbl.get_entry(idx.into()).is_undefined()
+                    || skeleton.is_some_and(|skeleton| skeleton.core.is_dynamic);
+
+                is_defined && !key.0.as_str().starts_with('$') && !is_imported(key, idx)
+            })
+            .map(|(&key, _)| {
+                let (name, arity) = key;
+                let skeleton = self
+                    .indices
+                    .get_predicate_skeleton(&compilation_target, &key);
+
+                let meta_predicate = self
+                    .indices
+                    .get_meta_predicate_spec(name, arity, &compilation_target)
+                    .map(|meta_specs| {
+                        let head = Term::compound(
+                            name.as_str().to_string(),
+                            meta_specs.iter().map(|meta_spec| match meta_spec {
+                                MetaSpec::Minus => Term::atom("+"),
+                                MetaSpec::Plus => Term::atom("-"),
+                                MetaSpec::Either => Term::atom("?"),
+                                MetaSpec::Colon => Term::atom(":"),
+                                MetaSpec::RequiresExpansionWithArgument(arg_num) => {
+                                    Term::integer(*arg_num)
+                                }
+                            }),
+                        );
+
+                        Term::compound("meta_predicate", [head])
+                    });
+
+                PredicateInfo {
+                    indicator: PredicateIndicator::from_xref((module_name, key)),
+                    is_dynamic: skeleton.is_some_and(|skeleton| skeleton.core.is_dynamic),
+                    is_multifile: skeleton.is_some_and(|skeleton| skeleton.core.is_multifile),
+                    is_discontiguous: skeleton
+                        .is_some_and(|skeleton| skeleton.core.is_discontiguous),
+                    is_built_in: self.indices.builtin_property(key),
+                    is_exported: is_exported_by(exports, key),
+                    meta_predicate,
+                }
+            })
+            .collect()
+    }
+
+    /// The clauses of a predicate of a module, as `clause/2` finds
+    /// them: facts as their heads, and rules as `Head :- Body` terms.
+    ///
+    /// The clauses of static predicates aren't kept once compiled, so
+    /// only those of dynamic predicates are returned, as
+    /// [`PredicateInfo::is_dynamic`] tells. Neither are clauses
+    /// returned for predicates that don't exist.
+    pub fn clauses(&mut self, module: &str, name: &str, arity: usize) -> Vec<Term> {
+        let head = match arity {
+            0 => Term::atom(name),
+            _ => Term::compound(name, vec![Term::variable("_"); arity]),
+        };
+
+        // the clauses of files consulted outside of a module are found
+        // by unqualified goals.
+        let query = match module {
+            "user" => format!("Head = {head}, clause(Head, Body)."),
+            _ => format!("Head = {head}, clause({}:Head, Body).", Term::atom(module)),
+        };
+
+        self.run_query(query)
+            .filter_map(|answer| match answer {
+                Ok(LeafAnswer::LeafAnswer { mut bindings, .. }) => {
+                    let head = bindings.remove("Head")?;
+
+                    match bindings.remove("Body")? {
+                        Term::Atom(body) if body == "true" => Some(head),
+                        body => Some(Term::compound(":-", [head, body])),
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Sets the Prolog flag `name` to `value`, as `set_prolog_flag/2`
+    /// does, e.g. `double_quotes` to `codes` or `unknown` to `fail`.
+    ///
+    /// The error of `set_prolog_flag/2` is returned if there's no such
+    /// flag or `value` isn't one of its values, and a permission error
+    /// if the flag is read only, as `bounded` is.
+    pub fn set_flag(&mut self, name: &str, value: Term) -> Result<(), Term> {
+        let query = format!("set_prolog_flag({}, {value}).", Term::atom(name));
+
+        match self.run_query(query).next() {
+            Some(Ok(LeafAnswer::True | LeafAnswer::LeafAnswer { .. })) => Ok(()),
+            Some(Ok(LeafAnswer::Exception(error)) | Err(error)) => Err(error),
+            Some(Ok(LeafAnswer::False)) | None => Err(Term::compound(
+                "error",
+                [
+                    Term::compound(
+                        "permission_error",
+                        [Term::atom("modify"), Term::atom("flag"), Term::atom(name)],
+                    ),
+                    Term::compound("/", [Term::atom("set_prolog_flag"), Term::integer(2)]),
+                ],
+            )),
+        }
+    }
+
+    /// The value of the Prolog flag `name`, as `current_prolog_flag/2`
+    /// finds it, or `None` if there's no such flag or it has no value,
+    /// as `max_integer` hasn't.
+    pub fn get_flag(&mut self, name: &str) -> Option<Term> {
+        let query = format!("current_prolog_flag({}, Value).", Term::atom(name));
+
+        match self.run_query(query).next() {
+            Some(Ok(LeafAnswer::LeafAnswer { mut bindings, .. })) => bindings.remove("Value"),
+            _ => None,
+        }
+    }
+
+    /// Runs the readiness checks of `library(health)` and reports them
+    /// with the memory used by the machine, as `health_report/1` does.
+    pub fn health(&mut self) -> HealthReport {
+        let query = "(current_module(health) -> true ; use_module(library(health), [])), \
+                     health:health_report(health(Status, Checks0)), \
+                     lists:maplist(health:check_output, Checks0, Checks), \
+                     Report = health(Status, Checks).";
+
+        match self.run_query(query).next() {
+            Some(Ok(LeafAnswer::LeafAnswer { bindings, .. })) => bindings
+                .get("Report")
+                .and_then(HealthReport::from_term)
+                .expect("health_report/1 should report the health of the machine"),
+            answer => panic!("health_report/1 should report the health of the machine: {answer:?}"),
+        }
+    }
+
+    /// Returns a handle telling from other threads whether the machine
+    /// is responsive while it runs queries.
+    ///
+    /// The machine records the time it polls for interrupts once it
+    /// has a monitor.
+    pub fn health_monitor(&mut self) -> HealthMonitor {
+        let heartbeat = self
+            .machine_st
+            .heartbeat
+            .get_or_insert_with(|| Arc::new(Heartbeat::new()));
+
+        HealthMonitor {
+            heartbeat: heartbeat.clone(),
+        }
+    }
+
+    /// Returns handles to the queries started by the machine that
+    /// aren't finished, oldest first.
+    ///
+    /// Queries are started by [`Machine::run_query`] and the like, and
+    /// finish once they have no more answers or their [`QueryState`] is
+    /// dropped.
+    pub fn active_queries(&mut self) -> Vec<QueryHandle> {
+        self.machine_st
+            .active_queries
+            .retain(|query| !query.is_finished());
+
+        self.machine_st
+            .active_queries
+            .iter()
+            .map(|query| QueryHandle {
+                query: query.clone(),
+            })
+            .collect()
+    }
+
+    /// The configuration the machine runs with, as set by the
+    /// [`MachineBuilder`](crate::MachineBuilder) it was built by and
+    /// changed since, e.g. by setting the `unknown` flag.
+    pub fn config(&mut self) -> MachineConfig {
+        let flags = self
+            .configured_flags
+            .clone()
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.get_flag(&name)?;
+                Some((name, value))
+            })
+            .collect();
+
+        MachineConfig {
+            unknown: self.machine_st.flags.unknown.into(),
+            library_paths: self.machine_st.library_paths.clone(),
+            parallel_compilation: self.machine_st.compile_workers.is_some(),
+            index_cache: self.machine_st.index_cache.is_some(),
+            query_options: self.query_options.clone(),
+            flags,
+            crash_report: self
+                .crash_reporter
+                .as_ref()
+                .map(|crash_reporter| crash_reporter.path().clone()),
+        }
+    }
+
+    /// The identifiers of the background tasks started by
+    /// `spawn_initialization/1` that weren't joined yet.
+    pub fn background_tasks(&self) -> Vec<String> {
+        threads::background_threads()
+            .into_iter()
+            .map(|id| id.as_str().to_string())
+            .collect()
+    }
+
+    /// Asks the background task `id` to stop, as `thread_cancel/1`
+    /// does. Returns `false` if there's no such task.
+    pub fn cancel_background_task(&self, id: &str) -> bool {
+        self.background_task(id).is_some_and(threads::cancel_thread)
+    }
+
+    /// Waits for the background task `id` to finish and returns its
+    /// status, as `thread_join/2` does, or `None` if there's no such
+    /// task.
+    pub fn join_background_task(&mut self, id: &str) -> Option<Term> {
+        let status = threads::join_thread(self.background_task(id)?)?;
+
+        match self.run_query(format!("Status = {status}")).next() {
+            Some(Ok(LeafAnswer::LeafAnswer { mut bindings, .. })) => bindings.remove("Status"),
+            _ => None,
+        }
+    }
+
+    fn background_task(&self, id: &str) -> Option<Atom> {
+        threads::background_threads()
+            .into_iter()
+            .find(|task| *task.as_str() == *id)
     }
 
     pub(crate) fn allocate_stub_choice_point(&mut self) {
@@ -560,10 +2333,40 @@ pub(crate) fn allocate_stub_choice_point(&mut self) {
         self.machine_st.block = stub_b;
     }
 
-    /// Runs a query.
+    /// Runs a query with the options of
+    /// [`MachineBuilder::with_query_options`](crate::MachineBuilder::with_query_options).
     pub fn run_query(&mut self, query: impl Into<String>) -> QueryState {
+        self.run_query_with_options(query, self.query_options.clone())
+    }
+
+    /// Runs a query with the given [`QueryOptions`].
+    pub fn run_query_with_options(
+        &mut self,
+        query: impl Into<String>,
+        options: QueryOptions,
+    ) -> QueryState<'_> {
+        let cursor = self.start_query(query, options);
+
+        QueryState {
+            machine: self,
+            cursor,
+        }
+    }
+
+    // starts a query whose answers are produced by the returned
+    // cursor. the machine must be passed to it for each answer, and
+    // trust_me called once it is no longer needed.
+    pub(crate) fn start_query(
+        &mut self,
+        query: impl Into<String>,
+        options: QueryOptions,
+    ) -> QueryCursor {
+        let query = query.into();
+        let query_text = query.clone();
+        self.record_crash_query(|_| query.clone());
+
         let mut parser = Parser::new(
-            Stream::from_owned_string(query.into(), &mut self.machine_st.arena),
+            Stream::from_owned_string(query, &mut self.machine_st.arena),
             &mut self.machine_st,
         );
         let op_dir = CompositeOpDir::new(&self.indices.op_dir, None);
@@ -610,12 +2413,89 @@ pub fn run_query(&mut self, query: impl Into<String>) -> QueryState {
         self.machine_st.execute_at_index(1, call_index_p);
 
         let stub_b = self.machine_st.b;
-        QueryState {
-            machine: self,
+
+        let query = Arc::new(ActiveQuery::new(
+            self.machine_st.next_query_id,
+            query_text,
+            stub_b,
+            self.get_inference_count(),
+        ));
+
+        self.machine_st.next_query_id += 1;
+        self.machine_st
+            .active_queries
+            .retain(|query| !query.is_finished());
+        self.machine_st.active_queries.push(query.clone());
+
+        QueryCursor {
             term: term_write_result,
             stub_b,
             var_names,
             called: false,
+            capture_output: options.capture_output,
+            captured_output: None,
+            step_limit: options.step_limit,
+            heap_limit: options.heap_limit,
+            variable_names: options.variable_names,
+            query,
         }
     }
+
+    // points user_output and user_error to fresh memory streams,
+    // returning them together with the streams they replace.
+    fn redirect_user_streams(&mut self) -> OutputCapture {
+        let capture = OutputCapture {
+            user_output: Stream::from_owned_string(String::new(), &mut self.machine_st.arena),
+            user_error: Stream::from_owned_string(String::new(), &mut self.machine_st.arena),
+            prev_user_output: self.user_output,
+            prev_user_error: self.user_error,
+        };
+
+        self.user_output = capture.user_output;
+        self.user_error = capture.user_error;
+        self.configure_streams();
+
+        capture
+    }
+
+    // collects the text written to the capturing streams and restores
+    // the previous streams, unless the query replaced the capturing
+    // streams itself, e.g. with set_output/1.
+    fn restore_user_streams(&mut self, capture: OutputCapture) -> CapturedOutput {
+        let mut output = CapturedOutput::default();
+
+        let OutputCapture {
+            user_output: mut capture_output,
+            user_error: mut capture_error,
+            prev_user_output,
+            prev_user_error,
+        } = capture;
+
+        let _ = capture_output.read_to_string(&mut output.user_output);
+        let _ = capture_error.read_to_string(&mut output.user_error);
+
+        if self.user_output == capture_output {
+            self.user_output = prev_user_output;
+        }
+
+        if self.user_error == capture_error {
+            self.user_error = prev_user_error;
+        }
+
+        self.configure_streams();
+
+        for mut stream in [capture_output, capture_error] {
+            self.indices.remove_stream(stream);
+            let _ = stream.close();
+        }
+
+        output
+    }
+}
+
+struct OutputCapture {
+    user_output: Stream,
+    user_error: Stream,
+    prev_user_output: Stream,
+    prev_user_error: Stream,
 }
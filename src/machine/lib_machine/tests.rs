@@ -1,5 +1,5 @@
 use super::*;
-use crate::MachineBuilder;
+use crate::{MachineBuilder, UnknownProcedure};
 
 #[test]
 #[cfg_attr(miri, ignore = "it takes too long to run")]
@@ -564,6 +564,74 @@ fn differentiate_anonymous_variables() {
     );
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn answer_variable_names() {
+    let mut machine = MachineBuilder::default().build();
+
+    let answers: Vec<_> = machine
+        .run_query("copy_term(f(A)-g(B,A), X-Y).")
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        answers,
+        [LeafAnswer::from_bindings([
+            ("X", Term::compound("f", [Term::variable("_A")])),
+            (
+                "Y",
+                Term::compound("g", [Term::variable("_B"), Term::variable("_A")])
+            ),
+        ])]
+    );
+
+    let options = QueryOptions {
+        variable_names: AnswerVariableNames::Letters,
+        ..QueryOptions::default()
+    };
+    let answers: Vec<_> = machine
+        .run_query_with_options("X = f(_, A, _).", options)
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        answers,
+        [LeafAnswer::from_bindings([(
+            "X",
+            Term::compound(
+                "f",
+                [
+                    Term::variable("B"),
+                    Term::variable("A"),
+                    Term::variable("C")
+                ]
+            )
+        )])]
+    );
+
+    // as charsio:fabricate_var_name/3 names them for the toplevel.
+    assert_eq!(fabricate_var_name("", 25), "Z");
+    assert_eq!(fabricate_var_name("_", 27), "_B1");
+
+    let options = QueryOptions {
+        variable_names: AnswerVariableNames::Internal,
+        capture_output: true,
+        ..QueryOptions::default()
+    };
+    let mut query = machine.run_query_with_options("copy_term(f(_), X), write(X).", options);
+
+    let Some(Ok(LeafAnswer::LeafAnswer { bindings })) = query.next() else {
+        panic!("expected a leaf answer");
+    };
+    let written = query.captured_output().unwrap().user_output.clone();
+
+    assert_eq!(
+        bindings["X"].to_string(),
+        written,
+        "answers name variables as write/1 does"
+    );
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn order_of_variables_in_binding() {
@@ -607,4 +675,1802 @@ fn errors_and_exceptions() {
         complete_answer,
         [Ok(LeafAnswer::Exception(Term::atom("a")))]
     );
+
+    let complete_answer: Vec<_> = machine.run_query("X = a.").collect();
+
+    assert_eq!(
+        complete_answer,
+        [Ok(LeafAnswer::from_bindings([("X", Term::atom("a"))]))]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn capture_output_per_answer() {
+    let mut machine = MachineBuilder::default().build();
+
+    let options = QueryOptions {
+        capture_output: true,
+        ..QueryOptions::default()
+    };
+    let mut query = machine.run_query_with_options(
+        "(X = a ; X = b), write(X), write(user_error, err(X)).",
+        options,
+    );
+    let mut captured = vec![];
+
+    while let Some(answer) = query.next() {
+        let output = query.captured_output().unwrap();
+        captured.push((
+            answer,
+            output.user_output.clone(),
+            output.user_error.clone(),
+        ));
+    }
+
+    assert_eq!(
+        captured,
+        [
+            (
+                Ok(LeafAnswer::from_bindings([("X", Term::atom("a"))])),
+                "a".to_string(),
+                "err(a)".to_string(),
+            ),
+            (
+                Ok(LeafAnswer::from_bindings([("X", Term::atom("b"))])),
+                "b".to_string(),
+                "err(b)".to_string(),
+            ),
+        ]
+    );
+    drop(query);
+
+    let mut query = machine.run_query("write(uncaptured).");
+
+    assert_eq!(query.next(), Some(Ok(LeafAnswer::True)));
+    assert_eq!(query.captured_output(), None);
+    drop(query);
+
+    let mut actual = String::new();
+    machine.user_output.read_to_string(&mut actual).unwrap();
+    assert_eq!(actual, "uncaptured");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn open_streams() {
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    let mut machine = MachineBuilder::default().build();
+    let mut input = machine.open_input_stream("data");
+
+    input.write_all(b"p(1). p(").unwrap();
+    input.write_all(b"2).\n").unwrap();
+
+    assert_eq!(
+        machine
+            .run_query("read(data, T), read(data, U).")
+            .collect::<Vec<_>>(),
+        [Ok(LeafAnswer::from_bindings([
+            ("T", Term::compound("p", [Term::integer(1)])),
+            ("U", Term::compound("p", [Term::integer(2)])),
+        ]))],
+    );
+
+    input.write_all(b"q.\n").unwrap();
+    drop(input);
+
+    assert_eq!(
+        machine
+            .run_query("read(data, T), read(data, U).")
+            .collect::<Vec<_>>(),
+        [Ok(LeafAnswer::from_bindings([
+            ("T", Term::atom("q")),
+            ("U", Term::atom("end_of_file")),
+        ]))],
+    );
+
+    // a read finding no input pending waits for the input written
+    // later, rather than seeing the end of the stream.
+    let mut input = machine.open_input_stream("feed");
+    let feeder = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        input.write_all(b"r.\n").unwrap();
+    });
+
+    assert_eq!(
+        machine
+            .run_query("read(feed, T), read(feed, U).")
+            .collect::<Vec<_>>(),
+        [Ok(LeafAnswer::from_bindings([
+            ("T", Term::atom("r")),
+            ("U", Term::atom("end_of_file")),
+        ]))],
+    );
+    feeder.join().unwrap();
+
+    let output = Rc::new(RefCell::new(String::new()));
+
+    machine.open_output_stream(
+        "log",
+        Box::new({
+            let output = output.clone();
+            move |buffer| {
+                buffer.read_to_string(&mut output.borrow_mut()).unwrap();
+            }
+        }),
+    );
+
+    assert_eq!(
+        machine
+            .run_query("write(log, hello), nl(log), flush_output(log).")
+            .collect::<Vec<_>>(),
+        [Ok(LeafAnswer::True)],
+    );
+    assert_eq!(output.borrow().as_str(), "hello\n");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn step_limit_per_answer() {
+    let mut machine = MachineBuilder::default().build();
+
+    machine.load_module_string(
+        "facts",
+        r#"
+            loop :- loop.
+            count(N, N).
+            count(N0, N) :- N1 is N0 + 1, count(N1, N).
+        "#,
+    );
+
+    let options = QueryOptions {
+        step_limit: Some(10_000),
+        ..QueryOptions::default()
+    };
+    let steps_error = Err(Term::compound(
+        "error",
+        [
+            Term::compound("resource_error", [Term::atom("steps")]),
+            Term::compound("/", [Term::atom("call"), Term::integer(1)]),
+        ],
+    ));
+
+    let answers: Vec<_> = machine
+        .run_query_with_options("loop.", options.clone())
+        .collect();
+    assert_eq!(answers, std::slice::from_ref(&steps_error));
+
+    let answers: Vec<_> = machine
+        .run_query_with_options("catch(loop, _, true).", options.clone())
+        .collect();
+    assert_eq!(answers, [steps_error]);
+
+    // the budget applies to each answer rather than to the whole query.
+    let answers: Vec<_> = machine
+        .run_query_with_options("count(0, N).", options)
+        .take(100)
+        .collect();
+    assert_eq!(answers.len(), 100);
+    assert!(answers.iter().all(Result::is_ok));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn heap_limit_per_answer() {
+    let mut machine = MachineBuilder::default().build();
+
+    machine.load_module_string(
+        "facts",
+        r#"
+            list(0, []).
+            list(N0, [_|Xs]) :- N0 > 0, N is N0 - 1, list(N, Xs).
+        "#,
+    );
+
+    let options = QueryOptions {
+        heap_limit: Some(1 << 20),
+        ..QueryOptions::default()
+    };
+
+    let answers: Vec<_> = machine
+        .run_query_with_options("list(1000, L), L = [].", options.clone())
+        .collect();
+    assert_eq!(answers, [Ok(LeafAnswer::False)]);
+
+    let answers: Vec<_> = machine
+        .run_query_with_options("catch(list(1000000, _), _, true).", options)
+        .collect();
+    assert_eq!(
+        answers,
+        [Err(Term::compound(
+            "error",
+            [
+                Term::compound("resource_error", [Term::atom("heap")]),
+                Term::compound("/", [Term::atom("call"), Term::integer(1)]),
+            ],
+        ))]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn active_queries() {
+    let mut machine = MachineBuilder::default().build();
+
+    machine.load_module_string(
+        "facts",
+        r#"
+            loop :- loop.
+            count(N, N).
+            count(N0, N) :- N1 is N0 + 1, count(N1, N).
+        "#,
+    );
+
+    assert!(machine.active_queries().is_empty());
+
+    let cancelled_error = Err(Term::compound(
+        "error",
+        [
+            Term::atom("query_cancelled"),
+            Term::compound("/", [Term::atom("call"), Term::integer(1)]),
+        ],
+    ));
+
+    let mut answers = machine.run_query("count(0, N).");
+    let handle = answers.handle();
+
+    assert_eq!(handle.query(), "count(0, N).");
+    assert!(handle.started_at() <= std::time::SystemTime::now());
+    assert_eq!(answers.by_ref().take(10).count(), 10);
+    assert!(handle.inferences() >= 10);
+    assert!(!handle.is_finished());
+
+    // a query cancelled between answers has no more of them.
+    handle.cancel();
+    assert_eq!(answers.next(), Some(cancelled_error.clone()));
+    assert_eq!(answers.next(), None);
+    assert!(handle.is_finished());
+    drop(answers);
+
+    assert!(machine.active_queries().is_empty());
+
+    let mut answers = machine.run_query("true.");
+    let id = answers.handle().id();
+    answers.handle().cancel();
+    assert_eq!(answers.next(), Some(cancelled_error.clone()));
+    drop(answers);
+
+    let answers = machine.run_query("loop.");
+    let handle = answers.handle();
+    assert!(handle.id() > id);
+    drop(answers);
+    assert!(handle.is_finished());
+
+    // a running query is cancelled from another thread.
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let canceller = std::thread::spawn(move || {
+        let handle: QueryHandle = rx.recv().unwrap();
+
+        while handle.inferences() < 100_000 {
+            std::thread::yield_now();
+        }
+
+        handle.cancel();
+    });
+
+    let mut answers = machine.run_query("catch(loop, _, true).");
+    tx.send(answers.handle()).unwrap();
+
+    assert_eq!(answers.next(), Some(cancelled_error));
+    canceller.join().unwrap();
+    drop(answers);
+
+    // queries kept next to the machine are active until their cursor
+    // is exhausted or dropped.
+    let mut cursor = machine.start_query("X = 1 ; X = 2.", QueryOptions::default());
+    let active = machine.active_queries();
+
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].query(), "X = 1 ; X = 2.");
+    assert!(cursor.next_answer(&mut machine).is_some());
+    assert!(cursor.next_answer(&mut machine).is_some());
+    assert!(cursor.next_answer(&mut machine).is_none());
+    assert!(active[0].is_finished());
+    assert!(machine.active_queries().is_empty());
+    machine.trust_me();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn parallel_compilation() {
+    let mut program = String::new();
+
+    for p in 0..20 {
+        for n in 0..50 {
+            program += &format!("p{p}({n}, f(X, {n}.5), [X|_]).\n");
+        }
+
+        program += &format!(
+            "q{p}(N, S) :- findall(X, p{p}(X, _, _), Xs), sum_list(Xs, S0), S is S0 + N.\n"
+        );
+
+        // the floats of arithmetic are read by the workers.
+        program += &format!("h{p}(X, Y) :- Y is X * {p}.25.\n");
+    }
+
+    // an expansion calling the predicates compiled on workers, a
+    // directive and discontiguous clauses after them.
+    program += r#"
+        term_expansion(t(X), r(Y)) :- p0(X, _, _), Y is X * 2.
+        t(3).
+        :- use_module(library(lists)).
+        p0(100, f(_, 0.0), []).
+        s(X) :- r(X).
+    "#;
+
+    let mut answers = vec![];
+
+    for parallel_compilation in [false, true] {
+        let mut machine = MachineBuilder::default()
+            .with_parallel_compilation(parallel_compilation)
+            .build();
+
+        let diagnostics = machine.consult_module_string("facts", program.clone());
+
+        answers.push((
+            diagnostics,
+            machine
+                .run_query(
+                    "q0(1, S0), q19(2, S19), s(R), p7(49, F, L), findall(X, p0(X, _, _), Xs), h3(2, H).",
+                )
+                .collect::<Vec<_>>(),
+        ));
+    }
+
+    assert_eq!(answers[0], answers[1]);
+
+    let (diagnostics, answers) = &answers[1];
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, "discontiguous_predicate");
+
+    assert_eq!(answers.len(), 1);
+    let Ok(LeafAnswer::LeafAnswer { bindings, .. }) = &answers[0] else {
+        panic!("{answers:?}");
+    };
+
+    assert_eq!(bindings["S0"], Term::integer(101));
+    assert_eq!(bindings["S19"], Term::integer(1227));
+    assert_eq!(bindings["R"], Term::integer(6));
+    assert_eq!(bindings["Xs"], Term::list([Term::integer(100)]));
+    assert_eq!(bindings["H"], Term::float(6.5));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn index_cache() {
+    let dir = std::env::temp_dir().join(format!("scryer_index_cache_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let source = dir.join("facts.pl");
+    let index_file = dir.join("facts.pl.idx");
+
+    let mut program = String::new();
+
+    for n in 0..200 {
+        program += &format!("edge(n{}, {n}, f(a{}, {})).\n", n % 20, n % 7, n % 3);
+    }
+
+    std::fs::write(&source, &program).unwrap();
+
+    let query = format!(
+        "consult('{}'), findall(X-Y, edge(n3, X, f(Y, 1)), Es).",
+        source.display()
+    );
+
+    let mut answers = vec![];
+    let mut index_files = vec![];
+
+    for (index_cache, parallel_compilation) in [(false, false), (true, false), (true, true)] {
+        let mut machine = MachineBuilder::default()
+            .with_index_cache(index_cache)
+            .with_parallel_compilation(parallel_compilation)
+            .build();
+
+        answers.push(machine.run_query(query.clone()).collect::<Vec<_>>());
+        index_files.push(std::fs::read(&index_file).ok());
+    }
+
+    assert_eq!(answers[0], answers[1]);
+    assert_eq!(answers[0], answers[2]);
+
+    // the index file is written by the first load with the cache, and
+    // read back unchanged by the second.
+    assert_eq!(index_files[0], None);
+    assert!(index_files[1].is_some());
+    assert_eq!(index_files[1], index_files[2]);
+
+    let Ok(LeafAnswer::LeafAnswer { bindings, .. }) = &answers[0][0] else {
+        panic!("{answers:?}");
+    };
+
+    assert_eq!(
+        bindings["Es"],
+        Term::list([
+            Term::compound("-", [Term::integer(43), Term::atom("a1")]),
+            Term::compound("-", [Term::integer(103), Term::atom("a5")]),
+            Term::compound("-", [Term::integer(163), Term::atom("a2")]),
+        ])
+    );
+
+    // the index of a changed file is computed anew.
+    std::fs::write(&source, program + "edge(n3, 1000, f(a0, 1)).\n").unwrap();
+
+    let mut machine = MachineBuilder::default().with_index_cache(true).build();
+    let answers: Vec<_> = machine.run_query(query).collect();
+
+    let Ok(LeafAnswer::LeafAnswer { bindings, .. }) = &answers[0] else {
+        panic!("{answers:?}");
+    };
+
+    let Term::List(edges) = &bindings["Es"] else {
+        panic!("{bindings:?}");
+    };
+
+    assert_eq!(edges.len(), 4);
+    assert_ne!(std::fs::read(&index_file).ok(), index_files[1]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn load_progress_hook() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let reports = Rc::new(RefCell::new(vec![]));
+    let hook_reports = reports.clone();
+
+    let mut machine = MachineBuilder::default()
+        .with_load_progress_hook(move |progress| hook_reports.borrow_mut().push(progress.clone()))
+        .build();
+
+    let program: String = (0..2500).map(|n| format!("f({n}).\n")).collect();
+    machine.consult_module_string("facts", program);
+
+    let reports = reports.borrow();
+
+    assert_eq!(
+        reports
+            .iter()
+            .map(|progress| (progress.stage, progress.clauses))
+            .collect::<Vec<_>>(),
+        [
+            (LoadStage::Started, 0),
+            (LoadStage::Clauses, 1000),
+            (LoadStage::Clauses, 2000),
+            (LoadStage::Loaded, 2500),
+            (LoadStage::Finished, 2500),
+        ]
+    );
+
+    assert!(reports.iter().all(|progress| progress.file.is_none()));
+    assert!(reports.iter().all(|progress| progress.files == 0));
+    assert!(reports
+        .windows(2)
+        .all(|pair| pair[0].elapsed <= pair[1].elapsed));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn trace_hook_events() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let hook_events = events.clone();
+
+    let mut machine = MachineBuilder::default()
+        .with_trace_hook(move |event| {
+            hook_events.borrow_mut().push(event.clone());
+
+            match &event.goal {
+                Term::Compound(name, _) if name == "r" => TraceAction::Fail,
+                _ => TraceAction::Creep,
+            }
+        })
+        .build();
+
+    machine.consult_module_string(
+        "facts",
+        r#"
+            p(X) :- q(X).
+            q(1).
+            q(2).
+            r(_).
+        "#,
+    );
+
+    let answers: Vec<_> = machine
+        .run_query("use_module(library(trace)), trace, p(2), notrace.")
+        .collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let event = |port, depth, goal| TraceEvent { port, depth, goal };
+    let p = |n| Term::compound("p", [Term::integer(n)]);
+    let q = |n| Term::compound("q", [Term::integer(n)]);
+
+    assert_eq!(
+        events.borrow_mut().drain(..).collect::<Vec<_>>(),
+        [
+            event(TracePort::Call, 1, p(2)),
+            event(TracePort::Call, 2, q(2)),
+            event(TracePort::Exit, 2, q(2)),
+            event(TracePort::Exit, 1, p(2)),
+        ]
+    );
+
+    let answers: Vec<_> = machine
+        .run_query("trace, (r(1) ; true), notrace.")
+        .collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let r = Term::compound("r", [Term::integer(1)]);
+
+    assert_eq!(
+        events.borrow_mut().drain(..).collect::<Vec<_>>(),
+        [
+            event(TracePort::Call, 1, r.clone()),
+            event(TracePort::Fail, 1, r),
+        ]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn compare_and_hash_terms() {
+    use crate::parser::dashu::rational::RBig;
+    use std::collections::hash_map::DefaultHasher;
+
+    let mut machine = MachineBuilder::default().build();
+
+    let a = || Term::atom("a");
+    let b = || Term::atom("b");
+
+    let mut terms = vec![
+        Term::compound("h", [a(), b(), a()]),
+        Term::string("ab"),
+        Term::compound("g", [a()]),
+        Term::compound("f", [a()]),
+        b(),
+        a(),
+        Term::integer(2),
+        Term::integer(1),
+        Term::rational(RBig::from_parts(1.into(), 2u32.into())),
+        Term::float(3.0),
+        Term::float(1.5),
+        Term::variable("X"),
+    ];
+
+    terms.sort_by(|t1, t2| machine.compare_terms(t1, t2));
+
+    assert_eq!(
+        terms,
+        [
+            Term::variable("X"),
+            Term::float(1.5),
+            Term::float(3.0),
+            Term::rational(RBig::from_parts(1.into(), 2u32.into())),
+            Term::integer(1),
+            Term::integer(2),
+            a(),
+            b(),
+            Term::compound("f", [a()]),
+            Term::compound("g", [a()]),
+            Term::string("ab"),
+            Term::compound("h", [a(), b(), a()]),
+        ]
+    );
+
+    assert_eq!(
+        machine.compare_terms(&Term::variable("X"), &Term::variable("Y")),
+        Ordering::Less
+    );
+
+    let hash = |term: &Term| {
+        let mut hasher = DefaultHasher::new();
+        term.hash_standard_order(&mut hasher);
+        hasher.finish()
+    };
+
+    // the same terms in distinct representations.
+    for (t1, t2) in [
+        (Term::string("ab"), Term::list([a(), b()])),
+        (
+            Term::string("ab"),
+            Term::compound(".", [a(), Term::compound(".", [b(), Term::atom("[]")])]),
+        ),
+        (Term::list([]), Term::atom("[]")),
+        (Term::rational(RBig::from(2)), Term::integer(2)),
+        (
+            Term::compound("f", [Term::variable("X"), Term::variable("X")]),
+            Term::compound("f", [Term::variable("X"), Term::variable("X")]),
+        ),
+    ] {
+        assert_eq!(machine.compare_terms(&t1, &t2), Ordering::Equal);
+        assert_eq!(hash(&t1), hash(&t2));
+    }
+
+    assert_ne!(
+        machine.compare_terms(&Term::float(1.0), &Term::integer(1)),
+        Ordering::Equal
+    );
+
+    let answers: Vec<_> = machine.run_query("X = a.").collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([("X", Term::atom("a"))]))]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn consult_diagnostics() {
+    let mut machine = MachineBuilder::default().build();
+
+    let diagnostics = machine.consult_module_string(
+        "facts",
+        r#"
+p(X, Y).
+:- initialization(fail).
+q(a).
+"#,
+    );
+
+    assert_eq!(diagnostics.len(), 3);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].line, Some(2));
+    assert_eq!(diagnostics[0].kind, "singleton_variable");
+    assert_eq!(diagnostics[0].culprit, Some(Term::atom("X")));
+    assert_eq!(diagnostics[1].culprit, Some(Term::atom("Y")));
+    assert_eq!(diagnostics[2].kind, "initialization_failed");
+
+    let diagnostics = machine.consult_module_string("facts", "q(b).\nr(\n");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].line, Some(2));
+    assert_eq!(diagnostics[0].kind, "syntax_error");
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "user:2: error: syntax_error: incomplete_reduction"
+    );
+
+    // the error of the load isn't found by the next query.
+    let answers: Vec<_> = machine.run_query("true.").collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let mut machine = MachineBuilder::default().build();
+    let diagnostics = machine.consult_module_string("facts", "atom_length(a, b).\n");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, "permission_error");
+    assert_eq!(
+        diagnostics[0].culprit,
+        Some(Term::compound(
+            "/",
+            [Term::atom("atom_length"), Term::integer(2)]
+        ))
+    );
+
+    let answers: Vec<_> = machine.run_query("atom_length(1, _).").collect();
+    let [Err(error)] = &answers[..] else {
+        panic!("expected an error, got {answers:?}");
+    };
+    let diagnostic = Diagnostic::from_error(error);
+
+    assert_eq!(diagnostic.kind, "type_error");
+    assert_eq!(diagnostic.culprit, Some(Term::integer(1)));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn consult_module_feed() {
+    let source = r#"
+% a comment. with a full stop
+p('a. b', "c. d").
+p(0'., 0''').
+p(foo, Y) :- foo =.. Y.
+/* another. comment */
+p(1.5, "e.\nf").
+:- initialization(assertz(q(done))).
+"#;
+
+    // the source arrives one byte at a time, so that a piece ends
+    // within each clause.
+    let mut machine = MachineBuilder::default().build();
+    let mut feed = machine.consult_module_feed("facts");
+
+    for byte in source.as_bytes() {
+        feed.push([*byte]);
+    }
+
+    assert_eq!(feed.finish(), []);
+
+    let answers: Vec<_> = machine
+        .run_query("findall(X-Y, p(X, Y), Ps), q(Q).")
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        answers,
+        [LeafAnswer::from_bindings([
+            (
+                "Ps",
+                Term::list([
+                    Term::compound("-", [Term::atom("a. b"), Term::string("c. d")]),
+                    Term::compound("-", [Term::integer(46), Term::integer(39)]),
+                    Term::compound("-", [Term::atom("foo"), Term::list([Term::atom("foo")])]),
+                    Term::compound("-", [Term::float(1.5), Term::string("e.\nf")]),
+                ])
+            ),
+            ("Q", Term::atom("done")),
+        ])]
+    );
+
+    // a clause is read once the rest of its text arrives.
+    let mut feed = machine.consult_module_feed("facts");
+
+    feed.push("r(one).\nr(");
+    feed.push("two).\n");
+
+    assert_eq!(feed.finish(), []);
+
+    let answers: Vec<_> = machine
+        .run_query("findall(X, r(X), Rs).")
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        answers,
+        [LeafAnswer::from_bindings([(
+            "Rs",
+            Term::list([Term::atom("one"), Term::atom("two")])
+        )])]
+    );
+
+    let mut feed = machine.consult_module_feed("facts");
+
+    feed.push("r(a).\nr(");
+    feed.push("b).\ns(\n");
+
+    let diagnostics = feed.finish();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, "syntax_error");
+    assert_eq!(diagnostics[0].line, Some(3));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn cross_reference() {
+    let mut machine = MachineBuilder::default().build();
+
+    machine.load_module_string(
+        "shapes",
+        r#"
+:- module(shapes, [area/2, perimeter/2]).
+:- use_module(library(lists)).
+
+area(square(S), A) :- A is S * S.
+area(shapes(Ss), A) :- findall(A0, (member(S, Ss), area(S, A0)), As), total(As, A).
+
+perimeter(square(S), P) :- P is 4 * S.
+"#,
+    );
+
+    machine.consult_module_string(
+        "facts",
+        "main :- shapes:area(square(2), _), missing.
+",
+    );
+
+    let calls = machine.predicate_calls();
+    let callees_of = |caller: &str| -> Vec<String> {
+        calls
+            .iter()
+            .filter(|call| call.caller.to_string() == caller)
+            .map(|call| call.callee.to_string())
+            .collect()
+    };
+
+    let area_callees = callees_of("shapes:area/2");
+
+    assert!(area_callees.contains(&"builtins:findall/3".to_string()));
+    assert!(area_callees.contains(&"lists:member/2".to_string()));
+    assert!(area_callees.contains(&"shapes:area/2".to_string()));
+    assert!(area_callees.contains(&"shapes:total/2".to_string()));
+    assert_eq!(
+        callees_of("user:main/0"),
+        ["shapes:area/2", "user:missing/0"]
+    );
+
+    let undefined: Vec<_> = machine
+        .undefined_calls()
+        .into_iter()
+        .filter(|call| ["shapes", "user"].contains(&call.caller.module.as_str()))
+        .map(|call| format!("{} -> {}", call.caller, call.callee))
+        .collect();
+
+    assert_eq!(
+        undefined,
+        [
+            "user:main/0 -> user:missing/0",
+            "shapes:area/2 -> shapes:total/2"
+        ]
+    );
+
+    let unused_exports: Vec<_> = machine
+        .unused_exports()
+        .into_iter()
+        .filter(|predicate| predicate.module == "shapes")
+        .collect();
+
+    assert_eq!(
+        unused_exports,
+        [PredicateIndicator {
+            module: "shapes".into(),
+            name: "perimeter".into(),
+            arity: 2,
+        }]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn module_graph() {
+    let dir = std::env::temp_dir().join(format!("scryer_module_graph_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(
+        dir.join("geometry.pl"),
+        ":- module(geometry, [area/2]).\narea(square(S), A) :- A is S * S.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("report.pl"),
+        ":- module(report, [report/1]).\n:- use_module(library(lists)).\n\
+         :- use_module(geometry).\nreport(A) :- area(square(3), A).\n",
+    )
+    .unwrap();
+
+    let mut machine = MachineBuilder::default().build();
+
+    let query = format!("use_module('{}').", dir.join("report.pl").display());
+    let answers: Vec<_> = machine.run_query(query).collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let graph = machine.module_graph();
+
+    assert_eq!(graph.modules[0], "user");
+    assert!(graph.modules.contains(&"report".to_string()));
+
+    let report_imports: Vec<_> = graph
+        .imports
+        .iter()
+        .filter(|import| import.importer == "report")
+        .map(|import| import.imported.as_str())
+        .collect();
+
+    assert_eq!(report_imports, ["lists", "geometry"]);
+    assert!(graph.to_dot().contains("    \"report\" -> \"geometry\";\n"));
+
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    assert!(json["imports"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!({"importer": "report", "imported": "geometry"})));
+
+    let mut succeeds = |query: &str| {
+        machine
+            .run_query(query)
+            .next()
+            .is_some_and(|answer| answer == Ok(LeafAnswer::True))
+    };
+
+    assert!(succeeds("visible_predicate(report, area/2)."));
+    assert!(succeeds("visible_predicate(report, append/3)."));
+    assert!(!succeeds("visible_predicate(geometry, report/1)."));
+    assert!(succeeds("module_imports(report, geometry)."));
+    assert!(succeeds("module_imports(user, report)."));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn registered_libraries() {
+    let mut machine = MachineBuilder::default()
+        .with_library(
+            "myapp/rules",
+            ":- module(rules, [allowed/1]).\n\
+             :- use_module(library('myapp/roles')).\n\
+             allowed(X) :- role(X, admin).\n",
+        )
+        .with_library(
+            "myapp/roles",
+            ":- module(roles, [role/2]).\nrole(ann, admin).\nrole(bob, guest).\n",
+        )
+        .build();
+
+    let answers: Vec<_> = machine
+        .run_query("use_module(library('myapp/rules')), findall(X, allowed(X), Xs).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "Xs",
+            Term::list([Term::atom("ann")])
+        )]))]
+    );
+
+    machine.add_library("extra", ":- module(extra, [answer/1]).\nanswer(42).\n");
+
+    let answers: Vec<_> = machine
+        .run_query("use_module(library(extra)), answer(A).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([("A", Term::integer(42))]))]
+    );
+
+    let answers: Vec<_> = machine
+        .run_query("catch(use_module(library(missing)), error(E, _), true).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "E",
+            Term::compound(
+                "existence_error",
+                [
+                    Term::atom("source_sink"),
+                    Term::compound("library", [Term::atom("missing")])
+                ]
+            )
+        )]))]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn registered_resources() {
+    let mut machine = MachineBuilder::default()
+        .with_resource("data/greeting.txt", b"hello\n")
+        .with_resource("logo.bin", vec![0, 255, 7])
+        .build();
+
+    let answers: Vec<_> = machine
+        .run_query(
+            "use_module(library(charsio)), \
+             open(resource('data/greeting.txt'), read, S), \
+             get_n_chars(S, 5, Cs), close(S).",
+        )
+        .collect();
+    assert!(matches!(
+        &answers[..],
+        [Ok(LeafAnswer::LeafAnswer { bindings, .. })]
+            if bindings.get("Cs") == Some(&Term::string("hello"))
+    ));
+
+    let answers: Vec<_> = machine
+        .run_query(
+            "open(resource(\"logo.bin\"), read, S, [type(binary)]), \
+             get_byte(S, B1), get_byte(S, B2), get_byte(S, B3), get_byte(S, B4), \
+             close(S).",
+        )
+        .collect();
+    assert!(matches!(
+        &answers[..],
+        [Ok(LeafAnswer::LeafAnswer { bindings, .. })]
+            if bindings.get("B1") == Some(&Term::integer(0))
+                && bindings.get("B2") == Some(&Term::integer(255))
+                && bindings.get("B3") == Some(&Term::integer(7))
+                && bindings.get("B4") == Some(&Term::integer(-1))
+    ));
+
+    machine.add_resource("data/greeting.txt", "bye".as_bytes().to_vec());
+
+    let answers: Vec<_> = machine
+        .run_query(
+            "open(resource('data/greeting.txt'), read, S), \
+             get_n_chars(S, 5, Cs), close(S).",
+        )
+        .collect();
+    assert!(matches!(
+        &answers[..],
+        [Ok(LeafAnswer::LeafAnswer { bindings, .. })]
+            if bindings.get("Cs") == Some(&Term::string("bye"))
+    ));
+
+    let answers: Vec<_> = machine
+        .run_query("catch(open(resource(missing), read, _), error(E, _), true).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "E",
+            Term::compound(
+                "existence_error",
+                [
+                    Term::atom("source_sink"),
+                    Term::compound("resource", [Term::atom("missing")])
+                ]
+            )
+        )]))]
+    );
+
+    let answers: Vec<_> = machine
+        .run_query("catch(open(resource('logo.bin'), write, _), error(E, _), true).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "E",
+            Term::compound(
+                "permission_error",
+                [
+                    Term::atom("open"),
+                    Term::atom("source_sink"),
+                    Term::compound("resource", [Term::atom("logo.bin")])
+                ]
+            )
+        )]))]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn prolog_flags() {
+    let mut machine = MachineBuilder::default()
+        .with_flag("double_quotes", Term::atom("codes"))
+        .unwrap()
+        .build();
+
+    assert_eq!(machine.get_flag("double_quotes"), Some(Term::atom("codes")));
+    assert_eq!(machine.get_flag("bounded"), Some(Term::atom("false")));
+    assert_eq!(machine.get_flag("occurs_check"), Some(Term::atom("false")));
+    assert_eq!(machine.get_flag("max_integer"), None);
+    assert_eq!(machine.get_flag("no_such_flag"), None);
+
+    let answers: Vec<_> = machine.run_query(r#"X = "ab"."#).collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "X",
+            Term::list([Term::integer(97), Term::integer(98)])
+        )]))]
+    );
+
+    assert_eq!(machine.set_flag("unknown", Term::atom("fail")), Ok(()));
+    assert_eq!(machine.get_flag("unknown"), Some(Term::atom("fail")));
+
+    let answers: Vec<_> = machine.run_query("undefined_predicate.").collect();
+    assert_eq!(answers, [Ok(LeafAnswer::False)]);
+
+    assert_eq!(machine.set_flag("occurs_check", Term::atom("true")), Ok(()));
+    assert_eq!(machine.get_flag("occurs_check"), Some(Term::atom("true")));
+
+    assert_eq!(
+        machine.set_flag("double_quotes", Term::atom("bytes")),
+        Err(Term::compound(
+            "error",
+            [
+                Term::compound(
+                    "domain_error",
+                    [
+                        Term::atom("flag_value"),
+                        Term::compound("+", [Term::atom("double_quotes"), Term::atom("bytes")])
+                    ]
+                ),
+                Term::compound("/", [Term::atom("set_prolog_flag"), Term::integer(2)])
+            ]
+        ))
+    );
+    assert!(machine.set_flag("bounded", Term::atom("true")).is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn builder_flags() {
+    use crate::FlagError;
+
+    let valid = [
+        ("bounded", Term::atom("false")),
+        ("integer_rounding_function", Term::atom("down")),
+        ("occurs_check", Term::atom("error")),
+        (
+            "answer_write_options",
+            Term::list([
+                Term::compound("max_depth", [Term::integer(5)]),
+                Term::compound("quoted", [Term::atom("true")]),
+            ]),
+        ),
+        ("answer_duplicates", Term::atom("variant")),
+        ("float_undefined", Term::atom("nan")),
+        ("term_hash_algorithm", Term::atom("sha3_256")),
+        ("shuffle_seed", Term::integer(42)),
+        ("rational_precision", Term::integer(50)),
+        ("rational_rounding", Term::atom("down")),
+    ];
+
+    let invalid = [
+        ("bounded", Term::atom("true")),
+        ("max_integer", Term::integer(1)),
+        ("double_quotes", Term::atom("bytes")),
+        (
+            "answer_write_options",
+            Term::list([Term::compound("max_depth", [Term::integer(-1)])]),
+        ),
+        ("float_undefined", Term::atom("infinity")),
+        ("shuffle_seed", Term::integer(-1)),
+        ("rational_precision", Term::integer(0)),
+        ("rational_rounding", Term::atom("half_down")),
+        ("no_such_flag", Term::atom("true")),
+    ];
+
+    // the flags are checked as set_prolog_flag/2 checks them.
+    let mut machine = MachineBuilder::default().build();
+
+    for (name, value) in &valid {
+        assert_eq!(machine.set_flag(name, value.clone()), Ok(()), "{name}");
+    }
+
+    for (name, value) in &invalid {
+        assert!(machine.set_flag(name, value.clone()).is_err(), "{name}");
+    }
+
+    let mut builder = MachineBuilder::default();
+
+    for (name, value) in valid {
+        builder = builder.with_flag(name, value).unwrap();
+    }
+
+    let mut machine = builder.build();
+    assert_eq!(machine.get_flag("shuffle_seed"), Some(Term::integer(42)));
+    assert_eq!(
+        machine.get_flag("rational_precision"),
+        Some(Term::integer(50))
+    );
+
+    for (name, value) in invalid {
+        assert_eq!(
+            MachineBuilder::default()
+                .with_flag(name, value.clone())
+                .err(),
+            Some(FlagError {
+                name: name.to_string(),
+                value
+            })
+        );
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn predicates_and_clauses() {
+    let mut machine = MachineBuilder::default().build();
+
+    machine.load_module_string(
+        "kb",
+        r#"
+:- module(kb, [parent/2, ancestor/2, apply_to/2]).
+:- use_module(library(lists)).
+:- meta_predicate(apply_to(1, ?)).
+:- dynamic(parent/2).
+
+parent(tom, bob).
+parent(bob, 'Ann').
+
+ancestor(X, Y) :- parent(X, Y).
+ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y).
+
+apply_to(G, X) :- call(G, X).
+"#,
+    );
+
+    let predicates = machine.predicates("kb");
+    let names: Vec<_> = predicates
+        .iter()
+        .map(|predicate| predicate.indicator.to_string())
+        .collect();
+
+    assert_eq!(names, ["kb:parent/2", "kb:ancestor/2", "kb:apply_to/2"]);
+
+    assert!(predicates[0].is_dynamic);
+    assert!(predicates[0].is_exported);
+    assert!(!predicates[1].is_dynamic);
+    assert!(!predicates[1].is_built_in);
+    assert_eq!(
+        predicates[2].meta_predicate,
+        Some(Term::compound(
+            "meta_predicate",
+            [Term::compound(
+                "apply_to",
+                [Term::integer(1), Term::atom("?")]
+            )]
+        ))
+    );
+
+    assert_eq!(
+        machine.clauses("kb", "parent", 2),
+        [
+            Term::compound("parent", [Term::atom("tom"), Term::atom("bob")]),
+            Term::compound("parent", [Term::atom("bob"), Term::atom("Ann")]),
+        ]
+    );
+
+    // static predicates keep no clauses.
+    assert_eq!(machine.clauses("kb", "ancestor", 2), []);
+    assert_eq!(machine.clauses("kb", "missing", 0), []);
+
+    machine.consult_module_string(
+        "facts",
+        ":- dynamic(rule/1).\nrule(X) :- X > 1, kb:parent(X, _).\n",
+    );
+
+    assert!(machine
+        .predicates("user")
+        .iter()
+        .any(|predicate| predicate.indicator.to_string() == "user:rule/1"));
+    assert_eq!(
+        machine.clauses("user", "rule", 1),
+        [Term::compound(
+            ":-",
+            [
+                Term::compound("rule", [Term::variable("_A")]),
+                Term::conjunction([
+                    Term::compound(">", [Term::variable("_A"), Term::integer(1)]),
+                    Term::compound(
+                        ":",
+                        [
+                            Term::atom("kb"),
+                            Term::compound("parent", [Term::variable("_A"), Term::variable("_B")])
+                        ]
+                    ),
+                ]),
+            ]
+        )]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn style_check_diagnostics() {
+    let mut machine = MachineBuilder::default().build();
+
+    let diagnostics = machine.consult_module_string("facts", "p(1).\nq(1).\np(2).\n");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].line, Some(3));
+    assert_eq!(diagnostics[0].kind, "discontiguous_predicate");
+    assert_eq!(
+        diagnostics[0].culprit,
+        Some(Term::compound("/", [Term::atom("p"), Term::integer(1)]))
+    );
+
+    let diagnostics = machine.consult_module_string("more_facts", "r(1).\nq(2).\n");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, Some(2));
+    assert_eq!(diagnostics[0].kind, "redefined_predicate");
+
+    let diagnostics = machine.consult_module_string(
+        "facts",
+        ":- style_check(-discontiguous).\n:- style_check(-singleton).\ns(X).\nt.\ns(2).\n",
+    );
+
+    assert_eq!(diagnostics, []);
+
+    let answers: Vec<_> = machine.run_query("style_check(?(C)).").collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "C",
+            Term::atom("redefined")
+        )]))]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn module_unknown_flag() {
+    let mut machine = MachineBuilder::default().build();
+
+    machine.load_module_string(
+        "ported",
+        ":- module(ported, [p/1, q/0]).\n\
+         :- set_prolog_flag(ported:unknown, fail).\n\
+         p(X) :- missing(X).\n\
+         p(2).\n\
+         q :- call(missing).\n",
+    );
+
+    machine.load_module_string("strict", ":- module(strict, [s/0]).\ns :- missing.\n");
+
+    let answers: Vec<_> = machine.run_query("findall(X, ported:p(X), Xs).").collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "Xs",
+            Term::list([Term::integer(2)])
+        )]))]
+    );
+
+    let answers: Vec<_> = machine.run_query("ported:q.").collect();
+    assert_eq!(answers, [Ok(LeafAnswer::False)]);
+
+    // the calls of other modules are still checked.
+    let answers: Vec<_> = machine
+        .run_query("catch(strict:s, error(E, _), true).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([(
+            "E",
+            Term::compound(
+                "existence_error",
+                [
+                    Term::atom("procedure"),
+                    Term::compound("/", [Term::atom("missing"), Term::integer(0)]),
+                ]
+            )
+        )]))]
+    );
+
+    let answers: Vec<_> = machine
+        .run_query(
+            "current_prolog_flag(ported:unknown, P), current_prolog_flag(strict:unknown, S).",
+        )
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([
+            ("P", Term::atom("fail")),
+            ("S", Term::atom("error")),
+        ]))]
+    );
+
+    let mut machine = MachineBuilder::default()
+        .with_unknown(UnknownProcedure::Fail)
+        .build();
+
+    let answers: Vec<_> = machine
+        .run_query("current_prolog_flag(unknown, U), \\+ missing.")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([("U", Term::atom("fail"))]))]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn make_reloads_changed_files() {
+    let dir = std::env::temp_dir().join(format!("scryer_make_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let main = dir.join("main.pl");
+    let lib = dir.join("lib.pl");
+
+    std::fs::write(
+        &main,
+        ":- use_module(lib).\n:- dynamic(counter/1).\ncounter(0).\nversion(1).\n",
+    )
+    .unwrap();
+    std::fs::write(&lib, ":- module(lib, [greeting/1]).\ngreeting(hello).\n").unwrap();
+
+    let mut machine = MachineBuilder::default().build();
+
+    let query = format!(
+        "consult('{}'), retract(counter(0)), assertz(counter(5)).",
+        main.display()
+    );
+    let answers: Vec<_> = machine.run_query(query).collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    // nothing changed, so nothing is reloaded.
+    let answers: Vec<_> = machine.run_query("make, counter(C).").collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([("C", Term::integer(5))]))]
+    );
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    std::fs::write(
+        &main,
+        ":- use_module(lib).\n:- dynamic(counter/1).\ncounter(0).\nversion(2).\n",
+    )
+    .unwrap();
+    std::fs::write(&lib, ":- module(lib, [greeting/1]).\ngreeting(goodbye).\n").unwrap();
+
+    // the changed files are reloaded, keeping the asserted counter.
+    let answers: Vec<_> = machine
+        .run_query("make, version(V), greeting(G), counter(C).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([
+            ("V", Term::integer(2)),
+            ("G", Term::atom("goodbye")),
+            ("C", Term::integer(5)),
+        ]))]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn crash_report() {
+    let dir = std::env::temp_dir().join(format!("scryer_crash_report_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("crash.txt");
+
+    let mut machine = MachineBuilder::default()
+        .with_crash_report(&path)
+        .with_trace_hook(|event| match &event.goal {
+            Term::Compound(name, _) if name == "crash" => panic!("crash in the trace hook"),
+            _ => TraceAction::Creep,
+        })
+        .build();
+
+    assert_eq!(machine.config().crash_report.as_ref(), Some(&path));
+
+    machine.consult_module_string(
+        "crashes",
+        r#"
+            p(X) :- q(X), true.
+            q(X) :- crash(X), true.
+            crash(_).
+        "#,
+    );
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        machine
+            .run_query("use_module(library(trace)), trace, p(1).")
+            .next()
+    }));
+    assert!(result.is_err());
+
+    let report = std::fs::read_to_string(&path).unwrap();
+
+    assert!(report.starts_with("Scryer Prolog crash report\n"));
+    assert!(report.contains("crash in the trace hook"));
+    assert!(report.contains("\nquery: use_module(library(trace)), trace, p(1).\n"));
+    assert!(report.contains("\n  user:q/1\n"));
+    assert!(report.contains("\n  user:p/1\n"));
+    assert!(report.contains("\nflags:\n  double_quotes: chars\n  unknown: error\n"));
+    assert!(report.contains("\n  lists: library\n"));
+
+    let mut machine = MachineBuilder::default().with_crash_report(&path).build();
+
+    machine.consult_module_string("toplevel", "report_crash(E) :- '$report_crash'(E).");
+
+    let answers: Vec<_> = machine
+        .run_query("report_crash(error(resource_error(memory), repl/0)).")
+        .collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let report = std::fs::read_to_string(&path).unwrap();
+    assert!(report.contains(
+        "\ncause: uncaught error in the toplevel: error(resource_error(memory),repl/0)\n"
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn background_tasks() {
+    let dir = std::env::temp_dir().join(format!("scryer_background_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(
+        dir.join("tasks.pl"),
+        ":- spawn_initialization(wait).\n:- spawn_initialization(spin).\n\
+         wait :- threads:thread_get_message(stop).\nspin :- repeat, fail.\n",
+    )
+    .unwrap();
+
+    let mut machine = MachineBuilder::default().build();
+
+    let query = format!("consult('{}').", dir.join("tasks.pl").display());
+    let answers: Vec<_> = machine.run_query(query).collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let tasks = machine.background_tasks();
+    assert_eq!(tasks.len(), 2);
+
+    assert!(machine.cancel_background_task(&tasks[1]));
+    assert_eq!(
+        machine.join_background_task(&tasks[1]),
+        Some(Term::atom("cancelled"))
+    );
+
+    let query = format!(
+        "threads:thread_send_message({}, stop).",
+        Term::atom(&tasks[0])
+    );
+    let answers: Vec<_> = machine.run_query(query).collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    assert_eq!(
+        machine.join_background_task(&tasks[0]),
+        Some(Term::atom("true"))
+    );
+
+    assert!(machine.background_tasks().is_empty());
+    assert!(!machine.cancel_background_task(&tasks[0]));
+    assert_eq!(machine.join_background_task(&tasks[0]), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn config_from_env() {
+    use crate::{EnvVarError, QueryOptions};
+    use std::path::PathBuf;
+
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+        move |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    };
+
+    let mut machine = MachineBuilder::from_env_vars(env(&[
+        ("SCRYER_LIBRARY_PATH", "/opt/prolog/lib"),
+        ("SCRYER_UNKNOWN", "fail"),
+        ("SCRYER_STEP_LIMIT", "100000"),
+        ("SCRYER_FLAGS", "double_quotes=codes, occurs_check=error"),
+    ]))
+    .unwrap()
+    .build();
+
+    let answers: Vec<_> = machine.run_query("undefined_predicate.").collect();
+    assert_eq!(answers, [Ok(LeafAnswer::False)]);
+
+    assert_eq!(
+        machine.config(),
+        MachineConfig {
+            unknown: UnknownProcedure::Fail,
+            library_paths: vec![PathBuf::from("/opt/prolog/lib")],
+            parallel_compilation: false,
+            index_cache: false,
+            query_options: QueryOptions {
+                step_limit: Some(100000),
+                ..QueryOptions::default()
+            },
+            flags: vec![
+                ("double_quotes".to_string(), Term::atom("codes")),
+                ("occurs_check".to_string(), Term::atom("error")),
+            ],
+            crash_report: None,
+        }
+    );
+
+    let answers: Vec<_> = machine.run_query("repeat, false.").collect();
+    assert_eq!(
+        answers,
+        [Err(Term::compound(
+            "error",
+            [
+                Term::compound("resource_error", [Term::atom("steps")]),
+                Term::compound("/", [Term::atom("call"), Term::integer(1)]),
+            ]
+        ))]
+    );
+
+    assert_eq!(machine.set_flag("unknown", Term::atom("error")), Ok(()));
+    assert_eq!(machine.config().unknown, UnknownProcedure::Error);
+
+    assert_eq!(
+        MachineBuilder::from_env_vars(env(&[("SCRYER_INDEX_CACHE", "yes")])).err(),
+        Some(EnvVarError {
+            variable: "SCRYER_INDEX_CACHE".to_string(),
+            value: "yes".to_string(),
+        })
+    );
+    assert!(MachineBuilder::from_env_vars(env(&[("SCRYER_FLAGS", "double_quotes")])).is_err());
+    assert_eq!(
+        MachineBuilder::from_env_vars(env(&[("SCRYER_FLAGS", "double_quotes=bytes")])).err(),
+        Some(EnvVarError {
+            variable: "SCRYER_FLAGS".to_string(),
+            value: "double_quotes=bytes".to_string(),
+        })
+    );
+    assert!(MachineBuilder::from_env_vars(env(&[("SCRYER_FLAGS", "no_such_flag=1")])).is_err());
+    assert!(MachineBuilder::from_env_vars(env(&[("SCRYER_HEAP_LIMIT", "-1")])).is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn health_checks() {
+    use crate::{HealthStatus, Liveness, QueryOptions};
+    use std::time::Duration;
+
+    let mut machine = MachineBuilder::default().build();
+
+    machine.consult_module_string("user", "db_connected.");
+
+    let answers: Vec<_> = machine
+        .run_query("use_module(library(health)), add_readiness_check(db, db_connected).")
+        .collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let report = machine.health();
+    assert_eq!(report.status, HealthStatus::Pass);
+
+    let names: Vec<_> = report
+        .checks
+        .iter()
+        .map(|check| check.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "memory:heap",
+            "memory:stack",
+            "memory:trail",
+            "readiness:db"
+        ]
+    );
+    assert!(report.checks[0]
+        .observed_bytes
+        .is_some_and(|bytes| bytes > 0));
+
+    let answers: Vec<_> = machine
+        .run_query("add_readiness_check(cache, throw(unreachable)).")
+        .collect();
+    assert_eq!(answers, [Ok(LeafAnswer::True)]);
+
+    let report = machine.health();
+    assert_eq!(report.status, HealthStatus::Fail);
+    assert_eq!(report.checks[4].output.as_deref(), Some("unreachable"));
+    assert_eq!(report.checks[4].status, HealthStatus::Fail);
+    assert!(report
+        .to_json()
+        .contains(r#""readiness:cache":[{"output":"unreachable","status":"fail"}]"#));
+
+    let monitor = machine.health_monitor();
+    assert_eq!(monitor.liveness(), Liveness::Idle);
+
+    let watcher = std::thread::spawn(move || loop {
+        if let Liveness::Running { since_heartbeat } = monitor.liveness() {
+            return since_heartbeat;
+        }
+
+        std::thread::yield_now();
+    });
+
+    let options = QueryOptions {
+        step_limit: Some(1_000_000),
+        ..QueryOptions::default()
+    };
+
+    while !watcher.is_finished() {
+        let _ = machine
+            .run_query_with_options("repeat, false.", options.clone())
+            .next();
+    }
+
+    assert!(watcher.join().unwrap() < Duration::from_secs(60));
+    assert_eq!(machine.health_monitor().liveness(), Liveness::Idle);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn shuffle_seed() {
+    fn arities(seed: Option<i64>) -> Term {
+        let mut builder = MachineBuilder::default();
+
+        if let Some(seed) = seed {
+            builder = builder
+                .with_flag("shuffle_seed", Term::integer(seed))
+                .unwrap();
+        }
+
+        let mut machine = builder.build();
+
+        machine.consult_module_string(
+            "user",
+            "p(_). p(_, _). p(_, _, _). p(_, _, _, _). p(_, _, _, _, _). p(_, _, _, _, _, _).",
+        );
+
+        let answers: Vec<_> = machine
+            .run_query("findall(A, current_predicate(p/A), As).")
+            .collect();
+
+        match &answers[..] {
+            [Ok(LeafAnswer::LeafAnswer { bindings, .. })] => bindings["As"].clone(),
+            _ => panic!("{answers:?}"),
+        }
+    }
+
+    let unshuffled = arities(None);
+    assert_eq!(
+        unshuffled,
+        Term::list((1..=6).map(Term::integer).collect::<Vec<_>>())
+    );
+
+    let shuffled = arities(Some(42));
+    assert_ne!(shuffled, unshuffled);
+    assert_eq!(shuffled, arities(Some(42)));
+
+    let Term::List(mut sorted) = shuffled else {
+        panic!("{shuffled:?}");
+    };
+    sorted.sort_by_key(|term| format!("{term:?}"));
+    assert_eq!(Term::List(sorted), unshuffled);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn non_finite_floats() {
+    let mut machine = MachineBuilder::default().build();
+
+    let floats = [f64::NAN, f64::NEG_INFINITY, -0.0, 0.0, 1.5, f64::INFINITY];
+
+    for f in floats {
+        let answers: Vec<_> = machine
+            .run_query(format!("X = {}.", Term::float(f)))
+            .collect();
+        assert_eq!(
+            answers,
+            [Ok(LeafAnswer::from_bindings([("X", Term::float(f))]))]
+        );
+    }
+
+    assert_eq!(Term::float(f64::NAN).to_string(), "1.5NaN");
+    assert_eq!(Term::float(f64::NEG_INFINITY).to_string(), "-1.0Inf");
+    assert_eq!(Term::float(-0.0).to_string(), "-0.0");
+    assert_ne!(Term::float(-0.0), Term::float(0.0));
+
+    for pair in floats.windows(2) {
+        assert_eq!(
+            machine.compare_terms(&Term::float(pair[0]), &Term::float(pair[1])),
+            Ordering::Less
+        );
+    }
+
+    let answers: Vec<_> = machine.run_query("X is 1 / 0.0.").collect();
+    assert!(matches!(&answers[..], [Err(_)]), "{answers:?}");
+
+    assert_eq!(
+        machine.set_flag("float_zero_div", Term::atom("infinity")),
+        Ok(())
+    );
+
+    let answers: Vec<_> = machine
+        .run_query("X is 1 / 0.0, Y is nan, (Y =:= Y -> Z = t ; Z = f).")
+        .collect();
+    assert_eq!(
+        answers,
+        [Ok(LeafAnswer::from_bindings([
+            ("X", Term::float(f64::INFINITY)),
+            ("Y", Term::float(f64::NAN)),
+            ("Z", Term::atom("f")),
+        ]))]
+    );
 }
@@ -118,6 +118,30 @@ pub(super) fn add_op_decl(
     }
 }
 
+// errs if any of the predicates among exports lie outside the frozen
+// interface of module, if it has one.
+fn check_frozen_exports<'a>(
+    module: &Module,
+    exports: impl Iterator<Item = &'a ModuleExport>,
+) -> Result<(), SessionError> {
+    let Some(frozen_exports) = &module.frozen_exports else {
+        return Ok(());
+    };
+
+    for export in exports {
+        if let ModuleExport::PredicateKey(key) = export {
+            if !frozen_exports.contains(key) {
+                return Err(SessionError::FrozenModuleInterface(
+                    module.module_decl.name,
+                    *key,
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub(super) fn import_module_exports<'a, LS: LoadState<'a>>(
     payload: &mut LS::LoaderFieldType,
     compilation_target: &CompilationTarget,
@@ -917,6 +941,12 @@ pub(super) fn add_dynamically_generated_module(&mut self, module_name: Atom) {
         let listing_src = ListingSource::DynamicallyGenerated;
         let mut module = Module::new(module_decl, listing_src);
 
+        module.location = self
+            .wam_prelude
+            .load_contexts
+            .last()
+            .and_then(LoadContext::clause_location);
+
         self.import_builtins_in_module(
             module_name,
             &mut module.code_dir,
@@ -1024,6 +1054,8 @@ pub(crate) fn add_module(
             } else {
                 LS::err_on_builtin_module_overwrite(module_name)?;
             }
+
+            check_frozen_exports(module, module_decl.exports.iter())?;
         }
 
         self.reset_in_situ_module(module_decl.clone(), &listing_src);
@@ -1031,6 +1063,7 @@ pub(crate) fn add_module(
         let mut module = match self.wam_prelude.indices.modules.swap_remove(&module_name) {
             Some(mut module) => {
                 module.listing_src = listing_src;
+                module.reexports.clear();
                 module
             }
             None => {
@@ -1042,6 +1075,12 @@ pub(crate) fn add_module(
             }
         };
 
+        module.location = self
+            .wam_prelude
+            .load_contexts
+            .last()
+            .and_then(LoadContext::clause_location);
+
         self.import_builtins_in_module(
             module_name,
             &mut module.code_dir,
@@ -1065,10 +1104,25 @@ pub(crate) fn add_module(
         }
 
         self.wam_prelude.indices.modules.insert(module_name, module);
+        self.wam_prelude
+            .indices
+            .module_imports
+            .swap_remove(&module_name);
 
         Ok(())
     }
 
+    fn record_module_import(&mut self, module_name: Atom) {
+        let importer = self.payload.compilation_target.module_name();
+
+        self.wam_prelude
+            .indices
+            .module_imports
+            .entry(importer)
+            .or_default()
+            .insert(module_name);
+    }
+
     pub(super) fn import_module(&mut self, module_name: Atom) -> Result<(), SessionError> {
         if let Some(module) = self.wam_prelude.indices.modules.swap_remove(&module_name) {
             let payload_compilation_target = self.payload.compilation_target;
@@ -1113,6 +1167,8 @@ pub(super) fn import_module(&mut self, module_name: Atom) -> Result<(), SessionE
             }
 
             self.wam_prelude.indices.modules.insert(module_name, module);
+            self.record_module_import(module_name);
+
             Ok(())
         } else {
             Err(SessionError::ExistenceError(ExistenceError::Module(
@@ -1161,6 +1217,11 @@ pub(super) fn import_qualified_module(
             };
 
             self.wam_prelude.indices.modules.insert(module_name, module);
+
+            if result.is_ok() {
+                self.record_module_import(module_name);
+            }
+
             result
         } else {
             Err(SessionError::ExistenceError(ExistenceError::Module(
@@ -1169,6 +1230,75 @@ pub(super) fn import_qualified_module(
         }
     }
 
+    // adds exports, or all of the exports of module_name if exports is
+    // empty, to those of the module being loaded, which imported them
+    // from module_name.
+    pub(super) fn reexport_module(
+        &mut self,
+        module_name: Atom,
+        exports: IndexSet<ModuleExport>,
+    ) -> Result<(), SessionError> {
+        let exports = if exports.is_empty() {
+            match self.wam_prelude.indices.modules.get(&module_name) {
+                Some(module) => module.module_decl.exports.iter().cloned().collect(),
+                None => {
+                    return Err(SessionError::ExistenceError(ExistenceError::Module(
+                        module_name,
+                    )));
+                }
+            }
+        } else {
+            exports
+        };
+
+        // the exports of a file without a module declaration are those
+        // of user, which sees them already.
+        let CompilationTarget::Module(target_module_name) = self.payload.compilation_target else {
+            return Ok(());
+        };
+
+        if let Some(target_module) = self
+            .wam_prelude
+            .indices
+            .modules
+            .get_mut(&target_module_name)
+        {
+            check_frozen_exports(target_module, exports.iter())?;
+
+            for export in exports {
+                if !target_module.module_decl.exports.contains(&export) {
+                    target_module.module_decl.exports.push(export.clone());
+                }
+
+                target_module.reexports.insert(export);
+            }
+        }
+
+        Ok(())
+    }
+
+    // freezes the interface of the module being loaded at its current
+    // exports, after which adding to them is a permission error.
+    pub(super) fn freeze_module_interface(&mut self) {
+        let CompilationTarget::Module(module_name) = self.payload.compilation_target else {
+            return;
+        };
+
+        if let Some(module) = self.wam_prelude.indices.modules.get_mut(&module_name) {
+            let exports = module
+                .module_decl
+                .exports
+                .iter()
+                .filter_map(|export| match export {
+                    ModuleExport::PredicateKey(key) => Some(*key),
+                    ModuleExport::OpDecl(_) => None,
+                })
+                .collect();
+
+            module.frozen_exports = Some(exports);
+        }
+    }
+
     pub(crate) fn use_module(&mut self, module_src: ModuleSource) -> Result<(), SessionError> {
         let (stream, listing_src) = match module_src {
             ModuleSource::File(filename) => {
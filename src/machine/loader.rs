@@ -1,9 +1,13 @@
 use crate::arena::*;
 use crate::atom_table::*;
 use crate::forms::*;
+use crate::functor_macro::*;
 use crate::heap_iter::*;
 use crate::indexing::*;
 use crate::instructions::*;
+use crate::machine::code_locations::*;
+use crate::machine::index_cache::SourceStamp;
+use crate::machine::lib_machine::{self, Diagnostic, LoadProgress, LoadStage, Severity};
 use crate::machine::load_state::*;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
@@ -13,14 +17,16 @@
 use crate::parser::ast::*;
 use crate::types::*;
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 
 use std::cell::Cell;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops::{Deref, DerefMut};
-use std::rc::Rc;
+use std::path::Path;
+use std::sync::Arc;
+use web_time::Instant;
 
 /*
  * The loader compiles Prolog terms read from a TermStream instance,
@@ -179,13 +185,18 @@ pub(crate) fn module_name(&self) -> Atom {
 
 pub struct PredicateQueue {
     pub(super) predicates: Vec<Term>,
+    // the locations of the predicates, if they were consulted from a file.
+    pub(super) locations: Vec<Option<ClauseLocation>>,
+    // the line of the first predicate in its load, if known.
+    pub(super) line: Option<usize>,
     pub(super) compilation_target: CompilationTarget,
 }
 
 impl PredicateQueue {
     #[inline]
-    pub(super) fn push(&mut self, clause: Term) {
+    pub(super) fn push(&mut self, clause: Term, location: Option<ClauseLocation>) {
         self.predicates.push(clause);
+        self.locations.push(location);
     }
 
     #[inline]
@@ -202,6 +213,8 @@ pub(crate) fn is_empty(&self) -> bool {
     pub(super) fn take(&mut self) -> Self {
         Self {
             predicates: std::mem::take(&mut self.predicates),
+            locations: std::mem::take(&mut self.locations),
+            line: self.line.take(),
             compilation_target: self.compilation_target,
         }
     }
@@ -213,14 +226,64 @@ pub(super) fn len(&self) -> usize {
 }
 
 macro_rules! predicate_queue {
-    [$($v:expr),*] => (
+    [] => (
         PredicateQueue {
-            predicates: vec![$($v,)*],
+            predicates: vec![],
+            locations: vec![],
+            line: None,
             compilation_target: CompilationTarget::default(),
         }
     )
 }
 
+// a warning of the style checks set by style_check/1, raised while
+// compiling the predicates of a load and reported by the loader.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum StyleWarning {
+    // the clauses of a predicate already compiled by the load, and not
+    // declared discontiguous, replace its earlier clauses.
+    Discontiguous(CompilationTarget, PredicateKey),
+    // the clauses of a predicate defined before the load replace its
+    // definition.
+    Redefined(CompilationTarget, PredicateKey),
+}
+
+// the clauses loaded between the reports of the progress of a load.
+const CLAUSES_PER_PROGRESS_REPORT: usize = 1000;
+
+// the progress of the outermost load, reported to the hook of
+// MachineBuilder::with_load_progress_hook.
+#[derive(Debug)]
+pub(crate) struct LoadProgressMeter {
+    hook: LoadProgressHook,
+    started: Instant,
+    files: usize,
+    clauses: usize,
+}
+
+impl LoadProgressMeter {
+    pub(crate) fn new(hook: LoadProgressHook) -> Self {
+        Self {
+            hook,
+            started: Instant::now(),
+            files: 0,
+            clauses: 0,
+        }
+    }
+
+    fn report(&mut self, stage: LoadStage, file: Option<Atom>) {
+        let progress = LoadProgress {
+            stage,
+            file: file.map(|file| file.as_str().to_string()),
+            files: self.files,
+            clauses: self.clauses,
+            elapsed: self.started.elapsed(),
+        };
+
+        (self.hook.0)(&progress);
+    }
+}
+
 pub type LiveLoadState = LoadStatePayload<LiveTermStream>;
 
 pub struct BootstrappingLoadState<'a>(pub LoadStatePayload<BootstrappingTermStream<'a>>);
@@ -526,7 +589,14 @@ fn dequeue_terms(&mut self) -> Result<Option<Declaration>, SessionError> {
                 term => term,
             };
 
-            self.payload.predicates.push(term);
+            let load_context = self.wam_prelude.load_contexts.last();
+            let location = load_context.and_then(LoadContext::clause_location);
+
+            if self.payload.predicates.is_empty() {
+                self.payload.predicates.line = load_context.and_then(|context| context.line);
+            }
+
+            self.payload.predicates.push(term, location);
         }
 
         Ok(None)
@@ -845,7 +915,11 @@ fn reset_machine(&mut self) {
                         .get_predicate_skeleton_mut(&compilation_target, &key)
                     {
                         skeleton.clauses.pop_back();
-                        skeleton.core.clause_clause_locs.pop_back();
+
+                        if let Some(clause_clause_loc) = skeleton.core.clause_clause_locs.pop_back()
+                        {
+                            skeleton.clause_store.remove(clause_clause_loc);
+                        }
                     }
                 }
                 RetractionRecord::SkeletonClausePopFront(compilation_target, key) => {
@@ -855,7 +929,12 @@ fn reset_machine(&mut self) {
                         .get_predicate_skeleton_mut(&compilation_target, &key)
                     {
                         skeleton.clauses.pop_front();
-                        skeleton.core.clause_clause_locs.pop_front();
+
+                        if let Some(clause_clause_loc) =
+                            skeleton.core.clause_clause_locs.pop_front()
+                        {
+                            skeleton.clause_store.remove(clause_clause_loc);
+                        }
                         skeleton.core.clause_assert_margin -= 1;
                     }
                 }
@@ -921,7 +1000,10 @@ fn reset_machine(&mut self) {
                         .get_predicate_skeleton_mut(&compilation_target, &key)
                     {
                         skeleton.clauses.truncate(len);
-                        skeleton.core.clause_clause_locs.truncate(len);
+
+                        for clause_clause_loc in skeleton.core.clause_clause_locs.drain(len..) {
+                            skeleton.clause_store.remove(clause_clause_loc);
+                        }
                     }
                 }
                 RetractionRecord::SkeletonClauseStartReplaced(
@@ -959,6 +1041,7 @@ fn reset_machine(&mut self) {
                                 .insert(target_pos, clause_clause_loc);
 
                             skeleton.clauses.insert(target_pos, clause_index_info);
+                            skeleton.rebuild_clause_store();
                         }
                     }
                 }
@@ -979,6 +1062,10 @@ fn reset_machine(&mut self) {
                             .clause_clause_locs
                             .insert(target_pos, clause_clause_loc);
                         skeleton.clauses.insert(target_pos, clause_index_info);
+
+                        if skeleton.core.is_dynamic {
+                            skeleton.rebuild_clause_store();
+                        }
                     }
                 }
                 RetractionRecord::ReplacedIndexingLine(index_loc, indexing_code) => {
@@ -1270,6 +1357,10 @@ fn add_dynamic_predicate(
         name: Atom,
         arity: usize,
     ) -> Result<(), SessionError> {
+        self.payload
+            .dynamic_predicates
+            .insert((compilation_target, (name, arity)));
+
         self.add_extensible_predicate_declaration(
             compilation_target,
             name,
@@ -1279,6 +1370,36 @@ fn add_dynamic_predicate(
         )
     }
 
+    // records the file of the load, if it was loaded from one, with the
+    // predicates it compiled, for make/0 to reload it once it changes.
+    fn record_loaded_file(&mut self) {
+        let Some(load_context) = self.wam_prelude.load_contexts.last() else {
+            return;
+        };
+
+        let Some(stamp) = SourceStamp::of(&load_context.path) else {
+            return;
+        };
+
+        let Some(path) = load_context.path.to_str() else {
+            return;
+        };
+
+        let path = AtomTable::build_with(&LS::machine_st(&mut self.payload).atom_tbl, path);
+
+        let predicates = self
+            .payload
+            .compiled_predicates
+            .union(&self.payload.dynamic_predicates)
+            .cloned()
+            .collect();
+
+        self.wam_prelude
+            .indices
+            .loaded_files
+            .insert(path, LoadedFile { stamp, predicates });
+    }
+
     fn add_multifile_predicate(
         &mut self,
         compilation_target: CompilationTarget,
@@ -1395,10 +1516,10 @@ pub(super) fn read_term_from_heap(&mut self, term_addr: HeapCellValue) -> Term {
 
                     match as_partial_string(head, tail) {
                         Ok((string, Some(tail))) => {
-                            term_stack.push(Term::PartialString(Cell::default(), Rc::new(string), tail));
+                            term_stack.push(Term::PartialString(Cell::default(), Arc::new(string), tail));
                         }
                         Ok((string, None)) => {
-                            term_stack.push(Term::CompleteString(Cell::default(), Rc::new(string)));
+                            term_stack.push(Term::CompleteString(Cell::default(), Arc::new(string)));
                         }
                         Err(cons_term) => term_stack.push(cons_term),
                     }
@@ -1439,12 +1560,12 @@ pub(super) fn read_term_from_heap(&mut self, term_addr: HeapCellValue) -> Term {
                     term_stack.push(if matches!(tail, Term::Literal(_, Literal::Atom(atom!("[]")))) {
                         Term::CompleteString(
                             Cell::default(),
-                            Rc::new(string.to_owned()),
+                            Arc::new(string.to_owned()),
                         )
                     } else {
                         Term::PartialString(
                             Cell::default(),
-                            Rc::new(string.to_owned()),
+                            Arc::new(string.to_owned()),
                             Box::new(tail),
                         )
                     });
@@ -1494,6 +1615,61 @@ pub(crate) fn use_module(&mut self) -> CallResult {
         self.restore_load_state_payload(result)
     }
 
+    pub(crate) fn reexport(&mut self) -> CallResult {
+        let module_name = cell_as_atom!(self.deref_register(1));
+        let mut loader = self.loader_from_heap_evacuable(temp_v!(3));
+
+        let reexport = || {
+            let export_list = loader.extract_module_export_list_from_heap(temp_v!(2))?;
+
+            loader.reexport_module(module_name, export_list)?;
+            LiveLoadAndMachineState::evacuate(loader)
+        };
+
+        let result = reexport();
+        self.restore_load_state_payload(result)
+    }
+
+    pub(crate) fn freeze_module_interface(&mut self) -> CallResult {
+        let mut loader = self.loader_from_heap_evacuable(temp_v!(1));
+        loader.freeze_module_interface();
+
+        let result = LiveLoadAndMachineState::evacuate(loader);
+        self.restore_load_state_payload(result)
+    }
+
+    // succeeds if the module named in register 1 has frozen its
+    // interface.
+    pub(crate) fn module_interface_frozen(&mut self) {
+        let module_name = cell_as_atom!(self.deref_register(1));
+
+        self.machine_st.fail = self
+            .indices
+            .modules
+            .get(&module_name)
+            .is_none_or(|module| module.frozen_exports.is_none());
+    }
+
+    // unifies the name of the module declared by the load of the
+    // evacuable in register 1 with register 2, failing if the load
+    // declared none.
+    pub(crate) fn loaded_module(&mut self) {
+        let payload = cell_as_load_state_payload!(self.deref_register(1));
+
+        match payload.compilation_target {
+            CompilationTarget::Module(module_name) => {
+                unify_fn!(
+                    self.machine_st,
+                    atom_as_cell!(module_name),
+                    self.machine_st.registers[2]
+                );
+            }
+            CompilationTarget::User => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
     pub(crate) fn load_compiled_library(&mut self) -> CallResult {
         let library = cell_as_atom!(self
             .machine_st
@@ -1622,6 +1798,9 @@ pub(crate) fn add_term_expansion_clause(&mut self) -> CallResult {
         let mut loader = self.loader_from_heap_evacuable(temp_v!(2));
 
         let add_clause = || {
+            // the expansion may call the predicates loaded before it.
+            loader.complete_compilations()?;
+
             let term = loader.read_term_from_heap(temp_v!(1));
 
             loader.incremental_compile_clause(
@@ -1652,6 +1831,9 @@ pub(crate) fn add_goal_expansion_clause(&mut self) -> CallResult {
         };
 
         let add_clause = || {
+            // the expansion may call the predicates loaded before it.
+            loader.complete_compilations()?;
+
             let term = loader.read_term_from_heap(temp_v!(2));
 
             let indexing_arg = match term.name() {
@@ -1793,7 +1975,78 @@ pub(crate) fn pop_load_state_payload(&mut self) {
 
     #[inline]
     pub(crate) fn pop_load_context(&mut self) {
-        self.load_contexts.pop();
+        let load_context = self.load_contexts.pop();
+
+        if let Some(meter) = &mut self.load_progress {
+            let file = load_context.and_then(|load_context| load_context.file);
+            meter.report(LoadStage::Loaded, file);
+
+            if self.load_contexts.is_empty() {
+                meter.report(LoadStage::Finished, None);
+            }
+        }
+    }
+
+    // counts a clause loaded, reporting the progress of the load after
+    // every CLAUSES_PER_PROGRESS_REPORT clauses.
+    fn count_loaded_clause(&mut self) {
+        if let Some(meter) = &mut self.load_progress {
+            meter.clauses += 1;
+
+            if meter.clauses % CLAUSES_PER_PROGRESS_REPORT == 0 {
+                let file = self
+                    .load_contexts
+                    .last()
+                    .and_then(|load_context| load_context.file);
+                meter.report(LoadStage::Clauses, file);
+            }
+        }
+    }
+
+    // collects the diagnostic of the severity, error, file and line in
+    // registers 1 to 4, the file and line being unbound if unknown.
+    // fails if the load doesn't collect diagnostics, leaving the loader
+    // to print it.
+    pub(crate) fn report_diagnostic(&mut self) {
+        if self.diagnostics.is_none() {
+            self.machine_st.fail = true;
+            return;
+        }
+
+        let severity = match cell_as_atom!(self.deref_register(1)) {
+            atom!("warning") => Severity::Warning,
+            _ => Severity::Error,
+        };
+
+        let error = lib_machine::Term::from_heapcell(
+            self,
+            self.machine_st.registers[2],
+            &mut IndexMap::new(),
+        );
+
+        let file = read_heap_cell!(self.deref_register(3),
+            (HeapCellValueTag::Atom, (name, _arity)) => {
+                Some(name.as_str().to_string())
+            }
+            _ => {
+                None
+            }
+        );
+
+        let line = match Number::try_from((self.deref_register(4), &self.machine_st.arena.f64_tbl))
+        {
+            Ok(Number::Fixnum(n)) => usize::try_from(n.get_num()).ok(),
+            _ => None,
+        };
+
+        let mut diagnostic = Diagnostic::from_term(severity, &error);
+
+        diagnostic.file = diagnostic.file.or(file);
+        diagnostic.line = diagnostic.line.or(line);
+
+        if let Some(diagnostics) = &mut self.diagnostics {
+            diagnostics.push(diagnostic);
+        }
     }
 
     pub(crate) fn push_load_context(&mut self) -> CallResult {
@@ -1805,12 +2058,44 @@ pub(crate) fn push_load_context(&mut self) -> CallResult {
         )?;
 
         let path = cell_as_atom!(self.deref_register(2));
+        let mut load_context = LoadContext::new(&path.as_str(), stream);
+
+        if let Stream::InputFile(_) = stream {
+            let path_str = load_context.path.to_str().unwrap();
+            load_context.file = Some(AtomTable::build_with(&self.machine_st.atom_tbl, path_str));
+        }
 
-        self.load_contexts
-            .push(LoadContext::new(&path.as_str(), stream));
+        if let Some(meter) = &mut self.load_progress {
+            if self.load_contexts.is_empty() {
+                meter.started = Instant::now();
+                meter.files = 0;
+                meter.clauses = 0;
+            }
+
+            if load_context.file.is_some() {
+                meter.files += 1;
+            }
+
+            meter.report(LoadStage::Started, load_context.file);
+        }
+
+        self.load_contexts.push(load_context);
         Ok(())
     }
 
+    // sets the line of the term being loaded to that in register 1.
+    pub(crate) fn set_load_context_line(&mut self) {
+        let line = match Number::try_from((self.deref_register(1), &self.machine_st.arena.f64_tbl))
+        {
+            Ok(Number::Fixnum(n)) => usize::try_from(n.get_num()).ok(),
+            _ => None,
+        };
+
+        if let Some(load_context) = self.load_contexts.last_mut() {
+            load_context.line = line;
+        }
+    }
+
     pub(crate) fn restore_load_state_payload(
         &mut self,
         result: Result<TypedArenaPtr<LiveLoadState>, SessionError>,
@@ -1863,7 +2148,10 @@ pub(crate) fn scoped_clause_to_evacuable(&mut self) -> CallResult {
         };
 
         let result = loader.read_and_enqueue_term(temp_v!(2), compilation_target);
-        self.restore_load_state_payload(result)
+        self.restore_load_state_payload(result)?;
+        self.count_loaded_clause();
+
+        Ok(())
     }
 
     pub(crate) fn clause_to_evacuable(&mut self) -> CallResult {
@@ -1871,7 +2159,10 @@ pub(crate) fn clause_to_evacuable(&mut self) -> CallResult {
         let compilation_target = loader.payload.compilation_target;
 
         let result = loader.read_and_enqueue_term(temp_v!(1), compilation_target);
-        self.restore_load_state_payload(result)
+        self.restore_load_state_payload(result)?;
+        self.count_loaded_clause();
+
+        Ok(())
     }
 
     pub(crate) fn conclude_load(&mut self) -> CallResult {
@@ -1882,6 +2173,9 @@ pub(crate) fn conclude_load(&mut self) -> CallResult {
                 loader.compile_and_submit()?;
             }
 
+            loader.complete_compilations()?;
+            loader.save_index_cache();
+            loader.record_loaded_file();
             loader.remove_module_op_exports();
             LiveLoadAndMachineState::evacuate(loader)
         };
@@ -2286,6 +2580,66 @@ pub(crate) fn is_consistent_with_term_queue(&mut self) -> CallResult {
         self.restore_load_state_payload(result)
     }
 
+    // unifies register 2 with the list of the style warnings raised by
+    // the load since they were last taken, as Warning-Line pairs, the
+    // line being unbound if unknown.
+    pub(crate) fn style_warnings(&mut self) -> CallResult {
+        let mut loader = self.loader_from_heap_evacuable(temp_v!(1));
+        let warnings = std::mem::take(&mut loader.payload.style_warnings);
+
+        let result = LiveLoadAndMachineState::evacuate(loader);
+        self.restore_load_state_payload(result)?;
+
+        let warnings: Vec<_> = warnings
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (warning, line))| {
+                let (name, compilation_target, (pred_name, arity)) = match warning {
+                    StyleWarning::Discontiguous(compilation_target, key) => {
+                        ("discontiguous_predicate", compilation_target, key)
+                    }
+                    StyleWarning::Redefined(compilation_target, key) => {
+                        ("redefined_predicate", compilation_target, key)
+                    }
+                };
+
+                let pi = lib_machine::Term::compound(
+                    "/",
+                    [
+                        lib_machine::Term::atom(pred_name.as_str().to_string()),
+                        lib_machine::Term::integer(arity),
+                    ],
+                );
+
+                let pi = match compilation_target {
+                    CompilationTarget::User => pi,
+                    CompilationTarget::Module(module_name) => lib_machine::Term::compound(
+                        ":",
+                        [
+                            lib_machine::Term::atom(module_name.as_str().to_string()),
+                            pi,
+                        ],
+                    ),
+                };
+
+                let line = match line {
+                    Some(line) => lib_machine::Term::integer(line),
+                    None => lib_machine::Term::Var(format!("_{idx}")),
+                };
+
+                lib_machine::Term::compound("-", [lib_machine::Term::compound(name, [pi]), line])
+            })
+            .collect();
+
+        let warnings = resource_error_call_result!(
+            self.machine_st,
+            lib_machine::Term::list(warnings).to_heapcell(self, &mut IndexMap::new())
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[2], warnings);
+        Ok(())
+    }
+
     pub(crate) fn flush_term_queue(&mut self) -> CallResult {
         let mut loader = self.loader_from_heap_evacuable(temp_v!(1));
 
@@ -2301,6 +2655,20 @@ pub(crate) fn flush_term_queue(&mut self) -> CallResult {
         self.restore_load_state_payload(result)
     }
 
+    // installs the code of the predicates the load in register 1 is
+    // compiling on the compile workers.
+    pub(crate) fn complete_compilations(&mut self) -> CallResult {
+        let mut loader = self.loader_from_heap_evacuable(temp_v!(1));
+
+        let complete_compilations = || {
+            loader.complete_compilations()?;
+            LiveLoadAndMachineState::evacuate(loader)
+        };
+
+        let result = complete_compilations();
+        self.restore_load_state_payload(result)
+    }
+
     pub(crate) fn remove_module_exports(&mut self) -> CallResult {
         let module_name = cell_as_atom!(self
             .machine_st
@@ -2393,6 +2761,167 @@ pub(crate) fn meta_predicate_property(&mut self) {
         }
     }
 
+    // unifies register 2 with the list of the predicates exported by
+    // the module named in register 1, failing if there's no such
+    // module.
+    pub(crate) fn module_exports(&mut self) -> CallResult {
+        let module_name = cell_as_atom!(self.deref_register(1));
+
+        let Some(module) = self.indices.modules.get(&module_name) else {
+            self.machine_st.fail = true;
+            return Ok(());
+        };
+
+        let keys: Vec<_> = module
+            .module_decl
+            .exports
+            .iter()
+            .filter_map(|export| match export {
+                ModuleExport::PredicateKey(key) => Some(*key),
+                ModuleExport::OpDecl(_) => None,
+            })
+            .collect();
+
+        let mut export_cells = Vec::with_capacity(keys.len());
+
+        for (name, arity) in keys {
+            let mut writer =
+                Heap::functor_writer(functor!(atom!("/"), [atom_as_cell(name), fixnum(arity)]));
+
+            export_cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
+
+        let exports = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                export_cells.len(),
+                export_cells.into_iter()
+            )
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[2], exports);
+        Ok(())
+    }
+
+    // unifies registers 2 and 3 with the file and line of the
+    // declaration of the module named in register 1, failing if it
+    // wasn't consulted from a file.
+    pub(crate) fn module_location(&mut self) {
+        let module_name = cell_as_atom!(self.deref_register(1));
+
+        let location = self
+            .indices
+            .modules
+            .get(&module_name)
+            .and_then(|module| module.location);
+
+        match location {
+            Some(ClauseLocation { file, line }) => {
+                unify_fn!(
+                    self.machine_st,
+                    atom_as_cell!(file),
+                    self.machine_st.registers[2]
+                );
+
+                if !self.machine_st.fail {
+                    unify_fn!(
+                        self.machine_st,
+                        fixnum_as_cell!(Fixnum::build_with_checked(line as i64).unwrap()),
+                        self.machine_st.registers[3]
+                    );
+                }
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    // unifies register 1 with the list of the files loaded, in the
+    // order they were first loaded.
+    pub(crate) fn loaded_files(&mut self) -> CallResult {
+        let files: Vec<_> = self
+            .indices
+            .loaded_files
+            .keys()
+            .map(|&file| atom_as_cell!(file))
+            .collect();
+
+        let files = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, files.len(), files.into_iter())
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[1], files);
+        Ok(())
+    }
+
+    // succeeds if the loaded file named in register 1 was changed since
+    // it was loaded. it fails if the file was removed.
+    pub(crate) fn file_modified(&mut self) {
+        let file = cell_as_atom!(self.deref_register(1));
+
+        self.machine_st.fail = match self.indices.loaded_files.get(&file) {
+            Some(loaded_file) => match SourceStamp::of(Path::new(&*file.as_str())) {
+                Some(stamp) => stamp == loaded_file.stamp,
+                None => true,
+            },
+            None => true,
+        };
+    }
+
+    // unifies register 2 with the list of the predicates of the loaded
+    // file named in register 1 that are dynamic, as Module:Name/Arity.
+    pub(crate) fn loaded_file_dynamic_predicates(&mut self) -> CallResult {
+        let file = cell_as_atom!(self.deref_register(1));
+
+        let predicates: Vec<_> = match self.indices.loaded_files.get(&file) {
+            Some(loaded_file) => loaded_file
+                .predicates
+                .iter()
+                .filter(|(compilation_target, key)| {
+                    self.indices
+                        .get_predicate_skeleton(compilation_target, key)
+                        .map(|skeleton| skeleton.core.is_dynamic)
+                        .unwrap_or(false)
+                })
+                .map(|(compilation_target, key)| (compilation_target.module_name(), *key))
+                .collect(),
+            None => vec![],
+        };
+
+        let mut predicate_cells = Vec::with_capacity(predicates.len());
+
+        for (module_name, (name, arity)) in predicates {
+            let key = functor!(atom!("/"), [atom_as_cell(name), fixnum(arity)]);
+            let mut writer = Heap::functor_writer(functor!(
+                atom!(":"),
+                [atom_as_cell(module_name), functor(key)]
+            ));
+
+            predicate_cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
+
+        let predicates = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                predicate_cells.len(),
+                predicate_cells.into_iter()
+            )
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[2], predicates);
+        Ok(())
+    }
+
     pub(crate) fn dynamic_property(&mut self) {
         let module_name = cell_as_atom!(self
             .machine_st
@@ -6,6 +6,7 @@
 use crate::ffi::FFIError;
 use crate::forms::*;
 use crate::functor_macro::*;
+use crate::machine::code_locations::*;
 use crate::machine::heap::*;
 use crate::machine::loader::CompilationTarget;
 use crate::machine::machine_state::*;
@@ -44,7 +45,10 @@ pub(crate) enum ValidType {
     //    PredicateIndicator,
     //    Variable
     TcpListener,
+    UdpSocket,
     Process,
+    #[cfg(feature = "http")]
+    WebSocket,
 }
 
 impl ValidType {
@@ -68,7 +72,10 @@ pub(crate) fn as_atom(self) -> Atom {
             //            ValidType::PredicateIndicator => atom!("predicate_indicator"),
             //            ValidType::Variable => atom!("variable")
             ValidType::TcpListener => atom!("tcp_listener"),
+            ValidType::UdpSocket => atom!("udp_socket"),
             ValidType::Process => atom!("process"),
+            #[cfg(feature = "http")]
+            ValidType::WebSocket => atom!("websocket"),
         }
     }
 }
@@ -77,6 +84,9 @@ pub(crate) fn as_atom(self) -> Atom {
 pub(crate) enum ResourceError {
     FiniteMemory(HeapCellValue),
     OutOfFiles,
+    PendingInput,
+    QuerySteps,
+    QueryHeap,
 }
 
 pub(crate) trait TypeError {
@@ -291,6 +301,15 @@ pub(super) fn interrupt_error(&mut self) -> MachineError {
         }
     }
 
+    pub(super) fn query_cancelled_error(&mut self) -> MachineError {
+        let stub = functor!(atom!("query_cancelled"));
+
+        MachineError {
+            stub,
+            location: None,
+        }
+    }
+
     pub(super) fn evaluation_error(&mut self, eval_error: EvalError) -> MachineError {
         let stub = functor!(
             atom!("evaluation_error"),
@@ -317,6 +336,18 @@ pub(super) fn resource_error(err: ResourceError) -> MachineError {
                     [atom_as_cell((atom!("file_descriptors")))]
                 )
             }
+            ResourceError::PendingInput => {
+                functor!(
+                    atom!("resource_error"),
+                    [atom_as_cell((atom!("pending_input")))]
+                )
+            }
+            ResourceError::QuerySteps => {
+                functor!(atom!("resource_error"), [atom_as_cell((atom!("steps")))])
+            }
+            ResourceError::QueryHeap => {
+                functor!(atom!("resource_error"), [atom_as_cell((atom!("heap")))])
+            }
         };
 
         MachineError {
@@ -418,6 +449,18 @@ pub(super) fn existence_error(&mut self, err: ExistenceError) -> MachineError {
                     [atom_as_cell((atom!("process"))), cell(culprit)]
                 );
 
+                MachineError {
+                    stub,
+                    location: None,
+                }
+            }
+            #[cfg(feature = "http")]
+            ExistenceError::WebSocket(culprit) => {
+                let stub = functor!(
+                    atom!("existence_error"),
+                    [atom_as_cell((atom!("websocket"))), cell(culprit)]
+                );
+
                 MachineError {
                     stub,
                     location: None,
@@ -520,6 +563,15 @@ pub(super) fn session_error(&mut self, err: SessionError) -> MachineError {
                 self.permission_error(Permission::Modify, atom!("static_module"), module)
             }
             SessionError::ExistenceError(err) => self.existence_error(err),
+            SessionError::FrozenModuleInterface(module_name, key) => {
+                let functor_stub = functor_stub(key.0, key.1);
+                let stub = functor!(
+                    atom!(":"),
+                    [atom_as_cell(module_name), functor(functor_stub)]
+                );
+
+                self.permission_error(Permission::Modify, atom!("module_interface"), stub)
+            }
             SessionError::ModuleDoesNotContainExport(module_name, key) => {
                 let functor_stub = functor_stub(key.0, key.1);
 
@@ -668,9 +720,65 @@ pub(super) fn throw_exception(&mut self, err: MachineStub) {
             }
         };
 
+        if self.flags.error_locations {
+            self.locate_error();
+        }
+
         self.set_ball();
         self.unwind_stack();
     }
+
+    // the context of an error error(E, Context) in register 1 raised
+    // while a clause consulted from a file runs becomes
+    // location(Context, File, Line), the location of the innermost such
+    // clause with an activation. errors already located are left as
+    // they are, so that rethrowing them doesn't locate them again.
+    pub(super) fn locate_error(&mut self) {
+        let Some(ClauseLocation { file, line }) =
+            self.code_locations
+                .innermost(self.p, self.cp, self.e, &self.stack)
+        else {
+            return;
+        };
+
+        let s = read_heap_cell!(self.store(self.deref(self.registers[1])),
+            (HeapCellValueTag::Str, s) => {
+                s
+            }
+            _ => {
+                return;
+            }
+        );
+
+        if self.heap[s] != atom_as_cell!(atom!("error"), 2) {
+            return;
+        }
+
+        let context = self.store(self.deref(self.heap[s + 2]));
+
+        read_heap_cell!(context,
+            (HeapCellValueTag::Str, s) => {
+                if self.heap[s] == atom_as_cell!(atom!("location"), 3) {
+                    return;
+                }
+            }
+            _ => {
+            }
+        );
+
+        let location = functor!(
+            atom!("location"),
+            [cell(context), atom_as_cell(file), fixnum(line)]
+        );
+        let stub = functor!(
+            atom!("error"),
+            [cell((self.heap[s + 1])), functor(location)]
+        );
+
+        if let Ok(error) = Heap::functor_writer(stub)(&mut self.heap) {
+            self.registers[1] = error;
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -807,6 +915,8 @@ pub(crate) enum DomainErrorType {
     OperatorSpecifier,
     OperatorPriority,
     Directive,
+    #[cfg(feature = "http")]
+    HttpHeader,
 }
 
 impl DomainErrorType {
@@ -821,6 +931,8 @@ pub(crate) fn as_atom(self) -> Atom {
             DomainErrorType::OperatorSpecifier => atom!("operator_specifier"),
             DomainErrorType::OperatorPriority => atom!("operator_priority"),
             DomainErrorType::Directive => atom!("directive"),
+            #[cfg(feature = "http")]
+            DomainErrorType::HttpHeader => atom!("http_header"),
         }
     }
 }
@@ -1026,6 +1138,8 @@ pub enum ExistenceError {
     SourceSink(HeapCellValue),
     Stream(HeapCellValue),
     Process(HeapCellValue),
+    #[cfg(feature = "http")]
+    WebSocket(HeapCellValue),
 }
 
 #[derive(Debug)]
@@ -1035,6 +1149,7 @@ pub enum SessionError {
     CannotOverwriteBuiltInModule(Atom),
     CannotOverwriteStaticProcedure(PredicateKey),
     ExistenceError(ExistenceError),
+    FrozenModuleInterface(Atom, PredicateKey),
     ModuleDoesNotContainExport(Atom, PredicateKey),
     ModuleCannotImportSelf(Atom),
     NamelessEntry,
@@ -16,6 +16,7 @@
 use scryer_modular_bitfield::specifiers::*;
 use scryer_modular_bitfield::{bitfield, BitfieldSpecifier};
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
 
@@ -244,6 +245,9 @@ pub struct IndexStore {
     pub(super) goal_expansion_indices: GoalExpansionIndices,
     pub(super) meta_predicates: MetaPredicateDir,
     pub(super) modules: ModuleDir,
+    // the files loaded, in the order they were first loaded.
+    pub(super) loaded_files: LoadedFiles,
+    pub(super) module_imports: ModuleImports,
     pub(super) op_dir: OpDir,
     streams: StreamDir,
     stream_aliases: StreamAliasDir,
@@ -287,6 +291,30 @@ pub(crate) fn goal_expansion_defined(&self, key: PredicateKey, module_name: Atom
         }
     }
 
+    // the operators in effect in module_name: those of user, shadowed
+    // by those the module declares or imports. every module op_dir is
+    // seeded with default_op_dir(), which must not shadow user.
+    pub(crate) fn module_op_dir(&self, module_name: Atom) -> Cow<'_, OpDir> {
+        let Some(module) = self.modules.get(&module_name) else {
+            return Cow::Borrowed(&self.op_dir);
+        };
+
+        let default_op_dir = default_op_dir();
+        let mut module_ops = module
+            .op_dir
+            .iter()
+            .filter(|(key, op_desc)| default_op_dir.get(*key) != Some(*op_desc))
+            .peekable();
+
+        if module_name == atom!("user") || module_ops.peek().is_none() {
+            return Cow::Borrowed(&self.op_dir);
+        }
+
+        let mut op_dir = self.op_dir.clone();
+        op_dir.extend(module_ops.map(|(key, op_desc)| (*key, *op_desc)));
+        Cow::Owned(op_dir)
+    }
+
     pub(crate) fn get_predicate_skeleton_mut(
         &mut self,
         compilation_target: &CompilationTarget,
@@ -5,12 +5,17 @@
 use crate::heap_iter::*;
 use crate::heap_print::*;
 use crate::machine::attributed_variables::*;
+use crate::machine::code_locations::*;
+use crate::machine::compile_workers::*;
 use crate::machine::copier::*;
 use crate::machine::heap::*;
+use crate::machine::index_cache::*;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
+use crate::machine::profiler::*;
 use crate::machine::stack::*;
 use crate::machine::streams::*;
+use crate::machine::tracer::*;
 use crate::machine::Machine;
 use crate::parser::ast::*;
 use crate::read::TermWriteResult;
@@ -20,10 +25,15 @@
 
 use indexmap::IndexMap;
 
+use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops::{Index, IndexMut, Range};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering};
 use std::sync::Arc;
+use std::time::Duration;
+use web_time::{Instant, SystemTime};
 
 pub(crate) type Registers = [HeapCellValue; MAX_ARITY + 1];
 
@@ -59,6 +69,11 @@ pub enum OnEOF {
 }
 
 pub struct MachineState {
+    // the workers generating the code of static predicates, set up by
+    // MachineBuilder::with_parallel_compilation. they come first so
+    // that they're joined before the arena the literals of their jobs
+    // point into is dropped.
+    pub(crate) compile_workers: Option<CompileWorkers>,
     pub atom_tbl: Arc<AtomTable>,
     pub arena: Arena,
     pub(super) pdl: Vec<HeapCellValue>,
@@ -90,7 +105,42 @@ pub struct MachineState {
     // locations of cleaners, cut points, the previous scc_block. for setup_call_cleanup/3.
     pub(super) cont_pts: Vec<(HeapCellValue, usize, usize)>,
     pub(super) cwil: CWIL,
+    // deadlines installed by call_with_time_limit/2, innermost last.
+    // an entry is cleared once its time_limit_exceeded ball is thrown.
+    pub(crate) time_limits: Vec<Option<Instant>>,
+    // the budget of the leaf answer the library interface is computing.
+    pub(crate) query_budget: Option<QueryBudget>,
+    // the queries started by the library interface and not yet
+    // finished, and the one whose leaf answer is being computed.
+    pub(crate) active_queries: Vec<Arc<ActiveQuery>>,
+    pub(crate) running_query: Option<Arc<ActiveQuery>>,
+    pub(crate) next_query_id: u64,
+    // the heartbeat watched by the HealthMonitor of
+    // Machine::health_monitor, once there is one.
+    pub(crate) heartbeat: Option<Arc<Heartbeat>>,
+    // the counters of a running profile/1, and those of the last one
+    // once it finishes.
+    pub(crate) profiler: Option<Box<Profiler>>,
+    pub(crate) last_profile: Option<Box<Profiler>>,
+    // the port tracer of library(trace), once it's first used.
+    pub(crate) tracer: Option<Box<Tracer>>,
     pub(crate) flags: MachineFlags,
+    // the debug-info table locating the clauses consulted from files.
+    pub(crate) code_locations: CodeLocations,
+    // the indexing code of the large static predicates of loaded
+    // files, set up by MachineBuilder::with_index_cache.
+    pub(crate) index_cache: Option<IndexCache>,
+    // the directories searched for library(Path) after the libraries
+    // of the system, set up by MachineBuilder::with_library_path.
+    pub(crate) library_paths: Vec<PathBuf>,
+    // the sources of the libraries registered by the embedder, by
+    // MachineBuilder::with_library and Machine::add_library, loaded as
+    // library(Name) after the libraries of the system.
+    pub(crate) registered_libraries: IndexMap<String, String>,
+    // the resource files registered by the embedder, by
+    // MachineBuilder::with_resource and Machine::add_resource, opened
+    // as resource(Name) for reading.
+    pub(crate) registered_resources: IndexMap<String, Cow<'static, [u8]>>,
     pub(crate) cc: usize,
     pub(crate) global_clock: usize,
     pub(crate) dynamic_mode: FirstOrNext,
@@ -504,6 +554,10 @@ pub(crate) fn backtrack(&mut self) {
         self.b0 = or_frame.prelude.b0;
         self.p = or_frame.prelude.bp;
 
+        if let Some(profiler) = self.profiler.as_deref_mut() {
+            profiler.record_redo(self.p);
+        }
+
         self.oip = or_frame.prelude.boip;
         self.iip = or_frame.prelude.biip;
 
@@ -582,6 +636,10 @@ pub(super) fn throw_undefined_error(&mut self, name: Atom, arity: usize) -> Mach
 
     #[inline(always)]
     pub(super) fn call_at_index(&mut self, arity: usize, p: usize) {
+        if let Some(profiler) = self.profiler.as_deref_mut() {
+            profiler.record_call(p);
+        }
+
         self.cp = self.p + 1;
         self.p = p;
         self.oip = 0;
@@ -592,6 +650,10 @@ pub(super) fn call_at_index(&mut self, arity: usize, p: usize) {
 
     #[inline(always)]
     pub(super) fn execute_at_index(&mut self, arity: usize, p: usize) {
+        if let Some(profiler) = self.profiler.as_deref_mut() {
+            profiler.record_call(p);
+        }
+
         self.p = p;
         self.oip = 0;
         self.iip = 0;
@@ -751,7 +813,7 @@ pub fn read_term_from_user_input(
             readline.set_atoms_for_completion(&self.atom_tbl);
             return self.read_term(
                 stream,
-                indices,
+                &indices.op_dir,
                 MachineState::read_term_from_user_input_eof_handler,
             );
         }
@@ -759,7 +821,7 @@ pub fn read_term_from_user_input(
         if let Stream::Byte(_) = stream {
             return self.read_term(
                 stream,
-                indices,
+                &indices.op_dir,
                 MachineState::read_term_from_user_input_eof_handler,
             );
         }
@@ -769,7 +831,11 @@ pub fn read_term_from_user_input(
 
     pub fn read_term_eof_handler(&mut self, mut stream: Stream) -> Result<OnEOF, MachineStub> {
         if stream.at_end_of_stream() {
-            unify!(self, self.registers[2], atom_as_cell!(atom!("end_of_file")));
+            unify_fn!(
+                *self,
+                self.registers[2],
+                atom_as_cell!(atom!("end_of_file"))
+            );
             stream.set_past_end_of_stream(true);
             return Ok(OnEOF::Return);
         } else if stream.past_end_of_stream() {
@@ -785,8 +851,8 @@ pub fn read_term_eof_handler(&mut self, mut stream: Stream) -> Result<OnEOF, Mac
 
     pub fn read_term(
         &mut self,
-        stream: Stream,
-        indices: &mut IndexStore,
+        mut stream: Stream,
+        op_dir: &OpDir,
         eof_handler: impl Fn(&mut Self, Stream) -> Result<OnEOF, MachineStub>,
     ) -> CallResult {
         self.check_stream_properties(
@@ -802,7 +868,7 @@ pub fn read_term(
         }
 
         loop {
-            match self.read(stream, &indices.op_dir) {
+            match self.read(stream, op_dir) {
                 Ok(term_write_result) => return self.read_term_body(term_write_result),
                 Err(err) => {
                     match &err {
@@ -815,6 +881,9 @@ pub fn read_term(
                                 OnEOF::Continue => continue,
                             }
                         }
+                        CompilationError::ParserError(_) if stream.input_unavailable() => {
+                            return self.input_unavailable_action(atom!("read_term"), 3);
+                        }
                         _ => {}
                     }
 
@@ -1050,6 +1119,130 @@ pub(super) fn cut_prev_body(&mut self, value: HeapCellValue) {
     }
 }
 
+// the limits of QueryOptions, checked every
+// INSTRUCTIONS_PER_INTERRUPT_POLL instructions.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct QueryBudget {
+    // the stub choice point of the query. an exceeded budget unwinds
+    // straight to it.
+    pub(crate) stub_b: usize,
+    pub(crate) steps_left: Option<u64>,
+    // the heap length, in cells, the answer may not exceed.
+    pub(crate) max_heap_len: Option<usize>,
+}
+
+// the time the dispatch loop last polled for interrupts, every
+// INSTRUCTIONS_PER_INTERRUPT_POLL instructions.
+#[derive(Debug)]
+pub(crate) struct Heartbeat {
+    start: Instant,
+    // the milliseconds from start to the last poll, or IDLE while the
+    // machine isn't running.
+    last_poll: AtomicU64,
+}
+
+impl Heartbeat {
+    const IDLE: u64 = u64::MAX;
+
+    pub(crate) fn new() -> Self {
+        Heartbeat {
+            start: Instant::now(),
+            last_poll: AtomicU64::new(Self::IDLE),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn beat(&self) {
+        let millis = self.start.elapsed().as_millis() as u64;
+        self.last_poll.store(millis, AtomicOrdering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn stop(&self) {
+        self.last_poll.store(Self::IDLE, AtomicOrdering::Relaxed);
+    }
+
+    // the time since the last poll, or None while the machine isn't
+    // running.
+    pub(crate) fn since_last_poll(&self) -> Option<Duration> {
+        match self.last_poll.load(AtomicOrdering::Relaxed) {
+            Self::IDLE => None,
+            millis => Some(
+                self.start
+                    .elapsed()
+                    .saturating_sub(Duration::from_millis(millis)),
+            ),
+        }
+    }
+}
+
+// a query started by the library interface, shared with the
+// QueryHandles of Machine::active_queries. its inference count is
+// updated every INSTRUCTIONS_PER_INTERRUPT_POLL instructions and after
+// each answer.
+#[derive(Debug)]
+pub(crate) struct ActiveQuery {
+    pub(crate) id: u64,
+    pub(crate) query: String,
+    pub(crate) started_at: SystemTime,
+    pub(crate) start: Instant,
+    // the stub choice point of the query. a cancelled query unwinds
+    // straight to it.
+    pub(crate) stub_b: usize,
+    // the global inference count when the query started, and at the
+    // last update.
+    start_inferences: u64,
+    inferences: AtomicU64,
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl ActiveQuery {
+    pub(crate) fn new(id: u64, query: String, stub_b: usize, inferences: u64) -> Self {
+        ActiveQuery {
+            id,
+            query,
+            started_at: SystemTime::now(),
+            start: Instant::now(),
+            stub_b,
+            start_inferences: inferences,
+            inferences: AtomicU64::new(inferences),
+            cancelled: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn update_inferences(&self, global_count: &Integer) {
+        if let Ok(count) = u64::try_from(global_count) {
+            self.inferences.store(count, AtomicOrdering::Relaxed);
+        }
+    }
+
+    pub(crate) fn inferences(&self) -> u64 {
+        self.inferences
+            .load(AtomicOrdering::Relaxed)
+            .saturating_sub(self.start_inferences)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, AtomicOrdering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(AtomicOrdering::Relaxed)
+    }
+
+    pub(crate) fn finish(&self) {
+        self.finished.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished.load(AtomicOrdering::Relaxed)
+    }
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub(crate) struct CWIL {
@@ -1,8 +1,11 @@
 use crate::arena::*;
+use crate::arithmetic::float_cmp;
 use crate::atom_table::*;
 use crate::forms::*;
+use crate::functor_macro::*;
 use crate::heap_iter::*;
 use crate::machine::attributed_variables::*;
+use crate::machine::code_locations::*;
 use crate::machine::copier::*;
 use crate::machine::heap::*;
 use crate::machine::machine_errors::*;
@@ -16,10 +19,11 @@
 use crate::parser::dashu::{Integer, Rational};
 use crate::types::*;
 
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 
 use std::cmp::Ordering;
 use std::convert::TryFrom;
+use web_time::Instant;
 
 impl MachineState {
     pub(crate) fn new() -> Self {
@@ -60,7 +64,22 @@ pub(crate) fn new() -> Self {
             interms: vec![Number::default(); 256],
             cont_pts: Vec::with_capacity(256),
             cwil: CWIL::new(),
+            time_limits: vec![],
+            query_budget: None,
+            active_queries: vec![],
+            running_query: None,
+            next_query_id: 0,
+            heartbeat: None,
+            profiler: None,
+            last_profile: None,
+            tracer: None,
             flags: MachineFlags::default(),
+            code_locations: CodeLocations::default(),
+            compile_workers: None,
+            index_cache: None,
+            library_paths: vec![],
+            registered_libraries: IndexMap::new(),
+            registered_resources: IndexMap::new(),
             cc: 0,
             global_clock: 0,
             dynamic_mode: FirstOrNext::First,
@@ -341,6 +360,32 @@ pub(super) fn unwind_stack(&mut self) {
         self.fail = true;
     }
 
+    // the lines of the indexing code at index_loc whose choices are
+    // iterated by choice points, or None if there are too many choice
+    // points to search.
+    pub(super) fn iterated_indexing_lines(&self, index_loc: usize) -> Option<Vec<u32>> {
+        const MAX_SEARCHED_CHOICE_POINTS: usize = 256;
+
+        let mut b = self.b;
+        let mut lines = vec![];
+
+        for _ in 0..MAX_SEARCHED_CHOICE_POINTS {
+            if b == 0 {
+                return Some(lines);
+            }
+
+            let prelude = &self.stack.index_or_frame(b).prelude;
+
+            if prelude.bp == index_loc {
+                lines.push(prelude.boip);
+            }
+
+            b = prelude.b;
+        }
+
+        None
+    }
+
     // return the read value and the succeeding HeapPtr
     pub(crate) fn read_s(&mut self) -> (HeapCellValue, usize) {
         match self.s {
@@ -375,13 +420,19 @@ pub(crate) fn read_s(&mut self) -> (HeapCellValue, usize) {
     pub fn compare_term_test(&mut self, var_comparison: VarComparison) -> Option<Ordering> {
         let mut tabu_list = IndexSet::new();
 
+        // the variable pairings of VarComparison::Variant, in both
+        // directions.
+        let mut var_pairs = IndexMap::new();
+        let mut var_pairs_rev = IndexMap::new();
+
         while let Some(s1) = self.pdl.pop() {
             let s1 = self.deref(s1);
 
             let s2 = self.pdl.pop().unwrap();
             let s2 = self.deref(s2);
 
-            if s1 == s2 {
+            // variants pair even the variables of identical subterms.
+            if s1 == s2 && var_comparison != VarComparison::Variant {
                 continue;
             }
 
@@ -397,8 +448,8 @@ pub fn compare_term_test(&mut self, var_comparison: VarComparison) -> Option<Ord
             }
 
             match order_cat_v1 {
-                Some(TermOrderCategory::Variable) => {
-                    if let VarComparison::Distinct = var_comparison {
+                Some(TermOrderCategory::Variable) => match var_comparison {
+                    VarComparison::Distinct => {
                         let v1 = v1.as_var().unwrap();
                         let v2 = v2.as_var().unwrap();
 
@@ -407,7 +458,25 @@ pub fn compare_term_test(&mut self, var_comparison: VarComparison) -> Option<Ord
                             return Some(v1.cmp(&v2));
                         }
                     }
-                }
+                    VarComparison::Variant => {
+                        let v1 = v1.as_var().unwrap();
+                        let v2 = v2.as_var().unwrap();
+
+                        let paired_v2 = *var_pairs.entry(v1).or_insert(v2);
+                        let paired_v1 = *var_pairs_rev.entry(v2).or_insert(v1);
+
+                        if paired_v2 != v2 {
+                            self.pdl.clear();
+                            return Some(paired_v2.cmp(&v2));
+                        }
+
+                        if paired_v1 != v1 {
+                            self.pdl.clear();
+                            return Some(v1.cmp(&paired_v1));
+                        }
+                    }
+                    VarComparison::Indistinct => {}
+                },
                 Some(TermOrderCategory::FloatingPoint) => {
                     let v1 = cell_as_f64_offset!(v1);
                     let v2 = cell_as_f64_offset!(v2);
@@ -415,9 +484,12 @@ pub fn compare_term_test(&mut self, var_comparison: VarComparison) -> Option<Ord
                     let v1 = self.arena.f64_tbl.get_entry(v1);
                     let v2 = self.arena.f64_tbl.get_entry(v2);
 
-                    if v1 != v2 {
-                        self.pdl.clear();
-                        return Some(v1.cmp(&v2));
+                    match float_cmp(*v1, *v2) {
+                        Ordering::Equal => {}
+                        ordering => {
+                            self.pdl.clear();
+                            return Some(ordering);
+                        }
                     }
                 }
                 Some(TermOrderCategory::Integer) => {
@@ -594,18 +666,22 @@ pub fn compare_term_test(&mut self, var_comparison: VarComparison) -> Option<Ord
                                     let (name, arity) = cell_as_atom_cell!(self.heap[s])
                                         .get_name_and_arity();
 
-                                    if name == atom!(".") && arity == 2 {
-                                        tabu_list.insert((l1, s));
+                                    match (2, atom!(".")).cmp(&(arity, name)) {
+                                        Ordering::Equal => {
+                                            tabu_list.insert((l1, s));
 
-                                        let (c, succ_cell) = self.heap.last_str_char_and_tail(l1);
+                                            let (c, succ_cell) = self.heap.last_str_char_and_tail(l1);
 
-                                        self.pdl.push(heap_loc_as_cell!(s+2));
-                                        self.pdl.push(succ_cell);
+                                            self.pdl.push(heap_loc_as_cell!(s+2));
+                                            self.pdl.push(succ_cell);
 
-                                        self.pdl.push(heap_loc_as_cell!(s+1));
-                                        self.pdl.push(char_as_cell!(c));
-                                    } else {
-                                        self.fail = true;
+                                            self.pdl.push(heap_loc_as_cell!(s+1));
+                                            self.pdl.push(char_as_cell!(c));
+                                        }
+                                        ordering => {
+                                            self.pdl.clear();
+                                            return Some(ordering);
+                                        }
                                     }
                                 }
                                 _ => {
@@ -669,16 +745,20 @@ pub fn compare_term_test(&mut self, var_comparison: VarComparison) -> Option<Ord
                                     let (name, arity) = cell_as_atom_cell!(self.heap[s1])
                                         .get_name_and_arity();
 
-                                    if name == atom!(".") && arity == 2 {
-                                        let (c, succ_cell) = self.heap.last_str_char_and_tail(l2);
+                                    match (arity, name).cmp(&(2, atom!("."))) {
+                                        Ordering::Equal => {
+                                            let (c, succ_cell) = self.heap.last_str_char_and_tail(l2);
 
-                                        self.pdl.push(succ_cell);
-                                        self.pdl.push(heap_loc_as_cell!(s1+2));
+                                            self.pdl.push(succ_cell);
+                                            self.pdl.push(heap_loc_as_cell!(s1+2));
 
-                                        self.pdl.push(char_as_cell!(c));
-                                        self.pdl.push(heap_loc_as_cell!(s1+1));
-                                    } else {
-                                        self.fail = true;
+                                            self.pdl.push(char_as_cell!(c));
+                                            self.pdl.push(heap_loc_as_cell!(s1+1));
+                                        }
+                                        ordering => {
+                                            self.pdl.clear();
+                                            return Some(ordering);
+                                        }
                                     }
                                 }
                                 _ => {
@@ -890,7 +970,7 @@ fn try_functor_compound_case(&mut self, name: Atom, arity: usize) {
 
     fn try_functor_unify_components(&mut self, name: HeapCellValue, arity: usize) {
         let a2 = self.deref(self.registers[2]);
-        unify!(self, a2, name);
+        unify_fn!(*self, a2, name);
 
         if !self.fail {
             let a3 = self.store(self.deref(self.registers[3]));
@@ -1291,4 +1371,109 @@ pub fn throw_interrupt_exception(&mut self) {
 
         self.throw_exception(err);
     }
+
+    pub(crate) fn throw_thread_cancelled_exception(&mut self) {
+        self.throw_exception(functor!(atom!("$thread_cancelled")));
+    }
+
+    #[inline(always)]
+    pub(crate) fn time_limit_expired(&self) -> bool {
+        if self.time_limits.is_empty() {
+            return false;
+        }
+
+        let now = Instant::now();
+
+        self.time_limits
+            .iter()
+            .any(|deadline| matches!(deadline, Some(deadline) if *deadline <= now))
+    }
+
+    pub(crate) fn throw_time_limit_exception(&mut self) {
+        let now = Instant::now();
+
+        // every expired limit is disarmed so that the cleanup goals
+        // run while unwinding aren't interrupted a second time.
+        for deadline in self.time_limits.iter_mut() {
+            if matches!(deadline, Some(deadline) if *deadline <= now) {
+                *deadline = None;
+            }
+        }
+
+        self.throw_exception(functor!(atom!("time_limit_exceeded")));
+    }
+
+    // charges the query budget for another block of instructions,
+    // returning the resource it then exceeds, if any.
+    pub(crate) fn query_budget_exceeded(&mut self, steps: u64) -> Option<ResourceError> {
+        let heap_len = self.heap.cell_len();
+        let budget = self.query_budget.as_mut()?;
+
+        if let Some(steps_left) = budget.steps_left.as_mut() {
+            if *steps_left <= steps {
+                return Some(ResourceError::QuerySteps);
+            }
+
+            *steps_left -= steps;
+        }
+
+        match budget.max_heap_len {
+            Some(max_heap_len) if heap_len > max_heap_len => Some(ResourceError::QueryHeap),
+            _ => None,
+        }
+    }
+
+    // unlike other errors, an exceeded query budget can't be caught:
+    // the ball is thrown straight to the stub choice point of the
+    // query, skipping every catch/3 and cleanup goal in between.
+    pub(crate) fn throw_query_budget_exception(&mut self, err: ResourceError) {
+        let stub_b = match self.query_budget.take() {
+            Some(budget) => budget.stub_b,
+            None => return,
+        };
+
+        let err = MachineState::resource_error(err);
+        self.throw_to_query_stub(stub_b, err);
+    }
+
+    // true if the query whose leaf answer is being computed was
+    // cancelled by QueryHandle::cancel. its inference count is updated
+    // meanwhile.
+    #[inline]
+    pub(crate) fn query_cancelled(&self) -> bool {
+        match &self.running_query {
+            Some(query) => {
+                query.update_inferences(&self.cwil.global_count);
+                query.is_cancelled()
+            }
+            None => false,
+        }
+    }
+
+    // a cancelled query can't catch its cancellation any more than it
+    // can an exceeded budget.
+    pub(crate) fn throw_query_cancelled_exception(&mut self) {
+        let stub_b = match self.running_query.take() {
+            Some(query) => query.stub_b,
+            None => return,
+        };
+
+        self.query_budget = None;
+
+        let err = self.query_cancelled_error();
+        self.throw_to_query_stub(stub_b, err);
+    }
+
+    fn throw_to_query_stub(&mut self, stub_b: usize, err: MachineError) {
+        self.block = stub_b;
+        self.scc_block = 0;
+        self.cont_pts.clear();
+        self.cwil.reset();
+        self.time_limits.clear();
+
+        let stub = functor_stub(atom!("call"), 1);
+        let err = self.error_form(err, stub);
+
+        self.throw_exception(err);
+    }
 }
@@ -0,0 +1,277 @@
+use crate::atom_table::*;
+
+use crossbeam_channel::{Receiver, Select, Sender};
+use indexmap::IndexMap;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+// message queues are shared by every machine of the process, so
+// their messages are kept in the textual form written by
+// thread_send_message/2 rather than as heap cells.
+static MESSAGE_QUEUES: LazyLock<Mutex<IndexMap<Atom, Arc<MessageQueue>>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+static ANONYMOUS_QUEUE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// blocked receivers wake up at least this often to check for
+// interrupts.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// senders push messages onto a channel without taking any lock.
+// receivers move them to the received buffer, where they are
+// numbered and can be scanned for a message matching a pattern.
+#[derive(Debug)]
+pub(crate) struct MessageQueue {
+    sender: Sender<String>,
+    receiver: Receiver<String>,
+    received: Mutex<Messages>,
+    // wakes receivers blocked on the channel when another receiver
+    // moved the messages they wait for to the received buffer.
+    nudge_sender: Sender<()>,
+    nudge_receiver: Receiver<()>,
+    destroyed: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct Messages {
+    next_id: usize,
+    queue: VecDeque<(usize, String)>,
+    waiting: usize,
+}
+
+pub(crate) enum WaitOutcome {
+    Arrived(Option<Duration>),
+    TimedOut,
+    Interrupted,
+    Destroyed,
+}
+
+pub(crate) enum TakeOutcome {
+    Taken(String),
+    Interrupted,
+    Destroyed,
+}
+
+impl Default for MessageQueue {
+    fn default() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let (nudge_sender, nudge_receiver) = crossbeam_channel::unbounded();
+
+        MessageQueue {
+            sender,
+            receiver,
+            received: Mutex::new(Messages::default()),
+            nudge_sender,
+            nudge_receiver,
+            destroyed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl MessageQueue {
+    pub(crate) fn lookup(name: Atom) -> Option<Arc<MessageQueue>> {
+        MESSAGE_QUEUES.lock().unwrap().get(&name).cloned()
+    }
+
+    // returns false if a queue named name already exists.
+    pub(crate) fn create(name: Atom) -> bool {
+        let mut queues = MESSAGE_QUEUES.lock().unwrap();
+
+        if queues.contains_key(&name) {
+            return false;
+        }
+
+        queues.insert(name, Arc::new(MessageQueue::default()));
+        true
+    }
+
+    pub(crate) fn create_anonymous(atom_tbl: &AtomTable) -> Atom {
+        let mut queues = MESSAGE_QUEUES.lock().unwrap();
+
+        loop {
+            let count = ANONYMOUS_QUEUE_COUNT.fetch_add(1, Ordering::Relaxed);
+            let name = AtomTable::build_with(atom_tbl, &format!("$message_queue_{count}"));
+
+            if !queues.contains_key(&name) {
+                queues.insert(name, Arc::new(MessageQueue::default()));
+                return name;
+            }
+        }
+    }
+
+    // receivers blocked on a destroyed queue give up, and streams
+    // reading from it reach end of file once it is empty.
+    pub(crate) fn destroy(name: Atom) -> bool {
+        let Some(queue) = MESSAGE_QUEUES.lock().unwrap().shift_remove(&name) else {
+            return false;
+        };
+
+        let received = queue.received.lock().unwrap();
+        queue.destroyed.store(true, Ordering::Relaxed);
+        queue.nudge(received.waiting);
+
+        true
+    }
+
+    pub(crate) fn send(&self, message: String) {
+        // the queue owns a receiver, so the channel is never
+        // disconnected.
+        self.sender.send(message).unwrap();
+    }
+
+    fn nudge(&self, waiting: usize) {
+        for _ in 0..waiting {
+            self.nudge_sender.send(()).unwrap();
+        }
+    }
+
+    // moves the messages sent so far to the received buffer. ids are
+    // assigned while the buffer is locked, so they follow the order
+    // of the channel.
+    fn drain(&self, received: &mut Messages) {
+        let mut drained = false;
+
+        while let Ok(message) = self.receiver.try_recv() {
+            let id = received.next_id;
+
+            received.next_id += 1;
+            received.queue.push_back((id, message));
+            drained = true;
+        }
+
+        if drained {
+            self.nudge(received.waiting);
+        }
+    }
+
+    fn lock_received(&self) -> std::sync::MutexGuard<'_, Messages> {
+        let mut received = self.received.lock().unwrap();
+        self.drain(&mut received);
+        received
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock_received().queue.len()
+    }
+
+    // the oldest message sent after the message identified by after,
+    // which is None to start from the beginning of the queue.
+    pub(crate) fn next(&self, after: Option<usize>) -> Option<(usize, String)> {
+        self.lock_received()
+            .queue
+            .iter()
+            .find(|(id, _)| after.is_none_or(|after| *id > after))
+            .cloned()
+    }
+
+    // returns false if the message was already taken by another receiver.
+    pub(crate) fn remove(&self, id: usize) -> bool {
+        let mut received = self.received.lock().unwrap();
+
+        match received.queue.iter().position(|(msg_id, _)| *msg_id == id) {
+            Some(idx) => {
+                received.queue.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // blocks the caller, which was counted as waiting, until a
+    // message is sent, another receiver nudges it or slice runs out.
+    fn block(&self, slice: Duration) {
+        let mut select = Select::new();
+
+        select.recv(&self.receiver);
+        select.recv(&self.nudge_receiver);
+
+        if let Ok(1) = select.ready_timeout(slice) {
+            let _ = self.nudge_receiver.try_recv();
+        }
+    }
+
+    // blocks until the queue holds a message sent after the message
+    // identified by after, or until timeout (None blocks indefinitely)
+    // runs out. An arrival reports the remaining timeout.
+    pub(crate) fn wait(
+        &self,
+        after: Option<usize>,
+        timeout: Option<Duration>,
+        interrupted: impl Fn() -> bool,
+    ) -> WaitOutcome {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+
+        loop {
+            let mut received = self.lock_received();
+
+            let arrived = received
+                .queue
+                .back()
+                .is_some_and(|(id, _)| after.is_none_or(|after| *id > after));
+
+            if arrived {
+                let remaining =
+                    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+                return WaitOutcome::Arrived(remaining);
+            }
+
+            if self.destroyed.load(Ordering::Relaxed) {
+                return WaitOutcome::Destroyed;
+            }
+
+            if interrupted() {
+                return WaitOutcome::Interrupted;
+            }
+
+            let slice = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+
+                    if deadline <= now {
+                        return WaitOutcome::TimedOut;
+                    }
+
+                    (deadline - now).min(INTERRUPT_POLL_INTERVAL)
+                }
+                None => INTERRUPT_POLL_INTERVAL,
+            };
+
+            received.waiting += 1;
+            drop(received);
+
+            self.block(slice);
+            self.received.lock().unwrap().waiting -= 1;
+        }
+    }
+
+    // removes the oldest message of the queue, blocking until there
+    // is one.
+    pub(crate) fn take(&self, interrupted: impl Fn() -> bool) -> TakeOutcome {
+        loop {
+            let mut received = self.lock_received();
+
+            if let Some((_, message)) = received.queue.pop_front() {
+                return TakeOutcome::Taken(message);
+            }
+
+            if self.destroyed.load(Ordering::Relaxed) {
+                return TakeOutcome::Destroyed;
+            }
+
+            if interrupted() {
+                return TakeOutcome::Interrupted;
+            }
+
+            received.waiting += 1;
+            drop(received);
+
+            self.block(INTERRUPT_POLL_INTERVAL);
+            self.received.lock().unwrap().waiting -= 1;
+        }
+    }
+}
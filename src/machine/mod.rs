@@ -1,32 +1,59 @@
 pub mod args;
+pub mod array_formats;
 #[macro_use]
 pub mod arithmetic_ops;
 pub mod attributed_variables;
+pub mod bridge;
+pub mod code_locations;
 pub mod code_walker;
+pub mod compile_workers;
 #[macro_use]
 pub mod loader;
 pub mod compile;
 pub mod config;
+pub mod config_formats;
 pub mod copier;
+pub mod crash_report;
+pub mod csv;
 pub mod cycle_detection;
 pub mod disjuncts;
 pub mod dispatch;
+pub mod dynamic_clause_store;
+pub mod engines;
+pub mod fd_domains;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fswatch;
 pub mod gc;
 pub mod heap;
+pub mod index_cache;
+pub mod interval_arithmetic;
+pub mod json;
 pub mod lib_machine;
 pub mod load_state;
 pub mod machine_errors;
 pub mod machine_indices;
 pub mod machine_state;
 pub mod machine_state_impl;
+pub mod message_queues;
 pub mod mock_wam;
+pub mod mutexes;
 pub mod partial_string;
 pub mod preprocessor;
+pub mod profiler;
+pub mod rational_arithmetic;
+pub mod regex_cache;
+pub mod resilience;
+pub mod signals;
+pub mod source_analysis;
 pub mod stack;
 pub mod streams;
 pub mod system_calls;
+pub mod templates;
 pub mod term_stream;
+pub mod threads;
+pub mod tracer;
 pub mod unify;
+pub mod xref;
 
 use crate::arena::*;
 use crate::arithmetic::*;
@@ -36,9 +63,14 @@
 use crate::forms::*;
 use crate::instructions::*;
 use crate::machine::args::*;
+use crate::machine::bridge::*;
+use crate::machine::code_locations::*;
 use crate::machine::compile::*;
 use crate::machine::copier::*;
+use crate::machine::crash_report::*;
+use crate::machine::engines::*;
 use crate::machine::heap::*;
+use crate::machine::lib_machine::{Diagnostic, LoadProgressHook, QueryOptions, TraceHook};
 use crate::machine::loader::*;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
@@ -82,6 +114,27 @@ pub struct Machine {
     #[cfg(feature = "ffi")]
     pub(super) foreign_function_table: ForeignFunctionTable,
     pub(super) rng: StdRng,
+    // shuffles the answers of builtins that enumerate them in an
+    // unspecified order, while the shuffle_seed flag is an integer.
+    pub(super) shuffle_rng: Option<StdRng>,
+    pub(super) bridged_machines: IndexMap<Atom, BridgedMachine>,
+    // the engines of library(engines), and the names of those running,
+    // innermost last.
+    pub(super) engines: IndexMap<Atom, PrologEngine>,
+    pub(super) running_engines: Vec<Atom>,
+    // the length of the code once the toplevel is loaded. the code
+    // before it belongs to the system.
+    pub(super) boot_code_end: usize,
+    pub(super) trace_hook: Option<TraceHook>,
+    // the diagnostics of a load run through Machine::consult_module_string
+    // or Machine::load_module_string, which collects them.
+    pub(super) diagnostics: Option<Vec<Diagnostic>>,
+    pub(super) load_progress: Option<LoadProgressMeter>,
+    // the options of the queries run by Machine::run_query.
+    pub(super) query_options: QueryOptions,
+    // the names of the flags set by MachineBuilder::with_flag.
+    pub(super) configured_flags: Vec<String>,
+    pub(super) crash_reporter: Option<CrashReporter>,
 }
 
 #[derive(Debug)]
@@ -89,6 +142,11 @@ pub struct LoadContext {
     pub(super) path: PathBuf,
     pub(super) stream: Stream,
     pub(super) module: Atom,
+    // the path, if the stream reads a file, and the line of the term
+    // being loaded from it, which locate the clauses compiled from the
+    // term.
+    pub(super) file: Option<Atom>,
+    pub(super) line: Option<usize>,
 }
 
 impl LoadContext {
@@ -106,8 +164,18 @@ fn new(path: &str, stream: Stream) -> Self {
             path: path_buf,
             stream,
             module: atom!("user"),
+            file: None,
+            line: None,
         }
     }
+
+    #[inline]
+    pub(super) fn clause_location(&self) -> Option<ClauseLocation> {
+        Some(ClauseLocation {
+            file: self.file?,
+            line: self.line?,
+        })
+    }
 }
 
 #[inline]
@@ -138,6 +206,15 @@ pub(crate) fn contains(name: &str) -> bool {
     pub(crate) fn get(name: &str) -> Option<&'static str> {
         LIBRARIES.get(name).copied()
     }
+
+    // whether name is the module of a library, which is named after
+    // the last part of the library name, as json is for
+    // serialization/json.
+    pub(crate) fn contains_module(name: &str) -> bool {
+        LIBRARIES
+            .keys()
+            .any(|library| library.rsplit('/').next() == Some(name))
+    }
 }
 
 pub static BREAK_FROM_DISPATCH_LOOP_LOC: usize = 0;
@@ -521,9 +598,32 @@ fn next_clause_applicable(&mut self, mut offset: usize) -> bool {
                                 self.machine_st
                                     .select_switch_on_term_index(cell, v, c, l, s)
                             }
+                            &IndexingLine::Indexing(IndexingInstruction::SwitchOnSubterm(
+                                arg,
+                                v,
+                                c,
+                                l,
+                                s,
+                            )) => {
+                                read_heap_cell!(cell,
+                                    (HeapCellValueTag::Str, st) => {
+                                        cell = self.machine_st.store(
+                                            self.machine_st.deref(self.machine_st.heap[st + arg]),
+                                        );
+
+                                        self.machine_st
+                                            .select_switch_on_term_index(cell, v, c, l, s)
+                                    }
+                                    _ => {
+                                        v
+                                    }
+                                )
+                            }
                             IndexingLine::Indexing(IndexingInstruction::SwitchOnConstant(hm)) => {
-                                // let lit = self.machine_st.constant_to_literal(cell);
-                                hm.get(&cell).cloned().unwrap_or(IndexingCodePtr::Fail)
+                                self.machine_st
+                                    .switch_on_constant_key(cell)
+                                    .and_then(|key| hm.get(&key).cloned())
+                                    .unwrap_or(IndexingCodePtr::Fail)
                             }
                             IndexingLine::Indexing(IndexingInstruction::SwitchOnStructure(hm)) => {
                                 self.machine_st.select_switch_on_structure_index(cell, hm)
@@ -584,6 +684,30 @@ fn next_clause_applicable(&mut self, mut offset: usize) -> bool {
                         }
                     );
                 }
+                &Instruction::GetListVariables(Level::Shallow, RegType::Temp(t), ..) => {
+                    let cell = self.deref_register(t);
+
+                    read_heap_cell!(cell,
+                        (HeapCellValueTag::Lis | HeapCellValueTag::PStrLoc) => {
+                            offset += 1;
+                        }
+                        (HeapCellValueTag::Str, s) => {
+                            let (name, arity) = cell_as_atom_cell!(self.machine_st.heap[s]).get_name_and_arity();
+
+                            if name == atom!(".") && arity == 2 {
+                                offset += 1;
+                            } else {
+                                return false;
+                            }
+                        }
+                        (HeapCellValueTag::AttrVar | HeapCellValueTag::Var | HeapCellValueTag::StackVar) => {
+                            offset += 1;
+                        }
+                        _ => {
+                            return false;
+                        }
+                    );
+                }
                 &Instruction::GetStructure(Level::Shallow, name, arity, RegType::Temp(t)) => {
                     let cell = self.deref_register(t);
 
@@ -638,6 +762,7 @@ fn next_clause_applicable(&mut self, mut offset: usize) -> bool {
                 }
                 Instruction::GetConstant(..)
                 | Instruction::GetList(..)
+                | Instruction::GetListVariables(..)
                 | Instruction::GetStructure(..)
                 | Instruction::GetPartialString(..)
                 | &Instruction::UnifyVoid(..)
@@ -858,7 +983,9 @@ fn retry(&mut self, offset: usize) {
             self.machine_st.e = or_frame.prelude.e;
             self.machine_st.cp = or_frame.prelude.cp;
 
-            or_frame.prelude.biip += iip_offset;
+            // iip is past the retracted clauses of a dynamic choice
+            // skipped since the choice point was last taken.
+            or_frame.prelude.biip = self.machine_st.iip + iip_offset;
 
             let target_h = or_frame.prelude.h;
             let attr_var_queue_len = or_frame.prelude.attr_var_queue_len;
@@ -984,8 +1111,19 @@ fn trust_me_epilogue(&mut self) {
     }
 
     #[inline(always)]
-    fn undefined_procedure(&mut self, name: Atom, arity: usize) -> CallResult {
-        match self.machine_st.flags.unknown {
+    fn undefined_procedure(
+        &mut self,
+        module_name: Option<Atom>,
+        name: Atom,
+        arity: usize,
+    ) -> CallResult {
+        let unknown = module_name
+            .or_else(|| self.calling_module((name, arity)))
+            .and_then(|module_name| self.indices.modules.get(&module_name))
+            .and_then(|module| module.unknown)
+            .unwrap_or(self.machine_st.flags.unknown);
+
+        match unknown {
             Unknown::Error => Err(self.machine_st.throw_undefined_error(name, arity)),
             Unknown::Fail => {
                 self.machine_st.fail = true;
@@ -1003,16 +1141,58 @@ fn undefined_procedure(&mut self, name: Atom, arity: usize) -> CallResult {
         }
     }
 
+    // the module whose code index of key the call instruction at p
+    // looks up, if any.
+    fn calling_module(&self, key: PredicateKey) -> Option<Atom> {
+        let idx = match self.code.get(self.machine_st.p)? {
+            &Instruction::CallNamed(arity, name, idx)
+            | &Instruction::ExecuteNamed(arity, name, idx)
+            | &Instruction::DefaultCallNamed(arity, name, idx)
+            | &Instruction::DefaultExecuteNamed(arity, name, idx)
+                if (name, arity) == key =>
+            {
+                idx
+            }
+            _ => return None,
+        };
+
+        let offset = usize::from(CodeIndexOffset::from(idx));
+
+        self.indices
+            .modules
+            .iter()
+            .find(|(_, module)| {
+                module.code_dir.get(&key).is_some_and(|&module_idx| {
+                    usize::from(CodeIndexOffset::from(module_idx)) == offset
+                })
+            })
+            .map(|(&module_name, _)| module_name)
+    }
+
     #[inline(always)]
-    fn try_call(&mut self, name: Atom, arity: usize, idx: IndexPtr) -> CallResult {
+    fn try_call(
+        &mut self,
+        module_name: Option<Atom>,
+        name: Atom,
+        arity: usize,
+        idx: IndexPtr,
+    ) -> CallResult {
         let compiled_tl_index = idx.p() as usize;
 
+        if signals::any_pending() && self.signal_goal(name, arity, idx, false)? {
+            return Ok(());
+        }
+
+        if self.machine_st.tracer.is_some() && self.trace_goal(name, arity, idx, false)? {
+            return Ok(());
+        }
+
         match idx.tag() {
             IndexPtrTag::DynamicUndefined => {
                 self.machine_st.fail = true;
             }
             IndexPtrTag::Undefined => {
-                return self.undefined_procedure(name, arity);
+                return self.undefined_procedure(module_name, name, arity);
             }
             IndexPtrTag::DynamicIndex => {
                 self.machine_st.dynamic_mode = FirstOrNext::First;
@@ -1027,15 +1207,29 @@ fn try_call(&mut self, name: Atom, arity: usize, idx: IndexPtr) -> CallResult {
     }
 
     #[inline(always)]
-    fn try_execute(&mut self, name: Atom, arity: usize, idx: IndexPtr) -> CallResult {
+    fn try_execute(
+        &mut self,
+        module_name: Option<Atom>,
+        name: Atom,
+        arity: usize,
+        idx: IndexPtr,
+    ) -> CallResult {
         let compiled_tl_index = idx.p() as usize;
 
+        if signals::any_pending() && self.signal_goal(name, arity, idx, true)? {
+            return Ok(());
+        }
+
+        if self.machine_st.tracer.is_some() && self.trace_goal(name, arity, idx, true)? {
+            return Ok(());
+        }
+
         match idx.tag() {
             IndexPtrTag::DynamicUndefined => {
                 self.machine_st.fail = true;
             }
             IndexPtrTag::Undefined => {
-                return self.undefined_procedure(name, arity);
+                return self.undefined_procedure(module_name, name, arity);
             }
             IndexPtrTag::DynamicIndex => {
                 self.machine_st.dynamic_mode = FirstOrNext::First;
@@ -1054,16 +1248,16 @@ fn call_clause(&mut self, module_name: Atom, key: PredicateKey) -> CallResult {
         if module_name == atom!("user") {
             if let Some(idx) = self.indices.code_dir.get(&(name, arity)).cloned() {
                 let index_ptr = self.machine_st.arena.code_index_tbl.get_entry(idx.into());
-                self.try_call(name, arity, index_ptr)
+                self.try_call(Some(module_name), name, arity, index_ptr)
             } else {
-                Err(self.machine_st.throw_undefined_error(name, arity))
+                self.undefined_procedure(Some(module_name), name, arity)
             }
         } else if let Some(module) = self.indices.modules.get(&module_name) {
             if let Some(idx) = module.code_dir.get(&(name, arity)).cloned() {
                 let index_ptr = self.machine_st.arena.code_index_tbl.get_entry(idx.into());
-                self.try_call(name, arity, index_ptr)
+                self.try_call(Some(module_name), name, arity, index_ptr)
             } else {
-                self.undefined_procedure(name, arity)
+                self.undefined_procedure(Some(module_name), name, arity)
             }
         } else {
             let stub = functor_stub(name, arity);
@@ -1090,9 +1284,9 @@ fn execute_clause(&mut self, module_name: Atom, key: PredicateKey) -> CallResult
                     .arena
                     .code_index_tbl
                     .get_entry(offset.into());
-                self.try_execute(name, arity, index_ptr)
+                self.try_execute(Some(module_name), name, arity, index_ptr)
             } else {
-                self.undefined_procedure(name, arity)
+                self.undefined_procedure(Some(module_name), name, arity)
             }
         } else if let Some(module) = self.indices.modules.get(&module_name) {
             if let Some(offset) = module.code_dir.get(&(name, arity)).cloned() {
@@ -1101,9 +1295,9 @@ fn execute_clause(&mut self, module_name: Atom, key: PredicateKey) -> CallResult
                     .arena
                     .code_index_tbl
                     .get_entry(offset.into());
-                self.try_execute(name, arity, index_ptr)
+                self.try_execute(Some(module_name), name, arity, index_ptr)
             } else {
-                self.undefined_procedure(name, arity)
+                self.undefined_procedure(Some(module_name), name, arity)
             }
         } else {
             let stub = functor_stub(name, arity);
@@ -0,0 +1,304 @@
+use crate::atom_table::*;
+
+use indexmap::IndexMap;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, LazyLock, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+// like message queues, mutexes and condition variables are shared by
+// every machine of the process. a mutex is held by a Prolog thread,
+// identified by the name thread_self/1 reports for it.
+static MUTEXES: LazyLock<Mutex<IndexMap<Atom, Arc<PrologMutex>>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+static CONDITIONS: LazyLock<Mutex<IndexMap<Atom, Arc<Condition>>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+static ANONYMOUS_MUTEX_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ANONYMOUS_CONDITION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// blocked threads wake up at least this often to check for
+// interrupts.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// a recursive mutex: its holder may lock it again, and it is released
+// once it has been unlocked as often as it was locked.
+#[derive(Debug, Default)]
+pub(crate) struct PrologMutex {
+    holder: Mutex<Holder>,
+    released: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Holder {
+    owner: Option<Atom>,
+    count: usize,
+}
+
+pub(crate) enum ConditionOutcome {
+    Signalled,
+    TimedOut,
+    Interrupted,
+}
+
+fn create_named<T: Default>(registry: &Mutex<IndexMap<Atom, Arc<T>>>, name: Atom) -> bool {
+    let mut entries = registry.lock().unwrap();
+
+    if entries.contains_key(&name) {
+        return false;
+    }
+
+    entries.insert(name, Arc::new(T::default()));
+    true
+}
+
+fn create_anonymous<T: Default>(
+    registry: &Mutex<IndexMap<Atom, Arc<T>>>,
+    count: &AtomicUsize,
+    prefix: &str,
+    atom_tbl: &AtomTable,
+) -> Atom {
+    let mut entries = registry.lock().unwrap();
+
+    loop {
+        let n = count.fetch_add(1, Ordering::Relaxed);
+        let name = AtomTable::build_with(atom_tbl, &format!("{prefix}{n}"));
+
+        if !entries.contains_key(&name) {
+            entries.insert(name, Arc::new(T::default()));
+            return name;
+        }
+    }
+}
+
+impl PrologMutex {
+    pub(crate) fn lookup(name: Atom) -> Option<Arc<PrologMutex>> {
+        MUTEXES.lock().unwrap().get(&name).cloned()
+    }
+
+    // returns false if a mutex named name already exists.
+    pub(crate) fn create(name: Atom) -> bool {
+        create_named(&MUTEXES, name)
+    }
+
+    pub(crate) fn create_anonymous(atom_tbl: &AtomTable) -> Atom {
+        create_anonymous(&MUTEXES, &ANONYMOUS_MUTEX_COUNT, "$mutex_", atom_tbl)
+    }
+
+    // returns false if there is no such mutex or it is locked.
+    pub(crate) fn destroy(name: Atom) -> bool {
+        let mut mutexes = MUTEXES.lock().unwrap();
+
+        match mutexes.get(&name) {
+            Some(mutex) if mutex.holder().is_none() => {
+                mutexes.shift_remove(&name);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // the thread holding the mutex and how often it locked it.
+    pub(crate) fn holder(&self) -> Option<(Atom, usize)> {
+        let holder = self.holder.lock().unwrap();
+        holder.owner.map(|owner| (owner, holder.count))
+    }
+
+    // blocks until owner holds the mutex, or returns false if the wait
+    // was interrupted first.
+    pub(crate) fn lock(&self, owner: Atom, interrupted: impl Fn() -> bool) -> bool {
+        let mut holder = self.holder.lock().unwrap();
+
+        loop {
+            match holder.owner {
+                Some(current) if current != owner => {
+                    if interrupted() {
+                        return false;
+                    }
+
+                    holder = self
+                        .released
+                        .wait_timeout(holder, INTERRUPT_POLL_INTERVAL)
+                        .unwrap()
+                        .0;
+                }
+                _ => {
+                    holder.owner = Some(owner);
+                    holder.count += 1;
+                    return true;
+                }
+            }
+        }
+    }
+
+    // returns false if the mutex is held by another thread.
+    pub(crate) fn try_lock(&self, owner: Atom) -> bool {
+        let mut holder = self.holder.lock().unwrap();
+
+        match holder.owner {
+            Some(current) if current != owner => false,
+            _ => {
+                holder.owner = Some(owner);
+                holder.count += 1;
+                true
+            }
+        }
+    }
+
+    // returns false if owner doesn't hold the mutex.
+    pub(crate) fn unlock(&self, owner: Atom) -> bool {
+        let mut holder = self.holder.lock().unwrap();
+
+        if holder.owner != Some(owner) {
+            return false;
+        }
+
+        holder.count -= 1;
+
+        if holder.count == 0 {
+            holder.owner = None;
+            self.released.notify_one();
+        }
+
+        true
+    }
+
+    // releases the mutex however often owner locked it, returning that
+    // count for relock.
+    fn release(&self, owner: Atom) -> usize {
+        let mut holder = self.holder.lock().unwrap();
+        let count = std::mem::take(&mut holder.count);
+
+        debug_assert_eq!(holder.owner, Some(owner));
+
+        holder.owner = None;
+        self.released.notify_one();
+
+        count
+    }
+
+    // locks the mutex count times, as it was locked before release.
+    // this wait is not interruptible, so that the cleanup of an
+    // interrupted goal finds the mutex held.
+    fn relock(&self, owner: Atom, count: usize) {
+        let mut holder = self.holder.lock().unwrap();
+
+        while holder.owner.is_some() {
+            holder = self.released.wait(holder).unwrap();
+        }
+
+        holder.owner = Some(owner);
+        holder.count = count;
+    }
+}
+
+// a condition variable, waited on by threads holding a mutex.
+#[derive(Debug, Default)]
+pub(crate) struct Condition {
+    waiters: Mutex<Waiters>,
+    signalled: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Waiters {
+    waiting: usize,
+    // the number of waiting threads to wake.
+    wakeups: usize,
+}
+
+impl Condition {
+    pub(crate) fn lookup(name: Atom) -> Option<Arc<Condition>> {
+        CONDITIONS.lock().unwrap().get(&name).cloned()
+    }
+
+    // returns false if a condition variable named name already exists.
+    pub(crate) fn create(name: Atom) -> bool {
+        create_named(&CONDITIONS, name)
+    }
+
+    pub(crate) fn create_anonymous(atom_tbl: &AtomTable) -> Atom {
+        create_anonymous(
+            &CONDITIONS,
+            &ANONYMOUS_CONDITION_COUNT,
+            "$condition_",
+            atom_tbl,
+        )
+    }
+
+    pub(crate) fn destroy(name: Atom) -> bool {
+        CONDITIONS.lock().unwrap().shift_remove(&name).is_some()
+    }
+
+    // wakes one of the threads waiting on the condition, if any.
+    pub(crate) fn signal(&self) {
+        let mut waiters = self.waiters.lock().unwrap();
+
+        if waiters.wakeups < waiters.waiting {
+            waiters.wakeups += 1;
+            self.signalled.notify_all();
+        }
+    }
+
+    // wakes every thread waiting on the condition.
+    pub(crate) fn broadcast(&self) {
+        let mut waiters = self.waiters.lock().unwrap();
+
+        waiters.wakeups = waiters.waiting;
+        self.signalled.notify_all();
+    }
+
+    // releases mutex, which owner holds, and blocks until the
+    // condition is signalled, timeout (None blocks indefinitely) runs
+    // out or the wait is interrupted. the mutex is locked again
+    // before returning, in every case.
+    pub(crate) fn wait(
+        &self,
+        mutex: &PrologMutex,
+        owner: Atom,
+        timeout: Option<Duration>,
+        interrupted: impl Fn() -> bool,
+    ) -> ConditionOutcome {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut waiters = self.waiters.lock().unwrap();
+
+        // the mutex is released only once the waiter is counted, so
+        // that a signal sent by its next holder isn't lost.
+        waiters.waiting += 1;
+        let count = mutex.release(owner);
+
+        let outcome = loop {
+            if waiters.wakeups > 0 {
+                waiters.wakeups -= 1;
+                break ConditionOutcome::Signalled;
+            }
+
+            if interrupted() {
+                break ConditionOutcome::Interrupted;
+            }
+
+            let slice = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+
+                    if deadline <= now {
+                        break ConditionOutcome::TimedOut;
+                    }
+
+                    (deadline - now).min(INTERRUPT_POLL_INTERVAL)
+                }
+                None => INTERRUPT_POLL_INTERVAL,
+            };
+
+            waiters = self.signalled.wait_timeout(waiters, slice).unwrap().0;
+        };
+
+        waiters.waiting -= 1;
+        waiters.wakeups = waiters.wakeups.min(waiters.waiting);
+        drop(waiters);
+
+        mutex.relock(owner, count);
+        outcome
+    }
+}
@@ -1,3 +1,4 @@
+use crate::arena::*;
 use crate::atom_table::*;
 use crate::codegen::CodeGenSettings;
 use crate::forms::*;
@@ -21,7 +22,7 @@ pub(crate) fn to_op_decl_spec(spec: Atom) -> Result<OpDeclSpec, CompilationError
     })
 }
 
-fn setup_op_decl(mut terms: Vec<Term>) -> Result<OpDecl, CompilationError> {
+pub(super) fn setup_op_decl(mut terms: Vec<Term>) -> Result<OpDecl, CompilationError> {
     // should allow non-partial lists?
     let name = match terms.pop().unwrap() {
         Term::Literal(_, Literal::Atom(name)) => name,
@@ -592,18 +593,25 @@ pub(super) fn try_term_to_tl<'a, LS: LoadState<'a>>(
         loader: &mut Loader<'a, LS>,
         term: Term,
     ) -> Result<PredicateClause, CompilationError> {
+        let arena = &mut LS::machine_st(&mut loader.payload).arena;
+
         match term {
             Term::Clause(r, name, mut terms) => {
                 let is_rule = name == atom!(":-") && terms.len() == 2;
 
                 if is_rule {
                     let tail = terms.pop().unwrap();
-                    let head = terms.pop().unwrap();
+                    let mut head = terms.pop().unwrap();
+
+                    index_floats(arena, &mut head);
 
                     let (rule, var_data) = self.setup_rule(loader, head, tail)?;
                     Ok(PredicateClause::Rule(rule, var_data))
                 } else {
-                    let term = Term::Clause(r, name, terms);
+                    let mut term = Term::Clause(r, name, terms);
+
+                    index_floats(arena, &mut term);
+
                     let (fact, var_data) = self.setup_fact(term)?;
                     Ok(PredicateClause::Fact(fact, var_data))
                 }
@@ -615,3 +623,29 @@ pub(super) fn try_term_to_tl<'a, LS: LoadState<'a>>(
         }
     }
 }
+
+// replaces the floats of the head of a clause by equal floats of the
+// same offset (see Arena::f64_index_key), as the clause is indexed on
+// the offsets of its floats.
+fn index_floats(arena: &mut Arena, head: &mut Term) {
+    let mut terms = vec![head];
+
+    while let Some(term) = terms.pop() {
+        match term {
+            Term::Literal(_, Literal::F64Offset(offset)) => {
+                *offset = arena.f64_index_key(*offset);
+            }
+            Term::Clause(_, _, args) => {
+                terms.extend(args.iter_mut());
+            }
+            Term::Cons(_, head, tail) => {
+                terms.push(head);
+                terms.push(tail);
+            }
+            Term::PartialString(_, _, tail) => {
+                terms.push(tail);
+            }
+            _ => {}
+        }
+    }
+}
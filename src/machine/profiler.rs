@@ -0,0 +1,161 @@
+use crate::atom_table::*;
+use crate::machine::stack::*;
+
+use std::time::Duration;
+use web_time::Instant;
+
+// time is charged to the running predicates at most this often. a
+// sample walks every environment, so deep recursions are sampled less
+// often, keeping the walks to about a tenth of the running time.
+const SAMPLE_INTERVAL: Duration = Duration::from_micros(50);
+const SAMPLE_COST_FACTOR: u32 = 10;
+
+#[derive(Debug)]
+pub(crate) struct ProfileEntry {
+    pub(crate) module_name: Atom,
+    pub(crate) name: Atom,
+    pub(crate) arity: usize,
+    pub(crate) calls: u64,
+    pub(crate) redos: u64,
+    pub(crate) inclusive: Duration,
+    pub(crate) exclusive: Duration,
+    // the last sample charged to the entry, so that a recursive
+    // predicate is charged inclusive time once per sample.
+    sample: u64,
+}
+
+// the predicate counters of profile/1. calls are counted by
+// call_at_index and execute_at_index, redos by backtrack, and time is
+// charged at the interrupt poll of the dispatch loop.
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    pub(crate) entries: Vec<ProfileEntry>,
+    // the code offsets at which predicates and their clauses start,
+    // sorted, with the rank of the module and the entry of their
+    // predicate. the code of a predicate is shared by every module
+    // importing it, and is attributed to the lowest ranked one.
+    starts: Vec<(usize, u8, usize)>,
+    // the length of the code when profiling began. code compiled
+    // since isn't attributed to a predicate.
+    code_end: usize,
+    samples: u64,
+    last_sample: Instant,
+    next_sample: Instant,
+}
+
+impl Profiler {
+    pub(crate) fn new(code_end: usize) -> Self {
+        let now = Instant::now();
+
+        Profiler {
+            entries: vec![],
+            starts: vec![],
+            code_end,
+            samples: 0,
+            last_sample: now,
+            next_sample: now + SAMPLE_INTERVAL,
+        }
+    }
+
+    pub(crate) fn add_predicate(
+        &mut self,
+        module_name: Atom,
+        (name, arity): (Atom, usize),
+        rank: u8,
+        starts: impl IntoIterator<Item = usize>,
+    ) {
+        let entry = self.entries.len();
+
+        self.entries.push(ProfileEntry {
+            module_name,
+            name,
+            arity,
+            calls: 0,
+            redos: 0,
+            inclusive: Duration::ZERO,
+            exclusive: Duration::ZERO,
+            sample: 0,
+        });
+
+        self.starts
+            .extend(starts.into_iter().map(|start| (start, rank, entry)));
+    }
+
+    pub(crate) fn finish_predicates(&mut self) {
+        self.starts.sort_unstable();
+        self.starts.dedup_by_key(|(start, ..)| *start);
+    }
+
+    fn owner(&self, p: usize) -> Option<usize> {
+        if p >= self.code_end {
+            return None;
+        }
+
+        let idx = self.starts.partition_point(|&(start, ..)| start <= p);
+        idx.checked_sub(1).map(|idx| self.starts[idx].2)
+    }
+
+    // the predicate whose code p is in.
+    pub(crate) fn predicate_at(&self, p: usize) -> Option<&ProfileEntry> {
+        self.owner(p).map(|entry| &self.entries[entry])
+    }
+
+    #[inline]
+    pub(crate) fn record_call(&mut self, p: usize) {
+        if let Some(entry) = self.owner(p) {
+            self.entries[entry].calls += 1;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record_redo(&mut self, p: usize) {
+        if let Some(entry) = self.owner(p) {
+            self.entries[entry].redos += 1;
+        }
+    }
+
+    fn charge_inclusive(&mut self, p: usize, elapsed: Duration) {
+        if let Some(entry) = self.owner(p) {
+            let entry = &mut self.entries[entry];
+
+            if entry.sample != self.samples {
+                entry.sample = self.samples;
+                entry.inclusive += elapsed;
+            }
+        }
+    }
+
+    // charges the time since the last sample exclusively to the
+    // predicate running at p and inclusively to every predicate with
+    // an activation in the continuation chain.
+    pub(crate) fn sample(&mut self, p: usize, cp: usize, mut e: usize, stack: &Stack) {
+        let now = Instant::now();
+
+        if now < self.next_sample {
+            return;
+        }
+
+        let elapsed = now - self.last_sample;
+        self.samples += 1;
+
+        if let Some(entry) = self.owner(p) {
+            self.entries[entry].exclusive += elapsed;
+        }
+
+        self.charge_inclusive(p, elapsed);
+        self.charge_inclusive(cp, elapsed);
+
+        while e != 0 {
+            let frame = stack.index_and_frame(e);
+            let cp = frame.prelude.cp;
+
+            e = frame.prelude.e;
+            self.charge_inclusive(cp, elapsed);
+        }
+
+        let walked = Instant::now();
+
+        self.last_sample = walked;
+        self.next_sample = walked + SAMPLE_INTERVAL.max((walked - now) * SAMPLE_COST_FACTOR);
+    }
+}
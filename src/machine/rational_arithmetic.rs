@@ -0,0 +1,435 @@
+// the elementary functions of integers and rationals under the
+// prefer_rationals flag, whose results are rationals of the
+// significant digits of the rational_precision flag, rounded as the
+// rational_rounding flag says.
+//
+// the functions are evaluated on decimals of GUARD_DIGITS more digits
+// than the flag asks for, enough to round the exact result in all but
+// the cases where it lies within a unit of the last guard digit of a
+// rounding boundary. results much smaller than their arguments, such
+// as the sines of numbers close to multiples of pi, are evaluated
+// again with the digits that cancelled.
+
+use crate::parser::ast::{RationalFlags, RationalRounding};
+use crate::parser::dashu::Rational;
+
+use dashu::base::{Abs, SquareRoot};
+use dashu::float::round::mode::{Away, Down, HalfAway, HalfEven, Up, Zero};
+use dashu::float::round::Round;
+use dashu::Decimal;
+
+const GUARD_DIGITS: usize = 10;
+
+// the position m of the leading digit of d, 10^(m-1) <= |d| < 10^m.
+fn magnitude(d: &Decimal) -> isize {
+    if d.repr().is_zero() {
+        isize::MIN / 2
+    } else {
+        d.repr().digits() as isize + d.repr().exponent()
+    }
+}
+
+// the digits of the integer part of r.
+fn integer_digits(r: &Rational) -> usize {
+    magnitude(&decimal(r, 2)).max(0) as usize
+}
+
+fn decimal(r: &Rational, precision: usize) -> Decimal {
+    r.to_float::<HalfAway, 10>(precision).value()
+}
+
+fn constant(n: i64, precision: usize) -> Decimal {
+    Decimal::from(n).with_precision(precision).value()
+}
+
+fn round_as<R: Round>(r: &Rational, precision: usize) -> Rational {
+    Rational::try_from(r.to_float::<R, 10>(precision).value())
+        .expect("finite decimals are rationals")
+}
+
+// r rounded to the significant digits of flags.
+pub(crate) fn round(r: &Rational, flags: RationalFlags) -> Rational {
+    let precision = flags.precision;
+
+    match flags.rounding {
+        RationalRounding::HalfEven => round_as::<HalfEven>(r, precision),
+        RationalRounding::HalfAwayFromZero => round_as::<HalfAway>(r, precision),
+        RationalRounding::TowardZero => round_as::<Zero>(r, precision),
+        RationalRounding::AwayFromZero => round_as::<Away>(r, precision),
+        RationalRounding::Down => round_as::<Down>(r, precision),
+        RationalRounding::Up => round_as::<Up>(r, precision),
+    }
+}
+
+fn finish(d: Decimal, flags: RationalFlags) -> Rational {
+    let r = Rational::try_from(d).expect("finite decimals are rationals");
+    round(&r, flags)
+}
+
+// whether adding term to sum no longer changes its precision digits.
+fn negligible(term: &Decimal, sum: &Decimal, precision: usize) -> bool {
+    term.repr().is_zero() || magnitude(term) + (precision as isize) < magnitude(sum)
+}
+
+// x - x^3/3! + x^5/5! - ..., for |x| <= pi.
+fn sin_series(x: &Decimal, precision: usize) -> Decimal {
+    let x2 = x * x;
+    let mut term = x.clone();
+    let mut sum = x.clone();
+    let mut k = 1;
+
+    loop {
+        term = -(term * &x2) / constant((k + 1) * (k + 2), precision);
+        k += 2;
+
+        if negligible(&term, &sum, precision) {
+            return sum;
+        }
+
+        sum += &term;
+    }
+}
+
+// 1 - x^2/2! + x^4/4! - ..., for |x| <= pi.
+fn cos_series(x: &Decimal, precision: usize) -> Decimal {
+    let x2 = x * x;
+    let mut term = constant(1, precision);
+    let mut sum = term.clone();
+    let mut k = 0;
+
+    loop {
+        term = -(term * &x2) / constant((k + 1) * (k + 2), precision);
+        k += 2;
+
+        if negligible(&term, &sum, precision) {
+            return sum;
+        }
+
+        sum += &term;
+    }
+}
+
+// x - x^3/3 + x^5/5 - ..., for |x| < 1/10.
+fn atan_series(x: &Decimal, precision: usize) -> Decimal {
+    let x2 = -(x * x);
+    let mut power = x.clone();
+    let mut sum = x.clone();
+    let mut k = 1;
+
+    loop {
+        power *= &x2;
+        k += 2;
+
+        let term = &power / constant(k, precision);
+
+        if negligible(&term, &sum, precision) {
+            return sum;
+        }
+
+        sum += &term;
+    }
+}
+
+// pi by Machin's formula, 16 atan(1/5) - 4 atan(1/239).
+fn pi(precision: usize) -> Decimal {
+    let precision = precision + 2;
+    let atan_inverse = |n| {
+        atan_series(
+            &(constant(1, precision) / constant(n, precision)),
+            precision,
+        )
+    };
+
+    constant(16, precision) * atan_inverse(5) - constant(4, precision) * atan_inverse(239)
+}
+
+fn atan_decimal(x: &Decimal, precision: usize) -> Decimal {
+    let one = constant(1, precision);
+
+    if x.clone().abs() > one {
+        // atan(x) = sign(x) pi/2 - atan(1/x)
+        let half_pi = pi(precision) / constant(2, precision);
+        let reduced = atan_decimal(&(&one / x), precision);
+
+        return if x.repr().sign() == dashu::base::Sign::Negative {
+            -half_pi - reduced
+        } else {
+            half_pi - reduced
+        };
+    }
+
+    // atan(x) = 2 atan(x / (1 + sqrt(1 + x^2))), halving |x| <= 1 below
+    // 1/10 at most three times.
+    let mut x = x.clone();
+    let mut doublings = 0;
+
+    while magnitude(&x) > -1 {
+        x = &x / (&one + (&one + &x * &x).sqrt());
+        doublings += 1;
+    }
+
+    atan_series(&x, precision) * constant(1 << doublings, precision)
+}
+
+// the sine and cosine of x, its multiples of 2 pi removed.
+fn sin_cos(x: &Rational, precision: usize) -> (Decimal, Decimal) {
+    let precision = precision + integer_digits(x);
+    let x = decimal(x, precision);
+    let two_pi = pi(precision) * constant(2, precision);
+    let turns = (&x / &two_pi).round();
+    let x = x - turns * two_pi;
+
+    (sin_series(&x, precision), cos_series(&x, precision))
+}
+
+// evaluates f at the working precision of flags, and again with the
+// digits lost if its result has fewer digits before the point than
+// its argument.
+fn with_cancelled_digits(flags: RationalFlags, f: impl Fn(usize) -> Decimal) -> Rational {
+    let precision = flags.precision + GUARD_DIGITS;
+    let result = f(precision);
+    let lost = -magnitude(&result);
+
+    if lost > 0 && !result.repr().is_zero() {
+        finish(f(precision + lost as usize), flags)
+    } else {
+        finish(result, flags)
+    }
+}
+
+pub(crate) fn sqrt(x: &Rational, flags: RationalFlags) -> Option<Rational> {
+    if x < &Rational::ZERO {
+        return None;
+    }
+
+    let precision = flags.precision + GUARD_DIGITS;
+    Some(finish(decimal(x, precision).sqrt(), flags))
+}
+
+pub(crate) fn exp(x: &Rational, flags: RationalFlags) -> Rational {
+    // the error of x is magnified by its size in exp(x).
+    let precision = flags.precision + GUARD_DIGITS + integer_digits(x);
+    finish(decimal(x, precision).exp(), flags)
+}
+
+pub(crate) fn expm1(x: &Rational, flags: RationalFlags) -> Rational {
+    let precision = flags.precision + GUARD_DIGITS + integer_digits(x);
+    finish(decimal(x, precision).exp_m1(), flags)
+}
+
+pub(crate) fn log(x: &Rational, flags: RationalFlags) -> Option<Rational> {
+    if x <= &Rational::ZERO {
+        return None;
+    }
+
+    // log(x) = log1p(x - 1), exactly so for x close to 1.
+    log1p(&(x - Rational::ONE), flags)
+}
+
+fn log1p_decimal(x: &Rational, precision: usize) -> Decimal {
+    decimal(x, precision).ln_1p()
+}
+
+pub(crate) fn log1p(x: &Rational, flags: RationalFlags) -> Option<Rational> {
+    if x <= &-Rational::ONE {
+        return None;
+    }
+
+    let precision = flags.precision + GUARD_DIGITS;
+    Some(finish(log1p_decimal(x, precision), flags))
+}
+
+// x ** y of a non-negative x and a y that isn't an integer.
+pub(crate) fn pow(x: &Rational, y: &Rational, flags: RationalFlags) -> Option<Rational> {
+    if x < &Rational::ZERO || (x == &Rational::ZERO && y <= &Rational::ZERO) {
+        return None;
+    } else if x == &Rational::ZERO {
+        return Some(Rational::ZERO);
+    }
+
+    // x ** y = exp(y * log(x)), whose error is magnified by the size
+    // of y * log(x) as in exp.
+    let exponent =
+        |precision| decimal(y, precision) * log1p_decimal(&(x - Rational::ONE), precision);
+    let precision = flags.precision + GUARD_DIGITS;
+    let digits = magnitude(&exponent(precision)).max(0) as usize;
+
+    Some(finish(exponent(precision + digits).exp(), flags))
+}
+
+pub(crate) fn sin(x: &Rational, flags: RationalFlags) -> Rational {
+    with_cancelled_digits(flags, |precision| sin_cos(x, precision).0)
+}
+
+pub(crate) fn cos(x: &Rational, flags: RationalFlags) -> Rational {
+    with_cancelled_digits(flags, |precision| sin_cos(x, precision).1)
+}
+
+pub(crate) fn tan(x: &Rational, flags: RationalFlags) -> Rational {
+    // the cosine may cancel as much as the sine.
+    let precision = flags.precision + GUARD_DIGITS;
+    let (_, cos) = sin_cos(x, precision);
+    let lost = (-magnitude(&cos)).max(0) as usize;
+
+    with_cancelled_digits(flags, |precision| {
+        let (sin, cos) = sin_cos(x, precision + lost);
+        sin / cos
+    })
+}
+
+pub(crate) fn atan(x: &Rational, flags: RationalFlags) -> Rational {
+    let precision = flags.precision + GUARD_DIGITS;
+    finish(atan_decimal(&decimal(x, precision), precision), flags)
+}
+
+pub(crate) fn asin(x: &Rational, flags: RationalFlags) -> Option<Rational> {
+    let one = Rational::ONE;
+
+    if x > &one || x < &-one.clone() {
+        return None;
+    }
+
+    let precision = flags.precision + GUARD_DIGITS;
+
+    let result = if x.clone().abs() == one {
+        let half_pi = pi(precision) / constant(2, precision);
+
+        if x < &Rational::ZERO {
+            -half_pi
+        } else {
+            half_pi
+        }
+    } else {
+        // asin(x) = atan(x / sqrt(1 - x^2))
+        let cos = decimal(&(one - x * x), precision).sqrt();
+        atan_decimal(&(decimal(x, precision) / cos), precision)
+    };
+
+    Some(finish(result, flags))
+}
+
+pub(crate) fn acos(x: &Rational, flags: RationalFlags) -> Option<Rational> {
+    let one = Rational::ONE;
+
+    if x > &one || x < &-one.clone() {
+        return None;
+    }
+
+    let precision = flags.precision + GUARD_DIGITS;
+
+    let result = if x == &-one.clone() {
+        pi(precision)
+    } else {
+        // acos(x) = 2 atan(sqrt((1 - x) / (1 + x))), which doesn't
+        // cancel for x close to 1.
+        let tan_half = decimal(&((&one - x) / (&one + x)), precision).sqrt();
+        atan_decimal(&tan_half, precision) * constant(2, precision)
+    };
+
+    Some(finish(result, flags))
+}
+
+pub(crate) fn atan2(y: &Rational, x: &Rational, flags: RationalFlags) -> Option<Rational> {
+    let precision = flags.precision + GUARD_DIGITS;
+
+    let result = if x == &Rational::ZERO {
+        if y == &Rational::ZERO {
+            return None;
+        }
+
+        let half_pi = pi(precision) / constant(2, precision);
+
+        if y < &Rational::ZERO {
+            -half_pi
+        } else {
+            half_pi
+        }
+    } else {
+        let atan = atan_decimal(&decimal(&(y / x), precision), precision);
+
+        if x > &Rational::ZERO {
+            atan
+        } else if y < &Rational::ZERO {
+            atan - pi(precision)
+        } else {
+            atan + pi(precision)
+        }
+    };
+
+    Some(finish(result, flags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(precision: usize, rounding: RationalRounding) -> RationalFlags {
+        RationalFlags {
+            precision,
+            rounding,
+        }
+    }
+
+    fn rational(n: i64, d: u64) -> Rational {
+        Rational::from_parts(n.into(), d.into())
+    }
+
+    // n / 10^e
+    fn scaled(n: i128, e: u32) -> Rational {
+        Rational::from_parts(n.into(), dashu::integer::UBig::from(10u8).pow(e as usize))
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let x = rational(-25, 10);
+        let modes = [
+            (RationalRounding::HalfEven, rational(-2, 1)),
+            (RationalRounding::HalfAwayFromZero, rational(-3, 1)),
+            (RationalRounding::TowardZero, rational(-2, 1)),
+            (RationalRounding::AwayFromZero, rational(-3, 1)),
+            (RationalRounding::Down, rational(-3, 1)),
+            (RationalRounding::Up, rational(-2, 1)),
+        ];
+
+        for (rounding, rounded) in modes {
+            assert_eq!(round(&x, flags(1, rounding)), rounded);
+        }
+    }
+
+    #[test]
+    fn elementary_functions() {
+        let digits = flags(20, RationalRounding::HalfEven);
+
+        assert_eq!(
+            sqrt(&rational(2, 1), digits),
+            Some(scaled(14142135623730950488, 19))
+        );
+        assert_eq!(sqrt(&rational(9, 4), digits), Some(rational(3, 2)));
+        assert_eq!(sqrt(&rational(-1, 1), digits), None);
+        assert_eq!(
+            exp(&rational(1, 1), digits),
+            scaled(27182818284590452354, 19)
+        );
+        assert_eq!(log(&rational(1, 1), digits), Some(Rational::ZERO));
+        assert_eq!(log(&Rational::ZERO, digits), None);
+        assert_eq!(
+            atan(&rational(1, 1), digits),
+            scaled(78539816339744830962, 20)
+        );
+        assert_eq!(
+            acos(&rational(-1, 1), digits),
+            Some(scaled(31415926535897932385, 19))
+        );
+        assert_eq!(sin(&Rational::ZERO, digits), Rational::ZERO);
+        assert_eq!(cos(&Rational::ZERO, digits), Rational::ONE);
+        assert_eq!(
+            pow(&rational(2, 1), &rational(1, 2), digits),
+            sqrt(&rational(2, 1), digits)
+        );
+        // sin(355) = -3.0144353359...e-5 cancels the digits of 355.
+        assert_eq!(
+            sin(&rational(355, 1), flags(5, RationalRounding::HalfEven)),
+            scaled(-30144, 9)
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use indexmap::IndexMap;
+use regex::Regex;
+
+use std::sync::{LazyLock, Mutex};
+
+// the most patterns kept compiled. scripts tend to match a handful of
+// patterns against many lines, so patterns are compiled once and
+// kept, the oldest making room for new ones once the cache is full.
+const MAX_CACHED_REGEXES: usize = 256;
+
+static REGEX_CACHE: LazyLock<Mutex<IndexMap<String, Regex>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+// the compiled pattern, or None if pattern isn't a valid regular
+// expression.
+pub(crate) fn compiled_regex(pattern: &str) -> Option<Regex> {
+    let mut cache = REGEX_CACHE.lock().unwrap();
+
+    if let Some(regex) = cache.get(pattern) {
+        return Some(regex.clone());
+    }
+
+    let regex = Regex::new(pattern).ok()?;
+
+    if cache.len() == MAX_CACHED_REGEXES {
+        cache.shift_remove_index(0);
+    }
+
+    cache.insert(pattern.to_string(), regex.clone());
+    Some(regex)
+}
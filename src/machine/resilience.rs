@@ -0,0 +1,236 @@
+use crate::atom_table::*;
+
+use indexmap::IndexMap;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+// rate limiters and circuit breakers guard calls to downstream
+// systems, whichever machine of the process makes them, so like
+// message queues they are shared by every machine.
+static RESILIENCE_OBJECTS: LazyLock<Mutex<IndexMap<Atom, ResilienceObject>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+static ANONYMOUS_OBJECT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub(crate) enum ResilienceObject {
+    RateLimiter(RateLimiter),
+    CircuitBreaker(CircuitBreaker),
+}
+
+impl ResilienceObject {
+    fn kind(&self) -> Atom {
+        match self {
+            ResilienceObject::RateLimiter(_) => atom!("rate_limiter"),
+            ResilienceObject::CircuitBreaker(_) => atom!("circuit_breaker"),
+        }
+    }
+
+    // the name of the new object, which is name if given, or None if
+    // an object named name already exists.
+    pub(crate) fn create(name: Option<Atom>, object: Self, atom_tbl: &AtomTable) -> Option<Atom> {
+        let mut objects = RESILIENCE_OBJECTS.lock().unwrap();
+
+        let name = match name {
+            Some(name) if objects.contains_key(&name) => return None,
+            Some(name) => name,
+            None => loop {
+                let count = ANONYMOUS_OBJECT_COUNT.fetch_add(1, Ordering::Relaxed);
+                let name = AtomTable::build_with(
+                    atom_tbl,
+                    &format!("${}_{count}", object.kind().as_str()),
+                );
+
+                if !objects.contains_key(&name) {
+                    break name;
+                }
+            },
+        };
+
+        objects.insert(name, object);
+        Some(name)
+    }
+
+    // destroys the object named name if it is of kind kind.
+    pub(crate) fn destroy(name: Atom, kind: Atom) -> bool {
+        let mut objects = RESILIENCE_OBJECTS.lock().unwrap();
+
+        match objects.get(&name) {
+            Some(object) if object.kind() == kind => {
+                objects.shift_remove(&name);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn with_rate_limiter<R>(
+        name: Atom,
+        f: impl FnOnce(&mut RateLimiter) -> R,
+    ) -> Option<R> {
+        match RESILIENCE_OBJECTS.lock().unwrap().get_mut(&name) {
+            Some(ResilienceObject::RateLimiter(limiter)) => Some(f(limiter)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn with_circuit_breaker<R>(
+        name: Atom,
+        f: impl FnOnce(&mut CircuitBreaker) -> R,
+    ) -> Option<R> {
+        match RESILIENCE_OBJECTS.lock().unwrap().get_mut(&name) {
+            Some(ResilienceObject::CircuitBreaker(breaker)) => Some(f(breaker)),
+            _ => None,
+        }
+    }
+}
+
+// a token bucket holding up to capacity tokens, refilled at rate
+// tokens per second. it starts full, so bursts of up to capacity
+// calls go through at once.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(capacity: f64, rate: f64) -> Self {
+        RateLimiter {
+            capacity,
+            rate,
+            tokens: capacity,
+            refilled: Instant::now(),
+        }
+    }
+
+    // takes n tokens if the bucket holds them, and else returns how
+    // long it takes until it does, which is never if n exceeds the
+    // capacity.
+    pub(crate) fn acquire(&mut self, n: f64) -> Result<(), Option<Duration>> {
+        if n > self.capacity {
+            return Err(None);
+        }
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+
+        self.tokens = self.capacity.min(self.tokens + elapsed * self.rate);
+        self.refilled = now;
+
+        if self.tokens >= n {
+            self.tokens -= n;
+            Ok(())
+        } else {
+            Err(Some(Duration::from_secs_f64((n - self.tokens) / self.rate)))
+        }
+    }
+}
+
+#[derive(Debug)]
+enum BreakerState {
+    Closed { failures: usize },
+    Open { until: Instant },
+    // trial is true while the single call let through to probe the
+    // downstream system is running.
+    HalfOpen { trial: bool },
+}
+
+// a circuit breaker opens after threshold consecutive failed calls,
+// rejecting calls until reset_timeout runs out. it then lets a single
+// trial call through, which closes it again if it succeeds.
+//
+// every change of state starts a new generation, and calls only
+// count towards the generation they entered in.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    threshold: usize,
+    reset_timeout: Duration,
+    state: BreakerState,
+    generation: usize,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(threshold: usize, reset_timeout: Duration) -> Self {
+        CircuitBreaker {
+            threshold,
+            reset_timeout,
+            state: BreakerState::Closed { failures: 0 },
+            generation: 0,
+        }
+    }
+
+    fn transition(&mut self, state: BreakerState) {
+        self.state = state;
+        self.generation += 1;
+    }
+
+    fn update(&mut self) {
+        if let BreakerState::Open { until } = self.state {
+            if Instant::now() >= until {
+                self.transition(BreakerState::HalfOpen { trial: false });
+            }
+        }
+    }
+
+    pub(crate) fn state(&mut self) -> Atom {
+        self.update();
+
+        match self.state {
+            BreakerState::Closed { .. } => atom!("closed"),
+            BreakerState::Open { .. } => atom!("open"),
+            BreakerState::HalfOpen { .. } => atom!("half_open"),
+        }
+    }
+
+    // the generation of a call that may go ahead, which it must pass
+    // to exit, or None if the call is rejected.
+    pub(crate) fn enter(&mut self) -> Option<usize> {
+        self.update();
+
+        let permitted = match &mut self.state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { .. } => false,
+            BreakerState::HalfOpen { trial } => !std::mem::replace(trial, true),
+        };
+
+        permitted.then_some(self.generation)
+    }
+
+    pub(crate) fn exit(&mut self, generation: usize, success: bool) {
+        if generation != self.generation {
+            return;
+        }
+
+        let open = BreakerState::Open {
+            until: Instant::now() + self.reset_timeout,
+        };
+
+        match &mut self.state {
+            BreakerState::Closed { failures } => {
+                if success {
+                    *failures = 0;
+                } else {
+                    *failures += 1;
+
+                    if *failures >= self.threshold {
+                        self.transition(open);
+                    }
+                }
+            }
+            BreakerState::Open { .. } => {}
+            BreakerState::HalfOpen { .. } => {
+                if success {
+                    self.transition(BreakerState::Closed { failures: 0 });
+                } else {
+                    self.transition(open);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::atom_table::*;
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+// the handling of a signal, set by on_signal/2 of library(os).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SignalAction {
+    Default = 0,
+    Ignore = 1,
+    // the signal is marked pending, and its goal is run by
+    // os:'$signal_goal'/3 at the next call of a predicate.
+    Goal = 2,
+}
+
+impl SignalAction {
+    pub(crate) fn from_atom(atom: Atom) -> Option<Self> {
+        match atom {
+            atom!("default") => Some(SignalAction::Default),
+            atom!("ignore") => Some(SignalAction::Ignore),
+            atom!("goal") => Some(SignalAction::Goal),
+            _ => None,
+        }
+    }
+}
+
+struct Signal {
+    name: &'static str,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    number: i32,
+    action: AtomicU8,
+    pending: AtomicBool,
+    // whether the handler of the signal was the default of the system
+    // before ours was registered, in which case the default action is
+    // emulated. otherwise signal-hook calls the previous handler, such
+    // as that of ctrlc which interrupts the toplevel.
+    emulates_default: AtomicBool,
+    registered: AtomicBool,
+}
+
+impl Signal {
+    const fn new(name: &'static str, number: i32) -> Self {
+        Signal {
+            name,
+            number,
+            action: AtomicU8::new(SignalAction::Default as u8),
+            pending: AtomicBool::new(false),
+            emulates_default: AtomicBool::new(false),
+            registered: AtomicBool::new(false),
+        }
+    }
+}
+
+#[cfg(unix)]
+static SIGNALS: [Signal; 2] = [
+    Signal::new("int", libc::SIGINT),
+    Signal::new("term", libc::SIGTERM),
+];
+
+#[cfg(not(unix))]
+static SIGNALS: [Signal; 0] = [];
+
+// whether any signal mapped to a goal is pending.
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+static REGISTRATION: Mutex<()> = Mutex::new(());
+
+// sets the action of the signal named name, registering its handler
+// the first time. fails if the signal isn't supported.
+pub(crate) fn set_action(name: Atom, action: SignalAction) -> bool {
+    let Some(signal) = SIGNALS.iter().find(|signal| signal.name == &*name.as_str()) else {
+        return false;
+    };
+
+    let _guard = REGISTRATION.lock().unwrap();
+
+    if !signal.registered.load(Ordering::Relaxed) {
+        if !register(signal) {
+            return false;
+        }
+
+        signal.registered.store(true, Ordering::Relaxed);
+    }
+
+    signal.action.store(action as u8, Ordering::Relaxed);
+    true
+}
+
+#[cfg(unix)]
+fn register(signal: &'static Signal) -> bool {
+    // SAFETY: sigaction with a null action only reads the handler.
+    let previous = unsafe {
+        let mut previous: libc::sigaction = std::mem::zeroed();
+
+        if libc::sigaction(signal.number, std::ptr::null(), &mut previous) != 0 {
+            return false;
+        }
+
+        previous.sa_sigaction
+    };
+
+    signal
+        .emulates_default
+        .store(previous == libc::SIG_DFL, Ordering::Relaxed);
+
+    // SAFETY: the action only uses atomics and
+    // emulate_default_handler, which are async-signal-safe.
+    unsafe { signal_hook::low_level::register(signal.number, move || handle(signal)) }.is_ok()
+}
+
+#[cfg(not(unix))]
+fn register(_signal: &'static Signal) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn handle(signal: &Signal) {
+    match signal.action.load(Ordering::Relaxed) {
+        action if action == SignalAction::Goal as u8 => {
+            signal.pending.store(true, Ordering::Relaxed);
+            PENDING.store(true, Ordering::Relaxed);
+        }
+        action if action == SignalAction::Ignore as u8 => {}
+        _ => {
+            if signal.emulates_default.load(Ordering::Relaxed) {
+                let _ = signal_hook::low_level::emulate_default_handler(signal.number);
+            }
+        }
+    }
+}
+
+// whether SIGINT interrupts the running goal, as it does unless
+// on_signal/2 maps it to a goal or ignores it.
+pub(crate) fn interrupts() -> bool {
+    SIGNALS
+        .iter()
+        .find(|signal| signal.name == "int")
+        .is_none_or(|signal| signal.action.load(Ordering::Relaxed) == SignalAction::Default as u8)
+}
+
+#[inline(always)]
+pub(crate) fn take_any_pending() -> bool {
+    PENDING.load(Ordering::Relaxed) && PENDING.swap(false, Ordering::Relaxed)
+}
+
+// the names of the pending signals mapped to goals, which are no
+// longer pending.
+pub(crate) fn take_pending() -> Vec<&'static str> {
+    SIGNALS
+        .iter()
+        .filter(|signal| signal.pending.swap(false, Ordering::Relaxed))
+        .map(|signal| signal.name)
+        .collect()
+}
+
+// whether a signal mapped to a goal is pending, for builtins that
+// wait, which return early so that the goal runs.
+#[inline(always)]
+pub(crate) fn any_pending() -> bool {
+    PENDING.load(Ordering::Relaxed)
+}
@@ -0,0 +1,728 @@
+//! The analysis of Prolog source text behind the language server of
+//! `crate::lsp`: the positions of its tokens, the predicates it
+//! defines and calls, the files and libraries it loads, and its syntax
+//! errors and singleton variables.
+
+use crate::atom_table::*;
+use crate::forms::*;
+use crate::instructions::*;
+use crate::machine::preprocessor::*;
+use crate::machine::*;
+use crate::parser::ast::*;
+use crate::parser::char_reader::{CharRead, CharReader};
+use crate::parser::lexer::*;
+use crate::parser::parser::*;
+use crate::{MachineBuilder, StreamConfig};
+
+use indexmap::IndexSet;
+
+use std::io::Cursor;
+
+/// A position in source text, as a line and a column counted in
+/// characters, both from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SourcePos {
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SourceTokenKind {
+    Atom(String),
+    Var(String),
+    // the parenthesis opening the arguments of a compound term.
+    FunctorOpen,
+    Open,
+    Close,
+    Comma,
+    End,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SourceToken {
+    pub(crate) kind: SourceTokenKind,
+    pub(crate) start: SourcePos,
+    pub(crate) end: SourcePos,
+}
+
+/// A predicate named in the source, as the head of a clause or as a
+/// goal.
+#[derive(Debug, Clone)]
+pub(crate) struct PredicateSite {
+    pub(crate) name: String,
+    pub(crate) arity: usize,
+    pub(crate) start: SourcePos,
+    pub(crate) end: SourcePos,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SourceDiagnostic {
+    pub(crate) severity: Severity,
+    pub(crate) start: SourcePos,
+    pub(crate) end: SourcePos,
+    pub(crate) message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceAnalysis {
+    pub(crate) tokens: Vec<SourceToken>,
+    pub(crate) definitions: Vec<PredicateSite>,
+    pub(crate) goals: Vec<PredicateSite>,
+    // the predicates declared dynamic, discontiguous or multifile,
+    // which are defined even without clauses.
+    pub(crate) declared: Vec<PredicateKey>,
+    // the predicates exported by the libraries the source uses.
+    pub(crate) imported: Vec<PredicateKey>,
+    // the files the source consults, includes or uses, as written.
+    pub(crate) loaded_files: Vec<String>,
+    pub(crate) diagnostics: Vec<SourceDiagnostic>,
+}
+
+/// Analyzes source text with the operators and predicates of a fresh
+/// machine, in which the libraries the text uses are loaded to learn
+/// their exports.
+#[derive(Debug)]
+pub(crate) struct SourceAnalyzer {
+    machine: Machine,
+    // the operators and predicates of user as the machine was built,
+    // before any library was loaded.
+    op_dir: OpDir,
+    visible: IndexSet<PredicateKey>,
+}
+
+// what stopped the reading of the clauses of a source.
+enum Interruption {
+    // a library is to be loaded before the clauses from pos are read.
+    Library(String, SourcePos),
+    // the clauses are read again from pos, past a lexical error.
+    Resync(SourcePos),
+}
+
+impl SourceAnalyzer {
+    pub(crate) fn new() -> Self {
+        let machine = MachineBuilder::new()
+            .with_streams(StreamConfig::in_memory())
+            .build();
+
+        let op_dir = machine.indices.op_dir.clone();
+        let code_index_tbl = &machine.machine_st.arena.code_index_tbl;
+
+        let visible = machine
+            .indices
+            .code_dir
+            .iter()
+            .filter(|(_, idx)| {
+                let ptr = code_index_tbl.get_entry((*idx).into());
+                !ptr.is_undefined()
+            })
+            .map(|(key, _)| *key)
+            .collect();
+
+        SourceAnalyzer {
+            machine,
+            op_dir,
+            visible,
+        }
+    }
+
+    /// Whether name/arity is a control construct or a predicate
+    /// callable from user without loading anything.
+    pub(crate) fn is_visible(&self, name: &str, arity: usize) -> bool {
+        let name = AtomTable::build_with(&self.machine.machine_st.atom_tbl, name);
+
+        matches!(
+            (name, arity),
+            (atom!(","), 2)
+                | (atom!(";"), 2)
+                | (atom!("->"), 2)
+                | (atom!("*->"), 2)
+                | (atom!("!"), 0)
+                | (atom!("call"), 1..=8)
+                | (atom!(":"), 2)
+        ) || ClauseType::is_inbuilt(name, arity)
+            || ClauseType::is_inlined(name, arity)
+            || self.visible.contains(&(name, arity))
+    }
+
+    /// The predicates callable from user without loading anything,
+    /// apart from those whose names start with `$`.
+    pub(crate) fn visible_predicates(&self) -> Vec<(String, usize)> {
+        self.visible
+            .iter()
+            .map(|(name, arity)| (name.as_str().to_string(), *arity))
+            .filter(|(name, _)| !name.starts_with('$'))
+            .collect()
+    }
+
+    pub(crate) fn analyze(&mut self, text: &str) -> SourceAnalysis {
+        let mut analysis = SourceAnalysis::default();
+        let mut op_dir = self.op_dir.clone();
+        let mut pos = SourcePos { line: 0, col: 0 };
+
+        loop {
+            match self.read_clauses(text, pos, &mut op_dir, &mut analysis) {
+                Some(Interruption::Library(library, next_pos)) => {
+                    self.import_library(&library, &mut op_dir, &mut analysis);
+                    pos = next_pos;
+                }
+                Some(Interruption::Resync(next_pos)) => {
+                    pos = next_pos;
+                }
+                None => break,
+            }
+        }
+
+        analysis
+    }
+
+    // reads and analyzes the clauses of text from pos until its end,
+    // or until the reading is interrupted.
+    fn read_clauses(
+        &mut self,
+        text: &str,
+        pos: SourcePos,
+        op_dir: &mut OpDir,
+        analysis: &mut SourceAnalysis,
+    ) -> Option<Interruption> {
+        let rest = text[byte_offset(text, pos)..].to_owned();
+        let mut lexer = Lexer::new(
+            CharReader::new(Cursor::new(rest)),
+            &mut self.machine.machine_st,
+        );
+
+        lexer.line_num = pos.line;
+        lexer.col_num = pos.col;
+
+        loop {
+            let (tokens, clause_tokens) = match read_clause_tokens(&mut lexer) {
+                Ok(Some(tokens)) => tokens,
+                Ok(None) => return None,
+                Err(err) => {
+                    let at = err
+                        .line_and_col_num()
+                        .map(|(line, col)| SourcePos { line, col })
+                        .unwrap_or(SourcePos {
+                            line: lexer.line_num,
+                            col: lexer.col_num,
+                        });
+
+                    analysis.diagnostics.push(SourceDiagnostic {
+                        severity: Severity::Error,
+                        start: at,
+                        end: at,
+                        message: format!("syntax error: {}", err.as_atom().as_str()),
+                    });
+
+                    return next_clause_start(text, at)
+                        .filter(|next_pos| *next_pos > pos)
+                        .map(Interruption::Resync);
+                }
+            };
+
+            let start = clause_tokens[0].start;
+            let end = clause_tokens[clause_tokens.len() - 1].end;
+            let next_pos = SourcePos {
+                line: lexer.line_num,
+                col: lexer.col_num,
+            };
+
+            let mut parser = Parser::from_lexer(lexer);
+            let term =
+                parser.read_term(&CompositeOpDir::new(op_dir, None), Tokens::Provided(tokens));
+            lexer = parser.lexer;
+
+            let library = match term {
+                Ok(term) => {
+                    let mut sites = ClauseSites {
+                        tokens: &clause_tokens,
+                        cursor: 0,
+                    };
+
+                    analyze_clause(term, &mut sites, op_dir, analysis)
+                }
+                Err(err) => {
+                    analysis.diagnostics.push(SourceDiagnostic {
+                        severity: Severity::Error,
+                        start,
+                        end,
+                        message: format!("syntax error: {}", err.as_atom().as_str()),
+                    });
+
+                    None
+                }
+            };
+
+            report_singletons(&clause_tokens, analysis);
+            analysis.tokens.extend(clause_tokens);
+
+            if let Some(library) = library {
+                if libraries::contains(&library) {
+                    return Some(Interruption::Library(library, next_pos));
+                }
+
+                analysis.diagnostics.push(SourceDiagnostic {
+                    severity: Severity::Error,
+                    start,
+                    end,
+                    message: format!("unknown library: {library}"),
+                });
+            }
+        }
+    }
+
+    // loads library into the machine and adds its exports to the
+    // operators and predicates of the source.
+    fn import_library(&mut self, library: &str, op_dir: &mut OpDir, analysis: &mut SourceAnalysis) {
+        let module_name = library.rsplit('/').next().unwrap_or(library);
+        let module_name = AtomTable::build_with(&self.machine.machine_st.atom_tbl, module_name);
+
+        if !self.machine.indices.modules.contains_key(&module_name) {
+            self.machine
+                .run_query(format!("use_module(library({library})).").as_str())
+                .next();
+        }
+
+        let Some(module) = self.machine.indices.modules.get(&module_name) else {
+            return;
+        };
+
+        for export in &module.module_decl.exports {
+            match export {
+                ModuleExport::OpDecl(op_decl) => {
+                    op_decl.insert_into_op_dir(op_dir);
+                }
+                ModuleExport::PredicateKey(key) => {
+                    analysis.imported.push(*key);
+                }
+            }
+        }
+    }
+}
+
+// the tokens of the next clause, reversed for the parser, and their
+// positions, or None at the end of the source.
+#[allow(clippy::type_complexity)]
+fn read_clause_tokens<R: CharRead>(
+    lexer: &mut Lexer<'_, R>,
+) -> Result<Option<(Vec<Token>, Vec<SourceToken>)>, ParserError> {
+    let mut tokens = vec![];
+    let mut source_tokens = vec![];
+
+    loop {
+        let token = lexer.scan_for_layout().and_then(|layout_inserted| {
+            let start = SourcePos {
+                line: lexer.line_num,
+                col: lexer.col_num,
+            };
+
+            // next_token sees no layout before the token once it
+            // is skipped here.
+            match lexer.next_token()? {
+                Token::OpenCT if layout_inserted => Ok((Token::Open, start)),
+                token => Ok((token, start)),
+            }
+        });
+
+        let (token, start) = match token {
+            Ok(token) => token,
+            Err(err) if err.is_unexpected_eof() && tokens.is_empty() => return Ok(None),
+            Err(err) if err.is_unexpected_eof() => {
+                return Err(ParserError::IncompleteReduction(
+                    lexer.line_num,
+                    lexer.col_num,
+                ));
+            }
+            Err(err) => return Err(err),
+        };
+
+        let kind = match &token {
+            Token::Literal(Literal::Atom(name)) => SourceTokenKind::Atom(name.as_str().to_string()),
+            Token::Var(name) => SourceTokenKind::Var(name.to_string()),
+            Token::OpenCT => SourceTokenKind::FunctorOpen,
+            Token::Open | Token::OpenList | Token::OpenCurly => SourceTokenKind::Open,
+            Token::Close | Token::CloseList | Token::CloseCurly => SourceTokenKind::Close,
+            Token::Comma => SourceTokenKind::Comma,
+            Token::End => SourceTokenKind::End,
+            _ => SourceTokenKind::Other,
+        };
+
+        let at_end = kind == SourceTokenKind::End;
+
+        source_tokens.push(SourceToken {
+            kind,
+            start,
+            end: SourcePos {
+                line: lexer.line_num,
+                col: lexer.col_num,
+            },
+        });
+        tokens.push(token);
+
+        if at_end {
+            tokens.reverse();
+            return Ok(Some((tokens, source_tokens)));
+        }
+    }
+}
+
+// finds the tokens of the predicates named in a clause, in the order
+// of the source.
+struct ClauseSites<'a> {
+    tokens: &'a [SourceToken],
+    cursor: usize,
+}
+
+impl ClauseSites<'_> {
+    // the first atom token from the cursor naming a predicate of
+    // name/arity, or else the first token of the clause.
+    fn site(&mut self, name: Atom, arity: usize) -> (SourcePos, SourcePos) {
+        let name = name.as_str();
+
+        for idx in self.cursor..self.tokens.len() {
+            let is_compound = matches!(
+                self.tokens.get(idx + 1),
+                Some(SourceToken {
+                    kind: SourceTokenKind::FunctorOpen,
+                    ..
+                })
+            );
+
+            match &self.tokens[idx].kind {
+                SourceTokenKind::Atom(atom) if **atom == *name && (arity > 0 || !is_compound) => {
+                    self.cursor = idx + 1;
+                    return (self.tokens[idx].start, self.tokens[idx].end);
+                }
+                _ => {}
+            }
+        }
+
+        (self.tokens[0].start, self.tokens[0].end)
+    }
+
+    fn push(
+        &mut self,
+        sites: &mut Vec<PredicateSite>,
+        name: Atom,
+        arity: usize,
+        extra_args: usize,
+    ) {
+        let (start, end) = self.site(name, arity);
+
+        sites.push(PredicateSite {
+            name: name.as_str().to_string(),
+            arity: arity + extra_args,
+            start,
+            end,
+        });
+    }
+}
+
+// analyzes a clause or a directive, giving the library it uses if
+// any.
+fn analyze_clause(
+    term: Term,
+    sites: &mut ClauseSites,
+    op_dir: &mut OpDir,
+    analysis: &mut SourceAnalysis,
+) -> Option<String> {
+    match term {
+        Term::Clause(_, atom!(":-"), mut terms) if terms.len() == 1 => {
+            return analyze_directive(terms.pop().unwrap(), sites, op_dir, analysis);
+        }
+        Term::Clause(_, atom!(":-"), terms) if terms.len() == 2 => {
+            analyze_head(&terms[0], 0, sites, analysis);
+            analyze_body(&terms[1], false, sites, analysis);
+        }
+        Term::Clause(_, atom!("-->"), terms) if terms.len() == 2 => {
+            let head = match &terms[0] {
+                Term::Clause(_, atom!(","), pushback) if pushback.len() == 2 => &pushback[0],
+                head => head,
+            };
+
+            analyze_head(head, 2, sites, analysis);
+            analyze_body(&terms[1], true, sites, analysis);
+        }
+        head => analyze_head(&head, 0, sites, analysis),
+    }
+
+    None
+}
+
+fn analyze_head(
+    head: &Term,
+    extra_args: usize,
+    sites: &mut ClauseSites,
+    analysis: &mut SourceAnalysis,
+) {
+    match head {
+        Term::Literal(_, Literal::Atom(name)) => {
+            sites.push(&mut analysis.definitions, *name, 0, extra_args);
+        }
+        Term::Clause(_, name, terms) if *name != atom!(":") => {
+            sites.push(&mut analysis.definitions, *name, terms.len(), extra_args);
+        }
+        _ => {}
+    }
+}
+
+// records the goals of a clause body, or of a grammar rule body if
+// is_dcg.
+fn analyze_body(body: &Term, is_dcg: bool, sites: &mut ClauseSites, analysis: &mut SourceAnalysis) {
+    let extra_args = if is_dcg { 2 } else { 0 };
+
+    match body {
+        Term::Clause(_, name, terms)
+            if terms.len() == 2
+                && matches!(
+                    *name,
+                    atom!(",") | atom!(";") | atom!("->") | atom!("*->") | atom!("|")
+                ) =>
+        {
+            analyze_body(&terms[0], is_dcg, sites, analysis);
+            analyze_body(&terms[1], is_dcg, sites, analysis);
+        }
+        Term::Clause(_, atom!("\\+"), terms) if terms.len() == 1 => {
+            analyze_body(&terms[0], is_dcg, sites, analysis);
+        }
+        Term::Clause(_, atom!(":"), terms) if terms.len() == 2 => {}
+        Term::Clause(_, atom!("{}"), terms) if is_dcg && terms.len() == 1 => {
+            analyze_body(&terms[0], false, sites, analysis);
+        }
+        Term::Clause(_, atom!("call"), _) if is_dcg => {}
+        Term::Clause(_, name, terms) => {
+            sites.push(&mut analysis.goals, *name, terms.len(), extra_args);
+
+            for goal in meta_arguments(*name, terms) {
+                analyze_body(goal, false, sites, analysis);
+            }
+        }
+        Term::Literal(_, Literal::Atom(atom!("[]"))) if is_dcg => {}
+        Term::Literal(_, Literal::Atom(name)) => {
+            sites.push(&mut analysis.goals, *name, 0, extra_args);
+        }
+        _ => {}
+    }
+}
+
+// the arguments of the control predicates that are goals themselves.
+fn meta_arguments(name: Atom, terms: &[Term]) -> Vec<&Term> {
+    fn strip_carets(mut goal: &Term) -> &Term {
+        while let Term::Clause(_, atom!("^"), terms) = goal {
+            if terms.len() != 2 {
+                break;
+            }
+
+            goal = &terms[1];
+        }
+
+        goal
+    }
+
+    match (name, terms.len()) {
+        (atom!("call") | atom!("once") | atom!("ignore"), 1) => vec![&terms[0]],
+        (atom!("forall"), 2) => vec![&terms[0], &terms[1]],
+        (atom!("catch"), 3) => vec![&terms[0], &terms[2]],
+        (atom!("findall"), 3 | 4) => vec![&terms[1]],
+        (atom!("bagof") | atom!("setof"), 3) => vec![strip_carets(&terms[1])],
+        _ => vec![],
+    }
+}
+
+fn analyze_directive(
+    directive: Term,
+    sites: &mut ClauseSites,
+    op_dir: &mut OpDir,
+    analysis: &mut SourceAnalysis,
+) -> Option<String> {
+    let Term::Clause(_, name, mut terms) = directive else {
+        if let Term::Cons(..) = directive {
+            analysis
+                .loaded_files
+                .extend(list_items(&directive).filter_map(path_text));
+        }
+
+        return None;
+    };
+
+    match (name, terms.len()) {
+        (atom!("op"), 3) => {
+            let names = terms.pop().unwrap();
+
+            for name in list_items(&names) {
+                let op_terms = vec![terms[0].clone(), terms[1].clone(), name.clone()];
+
+                if let Ok(op_decl) = setup_op_decl(op_terms) {
+                    op_decl.insert_into_op_dir(op_dir);
+                }
+            }
+        }
+        (atom!("module"), 2) => {
+            let exports = setup_module_export_list(terms.pop().unwrap()).unwrap_or_default();
+
+            for export in exports {
+                if let ModuleExport::OpDecl(op_decl) = export {
+                    op_decl.insert_into_op_dir(op_dir);
+                }
+            }
+        }
+        (atom!("use_module") | atom!("reexport"), 1 | 2)
+        | (atom!("ensure_loaded") | atom!("consult"), 1) => {
+            return match &terms[0] {
+                Term::Clause(_, atom!("library"), path) if path.len() == 1 => path_text(&path[0]),
+                file => {
+                    analysis.loaded_files.extend(path_text(file));
+                    None
+                }
+            };
+        }
+        (atom!("include"), 1) => {
+            analysis.loaded_files.extend(path_text(&terms[0]));
+        }
+        (atom!("dynamic") | atom!("discontiguous") | atom!("multifile"), 1) => {
+            analysis
+                .declared
+                .extend(list_items(&terms[0]).filter_map(predicate_indicator));
+        }
+        (atom!("initialization"), 1 | 2) => {
+            sites.cursor = 1;
+            analyze_body(&terms[0], false, sites, analysis);
+        }
+        _ => {}
+    }
+
+    None
+}
+
+// the items of a list or a conjunction, or else the term itself.
+fn list_items(term: &Term) -> impl Iterator<Item = &Term> {
+    let mut rest = Some(term);
+
+    std::iter::from_fn(move || match rest? {
+        Term::Cons(_, head, tail) => {
+            rest = Some(tail);
+            Some(&**head)
+        }
+        Term::Clause(_, atom!(","), terms) if terms.len() == 2 => {
+            rest = Some(&terms[1]);
+            Some(&terms[0])
+        }
+        Term::Literal(_, Literal::Atom(atom!("[]"))) => None,
+        item => {
+            rest = None;
+            Some(item)
+        }
+    })
+}
+
+fn predicate_indicator(term: &Term) -> Option<PredicateKey> {
+    let Term::Clause(_, slash, terms) = term else {
+        return None;
+    };
+
+    let extra_args = match *slash {
+        atom!("/") => 0,
+        atom!("//") => 2,
+        _ => return None,
+    };
+
+    match (terms.first(), terms.get(1)) {
+        (
+            Some(Term::Literal(_, Literal::Atom(name))),
+            Some(Term::Literal(_, Literal::Fixnum(n))),
+        ) => usize::try_from(n.get_num())
+            .ok()
+            .map(|arity| (*name, arity + extra_args)),
+        _ => None,
+    }
+}
+
+// the text of a file or library path such as foo/bar.
+fn path_text(term: &Term) -> Option<String> {
+    match term {
+        Term::Literal(_, Literal::Atom(name)) => Some(name.as_str().to_string()),
+        Term::CompleteString(_, string) | Term::PartialString(_, string, _) => {
+            Some(string.to_string())
+        }
+        Term::Clause(_, atom!("/"), terms) if terms.len() == 2 => Some(format!(
+            "{}/{}",
+            path_text(&terms[0])?,
+            path_text(&terms[1])?
+        )),
+        _ => None,
+    }
+}
+
+fn report_singletons(tokens: &[SourceToken], analysis: &mut SourceAnalysis) {
+    let var_names = tokens.iter().filter_map(|token| match &token.kind {
+        SourceTokenKind::Var(name) => Some((name, token)),
+        _ => None,
+    });
+
+    for (name, token) in var_names.clone() {
+        if name.starts_with('_')
+            || var_names
+                .clone()
+                .filter(|(other, _)| *other == name)
+                .count()
+                > 1
+        {
+            continue;
+        }
+
+        analysis.diagnostics.push(SourceDiagnostic {
+            severity: Severity::Warning,
+            start: token.start,
+            end: token.end,
+            message: format!("singleton variable {name}"),
+        });
+    }
+}
+
+fn byte_offset(text: &str, pos: SourcePos) -> usize {
+    let mut offset = 0;
+
+    for (line_num, line) in text.split_inclusive('\n').enumerate() {
+        if line_num == pos.line {
+            return offset
+                + line
+                    .char_indices()
+                    .nth(pos.col)
+                    .map_or(line.len(), |(idx, _)| idx);
+        }
+
+        offset += line.len();
+    }
+
+    text.len()
+}
+
+// the position just past the end token following pos, if any.
+fn next_clause_start(text: &str, pos: SourcePos) -> Option<SourcePos> {
+    let offset = byte_offset(text, pos);
+    let rest = &text[offset..];
+
+    let end = rest.char_indices().find_map(|(idx, c)| {
+        let is_end = c == '.'
+            && rest[idx + 1..]
+                .chars()
+                .next()
+                .is_none_or(|c| c.is_whitespace() || c == '%');
+
+        is_end.then_some(offset + idx + 1)
+    })?;
+
+    let line = text[..end].matches('\n').count();
+    let line_start = text[..end].rfind('\n').map_or(0, |idx| idx + 1);
+
+    Some(SourcePos {
+        line,
+        col: text[line_start..end].chars().count(),
+    })
+}
@@ -6,10 +6,12 @@
 use crate::read::*;
 
 #[cfg(feature = "http")]
-use crate::http::HttpResponse;
+use crate::http::{self, HttpResponse};
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::machine_state::*;
+use crate::machine::message_queues::*;
+use crate::machine::{self, threads};
 use crate::types::*;
 
 pub use scryer_modular_bitfield::prelude::*;
@@ -33,10 +35,15 @@
 use std::ptr;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
 
 #[cfg(feature = "tls")]
 use native_tls::TlsStream;
 
+#[cfg(feature = "http")]
+use std::time::Duration;
+#[cfg(feature = "http")]
+use tokio::runtime::Handle;
 #[cfg(feature = "http")]
 use warp::hyper;
 
@@ -280,6 +287,46 @@ fn flush(&mut self) -> std::io::Result<()> {
 pub struct HttpReadStream {
     url: Atom,
     body_reader: BufReader<Bytes>,
+    // the response of http_open/3, whose body is read a chunk at a
+    // time as body_reader runs out. None once the body is read.
+    response: Option<reqwest::Response>,
+    timeout: Option<Duration>,
+}
+
+#[cfg(feature = "http")]
+impl HttpReadStream {
+    // reads the next chunk of the response body into body_reader,
+    // false if there are no more.
+    fn read_chunk(&mut self) -> std::io::Result<bool> {
+        let Some(response) = self.response.as_mut() else {
+            return Ok(false);
+        };
+
+        let timeout = self.timeout;
+
+        let chunk = tokio::task::block_in_place(|| {
+            Handle::current().block_on(async {
+                match timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, response.chunk())
+                        .await
+                        .map_err(|_| std::io::Error::from(ErrorKind::TimedOut))?,
+                    None => response.chunk().await,
+                }
+                .map_err(std::io::Error::other)
+            })
+        })?;
+
+        match chunk {
+            Some(chunk) => {
+                self.body_reader = chunk.reader();
+                Ok(true)
+            }
+            None => {
+                self.response = None;
+                Ok(false)
+            }
+        }
+    }
 }
 
 #[cfg(feature = "http")]
@@ -293,6 +340,12 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 impl Read for HttpReadStream {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while !self.body_reader.get_ref().has_remaining() {
+            if !self.read_chunk()? {
+                return Ok(0);
+            }
+        }
+
         self.body_reader.read(buf)
     }
 }
@@ -303,6 +356,28 @@ pub struct HttpWriteStream {
     headers: std::mem::ManuallyDrop<hyper::HeaderMap>,
     response: TypedArenaPtr<HttpResponse>,
     buffer: std::mem::ManuallyDrop<Vec<u8>>,
+    // the sender of the body of a chunked response, which is answered
+    // as soon as the stream is opened. None if the body is sent whole
+    // once the stream is closed.
+    body_sender: Option<hyper::body::Sender>,
+}
+
+// the size at which the buffer of a chunked response is sent without
+// waiting for the stream to be flushed.
+#[cfg(feature = "http")]
+const HTTP_CHUNK_SIZE: usize = 8192;
+
+#[cfg(feature = "http")]
+fn answer_http_response(
+    response: &HttpResponse,
+    status_code: u16,
+    headers: hyper::HeaderMap,
+    body: hyper::Body,
+) {
+    let mut reply = warp::http::Response::builder().status(status_code);
+    *reply.headers_mut().unwrap() = headers;
+
+    http::answer(response, reply.body(body).unwrap());
 }
 
 #[cfg(feature = "http")]
@@ -317,36 +392,54 @@ impl Write for HttpWriteStream {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.buffer.extend_from_slice(buf);
+
+        if self.body_sender.is_some() && self.buffer.len() >= HTTP_CHUNK_SIZE {
+            self.flush()?;
+        }
+
         Ok(buf.len())
     }
 
+    // sends the buffer as the next chunk of a chunked response.
     #[inline]
     fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+        let Some(body_sender) = self.body_sender.as_mut() else {
+            return Ok(());
+        };
+
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = Bytes::from(std::mem::take(&mut *self.buffer));
+
+        tokio::task::block_in_place(|| Handle::current().block_on(body_sender.send_data(chunk)))
+            .map_err(|_| std::io::Error::from(ErrorKind::BrokenPipe))
     }
 }
 
+// sends the response, or the rest of a chunked response, when the
+// stream is closed.
 #[cfg(feature = "http")]
-impl HttpWriteStream {
-    // TODO why is this suddenly dead code and should it be used somewhere?
-    // Should this be impl Drop for HttpWriteStream?
-    #[allow(dead_code)]
+impl Drop for HttpWriteStream {
     fn drop(&mut self) {
+        if self.body_sender.is_some() {
+            // the client may be gone, which the handler can't be
+            // told of anymore.
+            let _ = self.flush();
+        }
+
         let headers = unsafe { std::mem::ManuallyDrop::take(&mut self.headers) };
         let buffer = unsafe { std::mem::ManuallyDrop::take(&mut self.buffer) };
 
-        let (ready, response, cvar) = &**self.response;
-
-        let mut ready = ready.lock().unwrap();
-        {
-            let mut response = response.lock().unwrap();
-
-            let mut response_ = warp::http::Response::builder().status(self.status_code);
-            *response_.headers_mut().unwrap() = headers;
-            *response = Some(response_.body(warp::hyper::Body::from(buffer)).unwrap());
+        if self.body_sender.is_none() {
+            answer_http_response(
+                &self.response,
+                self.status_code,
+                headers,
+                warp::hyper::Body::from(buffer),
+            );
         }
-        *ready = true;
-        cvar.notify_one();
     }
 }
 
@@ -419,11 +512,60 @@ pub struct InputChannelStream {
     pub(crate) inner: Cursor<Vec<u8>>,
     pub eof: bool,
     channel: Receiver<Vec<u8>>,
+    // set for streams the host feeds while Prolog reads them, where
+    // no input being pending doesn't mean the stream has ended.
+    awaits_input: bool,
+    // set when a read of such a stream returned without input, which
+    // the read is then to report instead of the end of the stream.
+    pub(crate) unavailable: bool,
+}
+
+impl InputChannelStream {
+    // waits for the host to send input, which ends the wait with
+    // Ok(true). the wait also ends once the host drops its end of the
+    // channel, with Ok(false). natively it can be interrupted, and on
+    // wasm32, where nothing can be sent while Prolog runs, it doesn't
+    // wait at all.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn await_input(&mut self) -> std::io::Result<bool> {
+        use std::sync::mpsc::RecvTimeoutError;
+        use std::time::Duration;
+
+        let interrupted = || {
+            machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed)
+                || threads::cancel_pending()
+        };
+
+        loop {
+            match self.channel.recv_timeout(Duration::from_millis(100)) {
+                Ok(data) => {
+                    self.inner = Cursor::new(data);
+                    return Ok(true);
+                }
+                Err(RecvTimeoutError::Timeout) if interrupted() => {
+                    // not ErrorKind::Interrupted, which readers such
+                    // as read_to_end retry until the input arrives.
+                    self.unavailable = true;
+                    return Err(std::io::Error::from(ErrorKind::WouldBlock));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(false),
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn await_input(&mut self) -> std::io::Result<bool> {
+        self.unavailable = true;
+        Err(std::io::Error::from(ErrorKind::WouldBlock))
+    }
 }
 
 impl Read for InputChannelStream {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.unavailable = false;
+
         if self.eof {
             return Ok(0);
         }
@@ -438,11 +580,19 @@ fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
                 // We need to get more data to read
                 match self.channel.try_recv() {
                     Ok(data) => {
-                        // Append into self.inner
+                        // Replace the data read so far in self.inner,
+                        // so that it only holds what is unread
                         let pos = self.inner.position();
                         assert_eq!(pos as usize, self.inner.get_ref().len());
-                        self.inner.write_all(&data)?;
-                        self.inner.seek(SeekFrom::Start(pos))?;
+                        self.inner.get_mut().clear();
+                        self.inner.get_mut().extend_from_slice(&data);
+                        self.inner.set_position(0);
+                    }
+                    Err(TryRecvError::Empty) if self.awaits_input && total_read == 0 => {
+                        if !self.await_input()? {
+                            self.eof = true;
+                            break;
+                        }
                     }
                     Err(TryRecvError::Empty) => {
                         // Data is pending
@@ -463,6 +613,48 @@ fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
     }
 }
 
+// reads the messages of a message queue as Prolog text, one clause
+// per message. reading blocks until a message arrives, and the
+// stream is at its end once the queue is destroyed and empty.
+#[derive(Debug)]
+pub struct MessageQueueStream {
+    queue: Arc<MessageQueue>,
+    pending: Cursor<Vec<u8>>,
+    pub eof: bool,
+}
+
+impl Read for MessageQueueStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.eof {
+            return Ok(0);
+        }
+
+        if self.pending.position() as usize == self.pending.get_ref().len() {
+            let interrupted = || {
+                machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed)
+                    || threads::cancel_pending()
+            };
+
+            match self.queue.take(interrupted) {
+                TakeOutcome::Taken(message) => {
+                    let mut bytes = message.into_bytes();
+                    bytes.extend_from_slice(b" .\n");
+                    self.pending = Cursor::new(bytes);
+                }
+                TakeOutcome::Destroyed => {
+                    self.eof = true;
+                    return Ok(0);
+                }
+                TakeOutcome::Interrupted => {
+                    return Err(std::io::Error::from(ErrorKind::Interrupted))
+                }
+            }
+        }
+
+        self.pending.read(buf)
+    }
+}
+
 #[bitfield]
 #[repr(u64)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -590,6 +782,7 @@ unsafe fn dealloc(ptr: std::ptr::NonNull<TypedAllocSlab<Self>>) {
 arena_allocated_impl_for_stream!(StandardErrorStream, StandardErrorStream);
 arena_allocated_impl_for_stream!(CharReader<CallbackStream>, CallbackStream);
 arena_allocated_impl_for_stream!(CharReader<InputChannelStream>, InputChannelStream);
+arena_allocated_impl_for_stream!(CharReader<MessageQueueStream>, MessageQueueStream);
 arena_allocated_impl_for_stream!(CharReader<PipeReader>, PipeReader);
 arena_allocated_impl_for_stream!(CharReader<PipeWriter>, PipeWriter);
 
@@ -612,6 +805,7 @@ pub enum Stream {
     StandardError(TypedArenaPtr<StandardErrorStream>),
     Callback(TypedArenaPtr<CallbackStream>),
     InputChannel(TypedArenaPtr<InputChannelStream>),
+    MessageQueue(TypedArenaPtr<MessageQueueStream>),
     PipeReader(TypedArenaPtr<PipeReader>),
     PipeWriter(TypedArenaPtr<PipeWriter>),
 }
@@ -626,10 +820,13 @@ fn from(stream: TypedArenaPtr<ReadlineStream>) -> Stream {
 impl Stream {
     #[inline]
     pub fn from_owned_string(string: String, arena: &mut Arena) -> Stream {
+        Stream::from_owned_bytes(string.into_bytes(), arena)
+    }
+
+    #[inline]
+    pub fn from_owned_bytes(bytes: Vec<u8>, arena: &mut Arena) -> Stream {
         Stream::Byte(arena_alloc!(
-            StreamLayout::new(CharReader::new(ByteStream(Cursor::new(
-                string.into_bytes()
-            )))),
+            StreamLayout::new(CharReader::new(ByteStream(Cursor::new(bytes)))),
             arena
         ))
     }
@@ -646,21 +843,36 @@ pub fn from_static_string(src: &'static str, arena: &mut Arena) -> Stream {
 
     #[inline]
     pub fn input_channel(channel: Receiver<Vec<u8>>, arena: &mut Arena) -> Stream {
+        Self::channel_stream(channel, false, arena)
+    }
+
+    // an input channel the host feeds while Prolog reads it. a read
+    // finding no input pending waits for it natively, and on wasm32
+    // is reported by Stream::input_unavailable, so that the stream
+    // only ends once the host drops its end of the channel.
+    #[inline]
+    pub fn awaited_input_channel(channel: Receiver<Vec<u8>>, arena: &mut Arena) -> Stream {
+        Self::channel_stream(channel, true, arena)
+    }
+
+    fn channel_stream(channel: Receiver<Vec<u8>>, awaits_input: bool, arena: &mut Arena) -> Stream {
         let inner = Cursor::new(Vec::new());
         Stream::InputChannel(arena_alloc!(
             StreamLayout::new(CharReader::new(InputChannelStream {
                 inner,
                 eof: false,
-                channel
+                channel,
+                awaits_input,
+                unavailable: false,
             })),
             arena
         ))
     }
 
     #[inline]
-    pub fn stdin(arena: &mut Arena, add_history: bool) -> Stream {
+    pub fn stdin(arena: &mut Arena, add_history: bool, output: Stream) -> Stream {
         Stream::Readline(arena_alloc!(
-            StreamLayout::new(ReadlineStream::new("", add_history)),
+            StreamLayout::new(ReadlineStream::new("", add_history, output)),
             arena
         ))
     }
@@ -694,6 +906,9 @@ pub fn from_tag(tag: ArenaHeaderTag, ptr: UntypedArenaPtr) -> Self {
             ArenaHeaderTag::InputChannelStream => {
                 Stream::InputChannel(unsafe { ptr.as_typed_ptr() })
             }
+            ArenaHeaderTag::MessageQueueStream => {
+                Stream::MessageQueue(unsafe { ptr.as_typed_ptr() })
+            }
             ArenaHeaderTag::PipeReader => Stream::PipeReader(unsafe { ptr.as_typed_ptr() }),
             ArenaHeaderTag::PipeWriter => Stream::PipeWriter(unsafe { ptr.as_typed_ptr() }),
             _ => unreachable!(),
@@ -734,6 +949,7 @@ pub fn as_ptr(&self) -> *const ArenaHeader {
             Stream::StandardError(ptr) => ptr.header_ptr(),
             Stream::Callback(ptr) => ptr.header_ptr(),
             Stream::InputChannel(ptr) => ptr.header_ptr(),
+            Stream::MessageQueue(ptr) => ptr.header_ptr(),
             Stream::PipeReader(ptr) => ptr.header_ptr(),
             Stream::PipeWriter(ptr) => ptr.header_ptr(),
         }
@@ -758,6 +974,7 @@ pub fn options(&self) -> &StreamOptions {
             Stream::StandardError(ref ptr) => &ptr.options,
             Stream::Callback(ref ptr) => &ptr.options,
             Stream::InputChannel(ref ptr) => &ptr.options,
+            Stream::MessageQueue(ref ptr) => &ptr.options,
             Stream::PipeReader(ref ptr) => &ptr.options,
             Stream::PipeWriter(ref ptr) => &ptr.options,
         }
@@ -782,6 +999,7 @@ pub(super) fn options_mut(&mut self) -> &mut StreamOptions {
             Stream::StandardError(ref mut ptr) => &mut ptr.options,
             Stream::Callback(ref mut ptr) => &mut ptr.options,
             Stream::InputChannel(ref mut ptr) => &mut ptr.options,
+            Stream::MessageQueue(ref mut ptr) => &mut ptr.options,
             Stream::PipeReader(ref mut ptr) => &mut ptr.options,
             Stream::PipeWriter(ref mut ptr) => &mut ptr.options,
         }
@@ -807,6 +1025,7 @@ pub(crate) fn add_lines_read(&mut self, incr_num_lines_read: usize) {
             Stream::StandardError(ptr) => ptr.lines_read += incr_num_lines_read,
             Stream::Callback(ptr) => ptr.lines_read += incr_num_lines_read,
             Stream::InputChannel(ptr) => ptr.lines_read += incr_num_lines_read,
+            Stream::MessageQueue(ptr) => ptr.lines_read += incr_num_lines_read,
             Stream::PipeReader(ptr) => ptr.lines_read += incr_num_lines_read,
             Stream::PipeWriter(_) => {}
         }
@@ -832,6 +1051,7 @@ pub(crate) fn set_lines_read(&mut self, value: usize) {
             Stream::StandardError(ptr) => ptr.lines_read = value,
             Stream::Callback(ptr) => ptr.lines_read = value,
             Stream::InputChannel(ptr) => ptr.lines_read = value,
+            Stream::MessageQueue(ptr) => ptr.lines_read = value,
             Stream::PipeReader(ptr) => ptr.lines_read = value,
             Stream::PipeWriter(_) => {}
         }
@@ -857,6 +1077,7 @@ pub(crate) fn lines_read(&self) -> usize {
             Stream::StandardError(ptr) => ptr.lines_read,
             Stream::Callback(ptr) => ptr.lines_read,
             Stream::InputChannel(ptr) => ptr.lines_read,
+            Stream::MessageQueue(ptr) => ptr.lines_read,
             Stream::PipeReader(ptr) => ptr.lines_read,
             Stream::PipeWriter(_) => 0,
         }
@@ -876,6 +1097,7 @@ fn peek_char(&mut self) -> Option<std::io::Result<char>> {
             Stream::StaticString(src) => (*src).peek_char(),
             Stream::Byte(cursor) => (*cursor).peek_char(),
             Stream::InputChannel(cursor) => (*cursor).peek_char(),
+            Stream::MessageQueue(cursor) => (*cursor).peek_char(),
             Stream::PipeReader(cursor) => (*cursor).peek_char(),
 
             #[cfg(feature = "http")]
@@ -907,6 +1129,7 @@ fn read_char(&mut self) -> Option<std::io::Result<char>> {
             Stream::StaticString(src) => (*src).read_char(),
             Stream::Byte(cursor) => (*cursor).read_char(),
             Stream::InputChannel(cursor) => (*cursor).read_char(),
+            Stream::MessageQueue(cursor) => (*cursor).read_char(),
             Stream::PipeReader(cursor) => (*cursor).read_char(),
             #[cfg(feature = "http")]
             Stream::HttpWrite(_) => Some(Err(std::io::Error::new(
@@ -936,7 +1159,9 @@ fn put_back_char(&mut self, c: char) {
             Stream::Readline(rl_stream) => rl_stream.put_back_char(c),
             Stream::StaticString(src) => src.put_back_char(c),
             Stream::Byte(cursor) => cursor.put_back_char(c),
+            Stream::InputChannel(cursor) => cursor.put_back_char(c),
             Stream::PipeReader(cursor) => cursor.put_back_char(c),
+            Stream::MessageQueue(cursor) => cursor.put_back_char(c),
             #[cfg(feature = "http")]
             Stream::HttpWrite(_) => {}
             Stream::OutputFile(_)
@@ -945,7 +1170,6 @@ fn put_back_char(&mut self, c: char) {
             | Stream::Null(_)
             | Stream::Callback(_)
             | Stream::PipeWriter(_) => {}
-            Stream::InputChannel(_) => {}
         }
     }
 
@@ -961,6 +1185,7 @@ fn consume(&mut self, nread: usize) {
             Stream::StaticString(ref mut src) => src.consume(nread),
             Stream::Byte(ref mut cursor) => cursor.consume(nread),
             Stream::InputChannel(ref mut cursor) => cursor.consume(nread),
+            Stream::MessageQueue(ref mut cursor) => cursor.consume(nread),
             Stream::PipeReader(ref mut cursor) => cursor.consume(nread),
             #[cfg(feature = "http")]
             Stream::HttpWrite(_) => {}
@@ -988,6 +1213,7 @@ fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
             Stream::StaticString(src) => (*src).read(buf),
             Stream::Byte(cursor) => (*cursor).read(buf),
             Stream::InputChannel(cursor) => (*cursor).read(buf),
+            Stream::MessageQueue(cursor) => (*cursor).read(buf),
             Stream::PipeReader(cursor) => (*cursor).read(buf),
             #[cfg(feature = "http")]
             Stream::HttpWrite(_) => Err(std::io::Error::new(
@@ -1029,6 +1255,7 @@ fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
             Stream::Null(_) => Ok(buf.len()),
             Stream::StaticString(_)
             | Stream::InputChannel(_)
+            | Stream::MessageQueue(_)
             | Stream::Readline(_)
             | Stream::InputFile(..)
             | Stream::PipeReader(_) => Err(std::io::Error::new(
@@ -1059,6 +1286,7 @@ fn flush(&mut self) -> std::io::Result<()> {
             Stream::Null(_) => Ok(()),
             Stream::StaticString(_)
             | Stream::InputChannel(_)
+            | Stream::MessageQueue(_)
             | Stream::Readline(_)
             | Stream::InputFile(_)
             | Stream::PipeReader(_) => Err(std::io::Error::new(
@@ -1178,7 +1406,7 @@ pub(crate) fn position(&mut self) -> Option<(u64, usize)> {
             Stream::InputFile(file_stream) => file_stream.position(),
             #[cfg(feature = "tls")]
             Stream::NamedTls(..) => Some(0),
-            Stream::NamedTcp(..) | Stream::Readline(..) => Some(0),
+            Stream::NamedTcp(..) | Stream::Readline(..) | Stream::InputChannel(..) => Some(0),
             _ => None,
         };
 
@@ -1227,6 +1455,7 @@ pub(crate) fn past_end_of_stream(&self) -> bool {
             Stream::StandardError(stream) => stream.past_end_of_stream,
             Stream::Callback(stream) => stream.past_end_of_stream,
             Stream::InputChannel(stream) => stream.past_end_of_stream,
+            Stream::MessageQueue(stream) => stream.past_end_of_stream,
             Stream::PipeReader(stream) => stream.past_end_of_stream,
             Stream::PipeWriter(stream) => stream.past_end_of_stream,
         }
@@ -1237,6 +1466,19 @@ pub(crate) fn at_end_of_stream(&mut self) -> bool {
         self.position_relative_to_end() == AtEndOfStream::At
     }
 
+    // true if the last read of the stream returned without input
+    // that the host has yet to send, the stream not having ended.
+    // taking the report clears it.
+    #[inline]
+    pub(crate) fn input_unavailable(&mut self) -> bool {
+        match self {
+            Stream::InputChannel(stream) => {
+                std::mem::take(&mut stream.stream.get_mut().unavailable)
+            }
+            _ => false,
+        }
+    }
+
     #[inline]
     pub(crate) fn set_past_end_of_stream(&mut self, value: bool) {
         match self {
@@ -1257,6 +1499,7 @@ pub(crate) fn set_past_end_of_stream(&mut self, value: bool) {
             Stream::StandardError(stream) => stream.past_end_of_stream = value,
             Stream::Callback(stream) => stream.past_end_of_stream = value,
             Stream::InputChannel(stream) => stream.past_end_of_stream = value,
+            Stream::MessageQueue(stream) => stream.past_end_of_stream = value,
             Stream::PipeReader(stream) => stream.past_end_of_stream = value,
             Stream::PipeWriter(stream) => stream.past_end_of_stream = value,
         }
@@ -1342,6 +1585,13 @@ pub(crate) fn position_relative_to_end(&mut self) -> AtEndOfStream {
                     AtEndOfStream::Not
                 }
             }
+            Stream::MessageQueue(stream_layout) => {
+                if stream_layout.stream.get_ref().eof {
+                    AtEndOfStream::At
+                } else {
+                    AtEndOfStream::Not
+                }
+            }
             _ => AtEndOfStream::Not,
         }
     }
@@ -1367,6 +1617,7 @@ pub(crate) fn mode(&self) -> Atom {
             Stream::NamedTls(..) => atom!("read_append"),
             Stream::Byte(_)
             | Stream::InputChannel(_)
+            | Stream::MessageQueue(_)
             | Stream::Readline(_)
             | Stream::StaticString(_)
             | Stream::InputFile(..)
@@ -1420,6 +1671,17 @@ pub(crate) fn from_pipe_writer(writer: io::PipeWriter, arena: &mut Arena) -> Str
         ))
     }
 
+    pub(crate) fn from_message_queue(queue: Arc<MessageQueue>, arena: &mut Arena) -> Stream {
+        Stream::MessageQueue(arena_alloc!(
+            ManuallyDrop::new(StreamLayout::new(CharReader::new(MessageQueueStream {
+                queue,
+                pending: Cursor::new(Vec::new()),
+                eof: false,
+            }))),
+            arena
+        ))
+    }
+
     pub(crate) fn from_pipe_reader(reader: io::PipeReader, arena: &mut Arena) -> Stream {
         Stream::PipeReader(arena_alloc!(
             ManuallyDrop::new(StreamLayout::new(CharReader::new(reader))),
@@ -1467,7 +1729,28 @@ pub(crate) fn from_http_stream(
         Stream::HttpRead(arena_alloc!(
             StreamLayout::new(CharReader::new(HttpReadStream {
                 url,
-                body_reader: http_stream
+                body_reader: http_stream,
+                response: None,
+                timeout: None,
+            })),
+            arena
+        ))
+    }
+
+    #[cfg(feature = "http")]
+    #[inline]
+    pub(crate) fn from_http_response(
+        url: Atom,
+        response: reqwest::Response,
+        timeout: Option<Duration>,
+        arena: &mut Arena,
+    ) -> Self {
+        Stream::HttpRead(arena_alloc!(
+            StreamLayout::new(CharReader::new(HttpReadStream {
+                url,
+                body_reader: Bytes::new().reader(),
+                response: Some(response),
+                timeout,
             })),
             arena
         ))
@@ -1479,14 +1762,24 @@ pub(crate) fn from_http_sender(
         response: TypedArenaPtr<HttpResponse>,
         status_code: u16,
         headers: hyper::HeaderMap,
+        chunked: bool,
         arena: &mut Arena,
     ) -> Self {
+        let (headers, body_sender) = if chunked {
+            let (body_sender, body) = hyper::Body::channel();
+            answer_http_response(&response, status_code, headers, body);
+            (hyper::HeaderMap::new(), Some(body_sender))
+        } else {
+            (headers, None)
+        };
+
         Stream::HttpWrite(arena_alloc!(
             StreamLayout::new(CharReader::new(HttpWriteStream {
                 response,
                 status_code,
                 headers: std::mem::ManuallyDrop::new(headers),
                 buffer: std::mem::ManuallyDrop::new(Vec::new()),
+                body_sender,
             })),
             arena
         ))
@@ -1562,6 +1855,10 @@ pub(crate) fn close(&mut self) -> Result<(), std::io::Error> {
                 stream.drop_payload();
                 Ok(())
             }
+            Stream::MessageQueue(mut stream) => {
+                stream.drop_payload();
+                Ok(())
+            }
             Stream::StaticString(mut stream) => {
                 stream.drop_payload();
                 Ok(())
@@ -1600,6 +1897,7 @@ pub(crate) fn is_input_stream(&self) -> bool {
             Stream::NamedTcp(..)
             | Stream::Byte(_)
             | Stream::InputChannel(_)
+            | Stream::MessageQueue(_)
             | Stream::Readline(_)
             | Stream::StaticString(_)
             | Stream::InputFile(..)
@@ -1705,6 +2003,10 @@ pub(crate) fn eof_action(
         caller: Atom,
         arity: usize,
     ) -> CallResult {
+        if stream.input_unavailable() {
+            return self.input_unavailable_action(caller, arity);
+        }
+
         let eof_action = stream.options().eof_action();
 
         match eof_action {
@@ -1720,7 +2022,7 @@ pub(crate) fn eof_action(
                 };
 
                 stream.set_past_end_of_stream(true);
-                unify!(self, result, end_of_stream);
+                unify_fn!(*self, result, end_of_stream);
                 Ok(())
             }
             EOFAction::Reset => {
@@ -1734,6 +2036,24 @@ pub(crate) fn eof_action(
         }
     }
 
+    // a read found no input on a stream the host has yet to feed. on
+    // wasm32 this is thrown as resource_error(pending_input), leaving
+    // the stream to be read again once the host has sent input.
+    // natively, the read waited for input until it was interrupted,
+    // so it fails, and the interrupt (or the cancellation) is raised
+    // by the dispatch loop.
+    pub(crate) fn input_unavailable_action(&mut self, caller: Atom, arity: usize) -> CallResult {
+        if cfg!(target_arch = "wasm32") {
+            let stub = functor_stub(caller, arity);
+            let err = MachineState::resource_error(ResourceError::PendingInput);
+
+            Err(self.error_form(err, stub))
+        } else {
+            self.fail = true;
+            Ok(())
+        }
+    }
+
     /// ## Warning
     ///
     /// The options of streams stored in `Machine::indices` should only
@@ -1949,6 +2269,9 @@ pub(crate) fn open_parsing_stream(
     ) -> Result<Stream, ParserError> {
         match stream.peek_char() {
             None => Ok(stream), // empty stream is handled gracefully by Lexer::eof
+            // input the host has yet to send is reported by the read
+            // that follows.
+            Some(Err(_)) if stream.input_unavailable() => Ok(stream),
             Some(Err(e)) => Err(ParserError::IO(e)),
             Some(Ok(c)) => {
                 if c == '\u{feff}' {
@@ -2,8 +2,10 @@
 use dashu::integer::{Sign, UBig};
 use lazy_static::lazy_static;
 use num_order::NumOrd;
+use regex::Regex;
 
 use crate::arena::*;
+use crate::arithmetic::publish_float_flags;
 use crate::atom_table::*;
 #[cfg(feature = "ffi")]
 use crate::ffi::*;
@@ -12,18 +14,42 @@
 use crate::heap_iter::*;
 use crate::heap_print::*;
 #[cfg(feature = "http")]
-use crate::http::{HttpListener, HttpRequest, HttpRequestData, HttpResponse};
+use crate::http::{
+    offer_websocket_upgrade, take_websocket_upgrade, HttpListener, HttpRequest, HttpRequestData,
+    HttpResponse, WebSocket, WebSocketMessage,
+};
 use crate::instructions::*;
 use crate::machine;
+use crate::machine::array_formats::*;
+use crate::machine::bridge::*;
 use crate::machine::code_walker::*;
+use crate::machine::config_formats::*;
 use crate::machine::copier::*;
+use crate::machine::csv::*;
+use crate::machine::dynamic_clause_store::FirstArgKey;
+use crate::machine::engines::*;
+use crate::machine::fd_domains;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::machine::fswatch;
 use crate::machine::heap::*;
+use crate::machine::interval_arithmetic::Interval;
+use crate::machine::json::*;
+use crate::machine::lib_machine::{TraceAction, TraceEvent, TracePort};
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::machine_state::*;
+use crate::machine::message_queues::*;
+use crate::machine::mutexes::*;
 use crate::machine::partial_string::*;
+use crate::machine::profiler::*;
+use crate::machine::regex_cache::*;
+use crate::machine::resilience::*;
+use crate::machine::signals::{self, SignalAction};
 use crate::machine::stack::*;
 use crate::machine::streams::*;
+use crate::machine::templates;
+use crate::machine::threads;
+use crate::machine::tracer::*;
 use crate::machine::{get_structure_index, Machine, VERIFY_ATTR_INTERRUPT_LOC};
 use crate::offset_table::*;
 use crate::parser::ast::*;
@@ -33,40 +59,49 @@
 use crate::read::*;
 use crate::types::*;
 use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 
 use ordered_float::OrderedFloat;
 
-use fxhash::{FxBuildHasher, FxHasher};
-use indexmap::IndexSet;
+use fxhash::{FxBuildHasher, FxHasher, FxHasher64};
+use indexmap::{IndexMap, IndexSet};
 
 use std::cell::Cell;
 use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::env;
 #[cfg(feature = "ffi")]
 use std::ffi::CString;
 use std::fs;
-use std::hash::{BuildHasher, BuildHasherDefault};
+use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
 use std::io::{ErrorKind, Read, Write};
 use std::iter::{once, FromIterator};
 use std::mem;
 #[cfg(feature = "http")]
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::net::{TcpListener, TcpStream};
+use std::net::{TcpListener, TcpStream, UdpSocket};
 use std::num::NonZeroU32;
 use std::process;
 use std::process::Child;
 use std::process::Stdio;
 #[cfg(feature = "http")]
 use std::str::FromStr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::OnceLock;
 #[cfg(feature = "http")]
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
 
-use chrono::{offset::Local, DateTime};
+use chrono::format::{Item, Parsed, StrftimeItems};
+use chrono::{
+    offset::Local, DateTime, Datelike, FixedOffset, NaiveDate, NaiveTime, Offset, TimeZone,
+    Timelike,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use cpu_time::ProcessTime;
 use std::time::{Duration, SystemTime};
+use web_time::Instant;
 
 #[cfg(feature = "repl")]
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
@@ -85,7 +120,7 @@
 use crrl::{ed25519, secp256k1, x25519};
 
 #[cfg(feature = "tls")]
-use native_tls::{Identity, TlsAcceptor, TlsConnector};
+use native_tls::{Certificate, Identity, TlsAcceptor, TlsConnector};
 
 use base64;
 use roxmltree;
@@ -97,6 +132,8 @@
 use tokio::runtime::Handle;
 use tokio::task;
 #[cfg(feature = "http")]
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+#[cfg(feature = "http")]
 use warp::hyper::header::{HeaderName, HeaderValue};
 #[cfg(feature = "http")]
 use warp::hyper::{HeaderMap, Method};
@@ -565,6 +602,43 @@ struct AttrListMatch {
     prev_tail: Option<usize>,
 }
 
+// the code of the '$clause'/2 clauses of a dynamic predicate.
+#[derive(Debug, Clone, Copy)]
+enum ClauseClauseCode<'a> {
+    // the predicate has a single clause, whose '$clause'/2 clause is
+    // at this location.
+    Single(usize),
+    // the indexed choice instructions of the '$clause'/2 clauses of
+    // the predicate in clause order, with offsets relative to the
+    // given location.
+    Indexed(usize, &'a VecDeque<IndexedChoiceInstruction>),
+}
+
+// a hasher feeding a cryptographic digest, whose first eight bytes
+// are the hash.
+#[derive(Clone)]
+struct DigestHasher<D: Digest + Clone>(D);
+
+// the terms JSON null, true and false are read into and written from,
+// and whether object keys are read as string(Chars), atoms or chars.
+struct JsonTermOptions {
+    null: HeapCellValue,
+    true_term: HeapCellValue,
+    false_term: HeapCellValue,
+    keys: Atom,
+}
+
+impl<D: Digest + Clone> Hasher for DigestHasher<D> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_be_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct FindallCopyInfo {
     offset: usize,
@@ -672,6 +746,92 @@ pub(crate) fn name_and_arity_from_heap(&self, cell: HeapCellValue) -> Option<Pre
         )
     }
 
+    // the key of the first argument of head, None if it's unbound or
+    // may unify with constants keyed otherwise, like big integers.
+    fn first_arg_key(&self, head: HeapCellValue) -> Option<FirstArgKey> {
+        let arg = match head.get_tag() {
+            HeapCellValueTag::Str => {
+                let s = head.get_value() as usize;
+                self.store(self.deref(self.heap[s + 1]))
+            }
+            _ => return None,
+        };
+
+        read_heap_cell!(arg,
+            (HeapCellValueTag::Atom, (_name, arity)) => {
+                (arity == 0).then_some(FirstArgKey::Constant(arg))
+            }
+            (HeapCellValueTag::Fixnum) => {
+                Some(FirstArgKey::Constant(arg))
+            }
+            (HeapCellValueTag::F64Offset) => {
+                self.switch_on_constant_key(arg).map(FirstArgKey::Constant)
+            }
+            (HeapCellValueTag::Lis | HeapCellValueTag::PStrLoc) => {
+                Some(FirstArgKey::List)
+            }
+            (HeapCellValueTag::Str, s) => {
+                match cell_as_atom_cell!(self.heap[s]).get_name_and_arity() {
+                    (atom!("."), 2) => Some(FirstArgKey::List),
+                    (_, 0) => None,
+                    (name, arity) => Some(FirstArgKey::Structure(name, arity)),
+                }
+            }
+            _ => {
+                None
+            }
+        )
+    }
+
+    // false if the argument of head at the index of key can't unify
+    // with the indexed argument of a clause with that key.
+    fn indexed_arg_may_unify(&self, head: HeapCellValue, key: &OptArgIndexKey) -> bool {
+        let arg = match (head.get_tag(), key.arg_num()) {
+            (HeapCellValueTag::Str, arg_num) if arg_num > 0 => {
+                let s = head.get_value() as usize;
+                self.store(self.deref(self.heap[s + arg_num]))
+            }
+            _ => return true,
+        };
+
+        // only atoms and fixnums are compared, other constants may
+        // be equal without being identical cells.
+        let literal_may_unify = |literal: Literal| match literal {
+            Literal::Atom(_) | Literal::Fixnum(_) => HeapCellValue::from(literal) == arg,
+            _ => true,
+        };
+
+        read_heap_cell!(arg,
+            (HeapCellValueTag::Str, s) => {
+                let (name, arity) = cell_as_atom_cell!(self.heap[s]).get_name_and_arity();
+
+                match *key {
+                    OptArgIndexKey::Structure(_, _, key_name, key_arity) => {
+                        (name, arity) == (key_name, key_arity)
+                    }
+                    OptArgIndexKey::List(..) => (name, arity) == (atom!("."), 2),
+                    OptArgIndexKey::Literal(..) => false,
+                    OptArgIndexKey::None => true,
+                }
+            }
+            (HeapCellValueTag::Lis | HeapCellValueTag::PStrLoc) => {
+                matches!(key, OptArgIndexKey::List(..) | OptArgIndexKey::None)
+            }
+            (HeapCellValueTag::Atom | HeapCellValueTag::Fixnum) => {
+                match *key {
+                    OptArgIndexKey::Literal(_, _, literal, overlapping) => {
+                        literal_may_unify(literal) || overlapping.is_some_and(literal_may_unify)
+                    }
+                    OptArgIndexKey::Structure(..) | OptArgIndexKey::List(..) => false,
+                    OptArgIndexKey::None => true,
+                }
+            }
+            _ => {
+                true
+            }
+        )
+    }
+
     #[inline]
     pub(crate) fn variable_set<S: BuildHasher>(
         &mut self,
@@ -752,7 +912,7 @@ fn step(heap: &Heap, mut value: HeapCellValue) -> usize {
         );
 
         if !self.fail {
-            unify!(self, self.registers[4], self.heap[prev_hare]);
+            unify_fn!(*self, self.registers[4], self.heap[prev_hare]);
         }
     }
 
@@ -765,7 +925,7 @@ fn finalize_skip_max_list(&mut self, n: i64, value: HeapCellValue) {
 
         if !self.fail {
             let xs = self.registers[4];
-            unify!(self, value, xs);
+            unify_fn!(*self, value, xs);
         }
     }
 
@@ -1215,6 +1375,150 @@ pub(crate) fn strip_module(
     }
 }
 
+// whether name matches the glob pattern, in which * matches any
+// sequence of characters, ? any single character and [...] any
+// character of a class such as [abc], [a-z] or its complement [!a-z].
+// A [ that isn't closed matches itself.
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // the positions after the last * and in name where it was tried.
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            Some('[') => match wildcard_class(&pattern[p + 1..], name[n]) {
+                Some((true, len)) => {
+                    p += len + 1;
+                    n += 1;
+                    continue;
+                }
+                None if name[n] == '[' => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                _ => {}
+            },
+            Some(&c) if c == name[n] => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        // the last * absorbs one more character.
+        match star {
+            Some((star_p, star_n)) => {
+                p = star_p;
+                n = star_n + 1;
+                star = Some((star_p, n));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// whether c is in the class at the start of class, just after its [,
+// and the length of the class up to and including its ], or None if
+// the class isn't closed.
+fn wildcard_class(class: &[char], c: char) -> Option<(bool, usize)> {
+    let negated = matches!(class.first(), Some('!' | '^'));
+    let start = if negated { 1 } else { 0 };
+
+    let mut matched = false;
+    let mut i = start;
+
+    while i < class.len() {
+        if class[i] == ']' && i > start {
+            return Some((matched != negated, i + 1));
+        }
+
+        if i + 2 < class.len() && class[i + 1] == '-' && class[i + 2] != ']' {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+
+    None
+}
+
+// the fields [Year, Month, Day, Hour, Minute, Second, Nanosecond,
+// Offset] of the date_time/7 terms of library(time), with Offset in
+// seconds east of UTC.
+fn date_time_fields<Tz: TimeZone>(date_time: &DateTime<Tz>) -> [i32; 8] {
+    [
+        date_time.year(),
+        date_time.month() as i32,
+        date_time.day() as i32,
+        date_time.hour() as i32,
+        date_time.minute() as i32,
+        date_time.second() as i32,
+        date_time.nanosecond() as i32,
+        date_time.offset().fix().local_minus_utc(),
+    ]
+}
+
+// the date and time of the fields of date_time_fields, or None if
+// they aren't those of a valid date and time.
+fn fields_date_time(fields: &[i64]) -> Option<DateTime<FixedOffset>> {
+    let &[year, month, day, hour, minute, second, nanosecond, offset] = fields else {
+        return None;
+    };
+
+    let date = NaiveDate::from_ymd_opt(
+        year.try_into().ok()?,
+        month.try_into().ok()?,
+        day.try_into().ok()?,
+    )?;
+    let time = NaiveTime::from_hms_nano_opt(
+        hour.try_into().ok()?,
+        minute.try_into().ok()?,
+        second.try_into().ok()?,
+        nanosecond.try_into().ok()?,
+    )?;
+
+    FixedOffset::east_opt(offset.try_into().ok()?)?
+        .from_local_datetime(&date.and_time(time))
+        .single()
+}
+
+// the date and time that text describes in the strftime format,
+// without an offset in UTC. dates without times are at midnight.
+fn parse_date_time(text: &str, format: &str) -> Option<DateTime<FixedOffset>> {
+    let mut parsed = Parsed::new();
+    chrono::format::parse(&mut parsed, text, StrftimeItems::new(format)).ok()?;
+
+    let offset = parsed.offset().unwrap_or(0);
+
+    let date_time = match parsed.to_naive_datetime_with_offset(offset) {
+        Ok(date_time) => date_time,
+        Err(_) if parsed.hour_mod_12().is_none() => {
+            parsed.to_naive_date().ok()?.and_time(NaiveTime::MIN)
+        }
+        Err(_) => return None,
+    };
+
+    FixedOffset::east_opt(offset)?
+        .from_local_datetime(&date_time)
+        .single()
+}
+
 impl Machine {
     #[inline(always)]
     pub(crate) fn delete_all_attributes_from_var(&mut self) {
@@ -1228,29 +1532,9 @@ pub(crate) fn delete_all_attributes_from_var(&mut self) {
         }
     }
 
-    #[inline(always)]
-    pub(crate) fn get_clause_p(&self, module_name: Atom) -> (usize, usize) {
-        use crate::machine::loader::CompilationTarget;
-
-        let key_cell = self.machine_st.registers[1];
-        let key = self.machine_st.name_and_arity_from_heap(key_cell).unwrap();
-
-        let compilation_target = if module_name == atom!("user") {
-            CompilationTarget::User
-        } else {
-            CompilationTarget::Module(module_name)
-        };
-
-        let skeleton = self
-            .indices
-            .get_predicate_skeleton(&compilation_target, &key)
-            .unwrap();
-
-        let module_name = match compilation_target {
-            CompilationTarget::User => atom!("builtins"),
-            CompilationTarget::Module(target) => target,
-        };
-
+    // the code of the '$clause'/2 clauses of the predicate key, which
+    // are in the module module_name.
+    fn clause_clause_code(&self, key: PredicateKey, module_name: Atom) -> ClauseClauseCode<'_> {
         let mut bp = self
             .indices
             .get_predicate_code_index(atom!("$clause"), 2, module_name)
@@ -1266,12 +1550,7 @@ pub(crate) fn get_clause_p(&self, module_name: Atom) -> (usize, usize) {
         macro_rules! extract_ptr {
             ($ptr: expr) => {
                 match $ptr {
-                    IndexingCodePtr::External(p) => {
-                        return (
-                            skeleton.core.clause_clause_locs.back().cloned().unwrap(),
-                            bp + p,
-                        )
-                    }
+                    IndexingCodePtr::External(p) => return ClauseClauseCode::Single(bp + p),
                     IndexingCodePtr::Internal(boip) => boip,
                     _ => unreachable!(),
                 }
@@ -1314,31 +1593,7 @@ macro_rules! extract_ptr {
 
                     match &indexing_code[boip] {
                         IndexingLine::IndexedChoice(indexed_choice) => {
-                            let p = if self.machine_st.b > self.machine_st.e {
-                                // this means the last
-                                // self.machine_st.iip value has yet
-                                // to be overwritten by the Trust
-                                // instruction. In this case, return
-                                // it.
-                                self.machine_st.iip as usize
-                            } else {
-                                // otherwise, read the '$clause'
-                                // choicepoint from the top of the
-                                // stack. this is very volatile in
-                                // that it depends on '$clause'
-                                // immediately preceding
-                                // '$get_clause_p', which cannot be
-                                // the last clause of the retract
-                                // helper to delay deallocation of its
-                                // environment frame.
-                                let clause_b = self.machine_st.stack.top();
-                                self.machine_st.stack.index_or_frame(clause_b).prelude.biip as usize
-                            };
-
-                            return (
-                                skeleton.core.clause_clause_locs[p],
-                                bp + indexed_choice[p].offset(),
-                            );
+                            return ClauseClauseCode::Indexed(bp, indexed_choice);
                         }
                         _ => unreachable!(),
                     }
@@ -1347,12 +1602,210 @@ macro_rules! extract_ptr {
                     bp -= offset;
                 }
                 _ => {
-                    return (
-                        skeleton.core.clause_clause_locs.back().cloned().unwrap(),
-                        bp,
-                    );
+                    return ClauseClauseCode::Single(bp);
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_clause_p(&self, module_name: Atom) -> (usize, usize) {
+        use crate::machine::loader::CompilationTarget;
+
+        let key_cell = self.machine_st.registers[1];
+        let key = self.machine_st.name_and_arity_from_heap(key_cell).unwrap();
+
+        let compilation_target = if module_name == atom!("user") {
+            CompilationTarget::User
+        } else {
+            CompilationTarget::Module(module_name)
+        };
+
+        let skeleton = self
+            .indices
+            .get_predicate_skeleton(&compilation_target, &key)
+            .unwrap();
+
+        let module_name = match compilation_target {
+            CompilationTarget::User => atom!("builtins"),
+            CompilationTarget::Module(target) => target,
+        };
+
+        match self.clause_clause_code(key, module_name) {
+            ClauseClauseCode::Single(p) => {
+                (skeleton.core.clause_clause_locs.back().cloned().unwrap(), p)
+            }
+            ClauseClauseCode::Indexed(bp, indexed_choice) => {
+                let p = if self.machine_st.b > self.machine_st.e {
+                    // this means the last self.machine_st.iip value
+                    // has yet to be overwritten by the Trust
+                    // instruction. In this case, return it.
+                    self.machine_st.iip as usize
+                } else {
+                    // otherwise, read the '$clause' choicepoint from
+                    // the top of the stack. this is very volatile in
+                    // that it depends on '$clause' immediately
+                    // preceding '$get_clause_p', which cannot be the
+                    // last clause of the retract helper to delay
+                    // deallocation of its environment frame.
+                    let clause_b = self.machine_st.stack.top();
+                    self.machine_st.stack.index_or_frame(clause_b).prelude.biip as usize
+                };
+
+                (
+                    skeleton.core.clause_clause_locs[p],
+                    bp + indexed_choice[p].offset(),
+                )
+            }
+        }
+    }
+
+    // unifies the third argument with the list of the L-P pairs
+    // '$get_clause_p'/3 would find for the clauses of the dynamic
+    // predicate of the head in the first argument, in clause order,
+    // leaving out the clauses whose indexed argument can't unify with
+    // that of the head. This spares the caller from backtracking into
+    // '$clause'/2, whose index only discriminates by predicate, over
+    // every clause of the predicate. Fails if no clause is left out.
+    pub(crate) fn clause_candidates(&mut self) -> CallResult {
+        use crate::machine::loader::CompilationTarget;
+
+        let head = self.deref_register(1);
+        let key = self.machine_st.name_and_arity_from_heap(head).unwrap();
+        let module_name = cell_as_atom!(self.deref_register(2));
+
+        let compilation_target = if module_name == atom!("user") {
+            CompilationTarget::User
+        } else {
+            CompilationTarget::Module(module_name)
+        };
+
+        let mut target_poses = vec![];
+
+        if let Some(skeleton) = self
+            .indices
+            .get_predicate_skeleton_mut(&compilation_target, &key)
+            .filter(|skeleton| !skeleton.clauses.is_empty())
+        {
+            // the store holds every clause unless the predicate was
+            // declared dynamic after its clauses were compiled.
+            let first_arg_key = self
+                .machine_st
+                .first_arg_key(head)
+                .filter(|_| skeleton.clause_store.len() == skeleton.clauses.len());
+
+            match first_arg_key {
+                Some(first_arg_key) => {
+                    let clause_locs: Vec<_> =
+                        skeleton.clause_store.candidates(first_arg_key).collect();
+
+                    for l in clause_locs {
+                        target_poses.push(skeleton.target_pos_of_clause_clause_loc(l).unwrap());
+                    }
+                }
+                None => {
+                    for (j, clause) in skeleton.clauses.iter().enumerate() {
+                        if self
+                            .machine_st
+                            .indexed_arg_may_unify(head, &clause.opt_arg_index_key)
+                        {
+                            target_poses.push(j);
+                        }
+                    }
                 }
             }
+
+            if target_poses.len() == skeleton.clauses.len() {
+                self.machine_st.fail = true;
+                return Ok(());
+            }
+        }
+
+        let mut candidates = Vec::with_capacity(target_poses.len());
+
+        if let Some(skeleton) = self
+            .indices
+            .get_predicate_skeleton(&compilation_target, &key)
+            .filter(|_| !target_poses.is_empty())
+        {
+            let module_name = match compilation_target {
+                CompilationTarget::User => atom!("builtins"),
+                CompilationTarget::Module(target) => target,
+            };
+
+            let code = self.clause_clause_code(key, module_name);
+
+            for j in target_poses {
+                let p = match code {
+                    ClauseClauseCode::Single(p) => p,
+                    ClauseClauseCode::Indexed(bp, indexed_choice) => {
+                        bp + indexed_choice[j].offset()
+                    }
+                };
+
+                candidates.push((skeleton.core.clause_clause_locs[j], p));
+            }
+        }
+
+        let mut cells = Vec::with_capacity(candidates.len());
+
+        for (l, p) in candidates {
+            let mut writer = Heap::functor_writer(functor!(atom!("-"), [fixnum(l), fixnum(p)]));
+            cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
+
+        let list_cell = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, cells.len(), cells.into_iter())
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[3], list_cell);
+        Ok(())
+    }
+
+    // unifies the fourth argument with the L of the first or the last
+    // clause of the dynamic predicate of the head in the second
+    // argument, as the third argument is first or last, that is, of
+    // the clause just added by asserta/1 or assertz/1.
+    #[inline(always)]
+    pub(crate) fn asserted_clause_loc(&mut self) {
+        use crate::machine::loader::CompilationTarget;
+
+        let module_name = cell_as_atom!(self.deref_register(1));
+        let head = self.deref_register(2);
+
+        let compilation_target = if module_name == atom!("user") {
+            CompilationTarget::User
+        } else {
+            CompilationTarget::Module(module_name)
+        };
+
+        let clause_clause_locs = self
+            .machine_st
+            .name_and_arity_from_heap(head)
+            .and_then(|key| {
+                self.indices
+                    .get_predicate_skeleton(&compilation_target, &key)
+            })
+            .map(|skeleton| &skeleton.core.clause_clause_locs);
+
+        let l = match cell_as_atom!(self.deref_register(3)) {
+            atom!("first") => clause_clause_locs.and_then(|locs| locs.front()),
+            _ => clause_clause_locs.and_then(|locs| locs.back()),
+        };
+
+        match l.copied() {
+            Some(l) => {
+                let l = Fixnum::build_with_checked(l as i64).unwrap();
+                self.machine_st
+                    .unify_fixnum(l, self.machine_st.registers[4]);
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
         }
     }
 
@@ -1394,7 +1847,7 @@ fn quantification_to_module_name(
     pub(crate) fn fast_call(
         &mut self,
         arity: usize,
-        call_at_index: impl Fn(&mut Machine, Atom, usize, IndexPtr) -> CallResult,
+        call_at_index: impl Fn(&mut Machine, Atom, Atom, usize, IndexPtr) -> CallResult,
     ) -> CallResult {
         let load_registers = |machine_st: &mut MachineState,
                               goal: HeapCellValue,
@@ -1498,7 +1951,7 @@ pub(crate) fn fast_call(
             if !index_ptr.is_undefined() {
                 load_registers(&mut self.machine_st, goal, goal_arity);
                 self.machine_st.neck_cut();
-                return call_at_index(self, name, arity, index_ptr);
+                return call_at_index(self, module_name, name, arity, index_ptr);
             }
         }
 
@@ -1699,7 +2152,7 @@ struct GoalAnalysisResult {
         };
 
         let truncated_goal = self.machine_st.registers[3];
-        unify!(&mut self.machine_st, expanded_term, truncated_goal);
+        unify_fn!(&mut self.machine_st, expanded_term, truncated_goal);
 
         Ok(())
     }
@@ -1989,10 +2442,7 @@ pub(crate) fn current_output(&mut self) -> CallResult {
 
     #[inline(always)]
     pub(crate) fn directory_files(&mut self) -> CallResult {
-        if let Some(dir) = self
-            .machine_st
-            .value_to_str_like(self.machine_st.registers[1])
-        {
+        if let Some(dir) = self.machine_st.value_to_str_like(self.deref_register(1)) {
             let str = dir.as_str();
             let path = std::path::Path::new(&*str);
             let mut files = Vec::new();
@@ -2027,7 +2477,7 @@ pub(crate) fn directory_files(&mut self) -> CallResult {
                     )
                 );
 
-                unify!(
+                unify_fn!(
                     self.machine_st,
                     self.machine_st.registers[2],
                     files_list_cell
@@ -2046,10 +2496,12 @@ pub(crate) fn file_size(&mut self) {
             .machine_st
             .value_to_str_like(self.machine_st.registers[1])
         {
-            let len = Number::arena_from(
-                fs::metadata(&*file.as_str()).unwrap().len(),
-                &mut self.machine_st.arena,
-            );
+            let Ok(md) = fs::metadata(&*file.as_str()) else {
+                self.machine_st.fail = true;
+                return;
+            };
+
+            let len = Number::arena_from(md.len(), &mut self.machine_st.arena);
 
             match len {
                 Number::Fixnum(n) => self
@@ -2067,10 +2519,7 @@ pub(crate) fn file_size(&mut self) {
 
     #[inline(always)]
     pub(crate) fn file_exists(&mut self) {
-        if let Some(file) = self
-            .machine_st
-            .value_to_str_like(self.machine_st.registers[1])
-        {
+        if let Some(file) = self.machine_st.value_to_str_like(self.deref_register(1)) {
             let file_str = file.as_str();
 
             if !std::path::Path::new(&*file_str).exists()
@@ -2085,10 +2534,7 @@ pub(crate) fn file_exists(&mut self) {
 
     #[inline(always)]
     pub(crate) fn directory_exists(&mut self) {
-        if let Some(dir) = self
-            .machine_st
-            .value_to_str_like(self.machine_st.registers[1])
-        {
+        if let Some(dir) = self.machine_st.value_to_str_like(self.deref_register(1)) {
             let dir_str = dir.as_str();
 
             if !std::path::Path::new(&*dir_str).exists()
@@ -2125,7 +2571,7 @@ pub(crate) fn file_time(&mut self) {
                         self.machine_st.heap.allocate_cstr(&chars_string)
                     );
 
-                    unify!(self.machine_st, cstr_cell, self.machine_st.registers[3]);
+                    unify_fn!(self.machine_st, cstr_cell, self.machine_st.registers[3]);
                     return;
                 }
             }
@@ -2135,13 +2581,170 @@ pub(crate) fn file_time(&mut self) {
     }
 
     #[inline(always)]
-    pub(crate) fn directory_separator(&mut self) {
-        self.machine_st
-            .unify_char(std::path::MAIN_SEPARATOR, self.machine_st.registers[1]);
-    }
+    pub(crate) fn file_type(&mut self) {
+        if let Some(file) = self.machine_st.value_to_str_like(self.deref_register(1)) {
+            // symbolic links are not followed.
+            if let Ok(md) = fs::symlink_metadata(&*file.as_str()) {
+                let file_type = md.file_type();
 
-    #[inline(always)]
-    pub(crate) fn make_directory(&mut self) {
+                let type_atom = if file_type.is_symlink() {
+                    atom!("symlink")
+                } else if file_type.is_dir() {
+                    atom!("directory")
+                } else if file_type.is_file() {
+                    atom!("file")
+                } else {
+                    atom!("other")
+                };
+
+                self.machine_st
+                    .unify_atom(type_atom, self.machine_st.registers[2]);
+                return;
+            }
+        }
+
+        self.machine_st.fail = true;
+    }
+
+    #[inline(always)]
+    pub(crate) fn wildcard_match(&mut self) {
+        let pattern = self.deref_register(1);
+        let name = self.deref_register(2);
+
+        // the empty string [] is not str-like.
+        let mut chars = |cell: HeapCellValue| -> Vec<char> {
+            self.machine_st
+                .value_to_str_like(cell)
+                .map(|s| s.as_str().chars().collect())
+                .unwrap_or_default()
+        };
+
+        let pattern = chars(pattern);
+        let name = chars(name);
+
+        self.machine_st.fail = !wildcard_match(&pattern, &name);
+    }
+
+    #[inline(always)]
+    pub(crate) fn fswatch_create(&mut self) -> CallResult {
+        // A2 = the poll interval in seconds, or native for the file
+        // system notifications of the OS.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = self.deref_register(1);
+            let interval = self.timeout_arg(2);
+
+            if let Some(path_str) = self.machine_st.value_to_str_like(path) {
+                let path_str = path_str.as_str();
+                let path_str = std::path::Path::new(&*path_str);
+
+                if let Ok(name) =
+                    fswatch::PathWatch::create(&self.machine_st.atom_tbl, path_str, interval)
+                {
+                    self.machine_st
+                        .unify_atom(name, self.machine_st.registers[3]);
+                    return Ok(());
+                }
+            }
+
+            let stub = functor_stub(atom!("watch_path"), 3);
+            let err = self
+                .machine_st
+                .permission_error(Permission::Access, atom!("file"), path);
+
+            Err(self.machine_st.error_form(err, stub))
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.machine_st.fail = true;
+            Ok(())
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn fswatch_wait(&mut self) -> CallResult {
+        // A2 = the timeout in seconds or infinite. A3 and A4 are
+        // unified with the kinds and the paths of the changes, [] if
+        // there were none before the timeout or an interrupt.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let name = cell_as_atom!(self.deref_register(1));
+            let timeout = self.timeout_arg(2);
+
+            let Some(watch) = fswatch::PathWatch::lookup(name) else {
+                self.machine_st.fail = true;
+                return Ok(());
+            };
+
+            let interrupted = || {
+                machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed)
+                    || threads::cancel_pending()
+            };
+
+            let changes = match watch.wait(timeout, interrupted) {
+                fswatch::WatchOutcome::Changed(changes) => changes,
+                fswatch::WatchOutcome::TimedOut | fswatch::WatchOutcome::Interrupted => vec![],
+            };
+
+            let mut kinds = Vec::with_capacity(changes.len());
+            let mut paths = Vec::with_capacity(changes.len());
+
+            for (change, path) in changes {
+                kinds.push(atom_as_cell!(change.as_atom()));
+                paths.push(resource_error_call_result!(
+                    self.machine_st,
+                    self.machine_st.heap.allocate_cstr(&path)
+                ));
+            }
+
+            let kinds = resource_error_call_result!(
+                self.machine_st,
+                sized_iter_to_heap_list(&mut self.machine_st.heap, kinds.len(), kinds.into_iter())
+            );
+            let paths = resource_error_call_result!(
+                self.machine_st,
+                sized_iter_to_heap_list(&mut self.machine_st.heap, paths.len(), paths.into_iter())
+            );
+
+            unify_fn!(self.machine_st, kinds, self.machine_st.registers[3]);
+
+            if !self.machine_st.fail {
+                unify_fn!(self.machine_st, paths, self.machine_st.registers[4]);
+            }
+
+            Ok(())
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.machine_st.fail = true;
+            Ok(())
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn fswatch_remove(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let name = cell_as_atom!(self.deref_register(1));
+            self.machine_st.fail = !fswatch::PathWatch::remove(name);
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.machine_st.fail = true;
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn directory_separator(&mut self) {
+        self.machine_st
+            .unify_char(std::path::MAIN_SEPARATOR, self.machine_st.registers[1]);
+    }
+
+    #[inline(always)]
+    pub(crate) fn make_directory(&mut self) {
         if let Some(dir) = self
             .machine_st
             .value_to_str_like(self.machine_st.registers[1])
@@ -2242,6 +2845,24 @@ pub(crate) fn delete_directory(&mut self) {
         }
     }
 
+    #[inline(always)]
+    pub(crate) fn delete_directory_tree(&mut self) -> CallResult {
+        let dir = self.deref_register(1);
+
+        if let Some(dir_str) = self.machine_st.value_to_str_like(dir) {
+            if fs::remove_dir_all(&*dir_str.as_str()).is_ok() {
+                return Ok(());
+            }
+        }
+
+        let stub = functor_stub(atom!("delete_directory"), 2);
+        let err = self
+            .machine_st
+            .permission_error(Permission::Modify, atom!("directory"), dir);
+
+        Err(self.machine_st.error_form(err, stub))
+    }
+
     #[inline(always)]
     pub(crate) fn working_directory(&mut self) -> CallResult {
         if let Ok(dir) = env::current_dir() {
@@ -2261,7 +2882,7 @@ pub(crate) fn working_directory(&mut self) -> CallResult {
                 self.machine_st.heap.allocate_cstr(current)
             );
 
-            unify!(
+            unify_fn!(
                 self.machine_st,
                 current_string,
                 self.machine_st.registers[1]
@@ -2307,7 +2928,7 @@ pub(crate) fn path_canonical(&mut self) -> CallResult {
                     self.machine_st.heap.allocate_cstr(cs)
                 );
 
-                unify!(
+                unify_fn!(
                     self.machine_st,
                     canonical_string,
                     self.machine_st.registers[2]
@@ -2333,7 +2954,7 @@ pub(crate) fn atom_chars(&mut self) {
                     self.machine_st.heap.allocate_cstr(&name.as_str())
                 );
 
-                unify!(self.machine_st, self.machine_st.registers[2], cell);
+                unify_fn!(self.machine_st, self.machine_st.registers[2], cell);
             }
             (HeapCellValueTag::Var | HeapCellValueTag::AttrVar | HeapCellValueTag::StackVar) => {
                 let a2 = self.deref_register(2);
@@ -2376,7 +2997,7 @@ pub(crate) fn atom_codes(&mut self) -> CallResult {
                 self.machine_st.heap.push(fixnum_as_cell!(Fixnum::build_with(u32::from(c))));
                 self.machine_st.heap.push(empty_list_as_cell!());
 
-                unify!(self.machine_st, list_loc_as_cell!(h), self.machine_st.registers[2]);
+                unify_fn!(self.machine_st, list_loc_as_cell!(h), self.machine_st.registers[2]);
             }
             */
             (HeapCellValueTag::Atom, (name, arity)) => {
@@ -2394,7 +3015,7 @@ pub(crate) fn atom_codes(&mut self) -> CallResult {
                     )
                 );
 
-                unify!(self.machine_st, list_cell, self.machine_st.registers[2]);
+                unify_fn!(self.machine_st, list_cell, self.machine_st.registers[2]);
             }
             /*
             (HeapCellValueTag::Str, s) => {
@@ -2415,7 +3036,7 @@ pub(crate) fn atom_codes(&mut self) -> CallResult {
                         )
                     );
 
-                    unify!(self.machine_st, list_cell, self.machine_st.registers[2]);
+                    unify_fn!(self.machine_st, list_cell, self.machine_st.registers[2]);
                 } else {
                 */
                 self.machine_st.fail = true;
@@ -2486,6 +3107,85 @@ pub(crate) fn atom_length(&mut self) {
         );
     }
 
+    // atom_concat/3 of two atoms, interning the concatenation directly
+    // rather than appending the char lists of Atom_1 and Atom_2.
+    #[inline(always)]
+    pub(crate) fn atom_concat(&mut self) {
+        let atom_1 = cell_as_atom!(self.deref_register(1));
+        let atom_2 = cell_as_atom!(self.deref_register(2));
+
+        let mut string = atom_1.as_str().to_owned();
+        string += &*atom_2.as_str();
+
+        let atom_12 = AtomTable::build_with(&self.machine_st.atom_tbl, &string);
+        let a3 = self.deref_register(3);
+
+        self.machine_st.unify_atom(atom_12, a3);
+    }
+
+    // sub_atom/5 with Before and Length known. Fails if Atom has fewer
+    // than Before + Length characters.
+    #[inline(always)]
+    pub(crate) fn sub_atom(&mut self) {
+        let atom = cell_as_atom!(self.deref_register(1));
+
+        let before =
+            match Number::try_from((self.deref_register(2), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => n.get_num() as usize,
+                _ => {
+                    self.machine_st.fail = true;
+                    return;
+                }
+            };
+
+        let length =
+            match Number::try_from((self.deref_register(3), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => n.get_num() as usize,
+                _ => {
+                    self.machine_st.fail = true;
+                    return;
+                }
+            };
+
+        let name = atom.as_str();
+        let mut indices = name
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(std::iter::once(name.len()));
+
+        let start = indices.nth(before);
+        let end = if length == 0 {
+            start
+        } else {
+            indices.nth(length - 1)
+        };
+
+        let (Some(start), Some(end)) = (start, end) else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let sub_atom = AtomTable::build_with(&self.machine_st.atom_tbl, &name[start..end]);
+        let a4 = self.deref_register(4);
+
+        self.machine_st.unify_atom(sub_atom, a4);
+    }
+
+    // reports the error ending the toplevel as a crash, if crashes are
+    // reported.
+    #[inline(always)]
+    pub(crate) fn report_toplevel_crash(&mut self) {
+        if self.crash_reporter.is_some() {
+            let error = self.deref_register(1);
+            let cause = format!(
+                "uncaught error in the toplevel: {}",
+                self.term_to_string(error)
+            );
+
+            self.report_crash(&cause);
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn call_continuation(&mut self, last_call: bool) -> CallResult {
         let stub_gen = || functor_stub(atom!("call_continuation"), 1);
@@ -2543,11 +3243,11 @@ pub(crate) fn create_partial_string(&mut self) {
                 self.machine_st.heap.push_cell(heap_loc_as_cell!(tail_loc))
             );
 
-            unify!(self.machine_st, self.machine_st.registers[2], pstr_loc_cell);
+            unify_fn!(self.machine_st, self.machine_st.registers[2], pstr_loc_cell);
 
             if !self.machine_st.fail {
                 let tail = self.machine_st.registers[3];
-                unify!(self.machine_st, tail, heap_loc_as_cell!(tail_loc));
+                unify_fn!(self.machine_st, tail, heap_loc_as_cell!(tail_loc));
             }
         }
     }
@@ -2909,7 +3609,7 @@ pub(crate) fn number_to_chars(&mut self) {
             self.machine_st.heap.allocate_cstr(string.trim())
         );
 
-        unify!(self.machine_st, cstr_cell, chs);
+        unify_fn!(self.machine_st, cstr_cell, chs);
     }
 
     #[inline(always)]
@@ -2948,7 +3648,7 @@ pub(crate) fn number_to_codes(&mut self) {
             )
         );
 
-        unify!(self.machine_st, list_cell, chs);
+        unify_fn!(self.machine_st, list_cell, chs);
     }
 
     #[inline(always)]
@@ -3145,7 +3845,7 @@ macro_rules! method_check {
                             self.machine_st,
                             self.machine_st.heap.allocate_cstr(&c.to_uppercase().to_string())
                         );
-                        unify!(self.machine_st, reg, upper_str);
+                        unify_fn!(self.machine_st, reg, upper_str);
                     }
                     (atom!("lower"), 1) => {
                         let reg = self.machine_st.deref(self.machine_st.heap[s+1]);
@@ -3154,7 +3854,7 @@ macro_rules! method_check {
                             self.machine_st.heap.allocate_cstr(&c.to_uppercase().to_string())
                         );
 
-                        unify!(self.machine_st, reg, lower_str);
+                        unify_fn!(self.machine_st, reg, lower_str);
                     }
                     _ => {
                         unreachable!()
@@ -3507,6 +4207,11 @@ pub(crate) fn get_byte(&mut self) -> CallResult {
             Ok(1) => {
                 self.machine_st.unify_fixnum(Fixnum::build_with(b[0]), addr);
             }
+            Err(_) if stream.input_unavailable() => {
+                return self
+                    .machine_st
+                    .input_unavailable_action(atom!("get_byte"), 2);
+            }
             _ => {
                 stream.set_past_end_of_stream(true);
                 self.machine_st
@@ -3614,7 +4319,7 @@ pub(crate) fn get_char(&mut self) -> CallResult {
 
     #[inline(always)]
     pub(crate) fn get_n_chars(&mut self) -> CallResult {
-        let stream = self.machine_st.get_stream_or_alias(
+        let mut stream = self.machine_st.get_stream_or_alias(
             self.machine_st.registers[1],
             &self.indices,
             atom!("get_n_chars"),
@@ -3641,7 +4346,12 @@ pub(crate) fn get_n_chars(&mut self) -> CallResult {
             let mut buf = vec![];
             let mut chunk = stream.take(num as u64);
 
-            chunk.read_to_end(&mut buf).ok();
+            if chunk.read_to_end(&mut buf).is_err() && buf.is_empty() && stream.input_unavailable()
+            {
+                return self
+                    .machine_st
+                    .input_unavailable_action(atom!("get_n_chars"), 3);
+            }
 
             for c in buf {
                 string.push(c as char);
@@ -3661,6 +4371,15 @@ pub(crate) fn get_n_chars(&mut self) -> CallResult {
                     Some(Ok(c)) => {
                         string.push(c);
                     }
+                    Some(Err(_)) if iter.input_unavailable() => {
+                        if string.is_empty() {
+                            return self
+                                .machine_st
+                                .input_unavailable_action(atom!("get_n_chars"), 3);
+                        }
+
+                        break;
+                    }
                     Some(Err(e)) => {
                         let stub = functor_stub(atom!("$get_n_chars"), 3);
                         let err = self.machine_st.session_error(SessionError::from(e));
@@ -3680,7 +4399,7 @@ pub(crate) fn get_n_chars(&mut self) -> CallResult {
             self.machine_st.heap.allocate_cstr(&string)
         );
 
-        unify!(self.machine_st, cstr_cell, output);
+        unify_fn!(self.machine_st, cstr_cell, output);
         Ok(())
     }
 
@@ -4115,7 +4834,7 @@ pub(crate) fn get_db_refs(&mut self) {
             )
         );
 
-        unify!(
+        unify_fn!(
             self.machine_st,
             functor_list_cell,
             self.machine_st.registers[4]
@@ -4127,6 +4846,9 @@ pub(crate) fn get_next_op_db_ref(&mut self) {
         let prec = self.deref_register(1);
         let h = self.machine_st.heap.cell_len();
 
+        let module_name = cell_as_atom!(self.deref_register(5));
+        let op_dir = self.indices.module_op_dir(module_name);
+
         fn write_op_functors_to_heap(
             heap: &mut Heap,
             op_descs: impl Iterator<Item = (Atom, OpDesc)>,
@@ -4187,9 +4909,9 @@ fn write_op_functors_to_heap(
                 );
 
                 let op_descs = [
-                    self.indices.op_dir.get(&(orig_op, Fixity::In)),
-                    self.indices.op_dir.get(&(orig_op, Fixity::Pre)),
-                    self.indices.op_dir.get(&(orig_op, Fixity::Post)),
+                    op_dir.get(&(orig_op, Fixity::In)),
+                    op_dir.get(&(orig_op, Fixity::Pre)),
+                    op_dir.get(&(orig_op, Fixity::Post)),
                 ];
 
                 let number_of_keys = op_descs[0].is_some() as usize
@@ -4217,7 +4939,7 @@ fn write_op_functors_to_heap(
                         )
                     );
 
-                    unify!(
+                    unify_fn!(
                         self.machine_st,
                         functor_list_cell,
                         self.machine_st.registers[4]
@@ -4226,7 +4948,7 @@ fn write_op_functors_to_heap(
 
                 return;
             } else {
-                let op_descs = self.indices.op_dir.iter().filter_map(|(key, op_desc)| {
+                let op_descs = op_dir.iter().filter_map(|(key, op_desc)| {
                     let (other_prec, other_spec) = (op_desc.get_prec(), op_desc.get_spec());
                     let name = key.0;
 
@@ -4259,7 +4981,7 @@ fn write_op_functors_to_heap(
                 )
             );
 
-            unify!(
+            unify_fn!(
                 self.machine_st,
                 functor_list_cell,
                 self.machine_st.registers[4]
@@ -4278,7 +5000,7 @@ fn write_op_functors_to_heap(
                 }
             };
 
-            match self.indices.op_dir.get(&(op_atom, fixity)).cloned() {
+            match op_dir.get(&(op_atom, fixity)).cloned() {
                 Some(op_desc) => {
                     let num_functors = step_or_resource_error!(
                         self.machine_st,
@@ -4297,7 +5019,7 @@ fn write_op_functors_to_heap(
                         )
                     );
 
-                    unify!(self.machine_st, functor_list, self.machine_st.registers[4]);
+                    unify_fn!(self.machine_st, functor_list, self.machine_st.registers[4]);
                 }
                 _ => {
                     self.machine_st.fail = true;
@@ -4399,512 +5121,795 @@ pub(crate) fn cpu_now(&mut self) {
         self.machine_st.unify_f64(secs, self.deref_register(1));
     }
 
-    #[inline(always)]
-    pub(crate) fn det_length_rundown(&mut self) -> CallResult {
-        let stub_gen = || functor_stub(atom!("length"), 2);
-        let len = self.deref_register(2);
+    // starts counting the calls, redos and time of every predicate
+    // defined so far. fails if profiling has already started.
+    pub(crate) fn profiler_start(&mut self) {
+        if self.machine_st.profiler.is_some() {
+            self.machine_st.fail = true;
+            return;
+        }
 
-        let n = match Number::try_from((len, &self.machine_st.arena.f64_tbl)) {
-            Ok(Number::Fixnum(n)) => n.get_num() as usize,
-            Ok(Number::Integer(n)) => match (&*n).try_into() as Result<usize, _> {
-                Ok(n) => n,
-                Err(_) => {
-                    let err = MachineState::resource_error(ResourceError::FiniteMemory(len));
-                    return Err(self.machine_st.error_form(err, stub_gen()));
-                }
-            },
-            _ => {
-                unreachable!()
+        let mut profiler = Profiler::new(self.code.len());
+
+        self.add_profiled_predicates(&mut profiler);
+        self.machine_st.profiler = Some(Box::new(profiler));
+    }
+
+    // adds the predicates of every module to profiler, which
+    // attributes the code to them.
+    pub(crate) fn add_profiled_predicates(&self, profiler: &mut Profiler) {
+        let code_index_tbl = &self.machine_st.arena.code_index_tbl;
+
+        // predicates are attributed to the module exporting them, then
+        // to user or the module defining them, and only then to the
+        // modules generated for files without a module declaration.
+        let user = (atom!("user"), 1, &self.indices.code_dir, &[][..]);
+        let modules = self.indices.modules.iter().map(|(&module_name, module)| {
+            let rank = match module.listing_src {
+                ListingSource::DynamicallyGenerated => 2,
+                _ => 1,
+            };
+
+            (
+                module_name,
+                rank,
+                &module.code_dir,
+                &module.module_decl.exports[..],
+            )
+        });
+
+        for (module_name, rank, code_dir, exports) in std::iter::once(user).chain(modules) {
+            let skeletons = match module_name {
+                atom!("user") => &self.indices.extensible_predicates,
+                _ => &self.indices.modules[&module_name].extensible_predicates,
+            };
+
+            for (&key, idx) in code_dir.iter() {
+                let Some(entry) = code_index_tbl.get_entry(idx.into()).local() else {
+                    continue;
+                };
+
+                let clause_starts = skeletons
+                    .get(&key)
+                    .into_iter()
+                    .flat_map(|skeleton| skeleton.clauses.iter())
+                    .map(|clause| clause.clause_start);
+
+                let exported = exports
+                    .iter()
+                    .any(|export| matches!(export, ModuleExport::PredicateKey(k) if *k == key));
+
+                profiler.add_predicate(
+                    module_name,
+                    key,
+                    if exported { 0 } else { rank },
+                    std::iter::once(entry).chain(clause_starts),
+                );
             }
-        };
+        }
 
-        let h = self.machine_st.heap.cell_len();
+        profiler.finish_predicates();
+    }
+
+    pub(crate) fn profiler_stop(&mut self) {
+        if let Some(profiler) = self.machine_st.profiler.take() {
+            self.machine_st.last_profile = Some(profiler);
+        }
+    }
+
+    // unifies the first register with a list of
+    // profile(Module:Name/Arity, Calls, Redos, Inclusive, Exclusive)
+    // terms, with times in milliseconds, for the predicates called or
+    // retried under the running or the last profile/1.
+    pub(crate) fn profile_data(&mut self) -> CallResult {
+        let profiler = self
+            .machine_st
+            .profiler
+            .as_deref()
+            .or(self.machine_st.last_profile.as_deref());
+
+        let rows: Vec<_> = profiler
+            .map(|profiler| {
+                profiler
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.calls > 0 || entry.redos > 0)
+                    .map(|entry| {
+                        (
+                            entry.module_name,
+                            entry.name,
+                            entry.arity,
+                            entry.calls,
+                            entry.redos,
+                            entry.inclusive.as_secs_f64() * 1000.0,
+                            entry.exclusive.as_secs_f64() * 1000.0,
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut cells = Vec::with_capacity(rows.len());
+
+        for (module_name, name, arity, calls, redos, inclusive, exclusive) in rows {
+            let inclusive = HeapCellValue::from(float_alloc!(inclusive, self.machine_st.arena));
+            let exclusive = HeapCellValue::from(float_alloc!(exclusive, self.machine_st.arena));
+
+            let key = functor!(atom!("/"), [atom_as_cell(name), fixnum(arity)]);
+            let qualified_key = functor!(atom!(":"), [atom_as_cell(module_name), functor(key)]);
+
+            let mut writer = Heap::functor_writer(functor!(
+                atom!("profile"),
+                [
+                    functor(qualified_key),
+                    fixnum(calls),
+                    fixnum(redos),
+                    cell(inclusive),
+                    cell(exclusive)
+                ]
+            ));
+
+            cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
 
         let list_cell = resource_error_call_result!(
             self.machine_st,
-            sized_iter_to_heap_list(
-                &mut self.machine_st.heap,
-                n,
-                (0..n).map(|i| heap_loc_as_cell!(h + 2 * i + 1)),
-            )
+            sized_iter_to_heap_list(&mut self.machine_st.heap, cells.len(), cells.into_iter())
         );
 
-        unify!(self.machine_st, self.deref_register(1), list_cell);
+        unify_fn!(self.machine_st, self.machine_st.registers[1], list_cell);
         Ok(())
     }
 
-    #[cfg(feature = "http")]
-    #[inline(always)]
-    pub(crate) fn http_open(&mut self) -> CallResult {
-        let address_sink = self.deref_register(1);
-        let method = read_heap_cell!(self.deref_register(3),
-            (HeapCellValueTag::Atom, (name, arity)) => {
-                debug_assert_eq!(arity, 0);
-                match name {
-                    atom!("get") => Method::GET,
-                    atom!("post") => Method::POST,
-                    atom!("put") => Method::PUT,
-                    atom!("delete") => Method::DELETE,
-                    atom!("patch") => Method::PATCH,
-                    atom!("head") => Method::HEAD,
-                    _ => unreachable!(),
-                }
-            }
-            _ => {
-                unreachable!()
-            }
-        );
-        let address_status = self.deref_register(4);
-        let address_data = self.deref_register(5);
-        let mut bytes: Vec<u8> = Vec::new();
-        if let Some(string) = self.machine_st.value_to_str_like(address_data) {
-            bytes = string.as_str().bytes().collect();
-        }
-        let stub_gen = || functor_stub(atom!("http_open"), 3);
+    // unifies the first register with a list of
+    // call(Caller, Callee, Defined) terms, the predicates being written
+    // Module:Name/Arity and Defined true or false, and the second with
+    // the list of the exported predicates called by no predicate of
+    // another module.
+    pub(crate) fn xref(&mut self) -> CallResult {
+        let xref = self.cross_reference();
 
-        let headers = match self
-            .machine_st
-            .try_from_list(self.machine_st.registers[7], stub_gen)
+        let mut call_cells = Vec::with_capacity(xref.calls.len());
+
+        for (
+            (caller_module, (caller_name, caller_arity)),
+            (callee_module, (callee_name, callee_arity)),
+            is_defined,
+        ) in xref.calls
         {
-            Ok(addrs) => {
-                let mut header_map = HeaderMap::new();
-                for heap_cell in addrs {
-                    read_heap_cell!(heap_cell,
-                        (HeapCellValueTag::Str, s) => {
-                            let name = cell_as_atom_cell!(self.machine_st.heap[s]).get_name();
-                            let value = self.machine_st.value_to_str_like(self.machine_st.heap[s + 1]).unwrap();
-                            header_map.insert(HeaderName::from_str(&name.as_str()).unwrap(), HeaderValue::from_str(&value.as_str()).unwrap());
-                        }
-                        _ => {
-                            unreachable!()
-                        }
-                    )
-                }
-                header_map
-            }
-            Err(e) => return Err(e),
-        };
-        if let Some(address_sink) = self.machine_st.value_to_str_like(address_sink) {
-            let address_string = address_sink.as_str(); //to_string();
-            let address: Url = address_string.parse().unwrap();
+            let caller_key = functor!(
+                atom!("/"),
+                [atom_as_cell(caller_name), fixnum(caller_arity)]
+            );
+            let caller = functor!(
+                atom!(":"),
+                [atom_as_cell(caller_module), functor(caller_key)]
+            );
+            let callee_key = functor!(
+                atom!("/"),
+                [atom_as_cell(callee_name), fixnum(callee_arity)]
+            );
+            let callee = functor!(
+                atom!(":"),
+                [atom_as_cell(callee_module), functor(callee_key)]
+            );
 
-            let client = reqwest::Client::builder().build().unwrap();
+            let is_defined = if is_defined {
+                atom!("true")
+            } else {
+                atom!("false")
+            };
 
-            // request
-            let mut req = client.request(method, address).headers(headers);
+            let mut writer = Heap::functor_writer(functor!(
+                atom!("call"),
+                [functor(caller), functor(callee), atom_as_cell(is_defined)]
+            ));
 
-            if !bytes.is_empty() {
-                req = req.body(bytes);
-            }
+            call_cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
 
-            // do it!
-            task::block_in_place(move || {
-                match Handle::current().block_on(req.send()) {
-                    Ok(resp) => {
-                        // status code
-                        let status = resp.status().as_u16();
-                        self.machine_st
-                            .unify_fixnum(Fixnum::build_with(status), address_status);
-                        // headers
-                        let mut headers: Vec<HeapCellValue> = vec![];
+        let mut export_cells = Vec::with_capacity(xref.unused_exports.len());
 
-                        for (header_name, header_value) in resp.headers().iter() {
-                            let string_cell = resource_error_call_result!(
-                                self.machine_st,
-                                self.machine_st
-                                    .heap
-                                    .allocate_cstr(header_value.to_str().unwrap())
-                            );
+        for (module_name, (name, arity)) in xref.unused_exports {
+            let key = functor!(atom!("/"), [atom_as_cell(name), fixnum(arity)]);
+            let mut writer = Heap::functor_writer(functor!(
+                atom!(":"),
+                [atom_as_cell(module_name), functor(key)]
+            ));
 
-                            let header_term = functor!(
-                                AtomTable::build_with(
-                                    &self.machine_st.atom_tbl,
-                                    header_name.as_str()
-                                ),
-                                [cell(string_cell)]
-                            );
+            export_cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
 
-                            let mut functor_writer = Heap::functor_writer(header_term);
+        let calls = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                call_cells.len(),
+                call_cells.into_iter()
+            )
+        );
 
-                            let functor_cell = resource_error_call_result!(
-                                self.machine_st,
-                                functor_writer(&mut self.machine_st.heap)
-                            );
+        let unused_exports = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                export_cells.len(),
+                export_cells.into_iter()
+            )
+        );
 
-                            headers.push(functor_cell);
-                        }
+        unify_fn!(self.machine_st, self.machine_st.registers[1], calls);
 
-                        let headers_list_cell = resource_error_call_result!(
-                            self.machine_st,
-                            sized_iter_to_heap_list(
-                                &mut self.machine_st.heap,
-                                headers.len(),
-                                headers.into_iter(),
-                            )
-                        );
+        if !self.machine_st.fail {
+            unify_fn!(
+                self.machine_st,
+                self.machine_st.registers[2],
+                unused_exports
+            );
+        }
 
-                        unify!(
-                            self.machine_st,
-                            headers_list_cell,
-                            self.machine_st.registers[6]
-                        );
+        Ok(())
+    }
 
-                        // body
-                        let reader = futures::executor::block_on(resp.bytes()).unwrap().reader();
+    // unifies the first register with the list of Importer-Imported
+    // pairs of the modules imported by each module, or by user.
+    pub(crate) fn module_imports(&mut self) -> CallResult {
+        let imports: Vec<_> = self
+            .indices
+            .module_imports
+            .iter()
+            .flat_map(|(&importer, imported_modules)| {
+                imported_modules
+                    .iter()
+                    .map(move |&imported| (importer, imported))
+            })
+            .collect();
 
-                        let mut stream = Stream::from_http_stream(
-                            AtomTable::build_with(&self.machine_st.atom_tbl, &address_string),
-                            reader,
-                            &mut self.machine_st.arena,
-                        );
-                        *stream.options_mut() = StreamOptions::default();
+        let mut import_cells = Vec::with_capacity(imports.len());
 
-                        self.indices
-                            .add_stream(stream, atom!("http_open"), 3)
-                            .map_err(|stub_gen| stub_gen(&mut self.machine_st))
-                            .unwrap();
+        for (importer, imported) in imports {
+            let mut writer = Heap::functor_writer(functor!(
+                atom!("-"),
+                [atom_as_cell(importer), atom_as_cell(imported)]
+            ));
 
-                        let stream_addr = self.deref_register(2);
-                        self.machine_st
-                            .bind(stream_addr.as_var().unwrap(), stream.into());
-                    }
-                    Err(_) => {
-                        self.machine_st.fail = true;
-                    }
-                }
+            import_cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
 
-                Ok::<(), _>(())
-            })?;
-        } else {
-            let err = self
-                .machine_st
-                .domain_error(DomainErrorType::SourceSink, address_sink);
-            let stub = functor_stub(atom!("http_open"), 3);
+        let imports = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                import_cells.len(),
+                import_cells.into_iter()
+            )
+        );
 
-            return Err(self.machine_st.error_form(err, stub));
+        unify_fn!(self.machine_st, self.machine_st.registers[1], imports);
+        Ok(())
+    }
+
+    // unifies the second register with the list of the predicates
+    // visible from the module named in the first, failing if there's
+    // no such module.
+    pub(crate) fn visible_predicates(&mut self) -> CallResult {
+        let module_name = cell_as_atom!(self.deref_register(1));
+
+        let Some(keys) = self.visible_predicate_keys(module_name) else {
+            self.machine_st.fail = true;
+            return Ok(());
+        };
+
+        let mut key_cells = Vec::with_capacity(keys.len());
+
+        for (name, arity) in keys {
+            let mut writer =
+                Heap::functor_writer(functor!(atom!("/"), [atom_as_cell(name), fixnum(arity)]));
+
+            key_cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
         }
 
+        let keys = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                key_cells.len(),
+                key_cells.into_iter()
+            )
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[2], keys);
         Ok(())
     }
 
-    #[cfg(feature = "http")]
-    #[inline(always)]
-    pub(crate) fn http_listen(&mut self) -> CallResult {
-        let address_sink = self.deref_register(1);
-        let tls_key = self.deref_register(3);
-        let tls_cert = self.deref_register(4);
-        let content_length_limit = self.deref_register(5);
-        const CONTENT_LENGTH_LIMIT_DEFAULT: u64 = 32768;
-        let content_length_limit =
-            match Number::try_from((content_length_limit, &self.machine_st.arena.f64_tbl)) {
-                Ok(Number::Fixnum(n)) => {
-                    if n.get_num() >= 0 {
-                        n.get_num() as u64
-                    } else {
-                        CONTENT_LENGTH_LIMIT_DEFAULT
-                    }
-                }
-                Ok(Number::Integer(n)) => {
-                    let n: Result<u64, _> = (&*n).try_into();
-                    match n {
-                        Ok(u) => u,
-                        Err(_) => CONTENT_LENGTH_LIMIT_DEFAULT,
-                    }
-                }
-                _ => CONTENT_LENGTH_LIMIT_DEFAULT,
+    // unifies the second register with the module graph written in the
+    // format named in the first, dot or json.
+    pub(crate) fn write_module_graph(&mut self) -> CallResult {
+        let graph = self.module_graph();
+
+        let text = match cell_as_atom!(self.deref_register(1)) {
+            atom!("dot") => graph.to_dot(),
+            _ => graph.to_json(),
+        };
+
+        let text =
+            resource_error_call_result!(self.machine_st, self.machine_st.heap.allocate_cstr(&text));
+
+        unify_fn!(self.machine_st, self.machine_st.registers[2], text);
+        Ok(())
+    }
+
+    // the code offsets at which predicates and their clauses start,
+    // with whether they belong to a library or the system, whose
+    // calls among themselves aren't traced.
+    fn trace_code_starts(&self) -> Vec<(usize, bool)> {
+        let code_index_tbl = &self.machine_st.arena.code_index_tbl;
+
+        let user = (atom!("user"), false, &self.indices.code_dir);
+        let modules = self.indices.modules.iter().map(|(&module_name, module)| {
+            let is_library = libraries::contains_module(&module_name.as_str());
+            (module_name, is_library, &module.code_dir)
+        });
+
+        let mut starts = vec![];
+
+        for (module_name, is_library, code_dir) in once(user).chain(modules) {
+            let skeletons = match module_name {
+                atom!("user") => &self.indices.extensible_predicates,
+                _ => &self.indices.modules[&module_name].extensible_predicates,
             };
 
-        let ssl_server: Option<(String, String)> = {
-            match self.machine_st.value_to_str_like(tls_key) {
-                Some(key) => match self.machine_st.value_to_str_like(tls_cert) {
-                    Some(cert) => {
-                        let key_str = key.as_str();
-                        let cert_str = cert.as_str();
+            for (key, idx) in code_dir.iter() {
+                let Some(entry) = code_index_tbl.get_entry(idx.into()).local() else {
+                    continue;
+                };
 
-                        if key_str.is_empty() || cert_str.is_empty() {
-                            None
-                        } else {
-                            Some((key_str.to_string(), cert_str.to_string()))
-                        }
-                    }
-                    None => None,
-                },
-                None => None,
+                let clause_starts = skeletons
+                    .get(key)
+                    .into_iter()
+                    .flat_map(|skeleton| skeleton.clauses.iter())
+                    .map(|clause| clause.clause_start);
+
+                starts.extend(
+                    once(entry)
+                        .chain(clause_starts)
+                        .map(|p| (p, is_library || p < self.boot_code_end)),
+                );
             }
+        }
+
+        starts
+    }
+
+    // calls the wrapper of the tracer in place of the call of
+    // name/arity at idx if the tracer traces it, with the goal, its
+    // code and whether it's dynamic. returns whether it did.
+    #[inline(never)]
+    pub(crate) fn trace_goal(
+        &mut self,
+        name: Atom,
+        arity: usize,
+        idx: IndexPtr,
+        is_last_call: bool,
+    ) -> Result<bool, MachineStub> {
+        let callee_p = match idx.local() {
+            Some(p) => p,
+            None => return Ok(false),
         };
 
-        if let Some(address_str) = self.machine_st.value_to_str_like(address_sink) {
-            let address_string = address_str.as_str();
-            let addr: SocketAddr = match address_string
-                .to_socket_addrs()
-                .ok()
-                .and_then(|mut s| s.next())
-            {
-                Some(addr) => addr,
-                _ => {
-                    self.machine_st.fail = true;
-                    return Ok(());
-                }
-            };
+        let caller_p = self.machine_st.p;
+        let code_end = self.code.len();
 
-            let (tx, rx) = std::sync::mpsc::sync_channel(1024);
+        let starts = match self.machine_st.tracer.as_deref() {
+            Some(tracer) if tracer.mode == TraceMode::Off => return Ok(false),
+            Some(tracer) if tracer.is_stale(caller_p.max(callee_p)) => {
+                Some(self.trace_code_starts())
+            }
+            Some(_) => None,
+            None => return Ok(false),
+        };
 
-            let runtime = tokio::runtime::Handle::current();
-            let _guard = runtime.enter();
+        let tracer = self.machine_st.tracer.as_deref_mut().unwrap();
 
-            let serve = warp::body::bytes()
-                .and(warp::header::optional::<u64>(
-                    warp::http::header::CONTENT_LENGTH.as_str(),
-                ))
-                .and(warp::method())
-                .and(warp::header::headers_cloned())
-                .and(warp::path::full())
-                .and(warp::query::raw().or_else(|_| {
-                    future::ready(Ok::<(String,), warp::Rejection>(("".to_string(),)))
-                }))
-                .map(
-                    move |body: bytes::Bytes,
-                          content_length,
-                          method,
-                          headers: warp::http::HeaderMap,
-                          path: warp::filters::path::FullPath,
-                          query| {
-                        if let Some(content_length) = content_length {
-                            if content_length > content_length_limit {
-                                return warp::http::Response::builder()
-                                    .status(413)
-                                    .body(warp::hyper::Body::empty())
-                                    .unwrap();
-                            }
-                        }
+        if let Some(starts) = starts {
+            tracer.set_starts(starts, code_end);
+        }
 
-                        let http_request_data = HttpRequestData {
-                            method,
-                            headers,
-                            path: path.as_str().to_string(),
-                            query,
-                            body: body.reader(),
-                        };
-                        let response =
-                            Arc::new((Mutex::new(false), Mutex::new(None), Condvar::new()));
-                        let http_request = HttpRequest {
-                            request_data: http_request_data,
-                            response: Arc::clone(&response),
-                        };
-                        // we send the request to http_accept
-                        tx.send(http_request).unwrap();
+        if !tracer.traces((name, arity), caller_p, callee_p) {
+            return Ok(false);
+        }
 
-                        // we wait for the Response info from Prolog
-                        {
-                            let (ready, _response, cvar) = &*response;
-                            let mut ready = ready.lock().unwrap();
-                            while !*ready {
-                                ready = cvar.wait(ready).unwrap();
-                            }
-                        }
-                        {
-                            let (_, response, _) = &*response;
-                            let response = response.lock().unwrap().take();
-                            response.expect("Data race error in HTTP server")
-                        }
-                    },
-                );
+        let (module_name, wrapper_name) = tracer.wrapper();
 
-            runtime.spawn(async move {
-                match ssl_server {
-                    Some((key, cert)) => {
-                        warp::serve(serve).tls().key(key).cert(cert).run(addr).await
-                    }
-                    None => warp::serve(serve).run(addr).await,
-                }
+        self.call_wrapper(module_name, wrapper_name, name, arity, idx, is_last_call)
+    }
+
+    // calls os:'$signal_goal'/3 in place of the call of name/arity at
+    // idx if signals mapped to goals by on_signal/2 are pending, which
+    // runs their goals before the call. returns whether it did.
+    #[inline(never)]
+    pub(crate) fn signal_goal(
+        &mut self,
+        name: Atom,
+        arity: usize,
+        idx: IndexPtr,
+        is_last_call: bool,
+    ) -> Result<bool, MachineStub> {
+        if !signals::take_any_pending() {
+            return Ok(false);
+        }
+
+        self.call_wrapper(
+            atom!("os"),
+            atom!("$signal_goal"),
+            name,
+            arity,
+            idx,
+            is_last_call,
+        )
+    }
+
+    // calls the wrapper module_name:wrapper_name/3 in place of the
+    // call of name/arity at idx, with the goal, its code and whether
+    // it's dynamic, so that the wrapper runs the goal by
+    // '$trace_call_at'/3. returns whether it did.
+    fn call_wrapper(
+        &mut self,
+        module_name: Atom,
+        wrapper_name: Atom,
+        name: Atom,
+        arity: usize,
+        idx: IndexPtr,
+        is_last_call: bool,
+    ) -> Result<bool, MachineStub> {
+        let callee_p = match idx.local() {
+            Some(p) => p,
+            None => return Ok(false),
+        };
+
+        let wrapper_p = self
+            .indices
+            .modules
+            .get(&module_name)
+            .and_then(|module| module.code_dir.get(&(wrapper_name, 3)))
+            .and_then(|idx| {
+                self.machine_st
+                    .arena
+                    .code_index_tbl
+                    .get_entry(idx.into())
+                    .local()
             });
 
-            let http_listener = HttpListener { incoming: rx };
-            let http_listener: TypedArenaPtr<HttpListener> =
-                arena_alloc!(http_listener, &mut self.machine_st.arena);
+        let Some(wrapper_p) = wrapper_p else {
+            return Ok(false);
+        };
 
-            let addr = self.deref_register(2);
-            self.machine_st.bind(
-                addr.as_var().unwrap(),
-                typed_arena_ptr_as_cell!(http_listener),
-            );
-        }
-        Ok(())
-    }
+        // the arguments may be unbound variables of the caller's
+        // environment, which is gone once the goal is left. they are
+        // bound to new heap variables, as by put_unsafe_value.
+        for idx in 1..arity + 1 {
+            let addr = self
+                .machine_st
+                .store(self.machine_st.deref(self.machine_st.registers[idx]));
 
-    #[cfg(feature = "http")]
-    #[inline(always)]
-    pub(crate) fn http_accept(&mut self) -> CallResult {
-        let culprit = self.deref_register(1);
-        let method = self.deref_register(2);
-        let path = self.deref_register(3);
-        let query = self.deref_register(5);
-        let stream_addr = self.deref_register(6);
-        let handle_addr = self.deref_register(7);
-        read_heap_cell!(culprit,
-        (HeapCellValueTag::Cons, cons_ptr) => {
-        match_untyped_arena_ptr!(cons_ptr,
-            (ArenaHeaderTag::HttpListener, http_listener) => {
-            loop {
-                match http_listener.incoming.recv_timeout(std::time::Duration::from_millis(200)) {
-                    Ok(request) => {
-                        let method_atom = match request.request_data.method {
-                            Method::GET => atom!("get"),
-                            Method::POST => atom!("post"),
-                            Method::PUT => atom!("put"),
-                            Method::DELETE => atom!("delete"),
-                            Method::PATCH => atom!("patch"),
-                            Method::HEAD => atom!("head"),
-                            Method::OPTIONS => atom!("options"),
-                            Method::TRACE => atom!("trace"),
-                            Method::CONNECT => atom!("connect"),
-                            _ => atom!("unsupported_extension"),
-                        };
+            if addr.get_tag() == HeapCellValueTag::StackVar {
+                let h = self.machine_st.heap.cell_len();
 
-                        let path_atom = AtomTable::build_with(&self.machine_st.atom_tbl, &request.request_data.path);
-                        let path_cell = resource_error_call_result!(
-                            self.machine_st,
-                            self.machine_st.heap.allocate_cstr(&request.request_data.path)
-                        );
+                resource_error_call_result!(
+                    self.machine_st,
+                    self.machine_st.heap.push_cell(heap_loc_as_cell!(h))
+                );
 
-                        let mut headers = vec![];
+                (self.machine_st.bind_fn)(&mut self.machine_st, Ref::heap_cell(h), addr);
+                self.machine_st.registers[idx] = heap_loc_as_cell!(h);
+            }
+        }
 
-                        for (header_name, header_value) in request.request_data.headers {
-                            let header_value = resource_error_call_result!(
-                                self.machine_st,
-                                self.machine_st.heap.allocate_cstr(header_value.to_str().unwrap())
-                            );
+        let goal = if arity == 0 {
+            atom_as_cell!(name)
+        } else {
+            let h = self.machine_st.heap.cell_len();
 
-                            let header_term = functor!(
-                                AtomTable::build_with(&self.machine_st.atom_tbl, header_name.unwrap().as_str()),
-                                [cell(header_value)]
-                            );
+            let mut writer = resource_error_call_result!(
+                self.machine_st,
+                self.machine_st.heap.reserve(1 + arity)
+            );
 
-                            let mut functor_writer = Heap::functor_writer(header_term);
+            writer.write_with(|section| {
+                section.push_cell(atom_as_cell!(name, arity));
 
-                            let functor_cell = resource_error_call_result!(
-                                self.machine_st,
-                                functor_writer(&mut self.machine_st.heap)
-                            );
+                for idx in 1..arity + 1 {
+                    section.push_cell(self.machine_st.registers[idx]);
+                }
+            });
 
-                            headers.push(functor_cell);
-                        }
+            str_loc_as_cell!(h)
+        };
 
-                        let headers_list_cell = resource_error_call_result!(
-                            self.machine_st,
-                            sized_iter_to_heap_list(
-                                &mut self.machine_st.heap,
-                                headers.len(),
-                                headers.into_iter(),
-                            )
-                        );
+        let is_dynamic = idx.tag() == IndexPtrTag::DynamicIndex;
 
-                        let query_str  = request.request_data.query;
-                        let query_cell = resource_error_call_result!(
-                            self.machine_st,
-                            self.machine_st.heap.allocate_cstr(&query_str)
-                        );
+        self.machine_st.registers[1] = goal;
+        self.machine_st.registers[2] =
+            fixnum_as_cell!(Fixnum::build_with_checked(callee_p as i64).unwrap());
+        self.machine_st.registers[3] = atom_as_cell!(if is_dynamic {
+            atom!("true")
+        } else {
+            atom!("false")
+        });
 
-                        let mut stream = Stream::from_http_stream(
-                            path_atom,
-                            request.request_data.body,
-                            &mut self.machine_st.arena
-                        );
-                        *stream.options_mut() = StreamOptions::default();
-                        stream.options_mut().set_stream_type(StreamType::Binary);
+        if is_last_call {
+            self.machine_st.execute_at_index(3, wrapper_p);
+        } else {
+            self.machine_st.call_at_index(3, wrapper_p);
+        }
 
-                        self.indices.add_stream(stream, atom!("http_accept"), 7)
-                            .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+        Ok(true)
+    }
 
-                        let stream = stream_as_cell!(stream);
+    // calls the goal of the first register at the code of the second,
+    // as a dynamic predicate if the third is true, without tracing it.
+    #[inline(always)]
+    pub(crate) fn trace_call_at(&mut self, is_last_call: bool) {
+        let goal = self.deref_register(1);
 
-                        let handle = arena_alloc!(request.response, &mut self.machine_st.arena)
-                            as TypedArenaPtr<HttpResponse>;
+        let p = match self.deref_register(2).to_fixnum() {
+            Some(n) => n.get_num() as usize,
+            None => unreachable!(),
+        };
 
-                        self.machine_st.bind(method.as_var().unwrap(), atom_as_cell!(method_atom));
-                        self.machine_st.bind(path.as_var().unwrap(), path_cell);
-                        unify!(self.machine_st, headers_list_cell, self.machine_st.registers[4]);
-                        self.machine_st.bind(query.as_var().unwrap(), query_cell);
-                        self.machine_st.bind(stream_addr.as_var().unwrap(), stream);
-                        self.machine_st.bind(handle_addr.as_var().unwrap(), typed_arena_ptr_as_cell!(handle));
+        let is_dynamic = self.deref_register(3) == atom_as_cell!(atom!("true"));
 
-                        break
-                    }
-                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                        let interrupted = machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed);
+        let arity = read_heap_cell!(goal,
+            (HeapCellValueTag::Str, s) => {
+                let arity = cell_as_atom_cell!(self.machine_st.heap[s]).get_arity();
 
-                        match machine::INTERRUPT.compare_exchange(
-                            interrupted,
-                            false,
-                            std::sync::atomic::Ordering::Relaxed,
-                            std::sync::atomic::Ordering::Relaxed,
-                        ) {
-                            Ok(interruption) => {
-                                if interruption {
-                                    self.machine_st.throw_interrupt_exception();
-                                    self.machine_st.backtrack();
-                                    // We have extracted controll over the Tokio runtime to the calling context for enabling library use case
-                                    // (see https://github.com/mthom/scryer-prolog/pull/1880)
-                                    // So we only have access to a runtime handle in here and can't shut it down.
-                                    // Since I'm not aware of the consequences of deactivating this new code which came in while PR 1880
-                                    // was not merged, I'm only deactivating it for now.
-                                    //let old_runtime = std::mem::replace(&mut self.runtime, tokio::runtime::Runtime::new().unwrap());
-                                    //old_runtime.shutdown_background();
-                                    break
-                                }
-                            }
-                            Err(_) => unreachable!(),
-                        }
-                    }
-                  Err(_) => {
-                      self.machine_st.fail = true;
-                  }
-                }
-            }
-            }
-            _ => {
-                    unreachable!();
+                for idx in 1..arity + 1 {
+                    self.machine_st.registers[idx] = self.machine_st.heap[s + idx];
                 }
-            );
+
+                arity
             }
             _ => {
-                unreachable!();
+                0
             }
         );
-        Ok(())
+
+        if is_dynamic {
+            self.machine_st.dynamic_mode = FirstOrNext::First;
+        }
+
+        if is_last_call {
+            self.machine_st.execute_at_index(arity, p);
+        } else {
+            self.machine_st.call_at_index(arity, p);
+        }
     }
 
-    #[cfg(feature = "http")]
+    fn tracer(&mut self) -> &mut Tracer {
+        self.machine_st
+            .tracer
+            .get_or_insert_with(|| Box::new(Tracer::new()))
+    }
+
+    // unifies the first register with the mode of the tracer, and sets
+    // it to the second if it's bound.
     #[inline(always)]
-    pub(crate) fn http_answer(&mut self) -> CallResult {
-        let culprit = self.deref_register(1);
-        let status_code = self.deref_register(2);
-        let status_code: u16 = match Number::try_from((status_code, &self.machine_st.arena.f64_tbl))
-        {
-            Ok(Number::Fixnum(n)) => n.get_num() as u16,
-            Ok(Number::Integer(n)) => {
-                let n: Result<u16, _> = (&*n).try_into();
+    pub(crate) fn trace_mode(&mut self) {
+        let mode = self.tracer().mode.as_atom();
 
-                if let Ok(value) = n {
-                    value
-                } else {
-                    self.machine_st.fail = true;
-                    return Ok(());
-                }
+        self.machine_st
+            .unify_atom(mode, self.machine_st.registers[1]);
+
+        if self.machine_st.fail {
+            return;
+        }
+
+        let new_mode = self.deref_register(2);
+
+        if new_mode.is_var() {
+            return;
+        }
+
+        match TraceMode::from_atom(cell_as_atom!(new_mode)) {
+            Some(new_mode) => {
+                self.machine_st.tracer.as_deref_mut().unwrap().mode = new_mode;
             }
-            _ => unreachable!(),
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    // adds the spy point of name/arity in the first two registers if
+    // the third is true, and removes it otherwise.
+    #[inline(always)]
+    pub(crate) fn trace_spy(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+
+        let arity = match self.deref_register(2).to_fixnum() {
+            Some(n) => n.get_num() as usize,
+            None => unreachable!(),
         };
-        let stub_gen = || functor_stub(atom!("http_listen"), 2);
-        let headers = match self
-            .machine_st
-            .try_from_list(self.machine_st.registers[3], stub_gen)
-        {
-            Ok(addrs) => {
-                let mut header_map = HeaderMap::new();
-                for heap_cell in addrs {
-                    read_heap_cell!(heap_cell,
-                        (HeapCellValueTag::Str, s) => {
-                            let name = cell_as_atom_cell!(self.machine_st.heap[s]).get_name();
+
+        let is_spied = self.deref_register(3) == atom_as_cell!(atom!("true"));
+
+        let tracer = self.tracer();
+
+        if is_spied {
+            tracer.spy_points.insert((name, arity));
+        } else {
+            tracer.spy_points.shift_remove(&(name, arity));
+        }
+    }
+
+    // passes the port, depth and goal in the first three registers to
+    // the hook of MachineBuilder::with_trace_hook, unifying the fourth
+    // with the action it returns. fails if there's no hook.
+    pub(crate) fn trace_event(&mut self) {
+        let Some(mut hook) = self.trace_hook.take() else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let port = match cell_as_atom!(self.deref_register(1)) {
+            atom!("call") => TracePort::Call,
+            atom!("exit") => TracePort::Exit,
+            atom!("redo") => TracePort::Redo,
+            _ => TracePort::Fail,
+        };
+
+        let depth = match self.deref_register(2).to_fixnum() {
+            Some(n) => n.get_num() as usize,
+            None => unreachable!(),
+        };
+
+        // the output written before the port reaches the embedding first.
+        let _ = self.user_output.flush();
+        let _ = self.user_error.flush();
+
+        let goal = self.deref_register(3);
+        let goal = machine::lib_machine::Term::from_heapcell(self, goal, &mut IndexMap::new());
+
+        let action = (hook.0)(&TraceEvent { port, depth, goal });
+        self.trace_hook = Some(hook);
+
+        let action = match action {
+            TraceAction::Creep => atom!("creep"),
+            TraceAction::Skip => atom!("skip"),
+            TraceAction::Leap => atom!("leap"),
+            TraceAction::Fail => atom!("fail"),
+            TraceAction::Abort => atom!("abort"),
+        };
+
+        self.machine_st
+            .unify_atom(action, self.machine_st.registers[4]);
+    }
+
+    // the bytes in use by the heap, the stack and the trail.
+    #[inline(always)]
+    pub(crate) fn memory_usage(&mut self) {
+        let heap_bytes = self.machine_st.heap.byte_len();
+        let stack_bytes = self.machine_st.stack.top();
+        let trail_bytes = self.machine_st.trail.len() * std::mem::size_of::<TrailEntry>();
+
+        for (idx, bytes) in [heap_bytes, stack_bytes, trail_bytes]
+            .into_iter()
+            .enumerate()
+        {
+            let bytes = Fixnum::build_with_checked(bytes as i64).unwrap();
+            self.machine_st
+                .unify_fixnum(bytes, self.machine_st.registers[idx + 1]);
+
+            if self.machine_st.fail {
+                return;
+            }
+        }
+    }
+
+    // the number of choice points on the stack.
+    #[inline(always)]
+    pub(crate) fn choice_point_count(&mut self) {
+        let mut b = self.machine_st.b;
+        let mut count: i64 = 0;
+
+        while b > 0 {
+            count += 1;
+            b = self.machine_st.stack.index_or_frame(b).prelude.b;
+        }
+
+        let count = Fixnum::build_with_checked(count).unwrap();
+        self.machine_st
+            .unify_fixnum(count, self.machine_st.registers[1]);
+    }
+
+    #[inline(always)]
+    pub(crate) fn det_length_rundown(&mut self) -> CallResult {
+        let stub_gen = || functor_stub(atom!("length"), 2);
+        let len = self.deref_register(2);
+
+        let n = match Number::try_from((len, &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Fixnum(n)) => n.get_num() as usize,
+            Ok(Number::Integer(n)) => match (&*n).try_into() as Result<usize, _> {
+                Ok(n) => n,
+                Err(_) => {
+                    let err = MachineState::resource_error(ResourceError::FiniteMemory(len));
+                    return Err(self.machine_st.error_form(err, stub_gen()));
+                }
+            },
+            _ => {
+                unreachable!()
+            }
+        };
+
+        let h = self.machine_st.heap.cell_len();
+
+        let list_cell = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                n,
+                (0..n).map(|i| heap_loc_as_cell!(h + 2 * i + 1)),
+            )
+        );
+
+        unify_fn!(self.machine_st, self.deref_register(1), list_cell);
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn http_open(&mut self) -> CallResult {
+        let address_sink = self.deref_register(1);
+        let method = read_heap_cell!(self.deref_register(3),
+            (HeapCellValueTag::Atom, (name, arity)) => {
+                debug_assert_eq!(arity, 0);
+                match name {
+                    atom!("get") => Method::GET,
+                    atom!("post") => Method::POST,
+                    atom!("put") => Method::PUT,
+                    atom!("delete") => Method::DELETE,
+                    atom!("patch") => Method::PATCH,
+                    atom!("head") => Method::HEAD,
+                    _ => unreachable!(),
+                }
+            }
+            _ => {
+                unreachable!()
+            }
+        );
+        let address_status = self.deref_register(4);
+        let address_data = self.deref_register(5);
+        let mut bytes: Vec<u8> = Vec::new();
+        if let Some(string) = self.machine_st.value_to_str_like(address_data) {
+            bytes = string.as_str().bytes().collect();
+        }
+        let stub_gen = || functor_stub(atom!("http_open"), 3);
+
+        let headers = match self
+            .machine_st
+            .try_from_list(self.machine_st.registers[7], stub_gen)
+        {
+            Ok(addrs) => {
+                let mut header_map = HeaderMap::new();
+                for heap_cell in addrs {
+                    read_heap_cell!(heap_cell,
+                        (HeapCellValueTag::Str, s) => {
+                            let name = cell_as_atom_cell!(self.machine_st.heap[s]).get_name();
                             let value = self.machine_st.value_to_str_like(self.machine_st.heap[s + 1]).unwrap();
                             header_map.insert(HeaderName::from_str(&name.as_str()).unwrap(), HeaderValue::from_str(&value.as_str()).unwrap());
                         }
@@ -4917,1053 +5922,4374 @@ pub(crate) fn http_answer(&mut self) -> CallResult {
             }
             Err(e) => return Err(e),
         };
-        let stream_addr = self.deref_register(4);
+        // A8 = the timeout in seconds or infinite, A9 = the maximum
+        // number of redirects to follow, A10 = whether to verify the
+        // certificate of the server, all validated by http_open/3.
+        let timeout =
+            match Number::try_from((self.deref_register(8), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Float(n)) => Duration::try_from_secs_f64(n.into_inner()).ok(),
+                Ok(Number::Fixnum(n)) => Duration::try_from_secs_f64(n.get_num() as f64).ok(),
+                Ok(Number::Integer(n)) => Duration::try_from_secs_f64(n.to_f64().value()).ok(),
+                _ => None,
+            };
 
-        read_heap_cell!(culprit,
-            (HeapCellValueTag::Cons, cons_ptr) => {
-                match_untyped_arena_ptr!(cons_ptr,
-                    (ArenaHeaderTag::HttpResponse, http_response) => {
-                        let mut stream = Stream::from_http_sender(
-                            http_response,
-                            status_code,
-                            headers,
-                            &mut self.machine_st.arena
+        let max_redirect =
+            match Number::try_from((self.deref_register(9), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => usize::try_from(n.get_num()).unwrap_or(usize::MAX),
+                _ => usize::MAX,
+            };
+
+        let redirect_policy = if max_redirect == 0 {
+            reqwest::redirect::Policy::none()
+        } else {
+            reqwest::redirect::Policy::limited(max_redirect)
+        };
+
+        let cert_verify = self.deref_register(10) != atom_as_cell!(atom!("false"));
+        let final_url = self.deref_register(11);
+
+        if let Some(address_sink) = self.machine_st.value_to_str_like(address_sink) {
+            let address_string = address_sink.as_str(); //to_string();
+            let address: Url = address_string.parse().unwrap();
+
+            let client = reqwest::Client::builder()
+                .redirect(redirect_policy)
+                .danger_accept_invalid_certs(!cert_verify)
+                .build()
+                .unwrap();
+
+            // request
+            let mut req = client.request(method, address).headers(headers);
+
+            if !bytes.is_empty() {
+                req = req.body(bytes);
+            }
+
+            // do it! the timeout bounds the wait for the reply and then
+            // for each chunk of its body, which is read as the stream is.
+            let send = async move {
+                match timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, req.send()).await.ok(),
+                    None => Some(req.send().await),
+                }
+            };
+
+            task::block_in_place(move || {
+                match Handle::current().block_on(send) {
+                    Some(Ok(resp)) => {
+                        // status code
+                        let status = resp.status().as_u16();
+                        self.machine_st
+                            .unify_fixnum(Fixnum::build_with(status), address_status);
+                        // headers
+                        let mut headers: Vec<HeapCellValue> = vec![];
+
+                        for (header_name, header_value) in resp.headers().iter() {
+                            let string_cell = resource_error_call_result!(
+                                self.machine_st,
+                                self.machine_st
+                                    .heap
+                                    .allocate_cstr(header_value.to_str().unwrap())
+                            );
+
+                            let header_term = functor!(
+                                AtomTable::build_with(
+                                    &self.machine_st.atom_tbl,
+                                    header_name.as_str()
+                                ),
+                                [cell(string_cell)]
+                            );
+
+                            let mut functor_writer = Heap::functor_writer(header_term);
+
+                            let functor_cell = resource_error_call_result!(
+                                self.machine_st,
+                                functor_writer(&mut self.machine_st.heap)
+                            );
+
+                            headers.push(functor_cell);
+                        }
+
+                        let headers_list_cell = resource_error_call_result!(
+                            self.machine_st,
+                            sized_iter_to_heap_list(
+                                &mut self.machine_st.heap,
+                                headers.len(),
+                                headers.into_iter(),
+                            )
+                        );
+
+                        unify_fn!(
+                            self.machine_st,
+                            headers_list_cell,
+                            self.machine_st.registers[6]
+                        );
+
+                        let final_url_cell = resource_error_call_result!(
+                            self.machine_st,
+                            self.machine_st.heap.allocate_cstr(resp.url().as_str())
+                        );
+
+                        unify_fn!(self.machine_st, final_url_cell, final_url);
+
+                        // body
+                        let mut stream = Stream::from_http_response(
+                            AtomTable::build_with(&self.machine_st.atom_tbl, &address_string),
+                            resp,
+                            timeout,
+                            &mut self.machine_st.arena,
                         );
+                        *stream.options_mut() = StreamOptions::default();
+
+                        self.indices
+                            .add_stream(stream, atom!("http_open"), 3)
+                            .map_err(|stub_gen| stub_gen(&mut self.machine_st))
+                            .unwrap();
+
+                        let stream_addr = self.deref_register(2);
+                        self.machine_st
+                            .bind(stream_addr.as_var().unwrap(), stream.into());
+                    }
+                    _ => {
+                        self.machine_st.fail = true;
+                    }
+                }
+
+                Ok::<(), _>(())
+            })?;
+        } else {
+            let err = self
+                .machine_st
+                .domain_error(DomainErrorType::SourceSink, address_sink);
+            let stub = functor_stub(atom!("http_open"), 3);
+
+            return Err(self.machine_st.error_form(err, stub));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn http_listen(&mut self) -> CallResult {
+        let address_sink = self.deref_register(1);
+        let tls_key = self.deref_register(3);
+        let tls_cert = self.deref_register(4);
+        let content_length_limit = self.deref_register(5);
+        const CONTENT_LENGTH_LIMIT_DEFAULT: u64 = 32768;
+        let content_length_limit =
+            match Number::try_from((content_length_limit, &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => {
+                    if n.get_num() >= 0 {
+                        n.get_num() as u64
+                    } else {
+                        CONTENT_LENGTH_LIMIT_DEFAULT
+                    }
+                }
+                Ok(Number::Integer(n)) => {
+                    let n: Result<u64, _> = (&*n).try_into();
+                    match n {
+                        Ok(u) => u,
+                        Err(_) => CONTENT_LENGTH_LIMIT_DEFAULT,
+                    }
+                }
+                _ => CONTENT_LENGTH_LIMIT_DEFAULT,
+            };
+
+        let ssl_server: Option<(String, String)> = {
+            match self.machine_st.value_to_str_like(tls_key) {
+                Some(key) => match self.machine_st.value_to_str_like(tls_cert) {
+                    Some(cert) => {
+                        let key_str = key.as_str();
+                        let cert_str = cert.as_str();
+
+                        if key_str.is_empty() || cert_str.is_empty() {
+                            None
+                        } else {
+                            Some((key_str.to_string(), cert_str.to_string()))
+                        }
+                    }
+                    None => None,
+                },
+                None => None,
+            }
+        };
+
+        if let Some(address_str) = self.machine_st.value_to_str_like(address_sink) {
+            let address_string = address_str.as_str();
+            let addr: SocketAddr = match address_string
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut s| s.next())
+            {
+                Some(addr) => addr,
+                _ => {
+                    self.machine_st.fail = true;
+                    return Ok(());
+                }
+            };
+
+            let (tx, rx) = std::sync::mpsc::sync_channel(1024);
+
+            let runtime = tokio::runtime::Handle::current();
+            let _guard = runtime.enter();
+
+            let serve = warp::body::bytes()
+                .and(warp::header::optional::<u64>(
+                    warp::http::header::CONTENT_LENGTH.as_str(),
+                ))
+                .and(warp::method())
+                .and(warp::header::headers_cloned())
+                .and(warp::path::full())
+                .and(warp::query::raw().or_else(|_| {
+                    future::ready(Ok::<(String,), warp::Rejection>(("".to_string(),)))
+                }))
+                .and(warp::ws().map(Some).or(warp::any().map(|| None)).unify())
+                .map(
+                    move |body: bytes::Bytes,
+                          content_length,
+                          method,
+                          headers: warp::http::HeaderMap,
+                          path: warp::filters::path::FullPath,
+                          query,
+                          ws: Option<warp::ws::Ws>| {
+                        if let Some(content_length) = content_length {
+                            if content_length > content_length_limit {
+                                return warp::http::Response::builder()
+                                    .status(413)
+                                    .body(warp::hyper::Body::empty())
+                                    .unwrap();
+                            }
+                        }
+
+                        let http_request_data = HttpRequestData {
+                            method,
+                            headers,
+                            path: path.as_str().to_string(),
+                            query,
+                            body: body.reader(),
+                        };
+                        let response =
+                            Arc::new((Mutex::new(false), Mutex::new(None), Condvar::new()));
+                        let http_request = HttpRequest {
+                            request_data: http_request_data,
+                            response: Arc::clone(&response),
+                        };
+                        // the handler may upgrade a WebSocket
+                        // handshake with '$http_websocket'/2.
+                        if let Some(ws) = ws {
+                            offer_websocket_upgrade(&response, ws);
+                        }
+                        // we send the request to http_accept. requests
+                        // beyond those queued for the handlers are
+                        // refused rather than queued without bound.
+                        if tx.try_send(http_request).is_err() {
+                            return warp::http::Response::builder()
+                                .status(503)
+                                .body(warp::hyper::Body::empty())
+                                .unwrap();
+                        }
+
+                        // we wait for the Response info from Prolog,
+                        // letting the runtime serve other requests
+                        // meanwhile.
+                        // the locks are only poisoned if a worker
+                        // panicked while answering, in which case the
+                        // request is answered with 500 Internal Server
+                        // Error instead.
+                        task::block_in_place(|| {
+                            let (ready, _response, cvar) = &*response;
+                            let mut ready = ready.lock().unwrap_or_else(PoisonError::into_inner);
+                            while !*ready {
+                                ready = cvar.wait(ready).unwrap_or_else(PoisonError::into_inner);
+                            }
+                        });
+                        take_websocket_upgrade(&response);
+                        {
+                            let (_, response, _) = &*response;
+                            let response = response
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .take();
+
+                            response.unwrap_or_else(|| {
+                                warp::http::Response::builder()
+                                    .status(500)
+                                    .body(warp::hyper::Body::empty())
+                                    .unwrap()
+                            })
+                        }
+                    },
+                );
+
+            runtime.spawn(async move {
+                match ssl_server {
+                    Some((key, cert)) => {
+                        warp::serve(serve).tls().key(key).cert(cert).run(addr).await
+                    }
+                    None => warp::serve(serve).run(addr).await,
+                }
+            });
+
+            let http_listener = HttpListener::register(
+                AtomTable::build_with(&self.machine_st.atom_tbl, &address_string),
+                rx,
+            );
+            let http_listener: TypedArenaPtr<HttpListener> =
+                arena_alloc!(http_listener, &mut self.machine_st.arena);
+
+            let addr = self.deref_register(2);
+            self.machine_st.bind(
+                addr.as_var().unwrap(),
+                typed_arena_ptr_as_cell!(http_listener),
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn http_lookup_listener(&mut self) {
+        // A1 = the address of a server started by http_listen/3, whose
+        // requests are accepted by the machines of its workers.
+        let address = self
+            .machine_st
+            .value_to_str_like(self.deref_register(1))
+            .map(|address| AtomTable::build_with(&self.machine_st.atom_tbl, &address.as_str()));
+
+        match address.and_then(HttpListener::lookup) {
+            Some(http_listener) => {
+                let http_listener: TypedArenaPtr<HttpListener> =
+                    arena_alloc!(http_listener, &mut self.machine_st.arena);
+
+                unify_fn!(
+                    self.machine_st,
+                    self.machine_st.registers[2],
+                    typed_arena_ptr_as_cell!(http_listener)
+                );
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn http_accept(&mut self) -> CallResult {
+        let culprit = self.deref_register(1);
+        let method = self.deref_register(2);
+        let path = self.deref_register(3);
+        let query = self.deref_register(5);
+        let stream_addr = self.deref_register(6);
+        let handle_addr = self.deref_register(7);
+        read_heap_cell!(culprit,
+        (HeapCellValueTag::Cons, cons_ptr) => {
+        match_untyped_arena_ptr!(cons_ptr,
+            (ArenaHeaderTag::HttpListener, http_listener) => {
+            loop {
+                let Ok(incoming) = http_listener.incoming.lock() else {
+                    // a worker panicked while accepting a request.
+                    let stub = functor_stub(atom!("http_accept"), 7);
+                    let err = self.machine_st.unreachable_error();
+                    return Err(self.machine_st.error_form(err, stub));
+                };
+
+                let received = incoming.recv_timeout(std::time::Duration::from_millis(200));
+
+                drop(incoming);
+
+                match received {
+                    Ok(request) => {
+                        let method_atom = match request.request_data.method {
+                            Method::GET => atom!("get"),
+                            Method::POST => atom!("post"),
+                            Method::PUT => atom!("put"),
+                            Method::DELETE => atom!("delete"),
+                            Method::PATCH => atom!("patch"),
+                            Method::HEAD => atom!("head"),
+                            Method::OPTIONS => atom!("options"),
+                            Method::TRACE => atom!("trace"),
+                            Method::CONNECT => atom!("connect"),
+                            _ => atom!("unsupported_extension"),
+                        };
+
+                        let path_atom = AtomTable::build_with(&self.machine_st.atom_tbl, &request.request_data.path);
+                        let path_cell = resource_error_call_result!(
+                            self.machine_st,
+                            self.machine_st.heap.allocate_cstr(&request.request_data.path)
+                        );
+
+                        let mut headers = vec![];
+
+                        for (header_name, header_value) in request.request_data.headers {
+                            let header_value = resource_error_call_result!(
+                                self.machine_st,
+                                self.machine_st.heap.allocate_cstr(header_value.to_str().unwrap())
+                            );
+
+                            let header_term = functor!(
+                                AtomTable::build_with(&self.machine_st.atom_tbl, header_name.unwrap().as_str()),
+                                [cell(header_value)]
+                            );
+
+                            let mut functor_writer = Heap::functor_writer(header_term);
+
+                            let functor_cell = resource_error_call_result!(
+                                self.machine_st,
+                                functor_writer(&mut self.machine_st.heap)
+                            );
+
+                            headers.push(functor_cell);
+                        }
+
+                        let headers_list_cell = resource_error_call_result!(
+                            self.machine_st,
+                            sized_iter_to_heap_list(
+                                &mut self.machine_st.heap,
+                                headers.len(),
+                                headers.into_iter(),
+                            )
+                        );
+
+                        let query_str  = request.request_data.query;
+                        let query_cell = resource_error_call_result!(
+                            self.machine_st,
+                            self.machine_st.heap.allocate_cstr(&query_str)
+                        );
+
+                        let mut stream = Stream::from_http_stream(
+                            path_atom,
+                            request.request_data.body,
+                            &mut self.machine_st.arena
+                        );
+                        *stream.options_mut() = StreamOptions::default();
+                        stream.options_mut().set_stream_type(StreamType::Binary);
+
+                        self.indices.add_stream(stream, atom!("http_accept"), 7)
+                            .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+
+                        let stream = stream_as_cell!(stream);
+
+                        let handle = arena_alloc!(request.response, &mut self.machine_st.arena)
+                            as TypedArenaPtr<HttpResponse>;
+
+                        self.machine_st.bind(method.as_var().unwrap(), atom_as_cell!(method_atom));
+                        self.machine_st.bind(path.as_var().unwrap(), path_cell);
+                        unify_fn!(self.machine_st, headers_list_cell, self.machine_st.registers[4]);
+                        self.machine_st.bind(query.as_var().unwrap(), query_cell);
+                        self.machine_st.bind(stream_addr.as_var().unwrap(), stream);
+                        self.machine_st.bind(handle_addr.as_var().unwrap(), typed_arena_ptr_as_cell!(handle));
+
+                        break
+                    }
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                        if threads::take_cancellation() {
+                            self.machine_st.throw_thread_cancelled_exception();
+                            self.machine_st.backtrack();
+                            break
+                        }
+
+                        let interrupted = machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed);
+
+                        match machine::INTERRUPT.compare_exchange(
+                            interrupted,
+                            false,
+                            std::sync::atomic::Ordering::Relaxed,
+                            std::sync::atomic::Ordering::Relaxed,
+                        ) {
+                            Ok(interruption) => {
+                                if interruption {
+                                    self.machine_st.throw_interrupt_exception();
+                                    self.machine_st.backtrack();
+                                    // We have extracted controll over the Tokio runtime to the calling context for enabling library use case
+                                    // (see https://github.com/mthom/scryer-prolog/pull/1880)
+                                    // So we only have access to a runtime handle in here and can't shut it down.
+                                    // Since I'm not aware of the consequences of deactivating this new code which came in while PR 1880
+                                    // was not merged, I'm only deactivating it for now.
+                                    //let old_runtime = std::mem::replace(&mut self.runtime, tokio::runtime::Runtime::new().unwrap());
+                                    //old_runtime.shutdown_background();
+                                    break
+                                }
+                            }
+                            Err(_) => unreachable!(),
+                        }
+                    }
+                  Err(_) => {
+                      self.machine_st.fail = true;
+                  }
+                }
+            }
+            }
+            _ => {
+                    unreachable!();
+                }
+            );
+            }
+            _ => {
+                unreachable!();
+            }
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn http_answer(&mut self) -> CallResult {
+        let culprit = self.deref_register(1);
+        let status_code = self.deref_register(2);
+        let status_code: u16 = match Number::try_from((status_code, &self.machine_st.arena.f64_tbl))
+        {
+            Ok(Number::Fixnum(n)) => n.get_num() as u16,
+            Ok(Number::Integer(n)) => {
+                let n: Result<u16, _> = (&*n).try_into();
+
+                if let Ok(value) = n {
+                    value
+                } else {
+                    self.machine_st.fail = true;
+                    return Ok(());
+                }
+            }
+            _ => unreachable!(),
+        };
+        let stub_gen = || functor_stub(atom!("http_listen"), 2);
+        let headers = match self
+            .machine_st
+            .try_from_list(self.machine_st.registers[3], stub_gen)
+        {
+            Ok(addrs) => {
+                let mut header_map = HeaderMap::new();
+                for heap_cell in addrs {
+                    read_heap_cell!(heap_cell,
+                        (HeapCellValueTag::Str, s) => {
+                            let name = cell_as_atom_cell!(self.machine_st.heap[s]).get_name();
+                            let value = self.machine_st.value_to_str_like(self.machine_st.heap[s + 1]).unwrap();
+                            header_map.insert(HeaderName::from_str(&name.as_str()).unwrap(), HeaderValue::from_str(&value.as_str()).unwrap());
+                        }
+                        _ => {
+                            unreachable!()
+                        }
+                    )
+                }
+                header_map
+            }
+            Err(e) => return Err(e),
+        };
+        // A4 = true if the body is sent in chunks as the stream is
+        // flushed, rather than when it is closed.
+        let chunked = self.deref_register(4) == atom_as_cell!(atom!("true"));
+        let stream_addr = self.deref_register(5);
+
+        read_heap_cell!(culprit,
+            (HeapCellValueTag::Cons, cons_ptr) => {
+                match_untyped_arena_ptr!(cons_ptr,
+                    (ArenaHeaderTag::HttpResponse, http_response) => {
+                        let mut stream = Stream::from_http_sender(
+                            http_response,
+                            status_code,
+                            headers,
+                            chunked,
+                            &mut self.machine_st.arena
+                        );
+
+                        *stream.options_mut() = StreamOptions::default();
+                        stream.options_mut().set_stream_type(StreamType::Binary);
+
+                        self.indices.add_stream(stream, atom!("http_answer"), 5)
+                            .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+                        self.machine_st.bind(stream_addr.as_var().unwrap(), stream.into());
+                    }
+                    _ => {
+                        unreachable!();
+                    }
+                );
+            }
+            _ => {
+                unreachable!();
+            }
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn http_websocket(&mut self) -> CallResult {
+        // A1 = the handle of the response to a request, A2 = the
+        // WebSocket its handshake is upgraded to. fails if the request
+        // isn't a WebSocket handshake, leaving it to be answered.
+        let http_response = read_heap_cell!(self.deref_register(1),
+            (HeapCellValueTag::Cons, cons_ptr) => {
+                match_untyped_arena_ptr!(cons_ptr,
+                    (ArenaHeaderTag::HttpResponse, http_response) => {
+                        http_response
+                    }
+                    _ => {
+                        unreachable!();
+                    }
+                )
+            }
+            _ => {
+                unreachable!();
+            }
+        );
+
+        let Some(ws) = take_websocket_upgrade(&http_response) else {
+            self.machine_st.fail = true;
+            return Ok(());
+        };
+
+        // the socket is sent once the handshake is answered and the
+        // connection upgraded.
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        let reply = ws.on_upgrade(move |socket| async move {
+            let _ = tx.send(socket);
+        });
+
+        crate::http::answer(&http_response, warp::Reply::into_response(reply));
+
+        match task::block_in_place(|| Handle::current().block_on(rx)) {
+            Ok(socket) => {
+                let web_socket: TypedArenaPtr<WebSocket> =
+                    arena_alloc!(WebSocket::Server(socket), &mut self.machine_st.arena);
+                unify_fn!(
+                    self.machine_st,
+                    self.machine_st.registers[2],
+                    typed_arena_ptr_as_cell!(web_socket)
+                );
+            }
+            Err(_) => {
+                self.machine_st.fail = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn ws_open(&mut self) -> CallResult {
+        // A1 = the URL, A2 = the headers of the handshake as
+        // Name(Value) terms, A3 = the WebSocket, all but A3 validated
+        // by ws_open/3.
+        let stub_gen = || functor_stub(atom!("ws_open"), 3);
+        let url = self.deref_register(1);
+
+        let mut request = match self
+            .machine_st
+            .value_to_str_like(url)
+            .and_then(|url| url.as_str().into_client_request().ok())
+        {
+            Some(request) => request,
+            None => {
+                let err = self
+                    .machine_st
+                    .domain_error(DomainErrorType::SourceSink, url);
+                return Err(self.machine_st.error_form(err, stub_gen()));
+            }
+        };
+
+        for heap_cell in self
+            .machine_st
+            .try_from_list(self.machine_st.registers[2], stub_gen)?
+        {
+            read_heap_cell!(heap_cell,
+                (HeapCellValueTag::Str, s) => {
+                    let name = cell_as_atom_cell!(self.machine_st.heap[s]).get_name();
+                    let value = self.machine_st.value_to_str_like(self.machine_st.heap[s + 1]).unwrap();
+
+                    match (
+                        tungstenite::http::HeaderName::from_str(&name.as_str()),
+                        tungstenite::http::HeaderValue::from_str(&value.as_str()),
+                    ) {
+                        (Ok(name), Ok(value)) => {
+                            request.headers_mut().insert(name, value);
+                        }
+                        _ => {
+                            let err = self.machine_st.domain_error(DomainErrorType::HttpHeader, heap_cell);
+                            return Err(self.machine_st.error_form(err, stub_gen()));
+                        }
+                    }
+                }
+                _ => {
+                    unreachable!()
+                }
+            )
+        }
+
+        match WebSocket::connect(request) {
+            Ok(web_socket) => {
+                let web_socket: TypedArenaPtr<WebSocket> =
+                    arena_alloc!(web_socket, &mut self.machine_st.arena);
+                unify_fn!(
+                    self.machine_st,
+                    self.machine_st.registers[3],
+                    typed_arena_ptr_as_cell!(web_socket)
+                );
+                Ok(())
+            }
+            Err(_) => {
+                let err = self
+                    .machine_st
+                    .existence_error(ExistenceError::SourceSink(url));
+                Err(self.machine_st.error_form(err, stub_gen()))
+            }
+        }
+    }
+
+    #[cfg(feature = "http")]
+    fn web_socket(
+        &mut self,
+        web_socket: HeapCellValue,
+        stub_gen: impl Fn() -> MachineStub,
+    ) -> Result<TypedArenaPtr<WebSocket>, MachineStub> {
+        if let Some(ptr) = web_socket.to_untyped_arena_ptr() {
+            match_untyped_arena_ptr!(ptr,
+                (ArenaHeaderTag::WebSocket, web_socket) => {
+                    return Ok(web_socket);
+                }
+                (ArenaHeaderTag::Dropped, _dropped) => {
+                    let err = self.machine_st.existence_error(ExistenceError::WebSocket(web_socket));
+                    return Err(self.machine_st.error_form(err, stub_gen()));
+                }
+                _ => {
+                }
+            );
+        }
+
+        let err = self.machine_st.type_error(ValidType::WebSocket, web_socket);
+        Err(self.machine_st.error_form(err, stub_gen()))
+    }
+
+    #[cfg(feature = "http")]
+    fn web_socket_text(&mut self, text: &str) -> Result<HeapCellValue, usize> {
+        if text.is_empty() {
+            Ok(empty_list_as_cell!())
+        } else {
+            self.machine_st.heap.allocate_cstr(text)
+        }
+    }
+
+    #[cfg(feature = "http")]
+    fn web_socket_bytes(&mut self, bytes: Vec<u8>) -> Result<HeapCellValue, usize> {
+        sized_iter_to_heap_list(
+            &mut self.machine_st.heap,
+            bytes.len(),
+            bytes
+                .into_iter()
+                .map(|byte| fixnum_as_cell!(Fixnum::build_with(byte))),
+        )
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn ws_send(&mut self) -> CallResult {
+        // A2 = text, binary, ping or pong, A3 = the payload as bytes,
+        // validated by ws_send/2.
+        let stub_gen = || functor_stub(atom!("ws_send"), 2);
+        let web_socket_cell = self.deref_register(1);
+        let mut web_socket = self.web_socket(web_socket_cell, stub_gen)?;
+        let payload = self
+            .machine_st
+            .integers_to_bytevec(self.machine_st.registers[3], stub_gen);
+
+        let message = match cell_as_atom!(self.deref_register(2)) {
+            atom!("text") => match String::from_utf8(payload) {
+                Ok(text) => WebSocketMessage::Text(text),
+                Err(_) => unreachable!(),
+            },
+            atom!("binary") => WebSocketMessage::Binary(payload),
+            atom!("ping") => WebSocketMessage::Ping(payload),
+            atom!("pong") => WebSocketMessage::Pong(payload),
+            _ => unreachable!(),
+        };
+
+        if web_socket.send(message).is_err() {
+            let err = self
+                .machine_st
+                .existence_error(ExistenceError::WebSocket(web_socket_cell));
+            return Err(self.machine_st.error_form(err, stub_gen()));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn ws_receive(&mut self) -> CallResult {
+        let stub_gen = || functor_stub(atom!("ws_receive"), 2);
+        let mut web_socket = self.web_socket(self.deref_register(1), stub_gen)?;
+
+        // a connection that fails is as closed.
+        let message = match web_socket.receive() {
+            Ok(Some(message)) => message,
+            Ok(None) | Err(_) => {
+                unify_fn!(
+                    self.machine_st,
+                    self.machine_st.registers[2],
+                    atom_as_cell!(atom!("end_of_file"))
+                );
+                return Ok(());
+            }
+        };
+
+        let message = match message {
+            WebSocketMessage::Text(text) => {
+                let text =
+                    resource_error_call_result!(self.machine_st, self.web_socket_text(&text));
+                functor!(atom!("text"), [cell(text)])
+            }
+            WebSocketMessage::Binary(bytes) => {
+                let bytes =
+                    resource_error_call_result!(self.machine_st, self.web_socket_bytes(bytes));
+                functor!(atom!("binary"), [cell(bytes)])
+            }
+            WebSocketMessage::Ping(bytes) => {
+                let bytes =
+                    resource_error_call_result!(self.machine_st, self.web_socket_bytes(bytes));
+                functor!(atom!("ping"), [cell(bytes)])
+            }
+            WebSocketMessage::Pong(bytes) => {
+                let bytes =
+                    resource_error_call_result!(self.machine_st, self.web_socket_bytes(bytes));
+                functor!(atom!("pong"), [cell(bytes)])
+            }
+            WebSocketMessage::Close(code, reason) => {
+                let reason =
+                    resource_error_call_result!(self.machine_st, self.web_socket_text(&reason));
+                functor!(atom!("close"), [fixnum(code), cell(reason)])
+            }
+        };
+
+        let mut writer = Heap::functor_writer(message);
+        let message =
+            resource_error_call_result!(self.machine_st, writer(&mut self.machine_st.heap));
+
+        unify_fn!(self.machine_st, self.machine_st.registers[2], message);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "http")]
+    #[inline(always)]
+    pub(crate) fn ws_close(&mut self) -> CallResult {
+        // A2 = the status code, A3 = the reason as bytes, validated by
+        // ws_close/3. closing a closed WebSocket succeeds.
+        let web_socket = self.deref_register(1);
+
+        if let Some(ptr) = web_socket.to_untyped_arena_ptr() {
+            match_untyped_arena_ptr!(ptr,
+                (ArenaHeaderTag::WebSocket, web_socket) => {
+                    let stub_gen = || functor_stub(atom!("ws_close"), 3);
+                    let code = match self.deref_register(2).to_fixnum() {
+                        Some(code) => code.get_num() as u16,
+                        None => unreachable!(),
+                    };
+                    let reason = self
+                        .machine_st
+                        .integers_to_bytevec(self.machine_st.registers[3], stub_gen);
+
+                    // the peer may have closed the connection already.
+                    let _ = web_socket.send(WebSocketMessage::Close(
+                        code,
+                        String::from_utf8_lossy(&reason).into_owned(),
+                    ));
+
+                    web_socket.drop_payload();
+                    return Ok(());
+                }
+                (ArenaHeaderTag::Dropped, _dropped) => {
+                    return Ok(());
+                }
+                _ => {
+                }
+            );
+        }
+
+        let err = self.machine_st.type_error(ValidType::WebSocket, web_socket);
+        Err(self
+            .machine_st
+            .error_form(err, functor_stub(atom!("ws_close"), 3)))
+    }
+
+    #[cfg(feature = "ffi")]
+    #[inline(always)]
+    pub(crate) fn load_foreign_lib(&mut self) -> CallResult {
+        let library_name = self.deref_register(1);
+        let args_reg = self.deref_register(2);
+        if let Some(library_name) = self.machine_st.value_to_str_like(library_name) {
+            let stub_gen = || functor_stub(atom!("use_foreign_module"), 2);
+            match self.machine_st.try_from_list(args_reg, stub_gen) {
+                Ok(addrs) => {
+                    let mut functions = Vec::new();
+                    for heap_cell in addrs {
+                        read_heap_cell!(heap_cell,
+                            (HeapCellValueTag::Str, s) => {
+                                let name = cell_as_atom_cell!(self.machine_st.heap[s]).get_name();
+                            let args: Vec<Atom> = match self.machine_st.try_from_list(self.machine_st.heap[s + 1], stub_gen) {
+                                Ok(addrs) => {
+                                let mut args = Vec::new();
+                                for heap_cell in addrs {
+                                    args.push(cell_as_atom_cell!(heap_cell).get_name());
+                                }
+                                args
+                                }
+                                Err(e) => return Err(e)
+                            };
+                            let return_value = cell_as_atom_cell!(self.machine_st.heap[s + 2]);
+                            functions.push(FunctionDefinition {
+                                name: name.as_str().to_string(),
+                                args,
+                                return_value: return_value.get_name(),
+                            });
+                            }
+                            _ => {
+                                unreachable!()
+                                }
+                        )
+                    }
+                    if self
+                        .foreign_function_table
+                        .load_library(&library_name.as_str(), &functions)
+                        .is_ok()
+                    {
+                        return Ok(());
+                    }
+                }
+                Err(e) => return Err(e),
+            };
+        }
+        self.machine_st.fail = true;
+        Ok(())
+    }
+
+    #[cfg(feature = "ffi")]
+    #[inline(always)]
+    pub(crate) fn foreign_call(&mut self) -> CallResult {
+        let function_name = self.deref_register(1);
+        let args_reg = self.deref_register(2);
+        let return_value = self.deref_register(3);
+        if let Some(function_name) = self.machine_st.value_to_str_like(function_name) {
+            let stub_gen = || functor_stub(atom!("foreign_call"), 3);
+            fn map_arg(machine_st: &mut MachineState, source: HeapCellValue) -> crate::ffi::Value {
+                match Number::try_from((source, &machine_st.arena.f64_tbl)) {
+                    Ok(Number::Fixnum(n)) => Value::Int(n.get_num()),
+                    Ok(Number::Float(n)) => Value::Float(n.into_inner()),
+                    _ => {
+                        let stub_gen = || functor_stub(atom!("foreign_call"), 3);
+                        if let Some(string) = machine_st.value_to_str_like(source) {
+                            Value::CString(CString::new(&*string.as_str()).unwrap())
+                        } else {
+                            match machine_st.try_from_list(source, stub_gen) {
+                                Ok(args) => {
+                                    let mut iter = args.into_iter();
+                                    if let Some(struct_name) =
+                                        machine_st.value_to_str_like(iter.next().unwrap())
+                                    {
+                                        Value::Struct(
+                                            struct_name.as_str().to_string(),
+                                            iter.map(|x| map_arg(machine_st, x)).collect(),
+                                        )
+                                    } else {
+                                        unreachable!()
+                                    }
+                                }
+                                _ => {
+                                    unreachable!()
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            match self.machine_st.try_from_list(args_reg, stub_gen) {
+                Ok(args) => {
+                    let args: Vec<_> = args
+                        .into_iter()
+                        .map(|x| map_arg(&mut self.machine_st, x))
+                        .collect();
+                    match self
+                        .foreign_function_table
+                        .exec(&function_name.as_str(), args)
+                    {
+                        Ok(result) => {
+                            match result {
+                                Value::Int(n) => self.machine_st.unify_fixnum(
+                                    Fixnum::build_with_checked(n).unwrap_or_else(|_| {
+                                        todo!("handle integer values that don't fit in fixnum")
+                                    }),
+                                    return_value,
+                                ),
+                                Value::Float(n) => {
+                                    let n = float_alloc!(n, self.machine_st.arena);
+                                    self.machine_st.unify_f64(n, return_value)
+                                }
+                                Value::Struct(name, args) => {
+                                    let struct_value = resource_error_call_result!(
+                                        self.machine_st,
+                                        self.build_struct(&name, args)
+                                    );
+
+                                    unify_fn!(self.machine_st, return_value, struct_value);
+                                }
+                                Value::CString(cstr) => {
+                                    let str_cell = resource_error_call_result!(
+                                        self.machine_st,
+                                        self.machine_st.heap.allocate_cstr(cstr.to_str().unwrap())
+                                    );
+
+                                    unify_fn!(self.machine_st, str_cell, return_value);
+                                }
+                            }
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            let stub = functor_stub(atom!("current_input"), 1);
+                            let err = self.machine_st.ffi_error(e);
+
+                            return Err(self.machine_st.error_form(err, stub));
+                        }
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.machine_st.fail = true;
+        Ok(())
+    }
+
+    #[cfg(feature = "ffi")]
+    fn build_struct(&mut self, name: &str, mut args: Vec<Value>) -> Result<HeapCellValue, usize> {
+        args.insert(0, Value::CString(CString::new(name).unwrap()));
+
+        let mut expanded_args = Vec::with_capacity(args.len());
+
+        for val in args {
+            expanded_args.push(match val {
+                Value::Int(n) => {
+                    if let Ok(fixnum) = Fixnum::build_with_checked(n) {
+                        fixnum_as_cell!(fixnum)
+                    } else {
+                        integer_as_cell!(Number::Integer(arena_alloc!(
+                            Integer::from(n),
+                            &mut self.machine_st.arena
+                        )))
+                    }
+                }
+                Value::Float(n) => HeapCellValue::from(float_alloc!(n, self.machine_st.arena)),
+                Value::CString(cstr) => atom_as_cell!(AtomTable::build_with(
+                    &self.machine_st.atom_tbl,
+                    &cstr.into_string().unwrap()
+                )),
+                Value::Struct(name, struct_args) => self.build_struct(&name, struct_args)?,
+            });
+        }
+
+        sized_iter_to_heap_list(
+            &mut self.machine_st.heap,
+            expanded_args.len(),
+            expanded_args.into_iter(),
+        )
+    }
+
+    #[cfg(feature = "ffi")]
+    #[inline(always)]
+    pub(crate) fn define_foreign_struct(&mut self) -> CallResult {
+        let struct_name = self.deref_register(1);
+        let fields_reg = self.deref_register(2);
+        if let Some(struct_name) = self.machine_st.value_to_str_like(struct_name) {
+            let stub_gen = || functor_stub(atom!("define_foreign_struct"), 2);
+            let fields: Vec<Atom> = match self.machine_st.try_from_list(fields_reg, stub_gen) {
+                Ok(addrs) => {
+                    let mut args = Vec::new();
+                    for heap_cell in addrs {
+                        args.push(cell_as_atom_cell!(heap_cell).get_name());
+                    }
+                    args
+                }
+                Err(e) => return Err(e),
+            };
+            self.foreign_function_table
+                .define_struct(&struct_name.as_str(), fields);
+            return Ok(());
+        }
+        self.machine_st.fail = true;
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline(always)]
+    pub(crate) fn js_eval(&mut self) -> CallResult {
+        unimplemented!()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[inline(always)]
+    pub(crate) fn js_eval(&mut self) -> CallResult {
+        let code = self.deref_register(1);
+        let result_reg = self.deref_register(2);
+        if let Some(code) = self.machine_st.value_to_str_like(code) {
+            match js_sys::eval(&code.as_str()) {
+                Ok(result) => self.unify_js_value(result, result_reg)?,
+                Err(result) => self.unify_js_value(result, result_reg)?,
+            };
+
+            return Ok(());
+        }
+        self.machine_st.fail = true;
+        Ok(())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn unify_js_value(
+        &mut self,
+        result: wasm_bindgen::JsValue,
+        result_reg: HeapCellValue,
+    ) -> CallResult {
+        match result.as_bool() {
+            Some(result) => match result {
+                true => self.machine_st.unify_atom(atom!("true"), result_reg),
+                false => self.machine_st.unify_atom(atom!("false"), result_reg),
+            },
+            None => match result.as_f64() {
+                Some(result) => {
+                    let n = float_alloc!(result, self.machine_st.arena);
+                    self.machine_st.unify_f64(n, result_reg);
+                }
+                None => match result.as_string() {
+                    Some(result) => {
+                        resource_error_call_result!(
+                            self.machine_st,
+                            self.machine_st.heap.allocate_cstr(result.as_str())
+                        );
+                    }
+                    None => {
+                        if result.is_null() {
+                            self.machine_st.unify_atom(atom!("null"), result_reg);
+                        } else if result.is_undefined() {
+                            self.machine_st.unify_atom(atom!("undefined"), result_reg);
+                        } else if result.is_symbol() {
+                            self.machine_st.unify_atom(atom!("js_symbol"), result_reg);
+                        } else if result.is_object() {
+                            self.machine_st.unify_atom(atom!("js_object"), result_reg);
+                        } else if result.is_array() {
+                            self.machine_st.unify_atom(atom!("js_array"), result_reg);
+                        } else if result.is_function() {
+                            self.machine_st.unify_atom(atom!("js_function"), result_reg);
+                        } else if result.is_bigint() {
+                            self.machine_st.unify_atom(atom!("js_bigint"), result_reg);
+                        } else {
+                            self.machine_st
+                                .unify_atom(atom!("js_unknown_type"), result_reg);
+                        }
+                    }
+                },
+            },
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn argv(&mut self) -> CallResult {
+        let args = self.deref_register(1);
+        let mut args_pstrs = vec![];
+
+        for arg in env::args() {
+            let pstr_cell = resource_error_call_result!(
+                self.machine_st,
+                self.machine_st.heap.allocate_cstr(&arg)
+            );
+
+            args_pstrs.push(pstr_cell);
+        }
+
+        let list_cell = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                args_pstrs.len(),
+                args_pstrs.into_iter(),
+            )
+        );
+
+        unify_fn!(self.machine_st, args, list_cell);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn current_time(&mut self) {
+        let timestamp = self.systemtime_to_timestamp(SystemTime::now());
+        let cstr_cell = step_or_resource_error!(
+            self.machine_st,
+            self.machine_st.heap.allocate_cstr(&timestamp)
+        );
+
+        unify_fn!(self.machine_st, cstr_cell, self.machine_st.registers[1]);
+    }
+
+    #[inline(always)]
+    pub(crate) fn wall_time(&mut self) {
+        let now = web_time::SystemTime::now();
+
+        let secs = match now.duration_since(web_time::SystemTime::UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs_f64(),
+            Err(err) => -err.duration().as_secs_f64(),
+        };
+        let secs = float_alloc!(secs, self.machine_st.arena);
+
+        self.machine_st
+            .unify_f64(secs, self.machine_st.registers[1]);
+    }
+
+    // the seconds since the first call, by a clock that never goes
+    // back, unlike the time of the system.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline(always)]
+    pub(crate) fn monotonic_time(&mut self) {
+        static START: OnceLock<Instant> = OnceLock::new();
+
+        let secs = START.get_or_init(Instant::now).elapsed().as_secs_f64();
+        let secs = float_alloc!(secs, self.machine_st.arena);
+
+        self.machine_st
+            .unify_f64(secs, self.machine_st.registers[1]);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[inline(always)]
+    pub(crate) fn monotonic_time(&mut self) {
+        let millisecs = web_sys::window()
+            .expect("window global object should be available")
+            .performance()
+            .expect("performance property in window should be available")
+            .now();
+        let secs = float_alloc!(millisecs / 1000.0, self.machine_st.arena);
+
+        self.machine_st.unify_f64(secs, self.deref_register(1));
+    }
+
+    // A1 = the time stamp, A2 = local or the offset in seconds east of
+    // UTC, A3 = the fields of the date and time of the stamp there.
+    // fails if the stamp is out of range.
+    #[inline(always)]
+    pub(crate) fn stamp_date_time(&mut self) {
+        let stamp = match Number::try_from((self.deref_register(1), &self.machine_st.arena.f64_tbl))
+        {
+            Ok(Number::Fixnum(n)) => Some((n.get_num(), 0)),
+            Ok(Number::Float(f)) if f.is_finite() && f.abs() < 1e15 => {
+                let secs = f.floor();
+                let nanos = ((f.into_inner() - secs) * 1e9).round().min(999_999_999.0);
+                Some((secs as i64, nanos as u32))
+            }
+            _ => None,
+        };
+
+        let fields = stamp.and_then(|(secs, nanos)| {
+            let zone = self.deref_register(2);
+
+            if zone == atom_as_cell!(atom!("local")) {
+                Local
+                    .timestamp_opt(secs, nanos)
+                    .single()
+                    .map(|date_time| date_time_fields(&date_time))
+            } else {
+                let offset = match Number::try_from((zone, &self.machine_st.arena.f64_tbl)) {
+                    Ok(Number::Fixnum(n)) => i32::try_from(n.get_num()).ok(),
+                    _ => None,
+                };
+
+                offset
+                    .and_then(FixedOffset::east_opt)
+                    .and_then(|offset| offset.timestamp_opt(secs, nanos).single())
+                    .map(|date_time| date_time_fields(&date_time))
+            }
+        });
+
+        let Some(fields) = fields else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let list_cell = step_or_resource_error!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                fields.len(),
+                fields
+                    .into_iter()
+                    .map(|field| fixnum_as_cell!(Fixnum::build_with(field)))
+            )
+        );
+
+        unify_fn!(self.machine_st, list_cell, self.machine_st.registers[3]);
+    }
+
+    // the integers of the list in register r, or None if it has
+    // anything else.
+    fn fixnum_list(&mut self, r: usize) -> Option<Vec<i64>> {
+        let stub_gen = || functor_stub(atom!("date_time_stamp"), 2);
+        let cells = self
+            .machine_st
+            .try_from_list(self.machine_st.registers[r], stub_gen)
+            .ok()?;
+
+        cells
+            .into_iter()
+            .map(|cell| {
+                let cell = self.machine_st.store(self.machine_st.deref(cell));
+
+                match Number::try_from((cell, &self.machine_st.arena.f64_tbl)) {
+                    Ok(Number::Fixnum(n)) => Some(n.get_num()),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    // A1 = the fields of a date and time, A2 = its time stamp, an
+    // integer if it has no fraction of a second. fails if the fields
+    // aren't those of a valid date and time.
+    #[inline(always)]
+    pub(crate) fn date_time_stamp(&mut self) {
+        let Some(date_time) = self
+            .fixnum_list(1)
+            .and_then(|fields| fields_date_time(&fields))
+        else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let secs = date_time.timestamp();
+        let nanos = date_time.timestamp_subsec_nanos();
+
+        if nanos == 0 {
+            if let Ok(secs) = Fixnum::build_with_checked(secs) {
+                self.machine_st
+                    .unify_fixnum(secs, self.machine_st.registers[2]);
+                return;
+            }
+        }
+
+        let secs = float_alloc!(secs as f64 + nanos as f64 / 1e9, self.machine_st.arena);
+
+        self.machine_st
+            .unify_f64(secs, self.machine_st.registers[2]);
+    }
+
+    // A1 = the strftime format, A2 = the fields of a date and time,
+    // A3 = the formatted string. fails if the format or the fields are
+    // invalid.
+    #[inline(always)]
+    pub(crate) fn format_date_time(&mut self) {
+        // the empty string [] is not str-like.
+        let format = self
+            .machine_st
+            .value_to_str_like(self.deref_register(1))
+            .map(|format| format.as_str().to_string())
+            .unwrap_or_default();
+
+        // StrftimeItems yields Item::Error without end past an invalid
+        // specifier.
+        let mut items = vec![];
+
+        for item in StrftimeItems::new(&format) {
+            if item == Item::Error {
+                self.machine_st.fail = true;
+                return;
+            }
+
+            items.push(item);
+        }
+
+        let Some(date_time) = self
+            .fixnum_list(2)
+            .and_then(|fields| fields_date_time(&fields))
+        else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let mut chars = String::new();
+
+        let formatted = format_args!("{}", date_time.format_with_items(items.into_iter()));
+
+        if std::fmt::write(&mut chars, formatted).is_err() {
+            self.machine_st.fail = true;
+            return;
+        }
+
+        let cstr_cell =
+            step_or_resource_error!(self.machine_st, self.machine_st.heap.allocate_cstr(&chars));
+
+        unify_fn!(self.machine_st, cstr_cell, self.machine_st.registers[3]);
+    }
+
+    // A1 = the string, A2 = the strftime format, A3 = the fields of the
+    // date and time the string describes. fails if it doesn't match the
+    // format or describe a date.
+    #[inline(always)]
+    pub(crate) fn parse_date_time(&mut self) {
+        let mut string = |r: usize| {
+            self.machine_st
+                .value_to_str_like(self.machine_st.registers[r])
+                .map(|string| string.as_str().to_string())
+                .unwrap_or_default()
+        };
+
+        let text = string(1);
+        let format = string(2);
+
+        let Some(date_time) = parse_date_time(&text, &format) else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let fields = date_time_fields(&date_time);
+
+        let list_cell = step_or_resource_error!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                fields.len(),
+                fields
+                    .into_iter()
+                    .map(|field| fixnum_as_cell!(Fixnum::build_with(field)))
+            )
+        );
+
+        unify_fn!(self.machine_st, list_cell, self.machine_st.registers[3]);
+    }
+
+    #[inline(always)]
+    pub(crate) fn open(&mut self) -> CallResult {
+        let alias = self.machine_st.registers[4];
+        let eof_action = self.machine_st.registers[5];
+        let reposition = self.machine_st.registers[6];
+        let stream_type = self.machine_st.registers[7];
+
+        let options =
+            self.machine_st
+                .get_stream_options(alias, eof_action, reposition, stream_type);
+        let src_sink = self.deref_register(1);
+
+        if let Some(file_spec) = self.machine_st.value_to_str_like(src_sink) {
+            let file_spec = file_spec.as_atom(&self.machine_st.atom_tbl);
+
+            let mut stream =
+                self.machine_st
+                    .stream_from_file_spec(file_spec, &mut self.indices, &options)?;
+
+            *stream.options_mut() = options;
+
+            self.indices
+                .add_stream(stream, atom!("open"), 4)
+                .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+
+            let stream_var = self.deref_register(3);
+            self.machine_st
+                .bind(stream_var.as_var().unwrap(), stream.into());
+        } else {
+            let err = self
+                .machine_st
+                .domain_error(DomainErrorType::SourceSink, src_sink);
+            let stub = functor_stub(atom!("open"), 4);
+
+            return Err(self.machine_st.error_form(err, stub));
+        }
+
+        Ok(())
+    }
+
+    // opens the resource registered by the embedder under the name of
+    // resource(Name) in register 1 as an input stream.
+    pub(crate) fn open_resource(&mut self) -> CallResult {
+        let alias = self.machine_st.registers[4];
+        let eof_action = self.machine_st.registers[5];
+        let reposition = self.machine_st.registers[6];
+        let stream_type = self.machine_st.registers[7];
+
+        let options =
+            self.machine_st
+                .get_stream_options(alias, eof_action, reposition, stream_type);
+        let src_sink = self.deref_register(1);
+
+        let name = read_heap_cell!(src_sink,
+            (HeapCellValueTag::Str, s) => {
+                let name = self.machine_st.store(self.machine_st.deref(self.machine_st.heap[s + 1]));
+                self.machine_st.value_to_str_like(name)
+            }
+            _ => {
+                None
+            }
+        );
+
+        let Some(name) = name else {
+            let err = self
+                .machine_st
+                .domain_error(DomainErrorType::SourceSink, src_sink);
+            let stub = functor_stub(atom!("open"), 4);
+
+            return Err(self.machine_st.error_form(err, stub));
+        };
+
+        match cell_as_atom!(self.deref_register(2)) {
+            atom!("read") => {}
+            atom!("write") | atom!("append") => {
+                return Err(self
+                    .machine_st
+                    .open_permission_error(src_sink, atom!("open"), 4));
+            }
+            _ => {
+                let err = self
+                    .machine_st
+                    .domain_error(DomainErrorType::IOMode, self.machine_st.registers[2]);
+                let stub = functor_stub(atom!("open"), 4);
+
+                return Err(self.machine_st.error_form(err, stub));
+            }
+        }
+
+        let contents = match self.machine_st.registered_resources.get(&*name.as_str()) {
+            Some(contents) => contents.to_vec(),
+            None => {
+                let err = self
+                    .machine_st
+                    .existence_error(ExistenceError::SourceSink(src_sink));
+                let stub = functor_stub(atom!("open"), 4);
+
+                return Err(self.machine_st.error_form(err, stub));
+            }
+        };
+
+        let mut stream = Stream::from_owned_bytes(contents, &mut self.machine_st.arena);
+
+        *stream.options_mut() = options;
+
+        self.indices
+            .add_stream(stream, atom!("open"), 4)
+            .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+
+        let stream_var = self.deref_register(3);
+        self.machine_st
+            .bind(stream_var.as_var().unwrap(), stream.into());
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn op_declaration(&mut self) -> CallResult {
+        let priority = self.deref_register(1);
+        let specifier = cell_as_atom_cell!(self.deref_register(2)).get_name();
+
+        let priority = match Number::try_from((priority, &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Integer(n)) => {
+                let n: u16 = (&*n).try_into().unwrap();
+                n
+            }
+            Ok(Number::Fixnum(n)) => u16::try_from(n.get_num()).unwrap(),
+            _ => {
+                unreachable!();
+            }
+        };
+
+        let op = read_heap_cell!(self.deref_register(3),
+            /*
+            (HeapCellValueTag::Char, c) => {
+                AtomTable::build_with(&self.machine_st.atom_tbl, &c.to_string())
+            }
+            */
+            (HeapCellValueTag::Atom, (name, _arity)) => {
+                name
+            }
+            (HeapCellValueTag::Str, s) => {
+                cell_as_atom!(self.machine_st.heap[s])
+            }
+            _ => {
+                unreachable!()
+            }
+        );
+
+        let module_name = cell_as_atom!(self.deref_register(4));
+
+        let result = to_op_decl_spec(specifier)
+            .map_err(SessionError::from)
+            .map(|specifier| to_op_decl(priority, specifier, op))
+            .and_then(|mut op_decl| {
+                // operators declared in a module other than user
+                // shadow those of user in that module only.
+                let (op_dir, spec) = match module_name {
+                    atom!("user") => {
+                        let spec = get_op_desc(
+                            op_decl.name,
+                            &CompositeOpDir::new(&self.indices.op_dir, None),
+                        );
+
+                        (&mut self.indices.op_dir, spec)
+                    }
+                    _ => match self.indices.modules.get_mut(&module_name) {
+                        Some(module) => {
+                            let spec = get_op_desc(
+                                op_decl.name,
+                                &CompositeOpDir::new(&self.indices.op_dir, Some(&module.op_dir)),
+                            );
+
+                            (&mut module.op_dir, spec)
+                        }
+                        None => {
+                            return Err(SessionError::ExistenceError(ExistenceError::Module(
+                                module_name,
+                            )));
+                        }
+                    },
+                };
+
+                if op_decl.op_desc.get_prec() == 0 {
+                    op_decl.remove(op_dir);
+                    Ok(())
+                } else {
+                    op_decl.submit(spec, op_dir)
+                }
+            });
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                // 8.14.3.3 l)
+                let err = self.machine_st.session_error(e);
+                let stub = functor_stub(atom!("op"), 3);
+
+                Err(self.machine_st.error_form(err, stub))
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_stream_options(&mut self) -> CallResult {
+        let stream = self.machine_st.get_stream_or_alias(
+            self.machine_st.registers[1],
+            &self.indices,
+            atom!("open"),
+            4,
+        )?;
+
+        let alias = self.machine_st.registers[2];
+        let eof_action = self.machine_st.registers[3];
+        let reposition = self.machine_st.registers[4];
+        let stream_type = self.machine_st.registers[5];
+
+        let new_options =
+            self.machine_st
+                .get_stream_options(alias, eof_action, reposition, stream_type);
+        self.indices.update_stream_options(stream, |options| {
+            *options = new_options;
+        });
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn truncate_if_no_lifted_heap_growth_diff(&mut self) {
+        self.machine_st
+            .truncate_if_no_lifted_heap_diff(|h| heap_loc_as_cell!(h))
+    }
+
+    #[inline(always)]
+    pub(crate) fn truncate_if_no_lifted_heap_growth(&mut self) {
+        self.machine_st
+            .truncate_if_no_lifted_heap_diff(|_| empty_list_as_cell!())
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_attributed_variable_list(&mut self) {
+        let attr_var = self.deref_register(1);
+        let attr_var_list = read_heap_cell!(attr_var,
+            (HeapCellValueTag::AttrVar, h) => {
+                h+1
+            }
+            (HeapCellValueTag::Var, h) => {
+                h
+            }
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        );
+
+        let list_addr = self.deref_register(2);
+        self.machine_st
+            .bind(Ref::heap_cell(attr_var_list), list_addr);
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_from_attributed_variable_list(&mut self) {
+        let attr_var = self.deref_register(1);
+        let attr = self.deref_register(3);
+        let attr_var_list = read_heap_cell!(attr_var,
+            (HeapCellValueTag::AttrVar, h) => {
+                self.machine_st.heap[h+1]
+            }
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        );
+
+        let module = self.deref_register(2);
+
+        match self.match_attribute(attr_var_list, module, attr) {
+            Some(AttrListMatch {
+                match_site: MatchSite::Match(match_site),
+                ..
+            }) => {
+                let list_head = self.machine_st.heap[match_site];
+
+                if list_head.get_value() as usize == match_site {
+                    // at the end of the list, no match found in this case.
+                    self.machine_st.fail = true;
+                } else {
+                    let (_, qualified_goal) = self.machine_st.strip_module(list_head);
+
+                    unify_fn!(self.machine_st, qualified_goal, attr);
+                }
+            }
+            _ => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_attr_var_queue_delimiter(&mut self) {
+        let addr = self.deref_register(1);
+
+        /* FIXME this is not safe */
+        let value = unsafe {
+            Fixnum::build_with_unchecked(self.machine_st.attr_var_init.attr_var_queue.len() as i64)
+        };
+
+        self.machine_st.unify_fixnum(value, addr);
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_attr_var_queue_beyond(&mut self) {
+        let addr = self.deref_register(1);
+
+        let b = match Number::try_from((addr, &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Integer(n)) => {
+                let value: usize = (&*n).try_into().unwrap();
+                Some(value)
+            }
+            Ok(Number::Fixnum(n)) => usize::try_from(n.get_num()).ok(),
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        if let Some(b) = b {
+            let attr_vars = self.machine_st.gather_attr_vars_created_since(b);
+
+            let var_list_addr = step_or_resource_error!(
+                self.machine_st,
+                sized_iter_to_heap_list(
+                    &mut self.machine_st.heap,
+                    attr_vars.len(),
+                    attr_vars.into_iter(),
+                )
+            );
+
+            let list_addr = self.machine_st.registers[2];
+            unify_fn!(self.machine_st, var_list_addr, list_addr);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn delete_from_attributed_variable_list(&mut self) {
+        let attr_var = self.deref_register(1);
+        let attr = self.deref_register(3);
+        let attr_var_list = read_heap_cell!(attr_var,
+            (HeapCellValueTag::AttrVar, h) => {
+                h + 1
+            }
+            _ => {
+                return;
+            }
+        );
+
+        let module = self.deref_register(2);
+
+        if let Some(AttrListMatch {
+            prev_tail,
+            match_site: MatchSite::Match(match_site),
+        }) = self.match_attribute(self.machine_st.heap[attr_var_list], module, attr)
+        {
+            let prev_tail = if let Some(prev_tail) = prev_tail {
+                // not at the head.
+                prev_tail
+            } else {
+                if self.machine_st.heap[match_site + 1].is_var() {
+                    let h = attr_var.get_value() as usize;
+
+                    self.machine_st.heap[h] = heap_loc_as_cell!(h);
+                    self.machine_st.trail(TrailRef::Ref(Ref::attr_var(h)));
+                }
+
+                // at the head.
+                attr_var_list
+            };
+
+            if self.machine_st.heap[match_site + 1].get_tag() == HeapCellValueTag::Lis {
+                let prev_tail_value = self.machine_st.heap[match_site + 1].get_value();
+                self.machine_st.heap[prev_tail].set_value(prev_tail_value);
+            } else {
+                self.machine_st.heap[prev_tail] = heap_loc_as_cell!(prev_tail);
+            }
+
+            self.machine_st
+                .trail(TrailRef::AttrVarListLink(prev_tail, match_site));
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn put_to_attributed_variable_list(&mut self) {
+        let attr_var = self.deref_register(1);
+        let attr = self.deref_register(3);
+        let attr_var_list_result =
+            step_or_resource_error!(self.machine_st, self.machine_st.get_attr_var_list(attr_var));
+
+        let attr_var_list = match attr_var_list_result {
+            Some(h) => h,
+            None => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        let module = self.deref_register(2);
+
+        /*
+         * How to handle attribute trailing using just AttrVarListLink (which
+         * should be re-named to something more general) in unwind_trail:
+         *
+         * Given AttrVarListLink(h, l):
+         *
+         * 1. Check cell at offset l.
+         * 2. If h == l, set heap[h] = heap_loc_as_cell!(h).
+         * 3. If cell is a Var, set heap[h] = list_loc_as_cell!(l).
+         * 4. Otherwise, cell points to an element of the list which is therefore
+         *    an atom or str. Set heap[h] accordingly.
+         *
+         * For this to work, all elements of attributed variable lists must be
+         * heap cell locs pointing to later elements in the heap, either atoms (0-arity)
+         * or str cells (> 0-arity).
+         */
+
+        let module_functor = functor!(atom!(":"), [cell(module), cell(attr)]);
+        let h = self.machine_st.heap.cell_len();
+
+        step_or_resource_error!(
+            self.machine_st,
+            self.machine_st.heap.push_cell(str_loc_as_cell!(h + 1))
+        );
+
+        let mut functor_writer = Heap::functor_writer(module_functor);
+        step_or_resource_error!(self.machine_st, functor_writer(&mut self.machine_st.heap));
+
+        match self.match_attribute(self.machine_st.heap[attr_var_list], module, attr) {
+            Some(AttrListMatch { match_site, .. }) => {
+                let (match_site, l) = match match_site {
+                    MatchSite::NoMatchVarTail(match_site) => {
+                        let l = self.machine_st.heap[match_site].get_value();
+
+                        // at the end of the (non-empty) list here.
+                        self.machine_st.heap[match_site] = list_loc_as_cell!(h + 4);
+
+                        let mut writer = step_or_resource_error!(
+                            self.machine_st,
+                            self.machine_st.heap.reserve(2)
+                        );
+
+                        writer.write_with(|section| {
+                            section.push_cell(heap_loc_as_cell!(h));
+                            section.push_cell(heap_loc_as_cell!(h + 5));
+                        });
+
+                        (match_site, l)
+                    }
+                    MatchSite::Match(match_site) => {
+                        let l = self.machine_st.heap[match_site].get_value();
+                        self.machine_st.heap[match_site].set_value(h as u64);
+
+                        (match_site, l)
+                    }
+                };
+
+                self.machine_st
+                    .trail(TrailRef::AttrVarListLink(match_site, l as usize));
+            }
+            None => {
+                // the list is empty.
+                self.machine_st.heap[attr_var_list] = list_loc_as_cell!(h + 4);
+
+                let mut writer =
+                    step_or_resource_error!(self.machine_st, self.machine_st.heap.reserve(2));
+
+                writer.write_with(|section| {
+                    section.push_cell(heap_loc_as_cell!(h));
+                    section.push_cell(heap_loc_as_cell!(h + 5));
+                });
+
+                self.machine_st
+                    .attr_var_init
+                    .attr_var_queue
+                    .push(attr_var_list - 1);
+                self.machine_st
+                    .trail(TrailRef::AttrVarListLink(attr_var_list, attr_var_list));
+            }
+        }
+    }
+
+    fn match_attribute(
+        &self,
+        mut attrs_list: HeapCellValue,
+        module: HeapCellValue,
+        attr: HeapCellValue,
+    ) -> Option<AttrListMatch> {
+        let (name, arity) = match self.machine_st.name_and_arity_from_heap(attr) {
+            Some(key) => key,
+            None => {
+                return None;
+            }
+        };
+
+        let mut prev_tail = None;
+
+        while let HeapCellValueTag::Lis = attrs_list.get_tag() {
+            let mut list_head = self.machine_st.heap[attrs_list.get_value() as usize];
+
+            loop {
+                read_heap_cell!(list_head,
+                    (HeapCellValueTag::AttrVar | HeapCellValueTag::Var, h) => {
+                        debug_assert!(list_head != self.machine_st.heap[h]);
+                        list_head = self.machine_st.heap[h];
+                    }
+                    (HeapCellValueTag::Str | HeapCellValueTag::Atom) => {
+                        let (quantification, qualified_goal) = self.machine_st.strip_module(list_head);
+
+                        let (t_name, t_arity) = self.machine_st
+                            .name_and_arity_from_heap(qualified_goal)
+                            .unwrap();
+
+                        if Some(module) == quantification.specified() &&
+                            name == t_name &&
+                            arity == t_arity
+                        {
+                            return Some(AttrListMatch {
+                                match_site: MatchSite::Match(attrs_list.get_value() as usize),
+                                prev_tail,
+                            });
+                        }
+
+                        break;
+                    }
+                    _ => {
+                        break;
+                    }
+                );
+            }
+
+            let tail_loc = attrs_list.get_value() as usize + 1;
+            prev_tail = Some(tail_loc);
+
+            // do the work of self.store(self.deref(...)) but inline it
+            // for speed and simplify it.
+            let mut list_tail = self.machine_st.heap[tail_loc];
+
+            loop {
+                read_heap_cell!(list_tail,
+                    (HeapCellValueTag::AttrVar | HeapCellValueTag::Var, h) => {
+                        if list_tail != self.machine_st.heap[h] {
+                            list_tail = self.machine_st.heap[h];
+                        } else {
+                            return Some(AttrListMatch {
+                                match_site: MatchSite::NoMatchVarTail(h),
+                                prev_tail,
+                            });
+                        }
+                    }
+                    (HeapCellValueTag::Lis) => {
+                        attrs_list = list_tail;
+                        break;
+                    }
+                    _ => {
+                        unreachable!()
+                    }
+                );
+            }
+        }
+
+        None
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_continuation_chunk(&mut self) {
+        let e = self.deref_register(1);
+        let e = unsafe { e.to_fixnum_or_cut_point_unchecked() }.get_num() as usize;
+        let h = self.machine_st.heap.cell_len();
+
+        let p_functor_cell = self.deref_register(2);
+        let num_cells = self.machine_st.stack.index_and_frame(e).prelude.num_cells;
+
+        let mut writer =
+            step_or_resource_error!(self.machine_st, self.machine_st.heap.reserve(2 + num_cells));
+
+        writer.write_with(|section| {
+            section.push_cell(atom_as_cell!(atom!("cont_chunk"), 1 + num_cells));
+            section.push_cell(p_functor_cell);
+
+            for idx in 1..=num_cells {
+                let mut stack_offset = stack_loc!(AndFrame, e, idx);
+                let mut addr = self.machine_st.stack[stack_offset];
+
+                while addr.get_tag() == HeapCellValueTag::StackVar {
+                    stack_offset = addr.get_value() as usize;
+
+                    if self.machine_st.stack[stack_offset] == addr {
+                        break;
+                    }
+
+                    addr = self.machine_st.stack[stack_offset];
+                }
+
+                if addr.get_tag() == HeapCellValueTag::StackVar {
+                    section.push_cell(heap_loc_as_cell!(h + 1 + idx));
+                    self.machine_st.stack[stack_offset] = heap_loc_as_cell!(h + 1 + idx);
+
+                    // have to inline the TrailRef::Ref(RefTag::StackCell) case of MachineState::trail
+                    // here to get around the borrow checker.
+                    if stack_offset < self.machine_st.b {
+                        self.machine_st.trail.push(TrailEntry::build_with(
+                            TrailEntryTag::TrailedStackVar,
+                            stack_offset as u64,
+                        ));
+
+                        self.machine_st.tr += 1;
+                    }
+                } else {
+                    section.push_cell(addr);
+                }
+            }
+        });
+
+        let chunk = str_loc_as_cell!(h);
+        unify_fn!(self.machine_st, self.machine_st.registers[3], chunk);
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_lifted_heap_from_offset_diff(&mut self) {
+        let lh_offset = self.machine_st.registers[1];
+        let lh_offset = unsafe {
+            self.machine_st
+                .store(self.machine_st.deref(lh_offset))
+                .to_fixnum_or_cut_point_unchecked()
+        }
+        .get_num() as usize;
+
+        if lh_offset >= self.machine_st.lifted_heap.cell_len() {
+            let solutions = self.machine_st.registers[2];
+            let diff = self.machine_st.registers[3];
+
+            unify_fn!(self.machine_st, solutions, diff);
+        } else {
+            let h = self.machine_st.heap.cell_len();
+            self.machine_st.copy_lifted_heap_from_offset(h, lh_offset);
+
+            let diff = self.machine_st.registers[3];
+            unify_fn!(
+                self.machine_st,
+                diff,
+                self.machine_st.heap.last_cell().unwrap()
+            );
+
+            self.machine_st.lifted_heap.truncate(lh_offset);
+
+            let solutions = self.machine_st.registers[2];
+            unify_fn!(self.machine_st, heap_loc_as_cell!(h), solutions);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_lifted_heap_from_offset(&mut self) {
+        let lh_offset = self.machine_st.registers[1];
+        let lh_offset = unsafe {
+            self.machine_st
+                .store(self.machine_st.deref(lh_offset))
+                .to_fixnum_or_cut_point_unchecked()
+        }
+        .get_num() as usize;
+
+        if lh_offset >= self.machine_st.lifted_heap.cell_len() {
+            let solutions = self.machine_st.registers[2];
+            unify_fn!(self.machine_st, solutions, empty_list_as_cell!());
+        } else {
+            let h = self.machine_st.heap.cell_len();
+
+            self.machine_st.copy_lifted_heap_from_offset(h, lh_offset);
+            self.machine_st.lifted_heap.truncate(lh_offset);
+
+            let solutions = self.machine_st.registers[2];
+            unify_fn!(self.machine_st, heap_loc_as_cell!(h), solutions);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_double_quotes(&mut self) {
+        let a1 = self.deref_register(1);
+
+        self.machine_st.unify_atom(
+            match self.machine_st.flags.double_quotes {
+                DoubleQuotes::Chars => atom!("chars"),
+                DoubleQuotes::Atom => atom!("atom"),
+                DoubleQuotes::Codes => atom!("codes"),
+            },
+            a1,
+        );
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_unknown(&mut self) {
+        let a1 = self.deref_register(1);
+
+        self.machine_st.unify_atom(
+            match self.machine_st.flags.unknown {
+                Unknown::Error => atom!("error"),
+                Unknown::Fail => atom!("fail"),
+                Unknown::Warn => atom!("warning"),
+            },
+            a1,
+        );
+    }
+
+    // unifies register 2 with the unknown flag of the module named in
+    // register 1, that of the machine if the module has none. the flag
+    // of user is that of the machine.
+    pub(crate) fn get_module_unknown(&mut self) -> CallResult {
+        let module_name = cell_as_atom!(self.deref_register(1));
+
+        let unknown = if module_name == atom!("user") {
+            self.machine_st.flags.unknown
+        } else {
+            match self.indices.modules.get(&module_name) {
+                Some(module) => module.unknown.unwrap_or(self.machine_st.flags.unknown),
+                None => {
+                    return Err(
+                        self.module_existence_error(module_name, atom!("current_prolog_flag"))
+                    );
+                }
+            }
+        };
+
+        let a2 = self.deref_register(2);
+
+        self.machine_st.unify_atom(
+            match unknown {
+                Unknown::Error => atom!("error"),
+                Unknown::Fail => atom!("fail"),
+                Unknown::Warn => atom!("warning"),
+            },
+            a2,
+        );
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_error_locations(&mut self) {
+        let a1 = self.deref_register(1);
+
+        self.machine_st.unify_atom(
+            if self.machine_st.flags.error_locations {
+                atom!("true")
+            } else {
+                atom!("false")
+            },
+            a1,
+        );
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_prefer_rationals(&mut self) {
+        let a1 = self.deref_register(1);
+
+        self.machine_st.unify_atom(
+            if self.machine_st.flags.prefer_rationals {
+                atom!("true")
+            } else {
+                atom!("false")
+            },
+            a1,
+        );
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_float_flag(&mut self) {
+        let flags = self.machine_st.flags.float_flags;
+
+        let value = match cell_as_atom!(self.deref_register(1)) {
+            atom!("float_overflow") if flags.overflow_to_infinity => atom!("infinity"),
+            atom!("float_zero_div") if flags.zero_div_to_infinity => atom!("infinity"),
+            atom!("float_undefined") if flags.undefined_to_nan => atom!("nan"),
+            _ => atom!("error"),
+        };
+
+        let a2 = self.deref_register(2);
+        self.machine_st.unify_atom(value, a2);
+    }
+
+    pub(crate) fn get_rational_flag(&mut self) {
+        let flags = self.machine_st.flags.rational_flags;
+        let a2 = self.deref_register(2);
+
+        match cell_as_atom!(self.deref_register(1)) {
+            atom!("rational_precision") => {
+                let precision = Fixnum::build_with(flags.precision as u32);
+                self.machine_st.unify_fixnum(precision, a2);
+            }
+            _ => {
+                let rounding = match flags.rounding {
+                    RationalRounding::HalfEven => atom!("half_even"),
+                    RationalRounding::HalfAwayFromZero => atom!("half_away_from_zero"),
+                    RationalRounding::TowardZero => atom!("toward_zero"),
+                    RationalRounding::AwayFromZero => atom!("away_from_zero"),
+                    RationalRounding::Down => atom!("down"),
+                    RationalRounding::Up => atom!("up"),
+                };
+
+                self.machine_st.unify_atom(rounding, a2);
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_integer_rounding_function(&mut self) {
+        let a1 = self.deref_register(1);
+
+        self.machine_st.unify_atom(
+            match self.machine_st.flags.integer_rounding_function {
+                IntegerRoundingFunction::TowardZero => atom!("toward_zero"),
+                IntegerRoundingFunction::Down => atom!("down"),
+            },
+            a1,
+        );
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_style_check(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        let a2 = self.deref_register(2);
+
+        match self.machine_st.flags.style_check.check_mut(name) {
+            Some(&mut is_enabled) => {
+                self.machine_st.unify_atom(
+                    if is_enabled {
+                        atom!("true")
+                    } else {
+                        atom!("false")
+                    },
+                    a2,
+                );
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_scc_cleaner(&mut self) {
+        let dest = self.machine_st.registers[1];
+
+        if let Some((addr, b_cutoff, prev_block)) = self.machine_st.cont_pts.pop() {
+            let b = self
+                .machine_st
+                .stack
+                .index_or_frame(self.machine_st.b)
+                .prelude
+                .b;
+
+            if b <= b_cutoff {
+                self.machine_st.scc_block = prev_block;
+
+                if let Some(r) = dest.as_var() {
+                    self.machine_st.bind(r, addr);
+                    return;
+                }
+            } else {
+                self.machine_st.cont_pts.push((addr, b_cutoff, prev_block));
+            }
+        }
+
+        self.machine_st.fail = true;
+    }
+
+    #[inline(always)]
+    pub(crate) fn halt(&mut self) -> std::process::ExitCode {
+        let code = self.deref_register(1);
+
+        let code = match Number::try_from((code, &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Fixnum(n)) => u8::try_from(n.get_num()).unwrap(),
+            Ok(Number::Integer(n)) => {
+                let n: u8 = (&*n).try_into().unwrap();
+                n
+            }
+            Ok(Number::Rational(r)) => {
+                // n has already been confirmed as an integer, and
+                // internally, Rational is assumed reduced, so its
+                // denominator must be 1.
+                r.numerator().try_into().unwrap()
+            }
+            _ => {
+                unreachable!()
+            }
+        };
+
+        std::process::ExitCode::from(code)
+    }
+
+    #[inline(always)]
+    pub(crate) fn install_scc_cleaner(&mut self) {
+        let addr = self.machine_st.registers[1];
+        let b = self.machine_st.b;
+        let prev_block = self.machine_st.scc_block;
+
+        self.machine_st.run_cleaners_fn = Machine::run_cleaners;
+        self.machine_st.scc_block = b;
+        self.machine_st.cont_pts.push((addr, b, prev_block));
+    }
+
+    #[inline(always)]
+    pub(crate) fn install_inference_counter(&mut self) -> CallResult {
+        // A1 = B, A2 = L
+        let a1 = self.deref_register(1);
+        let a2 = self.deref_register(2);
+
+        let n = match Number::try_from((a2, &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Fixnum(bp)) => Integer::from(bp.get_num() as usize),
+            Ok(Number::Integer(n)) => (*n).clone(),
+            _ => {
+                let stub = functor_stub(atom!("call_with_inference_limit"), 3);
+                let err = self.machine_st.type_error(ValidType::Integer, a2);
+                return Err(self.machine_st.error_form(err, stub));
+            }
+        };
+
+        let bp = unsafe { a1.to_fixnum_or_cut_point_unchecked() }.get_num() as usize;
+        let a3 = self.deref_register(3);
+
+        let count = self.machine_st.cwil.add_limit(n, bp).clone();
+        self.inference_count(a3, count);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn install_time_limit(&mut self) {
+        // A1 = the time limit in seconds, validated by call_with_time_limit/2.
+        let time = self.deref_register(1);
+
+        let secs = match Number::try_from((time, &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Float(n)) => n.into_inner(),
+            Ok(Number::Fixnum(n)) => n.get_num() as f64,
+            Ok(Number::Integer(n)) => n.to_f64().value(),
+            _ => {
+                unreachable!()
+            }
+        };
+
+        let deadline = Duration::try_from_secs_f64(secs)
+            .ok()
+            .and_then(|duration| Instant::now().checked_add(duration));
+
+        // a limit too large to represent never expires.
+        self.machine_st.time_limits.push(deadline);
+    }
+
+    #[inline(always)]
+    pub(crate) fn engine_create(&mut self) {
+        // A1 = Template-Goal, A2 = the alias of the engine, or a
+        // variable.
+        let alias = self.deref_register(2);
+
+        let name = if alias.is_var() {
+            anonymous_engine_name(&self.engines, &self.machine_st.atom_tbl)
+        } else {
+            let alias = cell_as_atom!(alias);
+
+            if self.engines.contains_key(&alias) {
+                self.machine_st.fail = true;
+                return;
+            }
+
+            alias
+        };
+
+        let goal = self.machine_st.registers[1];
+        let goal =
+            step_or_resource_error!(self.machine_st, copy_to_ball(&mut self.machine_st, goal));
+
+        let engine = step_or_resource_error!(
+            self.machine_st,
+            PrologEngine::new(goal, self.machine_st.attr_var_init.verify_attrs_loc)
+        );
+
+        self.engines.insert(name, engine);
+        self.machine_st
+            .unify_atom(name, self.machine_st.registers[3]);
+    }
+
+    #[inline(always)]
+    pub(crate) fn engine_destroy(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        self.machine_st.fail = self.engines.shift_remove(&name).is_none();
+    }
+
+    #[inline(always)]
+    pub(crate) fn engine_status(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+
+        let status = match self.engines.get(&name) {
+            Some(engine) if engine.is_running() => atom!("running"),
+            Some(engine) if engine.is_finished() => atom!("finished"),
+            Some(_) => atom!("suspended"),
+            None => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        self.machine_st
+            .unify_atom(status, self.machine_st.registers[2]);
+    }
+
+    // unifies A3 with the kind of the next answer of the engine A1,
+    // which is the, exception or no, and A4 with the answer. A2 is
+    // the time slice of the engine, in interrupt polls of the dispatch
+    // loop, or infinite; the kind is pending if it runs out. Returns
+    // the exit code if the engine halted the machine.
+    pub(crate) fn engine_next(&mut self) -> Result<(), std::process::ExitCode> {
+        let name = cell_as_atom!(self.deref_register(1));
+
+        let slice = match Number::try_from((self.deref_register(2), &self.machine_st.arena.f64_tbl))
+        {
+            Ok(Number::Fixnum(n)) => usize::try_from(n.get_num()).ok(),
+            _ => None,
+        };
+
+        let answer = match self.engines.get(&name) {
+            Some(engine) if engine.is_finished() => EngineAnswer::No,
+            Some(engine) if !engine.is_running() => self.run_engine(name, slice)?,
+            _ => {
+                self.machine_st.fail = true;
+                return Ok(());
+            }
+        };
+
+        let (kind, ball) = match answer {
+            EngineAnswer::The(ball) => (atom!("the"), ball),
+            EngineAnswer::Exception(ball) => (atom!("exception"), ball),
+            EngineAnswer::No => {
+                self.machine_st
+                    .unify_atom(atom!("no"), self.machine_st.registers[3]);
+                return Ok(());
+            }
+            EngineAnswer::Pending => {
+                self.machine_st
+                    .unify_atom(atom!("pending"), self.machine_st.registers[3]);
+                return Ok(());
+            }
+        };
+
+        let h = step_or_resource_error!(
+            self.machine_st,
+            ball.copy_and_align_to(&mut self.machine_st.heap),
+            {
+                return Ok(());
+            }
+        );
+
+        self.machine_st
+            .unify_atom(kind, self.machine_st.registers[3]);
+
+        if !self.machine_st.fail {
+            unify_fn!(
+                self.machine_st,
+                self.machine_st.registers[4],
+                self.machine_st.heap[h]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn engine_post(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+
+        if !self.engines.contains_key(&name) {
+            self.machine_st.fail = true;
+            return;
+        }
+
+        let term = self.machine_st.registers[2];
+        let term =
+            step_or_resource_error!(self.machine_st, copy_to_ball(&mut self.machine_st, term));
+
+        self.engines.get_mut(&name).unwrap().posted.push_back(term);
+    }
+
+    #[inline(always)]
+    pub(crate) fn engine_fetch(&mut self) {
+        let term = self
+            .running_engines
+            .last()
+            .and_then(|name| self.engines.get_mut(name))
+            .and_then(|engine| engine.posted.pop_front());
+
+        let Some(term) = term else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let h = step_or_resource_error!(
+            self.machine_st,
+            term.copy_and_align_to(&mut self.machine_st.heap)
+        );
+
+        unify_fn!(
+            self.machine_st,
+            self.machine_st.registers[1],
+            self.machine_st.heap[h]
+        );
+    }
+
+    #[inline(always)]
+    pub(crate) fn engine_self(&mut self) {
+        match self.running_engines.last() {
+            Some(&name) => self
+                .machine_st
+                .unify_atom(name, self.machine_st.registers[1]),
+            None => self.machine_st.fail = true,
+        }
+    }
+
+    // makes A1 the next answer of the running engine, whose goal
+    // resumes at resume_p. The dispatch loop is then left for the
+    // engine to be suspended.
+    #[inline(always)]
+    pub(crate) fn engine_yield(&mut self, resume_p: usize) {
+        let Some(&name) = self.running_engines.last() else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let term = self.machine_st.registers[1];
+        let term =
+            step_or_resource_error!(self.machine_st, copy_to_ball(&mut self.machine_st, term));
+
+        let engine = self.engines.get_mut(&name).unwrap();
+
+        engine.yielded = Some(term);
+        engine.status = EngineStatus::Yielded(resume_p);
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_create(&mut self) {
+        let queue = self.deref_register(1);
+
+        if queue.is_var() {
+            let name = MessageQueue::create_anonymous(&self.machine_st.atom_tbl);
+            self.machine_st.unify_atom(name, queue);
+        } else {
+            self.machine_st.fail = !MessageQueue::create(cell_as_atom!(queue));
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_destroy(&mut self) {
+        let queue = cell_as_atom!(self.deref_register(1));
+        self.machine_st.fail = !MessageQueue::destroy(queue);
+    }
+
+    // the timeout in seconds in register r, which is None for
+    // infinite or a timeout too large to represent.
+    fn timeout_arg(&mut self, r: usize) -> Option<Duration> {
+        match Number::try_from((self.deref_register(r), &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Float(n)) => Duration::try_from_secs_f64(n.into_inner()).ok(),
+            Ok(Number::Fixnum(n)) => Duration::try_from_secs_f64(n.get_num() as f64).ok(),
+            Ok(Number::Integer(n)) => Duration::try_from_secs_f64(n.to_f64().value()).ok(),
+            _ => None,
+        }
+    }
+
+    fn message_queue_id_arg(&mut self, r: usize) -> Option<usize> {
+        match Number::try_from((self.deref_register(r), &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Fixnum(n)) => usize::try_from(n.get_num()).ok(),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_send(&mut self) {
+        let queue = cell_as_atom!(self.deref_register(1));
+        let message = self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[2]);
+
+        match (MessageQueue::lookup(queue), message) {
+            (Some(queue), Some(message)) => queue.send(message.as_str().to_string()),
+            _ => self.machine_st.fail = true,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_next(&mut self) {
+        // A2 = the id of the last message rejected by the receiver, or -1.
+        let queue = cell_as_atom!(self.deref_register(1));
+        let after = self.message_queue_id_arg(2);
+
+        let Some((id, message)) = MessageQueue::lookup(queue).and_then(|queue| queue.next(after))
+        else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let id = Fixnum::build_with_checked(id).unwrap();
+        self.machine_st
+            .unify_fixnum(id, self.machine_st.registers[3]);
+
+        if !self.machine_st.fail {
+            let message = step_or_resource_error!(
+                self.machine_st,
+                self.machine_st.heap.allocate_cstr(&message)
+            );
+
+            unify_fn!(self.machine_st, self.machine_st.registers[4], message);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_remove(&mut self) {
+        let queue = cell_as_atom!(self.deref_register(1));
+        let id = self.message_queue_id_arg(2);
+
+        self.machine_st.fail = !MessageQueue::lookup(queue)
+            .zip(id)
+            .is_some_and(|(queue, id)| queue.remove(id));
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_wait(&mut self) {
+        // A3 = the timeout in seconds or infinite, validated by
+        // thread_get_message/3. A4 is unified with the time remaining.
+        let queue = cell_as_atom!(self.deref_register(1));
+        let after = self.message_queue_id_arg(2);
+        let timeout = self.timeout_arg(3);
+
+        let Some(queue) = MessageQueue::lookup(queue) else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let interrupted = || {
+            machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed)
+                || threads::cancel_pending()
+        };
+
+        match queue.wait(after, timeout, interrupted) {
+            WaitOutcome::Arrived(Some(remaining)) => {
+                let remaining = float_alloc!(remaining.as_secs_f64(), self.machine_st.arena);
+                self.machine_st
+                    .unify_f64(remaining, self.machine_st.registers[4]);
+            }
+            WaitOutcome::Arrived(None) => {
+                self.machine_st
+                    .unify_atom(atom!("infinite"), self.machine_st.registers[4]);
+            }
+            WaitOutcome::Interrupted => {
+                // the interrupt (or the cancellation) is raised by
+                // the dispatch loop once the receiver resumes.
+                unify_fn!(
+                    self.machine_st,
+                    self.machine_st.registers[4],
+                    self.machine_st.registers[3]
+                );
+            }
+            WaitOutcome::TimedOut | WaitOutcome::Destroyed => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_size(&mut self) {
+        let queue = cell_as_atom!(self.deref_register(1));
+
+        match MessageQueue::lookup(queue) {
+            Some(queue) => {
+                let size = Fixnum::build_with_checked(queue.len()).unwrap();
+                self.machine_st
+                    .unify_fixnum(size, self.machine_st.registers[2]);
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn message_queue_stream(&mut self) -> CallResult {
+        let queue = cell_as_atom!(self.deref_register(1));
+
+        let Some(queue) = MessageQueue::lookup(queue) else {
+            self.machine_st.fail = true;
+            return Ok(());
+        };
+
+        let stream = Stream::from_message_queue(queue, &mut self.machine_st.arena);
+
+        self.indices
+            .add_stream(stream, atom!("message_queue_stream"), 2)
+            .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+
+        let stream_var = self.deref_register(2);
+        self.machine_st
+            .bind(stream_var.as_var().unwrap(), stream.into());
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn mutex_create(&mut self) {
+        let mutex = self.deref_register(1);
+
+        if mutex.is_var() {
+            let name = PrologMutex::create_anonymous(&self.machine_st.atom_tbl);
+            self.machine_st.unify_atom(name, mutex);
+        } else {
+            self.machine_st.fail = !PrologMutex::create(cell_as_atom!(mutex));
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn mutex_destroy(&mut self) {
+        let mutex = cell_as_atom!(self.deref_register(1));
+        self.machine_st.fail = !PrologMutex::destroy(mutex);
+    }
+
+    // A2 = the thread holding the mutex, left unbound if the mutex is
+    // unlocked, A3 = how often it locked the mutex. fails if there is
+    // no such mutex.
+    #[inline(always)]
+    pub(crate) fn mutex_holder(&mut self) {
+        let mutex = cell_as_atom!(self.deref_register(1));
+
+        let Some(mutex) = PrologMutex::lookup(mutex) else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let (owner, count) = match mutex.holder() {
+            Some((owner, count)) => (Some(owner), count),
+            None => (None, 0),
+        };
+
+        let count = Fixnum::build_with_checked(count).unwrap();
+        self.machine_st
+            .unify_fixnum(count, self.machine_st.registers[3]);
+
+        if let Some(owner) = owner {
+            if !self.machine_st.fail {
+                self.machine_st
+                    .unify_atom(owner, self.machine_st.registers[2]);
+            }
+        }
+    }
+
+    // fails if there is no such mutex or the wait was interrupted,
+    // which is raised by the dispatch loop once the locker resumes.
+    #[inline(always)]
+    pub(crate) fn mutex_lock(&mut self) {
+        let mutex = cell_as_atom!(self.deref_register(1));
+
+        let interrupted = || {
+            machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed)
+                || threads::cancel_pending()
+        };
+
+        self.machine_st.fail = !PrologMutex::lookup(mutex)
+            .is_some_and(|mutex| mutex.lock(threads::thread_self(), interrupted));
+    }
+
+    #[inline(always)]
+    pub(crate) fn mutex_trylock(&mut self) {
+        let mutex = cell_as_atom!(self.deref_register(1));
+
+        self.machine_st.fail =
+            !PrologMutex::lookup(mutex).is_some_and(|mutex| mutex.try_lock(threads::thread_self()));
+    }
+
+    #[inline(always)]
+    pub(crate) fn mutex_unlock(&mut self) {
+        let mutex = cell_as_atom!(self.deref_register(1));
+
+        self.machine_st.fail =
+            !PrologMutex::lookup(mutex).is_some_and(|mutex| mutex.unlock(threads::thread_self()));
+    }
+
+    #[inline(always)]
+    pub(crate) fn condition_create(&mut self) {
+        let condition = self.deref_register(1);
+
+        if condition.is_var() {
+            let name = Condition::create_anonymous(&self.machine_st.atom_tbl);
+            self.machine_st.unify_atom(name, condition);
+        } else {
+            self.machine_st.fail = !Condition::create(cell_as_atom!(condition));
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn condition_destroy(&mut self) {
+        let condition = cell_as_atom!(self.deref_register(1));
+        self.machine_st.fail = !Condition::destroy(condition);
+    }
+
+    #[inline(always)]
+    pub(crate) fn condition_signal(&mut self) {
+        let condition = cell_as_atom!(self.deref_register(1));
+
+        match Condition::lookup(condition) {
+            Some(condition) => condition.signal(),
+            None => self.machine_st.fail = true,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn condition_broadcast(&mut self) {
+        let condition = cell_as_atom!(self.deref_register(1));
+
+        match Condition::lookup(condition) {
+            Some(condition) => condition.broadcast(),
+            None => self.machine_st.fail = true,
+        }
+    }
+
+    // A1 = the condition, A2 = the mutex held by the calling thread,
+    // A3 = the timeout in seconds or infinite, all validated by
+    // condition_wait/3. A4 is unified with signalled or timeout, and
+    // the call fails if there is no such condition or mutex. an
+    // interrupted wait is reported like a spurious wakeup, and the
+    // interrupt is raised by the dispatch loop once the waiter
+    // resumes.
+    #[inline(always)]
+    pub(crate) fn condition_wait(&mut self) {
+        let condition = cell_as_atom!(self.deref_register(1));
+        let mutex = cell_as_atom!(self.deref_register(2));
+        let timeout = self.timeout_arg(3);
+
+        let (Some(condition), Some(mutex)) =
+            (Condition::lookup(condition), PrologMutex::lookup(mutex))
+        else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let interrupted = || {
+            machine::INTERRUPT.load(std::sync::atomic::Ordering::Relaxed)
+                || threads::cancel_pending()
+        };
+
+        match condition.wait(&mutex, threads::thread_self(), timeout, interrupted) {
+            ConditionOutcome::Signalled | ConditionOutcome::Interrupted => self
+                .machine_st
+                .unify_atom(atom!("signalled"), self.machine_st.registers[4]),
+            ConditionOutcome::TimedOut => self
+                .machine_st
+                .unify_atom(atom!("timeout"), self.machine_st.registers[4]),
+        }
+    }
+
+    fn resilience_f64_arg(&mut self, r: usize) -> f64 {
+        match Number::try_from((self.deref_register(r), &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Float(n)) => n.into_inner(),
+            Ok(Number::Fixnum(n)) => n.get_num() as f64,
+            Ok(Number::Integer(n)) => n.to_f64().value(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn resilience_object_create(&mut self, object: ResilienceObject) {
+        let name = self.deref_register(1);
+
+        let new_name = if name.is_var() {
+            ResilienceObject::create(None, object, &self.machine_st.atom_tbl)
+        } else {
+            ResilienceObject::create(Some(cell_as_atom!(name)), object, &self.machine_st.atom_tbl)
+        };
+
+        match new_name {
+            Some(new_name) => self.machine_st.unify_atom(new_name, name),
+            None => self.machine_st.fail = true,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn rate_limiter_create(&mut self) {
+        let capacity = self.resilience_f64_arg(2);
+        let rate = self.resilience_f64_arg(3);
+
+        self.resilience_object_create(ResilienceObject::RateLimiter(RateLimiter::new(
+            capacity, rate,
+        )));
+    }
+
+    // '$rate_limiter_acquire'(+Limiter, +N, -Wait) takes N tokens of
+    // Limiter, with Wait 0, or else leaves them and unifies Wait with
+    // the seconds until Limiter holds them, or `never` if N exceeds
+    // its capacity.
+    #[inline(always)]
+    pub(crate) fn rate_limiter_acquire(&mut self) {
+        let limiter = cell_as_atom!(self.deref_register(1));
+        let n = self.resilience_f64_arg(2);
+
+        match ResilienceObject::with_rate_limiter(limiter, |limiter| limiter.acquire(n)) {
+            Some(Ok(())) => {
+                self.machine_st
+                    .unify_fixnum(Fixnum::build_with(0), self.machine_st.registers[3]);
+            }
+            Some(Err(Some(wait))) => {
+                let wait = float_alloc!(wait.as_secs_f64(), self.machine_st.arena);
+                self.machine_st
+                    .unify_f64(wait, self.machine_st.registers[3]);
+            }
+            Some(Err(None)) => {
+                self.machine_st
+                    .unify_atom(atom!("never"), self.machine_st.registers[3]);
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn circuit_breaker_create(&mut self) {
+        let threshold =
+            match Number::try_from((self.deref_register(2), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => n.get_num() as usize,
+                _ => unreachable!(),
+            };
+
+        let reset_timeout = Duration::from_secs_f64(self.resilience_f64_arg(3));
+
+        self.resilience_object_create(ResilienceObject::CircuitBreaker(CircuitBreaker::new(
+            threshold,
+            reset_timeout,
+        )));
+    }
+
+    // '$circuit_breaker_enter'(+Breaker, -Permit) unifies Permit with
+    // the generation of Breaker that a permitted call reports its
+    // outcome to, or with `rejected`.
+    #[inline(always)]
+    pub(crate) fn circuit_breaker_enter(&mut self) {
+        let breaker = cell_as_atom!(self.deref_register(1));
+
+        match ResilienceObject::with_circuit_breaker(breaker, |breaker| breaker.enter()) {
+            Some(Some(generation)) => {
+                let generation = Fixnum::build_with_checked(generation as i64).unwrap();
+                self.machine_st
+                    .unify_fixnum(generation, self.machine_st.registers[2]);
+            }
+            Some(None) => {
+                self.machine_st
+                    .unify_atom(atom!("rejected"), self.machine_st.registers[2]);
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    // '$circuit_breaker_exit'(+Breaker, +Permit, +Outcome), with
+    // Outcome `success` or `failure`.
+    #[inline(always)]
+    pub(crate) fn circuit_breaker_exit(&mut self) {
+        let breaker = cell_as_atom!(self.deref_register(1));
+
+        let generation =
+            match Number::try_from((self.deref_register(2), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => n.get_num() as usize,
+                _ => unreachable!(),
+            };
+
+        let success = cell_as_atom!(self.deref_register(3)) == atom!("success");
+
+        self.machine_st.fail = ResilienceObject::with_circuit_breaker(breaker, |breaker| {
+            breaker.exit(generation, success)
+        })
+        .is_none();
+    }
+
+    #[inline(always)]
+    pub(crate) fn circuit_breaker_state(&mut self) {
+        let breaker = cell_as_atom!(self.deref_register(1));
+
+        match ResilienceObject::with_circuit_breaker(breaker, |breaker| breaker.state()) {
+            Some(state) => {
+                self.machine_st
+                    .unify_atom(state, self.machine_st.registers[2]);
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
+    }
+
+    // '$resilience_object_destroy'(+Name, +Kind) destroys the rate
+    // limiter or circuit breaker Name, as Kind is.
+    #[inline(always)]
+    pub(crate) fn resilience_object_destroy(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        let kind = cell_as_atom!(self.deref_register(2));
+
+        self.machine_st.fail = !ResilienceObject::destroy(name, kind);
+    }
+
+    // the text of the string in register r, checked to be a list of
+    // chars by library(regex).
+    fn regex_text_arg(&mut self, r: usize) -> String {
+        match self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[r])
+        {
+            Some(AtomOrString::Atom(atom!("[]"))) => String::new(),
+            Some(atom_or_string) => atom_or_string.into(),
+            None => unreachable!(),
+        }
+    }
+
+    // the compiled pattern of register 1. fails if it isn't a valid
+    // regular expression.
+    fn regex_arg(&mut self) -> Option<Regex> {
+        let pattern = self.regex_text_arg(1);
+        let regex = compiled_regex(&pattern);
+
+        self.machine_st.fail = regex.is_none();
+        regex
+    }
+
+    // the limit of register r, where 0 means no limit.
+    fn regex_limit_arg(&mut self, r: usize) -> usize {
+        match Number::try_from((self.deref_register(r), &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Fixnum(n)) => n.get_num() as usize,
+            Ok(Number::Integer(_)) => 0,
+            _ => unreachable!(),
+        }
+    }
+
+    // '$regex_matches'(+Pattern, +String, -Matches) unifies Matches
+    // with the list of the successive non-overlapping matches of
+    // Pattern in String, each the list of the match and its groups,
+    // with `none` for the groups that didn't participate.
+    #[inline(always)]
+    pub(crate) fn regex_matches(&mut self) {
+        let Some(regex) = self.regex_arg() else {
+            return;
+        };
+
+        let text = self.regex_text_arg(2);
+        let mut matches = vec![];
+
+        for captures in regex.captures_iter(&text) {
+            let mut groups = Vec::with_capacity(captures.len());
+
+            for group in captures.iter() {
+                let group = match group {
+                    Some(group) => step_or_resource_error!(
+                        self.machine_st,
+                        self.machine_st.heap.allocate_cstr(group.as_str())
+                    ),
+                    None => atom_as_cell!(atom!("none")),
+                };
+
+                groups.push(group);
+            }
+
+            let groups = step_or_resource_error!(
+                self.machine_st,
+                sized_iter_to_heap_list(
+                    &mut self.machine_st.heap,
+                    groups.len(),
+                    groups.into_iter()
+                )
+            );
+
+            matches.push(groups);
+        }
+
+        let matches = step_or_resource_error!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                matches.len(),
+                matches.into_iter()
+            )
+        );
+
+        unify_fn!(self.machine_st, matches, self.machine_st.registers[3]);
+    }
+
+    // '$regex_split'(+Pattern, +String, +Limit, -Parts) unifies Parts
+    // with the parts of String between the matches of Pattern, at most
+    // Limit of them unless Limit is 0.
+    #[inline(always)]
+    pub(crate) fn regex_split(&mut self) {
+        let Some(regex) = self.regex_arg() else {
+            return;
+        };
 
-                        *stream.options_mut() = StreamOptions::default();
-                        stream.options_mut().set_stream_type(StreamType::Binary);
+        let text = self.regex_text_arg(2);
+        let limit = self.regex_limit_arg(3);
 
-                        self.indices.add_stream(stream, atom!("http_answer"), 4)
-                            .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
-                        self.machine_st.bind(stream_addr.as_var().unwrap(), stream.into());
-                    }
-                    _ => {
-                        unreachable!();
-                    }
-                );
-            }
-            _ => {
-                unreachable!();
-            }
+        let parts: Vec<&str> = if limit == 0 {
+            regex.split(&text).collect()
+        } else {
+            regex.splitn(&text, limit).collect()
+        };
+
+        let mut cells = Vec::with_capacity(parts.len());
+
+        for part in parts {
+            cells.push(step_or_resource_error!(
+                self.machine_st,
+                self.machine_st.heap.allocate_cstr(part)
+            ));
+        }
+
+        let parts = step_or_resource_error!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, cells.len(), cells.into_iter())
         );
 
-        Ok(())
+        unify_fn!(self.machine_st, parts, self.machine_st.registers[4]);
     }
 
-    #[cfg(feature = "ffi")]
+    // '$regex_replace'(+Pattern, +Replacement, +String, +Limit, -Result)
+    // unifies Result with String with its first Limit matches of
+    // Pattern replaced by Replacement, or all of them if Limit is 0.
     #[inline(always)]
-    pub(crate) fn load_foreign_lib(&mut self) -> CallResult {
-        let library_name = self.deref_register(1);
-        let args_reg = self.deref_register(2);
-        if let Some(library_name) = self.machine_st.value_to_str_like(library_name) {
-            let stub_gen = || functor_stub(atom!("use_foreign_module"), 2);
-            match self.machine_st.try_from_list(args_reg, stub_gen) {
-                Ok(addrs) => {
-                    let mut functions = Vec::new();
-                    for heap_cell in addrs {
-                        read_heap_cell!(heap_cell,
-                            (HeapCellValueTag::Str, s) => {
-                                let name = cell_as_atom_cell!(self.machine_st.heap[s]).get_name();
-                            let args: Vec<Atom> = match self.machine_st.try_from_list(self.machine_st.heap[s + 1], stub_gen) {
-                                Ok(addrs) => {
-                                let mut args = Vec::new();
-                                for heap_cell in addrs {
-                                    args.push(cell_as_atom_cell!(heap_cell).get_name());
-                                }
-                                args
-                                }
-                                Err(e) => return Err(e)
-                            };
-                            let return_value = cell_as_atom_cell!(self.machine_st.heap[s + 2]);
-                            functions.push(FunctionDefinition {
-                                name: name.as_str().to_string(),
-                                args,
-                                return_value: return_value.get_name(),
-                            });
-                            }
-                            _ => {
-                                unreachable!()
-                                }
-                        )
-                    }
-                    if self
-                        .foreign_function_table
-                        .load_library(&library_name.as_str(), &functions)
-                        .is_ok()
-                    {
-                        return Ok(());
-                    }
-                }
-                Err(e) => return Err(e),
-            };
+    pub(crate) fn regex_replace(&mut self) {
+        let Some(regex) = self.regex_arg() else {
+            return;
+        };
+
+        let replacement = self.regex_text_arg(2);
+        let text = self.regex_text_arg(3);
+        let limit = self.regex_limit_arg(4);
+
+        let result = regex.replacen(&text, limit, replacement.as_str());
+        let result =
+            step_or_resource_error!(self.machine_st, self.machine_st.heap.allocate_cstr(&result));
+
+        unify_fn!(self.machine_st, result, self.machine_st.registers[5]);
+    }
+
+    // the terms of registers 2 to 5 of '$json_parse' and
+    // '$json_generate': the terms of null, true and false, and the
+    // representation of object keys.
+    fn json_term_options(&mut self) -> JsonTermOptions {
+        JsonTermOptions {
+            null: self.deref_register(2),
+            true_term: self.deref_register(3),
+            false_term: self.deref_register(4),
+            keys: cell_as_atom!(self.deref_register(5)),
         }
-        self.machine_st.fail = true;
-        Ok(())
     }
 
-    #[cfg(feature = "ffi")]
-    #[inline(always)]
-    pub(crate) fn foreign_call(&mut self) -> CallResult {
-        let function_name = self.deref_register(1);
-        let args_reg = self.deref_register(2);
-        let return_value = self.deref_register(3);
-        if let Some(function_name) = self.machine_st.value_to_str_like(function_name) {
-            let stub_gen = || functor_stub(atom!("foreign_call"), 3);
-            fn map_arg(machine_st: &mut MachineState, source: HeapCellValue) -> crate::ffi::Value {
-                match Number::try_from((source, &machine_st.arena.f64_tbl)) {
-                    Ok(Number::Fixnum(n)) => Value::Int(n.get_num()),
-                    Ok(Number::Float(n)) => Value::Float(n.into_inner()),
-                    _ => {
-                        let stub_gen = || functor_stub(atom!("foreign_call"), 3);
-                        if let Some(string) = machine_st.value_to_str_like(source) {
-                            Value::CString(CString::new(&*string.as_str()).unwrap())
-                        } else {
-                            match machine_st.try_from_list(source, stub_gen) {
-                                Ok(args) => {
-                                    let mut iter = args.into_iter();
-                                    if let Some(struct_name) =
-                                        machine_st.value_to_str_like(iter.next().unwrap())
-                                    {
-                                        Value::Struct(
-                                            struct_name.as_str().to_string(),
-                                            iter.map(|x| map_arg(machine_st, x)).collect(),
-                                        )
-                                    } else {
-                                        unreachable!()
-                                    }
-                                }
-                                _ => {
-                                    unreachable!()
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    fn json_number_cell(&mut self, number: JsonNumber) -> HeapCellValue {
+        let number = match number {
+            JsonNumber::Integer(n) => Number::arena_from(n, &mut self.machine_st.arena),
+            JsonNumber::Float(n) => Number::Float(OrderedFloat(n)),
+        };
 
-            match self.machine_st.try_from_list(args_reg, stub_gen) {
-                Ok(args) => {
-                    let args: Vec<_> = args
-                        .into_iter()
-                        .map(|x| map_arg(&mut self.machine_st, x))
-                        .collect();
-                    match self
-                        .foreign_function_table
-                        .exec(&function_name.as_str(), args)
-                    {
-                        Ok(result) => {
-                            match result {
-                                Value::Int(n) => self.machine_st.unify_fixnum(
-                                    Fixnum::build_with_checked(n).unwrap_or_else(|_| {
-                                        todo!("handle integer values that don't fit in fixnum")
-                                    }),
-                                    return_value,
-                                ),
-                                Value::Float(n) => {
-                                    let n = float_alloc!(n, self.machine_st.arena);
-                                    self.machine_st.unify_f64(n, return_value)
-                                }
-                                Value::Struct(name, args) => {
-                                    let struct_value = resource_error_call_result!(
-                                        self.machine_st,
-                                        self.build_struct(&name, args)
-                                    );
+        HeapCellValue::from((number, &mut self.machine_st.arena))
+    }
 
-                                    unify!(self.machine_st, return_value, struct_value);
-                                }
-                                Value::CString(cstr) => {
-                                    let str_cell = resource_error_call_result!(
-                                        self.machine_st,
-                                        self.machine_st.heap.allocate_cstr(cstr.to_str().unwrap())
-                                    );
+    fn json_tagged_string(&mut self, string: &str) -> Result<HeapCellValue, usize> {
+        let string = self.machine_st.heap.allocate_cstr(string)?;
+        Heap::functor_writer(functor!(atom!("string"), [cell(string)]))(&mut self.machine_st.heap)
+    }
 
-                                    unify!(self.machine_st, str_cell, return_value);
-                                }
-                            }
-                            return Ok(());
+    fn json_value_to_heap(
+        &mut self,
+        value: JsonValue,
+        options: &JsonTermOptions,
+    ) -> Result<HeapCellValue, usize> {
+        let (name, arg) = match value {
+            JsonValue::Object(members) => {
+                let mut pairs = Vec::with_capacity(members.len());
+
+                for (key, value) in members {
+                    let key = match options.keys {
+                        atom!("atom") => {
+                            atom_as_cell!(AtomTable::build_with(&self.machine_st.atom_tbl, &key))
                         }
-                        Err(e) => {
-                            let stub = functor_stub(atom!("current_input"), 1);
-                            let err = self.machine_st.ffi_error(e);
+                        atom!("chars") => self.machine_st.heap.allocate_cstr(&key)?,
+                        _ => self.json_tagged_string(&key)?,
+                    };
 
-                            return Err(self.machine_st.error_form(err, stub));
-                        }
-                    }
+                    let value = self.json_value_to_heap(value, options)?;
+
+                    pairs.push(Heap::functor_writer(functor!(
+                        atom!("-"),
+                        [cell(key), cell(value)]
+                    ))(&mut self.machine_st.heap)?);
                 }
-                Err(e) => return Err(e),
+
+                let pairs = sized_iter_to_heap_list(
+                    &mut self.machine_st.heap,
+                    pairs.len(),
+                    pairs.into_iter(),
+                )?;
+
+                (atom!("pairs"), pairs)
             }
-        }
+            JsonValue::Array(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
 
-        self.machine_st.fail = true;
-        Ok(())
+                for value in elements {
+                    values.push(self.json_value_to_heap(value, options)?);
+                }
+
+                let values = sized_iter_to_heap_list(
+                    &mut self.machine_st.heap,
+                    values.len(),
+                    values.into_iter(),
+                )?;
+
+                (atom!("list"), values)
+            }
+            JsonValue::String(string) => return self.json_tagged_string(&string),
+            JsonValue::Number(number) => (atom!("number"), self.json_number_cell(number)),
+            JsonValue::Boolean(true) => return Ok(options.true_term),
+            JsonValue::Boolean(false) => return Ok(options.false_term),
+            JsonValue::Null => return Ok(options.null),
+        };
+
+        Heap::functor_writer(functor!(name, [cell(arg)]))(&mut self.machine_st.heap)
     }
 
-    #[cfg(feature = "ffi")]
-    fn build_struct(&mut self, name: &str, mut args: Vec<Value>) -> Result<HeapCellValue, usize> {
-        args.insert(0, Value::CString(CString::new(name).unwrap()));
+    // unifies register 6 with the term of the value read by read from
+    // the text of register 1, represented as in registers 2 to 5.
+    // fails if read finds no value.
+    fn parse_json_term(&mut self, read: impl FnOnce(&str) -> Option<JsonValue>) {
+        let Some(value) = self
+            .json_text(self.machine_st.registers[1])
+            .and_then(|text| read(&text))
+        else {
+            self.machine_st.fail = true;
+            return;
+        };
 
-        let mut expanded_args = Vec::with_capacity(args.len());
+        let options = self.json_term_options();
 
-        for val in args {
-            expanded_args.push(match val {
-                Value::Int(n) => {
-                    if let Ok(fixnum) = Fixnum::build_with_checked(n) {
-                        fixnum_as_cell!(fixnum)
-                    } else {
-                        integer_as_cell!(Number::Integer(arena_alloc!(
-                            Integer::from(n),
-                            &mut self.machine_st.arena
-                        )))
-                    }
-                }
-                Value::Float(n) => HeapCellValue::from(float_alloc!(n, self.machine_st.arena)),
-                Value::CString(cstr) => atom_as_cell!(AtomTable::build_with(
-                    &self.machine_st.atom_tbl,
-                    &cstr.into_string().unwrap()
-                )),
-                Value::Struct(name, struct_args) => self.build_struct(&name, struct_args)?,
-            });
-        }
+        let term =
+            step_or_resource_error!(self.machine_st, self.json_value_to_heap(value, &options));
 
-        sized_iter_to_heap_list(
-            &mut self.machine_st.heap,
-            expanded_args.len(),
-            expanded_args.into_iter(),
-        )
+        unify_fn!(self.machine_st, term, self.machine_st.registers[6]);
     }
 
-    #[cfg(feature = "ffi")]
-    #[inline(always)]
-    pub(crate) fn define_foreign_struct(&mut self) -> CallResult {
-        let struct_name = self.deref_register(1);
-        let fields_reg = self.deref_register(2);
-        if let Some(struct_name) = self.machine_st.value_to_str_like(struct_name) {
-            let stub_gen = || functor_stub(atom!("define_foreign_struct"), 2);
-            let fields: Vec<Atom> = match self.machine_st.try_from_list(fields_reg, stub_gen) {
-                Ok(addrs) => {
-                    let mut args = Vec::new();
-                    for heap_cell in addrs {
-                        args.push(cell_as_atom_cell!(heap_cell).get_name());
-                    }
-                    args
-                }
-                Err(e) => return Err(e),
-            };
-            self.foreign_function_table
-                .define_struct(&struct_name.as_str(), fields);
-            return Ok(());
-        }
-        self.machine_st.fail = true;
-        Ok(())
+    // '$json_parse'(+Chars, +Null, +True, +False, +Keys, -Term) unifies
+    // Term with the value of the JSON text Chars. fails if Chars isn't
+    // JSON.
+    pub(crate) fn json_parse(&mut self) {
+        self.parse_json_term(|text| {
+            let mut reader = JsonReader::new(CharReader::new(text.as_bytes()));
+            let value = reader.read_value().ok()?;
+
+            (reader.at_end() == Ok(true)).then_some(value)
+        });
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    #[inline(always)]
-    pub(crate) fn js_eval(&mut self) -> CallResult {
-        unimplemented!()
+    // '$yaml_parse'(+Chars, +Null, +True, +False, +Keys, -Term) is
+    // '$json_parse' for YAML documents.
+    pub(crate) fn yaml_parse(&mut self) {
+        self.parse_json_term(read_yaml);
     }
 
-    #[cfg(target_arch = "wasm32")]
-    #[inline(always)]
-    pub(crate) fn js_eval(&mut self) -> CallResult {
-        let code = self.deref_register(1);
-        let result_reg = self.deref_register(2);
-        if let Some(code) = self.machine_st.value_to_str_like(code) {
-            match js_sys::eval(&code.as_str()) {
-                Ok(result) => self.unify_js_value(result, result_reg)?,
-                Err(result) => self.unify_js_value(result, result_reg)?,
-            };
+    // '$toml_parse'(+Chars, +Null, +True, +False, +Keys, -Term) is
+    // '$json_parse' for TOML documents.
+    pub(crate) fn toml_parse(&mut self) {
+        self.parse_json_term(read_toml);
+    }
+
+    // the text of a string of a JSON term, a list of characters or an
+    // atom.
+    fn json_text(&mut self, term: HeapCellValue) -> Option<String> {
+        let term = self.machine_st.store(self.machine_st.deref(term));
 
-            return Ok(());
+        match self.machine_st.value_to_str_like(term)? {
+            AtomOrString::Atom(atom!("[]")) => Some(String::new()),
+            atom_or_string => Some(atom_or_string.into()),
         }
-        self.machine_st.fail = true;
-        Ok(())
     }
 
-    #[cfg(target_arch = "wasm32")]
-    fn unify_js_value(
-        &mut self,
-        result: wasm_bindgen::JsValue,
-        result_reg: HeapCellValue,
-    ) -> CallResult {
-        match result.as_bool() {
-            Some(result) => match result {
-                true => self.machine_st.unify_atom(atom!("true"), result_reg),
-                false => self.machine_st.unify_atom(atom!("false"), result_reg),
-            },
-            None => match result.as_f64() {
-                Some(result) => {
-                    let n = float_alloc!(result, self.machine_st.arena);
-                    self.machine_st.unify_f64(n, result_reg);
-                }
-                None => match result.as_string() {
-                    Some(result) => {
-                        resource_error_call_result!(
-                            self.machine_st,
-                            self.machine_st.heap.allocate_cstr(result.as_str())
-                        );
-                    }
-                    None => {
-                        if result.is_null() {
-                            self.machine_st.unify_atom(atom!("null"), result_reg);
-                        } else if result.is_undefined() {
-                            self.machine_st.unify_atom(atom!("undefined"), result_reg);
-                        } else if result.is_symbol() {
-                            self.machine_st.unify_atom(atom!("js_symbol"), result_reg);
-                        } else if result.is_object() {
-                            self.machine_st.unify_atom(atom!("js_object"), result_reg);
-                        } else if result.is_array() {
-                            self.machine_st.unify_atom(atom!("js_array"), result_reg);
-                        } else if result.is_function() {
-                            self.machine_st.unify_atom(atom!("js_function"), result_reg);
-                        } else if result.is_bigint() {
-                            self.machine_st.unify_atom(atom!("js_bigint"), result_reg);
-                        } else {
-                            self.machine_st
-                                .unify_atom(atom!("js_unknown_type"), result_reg);
-                        }
-                    }
-                },
-            },
-        }
+    // the argument of term if it is name(Arg).
+    fn json_tagged_arg(&mut self, term: HeapCellValue, name: Atom) -> Option<HeapCellValue> {
+        let term = self.machine_st.store(self.machine_st.deref(term));
 
-        Ok(())
+        read_heap_cell!(term,
+            (HeapCellValueTag::Str, s) => {
+                let (other_name, arity) = cell_as_atom_cell!(self.machine_st.heap[s])
+                    .get_name_and_arity();
+
+                (other_name == name && arity == 1).then(|| self.machine_st.heap[s + 1])
+            }
+            _ => {
+                None
+            }
+        )
     }
 
-    #[inline(always)]
-    pub(crate) fn argv(&mut self) -> CallResult {
-        let args = self.deref_register(1);
-        let mut args_pstrs = vec![];
+    fn json_list(&mut self, term: HeapCellValue) -> Option<Vec<HeapCellValue>> {
+        let stub_gen = || functor_stub(atom!("json_chars"), 2);
+        self.machine_st.try_from_list(term, stub_gen).ok()
+    }
 
-        for arg in env::args() {
-            let pstr_cell = resource_error_call_result!(
-                self.machine_st,
-                self.machine_st.heap.allocate_cstr(&arg)
-            );
+    // the JSON value of term, a term of library(serialization/json)
+    // represented as in options. None if term isn't the term of a JSON
+    // value.
+    fn json_term_value(
+        &mut self,
+        term: HeapCellValue,
+        options: &JsonTermOptions,
+        depth: usize,
+    ) -> Option<JsonValue> {
+        if depth == MAX_DEPTH {
+            return None;
+        }
 
-            args_pstrs.push(pstr_cell);
+        let term = self.machine_st.store(self.machine_st.deref(term));
+
+        for (value, option) in [
+            (JsonValue::Null, options.null),
+            (JsonValue::Boolean(true), options.true_term),
+            (JsonValue::Boolean(false), options.false_term),
+        ] {
+            if !self.machine_st.eq_test(term, option) {
+                return Some(value);
+            }
         }
 
-        let list_cell = resource_error_call_result!(
-            self.machine_st,
-            sized_iter_to_heap_list(
-                &mut self.machine_st.heap,
-                args_pstrs.len(),
-                args_pstrs.into_iter(),
-            )
-        );
+        if let Some(pairs) = self.json_tagged_arg(term, atom!("pairs")) {
+            let pairs = self.json_list(pairs)?;
+            let mut members = Vec::with_capacity(pairs.len());
 
-        unify!(self.machine_st, args, list_cell);
-        Ok(())
-    }
+            for pair in pairs {
+                let pair = self.machine_st.store(self.machine_st.deref(pair));
 
-    #[inline(always)]
-    pub(crate) fn current_time(&mut self) {
-        let timestamp = self.systemtime_to_timestamp(SystemTime::now());
-        let cstr_cell = step_or_resource_error!(
-            self.machine_st,
-            self.machine_st.heap.allocate_cstr(&timestamp)
-        );
+                let (key, value) = read_heap_cell!(pair,
+                    (HeapCellValueTag::Str, s) => {
+                        let (name, arity) = cell_as_atom_cell!(self.machine_st.heap[s])
+                            .get_name_and_arity();
 
-        unify!(self.machine_st, cstr_cell, self.machine_st.registers[1]);
-    }
+                        if (name, arity) != (atom!("-"), 2) {
+                            return None;
+                        }
 
-    #[inline(always)]
-    pub(crate) fn open(&mut self) -> CallResult {
-        let alias = self.machine_st.registers[4];
-        let eof_action = self.machine_st.registers[5];
-        let reposition = self.machine_st.registers[6];
-        let stream_type = self.machine_st.registers[7];
+                        (self.machine_st.heap[s + 1], self.machine_st.heap[s + 2])
+                    }
+                    _ => {
+                        return None;
+                    }
+                );
 
-        let options =
-            self.machine_st
-                .get_stream_options(alias, eof_action, reposition, stream_type);
-        let src_sink = self.deref_register(1);
+                let key = match self.json_tagged_arg(key, atom!("string")) {
+                    Some(key) => self.json_text(key)?,
+                    None => self.json_text(key)?,
+                };
 
-        if let Some(file_spec) = self.machine_st.value_to_str_like(src_sink) {
-            let file_spec = file_spec.as_atom(&self.machine_st.atom_tbl);
+                members.push((key, self.json_term_value(value, options, depth + 1)?));
+            }
 
-            let mut stream =
-                self.machine_st
-                    .stream_from_file_spec(file_spec, &mut self.indices, &options)?;
+            Some(JsonValue::Object(members))
+        } else if let Some(values) = self.json_tagged_arg(term, atom!("list")) {
+            let values = self.json_list(values)?;
+            let mut elements = Vec::with_capacity(values.len());
 
-            *stream.options_mut() = options;
+            for value in values {
+                elements.push(self.json_term_value(value, options, depth + 1)?);
+            }
 
-            self.indices
-                .add_stream(stream, atom!("open"), 4)
-                .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+            Some(JsonValue::Array(elements))
+        } else if let Some(string) = self.json_tagged_arg(term, atom!("string")) {
+            Some(JsonValue::String(self.json_text(string)?))
+        } else if let Some(number) = self.json_tagged_arg(term, atom!("number")) {
+            let number = self.machine_st.store(self.machine_st.deref(number));
 
-            let stream_var = self.deref_register(3);
-            self.machine_st
-                .bind(stream_var.as_var().unwrap(), stream.into());
-        } else {
-            let err = self
-                .machine_st
-                .domain_error(DomainErrorType::SourceSink, src_sink);
-            let stub = functor_stub(atom!("open"), 4);
+            let number = match Number::try_from((number, &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => JsonNumber::Integer(Integer::from(n.get_num())),
+                Ok(Number::Integer(n)) => JsonNumber::Integer((*n).clone()),
+                Ok(Number::Float(OrderedFloat(n))) if n.is_finite() => JsonNumber::Float(n),
+                _ => return None,
+            };
 
-            return Err(self.machine_st.error_form(err, stub));
+            Some(JsonValue::Number(number))
+        } else {
+            None
         }
+    }
 
-        Ok(())
+    // unifies register 6 with the text written by write of the value
+    // of the term of register 1, represented as in registers 2 to 5.
+    // fails if the term isn't the term of a value, or write writes no
+    // text of it.
+    fn generate_json_term(&mut self, write: impl FnOnce(&JsonValue) -> Option<String>) {
+        let options = self.json_term_options();
+
+        let Some(text) = self
+            .json_term_value(self.machine_st.registers[1], &options, 0)
+            .and_then(|value| write(&value))
+        else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let chars =
+            step_or_resource_error!(self.machine_st, self.machine_st.heap.allocate_cstr(&text));
+
+        unify_fn!(self.machine_st, chars, self.machine_st.registers[6]);
     }
 
-    #[inline(always)]
-    pub(crate) fn op_declaration(&mut self) -> CallResult {
-        let priority = self.deref_register(1);
-        let specifier = cell_as_atom_cell!(self.deref_register(2)).get_name();
+    // '$json_generate'(+Term, +Null, +True, +False, +Keys, -Chars)
+    // unifies Chars with the JSON text of Term. fails if Term isn't the
+    // term of a JSON value.
+    pub(crate) fn json_generate(&mut self) {
+        self.generate_json_term(|value| {
+            let mut json = String::new();
+            write_json_value(&mut json, value);
+            Some(json)
+        });
+    }
 
-        let priority = match Number::try_from((priority, &self.machine_st.arena.f64_tbl)) {
-            Ok(Number::Integer(n)) => {
-                let n: u16 = (&*n).try_into().unwrap();
-                n
-            }
-            Ok(Number::Fixnum(n)) => u16::try_from(n.get_num()).unwrap(),
-            _ => {
-                unreachable!();
+    // '$yaml_generate'(+Term, +Null, +True, +False, +Keys, -Chars) is
+    // '$json_generate' for YAML documents.
+    pub(crate) fn yaml_generate(&mut self) {
+        self.generate_json_term(write_yaml);
+    }
+
+    // '$toml_generate'(+Term, +Null, +True, +False, +Keys, -Chars) is
+    // '$json_generate' for TOML documents, which are objects.
+    pub(crate) fn toml_generate(&mut self) {
+        self.generate_json_term(write_toml);
+    }
+
+    // '$json_next_token'(+Stream, -Token) unifies Token with the next
+    // token of the JSON text read from Stream: begin_object,
+    // end_object, begin_array, end_array, name_separator,
+    // value_separator, string(Chars), number(N), true, false, null, or
+    // end_of_file at its end. only the token is read from Stream.
+    // fails if the text isn't JSON.
+    pub(crate) fn json_next_token(&mut self) -> CallResult {
+        let stream = self.machine_st.get_stream_or_alias(
+            self.machine_st.registers[1],
+            &self.indices,
+            atom!("json_events_foldl"),
+            4,
+        )?;
+
+        self.machine_st.check_stream_properties(
+            stream,
+            StreamType::Text,
+            Some(self.machine_st.registers[2]),
+            atom!("json_events_foldl"),
+            4,
+        )?;
+
+        let token = match JsonReader::new(stream).next_token() {
+            Ok(token) => token,
+            Err(JsonSyntaxError) => {
+                self.machine_st.fail = true;
+                return Ok(());
             }
         };
 
-        let op = read_heap_cell!(self.deref_register(3),
-            /*
-            (HeapCellValueTag::Char, c) => {
-                AtomTable::build_with(&self.machine_st.atom_tbl, &c.to_string())
+        let token = match token {
+            Some(JsonToken::BeginObject) => atom_as_cell!(atom!("begin_object")),
+            Some(JsonToken::EndObject) => atom_as_cell!(atom!("end_object")),
+            Some(JsonToken::BeginArray) => atom_as_cell!(atom!("begin_array")),
+            Some(JsonToken::EndArray) => atom_as_cell!(atom!("end_array")),
+            Some(JsonToken::NameSeparator) => atom_as_cell!(atom!("name_separator")),
+            Some(JsonToken::ValueSeparator) => atom_as_cell!(atom!("value_separator")),
+            Some(JsonToken::String(string)) => {
+                resource_error_call_result!(self.machine_st, self.json_tagged_string(&string))
             }
-            */
-            (HeapCellValueTag::Atom, (name, _arity)) => {
-                name
+            Some(JsonToken::Number(number)) => {
+                let number = self.json_number_cell(number);
+
+                resource_error_call_result!(
+                    self.machine_st,
+                    Heap::functor_writer(functor!(atom!("number"), [cell(number)]))(
+                        &mut self.machine_st.heap
+                    )
+                )
             }
-            (HeapCellValueTag::Str, s) => {
-                cell_as_atom!(self.machine_st.heap[s])
+            Some(JsonToken::True) => atom_as_cell!(atom!("true")),
+            Some(JsonToken::False) => atom_as_cell!(atom!("false")),
+            Some(JsonToken::Null) => atom_as_cell!(atom!("null")),
+            None => atom_as_cell!(atom!("end_of_file")),
+        };
+
+        unify_fn!(self.machine_st, token, self.machine_st.registers[2]);
+        Ok(())
+    }
+
+    // the delimiter of register i, the code of an ASCII character.
+    fn csv_delimiter(&mut self, i: usize) -> u8 {
+        match Number::try_from((self.deref_register(i), &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Fixnum(n)) => u8::try_from(n.get_num()).unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    // '$csv_read_row'(+Stream, +Delimiter, +Convert, -Row) unifies Row
+    // with the list of the fields of the next row of the CSV text read
+    // from Stream, or with end_of_file at its end. fields written as
+    // numbers are read as numbers if Convert is true. only the row is
+    // read from Stream. fails if the text of Stream can't be read.
+    pub(crate) fn csv_read_row(&mut self) -> CallResult {
+        let mut stream = self.machine_st.get_stream_or_alias(
+            self.machine_st.registers[1],
+            &self.indices,
+            atom!("csv_read_stream"),
+            3,
+        )?;
+
+        self.machine_st.check_stream_properties(
+            stream,
+            StreamType::Text,
+            Some(self.machine_st.registers[4]),
+            atom!("csv_read_stream"),
+            3,
+        )?;
+
+        let delimiter = self.csv_delimiter(2);
+        let convert = self.deref_register(3) == atom_as_cell!(atom!("true"));
+
+        let fields = match read_csv_row(&mut stream, delimiter) {
+            Ok(Some(fields)) => fields,
+            Ok(None) => {
+                let row = atom_as_cell!(atom!("end_of_file"));
+                unify_fn!(self.machine_st, row, self.machine_st.registers[4]);
+                return Ok(());
             }
-            _ => {
-                unreachable!()
+            Err(_) => {
+                self.machine_st.fail = true;
+                return Ok(());
             }
+        };
+
+        let mut cells = Vec::with_capacity(fields.len());
+
+        for field in fields {
+            let cell = match csv_number(&field).filter(|_| convert) {
+                Some(CsvNumber::Integer(n)) => {
+                    let n = Number::arena_from(n, &mut self.machine_st.arena);
+                    HeapCellValue::from((n, &mut self.machine_st.arena))
+                }
+                Some(CsvNumber::Float(n)) => HeapCellValue::from((
+                    Number::Float(OrderedFloat(n)),
+                    &mut self.machine_st.arena,
+                )),
+                None => resource_error_call_result!(
+                    self.machine_st,
+                    self.machine_st.heap.allocate_cstr(&field)
+                ),
+            };
+
+            cells.push(cell);
+        }
+
+        let row = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, cells.len(), cells.into_iter())
         );
 
-        let result = to_op_decl_spec(specifier)
-            .map_err(SessionError::from)
-            .map(|specifier| to_op_decl(priority, specifier, op))
-            .and_then(|mut op_decl| {
-                if op_decl.op_desc.get_prec() == 0 {
-                    op_decl.remove(&mut self.indices.op_dir);
-                    Ok(())
-                } else {
-                    let spec = get_op_desc(
-                        op_decl.name,
-                        &CompositeOpDir::new(&self.indices.op_dir, None),
-                    );
+        unify_fn!(self.machine_st, row, self.machine_st.registers[4]);
+        Ok(())
+    }
+
+    // '$csv_row_chars'(+Fields, +Delimiter, -Chars) unifies Chars with
+    // the CSV text of the row of Fields, numbers, atoms or lists of
+    // characters, without a line separator.
+    pub(crate) fn csv_row_chars(&mut self) {
+        let stub_gen = || functor_stub(atom!("csv_write_file"), 3);
+
+        let Ok(cells) = self
+            .machine_st
+            .try_from_list(self.machine_st.registers[1], stub_gen)
+        else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let mut fields = Vec::with_capacity(cells.len());
+
+        for cell in cells {
+            let cell = self.machine_st.store(self.machine_st.deref(cell));
+
+            let field = match Number::try_from((cell, &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => n.get_num().to_string(),
+                Ok(Number::Integer(n)) => n.to_string(),
+                Ok(Number::Float(OrderedFloat(n))) => fmt_float(n),
+                Ok(Number::Rational(n)) => n.to_string(),
+                Err(_) => match self.machine_st.value_to_str_like(cell) {
+                    Some(AtomOrString::Atom(atom!("[]"))) => String::new(),
+                    Some(atom_or_string) => atom_or_string.into(),
+                    None => {
+                        self.machine_st.fail = true;
+                        return;
+                    }
+                },
+            };
 
-                    op_decl.submit(spec, &mut self.indices.op_dir)
-                }
-            });
+            fields.push(field);
+        }
 
-        match result {
-            Ok(()) => Ok(()),
-            Err(e) => {
-                // 8.14.3.3 l)
-                let err = self.machine_st.session_error(e);
-                let stub = functor_stub(atom!("op"), 3);
+        let mut csv = String::new();
+        write_csv_row(&mut csv, &fields, self.csv_delimiter(2));
 
-                Err(self.machine_st.error_form(err, stub))
+        let chars =
+            step_or_resource_error!(self.machine_st, self.machine_st.heap.allocate_cstr(&csv));
+
+        unify_fn!(self.machine_st, chars, self.machine_st.registers[3]);
+    }
+
+    // the term of element, a number, true, false or complex(Re, Im).
+    fn array_element_cell(&mut self, element: ArrayElement) -> Result<HeapCellValue, usize> {
+        Ok(match element {
+            ArrayElement::Integer(n) => {
+                let n = Number::arena_from(n, &mut self.machine_st.arena);
+                HeapCellValue::from((n, &mut self.machine_st.arena))
             }
-        }
+            ArrayElement::Float(f) => {
+                HeapCellValue::from((Number::Float(OrderedFloat(f)), &mut self.machine_st.arena))
+            }
+            ArrayElement::Complex(re, im) => {
+                let re = HeapCellValue::from((
+                    Number::Float(OrderedFloat(re)),
+                    &mut self.machine_st.arena,
+                ));
+                let im = HeapCellValue::from((
+                    Number::Float(OrderedFloat(im)),
+                    &mut self.machine_st.arena,
+                ));
+
+                let mut writer =
+                    Heap::functor_writer(functor!(atom!("complex"), [cell(re), cell(im)]));
+                writer(&mut self.machine_st.heap)?
+            }
+            ArrayElement::Boolean(true) => atom_as_cell!(atom!("true")),
+            ArrayElement::Boolean(false) => atom_as_cell!(atom!("false")),
+        })
     }
 
-    #[inline(always)]
-    pub(crate) fn set_stream_options(&mut self) -> CallResult {
-        let stream = self.machine_st.get_stream_or_alias(
-            self.machine_st.registers[1],
-            &self.indices,
-            atom!("open"),
-            4,
-        )?;
+    // the nested lists of the elements of an array of shape, in
+    // row-major order.
+    fn array_elements_cell(
+        &mut self,
+        shape: &[usize],
+        elements: &[HeapCellValue],
+    ) -> Result<HeapCellValue, usize> {
+        let Some((&len, shape)) = shape.split_first() else {
+            return Ok(elements[0]);
+        };
 
-        let alias = self.machine_st.registers[2];
-        let eof_action = self.machine_st.registers[3];
-        let reposition = self.machine_st.registers[4];
-        let stream_type = self.machine_st.registers[5];
+        let stride = shape.iter().product::<usize>();
+        let mut rows = Vec::with_capacity(len);
 
-        let new_options =
-            self.machine_st
-                .get_stream_options(alias, eof_action, reposition, stream_type);
-        self.indices.update_stream_options(stream, |options| {
-            *options = new_options;
-        });
+        for i in 0..len {
+            let row = &elements[i * stride..(i + 1) * stride];
+            rows.push(self.array_elements_cell(shape, row)?);
+        }
 
-        Ok(())
+        sized_iter_to_heap_list(&mut self.machine_st.heap, len, rows.into_iter())
     }
 
-    #[inline(always)]
-    pub(crate) fn truncate_if_no_lifted_heap_growth_diff(&mut self) {
-        self.machine_st
-            .truncate_if_no_lifted_heap_diff(|h| heap_loc_as_cell!(h))
-    }
+    fn usizes_cell(&mut self, ns: &[usize]) -> Result<HeapCellValue, usize> {
+        let ns: Vec<_> = ns
+            .iter()
+            .map(|&n| {
+                let n = Number::arena_from(n, &mut self.machine_st.arena);
+                HeapCellValue::from((n, &mut self.machine_st.arena))
+            })
+            .collect();
 
-    #[inline(always)]
-    pub(crate) fn truncate_if_no_lifted_heap_growth(&mut self) {
-        self.machine_st
-            .truncate_if_no_lifted_heap_diff(|_| empty_list_as_cell!())
+        sized_iter_to_heap_list(&mut self.machine_st.heap, ns.len(), ns.into_iter())
     }
 
-    #[inline(always)]
-    pub(crate) fn get_attributed_variable_list(&mut self) {
-        let attr_var = self.deref_register(1);
-        let attr_var_list = read_heap_cell!(attr_var,
-            (HeapCellValueTag::AttrVar, h) => {
-                h+1
-            }
-            (HeapCellValueTag::Var, h) => {
-                h
-            }
-            _ => {
-                self.machine_st.fail = true;
-                return;
-            }
-        );
+    // array(Shape, Elements) of array.
+    fn array_cell(&mut self, array: Array) -> Result<HeapCellValue, usize> {
+        let mut elements = Vec::with_capacity(array.elements.len());
 
-        let list_addr = self.deref_register(2);
-        self.machine_st
-            .bind(Ref::heap_cell(attr_var_list), list_addr);
-    }
+        for element in array.elements {
+            elements.push(self.array_element_cell(element)?);
+        }
 
-    #[inline(always)]
-    pub(crate) fn get_from_attributed_variable_list(&mut self) {
-        let attr_var = self.deref_register(1);
-        let attr = self.deref_register(3);
-        let attr_var_list = read_heap_cell!(attr_var,
-            (HeapCellValueTag::AttrVar, h) => {
-                self.machine_st.heap[h+1]
-            }
-            _ => {
-                self.machine_st.fail = true;
-                return;
-            }
-        );
+        let shape = self.usizes_cell(&array.shape)?;
+        let elements = self.array_elements_cell(&array.shape, &elements)?;
 
-        let module = self.deref_register(2);
+        let mut writer =
+            Heap::functor_writer(functor!(atom!("array"), [cell(shape), cell(elements)]));
+        writer(&mut self.machine_st.heap)
+    }
 
-        match self.match_attribute(attr_var_list, module, attr) {
-            Some(AttrListMatch {
-                match_site: MatchSite::Match(match_site),
-                ..
-            }) => {
-                let list_head = self.machine_st.heap[match_site];
+    // sparse([Rows, Columns], Entries) of matrix, with the entries as
+    // entry(I, J, Value).
+    fn sparse_matrix_cell(&mut self, matrix: SparseMatrix) -> Result<HeapCellValue, usize> {
+        let mut entries = Vec::with_capacity(matrix.entries.len());
 
-                if list_head.get_value() as usize == match_site {
-                    // at the end of the list, no match found in this case.
-                    self.machine_st.fail = true;
-                } else {
-                    let (_, qualified_goal) = self.machine_st.strip_module(list_head);
+        for (i, j, element) in matrix.entries {
+            let element = self.array_element_cell(element)?;
+            let i = HeapCellValue::from((
+                Number::arena_from(i, &mut self.machine_st.arena),
+                &mut self.machine_st.arena,
+            ));
+            let j = HeapCellValue::from((
+                Number::arena_from(j, &mut self.machine_st.arena),
+                &mut self.machine_st.arena,
+            ));
 
-                    unify!(self.machine_st, qualified_goal, attr);
-                }
-            }
-            _ => {
-                self.machine_st.fail = true;
-            }
+            let mut writer =
+                Heap::functor_writer(functor!(atom!("entry"), [cell(i), cell(j), cell(element)]));
+            entries.push(writer(&mut self.machine_st.heap)?);
         }
-    }
 
-    #[inline(always)]
-    pub(crate) fn get_attr_var_queue_delimiter(&mut self) {
-        let addr = self.deref_register(1);
+        let shape = self.usizes_cell(&[matrix.rows, matrix.columns])?;
+        let entries = sized_iter_to_heap_list(
+            &mut self.machine_st.heap,
+            entries.len(),
+            entries.into_iter(),
+        )?;
 
-        /* FIXME this is not safe */
-        let value = unsafe {
-            Fixnum::build_with_unchecked(self.machine_st.attr_var_init.attr_var_queue.len() as i64)
+        let mut writer =
+            Heap::functor_writer(functor!(atom!("sparse"), [cell(shape), cell(entries)]));
+        writer(&mut self.machine_st.heap)
+    }
+
+    // '$array_read'(+Stream, +Format, -Array) unifies Array with the
+    // array read from the rest of the binary Stream, of the format npy
+    // or matrix_market. fails if the bytes aren't of the format.
+    pub(crate) fn array_read(&mut self) -> CallResult {
+        let format = cell_as_atom!(self.deref_register(2));
+        let caller = match format {
+            atom!("npy") => atom!("npy_read"),
+            _ => atom!("mm_read"),
         };
 
-        self.machine_st.unify_fixnum(value, addr);
-    }
+        let mut stream = self.machine_st.get_stream_or_alias(
+            self.machine_st.registers[1],
+            &self.indices,
+            caller,
+            2,
+        )?;
 
-    #[inline(always)]
-    pub(crate) fn get_attr_var_queue_beyond(&mut self) {
-        let addr = self.deref_register(1);
+        self.machine_st.check_stream_properties(
+            stream,
+            StreamType::Binary,
+            Some(self.machine_st.registers[3]),
+            caller,
+            2,
+        )?;
 
-        let b = match Number::try_from((addr, &self.machine_st.arena.f64_tbl)) {
-            Ok(Number::Integer(n)) => {
-                let value: usize = (&*n).try_into().unwrap();
-                Some(value)
-            }
-            Ok(Number::Fixnum(n)) => usize::try_from(n.get_num()).ok(),
-            _ => {
-                self.machine_st.fail = true;
-                return;
-            }
+        let mut bytes = vec![];
+
+        if stream.read_to_end(&mut bytes).is_err() {
+            self.machine_st.fail = true;
+            return Ok(());
+        }
+
+        let array = match format {
+            atom!("npy") => read_npy(&bytes).map(|array| self.array_cell(array)),
+            _ => match read_matrix_market(&String::from_utf8_lossy(&bytes)) {
+                Some(MatrixMarket::Array(array)) => Some(self.array_cell(array)),
+                Some(MatrixMarket::Coordinate(matrix)) => Some(self.sparse_matrix_cell(matrix)),
+                None => None,
+            },
         };
 
-        if let Some(b) = b {
-            let attr_vars = self.machine_st.gather_attr_vars_created_since(b);
+        let Some(array) = array else {
+            self.machine_st.fail = true;
+            return Ok(());
+        };
 
-            let var_list_addr = step_or_resource_error!(
-                self.machine_st,
-                sized_iter_to_heap_list(
-                    &mut self.machine_st.heap,
-                    attr_vars.len(),
-                    attr_vars.into_iter(),
-                )
-            );
+        let array = resource_error_call_result!(self.machine_st, array);
 
-            let list_addr = self.machine_st.registers[2];
-            unify!(self.machine_st, var_list_addr, list_addr);
-        }
+        unify_fn!(self.machine_st, array, self.machine_st.registers[3]);
+        Ok(())
     }
 
     #[inline(always)]
-    pub(crate) fn delete_from_attributed_variable_list(&mut self) {
-        let attr_var = self.deref_register(1);
-        let attr = self.deref_register(3);
-        let attr_var_list = read_heap_cell!(attr_var,
-            (HeapCellValueTag::AttrVar, h) => {
-                h + 1
-            }
-            _ => {
-                return;
-            }
-        );
-
-        let module = self.deref_register(2);
-
-        if let Some(AttrListMatch {
-            prev_tail,
-            match_site: MatchSite::Match(match_site),
-        }) = self.match_attribute(self.machine_st.heap[attr_var_list], module, attr)
-        {
-            let prev_tail = if let Some(prev_tail) = prev_tail {
-                // not at the head.
-                prev_tail
-            } else {
-                if self.machine_st.heap[match_site + 1].is_var() {
-                    let h = attr_var.get_value() as usize;
+    pub(crate) fn thread_create(&mut self) {
+        self.spawn_thread(false);
+    }
 
-                    self.machine_st.heap[h] = heap_loc_as_cell!(h);
-                    self.machine_st.trail(TrailRef::Ref(Ref::attr_var(h)));
-                }
+    #[inline(always)]
+    pub(crate) fn thread_create_background(&mut self) {
+        self.spawn_thread(true);
+    }
 
-                // at the head.
-                attr_var_list
-            };
+    fn spawn_thread(&mut self, background: bool) {
+        // A1 = the goal, written by thread_create/3 or
+        // spawn_initialization/2.
+        let goal = self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[1]);
+        let id = self.deref_register(2);
 
-            if self.machine_st.heap[match_site + 1].get_tag() == HeapCellValueTag::Lis {
-                let prev_tail_value = self.machine_st.heap[match_site + 1].get_value();
-                self.machine_st.heap[prev_tail].set_value(prev_tail_value);
-            } else {
-                self.machine_st.heap[prev_tail] = heap_loc_as_cell!(prev_tail);
-            }
+        let alias = if id.is_var() {
+            None
+        } else {
+            Some(cell_as_atom!(id))
+        };
 
-            self.machine_st
-                .trail(TrailRef::AttrVarListLink(prev_tail, match_site));
+        match goal.and_then(|goal| {
+            threads::spawn_thread(
+                &self.machine_st.atom_tbl,
+                alias,
+                goal.as_str().to_string(),
+                background,
+            )
+        }) {
+            Some(name) => self.machine_st.unify_atom(name, id),
+            None => self.machine_st.fail = true,
         }
     }
 
     #[inline(always)]
-    pub(crate) fn put_to_attributed_variable_list(&mut self) {
-        let attr_var = self.deref_register(1);
-        let attr = self.deref_register(3);
-        let attr_var_list_result =
-            step_or_resource_error!(self.machine_st, self.machine_st.get_attr_var_list(attr_var));
+    pub(crate) fn thread_join(&mut self) {
+        let id = cell_as_atom!(self.deref_register(1));
 
-        let attr_var_list = match attr_var_list_result {
-            Some(h) => h,
+        match threads::join_thread(id) {
+            Some(status) => {
+                let status = step_or_resource_error!(
+                    self.machine_st,
+                    self.machine_st.heap.allocate_cstr(&status)
+                );
+
+                unify_fn!(self.machine_st, self.machine_st.registers[2], status);
+            }
             None => {
                 self.machine_st.fail = true;
-                return;
             }
-        };
-
-        let module = self.deref_register(2);
-
-        /*
-         * How to handle attribute trailing using just AttrVarListLink (which
-         * should be re-named to something more general) in unwind_trail:
-         *
-         * Given AttrVarListLink(h, l):
-         *
-         * 1. Check cell at offset l.
-         * 2. If h == l, set heap[h] = heap_loc_as_cell!(h).
-         * 3. If cell is a Var, set heap[h] = list_loc_as_cell!(l).
-         * 4. Otherwise, cell points to an element of the list which is therefore
-         *    an atom or str. Set heap[h] accordingly.
-         *
-         * For this to work, all elements of attributed variable lists must be
-         * heap cell locs pointing to later elements in the heap, either atoms (0-arity)
-         * or str cells (> 0-arity).
-         */
+        }
+    }
 
-        let module_functor = functor!(atom!(":"), [cell(module), cell(attr)]);
-        let h = self.machine_st.heap.cell_len();
+    #[inline(always)]
+    pub(crate) fn thread_self(&mut self) {
+        self.machine_st
+            .unify_atom(threads::thread_self(), self.machine_st.registers[1]);
+    }
 
-        step_or_resource_error!(
+    #[inline(always)]
+    pub(crate) fn thread_goal(&mut self) {
+        let goal = step_or_resource_error!(
             self.machine_st,
-            self.machine_st.heap.push_cell(str_loc_as_cell!(h + 1))
+            self.machine_st.heap.allocate_cstr(&threads::thread_goal())
         );
 
-        let mut functor_writer = Heap::functor_writer(module_functor);
-        step_or_resource_error!(self.machine_st, functor_writer(&mut self.machine_st.heap));
+        unify_fn!(self.machine_st, self.machine_st.registers[1], goal);
+    }
 
-        match self.match_attribute(self.machine_st.heap[attr_var_list], module, attr) {
-            Some(AttrListMatch { match_site, .. }) => {
-                let (match_site, l) = match match_site {
-                    MatchSite::NoMatchVarTail(match_site) => {
-                        let l = self.machine_st.heap[match_site].get_value();
+    #[inline(always)]
+    pub(crate) fn thread_set_status(&mut self) {
+        if let Some(status) = self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[1])
+        {
+            threads::set_thread_status(status.as_str().to_string());
+        }
+    }
 
-                        // at the end of the (non-empty) list here.
-                        self.machine_st.heap[match_site] = list_loc_as_cell!(h + 4);
+    #[inline(always)]
+    pub(crate) fn thread_status(&mut self) {
+        let id = cell_as_atom!(self.deref_register(1));
 
-                        let mut writer = step_or_resource_error!(
-                            self.machine_st,
-                            self.machine_st.heap.reserve(2)
-                        );
+        match threads::thread_is_running(id) {
+            Some(true) => self
+                .machine_st
+                .unify_atom(atom!("running"), self.machine_st.registers[2]),
+            Some(false) => self
+                .machine_st
+                .unify_atom(atom!("finished"), self.machine_st.registers[2]),
+            None => self.machine_st.fail = true,
+        }
+    }
 
-                        writer.write_with(|section| {
-                            section.push_cell(heap_loc_as_cell!(h));
-                            section.push_cell(heap_loc_as_cell!(h + 5));
-                        });
+    #[inline(always)]
+    pub(crate) fn thread_cancel(&mut self) {
+        let id = cell_as_atom!(self.deref_register(1));
+        self.machine_st.fail = !threads::cancel_thread(id);
+    }
 
-                        (match_site, l)
-                    }
-                    MatchSite::Match(match_site) => {
-                        let l = self.machine_st.heap[match_site].get_value();
-                        self.machine_st.heap[match_site].set_value(h as u64);
+    #[inline(always)]
+    pub(crate) fn background_threads(&mut self) {
+        let ids: Vec<_> = threads::background_threads()
+            .into_iter()
+            .map(|id| atom_as_cell!(id))
+            .collect();
 
-                        (match_site, l)
-                    }
-                };
+        let ids = step_or_resource_error!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, ids.len(), ids.into_iter())
+        );
 
-                self.machine_st
-                    .trail(TrailRef::AttrVarListLink(match_site, l as usize));
-            }
-            None => {
-                // the list is empty.
-                self.machine_st.heap[attr_var_list] = list_loc_as_cell!(h + 4);
+        unify_fn!(self.machine_st, self.machine_st.registers[1], ids);
+    }
 
-                let mut writer =
-                    step_or_resource_error!(self.machine_st, self.machine_st.heap.reserve(2));
+    // unifies A1 with the number of threads that can run in parallel,
+    // as estimated by the standard library.
+    #[inline(always)]
+    pub(crate) fn cpu_count(&mut self) {
+        let count = std::thread::available_parallelism().map_or(1, usize::from);
 
-                writer.write_with(|section| {
-                    section.push_cell(heap_loc_as_cell!(h));
-                    section.push_cell(heap_loc_as_cell!(h + 5));
-                });
+        self.machine_st.unify_fixnum(
+            Fixnum::build_with(i32::try_from(count).unwrap_or(i32::MAX)),
+            self.machine_st.registers[1],
+        );
+    }
 
-                self.machine_st
-                    .attr_var_init
-                    .attr_var_queue
-                    .push(attr_var_list - 1);
-                self.machine_st
-                    .trail(TrailRef::AttrVarListLink(attr_var_list, attr_var_list));
+    #[inline(always)]
+    pub(crate) fn parse_template(&mut self) {
+        let Some(src) = self.machine_st.value_to_str_like(self.deref_register(1)) else {
+            self.machine_st.fail = true;
+            return;
+        };
+
+        let heap = &mut self.machine_st.heap;
+        let atom_tbl = &self.machine_st.atom_tbl;
+
+        let result = match templates::parse_template(&src.as_str()) {
+            Ok(tokens) => {
+                let mut cells = Vec::with_capacity(tokens.len());
+
+                for token in &tokens {
+                    cells.push(step_or_resource_error!(
+                        self.machine_st,
+                        templates::token_cell(heap, atom_tbl, token)
+                    ));
+                }
+
+                sized_iter_to_heap_list(heap, cells.len(), cells.into_iter())
             }
-        }
+            Err(err) => templates::error_cell(heap, atom_tbl, &err),
+        };
+
+        let result = step_or_resource_error!(self.machine_st, result);
+        unify_fn!(self.machine_st, self.machine_st.registers[2], result);
     }
 
-    fn match_attribute(
-        &self,
-        mut attrs_list: HeapCellValue,
-        module: HeapCellValue,
-        attr: HeapCellValue,
-    ) -> Option<AttrListMatch> {
-        let (name, arity) = match self.machine_st.name_and_arity_from_heap(attr) {
-            Some(key) => key,
+    // unifies the third argument with the domain of intervals, which is
+    // one of the domains given as operands if it has their intervals,
+    // so that library(clpz) sees unchanged domains are unchanged.
+    fn unify_fd_domain(
+        &mut self,
+        intervals: fd_domains::Intervals,
+        operands: &[(&fd_domains::Intervals, HeapCellValue)],
+    ) {
+        let domain = match operands.iter().find(|(operand, _)| **operand == intervals) {
+            Some(&(_, domain)) => domain,
+            None if fd_domains::fixnum_bounds(&intervals) => step_or_resource_error!(
+                self.machine_st,
+                fd_domains::domain_cell(&mut self.machine_st.heap, &intervals)
+            ),
             None => {
-                return None;
+                self.machine_st.fail = true;
+                return;
             }
         };
 
-        let mut prev_tail = None;
+        unify_fn!(self.machine_st, self.machine_st.registers[3], domain);
+    }
 
-        while let HeapCellValueTag::Lis = attrs_list.get_tag() {
-            let mut list_head = self.machine_st.heap[attrs_list.get_value() as usize];
+    fn fd_domain_operands(
+        &mut self,
+    ) -> Option<(
+        fd_domains::Intervals,
+        HeapCellValue,
+        fd_domains::Intervals,
+        HeapCellValue,
+    )> {
+        let d1 = self.deref_register(1);
+        let d2 = self.deref_register(2);
 
-            loop {
-                read_heap_cell!(list_head,
-                    (HeapCellValueTag::AttrVar | HeapCellValueTag::Var, h) => {
-                        debug_assert!(list_head != self.machine_st.heap[h]);
-                        list_head = self.machine_st.heap[h];
-                    }
-                    (HeapCellValueTag::Str | HeapCellValueTag::Atom) => {
-                        let (quantification, qualified_goal) = self.machine_st.strip_module(list_head);
+        let intervals1 = fd_domains::read_domain(&self.machine_st, d1)?;
+        let intervals2 = fd_domains::read_domain(&self.machine_st, d2)?;
 
-                        let (t_name, t_arity) = self.machine_st
-                            .name_and_arity_from_heap(qualified_goal)
-                            .unwrap();
+        Some((intervals1, d1, intervals2, d2))
+    }
 
-                        if Some(module) == quantification.specified() &&
-                            name == t_name &&
-                            arity == t_arity
-                        {
-                            return Some(AttrListMatch {
-                                match_site: MatchSite::Match(attrs_list.get_value() as usize),
-                                prev_tail,
-                            });
-                        }
+    #[inline(always)]
+    pub(crate) fn fd_domains_intersection(&mut self) {
+        let Some((intervals1, d1, intervals2, d2)) = self.fd_domain_operands() else {
+            self.machine_st.fail = true;
+            return;
+        };
 
-                        break;
-                    }
-                    _ => {
-                        break;
-                    }
-                );
-            }
+        let intervals = fd_domains::intersection(&intervals1, &intervals2);
+        self.unify_fd_domain(intervals, &[(&intervals1, d1), (&intervals2, d2)]);
+    }
 
-            let tail_loc = attrs_list.get_value() as usize + 1;
-            prev_tail = Some(tail_loc);
+    #[inline(always)]
+    pub(crate) fn fd_domains_union(&mut self) {
+        let Some((intervals1, d1, intervals2, d2)) = self.fd_domain_operands() else {
+            self.machine_st.fail = true;
+            return;
+        };
 
-            // do the work of self.store(self.deref(...)) but inline it
-            // for speed and simplify it.
-            let mut list_tail = self.machine_st.heap[tail_loc];
+        let intervals = fd_domains::union(&intervals1, &intervals2);
+        self.unify_fd_domain(intervals, &[(&intervals1, d1), (&intervals2, d2)]);
+    }
 
-            loop {
-                read_heap_cell!(list_tail,
-                    (HeapCellValueTag::AttrVar | HeapCellValueTag::Var, h) => {
-                        if list_tail != self.machine_st.heap[h] {
-                            list_tail = self.machine_st.heap[h];
-                        } else {
-                            return Some(AttrListMatch {
-                                match_site: MatchSite::NoMatchVarTail(h),
-                                prev_tail,
-                            });
-                        }
-                    }
-                    (HeapCellValueTag::Lis) => {
-                        attrs_list = list_tail;
-                        break;
-                    }
-                    _ => {
-                        unreachable!()
-                    }
-                );
-            }
-        }
+    #[inline(always)]
+    pub(crate) fn fd_domain_subtract(&mut self) {
+        let Some((intervals1, d1, intervals2, _)) = self.fd_domain_operands() else {
+            self.machine_st.fail = true;
+            return;
+        };
 
-        None
+        let intervals = fd_domains::subtract(&intervals1, &intervals2);
+        self.unify_fd_domain(intervals, &[(&intervals1, d1)]);
     }
 
-    #[inline(always)]
-    pub(crate) fn get_continuation_chunk(&mut self) {
-        let e = self.deref_register(1);
-        let e = unsafe { e.to_fixnum_or_cut_point_unchecked() }.get_num() as usize;
-        let h = self.machine_st.heap.cell_len();
+    fn interval_operand(&self, lo: usize, hi: usize) -> Option<Interval> {
+        let f64_tbl = &self.machine_st.arena.f64_tbl;
 
-        let p_functor_cell = self.deref_register(2);
-        let num_cells = self.machine_st.stack.index_and_frame(e).prelude.num_cells;
+        match (
+            Number::try_from((self.deref_register(lo), f64_tbl)),
+            Number::try_from((self.deref_register(hi), f64_tbl)),
+        ) {
+            (Ok(Number::Float(lo)), Ok(Number::Float(hi))) => Some(Interval {
+                lo: lo.into_inner(),
+                hi: hi.into_inner(),
+            }),
+            _ => None,
+        }
+    }
 
-        let mut writer =
-            step_or_resource_error!(self.machine_st, self.machine_st.heap.reserve(2 + num_cells));
+    fn unify_interval(
+        &mut self,
+        result: Result<Interval, EvalError>,
+        lo: usize,
+        hi: usize,
+    ) -> CallResult {
+        let interval = result.map_err(|err| {
+            let err = self.machine_st.evaluation_error(err);
+            let stub = functor_stub(atom!("is_interval"), 2);
 
-        writer.write_with(|section| {
-            section.push_cell(atom_as_cell!(atom!("cont_chunk"), 1 + num_cells));
-            section.push_cell(p_functor_cell);
+            self.machine_st.error_form(err, stub)
+        })?;
 
-            for idx in 1..=num_cells {
-                let mut stack_offset = stack_loc!(AndFrame, e, idx);
-                let mut addr = self.machine_st.stack[stack_offset];
+        let lo_cell = float_alloc!(interval.lo, self.machine_st.arena);
+        let hi_cell = float_alloc!(interval.hi, self.machine_st.arena);
 
-                while addr.get_tag() == HeapCellValueTag::StackVar {
-                    stack_offset = addr.get_value() as usize;
+        self.machine_st
+            .unify_f64(lo_cell, self.machine_st.registers[lo]);
 
-                    if self.machine_st.stack[stack_offset] == addr {
-                        break;
-                    }
+        if !self.machine_st.fail {
+            self.machine_st
+                .unify_f64(hi_cell, self.machine_st.registers[hi]);
+        }
 
-                    addr = self.machine_st.stack[stack_offset];
+        Ok(())
+    }
+
+    // '$interval_number'(+N, -Lo, -Hi) unifies Lo and Hi with the
+    // bounds of the least interval of floats enclosing the number N.
+    #[inline(always)]
+    pub(crate) fn interval_number(&mut self) -> CallResult {
+        let result =
+            match Number::try_from((self.deref_register(1), &self.machine_st.arena.f64_tbl)) {
+                Ok(n) => Interval::from_number(&n),
+                Err(_) => {
+                    self.machine_st.fail = true;
+                    return Ok(());
                 }
+            };
 
-                if addr.get_tag() == HeapCellValueTag::StackVar {
-                    section.push_cell(heap_loc_as_cell!(h + 1 + idx));
-                    self.machine_st.stack[stack_offset] = heap_loc_as_cell!(h + 1 + idx);
+        self.unify_interval(result, 2, 3)
+    }
 
-                    // have to inline the TrailRef::Ref(RefTag::StackCell) case of MachineState::trail
-                    // here to get around the borrow checker.
-                    if stack_offset < self.machine_st.b {
-                        self.machine_st.trail.push(TrailEntry::build_with(
-                            TrailEntryTag::TrailedStackVar,
-                            stack_offset as u64,
-                        ));
+    // '$interval_op'(+Op, +Lo1, +Hi1, +Lo2, +Hi2, -Lo, -Hi) applies
+    // the evaluable Op of library(intervals) to the intervals Lo1..Hi1
+    // and Lo2..Hi2, the second of which unary evaluables ignore. The
+    // exponent of ^ is the integer Lo2.
+    pub(crate) fn interval_op(&mut self) -> CallResult {
+        let op = cell_as_atom!(self.deref_register(1));
 
-                        self.machine_st.tr += 1;
-                    }
-                } else {
-                    section.push_cell(addr);
+        let Some(x) = self.interval_operand(2, 3) else {
+            self.machine_st.fail = true;
+            return Ok(());
+        };
+
+        let result = if op == atom!("^") {
+            let exponent =
+                Number::try_from((self.deref_register(4), &self.machine_st.arena.f64_tbl));
+
+            match exponent.ok().as_ref().and_then(Interval::integer_exponent) {
+                Some(n) => x.powi(n),
+                None => {
+                    self.machine_st.fail = true;
+                    return Ok(());
                 }
             }
-        });
+        } else {
+            let y = self.interval_operand(4, 5);
+
+            match (op, y) {
+                (atom!("-"), _) if y.is_none() => x.neg(),
+                (atom!("abs"), _) => x.abs(),
+                (atom!("sqrt"), _) => x.sqrt(),
+                (atom!("exp"), _) => x.exp(),
+                (atom!("log"), _) => x.log(),
+                (atom!("sin"), _) => x.sin(),
+                (atom!("cos"), _) => x.cos(),
+                (atom!("tan"), _) => x.tan(),
+                (atom!("atan"), _) => x.atan(),
+                (atom!("+"), Some(y)) => x.add(y),
+                (atom!("-"), Some(y)) => x.sub(y),
+                (atom!("*"), Some(y)) => x.mul(y),
+                (atom!("/"), Some(y)) => x.div(y),
+                (atom!("min"), Some(y)) => x.min(y),
+                (atom!("max"), Some(y)) => x.max(y),
+                (atom!("**"), Some(y)) => x.pow(y),
+                _ => {
+                    self.machine_st.fail = true;
+                    return Ok(());
+                }
+            }
+        };
 
-        let chunk = str_loc_as_cell!(h);
-        unify!(self.machine_st, self.machine_st.registers[3], chunk);
+        self.unify_interval(result, 6, 7)
     }
 
-    #[inline(always)]
-    pub(crate) fn get_lifted_heap_from_offset_diff(&mut self) {
-        let lh_offset = self.machine_st.registers[1];
-        let lh_offset = unsafe {
-            self.machine_st
-                .store(self.machine_st.deref(lh_offset))
-                .to_fixnum_or_cut_point_unchecked()
-        }
-        .get_num() as usize;
+    // '$btree_find_node'(+Key, +Tree, +Format, -Node, -Rel) descends
+    // the binary search tree Tree towards Key, as in SWI-Prolog. Format
+    // is 0xKKLLRR, the argument positions of the key and the left and
+    // right subtrees of each node. Node is the node holding Key, with
+    // Rel `=`, or else the last node on the way, with Rel `<` or `>`
+    // for the subtree Key would be in. Subtrees of another functor than
+    // Tree are empty. Fails if Tree isn't a node.
+    #[inline(always)]
+    pub(crate) fn btree_find_node(&mut self) {
+        let key = self.machine_st.registers[1];
+        let mut tree = self.deref_register(2);
 
-        if lh_offset >= self.machine_st.lifted_heap.cell_len() {
-            let solutions = self.machine_st.registers[2];
-            let diff = self.machine_st.registers[3];
+        let format =
+            match Number::try_from((self.deref_register(3), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => n.get_num() as usize,
+                _ => {
+                    self.machine_st.fail = true;
+                    return;
+                }
+            };
 
-            unify_fn!(self.machine_st, solutions, diff);
-        } else {
-            let h = self.machine_st.heap.cell_len();
-            self.machine_st.copy_lifted_heap_from_offset(h, lh_offset);
+        let key_arg = (format >> 16) & 0xff;
+        let left_arg = (format >> 8) & 0xff;
+        let right_arg = format & 0xff;
 
-            let diff = self.machine_st.registers[3];
-            unify_fn!(
-                self.machine_st,
-                diff,
-                self.machine_st.heap.last_cell().unwrap()
-            );
+        let node_functor = |machine_st: &MachineState, tree: HeapCellValue| {
+            read_heap_cell!(tree,
+                (HeapCellValueTag::Str, s) => {
+                    Some((s, cell_as_atom_cell!(machine_st.heap[s]).get_name_and_arity()))
+                }
+                _ => {
+                    None
+                }
+            )
+        };
 
-            self.machine_st.lifted_heap.truncate(lh_offset);
+        let Some((mut s, functor)) = node_functor(&self.machine_st, tree) else {
+            self.machine_st.fail = true;
+            return;
+        };
 
-            let solutions = self.machine_st.registers[2];
-            unify_fn!(self.machine_st, heap_loc_as_cell!(h), solutions);
+        if [key_arg, left_arg, right_arg]
+            .iter()
+            .any(|&arg| arg == 0 || arg > functor.1)
+        {
+            self.machine_st.fail = true;
+            return;
         }
-    }
 
-    #[inline(always)]
-    pub(crate) fn get_lifted_heap_from_offset(&mut self) {
-        let lh_offset = self.machine_st.registers[1];
-        let lh_offset = unsafe {
-            self.machine_st
-                .store(self.machine_st.deref(lh_offset))
-                .to_fixnum_or_cut_point_unchecked()
-        }
-        .get_num() as usize;
+        let rel = loop {
+            let node_key = self.machine_st.heap[s + key_arg];
 
-        if lh_offset >= self.machine_st.lifted_heap.cell_len() {
-            let solutions = self.machine_st.registers[2];
-            unify_fn!(self.machine_st, solutions, empty_list_as_cell!());
-        } else {
-            let h = self.machine_st.heap.cell_len();
+            let (child_arg, rel) = match compare_term_test!(self.machine_st, key, node_key) {
+                Some(Ordering::Equal) => break atom!("="),
+                Some(Ordering::Less) | None => (left_arg, atom!("<")),
+                Some(Ordering::Greater) => (right_arg, atom!(">")),
+            };
 
-            self.machine_st.copy_lifted_heap_from_offset(h, lh_offset);
-            self.machine_st.lifted_heap.truncate(lh_offset);
+            let child = self.machine_st.deref(self.machine_st.heap[s + child_arg]);
 
-            let solutions = self.machine_st.registers[2];
-            unify_fn!(self.machine_st, heap_loc_as_cell!(h), solutions);
+            match node_functor(&self.machine_st, child) {
+                Some((child_s, child_functor)) if child_functor == functor => {
+                    tree = child;
+                    s = child_s;
+                }
+                _ => break rel,
+            }
+        };
+
+        unify_fn!(self.machine_st, self.machine_st.registers[4], tree);
+
+        if !self.machine_st.fail {
+            self.machine_st
+                .unify_atom(rel, self.machine_st.registers[5]);
         }
     }
 
     #[inline(always)]
-    pub(crate) fn get_double_quotes(&mut self) {
-        let a1 = self.deref_register(1);
+    pub(crate) fn machine_create(&mut self) {
+        let (name, machine) = BridgedMachine::new(&self.machine_st.atom_tbl);
 
-        self.machine_st.unify_atom(
-            match self.machine_st.flags.double_quotes {
-                DoubleQuotes::Chars => atom!("chars"),
-                DoubleQuotes::Atom => atom!("atom"),
-                DoubleQuotes::Codes => atom!("codes"),
-            },
-            a1,
-        );
+        self.bridged_machines.insert(name, machine);
+        self.machine_st
+            .unify_atom(name, self.machine_st.registers[1]);
     }
 
     #[inline(always)]
-    pub(crate) fn get_unknown(&mut self) {
-        let a1 = self.deref_register(1);
-
-        self.machine_st.unify_atom(
-            match self.machine_st.flags.unknown {
-                Unknown::Error => atom!("error"),
-                Unknown::Fail => atom!("fail"),
-                Unknown::Warn => atom!("warning"),
-            },
-            a1,
-        );
+    pub(crate) fn machine_destroy(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        self.machine_st.fail = self.bridged_machines.swap_remove(&name).is_none();
     }
 
     #[inline(always)]
-    pub(crate) fn get_scc_cleaner(&mut self) {
-        let dest = self.machine_st.registers[1];
+    pub(crate) fn machine_status(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
 
-        if let Some((addr, b_cutoff, prev_block)) = self.machine_st.cont_pts.pop() {
-            let b = self
+        match self.bridged_machines.get(&name) {
+            Some(machine) if machine.is_idle() => self
                 .machine_st
-                .stack
-                .index_or_frame(self.machine_st.b)
-                .prelude
-                .b;
-
-            if b <= b_cutoff {
-                self.machine_st.scc_block = prev_block;
-
-                if let Some(r) = dest.as_var() {
-                    self.machine_st.bind(r, addr);
-                    return;
-                }
-            } else {
-                self.machine_st.cont_pts.push((addr, b_cutoff, prev_block));
-            }
+                .unify_atom(atom!("idle"), self.machine_st.registers[2]),
+            Some(_) => self
+                .machine_st
+                .unify_atom(atom!("querying"), self.machine_st.registers[2]),
+            None => self.machine_st.fail = true,
         }
-
-        self.machine_st.fail = true;
     }
 
     #[inline(always)]
-    pub(crate) fn halt(&mut self) -> std::process::ExitCode {
-        let code = self.deref_register(1);
+    pub(crate) fn machine_consult(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        let program = self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[2]);
 
-        let code = match Number::try_from((code, &self.machine_st.arena.f64_tbl)) {
-            Ok(Number::Fixnum(n)) => u8::try_from(n.get_num()).unwrap(),
-            Ok(Number::Integer(n)) => {
-                let n: u8 = (&*n).try_into().unwrap();
-                n
-            }
-            Ok(Number::Rational(r)) => {
-                // n has already been confirmed as an integer, and
-                // internally, Rational is assumed reduced, so its
-                // denominator must be 1.
-                r.numerator().try_into().unwrap()
-            }
-            _ => {
-                unreachable!()
-            }
+        self.machine_st.fail = match (self.bridged_machines.get_mut(&name), program) {
+            (Some(machine), Some(program)) => !machine.consult(program.as_str().to_string()),
+            _ => true,
         };
-
-        std::process::ExitCode::from(code)
     }
 
     #[inline(always)]
-    pub(crate) fn install_scc_cleaner(&mut self) {
-        let addr = self.machine_st.registers[1];
-        let b = self.machine_st.b;
-        let prev_block = self.machine_st.scc_block;
+    pub(crate) fn machine_query(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        let query = self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[2]);
 
-        self.machine_st.run_cleaners_fn = Machine::run_cleaners;
-        self.machine_st.scc_block = b;
-        self.machine_st.cont_pts.push((addr, b, prev_block));
+        let query_id = match (self.bridged_machines.get_mut(&name), query) {
+            (Some(machine), Some(query)) => machine.start_query(query.as_str().to_string() + " ."),
+            _ => None,
+        };
+
+        match query_id {
+            Some(query_id) => {
+                let query_id = Fixnum::build_with_checked(query_id as i64).unwrap();
+                self.machine_st
+                    .unify_fixnum(query_id, self.machine_st.registers[3]);
+            }
+            None => {
+                self.machine_st.fail = true;
+            }
+        }
     }
 
-    #[inline(always)]
-    pub(crate) fn install_inference_counter(&mut self) -> CallResult {
-        // A1 = B, A2 = L
-        let a1 = self.deref_register(1);
-        let a2 = self.deref_register(2);
+    // unifies the third register with answer(Answer) for the next
+    // answer of the query, Answer being the binding of its variable
+    // Answer, with last(Answer) if no more answers can follow, or with
+    // exception(E) if the query threw E. fails once there are no more
+    // answers.
+    pub(crate) fn machine_next(&mut self) -> CallResult {
+        let name = cell_as_atom!(self.deref_register(1));
+        let query_id =
+            match Number::try_from((self.deref_register(2), &self.machine_st.arena.f64_tbl)) {
+                Ok(Number::Fixnum(n)) => n.get_num() as usize,
+                _ => {
+                    self.machine_st.fail = true;
+                    return Ok(());
+                }
+            };
 
-        let n = match Number::try_from((a2, &self.machine_st.arena.f64_tbl)) {
-            Ok(Number::Fixnum(bp)) => Integer::from(bp.get_num() as usize),
-            Ok(Number::Integer(n)) => (*n).clone(),
-            _ => {
-                let stub = functor_stub(atom!("call_with_inference_limit"), 3);
-                let err = self.machine_st.type_error(ValidType::Integer, a2);
-                return Err(self.machine_st.error_form(err, stub));
+        let answer = self
+            .bridged_machines
+            .get_mut(&name)
+            .and_then(|machine| machine.next_answer(query_id));
+
+        let (functor_name, term) = match answer {
+            Some(BridgedAnswer::Answer(term)) => (atom!("answer"), term),
+            Some(BridgedAnswer::Last(term)) => (atom!("last"), term),
+            Some(BridgedAnswer::Exception(term)) => (atom!("exception"), term),
+            None => {
+                self.machine_st.fail = true;
+                return Ok(());
             }
         };
 
-        let bp = unsafe { a1.to_fixnum_or_cut_point_unchecked() }.get_num() as usize;
-        let a3 = self.deref_register(3);
+        let term = resource_error_call_result!(
+            self.machine_st,
+            term.to_heapcell(self, &mut IndexMap::new())
+        );
 
-        let count = self.machine_st.cwil.add_limit(n, bp).clone();
-        self.inference_count(a3, count);
+        let mut writer = Heap::functor_writer(functor!(functor_name, [cell(term)]));
+        let answer =
+            resource_error_call_result!(self.machine_st, writer(&mut self.machine_st.heap));
 
+        unify_fn!(self.machine_st, self.machine_st.registers[3], answer);
         Ok(())
     }
 
+    #[inline(always)]
+    pub(crate) fn machine_close(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+
+        if let Ok(Number::Fixnum(n)) =
+            Number::try_from((self.deref_register(2), &self.machine_st.arena.f64_tbl))
+        {
+            if let Some(machine) = self.bridged_machines.get_mut(&name) {
+                machine.close_query(n.get_num() as usize);
+            }
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn inference_count(&mut self, count_var: HeapCellValue, count: Integer) {
         if let Some(value) = <&Integer as TryInto<i64>>::try_into(&count)
@@ -6267,9 +10593,158 @@ pub(crate) fn set_unknown(&mut self) {
             atom!("warning") => Unknown::Warn,
             _ => {
                 self.machine_st.fail = true;
-                return;
+                return;
+            }
+        };
+    }
+
+    // sets the unknown flag of the module named in register 1 to the
+    // value in register 2. setting that of user sets the flag of the
+    // machine.
+    pub(crate) fn set_module_unknown(&mut self) -> CallResult {
+        let module_name = cell_as_atom!(self.deref_register(1));
+
+        let unknown = match cell_as_atom!(self.deref_register(2)) {
+            atom!("error") => Unknown::Error,
+            atom!("fail") => Unknown::Fail,
+            atom!("warning") => Unknown::Warn,
+            _ => {
+                self.machine_st.fail = true;
+                return Ok(());
+            }
+        };
+
+        if module_name == atom!("user") {
+            self.machine_st.flags.unknown = unknown;
+            return Ok(());
+        }
+
+        match self.indices.modules.get_mut(&module_name) {
+            Some(module) => {
+                module.unknown = Some(unknown);
+                Ok(())
+            }
+            None => Err(self.module_existence_error(module_name, atom!("set_prolog_flag"))),
+        }
+    }
+
+    fn module_existence_error(&mut self, module_name: Atom, flag_predicate: Atom) -> MachineStub {
+        let stub = functor_stub(flag_predicate, 2);
+        let err = self
+            .machine_st
+            .existence_error(ExistenceError::Module(module_name));
+
+        self.machine_st.error_form(err, stub)
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_error_locations(&mut self) {
+        let atom = cell_as_atom!(self.deref_register(1));
+
+        self.machine_st.flags.error_locations = match atom {
+            atom!("true") => true,
+            atom!("false") => false,
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_prefer_rationals(&mut self) {
+        let atom = cell_as_atom!(self.deref_register(1));
+
+        self.machine_st.flags.prefer_rationals = match atom {
+            atom!("true") => true,
+            atom!("false") => false,
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_float_flag(&mut self) {
+        // the values were validated by set_prolog_flag/2, error being
+        // the only value of each flag that raises.
+        let flag = cell_as_atom!(self.deref_register(1));
+        let raises = cell_as_atom!(self.deref_register(2)) == atom!("error");
+        let flags = &mut self.machine_st.flags.float_flags;
+
+        match flag {
+            atom!("float_overflow") => flags.overflow_to_infinity = !raises,
+            atom!("float_zero_div") => flags.zero_div_to_infinity = !raises,
+            atom!("float_undefined") => flags.undefined_to_nan = !raises,
+            _ => unreachable!(),
+        }
+
+        publish_float_flags(*flags);
+    }
+
+    pub(crate) fn set_rational_flag(&mut self) {
+        // the values were validated by set_prolog_flag/2, save for
+        // precisions of more than u32::MAX digits.
+        let flag = cell_as_atom!(self.deref_register(1));
+        let value = self.deref_register(2);
+        let flags = &mut self.machine_st.flags.rational_flags;
+
+        match flag {
+            atom!("rational_precision") => {
+                let precision = match Number::try_from((value, &self.machine_st.arena.f64_tbl)) {
+                    Ok(Number::Fixnum(n)) => u32::try_from(n.get_num()).ok(),
+                    _ => None,
+                };
+
+                match precision {
+                    Some(precision) => flags.precision = precision as usize,
+                    None => self.machine_st.fail = true,
+                }
+            }
+            _ => {
+                flags.rounding = match cell_as_atom!(value) {
+                    atom!("half_even") => RationalRounding::HalfEven,
+                    atom!("half_away_from_zero") => RationalRounding::HalfAwayFromZero,
+                    atom!("toward_zero") => RationalRounding::TowardZero,
+                    atom!("away_from_zero") => RationalRounding::AwayFromZero,
+                    atom!("down") => RationalRounding::Down,
+                    atom!("up") => RationalRounding::Up,
+                    _ => unreachable!(),
+                };
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_integer_rounding_function(&mut self) {
+        let atom = cell_as_atom!(self.deref_register(1));
+
+        self.machine_st.flags.integer_rounding_function = match atom {
+            atom!("toward_zero") => IntegerRoundingFunction::TowardZero,
+            atom!("down") => IntegerRoundingFunction::Down,
+            _ => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_style_check(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        let value = cell_as_atom!(self.deref_register(2));
+
+        match self.machine_st.flags.style_check.check_mut(name) {
+            Some(is_enabled) => match value {
+                atom!("true") => *is_enabled = true,
+                atom!("false") => *is_enabled = false,
+                _ => self.machine_st.fail = true,
+            },
+            None => {
+                self.machine_st.fail = true;
             }
-        };
+        }
     }
 
     #[inline(always)]
@@ -6417,7 +10892,7 @@ pub(crate) fn next_ep(&mut self) {
                 let p_functor_cell =
                     step_or_resource_error!(machine_st, writer(&mut machine_st.heap));
 
-                unify!(machine_st, p_functor_cell, machine_st.registers[3]);
+                unify_fn!(*machine_st, p_functor_cell, machine_st.registers[3]);
             }
         };
 
@@ -6461,7 +10936,7 @@ pub(crate) fn next_ep(&mut self) {
                 self.machine_st.unify_fixnum(e, self.machine_st.registers[2]);
 
                 if !self.machine_st.fail {
-                    unify!(self.machine_st, p_functor_cell, self.machine_st.registers[3]);
+                    unify_fn!(self.machine_st, p_functor_cell, self.machine_st.registers[3]);
                 }
             }
             _ => {
@@ -6535,7 +11010,14 @@ pub(crate) fn read_query_term(&mut self) -> CallResult {
         set_prompt(false);
 
         match result {
-            Ok(()) => Ok(()),
+            Ok(()) => {
+                self.record_crash_query(|machine| {
+                    let query = machine.deref_register(2);
+                    machine.term_to_string(query)
+                });
+
+                Ok(())
+            }
             Err(e) => {
                 self.user_input.reset();
                 Err(e)
@@ -6543,6 +11025,56 @@ pub(crate) fn read_query_term(&mut self) -> CallResult {
         }
     }
 
+    // the list of pairs Query-Names of the queries of the history of
+    // the toplevel, oldest first, with the atoms they are bookmarked
+    // as. the list is empty if user_input isn't read by the line
+    // editor.
+    #[inline(always)]
+    pub(crate) fn history(&mut self) {
+        let history = match self.user_input {
+            Stream::Readline(readline) => readline.history(),
+            _ => vec![],
+        };
+
+        let mut pairs = Vec::with_capacity(history.len());
+
+        for (query, names) in history {
+            let query = step_or_resource_error!(
+                self.machine_st,
+                self.machine_st.heap.allocate_cstr(&query)
+            );
+
+            let names = step_or_resource_error!(
+                self.machine_st,
+                sized_iter_to_heap_list(
+                    &mut self.machine_st.heap,
+                    names.len(),
+                    names.iter().map(|name| {
+                        atom_as_cell!(AtomTable::build_with(&self.machine_st.atom_tbl, name))
+                    })
+                )
+            );
+
+            pairs.push(str_loc_as_cell!(self.machine_st.heap.cell_len()));
+
+            let mut writer =
+                step_or_resource_error!(self.machine_st, self.machine_st.heap.reserve(3));
+
+            writer.write_with(|section| {
+                section.push_cell(atom_as_cell!(atom!("-"), 2));
+                section.push_cell(query);
+                section.push_cell(names);
+            });
+        }
+
+        let list_cell = step_or_resource_error!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, pairs.len(), pairs.into_iter())
+        );
+
+        unify_fn!(self.machine_st, list_cell, self.machine_st.registers[1]);
+    }
+
     #[inline(always)]
     pub(crate) fn read_term(&mut self) -> CallResult {
         set_prompt(false);
@@ -6554,18 +11086,18 @@ pub(crate) fn read_term(&mut self) -> CallResult {
             3,
         )?;
 
+        let module_name = cell_as_atom!(self.deref_register(6));
+        let op_dir = self.indices.module_op_dir(module_name);
+
         if let Stream::Readline(..) = stream {
             self.machine_st.read_term(
                 stream,
-                &mut self.indices,
+                &op_dir,
                 MachineState::read_term_from_user_input_eof_handler,
             )
         } else {
-            self.machine_st.read_term(
-                stream,
-                &mut self.indices,
-                MachineState::read_term_eof_handler,
-            )
+            self.machine_st
+                .read_term(stream, &op_dir, MachineState::read_term_eof_handler)
         }
     }
 
@@ -6573,6 +11105,7 @@ pub(crate) fn read_term(&mut self) -> CallResult {
     fn read_term_and_write_to_heap(
         &mut self,
         atom_or_string: AtomOrString,
+        module_name: Atom,
     ) -> Result<Option<TermWriteResult>, MachineStub> {
         let string = match atom_or_string {
             AtomOrString::Atom(atom!("[]")) => "".to_owned(),
@@ -6581,7 +11114,8 @@ fn read_term_and_write_to_heap(
 
         let chars = CharReader::new(ByteStream::from_string(string));
         let mut parser = Parser::new(chars, &mut self.machine_st);
-        let op_dir = CompositeOpDir::new(&self.indices.op_dir, None);
+        let op_dir = self.indices.module_op_dir(module_name);
+        let op_dir = CompositeOpDir::new(&op_dir, None);
 
         let term_write_result = parser
             .read_term(&op_dir, Tokens::Default)
@@ -6611,7 +11145,9 @@ pub(crate) fn read_from_chars(&mut self) -> CallResult {
             .machine_st
             .value_to_str_like(self.machine_st.registers[1])
         {
-            if let Some(term_write_result) = self.read_term_and_write_to_heap(atom_or_string)? {
+            if let Some(term_write_result) =
+                self.read_term_and_write_to_heap(atom_or_string, atom!("user"))?
+            {
                 let result = heap_loc_as_cell!(term_write_result.heap_loc);
                 let var = self.deref_register(2).as_var().unwrap();
 
@@ -6626,11 +11162,15 @@ pub(crate) fn read_from_chars(&mut self) -> CallResult {
 
     #[inline(always)]
     pub(crate) fn read_term_from_chars(&mut self) -> CallResult {
+        let module_name = cell_as_atom!(self.deref_register(6));
+
         if let Some(atom_or_string) = self
             .machine_st
             .value_to_str_like(self.machine_st.registers[1])
         {
-            if let Some(term_write_result) = self.read_term_and_write_to_heap(atom_or_string)? {
+            if let Some(term_write_result) =
+                self.read_term_and_write_to_heap(atom_or_string, module_name)?
+            {
                 self.machine_st.read_term_body(term_write_result)
             } else {
                 if !self.machine_st.fail {
@@ -6689,6 +11229,10 @@ pub(crate) fn reset_continuation_marker(&mut self) {
 
     #[inline(always)]
     pub(crate) fn set_ball(&mut self) {
+        if self.machine_st.flags.error_locations {
+            self.machine_st.locate_error();
+        }
+
         self.machine_st.set_ball();
     }
 
@@ -6720,6 +11264,65 @@ pub(crate) fn set_seed(&mut self) {
         }
     }
 
+    // Seed is false, to enumerate answers in the order of the builtins,
+    // or a non-negative integer seeding the generator that shuffles
+    // them.
+    pub(crate) fn set_shuffle_seed(&mut self) {
+        let seed = self.deref_register(1);
+
+        if seed == atom_as_cell!(atom!("false")) {
+            self.shuffle_rng = None;
+            return;
+        }
+
+        let seed: Option<u64> = match Number::try_from((seed, &self.machine_st.arena.f64_tbl)) {
+            Ok(Number::Fixnum(n)) => n.get_num().try_into().ok(),
+            Ok(Number::Integer(n)) => (&*n).try_into().ok(),
+            _ => None,
+        };
+
+        match seed {
+            Some(seed) => self.shuffle_rng = Some(StdRng::seed_from_u64(seed)),
+            None => self.machine_st.fail = true,
+        }
+    }
+
+    // List0, List: List is List0, an enumeration of answers whose order
+    // the standard leaves unspecified, shuffled while the shuffle_seed
+    // flag is an integer. the shuffles only depend on the seed and the
+    // lists shuffled since it was set, so a run relying on the order of
+    // an enumeration can be repeated.
+    pub(crate) fn shuffle_unspecified(&mut self) -> CallResult {
+        let Some(shuffle_rng) = &mut self.shuffle_rng else {
+            unify_fn!(
+                self.machine_st,
+                self.machine_st.registers[1],
+                self.machine_st.registers[2]
+            );
+
+            return Ok(());
+        };
+
+        let stub_gen = || functor_stub(atom!("$shuffle_unspecified"), 2);
+        let mut answers = self
+            .machine_st
+            .try_from_list(self.machine_st.registers[1], stub_gen)?;
+
+        answers.shuffle(shuffle_rng);
+
+        let list = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                answers.len(),
+                answers.into_iter()
+            )
+        );
+
+        unify_fn!(self.machine_st, list, self.machine_st.registers[2]);
+        Ok(())
+    }
+
     #[inline(always)]
     pub(crate) fn sleep(&mut self) {
         let time = self.deref_register(1);
@@ -6734,9 +11337,47 @@ pub(crate) fn sleep(&mut self) {
         };
 
         let duration = Duration::new(1, 0);
-        let duration = duration.mul_f64(time);
+        let deadline = Instant::now() + duration.mul_f64(time);
+
+        // wakes early for the goals of signals mapped by on_signal/2,
+        // which run at the next call.
+        while !signals::any_pending() {
+            let now = Instant::now();
+
+            if now >= deadline {
+                break;
+            }
+
+            std::thread::sleep((deadline - now).min(Duration::from_millis(50)));
+        }
+    }
+
+    // sets the action of the signal named in the first register to
+    // the second, failing if the signal isn't supported.
+    #[inline(always)]
+    pub(crate) fn signal_action(&mut self) {
+        let name = cell_as_atom!(self.deref_register(1));
+        let action = SignalAction::from_atom(cell_as_atom!(self.deref_register(2))).unwrap();
+
+        self.machine_st.fail = !signals::set_action(name, action);
+    }
+
+    // unifies the first register with the list of the names of the
+    // pending signals mapped to goals.
+    #[inline(always)]
+    pub(crate) fn pending_signals(&mut self) -> CallResult {
+        let names: Vec<_> = signals::take_pending()
+            .into_iter()
+            .map(|name| atom_as_cell!(AtomTable::build_with(&self.machine_st.atom_tbl, name)))
+            .collect();
+
+        let list = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, names.len(), names.into_iter())
+        );
 
-        std::thread::sleep(duration);
+        unify_fn!(self.machine_st, list, self.machine_st.registers[1]);
+        Ok(())
     }
 
     #[inline(always)]
@@ -6842,8 +11483,9 @@ pub(crate) fn socket_client_open(&mut self) -> CallResult {
         Ok(())
     }
 
-    #[inline(always)]
-    pub(crate) fn socket_server_open(&mut self) -> CallResult {
+    // the address of A1 and A2 of a socket to bind, with the port 0
+    // if A2 is a variable, and whether it is.
+    fn bind_socket_addr(&mut self) -> (String, bool) {
         let addr = self.deref_register(1);
         let socket_atom = cell_as_atom_cell!(addr).get_name();
 
@@ -6875,6 +11517,14 @@ pub(crate) fn socket_server_open(&mut self) -> CallResult {
             format!("{}:{}", socket_atom.as_str(), port)
         };
 
+        (server_addr, had_zero_port)
+    }
+
+    #[inline(always)]
+    pub(crate) fn socket_server_open(&mut self) -> CallResult {
+        let addr = self.deref_register(1);
+        let (server_addr, had_zero_port) = self.bind_socket_addr();
+
         let (tcp_listener, port): (TypedArenaPtr<TcpListener>, _) =
             match TcpListener::bind(server_addr).map_err(|e| e.kind()) {
                 Ok(tcp_listener) => {
@@ -6991,21 +11641,62 @@ pub(crate) fn socket_server_accept(&mut self) -> CallResult {
     #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_client_connect(&mut self) -> CallResult {
+        // A2 = the PEM certificates trusted in addition to those of the
+        // system, A3 and A4 = the PEM certificate and key of the client
+        // or [], A5 = whether the certificate of the server is verified,
+        // all validated by tls_client_context/2.
+        let stub_gen = || functor_stub(atom!("tls_client_negotiate"), 3);
+
         if let Some(hostname) = self
             .machine_st
             .value_to_str_like(self.machine_st.registers[1])
         {
+            let mut builder = TlsConnector::builder();
+
+            for cacert in self
+                .machine_st
+                .try_from_list(self.machine_st.registers[2], stub_gen)?
+            {
+                let pem = self.machine_st.value_to_str_like(cacert).unwrap();
+
+                match Certificate::from_pem(pem.as_str().as_bytes()) {
+                    Ok(cacert) => {
+                        builder.add_root_certificate(cacert);
+                    }
+                    Err(_) => {
+                        return Err(self.machine_st.open_permission_error(
+                            cacert,
+                            atom!("tls_client_negotiate"),
+                            3,
+                        ));
+                    }
+                }
+            }
+
+            if self.deref_register(3) != empty_list_as_cell!() {
+                let identity = self.pem_identity(3, atom!("tls_client_negotiate"))?;
+                builder.identity(identity);
+            }
+
+            if self.deref_register(5) == atom_as_cell!(atom!("false")) {
+                builder
+                    .danger_accept_invalid_certs(true)
+                    .danger_accept_invalid_hostnames(true);
+            }
+
             let stream0 = self.machine_st.get_stream_or_alias(
-                self.machine_st.registers[2],
+                self.machine_st.registers[6],
                 &self.indices,
                 atom!("tls_client_negotiate"),
                 3,
             )?;
 
-            let connector = TlsConnector::new().unwrap();
-            let stream = match connector.connect(&hostname.as_str(), stream0) {
-                Ok(tls_stream) => tls_stream,
-                Err(_) => {
+            let stream = match builder
+                .build()
+                .map(|connector| connector.connect(&hostname.as_str(), stream0))
+            {
+                Ok(Ok(tls_stream)) => tls_stream,
+                _ => {
                     return Err(self.machine_st.open_permission_error(
                         self.machine_st.registers[1],
                         atom!("tls_client_negotiate"),
@@ -7021,7 +11712,7 @@ pub(crate) fn tls_client_connect(&mut self) -> CallResult {
                 .add_stream(stream, atom!("tls_client_negotiate"), 3)
                 .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
 
-            let stream_addr = self.deref_register(3);
+            let stream_addr = self.deref_register(7);
             self.machine_st
                 .bind(stream_addr.as_var().unwrap(), stream.into());
 
@@ -7031,86 +11722,261 @@ pub(crate) fn tls_client_connect(&mut self) -> CallResult {
         }
     }
 
+    // the identity of the PEM certificate chain in register cert and
+    // the PEM PKCS #8 private key in the register after it.
+    #[cfg(feature = "tls")]
+    fn pem_identity(&mut self, cert: usize, caller: Atom) -> Result<Identity, MachineStub> {
+        let pem = self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[cert])
+            .unwrap();
+        let key = self
+            .machine_st
+            .value_to_str_like(self.machine_st.registers[cert + 1])
+            .unwrap();
+
+        Identity::from_pkcs8(pem.as_str().as_bytes(), key.as_str().as_bytes()).map_err(|_| {
+            self.machine_st
+                .open_permission_error(self.machine_st.registers[cert], caller, 3)
+        })
+    }
+
     #[cfg(feature = "tls")]
     #[inline(always)]
     pub(crate) fn tls_accept_client(&mut self) -> CallResult {
-        let pkcs12 = self.string_encoding_bytes(self.machine_st.registers[1], atom!("octet"));
+        // A1 = pkcs12, with A2 = the PKCS #12 archive and A3 = its
+        // password, or pem, with A2 and A3 = the PEM certificate chain
+        // and PKCS #8 private key of the server.
+        let identity = if cell_as_atom!(self.deref_register(1)) == atom!("pkcs12") {
+            let pkcs12 = self.string_encoding_bytes(self.machine_st.registers[2], atom!("octet"));
+            let password = self
+                .machine_st
+                .value_to_str_like(self.machine_st.registers[3])
+                .unwrap();
 
-        if let Some(password) = self
-            .machine_st
-            .value_to_str_like(self.machine_st.registers[2])
-        {
-            let identity = match Identity::from_pkcs12(&pkcs12, &password.as_str()) {
+            match Identity::from_pkcs12(&pkcs12, &password.as_str()) {
                 Ok(identity) => identity,
                 Err(_) => {
                     return Err(self.machine_st.open_permission_error(
-                        self.machine_st.registers[1],
+                        self.machine_st.registers[2],
                         atom!("tls_server_negotiate"),
                         3,
                     ));
                 }
+            }
+        } else {
+            self.pem_identity(2, atom!("tls_server_negotiate"))?
+        };
+
+        let stream0 = self.machine_st.get_stream_or_alias(
+            self.machine_st.registers[4],
+            &self.indices,
+            atom!("tls_server_negotiate"),
+            3,
+        )?;
+
+        let stream = match TlsAcceptor::new(identity).map(|acceptor| acceptor.accept(stream0)) {
+            Ok(Ok(tls_stream)) => tls_stream,
+            _ => {
+                return Err(self.machine_st.open_permission_error(
+                    self.machine_st.registers[4],
+                    atom!("tls_server_negotiate"),
+                    3,
+                ));
+            }
+        };
+
+        let stream = Stream::from_tls_stream(atom!("TLS"), stream, &mut self.machine_st.arena);
+
+        self.indices
+            .add_stream(stream, atom!("tls_server_negotiate"), 3)
+            .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+
+        let stream_addr = self.deref_register(5);
+        self.machine_st
+            .bind(stream_addr.as_var().unwrap(), stream.into());
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(crate) fn socket_server_close(&mut self) -> CallResult {
+        let culprit = self.deref_register(1);
+
+        read_heap_cell!(culprit,
+            (HeapCellValueTag::Cons, cons_ptr) => {
+                match_untyped_arena_ptr!(cons_ptr,
+                    (ArenaHeaderTag::TcpListener, tcp_listener) => {
+                        tcp_listener.drop_payload();
+
+                        return Ok(());
+                    }
+                    _ => {
+                    }
+                );
+            }
+            _ => {
+            }
+        );
+
+        let err = self.machine_st.type_error(ValidType::TcpListener, culprit);
+        let stub = functor_stub(atom!("socket_server_close"), 1);
+
+        Err(self.machine_st.error_form(err, stub))
+    }
+
+    #[inline(always)]
+    pub(crate) fn udp_socket_open(&mut self) -> CallResult {
+        let addr = self.deref_register(1);
+        let (socket_addr, had_zero_port) = self.bind_socket_addr();
+
+        let (udp_socket, port): (TypedArenaPtr<UdpSocket>, _) =
+            match UdpSocket::bind(socket_addr).map_err(|e| e.kind()) {
+                Ok(udp_socket) => {
+                    let port = udp_socket.local_addr().map(|addr| addr.port()).ok();
+
+                    if let Some(port) = port {
+                        (arena_alloc!(udp_socket, &mut self.machine_st.arena), port)
+                    } else {
+                        self.machine_st.fail = true;
+                        return Ok(());
+                    }
+                }
+                Err(ErrorKind::PermissionDenied) => {
+                    return Err(self.machine_st.open_permission_error(
+                        addr,
+                        atom!("udp_socket_open"),
+                        2,
+                    ));
+                }
+                _ => {
+                    self.machine_st.fail = true;
+                    return Ok(());
+                }
             };
 
-            let stream0 = self.machine_st.get_stream_or_alias(
-                self.machine_st.registers[3],
-                &self.indices,
-                atom!("tls_server_negotiate"),
-                3,
-            )?;
+        let addr = self.deref_register(3);
+        self.machine_st
+            .bind(addr.as_var().unwrap(), typed_arena_ptr_as_cell!(udp_socket));
+
+        if had_zero_port {
+            self.machine_st
+                .unify_fixnum(Fixnum::build_with(port), self.deref_register(2));
+        }
+
+        Ok(())
+    }
+
+    fn udp_socket(
+        &mut self,
+        culprit: HeapCellValue,
+        stub_gen: impl Fn() -> MachineStub,
+    ) -> Result<TypedArenaPtr<UdpSocket>, MachineStub> {
+        if let Some(ptr) = culprit.to_untyped_arena_ptr() {
+            match_untyped_arena_ptr!(ptr,
+                (ArenaHeaderTag::UdpSocket, udp_socket) => {
+                    return Ok(udp_socket);
+                }
+                _ => {
+                }
+            );
+        }
+
+        let err = self.machine_st.type_error(ValidType::UdpSocket, culprit);
+        Err(self.machine_st.error_form(err, stub_gen()))
+    }
+
+    #[inline(always)]
+    pub(crate) fn udp_send(&mut self) -> CallResult {
+        // A2 = the datagram as bytes, A3 and A4 = the address and port
+        // it is sent to, validated by udp_send/3.
+        let stub_gen = || functor_stub(atom!("udp_send"), 3);
+        let udp_socket = self.udp_socket(self.deref_register(1), stub_gen)?;
+        let datagram = self
+            .machine_st
+            .integers_to_bytevec(self.machine_st.registers[2], stub_gen);
+
+        let addr = self.deref_register(3);
+        let host = cell_as_atom!(addr);
+        let port = match Number::try_from((self.deref_register(4), &self.machine_st.arena.f64_tbl))
+        {
+            Ok(Number::Fixnum(n)) => n.get_num() as u16,
+            _ => {
+                unreachable!()
+            }
+        };
+
+        match udp_socket
+            .send_to(&datagram, (&*host.as_str(), port))
+            .map_err(|e| e.kind())
+        {
+            Ok(_) => Ok(()),
+            Err(ErrorKind::PermissionDenied) => {
+                Err(self
+                    .machine_st
+                    .open_permission_error(addr, atom!("udp_send"), 3))
+            }
+            Err(_) => {
+                let err = self
+                    .machine_st
+                    .existence_error(ExistenceError::SourceSink(addr));
+                Err(self.machine_st.error_form(err, stub_gen()))
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn udp_receive(&mut self) -> CallResult {
+        // A2 = the bytes of the next datagram, A3 = Address:Port of
+        // its sender.
+        let stub_gen = || functor_stub(atom!("udp_receive"), 3);
+        let udp_socket = self.udp_socket(self.deref_register(1), stub_gen)?;
 
-            let acceptor = TlsAcceptor::new(identity).unwrap();
+        // the largest payload of a UDP datagram.
+        let mut buf = vec![0; 65535];
 
-            let stream = match acceptor.accept(stream0) {
-                Ok(tls_stream) => tls_stream,
-                Err(_) => {
-                    return Err(self.machine_st.open_permission_error(
-                        self.machine_st.registers[3],
-                        atom!("tls_server_negotiate"),
-                        3,
-                    ));
-                }
-            };
+        let (len, sender) = match udp_socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(_) => {
+                self.machine_st.fail = true;
+                return Ok(());
+            }
+        };
 
-            let stream = Stream::from_tls_stream(atom!("TLS"), stream, &mut self.machine_st.arena);
+        let datagram = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                len,
+                buf[..len]
+                    .iter()
+                    .map(|&byte| fixnum_as_cell!(Fixnum::build_with(byte))),
+            )
+        );
 
-            self.indices
-                .add_stream(stream, atom!("tls_server_negotiate"), 3)
-                .map_err(|stub_gen| stub_gen(&mut self.machine_st))?;
+        let host = AtomTable::build_with(&self.machine_st.atom_tbl, &sender.ip().to_string());
+        let port = sender.port();
+        let mut writer =
+            Heap::functor_writer(functor!(atom!(":"), [atom_as_cell(host), fixnum(port)]));
+        let sender =
+            resource_error_call_result!(self.machine_st, writer(&mut self.machine_st.heap));
 
-            let stream_addr = self.deref_register(4);
-            self.machine_st
-                .bind(stream_addr.as_var().unwrap(), stream.into());
-        } else {
-            unreachable!();
+        unify_fn!(self.machine_st, self.machine_st.registers[2], datagram);
+
+        if !self.machine_st.fail {
+            unify_fn!(self.machine_st, self.machine_st.registers[3], sender);
         }
 
         Ok(())
     }
 
     #[inline(always)]
-    pub(crate) fn socket_server_close(&mut self) -> CallResult {
-        let culprit = self.deref_register(1);
-
-        read_heap_cell!(culprit,
-            (HeapCellValueTag::Cons, cons_ptr) => {
-                match_untyped_arena_ptr!(cons_ptr,
-                    (ArenaHeaderTag::TcpListener, tcp_listener) => {
-                        tcp_listener.drop_payload();
-
-                        return Ok(());
-                    }
-                    _ => {
-                    }
-                );
-            }
-            _ => {
-            }
-        );
-
-        let err = self.machine_st.type_error(ValidType::TcpListener, culprit);
-        let stub = functor_stub(atom!("socket_server_close"), 1);
+    pub(crate) fn udp_socket_close(&mut self) -> CallResult {
+        let stub_gen = || functor_stub(atom!("udp_socket_close"), 1);
+        let mut udp_socket = self.udp_socket(self.deref_register(1), stub_gen)?;
 
-        Err(self.machine_st.error_form(err, stub))
+        udp_socket.drop_payload();
+        Ok(())
     }
 
     #[inline(always)]
@@ -7235,7 +12101,7 @@ pub(crate) fn stream_property(&mut self) -> CallResult {
             }
         };
 
-        unify!(self.machine_st, property, self.machine_st.registers[3]);
+        unify_fn!(self.machine_st, property, self.machine_st.registers[3]);
         Ok(())
     }
 
@@ -7262,6 +12128,12 @@ pub(crate) fn store_global_var(&mut self) {
         );
 
         self.indices.global_variables.insert(key, (ball, None));
+
+        // the new value replaces those of the other computations, as
+        // suspended by engines.
+        for engine in self.engines.values_mut() {
+            engine.forget_global_var(key);
+        }
     }
 
     #[inline(always)]
@@ -7290,6 +12162,251 @@ pub(crate) fn store_backtrackable_global_var(&mut self) {
         }
     }
 
+    // Term, Depth, Range, Algorithm, Hash: Hash is the hash of Term
+    // computed by Algorithm, inspected to Depth with Term itself at
+    // depth 1, modulo Range. Hash is left unbound if a variable occurs
+    // within Depth. a Depth of -1 inspects the whole term, and fails
+    // if it is cyclic.
+    pub(crate) fn term_hash(&mut self) {
+        let max_depth =
+            unsafe { self.deref_register(2).to_fixnum_or_cut_point_unchecked() }.get_num();
+
+        let range = match Number::try_from((self.deref_register(3), &self.machine_st.arena.f64_tbl))
+        {
+            Ok(Number::Fixnum(n)) => Some(n.get_num() as u64),
+            Ok(Number::Integer(n)) => u64::try_from(&*n).ok(),
+            _ => unreachable!(),
+        };
+
+        let term = self.machine_st.registers[1];
+
+        let hash = match cell_as_atom!(self.deref_register(4)) {
+            atom!("sha3_256") => self.hash_term(term, max_depth, DigestHasher(Sha3_256::new())),
+            atom!("blake2b512") => self.hash_term(term, max_depth, DigestHasher(Blake2b512::new())),
+            _ => self.hash_term(term, max_depth, FxHasher64::default()),
+        };
+
+        let hash = match hash {
+            Ok(Some(hash)) => hash,
+            Ok(None) => return,
+            Err(()) => {
+                self.machine_st.fail = true;
+                return;
+            }
+        };
+
+        let hash = match range {
+            Some(range) => hash % range,
+            None => hash,
+        };
+
+        let a5 = self.deref_register(5);
+
+        match Number::arena_from(hash, &mut self.machine_st.arena) {
+            Number::Fixnum(n) => self.machine_st.unify_fixnum(n, a5),
+            Number::Integer(n) => self.machine_st.unify_big_int(n, a5),
+            _ => unreachable!(),
+        }
+    }
+
+    // the hash of term inspected to max_depth, as of term_hash/4: None
+    // if a variable occurs within max_depth, and Err(()) if max_depth
+    // is -1 and term is cyclic.
+    //
+    // terms equal in the standard order hash alike, whatever their
+    // representation: partial strings hash as lists of characters.
+    // the hash doesn't depend on the platform or session.
+    fn hash_term(
+        &mut self,
+        term: HeapCellValue,
+        max_depth: i64,
+        mut hasher: impl Hasher,
+    ) -> Result<Option<u64>, ()> {
+        const FLOAT: u8 = 1;
+        const NUMBER: u8 = 2;
+        const ATOM: u8 = 3;
+        const COMPOUND: u8 = 4;
+        const OTHER: u8 = 5;
+
+        enum HashStep {
+            Enter(HeapCellValue, i64),
+            // leaves the compound term, in checking for cycles.
+            Leave(HeapCellValue),
+        }
+
+        let mut steps = vec![HashStep::Enter(term, 1)];
+
+        // the compound terms containing the current one, which only
+        // an unbounded depth can revisit.
+        let mut path = IndexSet::with_hasher(FxBuildHasher::default());
+
+        while let Some(step) = steps.pop() {
+            let (value, depth) = match step {
+                HashStep::Enter(value, depth) => (value, depth),
+                HashStep::Leave(value) => {
+                    path.swap_remove(&value);
+                    continue;
+                }
+            };
+
+            if max_depth != -1 && depth > max_depth {
+                continue;
+            }
+
+            let value = self.machine_st.store(self.machine_st.deref(value));
+
+            if max_depth == -1 && value.is_compound(&self.machine_st.heap) {
+                if !path.insert(value) {
+                    return Err(());
+                }
+
+                steps.push(HashStep::Leave(value));
+            }
+
+            read_heap_cell!(value,
+                (HeapCellValueTag::Var | HeapCellValueTag::StackVar | HeapCellValueTag::AttrVar) => {
+                    return Ok(None);
+                }
+                (HeapCellValueTag::Atom, (name, _arity)) => {
+                    ATOM.hash(&mut hasher);
+                    name.as_str().hash(&mut hasher);
+                }
+                (HeapCellValueTag::Str, s) => {
+                    let (name, arity) = cell_as_atom_cell!(self.machine_st.heap[s])
+                        .get_name_and_arity();
+
+                    if arity == 0 {
+                        ATOM.hash(&mut hasher);
+                        name.as_str().hash(&mut hasher);
+                    } else {
+                        COMPOUND.hash(&mut hasher);
+                        arity.hash(&mut hasher);
+                        name.as_str().hash(&mut hasher);
+
+                        for idx in (1..=arity).rev() {
+                            steps.push(HashStep::Enter(self.machine_st.heap[s + idx], depth + 1));
+                        }
+                    }
+                }
+                (HeapCellValueTag::Lis, l) => {
+                    COMPOUND.hash(&mut hasher);
+                    2usize.hash(&mut hasher);
+                    ".".hash(&mut hasher);
+
+                    steps.push(HashStep::Enter(self.machine_st.heap[l + 1], depth + 1));
+                    steps.push(HashStep::Enter(self.machine_st.heap[l], depth + 1));
+                }
+                (HeapCellValueTag::PStrLoc, l) => {
+                    COMPOUND.hash(&mut hasher);
+                    2usize.hash(&mut hasher);
+                    ".".hash(&mut hasher);
+
+                    let (c, tail) = self.machine_st.heap.last_str_char_and_tail(l);
+
+                    steps.push(HashStep::Enter(tail, depth + 1));
+                    steps.push(HashStep::Enter(char_as_cell!(c), depth + 1));
+                }
+                _ => {
+                    match Number::try_from((value, &self.machine_st.arena.f64_tbl)) {
+                        Ok(Number::Float(f)) => {
+                            FLOAT.hash(&mut hasher);
+                            f.hash(&mut hasher);
+                        }
+                        Ok(Number::Fixnum(n)) => {
+                            NUMBER.hash(&mut hasher);
+                            n.get_num().hash(&mut hasher);
+                        }
+                        Ok(Number::Integer(n)) => {
+                            NUMBER.hash(&mut hasher);
+
+                            match i64::try_from(&*n) {
+                                Ok(n) => n.hash(&mut hasher),
+                                Err(_) => n.to_string().hash(&mut hasher),
+                            }
+                        }
+                        Ok(Number::Rational(r)) => {
+                            NUMBER.hash(&mut hasher);
+                            r.to_string().hash(&mut hasher);
+                        }
+                        Err(_) => {
+                            OTHER.hash(&mut hasher);
+                            value.into_bytes().hash(&mut hasher);
+                        }
+                    }
+                }
+            );
+        }
+
+        Ok(Some(hasher.finish()))
+    }
+
+    // '$group_pairs'(+Pairs, -Groups): Groups are the pairs Key-Values
+    // of the keys of the list Pairs of pairs Key-Value, in the order of
+    // their first occurrence, with Values the values of Key in Pairs.
+    // Keys equal in the standard order are the same key, which a hash
+    // table finds without sorting Pairs. Fails if a key isn't ground.
+    pub(crate) fn group_pairs(&mut self) -> CallResult {
+        let stub_gen = || functor_stub(atom!("$group_pairs"), 2);
+        let pairs = self
+            .machine_st
+            .try_from_list(self.machine_st.registers[1], stub_gen)?;
+
+        let mut buckets: IndexMap<u64, Vec<usize>, FxBuildHasher> =
+            IndexMap::with_hasher(FxBuildHasher::default());
+        let mut groups: Vec<(HeapCellValue, Vec<HeapCellValue>)> = vec![];
+
+        for pair in pairs {
+            let key = self.machine_st.project_onto_key(pair)?;
+            let value = heap_loc_as_cell!(key.get_value() as usize + 1);
+
+            let Ok(Some(hash)) = self.hash_term(key, -1, FxHasher64::default()) else {
+                self.machine_st.fail = true;
+                return Ok(());
+            };
+
+            let bucket = buckets.entry(hash).or_default();
+
+            let group = bucket.iter().copied().find(|&idx| {
+                compare_term_test!(self.machine_st, key, groups[idx].0) == Some(Ordering::Equal)
+            });
+
+            match group {
+                Some(idx) => groups[idx].1.push(value),
+                None => {
+                    bucket.push(groups.len());
+                    groups.push((key, vec![value]));
+                }
+            }
+        }
+
+        let mut cells = Vec::with_capacity(groups.len());
+
+        for (key, values) in groups {
+            let values = resource_error_call_result!(
+                self.machine_st,
+                sized_iter_to_heap_list(
+                    &mut self.machine_st.heap,
+                    values.len(),
+                    values.into_iter()
+                )
+            );
+
+            let mut writer = Heap::functor_writer(functor!(atom!("-"), [cell(key), cell(values)]));
+            cells.push(resource_error_call_result!(
+                self.machine_st,
+                writer(&mut self.machine_st.heap)
+            ));
+        }
+
+        let list_cell = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(&mut self.machine_st.heap, cells.len(), cells.into_iter())
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[2], list_cell);
+        Ok(())
+    }
+
     #[inline(always)]
     pub(crate) fn term_attributed_variables(&mut self) {
         if self.machine_st.registers[1].is_constant() {
@@ -7367,6 +12484,18 @@ pub(crate) fn term_variables_under_max_depth(&mut self) {
         );
     }
 
+    #[inline(always)]
+    pub(crate) fn term_variant(&mut self) {
+        let a1 = self.machine_st.registers[1];
+        let a2 = self.machine_st.registers[2];
+
+        if compare_term_test!(self.machine_st, a1, a2, VarComparison::Variant)
+            != Some(Ordering::Equal)
+        {
+            self.machine_st.fail = true;
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn truncate_lifted_heap_to(&mut self) {
         let a1 = self.deref_register(1);
@@ -7507,7 +12636,7 @@ pub(crate) fn wam_instructions(&mut self) -> CallResult {
 
         let listing_var = self.machine_st.registers[4];
 
-        unify!(self.machine_st, listing, listing_var);
+        unify_fn!(self.machine_st, listing, listing_var);
         Ok(())
     }
 
@@ -7529,7 +12658,7 @@ pub(crate) fn inlined_instructions(&mut self) {
 
         let listing_var = self.machine_st.registers[2];
 
-        unify!(self.machine_st, listing, listing_var);
+        unify_fn!(self.machine_st, listing, listing_var);
     }
 
     #[inline(always)]
@@ -7567,7 +12696,10 @@ pub(crate) fn write_term(&mut self) -> CallResult {
             ));
         }
 
-        let printer = match self.machine_st.write_term(&self.indices.op_dir)? {
+        let module_name = cell_as_atom!(self.deref_register(9));
+        let op_dir = self.indices.module_op_dir(module_name);
+
+        let printer = match self.machine_st.write_term(&op_dir)? {
             Some(printer) => printer,
             None => {
                 // this next line is executed by
@@ -7600,7 +12732,10 @@ pub(crate) fn write_term(&mut self) -> CallResult {
 
     #[inline(always)]
     pub(crate) fn write_term_to_chars(&mut self) -> CallResult {
-        let printer = match self.machine_st.write_term(&self.indices.op_dir)? {
+        let module_name = cell_as_atom!(self.deref_register(9));
+        let op_dir = self.indices.module_op_dir(module_name);
+
+        let printer = match self.machine_st.write_term(&op_dir)? {
             None => {
                 // this next line is executed by
                 // MachineState::write_term in this case. it's
@@ -7635,7 +12770,7 @@ pub(crate) fn scryer_prolog_version(&mut self) {
         let cstr_cell =
             step_or_resource_error!(self.machine_st, self.machine_st.heap.allocate_cstr(buffer));
 
-        unify!(self.machine_st, cstr_cell, self.machine_st.registers[1]);
+        unify_fn!(self.machine_st, cstr_cell, self.machine_st.registers[1]);
     }
 
     #[inline(always)]
@@ -7813,7 +12948,7 @@ pub(crate) fn crypto_data_hash(&mut self) {
             }
         };
 
-        unify!(self.machine_st, self.machine_st.registers[3], ints_list);
+        unify_fn!(self.machine_st, self.machine_st.registers[3], ints_list);
     }
 
     #[inline(always)]
@@ -7851,7 +12986,7 @@ pub(crate) fn crypto_hmac(&mut self) {
             )
         );
 
-        unify!(self.machine_st, self.machine_st.registers[4], ints_list);
+        unify_fn!(self.machine_st, self.machine_st.registers[4], ints_list);
     }
 
     #[inline(always)]
@@ -7923,7 +13058,7 @@ pub(crate) fn crypto_data_hkdf(&mut self) {
             )
         };
 
-        unify!(self.machine_st, self.machine_st.registers[7], ints_list);
+        unify_fn!(self.machine_st, self.machine_st.registers[7], ints_list);
     }
 
     #[inline(always)]
@@ -7979,7 +13114,7 @@ pub(crate) fn crypto_password_hash(&mut self) {
             )
         };
 
-        unify!(self.machine_st, self.machine_st.registers[4], ints_list);
+        unify_fn!(self.machine_st, self.machine_st.registers[4], ints_list);
     }
 
     #[cfg(feature = "crypto-full")]
@@ -8027,8 +13162,8 @@ pub(crate) fn crypto_data_encrypt(&mut self) {
 
         let complete_string = step_or_resource_error!(self.machine_st, self.u8s_to_string(&in_out));
 
-        unify!(self.machine_st, self.machine_st.registers[6], tag_list);
-        unify!(
+        unify_fn!(self.machine_st, self.machine_st.registers[6], tag_list);
+        unify_fn!(
             self.machine_st,
             self.machine_st.registers[7],
             complete_string
@@ -8091,7 +13226,7 @@ pub(crate) fn crypto_data_decrypt(&mut self) {
             }
         };
 
-        unify!(
+        unify_fn!(
             self.machine_st,
             self.machine_st.registers[6],
             complete_string
@@ -8117,7 +13252,7 @@ pub(crate) fn crypto_curve_scalar_mult(&mut self) {
             self.u8s_to_string(&point.encode_uncompressed())
         );
 
-        unify!(self.machine_st, self.machine_st.registers[4], uncompressed);
+        unify_fn!(self.machine_st, self.machine_st.registers[4], uncompressed);
     }
 
     #[inline(always)]
@@ -8134,7 +13269,7 @@ pub(crate) fn ed25519_seed_to_public_key(&mut self) {
             self.u8s_to_string(skey.public_key.encoded.as_ref())
         );
 
-        unify!(
+        unify_fn!(
             self.machine_st,
             self.machine_st.registers[2],
             complete_string
@@ -8165,7 +13300,7 @@ pub(crate) fn ed25519_sign_raw(&mut self) {
             )
         );
 
-        unify!(self.machine_st, self.machine_st.registers[4], sig_list);
+        unify_fn!(self.machine_st, self.machine_st.registers[4], sig_list);
     }
 
     #[inline(always)]
@@ -8203,7 +13338,7 @@ pub(crate) fn curve25519_scalar_mult(&mut self) {
 
         let string = step_or_resource_error!(self.machine_st, self.u8s_to_string(&result[..]));
 
-        unify!(self.machine_st, self.machine_st.registers[3], string);
+        unify_fn!(self.machine_st, self.machine_st.registers[3], string);
     }
 
     #[inline(always)]
@@ -8225,20 +13360,36 @@ pub(crate) fn first_non_octet(&mut self) {
         self.machine_st.fail = true;
     }
 
+    // parses the HTML of register 1 as a document, or as the content of
+    // a body element if register 3 is true.
     #[inline(always)]
     pub(crate) fn load_html(&mut self) -> Result<(), usize> {
         if let Some(string) = self
             .machine_st
             .value_to_str_like(self.machine_st.registers[1])
         {
-            let document = scraper::Html::parse_document(&string.as_str());
+            let fragment = cell_as_atom!(self.deref_register(3)) == atom!("true");
+
+            let root_nodes = if fragment {
+                let document = scraper::Html::parse_fragment(&string.as_str());
 
-            let root_nodes = document
-                .tree
-                .root()
-                .children()
-                .map(|child| self.html_node_to_term(child))
-                .collect::<Result<Vec<_>, _>>()?;
+                // the nodes of a fragment are parsed into an html
+                // element of their own.
+                document
+                    .root_element()
+                    .children()
+                    .map(|child| self.html_node_to_term(child))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                let document = scraper::Html::parse_document(&string.as_str());
+
+                document
+                    .tree
+                    .root()
+                    .children()
+                    .map(|child| self.html_node_to_term(child))
+                    .collect::<Result<Vec<_>, _>>()?
+            };
 
             let nodes = sized_iter_to_heap_list(
                 &mut self.machine_st.heap,
@@ -8246,7 +13397,7 @@ pub(crate) fn load_html(&mut self) -> Result<(), usize> {
                 root_nodes.into_iter(),
             )?;
 
-            unify!(self.machine_st, self.machine_st.registers[2], nodes);
+            unify_fn!(self.machine_st, self.machine_st.registers[2], nodes);
         } else {
             self.machine_st.fail = true;
         }
@@ -8263,7 +13414,7 @@ pub(crate) fn load_xml(&mut self) -> Result<(), usize> {
             match roxmltree::Document::parse(&string.as_str()) {
                 Ok(doc) => {
                     let result = self.xml_node_to_term(doc.root_element())?;
-                    unify!(self.machine_st, self.machine_st.registers[2], result);
+                    unify_fn!(self.machine_st, self.machine_st.registers[2], result);
                 }
                 _ => {
                     self.machine_st.fail = true;
@@ -8289,7 +13440,7 @@ pub(crate) fn get_env(&mut self) {
                         self.machine_st.heap.allocate_cstr(&value)
                     );
 
-                    unify!(self.machine_st, self.machine_st.registers[2], cstr);
+                    unify_fn!(self.machine_st, self.machine_st.registers[2], cstr);
                 }
                 _ => {
                     self.machine_st.fail = true;
@@ -8357,7 +13508,7 @@ fn command_result(
                 Ok(status) => match status.code() {
                     Some(code) => {
                         let code = integer_as_cell!(Number::arena_from(code, &mut machine.arena));
-                        unify!(machine, code, machine.registers[2]);
+                        unify_fn!(*machine, code, machine.registers[2]);
                     }
                     _ => {
                         machine.fail = true;
@@ -8500,7 +13651,7 @@ fn stub_gen() -> Vec<FunctorElement> {
                 let child_process_alloc: TypedArenaPtr<Child> =
                     arena_alloc!(child, &mut self.machine_st.arena);
 
-                unify!(
+                unify_fn!(
                     self.machine_st,
                     pid_r,
                     typed_arena_ptr_as_cell!(child_process_alloc)
@@ -8549,7 +13700,7 @@ fn handle_output_stream(&mut self, args: Vec<HeapCellValue>) -> Result<Stdio, Ma
             Some(atom!("file")) => {
                 let path = self.machine_st.value_to_str_like(args[1]).unwrap();
 
-                let file = match std::fs::File::open(&*path.as_str()) {
+                let file = match std::fs::File::create(&*path.as_str()) {
                     Ok(file) => file,
                     Err(_) => {
                         return Err(self.machine_st.open_permission_error(
@@ -8561,6 +13712,40 @@ fn handle_output_stream(&mut self, args: Vec<HeapCellValue>) -> Result<Stdio, Ma
                 };
                 Stdio::from(file)
             }
+            Some(atom!("stream")) => {
+                let stream = self.machine_st.get_stream_or_alias(
+                    args[1],
+                    &self.indices,
+                    atom!("process_create"),
+                    3,
+                )?;
+
+                // the writing end of a pipe, which stays open in this
+                // process until it's closed.
+                let writer = match stream {
+                    Stream::PipeWriter(writer) => writer.get_ref().try_clone(),
+                    _ => {
+                        return Err(self.machine_st.stream_permission_error(
+                            Permission::OutputStream,
+                            atom!("stream"),
+                            stream,
+                            atom!("process_create"),
+                            3,
+                        ));
+                    }
+                };
+
+                match writer {
+                    Ok(writer) => Stdio::from(writer),
+                    Err(_) => {
+                        return Err(self.machine_st.open_permission_error(
+                            args[1],
+                            atom!("process_create"),
+                            3,
+                        ));
+                    }
+                }
+            }
             _ => {
                 panic!("Invalid stdout tag")
             }
@@ -8609,6 +13794,41 @@ fn handle_input_stream(&mut self, args: Vec<HeapCellValue>) -> Result<Stdio, Mac
                 };
                 Stdio::from(file)
             }
+            Some(atom!("stream")) => {
+                let stream = self.machine_st.get_stream_or_alias(
+                    args[1],
+                    &self.indices,
+                    atom!("process_create"),
+                    3,
+                )?;
+
+                // the reading end of a pipe, typically the stdout of
+                // another process. Characters already buffered by reads
+                // in this process are not passed on.
+                let reader = match stream {
+                    Stream::PipeReader(reader) => reader.get_ref().try_clone(),
+                    _ => {
+                        return Err(self.machine_st.stream_permission_error(
+                            Permission::InputStream,
+                            atom!("stream"),
+                            stream,
+                            atom!("process_create"),
+                            3,
+                        ));
+                    }
+                };
+
+                match reader {
+                    Ok(reader) => Stdio::from(reader),
+                    Err(_) => {
+                        return Err(self.machine_st.open_permission_error(
+                            args[1],
+                            atom!("process_create"),
+                            3,
+                        ));
+                    }
+                }
+            }
             _ => {
                 panic!("Invalid stdin tag")
             }
@@ -8702,7 +13922,7 @@ fn stub_gen() -> Vec<FunctorElement> {
 
         match status {
             Ok(None) => {
-                unify!(self.machine_st, status_r, atom_as_cell!(atom!("timeout")));
+                unify_fn!(self.machine_st, status_r, atom_as_cell!(atom!("timeout")));
                 Ok(())
             }
             Ok(Some(exit_status)) => {
@@ -8712,7 +13932,7 @@ fn stub_gen() -> Vec<FunctorElement> {
 
                     match writer(&mut self.machine_st.heap) {
                         Ok(loc) => {
-                            unify!(self.machine_st, status_r, loc);
+                            unify_fn!(self.machine_st, status_r, loc);
                         }
                         Err(resource_err_loc) => {
                             self.machine_st.throw_resource_error(resource_err_loc);
@@ -8730,7 +13950,7 @@ fn stub_gen() -> Vec<FunctorElement> {
 
                             match writer(&mut self.machine_st.heap) {
                                 Ok(loc) => {
-                                    unify!(self.machine_st, status_r, loc);
+                                    unify_fn!(self.machine_st, status_r, loc);
                                 }
                                 Err(resource_err_loc) => {
                                     self.machine_st.throw_resource_error(resource_err_loc);
@@ -8845,7 +14065,7 @@ pub(crate) fn chars_base64(&mut self) -> CallResult {
                     let string =
                         resource_error_call_result!(self.machine_st, self.u8s_to_string(&bs));
 
-                    unify!(self.machine_st, self.machine_st.registers[1], string);
+                    unify_fn!(self.machine_st, self.machine_st.registers[1], string);
                 }
                 _ => {
                     self.machine_st.fail = true;
@@ -8868,7 +14088,7 @@ pub(crate) fn chars_base64(&mut self) -> CallResult {
             let string =
                 resource_error_call_result!(self.machine_st, self.u8s_to_string(b64.as_bytes()));
 
-            unify!(self.machine_st, self.machine_st.registers[2], string);
+            unify_fn!(self.machine_st, self.machine_st.registers[2], string);
         }
 
         Ok(())
@@ -8878,11 +14098,23 @@ pub(crate) fn chars_base64(&mut self) -> CallResult {
     pub(crate) fn load_library_as_stream(&mut self) -> CallResult {
         let library_name = cell_as_atom!(self.deref_register(1));
 
-        let lib = libraries::get(&library_name.as_str());
-        match lib {
-            Some(library) => {
-                let lib_stream = Stream::from_static_string(library, &mut self.machine_st.arena);
-                unify!(
+        let library_name_str = library_name.as_str();
+        let lib_stream = match libraries::get(&library_name_str) {
+            Some(library) => Some(Stream::from_static_string(
+                library,
+                &mut self.machine_st.arena,
+            )),
+            None => self
+                .machine_st
+                .registered_libraries
+                .get(&*library_name_str)
+                .cloned()
+                .map(|library| Stream::from_owned_string(library, &mut self.machine_st.arena)),
+        };
+
+        match lib_stream {
+            Some(lib_stream) => {
+                unify_fn!(
                     self.machine_st,
                     HeapCellValue::from(lib_stream),
                     self.machine_st.registers[2]
@@ -8915,6 +14147,48 @@ pub(crate) fn load_library_as_stream(&mut self) -> CallResult {
         Ok(())
     }
 
+    // succeeds if the library named in register 1 is one of the
+    // libraries of the system or those registered by the embedder.
+    pub(crate) fn embedded_library(&mut self) {
+        let library_name = cell_as_atom!(self.deref_register(1));
+        let library_name = library_name.as_str();
+
+        self.machine_st.fail = !libraries::contains(&library_name)
+            && !self
+                .machine_st
+                .registered_libraries
+                .contains_key(&*library_name);
+    }
+
+    // unifies register 1 with the list of the library paths of the
+    // machine.
+    pub(crate) fn library_paths(&mut self) -> CallResult {
+        let library_paths: Vec<_> = self
+            .machine_st
+            .library_paths
+            .iter()
+            .filter_map(|library_path| library_path.to_str())
+            .map(|library_path| {
+                atom_as_cell!(AtomTable::build_with(
+                    &self.machine_st.atom_tbl,
+                    library_path
+                ))
+            })
+            .collect();
+
+        let library_paths = resource_error_call_result!(
+            self.machine_st,
+            sized_iter_to_heap_list(
+                &mut self.machine_st.heap,
+                library_paths.len(),
+                library_paths.into_iter()
+            )
+        );
+
+        unify_fn!(self.machine_st, self.machine_st.registers[1], library_paths);
+        Ok(())
+    }
+
     #[inline(always)]
     pub(crate) fn devour_whitespace(&mut self) -> CallResult {
         let mut stream = self.machine_st.get_stream_or_alias(
@@ -8997,7 +14271,7 @@ pub(crate) fn home_directory(&mut self) {
                     self.machine_st.heap.allocate_cstr(path)
                 );
 
-                unify!(self.machine_st, self.machine_st.registers[1], path_string);
+                unify_fn!(self.machine_st, self.machine_st.registers[1], path_string);
                 return;
             }
         }
@@ -9030,16 +14304,16 @@ pub(crate) fn pop_count(&mut self) {
             }
         });
 
-        unify!(self.machine_st, self.machine_st.registers[2], pop_count);
+        unify_fn!(self.machine_st, self.machine_st.registers[2], pop_count);
     }
 
     pub(super) fn systemtime_to_timestamp(&mut self, system_time: SystemTime) -> String {
         let datetime: DateTime<Local> = system_time.into();
 
         let mut fstr = "[".to_string();
-        const SPECIFIERS: [char; 19] = [
+        const SPECIFIERS: [char; 20] = [
             'Y', 'm', 'd', 'H', 'M', 'S', 'y', 'b', 'B', 'a', 'A', 'w', 'u', 'U', 'W', 'j', 'D',
-            'x', 'v',
+            'x', 'v', 's',
         ];
 
         for spec in SPECIFIERS {
@@ -9072,6 +14346,18 @@ pub(super) fn xml_node_to_term(
     ) -> Result<HeapCellValue, usize> {
         if node.is_text() {
             self.machine_st.heap.allocate_cstr(node.text().unwrap())
+        } else if node.is_comment() {
+            let comment = self.machine_st.heap.allocate_cstr(node.text().unwrap())?;
+
+            let result = str_loc_as_cell!(self.machine_st.heap.cell_len());
+            let mut writer = self.machine_st.heap.reserve(2)?;
+
+            writer.write_with(|section| {
+                section.push_cell(atom_as_cell!(atom!("comment"), 1));
+                section.push_cell(comment);
+            });
+
+            Ok(result)
         } else {
             let mut avec = Vec::new();
 
@@ -9095,7 +14381,8 @@ pub(super) fn xml_node_to_term(
 
             let mut cvec = Vec::new();
 
-            for child in node.children() {
+            // processing instructions are skipped.
+            for child in node.children().filter(|child| !child.is_pi()) {
                 cvec.push(self.xml_node_to_term(child)?);
             }
 
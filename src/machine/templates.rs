@@ -0,0 +1,272 @@
+// the parser of the mustache-like templates rendered by
+// library(templates).
+
+use crate::atom_table::*;
+use crate::functor_macro::*;
+use crate::machine::heap::*;
+use crate::types::*;
+
+// a tag or a run of text of a template. names are given as the paths
+// of dotted names, e.g. `a.b` is ["a", "b"] and `.` is [].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum TemplateToken {
+    Text(String),
+    // a variable and whether its value is escaped.
+    Var(Vec<String>, bool),
+    Section(Vec<String>),
+    Inverted(Vec<String>),
+    // closes the innermost section or inverted section.
+    End,
+    Partial(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum TemplateError {
+    UnclosedTag,
+    EmptyTag,
+    UnclosedSection(String),
+    UnexpectedEnd(String),
+}
+
+fn name_path(name: &str) -> Vec<String> {
+    if name == "." {
+        vec![]
+    } else {
+        name.split('.')
+            .map(|part| part.trim().to_string())
+            .collect()
+    }
+}
+
+fn is_blank(s: &str) -> bool {
+    s.chars().all(|c| c == ' ' || c == '\t' || c == '\r')
+}
+
+// parses src to its tokens, checking that sections are balanced.
+// sections, comments and partials alone on a line take the line with
+// them, as in mustache.
+pub(crate) fn parse_template(src: &str) -> Result<Vec<TemplateToken>, TemplateError> {
+    let mut tokens = vec![];
+    let mut sections: Vec<&str> = vec![];
+    let mut text = String::new();
+    let mut pos = 0;
+    let mut last_tag_end = 0;
+
+    while let Some(offset) = src[pos..].find("{{") {
+        let tag_start = pos + offset;
+        text.push_str(&src[pos..tag_start]);
+
+        let (sigil, name, tag_end) = if src[tag_start..].starts_with("{{{") {
+            let close = src[tag_start + 3..]
+                .find("}}}")
+                .ok_or(TemplateError::UnclosedTag)?;
+            let body = &src[tag_start + 3..tag_start + 3 + close];
+
+            (Some('&'), body.trim(), tag_start + 3 + close + 3)
+        } else {
+            let close = src[tag_start + 2..]
+                .find("}}")
+                .ok_or(TemplateError::UnclosedTag)?;
+            let body = &src[tag_start + 2..tag_start + 2 + close];
+
+            match body.chars().next() {
+                Some(c @ ('#' | '^' | '/' | '!' | '>' | '&')) => {
+                    (Some(c), body[1..].trim(), tag_start + 2 + close + 2)
+                }
+                _ => (None, body.trim(), tag_start + 2 + close + 2),
+            }
+        };
+
+        if name.is_empty() && sigil != Some('!') {
+            return Err(TemplateError::EmptyTag);
+        }
+
+        pos = tag_end;
+
+        if matches!(sigil, Some('#' | '^' | '/' | '!' | '>')) {
+            let line_start = src[..tag_start].rfind('\n').map_or(0, |i| i + 1);
+
+            let (line_rest, next_line) = match src[tag_end..].find('\n') {
+                Some(i) => (&src[tag_end..tag_end + i], tag_end + i + 1),
+                None => (&src[tag_end..], src.len()),
+            };
+
+            if last_tag_end <= line_start
+                && is_blank(&src[line_start..tag_start])
+                && is_blank(line_rest)
+            {
+                text.truncate(text.len() - (tag_start - line_start));
+                pos = next_line;
+            }
+        }
+
+        last_tag_end = pos;
+
+        if !text.is_empty() {
+            tokens.push(TemplateToken::Text(std::mem::take(&mut text)));
+        }
+
+        match sigil {
+            Some('#') => {
+                sections.push(name);
+                tokens.push(TemplateToken::Section(name_path(name)));
+            }
+            Some('^') => {
+                sections.push(name);
+                tokens.push(TemplateToken::Inverted(name_path(name)));
+            }
+            Some('/') => match sections.pop() {
+                Some(open) if open == name => tokens.push(TemplateToken::End),
+                _ => return Err(TemplateError::UnexpectedEnd(name.to_string())),
+            },
+            Some('!') => {}
+            Some('>') => tokens.push(TemplateToken::Partial(name.to_string())),
+            Some(_) => tokens.push(TemplateToken::Var(name_path(name), false)),
+            None => tokens.push(TemplateToken::Var(name_path(name), true)),
+        }
+    }
+
+    text.push_str(&src[pos..]);
+
+    if !text.is_empty() {
+        tokens.push(TemplateToken::Text(text));
+    }
+
+    match sections.pop() {
+        Some(open) => Err(TemplateError::UnclosedSection(open.to_string())),
+        None => Ok(tokens),
+    }
+}
+
+fn path_cell(
+    heap: &mut Heap,
+    atom_tbl: &AtomTable,
+    path: &[String],
+) -> Result<HeapCellValue, usize> {
+    let parts: Vec<_> = path
+        .iter()
+        .map(|part| atom_as_cell!(AtomTable::build_with(atom_tbl, part)))
+        .collect();
+
+    sized_iter_to_heap_list(heap, parts.len(), parts.into_iter())
+}
+
+// writes token to the heap as the term library(templates) reads it:
+// text(String), var(Path, html | none), section(Path), inverted(Path),
+// end or partial(Name).
+pub(crate) fn token_cell(
+    heap: &mut Heap,
+    atom_tbl: &AtomTable,
+    token: &TemplateToken,
+) -> Result<HeapCellValue, usize> {
+    let functor = match token {
+        TemplateToken::Text(text) => {
+            let text = heap.allocate_cstr(text)?;
+            functor!(atom!("text"), [cell(text)])
+        }
+        TemplateToken::Var(path, escaped) => {
+            let path = path_cell(heap, atom_tbl, path)?;
+            let mode = if *escaped {
+                atom!("html")
+            } else {
+                atom!("none")
+            };
+
+            functor!(atom!("var"), [cell(path), atom_as_cell(mode)])
+        }
+        TemplateToken::Section(path) => {
+            let path = path_cell(heap, atom_tbl, path)?;
+            functor!(atom!("section"), [cell(path)])
+        }
+        TemplateToken::Inverted(path) => {
+            let path = path_cell(heap, atom_tbl, path)?;
+            functor!(atom!("inverted"), [cell(path)])
+        }
+        TemplateToken::End => return Ok(atom_as_cell!(atom!("end"))),
+        TemplateToken::Partial(name) => {
+            let name = AtomTable::build_with(atom_tbl, name);
+            functor!(atom!("partial"), [atom_as_cell(name)])
+        }
+    };
+
+    Heap::functor_writer(functor)(heap)
+}
+
+// writes err to the heap as error(Reason), Reason being the syntax
+// error library(templates) throws.
+pub(crate) fn error_cell(
+    heap: &mut Heap,
+    atom_tbl: &AtomTable,
+    err: &TemplateError,
+) -> Result<HeapCellValue, usize> {
+    let reason = match err {
+        TemplateError::UnclosedTag => atom_as_cell!(atom!("unclosed_tag")),
+        TemplateError::EmptyTag => atom_as_cell!(atom!("empty_tag")),
+        TemplateError::UnclosedSection(name) => {
+            let name = AtomTable::build_with(atom_tbl, name);
+            Heap::functor_writer(functor!(atom!("unclosed_section"), [atom_as_cell(name)]))(heap)?
+        }
+        TemplateError::UnexpectedEnd(name) => {
+            let name = AtomTable::build_with(atom_tbl, name);
+            Heap::functor_writer(functor!(atom!("unexpected_end"), [atom_as_cell(name)]))(heap)?
+        }
+    };
+
+    Heap::functor_writer(functor!(atom!("error"), [cell(reason)]))(heap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tags() {
+        let tokens =
+            parse_template("Hi {{name}}, {{{raw}}}{{& a.b}}{{! note }}{{> footer}}").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                TemplateToken::Text("Hi ".to_string()),
+                TemplateToken::Var(vec!["name".to_string()], true),
+                TemplateToken::Text(", ".to_string()),
+                TemplateToken::Var(vec!["raw".to_string()], false),
+                TemplateToken::Var(vec!["a".to_string(), "b".to_string()], false),
+                TemplateToken::Partial("footer".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn standalone_sections_take_their_lines() {
+        let tokens =
+            parse_template("<ul>\n  {{#items}}\n  <li>{{.}}</li>\n  {{/items}}\n</ul>\n").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                TemplateToken::Text("<ul>\n".to_string()),
+                TemplateToken::Section(vec!["items".to_string()]),
+                TemplateToken::Text("  <li>".to_string()),
+                TemplateToken::Var(vec![], true),
+                TemplateToken::Text("</li>\n".to_string()),
+                TemplateToken::End,
+                TemplateToken::Text("</ul>\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unbalanced_sections() {
+        assert_eq!(
+            parse_template("{{#a}}{{/b}}"),
+            Err(TemplateError::UnexpectedEnd("b".to_string()))
+        );
+        assert_eq!(
+            parse_template("{{^a}}"),
+            Err(TemplateError::UnclosedSection("a".to_string()))
+        );
+        assert_eq!(parse_template("{{a"), Err(TemplateError::UnclosedTag));
+        assert_eq!(parse_template("{{ }}"), Err(TemplateError::EmptyTag));
+    }
+}
@@ -22,6 +22,15 @@ pub struct LoadStatePayload<TS> {
     pub(super) non_counted_bt_preds: IndexSet<PredicateKey, FxBuildHasher>,
     pub(super) predicates: PredicateQueue,
     pub(super) clause_clauses: Vec<(Term, Term)>,
+    // the predicates compiled by the load, to tell discontiguous
+    // clauses from redefinitions.
+    pub(super) compiled_predicates: IndexSet<(CompilationTarget, PredicateKey), FxBuildHasher>,
+    // the predicates declared dynamic by the load.
+    pub(super) dynamic_predicates: IndexSet<(CompilationTarget, PredicateKey), FxBuildHasher>,
+    pub(super) style_warnings: Vec<(StyleWarning, Option<usize>)>,
+    // the static predicates compiling on the compile workers, in the
+    // order they were submitted.
+    pub(super) pending_compilations: Vec<PendingCompilation>,
 }
 
 pub trait TermStream: Sized {
@@ -103,6 +112,10 @@ pub(super) fn new(code_repo_len: usize, term_stream: TS) -> Self {
             non_counted_bt_preds: IndexSet::with_hasher(FxBuildHasher::default()),
             predicates: predicate_queue![],
             clause_clauses: vec![],
+            compiled_predicates: IndexSet::with_hasher(FxBuildHasher::default()),
+            dynamic_predicates: IndexSet::with_hasher(FxBuildHasher::default()),
+            style_warnings: vec![],
+            pending_compilations: vec![],
         }
     }
 }
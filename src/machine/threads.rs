@@ -0,0 +1,233 @@
+use crate::atom_table::*;
+use crate::machine::config::*;
+use crate::machine::message_queues::*;
+
+use indexmap::IndexMap;
+
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+// each Prolog thread runs its goal in a machine of its own. The goal
+// and the exit status cross between machines in their written form.
+static THREADS: LazyLock<Mutex<IndexMap<Atom, PrologThread>>> =
+    LazyLock::new(|| Mutex::new(IndexMap::new()));
+
+struct PrologThread {
+    handle: ThreadHandle,
+    cancelled: Arc<AtomicBool>,
+    background: bool,
+}
+
+// background threads run on the blocking pool of the tokio runtime
+// when there is one, the others on OS threads of their own.
+enum ThreadHandle {
+    Os(std::thread::JoinHandle<String>),
+    Task(tokio::task::JoinHandle<String>),
+}
+
+impl ThreadHandle {
+    fn is_finished(&self) -> bool {
+        match self {
+            ThreadHandle::Os(handle) => handle.is_finished(),
+            ThreadHandle::Task(handle) => handle.is_finished(),
+        }
+    }
+
+    fn join(self) -> String {
+        let status = match self {
+            ThreadHandle::Os(handle) => handle.join().ok(),
+            ThreadHandle::Task(handle) => futures::executor::block_on(handle).ok(),
+        };
+
+        status.unwrap_or_else(|| ABNORMAL_EXIT_STATUS.to_string())
+    }
+}
+
+static ANONYMOUS_THREAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_SELF: Cell<Option<Atom>> = const { Cell::new(None) };
+    static THREAD_GOAL: RefCell<String> = const { RefCell::new(String::new()) };
+    static THREAD_STATUS: RefCell<Option<String>> = const { RefCell::new(None) };
+    static THREAD_CANCELLED: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+    static THREAD_CANCELLABLE: Cell<bool> = const { Cell::new(false) };
+}
+
+// the status reported for threads that didn't set one, e.g. because
+// their machine panicked.
+const ABNORMAL_EXIT_STATUS: &str = "exception(thread_died) .";
+
+pub(crate) fn thread_self() -> Atom {
+    THREAD_SELF.with(|id| id.get()).unwrap_or(atom!("main"))
+}
+
+// a cancellation is answered by throwing only once the goal is
+// fetched, i.e. inside the catch/3 of '$thread_main'.
+pub(crate) fn thread_goal() -> String {
+    THREAD_CANCELLABLE.with(|cancellable| cancellable.set(true));
+    THREAD_GOAL.with(|goal| goal.borrow().clone())
+}
+
+pub(crate) fn set_thread_status(status: String) {
+    THREAD_STATUS.with(|slot| *slot.borrow_mut() = Some(status));
+}
+
+// whether the calling thread was asked to stop by thread_cancel/1.
+pub(crate) fn cancel_pending() -> bool {
+    THREAD_CANCELLABLE.get()
+        && THREAD_CANCELLED.with(|slot| {
+            slot.borrow()
+                .as_ref()
+                .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+        })
+}
+
+// like cancel_pending, but withdraws the request, which is then
+// answered by throwing '$thread_cancelled'.
+pub(crate) fn take_cancellation() -> bool {
+    THREAD_CANCELLABLE.get()
+        && THREAD_CANCELLED.with(|slot| {
+            slot.borrow()
+                .as_ref()
+                .is_some_and(|cancelled| cancelled.swap(false, Ordering::Relaxed))
+        })
+}
+
+// spawns a thread named alias (or an anonymous name if alias is None)
+// running goal, together with its message queue. Returns None if the
+// name is already taken by a thread or a message queue.
+pub(crate) fn spawn_thread(
+    atom_tbl: &AtomTable,
+    alias: Option<Atom>,
+    goal: String,
+    background: bool,
+) -> Option<Atom> {
+    let mut threads = THREADS.lock().unwrap();
+
+    let id = match alias {
+        Some(alias) => {
+            if threads.contains_key(&alias) || !MessageQueue::create(alias) {
+                return None;
+            }
+
+            alias
+        }
+        None => loop {
+            let count = ANONYMOUS_THREAD_COUNT.fetch_add(1, Ordering::Relaxed);
+            let id = AtomTable::build_with(atom_tbl, &format!("$thread_{count}"));
+
+            if !threads.contains_key(&id) && MessageQueue::create(id) {
+                break id;
+            }
+        },
+    };
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let run = {
+        let cancelled = cancelled.clone();
+        move || run_thread(id, goal, cancelled)
+    };
+
+    let handle = match tokio::runtime::Handle::try_current() {
+        Ok(runtime) if background => Ok(ThreadHandle::Task(runtime.spawn_blocking(run))),
+        runtime => std::thread::Builder::new()
+            .name(id.as_str().to_string())
+            .spawn(move || {
+                // the OS thread enters the runtime, if any, for
+                // http_open/3 and other predicates that need it.
+                let _guard = runtime.as_ref().ok().map(|runtime| runtime.enter());
+                run()
+            })
+            .map(ThreadHandle::Os),
+    };
+
+    match handle {
+        Ok(handle) => {
+            threads.insert(
+                id,
+                PrologThread {
+                    handle,
+                    cancelled,
+                    background,
+                },
+            );
+            Some(id)
+        }
+        Err(_) => {
+            MessageQueue::destroy(id);
+            None
+        }
+    }
+}
+
+fn run_thread(id: Atom, goal: String, cancelled: Arc<AtomicBool>) -> String {
+    THREAD_SELF.with(|slot| slot.set(Some(id)));
+    THREAD_GOAL.with(|slot| *slot.borrow_mut() = goal);
+    THREAD_CANCELLED.with(|slot| *slot.borrow_mut() = Some(cancelled));
+
+    let streams = StreamConfig::in_memory()
+        .with_user_output(OutputStreamConfig::stdout())
+        .with_user_error(OutputStreamConfig::stderr());
+
+    let mut machine = MachineBuilder::new().with_streams(streams).build();
+
+    machine
+        .run_query("use_module(library(threads)).")
+        .for_each(drop);
+    machine.run_query("threads:'$thread_main'.").for_each(drop);
+
+    // the threads of the tokio blocking pool are reused for other
+    // tasks.
+    THREAD_SELF.with(|slot| slot.set(None));
+    THREAD_CANCELLED.with(|slot| slot.borrow_mut().take());
+    THREAD_CANCELLABLE.with(|cancellable| cancellable.set(false));
+
+    THREAD_STATUS
+        .with(|slot| slot.borrow_mut().take())
+        .unwrap_or_else(|| ABNORMAL_EXIT_STATUS.to_string())
+}
+
+// waits for thread id to finish, returning its exit status. Returns
+// None if there is no such thread (or it was already joined).
+pub(crate) fn join_thread(id: Atom) -> Option<String> {
+    let thread = THREADS.lock().unwrap().shift_remove(&id)?;
+    let status = thread.handle.join();
+
+    MessageQueue::destroy(id);
+    Some(status)
+}
+
+// None if there is no such thread, otherwise whether it is still
+// running.
+pub(crate) fn thread_is_running(id: Atom) -> Option<bool> {
+    THREADS
+        .lock()
+        .unwrap()
+        .get(&id)
+        .map(|thread| !thread.handle.is_finished())
+}
+
+// asks thread id to stop, which it does by throwing
+// '$thread_cancelled' from its next inference. Returns false if there
+// is no such thread.
+pub(crate) fn cancel_thread(id: Atom) -> bool {
+    THREADS
+        .lock()
+        .unwrap()
+        .get(&id)
+        .map(|thread| thread.cancelled.store(true, Ordering::Relaxed))
+        .is_some()
+}
+
+// the threads started by spawn_initialization/1 that weren't joined
+// yet.
+pub(crate) fn background_threads() -> Vec<Atom> {
+    THREADS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, thread)| thread.background)
+        .map(|(id, _)| *id)
+        .collect()
+}
@@ -0,0 +1,109 @@
+use crate::atom_table::*;
+use crate::forms::PredicateKey;
+
+use indexmap::IndexSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TraceMode {
+    Off,
+    // every call into or out of user code shows its ports.
+    Creep,
+    // only the calls of spy points show their ports.
+    Leap,
+    // no call shows its ports, until the skipped goal is left.
+    Skip,
+    // the calls are recorded in the proof tree of prove/2.
+    Prove,
+}
+
+impl TraceMode {
+    pub(crate) fn from_atom(atom: Atom) -> Option<Self> {
+        match atom {
+            atom!("off") => Some(TraceMode::Off),
+            atom!("creep") => Some(TraceMode::Creep),
+            atom!("leap") => Some(TraceMode::Leap),
+            atom!("skip") => Some(TraceMode::Skip),
+            atom!("prove") => Some(TraceMode::Prove),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_atom(self) -> Atom {
+        match self {
+            TraceMode::Off => atom!("off"),
+            TraceMode::Creep => atom!("creep"),
+            TraceMode::Leap => atom!("leap"),
+            TraceMode::Skip => atom!("skip"),
+            TraceMode::Prove => atom!("prove"),
+        }
+    }
+}
+
+// the state of the port tracer of library(trace) and of prove/2 of
+// library(proof_trees). try_call and try_execute call the wrapper of
+// the mode in place of the goals it traces, which runs them with
+// '$trace_call_at'/3.
+#[derive(Debug)]
+pub(crate) struct Tracer {
+    pub(crate) mode: TraceMode,
+    pub(crate) spy_points: IndexSet<PredicateKey>,
+    // the code offsets at which predicates and their clauses start,
+    // sorted, with whether they belong to a library. code before the
+    // first start belongs to the system.
+    starts: Vec<(usize, bool)>,
+    // the length of the code when the starts were gathered. they are
+    // gathered again when code compiled since is called.
+    code_end: usize,
+}
+
+impl Tracer {
+    pub(crate) fn new() -> Self {
+        Tracer {
+            mode: TraceMode::Off,
+            spy_points: IndexSet::new(),
+            starts: vec![],
+            code_end: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_stale(&self, p: usize) -> bool {
+        p >= self.code_end
+    }
+
+    pub(crate) fn set_starts(&mut self, mut starts: Vec<(usize, bool)>, code_end: usize) {
+        // library code is shared by the modules importing it, whose
+        // claims on it are dropped.
+        starts.sort_unstable_by_key(|&(start, is_library)| (start, !is_library));
+        starts.dedup_by_key(|(start, _)| *start);
+
+        self.starts = starts;
+        self.code_end = code_end;
+    }
+
+    fn is_library_code(&self, p: usize) -> bool {
+        let idx = self.starts.partition_point(|&(start, _)| start <= p);
+        idx.checked_sub(1).is_none_or(|idx| self.starts[idx].1)
+    }
+
+    // the module and name of the wrapper called in place of the
+    // traced goals, of arity 3.
+    pub(crate) fn wrapper(&self) -> (Atom, Atom) {
+        match self.mode {
+            TraceMode::Prove => (atom!("proof_trees"), atom!("$prove_goal")),
+            _ => (atom!("trace"), atom!("$trace_goal")),
+        }
+    }
+
+    // whether the call of key at callee_p from the code at caller_p
+    // is traced. calls within libraries never are.
+    pub(crate) fn traces(&self, key: PredicateKey, caller_p: usize, callee_p: usize) -> bool {
+        let is_user_call = || !self.is_library_code(caller_p) || !self.is_library_code(callee_p);
+
+        match self.mode {
+            TraceMode::Off | TraceMode::Skip => false,
+            TraceMode::Creep | TraceMode::Prove => is_user_call(),
+            TraceMode::Leap => self.spy_points.contains(&key) && is_user_call(),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::arena::*;
+use crate::arithmetic::float_cmp;
 use crate::forms::*;
 use crate::heap_iter::{stackful_preorder_iter, NonListElider};
 use crate::machine::machine_state::*;
@@ -6,6 +7,7 @@
 use crate::offset_table::*;
 use crate::types::*;
 
+use std::cmp::Ordering;
 use std::ops::{Deref, DerefMut};
 
 use derive_more::*;
@@ -292,7 +294,7 @@ fn unify_f64(&mut self, f1: F64Offset, value: HeapCellValue) {
                 let f1 = machine_st.arena.f64_tbl.get_entry(f1);
                 let f2 = machine_st.arena.f64_tbl.get_entry(f2);
 
-                self.fail = f1 != f2;
+                self.fail = float_cmp(*f1, *f2) != Ordering::Equal;
             }
             _ => {
                 self.fail = true;
@@ -0,0 +1,195 @@
+use crate::atom_table::*;
+use crate::forms::*;
+use crate::instructions::*;
+use crate::machine::code_walker::*;
+use crate::machine::loader::CompilationTarget;
+use crate::machine::Machine;
+use crate::offset_table::*;
+use crate::types::*;
+
+use fxhash::FxBuildHasher;
+use indexmap::{IndexMap, IndexSet};
+
+// a predicate of a module.
+pub(crate) type XrefPredicate = (Atom, PredicateKey);
+
+// the cross-reference of the predicates loaded in a machine, read from
+// their code.
+#[derive(Debug, Default)]
+pub(crate) struct CrossReference {
+    // each predicate with the predicates it calls by name or passes as
+    // goals to meta-predicates, and whether they're defined.
+    pub(crate) calls: Vec<(XrefPredicate, XrefPredicate, bool)>,
+    // the exported predicates called by no predicate of another
+    // module.
+    pub(crate) unused_exports: Vec<XrefPredicate>,
+}
+
+// keeps the predicate of the lowest rank as the owner of key.
+fn insert_owner(
+    owners: &mut IndexMap<usize, (u8, XrefPredicate), FxBuildHasher>,
+    key: usize,
+    rank: u8,
+    predicate: XrefPredicate,
+) {
+    owners
+        .entry(key)
+        .and_modify(|owner| {
+            if rank < owner.0 {
+                *owner = (rank, predicate);
+            }
+        })
+        .or_insert((rank, predicate));
+}
+
+impl Machine {
+    pub(crate) fn cross_reference(&self) -> CrossReference {
+        let code_index_tbl = &self.machine_st.arena.code_index_tbl;
+
+        // as by the profiler, predicates are attributed to the module
+        // exporting them, then to user or the module defining them,
+        // and only then to the modules generated for files without a
+        // module declaration.
+        let user = (atom!("user"), 1, &self.indices.code_dir, &[][..], None);
+        let modules = self.indices.modules.iter().map(|(&module_name, module)| {
+            let rank = match module.listing_src {
+                ListingSource::DynamicallyGenerated => 2,
+                _ => 1,
+            };
+
+            (
+                module_name,
+                rank,
+                &module.code_dir,
+                &module.module_decl.exports[..],
+                Some(&module.reexports),
+            )
+        });
+
+        // the predicates owning the code at each offset, and each code
+        // index, which names the predicate even if it's undefined.
+        let mut code_owners = IndexMap::with_hasher(FxBuildHasher::default());
+        let mut index_owners = IndexMap::with_hasher(FxBuildHasher::default());
+        let mut exports = vec![];
+
+        for (module_name, rank, code_dir, module_exports, reexports) in
+            std::iter::once(user).chain(modules)
+        {
+            for (&key, idx) in code_dir.iter() {
+                // re-exported predicates belong to the modules they're
+                // imported from.
+                let exported = module_exports
+                    .iter()
+                    .any(|export| matches!(export, ModuleExport::PredicateKey(k) if *k == key))
+                    && !reexports.is_some_and(|reexports: &IndexSet<ModuleExport>| {
+                        reexports.contains(&ModuleExport::PredicateKey(key))
+                    });
+
+                let rank = if exported { 0 } else { rank };
+                let predicate = (module_name, key);
+
+                if exported {
+                    exports.push(predicate);
+                }
+
+                insert_owner(
+                    &mut index_owners,
+                    usize::from(CodeIndexOffset::from(idx)),
+                    rank,
+                    predicate,
+                );
+
+                if let Some(p) = code_index_tbl.get_entry(idx.into()).local() {
+                    insert_owner(&mut code_owners, p, rank, predicate);
+                }
+            }
+        }
+
+        let mut xref = CrossReference::default();
+        let mut called_from_outside = IndexSet::with_hasher(FxBuildHasher::default());
+
+        for (&p, &(_, caller)) in code_owners.iter() {
+            let mut callees = IndexSet::with_hasher(FxBuildHasher::default());
+
+            walk_code(&self.code, p, |instr| {
+                let idx = match instr {
+                    &Instruction::CallNamed(_, _, idx)
+                    | &Instruction::ExecuteNamed(_, _, idx)
+                    | &Instruction::DefaultCallNamed(_, _, idx)
+                    | &Instruction::DefaultExecuteNamed(_, _, idx) => CodeIndexOffset::from(idx),
+                    // the code index of a goal passed to a
+                    // meta-predicate precedes its structure.
+                    &Instruction::PutConstant(_, cell, _) | &Instruction::SetConstant(cell) => {
+                        read_heap_cell!(cell,
+                            (HeapCellValueTag::CodeIndexOffset, idx) => {
+                                idx
+                            }
+                            _ => {
+                                return;
+                            }
+                        )
+                    }
+                    _ => return,
+                };
+
+                let entry = code_index_tbl.get_entry(idx);
+
+                let callee = entry
+                    .local()
+                    .and_then(|p| code_owners.get(&p))
+                    .or_else(|| index_owners.get(&usize::from(idx)));
+
+                if let Some(&(_, callee)) = callee {
+                    callees.insert((callee, !entry.is_undefined()));
+                }
+            });
+
+            for (callee, is_defined) in callees {
+                if callee.0 != caller.0 {
+                    called_from_outside.insert(callee);
+                }
+
+                xref.calls.push((caller, callee, is_defined));
+            }
+        }
+
+        xref.unused_exports = exports
+            .into_iter()
+            .filter(|predicate| !called_from_outside.contains(predicate))
+            .collect();
+
+        xref
+    }
+
+    // the predicates callable without qualification from module_name,
+    // or None if there's no such module: those it defines, imports or
+    // declares dynamic, and the built-ins. internal predicates, whose
+    // names start with $, are left out.
+    pub(crate) fn visible_predicate_keys(&self, module_name: Atom) -> Option<Vec<PredicateKey>> {
+        let (code_dir, compilation_target) = match module_name {
+            atom!("user") => (&self.indices.code_dir, CompilationTarget::User),
+            _ => (
+                &self.indices.modules.get(&module_name)?.code_dir,
+                CompilationTarget::Module(module_name),
+            ),
+        };
+
+        let code_index_tbl = &self.machine_st.arena.code_index_tbl;
+
+        let keys = code_dir
+            .iter()
+            .filter(|(&key, &idx)| {
+                let is_dynamic = self
+                    .indices
+                    .get_predicate_skeleton(&compilation_target, &key)
+                    .is_some_and(|skeleton| skeleton.core.is_dynamic);
+
+                (is_dynamic || !code_index_tbl.get_entry(idx.into()).is_undefined())
+                    && !key.0.as_str().starts_with('$')
+            })
+            .map(|(&key, _)| key)
+            .collect();
+
+        Some(keys)
+    }
+}
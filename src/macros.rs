@@ -206,6 +206,12 @@ macro_rules! match_untyped_arena_ptr_pat_body {
         #[allow(unused_braces)]
         $code
     }};
+    ($ptr:ident, UdpSocket, $socket:ident, $code:expr) => {{
+        #[allow(unused_mut)]
+        let mut $socket = unsafe { $ptr.as_typed_ptr::<std::net::UdpSocket>() };
+        #[allow(unused_braces)]
+        $code
+    }};
     ($ptr:ident, HttpListener, $listener:ident, $code:expr) => {{
         #[allow(unused_mut)]
         let mut $listener = unsafe { $ptr.as_typed_ptr::<HttpListener>() };
@@ -236,6 +242,12 @@ macro_rules! match_untyped_arena_ptr_pat_body {
         #[allow(unused_braces)]
         $code
     }};
+    ($ptr:ident, WebSocket, $listener:ident, $code:expr) => {{
+        #[allow(unused_mut)]
+        let mut $listener = unsafe { $ptr.as_typed_ptr::<crate::http::WebSocket>() };
+        #[allow(unused_braces)]
+        $code
+    }};
     ($ptr:ident, $($tags:tt)|+, $s:ident, $code:expr) => {{
         let $s = Stream::from_tag($ptr.get_tag(), $ptr);
         #[allow(unused_braces)]
@@ -256,6 +268,7 @@ macro_rules! match_untyped_arena_ptr_pat {
             | ArenaHeaderTag::ByteStream
             | ArenaHeaderTag::CallbackStream
             | ArenaHeaderTag::InputChannelStream
+            | ArenaHeaderTag::MessageQueueStream
             | ArenaHeaderTag::StandardOutputStream
             | ArenaHeaderTag::StandardErrorStream
             | ArenaHeaderTag::PipeReader
@@ -404,6 +417,8 @@ macro_rules! index_store {
             goal_expansion_indices: GoalExpansionIndices::with_hasher(FxBuildHasher::default()),
             meta_predicates: MetaPredicateDir::with_hasher(FxBuildHasher::default()),
             modules: $modules,
+            loaded_files: LoadedFiles::with_hasher(FxBuildHasher::default()),
+            module_imports: ModuleImports::with_hasher(FxBuildHasher::default()),
             op_dir: $op_dir,
             streams: StreamDir::new(),
             stream_aliases: StreamAliasDir::with_hasher(FxBuildHasher::default()),
@@ -362,6 +362,20 @@ fn with_entry_mut<R, F: FnOnce(&mut T) -> R>(&self, offset: usize, f: F) -> R {
 pub type F64Table = OffsetTableImpl<OrderedFloat<f64>>;
 pub type CodeIndexTable = OffsetTableImpl<IndexPtr>;
 
+// the floats of the literals of clauses, as read by the code
+// generator. the compile workers read them from copies owned by their
+// jobs.
+pub(crate) trait F64Entries: fmt::Debug {
+    fn f64_entry(&self, offset: F64Offset) -> OrderedFloat<f64>;
+}
+
+impl F64Entries for F64Table {
+    #[inline]
+    fn f64_entry(&self, offset: F64Offset) -> OrderedFloat<f64> {
+        self.get_entry(offset)
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct F64Offset(usize);
 
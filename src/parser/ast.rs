@@ -6,15 +6,14 @@
 use crate::parser::char_reader::*;
 use crate::types::HeapCellValueTag;
 
-use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::cell::Cell;
 use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::io::{Error as IOError, ErrorKind};
+use std::ops::Neg;
 use std::ops::Not;
 use std::ops::RangeInclusive;
-use std::ops::{Deref, Neg};
-use std::rc::Rc;
 use std::sync::Arc;
 use std::vec::Vec;
 
@@ -22,6 +21,7 @@
 use dashu::Rational;
 use fxhash::FxBuildHasher;
 use indexmap::IndexMap;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use scryer_modular_bitfield::error::OutOfBounds;
 use scryer_modular_bitfield::prelude::*;
 
@@ -362,6 +362,91 @@ pub fn arity(self) -> usize {
 pub struct MachineFlags {
     pub double_quotes: DoubleQuotes,
     pub unknown: Unknown,
+    pub error_locations: bool,
+    // if set, `/` and `**` compute exact results for integers and rationals.
+    pub prefer_rationals: bool,
+    pub rational_flags: RationalFlags,
+    pub integer_rounding_function: IntegerRoundingFunction,
+    pub style_check: StyleCheck,
+    pub float_flags: FloatFlags,
+}
+
+// whether float arithmetic gives infinities and NaN where it would
+// otherwise raise evaluation errors, set by the float_overflow,
+// float_zero_div and float_undefined flags. none do by default, as
+// ISO requires.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FloatFlags {
+    pub overflow_to_infinity: bool,
+    pub zero_div_to_infinity: bool,
+    pub undefined_to_nan: bool,
+}
+
+// how `//` rounds the quotient of integers, and so `rem` its remainder,
+// set by the integer_rounding_function flag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntegerRoundingFunction {
+    #[default]
+    TowardZero,
+    Down,
+}
+
+// the significant digits of the rational results of elementary functions
+// such as sqrt and exp under the prefer_rationals flag, and how they're
+// rounded, set by the rational_precision and rational_rounding flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RationalFlags {
+    pub precision: usize,
+    pub rounding: RationalRounding,
+}
+
+impl Default for RationalFlags {
+    fn default() -> Self {
+        RationalFlags {
+            precision: 34,
+            rounding: RationalRounding::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RationalRounding {
+    #[default]
+    HalfEven,
+    HalfAwayFromZero,
+    TowardZero,
+    AwayFromZero,
+    Down,
+    Up,
+}
+
+// the warnings reported while loading, set by style_check/1.
+#[derive(Debug, Clone, Copy)]
+pub struct StyleCheck {
+    pub singleton: bool,
+    pub discontiguous: bool,
+    pub redefined: bool,
+}
+
+impl Default for StyleCheck {
+    fn default() -> Self {
+        StyleCheck {
+            singleton: true,
+            discontiguous: true,
+            redefined: true,
+        }
+    }
+}
+
+impl StyleCheck {
+    pub fn check_mut(&mut self, name: Atom) -> Option<&mut bool> {
+        match name {
+            atom!("singleton") => Some(&mut self.singleton),
+            atom!("discontiguous") => Some(&mut self.discontiguous),
+            atom!("redefined") => Some(&mut self.redefined),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
@@ -739,34 +824,37 @@ pub fn as_atom(&self, atom_tbl: &Arc<AtomTable>) -> Option<Atom> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct VarPtr(Rc<RefCell<Var>>);
+// the clauses of static predicates are compiled on other threads if
+// the machine compiles in parallel, so the variables they share are
+// locked rather than reference counted in cells.
+#[derive(Debug, Clone)]
+pub struct VarPtr(Arc<RwLock<Var>>);
 
-impl Hash for VarPtr {
+impl PartialEq for VarPtr {
     #[inline(always)]
-    fn hash<H: Hasher>(&self, hasher: &mut H) {
-        self.borrow().hash(hasher)
+    fn eq(&self, other: &VarPtr) -> bool {
+        *self.borrow() == *other.borrow()
     }
 }
 
-impl Deref for VarPtr {
-    type Target = RefCell<Var>;
+impl Eq for VarPtr {}
 
+impl Hash for VarPtr {
     #[inline(always)]
-    fn deref(&self) -> &Self::Target {
-        self.0.deref()
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.borrow().hash(hasher)
     }
 }
 
 impl VarPtr {
     #[inline(always)]
-    pub(crate) fn borrow(&self) -> Ref<'_, Var> {
-        self.0.borrow()
+    pub(crate) fn borrow(&self) -> RwLockReadGuard<'_, Var> {
+        self.0.read()
     }
 
     #[inline(always)]
-    pub(crate) fn borrow_mut(&self) -> RefMut<'_, Var> {
-        self.0.borrow_mut()
+    pub(crate) fn borrow_mut(&self) -> RwLockWriteGuard<'_, Var> {
+        self.0.write()
     }
 
     pub(crate) fn to_var_num(&self) -> Option<usize> {
@@ -785,7 +873,7 @@ pub(crate) fn set(&self, var: Var) {
 impl From<Var> for VarPtr {
     #[inline(always)]
     fn from(value: Var) -> VarPtr {
-        VarPtr(Rc::new(RefCell::new(value)))
+        VarPtr(Arc::new(RwLock::new(value)))
     }
 }
 
@@ -807,20 +895,20 @@ fn from(value: &str) -> VarPtr {
 pub enum Var {
     Generated(usize),
     InSitu(usize),
-    Named(Rc<String>),
+    Named(Arc<String>),
 }
 
 impl From<String> for Var {
     #[inline(always)]
     fn from(value: String) -> Var {
-        Var::Named(Rc::new(value))
+        Var::Named(Arc::new(value))
     }
 }
 
 impl From<&str> for Var {
     #[inline(always)]
     fn from(value: &str) -> Var {
-        Var::Named(Rc::new(value.to_owned()))
+        Var::Named(Arc::new(value.to_owned()))
     }
 }
 
@@ -843,8 +931,8 @@ pub enum Term {
     Literal(Cell<RegType>, Literal),
     // PartialString wraps a String in anticipation of it absorbing
     // other PartialString variants in as_partial_string.
-    PartialString(Cell<RegType>, Rc<String>, Box<Term>),
-    CompleteString(Cell<RegType>, Rc<String>),
+    PartialString(Cell<RegType>, Arc<String>, Box<Term>),
+    CompleteString(Cell<RegType>, Arc<String>),
     Var(Cell<VarReg>, VarPtr),
 }
 
@@ -682,10 +682,35 @@ fn name_token(&mut self, c: char) -> Result<Token, ParserError> {
 
     fn vacate_with_float(&mut self, mut token: String) -> Result<Number, ParserError> {
         self.return_char(token.pop().unwrap());
-        let n = parse_float_lossy(&token)?;
+        let n = parse_float_lossy(&token, self.line_num, self.col_num)?;
         Ok(Number::Float(float_alloc!(n, self.machine_st.arena)))
     }
 
+    // the infinity or NaN of the suffix Inf or NaN of a float, starting
+    // with c, as in 1.0Inf and 1.5NaN. the float itself is ignored.
+    fn non_finite_float_suffix(&mut self, c: char) -> Option<f64> {
+        let (suffix, n) = match c {
+            'I' => ("Inf", f64::INFINITY),
+            'N' => ("NaN", f64::NAN),
+            _ => return None,
+        };
+
+        for (idx, expected) in suffix.char_indices() {
+            match self.reader.peek_char() {
+                Some(Ok(c)) if c == expected => self.skip_char(c),
+                _ => {
+                    for c in suffix[..idx].chars().rev() {
+                        self.return_char(c);
+                    }
+
+                    return None;
+                }
+            }
+        }
+
+        Some(n)
+    }
+
     fn skip_underscore_in_number(&mut self) -> Result<char, ParserError> {
         let mut c = self.lookahead_char()?;
 
@@ -714,8 +739,7 @@ fn parse_integer_by_radix(&mut self, token: &str, radix: u32) -> Result<Number,
                     })
             })
             .or_else(|_| {
-                token
-                    .parse::<Integer>()
+                Integer::from_str_radix(token, radix)
                     .map(|n| Number::BigInt(arena_alloc!(n, &mut self.machine_st.arena)))
                     .map_err(|_| ParserError::ParseBigInt(self.line_num, self.col_num))
             })
@@ -805,7 +829,7 @@ fn number_token(&mut self, leading_c: char) -> Result<NumberToken, ParserError>
                             }
                         }
 
-                        let n = parse_float_lossy(&token)?;
+                        let n = parse_float_lossy(&token, self.line_num, self.col_num)?;
 
                         Ok(NumberToken::Number(Number::Float(float_alloc!(
                             n,
@@ -815,7 +839,11 @@ fn number_token(&mut self, leading_c: char) -> Result<NumberToken, ParserError>
                         return self.vacate_with_float(token).map(NumberToken::Number);
                     }
                 } else {
-                    let n = parse_float_lossy(&token)?;
+                    let n = match self.non_finite_float_suffix(c) {
+                        Some(n) => n,
+                        None => parse_float_lossy(&token, self.line_num, self.col_num)?,
+                    };
+
                     Ok(NumberToken::Number(Number::Float(float_alloc!(
                         n,
                         self.machine_st.arena
@@ -954,7 +982,7 @@ pub fn next_number_token(&mut self) -> Result<Token, ParserError> {
             Ok(NumberToken::Partial(token_string)) => match self.parse_integer(&token_string) {
                 Ok(n) => Ok(Token::Literal(n.to_literal())),
                 Err(_) => {
-                    let n = parse_float_lossy(&token_string)?;
+                    let n = parse_float_lossy(&token_string, self.line_num, self.col_num)?;
                     Ok(Token::Literal(Literal::F64Offset(float_alloc!(
                         n,
                         self.machine_st.arena
@@ -1071,12 +1099,19 @@ pub fn next_token(&mut self) -> Result<Token, ParserError> {
     }
 }
 
-fn parse_float_lossy(token: &str) -> Result<f64, ParserError> {
+// the float of token, which mustn't overflow to an infinity, those
+// being written 1.0Inf and -1.0Inf.
+fn parse_float_lossy(token: &str, line_num: usize, col_num: usize) -> Result<f64, ParserError> {
     const FORMAT: u128 = lexical::format::STANDARD;
     let options = lexical::ParseFloatOptions::builder()
         .lossy(true)
         .build()
         .unwrap();
     let n = lexical::parse_with_options::<f64, _, FORMAT>(token.as_bytes(), &options)?;
-    Ok(n)
+
+    if n.is_infinite() {
+        Err(ParserError::InfiniteFloat(line_num, col_num))
+    } else {
+        Ok(n)
+    }
 }
@@ -11,7 +11,7 @@
 use std::cell::Cell;
 use std::mem;
 use std::ops::Neg;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum TokenType {
@@ -400,7 +400,7 @@ fn shift(&mut self, token: Token, priority: usize, spec: Specifier) {
             Token::String(s) => {
                 debug_assert!(self.lexer.machine_st.flags.double_quotes.is_chars());
                 self.terms
-                    .push(Term::CompleteString(Cell::default(), Rc::new(s)));
+                    .push(Term::CompleteString(Cell::default(), Arc::new(s)));
                 TokenType::Term
             }
             Token::Literal(c) => {
@@ -556,10 +556,10 @@ fn reduce_term(&mut self) -> bool {
 
                     self.terms.push(match as_partial_string(head, tail) {
                         Ok((string_buf, Some(tail))) => {
-                            Term::PartialString(Cell::default(), Rc::new(string_buf), tail)
+                            Term::PartialString(Cell::default(), Arc::new(string_buf), tail)
                         }
                         Ok((string_buf, None)) => {
-                            Term::CompleteString(Cell::default(), Rc::new(string_buf))
+                            Term::CompleteString(Cell::default(), Arc::new(string_buf))
                         }
                         Err(term) => term,
                     });
@@ -741,10 +741,10 @@ fn reduce_list(&mut self) -> Result<bool, ParserError> {
         self.terms.push(match list {
             Term::Cons(_, head, tail) => match as_partial_string(*head, *tail) {
                 Ok((string_buf, Some(tail))) => {
-                    Term::PartialString(Cell::default(), Rc::new(string_buf), tail)
+                    Term::PartialString(Cell::default(), Arc::new(string_buf), tail)
                 }
                 Ok((string_buf, None)) => {
-                    Term::CompleteString(Cell::default(), Rc::new(string_buf))
+                    Term::CompleteString(Cell::default(), Arc::new(string_buf))
                 }
                 Err(term) => term,
             },
@@ -962,20 +962,6 @@ fn negate_rat_rc(t: TypedArenaPtr<Rational>, arena: &mut Arena) -> TypedArenaPtr
             Token::Literal(Literal::Rational(n)) => {
                 self.negate_number(n, negate_rat_rc, |r, _| Literal::Rational(r))
             }
-            Token::Literal(Literal::F64Offset(n))
-                if self
-                    .lexer
-                    .machine_st
-                    .arena
-                    .f64_tbl
-                    .get_entry(n)
-                    .is_infinite() =>
-            {
-                return Err(ParserError::InfiniteFloat(
-                    self.lexer.line_num,
-                    self.lexer.col_num,
-                ));
-            }
             Token::Literal(Literal::F64Offset(n)) => {
                 let n = self.lexer.machine_st.arena.f64_tbl.get_entry(n);
 
@@ -27,7 +27,7 @@
 use std::collections::VecDeque;
 use std::io::{Cursor, Read};
 #[cfg(feature = "repl")]
-use std::io::{Error, ErrorKind};
+use std::io::{Error, ErrorKind, Write};
 use std::sync::Arc;
 
 type SubtermDeque = VecDeque<(usize, usize)>;
@@ -107,10 +107,43 @@ fn get_prompt() -> &'static str {
     }
 }
 
+// a line of the toplevel that is a command rather than a query.
+#[cfg(feature = "repl")]
+enum ToplevelCommand<'a> {
+    // $h N or $h Name, which recalls the N-th query of history/0 or
+    // the query bookmarked as Name.
+    Recall(&'a str),
+    // :bookmark Name, which bookmarks the last query as Name.
+    Bookmark(&'a str),
+}
+
+#[cfg(feature = "repl")]
+impl<'a> ToplevelCommand<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let (command, arg) = line.trim().split_once(char::is_whitespace)?;
+        let arg = arg.trim();
+        let arg = arg.strip_suffix('.').unwrap_or(arg).trim_end();
+
+        if arg.is_empty() {
+            return None;
+        }
+
+        match command {
+            "$h" => Some(ToplevelCommand::Recall(arg)),
+            ":bookmark" => Some(ToplevelCommand::Bookmark(arg)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ReadlineStream {
     #[cfg(feature = "repl")]
     rl: Editor<Helper, DefaultHistory>,
+    // the user output stream, which the toplevel commands of the
+    // history write to.
+    #[cfg(feature = "repl")]
+    output: Stream,
     pending_input: CharReader<Cursor<String>>,
     #[allow(dead_code)]
     add_history: bool,
@@ -118,7 +151,8 @@ pub struct ReadlineStream {
 
 impl ReadlineStream {
     #[inline]
-    pub fn new(pending_input: &str, add_history: bool) -> Self {
+    #[allow(unused_variables)]
+    pub fn new(pending_input: &str, add_history: bool, output: Stream) -> Self {
         #[cfg(feature = "repl")]
         {
             let config = Config::builder().check_cursor_position(true).build();
@@ -137,6 +171,7 @@ pub fn new(pending_input: &str, add_history: bool) -> Self {
 
             ReadlineStream {
                 rl,
+                output,
                 pending_input: CharReader::new(Cursor::new(pending_input.to_owned())),
                 add_history,
             }
@@ -170,36 +205,121 @@ pub fn reset(&mut self) {
         pending_input.set_position(0);
     }
 
+    #[cfg(feature = "repl")]
+    fn write_line(&mut self, line: std::fmt::Arguments) {
+        let _ = writeln!(self.output, "{line}");
+        let _ = self.output.flush();
+    }
+
     #[cfg(feature = "repl")]
     fn call_readline(&mut self) -> std::io::Result<usize> {
-        match self.rl.readline(get_prompt()) {
-            Ok(text) => {
-                self.pending_input.reset_buffer();
-
-                *self.pending_input.get_mut().get_mut() = text;
-                self.pending_input.get_mut().set_position(0);
-
-                unsafe {
-                    if PROMPT {
-                        self.rl
-                            .add_history_entry(self.pending_input.get_ref().get_ref())
-                            .unwrap();
-                        self.save_history();
-                        PROMPT = false;
-                    }
+        loop {
+            let mut text = match self.rl.readline(get_prompt()) {
+                Ok(text) => text,
+                Err(ReadlineError::Eof) => return Err(Error::from(ErrorKind::UnexpectedEof)),
+                Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e)),
+            };
 
-                    if !self.pending_input.get_ref().get_ref().ends_with('\n') {
-                        *self.pending_input.get_mut().get_mut() += "\n";
+            unsafe {
+                if PROMPT {
+                    match ToplevelCommand::parse(&text) {
+                        Some(ToplevelCommand::Recall(key)) => match self.recall(key) {
+                            Some(query) => {
+                                self.write_line(format_args!("{query}"));
+                                text = query;
+                            }
+                            None => {
+                                self.write_line(format_args!(
+                                    "% Warning: no query {key} in the history"
+                                ));
+                                continue;
+                            }
+                        },
+                        Some(ToplevelCommand::Bookmark(name)) => {
+                            match self.history().pop() {
+                                Some((query, _)) => {
+                                    self.write_line(format_args!("% {name} bookmarks {query}"));
+                                    self.rl.add_history_entry(&text).unwrap();
+                                    self.save_history();
+                                }
+                                None => {
+                                    self.write_line(format_args!("% Warning: no query to bookmark"))
+                                }
+                            }
+
+                            continue;
+                        }
+                        None => {}
                     }
+
+                    self.rl.add_history_entry(&text).unwrap();
+                    self.save_history();
+                    PROMPT = false;
                 }
+            }
 
-                Ok(self.pending_input.get_ref().get_ref().len())
+            if !text.ends_with('\n') {
+                text += "\n";
             }
-            Err(ReadlineError::Eof) => Err(Error::from(ErrorKind::UnexpectedEof)),
-            Err(e) => Err(Error::new(ErrorKind::InvalidInput, e)),
+
+            self.pending_input.reset_buffer();
+
+            *self.pending_input.get_mut().get_mut() = text;
+            self.pending_input.get_mut().set_position(0);
+
+            return Ok(self.pending_input.get_ref().get_ref().len());
         }
     }
 
+    /// The queries of the history, oldest first, with the names they
+    /// are bookmarked as. `$h N` recalls the N-th of them.
+    #[cfg(feature = "repl")]
+    pub fn history(&self) -> Vec<(String, Vec<String>)> {
+        let mut queries: Vec<(String, Vec<String>)> = vec![];
+
+        for entry in self.rl.history().iter() {
+            match ToplevelCommand::parse(entry) {
+                Some(ToplevelCommand::Bookmark(name)) => {
+                    // a name bookmarks the last query it was given to.
+                    for (_, names) in &mut queries {
+                        names.retain(|other| other != name);
+                    }
+
+                    if let Some((_, names)) = queries.last_mut() {
+                        names.push(name.to_string());
+                    }
+                }
+                Some(ToplevelCommand::Recall(_)) => {}
+                None => queries.push((entry.clone(), vec![])),
+            }
+        }
+
+        queries
+    }
+
+    #[cfg(not(feature = "repl"))]
+    pub fn history(&self) -> Vec<(String, Vec<String>)> {
+        vec![]
+    }
+
+    // the query numbered or bookmarked as key.
+    #[cfg(feature = "repl")]
+    fn recall(&self, key: &str) -> Option<String> {
+        let mut queries = self.history();
+
+        if let Ok(n) = key.parse::<usize>() {
+            return n
+                .checked_sub(1)
+                .filter(|&i| i < queries.len())
+                .map(|i| queries.swap_remove(i).0);
+        }
+
+        queries
+            .into_iter()
+            .find(|(_, names)| names.iter().any(|name| name == key))
+            .map(|(query, _)| query)
+    }
+
     #[cfg(not(feature = "repl"))]
     fn call_readline(&mut self) -> std::io::Result<usize> {
         Ok(0)
@@ -6,7 +6,7 @@
 use crate::iterators::*;
 use crate::types::*;
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub(crate) struct FactInstruction;
 pub(crate) struct QueryInstruction;
@@ -23,7 +23,7 @@ pub(crate) trait CompilationTarget<'a> {
     fn to_void(num_subterms: usize) -> Instruction;
     fn is_void_instr(instr: &Instruction) -> bool;
 
-    fn to_pstr(lvl: Level, string: Rc<String>, r: RegType) -> Instruction;
+    fn to_pstr(lvl: Level, string: Arc<String>, r: RegType) -> Instruction;
 
     fn incr_void_instr(instr: &mut Instruction);
 
@@ -69,7 +69,7 @@ fn is_void_instr(instr: &Instruction) -> bool {
         matches!(instr, &Instruction::UnifyVoid(_))
     }
 
-    fn to_pstr(lvl: Level, string: Rc<String>, r: RegType) -> Instruction {
+    fn to_pstr(lvl: Level, string: Arc<String>, r: RegType) -> Instruction {
         Instruction::GetPartialString(lvl, string, r)
     }
 
@@ -131,7 +131,7 @@ fn to_list(lvl: Level, reg: RegType) -> Instruction {
         Instruction::PutList(lvl, reg)
     }
 
-    fn to_pstr(lvl: Level, string: Rc<String>, r: RegType) -> Instruction {
+    fn to_pstr(lvl: Level, string: Arc<String>, r: RegType) -> Instruction {
         Instruction::PutPartialString(lvl, string, r)
     }
 
@@ -284,6 +284,38 @@ test_mod_rem(X, Y) :-
     Rem == RemExpected,
     Rem2 == Rem.
 
+test_erf(X) :-
+    Y is erf(X),
+    Z is erfc(X),
+    One is Y + Z,
+    arithmetic_tests:approx_eq(One, 1.0),
+    call(is, Y, erf(X)),
+    call(is, Z, erfc(X)).
+
+factorial(0, 1).
+factorial(N, F) :-
+    N > 0,
+    N1 is N - 1,
+    factorial(N1, F1),
+    F is N * F1.
+
+test_lgamma(N) :-
+    Y is lgamma(N),
+    N1 is N - 1,
+    arithmetic_tests:factorial(N1, F),
+    Y2 is log(F),
+    arithmetic_tests:approx_eq(Y, Y2),
+    call(is, Y, lgamma(N)).
+
+test_expm1_log1p(X) :-
+    Y is expm1(X),
+    Y2 is exp(X) - 1,
+    arithmetic_tests:approx_eq(Y, Y2),
+    Z is log1p(Y),
+    arithmetic_tests:approx_eq(Z, X),
+    call(is, Y, expm1(X)),
+    call(is, Z, log1p(Y)).
+
 test_atan2(Angle) :-
     Cos is cos(Angle),
     Sin is sin(Angle),
@@ -450,6 +482,26 @@ test("atan", (
     )
 )).
 
+test("erf", (
+    forall(arithmetic_tests:test_value(X, _, _), arithmetic_tests:test_erf(X))
+)).
+
+test("lgamma", (
+    forall(between:between(1, 20, N), arithmetic_tests:test_lgamma(N)),
+    \+ catch(_ is lgamma(0), _, false),
+    \+ catch(_ is lgamma(-3), _, false),
+    \+ catch(_ is lgamma(-3.0), _, false)
+)).
+
+test("expm1_log1p", (
+    forall(
+        (arithmetic_tests:test_value(X, _, _), X < 10, X > -10),
+        arithmetic_tests:test_expm1_log1p(X)
+    ),
+    \+ catch(_ is log1p(-1), _, false),
+    \+ catch(_ is log1p(-2.0), _, false)
+)).
+
 test("bitwise_complement", (
     forall(arithmetic_tests:test_value(
         X, Exp,
@@ -1,5 +1,6 @@
 :- module(tests_on_builtins, []).
 
+:- use_module(library(charsio)).
 :- use_module(library(lists)).
 :- use_module(library(iso_ext)).
 
@@ -117,6 +118,25 @@ test_queries_on_builtins :-
     \+ 1 @=< 1.0,
     keysort([1-1,1-1],[1-1,1-1]),
     \+ \+ findall(Sorted, keysort([2-99,1-a,3-f(_),1-z,1-a,2-44],Sorted), [[1-a,1-z,1-a,2-99,2-44,3-f(_)]]),
-    \+ \+ findall(X, keysort([X-1,1-1],[2-1,1-1]), [2]).
+    \+ \+ findall(X, keysort([X-1,1-1],[2-1,1-1]), [2]),
+    atom_concat(abc, 'déf', 'abcdéf'),
+    atom_concat([], a, '[]a'),
+    \+ atom_concat(ab, c, abd),
+    \+ \+ findall(Prefix-Suffix, atom_concat(Prefix, Suffix, ab), [''-ab, a-b, ab-'']),
+    sub_atom('héllo', 1, 3, 1, 'éll'),
+    \+ \+ findall(Before, sub_atom('héllo', Before, 2, 1, _), [2]),
+    \+ \+ findall(Length-Sub, sub_atom('héllo', 1, Length, 1, Sub), [3-'éll']),
+    \+ \+ findall(After, sub_atom(abc, 3, 0, After, ''), [0]),
+    \+ sub_atom(abc, 2, 2, _, _),
+    \+ sub_atom(abc, 0, 2, 2, _),
+    \+ sub_atom(abc, 1, 1, _, c),
+    \+ \+ findall(Before-Length-After, sub_atom(abab, Before, Length, After, ab), [0-2-2, 2-2-0]),
+    \+ \+ (atom_chars('déf', Cs), partial_string(Cs), Cs = [_|Cs1], partial_string(Cs1)),
+    \+ \+ (number_chars(1.5, Ns), partial_string(Ns)),
+    \+ \+ (read_term_from_chars("f(\"ab\").", f(S), []), partial_string(S)),
+    \+ \+ (append("ab", [c], ABC), atom_chars(abc, ABC)),
+    \+ \+ (append([x], "yz", XYZ), atom_chars(xyz, XYZ)),
+    \+ \+ (append("1", ['2'], N12), number_chars(12, N12)),
+    \+ \+ (append("f(", [x, ')', '.'], FX), read_term_from_chars(FX, f(x), [])).
 
 :- initialization(test_queries_on_builtins).
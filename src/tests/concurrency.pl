@@ -0,0 +1,311 @@
+:- module(concurrency_tests, []).
+
+:- use_module(library(concurrency)).
+:- use_module(library(between)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lambda)).
+:- use_module(library(lists)).
+:- use_module(library(threads)).
+
+:- use_module(test_framework).
+
+test("call_with_time_limit/2 succeeds within the limit", (
+    call_with_time_limit(5, X = a),
+    X == a
+)).
+
+test("call_with_time_limit/2 fails if the goal fails", (
+    \+ call_with_time_limit(5, false)
+)).
+
+test("call_with_time_limit/2 throws when the limit is exceeded",
+     time_limit_exceeded(call_with_time_limit(0.1, (repeat, false)))).
+
+test("inner call_with_time_limit/2 expires first",
+     time_limit_exceeded(call_with_time_limit(5, call_with_time_limit(0.1, (repeat, false))))).
+
+test("outer call_with_time_limit/2 expires first",
+     time_limit_exceeded(call_with_time_limit(0.1, call_with_time_limit(5, (repeat, false))))).
+
+test("time limit is removed after the goal completes", (
+    call_with_time_limit(0.1, true),
+    length(_, N), N > 10000, !
+)).
+
+test("retry_with_backoff/3 rethrows after the last retry", (
+    catch(retry_with_backoff(throw(oops), 2, 0.001), E, true),
+    E == oops
+)).
+
+test("retry_with_backoff/3 retries until the goal succeeds",
+     retry_until_success).
+
+test("first_of/2 yields the first goal to find an answer",
+     first_of_winner).
+
+test("first_of/2 fails if all goals fail", (
+    \+ first_of([false, 1 = 2], _)
+)).
+
+test("first_of/2 propagates exceptions",
+     first_of_exception).
+
+test("first_of/2 lets goals take turns",
+     first_of_turns).
+
+test("first_of/2 resumes goals instead of restarting them",
+     first_of_resumes).
+
+test("concurrent_maplist/2 checks all elements",
+     concurrent_maplist_2).
+
+test("concurrent_maplist/3 relates both lists",
+     concurrent_maplist_3).
+
+test("concurrent_maplist/3 tasks don't share variables",
+     concurrent_maplist_3_copies).
+
+test("concurrent_maplist/2 fails while other tasks still run",
+     concurrent_maplist_cancels).
+
+test("parallel_findall/3 agrees with findall/3",
+     parallel_findall_solutions).
+
+test("parallel_findall/3 with a single goal",
+     parallel_findall_single_goal).
+
+test("parallel_findall/4 runs tasks in worker threads",
+     parallel_findall_workers).
+
+test("parallel_findall/4 rethrows the exceptions of workers",
+     parallel_findall_exception).
+
+test("parallel_findall/4 workers see the asserted and retracted clauses",
+     parallel_findall_dynamic).
+
+test("mutex_create/1 creates anonymous and named mutexes",
+     create_mutexes).
+
+test("with_mutex/2 releases the mutex on failure and exceptions",
+     with_mutex_releases).
+
+test("mutexes are recursive",
+     recursive_mutex).
+
+test("mutex_unlock/1 requires a locked mutex",
+     unlock_unlocked_mutex).
+
+test("mutex_property/2 reports the holder of a mutex",
+     mutex_holder).
+
+test("mutexes are shared between threads",
+     mutex_between_threads).
+
+test("condition_wait/2 waits for a signal from another thread",
+     condition_between_threads).
+
+test("condition_wait/3 fails after the timeout",
+     condition_timeout).
+
+time_limit_exceeded(Goal) :-
+    catch(Goal, E, true),
+    E == time_limit_exceeded.
+
+retry_until_success :-
+    bb_put(concurrency_attempts, 0),
+    retry_with_backoff(flaky_goal, 5, 0.001),
+    bb_get(concurrency_attempts, 3).
+
+flaky_goal :-
+    bb_get(concurrency_attempts, N0),
+    N is N0 + 1,
+    bb_put(concurrency_attempts, N),
+    (   N < 3 ->
+        throw(transient)
+    ;   true
+    ).
+
+first_of_winner :-
+    first_of([(length(_, N), N > 1000), X = a], W),
+    X == a,
+    W == (a = a).
+
+first_of_exception :-
+    catch(first_of([(repeat, false), throw(boom)], _), E, true),
+    E == boom.
+
+:- dynamic(race_calls/1).
+
+first_of_turns :-
+    first_of([(repeat, false), (length(_, N), N > 5000)], W),
+    W = (_, N1 > 5000),
+    N1 == 5001.
+
+first_of_resumes :-
+    assertz(race_calls(0)),
+    first_of([counted_length(_, 3000), counted_length(_, 6000)], W),
+    retract(race_calls(Calls)),
+    W = counted_length(L, _),
+    length(L, 3000),
+    Calls == 2.
+
+counted_length(L, N) :-
+    retract(race_calls(C0)),
+    C is C0 + 1,
+    assertz(race_calls(C)),
+    length(L, N0),
+    N0 >= N,
+    !.
+
+concurrent_maplist_cancels :-
+    \+ concurrent_maplist(\X^(X == loop -> repeat, false ; false), [loop, fail]).
+
+concurrent_maplist_2 :-
+    concurrent_maplist(\X^(X > 0), [1,2,3]),
+    \+ concurrent_maplist(\X^(X > 1), [1,2,3]).
+
+concurrent_maplist_3 :-
+    concurrent_maplist(\X^Y^(Y is X * 2), [1,2,3], Ys),
+    Ys == [2,4,6],
+    concurrent_maplist(succ, Xs, [1,2,3]),
+    Xs == [0,1,2].
+
+concurrent_maplist_3_copies :-
+    concurrent_maplist(\X^Y^(Y = X-_), [a,b], Ps),
+    Ps = [a-V1, b-V2],
+    V1 \== V2.
+
+create_mutexes :-
+    mutex_create(M1),
+    mutex_create(M2),
+    M1 \== M2,
+    mutex_create(concurrency_tests_mutex),
+    catch(mutex_create(concurrency_tests_mutex), E, true),
+    E = error(permission_error(create, mutex, concurrency_tests_mutex), _),
+    maplist(mutex_destroy, [M1, M2, concurrency_tests_mutex]),
+    catch(mutex_lock(M1), E1, true),
+    E1 = error(existence_error(mutex, M1), _).
+
+with_mutex_releases :-
+    mutex_create(M),
+    \+ with_mutex(M, false),
+    catch(with_mutex(M, throw(oops)), oops, true),
+    with_mutex(M, X = a),
+    X == a,
+    mutex_destroy(M).
+
+recursive_mutex :-
+    mutex_create(M),
+    with_mutex(M, with_mutex(M, true)),
+    mutex_lock(M),
+    catch(mutex_destroy(M), E, true),
+    E = error(permission_error(destroy, mutex, M), _),
+    mutex_unlock(M),
+    mutex_destroy(M).
+
+unlock_unlocked_mutex :-
+    mutex_create(M),
+    catch(mutex_unlock(M), E, true),
+    E = error(permission_error(unlock, mutex, M), _),
+    mutex_destroy(M).
+
+parallel_findall_solutions :-
+    Goal = (between(1, 20, X), between(1, X, Y), 0 =:= X mod Y),
+    findall(X-Y, Goal, Ps),
+    parallel_findall(X-Y, Goal, Ps),
+    parallel_findall(X, (member(X, [a,b]), false), []).
+
+parallel_findall_single_goal :-
+    parallel_findall(X, member(X, [c,b,a]), Xs),
+    Xs == [c,b,a].
+
+parallel_findall_workers :-
+    Goal = (between(1, 30, X), divisor(X, Y)),
+    findall(X-Y, Goal, Ps),
+    parallel_findall(X-Y, Goal, Ps, [workers(3)]),
+    parallel_findall(X, (member(X, [a,b]), false), [], [workers(2)]).
+
+divisor(X, Y) :-
+    between(1, X, Y),
+    0 =:= X mod Y.
+
+:- dynamic(weight/2).
+
+weight(c, 3).
+
+parallel_findall_dynamic :-
+    retract(weight(c, 3)),
+    assertz(weight(a, 1)),
+    assertz(weight(b, 2)),
+    Goal = (member(X, [a,b,c]), weight(X, W)),
+    findall(X-W, Goal, Ps),
+    parallel_findall(X-W, Goal, Ps, [workers(2)]),
+    Ps == [a-1,b-2].
+
+parallel_findall_exception :-
+    catch(parallel_findall(X, (member(X, [1,a]), _ is X + 1), _, [workers(2)]),
+          error(E, _),
+          true),
+    E == type_error(evaluable, a/0).
+
+mutex_holder :-
+    mutex_create(M),
+    mutex_property(M, status(unlocked)),
+    mutex_lock(M),
+    mutex_lock(M),
+    thread_self(Self),
+    mutex_property(M, status(locked(Self, 2))),
+    mutex_unlock(M),
+    mutex_unlock(M),
+    mutex_destroy(M).
+
+mutex_between_threads :-
+    mutex_create(concurrency_tests_shared),
+    mutex_lock(concurrency_tests_shared),
+    thread_create(( use_module(library(concurrency)),
+                    thread_send_message(main, ready),
+                    \+ mutex_trylock(concurrency_tests_shared),
+                    % goals passed to with_mutex/2 here would be
+                    % qualified by this module, which the thread lacks.
+                    mutex_lock(concurrency_tests_shared),
+                    thread_send_message(main, locked),
+                    mutex_unlock(concurrency_tests_shared)
+                  ),
+                  Id),
+    thread_get_message(main, ready),
+    \+ thread_get_message(main, locked, [timeout(0.2)]),
+    mutex_unlock(concurrency_tests_shared),
+    thread_get_message(main, locked),
+    thread_join(Id, Status),
+    Status == true,
+    mutex_destroy(concurrency_tests_shared).
+
+condition_between_threads :-
+    mutex_create(concurrency_tests_m),
+    condition_create(concurrency_tests_c),
+    thread_create(( use_module(library(concurrency)),
+                    mutex_lock(concurrency_tests_m),
+                    thread_send_message(main, waiting),
+                    condition_wait(concurrency_tests_c, concurrency_tests_m),
+                    mutex_unlock(concurrency_tests_m),
+                    thread_send_message(main, woken)
+                  ),
+                  Id),
+    thread_get_message(main, waiting),
+    % the waiter released the mutex once this thread can lock it.
+    with_mutex(concurrency_tests_m, condition_signal(concurrency_tests_c)),
+    thread_get_message(main, woken),
+    thread_join(Id, Status),
+    Status == true,
+    condition_destroy(concurrency_tests_c),
+    mutex_destroy(concurrency_tests_m).
+
+condition_timeout :-
+    mutex_create(M),
+    condition_create(C),
+    \+ with_mutex(M, condition_wait(C, M, [timeout(0.05)])),
+    mutex_property(M, status(unlocked)),
+    catch(condition_wait(C, M), E, true),
+    E = error(permission_error(unlock, mutex, M), _),
+    condition_destroy(C),
+    mutex_destroy(M).
@@ -0,0 +1,124 @@
+:- module(cont_tests, []).
+
+:- use_module(library(cont)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+
+:- use_module(test_framework).
+
+test("reset/3 without shift/1 yields none",
+     reset_without_shift).
+
+test("shift/1 suspends the goal until the continuation is called",
+     shift_and_resume).
+
+test("continuations can be called more than once",
+     resume_twice).
+
+test("shift/1 can be called repeatedly",
+     generator_to_list).
+
+test("nested reset/3 captures up to the innermost reset",
+     nested_reset).
+
+test("continuations keep the bindings made before shift/1",
+     resume_with_bindings).
+
+test("resumed continuations can be captured again",
+     state_handler).
+
+test("reset/3 can be backtracked into for the next ball",
+     backtrack_into_reset).
+
+test("shift/1 without reset/3 throws an existence error",
+     shift_without_reset).
+
+reset_without_shift :-
+    reset(X = a, Ball, Cont),
+    X == a,
+    var(Ball),
+    Cont == none.
+
+shift_and_resume :-
+    reset((shift(ball), X = resumed), Ball, cont(Goal)),
+    Ball == ball,
+    var(X),
+    call(Goal),
+    X == resumed.
+
+resume_twice :-
+    reset((shift(a), increment_counter), _, cont(Goal)),
+    bb_put(cont_counter, 0),
+    call(Goal),
+    call(Goal),
+    bb_get(cont_counter, 2).
+
+increment_counter :-
+    bb_get(cont_counter, N0),
+    N is N0 + 1,
+    bb_put(cont_counter, N).
+
+from_list([]).
+from_list([X|Xs]) :-
+    shift(yield(X)),
+    from_list(Xs).
+
+enumerate(Goal, Xs) :-
+    reset(Goal, Ball, Cont),
+    (   Cont == none ->
+        Xs = []
+    ;   Ball = yield(X),
+        Cont = cont(Goal1),
+        Xs = [X|Xs1],
+        enumerate(Goal1, Xs1)
+    ).
+
+generator_to_list :-
+    enumerate(from_list([a,b,c]), Xs),
+    Xs == [a,b,c].
+
+nested_reset :-
+    reset((reset((shift(inner), shift(outer)), B1, cont(G1)),
+           B1 == inner,
+           call(G1)),
+          B2, _),
+    B2 == outer.
+
+resume_with_bindings :-
+    reset((X = 1, shift(get(Y)), Z is X + Y), Ball, cont(Goal)),
+    Ball == get(Y),
+    Y = 2,
+    call(Goal),
+    Z == 3.
+
+% a state effect handled by resuming the continuation under a new
+% reset/3 after each get/1 or put/1.
+run_state(Goal, S0, S) :-
+    reset(Goal, Command, Cont),
+    (   Cont == none ->
+        S = S0
+    ;   Cont = cont(Goal1),
+        run_state(Command, Goal1, S0, S)
+    ).
+
+run_state(get(S0), Goal, S0, S) :-
+    run_state(Goal, S0, S).
+run_state(put(S1), Goal, _, S) :-
+    run_state(Goal, S1, S).
+
+incr :-
+    shift(get(N0)),
+    N is N0 + 1,
+    shift(put(N)).
+
+state_handler :-
+    run_state((incr, incr, incr), 0, S),
+    S == 3.
+
+backtrack_into_reset :-
+    findall(Ball, reset((member(X, [a,b,c]), shift(X)), Ball, _), Balls),
+    Balls == [a,b,c].
+
+shift_without_reset :-
+    catch(shift(ball), E, true),
+    E == error(existence_error(reset, ball), shift/1).
@@ -0,0 +1,62 @@
+:- module(coroutining_tests, []).
+
+:- use_module(library(coroutining)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+
+:- use_module(test_framework).
+
+test("freeze/2 delays the goal until the variable is bound",
+     freeze_delays).
+
+test("when/2 delays the goal until the condition holds",
+     when_delays).
+
+test("dif/2 fails once both terms are equal",
+     dif_prevents_equality).
+
+test("dif/2 doesn't wake other suspended goals",
+     dif_keeps_goals_suspended).
+
+test("frozen/2 yields the suspended goals",
+     frozen_goals).
+
+test("frozen/2 yields true without suspended goals",
+     frozen_true).
+
+freeze_delays :-
+    freeze(X, Y = woken),
+    var(Y),
+    X = a,
+    Y == woken.
+
+when_delays :-
+    when(ground(X-Y), Z is X + Y),
+    X = 1,
+    var(Z),
+    Y = 2,
+    Z == 3.
+
+dif_prevents_equality :-
+    dif(X, Y),
+    X = a,
+    \+ Y = a,
+    Y = b.
+
+dif_keeps_goals_suspended :-
+    bb_put(coroutining_woken, false),
+    when(nonvar(Y), bb_put(coroutining_woken, true)),
+    dif(a, Y),
+    bb_get(coroutining_woken, false),
+    Y = b,
+    bb_get(coroutining_woken, true).
+
+frozen_goals :-
+    freeze(X, true),
+    frozen(X, Goal),
+    Goal = freeze:freeze(V, _),
+    V == X.
+
+frozen_true :-
+    frozen(f(_, a), Goal),
+    Goal == true.
@@ -0,0 +1,175 @@
+:- module(engines_tests, []).
+
+:- use_module(library(concurrency), [call_with_time_limit/2]).
+:- use_module(library(engines)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+
+:- use_module(test_framework).
+
+test("engines compute answers on demand",
+     answers_on_demand).
+
+test("engine_post/3 posts terms fetched by the engine",
+     post_and_fetch).
+
+test("engine_next/2 rethrows the exceptions of an engine",
+     engine_exception).
+
+test("engine_self/1 fails outside engines",
+     \+ engine_self(_)).
+
+test("unknown engines throw existence errors",
+     unknown_engine).
+
+test("engine_yield/1 answers without leaving the goal",
+     yield_answers).
+
+test("engines share the database of their creator",
+     shared_database).
+
+test("engines resume at their last answer",
+     resume_at_last_answer).
+
+test("engines can be named by aliases",
+     engine_alias).
+
+test("engines can run engines",
+     nested_engines).
+
+test("engines keep their own backtrackable global variables",
+     engine_global_variables).
+
+test("the time limits of the creator apply within engines",
+     engine_time_limit).
+
+test("engines run with inference limits of their own",
+     engine_inference_limit).
+
+answers_on_demand :-
+    engine_create(X, member(X, [a,b]), E),
+    engine_next(E, A1),
+    engine_next(E, A2),
+    \+ engine_next(E, _),
+    \+ engine_next(E, _),
+    engine_destroy(E),
+    A1 == a,
+    A2 == b.
+
+post_and_fetch :-
+    engine_create(Y, (repeat, engine_fetch(X), Y is X * 10), E),
+    engine_post(E, 1),
+    engine_post(E, 2, Y1),
+    engine_post(E, 3, Y2),
+    engine_destroy(E),
+    Y1 == 10,
+    Y2 == 20.
+
+engine_exception :-
+    engine_create(_, throw(oops), E),
+    catch(engine_next(E, _), Ball, true),
+    engine_next_reified(E, Answer),
+    engine_destroy(E),
+    Ball == oops,
+    Answer == no.
+
+unknown_engine :-
+    catch(engine_next(no_such_engine, _), error(Error, _), true),
+    Error == existence_error(engine, no_such_engine).
+
+yield_answers :-
+    engine_create(X, (engine_yield(1), engine_yield(2), X = 3), E),
+    engine_answers(E, Answers),
+    engine_destroy(E),
+    Answers == [1,2,3].
+
+:- dynamic(shared_fact/1).
+
+shared_database :-
+    assertz(shared_fact(a)),
+    engine_create(X, (shared_fact(X), assertz(shared_fact(b))), E),
+    engine_next(E, X0),
+    engine_destroy(E),
+    findall(Y, retract(shared_fact(Y)), Ys),
+    X0 == a,
+    Ys == [a, b].
+
+:- dynamic(goal_calls/1).
+
+counted_member(X, Xs) :-
+    retract(goal_calls(N0)),
+    N is N0 + 1,
+    assertz(goal_calls(N)),
+    member(X, Xs).
+
+resume_at_last_answer :-
+    assertz(goal_calls(0)),
+    engine_create(X, counted_member(X, [a,b,c]), E),
+    engine_answers(E, Xs),
+    engine_destroy(E),
+    retract(goal_calls(N)),
+    Xs == [a,b,c],
+    N == 1.
+
+engine_alias :-
+    engine_create(X, member(X, [a]), E, [alias(named_engine)]),
+    catch(engine_create(_, true, _, [alias(named_engine)]), error(Error, _), true),
+    engine_next(named_engine, A),
+    engine_destroy(named_engine),
+    E == named_engine,
+    A == a,
+    Error == permission_error(create, engine, named_engine).
+
+nested_engines :-
+    engine_create(X-Y,
+                  ( member(X, [1,2]),
+                    engine_create(Z, member(Z, [a,b]), Inner),
+                    engine_answers(Inner, Y),
+                    engine_destroy(Inner)
+                  ),
+                  E),
+    engine_next(E, A1),
+    engine_next(E, A2),
+    engine_destroy(E),
+    A1 == 1-[a,b],
+    A2 == 2-[a,b].
+
+engine_answers(E, Answers) :-
+    (   engine_next(E, Answer) ->
+        Answers = [Answer | Answers0],
+        engine_answers(E, Answers0)
+    ;   Answers = []
+    ).
+
+engine_global_variables :-
+    b_setval(engine_var, parent),
+    engine_create(X,
+                  ( b_setval(engine_var, f(_)),
+                    b_getval(engine_var, X)
+                  ),
+                  E),
+    engine_next(E, A),
+    b_getval(engine_var, V),
+    engine_create(Y, b_getval(engine_var, Y), E1),
+    catch(engine_next(E1, _), error(Error, _), true),
+    engine_destroy(E),
+    engine_destroy(E1),
+    A = f(_),
+    V == parent,
+    Error == existence_error(variable, engine_var).
+
+engine_time_limit :-
+    engine_create(_, (repeat, false), E),
+    catch(call_with_time_limit(0.1, engine_next(E, _)), Error, true),
+    engine_destroy(E),
+    Error == time_limit_exceeded,
+    call_with_time_limit(5, true).
+
+engine_inference_limit :-
+    engine_create(R,
+                  call_with_inference_limit((repeat, false), 100, R),
+                  E),
+    call_with_inference_limit(engine_next(E, A), 1000000, R1),
+    engine_destroy(E),
+    A == inference_limit_exceeded,
+    R1 == !.
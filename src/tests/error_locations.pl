@@ -0,0 +1,55 @@
+:- module(error_locations_tests, []).
+
+:- use_module(test_framework).
+
+test("the error_locations flag is false by default",
+     current_prolog_flag(error_locations, false)).
+
+test("errors keep their context while the flag is false",
+     catch(p(_), error(instantiation_error, functor/3), true)).
+
+test("errors raised by consulted clauses are located",
+     (   with_error_locations(catch(p(_),
+                                    error(instantiation_error, location(functor/3, File, 36)),
+                                    true)),
+         atom_concat(_, 'error_locations.pl', File)
+     )).
+
+test("errors raised beneath consulted clauses locate the innermost",
+     with_error_locations(catch(findall(X, q(X), _),
+                                error(instantiation_error, location(functor/3, _, 36)),
+                                true))).
+
+test("each clause of a predicate has its own location",
+     with_error_locations(catch(r(2, _),
+                                error(instantiation_error, location(atom_length/2, _, 44)),
+                                true))).
+
+test("the flag only takes false and true",
+     catch(set_prolog_flag(error_locations, on),
+           error(domain_error(flag_value, error_locations+on), _),
+           true)).
+
+% the clauses whose errors are located. moving them means updating
+% the lines expected above.
+
+p(T) :-
+    functor(T, _, _).
+
+q(X) :-
+    p(X).
+
+r(1, X) :-
+    X is 1.
+r(2, X) :-
+    atom_length(X, N),
+    N > 0.
+
+with_error_locations(Goal) :-
+    set_prolog_flag(error_locations, true),
+    catch(Goal, E, true),
+    set_prolog_flag(error_locations, false),
+    (   nonvar(E) ->
+        throw(E)
+    ;   true
+    ).
@@ -0,0 +1,42 @@
+:- module(floundering_tests, []).
+
+:- use_module(test_framework).
+
+test("the floundering flag is false by default",
+     current_prolog_flag(floundering, false)).
+
+test("ground negations aren't flagged",
+     with_floundering(error, q(2))).
+
+test("non-ground negations raise an error",
+     with_floundering(error, catch(q(_), error(instantiation_error, (\+)/1), true))).
+
+test("negations called through call/1 are checked",
+     with_floundering(error, catch(call(\+ p(_)), error(instantiation_error, (\+)/1), true))).
+
+test("negations aren't checked once the flag is false",
+     \+ q(_)).
+
+test("the flag only takes false, warning and error",
+     catch(set_prolog_flag(floundering, on),
+           error(domain_error(flag_value, floundering+on), _),
+           true)).
+
+% the negations in the clauses loaded while the flag is set are checked.
+:- set_prolog_flag(floundering, error).
+
+p(1).
+
+q(X) :-
+    \+ p(X).
+
+:- set_prolog_flag(floundering, false).
+
+with_floundering(Value, Goal) :-
+    set_prolog_flag(floundering, Value),
+    catch(Goal, E, true),
+    set_prolog_flag(floundering, false),
+    (   nonvar(E) ->
+        throw(E)
+    ;   true
+    ).
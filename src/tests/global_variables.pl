@@ -0,0 +1,53 @@
+:- module(global_variables_tests, []).
+
+:- use_module(library(iso_ext)).
+
+:- use_module(test_framework).
+
+test("b_setval/2 is undone on backtracking",
+     b_setval_backtracks).
+
+test("b_setval/2 doesn't copy its value",
+     b_setval_shares).
+
+test("nb_setval/2 survives backtracking",
+     nb_setval_persists).
+
+test("b_getval/2 throws for unknown variables",
+     unknown_variable).
+
+test("keys must be atoms",
+     non_atom_keys).
+
+b_setval_backtracks :-
+    b_setval(global_variables_test, 1),
+    (   b_setval(global_variables_test, 2),
+        b_getval(global_variables_test, 2),
+        false
+    ;   b_getval(global_variables_test, X)
+    ),
+    X == 1.
+
+b_setval_shares :-
+    b_setval(global_variables_test, f(X)),
+    b_getval(global_variables_test, f(Y)),
+    Y = a,
+    X == a.
+
+nb_setval_persists :-
+    (   nb_setval(global_variables_test, f(_)),
+        false
+    ;   nb_getval(global_variables_test, X)
+    ),
+    X = f(Y),
+    var(Y).
+
+unknown_variable :-
+    catch(b_getval(global_variables_unknown, _), E, true),
+    E == error(existence_error(variable, global_variables_unknown), b_getval/2).
+
+non_atom_keys :-
+    catch(b_setval(_, 1), E1, true),
+    E1 = error(instantiation_error, _),
+    catch(nb_getval(1, _), E2, true),
+    E2 = error(type_error(atom, 1), _).
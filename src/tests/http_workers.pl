@@ -0,0 +1,85 @@
+:- module(http_workers_tests, []).
+
+:- use_module(library(between)).
+:- use_module(library(charsio)).
+:- use_module(library(http/http_open)).
+:- use_module(library(http/http_server)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+:- use_module(library(threads)).
+:- use_module(library(time)).
+
+:- use_module(test_framework).
+
+% the server runs in a background thread, its workers in threads of
+% their own, so that none of them is the thread of the tests.
+:- spawn_initialization(serve).
+
+serve :-
+    http_listen(7943, [get(slow, slow), get(fast, fast)], [workers(2)]).
+
+% answers once the tests send release, so that it keeps a worker busy.
+slow(_, Response) :-
+    thread_send_message(http_workers_test, started),
+    thread_get_message(http_workers_test, release),
+    http_body(Response, text("slow")).
+
+fast(_, Response) :-
+    http_body(Response, text("fast")).
+
+late(_, Response) :-
+    http_body(Response, text("late")).
+
+get(Path, Body) :-
+    append("http://127.0.0.1:7943/", Path, URL),
+    http_open(URL, Stream, [status_code(200)]),
+    get_n_chars(Stream, _, Body),
+    close(Stream).
+
+wait_for_server :-
+    catch(message_queue_create(http_workers_test),
+          error(permission_error(_, _, _), _),
+          true),
+    between(1, 600, _),
+    (   catch(get("fast", _), _, false) ->
+        !
+    ;   sleep(0.1),
+        false
+    ).
+
+% requests the slow page in another thread, which sends its body to
+% the thread of the tests, and waits until a worker handles it.
+start_slow_request :-
+    thread_create(( use_module(library(charsio)),
+                    use_module(library(http/http_open)),
+                    http_open("http://127.0.0.1:7943/slow", Stream, [status_code(200)]),
+                    get_n_chars(Stream, _, Body),
+                    thread_send_message(main, slow(Body))
+                  ),
+                  _),
+    thread_get_message(http_workers_test, started).
+
+finish_slow_request :-
+    thread_send_message(http_workers_test, release),
+    thread_get_message(slow(Body)),
+    Body == "slow".
+
+test("a worker answers while another one is busy", (
+    wait_for_server,
+    start_slow_request,
+    get("fast", Body),
+    finish_slow_request,
+    Body == "fast"
+)).
+
+test("workers use the handlers registered after the server started", (
+    wait_for_server,
+    http_handler(get, late, http_workers_tests:late),
+    start_slow_request,
+    get("late", Body1),
+    finish_slow_request,
+    get("late", Body2),
+    http_delete_handler(get, late),
+    Body1 == "late",
+    Body2 == "late"
+)).
@@ -0,0 +1,183 @@
+:- module(indexing_tests, []).
+
+:- use_module(library(iso_ext)).
+:- use_module(library(between)).
+:- use_module(library(lists)).
+
+:- use_module(test_framework).
+
+test("calls are indexed on the arguments of the first argument",
+     deep_indexing).
+
+test("calls are indexed on further arguments",
+     multi_argument_indexing).
+
+test("unbound subterms select every clause of a key",
+     unbound_subterms).
+
+test("retract/1 only tries the clauses selected by the first argument",
+     indexed_retract).
+
+test("clauses whose heads match lists are skipped if they don't apply",
+     list_heads_lookahead).
+
+test("calls are indexed on the values of floats",
+     float_indexing).
+
+test("dynamic predicates are indexed on further arguments",
+     dynamic_multi_argument_indexing).
+
+test("choice points of retracted clauses are dropped",
+     retracted_choice_points).
+
+test("retract/1 looks up clauses by the keys of their first arguments",
+     hashed_retract).
+
+p(f(a), 1).
+p(f(b), 2).
+p(f(c), 3).
+p(g, 4).
+p(f(a), 5).
+p(h(x, [1]), 6).
+p(h(x, [2]), 7).
+p(h(y, "ab"), 8).
+
+e(1, a, k(1)).
+e(1, b, k(2)).
+e(1, b, k(3)).
+e(2, c, k(4)).
+e(1, a, k(5)).
+
+w(1.5, a).
+w(2.5, b).
+w(x, c).
+w(f(0.25), d).
+w(1.5, e).
+w(-0.0, n).
+w(0.0, p).
+
+:- dynamic(d/2).
+:- dynamic(f/3).
+
+zip([], [], []).
+zip([A-B|ABs], [A|As], [B|Bs]) :-
+    zip(ABs, As, Bs).
+
+deterministic(Goal) :-
+    call_cleanup(Goal, Det = true),
+    Det == true.
+
+deep_indexing :-
+    deterministic(p(f(b), 2)),
+    deterministic(p(f(c), X)),
+    X == 3,
+    deterministic(p(h(x, [2]), Y)),
+    Y == 7,
+    findall(Z, p(f(a), Z), [1, 5]),
+    \+ p(f(d), _).
+
+multi_argument_indexing :-
+    deterministic(e(1, a, k(1))),
+    deterministic(e(1, b, k(3))),
+    findall(B, e(1, b, k(B)), [2, 3]),
+    findall(A-B, e(1, A, k(B)), [a-1, b-2, b-3, a-5]),
+    \+ e(1, c, _).
+
+unbound_subterms :-
+    findall(Y, p(f(_), Y), [1, 2, 3, 5]),
+    findall(Y-Z, p(h(Z, _), Y), [6-x, 7-x, 8-y]),
+    findall(Y, p(h(_, [1]), Y), [6]),
+    findall(B, e(_, b, k(B)), [2, 3]),
+    findall(X-Y, p(X, Y), L),
+    length(L, 8).
+
+indexed_retract :-
+    retractall(d(_, _)),
+    assertz(d(1, a)),
+    assertz(d(2, b)),
+    assertz(d(_, c)),
+    assertz(d([x], l)),
+    assertz(d(g(1), s)),
+    asserta(d(2, z)),
+    deterministic(retract(d(1, a))),
+    findall(B, retract(d(2, B)), [z, b, c]),
+    \+ retract(d(3, _)),
+    retract(d([_], L)),
+    L == l,
+    deterministic(retract(d(g(_), S))),
+    S == s,
+    \+ d(_, _).
+
+float_indexing :-
+    X is 3 / 2,
+    findall(Y, w(X, Y), [a, e]),
+    Z is 5 / 2,
+    deterministic(w(Z, B)),
+    B == b,
+    Q is 1 / 4,
+    deterministic(w(f(Q), D)),
+    D == d,
+    \+ w(3.5, _),
+    deterministic(w(-0.0, N)),
+    N == n,
+    deterministic(w(0.0, P)),
+    P == p,
+    retractall(d(_, _)),
+    assertz(d(Q, q)),
+    assertz(d(X, x)),
+    deterministic(d(0.25, Dq)),
+    Dq == q,
+    deterministic(retract(d(1.5, Dx))),
+    Dx == x,
+    retract(d(_, q)).
+
+list_heads_lookahead :-
+    deterministic(zip(_, [], _)),
+    deterministic(zip(Ps, [a, b], _)),
+    Ps = [a-_, b-_],
+    deterministic(zip([x-y], _, _)).
+
+dynamic_multi_argument_indexing :-
+    retractall(f(_, _, _)),
+    forall(between(1, 16, N), assertz(f(k, N, g(N)))),
+    deterministic(f(k, 9, G)),
+    G == g(9),
+    asserta(f(k, 9, h)),
+    assertz(f(k, 9, i)),
+    findall(X, f(k, 9, X), [h, g(9), i]),
+    forall(between(17, 32, N), asserta(f(k, N, g(N)))),
+    deterministic(f(k, 20, g(_))),
+    deterministic(retract(f(k, 5, _))),
+    \+ f(k, 5, _),
+    findall(X, retract(f(k, 9, X)), [h, g(9), i]),
+    \+ f(k, 9, _),
+    deterministic(f(k, 10, _)),
+    findall(N, f(k, N, g(N)), Ns),
+    length(Ns, 30).
+
+retracted_choice_points :-
+    retractall(d(_, _)),
+    assertz(d(k, c)),
+    assertz(d(k, b)),
+    assertz(d(k, _)),
+    once(retract(d(k, x))),
+    \+ d(k, z),
+    findall(B, d(k, B), [c, b]).
+
+hashed_retract :-
+    retractall(d(_, _)),
+    forall(between(1, 100, N), assertz(d(N, N))),
+    assertz(d(_, v)),
+    asserta(d(50, a)),
+    assertz(d(50, z)),
+    findall(B, retract(d(50, B)), [a, 50, v, z]),
+    \+ d(50, 50),
+    deterministic(retract(d(7, S))),
+    S == 7,
+    \+ retract(d(7, _)),
+    assertz(d(7, s)),
+    findall(N-B, d(N, B), NBs),
+    length(NBs, 99),
+    append(_, [7-s], NBs),
+    retractall(d(_, _)),
+    \+ d(_, _).
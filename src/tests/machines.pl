@@ -0,0 +1,67 @@
+:- module(machines_tests, []).
+
+:- use_module(library(machines)).
+
+:- use_module(test_framework).
+
+test("remote_call/3 enumerates the answers of another machine",
+     remote_answers).
+
+test("remote_call/3 preserves variable sharing in answers",
+     shared_variables).
+
+test("remote_call/3 is deterministic after the last answer",
+     deterministic_last_answer).
+
+test("remote_call/3 rethrows exceptions of the remote goal",
+     remote_exception).
+
+test("a machine runs one query at a time",
+     busy_machine).
+
+test("destroyed machines can't be queried",
+     destroyed_machine).
+
+with_machine(Program, M, Goal) :-
+    machine_create(M),
+    machine_consult(M, Program),
+    call(Goal),
+    machine_destroy(M).
+
+remote_answers :-
+    with_machine("p(1). p(2). p(3).", M,
+                 findall(X, remote_call(M, X, p(X)), [1,2,3])).
+
+shared_variables :-
+    with_machine("q(X, f(X, Y), Y).", M,
+                 (   remote_call(M, q(a, B, C)),
+                     B = f(a, Y),
+                     Y == C
+                 )).
+
+deterministic_last_answer :-
+    with_machine("p(1). p(2).", M,
+                 (   remote_call(M, p(2)),
+                     remote_call(M, X, p(X)),
+                     X == 1
+                 )).
+
+remote_exception :-
+    with_machine("", M,
+                 (   catch(remote_call(M, atom_length(_, _)),
+                           error(instantiation_error, _),
+                           true),
+                     catch(remote_call(M, throw(ball)), ball, true),
+                     remote_call(M, true)
+                 )).
+
+busy_machine :-
+    with_machine("p(1). p(2).", M,
+                 catch((remote_call(M, p(_)), remote_call(M, p(_))),
+                       error(permission_error(query, machine, M), _),
+                       true)).
+
+destroyed_machine :-
+    machine_create(M),
+    machine_destroy(M),
+    catch(remote_call(M, true), error(existence_error(machine, M), _), true).
@@ -0,0 +1,121 @@
+:- module(message_queues_tests, []).
+
+:- use_module(library(lists)).
+:- use_module(library(message_queues)).
+
+:- use_module(test_framework).
+
+test("messages are received in the order they were sent",
+     fifo_order).
+
+test("thread_get_message/2 takes the oldest matching message",
+     selective_receive).
+
+test("thread_get_message/3 fails after the timeout",
+     receive_timeout).
+
+test("thread_peek_message/2 leaves the message in the queue",
+     peek_message).
+
+test("messages are copies",
+     messages_are_copies).
+
+test("named queues can't be created twice",
+     named_queue).
+
+test("destroyed queues no longer exist",
+     destroyed_queue).
+
+test("queues can be read as streams of terms",
+     queue_stream).
+
+test("queue streams end once the queue is destroyed",
+     queue_stream_end).
+
+fifo_order :-
+    message_queue_create(Q),
+    thread_send_message(Q, a),
+    thread_send_message(Q, b),
+    thread_get_message(Q, X),
+    thread_get_message(Q, Y),
+    X == a,
+    Y == b,
+    message_queue_destroy(Q).
+
+selective_receive :-
+    message_queue_create(Q),
+    maplist(thread_send_message(Q), [x(1), y(2), x(3)]),
+    thread_get_message(Q, y(Y)),
+    Y == 2,
+    thread_get_message(Q, x(X1)),
+    thread_get_message(Q, x(X2)),
+    [X1, X2] == [1, 3],
+    message_queue_size(Q, 0),
+    message_queue_destroy(Q).
+
+receive_timeout :-
+    message_queue_create(Q),
+    thread_send_message(Q, a),
+    \+ thread_get_message(Q, b, [timeout(0.05)]),
+    \+ thread_get_message(Q, b, [timeout(0)]),
+    message_queue_size(Q, 1),
+    message_queue_destroy(Q).
+
+peek_message :-
+    message_queue_create(Q),
+    \+ thread_peek_message(Q, _),
+    thread_send_message(Q, f("text", 'an atom', [1.5|_])),
+    thread_peek_message(Q, f(S, A, _)),
+    S == "text",
+    A == 'an atom',
+    message_queue_size(Q, 1),
+    message_queue_destroy(Q).
+
+messages_are_copies :-
+    message_queue_create(Q),
+    thread_send_message(Q, f(X, X, _)),
+    thread_get_message(Q, f(A, B, C)),
+    var(X),
+    A == B,
+    A \== X,
+    A \== C,
+    message_queue_destroy(Q).
+
+named_queue :-
+    message_queue_create(message_queues_tests),
+    catch(message_queue_create(message_queues_tests), E, true),
+    E = error(permission_error(create, message_queue, message_queues_tests), _),
+    message_queue_destroy(message_queues_tests).
+
+destroyed_queue :-
+    message_queue_create(Q),
+    message_queue_destroy(Q),
+    catch(thread_send_message(Q, a), E, true),
+    E = error(existence_error(message_queue, Q), _).
+
+queue_stream :-
+    message_queue_create(Q),
+    message_queue_stream(Q, S),
+    thread_send_message(Q, f(X, "text", 'an atom')),
+    thread_send_message(Q, g),
+    read(S, T1),
+    T1 = f(A, Text, Atom),
+    var(A),
+    A \== X,
+    Text == "text",
+    Atom == 'an atom',
+    read(S, T2),
+    T2 == g,
+    message_queue_size(Q, 0),
+    message_queue_destroy(Q).
+
+queue_stream_end :-
+    message_queue_create(Q),
+    message_queue_stream(Q, S),
+    thread_send_message(Q, last),
+    message_queue_destroy(Q),
+    read(S, T1),
+    T1 == last,
+    read(S, T2),
+    T2 == end_of_file,
+    close(S).
@@ -0,0 +1,80 @@
+:- module(module_ops_tests, []).
+
+:- use_module(library(charsio)).
+:- use_module(library(iso_ext)).
+
+:- use_module(test_framework).
+
+:- op(700, xfx, ===>).
+
+test("operators declared in a module are not visible in user",
+     module_operator).
+
+test("op/3 called in a module declares the operator globally",
+     operator_declared_globally).
+
+test("op/3 declares an operator in the module qualifying it",
+     operator_declared_in_module).
+
+test("operators declared by op/3 are used by writeq/1 and read_from_chars/2",
+     write_and_read_runtime_operator).
+
+test("op/3 declares an operator in user if it qualifies it",
+     operator_declared_in_user).
+
+test("read_term_from_chars/3 reads with the operators of the calling module",
+     read_with_module_operators).
+
+test("read_term_from_chars/3 reads with the operators of the module option",
+     read_with_user_operators).
+
+test("write_term_to_chars/3 writes with the operators of the module option",
+     write_with_module_operators).
+
+module_operator :-
+    current_op(700, xfx, ===>),
+    \+ current_op(_, _, user:(===>)).
+
+operator_declared_globally :-
+    op(200, xfy, &&&),
+    current_op(200, xfy, &&&),
+    current_op(200, xfy, user:(&&&)),
+    op(0, xfy, &&&),
+    \+ current_op(_, _, &&&).
+
+operator_declared_in_module :-
+    op(200, xfy, module_ops_tests:(&&&)),
+    current_op(200, xfy, &&&),
+    \+ current_op(_, _, user:(&&&)),
+    op(0, xfy, module_ops_tests:(&&&)),
+    \+ current_op(_, _, &&&).
+
+write_and_read_runtime_operator :-
+    setup_call_cleanup(op(700, xfx, ==>>),
+                       ( write_term_to_chars(==>>(a, b), [quoted(true)], Cs),
+                         read_from_chars("a ==>> b.", T)
+                       ),
+                       op(0, xfx, ==>>)),
+    Cs == "a==>>b",
+    T == ==>>(a, b).
+
+operator_declared_in_user :-
+    op(300, xfx, user:(^^^)),
+    current_op(300, xfx, user:(^^^)),
+    op(0, xfx, user:(^^^)),
+    \+ current_op(_, _, user:(^^^)).
+
+read_with_module_operators :-
+    read_term_from_chars("a ===> b.", T, []),
+    T == ===>(a, b).
+
+read_with_user_operators :-
+    catch((read_term_from_chars("a ===> b.", _, [module(user)]), false),
+          error(syntax_error(_), _),
+          true).
+
+write_with_module_operators :-
+    write_term_to_chars(a ===> b, [module(module_ops_tests)], Cs0),
+    Cs0 == "a===>b",
+    write_term_to_chars(a ===> b, [], Cs1),
+    Cs1 == "===>(a,b)".
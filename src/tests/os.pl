@@ -0,0 +1,77 @@
+:- module(os_tests, []).
+
+:- use_module(library(os)).
+:- use_module(library(format)).
+:- use_module(library(time)).
+
+:- use_module(test_framework).
+
+:- dynamic(caught/1).
+
+test("on_signal/2 runs the goal of a signal at the next call",
+     signal_goal).
+
+test("sleep/1 returns early when a signal mapped to a goal arrives",
+     sleep_interrupted).
+
+test("ignored signals neither run goals nor stop the system",
+     ignored_signal).
+
+test("on_signal/2 rejects unknown signals and actions",
+     signal_errors).
+
+test("sleep/1 takes fractions of seconds",
+     sleep(0.01)).
+
+signal_goal :-
+    retractall(caught(_)),
+    on_signal(term, assertz(caught(term))),
+    kill(term),
+    on_signal(term, default),
+    caught(term).
+
+sleep_interrupted :-
+    retractall(caught(_)),
+    on_signal(term, assertz(caught(term))),
+    seconds(S0),
+    kill_later(term),
+    sleep(30),
+    seconds(S),
+    on_signal(term, default),
+    caught(term),
+    S - S0 < 10.
+
+ignored_signal :-
+    retractall(caught(_)),
+    on_signal(term, assertz(caught(term))),
+    on_signal(term, ignore),
+    kill(term),
+    sleep(0.2),
+    on_signal(term, default),
+    \+ caught(_).
+
+signal_errors :-
+    catch(on_signal(hup, true), error(E1, _), true),
+    E1 == domain_error(signal, hup),
+    catch(on_signal(term, _), error(E2, _), true),
+    E2 == instantiation_error,
+    G = 1,
+    catch(on_signal(term, G), error(E3, _), true),
+    E3 == type_error(callable, 1).
+
+kill(Signal) :-
+    pid(Pid),
+    phrase(format_("kill -~a ~d", [Signal, Pid]), Command),
+    shell(Command).
+
+% sends Signal to the system after a fifth of a second, while it
+% sleeps.
+kill_later(Signal) :-
+    pid(Pid),
+    phrase(format_("(sleep 0.2; kill -~a ~d) &", [Signal, Pid]), Command),
+    shell(Command).
+
+seconds(S) :-
+    current_time(T),
+    phrase(format_time("%s", T), Cs),
+    number_chars(S, Cs).
@@ -0,0 +1,56 @@
+:- module(profile_tests, []).
+
+:- use_module(library(lists)).
+:- use_module(library(profile)).
+:- use_module(library(time)).
+
+:- use_module(test_framework).
+
+test("profile/1 counts the calls of each predicate",
+     counted_calls).
+
+test("profile/1 counts redos on backtracking",
+     counted_redos).
+
+test("profile/1 stops profiling when its goal throws",
+     profile_with_exception).
+
+test("show_profile/1 rejects unknown orders",
+     unknown_order).
+
+count(0) :- !.
+count(N) :-
+    N1 is N - 1,
+    count(N1).
+
+q(a).
+q(b).
+q(c).
+
+r :-
+    q(X),
+    X == c.
+
+profile_row(Key, Calls, Redos) :-
+    statistics(profile, Rows),
+    member(profile(Key, Calls, Redos, Inclusive, Exclusive), Rows),
+    Inclusive >= Exclusive,
+    Exclusive >= 0.
+
+counted_calls :-
+    profile(count(10)),
+    profile_row(profile_tests:count/1, 11, 10).
+
+counted_redos :-
+    profile(r),
+    profile_row(profile_tests:q/1, 1, 2),
+    profile_row(profile_tests:r/0, 1, 0).
+
+profile_with_exception :-
+    catch(profile(throw(ball)), ball, true),
+    profile(r),
+    profile_row(profile_tests:r/0, 1, 0),
+    \+ profile_row(profile_tests:count/1, _, _).
+
+unknown_order :-
+    catch(show_profile([order(size)]), error(domain_error(profile_order, size), _), true).
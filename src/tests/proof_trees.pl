@@ -0,0 +1,65 @@
+:- module(proof_trees_tests, []).
+
+:- use_module(library(lists)).
+:- use_module(library(proof_trees)).
+
+:- use_module(test_framework).
+
+test("prove/2 gives the goals of a derivation as a tree",
+     grandparent_proof).
+
+test("prove/2 gives a proof for each answer",
+     proofs_on_backtracking).
+
+test("goals undone by backtracking have no nodes",
+     failed_branches).
+
+test("library predicates are leaves",
+     library_leaves).
+
+test("the proof of a conjunction has a node for each goal",
+     conjunction_proof).
+
+parent(ann, bob).
+parent(bob, cid).
+parent(bob, dot).
+
+grandparent(X, Z) :-
+    parent(X, Y),
+    parent(Y, Z).
+
+q(1).
+q(2).
+
+r(X) :-
+    q(X),
+    X > 1.
+
+s(Xs) :-
+    append(Xs, [b], [a,b]).
+
+grandparent_proof :-
+    prove(grandparent(ann, cid), Proof),
+    Proof == proof(grandparent(ann, cid),
+                   [proof(parent(ann, bob), []),
+                    proof(parent(bob, cid), [])]).
+
+proofs_on_backtracking :-
+    findall(Z-Ps, prove(grandparent(ann, Z), proof(_, Ps)), Answers),
+    Answers == [cid-[proof(parent(ann, bob), []), proof(parent(bob, cid), [])],
+                dot-[proof(parent(ann, bob), []), proof(parent(bob, dot), [])]].
+
+failed_branches :-
+    prove(r(X), Proof),
+    X == 2,
+    Proof == proof(r(2), [proof(q(2), [])]).
+
+library_leaves :-
+    prove(s(Xs), Proof),
+    Proof == proof(s([a]), [proof(append([a], [b], [a,b]), [])]),
+    Xs == [a].
+
+conjunction_proof :-
+    prove((q(1), r(Y)), Proof),
+    Proof == proof((q(1), r(2)), [proof(q(1), []), proof(r(2), [proof(q(2), [])])]),
+    Y == 2.
@@ -0,0 +1,54 @@
+:- module(provenance_tests, []).
+
+:- use_module(library(lists)).
+:- use_module(library(provenance)).
+
+:- use_module(test_framework).
+
+test("provenance_call/2 records the tag and goal of asserted clauses",
+     recorded_provenance).
+
+test("clauses asserted outside provenance_call/2 have no properties",
+     unrecorded_clauses).
+
+test("inner provenance_call/2 calls take precedence",
+     nested_calls).
+
+test("the provenance of retracted clauses is forgotten",
+     retracted_clause).
+
+:- dynamic(likes/2).
+
+derive(P) :-
+    assertz(likes(P, tea)),
+    asserta(likes(P, milk)).
+
+recorded_provenance :-
+    provenance_call(rule(r1), derive(ann)),
+    findall(X-T, clause_property(likes(ann, X), tag(T)), [milk-rule(r1), tea-rule(r1)]),
+    clause_property(likes(ann, tea), source(provenance_tests:derive(ann))),
+    clause_property(likes(ann, tea), timestamp(_)),
+    retractall(likes(_, _)).
+
+unrecorded_clauses :-
+    assertz(likes(bob, coffee)),
+    \+ clause_property(likes(bob, _), _),
+    retractall(likes(_, _)).
+
+nested_calls :-
+    provenance_call(outer,
+                    (   assertz(likes(cid, water)),
+                        provenance_call(inner, assertz(likes(cid, juice))),
+                        assertz(likes(cid, soda))
+                    )),
+    findall(X-T, clause_property(likes(cid, X), tag(T)),
+            [water-outer, juice-inner, soda-outer]),
+    retractall(likes(_, _)).
+
+retracted_clause :-
+    provenance_call(r, (assertz(likes(dee, tea)), assertz(likes(dee, tea)))),
+    retract(likes(dee, tea)),
+    findall(T, clause_property(likes(dee, tea), tag(T)), [r]),
+    length(Ls, 1),
+    findall(L, provenance:provenance(L, _, _, _), Ls),
+    retractall(likes(_, _)).
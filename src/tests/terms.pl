@@ -0,0 +1,124 @@
+:- module(terms_tests, []).
+
+:- use_module(library(freeze)).
+:- use_module(library(terms)).
+:- use_module(test_framework).
+
+test("variants rename variables one-to-one",
+     (   term_variant(f(X, Y, X), f(A, B, A)),
+         term_variant(f(X, Y), f(Y, X)),
+         \+ term_variant(f(X, Y), f(A, A)),
+         \+ term_variant(f(X, X), f(A, B)),
+         \+ term_variant(f(X, a), f(Y, X)),
+         var(X), var(Y), var(A), var(B)
+     )).
+
+test("variants pair the variables of shared subterms",
+     (   T = g(_),
+         \+ term_variant(f(T, Y), f(T, T)),
+         term_variant(f(T, Y), f(T, _))
+     )).
+
+test("variants compare partial strings as lists",
+     (   term_variant("ab", [a,b]),
+         term_variant([a|T], [a|U]),
+         \+ term_variant([a|T], [a|"b"]),
+         var(T), var(U)
+     )).
+
+test("cyclic terms can be variants",
+     (   X = f(X, A), Y = f(Y, B),
+         term_variant(X, Y),
+         var(A), var(B)
+     )).
+
+test("equal terms hash alike whatever their representation",
+     (   term_hash(f("ab", 1.0, 12345678901234567890), H),
+         L = [a|T], T = [b],
+         term_hash(f(L, 1.0, 12345678901234567890), H),
+         integer(H), H >= 0, H < 4294967296
+     )).
+
+test("non-ground terms are not hashed",
+     (   term_hash(f(_), H),
+         var(H)
+     )).
+
+test("depth bounds the inspected subterms",
+     (   term_hash(f(a, g(_)), 2, 100, H0),
+         integer(H0), H0 < 100,
+         term_hash(f(a, b), 1, 1000, H1),
+         term_hash(f(b, c), 1, 1000, H1),
+         term_hash(f(_), 1, 1000, H2),
+         integer(H2)
+     )).
+
+test("cyclic terms are hashed within a depth",
+     (   X = f(X),
+         term_hash(X, 3, 100, H),
+         integer(H),
+         catch(term_hash(X, _),
+               error(type_error(acyclic_term, _), term_hash/4),
+               true)
+     )).
+
+test("depth and range are checked",
+     (   catch(term_hash(a, -2, 10, _),
+               error(domain_error(term_hash_depth, -2), term_hash/4),
+               true),
+         catch(term_hash(a, 1, 0, _),
+               error(domain_error(positive_integer, 0), term_hash/4),
+               true)
+     )).
+
+test("numbervars binds variables in order of first occurrence",
+     (   T = f(X, _, X),
+         numbervars(T, 23, N),
+         T == f('$VAR'(23), '$VAR'(24), '$VAR'(23)),
+         N == 25
+     )).
+
+test("numbervars checks its arguments",
+     (   catch(numbervars(f(_), a, _),
+               error(type_error(integer, a), numbervars/3),
+               true),
+         catch(numbervars(f(_), _, _),
+               error(instantiation_error, numbervars/3),
+               true),
+         catch(numbervars(f(_), 0, _, [foo]),
+               error(domain_error(numbervars_option, foo), numbervars/4),
+               true)
+     )).
+
+test("numbervars can name the functor",
+     (   T = g(_, _),
+         numbervars(T, 0, 2, [functor_name(v)]),
+         T == g(v(0), v(1))
+     )).
+
+test("numbervars can skip attributed variables",
+     (   freeze(X, true),
+         T = f(X, _),
+         numbervars(T, 0, 1, [attvar(skip)]),
+         var(X),
+         catch(numbervars(T, 0, _, [attvar(error)]),
+               error(type_error(free_of_attvar, _), numbervars/4),
+               true)
+     )).
+
+test("the term_hash_algorithm flag selects the hash function",
+     (   current_prolog_flag(term_hash_algorithm, fxhash),
+         term_hash(f("ab", 1.0), H0),
+         set_prolog_flag(term_hash_algorithm, sha3_256),
+         term_hash(f("ab", 1.0), H1),
+         term_hash(f([a,b], 1.0), H1),
+         set_prolog_flag(term_hash_algorithm, blake2b512),
+         term_hash(f("ab", 1.0), 2, 1000, H2),
+         H2 < 1000,
+         set_prolog_flag(term_hash_algorithm, fxhash),
+         term_hash(f("ab", 1.0), H0),
+         H0 =\= H1,
+         catch(set_prolog_flag(term_hash_algorithm, md5),
+               error(domain_error(flag_value, term_hash_algorithm+md5), _),
+               true)
+     )).
@@ -4,13 +4,15 @@
 :- use_module(library(format)).
 
 main(TestModule) :-
-    findall(test(Name, TestModule:Goal), TestModule:test(Name, Goal), Tests),
+    findall(test(Name, TestModule:Goal), TestModule:test(Name, Goal), Tests0),
+    shuffle_tests(Tests0, Tests),
     run_tests(Tests, Failed),
     show_failed(Failed),
     halt.
 
 main_quiet(TestModule) :-
-    findall(test(Name, TestModule:Goal), TestModule:test(Name, Goal), Tests),
+    findall(test(Name, TestModule:Goal), TestModule:test(Name, Goal), Tests0),
+    shuffle_tests(Tests0, Tests),
     run_tests_quiet(Tests, Failed),
     (   Failed = [] ->
         format("All tests passed", [])
@@ -18,6 +20,17 @@ main_quiet(TestModule) :-
     ),
     halt.
 
+% with the shuffle_seed flag set, tests run in an order shuffled by
+% its seed, which is printed so that a run failing because tests
+% depend on each other, or on unspecified orders, can be repeated.
+shuffle_tests(Tests0, Tests) :-
+    (   current_prolog_flag(shuffle_seed, Seed),
+        integer(Seed) ->
+        format("Shuffling tests and unspecified orders with seed ~d~n", [Seed]),
+        '$shuffle_unspecified'(Tests0, Tests)
+    ;   Tests = Tests0
+    ).
+
 portray_failed_([]) --> [].
 portray_failed_([F|Fs]) -->
     "\"", F, "\"",  "\n", portray_failed_(Fs).
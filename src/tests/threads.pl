@@ -0,0 +1,93 @@
+:- module(threads_tests, []).
+
+:- use_module(library(threads)).
+
+:- use_module(test_framework).
+
+test("threads receive and send messages",
+     message_round_trip).
+
+test("thread_join/2 reports failure and exceptions",
+     join_status).
+
+test("the initial thread is main",
+     main_thread).
+
+test("threads block reading from queue streams",
+     queue_stream_reader).
+
+test("destroying a queue stops the threads waiting on it",
+     destroyed_while_waiting).
+
+test("threads have dynamic databases of their own",
+     separate_databases).
+
+:- dynamic(threads_tests_fact/1).
+
+message_round_trip :-
+    thread_create(( thread_get_message(X),
+                    Y is X * 2,
+                    thread_send_message(main, doubled(Y))
+                  ),
+                  Id,
+                  [alias(threads_tests_worker)]),
+    Id == threads_tests_worker,
+    thread_send_message(Id, 21),
+    thread_join(Id, Status),
+    Status == true,
+    thread_get_message(doubled(Y)),
+    Y == 42,
+    catch(thread_property(Id, _), E, true),
+    E = error(existence_error(thread, Id), _).
+
+join_status :-
+    thread_create(throw(oops), Id1),
+    thread_create(false, Id2),
+    thread_join(Id1, Status1),
+    thread_join(Id2, Status2),
+    Status1 == exception(oops),
+    Status2 == false.
+
+main_thread :-
+    thread_self(Id),
+    Id == main.
+
+queue_stream_reader :-
+    message_queue_create(threads_tests_input),
+    thread_create(( message_queue_stream(threads_tests_input, S),
+                    read(S, X),
+                    read(S, Y),
+                    Z is X + Y,
+                    thread_send_message(main, sum(Z))
+                  ),
+                  Id),
+    thread_send_message(threads_tests_input, 1),
+    thread_send_message(threads_tests_input, 2),
+    thread_join(Id, Status),
+    Status == true,
+    thread_get_message(sum(Z)),
+    Z == 3,
+    message_queue_destroy(threads_tests_input).
+
+destroyed_while_waiting :-
+    message_queue_create(threads_tests_doomed),
+    thread_create(thread_get_message(threads_tests_doomed, _), Id),
+    message_queue_destroy(threads_tests_doomed),
+    thread_join(Id, Status),
+    Status = exception(error(existence_error(message_queue, threads_tests_doomed), _)).
+
+separate_databases :-
+    assertz(threads_tests_fact(main)),
+    thread_create(( \+ catch(threads_tests_fact(_), _, false),
+                    assertz(threads_tests_fact(thread)),
+                    findall(X, threads_tests_fact(X), Xs),
+                    thread_send_message(main, facts(Xs))
+                  ),
+                  Id),
+    thread_join(Id, Status),
+    Status == true,
+    thread_get_message(facts(Xs)),
+    Xs == [thread],
+    findall(X, threads_tests_fact(X), Ys),
+    Ys == [main],
+    retract(threads_tests_fact(main)).
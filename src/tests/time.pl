@@ -0,0 +1,55 @@
+:- module(time_tests, []).
+
+:- use_module(library(time)).
+
+:- use_module(test_framework).
+
+test("stamp_date_time/3 gives the date and time of a stamp",
+     (   stamp_date_time(0, DT1, utc),
+         DT1 == date_time(1970,1,1,0,0,0,0),
+         stamp_date_time(1709296205.25, DT2, 3600),
+         DT2 == date_time(2024,3,1,13,30,5.25,3600)
+     )).
+
+test("date_time_stamp/2 is the inverse of stamp_date_time/3",
+     (   date_time_stamp(date_time(2024,3,1,12,30,5,0), S1),
+         S1 == 1709296205,
+         date_time_stamp(date_time(2024,3,1,13,30,5.25,3600), S2),
+         S2 == 1709296205.25
+     )).
+
+test("date_time_stamp/2 rejects invalid dates",
+     (   catch(date_time_stamp(date_time(2024,2,30,0,0,0,0), _), error(E, _), true),
+         E == domain_error(date_time, date_time(2024,2,30,0,0,0,0))
+     )).
+
+test("format_time/3 formats dates and times by strftime specifiers",
+     (   format_time("%a, %d %b %Y %H:%M:%S %:z", date_time(2024,3,1,12,30,5,3600), Cs),
+         Cs == "Fri, 01 Mar 2024 12:30:05 +01:00"
+     )).
+
+test("format_time/3 rejects invalid formats",
+     (   catch(format_time("%Q", date_time(2024,3,1,0,0,0,0), _), error(E, _), true),
+         E == domain_error(time_format, "%Q")
+     )).
+
+test("parse_time/3 parses dates and times with offsets",
+     (   parse_time("01/Mar/2024:12:30:05 +0100", "%d/%b/%Y:%H:%M:%S %z", DT),
+         DT == date_time(2024,3,1,12,30,5,3600)
+     )).
+
+test("parse_time/3 puts dates without times at midnight UTC",
+     (   parse_time("2024-03-01", "%Y-%m-%d", DT),
+         DT == date_time(2024,3,1,0,0,0,0)
+     )).
+
+test("parse_time/3 fails for strings that don't match the format",
+     \+ parse_time("garbage", "%Y-%m-%d", _)).
+
+test("get_time/1 and monotonic_time/1 give floats of seconds",
+     (   get_time(T),
+         float(T),
+         monotonic_time(M0),
+         monotonic_time(M),
+         M >= M0
+     )).
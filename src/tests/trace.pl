@@ -0,0 +1,56 @@
+:- use_module(library(trace)).
+:- use_module(library(dcgs)).
+:- use_module(library(format)).
+:- use_module(library(lists)).
+:- use_module(library(pio)).
+:- use_module(library(serialization/json)).
+
+app([], Ys, Ys).
+app([X|Xs], Ys, [X|Zs]) :-
+    app(Xs, Ys, Zs).
+
+p(X) :-
+    q(X),
+    r(X).
+
+q(1).
+q(2).
+
+r(2).
+
+w.
+w.
+
+v :-
+    w,
+    false.
+
+% writes the lines of the feed of trace_feed/2 in File without the
+% state of the machine, which changes as the system does, after
+% checking that it's there.
+feed_lines(File) :-
+    once(phrase_from_file(lines(Ls), File)),
+    maplist(feed_line, Ls).
+
+lines([]) --> [].
+lines([L|Ls]) --> seq(L), "\n", lines(Ls).
+
+feed_line(L) :-
+    json_chars_with_options(pairs([step-number(Step),
+                                   port-string(Port),
+                                   depth-number(D),
+                                   goal-string(Goal),
+                                   registers-list(Rs),
+                                   heap-number(H),
+                                   stack-number(S),
+                                   trail-number(T),
+                                   choicepoints-number(B)]),
+                            L,
+                            [keys(atom)]),
+    maplist(integer, [H, S, T, B]),
+    format("~d ~s: (~d) ~s,", [Step, Port, D, Goal]),
+    maplist(register, Rs),
+    nl.
+
+register(string(Cs)) :-
+    format(" ~s", [Cs]).
@@ -0,0 +1,54 @@
+:- module(xref_tests, [exported_and_called/0, exported_and_unused/0]).
+
+:- use_module(library(lists)).
+:- use_module(library(xref)).
+
+:- use_module(test_framework).
+
+test("xref_calls/2 finds calls by name",
+     xref_calls(xref_tests:caller/1, lists:append/3)).
+
+test("xref_calls/2 finds goals passed to meta-predicates",
+     (   xref_calls(xref_tests:caller/1, builtins:findall/3),
+         xref_calls(xref_tests:caller/1, lists:member/2)
+     )).
+
+test("xref_calls/2 enumerates the callees of a predicate once each",
+     (   findall(Callee, xref_calls(xref_tests:exported_and_called/0, Callee), Callees),
+         Callees == [xref_tests:caller/1]
+     )).
+
+test("xref_undefined/2 finds calls to undefined predicates",
+     (   findall(Caller-Callee,
+                 (   xref_undefined(Caller, Callee),
+                     Caller = xref_tests:_
+                 ),
+                 Undefined),
+         Undefined == [(xref_tests:caller/1)-(xref_tests:not_defined/1)]
+     )).
+
+test("declared dynamic predicates are defined",
+     \+ xref_undefined(_, xref_tests:declared/0)).
+
+test("xref_unused_export/1 finds exports no other module calls",
+     (   findall(PI, xref_unused_export(xref_tests:PI), PIs),
+         PIs == [exported_and_unused/0]
+     )).
+
+:- dynamic(declared/0).
+
+caller(Xs) :-
+    append(Xs, [], Ys),
+    findall(Y, member(Y, Ys), _),
+    (   declared ->
+        true
+    ;   not_defined(Xs)
+    ).
+
+exported_and_called :-
+    caller([]).
+
+exported_and_unused.
+
+user:calls_export :-
+    xref_tests:exported_and_called.
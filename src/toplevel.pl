@@ -1,4 +1,4 @@
-:- module('$toplevel', [repl/0]).
+:- module('$toplevel', [repl/0, history/0]).
 
 :- use_module(library(charsio)).
 :- use_module(library(error)).
@@ -31,14 +31,17 @@ load_scryerrc :-
 '$repl' :-
     catch(
         start_repl,
-        _,
+        E,
         % Something bad enough happened that the REPL itself threw an error.
         % This can be caused by a broken user_output stream, so we cannot
         % print an error.
         %
-        % The best we can do now is halt with an error code,
-        % so that users can try to diagnose the issue:
-        halt(99)
+        % The best we can do now is report the crash, if crashes are
+        % reported, and halt with an error code, so that users can try
+        % to diagnose the issue:
+        (   '$report_crash'(E),
+            halt(99)
+        )
     ).
 
 start_repl :-
@@ -73,12 +76,20 @@ delegate_task([], Goals0) :-
     run_goals(Goals),
     repl.
 
+delegate_task(["--library-path", _|Args], Goals0) :-
+    !,
+    delegate_task(Args, Goals0).
+delegate_task(["--crash-report", _|Args], Goals0) :-
+    !,
+    delegate_task(Args, Goals0).
 delegate_task([Arg0|Args], Goals0) :-
     (   (   member(Arg0, ["-h", "--help"]) -> print_help
         ;   member(Arg0, ["-v", "--version"]) -> print_version
         ;   member(Arg0, ["-g", "--goal"]) -> gather_goal(g, Args, Goals0)
         ;   member(Arg0, ["-f"]) -> disable_init_file
         ;   member(Arg0, ["--no-add-history"]) -> ignore_machine_arg
+        ;   member(Arg0, ["--progress"]) -> ignore_machine_arg
+        ;   member(Arg0, ["--index-cache"]) -> ignore_machine_arg
         ),
         !,
         delegate_task(Args, Goals0)
@@ -100,6 +111,18 @@ print_help :-
     write('Fast startup. Do not load initialization file (~/.scryerrc)'), nl,
     write('   --no-add-history       '),
     write('Prevent adding input to history file (~/.scryer_history)'), nl,
+    write('   --progress             '),
+    write('Report the progress of loading files on stderr'), nl,
+    write('   --index-cache          '),
+    write('Keep the indices of loaded fact tables in .idx files'), nl,
+    write('   --library-path DIR     '),
+    write('Search DIR for library(Path) after the system libraries'), nl,
+    write('   --crash-report FILE    '),
+    write('Write a report of the machine to FILE if it crashes'), nl,
+    write('   --dap                  '),
+    write('Run a Debug Adapter Protocol server on stdin and stdout'), nl,
+    write('   --lsp                  '),
+    write('Run a Language Server Protocol server on stdin and stdout'), nl,
     % write('                        '),
     halt.
 
@@ -143,7 +166,7 @@ run_goals([g(Gs0)|Goals]) :- !,
     ;   append(Gs0, ".", Gs1)
     ),
     double_quotes_option(DQ),
-    catch(read_term_from_chars(Gs1, Goal, [variable_names(VNs)]),
+    catch(read_term_from_chars(Gs1, Goal, [module(user), variable_names(VNs)]),
           E,
           (   write_term(Gs0, [double_quotes(DQ)]),
               write(' cannot be read: '), write(E), nl,
@@ -193,6 +216,33 @@ expand_op_list([Op | OtherOps], Pri, Spec, [(:- op(Pri, Spec, Op)) | OtherResult
     expand_op_list(OtherOps, Pri, Spec, OtherResults).
 
 
+%% history
+%
+% Lists the queries of the history of the toplevel, numbered as they
+% are recalled by `$h N`, with the names they are bookmarked as by
+% `:bookmark Name` and recalled by `$h Name`.
+history :-
+    '$history'(Queries),
+    foldl(print_history_query, Queries, 1, _).
+
+print_history_query(Query-Names, N0, N) :-
+    format("~t~d~6|  ~s", [N0, Query]),
+    (   Names == [] ->
+        true
+    ;   format("  % ", []),
+        print_bookmarks(Names)
+    ),
+    nl,
+    N is N0 + 1.
+
+print_bookmarks([Name|Names]) :-
+    format("~a", [Name]),
+    (   Names == [] ->
+        true
+    ;   format(", ", []),
+        print_bookmarks(Names)
+    ).
+
 read_and_match :-
     '$read_query_term'(_, Term, _, _, VarList),
     instruction_match(Term, VarList).
@@ -221,7 +271,7 @@ instruction_match(Term, VarList) :-
 % Runs a query from a string of chars, calling `Callback_3` on each leaf answer.
 % See `run_query_goal/4` for details.
 run_query(QueryChars, Callback_3, Options) :-
-    read_term_from_chars(QueryChars, QueryGoal, [variable_names(VarNames)]),
+    read_term_from_chars(QueryChars, QueryGoal, [module(user), variable_names(VarNames)]),
     run_query_goal(QueryGoal, VarNames, Callback_3, Options).
 
 %% run_query_goal(+QueryGoal, +VarNames, +Callback_3, +Options)
@@ -285,12 +335,12 @@ run_query_goal(QueryGoal, VarNames, Callback_3, _) :-
         ;   copy_term([Vars1, ResVars], [Vars1, ResVars], ResGoals),
             term_variables(ResGoals, ResGoalVars),
             append([Vars1, ResGoalVars, ResVars], Vars2),
-            charsio:extend_var_list(Vars2, VarNames, NewVarNames, fabricated),
+            name_answer_variables(Vars2, VarNames, NewVarNames),
             gather_equations(NewVarNames, Vars0, Bindings),
             maplist(\Term^Vs^term_variables_under_max_depth(Term, 22, Vs), Bindings, BindingVars),
             append([ResGoalVars | BindingVars], Vars3),
             term_variables(Vars3, Vars4), % deduplicate vars of Vars1 but preserve their order.
-            charsio:extend_var_list(Vars4, VarNames, NewVarNames1, fabricated),
+            name_answer_variables(Vars4, VarNames, NewVarNames1),
             (   Pending == true ->
                 call(
                     Callback_3,
@@ -312,10 +362,23 @@ run_query_goal(_, _, Callback_3, _) :-
     % then we get here so we have a (tail) false.
     call(Callback_3, final(false), [], _).
 
+% names the variables of an answer that aren't variables of the query
+% as set by the answer_variable_names flag. internal names are left to
+% write_term/2.
+name_answer_variables(Vars, VarNames, NewVarNames) :-
+    current_prolog_flag(answer_variable_names, Naming),
+    (   Naming == internal ->
+        NewVarNames = VarNames
+    ;   Naming == letters ->
+        charsio:extend_var_list(Vars, VarNames, NewVarNames, numbervars)
+    ;   charsio:extend_var_list(Vars, VarNames, NewVarNames, fabricated)
+    ).
+
 submit_query_and_print_results(QueryTerm, VarNames) :-
     bb_put('$answer_count', 0),
     bb_put('$report_all', false),
     bb_put('$report_n_more', 0),
+    bb_put('$answers', []),
     run_query_goal(QueryTerm, VarNames, toplevel_query_callback, []).
 
 handle_first_answer :-
@@ -330,19 +393,47 @@ increment_answer_count :-
     bb_put('$answer_count', Count).
 
 toplevel_query_callback(pending(LeafAnswer), _, Stop) :-
-    handle_first_answer,
-    increment_answer_count,
-    write_leaf_answer(LeafAnswer, []),
-    read_input(LeafAnswer, Stop).
-toplevel_query_callback(final(LeafAnswer), _, continue) :-
-    (   exception(Exception) = LeafAnswer ->
-        print_exception(Exception)
+    (   duplicate_answer(LeafAnswer) ->
+        Stop = continue
     ;   handle_first_answer,
+        increment_answer_count,
+        write_leaf_answer(LeafAnswer, []),
+        read_input(LeafAnswer, Stop)
+    ).
+toplevel_query_callback(final(LeafAnswer0), _, continue) :-
+    (   exception(Exception) = LeafAnswer0 ->
+        print_exception(Exception)
+    ;   (   duplicate_answer(LeafAnswer0) ->
+            LeafAnswer = false
+        ;   LeafAnswer = LeafAnswer0
+        ),
+        handle_first_answer,
         increment_answer_count,
         write_leaf_answer(LeafAnswer, []),
         write('.'), nl
     ).
 
+% whether LeafAnswer is skipped as a duplicate, by the
+% answer_duplicates flag. the answers to compare it with are kept in
+% '$answers'.
+duplicate_answer(LeafAnswer) :-
+    current_prolog_flag(answer_duplicates, Duplicates),
+    Duplicates \== all,
+    (   LeafAnswer = leaf_answer(Bindings, ResGoals, _) ->
+        Answer = Bindings-ResGoals
+    ;   Answer = LeafAnswer
+    ),
+    bb_get('$answers', Answers),
+    (   member(Answer0, Answers),
+        '$term_variant'(Answer0, Answer) ->
+        true
+    ;   Duplicates == consecutive ->
+        bb_put('$answers', [Answer]),
+        false
+    ;   bb_put('$answers', [Answer|Answers]),
+        false
+    ).
+
 write_leaf_answer(true, _) :- write(true).
 write_leaf_answer(false, _) :- write(false).
 write_leaf_answer(leaf_answer(Bindings, ResGoals, VarNames), Options) :-
@@ -542,6 +633,12 @@ gather_equations([Var = Value | Pairs], OrigVarList, Goals) :-
     gather_equations(Pairs, OrigVarList, Goals0)
     ).
 
+print_exception(E) :-
+    E == '$aborted',
+    !,
+    % thrown by the tracer of library(trace).
+    write('   % Execution aborted'),
+    nl.
 print_exception(E) :-
     (  E == error('$interrupt_thrown', repl) -> nl % print the
                                                    % exception on a
@@ -1,11 +1,15 @@
 //! Wasm interface
 
+use std::cell::RefCell;
+use std::io::Write;
 use std::mem;
+use std::rc::Rc;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
 
 use ouroboros::self_referencing;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 
 use crate::*;
 
@@ -58,8 +62,26 @@ fn ensure_machine_ownership(&mut self) -> Result<(), JsValue> {
     ///
     /// You can only have one query at a time. If you try to do anything with this machine while
     /// doing a query an error will be thrown.
+    ///
+    /// `options` may limit each leaf answer to a number of instructions with `stepLimit` and to
+    /// a number of bytes of heap growth with `heapLimit`. Exceeding a limit throws an `Error`
+    /// named `ResourceLimitError` from `next`, with the Prolog error term as its `cause`, and
+    /// ends the query. The limit can't be caught from Prolog with `catch/3`.
+    ///
+    /// `variableNames` is how answers name the variables that aren't variables of the query:
+    /// `"fabricated"` (the default, `_A`, `_B`, ...), `"letters"` (`A`, `B`, ...) or
+    /// `"internal"` (`_` followed by a number, as `write/1` names them).
     #[wasm_bindgen(js_name = runQuery)]
-    pub fn run_query(&mut self, query: String) -> Result<JsValue, JsValue> {
+    pub fn run_query(
+        &mut self,
+        query: String,
+        options: Option<js_sys::Object>,
+    ) -> Result<JsValue, JsValue> {
+        let options = match options {
+            Some(options) => query_options(&options)?,
+            None => QueryOptions::default(),
+        };
+
         self.ensure_machine_ownership()?;
         assert!(self.inner.is_ok());
 
@@ -72,7 +94,9 @@ pub fn run_query(&mut self, query: String) -> Result<JsValue, JsValue> {
                 WasmQueryStateInnerBuilder {
                     machine,
                     drop_channel: sender,
-                    query_state_builder: move |m: &mut Machine| m.run_query(query),
+                    query_state_builder: move |m: &mut Machine| {
+                        m.run_query_with_options(query, options)
+                    },
                 }
                 .build(),
             ),
@@ -83,7 +107,7 @@ pub fn run_query(&mut self, query: String) -> Result<JsValue, JsValue> {
         Ok(query_state)
     }
 
-    /// Consults a module.
+    /// Consults a module, throwing an error listing the errors of the load, if any.
     #[wasm_bindgen(js_name = consultModuleString)]
     pub fn consult_module_string(
         &mut self,
@@ -94,12 +118,337 @@ pub fn consult_module_string(
         assert!(self.inner.is_ok());
 
         let inner = self.inner.as_mut().unwrap();
-        inner.consult_module_string(&module, program);
+        let diagnostics = inner.consult_module_string(&module, program);
+
+        diagnostics_result(&diagnostics)
+    }
+
+    /// Consults a module from the source text at a URL.
+    ///
+    /// The source is fetched with `fetch`, and each clause is compiled as soon as its text
+    /// arrives. If `onProgress` is given, it is called after each chunk of the source arrives,
+    /// with the number of bytes received so far and the total number of bytes, or `undefined`
+    /// if the server didn't send a `Content-Length`.
+    ///
+    /// Returns a promise that resolves once the module is consulted, or rejects with the errors of
+    /// the load. Like with queries, an error is thrown if this machine is used before that.
+    #[wasm_bindgen(js_name = consultUrl)]
+    pub fn consult_url(
+        &mut self,
+        module: String,
+        url: String,
+        on_progress: Option<js_sys::Function>,
+    ) -> Result<js_sys::Promise, JsValue> {
+        self.ensure_machine_ownership()?;
+        assert!(self.inner.is_ok());
+
+        // Installs a receiver and gets the machine
+        let (sender, receiver) = mpsc::channel();
+        let mut machine = mem::replace(&mut self.inner, Err(receiver)).unwrap();
+
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let result = {
+                let mut feed = machine.consult_module_feed(&module);
+                let fetched =
+                    fetch_source(&url, on_progress.as_ref(), |chunk| feed.push(chunk)).await;
+
+                // if the fetch fails, dropping the feed concludes the consult of the source
+                // received before.
+                fetched.and_then(|()| diagnostics_result(&feed.finish()))
+            };
+
+            sender.send(machine).unwrap();
+            result.map(|()| JsValue::UNDEFINED)
+        }))
+    }
+
+    /// Opens an input stream with the alias `alias`, from which Prolog reads the text written
+    /// to the returned `InputStream`.
+    ///
+    /// The stream only ends once the `InputStream` is closed. As a page can't write while a
+    /// query runs, a read that finds no text pending throws `resource_error(pending_input)`
+    /// instead, and the stream can be read again once more text is written. A term that the
+    /// text written so far ends in the middle of is lost to such a read, so text is best written
+    /// in whole terms or lines.
+    #[wasm_bindgen(js_name = openInputStream)]
+    pub fn open_input_stream(&mut self, alias: String) -> Result<WasmInputStream, JsValue> {
+        self.ensure_machine_ownership()?;
+        assert!(self.inner.is_ok());
+
+        let inner = self.inner.as_mut().unwrap();
+
+        Ok(WasmInputStream {
+            inner: Some(inner.open_input_stream(&alias)),
+        })
+    }
+
+    /// Opens an output stream with the alias `alias`, whose text the returned `OutputStream`
+    /// reads once Prolog flushes it.
+    #[wasm_bindgen(js_name = openOutputStream)]
+    pub fn open_output_stream(&mut self, alias: String) -> Result<WasmOutputStream, JsValue> {
+        self.ensure_machine_ownership()?;
+        assert!(self.inner.is_ok());
+
+        let inner = self.inner.as_mut().unwrap();
+        let output = Rc::new(RefCell::new(Vec::new()));
+
+        inner.open_output_stream(
+            &alias,
+            Box::new({
+                let output = output.clone();
+                move |buffer| {
+                    output.borrow_mut().extend_from_slice(buffer.get_ref());
+                    buffer.get_mut().clear();
+                    buffer.set_position(0);
+                }
+            }),
+        );
+
+        Ok(WasmOutputStream { output })
+    }
+}
+
+/// A stream of a `Machine` that Prolog reads from, opened with `openInputStream`.
+#[wasm_bindgen(js_name = InputStream)]
+pub struct WasmInputStream {
+    inner: Option<UserInput>,
+}
+
+#[wasm_bindgen(js_class = InputStream)]
+impl WasmInputStream {
+    /// Writes text for Prolog to read. Throws an error if the stream is closed.
+    pub fn write(&mut self, text: &str) -> Result<(), JsValue> {
+        let closed = || js_sys::Error::new("The stream is closed");
+        let inner = self.inner.as_mut().ok_or_else(closed)?;
+
+        inner
+            .write_all(text.as_bytes())
+            .map_err(|_| closed().into())
+    }
+
+    /// Closes the stream, so that it ends after the text written to it.
+    pub fn close(&mut self) {
+        self.inner = None;
+    }
+}
+
+/// A stream of a `Machine` that Prolog writes to, opened with `openOutputStream`.
+#[wasm_bindgen(js_name = OutputStream)]
+pub struct WasmOutputStream {
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+#[wasm_bindgen(js_class = OutputStream)]
+impl WasmOutputStream {
+    /// Returns the text Prolog flushed to the stream since the last call.
+    pub fn read(&mut self) -> String {
+        let output = mem::take(&mut *self.output.borrow_mut());
+        String::from_utf8_lossy(&output).into_owned()
+    }
+}
+
+/// Reads the `stepLimit`, `heapLimit` and `variableNames` options of `runQuery`.
+fn query_options(options: &js_sys::Object) -> Result<QueryOptions, JsValue> {
+    let limit = |name: &str| -> Result<Option<f64>, JsValue> {
+        let value = js_sys::Reflect::get(options, &name.into())?;
 
+        if value.is_undefined() {
+            return Ok(None);
+        }
+
+        match value.as_f64() {
+            Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(Some(n)),
+            _ => Err(
+                js_sys::TypeError::new(&format!("{name} must be a non-negative integer")).into(),
+            ),
+        }
+    };
+
+    let variable_names = js_sys::Reflect::get(options, &"variableNames".into())?;
+
+    let variable_names = if variable_names.is_undefined() {
+        AnswerVariableNames::default()
+    } else {
+        match variable_names.as_string().as_deref() {
+            Some("fabricated") => AnswerVariableNames::Fabricated,
+            Some("letters") => AnswerVariableNames::Letters,
+            Some("internal") => AnswerVariableNames::Internal,
+            _ => {
+                return Err(js_sys::TypeError::new(
+                    "variableNames must be \"fabricated\", \"letters\" or \"internal\"",
+                )
+                .into())
+            }
+        }
+    };
+
+    Ok(QueryOptions {
+        step_limit: limit("stepLimit")?.map(|n| n as u64),
+        heap_limit: limit("heapLimit")?.map(|n| n as usize),
+        variable_names,
+        ..QueryOptions::default()
+    })
+}
+
+/// The message of the `ResourceLimitError` thrown for `error(resource_error(R), _)`, if `R` is
+/// a limit of `runQuery`.
+fn resource_limit_message(error_term: &Term) -> Option<&'static str> {
+    let Term::Compound(functor, args) = error_term else {
+        return None;
+    };
+
+    match (functor.as_str(), args.first()) {
+        ("error", Some(Term::Compound(error, resource))) if error == "resource_error" => {
+            match resource.as_slice() {
+                [Term::Atom(atom)] if atom == "steps" => Some("Step limit exceeded"),
+                [Term::Atom(atom)] if atom == "heap" => Some("Heap limit exceeded"),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The error thrown from `next` for the error term of a query.
+///
+/// If a limit of `runQuery` was exceeded, this is a `ResourceLimitError`. Otherwise it's a
+/// `PrologError` whose message is the error term. For an `error(Formal, Context)` term, its
+/// `kind` is the name of `Formal`, such as `"type_error"`, and its `formal` and `context` are
+/// the two arguments. The `cause` of either is the error term.
+fn query_error(error_term: Term) -> js_sys::Error {
+    let js_error = match resource_limit_message(&error_term) {
+        Some(message) => {
+            let js_error = js_sys::Error::new(message);
+            js_error.set_name("ResourceLimitError");
+            js_error
+        }
+        None => {
+            let js_error = js_sys::Error::new(&error_term.to_string());
+            js_error.set_name("PrologError");
+
+            if let Term::Compound(functor, args) = &error_term {
+                if let ("error", [formal, context]) = (functor.as_str(), args.as_slice()) {
+                    let kind = match formal {
+                        Term::Atom(name) | Term::Compound(name, _) => JsValue::from(name),
+                        _ => JsValue::UNDEFINED,
+                    };
+
+                    js_sys::Reflect::set(&js_error, &"kind".into(), &kind).unwrap();
+                    js_sys::Reflect::set(&js_error, &"formal".into(), &formal.clone().into())
+                        .unwrap();
+                    js_sys::Reflect::set(&js_error, &"context".into(), &context.clone().into())
+                        .unwrap();
+                }
+            }
+
+            js_error
+        }
+    };
+
+    js_error.set_cause(&error_term.into());
+    js_error
+}
+
+/// Throws an error listing the errors among the diagnostics of a consult, if any. Warnings
+/// don't fail the consult.
+fn diagnostics_result(diagnostics: &[Diagnostic]) -> Result<(), JsValue> {
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(Diagnostic::to_string)
+        .collect();
+
+    if errors.is_empty() {
         Ok(())
+    } else {
+        Err(js_sys::Error::new(&errors.join("\n")).into())
+    }
+}
+
+/// Fetches the text at `url` chunk by chunk, passing each chunk to `on_chunk` and then calling
+/// `on_progress`.
+async fn fetch_source(
+    url: &str,
+    on_progress: Option<&js_sys::Function>,
+    mut on_chunk: impl FnMut(Vec<u8>),
+) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| js_sys::Error::new("No window to fetch from"))?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+        .await?
+        .dyn_into()?;
+
+    if !response.ok() {
+        let message = format!("Failed to fetch {url}: status {}", response.status());
+        return Err(js_sys::Error::new(&message).into());
+    }
+
+    let total = response
+        .headers()
+        .get("content-length")?
+        .and_then(|len| len.parse::<f64>().ok())
+        .map_or(JsValue::UNDEFINED, JsValue::from);
+
+    let body = response
+        .body()
+        .ok_or_else(|| js_sys::Error::new("The response has no body"))?;
+    let reader: web_sys::ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+
+    let mut received = 0;
+
+    loop {
+        let chunk = JsFuture::from(reader.read()).await?;
+
+        if js_sys::Reflect::get(&chunk, &"done".into())?.is_truthy() {
+            return Ok(());
+        }
+
+        let bytes: js_sys::Uint8Array =
+            js_sys::Reflect::get(&chunk, &"value".into())?.dyn_into()?;
+        received += bytes.length();
+        on_chunk(bytes.to_vec());
+
+        if let Some(on_progress) = on_progress {
+            on_progress.call2(&JsValue::NULL, &(received as f64).into(), &total)?;
+        }
     }
 }
 
+/// Describes this build of Scryer Prolog, so that functionality can be checked for before it
+/// is used.
+///
+/// Returns an object with the `version` of Scryer Prolog, its optional `features`, each mapped
+/// to whether this build has it, and its `limits`. The only limit is `maxArity`, the largest
+/// arity of a compound term.
+#[wasm_bindgen(js_name = meta)]
+pub fn meta() -> JsValue {
+    let features = js_sys::Object::new();
+
+    for (feature, enabled) in [
+        ("ffi", cfg!(feature = "ffi")),
+        ("http", cfg!(feature = "http")),
+        ("tls", cfg!(feature = "tls")),
+        ("cryptoFull", cfg!(feature = "crypto-full")),
+    ] {
+        js_sys::Reflect::set(&features, &feature.into(), &enabled.into()).unwrap();
+    }
+
+    let limits = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &limits,
+        &"maxArity".into(),
+        &(crate::parser::ast::MAX_ARITY as f64).into(),
+    )
+    .unwrap();
+
+    let version = git_version::git_version!(cargo_prefix = "cargo:", fallback = "unknown");
+
+    let obj = js_sys::Object::new();
+    js_sys::Reflect::set(&obj, &"version".into(), &version.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"features".into(), &features.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"limits".into(), &limits.into()).unwrap();
+    obj.into()
+}
+
 #[self_referencing]
 struct WasmQueryStateInner {
     machine: Machine,
@@ -136,9 +485,7 @@ pub fn next_answer(&mut self) -> Result<JsValue, JsValue> {
                         js_sys::Reflect::set(&ret, &"done".into(), &false.into()).unwrap();
                     }
                     Some(Err(error_term)) => {
-                        let js_error = js_sys::Error::new("Prolog error");
-                        js_error.set_cause(&error_term.into());
-                        error = Some(js_error);
+                        error = Some(query_error(error_term));
                     }
                     None => {
                         js_sys::Reflect::set(&ret, &"done".into(), &true.into()).unwrap();
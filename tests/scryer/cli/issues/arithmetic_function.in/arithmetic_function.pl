@@ -0,0 +1,12 @@
+:- use_module(library(arithmetic)).
+
+:- arithmetic_function(mid/2).
+:- arithmetic_function(tau/0).
+
+mid(X, Y, Z) :- Z is (X + Y) / 2.
+
+tau(T) :- T is 2 * pi.
+
+scaled(X, Y) :- Y is 10 * mid(X, mid(0, 4)).
+
+between_ends(X) :- X > mid(0, 1), X < tau.
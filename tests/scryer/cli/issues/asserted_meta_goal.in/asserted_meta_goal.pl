@@ -0,0 +1,9 @@
+:- dynamic(remembered/1).
+
+:- meta_predicate(remember(0)).
+
+remember(Goal) :-
+    assertz(remembered(Goal)).
+
+remember_length :-
+    remember(atom_length(abc, _)).
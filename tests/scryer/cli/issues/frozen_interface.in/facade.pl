@@ -0,0 +1,6 @@
+:- module(facade, [quad/2]).
+
+:- reexport(base, [double/2]).
+:- freeze_module_interface.
+
+quad(X, Y) :- double(X, Z), double(Z, Y).
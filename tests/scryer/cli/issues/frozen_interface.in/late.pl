@@ -0,0 +1,4 @@
+:- module(late, []).
+
+:- freeze_module_interface.
+:- reexport(base, [triple/2]).
@@ -0,0 +1,3 @@
+:- module(greet, [greet/1]).
+
+greet(hello).
@@ -0,0 +1 @@
+file_search_path(proj, proj).
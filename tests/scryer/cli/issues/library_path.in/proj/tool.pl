@@ -0,0 +1,3 @@
+:- module(tool, [tool/1]).
+
+tool(hammer).
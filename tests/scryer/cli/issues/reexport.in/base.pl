@@ -0,0 +1,4 @@
+:- module(base, [double/2, triple/2, op(700, xfx, ===>)]).
+
+double(X, Y) :- Y is 2 * X.
+triple(X, Y) :- Y is 3 * X.
@@ -0,0 +1,6 @@
+:- module(layer, [quad/2]).
+
+:- reexport(base).
+:- reexport(library(lists), [append/3]).
+
+quad(X, Y) :- double(X, Z), double(Z, Y).
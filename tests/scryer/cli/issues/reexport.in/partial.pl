@@ -0,0 +1,2 @@
+:- module(partial, []).
+:- reexport(base, [triple/2]).
@@ -0,0 +1,13 @@
+:- use_module(library(threads)).
+
+:- initialization((write(loaded), nl)).
+:- spawn_initialization(echo).
+:- spawn_initialization(spin).
+
+echo :-
+    thread_get_message(Message),
+    thread_send_message(main, echo(Message)).
+
+spin :-
+    repeat,
+    fail.
@@ -0,0 +1,3 @@
+p(1).
+q(1).
+p(2).
@@ -0,0 +1,2 @@
+q(2).
+r(X).